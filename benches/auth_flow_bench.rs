@@ -62,7 +62,7 @@ fn jwt_token_benchmarks(c: &mut Criterion) {
     // Benchmark token validation
     group.bench_function("token_validation", |b| {
         b.iter(|| {
-            let claims = jwt_utils.validate_token(black_box(&token)).unwrap();
+            let claims = jwt_utils.validate_token(black_box(&token), None).unwrap();
             assert_eq!(claims.sub, user_id);
         })
     });
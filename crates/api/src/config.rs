@@ -1,9 +1,11 @@
 use std::time::Duration;
+use time::OffsetDateTime;
+use time::macros::datetime;
 
 /// Configuration for the API infrastructure
 #[derive(Debug, Clone)]
 pub struct ApiConfig {
-    /// Base path for the API
+    /// Base path for the API; each version is served below it, e.g. `/api/v1`
     pub base_path: String,
     /// CORS configuration
     pub cors: CorsConfig,
@@ -17,19 +19,51 @@ pub struct ApiConfig {
     pub documentation: DocumentationConfig,
     /// Metrics server address in format "ip:port"
     pub metrics_addr: String,
+    /// Response caching configuration
+    pub cache: CacheConfig,
+    /// Versioning of the routes
+    pub versioning: VersioningConfig,
 }
 
 /// Default configuration for the API
 impl Default for ApiConfig {
     fn default() -> Self {
         Self {
-            base_path: "/api/v1".to_string(),
+            base_path: "/api".to_string(),
             cors: CorsConfig::default(),
             rate_limit: RateLimitConfig::default(),
             timeout: TimeoutConfig::default(),
             body_limit: 5 * 1024 * 1024, // 5MB
             documentation: DocumentationConfig::default(),
             metrics_addr: "127.0.0.1:9091".to_string(),
+            cache: CacheConfig::default(),
+            versioning: VersioningConfig::default(),
+        }
+    }
+}
+
+/// Versioning of the routes
+///
+/// The paths without a version segment that predate versioning are served as
+/// aliases of `v1` until they are disabled.
+#[derive(Debug, Clone)]
+pub struct VersioningConfig {
+    /// Whether the paths without a version segment are still served
+    pub serve_aliases: bool,
+    /// Date since which the aliases are deprecated, sent as `Deprecation` header
+    pub deprecated_since: OffsetDateTime,
+    /// Date after which the aliases may be removed, sent as `Sunset` header
+    pub sunset: OffsetDateTime,
+}
+
+impl Default for VersioningConfig {
+    fn default() -> Self {
+        Self {
+            serve_aliases: true,
+            // When the versioned paths were introduced
+            deprecated_since: datetime!(2025-04-08 0:00 UTC),
+            // One year later
+            sunset: datetime!(2026-04-08 0:00 UTC),
         }
     }
 }
@@ -110,6 +144,24 @@ impl Default for TimeoutConfig {
     }
 }
 
+/// Caching of the GET responses that support conditional requests
+///
+/// Only handlers that compute an ETag are cached; all other responses stay
+/// uncached.
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// `max-age` of the `Cache-Control` header
+    pub max_age: Duration,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_age: Duration::from_secs(30),
+        }
+    }
+}
+
 /// API documentation configuration
 #[derive(Debug, Clone)]
 pub struct DocumentationConfig {
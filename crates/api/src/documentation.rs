@@ -1,4 +1,5 @@
 use crate::config::ApiConfig;
+use crate::versioning::ApiVersion;
 use axum::{Router, response::Html, routing::get};
 use serde_json;
 
@@ -23,10 +24,22 @@ impl ApiDocumentation {
     }
 
     /// Creates the documentation routes
+    ///
+    /// Every version has its own specification at `/<version>/openapi.json`;
+    /// `/openapi.json` is the one of the latest version.
     fn documentation_routes(&self) -> Router {
-        Router::new()
+        let spec_route = |version: ApiVersion| {
+            let base_path = self.config.base_path.clone();
+            get(move || async move { axum::Json(openapi_spec(version, &base_path)) })
+        };
+
+        let mut router = Router::new()
             .route("/", get(swagger_ui_handler))
-            .route("/openapi.json", get(openapi_json_handler))
+            .route("/openapi.json", spec_route(ApiVersion::LATEST));
+        for &version in ApiVersion::ALL {
+            router = router.route(&format!("/{}/openapi.json", version), spec_route(version));
+        }
+        router
     }
 }
 
@@ -74,24 +87,33 @@ async fn swagger_ui_handler() -> Html<String> {
     "#.to_string())
 }
 
-/// Handler for the OpenAPI JSON specification
-async fn openapi_json_handler() -> axum::Json<serde_json::Value> {
+/// OpenAPI specification of a version
+///
+/// The paths are relative to the version's server URL, e.g. `/api/v1`.
+fn openapi_spec(version: ApiVersion, base_path: &str) -> serde_json::Value {
     // Helper function to safely create JSON with proper error handling
     fn create_json_safely<T: serde::Serialize>(value: T) -> serde_json::Value {
         serde_json::to_value(value).expect("Failed to serialize OpenAPI documentation to JSON")
     }
 
+    let info_version = match version {
+        ApiVersion::V1 => "1.0.0",
+    };
+
     // Create the OpenAPI document in a safer way
     #[allow(clippy::disallowed_methods)]
-    axum::Json(create_json_safely(serde_json::json!({
+    create_json_safely(serde_json::json!({
         "openapi": "3.0.3",
         "info": {
             "title": "ACCI Framework API",
             "description": "API Documentation for ACCI Framework",
-            "version": "1.0.0"
+            "version": info_version
         },
+        "servers": [
+            { "url": version.prefix(base_path) }
+        ],
         "paths": {
-            "/api/v1/health": {
+            "/health": {
                 "get": {
                     "summary": "Health check",
                     "description": "Verifies that the API is functioning",
@@ -110,6 +132,25 @@ async fn openapi_json_handler() -> axum::Json<serde_json::Value> {
                         }
                     }
                 }
+            },
+            "/health/sessions": {
+                "get": {
+                    "summary": "Session health",
+                    "description": "Reports the last pass of the session reconciliation on this instance",
+                    "operationId": "sessionHealth",
+                    "responses": {
+                        "200": {
+                            "description": "Successful response",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/ApiResponse"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
             }
         },
         "components": {
@@ -143,5 +184,22 @@ async fn openapi_json_handler() -> axum::Json<serde_json::Value> {
                 }
             }
         }
-    })))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_of_each_version_names_its_server() {
+        for &version in ApiVersion::ALL {
+            let spec = openapi_spec(version, "/api");
+            assert_eq!(
+                spec["servers"][0]["url"],
+                format!("/api/{}", version.as_str())
+            );
+            assert!(spec["paths"]["/health"].is_object());
+        }
+    }
 }
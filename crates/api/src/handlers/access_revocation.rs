@@ -0,0 +1,393 @@
+//! Access revocation endpoint for security responders
+//!
+//! `POST /admin/users/{id}/revoke-access` cuts a user off at once: every
+//! session is invalidated, the API keys the user issued are revoked and, on
+//! request, password logins fail until a new password is set. It is
+//! restricted to users whose access token carries the platform admin claim.
+//!
+//! Repeating the request is safe. Artifacts revoked before are not counted
+//! again, so the response of a repeated request reports zeros.
+
+use crate::middleware::auth::{AuthExtractorState, AuthenticatedUser};
+use crate::monitoring;
+use crate::response::{ApiError, ApiResponse};
+use crate::validation::generate_request_id;
+use axum::{
+    extract::{FromRef, Json, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{debug, error, warn};
+use uuid::Uuid;
+
+use acci_auth::{
+    AccessRevocation, JwtUtils, UserError, UserService, UserServiceError,
+    services::session::SessionService,
+};
+
+/// Access Revocation Application State
+#[derive(Clone)]
+pub struct AccessRevocationAppState {
+    /// Revokes the access; revokes API keys if it has the API key service
+    pub user_service: Arc<UserService>,
+    /// Session service for authenticating the admin
+    pub session_service: Arc<SessionService>,
+    /// Validates the access token carrying the platform admin claim
+    pub jwt_utils: Arc<JwtUtils>,
+}
+
+impl FromRef<AccessRevocationAppState> for AuthExtractorState {
+    fn from_ref(state: &AccessRevocationAppState) -> Self {
+        Self {
+            session_service: state.session_service.clone(),
+            tenant_service: None,
+            jwt_utils: Some(state.jwt_utils.clone()),
+        }
+    }
+}
+
+/// Revoke access request DTO
+#[derive(Debug, Default, Deserialize)]
+pub struct RevokeAccessRequest {
+    /// Reject password logins until the user sets a new password
+    #[serde(default)]
+    pub force_password_reset: bool,
+}
+
+/// Revoke access response DTO
+#[derive(Debug, Serialize)]
+pub struct RevokeAccessResponse {
+    pub user_id: String,
+    /// Valid sessions invalidated by this request
+    pub sessions_revoked: u64,
+    /// API keys revoked by this request
+    pub api_keys_revoked: u64,
+    /// Whether this request made a password reset required
+    pub password_reset_required: bool,
+}
+
+impl RevokeAccessResponse {
+    fn new(user_id: Uuid, revocation: AccessRevocation) -> Self {
+        Self {
+            user_id: user_id.to_string(),
+            sessions_revoked: revocation.sessions_revoked,
+            api_keys_revoked: revocation.api_keys_revoked,
+            password_reset_required: revocation.password_reset_required,
+        }
+    }
+}
+
+/// Handler revoking all access of a user
+#[axum::debug_handler(state = AccessRevocationAppState)]
+pub async fn revoke_user_access(
+    State(state): State<AccessRevocationAppState>,
+    user: AuthenticatedUser,
+    Path(user_id): Path<Uuid>,
+    Json(request): Json<RevokeAccessRequest>,
+) -> Response {
+    debug!("Processing revoke access request");
+    let request_id = generate_request_id();
+
+    if !user.is_platform_admin() {
+        warn!(
+            request_id = %request_id,
+            user_id = %user.user_id,
+            "User is not a platform admin"
+        );
+        return ApiError::authorization_error(request_id).into_response();
+    }
+
+    match state
+        .user_service
+        .revoke_access(user_id, user.user_id, request.force_password_reset)
+        .await
+    {
+        Ok(revocation) => {
+            monitoring::record_auth_operation("revoke_access", "success");
+            let response = RevokeAccessResponse::new(user_id, revocation);
+            let api_response = ApiResponse::success(response, request_id);
+            (StatusCode::OK, Json(api_response)).into_response()
+        },
+        Err(UserServiceError::User(UserError::NotFound)) => {
+            ApiError::not_found_error("User", request_id).into_response()
+        },
+        Err(err) => {
+            monitoring::record_auth_operation("revoke_access", "failure");
+            error!(
+                request_id = %request_id,
+                user_id = %user_id,
+                error = %err,
+                "Failed to revoke access"
+            );
+            ApiError::internal_server_error(request_id).into_response()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::auth::{ApiAppState, validate_token};
+    use acci_auth::session::types::MfaStatus;
+    use acci_auth::test_support::{
+        MockApiKeyRepository, MockSessionRepository, MockUserRepository,
+    };
+    use acci_auth::{
+        ApiKeyService, AuthConfig, IssueApiKey, PLATFORM_ADMIN_CLAIM, User, UserRepository,
+        utils::password::hash_password,
+    };
+    use axum::{
+        Router,
+        body::Body,
+        http::{Request, header},
+        routing::post,
+    };
+    use serde_json::Value;
+    use time::OffsetDateTime;
+    use tower::ServiceExt;
+
+    const PASSWORD: &str = "Correct-Horse-42!";
+
+    struct Fixture {
+        state: AccessRevocationAppState,
+        api_keys: Arc<ApiKeyService>,
+        user_repo: Arc<MockUserRepository>,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let user_repo = Arc::new(MockUserRepository::new());
+            let config = Arc::new(AuthConfig::default());
+            let session_service = Arc::new(SessionService::new(
+                Arc::new(MockSessionRepository::new()),
+                config.clone(),
+            ));
+            let api_keys = Arc::new(ApiKeyService::new(Arc::new(MockApiKeyRepository::new())));
+            let jwt_utils = Arc::new(JwtUtils::new(b"test-secret"));
+            let user_service = Arc::new(
+                UserService::new(
+                    user_repo.clone(),
+                    jwt_utils.clone(),
+                    session_service.clone(),
+                    None,
+                    config,
+                )
+                .with_api_key_service(api_keys.clone()),
+            );
+            Self {
+                state: AccessRevocationAppState {
+                    user_service,
+                    session_service,
+                    jwt_utils,
+                },
+                api_keys,
+                user_repo,
+            }
+        }
+
+        async fn user(&self) -> User {
+            let password_hash = hash_password(PASSWORD).expect("Password is hashed");
+            let user = User::new("compromised@example.com".to_string(), password_hash);
+            self.user_repo.create(&user).await.expect("User is stored");
+            user
+        }
+
+        /// Log the user in, returning the session token
+        async fn login(&self) -> Result<String, UserServiceError> {
+            self.state
+                .user_service
+                .login(
+                    "compromised@example.com",
+                    PASSWORD,
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                )
+                .await
+                .map(|login| login.session_token)
+        }
+
+        async fn issue_key(&self, user_id: Uuid) -> String {
+            self.api_keys
+                .issue(
+                    Uuid::new_v4(),
+                    IssueApiKey {
+                        name: "Deploy".to_string(),
+                        created_by: Some(user_id),
+                        ..IssueApiKey::default()
+                    },
+                )
+                .await
+                .expect("Key is issued")
+                .key
+        }
+
+        async fn revoke(
+            &self,
+            admin: AuthenticatedUser,
+            user_id: Uuid,
+            force_password_reset: bool,
+        ) -> Response {
+            revoke_user_access(
+                State(self.state.clone()),
+                admin,
+                Path(user_id),
+                Json(RevokeAccessRequest {
+                    force_password_reset,
+                }),
+            )
+            .await
+        }
+
+        /// Status of token introspection with `token`
+        async fn introspect(&self, token: &str) -> StatusCode {
+            let app = Router::new()
+                .route("/auth/validate-token", post(validate_token))
+                .with_state(ApiAppState {
+                    user_service: self.state.user_service.clone(),
+                    session_service: self.state.session_service.clone(),
+                });
+            let request = Request::post("/auth/validate-token")
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .expect("valid request");
+            app.oneshot(request)
+                .await
+                .expect("Router responds")
+                .status()
+        }
+    }
+
+    fn admin(platform_admin: bool) -> AuthenticatedUser {
+        let now = OffsetDateTime::now_utc();
+        let mut custom_claims = serde_json::Map::new();
+        if platform_admin {
+            custom_claims.insert(PLATFORM_ADMIN_CLAIM.to_string(), Value::Bool(true));
+        }
+        AuthenticatedUser {
+            user_id: Uuid::new_v4(),
+            session_id: Uuid::new_v4(),
+            tenant_id: None,
+            mfa_status: MfaStatus::Verified,
+            memberships: Vec::new(),
+            claims: None,
+            custom_claims,
+            last_auth_at: now,
+            expires_at: now + time::Duration::hours(1),
+            impersonated_by: None,
+        }
+    }
+
+    async fn body(response: Response) -> Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read body");
+        serde_json::from_slice(&body).expect("Body is JSON")
+    }
+
+    #[tokio::test]
+    async fn test_revoked_tokens_fail_introspection() {
+        let fixture = Fixture::new();
+        let user = fixture.user().await;
+        let first = fixture.login().await.expect("Login succeeds");
+        let second = fixture.login().await.expect("Login succeeds");
+        let key = fixture.issue_key(user.id).await;
+        assert_eq!(fixture.introspect(&first).await, StatusCode::OK);
+        assert_eq!(fixture.introspect(&second).await, StatusCode::OK);
+        assert!(fixture.api_keys.authenticate(&key).await.is_ok());
+
+        let response = fixture.revoke(admin(true), user.id, true).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = body(response).await;
+        assert_eq!(body["data"]["user_id"], user.id.to_string());
+        assert_eq!(body["data"]["sessions_revoked"], 2);
+        assert_eq!(body["data"]["api_keys_revoked"], 1);
+        assert_eq!(body["data"]["password_reset_required"], true);
+
+        assert_eq!(fixture.introspect(&first).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(fixture.introspect(&second).await, StatusCode::UNAUTHORIZED);
+        assert!(fixture.api_keys.authenticate(&key).await.is_err());
+        // The old password no longer opens a session
+        assert!(matches!(
+            fixture.login().await,
+            Err(UserServiceError::PasswordExpired { .. })
+        ));
+
+        let events = fixture.user_repo.security_events(user.id);
+        let (_, details) = events
+            .iter()
+            .find(|(action, _)| action == "ACCESS_REVOKED")
+            .expect("Revocation is audited");
+        assert_eq!(details["sessions_revoked"], 2);
+        assert_eq!(details["api_keys_revoked"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_revocation_reports_nothing_revoked() {
+        let fixture = Fixture::new();
+        let user = fixture.user().await;
+        fixture.login().await.expect("Login succeeds");
+        fixture.issue_key(user.id).await;
+
+        let response = fixture.revoke(admin(true), user.id, true).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = fixture.revoke(admin(true), user.id, true).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = body(response).await;
+        assert_eq!(body["data"]["sessions_revoked"], 0);
+        assert_eq!(body["data"]["api_keys_revoked"], 0);
+        assert_eq!(body["data"]["password_reset_required"], false);
+        assert!(matches!(
+            fixture.login().await,
+            Err(UserServiceError::PasswordExpired { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_revocation_without_password_reset_allows_login() {
+        let fixture = Fixture::new();
+        let user = fixture.user().await;
+        let token = fixture.login().await.expect("Login succeeds");
+
+        let response = fixture.revoke(admin(true), user.id, false).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = body(response).await;
+        assert_eq!(body["data"]["sessions_revoked"], 1);
+        assert_eq!(body["data"]["password_reset_required"], false);
+
+        assert_eq!(fixture.introspect(&token).await, StatusCode::UNAUTHORIZED);
+        let token = fixture.login().await.expect("Login succeeds");
+        assert_eq!(fixture.introspect(&token).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_revocation_requires_platform_admin_claim() {
+        let fixture = Fixture::new();
+        let user = fixture.user().await;
+        let token = fixture.login().await.expect("Login succeeds");
+
+        let response = fixture.revoke(admin(false), user.id, true).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let mut impersonated = admin(true);
+        impersonated.impersonated_by = Some(Uuid::new_v4());
+        let response = fixture.revoke(impersonated, user.id, true).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        assert_eq!(fixture.introspect(&token).await, StatusCode::OK);
+        assert!(fixture.user_repo.security_events(user.id).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_user_is_not_found() {
+        let fixture = Fixture::new();
+
+        let response = fixture.revoke(admin(true), Uuid::new_v4(), true).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
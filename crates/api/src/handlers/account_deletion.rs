@@ -0,0 +1,403 @@
+//! Self-service account deletion
+//!
+//! `POST /auth/me/delete` schedules the deletion of the signed-in user's own
+//! account after a grace period and signs out all other sessions.
+//! `POST /auth/me/delete/cancel` takes the request back while the grace period
+//! lasts. The deletion itself is carried out by the background job of the
+//! [`AccountDeletionService`].
+
+use crate::middleware::auth::{AuthExtractorState, AuthenticatedUser};
+use crate::monitoring;
+use crate::response::{ApiError, ApiResponse};
+use crate::validation::generate_request_id;
+use axum::{
+    extract::{FromRef, Json, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+use tracing::{debug, info, warn};
+
+use acci_auth::{
+    AccountDeletionError, AccountDeletionService, DeletionConfirmation, SensitiveOperation,
+    services::session::SessionService, session::types::MfaStatus,
+};
+
+/// Services the account deletion endpoints need
+#[derive(Clone)]
+pub struct AccountDeletionAppState {
+    pub account_deletion_service: Arc<AccountDeletionService>,
+    pub session_service: Arc<SessionService>,
+}
+
+impl FromRef<AccountDeletionAppState> for AuthExtractorState {
+    fn from_ref(state: &AccountDeletionAppState) -> Self {
+        Self {
+            session_service: state.session_service.clone(),
+            tenant_service: None,
+            jwt_utils: None,
+        }
+    }
+}
+
+/// Account deletion request DTO
+#[derive(Debug, Default, Deserialize)]
+pub struct DeleteAccountRequest {
+    /// Current password; may be omitted right after completing a second factor
+    pub password: Option<String>,
+}
+
+/// Scheduled account deletion response DTO
+#[derive(Debug, Serialize)]
+pub struct AccountDeletionResponse {
+    /// When the deletion was requested (RFC 3339)
+    pub requested_at: Option<String>,
+    /// When the account will be deleted (RFC 3339)
+    pub scheduled_for: Option<String>,
+}
+
+fn rfc3339(time: OffsetDateTime) -> Option<String> {
+    time.format(&Rfc3339).ok()
+}
+
+/// Handler scheduling the deletion of the current user's account
+///
+/// The user confirms with the password, or with a second factor completed
+/// within the re-authentication window for account deletion.
+#[axum::debug_handler(state = AccountDeletionAppState)]
+pub async fn request_account_deletion(
+    State(state): State<AccountDeletionAppState>,
+    user: AuthenticatedUser,
+    Json(request): Json<DeleteAccountRequest>,
+) -> Response {
+    debug!("Processing account deletion request");
+    let request_id = generate_request_id();
+    monitoring::record_auth_operation("account_deletion", "attempt");
+
+    if user.is_impersonation() {
+        monitoring::record_auth_operation("account_deletion", "failure");
+        return ApiError::new(
+            StatusCode::FORBIDDEN,
+            "Accounts cannot be deleted from an impersonation session",
+            "IMPERSONATION_NOT_ALLOWED",
+            request_id,
+        )
+        .into_response();
+    }
+
+    let confirmation = match request.password.filter(|password| !password.is_empty()) {
+        Some(password) => DeletionConfirmation::Password(password),
+        None if user.mfa_status == MfaStatus::Verified => {
+            if let Err(err) =
+                user.require_recent_auth(&state.session_service, SensitiveOperation::DeleteAccount)
+            {
+                monitoring::record_auth_operation("account_deletion", "failure");
+                return err.into_response();
+            }
+            DeletionConfirmation::RecentSecondFactor
+        },
+        None => {
+            monitoring::record_auth_operation("account_deletion", "failure");
+            return ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "Password or a recent second factor is required",
+                "REAUTH_REQUIRED",
+                request_id,
+            )
+            .into_response();
+        },
+    };
+
+    match state
+        .account_deletion_service
+        .request_deletion(user.user_id, user.session_id, confirmation)
+        .await
+    {
+        Ok(deletion) => {
+            monitoring::record_auth_operation("account_deletion", "success");
+            info!(
+                request_id = %request_id,
+                user_id = %user.user_id,
+                scheduled_for = %deletion.scheduled_for,
+                "Account deletion scheduled"
+            );
+
+            let response = AccountDeletionResponse {
+                requested_at: rfc3339(deletion.requested_at),
+                scheduled_for: rfc3339(deletion.scheduled_for),
+            };
+            let api_response = ApiResponse::success(response, request_id);
+            (StatusCode::ACCEPTED, Json(api_response)).into_response()
+        },
+        Err(err) => {
+            monitoring::record_auth_operation("account_deletion", "failure");
+            warn!(
+                request_id = %request_id,
+                error = %err,
+                user_id = %user.user_id,
+                "Account deletion request failed"
+            );
+            account_deletion_error(err, request_id)
+        },
+    }
+}
+
+/// Handler cancelling the pending deletion of the current user's account
+#[axum::debug_handler(state = AccountDeletionAppState)]
+pub async fn cancel_account_deletion(
+    State(state): State<AccountDeletionAppState>,
+    user: AuthenticatedUser,
+) -> Response {
+    debug!("Processing account deletion cancellation");
+    let request_id = generate_request_id();
+    monitoring::record_auth_operation("account_deletion_cancel", "attempt");
+
+    match state
+        .account_deletion_service
+        .cancel_deletion(user.user_id)
+        .await
+    {
+        Ok(()) => {
+            monitoring::record_auth_operation("account_deletion_cancel", "success");
+            info!(
+                request_id = %request_id,
+                user_id = %user.user_id,
+                "Account deletion cancelled"
+            );
+
+            let api_response = ApiResponse::success(true, request_id);
+            (StatusCode::OK, Json(api_response)).into_response()
+        },
+        Err(err) => {
+            monitoring::record_auth_operation("account_deletion_cancel", "failure");
+            warn!(
+                request_id = %request_id,
+                error = %err,
+                user_id = %user.user_id,
+                "Account deletion cancellation failed"
+            );
+            account_deletion_error(err, request_id)
+        },
+    }
+}
+
+fn account_deletion_error(err: AccountDeletionError, request_id: String) -> Response {
+    match err {
+        AccountDeletionError::InvalidCredentials => ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            "Invalid password",
+            "INVALID_CREDENTIALS",
+            request_id,
+        )
+        .into_response(),
+        AccountDeletionError::UserNotFound => {
+            ApiError::authentication_error(request_id).into_response()
+        },
+        AccountDeletionError::AlreadyPending => ApiError::new(
+            StatusCode::CONFLICT,
+            "Account deletion has already been requested",
+            "ACCOUNT_PENDING_DELETION",
+            request_id,
+        )
+        .into_response(),
+        AccountDeletionError::NotPending => ApiError::new(
+            StatusCode::CONFLICT,
+            "No account deletion can be cancelled",
+            "NO_PENDING_DELETION",
+            request_id,
+        )
+        .into_response(),
+        _ => ApiError::internal_server_error(request_id).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acci_auth::{
+        AccountDeletionConfig, AccountDeletionRepository, AuthConfig, DeletionReport, User,
+        UserRepository, hash_password,
+        models::{tenant::mock::MockTenantRepository, user::mock::MockUserRepository},
+        repository::account_deletion_repository::mock::MockAccountDeletionRepository,
+        session::mock::MockSessionRepository,
+    };
+    use axum::{
+        extract::FromRequestParts,
+        http::{Request, header},
+    };
+    use serde_json::{Map, Value};
+    use uuid::Uuid;
+
+    const PASSWORD: &str = "Correct-Horse-9";
+
+    struct Fixture {
+        state: AccountDeletionAppState,
+        repository: Arc<MockAccountDeletionRepository>,
+        user_repo: Arc<MockUserRepository>,
+        tenant_repo: Arc<MockTenantRepository>,
+    }
+
+    impl Fixture {
+        fn new(grace_period_days: i64) -> Self {
+            let repository = Arc::new(MockAccountDeletionRepository::new());
+            let user_repo = Arc::new(MockUserRepository::new());
+            let tenant_repo = Arc::new(MockTenantRepository::new());
+            let session_service = Arc::new(SessionService::new(
+                Arc::new(MockSessionRepository::new()),
+                Arc::new(AuthConfig::default()),
+            ));
+            let account_deletion_service = Arc::new(
+                AccountDeletionService::new(
+                    repository.clone(),
+                    user_repo.clone(),
+                    session_service.clone(),
+                    AccountDeletionConfig {
+                        grace_period_days,
+                        ..AccountDeletionConfig::default()
+                    },
+                )
+                .with_tenant_repository(tenant_repo.clone()),
+            );
+            Self {
+                state: AccountDeletionAppState {
+                    account_deletion_service,
+                    session_service,
+                },
+                repository,
+                user_repo,
+                tenant_repo,
+            }
+        }
+
+        async fn user(&self) -> User {
+            let user = User::new(
+                format!("{}@example.com", Uuid::new_v4()),
+                hash_password(PASSWORD).expect("Password hashes"),
+            );
+            self.user_repo.create(&user).await.expect("User is stored");
+            user
+        }
+
+        async fn authenticate(&self, user_id: Uuid) -> AuthenticatedUser {
+            let (_, token) = self
+                .state
+                .session_service
+                .create_session(user_id, None, None, None, None, None, false)
+                .await
+                .expect("Session is created");
+            let mut parts = Request::builder()
+                .uri("/auth/me/delete")
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(())
+                .expect("valid request")
+                .into_parts()
+                .0;
+            AuthenticatedUser::from_request_parts(&mut parts, &self.state)
+                .await
+                .expect("Session is valid")
+        }
+
+        async fn delete(&self, user: AuthenticatedUser, password: Option<&str>) -> Response {
+            request_account_deletion(
+                State(self.state.clone()),
+                user,
+                Json(DeleteAccountRequest {
+                    password: password.map(str::to_string),
+                }),
+            )
+            .await
+        }
+
+        async fn cancel(&self, user: AuthenticatedUser) -> Response {
+            cancel_account_deletion(State(self.state.clone()), user).await
+        }
+    }
+
+    async fn body(response: Response) -> Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read body");
+        serde_json::from_slice(&body).expect("Body is JSON")
+    }
+
+    #[tokio::test]
+    async fn test_cancel_account_deletion() {
+        let fixture = Fixture::new(14);
+        let user = fixture.user().await;
+        let session = fixture.authenticate(user.id).await;
+
+        // Nothing to cancel yet
+        let response = fixture.cancel(session.clone()).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(body(response).await["code"], "NO_PENDING_DELETION");
+
+        // Without a password or second factor the request is refused
+        let response = fixture.delete(session.clone(), None).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(body(response).await["code"], "REAUTH_REQUIRED");
+
+        let response = fixture.delete(session.clone(), Some(PASSWORD)).await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        assert!(body(response).await["data"]["scheduled_for"].is_string());
+
+        let response = fixture.delete(session.clone(), Some(PASSWORD)).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(body(response).await["code"], "ACCOUNT_PENDING_DELETION");
+
+        let response = fixture.cancel(session).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(
+            fixture
+                .repository
+                .find_pending(user.id)
+                .await
+                .expect("Pending deletion is looked up")
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_last_admin_account_survives_deletion_job() {
+        // Without a grace period the job picks the request up right away
+        let fixture = Fixture::new(0);
+        let admin = fixture.user().await;
+        let tenant_id = fixture
+            .tenant_repo
+            .insert_tenant_with_metadata(Value::Object(Map::new()));
+        fixture.tenant_repo.add_member(tenant_id, admin.id, "ADMIN");
+
+        let session = fixture.authenticate(admin.id).await;
+        let response = fixture.delete(session.clone(), Some(PASSWORD)).await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        let report = fixture
+            .state
+            .account_deletion_service
+            .process_due()
+            .await
+            .expect("Deletion job runs");
+        assert_eq!(
+            report,
+            DeletionReport {
+                deleted: 0,
+                blocked: 1,
+                failed: 0,
+            }
+        );
+
+        // The account and its session stay usable, but the grace period is over
+        let kept = fixture
+            .user_repo
+            .find_by_id(admin.id)
+            .await
+            .expect("User is loaded")
+            .expect("User exists");
+        assert!(kept.is_active);
+        assert_eq!(kept.email, admin.email);
+        let response = fixture.cancel(session).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(body(response).await["code"], "NO_PENDING_DELETION");
+    }
+}
@@ -0,0 +1,432 @@
+//! Platform administration endpoints
+//!
+//! `GET /admin/overview` summarizes the whole platform for operators: tenants,
+//! recent signups, active sessions overall and by region, today's logins and
+//! the lockouts in effect. It is restricted to users whose access token
+//! carries the platform admin claim.
+//!
+//! Each metric is computed on its own. A metric that fails is `null` in the
+//! response and named in `warnings`, so an unavailable Redis does not hide the
+//! database metrics. The overview is computed at most once per
+//! [`OVERVIEW_CACHE_TTL`] per instance.
+
+use crate::middleware::auth::{AuthExtractorState, AuthenticatedUser};
+use crate::response::{ApiError, ApiResponse};
+use crate::validation::generate_request_id;
+use axum::{
+    extract::{FromRef, Json, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use time::format_description::well_known::Rfc3339;
+use time::{OffsetDateTime, Time};
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+use acci_auth::{
+    BruteForceProtection, JwtUtils, PlatformStatsRepository, RegionSessionCount,
+    TenantSessionCount, services::session::SessionService,
+};
+
+/// How long a computed overview is served before it is computed again
+pub const OVERVIEW_CACHE_TTL: Duration = Duration::from_secs(60);
+/// Number of tenants listed by active sessions
+const TOP_TENANTS: u32 = 5;
+
+/// Platform Administration Application State
+#[derive(Clone)]
+pub struct AdminOverviewAppState {
+    /// Aggregate queries over all tenants
+    pub stats_repository: Arc<dyn PlatformStatsRepository>,
+    /// Counts the lockouts in effect; the metric is `null` without it
+    pub brute_force: Option<Arc<BruteForceProtection>>,
+    /// Session service for authenticating the admin
+    pub session_service: Arc<SessionService>,
+    /// Validates the access token carrying the platform admin claim
+    pub jwt_utils: Arc<JwtUtils>,
+    /// Overview shared by the requests within the TTL
+    pub cache: Arc<OverviewCache>,
+}
+
+impl FromRef<AdminOverviewAppState> for AuthExtractorState {
+    fn from_ref(state: &AdminOverviewAppState) -> Self {
+        Self {
+            session_service: state.session_service.clone(),
+            tenant_service: None,
+            jwt_utils: Some(state.jwt_utils.clone()),
+        }
+    }
+}
+
+/// The last computed overview of this instance
+pub struct OverviewCache {
+    ttl: Duration,
+    entry: Mutex<Option<(Instant, AdminOverviewResponse)>>,
+}
+
+impl OverviewCache {
+    /// Create an empty cache serving an overview for `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entry: Mutex::new(None),
+        }
+    }
+
+    /// The cached overview, or the one `compute` returns once it expired
+    ///
+    /// Concurrent requests wait for the one computing the overview instead of
+    /// running the queries again.
+    async fn get_or_compute<F>(&self, compute: impl FnOnce() -> F) -> AdminOverviewResponse
+    where
+        F: Future<Output = AdminOverviewResponse>,
+    {
+        let mut entry = self.entry.lock().await;
+        if let Some((_, overview)) = entry
+            .as_ref()
+            .filter(|(computed_at, _)| computed_at.elapsed() < self.ttl)
+        {
+            return overview.clone();
+        }
+
+        let overview = compute().await;
+        *entry = Some((Instant::now(), overview.clone()));
+        overview
+    }
+}
+
+impl Default for OverviewCache {
+    fn default() -> Self {
+        Self::new(OVERVIEW_CACHE_TTL)
+    }
+}
+
+/// A metric missing from the overview
+#[derive(Debug, Clone, Serialize)]
+pub struct OverviewWarning {
+    /// Field of the overview that is `null`
+    pub metric: String,
+    pub message: String,
+}
+
+/// Tenant response DTO of the overview
+#[derive(Debug, Clone, Serialize)]
+pub struct TopTenantResponse {
+    pub tenant_id: String,
+    pub name: String,
+    pub active_sessions: u64,
+}
+
+impl From<TenantSessionCount> for TopTenantResponse {
+    fn from(tenant: TenantSessionCount) -> Self {
+        Self {
+            tenant_id: tenant.tenant_id.to_string(),
+            name: tenant.name,
+            active_sessions: tenant.active_sessions,
+        }
+    }
+}
+
+/// Platform overview response DTO
+///
+/// Metrics that could not be computed are `null`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AdminOverviewResponse {
+    pub total_tenants: Option<u64>,
+    /// Tenants not deactivated
+    pub active_tenants: Option<u64>,
+    pub users_created_last_7_days: Option<u64>,
+    pub users_created_last_30_days: Option<u64>,
+    /// Valid, unexpired sessions
+    pub active_sessions: Option<u64>,
+    /// Valid, unexpired sessions by region, most first
+    pub active_sessions_by_region: Option<Vec<RegionSessionCount>>,
+    /// Login attempts since midnight UTC
+    pub logins_today: Option<u64>,
+    /// Login attempts since midnight UTC that did not succeed
+    pub failed_logins_today: Option<u64>,
+    /// Usernames locked by the brute force protection
+    pub active_lockouts: Option<u64>,
+    /// Tenants with the most active sessions, most first
+    pub top_tenants_by_sessions: Option<Vec<TopTenantResponse>>,
+    /// When the overview was computed (RFC 3339)
+    pub generated_at: Option<String>,
+    /// Metrics missing from the overview
+    pub warnings: Vec<OverviewWarning>,
+}
+
+impl AdminOverviewResponse {
+    /// The value of `result`, or `None` with a warning for `metrics`
+    fn metric<T, E: Display>(&mut self, metrics: &[&str], result: Result<T, E>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                warn!(metrics = ?metrics, error = %e, "Failed to compute overview metric");
+                self.warnings
+                    .extend(metrics.iter().map(|metric| OverviewWarning {
+                        metric: metric.to_string(),
+                        message: "Metric is currently unavailable".to_string(),
+                    }));
+                None
+            },
+        }
+    }
+}
+
+/// Run the aggregate queries and count the lockouts, concurrently
+async fn compute_overview(state: &AdminOverviewAppState) -> AdminOverviewResponse {
+    let now = OffsetDateTime::now_utc();
+    let midnight = now.replace_time(Time::MIDNIGHT);
+    let repository = &state.stats_repository;
+
+    let lockouts = async {
+        match &state.brute_force {
+            Some(brute_force) => brute_force
+                .locked_accounts()
+                .await
+                .map_err(|e| e.to_string()),
+            None => Err("Brute force protection is not configured".to_string()),
+        }
+    };
+    let (tenants, signups, sessions, regions, logins, top_tenants, lockouts) = tokio::join!(
+        repository.tenant_counts(),
+        repository.user_signups(now),
+        repository.active_sessions(now),
+        repository.active_sessions_by_region(now),
+        repository.login_stats(midnight),
+        repository.top_tenants_by_sessions(now, TOP_TENANTS),
+        lockouts,
+    );
+
+    let mut overview = AdminOverviewResponse {
+        generated_at: now.format(&Rfc3339).ok(),
+        ..AdminOverviewResponse::default()
+    };
+    if let Some(tenants) = overview.metric(&["total_tenants", "active_tenants"], tenants) {
+        overview.total_tenants = Some(tenants.total);
+        overview.active_tenants = Some(tenants.active);
+    }
+    if let Some(signups) = overview.metric(
+        &["users_created_last_7_days", "users_created_last_30_days"],
+        signups,
+    ) {
+        overview.users_created_last_7_days = Some(signups.last_7_days);
+        overview.users_created_last_30_days = Some(signups.last_30_days);
+    }
+    overview.active_sessions = overview.metric(&["active_sessions"], sessions);
+    overview.active_sessions_by_region = overview.metric(&["active_sessions_by_region"], regions);
+    if let Some(logins) = overview.metric(&["logins_today", "failed_logins_today"], logins) {
+        overview.logins_today = Some(logins.total);
+        overview.failed_logins_today = Some(logins.failed);
+    }
+    overview.active_lockouts = overview.metric(&["active_lockouts"], lockouts);
+    overview.top_tenants_by_sessions = overview
+        .metric(&["top_tenants_by_sessions"], top_tenants)
+        .map(|tenants| tenants.into_iter().map(Into::into).collect());
+
+    overview
+}
+
+/// Handler for the platform overview
+#[axum::debug_handler(state = AdminOverviewAppState)]
+pub async fn get_admin_overview(
+    State(state): State<AdminOverviewAppState>,
+    user: AuthenticatedUser,
+) -> Response {
+    debug!("Processing admin overview request");
+    let request_id = generate_request_id();
+
+    if !user.is_platform_admin() {
+        warn!(
+            request_id = %request_id,
+            user_id = %user.user_id,
+            "User is not a platform admin"
+        );
+        return ApiError::authorization_error(request_id).into_response();
+    }
+
+    let overview = state
+        .cache
+        .get_or_compute(|| compute_overview(&state))
+        .await;
+    let api_response = ApiResponse::success(overview, request_id);
+    (StatusCode::OK, Json(api_response)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acci_auth::security::BruteForceConfig;
+    use acci_auth::session::types::MfaStatus;
+    use acci_auth::test_support::{
+        MockPlatformStats, MockPlatformStatsRepository, MockSessionRepository,
+    };
+    use acci_auth::{AuthConfig, LoginAttemptStats, PLATFORM_ADMIN_CLAIM, TenantCounts};
+    use serde_json::Value;
+    use uuid::Uuid;
+
+    fn state(
+        repository: Arc<MockPlatformStatsRepository>,
+        brute_force: Option<Arc<BruteForceProtection>>,
+        ttl: Duration,
+    ) -> AdminOverviewAppState {
+        AdminOverviewAppState {
+            stats_repository: repository,
+            brute_force,
+            session_service: Arc::new(SessionService::new(
+                Arc::new(MockSessionRepository::new()),
+                Arc::new(AuthConfig::default()),
+            )),
+            jwt_utils: Arc::new(JwtUtils::new(b"test-secret")),
+            cache: Arc::new(OverviewCache::new(ttl)),
+        }
+    }
+
+    fn repository(total_tenants: u64) -> Arc<MockPlatformStatsRepository> {
+        Arc::new(MockPlatformStatsRepository::new(MockPlatformStats {
+            tenants: TenantCounts {
+                total: total_tenants,
+                active: total_tenants - 1,
+            },
+            active_sessions: 12,
+            regions: vec![
+                RegionSessionCount {
+                    region: Some("eu-west".to_string()),
+                    active_sessions: 8,
+                },
+                RegionSessionCount {
+                    region: None,
+                    active_sessions: 4,
+                },
+            ],
+            logins: LoginAttemptStats {
+                total: 20,
+                succeeded: 17,
+                failed: 3,
+            },
+            top_tenants: vec![TenantSessionCount {
+                tenant_id: Uuid::new_v4(),
+                name: "Acme".to_string(),
+                active_sessions: 9,
+            }],
+            ..MockPlatformStats::default()
+        }))
+    }
+
+    /// Brute force protection whose Redis cannot be reached
+    fn unavailable_brute_force() -> Arc<BruteForceProtection> {
+        let client = redis::Client::open("redis://127.0.0.1:1").expect("valid Redis URL");
+        Arc::new(BruteForceProtection::new(
+            Arc::new(client),
+            BruteForceConfig::default(),
+        ))
+    }
+
+    fn user(platform_admin: bool) -> AuthenticatedUser {
+        let now = OffsetDateTime::now_utc();
+        let mut custom_claims = serde_json::Map::new();
+        if platform_admin {
+            custom_claims.insert(PLATFORM_ADMIN_CLAIM.to_string(), Value::Bool(true));
+        }
+        AuthenticatedUser {
+            user_id: Uuid::new_v4(),
+            session_id: Uuid::new_v4(),
+            tenant_id: None,
+            mfa_status: MfaStatus::Verified,
+            memberships: Vec::new(),
+            claims: None,
+            custom_claims,
+            last_auth_at: now,
+            expires_at: now + time::Duration::hours(1),
+            impersonated_by: None,
+        }
+    }
+
+    async fn overview(state: &AdminOverviewAppState) -> Value {
+        let response = get_admin_overview(State(state.clone()), user(true)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read body");
+        let body: Value = serde_json::from_slice(&body).expect("Body is JSON");
+        body["data"].clone()
+    }
+
+    #[tokio::test]
+    async fn test_cache_prevents_repeated_queries_within_ttl() {
+        let repository = repository(4);
+        let state = state(repository.clone(), None, OVERVIEW_CACHE_TTL);
+
+        let first = overview(&state).await;
+        let queries = repository.queries();
+        assert_eq!(queries, 6);
+        assert_eq!(first["total_tenants"], 4);
+
+        // A changed count is not seen until the TTL passed
+        repository.set_stats(MockPlatformStats::default());
+        let second = overview(&state).await;
+        assert_eq!(repository.queries(), queries);
+        assert_eq!(second, first);
+    }
+
+    #[tokio::test]
+    async fn test_expired_overview_is_computed_again() {
+        let repository = repository(4);
+        let state = state(repository.clone(), None, Duration::ZERO);
+
+        overview(&state).await;
+        repository.set_stats(MockPlatformStats::default());
+        let second = overview(&state).await;
+
+        assert_eq!(repository.queries(), 12);
+        assert_eq!(second["total_tenants"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_lockout_count_is_null_with_warning() {
+        let repository = repository(4);
+        let state = state(
+            repository,
+            Some(unavailable_brute_force()),
+            OVERVIEW_CACHE_TTL,
+        );
+
+        let overview = overview(&state).await;
+
+        assert_eq!(overview["active_lockouts"], Value::Null);
+        assert_eq!(overview["warnings"].as_array().map(Vec::len), Some(1));
+        assert_eq!(overview["warnings"][0]["metric"], "active_lockouts");
+        // The database metrics are still reported
+        assert_eq!(overview["total_tenants"], 4);
+        assert_eq!(overview["active_tenants"], 3);
+        assert_eq!(overview["active_sessions"], 12);
+        assert_eq!(
+            overview["active_sessions_by_region"][0]["region"],
+            "eu-west"
+        );
+        assert!(overview["active_sessions_by_region"][1]["region"].is_null());
+        assert_eq!(overview["logins_today"], 20);
+        assert_eq!(overview["failed_logins_today"], 3);
+        assert_eq!(overview["top_tenants_by_sessions"][0]["name"], "Acme");
+    }
+
+    #[tokio::test]
+    async fn test_overview_requires_platform_admin_claim() {
+        let repository = repository(4);
+        let state = state(repository.clone(), None, OVERVIEW_CACHE_TTL);
+
+        let response = get_admin_overview(State(state.clone()), user(false)).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let mut impersonated = user(true);
+        impersonated.impersonated_by = Some(Uuid::new_v4());
+        let response = get_admin_overview(State(state), impersonated).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        assert_eq!(repository.queries(), 0);
+    }
+}
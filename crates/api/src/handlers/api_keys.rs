@@ -0,0 +1,366 @@
+//! API key lifecycle endpoints for tenant admins
+//!
+//! `GET /tenants/{id}/api-keys` lists the keys of a tenant. It takes the
+//! optional filters `tags` (comma-separated, all must match), `search`,
+//! `expiring_within` and `unused_for`; the durations are given as seconds
+//! or as a duration such as `30d`. `POST /tenants/{id}/api-keys/{key_id}/rotate`
+//! issues a replacement key; the old key stays valid for the configured
+//! rotation overlap, so integrations can switch over without downtime.
+
+use crate::middleware::auth::{AuthExtractorState, AuthenticatedUser};
+use crate::monitoring;
+use crate::response::{ApiError, ApiResponse};
+use crate::validation::generate_request_id;
+use axum::{
+    extract::{FromRef, Json, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use time::format_description::well_known::Rfc3339;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use acci_auth::{
+    ApiKey, ApiKeyQuery, ApiKeyService, DurationSecs, RotatedApiKey,
+    services::{session::SessionService, tenant::TenantService},
+};
+
+/// Services the API key endpoints need
+#[derive(Clone)]
+pub struct ApiKeyAppState {
+    pub api_key_service: Arc<ApiKeyService>,
+    pub session_service: Arc<SessionService>,
+    /// Source of the tenant memberships the admin check relies on
+    pub tenant_service: Option<Arc<TenantService>>,
+}
+
+impl FromRef<ApiKeyAppState> for AuthExtractorState {
+    fn from_ref(state: &ApiKeyAppState) -> Self {
+        Self {
+            session_service: state.session_service.clone(),
+            tenant_service: state.tenant_service.clone(),
+            jwt_utils: None,
+        }
+    }
+}
+
+/// Query parameters of the key listing
+#[derive(Debug, Default, Deserialize)]
+pub struct ListApiKeysQuery {
+    /// Comma-separated tags a key must all carry
+    pub tags: Option<String>,
+    /// Text contained in the name, description or prefix of a key
+    pub search: Option<String>,
+    /// Only valid keys expiring within this time
+    pub expiring_within: Option<DurationSecs>,
+    /// Only valid keys not used for this time
+    pub unused_for: Option<DurationSecs>,
+}
+
+impl From<ListApiKeysQuery> for ApiKeyQuery {
+    fn from(query: ListApiKeysQuery) -> Self {
+        Self {
+            tags: query
+                .tags
+                .iter()
+                .flat_map(|tags| tags.split(','))
+                .filter(|tag| !tag.trim().is_empty())
+                .map(str::to_string)
+                .collect(),
+            search: query.search,
+            expiring_within: query.expiring_within.map(DurationSecs::to_time),
+            unused_for: query.unused_for.map(DurationSecs::to_time),
+        }
+    }
+}
+
+/// API key response DTO; the secret is never part of it
+#[derive(Debug, Serialize)]
+pub struct ApiKeyResponse {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub created_by: Option<String>,
+    /// Timestamps in RFC 3339
+    pub created_at: Option<String>,
+    pub expires_at: Option<String>,
+    pub last_used_at: Option<String>,
+    pub revoked_at: Option<String>,
+}
+
+impl From<ApiKey> for ApiKeyResponse {
+    fn from(key: ApiKey) -> Self {
+        Self {
+            id: key.id.to_string(),
+            name: key.name,
+            description: key.description,
+            tags: key.tags,
+            prefix: key.prefix,
+            scopes: key.scopes,
+            created_by: key.created_by.map(|user_id| user_id.to_string()),
+            created_at: key.created_at.format(&Rfc3339).ok(),
+            expires_at: key.expires_at.and_then(|at| at.format(&Rfc3339).ok()),
+            last_used_at: key.last_used_at.and_then(|at| at.format(&Rfc3339).ok()),
+            revoked_at: key.revoked_at.and_then(|at| at.format(&Rfc3339).ok()),
+        }
+    }
+}
+
+/// Key listing response DTO
+#[derive(Debug, Serialize)]
+pub struct ApiKeyListResponse {
+    pub api_keys: Vec<ApiKeyResponse>,
+}
+
+/// Rotated key response DTO
+#[derive(Debug, Serialize)]
+pub struct RotateApiKeyResponse {
+    /// The new key; it is shown only once
+    pub key: String,
+    pub api_key: ApiKeyResponse,
+    /// When the rotated key stops being accepted (RFC 3339)
+    pub previous_key_expires_at: Option<String>,
+}
+
+impl From<RotatedApiKey> for RotateApiKeyResponse {
+    fn from(rotated: RotatedApiKey) -> Self {
+        Self {
+            key: rotated.issued.key,
+            api_key: rotated.issued.api_key.into(),
+            previous_key_expires_at: rotated
+                .previous_key_expires_at
+                .and_then(|at| at.format(&Rfc3339).ok()),
+        }
+    }
+}
+
+/// Handler listing the API keys of a tenant
+#[axum::debug_handler(state = ApiKeyAppState)]
+pub async fn list_api_keys(
+    State(state): State<ApiKeyAppState>,
+    user: AuthenticatedUser,
+    Path(tenant_id): Path<Uuid>,
+    Query(query): Query<ListApiKeysQuery>,
+) -> Response {
+    debug!("Processing API key listing request");
+    let request_id = generate_request_id();
+
+    if !user.has_tenant_role(tenant_id, "ADMIN") {
+        monitoring::record_tenant_operation("list_api_keys", "failure");
+        warn!(
+            request_id = %request_id,
+            user_id = %user.user_id,
+            tenant_id = %tenant_id,
+            "User is not an administrator of the tenant"
+        );
+        return ApiError::authorization_error(request_id).into_response();
+    }
+
+    match state.api_key_service.list(tenant_id, query.into()).await {
+        Ok(keys) => {
+            monitoring::record_tenant_operation("list_api_keys", "success");
+            let response = ApiKeyListResponse {
+                api_keys: keys.into_iter().map(Into::into).collect(),
+            };
+            let api_response = ApiResponse::success(response, request_id);
+            (StatusCode::OK, Json(api_response)).into_response()
+        },
+        Err(err) => {
+            monitoring::record_tenant_operation("list_api_keys", "failure");
+            warn!(request_id = %request_id, error = %err, "Failed to list API keys");
+            ApiError::from_error(err, request_id).into_response()
+        },
+    }
+}
+
+/// Handler rotating an API key of a tenant
+#[axum::debug_handler(state = ApiKeyAppState)]
+pub async fn rotate_api_key(
+    State(state): State<ApiKeyAppState>,
+    user: AuthenticatedUser,
+    Path((tenant_id, key_id)): Path<(Uuid, Uuid)>,
+) -> Response {
+    debug!("Processing API key rotation request");
+    let request_id = generate_request_id();
+
+    if !user.has_tenant_role(tenant_id, "ADMIN") {
+        monitoring::record_tenant_operation("rotate_api_key", "failure");
+        warn!(
+            request_id = %request_id,
+            user_id = %user.user_id,
+            tenant_id = %tenant_id,
+            "User is not an administrator of the tenant"
+        );
+        return ApiError::authorization_error(request_id).into_response();
+    }
+
+    match state.api_key_service.rotate(tenant_id, key_id).await {
+        Ok(rotated) => {
+            monitoring::record_tenant_operation("rotate_api_key", "success");
+            let api_response =
+                ApiResponse::success(RotateApiKeyResponse::from(rotated), request_id);
+            (StatusCode::OK, Json(api_response)).into_response()
+        },
+        Err(err) => {
+            monitoring::record_tenant_operation("rotate_api_key", "failure");
+            warn!(
+                request_id = %request_id,
+                key_id = %key_id,
+                error = %err,
+                "Failed to rotate API key"
+            );
+            ApiError::from_error(err, request_id).into_response()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acci_auth::session::types::MfaStatus;
+    use acci_auth::test_support::{MockApiKeyRepository, MockSessionRepository};
+    use acci_auth::{AuthConfig, IssueApiKey, TenantUser};
+    use serde_json::Value;
+    use time::OffsetDateTime;
+
+    struct Fixture {
+        state: ApiKeyAppState,
+        tenant_id: Uuid,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let session_service = Arc::new(SessionService::new(
+                Arc::new(MockSessionRepository::new()),
+                Arc::new(AuthConfig::default()),
+            ));
+            Self {
+                state: ApiKeyAppState {
+                    api_key_service: Arc::new(ApiKeyService::new(Arc::new(
+                        MockApiKeyRepository::new(),
+                    ))),
+                    session_service,
+                    tenant_service: None,
+                },
+                tenant_id: Uuid::new_v4(),
+            }
+        }
+
+        fn user(&self, role: &str) -> AuthenticatedUser {
+            let user_id = Uuid::new_v4();
+            AuthenticatedUser {
+                user_id,
+                session_id: Uuid::new_v4(),
+                tenant_id: Some(self.tenant_id),
+                mfa_status: MfaStatus::Verified,
+                memberships: vec![TenantUser {
+                    tenant_id: self.tenant_id,
+                    user_id,
+                    tenant_role: role.to_string(),
+                    is_active: true,
+                    created_at: OffsetDateTime::now_utc(),
+                    updated_at: OffsetDateTime::now_utc(),
+                }],
+                claims: None,
+                custom_claims: Default::default(),
+                last_auth_at: OffsetDateTime::now_utc(),
+                expires_at: OffsetDateTime::now_utc() + time::Duration::hours(1),
+                impersonated_by: None,
+            }
+        }
+
+        async fn issue(&self, name: &str, tags: &[&str], expires_in_days: i64) -> String {
+            self.state
+                .api_key_service
+                .issue(
+                    self.tenant_id,
+                    IssueApiKey {
+                        name: name.to_string(),
+                        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                        expires_in: Some(time::Duration::days(expires_in_days)),
+                        ..IssueApiKey::default()
+                    },
+                )
+                .await
+                .expect("Key is issued")
+                .key
+        }
+
+        async fn list(&self, user: AuthenticatedUser, query: &str) -> Response {
+            let uri: axum::http::Uri = format!("/api-keys?{}", query).parse().expect("Valid URI");
+            let query = Query::<ListApiKeysQuery>::try_from_uri(&uri).expect("Valid query");
+            list_api_keys(State(self.state.clone()), user, Path(self.tenant_id), query).await
+        }
+    }
+
+    async fn data(response: Response) -> Value {
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read body");
+        let body: Value = serde_json::from_slice(&body).expect("Body is JSON");
+        body["data"].clone()
+    }
+
+    #[tokio::test]
+    async fn test_admin_lists_keys_expiring_within() {
+        let fixture = Fixture::new();
+        fixture.issue("billing", &["ci"], 10).await;
+        fixture.issue("reporting", &["ci"], 90).await;
+        fixture.issue("export", &[], 5).await;
+
+        let admin = fixture.user("ADMIN");
+        let data = data(fixture.list(admin, "expiring_within=30d&tags=CI").await).await;
+        let names: Vec<&str> = data["api_keys"]
+            .as_array()
+            .expect("Keys are listed")
+            .iter()
+            .filter_map(|key| key["name"].as_str())
+            .collect();
+        assert_eq!(names, vec!["billing"]);
+
+        let member = fixture.user("MEMBER");
+        let response = fixture.list(member, "").await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_rotation_keeps_old_key_valid_for_overlap() {
+        let fixture = Fixture::new();
+        let old_key = fixture.issue("billing", &[], 90).await;
+        let key_id = fixture
+            .state
+            .api_key_service
+            .authenticate(&old_key)
+            .await
+            .expect("Key is valid")
+            .id;
+
+        let response = rotate_api_key(
+            State(fixture.state.clone()),
+            fixture.user("ADMIN"),
+            Path((fixture.tenant_id, key_id)),
+        )
+        .await;
+        let data = data(response).await;
+        let new_key = data["key"].as_str().expect("New key is returned");
+        assert!(data["previous_key_expires_at"].is_string());
+
+        let service = &fixture.state.api_key_service;
+        assert!(service.authenticate(new_key).await.is_ok());
+        assert!(service.authenticate(&old_key).await.is_ok());
+
+        let response = rotate_api_key(
+            State(fixture.state.clone()),
+            fixture.user("ADMIN"),
+            Path((fixture.tenant_id, Uuid::new_v4())),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
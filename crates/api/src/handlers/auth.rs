@@ -1,24 +1,30 @@
+use crate::middleware::auth::{AuthExtractorState, AuthenticatedUser};
+use crate::middleware::client::ClientInfo;
 use crate::monitoring;
 use crate::response::{ApiError, ApiResponse};
 use crate::validation::{generate_request_id, validate_json_payload};
 use axum::{
-    extract::{Json, State},
-    http::StatusCode,
+    extract::{FromRef, Json, State},
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use time::format_description::well_known::Rfc3339;
 use tracing::{debug, info, warn};
+use uuid::Uuid;
 use validator::Validate;
 
 // Import auth services and models
 use acci_auth::{
-    CreateUser,
-    models::user::UserError,
+    ACCOUNT_PENDING_DELETION, AuthDecision, ClientFingerprint, CreateUser, CurrentUser,
+    CurrentUserRejection, MfaEnforcement, SessionError, TenantError, auth_event,
     services::{
-        session::SessionService,
-        user::{UserService, UserServiceError},
+        session::{SessionService, SessionServiceError},
+        user::{LoginResult, UserService, UserServiceError},
     },
+    session::types::MfaStatus,
+    utils::auth_log,
 };
 
 /// API Application State
@@ -30,6 +36,22 @@ pub struct ApiAppState {
     pub session_service: Arc<SessionService>,
 }
 
+impl FromRef<ApiAppState> for Arc<SessionService> {
+    fn from_ref(state: &ApiAppState) -> Self {
+        state.session_service.clone()
+    }
+}
+
+impl FromRef<ApiAppState> for AuthExtractorState {
+    fn from_ref(state: &ApiAppState) -> Self {
+        Self {
+            session_service: state.session_service.clone(),
+            tenant_service: None,
+            jwt_utils: None,
+        }
+    }
+}
+
 /// Login Request DTO
 #[derive(Debug, Deserialize, Validate)]
 pub struct LoginRequest {
@@ -41,6 +63,18 @@ pub struct LoginRequest {
 
     /// Optional tenant ID for multi-tenant context
     pub tenant_id: Option<String>,
+
+    /// Keep the user signed in with a long-lived session ("remember me")
+    #[serde(default)]
+    pub remember_me: bool,
+
+    /// Device attributes collected by the client, recorded with the new session
+    #[serde(default)]
+    pub fingerprint: Option<ClientFingerprint>,
+
+    /// Token from `/auth/devices/trust`; skips MFA if the fingerprint still matches
+    #[serde(default)]
+    pub trusted_device_token: Option<String>,
 }
 
 /// Login Response DTO
@@ -49,15 +83,123 @@ pub struct LoginResponse {
     pub token: String,
     pub user_id: String,
     pub expires_at: i64,
+    /// Active tenant of the new session
     pub tenant_id: Option<String>,
+    /// Whether the client has to pick a tenant via `/auth/switch-tenant`
+    pub tenant_selection_required: bool,
+    /// Whether the session has to complete a second factor before it is MFA-verified
+    pub mfa_required: bool,
+    /// Tenants to choose from; only listed when a selection is required
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tenants: Vec<TenantMembershipResponse>,
+    /// `ACCOUNT_PENDING_DELETION` while the account is scheduled for deletion
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+    /// When the account will be deleted (RFC 3339)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deletion_scheduled_for: Option<String>,
+    /// Whole days until the password expires, within the tenant's warning window
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password_expires_in_days: Option<u32>,
+}
+
+/// Data of a `PASSWORD_EXPIRED` login response
+#[derive(Debug, Serialize)]
+pub struct PasswordExpiredResponse {
+    /// Single-use token for `/auth/reset-password`; missing if resets are not configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reset_token: Option<String>,
+}
+
+/// Tenant membership offered for selection after login
+#[derive(Debug, Serialize)]
+pub struct TenantMembershipResponse {
+    pub tenant_id: String,
+    pub role: String,
+}
+
+impl From<LoginResult> for LoginResponse {
+    fn from(login_result: LoginResult) -> Self {
+        let tenant_selection_required = login_result.tenant_selection_required();
+        let tenants = if tenant_selection_required {
+            login_result
+                .memberships
+                .iter()
+                .map(|membership| TenantMembershipResponse {
+                    tenant_id: membership.tenant_id.to_string(),
+                    role: membership.tenant_role.clone(),
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Self {
+            token: login_result.session_token,
+            user_id: login_result.user.id.to_string(),
+            expires_at: 0, // We need to get this from somewhere else or compute it
+            tenant_id: login_result.active_tenant_id.map(|id| id.to_string()),
+            tenant_selection_required,
+            mfa_required: login_result.mfa_status == MfaStatus::Required,
+            tenants,
+            warning: login_result
+                .pending_deletion
+                .map(|_| ACCOUNT_PENDING_DELETION.to_string()),
+            deletion_scheduled_for: login_result
+                .pending_deletion
+                .and_then(|at| at.format(&Rfc3339).ok()),
+            password_expires_in_days: login_result.password_expires_in_days,
+        }
+    }
+}
+
+/// Bind a new session to the client that created it, if its tenant binds sessions
+///
+/// Failures are logged; an unbound session is accepted from any client.
+async fn bind_session_to_client(
+    session_service: &SessionService,
+    token: &str,
+    headers: &HeaderMap,
+    client: &ClientInfo,
+    request_id: &str,
+) {
+    let session = match session_service.validate_session(token).await {
+        Ok(Some(session)) => session,
+        Ok(None) => return,
+        Err(err) => {
+            warn!(request_id = %request_id, error = %err, "Failed to load session for client binding");
+            return;
+        },
+    };
+    let policy = match session_service.client_binding_policy(&session).await {
+        Ok(Some(policy)) => policy,
+        Ok(None) => return,
+        Err(err) => {
+            warn!(request_id = %request_id, error = %err, "Failed to load client binding policy");
+            return;
+        },
+    };
+    if let Err(err) = session_service
+        .bind_client(session.id, &client.characteristics(headers, &policy))
+        .await
+    {
+        warn!(
+            request_id = %request_id,
+            session_id = %session.id,
+            error = %err,
+            "Failed to bind session to client"
+        );
+    }
 }
 
 /// Handler for API login request
 #[axum::debug_handler]
 pub async fn api_login(
     State(state): State<ApiAppState>,
+    headers: HeaderMap,
+    client: ClientInfo,
     Json(request): Json<LoginRequest>,
-) -> Response {
+) -> Result<Response, ApiError> {
     debug!("Processing login request");
     let start = std::time::Instant::now();
 
@@ -69,7 +211,7 @@ pub async fn api_login(
         Ok(data) => data,
         Err(validation_error) => {
             // Convert validation error to response
-            return validation_error.into_response();
+            return Ok(validation_error.into_response());
         },
     };
 
@@ -82,103 +224,147 @@ pub async fn api_login(
             Ok(id) => Some(id),
             Err(_) => {
                 // Invalid UUID format for tenant ID
-                return ApiError::new(
+                return Err(ApiError::new(
                     StatusCode::BAD_REQUEST,
                     "Invalid tenant ID format",
                     "INVALID_TENANT_ID",
                     request_id,
-                )
-                .into_response();
+                ));
             },
         }
     } else {
         None
     };
 
+    let device_fingerprint = validated
+        .fingerprint
+        .clone()
+        .map(|fingerprint| fingerprint.into_device_fingerprint(client.user_agent.as_deref()));
+
     // Perform login process
-    match state
+    let login_result = match state
         .user_service
-        .login(
+        .login_with_trusted_device(
+            tenant_id,
             &validated.email,
             &validated.password,
+            validated.trusted_device_token.as_deref(),
             None, // device_id
-            None, // device_fingerprint
-            None, // ip_address
-            None, // user_agent
+            device_fingerprint,
+            client.ip_address.clone(),
+            client.user_agent.clone(),
+            validated.remember_me,
         )
         .await
     {
-        Ok(login_result) => {
-            // Verify user has access to the requested tenant if a tenant was specified
-            let tenant_id_to_use = tenant_id;
-
-            // If we have a tenant ID, we need to create a token with that tenant ID
-            if tenant_id_to_use.is_some() && login_result.session_token.starts_with("eyJ") {
-                // This is a JWT token, we should create a new one with tenant context
-                // TODO: Replace token with tenant-aware token
-            }
-
-            // Record successful login in metrics
-            monitoring::record_auth_operation("login", "success");
-
-            // Record login duration
-            let duration = start.elapsed();
-            monitoring::record_request_duration(duration.as_secs_f64(), "POST", "/auth/login");
-
-            // Successful login
-            let response = LoginResponse {
-                token: login_result.session_token,
-                user_id: login_result.user.id.to_string(),
-                expires_at: 0, // We need to get this from somewhere else or compute it
-                tenant_id: tenant_id_to_use.map(|id| id.to_string()),
-            };
-
-            info!(
-                request_id = %request_id,
-                user_id = %login_result.user.id,
-                tenant_id = ?tenant_id_to_use,
-                "Login successful"
+        Ok(login_result) => login_result,
+        // No session is created; the token only allows setting a new password
+        Err(UserServiceError::PasswordExpired { reset_token }) => {
+            monitoring::record_auth_operation("login", "password_expired");
+
+            auth_event!(
+                "login",
+                AuthDecision::Challenge,
+                "PASSWORD_EXPIRED",
+                request_id = &request_id,
+                tenant_id = tenant_id,
+                user = &validated.email,
+                ip = client.ip_address.as_deref(),
+                challenge = "password_reset",
+                latency = start.elapsed(),
             );
 
-            let api_response = ApiResponse::success(response, request_id);
-            (StatusCode::OK, Json(api_response)).into_response()
+            let api_response = ApiResponse::error_with_data(
+                PasswordExpiredResponse { reset_token },
+                "The password has expired and has to be reset",
+                "PASSWORD_EXPIRED",
+                request_id,
+            );
+            return Ok((StatusCode::FORBIDDEN, Json(api_response)).into_response());
         },
         Err(err) => {
             // Record failed login in metrics
             monitoring::record_auth_operation("login", "failure");
 
-            // Login error
-            let (status, message, code) = match err {
-                UserServiceError::InvalidCredentials => (
-                    StatusCode::UNAUTHORIZED,
-                    "Invalid email or password",
-                    "INVALID_CREDENTIALS",
-                ),
-                UserServiceError::User(UserError::InactiveUser) => {
-                    (StatusCode::FORBIDDEN, "Account is locked", "ACCOUNT_LOCKED")
-                },
-                UserServiceError::User(UserError::UnverifiedUser) => (
-                    StatusCode::FORBIDDEN,
-                    "Account is not verified",
-                    "ACCOUNT_UNVERIFIED",
-                ),
-                _ => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "An error occurred during login",
-                    "LOGIN_ERROR",
-                ),
-            };
-
-            warn!(
-                request_id = %request_id,
-                error = %err,
-                email = %validated.email,
-                "Login failed"
+            let error = ApiError::from_error(err, request_id.clone());
+            auth_event!(
+                "login",
+                AuthDecision::Deny,
+                error.code(),
+                request_id = &request_id,
+                tenant_id = tenant_id,
+                user = &validated.email,
+                ip = client.ip_address.as_deref(),
+                latency = start.elapsed(),
             );
 
-            ApiError::new(status, message, code, request_id).into_response()
+            return Err(error);
         },
+    };
+
+    let active_tenant_id = login_result.active_tenant_id;
+    let mfa_status = login_result.mfa_status.clone();
+    bind_session_to_client(
+        &state.session_service,
+        &login_result.session_token,
+        &headers,
+        &client,
+        &request_id,
+    )
+    .await;
+    let response = LoginResponse::from(login_result);
+
+    // Sessions pending MFA enrollment may only be used for the enrollment endpoints
+    if mfa_status == MfaStatus::Pending {
+        monitoring::record_auth_operation("login", "mfa_enrollment_required");
+
+        auth_event!(
+            "login",
+            AuthDecision::Challenge,
+            "MFA_ENROLLMENT_REQUIRED",
+            request_id = &request_id,
+            tenant_id = active_tenant_id,
+            user = &validated.email,
+            ip = client.ip_address.as_deref(),
+            challenge = "mfa_enrollment",
+            latency = start.elapsed(),
+        );
+
+        let api_response = ApiResponse::error_with_data(
+            response,
+            "MFA enrollment is required before the account can be used",
+            "MFA_ENROLLMENT_REQUIRED",
+            request_id,
+        );
+        return Ok((StatusCode::FORBIDDEN, Json(api_response)).into_response());
     }
+
+    // Record successful login in metrics
+    monitoring::record_auth_operation("login", "success");
+
+    // Record login duration
+    let duration = start.elapsed();
+    monitoring::record_request_duration(duration.as_secs_f64(), "POST", "/auth/login");
+
+    // Sessions of users with MFA still have to pass it before acting
+    let (decision, reason_code, challenge) = match mfa_status {
+        MfaStatus::Required => (AuthDecision::Challenge, "MFA_REQUIRED", Some("mfa")),
+        _ => (AuthDecision::Allow, "AUTHENTICATED", None),
+    };
+    auth_event!(
+        "login",
+        decision,
+        reason_code,
+        request_id = &request_id,
+        tenant_id = active_tenant_id,
+        user = &validated.email,
+        ip = client.ip_address.as_deref(),
+        challenge = challenge,
+        latency = duration,
+    );
+
+    let api_response = ApiResponse::success(response, request_id);
+    Ok((StatusCode::OK, Json(api_response)).into_response())
 }
 
 /// Registration Request DTO
@@ -206,7 +392,7 @@ pub struct RegistrationResponse {
 pub async fn api_register(
     State(state): State<ApiAppState>,
     Json(request): Json<RegistrationRequest>,
-) -> Response {
+) -> Result<Response, ApiError> {
     debug!("Processing registration request");
     let start = std::time::Instant::now();
 
@@ -218,7 +404,7 @@ pub async fn api_register(
         Ok(data) => data,
         Err(validation_error) => {
             // Convert validation error to response
-            return validation_error.into_response();
+            return Ok(validation_error.into_response());
         },
     };
 
@@ -231,98 +417,514 @@ pub async fn api_register(
         password: validated.password.clone(),
     };
 
-    match state.user_service.register(create_user).await {
-        Ok(user) => {
-            // Record successful registration
-            monitoring::record_auth_operation("register", "success");
+    let user = state
+        .user_service
+        .register(create_user)
+        .await
+        .map_err(|err| {
+            // Record failed registration
+            monitoring::record_auth_operation("register", "failure");
 
-            // Record registration duration
-            let duration = start.elapsed();
-            monitoring::record_request_duration(duration.as_secs_f64(), "POST", "/auth/register");
+            warn!(
+                request_id = %request_id,
+                error = %err,
+                user_ref = %auth_log::redact_user(&validated.email),
+                "Registration failed"
+            );
 
-            let response = RegistrationResponse {
-                user_id: user.id.to_string(),
-                email: user.email,
-            };
+            ApiError::from_error(err, request_id.clone())
+        })?;
+
+    // Record successful registration
+    monitoring::record_auth_operation("register", "success");
+
+    // Record registration duration
+    let duration = start.elapsed();
+    monitoring::record_request_duration(duration.as_secs_f64(), "POST", "/auth/register");
+
+    let response = RegistrationResponse {
+        user_id: user.id.to_string(),
+        email: user.email,
+    };
+
+    info!(
+        request_id = %request_id,
+        user_id = %user.id,
+        "User registration successful"
+    );
+
+    let api_response = ApiResponse::success(response, request_id);
+    Ok((StatusCode::CREATED, Json(api_response)).into_response())
+}
+
+/// Password reset request DTO
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResetPasswordRequest {
+    /// Token of a `PASSWORD_EXPIRED` login response
+    #[validate(length(min = 1, message = "Reset token is required"))]
+    pub reset_token: String,
+
+    #[validate(length(min = 8, message = "Password must be at least 8 characters long"))]
+    pub new_password: String,
+}
+
+/// Handler for setting a new password with the token of an expired one
+///
+/// Ends every session of the user; the client logs in with the new password.
+#[axum::debug_handler]
+pub async fn reset_password(
+    State(state): State<ApiAppState>,
+    Json(request): Json<ResetPasswordRequest>,
+) -> Response {
+    debug!("Processing password reset request");
+    let request_id = generate_request_id();
+
+    let validated = match validate_json_payload(Json(request)).await {
+        Ok(data) => data,
+        Err(validation_error) => return validation_error.into_response(),
+    };
+
+    monitoring::record_auth_operation("reset_password", "attempt");
+
+    match state
+        .user_service
+        .reset_password(&validated.reset_token, &validated.new_password)
+        .await
+    {
+        Ok(user) => {
+            monitoring::record_auth_operation("reset_password", "success");
 
             info!(
                 request_id = %request_id,
                 user_id = %user.id,
-                "User registration successful"
+                "Password reset"
             );
 
-            let api_response = ApiResponse::success(response, request_id);
-            (StatusCode::CREATED, Json(api_response)).into_response()
+            let api_response = ApiResponse::success(true, request_id);
+            (StatusCode::OK, Json(api_response)).into_response()
         },
         Err(err) => {
-            // Record failed registration
-            monitoring::record_auth_operation("register", "failure");
-
-            // Registration error
-            let (status, message, code) = match err {
-                UserServiceError::User(UserError::AlreadyExists) => (
-                    StatusCode::CONFLICT,
-                    "User with this email already exists",
-                    "USER_ALREADY_EXISTS",
-                ),
-                _ => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "An error occurred during registration",
-                    "REGISTRATION_ERROR",
-                ),
-            };
+            monitoring::record_auth_operation("reset_password", "failure");
 
             warn!(
                 request_id = %request_id,
                 error = %err,
-                email = %validated.email,
-                "Registration failed"
+                "Password reset failed"
             );
 
-            ApiError::new(status, message, code, request_id).into_response()
+            ApiError::from_error(err, request_id).into_response()
         },
     }
 }
 
 /// Handler for token validation
-#[axum::debug_handler]
-pub async fn validate_token(
-    State(state): State<ApiAppState>,
-    Json(token): Json<String>,
-) -> Response {
+///
+/// The session token is taken from the bearer authorization header; the
+/// [`AuthenticatedUser`] extractor rejects invalid, expired and
+/// enrollment-only sessions before the handler runs.
+#[axum::debug_handler(state = ApiAppState)]
+pub async fn validate_token(user: AuthenticatedUser) -> Response {
     debug!("Processing token validation request");
 
     // Generate a unique request ID
     let request_id = generate_request_id();
 
-    // No validation needed for token as it's just a string
-    // Record validation attempt
-    monitoring::record_auth_operation("validate_token", "attempt");
+    monitoring::record_auth_operation("validate_token", "success");
 
-    match state.session_service.validate_session(&token).await {
-        Ok(Some(session)) => {
-            // Record successful validation
-            monitoring::record_auth_operation("validate_token", "success");
+    info!(
+        request_id = %request_id,
+        user_id = %user.user_id,
+        "Token validation successful"
+    );
 
-            info!(
-                request_id = %request_id,
-                user_id = %session.user_id,
-                "Token validation successful"
+    let api_response = ApiResponse::success(true, request_id);
+    (StatusCode::OK, Json(api_response)).into_response()
+}
+
+/// Re-authentication request DTO
+#[derive(Debug, Deserialize, Validate)]
+pub struct ReauthenticateRequest {
+    #[validate(length(min = 1, message = "Password is required"))]
+    pub password: String,
+}
+
+/// Handler for re-authenticating the current session
+///
+/// Confirms the user's password and refreshes the session's last
+/// authentication time, so operations guarded by `REAUTH_REQUIRED` succeed
+/// again for their configured window.
+#[axum::debug_handler(state = ApiAppState)]
+pub async fn reauthenticate(
+    State(state): State<ApiAppState>,
+    user: AuthenticatedUser,
+    client: ClientInfo,
+    Json(request): Json<ReauthenticateRequest>,
+) -> Response {
+    debug!("Processing re-authentication request");
+    let start = std::time::Instant::now();
+    let request_id = generate_request_id();
+    let user_ref = user.user_id.to_string();
+
+    let validated = match validate_json_payload(Json(request)).await {
+        Ok(data) => data,
+        Err(validation_error) => return validation_error.into_response(),
+    };
+
+    monitoring::record_auth_operation("reauthenticate", "attempt");
+
+    match state
+        .user_service
+        .reauthenticate(user.user_id, user.session_id, &validated.password)
+        .await
+    {
+        Ok(()) => {
+            monitoring::record_auth_operation("reauthenticate", "success");
+
+            auth_event!(
+                "reauthenticate",
+                AuthDecision::Allow,
+                "AUTHENTICATED",
+                request_id = &request_id,
+                tenant_id = user.tenant_id,
+                user = &user_ref,
+                ip = client.ip_address.as_deref(),
+                latency = start.elapsed(),
             );
 
             let api_response = ApiResponse::success(true, request_id);
             (StatusCode::OK, Json(api_response)).into_response()
         },
-        _ => {
-            // Record failed validation
-            monitoring::record_auth_operation("validate_token", "failure");
+        Err(err) => {
+            monitoring::record_auth_operation("reauthenticate", "failure");
+
+            let error = match err {
+                UserServiceError::InvalidCredentials => ApiError::new(
+                    StatusCode::UNAUTHORIZED,
+                    "Invalid password",
+                    "INVALID_CREDENTIALS",
+                    request_id.clone(),
+                ),
+                _ => {
+                    warn!(request_id = %request_id, error = %err, "Re-authentication failed");
+                    ApiError::internal_server_error(request_id.clone())
+                },
+            };
+            auth_event!(
+                "reauthenticate",
+                AuthDecision::Deny,
+                error.code(),
+                request_id = &request_id,
+                tenant_id = user.tenant_id,
+                user = &user_ref,
+                ip = client.ip_address.as_deref(),
+                latency = start.elapsed(),
+            );
+
+            error.into_response()
+        },
+    }
+}
+
+/// Switch tenant request DTO
+#[derive(Debug, Deserialize)]
+pub struct SwitchTenantRequest {
+    pub tenant_id: Uuid,
+}
+
+/// Switch tenant response DTO
+#[derive(Debug, Serialize)]
+pub struct SwitchTenantResponse {
+    /// The new active tenant of the session
+    pub tenant_id: String,
+}
+
+/// Handler for switching the active tenant of the current session
+///
+/// The user must be an active member of the target tenant; the switch is
+/// recorded in the session audit log.
+#[axum::debug_handler(state = ApiAppState)]
+pub async fn switch_tenant(
+    State(state): State<ApiAppState>,
+    user: AuthenticatedUser,
+    client: ClientInfo,
+    Json(request): Json<SwitchTenantRequest>,
+) -> Response {
+    debug!("Processing tenant switch request");
+    let start = std::time::Instant::now();
+    let request_id = generate_request_id();
+    let user_ref = user.user_id.to_string();
+
+    monitoring::record_auth_operation("switch_tenant", "attempt");
+
+    match state
+        .session_service
+        .switch_tenant(user.session_id, request.tenant_id)
+        .await
+    {
+        Ok(()) => {
+            monitoring::record_auth_operation("switch_tenant", "success");
+
+            auth_event!(
+                "switch_tenant",
+                AuthDecision::Allow,
+                "TENANT_SWITCHED",
+                request_id = &request_id,
+                tenant_id = request.tenant_id,
+                user = &user_ref,
+                ip = client.ip_address.as_deref(),
+                latency = start.elapsed(),
+            );
+            if let Some(admin_id) = user.impersonated_by {
+                info!(
+                    request_id = %request_id,
+                    user_id = %user.user_id,
+                    impersonated_by = %admin_id,
+                    "Switched active tenant while impersonating"
+                );
+            }
+
+            let response = SwitchTenantResponse {
+                tenant_id: request.tenant_id.to_string(),
+            };
+            let api_response = ApiResponse::success(response, request_id);
+            (StatusCode::OK, Json(api_response)).into_response()
+        },
+        Err(err) => {
+            monitoring::record_auth_operation("switch_tenant", "failure");
+
+            let error = match err {
+                SessionServiceError::NotTenantMember => ApiError::new(
+                    StatusCode::FORBIDDEN,
+                    "User is not a member of the tenant",
+                    "TENANT_ACCESS_DENIED",
+                    request_id.clone(),
+                ),
+                SessionServiceError::TenantSwitchUnavailable => ApiError::new(
+                    StatusCode::NOT_IMPLEMENTED,
+                    "Tenant switching is not available",
+                    "TENANT_SWITCH_UNAVAILABLE",
+                    request_id.clone(),
+                ),
+                SessionServiceError::Repository(SessionError::NotFound) => {
+                    ApiError::authentication_error(request_id.clone())
+                },
+                _ => {
+                    warn!(request_id = %request_id, error = %err, "Failed to switch tenant");
+                    ApiError::internal_server_error(request_id.clone())
+                },
+            };
+            auth_event!(
+                "switch_tenant",
+                AuthDecision::Deny,
+                error.code(),
+                request_id = &request_id,
+                tenant_id = request.tenant_id,
+                user = &user_ref,
+                ip = client.ip_address.as_deref(),
+                latency = start.elapsed(),
+            );
+
+            error.into_response()
+        },
+    }
+}
+
+/// MFA status response DTO
+#[derive(Debug, Serialize)]
+pub struct MfaStatusResponse {
+    pub user_id: String,
+    pub tenant_id: Option<String>,
+    /// Whether the user has enrolled at least one second factor
+    pub enrolled: bool,
+    pub totp_enrolled: bool,
+    pub webauthn_credentials: usize,
+    /// MFA enforcement level of the tenant
+    pub enforcement: MfaEnforcement,
+    /// Whether the tenant policy requires this user to enroll
+    pub enrollment_required: bool,
+    /// Deadline after which unenrolled users can no longer log in (RFC 3339)
+    pub grace_deadline: Option<String>,
+    /// MFA status of the current session
+    pub session_mfa_status: String,
+}
+
+/// Handler for the MFA enrollment status of the current user
+///
+/// Sessions pending MFA enrollment are accepted so clients can drive the enrollment flow.
+#[axum::debug_handler]
+pub async fn get_mfa_status(
+    State(state): State<ApiAppState>,
+    current_user: Result<CurrentUser, CurrentUserRejection>,
+) -> Response {
+    debug!("Processing MFA status request");
+    let start = std::time::Instant::now();
+
+    // Generate a unique request ID
+    let request_id = generate_request_id();
+
+    monitoring::record_auth_operation("mfa_status", "attempt");
+
+    let current_user = match current_user {
+        Ok(current_user) => current_user,
+        Err(CurrentUserRejection::Service(err)) => {
+            monitoring::record_auth_operation("mfa_status", "failure");
+            warn!(request_id = %request_id, error = %err, "Failed to validate session");
+            return ApiError::internal_server_error(request_id).into_response();
+        },
+        Err(_) => {
+            monitoring::record_auth_operation("mfa_status", "failure");
+            return ApiError::authentication_error(request_id).into_response();
+        },
+    };
+    let tenant_id = current_user.tenant_id;
+
+    let result = async {
+        let policy = match tenant_id {
+            Some(tenant_id) => {
+                state
+                    .user_service
+                    .get_tenant_security_policy(tenant_id)
+                    .await?
+            },
+            None => Default::default(),
+        };
+        let enrollment = state
+            .user_service
+            .get_mfa_enrollment(current_user.user_id, tenant_id.unwrap_or_else(Uuid::nil))
+            .await?;
+        let enrollment_required = match tenant_id {
+            Some(tenant_id) => {
+                state
+                    .user_service
+                    .is_mfa_required(current_user.user_id, tenant_id, &policy)
+                    .await?
+            },
+            None => false,
+        };
+        Ok::<_, UserServiceError>((policy, enrollment, enrollment_required))
+    }
+    .await;
+
+    match result {
+        Ok((policy, enrollment, enrollment_required)) => {
+            monitoring::record_auth_operation("mfa_status", "success");
+
+            let duration = start.elapsed();
+            monitoring::record_request_duration(duration.as_secs_f64(), "GET", "/auth/mfa/status");
+
+            let response = MfaStatusResponse {
+                user_id: current_user.user_id.to_string(),
+                tenant_id: tenant_id.map(|id| id.to_string()),
+                enrolled: enrollment.is_enrolled(),
+                totp_enrolled: enrollment.totp_enrolled,
+                webauthn_credentials: enrollment.webauthn_credentials,
+                enforcement: policy.mfa_enforcement,
+                enrollment_required,
+                grace_deadline: policy.mfa_grace_deadline.and_then(|deadline| {
+                    deadline
+                        .format(&time::format_description::well_known::Rfc3339)
+                        .ok()
+                }),
+                session_mfa_status: current_user.mfa_status.to_string(),
+            };
+
+            let api_response = ApiResponse::success(response, request_id);
+            (StatusCode::OK, Json(api_response)).into_response()
+        },
+        Err(err) => {
+            monitoring::record_auth_operation("mfa_status", "failure");
 
             warn!(
                 request_id = %request_id,
-                "Token validation failed"
+                error = %err,
+                user_id = %current_user.user_id,
+                "Failed to determine MFA status"
             );
 
-            ApiError::authentication_error(request_id).into_response()
+            match err {
+                UserServiceError::Tenant(TenantError::NotFound) => {
+                    ApiError::not_found_error("tenant", request_id).into_response()
+                },
+                _ => ApiError::internal_server_error(request_id).into_response(),
+            }
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acci_auth::{TenantUser, User};
+    use time::OffsetDateTime;
+
+    fn login_result(active_tenant_id: Option<Uuid>, tenants: &[Uuid]) -> LoginResult {
+        let user = User::new("member@example.com".to_string(), "hash".to_string());
+        let now = OffsetDateTime::now_utc();
+        let memberships = tenants
+            .iter()
+            .map(|tenant_id| TenantUser {
+                tenant_id: *tenant_id,
+                user_id: user.id,
+                tenant_role: "MEMBER".to_string(),
+                is_active: true,
+                created_at: now,
+                updated_at: now,
+            })
+            .collect();
+
+        LoginResult {
+            user,
+            session_token: "session-token".to_string(),
+            mfa_status: MfaStatus::None,
+            active_tenant_id,
+            memberships,
+            pending_deletion: None,
+            password_expires_in_days: None,
+        }
+    }
+
+    #[test]
+    fn test_login_response_lists_tenants_when_selection_required() {
+        let tenants = [Uuid::new_v4(), Uuid::new_v4()];
+        let response = LoginResponse::from(login_result(None, &tenants));
+
+        let body = serde_json::to_value(&response).expect("Response serializes");
+        assert_eq!(body["token"], "session-token");
+        assert!(body["tenant_id"].is_null());
+        assert_eq!(body["tenant_selection_required"], true);
+
+        let listed = body["tenants"].as_array().expect("Tenants are listed");
+        assert_eq!(listed.len(), 2);
+        for (entry, tenant_id) in listed.iter().zip(tenants) {
+            assert_eq!(entry["tenant_id"], tenant_id.to_string());
+            assert_eq!(entry["role"], "MEMBER");
+        }
+    }
+
+    #[test]
+    fn test_login_response_omits_tenants_once_bound() {
+        let tenants = [Uuid::new_v4(), Uuid::new_v4()];
+        let response = LoginResponse::from(login_result(Some(tenants[1]), &tenants));
+
+        let body = serde_json::to_value(&response).expect("Response serializes");
+        assert_eq!(body["tenant_id"], tenants[1].to_string());
+        assert_eq!(body["tenant_selection_required"], false);
+        assert!(body.get("tenants").is_none());
+    }
+
+    #[test]
+    fn test_login_response_warns_about_pending_deletion() {
+        let body = serde_json::to_value(LoginResponse::from(login_result(None, &[])))
+            .expect("Response serializes");
+        assert!(body.get("warning").is_none());
+
+        let scheduled_for = OffsetDateTime::from_unix_timestamp(1_767_225_600).expect("timestamp");
+        let response = LoginResponse::from(LoginResult {
+            pending_deletion: Some(scheduled_for),
+            ..login_result(None, &[])
+        });
+        let body = serde_json::to_value(&response).expect("Response serializes");
+        assert_eq!(body["warning"], "ACCOUNT_PENDING_DELETION");
+        assert_eq!(body["deletion_scheduled_for"], "2026-01-01T00:00:00Z");
+    }
+}
@@ -0,0 +1,189 @@
+//! Delivery receipt callbacks of the message providers
+//!
+//! Twilio, Vonage and SendGrid report the delivery state of each message
+//! they were handed. The callbacks are verified with the provider's
+//! signature and applied to the tracked deliveries; receipts for messages
+//! that are not tracked are acknowledged and ignored, so the provider does
+//! not retry them.
+
+use crate::monitoring;
+use crate::response::ApiError;
+use crate::validation::generate_request_id;
+use axum::{
+    Form,
+    body::Bytes,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use tracing::{debug, error, warn};
+
+use acci_auth::{
+    DeliveryReceiptVerifier, DeliveryTracker, ReceiptOutcome,
+    models::DeliveryReceipt,
+    parse_sendgrid_events, parse_twilio_callback, parse_vonage_receipt,
+    security::WebhookError,
+    services::delivery_receipt::{
+        SENDGRID_SIGNATURE_HEADER, SENDGRID_TIMESTAMP_HEADER, TWILIO_SIGNATURE_HEADER,
+    },
+};
+
+/// State of the delivery receipt routes
+#[derive(Clone)]
+pub struct DeliveryReceiptAppState {
+    /// Tracker holding the deliveries the receipts are applied to
+    pub tracker: DeliveryTracker,
+    /// Verifier of the provider signatures
+    pub verifier: Arc<DeliveryReceiptVerifier>,
+}
+
+/// Handler for Twilio SMS status callbacks
+#[axum::debug_handler]
+pub async fn twilio_delivery_receipt(
+    State(state): State<DeliveryReceiptAppState>,
+    headers: HeaderMap,
+    Form(params): Form<Vec<(String, String)>>,
+) -> Response {
+    debug!("Processing Twilio status callback");
+    let request_id = generate_request_id();
+
+    let signature = header(&headers, TWILIO_SIGNATURE_HEADER);
+    if let Err(err) = state.verifier.verify_twilio(signature, &params) {
+        return webhook_error("twilio", err, request_id);
+    }
+
+    match parse_twilio_callback(&params) {
+        Ok(receipt) => {
+            apply_receipts(&state, "twilio", receipt.into_iter().collect(), request_id).await
+        },
+        Err(err) => invalid_payload("twilio", err, request_id),
+    }
+}
+
+/// Handler for Vonage SMS delivery receipts sent as query parameters
+#[axum::debug_handler]
+pub async fn vonage_delivery_receipt(
+    State(state): State<DeliveryReceiptAppState>,
+    Query(params): Query<Vec<(String, String)>>,
+) -> Response {
+    handle_vonage_receipt(state, params).await
+}
+
+/// Handler for Vonage SMS delivery receipts sent as a form
+#[axum::debug_handler]
+pub async fn vonage_delivery_receipt_form(
+    State(state): State<DeliveryReceiptAppState>,
+    Form(params): Form<Vec<(String, String)>>,
+) -> Response {
+    handle_vonage_receipt(state, params).await
+}
+
+async fn handle_vonage_receipt(
+    state: DeliveryReceiptAppState,
+    params: Vec<(String, String)>,
+) -> Response {
+    debug!("Processing Vonage delivery receipt");
+    let request_id = generate_request_id();
+
+    if let Err(err) = state.verifier.verify_vonage(&params) {
+        return webhook_error("vonage", err, request_id);
+    }
+
+    match parse_vonage_receipt(&params) {
+        Ok(receipt) => {
+            apply_receipts(&state, "vonage", receipt.into_iter().collect(), request_id).await
+        },
+        Err(err) => invalid_payload("vonage", err, request_id),
+    }
+}
+
+/// Handler for the SendGrid signed event webhook
+#[axum::debug_handler]
+pub async fn sendgrid_delivery_receipt(
+    State(state): State<DeliveryReceiptAppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    debug!("Processing SendGrid event webhook");
+    let request_id = generate_request_id();
+
+    if let Err(err) = state.verifier.verify_sendgrid(
+        header(&headers, SENDGRID_SIGNATURE_HEADER),
+        header(&headers, SENDGRID_TIMESTAMP_HEADER),
+        &body,
+    ) {
+        return webhook_error("sendgrid", err, request_id);
+    }
+
+    match parse_sendgrid_events(&body) {
+        Ok(receipts) => apply_receipts(&state, "sendgrid", receipts, request_id).await,
+        Err(err) => invalid_payload("sendgrid", err, request_id),
+    }
+}
+
+fn header<'h>(headers: &'h HeaderMap, name: &str) -> Option<&'h str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// Apply verified receipts, answering with a server error so the provider retries on failure
+async fn apply_receipts(
+    state: &DeliveryReceiptAppState,
+    provider: &str,
+    receipts: Vec<DeliveryReceipt>,
+    request_id: String,
+) -> Response {
+    for receipt in &receipts {
+        match state.tracker.apply_receipt(receipt).await {
+            Ok(outcome) => {
+                let result = match outcome {
+                    ReceiptOutcome::Updated => "updated",
+                    ReceiptOutcome::Resent(_) => "resent",
+                    ReceiptOutcome::Stale => "stale",
+                    ReceiptOutcome::Unknown => "unknown",
+                };
+                monitoring::record_auth_operation("delivery_receipt", result);
+            },
+            Err(err) => {
+                error!(
+                    request_id = %request_id,
+                    provider = provider,
+                    provider_message_id = %receipt.provider_message_id,
+                    error = %err,
+                    "Failed to apply delivery receipt"
+                );
+                monitoring::record_api_error("database", "DELIVERY_RECEIPT_ERROR", 500);
+                return ApiError::internal_server_error(request_id).into_response();
+            },
+        }
+    }
+
+    StatusCode::NO_CONTENT.into_response()
+}
+
+fn webhook_error(provider: &str, err: WebhookError, request_id: String) -> Response {
+    warn!(
+        request_id = %request_id,
+        provider = provider,
+        error = %err,
+        "Rejected delivery receipt"
+    );
+    monitoring::record_auth_operation("delivery_receipt", "rejected");
+    ApiError::new(
+        err.status(),
+        "Delivery receipt could not be verified",
+        "INVALID_DELIVERY_RECEIPT",
+        request_id,
+    )
+    .into_response()
+}
+
+fn invalid_payload(provider: &str, err: acci_core::error::Error, request_id: String) -> Response {
+    warn!(
+        request_id = %request_id,
+        provider = provider,
+        error = %err,
+        "Malformed delivery receipt"
+    );
+    ApiError::validation_error(err.to_string(), request_id).into_response()
+}
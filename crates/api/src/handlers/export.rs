@@ -0,0 +1,643 @@
+//! Streaming exports of session and login data
+//!
+//! Tenant admins download the sessions or the login history of their tenant
+//! as CSV (for spreadsheets) or NDJSON (for SIEMs). Sessions are read from the
+//! repository page by page and written to the response body as they arrive,
+//! so memory use does not depend on the size of the export. When the client
+//! disconnects, the body stream is dropped and no further pages are read.
+//!
+//! Login history is derived from the sessions: every session is one
+//! successful login.
+
+use crate::handlers::tenant::TenantAppState;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::monitoring;
+use crate::response::ApiError;
+use crate::validation::generate_request_id;
+use acci_core::datetime;
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use bytes::Bytes;
+use chrono::{SecondsFormat, Utc};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use acci_auth::{Session, SessionExportFilter, SessionFilter, services::session::SessionService};
+
+/// Failure that aborts an export mid-stream
+type ExportError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Most rows a single export returns
+pub const MAX_EXPORT_ROWS: u64 = 100_000;
+
+/// Output format of an export
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    #[default]
+    Ndjson,
+}
+
+impl ExportFormat {
+    fn content_type(&self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// Which sessions an export includes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportStatusFilter {
+    #[default]
+    All,
+    Active,
+    Inactive,
+}
+
+impl From<ExportStatusFilter> for SessionFilter {
+    fn from(filter: ExportStatusFilter) -> Self {
+        match filter {
+            ExportStatusFilter::All => SessionFilter::All,
+            ExportStatusFilter::Active => SessionFilter::Active,
+            ExportStatusFilter::Inactive => SessionFilter::Inactive,
+        }
+    }
+}
+
+/// Query parameters of the export endpoints
+#[derive(Debug, Default, Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub filter: ExportStatusFilter,
+    /// Only include sessions of this user
+    pub user_id: Option<Uuid>,
+    /// Only include sessions created at or after this time
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub since: Option<OffsetDateTime>,
+    /// Maximum number of rows, capped at [`MAX_EXPORT_ROWS`]
+    pub limit: Option<u64>,
+}
+
+/// A row of an export
+trait ExportRecord: Serialize {
+    /// Name of the export, used in file names and metrics
+    const KIND: &'static str;
+    const CSV_HEADER: &'static [&'static str];
+
+    fn from_session(session: &Session) -> Self;
+
+    /// Values in the order of [`CSV_HEADER`](Self::CSV_HEADER)
+    fn csv_values(&self) -> Vec<String>;
+}
+
+/// Exported session
+#[derive(Debug, Serialize)]
+struct SessionRecord {
+    session_id: Uuid,
+    user_id: Uuid,
+    created_at: String,
+    last_activity_at: String,
+    expires_at: String,
+    is_valid: bool,
+    invalidated_reason: Option<String>,
+    mfa_status: String,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+    device_id: Option<String>,
+    region: Option<String>,
+    impersonated_by: Option<Uuid>,
+}
+
+impl ExportRecord for SessionRecord {
+    const KIND: &'static str = "sessions";
+    const CSV_HEADER: &'static [&'static str] = &[
+        "session_id",
+        "user_id",
+        "created_at",
+        "last_activity_at",
+        "expires_at",
+        "is_valid",
+        "invalidated_reason",
+        "mfa_status",
+        "ip_address",
+        "user_agent",
+        "device_id",
+        "region",
+        "impersonated_by",
+    ];
+
+    fn from_session(session: &Session) -> Self {
+        Self {
+            session_id: session.id,
+            user_id: session.user_id,
+            created_at: timestamp(session.created_at),
+            last_activity_at: timestamp(session.last_activity_at),
+            expires_at: timestamp(session.expires_at),
+            is_valid: session.is_valid,
+            invalidated_reason: session.invalidated_reason.as_ref().and_then(|reason| {
+                serde_json::to_value(reason)
+                    .ok()
+                    .and_then(|value| value.as_str().map(str::to_string))
+            }),
+            mfa_status: session.mfa_status.to_string(),
+            ip_address: session.ip_address.clone(),
+            user_agent: session.user_agent.clone(),
+            device_id: session.device_id.clone(),
+            region: session.region.clone(),
+            impersonated_by: session.impersonated_by(),
+        }
+    }
+
+    fn csv_values(&self) -> Vec<String> {
+        vec![
+            self.session_id.to_string(),
+            self.user_id.to_string(),
+            self.created_at.clone(),
+            self.last_activity_at.clone(),
+            self.expires_at.clone(),
+            self.is_valid.to_string(),
+            self.invalidated_reason.clone().unwrap_or_default(),
+            self.mfa_status.clone(),
+            self.ip_address.clone().unwrap_or_default(),
+            self.user_agent.clone().unwrap_or_default(),
+            self.device_id.clone().unwrap_or_default(),
+            self.region.clone().unwrap_or_default(),
+            optional_id(self.impersonated_by),
+        ]
+    }
+}
+
+/// Exported login
+#[derive(Debug, Serialize)]
+struct LoginRecord {
+    logged_in_at: String,
+    user_id: Uuid,
+    session_id: Uuid,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+    device_id: Option<String>,
+    mfa_status: String,
+    persistent: bool,
+    impersonated_by: Option<Uuid>,
+}
+
+impl ExportRecord for LoginRecord {
+    const KIND: &'static str = "logins";
+    const CSV_HEADER: &'static [&'static str] = &[
+        "logged_in_at",
+        "user_id",
+        "session_id",
+        "ip_address",
+        "user_agent",
+        "device_id",
+        "mfa_status",
+        "persistent",
+        "impersonated_by",
+    ];
+
+    fn from_session(session: &Session) -> Self {
+        Self {
+            logged_in_at: timestamp(session.created_at),
+            user_id: session.user_id,
+            session_id: session.id,
+            ip_address: session.ip_address.clone(),
+            user_agent: session.user_agent.clone(),
+            device_id: session.device_id.clone(),
+            mfa_status: session.mfa_status.to_string(),
+            persistent: session.is_persistent(),
+            impersonated_by: session.impersonated_by(),
+        }
+    }
+
+    fn csv_values(&self) -> Vec<String> {
+        vec![
+            self.logged_in_at.clone(),
+            self.user_id.to_string(),
+            self.session_id.to_string(),
+            self.ip_address.clone().unwrap_or_default(),
+            self.user_agent.clone().unwrap_or_default(),
+            self.device_id.clone().unwrap_or_default(),
+            self.mfa_status.clone(),
+            self.persistent.to_string(),
+            optional_id(self.impersonated_by),
+        ]
+    }
+}
+
+fn timestamp(at: OffsetDateTime) -> String {
+    datetime::to_chrono(at)
+        .map(|at| at.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_default()
+}
+
+fn optional_id(id: Option<Uuid>) -> String {
+    id.map(|id| id.to_string()).unwrap_or_default()
+}
+
+/// Quote a CSV field if needed
+///
+/// Fields containing separators, quotes or line breaks are quoted with inner
+/// quotes doubled. Fields a spreadsheet would evaluate as a formula are
+/// prefixed with `'`, since user agents are chosen by the client.
+fn csv_field(value: &str) -> Cow<'_, str> {
+    let formula = value.starts_with(['=', '+', '-', '@', '\t', '\r']);
+    if !formula && !value.contains([',', '"', '\n', '\r']) {
+        return Cow::Borrowed(value);
+    }
+
+    let guard = if formula { "'" } else { "" };
+    Cow::Owned(format!("\"{}{}\"", guard, value.replace('"', "\"\"")))
+}
+
+fn csv_line<S: AsRef<str>>(values: &[S]) -> String {
+    let mut line = values
+        .iter()
+        .map(|value| csv_field(value.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
+
+fn encode<R: ExportRecord>(format: ExportFormat, record: &R) -> Result<Bytes, serde_json::Error> {
+    match format {
+        ExportFormat::Csv => Ok(Bytes::from(csv_line(&record.csv_values()))),
+        ExportFormat::Ndjson => {
+            let mut line = serde_json::to_vec(record)?;
+            line.push(b'\n');
+            Ok(Bytes::from(line))
+        },
+    }
+}
+
+/// Handler for exporting the sessions of a tenant
+#[axum::debug_handler(state = TenantAppState)]
+pub async fn export_sessions(
+    State(session_service): State<Arc<SessionService>>,
+    user: AuthenticatedUser,
+    Path(tenant_id): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    stream_export::<SessionRecord>(&session_service, &user, &tenant_id, query)
+}
+
+/// Handler for exporting the login history of a tenant
+#[axum::debug_handler(state = TenantAppState)]
+pub async fn export_login_history(
+    State(session_service): State<Arc<SessionService>>,
+    user: AuthenticatedUser,
+    Path(tenant_id): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    stream_export::<LoginRecord>(&session_service, &user, &tenant_id, query)
+}
+
+fn stream_export<R: ExportRecord + 'static>(
+    session_service: &SessionService,
+    user: &AuthenticatedUser,
+    tenant_id: &str,
+    query: ExportQuery,
+) -> Response {
+    debug!(kind = R::KIND, "Processing export request");
+    let request_id = generate_request_id();
+
+    let tenant_id = match Uuid::parse_str(tenant_id) {
+        Ok(id) => id,
+        Err(_) => {
+            return ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "Invalid tenant ID format",
+                "INVALID_TENANT_ID",
+                request_id,
+            )
+            .into_response();
+        },
+    };
+
+    if !user.has_tenant_role(tenant_id, "ADMIN") {
+        monitoring::record_tenant_operation("export", "failure");
+        warn!(
+            request_id = %request_id,
+            user_id = %user.user_id,
+            tenant_id = %tenant_id,
+            "User is not an administrator of the tenant"
+        );
+        return ApiError::authorization_error(request_id).into_response();
+    }
+
+    let format = query.format;
+    let limit = query.limit.unwrap_or(MAX_EXPORT_ROWS).min(MAX_EXPORT_ROWS);
+    let filter = SessionExportFilter {
+        status: query.filter.into(),
+        user_id: query.user_id,
+        created_after: query.since,
+        limit,
+        ..SessionExportFilter::new(tenant_id)
+    };
+
+    monitoring::record_tenant_operation("export", "success");
+    info!(
+        request_id = %request_id,
+        kind = R::KIND,
+        tenant_id = %tenant_id,
+        user_id = %user.user_id,
+        impersonated_by = ?user.impersonated_by,
+        format = ?format,
+        limit,
+        "Streaming export"
+    );
+
+    let header = match format {
+        ExportFormat::Csv => Some(Ok(Bytes::from(csv_line(R::CSV_HEADER)))),
+        ExportFormat::Ndjson => None,
+    };
+    let rows = session_service
+        .export_sessions(filter)
+        .map(move |session| -> Result<Bytes, ExportError> {
+            let record = R::from_session(&session?);
+            Ok(encode(format, &record)?)
+        })
+        .inspect(move |row| {
+            if let Err(err) = row {
+                error!(request_id = %request_id, error = %err, "Export aborted");
+            }
+        });
+    let body = Body::from_stream(stream::iter(header).chain(rows));
+
+    let filename = format!(
+        "{}-{}-{}.{}",
+        R::KIND,
+        tenant_id,
+        Utc::now().format("%Y%m%dT%H%M%SZ"),
+        format.extension()
+    );
+    let mut response = (StatusCode::OK, body).into_response();
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(format.content_type()),
+    );
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        HeaderValue::from_str(&format!("attachment; filename=\"{}\"", filename))
+            .expect("Export file name is a valid header value"),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acci_auth::{
+        ACTIVE_TENANT_METADATA_KEY, AuthConfig, TenantUser,
+        session::{
+            mock::MockSessionRepository,
+            types::{MfaStatus, SessionKind},
+        },
+    };
+    use serde_json::Value;
+    use std::time::Duration;
+
+    struct Fixture {
+        sessions: Arc<MockSessionRepository>,
+        service: Arc<SessionService>,
+        tenant_id: Uuid,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let sessions = Arc::new(MockSessionRepository::new());
+            let service = Arc::new(SessionService::new(
+                sessions.clone(),
+                Arc::new(AuthConfig::default()),
+            ));
+            Self {
+                sessions,
+                service,
+                tenant_id: Uuid::new_v4(),
+            }
+        }
+
+        /// Store `count` sessions of one user in `tenant_id`, one second apart
+        fn seed(&self, tenant_id: Uuid, count: usize, user_agent: &str) -> Uuid {
+            let user_id = Uuid::new_v4();
+            let base = OffsetDateTime::now_utc() - Duration::from_secs(count as u64 + 60);
+            for i in 0..count {
+                let created_at = base + Duration::from_secs(i as u64);
+                self.sessions.insert(Session {
+                    id: Uuid::new_v4(),
+                    user_id,
+                    kind: SessionKind::User,
+                    token_hash: format!("hash-{}", i),
+                    previous_token_hash: None,
+                    token_rotation_at: None,
+                    expires_at: created_at + Duration::from_secs(3600),
+                    created_at,
+                    last_activity_at: created_at,
+                    last_activity_update_at: None,
+                    ip_address: Some("192.0.2.10".to_string()),
+                    user_agent: Some(user_agent.to_string()),
+                    user_agent_details: None,
+                    region: None,
+                    device_id: None,
+                    device_fingerprint: None,
+                    is_valid: i % 2 == 0,
+                    invalidated_reason: None,
+                    metadata: Some(Value::Object(serde_json::Map::from_iter([(
+                        ACTIVE_TENANT_METADATA_KEY.to_string(),
+                        Value::String(tenant_id.to_string()),
+                    )]))),
+                    mfa_status: MfaStatus::None,
+                });
+            }
+            user_id
+        }
+
+        fn user(&self, role: &str) -> AuthenticatedUser {
+            let user_id = Uuid::new_v4();
+            AuthenticatedUser {
+                user_id,
+                session_id: Uuid::new_v4(),
+                tenant_id: Some(self.tenant_id),
+                mfa_status: MfaStatus::Verified,
+                memberships: vec![TenantUser {
+                    tenant_id: self.tenant_id,
+                    user_id,
+                    tenant_role: role.to_string(),
+                    is_active: true,
+                    created_at: OffsetDateTime::now_utc(),
+                    updated_at: OffsetDateTime::now_utc(),
+                }],
+                claims: None,
+                custom_claims: Default::default(),
+                last_auth_at: OffsetDateTime::now_utc(),
+                expires_at: OffsetDateTime::now_utc() + Duration::from_secs(3600),
+                impersonated_by: None,
+            }
+        }
+
+        async fn export(&self, query: ExportQuery) -> Response {
+            export_sessions(
+                State(self.service.clone()),
+                self.user("ADMIN"),
+                Path(self.tenant_id.to_string()),
+                Query(query),
+            )
+            .await
+        }
+    }
+
+    async fn body_text(response: Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read body");
+        String::from_utf8(bytes.to_vec()).expect("Export is UTF-8")
+    }
+
+    #[test]
+    fn test_csv_fields_are_quoted_and_escaped() {
+        assert_eq!(csv_field("Mozilla/5.0"), "Mozilla/5.0");
+        assert_eq!(
+            csv_field(r#"Mozilla/5.0 (X11, "Linux")"#),
+            r#""Mozilla/5.0 (X11, ""Linux"")""#
+        );
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("=HYPERLINK(\"x\")"), "\"'=HYPERLINK(\"\"x\"\")\"");
+        assert_eq!(csv_line(&["a", "b,c", ""]), "a,\"b,c\",\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_export_contains_every_session_of_the_tenant() {
+        let fixture = Fixture::new();
+        fixture.seed(fixture.tenant_id, 25, "Mozilla/5.0");
+        fixture.seed(Uuid::new_v4(), 5, "Mozilla/5.0");
+
+        let response = fixture.export(ExportQuery::default()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "application/x-ndjson"
+        );
+        let disposition = response.headers()[header::CONTENT_DISPOSITION]
+            .to_str()
+            .expect("Valid header");
+        assert!(disposition.starts_with(&format!(
+            "attachment; filename=\"sessions-{}-",
+            fixture.tenant_id
+        )));
+        assert!(disposition.ends_with(".ndjson\""));
+
+        let body = body_text(response).await;
+        let lines: Vec<Value> = body
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("Every line is a JSON object"))
+            .collect();
+        assert_eq!(lines.len(), 25);
+        assert!(
+            lines
+                .windows(2)
+                .all(|pair| pair[0]["created_at"].as_str() <= pair[1]["created_at"].as_str())
+        );
+
+        let active = fixture
+            .export(ExportQuery {
+                filter: ExportStatusFilter::Active,
+                limit: Some(10),
+                ..ExportQuery::default()
+            })
+            .await;
+        let body = body_text(active).await;
+        assert_eq!(body.lines().count(), 10);
+        assert!(body.lines().all(|line| line.contains("\"is_valid\":true")));
+    }
+
+    #[tokio::test]
+    async fn test_csv_login_history_escapes_user_agents() {
+        let fixture = Fixture::new();
+        let user_agent = r#"Mozilla/5.0 (X11, "Linux")"#;
+        fixture.seed(fixture.tenant_id, 3, user_agent);
+
+        let response = export_login_history(
+            State(fixture.service.clone()),
+            fixture.user("ADMIN"),
+            Path(fixture.tenant_id.to_string()),
+            Query(ExportQuery {
+                format: ExportFormat::Csv,
+                ..ExportQuery::default()
+            }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/csv; charset=utf-8"
+        );
+
+        let body = body_text(response).await;
+        let lines: Vec<&str> = body.split_terminator("\r\n").collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], LoginRecord::CSV_HEADER.join(","));
+        assert!(
+            lines[1..]
+                .iter()
+                .all(|line| line.contains(r#","Mozilla/5.0 (X11, ""Linux"")","#))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_requires_tenant_admin() {
+        let fixture = Fixture::new();
+        fixture.seed(fixture.tenant_id, 3, "Mozilla/5.0");
+
+        let response = export_sessions(
+            State(fixture.service.clone()),
+            fixture.user("MEMBER"),
+            Path(fixture.tenant_id.to_string()),
+            Query(ExportQuery::default()),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(fixture.sessions.streamed_sessions(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_client_disconnect_stops_reading_sessions() {
+        let fixture = Fixture::new();
+        fixture.seed(fixture.tenant_id, 1_200, "Mozilla/5.0");
+
+        let response = fixture.export(ExportQuery::default()).await;
+        let mut body = response.into_body().into_data_stream();
+        body.next()
+            .await
+            .expect("Export has rows")
+            .expect("First row is readable");
+
+        // The client goes away after the first row
+        drop(body);
+        let page_size = SessionExportFilter::new(fixture.tenant_id).page_size as usize;
+        assert_eq!(fixture.sessions.streamed_sessions(), page_size);
+    }
+}
@@ -0,0 +1,205 @@
+//! Authentication factors overview
+//!
+//! Lists the password, TOTP, recovery codes and WebAuthn credentials of the
+//! signed-in user and removes them. Both operations require a recent
+//! authentication, as they change how the account can be signed in to.
+
+use crate::handlers::auth::ApiAppState;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::monitoring;
+use crate::response::{ApiError, ApiResponse};
+use crate::validation::generate_request_id;
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use acci_auth::{AuthFactor, EnrolledFactor, SensitiveOperation, services::user::UserServiceError};
+
+/// Factor in the overview
+#[derive(Debug, Serialize)]
+pub struct FactorResponse {
+    /// Identifier accepted by the removal endpoint
+    pub id: String,
+    #[serde(flatten)]
+    pub factor: EnrolledFactor,
+}
+
+impl From<EnrolledFactor> for FactorResponse {
+    fn from(factor: EnrolledFactor) -> Self {
+        Self {
+            id: factor.factor.id(),
+            factor,
+        }
+    }
+}
+
+/// Handler listing the authentication factors of the current user
+#[axum::debug_handler(state = ApiAppState)]
+pub async fn list_factors(State(state): State<ApiAppState>, user: AuthenticatedUser) -> Response {
+    debug!("Processing factors overview request");
+    let request_id = generate_request_id();
+    monitoring::record_auth_operation("list_factors", "attempt");
+
+    if let Err(err) =
+        user.require_recent_auth(&state.session_service, SensitiveOperation::ManageFactors)
+    {
+        monitoring::record_auth_operation("list_factors", "failure");
+        return err.into_response();
+    }
+
+    match state
+        .user_service
+        .list_factors(user.user_id, user.tenant_id.unwrap_or_else(Uuid::nil))
+        .await
+    {
+        Ok(factors) => {
+            monitoring::record_auth_operation("list_factors", "success");
+            let factors: Vec<FactorResponse> = factors.into_iter().map(Into::into).collect();
+            let api_response = ApiResponse::success(factors, request_id);
+            (StatusCode::OK, Json(api_response)).into_response()
+        },
+        Err(err) => {
+            monitoring::record_auth_operation("list_factors", "failure");
+            warn!(
+                request_id = %request_id,
+                error = %err,
+                user_id = %user.user_id,
+                "Failed to list authentication factors"
+            );
+            factor_error(err, request_id)
+        },
+    }
+}
+
+/// Handler removing an authentication factor of the current user
+#[axum::debug_handler(state = ApiAppState)]
+pub async fn remove_factor(
+    State(state): State<ApiAppState>,
+    user: AuthenticatedUser,
+    Path(factor_id): Path<String>,
+) -> Response {
+    debug!("Processing factor removal request");
+    let request_id = generate_request_id();
+    monitoring::record_auth_operation("remove_factor", "attempt");
+
+    let Some(factor) = AuthFactor::from_id(&factor_id) else {
+        monitoring::record_auth_operation("remove_factor", "failure");
+        return ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "Unknown authentication factor",
+            "INVALID_FACTOR",
+            request_id,
+        )
+        .into_response();
+    };
+
+    if let Err(err) =
+        user.require_recent_auth(&state.session_service, SensitiveOperation::ManageFactors)
+    {
+        monitoring::record_auth_operation("remove_factor", "failure");
+        return err.into_response();
+    }
+
+    match state
+        .user_service
+        .remove_factor(
+            user.user_id,
+            user.tenant_id.unwrap_or_else(Uuid::nil),
+            user.session_id,
+            &factor,
+        )
+        .await
+    {
+        Ok(()) => {
+            monitoring::record_auth_operation("remove_factor", "success");
+            info!(
+                request_id = %request_id,
+                user_id = %user.user_id,
+                factor = %factor,
+                impersonated_by = ?user.impersonated_by,
+                "Authentication factor removed"
+            );
+            let api_response = ApiResponse::success(true, request_id);
+            (StatusCode::OK, Json(api_response)).into_response()
+        },
+        Err(err) => {
+            monitoring::record_auth_operation("remove_factor", "failure");
+            warn!(
+                request_id = %request_id,
+                error = %err,
+                user_id = %user.user_id,
+                factor = %factor,
+                "Failed to remove authentication factor"
+            );
+            factor_error(err, request_id)
+        },
+    }
+}
+
+/// Map a user service error of the factor endpoints to an API error response
+fn factor_error(err: UserServiceError, request_id: String) -> Response {
+    let (status, message, code) = match err {
+        UserServiceError::FactorNotFound => (
+            StatusCode::NOT_FOUND,
+            "Authentication factor not found",
+            "FACTOR_NOT_FOUND",
+        ),
+        UserServiceError::LastSignInMethod => (
+            StatusCode::CONFLICT,
+            "Link an external identity before removing the password",
+            "LAST_SIGN_IN_METHOD",
+        ),
+        UserServiceError::LastSecondFactor => (
+            StatusCode::CONFLICT,
+            "The tenant requires at least one second factor",
+            "LAST_SECOND_FACTOR",
+        ),
+        _ => return ApiError::internal_server_error(request_id).into_response(),
+    };
+
+    ApiError::new(status, message, code, request_id).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_factor_errors_map_to_client_errors() {
+        let cases = [
+            (UserServiceError::FactorNotFound, StatusCode::NOT_FOUND),
+            (UserServiceError::LastSignInMethod, StatusCode::CONFLICT),
+            (UserServiceError::LastSecondFactor, StatusCode::CONFLICT),
+            (
+                UserServiceError::UserNotFound,
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        ];
+
+        for (err, status) in cases {
+            let response = factor_error(err, "request".to_string());
+            assert_eq!(response.status(), status);
+        }
+    }
+
+    #[test]
+    fn test_factor_response_exposes_removal_id() {
+        let credential_id = Uuid::new_v4();
+        let response = FactorResponse::from(EnrolledFactor {
+            factor: AuthFactor::WebAuthn { credential_id },
+            name: Some("Security key".to_string()),
+            created_at: None,
+            last_used_at: None,
+        });
+
+        let body = serde_json::to_value(&response).expect("Response serializes");
+        assert_eq!(body["id"], format!("webauthn:{}", credential_id));
+        assert_eq!(body["factor"]["type"], "webauthn");
+        assert_eq!(body["name"], "Security key");
+    }
+}
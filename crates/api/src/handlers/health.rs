@@ -0,0 +1,36 @@
+//! Health endpoints
+//!
+//! `GET /health` answers a plain `OK` for load balancers. `GET /health/sessions`
+//! reports the last pass of the session reconciliation on this instance, so
+//! operators can check that sessions of deactivated users are being ended.
+
+use crate::handlers::auth::ApiAppState;
+use crate::response::ApiResponse;
+use crate::validation::generate_request_id;
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+};
+use serde::Serialize;
+
+use acci_auth::services::session::SessionReconciliationReport;
+
+/// Health of the session management
+#[derive(Debug, Serialize)]
+pub struct SessionHealthResponse {
+    /// Last completed reconciliation pass; `None` until the first pass finished
+    pub last_reconciliation: Option<SessionReconciliationReport>,
+}
+
+/// Report the last session reconciliation of this instance
+pub async fn get_session_health(
+    State(state): State<ApiAppState>,
+) -> (StatusCode, Json<ApiResponse<SessionHealthResponse>>) {
+    let response = SessionHealthResponse {
+        last_reconciliation: state.session_service.last_reconciliation(),
+    };
+    (
+        StatusCode::OK,
+        Json(ApiResponse::success(response, generate_request_id())),
+    )
+}
@@ -0,0 +1,447 @@
+//! Admin impersonation endpoints
+//!
+//! Tenant admins can act as a member of their tenant to debug issues without
+//! touching the member's credentials. Each impersonation runs in its own
+//! short-lived session that records the admin and the stated reason, and its
+//! start and end are audited for both users.
+
+use crate::handlers::auth::ApiAppState;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::monitoring;
+use crate::response::{ApiError, ApiResponse};
+use crate::validation::{generate_request_id, validate_json_payload};
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+use validator::Validate;
+
+use acci_auth::{
+    SessionError,
+    services::session::{SessionService, SessionServiceError},
+};
+
+/// Start impersonation request DTO
+#[derive(Debug, Deserialize, Validate)]
+pub struct StartImpersonationRequest {
+    /// User to act as
+    pub target_user_id: Uuid,
+    /// Tenant the admin and the target user belong to
+    pub tenant_id: Uuid,
+    /// Why the impersonation is needed, kept in the audit log
+    #[validate(length(min = 1, max = 500, message = "Reason must be 1-500 characters"))]
+    pub reason: String,
+    /// Requested lifetime in seconds; capped by the configured maximum
+    pub ttl_secs: Option<u64>,
+}
+
+/// Impersonation session response DTO
+#[derive(Debug, Serialize)]
+pub struct ImpersonationResponse {
+    pub session_id: String,
+    /// Session token acting as the target user
+    pub token: String,
+    pub user_id: String,
+    pub tenant_id: String,
+    pub impersonated_by: String,
+    /// Always `true`; lets clients render the impersonation banner
+    pub impersonation: bool,
+    /// Seconds until the session expires
+    pub expires_in_secs: u64,
+}
+
+/// Handler for starting an impersonation session
+///
+/// Requires the `ADMIN` role in the tenant. Impersonation sessions cannot
+/// start further impersonations.
+#[axum::debug_handler(state = ApiAppState)]
+pub async fn start_impersonation(
+    State(session_service): State<Arc<SessionService>>,
+    user: AuthenticatedUser,
+    Json(request): Json<StartImpersonationRequest>,
+) -> Response {
+    debug!("Processing start impersonation request");
+    let request_id = generate_request_id();
+
+    let validated = match validate_json_payload(Json(request)).await {
+        Ok(data) => data,
+        Err(validation_error) => return validation_error.into_response(),
+    };
+
+    monitoring::record_auth_operation("impersonation_start", "attempt");
+
+    if user.is_impersonation() {
+        monitoring::record_auth_operation("impersonation_start", "failure");
+        return ApiError::new(
+            StatusCode::FORBIDDEN,
+            "Impersonation sessions cannot impersonate other users",
+            "IMPERSONATION_NOT_ALLOWED",
+            request_id,
+        )
+        .into_response();
+    }
+
+    let ttl = Duration::from_secs(validated.ttl_secs.unwrap_or(u64::MAX));
+    match session_service
+        .create_impersonation_session(
+            user.user_id,
+            validated.target_user_id,
+            validated.tenant_id,
+            &validated.reason,
+            ttl,
+        )
+        .await
+    {
+        Ok((session, token)) => {
+            monitoring::record_auth_operation("impersonation_start", "success");
+
+            info!(
+                request_id = %request_id,
+                session_id = %session.id,
+                admin_user_id = %user.user_id,
+                target_user_id = %validated.target_user_id,
+                tenant_id = %validated.tenant_id,
+                "Impersonation started"
+            );
+
+            let expires_in_secs =
+                u64::try_from((session.expires_at - OffsetDateTime::now_utc()).whole_seconds())
+                    .unwrap_or_default();
+            let response = ImpersonationResponse {
+                session_id: session.id.to_string(),
+                token,
+                user_id: session.user_id.to_string(),
+                tenant_id: validated.tenant_id.to_string(),
+                impersonated_by: user.user_id.to_string(),
+                impersonation: true,
+                expires_in_secs,
+            };
+            let api_response = ApiResponse::success(response, request_id);
+            (StatusCode::CREATED, Json(api_response)).into_response()
+        },
+        Err(err) => {
+            monitoring::record_auth_operation("impersonation_start", "failure");
+
+            warn!(
+                request_id = %request_id,
+                error = %err,
+                admin_user_id = %user.user_id,
+                target_user_id = %validated.target_user_id,
+                tenant_id = %validated.tenant_id,
+                "Failed to start impersonation"
+            );
+
+            impersonation_error(err, request_id)
+        },
+    }
+}
+
+/// Handler for ending an impersonation session early
+///
+/// Only the admin who started the impersonation may end it.
+#[axum::debug_handler(state = ApiAppState)]
+pub async fn end_impersonation(
+    State(session_service): State<Arc<SessionService>>,
+    user: AuthenticatedUser,
+    Path(session_id): Path<String>,
+) -> Response {
+    debug!("Processing end impersonation request");
+    let request_id = generate_request_id();
+
+    let session_id = match Uuid::parse_str(&session_id) {
+        Ok(id) => id,
+        Err(_) => {
+            return ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "Invalid session ID format",
+                "INVALID_SESSION_ID",
+                request_id,
+            )
+            .into_response();
+        },
+    };
+
+    match session_service
+        .end_impersonation(session_id, user.user_id)
+        .await
+    {
+        Ok(()) => {
+            monitoring::record_auth_operation("impersonation_end", "success");
+
+            info!(
+                request_id = %request_id,
+                session_id = %session_id,
+                admin_user_id = %user.user_id,
+                "Impersonation ended"
+            );
+
+            let api_response = ApiResponse::success(true, request_id);
+            (StatusCode::OK, Json(api_response)).into_response()
+        },
+        Err(err) => {
+            monitoring::record_auth_operation("impersonation_end", "failure");
+
+            warn!(
+                request_id = %request_id,
+                error = %err,
+                session_id = %session_id,
+                admin_user_id = %user.user_id,
+                "Failed to end impersonation"
+            );
+
+            impersonation_error(err, request_id)
+        },
+    }
+}
+
+/// Map impersonation failures to API errors
+fn impersonation_error(err: SessionServiceError, request_id: String) -> Response {
+    match err {
+        SessionServiceError::ImpersonationNotAllowed(reason) => ApiError::new(
+            StatusCode::FORBIDDEN,
+            format!("Impersonation not allowed: {}", reason),
+            "IMPERSONATION_NOT_ALLOWED",
+            request_id,
+        )
+        .into_response(),
+        SessionServiceError::NotTenantMember => ApiError::new(
+            StatusCode::FORBIDDEN,
+            "Target user is not a member of the tenant",
+            "TENANT_ACCESS_DENIED",
+            request_id,
+        )
+        .into_response(),
+        SessionServiceError::ImpersonationUnavailable => ApiError::new(
+            StatusCode::NOT_IMPLEMENTED,
+            "Impersonation is not available",
+            "IMPERSONATION_UNAVAILABLE",
+            request_id,
+        )
+        .into_response(),
+        SessionServiceError::Repository(SessionError::NotFound) => {
+            ApiError::not_found_error("impersonation session", request_id).into_response()
+        },
+        _ => ApiError::internal_server_error(request_id).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::auth::AuthExtractorState;
+    use acci_auth::{
+        AuthConfig, ImpersonationAction, models::tenant::mock::MockTenantRepository,
+        session::mock::MockSessionRepository,
+    };
+    use axum::{
+        extract::FromRequestParts,
+        http::{Request, header},
+    };
+    use serde_json::Value;
+
+    struct Fixture {
+        sessions: Arc<MockSessionRepository>,
+        tenants: Arc<MockTenantRepository>,
+        service: Arc<SessionService>,
+        tenant_id: Uuid,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let sessions = Arc::new(MockSessionRepository::new());
+            let tenants = Arc::new(MockTenantRepository::new());
+            let service = Arc::new(
+                SessionService::new(sessions.clone(), Arc::new(AuthConfig::default()))
+                    .with_tenant_repository(tenants.clone()),
+            );
+            let tenant_id = tenants.insert_tenant_with_policy(&Default::default());
+            Self {
+                sessions,
+                tenants,
+                service,
+                tenant_id,
+            }
+        }
+
+        fn member(&self, role: &str) -> Uuid {
+            let user_id = Uuid::new_v4();
+            self.tenants.add_member(self.tenant_id, user_id, role);
+            user_id
+        }
+
+        async fn login(&self, user_id: Uuid) -> String {
+            let (_, token) = self
+                .service
+                .create_session(user_id, None, None, None, None, None, false)
+                .await
+                .expect("Failed to create session");
+            token
+        }
+
+        async fn authenticate(&self, token: &str) -> Result<AuthenticatedUser, ApiError> {
+            let request = Request::builder()
+                .uri("/")
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(())
+                .expect("valid request");
+            let state = AuthExtractorState {
+                session_service: self.service.clone(),
+                tenant_service: None,
+                jwt_utils: None,
+            };
+            AuthenticatedUser::from_request_parts(&mut request.into_parts().0, &state).await
+        }
+
+        async fn start(&self, admin_token: &str, target_user_id: Uuid) -> (StatusCode, Value) {
+            let admin = self
+                .authenticate(admin_token)
+                .await
+                .expect("Admin is authenticated");
+            let request = StartImpersonationRequest {
+                target_user_id,
+                tenant_id: self.tenant_id,
+                reason: "Ticket 4711: dashboard does not load".to_string(),
+                ttl_secs: Some(24 * 60 * 60),
+            };
+            body(start_impersonation(State(self.service.clone()), admin, Json(request)).await).await
+        }
+
+        fn audit_actions(&self, user_id: Uuid) -> Vec<String> {
+            self.sessions
+                .audit_entries()
+                .into_iter()
+                .filter(|entry| entry.user_id == user_id)
+                .map(|entry| entry.action)
+                .collect()
+        }
+    }
+
+    async fn body(response: Response) -> (StatusCode, Value) {
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read body");
+        (
+            status,
+            serde_json::from_slice(&bytes).expect("Body is JSON"),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_admin_impersonates_member_until_ended() {
+        let fixture = Fixture::new();
+        let admin_id = fixture.member("ADMIN");
+        let member_id = fixture.member("MEMBER");
+        let admin_token = fixture.login(admin_id).await;
+
+        let (status, body) = fixture.start(&admin_token, member_id).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let data = &body["data"];
+        assert_eq!(data["impersonation"], true);
+        assert_eq!(data["user_id"], member_id.to_string());
+        // The requested day is capped at the configured hour
+        assert!(data["expires_in_secs"].as_u64().expect("Expiry is set") <= 3600);
+
+        // Requests with the impersonation token act as the member, flagged as impersonation
+        let token = data["token"].as_str().expect("Token is returned");
+        let impersonated = fixture
+            .authenticate(token)
+            .await
+            .expect("Impersonation session is valid");
+        assert_eq!(impersonated.user_id, member_id);
+        assert_eq!(impersonated.tenant_id, Some(fixture.tenant_id));
+        assert_eq!(impersonated.impersonated_by, Some(admin_id));
+        let started = ImpersonationAction::Started.as_str().to_string();
+        assert_eq!(fixture.audit_actions(member_id), vec![started.clone()]);
+        assert_eq!(fixture.audit_actions(admin_id), vec![started]);
+
+        // Nested impersonation is refused
+        let (status, body) = fixture.start(token, Uuid::new_v4()).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["code"], "IMPERSONATION_NOT_ALLOWED");
+
+        let admin = fixture
+            .authenticate(&admin_token)
+            .await
+            .expect("Admin is authenticated");
+        let session_id = impersonated.session_id.to_string();
+        let (status, _) = body_of_end(&fixture, admin, session_id).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(fixture.authenticate(token).await.is_err());
+        assert_eq!(
+            fixture.audit_actions(admin_id).last().map(String::as_str),
+            Some(ImpersonationAction::Ended.as_str())
+        );
+    }
+
+    async fn body_of_end(
+        fixture: &Fixture,
+        user: AuthenticatedUser,
+        session_id: String,
+    ) -> (StatusCode, Value) {
+        body(end_impersonation(State(fixture.service.clone()), user, Path(session_id)).await).await
+    }
+
+    #[tokio::test]
+    async fn test_impersonation_requires_admin_and_non_admin_target() {
+        let fixture = Fixture::new();
+        let admin_id = fixture.member("ADMIN");
+        let other_admin_id = fixture.member("ADMIN");
+        let member_id = fixture.member("MEMBER");
+
+        let member_token = fixture.login(member_id).await;
+        let (status, body) = fixture.start(&member_token, admin_id).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["code"], "IMPERSONATION_NOT_ALLOWED");
+
+        let admin_token = fixture.login(admin_id).await;
+        let (status, body) = fixture.start(&admin_token, other_admin_id).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["code"], "IMPERSONATION_NOT_ALLOWED");
+
+        let (status, body) = fixture.start(&admin_token, Uuid::new_v4()).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["code"], "TENANT_ACCESS_DENIED");
+
+        assert!(fixture.sessions.audit_entries().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_only_impersonating_admin_can_end_session() {
+        let fixture = Fixture::new();
+        let admin_id = fixture.member("ADMIN");
+        let other_admin_id = fixture.member("ADMIN");
+        let member_id = fixture.member("MEMBER");
+        let (session, _) = fixture
+            .service
+            .create_impersonation_session(
+                admin_id,
+                member_id,
+                fixture.tenant_id,
+                "Debugging",
+                Duration::from_secs(600),
+            )
+            .await
+            .expect("Failed to start impersonation");
+
+        let other_admin = fixture
+            .authenticate(&fixture.login(other_admin_id).await)
+            .await
+            .expect("Admin is authenticated");
+        let (status, body) =
+            body_of_end(&fixture, other_admin.clone(), session.id.to_string()).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["code"], "IMPERSONATION_NOT_ALLOWED");
+
+        let (status, body) = body_of_end(&fixture, other_admin, Uuid::new_v4().to_string()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], "RESOURCE_NOT_FOUND");
+    }
+}
@@ -0,0 +1,432 @@
+//! Session info endpoint
+//!
+//! `GET /me` gives clients everything they need after login in one call: the
+//! user profile, the current session, the active tenant and the user's own
+//! tenant roles. The session is validated on every request, so the response
+//! always reflects whether it is still live. Clients polling the endpoint
+//! revalidate it with `If-None-Match` and get a `304` while nothing changed.
+
+use crate::config::CacheConfig;
+use crate::middleware::auth::{AuthExtractorState, AuthenticatedUser};
+use crate::monitoring;
+use crate::response::{ApiError, ApiResponse, maybe_not_modified, weak_etag, with_private_cache};
+use crate::validation::generate_request_id;
+use axum::{
+    extract::{Extension, FromRef, Json, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+use tracing::{debug, warn};
+
+use acci_auth::{
+    Tenant, TenantUser, User, UserError,
+    services::{
+        session::SessionService, tenant::TenantService, user::UserService, user::UserServiceError,
+    },
+    session::types::MfaStatus,
+};
+
+/// Services the session info endpoint needs
+#[derive(Clone)]
+pub struct MeAppState {
+    pub user_service: Arc<UserService>,
+    pub session_service: Arc<SessionService>,
+    /// Source of the tenant memberships; without it none are listed
+    pub tenant_service: Option<Arc<TenantService>>,
+}
+
+impl FromRef<MeAppState> for AuthExtractorState {
+    fn from_ref(state: &MeAppState) -> Self {
+        Self {
+            session_service: state.session_service.clone(),
+            tenant_service: state.tenant_service.clone(),
+            jwt_utils: None,
+        }
+    }
+}
+
+/// User profile in the session info
+#[derive(Debug, Serialize)]
+pub struct MeUserResponse {
+    pub id: String,
+    pub email: String,
+    pub display_name: String,
+    pub is_verified: bool,
+    /// Previous login (RFC 3339)
+    pub last_login: Option<String>,
+}
+
+/// Current session in the session info
+#[derive(Debug, Serialize)]
+pub struct MeSessionResponse {
+    pub id: String,
+    /// When the session expires (RFC 3339)
+    pub expires_at: Option<String>,
+    /// When the user last fully authenticated in this session (RFC 3339)
+    pub last_auth_at: Option<String>,
+    /// Admin acting as the user, set for impersonation sessions
+    pub impersonated_by: Option<String>,
+}
+
+/// Tenant membership of the user
+#[derive(Debug, Clone, Serialize)]
+pub struct MeTenantResponse {
+    pub id: String,
+    pub name: String,
+    pub subdomain: String,
+    /// Role of the user in the tenant
+    pub role: String,
+}
+
+/// Session info response DTO
+#[derive(Debug, Serialize)]
+pub struct MeResponse {
+    pub user: MeUserResponse,
+    pub session: MeSessionResponse,
+    /// MFA status of the current session
+    pub mfa_status: MfaStatus,
+    /// Tenant the session acts in, with the user's role there
+    pub tenant: Option<MeTenantResponse>,
+    /// All active tenant memberships of the user
+    pub memberships: Vec<MeTenantResponse>,
+}
+
+fn rfc3339(time: impl Into<OffsetDateTime>) -> Option<String> {
+    time.into().format(&Rfc3339).ok()
+}
+
+/// Pair each membership with its tenant, skipping tenants that no longer exist
+fn membership_responses(memberships: &[TenantUser], tenants: Vec<Tenant>) -> Vec<MeTenantResponse> {
+    let tenants: HashMap<_, _> = tenants.into_iter().map(|t| (t.id, t)).collect();
+    memberships
+        .iter()
+        .filter_map(|membership| {
+            let tenant = tenants.get(&membership.tenant_id)?;
+            Some(MeTenantResponse {
+                id: tenant.id.to_string(),
+                name: tenant.name.clone(),
+                subdomain: tenant.subdomain.clone(),
+                role: membership.tenant_role.clone(),
+            })
+        })
+        .collect()
+}
+
+/// ETag of the session info
+///
+/// Covers the versions of the profile, the session and every listed tenant
+/// and membership, so that a change to any of them rolls the tag.
+fn me_etag(profile: &User, user: &AuthenticatedUser, tenants: &[Tenant]) -> String {
+    let memberships: Vec<_> = user
+        .memberships
+        .iter()
+        .map(|m| (m.tenant_id, &m.tenant_role, m.updated_at))
+        .collect();
+    let tenants: Vec<_> = tenants.iter().map(|t| (t.id, t.updated_at)).collect();
+    weak_etag(&(
+        (profile.id, profile.updated_at, profile.last_login),
+        (user.session_id, user.tenant_id, &user.mfa_status),
+        (user.last_auth_at, user.expires_at, user.impersonated_by),
+        memberships,
+        tenants,
+    ))
+}
+
+/// Handler for the session info of the current user
+///
+/// Memberships come from the session's user only, and their tenants are
+/// loaded in a single lookup.
+#[axum::debug_handler]
+pub async fn get_me(
+    State(state): State<MeAppState>,
+    Extension(cache): Extension<CacheConfig>,
+    headers: HeaderMap,
+    user: AuthenticatedUser,
+) -> Response {
+    debug!("Processing session info request");
+    let start = std::time::Instant::now();
+    let request_id = generate_request_id();
+
+    let profile = match state.user_service.get_user(user.user_id).await {
+        Ok(profile) if profile.is_active => profile,
+        Ok(_) | Err(UserServiceError::User(UserError::NotFound)) => {
+            monitoring::record_auth_operation("me", "failure");
+            debug!(request_id = %request_id, user_id = %user.user_id, "Session user is gone");
+            return ApiError::authentication_error(request_id).into_response();
+        },
+        Err(err) => {
+            monitoring::record_auth_operation("me", "failure");
+            warn!(request_id = %request_id, error = %err, "Failed to load user profile");
+            return ApiError::internal_server_error(request_id).into_response();
+        },
+    };
+
+    let tenants = match &state.tenant_service {
+        Some(tenant_service) if !user.memberships.is_empty() => {
+            let ids: Vec<_> = user.memberships.iter().map(|m| m.tenant_id).collect();
+            match tenant_service.get_tenants(&ids).await {
+                Ok(tenants) => tenants,
+                Err(err) => {
+                    monitoring::record_auth_operation("me", "failure");
+                    warn!(request_id = %request_id, error = %err, "Failed to load tenants");
+                    return ApiError::internal_server_error(request_id).into_response();
+                },
+            }
+        },
+        _ => Vec::new(),
+    };
+
+    let etag = me_etag(&profile, &user, &tenants);
+    if let Some(not_modified) = maybe_not_modified(&etag, &headers) {
+        monitoring::record_auth_operation("me", "success");
+        return with_private_cache(not_modified, &etag, &cache);
+    }
+
+    let memberships = membership_responses(&user.memberships, tenants);
+    let tenant = user.tenant_id.and_then(|tenant_id| {
+        let tenant_id = tenant_id.to_string();
+        memberships.iter().find(|m| m.id == tenant_id).cloned()
+    });
+
+    let response = MeResponse {
+        user: MeUserResponse {
+            id: profile.id.to_string(),
+            email: profile.email,
+            display_name: profile.display_name,
+            is_verified: profile.is_verified,
+            last_login: profile.last_login.and_then(rfc3339),
+        },
+        session: MeSessionResponse {
+            id: user.session_id.to_string(),
+            expires_at: rfc3339(user.expires_at),
+            last_auth_at: rfc3339(user.last_auth_at),
+            impersonated_by: user.impersonated_by.map(|id| id.to_string()),
+        },
+        mfa_status: user.mfa_status,
+        tenant,
+        memberships,
+    };
+
+    monitoring::record_auth_operation("me", "success");
+    monitoring::record_request_duration(start.elapsed().as_secs_f64(), "GET", "/me");
+
+    let api_response = ApiResponse::success(response, request_id);
+    with_private_cache(
+        (StatusCode::OK, Json(api_response)).into_response(),
+        &etag,
+        &cache,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acci_auth::{
+        ACTIVE_TENANT_METADATA_KEY, AuthConfig, JwtUtils, User, UserRepository,
+        models::{tenant::mock::MockTenantRepository, user::mock::MockUserRepository},
+        session::mock::MockSessionRepository,
+    };
+    use axum::{
+        extract::FromRequestParts,
+        http::{Request, header},
+    };
+    use serde_json::{Map, Value};
+    use uuid::Uuid;
+
+    struct Fixture {
+        state: MeAppState,
+        user_repo: Arc<MockUserRepository>,
+        tenant_repo: Arc<MockTenantRepository>,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let user_repo = Arc::new(MockUserRepository::new());
+            let tenant_repo = Arc::new(MockTenantRepository::new());
+            let config = Arc::new(AuthConfig::default());
+            let session_service = Arc::new(SessionService::new(
+                Arc::new(MockSessionRepository::new()),
+                config.clone(),
+            ));
+            let user_service = Arc::new(UserService::new(
+                user_repo.clone(),
+                Arc::new(JwtUtils::new(b"test-secret")),
+                session_service.clone(),
+                None,
+                config,
+            ));
+            let tenant_service = Arc::new(TenantService::new(
+                tenant_repo.clone(),
+                user_repo.clone(),
+                user_service.clone(),
+            ));
+            Self {
+                state: MeAppState {
+                    user_service,
+                    session_service,
+                    tenant_service: Some(tenant_service),
+                },
+                user_repo,
+                tenant_repo,
+            }
+        }
+
+        async fn user(&self, email: &str) -> User {
+            let user = User::new(email.to_string(), "hash".to_string());
+            self.user_repo.create(&user).await.expect("User is stored");
+            user
+        }
+
+        async fn authenticate(
+            &self,
+            user_id: Uuid,
+            tenant_id: Uuid,
+            mfa_status: MfaStatus,
+        ) -> AuthenticatedUser {
+            let metadata = Value::Object(Map::from_iter([(
+                ACTIVE_TENANT_METADATA_KEY.to_string(),
+                Value::String(tenant_id.to_string()),
+            )]));
+            let (_, token) = self
+                .state
+                .session_service
+                .create_session_with_status(
+                    user_id,
+                    None,
+                    None,
+                    None,
+                    None,
+                    Some(metadata),
+                    false,
+                    mfa_status,
+                )
+                .await
+                .expect("Session is created");
+            let mut parts = Request::builder()
+                .uri("/me")
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(())
+                .expect("valid request")
+                .into_parts()
+                .0;
+            AuthenticatedUser::from_request_parts(&mut parts, &self.state)
+                .await
+                .expect("Session is valid")
+        }
+
+        async fn get(&self, user: AuthenticatedUser, if_none_match: Option<&str>) -> Response {
+            let mut headers = HeaderMap::new();
+            if let Some(etag) = if_none_match {
+                headers.insert(
+                    header::IF_NONE_MATCH,
+                    etag.parse().expect("valid header value"),
+                );
+            }
+            get_me(
+                State(self.state.clone()),
+                Extension(CacheConfig::default()),
+                headers,
+                user,
+            )
+            .await
+        }
+
+        async fn me(&self, user_id: Uuid, tenant_id: Uuid, mfa_status: MfaStatus) -> Value {
+            let user = self.authenticate(user_id, tenant_id, mfa_status).await;
+            let response = self.get(user, None).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .expect("Failed to read body");
+            let body: Value = serde_json::from_slice(&body).expect("Body is JSON");
+            body["data"].clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_me_for_mfa_verified_admin() {
+        let fixture = Fixture::new();
+        let admin = fixture.user("admin@example.com").await;
+        let colleague = fixture.user("colleague@example.com").await;
+        let tenant_id = fixture
+            .tenant_repo
+            .insert_tenant_with_metadata(Value::Object(Map::new()));
+        let other_tenant_id = fixture
+            .tenant_repo
+            .insert_tenant_with_metadata(Value::Object(Map::new()));
+        fixture.tenant_repo.add_member(tenant_id, admin.id, "ADMIN");
+        fixture
+            .tenant_repo
+            .add_member(other_tenant_id, admin.id, "MEMBER");
+        fixture
+            .tenant_repo
+            .add_member(other_tenant_id, colleague.id, "ADMIN");
+
+        let me = fixture.me(admin.id, tenant_id, MfaStatus::Verified).await;
+        assert_eq!(me["user"]["id"], admin.id.to_string());
+        assert_eq!(me["user"]["email"], "admin@example.com");
+        assert_eq!(me["mfa_status"], "VERIFIED");
+        assert_eq!(me["tenant"]["id"], tenant_id.to_string());
+        assert_eq!(me["tenant"]["role"], "ADMIN");
+        assert!(me["session"]["expires_at"].is_string());
+
+        // Only the user's own roles are listed, not those of other members
+        let memberships = me["memberships"]
+            .as_array()
+            .expect("Memberships are listed");
+        let roles: Vec<_> = memberships
+            .iter()
+            .map(|m| (m["id"].as_str().unwrap_or_default(), m["role"].clone()))
+            .collect();
+        assert_eq!(roles.len(), 2);
+        assert!(roles.contains(&(tenant_id.to_string().as_str(), Value::from("ADMIN"))));
+        assert!(roles.contains(&(other_tenant_id.to_string().as_str(), Value::from("MEMBER"))));
+
+        let me = fixture
+            .me(colleague.id, other_tenant_id, MfaStatus::None)
+            .await;
+        assert_eq!(me["mfa_status"], "NONE");
+        assert_eq!(me["memberships"].as_array().map(Vec::len), Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_me_revalidated_with_etag() {
+        let fixture = Fixture::new();
+        let user = fixture.user("poller@example.com").await;
+        let tenant_id = fixture
+            .tenant_repo
+            .insert_tenant_with_metadata(Value::Object(Map::new()));
+        fixture.tenant_repo.add_member(tenant_id, user.id, "MEMBER");
+        let session = fixture
+            .authenticate(user.id, tenant_id, MfaStatus::None)
+            .await;
+
+        let response = fixture.get(session.clone(), None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(headers[header::CACHE_CONTROL], "private, max-age=30");
+        let etag = headers[header::ETAG]
+            .to_str()
+            .expect("ETag is ASCII")
+            .to_string();
+
+        let response = fixture.get(session.clone(), Some(&etag)).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read body");
+        assert!(body.is_empty());
+
+        // Another session of the same user gets its own tag
+        let other_session = fixture
+            .authenticate(user.id, tenant_id, MfaStatus::None)
+            .await;
+        let response = fixture.get(other_session, Some(&etag)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::monitoring;
+use crate::response::{ApiError, ApiResponse};
+use crate::validation::generate_request_id;
+use axum::{
+    extract::{Json, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{debug, error};
+
+use acci_auth::{models::OutboxMessage, repository::MessageOutboxRepository};
+
+/// Default number of failed messages returned per request
+const DEFAULT_FAILED_LIMIT: u32 = 100;
+/// Upper bound for the `limit` query parameter
+const MAX_FAILED_LIMIT: u32 = 500;
+
+/// Message Administration Application State
+#[derive(Clone)]
+pub struct MessageAdminAppState {
+    /// Outbox repository holding queued and failed messages
+    pub outbox_repository: Arc<dyn MessageOutboxRepository>,
+}
+
+/// Failed Messages Query Parameters
+#[derive(Debug, Deserialize)]
+pub struct FailedMessagesQuery {
+    /// Maximum number of messages to return (defaults to 100, capped at 500)
+    pub limit: Option<u32>,
+}
+
+/// Failed Message Response DTO
+///
+/// The message body is omitted since it may contain verification codes.
+#[derive(Debug, Serialize)]
+pub struct FailedMessageResponse {
+    /// Outbox entry ID
+    pub id: String,
+    /// Tenant ID
+    pub tenant_id: String,
+    /// User ID
+    pub user_id: String,
+    /// Message type (email or sms)
+    pub message_type: String,
+    /// Recipient (email address or phone number)
+    pub recipient: String,
+    /// Number of delivery attempts made
+    pub attempts: u32,
+    /// Error reported on the last attempt
+    pub last_error: Option<String>,
+    /// When the message was enqueued
+    pub created_at: String,
+    /// When the message was marked as failed
+    pub failed_at: String,
+}
+
+impl From<OutboxMessage> for FailedMessageResponse {
+    fn from(message: OutboxMessage) -> Self {
+        Self {
+            id: message.id.to_string(),
+            tenant_id: message.tenant_id.to_string(),
+            user_id: message.user_id.to_string(),
+            message_type: format!("{:?}", message.message_type).to_lowercase(),
+            recipient: message.recipient,
+            attempts: message.attempts,
+            last_error: message.last_error,
+            created_at: message.created_at.to_string(),
+            failed_at: message.updated_at.to_string(),
+        }
+    }
+}
+
+/// Handler for listing messages that permanently failed delivery
+#[axum::debug_handler]
+pub async fn list_failed_messages(
+    State(state): State<MessageAdminAppState>,
+    Query(query): Query<FailedMessagesQuery>,
+) -> Response {
+    debug!("Processing failed messages request");
+
+    // Generate a unique request ID
+    let request_id = generate_request_id();
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_FAILED_LIMIT)
+        .clamp(1, MAX_FAILED_LIMIT);
+
+    match state.outbox_repository.list_failed(limit).await {
+        Ok(messages) => {
+            let messages: Vec<FailedMessageResponse> = messages
+                .into_iter()
+                .map(FailedMessageResponse::from)
+                .collect();
+            let response = ApiResponse::success(messages, request_id);
+            (StatusCode::OK, Json(response)).into_response()
+        },
+        Err(e) => {
+            error!("Failed to list failed messages: {}", e);
+            monitoring::record_api_error("database", "MESSAGE_OUTBOX_ERROR", 500);
+            ApiError::internal_server_error(request_id).into_response()
+        },
+    }
+}
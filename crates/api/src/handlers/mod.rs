@@ -1,15 +1,51 @@
 // Handler modules for the API
+pub mod access_revocation;
+pub mod account_deletion;
+pub mod admin;
+pub mod api_keys;
 pub mod auth;
+pub mod delivery_receipts;
 pub mod example;
 pub mod example_router;
+pub mod export;
+pub mod factors;
+pub mod health;
+pub mod impersonation;
+pub mod me;
+pub mod messages;
+pub mod oidc;
+pub mod rollout;
+pub mod saml;
+pub mod scim;
+pub mod telemetry;
 pub mod tenant;
+pub mod timeline;
+pub mod trusted_devices;
 pub mod verification;
 #[cfg(feature = "enable_webauthn")]
 pub mod webauthn;
 
 // Re-export handlers
+pub use access_revocation::*;
+pub use account_deletion::*;
+pub use admin::*;
+pub use api_keys::*;
 pub use auth::*;
+pub use delivery_receipts::*;
+pub use export::*;
+pub use factors::*;
+pub use health::*;
+pub use impersonation::*;
+pub use me::*;
+pub use messages::*;
+pub use oidc::*;
+pub use rollout::*;
+pub use saml::*;
+pub use scim::*;
+pub use telemetry::*;
 pub use tenant::*;
+pub use timeline::*;
+pub use trusted_devices::*;
 pub use verification::*;
 #[cfg(feature = "enable_webauthn")]
 pub use webauthn::*;
@@ -0,0 +1,310 @@
+//! External login endpoints
+//!
+//! Users sign in through an OAuth2/OpenID Connect provider such as Google or
+//! GitHub. The authorize endpoint redirects to the provider; the provider
+//! redirects back to the callback, which exchanges the authorization code and
+//! creates a session for the local user with the verified email address.
+
+use crate::handlers::auth::LoginResponse;
+use crate::monitoring;
+use crate::response::{ApiError, ApiResponse};
+use crate::validation::generate_request_id;
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use acci_auth::{
+    OidcError, OidcProvider, TenantError,
+    models::user::UserError,
+    services::user::{UserService, UserServiceError},
+    session::types::MfaStatus,
+};
+
+/// State of the external login routes
+#[derive(Clone)]
+pub struct OidcAppState {
+    /// User service creating the sessions
+    pub user_service: Arc<UserService>,
+    /// Configured providers by name
+    pub providers: Arc<HashMap<String, Arc<OidcProvider>>>,
+}
+
+/// Authorize query parameters
+#[derive(Debug, Deserialize)]
+pub struct OidcAuthorizeQuery {
+    /// Tenant to sign in to
+    pub tenant_id: Uuid,
+}
+
+/// Callback query parameters set by the provider
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    /// Set instead of `code` when the user denied access
+    pub error: Option<String>,
+}
+
+/// Handler redirecting to the provider's login page
+#[axum::debug_handler]
+pub async fn oidc_authorize(
+    State(state): State<OidcAppState>,
+    Path(provider): Path<String>,
+    Query(query): Query<OidcAuthorizeQuery>,
+) -> Response {
+    debug!("Processing external login request");
+    let request_id = generate_request_id();
+
+    let Some(provider) = state.providers.get(&provider) else {
+        return unknown_provider(request_id);
+    };
+
+    match provider.authorization_url(query.tenant_id).await {
+        Ok(url) => Redirect::to(&url).into_response(),
+        Err(err) => {
+            warn!(
+                request_id = %request_id,
+                error = %err,
+                provider = %provider.name(),
+                "Failed to start external login"
+            );
+            oidc_error(err, request_id)
+        },
+    }
+}
+
+/// Handler completing an external login
+///
+/// Responds like the password login, with the token of the new session.
+#[axum::debug_handler]
+pub async fn oidc_callback(
+    State(state): State<OidcAppState>,
+    Path(provider): Path<String>,
+    Query(query): Query<OidcCallbackQuery>,
+) -> Response {
+    debug!("Processing external login callback");
+    let request_id = generate_request_id();
+
+    let Some(provider) = state.providers.get(&provider) else {
+        return unknown_provider(request_id);
+    };
+
+    monitoring::record_auth_operation("oidc_login", "attempt");
+
+    let (code, login_state) = match (query.code, query.state, query.error) {
+        (Some(code), Some(login_state), None) => (code, login_state),
+        (_, _, Some(error)) => {
+            monitoring::record_auth_operation("oidc_login", "failure");
+            info!(
+                request_id = %request_id,
+                provider = %provider.name(),
+                error = %error,
+                "External login was not completed at the provider"
+            );
+            return ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "Login was cancelled at the identity provider",
+                "OIDC_LOGIN_CANCELLED",
+                request_id,
+            )
+            .into_response();
+        },
+        _ => {
+            monitoring::record_auth_operation("oidc_login", "failure");
+            return ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "Missing code or state",
+                "INVALID_OIDC_CALLBACK",
+                request_id,
+            )
+            .into_response();
+        },
+    };
+
+    let login = match provider.handle_callback(&code, &login_state).await {
+        Ok(login) => login,
+        Err(err) => {
+            monitoring::record_auth_operation("oidc_login", "failure");
+            warn!(
+                request_id = %request_id,
+                error = %err,
+                provider = %provider.name(),
+                "External login failed"
+            );
+            return oidc_error(err, request_id);
+        },
+    };
+
+    let result = state
+        .user_service
+        .login_with_identity(
+            login.user,
+            login.tenant_id,
+            &provider.login_type(),
+            None, // device_id
+            None, // device_fingerprint
+            None, // ip_address
+            None, // user_agent
+            false,
+        )
+        .await;
+
+    match result {
+        Ok(login_result) => {
+            let user_id = login_result.user.id;
+            let mfa_status = login_result.mfa_status.clone();
+            let response = LoginResponse::from(login_result);
+
+            if mfa_status == MfaStatus::Pending {
+                monitoring::record_auth_operation("oidc_login", "mfa_enrollment_required");
+                let api_response = ApiResponse::error_with_data(
+                    response,
+                    "MFA enrollment is required before the account can be used",
+                    "MFA_ENROLLMENT_REQUIRED",
+                    request_id,
+                );
+                return (StatusCode::FORBIDDEN, Json(api_response)).into_response();
+            }
+
+            monitoring::record_auth_operation("oidc_login", "success");
+            info!(
+                request_id = %request_id,
+                user_id = %user_id,
+                provider = %provider.name(),
+                created = login.created,
+                tenant_id = ?response.tenant_id,
+                "External login successful"
+            );
+
+            let api_response = ApiResponse::success(response, request_id);
+            (StatusCode::OK, Json(api_response)).into_response()
+        },
+        Err(err) => {
+            monitoring::record_auth_operation("oidc_login", "failure");
+            warn!(
+                request_id = %request_id,
+                error = %err,
+                provider = %provider.name(),
+                "Failed to create session for external login"
+            );
+
+            let (status, message, code) = match err {
+                UserServiceError::User(UserError::InactiveUser) => {
+                    (StatusCode::FORBIDDEN, "Account is locked", "ACCOUNT_LOCKED")
+                },
+                UserServiceError::MfaEnrollmentExpired => (
+                    StatusCode::FORBIDDEN,
+                    "MFA enrollment deadline has passed, please contact your administrator",
+                    "MFA_ENROLLMENT_EXPIRED",
+                ),
+                UserServiceError::LoginLocationBlocked(_) => (
+                    StatusCode::FORBIDDEN,
+                    "Logins from this location are not allowed by the tenant",
+                    "LOGIN_LOCATION_BLOCKED",
+                ),
+                _ => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "An error occurred during login",
+                    "LOGIN_ERROR",
+                ),
+            };
+            ApiError::new(status, message, code, request_id).into_response()
+        },
+    }
+}
+
+fn unknown_provider(request_id: String) -> Response {
+    ApiError::new(
+        StatusCode::NOT_FOUND,
+        "Identity provider not found",
+        "PROVIDER_NOT_FOUND",
+        request_id,
+    )
+    .into_response()
+}
+
+/// Map an external login error to an API error response
+fn oidc_error(err: OidcError, request_id: String) -> Response {
+    let (status, message, code) = match err {
+        OidcError::InvalidState => (
+            StatusCode::BAD_REQUEST,
+            "Login request is invalid or has expired",
+            "INVALID_OIDC_STATE",
+        ),
+        OidcError::UnverifiedEmail => (
+            StatusCode::FORBIDDEN,
+            "Email address is not verified by the identity provider",
+            "EMAIL_NOT_VERIFIED",
+        ),
+        OidcError::AccountNotFound => (
+            StatusCode::FORBIDDEN,
+            "No account exists for this identity",
+            "ACCOUNT_NOT_FOUND",
+        ),
+        OidcError::NotTenantMember => (
+            StatusCode::FORBIDDEN,
+            "User is not a member of the tenant",
+            "TENANT_ACCESS_DENIED",
+        ),
+        OidcError::User(UserError::InactiveUser) => {
+            (StatusCode::FORBIDDEN, "Account is locked", "ACCOUNT_LOCKED")
+        },
+        OidcError::Tenant(TenantError::NotFound) => (
+            StatusCode::NOT_FOUND,
+            "Tenant not found",
+            "TENANT_NOT_FOUND",
+        ),
+        OidcError::Tenant(TenantError::InactiveTenant) => (
+            StatusCode::FORBIDDEN,
+            "Tenant is inactive",
+            "TENANT_INACTIVE",
+        ),
+        OidcError::Provider(_) | OidcError::InvalidIdToken(_) => (
+            StatusCode::BAD_GATEWAY,
+            "Identity provider response could not be verified",
+            "IDENTITY_PROVIDER_ERROR",
+        ),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "An error occurred during external login",
+            "OIDC_ERROR",
+        ),
+    };
+
+    ApiError::new(status, message, code, request_id).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oidc_errors_map_to_client_errors() {
+        let cases = [
+            (OidcError::InvalidState, StatusCode::BAD_REQUEST),
+            (OidcError::UnverifiedEmail, StatusCode::FORBIDDEN),
+            (OidcError::AccountNotFound, StatusCode::FORBIDDEN),
+            (OidcError::NotTenantMember, StatusCode::FORBIDDEN),
+            (
+                OidcError::Tenant(TenantError::NotFound),
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                OidcError::InvalidIdToken("Nonce mismatch".to_string()),
+                StatusCode::BAD_GATEWAY,
+            ),
+        ];
+
+        for (err, status) in cases {
+            let response = oidc_error(err, "request".to_string());
+            assert_eq!(response.status(), status);
+        }
+    }
+}
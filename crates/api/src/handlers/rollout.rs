@@ -0,0 +1,310 @@
+//! Rollout flag administration endpoints
+//!
+//! `GET /admin/rollout-flags` lists the percentage-based rollouts of risky
+//! behavior changes and `PUT /admin/rollout-flags/{name}` creates or adjusts
+//! one. Both are restricted to users whose access token carries the platform
+//! admin claim. A change applies to this instance at once and to the others
+//! once their flag cache expires.
+
+use crate::middleware::auth::{AuthExtractorState, AuthenticatedUser};
+use crate::monitoring;
+use crate::response::{ApiError, ApiResponse};
+use crate::validation::{generate_request_id, validate_json_payload};
+use axum::{
+    extract::{FromRef, Json, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+use validator::Validate;
+
+use acci_auth::{JwtUtils, services::session::SessionService};
+use acci_core::rollout::{RolloutFlag, RolloutFlags};
+
+/// Rollout Administration Application State
+#[derive(Clone)]
+pub struct RolloutAdminAppState {
+    /// Flags evaluated by the services of this instance
+    pub rollout_flags: Arc<RolloutFlags>,
+    /// Session service for authenticating the admin
+    pub session_service: Arc<SessionService>,
+    /// Validates the access token carrying the platform admin claim
+    pub jwt_utils: Arc<JwtUtils>,
+}
+
+impl FromRef<RolloutAdminAppState> for AuthExtractorState {
+    fn from_ref(state: &RolloutAdminAppState) -> Self {
+        Self {
+            session_service: state.session_service.clone(),
+            tenant_service: None,
+            jwt_utils: Some(state.jwt_utils.clone()),
+        }
+    }
+}
+
+/// Rollout flag request DTO
+#[derive(Debug, Deserialize, Validate)]
+pub struct RolloutFlagRequest {
+    /// Share of the tenants or users the flag is enabled for
+    #[validate(range(max = 100, message = "Percentage must be between 0 and 100"))]
+    pub percentage: u8,
+    /// Tenants the flag is enabled for regardless of the percentage
+    #[serde(default)]
+    pub tenant_allowlist: Vec<Uuid>,
+}
+
+/// Rollout flag response DTO
+#[derive(Debug, Serialize)]
+pub struct RolloutFlagResponse {
+    pub name: String,
+    pub percentage: u8,
+    pub tenant_allowlist: Vec<String>,
+    /// When the flag was last changed (RFC 3339)
+    pub updated_at: Option<String>,
+}
+
+impl From<RolloutFlag> for RolloutFlagResponse {
+    fn from(flag: RolloutFlag) -> Self {
+        Self {
+            name: flag.name,
+            percentage: flag.percentage,
+            tenant_allowlist: flag
+                .tenant_allowlist
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            updated_at: flag.updated_at.format(&Rfc3339).ok(),
+        }
+    }
+}
+
+/// Rejection of users without the platform admin claim
+fn reject_non_platform_admin(user: &AuthenticatedUser, request_id: &str) -> Option<Response> {
+    if user.is_platform_admin() {
+        return None;
+    }
+    warn!(
+        request_id = %request_id,
+        user_id = %user.user_id,
+        "User is not a platform admin"
+    );
+    Some(ApiError::authorization_error(request_id).into_response())
+}
+
+/// Handler listing the rollout flags
+#[axum::debug_handler(state = RolloutAdminAppState)]
+pub async fn list_rollout_flags(
+    State(state): State<RolloutAdminAppState>,
+    user: AuthenticatedUser,
+) -> Response {
+    debug!("Processing list rollout flags request");
+    let request_id = generate_request_id();
+    if let Some(response) = reject_non_platform_admin(&user, &request_id) {
+        return response;
+    }
+
+    let flags: Vec<RolloutFlagResponse> = state
+        .rollout_flags
+        .flags()
+        .await
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    let api_response = ApiResponse::success(flags, request_id);
+    (StatusCode::OK, Json(api_response)).into_response()
+}
+
+/// Handler creating or adjusting a rollout flag
+#[axum::debug_handler(state = RolloutAdminAppState)]
+pub async fn set_rollout_flag(
+    State(state): State<RolloutAdminAppState>,
+    user: AuthenticatedUser,
+    Path(name): Path<String>,
+    Json(request): Json<RolloutFlagRequest>,
+) -> Response {
+    debug!("Processing set rollout flag request");
+    let request_id = generate_request_id();
+    if let Some(response) = reject_non_platform_admin(&user, &request_id) {
+        return response;
+    }
+
+    let validated = match validate_json_payload(Json(request)).await {
+        Ok(data) => data,
+        Err(validation_error) => return validation_error.into_response(),
+    };
+
+    let flag = RolloutFlag {
+        name,
+        percentage: validated.percentage,
+        tenant_allowlist: validated.tenant_allowlist,
+        updated_at: OffsetDateTime::now_utc(),
+    };
+    match state.rollout_flags.set_flag(flag).await {
+        Ok(flag) => {
+            info!(
+                request_id = %request_id,
+                user_id = %user.user_id,
+                flag = %flag.name,
+                percentage = flag.percentage,
+                "Rollout flag set"
+            );
+            let api_response = ApiResponse::success(RolloutFlagResponse::from(flag), request_id);
+            (StatusCode::OK, Json(api_response)).into_response()
+        },
+        Err(e @ acci_core::Error::Validation(_)) => {
+            ApiError::from_error(e, request_id).into_response()
+        },
+        Err(e) => {
+            error!("Failed to store rollout flag: {}", e);
+            monitoring::record_api_error("database", "ROLLOUT_FLAG_ERROR", 500);
+            ApiError::internal_server_error(request_id).into_response()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acci_auth::session::types::MfaStatus;
+    use acci_auth::test_support::MockSessionRepository;
+    use acci_auth::{AuthConfig, PLATFORM_ADMIN_CLAIM};
+    use acci_core::rollout::RolloutSubject;
+    use serde_json::Value;
+
+    fn state() -> RolloutAdminAppState {
+        RolloutAdminAppState {
+            rollout_flags: Arc::new(RolloutFlags::fixed([RolloutFlag::new(
+                "session_token_hmac",
+                10,
+            )])),
+            session_service: Arc::new(SessionService::new(
+                Arc::new(MockSessionRepository::new()),
+                Arc::new(AuthConfig::default()),
+            )),
+            jwt_utils: Arc::new(JwtUtils::new(b"test-secret")),
+        }
+    }
+
+    fn user(platform_admin: bool) -> AuthenticatedUser {
+        let now = OffsetDateTime::now_utc();
+        let mut custom_claims = serde_json::Map::new();
+        if platform_admin {
+            custom_claims.insert(PLATFORM_ADMIN_CLAIM.to_string(), Value::Bool(true));
+        }
+        AuthenticatedUser {
+            user_id: Uuid::new_v4(),
+            session_id: Uuid::new_v4(),
+            tenant_id: None,
+            mfa_status: MfaStatus::Verified,
+            memberships: Vec::new(),
+            claims: None,
+            custom_claims,
+            last_auth_at: now,
+            expires_at: now + time::Duration::hours(1),
+            impersonated_by: None,
+        }
+    }
+
+    fn request(percentage: u8, tenant_allowlist: Vec<Uuid>) -> Json<RolloutFlagRequest> {
+        Json(RolloutFlagRequest {
+            percentage,
+            tenant_allowlist,
+        })
+    }
+
+    async fn body(response: Response) -> Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read body");
+        serde_json::from_slice(&body).expect("Body is JSON")
+    }
+
+    #[tokio::test]
+    async fn test_admin_adjusts_flag_at_runtime() {
+        let state = state();
+        let tenant_id = Uuid::new_v4();
+        assert!(
+            !state
+                .rollout_flags
+                .is_enabled(
+                    "client_binding_enforcement",
+                    RolloutSubject::tenant(tenant_id)
+                )
+                .await
+        );
+
+        let response = set_rollout_flag(
+            State(state.clone()),
+            user(true),
+            Path("client_binding_enforcement".to_string()),
+            request(0, vec![tenant_id]),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let json = body(response).await;
+        assert_eq!(json["data"]["percentage"], 0);
+        assert_eq!(json["data"]["tenant_allowlist"][0], tenant_id.to_string());
+
+        // Evaluations of this instance see the change at once
+        assert!(
+            state
+                .rollout_flags
+                .is_enabled(
+                    "client_binding_enforcement",
+                    RolloutSubject::tenant(tenant_id)
+                )
+                .await
+        );
+
+        let response = list_rollout_flags(State(state), user(true)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let json = body(response).await;
+        let names: Vec<_> = json["data"]
+            .as_array()
+            .expect("Flags are listed")
+            .iter()
+            .map(|flag| flag["name"].clone())
+            .collect();
+        assert_eq!(names, ["client_binding_enforcement", "session_token_hmac"]);
+    }
+
+    #[tokio::test]
+    async fn test_percentage_above_100_is_rejected() {
+        let state = state();
+
+        let response = set_rollout_flag(
+            State(state.clone()),
+            user(true),
+            Path("session_token_hmac".to_string()),
+            request(101, Vec::new()),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(state.rollout_flags.flags().await[0].percentage, 10);
+    }
+
+    #[tokio::test]
+    async fn test_rollout_flags_require_platform_admin_claim() {
+        let state = state();
+
+        let response = list_rollout_flags(State(state.clone()), user(false)).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let mut impersonated = user(true);
+        impersonated.impersonated_by = Some(Uuid::new_v4());
+        let response = set_rollout_flag(
+            State(state.clone()),
+            impersonated,
+            Path("session_token_hmac".to_string()),
+            request(100, Vec::new()),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(state.rollout_flags.flags().await[0].percentage, 10);
+    }
+}
@@ -0,0 +1,261 @@
+//! SAML single sign-on endpoints
+//!
+//! Tenants with an enterprise identity provider sign in through SAML 2.0. The
+//! login endpoint redirects to the tenant's identity provider; the provider posts
+//! the signed response back to the tenant's assertion consumer service (ACS),
+//! which creates a session for the asserted user.
+
+use crate::handlers::auth::LoginResponse;
+use crate::monitoring;
+use crate::response::{ApiError, ApiResponse};
+use crate::validation::generate_request_id;
+use axum::{
+    Form,
+    extract::{Json, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use acci_auth::{
+    SamlError, SamlServiceProvider, TenantError,
+    models::user::UserError,
+    services::user::{UserService, UserServiceError},
+    session::types::MfaStatus,
+};
+
+/// Login type recorded in the metadata of sessions created through SAML
+const SAML_LOGIN_TYPE: &str = "saml";
+
+/// State of the SAML routes
+#[derive(Clone)]
+pub struct SamlAppState {
+    /// User service creating the sessions
+    pub user_service: Arc<UserService>,
+    /// Service provider validating the responses
+    pub provider: Arc<SamlServiceProvider>,
+}
+
+/// Form posted to the ACS by the HTTP-POST binding
+#[derive(Debug, Deserialize)]
+pub struct SamlAcsForm {
+    #[serde(rename = "SAMLResponse")]
+    pub saml_response: String,
+    #[serde(rename = "RelayState")]
+    pub relay_state: Option<String>,
+}
+
+/// Handler redirecting to the tenant's identity provider
+#[axum::debug_handler]
+pub async fn saml_login(
+    State(state): State<SamlAppState>,
+    Path(tenant_id): Path<Uuid>,
+) -> Response {
+    debug!("Processing SAML login request");
+    let request_id = generate_request_id();
+
+    match state.provider.authn_request_url(tenant_id).await {
+        Ok(url) => Redirect::to(&url).into_response(),
+        Err(err) => {
+            warn!(
+                request_id = %request_id,
+                error = %err,
+                tenant_id = %tenant_id,
+                "Failed to start SAML login"
+            );
+            saml_error(err, request_id)
+        },
+    }
+}
+
+/// Assertion consumer service of a tenant
+///
+/// Responds like the password login, with the token of the new session.
+#[axum::debug_handler]
+pub async fn saml_acs(
+    State(state): State<SamlAppState>,
+    Path(tenant_id): Path<Uuid>,
+    Form(form): Form<SamlAcsForm>,
+) -> Response {
+    debug!("Processing SAML response");
+    let request_id = generate_request_id();
+    monitoring::record_auth_operation("saml_login", "attempt");
+
+    let login = match state
+        .provider
+        .consume_response(tenant_id, &form.saml_response)
+        .await
+    {
+        Ok(login) => login,
+        Err(err) => {
+            monitoring::record_auth_operation("saml_login", "failure");
+            warn!(
+                request_id = %request_id,
+                error = %err,
+                tenant_id = %tenant_id,
+                "SAML login failed"
+            );
+            return saml_error(err, request_id);
+        },
+    };
+
+    let result = state
+        .user_service
+        .login_with_identity(
+            login.user,
+            login.tenant_id,
+            SAML_LOGIN_TYPE,
+            None, // device_id
+            None, // device_fingerprint
+            None, // ip_address
+            None, // user_agent
+            false,
+        )
+        .await;
+
+    match result {
+        Ok(login_result) => {
+            let user_id = login_result.user.id;
+            let mfa_status = login_result.mfa_status.clone();
+            let response = LoginResponse::from(login_result);
+
+            if mfa_status == MfaStatus::Pending {
+                monitoring::record_auth_operation("saml_login", "mfa_enrollment_required");
+                let api_response = ApiResponse::error_with_data(
+                    response,
+                    "MFA enrollment is required before the account can be used",
+                    "MFA_ENROLLMENT_REQUIRED",
+                    request_id,
+                );
+                return (StatusCode::FORBIDDEN, Json(api_response)).into_response();
+            }
+
+            monitoring::record_auth_operation("saml_login", "success");
+            info!(
+                request_id = %request_id,
+                user_id = %user_id,
+                tenant_id = %tenant_id,
+                relay_state = ?form.relay_state,
+                "SAML login successful"
+            );
+
+            let api_response = ApiResponse::success(response, request_id);
+            (StatusCode::OK, Json(api_response)).into_response()
+        },
+        Err(err) => {
+            monitoring::record_auth_operation("saml_login", "failure");
+            warn!(
+                request_id = %request_id,
+                error = %err,
+                tenant_id = %tenant_id,
+                "Failed to create session for SAML login"
+            );
+
+            let (status, message, code) = match err {
+                UserServiceError::User(UserError::InactiveUser) => {
+                    (StatusCode::FORBIDDEN, "Account is locked", "ACCOUNT_LOCKED")
+                },
+                UserServiceError::MfaEnrollmentExpired => (
+                    StatusCode::FORBIDDEN,
+                    "MFA enrollment deadline has passed, please contact your administrator",
+                    "MFA_ENROLLMENT_EXPIRED",
+                ),
+                UserServiceError::LoginLocationBlocked(_) => (
+                    StatusCode::FORBIDDEN,
+                    "Logins from this location are not allowed by the tenant",
+                    "LOGIN_LOCATION_BLOCKED",
+                ),
+                _ => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "An error occurred during login",
+                    "LOGIN_ERROR",
+                ),
+            };
+            ApiError::new(status, message, code, request_id).into_response()
+        },
+    }
+}
+
+/// Map a SAML error to an API error response
+fn saml_error(err: SamlError, request_id: String) -> Response {
+    let (status, message, code) = match err {
+        SamlError::NotConfigured => (
+            StatusCode::NOT_FOUND,
+            "Single sign-on is not configured for the tenant",
+            "SAML_NOT_CONFIGURED",
+        ),
+        SamlError::InvalidResponse(_)
+        | SamlError::InvalidSignature(_)
+        | SamlError::InvalidConditions(_)
+        | SamlError::Replayed => (
+            StatusCode::UNAUTHORIZED,
+            "SAML response could not be verified",
+            "INVALID_SAML_RESPONSE",
+        ),
+        SamlError::StatusNotSuccess(_) => (
+            StatusCode::UNAUTHORIZED,
+            "Login was not completed at the identity provider",
+            "SAML_LOGIN_FAILED",
+        ),
+        SamlError::AccountNotFound => (
+            StatusCode::FORBIDDEN,
+            "No account exists for this identity",
+            "ACCOUNT_NOT_FOUND",
+        ),
+        SamlError::NotTenantMember => (
+            StatusCode::FORBIDDEN,
+            "User is not a member of the tenant",
+            "TENANT_ACCESS_DENIED",
+        ),
+        SamlError::User(UserError::InactiveUser) => {
+            (StatusCode::FORBIDDEN, "Account is locked", "ACCOUNT_LOCKED")
+        },
+        SamlError::Tenant(TenantError::NotFound) => (
+            StatusCode::NOT_FOUND,
+            "Tenant not found",
+            "TENANT_NOT_FOUND",
+        ),
+        SamlError::Tenant(TenantError::InactiveTenant) => (
+            StatusCode::FORBIDDEN,
+            "Tenant is inactive",
+            "TENANT_INACTIVE",
+        ),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "An error occurred during single sign-on",
+            "SAML_ERROR",
+        ),
+    };
+
+    ApiError::new(status, message, code, request_id).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_saml_errors_map_to_client_errors() {
+        let cases = [
+            (SamlError::NotConfigured, StatusCode::NOT_FOUND),
+            (SamlError::Replayed, StatusCode::UNAUTHORIZED),
+            (
+                SamlError::InvalidResponse("Expected a Response".to_string()),
+                StatusCode::UNAUTHORIZED,
+            ),
+            (SamlError::NotTenantMember, StatusCode::FORBIDDEN),
+            (
+                SamlError::ReplayStore(anyhow::anyhow!("Redis unavailable")),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        ];
+
+        for (err, status) in cases {
+            let response = saml_error(err, "request".to_string());
+            assert_eq!(response.status(), status);
+        }
+    }
+}
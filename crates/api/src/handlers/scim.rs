@@ -0,0 +1,448 @@
+//! SCIM 2.0 provisioning endpoints
+//!
+//! Identity providers manage the users and groups of a tenant through
+//! `/scim/v2/Users` and `/scim/v2/Groups`. Requests carry the tenant's SCIM
+//! token as bearer token; responses use the SCIM media type and error format
+//! rather than the API envelope, as SCIM clients expect.
+
+use crate::monitoring;
+use crate::validation::generate_request_id;
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use acci_auth::{
+    ScimError, ScimGroup, ScimPage, ScimPatchRequest, ScimService, ScimUser,
+    services::{scim::ERROR_SCHEMA, tenant::TenantServiceError, user::UserServiceError},
+};
+
+/// Media type of SCIM requests and responses
+const SCIM_CONTENT_TYPE: &str = "application/scim+json";
+
+/// State of the SCIM routes
+#[derive(Clone)]
+pub struct ScimAppState {
+    /// Service provider handling the provisioning requests
+    pub scim_service: Arc<ScimService>,
+}
+
+/// Query parameters of list requests
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimQuery {
+    pub filter: Option<String>,
+    pub start_index: Option<usize>,
+    pub count: Option<usize>,
+}
+
+impl ScimQuery {
+    fn page(&self) -> ScimPage {
+        ScimPage {
+            start_index: self.start_index.unwrap_or(1),
+            count: self.count,
+        }
+    }
+}
+
+/// SCIM error response body (RFC 7644 section 3.12)
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScimErrorBody {
+    schemas: Vec<&'static str>,
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scim_type: Option<&'static str>,
+    detail: String,
+}
+
+/// Resolve the tenant of the request from its bearer token
+async fn authenticate(state: &ScimAppState, headers: &HeaderMap) -> Result<Uuid, ScimError> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(ScimError::InvalidToken)?;
+
+    state.scim_service.authenticate(token.trim()).await
+}
+
+fn if_match(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::IF_MATCH)
+        .and_then(|value| value.to_str().ok())
+}
+
+fn scim_json<T: Serialize>(status: StatusCode, body: &T) -> Response {
+    let mut response = (status, Json(body)).into_response();
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(SCIM_CONTENT_TYPE),
+    );
+    response
+}
+
+/// Response with a single resource and its version as `ETag`
+fn resource_response<T: Serialize>(
+    status: StatusCode,
+    resource: &T,
+    version: Option<&str>,
+) -> Response {
+    let mut response = scim_json(status, resource);
+    if let Some(etag) = version.and_then(|version| HeaderValue::from_str(version).ok()) {
+        response.headers_mut().insert(header::ETAG, etag);
+    }
+    response
+}
+
+fn user_response(status: StatusCode, user: &ScimUser) -> Response {
+    resource_response(
+        status,
+        user,
+        user.meta.as_ref().map(|meta| meta.version.as_str()),
+    )
+}
+
+fn group_response(status: StatusCode, group: &ScimGroup) -> Response {
+    resource_response(
+        status,
+        group,
+        group.meta.as_ref().map(|meta| meta.version.as_str()),
+    )
+}
+
+/// Answer `304 Not Modified` if the client already has the current version
+fn not_modified(headers: &HeaderMap, version: Option<&str>) -> bool {
+    let Some(version) = version else {
+        return false;
+    };
+    headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|tags| tags.split(',').any(|tag| tag.trim() == version))
+}
+
+/// Map a SCIM error to a SCIM error response
+fn scim_error(err: ScimError, request_id: &str) -> Response {
+    let status = match &err {
+        ScimError::InvalidToken => StatusCode::UNAUTHORIZED,
+        ScimError::NotFound => StatusCode::NOT_FOUND,
+        ScimError::Uniqueness => StatusCode::CONFLICT,
+        ScimError::InvalidFilter(_) | ScimError::InvalidValue(_) | ScimError::Mutability(_) => {
+            StatusCode::BAD_REQUEST
+        },
+        ScimError::PreconditionFailed => StatusCode::PRECONDITION_FAILED,
+        ScimError::User(UserServiceError::User(_) | UserServiceError::Password(_)) => {
+            StatusCode::BAD_REQUEST
+        },
+        ScimError::Tenant(TenantServiceError::InvalidInput(_)) => StatusCode::BAD_REQUEST,
+        ScimError::Tenant(TenantServiceError::TenantLimitExceeded { .. }) => StatusCode::FORBIDDEN,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+
+    let detail = if status == StatusCode::INTERNAL_SERVER_ERROR {
+        warn!(request_id = %request_id, error = %err, "SCIM request failed");
+        "An internal error occurred".to_string()
+    } else {
+        debug!(request_id = %request_id, error = %err, "SCIM request rejected");
+        err.to_string()
+    };
+    let body = ScimErrorBody {
+        schemas: vec![ERROR_SCHEMA],
+        status: status.as_u16().to_string(),
+        scim_type: err.scim_type(),
+        detail,
+    };
+
+    let mut response = scim_json(status, &body);
+    if status == StatusCode::UNAUTHORIZED {
+        response.headers_mut().insert(
+            header::WWW_AUTHENTICATE,
+            HeaderValue::from_static("Bearer realm=\"scim\""),
+        );
+    }
+    response
+}
+
+/// Record the outcome of a SCIM operation and map errors to SCIM error responses
+fn finish(operation: &str, result: Result<Response, ScimError>) -> Response {
+    match result {
+        Ok(response) => {
+            monitoring::record_auth_operation(operation, "success");
+            response
+        },
+        Err(err) => {
+            monitoring::record_auth_operation(operation, "failure");
+            scim_error(err, &generate_request_id())
+        },
+    }
+}
+
+/// Handler listing the users of the tenant
+pub async fn list_scim_users(
+    State(state): State<ScimAppState>,
+    headers: HeaderMap,
+    Query(query): Query<ScimQuery>,
+) -> Response {
+    let result = async {
+        let tenant_id = authenticate(&state, &headers).await?;
+        let users = state
+            .scim_service
+            .list_users(tenant_id, query.filter.as_deref(), query.page())
+            .await?;
+        Ok(scim_json(StatusCode::OK, &users))
+    }
+    .await;
+    finish("scim_list_users", result)
+}
+
+/// Handler returning a user of the tenant
+pub async fn get_scim_user(
+    State(state): State<ScimAppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Response {
+    let result = async {
+        let tenant_id = authenticate(&state, &headers).await?;
+        let user = state.scim_service.get_user(tenant_id, &id).await?;
+        let version = user.meta.as_ref().map(|meta| meta.version.as_str());
+        if not_modified(&headers, version) {
+            return Ok(StatusCode::NOT_MODIFIED.into_response());
+        }
+        Ok(user_response(StatusCode::OK, &user))
+    }
+    .await;
+    finish("scim_get_user", result)
+}
+
+/// Handler provisioning a user into the tenant
+pub async fn create_scim_user(
+    State(state): State<ScimAppState>,
+    headers: HeaderMap,
+    Json(resource): Json<ScimUser>,
+) -> Response {
+    let result = async {
+        let tenant_id = authenticate(&state, &headers).await?;
+        let user = state.scim_service.create_user(tenant_id, resource).await?;
+        Ok(user_response(StatusCode::CREATED, &user))
+    }
+    .await;
+    finish("scim_create_user", result)
+}
+
+/// Handler replacing a user of the tenant
+pub async fn replace_scim_user(
+    State(state): State<ScimAppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(resource): Json<ScimUser>,
+) -> Response {
+    let result = async {
+        let tenant_id = authenticate(&state, &headers).await?;
+        let user = state
+            .scim_service
+            .replace_user(tenant_id, &id, resource, if_match(&headers))
+            .await?;
+        Ok(user_response(StatusCode::OK, &user))
+    }
+    .await;
+    finish("scim_replace_user", result)
+}
+
+/// Handler patching a user of the tenant; `active=false` deprovisions the user
+pub async fn patch_scim_user(
+    State(state): State<ScimAppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(patch): Json<ScimPatchRequest>,
+) -> Response {
+    let result = async {
+        let tenant_id = authenticate(&state, &headers).await?;
+        let user = state
+            .scim_service
+            .patch_user(tenant_id, &id, patch, if_match(&headers))
+            .await?;
+        Ok(user_response(StatusCode::OK, &user))
+    }
+    .await;
+    finish("scim_patch_user", result)
+}
+
+/// Handler removing a user from the tenant
+pub async fn delete_scim_user(
+    State(state): State<ScimAppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Response {
+    let result = async {
+        let tenant_id = authenticate(&state, &headers).await?;
+        state
+            .scim_service
+            .delete_user(tenant_id, &id, if_match(&headers))
+            .await?;
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+    .await;
+    finish("scim_delete_user", result)
+}
+
+/// Handler listing the groups of the tenant
+pub async fn list_scim_groups(
+    State(state): State<ScimAppState>,
+    headers: HeaderMap,
+    Query(query): Query<ScimQuery>,
+) -> Response {
+    let result = async {
+        let tenant_id = authenticate(&state, &headers).await?;
+        let groups = state
+            .scim_service
+            .list_groups(tenant_id, query.filter.as_deref(), query.page())
+            .await?;
+        Ok(scim_json(StatusCode::OK, &groups))
+    }
+    .await;
+    finish("scim_list_groups", result)
+}
+
+/// Handler returning a group of the tenant
+pub async fn get_scim_group(
+    State(state): State<ScimAppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Response {
+    let result = async {
+        let tenant_id = authenticate(&state, &headers).await?;
+        let group = state.scim_service.get_group(tenant_id, &id).await?;
+        let version = group.meta.as_ref().map(|meta| meta.version.as_str());
+        if not_modified(&headers, version) {
+            return Ok(StatusCode::NOT_MODIFIED.into_response());
+        }
+        Ok(group_response(StatusCode::OK, &group))
+    }
+    .await;
+    finish("scim_get_group", result)
+}
+
+/// Handler creating a group in the tenant
+pub async fn create_scim_group(
+    State(state): State<ScimAppState>,
+    headers: HeaderMap,
+    Json(resource): Json<ScimGroup>,
+) -> Response {
+    let result = async {
+        let tenant_id = authenticate(&state, &headers).await?;
+        let group = state.scim_service.create_group(tenant_id, resource).await?;
+        Ok(group_response(StatusCode::CREATED, &group))
+    }
+    .await;
+    finish("scim_create_group", result)
+}
+
+/// Handler replacing a group of the tenant
+pub async fn replace_scim_group(
+    State(state): State<ScimAppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(resource): Json<ScimGroup>,
+) -> Response {
+    let result = async {
+        let tenant_id = authenticate(&state, &headers).await?;
+        let group = state
+            .scim_service
+            .replace_group(tenant_id, &id, resource, if_match(&headers))
+            .await?;
+        Ok(group_response(StatusCode::OK, &group))
+    }
+    .await;
+    finish("scim_replace_group", result)
+}
+
+/// Handler patching the members or name of a group
+pub async fn patch_scim_group(
+    State(state): State<ScimAppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(patch): Json<ScimPatchRequest>,
+) -> Response {
+    let result = async {
+        let tenant_id = authenticate(&state, &headers).await?;
+        let group = state
+            .scim_service
+            .patch_group(tenant_id, &id, patch, if_match(&headers))
+            .await?;
+        Ok(group_response(StatusCode::OK, &group))
+    }
+    .await;
+    finish("scim_patch_group", result)
+}
+
+/// Handler deleting a group of the tenant
+pub async fn delete_scim_group(
+    State(state): State<ScimAppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Response {
+    let result = async {
+        let tenant_id = authenticate(&state, &headers).await?;
+        state
+            .scim_service
+            .delete_group(tenant_id, &id, if_match(&headers))
+            .await?;
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+    .await;
+    finish("scim_delete_group", result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scim_errors_use_scim_format() {
+        let response = scim_error(ScimError::Uniqueness, "request");
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static(SCIM_CONTENT_TYPE))
+        );
+
+        let cases = [
+            (ScimError::InvalidToken, StatusCode::UNAUTHORIZED),
+            (ScimError::NotFound, StatusCode::NOT_FOUND),
+            (
+                ScimError::PreconditionFailed,
+                StatusCode::PRECONDITION_FAILED,
+            ),
+            (
+                ScimError::InvalidFilter("userName co \"a\"".to_string()),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                ScimError::Tenant(TenantServiceError::Database("down".to_string())),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        ];
+        for (err, status) in cases {
+            assert_eq!(scim_error(err, "request").status(), status);
+        }
+    }
+
+    #[test]
+    fn test_if_none_match_detects_current_version() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::IF_NONE_MATCH,
+            HeaderValue::from_static(r#"W/"old", W/"abc""#),
+        );
+
+        assert!(not_modified(&headers, Some(r#"W/"abc""#)));
+        assert!(!not_modified(&headers, Some(r#"W/"new""#)));
+        assert!(!not_modified(&HeaderMap::new(), Some(r#"W/"abc""#)));
+    }
+}
@@ -0,0 +1,246 @@
+//! Tracing administration endpoints
+//!
+//! Tenant admins can temporarily raise the sampling rate of their tenant's
+//! requests, e.g. to trace everything while debugging an issue.
+
+use crate::middleware::auth::{AuthExtractorState, AuthenticatedUser};
+use crate::monitoring;
+use crate::response::{ApiError, ApiResponse};
+use crate::validation::{generate_request_id, validate_json_payload};
+use axum::{
+    extract::{FromRef, Json, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, error, info};
+use uuid::Uuid;
+use validator::Validate;
+
+use acci_auth::services::session::SessionService;
+use acci_core::telemetry::{RedisSamplingOverrideStore, SamplingOverride, SamplingOverrides};
+
+/// Default lifetime of a sampling override
+const DEFAULT_OVERRIDE_SECS: u64 = 30 * 60;
+
+/// Tracing Administration Application State
+#[derive(Clone)]
+pub struct TracingAdminAppState {
+    /// Overrides consulted by the installed sampler
+    pub overrides: Arc<SamplingOverrides>,
+    /// Shares overrides with other instances; local only if not set
+    pub override_store: Option<Arc<RedisSamplingOverrideStore>>,
+    /// Session service for authenticating the admin
+    pub session_service: Arc<SessionService>,
+}
+
+impl FromRef<TracingAdminAppState> for AuthExtractorState {
+    fn from_ref(state: &TracingAdminAppState) -> Self {
+        Self {
+            session_service: state.session_service.clone(),
+            tenant_service: None,
+            jwt_utils: None,
+        }
+    }
+}
+
+/// Sampling override request DTO
+#[derive(Debug, Deserialize, Validate)]
+pub struct TracingOverrideRequest {
+    /// Tenant whose requests are sampled at `rate`
+    pub tenant_id: Uuid,
+    /// Fraction of requests to sample; defaults to all of them
+    #[validate(range(min = 0.0, max = 1.0, message = "Rate must be between 0 and 1"))]
+    pub rate: Option<f64>,
+    /// How long the override lasts in seconds; defaults to 30 minutes
+    #[validate(range(min = 1, max = 86400, message = "Duration must be 1-86400 seconds"))]
+    pub duration_secs: Option<u64>,
+}
+
+/// Sampling override response DTO
+#[derive(Debug, Serialize)]
+pub struct TracingOverrideResponse {
+    pub tenant_id: String,
+    pub rate: f64,
+    /// Unix timestamp at which the override ends
+    pub expires_at: u64,
+}
+
+/// Handler for raising the sampling rate of a tenant
+///
+/// Requires the `ADMIN` role in the tenant.
+#[axum::debug_handler]
+pub async fn set_tracing_override(
+    State(state): State<TracingAdminAppState>,
+    user: AuthenticatedUser,
+    Json(request): Json<TracingOverrideRequest>,
+) -> Response {
+    debug!("Processing tracing override request");
+    let request_id = generate_request_id();
+
+    let validated = match validate_json_payload(Json(request)).await {
+        Ok(data) => data,
+        Err(validation_error) => return validation_error.into_response(),
+    };
+
+    if !user.has_tenant_role(validated.tenant_id, "ADMIN") {
+        return ApiError::new(
+            StatusCode::FORBIDDEN,
+            "Only tenant admins can change tracing for the tenant",
+            "TENANT_ACCESS_DENIED",
+            request_id,
+        )
+        .into_response();
+    }
+
+    let tenant_id = validated.tenant_id.to_string();
+    let rate = validated.rate.unwrap_or(1.0);
+    let ttl = Duration::from_secs(validated.duration_secs.unwrap_or(DEFAULT_OVERRIDE_SECS));
+    let applied = match &state.override_store {
+        Some(store) => store.set_override(&tenant_id, rate, ttl).await,
+        None => {
+            let expires_at = SystemTime::now() + ttl;
+            state.overrides.set(&tenant_id, rate, expires_at);
+            Ok(SamplingOverride { rate, expires_at })
+        },
+    };
+
+    match applied {
+        Ok(applied) => {
+            info!(
+                request_id = %request_id,
+                user_id = %user.user_id,
+                tenant_id = %tenant_id,
+                rate = applied.rate,
+                ttl_secs = ttl.as_secs(),
+                "Tracing sampling override set"
+            );
+
+            let response = TracingOverrideResponse {
+                tenant_id,
+                rate: applied.rate,
+                expires_at: applied
+                    .expires_at
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs(),
+            };
+            let api_response = ApiResponse::success(response, request_id);
+            (StatusCode::OK, Json(api_response)).into_response()
+        },
+        Err(e) => {
+            error!("Failed to store tracing override: {}", e);
+            monitoring::record_api_error("redis", "TRACING_OVERRIDE_ERROR", 500);
+            ApiError::internal_server_error(request_id).into_response()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acci_auth::session::types::MfaStatus;
+    use acci_auth::{AuthConfig, TenantUser, session::mock::MockSessionRepository};
+    use time::OffsetDateTime;
+
+    fn state() -> TracingAdminAppState {
+        let session_repo = Arc::new(MockSessionRepository::new());
+        TracingAdminAppState {
+            overrides: Arc::new(SamplingOverrides::new()),
+            override_store: None,
+            session_service: Arc::new(SessionService::new(
+                session_repo,
+                Arc::new(AuthConfig::default()),
+            )),
+        }
+    }
+
+    fn user(tenant_id: Uuid, role: &str) -> AuthenticatedUser {
+        let now = OffsetDateTime::now_utc();
+        AuthenticatedUser {
+            user_id: Uuid::new_v4(),
+            session_id: Uuid::new_v4(),
+            tenant_id: Some(tenant_id),
+            mfa_status: MfaStatus::None,
+            memberships: vec![TenantUser {
+                tenant_id,
+                user_id: Uuid::new_v4(),
+                tenant_role: role.to_string(),
+                is_active: true,
+                created_at: now,
+                updated_at: now,
+            }],
+            claims: None,
+            custom_claims: Default::default(),
+            last_auth_at: now,
+            expires_at: now + Duration::from_secs(3600),
+            impersonated_by: None,
+        }
+    }
+
+    fn request(tenant_id: Uuid) -> Json<TracingOverrideRequest> {
+        Json(TracingOverrideRequest {
+            tenant_id,
+            rate: None,
+            duration_secs: Some(600),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_tenant_admin_sets_override() {
+        let state = state();
+        let tenant_id = Uuid::new_v4();
+
+        let response = set_tracing_override(
+            State(state.clone()),
+            user(tenant_id, "ADMIN"),
+            request(tenant_id),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let now = SystemTime::now();
+        assert_eq!(
+            state.overrides.rate_for(&tenant_id.to_string(), now),
+            Some(1.0)
+        );
+        assert_eq!(
+            state
+                .overrides
+                .rate_for(&tenant_id.to_string(), now + Duration::from_secs(601)),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_members_and_other_tenants_are_rejected() {
+        let state = state();
+        let tenant_id = Uuid::new_v4();
+        let other_tenant = Uuid::new_v4();
+
+        let response = set_tracing_override(
+            State(state.clone()),
+            user(tenant_id, "MEMBER"),
+            request(tenant_id),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = set_tracing_override(
+            State(state.clone()),
+            user(tenant_id, "ADMIN"),
+            request(other_tenant),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let now = SystemTime::now();
+        assert_eq!(state.overrides.rate_for(&tenant_id.to_string(), now), None);
+        assert_eq!(
+            state.overrides.rate_for(&other_tenant.to_string(), now),
+            None
+        );
+    }
+}
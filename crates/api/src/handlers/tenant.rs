@@ -1,10 +1,12 @@
+use crate::config::CacheConfig;
+use crate::middleware::auth::{AuthExtractorState, AuthenticatedUser};
 use crate::middleware::tenant::TenantContext;
 use crate::monitoring;
-use crate::response::{ApiError, ApiResponse};
-use crate::validation::{generate_request_id, validate_json_payload};
+use crate::response::{ApiError, ApiResponse, maybe_not_modified, weak_etag, with_private_cache};
+use crate::validation::{bounded_metadata, generate_request_id, validate_json_payload};
 use axum::{
-    extract::{Extension, Json, Path, State},
-    http::StatusCode,
+    extract::{Extension, FromRef, Json, Path, Query, State},
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
 };
 // lazy_static is imported in the regex module below
@@ -15,8 +17,9 @@ use uuid::Uuid;
 use validator::Validate;
 
 use acci_auth::{
-    CreateTenantDto, CreateTenantWithAdminDto, TenantPlanType, TenantService, TenantServiceError,
-    UpdateTenantDto, utils::jwt::Claims,
+    CreateTenantDto, CreateTenantWithAdminDto, SUBSCRIPTION_HISTORY_PAGE_SIZE, SensitiveOperation,
+    SubscriptionHistoryEntry, TenantPlanType, TenantSecurityPolicy, TenantService,
+    UnknownCountryAction, UpdateTenantDto, services::session::SessionService,
 };
 
 /// Module with regex patterns
@@ -38,6 +41,24 @@ pub mod regex {
 pub struct TenantAppState {
     /// Tenant service for tenant management
     pub tenant_service: Arc<TenantService>,
+    /// Session service for authenticating requests
+    pub session_service: Arc<SessionService>,
+}
+
+impl FromRef<TenantAppState> for Arc<SessionService> {
+    fn from_ref(state: &TenantAppState) -> Self {
+        state.session_service.clone()
+    }
+}
+
+impl FromRef<TenantAppState> for AuthExtractorState {
+    fn from_ref(state: &TenantAppState) -> Self {
+        Self {
+            session_service: state.session_service.clone(),
+            tenant_service: Some(state.tenant_service.clone()),
+            jwt_utils: None,
+        }
+    }
 }
 
 /// Create tenant request DTO
@@ -57,6 +78,7 @@ pub struct CreateTenantRequest {
     ))]
     pub subdomain: String,
 
+    /// Checked against the metadata limits after the other fields
     pub metadata: Option<serde_json::Value>,
 }
 
@@ -102,56 +124,12 @@ pub struct TenantResponse {
     pub metadata: Option<serde_json::Value>,
 }
 
-/// Helper function to map tenant errors to API responses
-fn map_tenant_error(err: &TenantServiceError) -> (StatusCode, &str, &str) {
-    match err {
-        TenantServiceError::NotFound(_) => (
-            StatusCode::NOT_FOUND,
-            "Tenant not found",
-            "TENANT_NOT_FOUND",
-        ),
-        TenantServiceError::Tenant(tenant_err) => match tenant_err {
-            acci_auth::TenantError::AlreadyExists => (
-                StatusCode::CONFLICT,
-                "Tenant with this subdomain already exists",
-                "TENANT_ALREADY_EXISTS",
-            ),
-            acci_auth::TenantError::ValidationError(_) => (
-                StatusCode::BAD_REQUEST,
-                "Invalid tenant data",
-                "INVALID_TENANT_DATA",
-            ),
-            _ => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "An error occurred with the tenant",
-                "TENANT_ERROR",
-            ),
-        },
-        TenantServiceError::InvalidInput(_) => (
-            StatusCode::BAD_REQUEST,
-            "Invalid input data",
-            "INVALID_INPUT",
-        ),
-        TenantServiceError::User(_) => (StatusCode::CONFLICT, "User error occurred", "USER_ERROR"),
-        TenantServiceError::Password(_) => (
-            StatusCode::BAD_REQUEST,
-            "Password does not meet security requirements",
-            "WEAK_PASSWORD",
-        ),
-        _ => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "An internal error occurred",
-            "INTERNAL_ERROR",
-        ),
-    }
-}
-
 /// Create tenant handler
 #[axum::debug_handler]
 pub async fn create_tenant(
     State(state): State<TenantAppState>,
     Json(request): Json<CreateTenantRequest>,
-) -> Response {
+) -> Result<Response, ApiError> {
     debug!("Processing create tenant request");
     let start = std::time::Instant::now();
 
@@ -162,68 +140,75 @@ pub async fn create_tenant(
     let validated = match validate_json_payload(Json(request)).await {
         Ok(data) => data,
         Err(validation_error) => {
-            return validation_error.into_response();
+            return Ok(validation_error.into_response());
         },
     };
 
     // Validate the subdomain
     if let Err(message) = validated.validate_subdomain() {
-        return ApiResponse::<()>::error(message, "INVALID_SUBDOMAIN", request_id).into_response();
+        return Ok(
+            ApiResponse::<()>::error(message, "INVALID_SUBDOMAIN", request_id).into_response(),
+        );
     }
 
+    let metadata = match bounded_metadata("metadata", validated.metadata) {
+        Ok(metadata) => metadata,
+        Err(validation_error) => {
+            return Ok(validation_error.into_response());
+        },
+    };
+
     // Convert to domain DTO
     let create_tenant = CreateTenantDto {
         name: validated.name,
         subdomain: validated.subdomain,
-        metadata: validated.metadata,
+        metadata,
     };
 
     // Create tenant
-    match state.tenant_service.create_tenant(create_tenant).await {
-        Ok(tenant) => {
-            // Record success
-            monitoring::record_tenant_operation("create", "success");
-
-            // Record duration
-            let duration = start.elapsed();
-            monitoring::record_request_duration(duration.as_secs_f64(), "POST", "/tenants");
-
-            // Successful creation
-            let response = TenantResponse {
-                id: tenant.id.to_string(),
-                name: tenant.name,
-                subdomain: tenant.subdomain,
-                is_active: tenant.is_active,
-                created_at: tenant.created_at.to_string(),
-                updated_at: tenant.updated_at.to_string(),
-                metadata: tenant.metadata,
-            };
-
-            info!(
-                request_id = %request_id,
-                tenant_id = %tenant.id,
-                "Tenant created successfully"
-            );
-
-            let api_response = ApiResponse::success(response, request_id);
-            (StatusCode::CREATED, Json(api_response)).into_response()
-        },
-        Err(err) => {
+    let tenant = state
+        .tenant_service
+        .create_tenant(create_tenant)
+        .await
+        .map_err(|err| {
             // Record failure
             monitoring::record_tenant_operation("create", "failure");
 
-            // Map error to appropriate response
-            let (status, message, code) = map_tenant_error(&err);
-
             warn!(
                 request_id = %request_id,
                 error = %err,
                 "Tenant creation failed"
             );
 
-            ApiError::new(status, message, code, request_id).into_response()
-        },
-    }
+            ApiError::from_error(err, request_id.clone())
+        })?;
+
+    // Record success
+    monitoring::record_tenant_operation("create", "success");
+
+    // Record duration
+    let duration = start.elapsed();
+    monitoring::record_request_duration(duration.as_secs_f64(), "POST", "/tenants");
+
+    // Successful creation
+    let response = TenantResponse {
+        id: tenant.id.to_string(),
+        name: tenant.name,
+        subdomain: tenant.subdomain,
+        is_active: !tenant.status.is_archived(),
+        created_at: tenant.created_at.to_string(),
+        updated_at: tenant.updated_at.to_string(),
+        metadata: tenant.metadata,
+    };
+
+    info!(
+        request_id = %request_id,
+        tenant_id = %tenant.id,
+        "Tenant created successfully"
+    );
+
+    let api_response = ApiResponse::success(response, request_id);
+    Ok((StatusCode::CREATED, Json(api_response)).into_response())
 }
 
 /// Create tenant with admin handler
@@ -231,7 +216,7 @@ pub async fn create_tenant(
 pub async fn create_tenant_with_admin(
     State(state): State<TenantAppState>,
     Json(request): Json<CreateTenantWithAdminRequest>,
-) -> Response {
+) -> Result<Response, ApiError> {
     debug!("Processing create tenant with admin request");
     let start = std::time::Instant::now();
 
@@ -242,7 +227,7 @@ pub async fn create_tenant_with_admin(
     let validated = match validate_json_payload(Json(request)).await {
         Ok(data) => data,
         Err(validation_error) => {
-            return validation_error.into_response();
+            return Ok(validation_error.into_response());
         },
     };
 
@@ -261,18 +246,25 @@ pub async fn create_tenant_with_admin(
                     "INVALID_PLAN_TYPE",
                     request_id,
                 );
-                return error.into_response();
+                return Err(error);
             },
         }
     } else {
         Some(TenantPlanType::Free) // Default to free plan
     };
 
+    let metadata = match bounded_metadata("tenant.metadata", validated.tenant.metadata) {
+        Ok(metadata) => metadata,
+        Err(validation_error) => {
+            return Ok(validation_error.into_response());
+        },
+    };
+
     // Convert to domain DTO
     let create_tenant = CreateTenantDto {
         name: validated.tenant.name,
         subdomain: validated.tenant.subdomain,
-        metadata: validated.tenant.metadata,
+        metadata,
     };
 
     let create_dto = CreateTenantWithAdminDto {
@@ -283,78 +275,73 @@ pub async fn create_tenant_with_admin(
     };
 
     // Create tenant with admin
-    match state
+    let result = state
         .tenant_service
         .create_tenant_with_admin(create_dto)
         .await
-    {
-        Ok(result) => {
-            // Record success
-            monitoring::record_tenant_operation("create_with_admin", "success");
-
-            // Record duration
-            let duration = start.elapsed();
-            monitoring::record_request_duration(
-                duration.as_secs_f64(),
-                "POST",
-                "/tenants/with-admin",
-            );
-
-            // Construct response
-            let tenant_response = TenantResponse {
-                id: result.tenant.id.to_string(),
-                name: result.tenant.name,
-                subdomain: result.tenant.subdomain,
-                is_active: result.tenant.is_active,
-                created_at: result.tenant.created_at.to_string(),
-                updated_at: result.tenant.updated_at.to_string(),
-                metadata: result.tenant.metadata,
-            };
-
-            // Structure response data
-            #[allow(clippy::disallowed_methods)]
-            let response_data = serde_json::json!({
-                "tenant": tenant_response,
-                "admin_user_id": result.admin_user.id.to_string(),
-                "admin_email": result.admin_user.email,
-                "has_subscription": result.subscription.is_some(),
-                "subscription_plan": result.subscription.map(|s| format!("{:?}", s.plan_type)),
-            });
-
-            info!(
-                request_id = %request_id,
-                tenant_id = %result.tenant.id,
-                user_id = %result.admin_user.id,
-                "Tenant with admin created successfully"
-            );
-
-            let api_response = ApiResponse::success(response_data, request_id);
-            (StatusCode::CREATED, Json(api_response)).into_response()
-        },
-        Err(err) => {
+        .map_err(|err| {
             // Record failure
             monitoring::record_tenant_operation("create_with_admin", "failure");
 
-            // Map error to appropriate response
-            let (status, message, code) = map_tenant_error(&err);
-
             warn!(
                 request_id = %request_id,
                 error = %err,
                 "Tenant with admin creation failed"
             );
 
-            ApiError::new(status, message, code, request_id).into_response()
-        },
-    }
+            ApiError::from_error(err, request_id.clone())
+        })?;
+
+    // Record success
+    monitoring::record_tenant_operation("create_with_admin", "success");
+
+    // Record duration
+    let duration = start.elapsed();
+    monitoring::record_request_duration(duration.as_secs_f64(), "POST", "/tenants/with-admin");
+
+    // Construct response
+    let tenant_response = TenantResponse {
+        id: result.tenant.id.to_string(),
+        name: result.tenant.name,
+        subdomain: result.tenant.subdomain,
+        is_active: !result.tenant.status.is_archived(),
+        created_at: result.tenant.created_at.to_string(),
+        updated_at: result.tenant.updated_at.to_string(),
+        metadata: result.tenant.metadata,
+    };
+
+    // Structure response data
+    #[allow(clippy::disallowed_methods)]
+    let response_data = serde_json::json!({
+        "tenant": tenant_response,
+        "admin_user_id": result.admin_user.id.to_string(),
+        "admin_email": result.admin_user.email,
+        "has_subscription": result.subscription.is_some(),
+        "subscription_plan": result.subscription.map(|s| format!("{:?}", s.plan_type)),
+    });
+
+    info!(
+        request_id = %request_id,
+        tenant_id = %result.tenant.id,
+        user_id = %result.admin_user.id,
+        "Tenant with admin created successfully"
+    );
+
+    let api_response = ApiResponse::success(response_data, request_id);
+    Ok((StatusCode::CREATED, Json(api_response)).into_response())
 }
 
 /// Get tenant handler
+///
+/// Clients may keep the response for the configured `max-age` and revalidate
+/// it with `If-None-Match`; the ETag changes with every tenant update.
 #[axum::debug_handler]
 pub async fn get_tenant(
     State(state): State<TenantAppState>,
     Extension(tenant_context): Extension<TenantContext>,
-) -> Response {
+    Extension(cache): Extension<CacheConfig>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
     debug!("Processing get tenant request");
     let start = std::time::Instant::now();
 
@@ -365,42 +352,14 @@ pub async fn get_tenant(
     let tenant_id = tenant_context.id;
 
     // Get tenant details
-    match state.tenant_service.get_tenant(&tenant_id).await {
-        Ok(tenant) => {
-            // Record success
-            monitoring::record_tenant_operation("get", "success");
-
-            // Record duration
-            let duration = start.elapsed();
-            monitoring::record_request_duration(duration.as_secs_f64(), "GET", "/tenant");
-
-            // Successful retrieval
-            let response = TenantResponse {
-                id: tenant.id.to_string(),
-                name: tenant.name,
-                subdomain: tenant.subdomain,
-                is_active: tenant.is_active,
-                created_at: tenant.created_at.to_string(),
-                updated_at: tenant.updated_at.to_string(),
-                metadata: tenant.metadata,
-            };
-
-            debug!(
-                request_id = %request_id,
-                tenant_id = %tenant.id,
-                "Tenant retrieved successfully"
-            );
-
-            let api_response = ApiResponse::success(response, request_id);
-            (StatusCode::OK, Json(api_response)).into_response()
-        },
-        Err(err) => {
+    let tenant = state
+        .tenant_service
+        .get_tenant(&tenant_id)
+        .await
+        .map_err(|err| {
             // Record failure
             monitoring::record_tenant_operation("get", "failure");
 
-            // Map error to appropriate response
-            let (status, message, code) = map_tenant_error(&err);
-
             warn!(
                 request_id = %request_id,
                 error = %err,
@@ -408,18 +367,56 @@ pub async fn get_tenant(
                 "Tenant retrieval failed"
             );
 
-            ApiError::new(status, message, code, request_id).into_response()
-        },
+            ApiError::from_error(err, request_id.clone())
+        })?;
+
+    // Record success
+    monitoring::record_tenant_operation("get", "success");
+
+    // Record duration
+    let duration = start.elapsed();
+    monitoring::record_request_duration(duration.as_secs_f64(), "GET", "/tenant");
+
+    let etag = weak_etag(&(tenant.id, tenant.updated_at));
+    if let Some(not_modified) = maybe_not_modified(&etag, &headers) {
+        debug!(request_id = %request_id, tenant_id = %tenant.id, "Tenant not modified");
+        return Ok(with_private_cache(not_modified, &etag, &cache));
     }
+
+    // Successful retrieval
+    let response = TenantResponse {
+        id: tenant.id.to_string(),
+        name: tenant.name,
+        subdomain: tenant.subdomain,
+        is_active: !tenant.status.is_archived(),
+        created_at: tenant.created_at.to_string(),
+        updated_at: tenant.updated_at.to_string(),
+        metadata: tenant.metadata,
+    };
+
+    debug!(
+        request_id = %request_id,
+        tenant_id = %tenant.id,
+        "Tenant retrieved successfully"
+    );
+
+    let api_response = ApiResponse::success(response, request_id);
+    Ok(with_private_cache(
+        (StatusCode::OK, Json(api_response)).into_response(),
+        &etag,
+        &cache,
+    ))
 }
 
-/// Get tenant by ID handler (admin operation)
+/// Get tenant by ID handler
+///
+/// Only members of the tenant may read it.
 #[axum::debug_handler]
 pub async fn get_tenant_by_id(
     State(state): State<TenantAppState>,
+    user: AuthenticatedUser,
     Path(tenant_id): Path<String>,
-    Extension(_claims): Extension<Claims>,
-) -> Response {
+) -> Result<Response, ApiError> {
     debug!("Processing get tenant by ID request");
     let start = std::time::Instant::now();
 
@@ -430,53 +427,35 @@ pub async fn get_tenant_by_id(
     let tenant_id = match Uuid::parse_str(&tenant_id) {
         Ok(id) => id,
         Err(_) => {
-            return ApiError::new(
+            return Err(ApiError::new(
                 StatusCode::BAD_REQUEST,
                 "Invalid tenant ID format",
                 "INVALID_TENANT_ID",
                 request_id,
-            )
-            .into_response();
+            ));
         },
     };
 
-    // Get tenant details
-    match state.tenant_service.get_tenant(&tenant_id).await {
-        Ok(tenant) => {
-            // Record success
-            monitoring::record_tenant_operation("get_by_id", "success");
-
-            // Record duration
-            let duration = start.elapsed();
-            monitoring::record_request_duration(duration.as_secs_f64(), "GET", "/tenants/:id");
-
-            // Successful retrieval
-            let response = TenantResponse {
-                id: tenant.id.to_string(),
-                name: tenant.name,
-                subdomain: tenant.subdomain,
-                is_active: tenant.is_active,
-                created_at: tenant.created_at.to_string(),
-                updated_at: tenant.updated_at.to_string(),
-                metadata: tenant.metadata,
-            };
-
-            debug!(
-                request_id = %request_id,
-                tenant_id = %tenant.id,
-                "Tenant retrieved successfully by ID"
-            );
+    if !user.is_member_of(tenant_id) {
+        monitoring::record_tenant_operation("get_by_id", "failure");
+        warn!(
+            request_id = %request_id,
+            user_id = %user.user_id,
+            tenant_id = %tenant_id,
+            "User is not a member of the tenant"
+        );
+        return Err(ApiError::authorization_error(request_id));
+    }
 
-            let api_response = ApiResponse::success(response, request_id);
-            (StatusCode::OK, Json(api_response)).into_response()
-        },
-        Err(err) => {
+    // Get tenant details
+    let tenant = state
+        .tenant_service
+        .get_tenant(&tenant_id)
+        .await
+        .map_err(|err| {
             // Record failure
             monitoring::record_tenant_operation("get_by_id", "failure");
 
-            // Map error to appropriate response
-            let (status, message, code) = map_tenant_error(&err);
-
             warn!(
                 request_id = %request_id,
                 error = %err,
@@ -484,12 +463,40 @@ pub async fn get_tenant_by_id(
                 "Tenant retrieval by ID failed"
             );
 
-            ApiError::new(status, message, code, request_id).into_response()
-        },
-    }
+            ApiError::from_error(err, request_id.clone())
+        })?;
+
+    // Record success
+    monitoring::record_tenant_operation("get_by_id", "success");
+
+    // Record duration
+    let duration = start.elapsed();
+    monitoring::record_request_duration(duration.as_secs_f64(), "GET", "/tenants/:id");
+
+    // Successful retrieval
+    let response = TenantResponse {
+        id: tenant.id.to_string(),
+        name: tenant.name,
+        subdomain: tenant.subdomain,
+        is_active: !tenant.status.is_archived(),
+        created_at: tenant.created_at.to_string(),
+        updated_at: tenant.updated_at.to_string(),
+        metadata: tenant.metadata,
+    };
+
+    debug!(
+        request_id = %request_id,
+        tenant_id = %tenant.id,
+        "Tenant retrieved successfully by ID"
+    );
+
+    let api_response = ApiResponse::success(response, request_id);
+    Ok((StatusCode::OK, Json(api_response)).into_response())
 }
 
 /// Update tenant request DTO
+///
+/// A JSON Merge Patch: omitted fields are left unchanged.
 #[derive(Debug, Deserialize, Validate)]
 pub struct UpdateTenantRequest {
     #[validate(length(
@@ -506,9 +513,13 @@ pub struct UpdateTenantRequest {
     ))]
     pub subdomain: Option<String>,
 
-    pub is_active: Option<bool>,
-
-    pub metadata: Option<serde_json::Value>,
+    /// Omitted leaves the metadata unchanged, `null` clears it; checked
+    /// against the metadata limits after the other fields
+    #[serde(
+        default,
+        deserialize_with = "acci_auth::utils::patch::deserialize_nullable"
+    )]
+    pub metadata: Option<Option<serde_json::Value>>,
 }
 
 impl UpdateTenantRequest {
@@ -528,7 +539,7 @@ pub async fn update_tenant(
     State(state): State<TenantAppState>,
     Extension(tenant_context): Extension<TenantContext>,
     Json(request): Json<UpdateTenantRequest>,
-) -> Response {
+) -> Result<Response, ApiError> {
     debug!("Processing update tenant request");
     let start = std::time::Instant::now();
 
@@ -539,15 +550,28 @@ pub async fn update_tenant(
     let validated = match validate_json_payload(Json(request)).await {
         Ok(data) => data,
         Err(validation_error) => {
-            return validation_error.into_response();
+            return Ok(validation_error.into_response());
         },
     };
 
     // Validate the subdomain if provided
     if let Err(message) = validated.validate_subdomain() {
-        return ApiResponse::<()>::error(message, "INVALID_SUBDOMAIN", request_id).into_response();
+        return Ok(
+            ApiResponse::<()>::error(message, "INVALID_SUBDOMAIN", request_id).into_response(),
+        );
     }
 
+    let metadata = match validated
+        .metadata
+        .map(|metadata| bounded_metadata("metadata", metadata))
+        .transpose()
+    {
+        Ok(metadata) => metadata,
+        Err(validation_error) => {
+            return Ok(validation_error.into_response());
+        },
+    };
+
     // Get tenant ID from context
     let tenant_id = tenant_context.id;
 
@@ -555,51 +579,18 @@ pub async fn update_tenant(
     let update_tenant = UpdateTenantDto {
         name: validated.name,
         subdomain: validated.subdomain,
-        is_active: validated.is_active,
-        metadata: validated.metadata,
+        metadata,
     };
 
     // Update tenant
-    match state
+    let tenant = state
         .tenant_service
         .update_tenant(&tenant_id, update_tenant)
         .await
-    {
-        Ok(tenant) => {
-            // Record success
-            monitoring::record_tenant_operation("update", "success");
-
-            // Record duration
-            let duration = start.elapsed();
-            monitoring::record_request_duration(duration.as_secs_f64(), "PUT", "/tenant");
-
-            // Successful update
-            let response = TenantResponse {
-                id: tenant.id.to_string(),
-                name: tenant.name,
-                subdomain: tenant.subdomain,
-                is_active: tenant.is_active,
-                created_at: tenant.created_at.to_string(),
-                updated_at: tenant.updated_at.to_string(),
-                metadata: tenant.metadata,
-            };
-
-            info!(
-                request_id = %request_id,
-                tenant_id = %tenant.id,
-                "Tenant updated successfully"
-            );
-
-            let api_response = ApiResponse::success(response, request_id);
-            (StatusCode::OK, Json(api_response)).into_response()
-        },
-        Err(err) => {
+        .map_err(|err| {
             // Record failure
             monitoring::record_tenant_operation("update", "failure");
 
-            // Map error to appropriate response
-            let (status, message, code) = map_tenant_error(&err);
-
             warn!(
                 request_id = %request_id,
                 error = %err,
@@ -607,18 +598,46 @@ pub async fn update_tenant(
                 "Tenant update failed"
             );
 
-            ApiError::new(status, message, code, request_id).into_response()
-        },
-    }
+            ApiError::from_error(err, request_id.clone())
+        })?;
+
+    // Record success
+    monitoring::record_tenant_operation("update", "success");
+
+    // Record duration
+    let duration = start.elapsed();
+    monitoring::record_request_duration(duration.as_secs_f64(), "PUT", "/tenant");
+
+    // Successful update
+    let response = TenantResponse {
+        id: tenant.id.to_string(),
+        name: tenant.name,
+        subdomain: tenant.subdomain,
+        is_active: !tenant.status.is_archived(),
+        created_at: tenant.created_at.to_string(),
+        updated_at: tenant.updated_at.to_string(),
+        metadata: tenant.metadata,
+    };
+
+    info!(
+        request_id = %request_id,
+        tenant_id = %tenant.id,
+        "Tenant updated successfully"
+    );
+
+    let api_response = ApiResponse::success(response, request_id);
+    Ok((StatusCode::OK, Json(api_response)).into_response())
 }
 
 /// Delete tenant handler (admin operation)
+///
+/// Requires the `ADMIN` role in the tenant.
 #[axum::debug_handler]
 pub async fn delete_tenant(
     State(state): State<TenantAppState>,
+    user: AuthenticatedUser,
     Path(tenant_id): Path<String>,
-    Extension(_claims): Extension<Claims>,
-) -> Response {
+) -> Result<Response, ApiError> {
     debug!("Processing delete tenant request");
     let start = std::time::Instant::now();
 
@@ -629,52 +648,350 @@ pub async fn delete_tenant(
     let tenant_id = match Uuid::parse_str(&tenant_id) {
         Ok(id) => id,
         Err(_) => {
-            return ApiError::new(
+            return Err(ApiError::new(
                 StatusCode::BAD_REQUEST,
                 "Invalid tenant ID format",
                 "INVALID_TENANT_ID",
                 request_id,
-            )
-            .into_response();
+            ));
         },
     };
 
-    // Delete tenant
-    match state.tenant_service.delete_tenant(&tenant_id).await {
-        Ok(_) => {
-            // Record success
-            monitoring::record_tenant_operation("delete", "success");
+    if !user.has_tenant_role(tenant_id, "ADMIN") {
+        monitoring::record_tenant_operation("delete", "failure");
+        warn!(
+            request_id = %request_id,
+            user_id = %user.user_id,
+            tenant_id = %tenant_id,
+            "User is not an administrator of the tenant"
+        );
+        return Err(ApiError::authorization_error(request_id));
+    }
 
-            // Record duration
-            let duration = start.elapsed();
-            monitoring::record_request_duration(duration.as_secs_f64(), "DELETE", "/tenants/:id");
+    if let Err(err) =
+        user.require_recent_auth(&state.session_service, SensitiveOperation::DeleteTenant)
+    {
+        monitoring::record_tenant_operation("delete", "failure");
+        return Err(err);
+    }
 
-            info!(
+    // Delete tenant
+    state
+        .tenant_service
+        .delete_tenant(&tenant_id)
+        .await
+        .map_err(|err| {
+            // Record failure
+            monitoring::record_tenant_operation("delete", "failure");
+
+            warn!(
                 request_id = %request_id,
+                error = %err,
                 tenant_id = %tenant_id,
-                "Tenant deleted successfully"
+                "Tenant deletion failed"
             );
 
-            let api_response = ApiResponse::success(true, request_id);
-            (StatusCode::OK, Json(api_response)).into_response()
+            ApiError::from_error(err, request_id.clone())
+        })?;
+
+    // Record success
+    monitoring::record_tenant_operation("delete", "success");
+
+    // Record duration
+    let duration = start.elapsed();
+    monitoring::record_request_duration(duration.as_secs_f64(), "DELETE", "/tenants/:id");
+
+    info!(
+        request_id = %request_id,
+        tenant_id = %tenant_id,
+        user_id = %user.user_id,
+        impersonated_by = ?user.impersonated_by,
+        "Tenant deleted successfully"
+    );
+
+    let api_response = ApiResponse::success(true, request_id);
+    Ok((StatusCode::OK, Json(api_response)).into_response())
+}
+
+/// Query parameters of the subscription history endpoint
+#[derive(Debug, Deserialize)]
+pub struct SubscriptionHistoryQuery {
+    /// Page number, starting at 1
+    #[serde(default = "first_page")]
+    pub page: u32,
+}
+
+fn first_page() -> u32 {
+    1
+}
+
+/// Subscription history response DTO
+#[derive(Debug, Serialize)]
+pub struct SubscriptionHistoryResponse {
+    pub entries: Vec<SubscriptionHistoryEntry>,
+    pub page: u32,
+    pub page_size: u32,
+}
+
+/// Get subscription history handler
+///
+/// Requires the `ADMIN` role in the tenant. Entries are listed newest first.
+#[axum::debug_handler]
+pub async fn get_subscription_history(
+    State(state): State<TenantAppState>,
+    user: AuthenticatedUser,
+    Path(tenant_id): Path<String>,
+    Query(query): Query<SubscriptionHistoryQuery>,
+) -> Result<Response, ApiError> {
+    debug!("Processing get subscription history request");
+    let start = std::time::Instant::now();
+
+    // Generate a unique request ID
+    let request_id = generate_request_id();
+
+    // Parse tenant ID
+    let tenant_id = match Uuid::parse_str(&tenant_id) {
+        Ok(id) => id,
+        Err(_) => {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "Invalid tenant ID format",
+                "INVALID_TENANT_ID",
+                request_id,
+            ));
         },
-        Err(err) => {
-            // Record failure
-            monitoring::record_tenant_operation("delete", "failure");
+    };
 
-            // Map error to appropriate response
-            let (status, message, code) = map_tenant_error(&err);
+    if !user.has_tenant_role(tenant_id, "ADMIN") {
+        monitoring::record_tenant_operation("subscription_history", "failure");
+        warn!(
+            request_id = %request_id,
+            user_id = %user.user_id,
+            tenant_id = %tenant_id,
+            "User is not an administrator of the tenant"
+        );
+        return Err(ApiError::authorization_error(request_id));
+    }
+
+    let entries = state
+        .tenant_service
+        .get_subscription_history(&tenant_id, query.page)
+        .await
+        .map_err(|err| {
+            monitoring::record_tenant_operation("subscription_history", "failure");
 
             warn!(
                 request_id = %request_id,
                 error = %err,
                 tenant_id = %tenant_id,
-                "Tenant deletion failed"
+                "Subscription history retrieval failed"
             );
 
-            ApiError::new(status, message, code, request_id).into_response()
+            ApiError::from_error(err, request_id.clone())
+        })?;
+
+    monitoring::record_tenant_operation("subscription_history", "success");
+
+    let duration = start.elapsed();
+    monitoring::record_request_duration(
+        duration.as_secs_f64(),
+        "GET",
+        "/tenants/:id/subscription/history",
+    );
+
+    let response = SubscriptionHistoryResponse {
+        entries,
+        page: query.page,
+        page_size: SUBSCRIPTION_HISTORY_PAGE_SIZE,
+    };
+    let api_response = ApiResponse::success(response, request_id);
+    Ok((StatusCode::OK, Json(api_response)).into_response())
+}
+
+/// Login location restrictions request DTO
+#[derive(Debug, Deserialize)]
+pub struct LoginRestrictionsRequest {
+    /// CIDR blocks logins are allowed from; empty allows every network
+    #[serde(default)]
+    pub allowed_ip_ranges: Vec<String>,
+    /// ISO 3166-1 alpha-2 country codes logins are allowed from; empty allows every country
+    #[serde(default)]
+    pub allowed_countries: Vec<String>,
+    /// How logins whose country cannot be determined are treated
+    #[serde(default)]
+    pub unknown_country: UnknownCountryAction,
+    /// Whether to invalidate existing sessions created from locations that are no longer allowed
+    #[serde(default)]
+    pub sweep_sessions: bool,
+}
+
+/// Login location restrictions response DTO
+#[derive(Debug, Serialize)]
+pub struct LoginRestrictionsResponse {
+    pub allowed_ip_ranges: Vec<String>,
+    pub allowed_countries: Vec<String>,
+    pub unknown_country: UnknownCountryAction,
+}
+
+impl From<TenantSecurityPolicy> for LoginRestrictionsResponse {
+    fn from(policy: TenantSecurityPolicy) -> Self {
+        Self {
+            allowed_ip_ranges: policy
+                .allowed_ip_ranges
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            allowed_countries: policy.allowed_countries,
+            unknown_country: policy.unknown_country,
+        }
+    }
+}
+
+/// Get login restrictions handler
+///
+/// Requires the `ADMIN` role in the tenant.
+#[axum::debug_handler]
+pub async fn get_login_restrictions(
+    State(state): State<TenantAppState>,
+    user: AuthenticatedUser,
+    Path(tenant_id): Path<String>,
+) -> Result<Response, ApiError> {
+    debug!("Processing get login restrictions request");
+
+    // Generate a unique request ID
+    let request_id = generate_request_id();
+
+    // Parse tenant ID
+    let tenant_id = match Uuid::parse_str(&tenant_id) {
+        Ok(id) => id,
+        Err(_) => {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "Invalid tenant ID format",
+                "INVALID_TENANT_ID",
+                request_id,
+            ));
+        },
+    };
+
+    if !user.has_tenant_role(tenant_id, "ADMIN") {
+        monitoring::record_tenant_operation("login_restrictions", "failure");
+        warn!(
+            request_id = %request_id,
+            user_id = %user.user_id,
+            tenant_id = %tenant_id,
+            "User is not an administrator of the tenant"
+        );
+        return Err(ApiError::authorization_error(request_id));
+    }
+
+    let tenant = state
+        .tenant_service
+        .get_tenant(&tenant_id)
+        .await
+        .map_err(|err| {
+            monitoring::record_tenant_operation("login_restrictions", "failure");
+            ApiError::from_error(err, request_id.clone())
+        })?;
+
+    monitoring::record_tenant_operation("login_restrictions", "success");
+
+    let response = LoginRestrictionsResponse::from(tenant.security_policy());
+    let api_response = ApiResponse::success(response, request_id);
+    Ok((StatusCode::OK, Json(api_response)).into_response())
+}
+
+/// Update login restrictions handler
+///
+/// Requires the `ADMIN` role in the tenant. Invalid CIDR blocks and country
+/// codes are rejected with a validation error. With `sweep_sessions`, sessions
+/// created from locations that are no longer allowed are invalidated by a
+/// background job.
+#[axum::debug_handler]
+pub async fn update_login_restrictions(
+    State(state): State<TenantAppState>,
+    user: AuthenticatedUser,
+    Path(tenant_id): Path<String>,
+    Json(request): Json<LoginRestrictionsRequest>,
+) -> Result<Response, ApiError> {
+    debug!("Processing update login restrictions request");
+    let start = std::time::Instant::now();
+
+    // Generate a unique request ID
+    let request_id = generate_request_id();
+
+    // Parse tenant ID
+    let tenant_id = match Uuid::parse_str(&tenant_id) {
+        Ok(id) => id,
+        Err(_) => {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "Invalid tenant ID format",
+                "INVALID_TENANT_ID",
+                request_id,
+            ));
         },
+    };
+
+    if !user.has_tenant_role(tenant_id, "ADMIN") {
+        monitoring::record_tenant_operation("update_login_restrictions", "failure");
+        warn!(
+            request_id = %request_id,
+            user_id = %user.user_id,
+            tenant_id = %tenant_id,
+            "User is not an administrator of the tenant"
+        );
+        return Err(ApiError::authorization_error(request_id));
     }
+
+    let policy = state
+        .tenant_service
+        .update_login_restrictions(
+            &tenant_id,
+            &request.allowed_ip_ranges,
+            &request.allowed_countries,
+            request.unknown_country,
+        )
+        .await
+        .map_err(|err| {
+            monitoring::record_tenant_operation("update_login_restrictions", "failure");
+
+            warn!(
+                request_id = %request_id,
+                error = %err,
+                tenant_id = %tenant_id,
+                "Login restrictions update failed"
+            );
+
+            ApiError::from_error(err, request_id.clone())
+        })?;
+
+    if request.sweep_sessions && policy.restricts_login_location() {
+        state
+            .session_service
+            .clone()
+            .spawn_login_location_sweep(tenant_id);
+    }
+
+    monitoring::record_tenant_operation("update_login_restrictions", "success");
+
+    let duration = start.elapsed();
+    monitoring::record_request_duration(
+        duration.as_secs_f64(),
+        "PUT",
+        "/tenants/:id/login-restrictions",
+    );
+
+    info!(
+        request_id = %request_id,
+        tenant_id = %tenant_id,
+        user_id = %user.user_id,
+        sweep_sessions = request.sweep_sessions,
+        "Login restrictions updated"
+    );
+
+    let api_response = ApiResponse::success(LoginRestrictionsResponse::from(policy), request_id);
+    Ok((StatusCode::OK, Json(api_response)).into_response())
 }
 
 /// Utility to validate tenant operations
@@ -688,3 +1005,224 @@ pub async fn is_tenant_admin(
         .await)
         .unwrap_or(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acci_auth::{
+        AuthConfig, JwtUtils,
+        models::{
+            tenant::{TenantStatus, mock::MockTenantRepository},
+            user::mock::MockUserRepository,
+        },
+        services::user::UserService,
+        session::mock::MockSessionRepository,
+    };
+    use axum::http::header;
+    use serde_json::{Map, Value, json};
+
+    struct Fixture {
+        state: TenantAppState,
+        context: TenantContext,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let user_repo = Arc::new(MockUserRepository::new());
+            let tenant_repo = Arc::new(MockTenantRepository::new());
+            let config = Arc::new(AuthConfig::default());
+            let session_service = Arc::new(SessionService::new(
+                Arc::new(MockSessionRepository::new()),
+                config.clone(),
+            ));
+            let user_service = Arc::new(UserService::new(
+                user_repo.clone(),
+                Arc::new(JwtUtils::new(b"test-secret")),
+                session_service.clone(),
+                None,
+                config,
+            ));
+            let tenant_id = tenant_repo.insert_tenant_with_metadata(Value::Object(Map::new()));
+            Self {
+                state: TenantAppState {
+                    tenant_service: Arc::new(TenantService::new(
+                        tenant_repo,
+                        user_repo,
+                        user_service,
+                    )),
+                    session_service,
+                },
+                context: TenantContext {
+                    id: tenant_id,
+                    name: "Test Tenant".to_string(),
+                    subdomain: "test".to_string(),
+                    database_schema: "public".to_string(),
+                    is_active: true,
+                    status: TenantStatus::Active,
+                },
+            }
+        }
+
+        async fn get(&self, if_none_match: Option<&str>) -> Response {
+            let mut headers = HeaderMap::new();
+            if let Some(etag) = if_none_match {
+                headers.insert(
+                    header::IF_NONE_MATCH,
+                    etag.parse().expect("valid header value"),
+                );
+            }
+            get_tenant(
+                State(self.state.clone()),
+                Extension(self.context.clone()),
+                Extension(CacheConfig::default()),
+                headers,
+            )
+            .await
+            .into_response()
+        }
+
+        async fn patch(&self, body: Value) -> Value {
+            let request = serde_json::from_value(body).expect("Patch is valid");
+            let response = update_tenant(
+                State(self.state.clone()),
+                Extension(self.context.clone()),
+                Json(request),
+            )
+            .await
+            .expect("Tenant is updated");
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .expect("Failed to read body");
+            let body: Value = serde_json::from_slice(&body).expect("Body is JSON");
+            body["data"].clone()
+        }
+
+        async fn etag(&self) -> String {
+            let response = self.get(None).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            response.headers()[header::ETAG]
+                .to_str()
+                .expect("ETag is ASCII")
+                .to_string()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_tenant_not_modified() {
+        let fixture = Fixture::new();
+
+        let response = fixture.get(None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CACHE_CONTROL],
+            "private, max-age=30"
+        );
+        let etag = fixture.etag().await;
+        assert!(etag.starts_with("W/"));
+
+        let response = fixture.get(Some(&etag)).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+        assert_eq!(
+            response.headers()[header::CACHE_CONTROL],
+            "private, max-age=30"
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read body");
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tenant_update_rolls_etag() {
+        let fixture = Fixture::new();
+        let etag = fixture.etag().await;
+
+        let response = update_tenant(
+            State(fixture.state.clone()),
+            Extension(fixture.context.clone()),
+            Json(UpdateTenantRequest {
+                name: Some("Renamed Tenant".to_string()),
+                subdomain: None,
+                metadata: None,
+            }),
+        )
+        .await
+        .expect("Tenant is updated");
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The previous tag no longer matches, so the new state is sent
+        let response = fixture.get(Some(&etag)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()[header::ETAG], etag.as_str());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read body");
+        let body: Value = serde_json::from_slice(&body).expect("Body is JSON");
+        assert_eq!(body["data"]["name"], "Renamed Tenant");
+    }
+
+    #[tokio::test]
+    async fn test_tenant_update_merge_patch_metadata() {
+        let fixture = Fixture::new();
+
+        let tenant = fixture
+            .patch(json!({
+                "name": "Full Update",
+                "subdomain": "full-update",
+                "metadata": { "plan": "pro" },
+            }))
+            .await;
+        assert_eq!(tenant["name"], "Full Update");
+        assert_eq!(tenant["subdomain"], "full-update");
+        assert_eq!(tenant["metadata"], json!({ "plan": "pro" }));
+
+        // Omitting metadata leaves it untouched
+        let tenant = fixture.patch(json!({ "name": "Renamed Tenant" })).await;
+        assert_eq!(tenant["name"], "Renamed Tenant");
+        assert_eq!(tenant["metadata"], json!({ "plan": "pro" }));
+
+        // An explicit null clears it, the other fields stay as they are
+        let tenant = fixture.patch(json!({ "metadata": null })).await;
+        assert_eq!(tenant["name"], "Renamed Tenant");
+        assert_eq!(tenant["subdomain"], "full-update");
+        assert_eq!(tenant["metadata"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_tenant_update_rejects_oversized_metadata() {
+        let fixture = Fixture::new();
+        fixture
+            .patch(json!({ "metadata": { "plan": "pro" } }))
+            .await;
+
+        let nested = (0..10).fold(json!("leaf"), |value, _| json!({ "nested": value }));
+        let request =
+            serde_json::from_value(json!({ "metadata": nested })).expect("Patch is valid");
+        let response = update_tenant(
+            State(fixture.state.clone()),
+            Extension(fixture.context.clone()),
+            Json(request),
+        )
+        .await
+        .expect("Validation failures are responses");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read body");
+        let body: Value = serde_json::from_slice(&body).expect("Body is JSON");
+        assert_eq!(
+            body["details"]["fields"]["metadata"],
+            json!(["Metadata is nested 10 levels deep, at most 8 are allowed"])
+        );
+
+        // The stored metadata is left as it was
+        let response = fixture.get(None).await;
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read body");
+        let body: Value = serde_json::from_slice(&body).expect("Body is JSON");
+        assert_eq!(body["data"]["metadata"], json!({ "plan": "pro" }));
+    }
+}
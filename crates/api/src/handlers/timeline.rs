@@ -0,0 +1,420 @@
+//! User activity timelines
+//!
+//! `GET /tenants/{id}/users/{user_id}/timeline` lets tenant admins page
+//! through the activity of a member of their tenant. `GET /auth/me/timeline`
+//! shows users their own activity across all tenants, without the events
+//! only admins get to see. Both take an optional `cursor` from the previous
+//! page and a `limit`.
+
+use crate::middleware::auth::{AuthExtractorState, AuthenticatedUser};
+use crate::monitoring;
+use crate::response::{ApiError, ApiResponse};
+use crate::validation::generate_request_id;
+use axum::{
+    extract::{FromRef, Json, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use time::format_description::well_known::Rfc3339;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use acci_auth::{
+    DEFAULT_TIMELINE_PAGE_SIZE, TimelineCursor, TimelineEvent, TimelineEventKind, TimelinePage,
+    TimelineSource, TimelineView, UserTimelineService,
+    services::{session::SessionService, tenant::TenantService},
+};
+
+/// Services the timeline endpoints need
+#[derive(Clone)]
+pub struct TimelineAppState {
+    pub timeline_service: Arc<UserTimelineService>,
+    pub session_service: Arc<SessionService>,
+    /// Source of the tenant memberships; the admin endpoint needs it
+    pub tenant_service: Option<Arc<TenantService>>,
+}
+
+impl FromRef<TimelineAppState> for AuthExtractorState {
+    fn from_ref(state: &TimelineAppState) -> Self {
+        Self {
+            session_service: state.session_service.clone(),
+            tenant_service: state.tenant_service.clone(),
+            jwt_utils: None,
+        }
+    }
+}
+
+/// Query parameters of the timeline endpoints
+#[derive(Debug, Default, Deserialize)]
+pub struct TimelineQuery {
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    /// Events per page, capped by the service
+    pub limit: Option<u32>,
+}
+
+/// Timeline event response DTO
+#[derive(Debug, Serialize)]
+pub struct TimelineEventResponse {
+    pub id: String,
+    /// When the event happened (RFC 3339)
+    pub at: Option<String>,
+    pub kind: TimelineEventKind,
+    pub source: TimelineSource,
+    pub summary: String,
+    pub metadata: serde_json::Value,
+}
+
+impl From<TimelineEvent> for TimelineEventResponse {
+    fn from(event: TimelineEvent) -> Self {
+        Self {
+            id: event.id.to_string(),
+            at: event.at.format(&Rfc3339).ok(),
+            kind: event.kind,
+            source: event.source,
+            summary: event.summary,
+            metadata: event.metadata,
+        }
+    }
+}
+
+/// Timeline page response DTO
+#[derive(Debug, Serialize)]
+pub struct TimelineResponse {
+    pub events: Vec<TimelineEventResponse>,
+    /// Cursor of the next page, absent on the last page
+    pub next_cursor: Option<String>,
+}
+
+impl From<TimelinePage> for TimelineResponse {
+    fn from(page: TimelinePage) -> Self {
+        Self {
+            events: page.events.into_iter().map(Into::into).collect(),
+            next_cursor: page.next_cursor.map(|cursor| cursor.to_string()),
+        }
+    }
+}
+
+/// Handler for the timeline of a member of a tenant
+#[axum::debug_handler(state = TimelineAppState)]
+pub async fn get_user_timeline(
+    State(state): State<TimelineAppState>,
+    user: AuthenticatedUser,
+    Path((tenant_id, user_id)): Path<(String, String)>,
+    Query(query): Query<TimelineQuery>,
+) -> Response {
+    debug!("Processing user timeline request");
+    let request_id = generate_request_id();
+
+    let (tenant_id, user_id) = match (Uuid::parse_str(&tenant_id), Uuid::parse_str(&user_id)) {
+        (Ok(tenant_id), Ok(user_id)) => (tenant_id, user_id),
+        _ => {
+            return ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "Invalid tenant or user ID format",
+                "INVALID_ID",
+                request_id,
+            )
+            .into_response();
+        },
+    };
+
+    let Some(tenant_service) = state
+        .tenant_service
+        .as_ref()
+        .filter(|_| user.has_tenant_role(tenant_id, "ADMIN"))
+    else {
+        monitoring::record_tenant_operation("user_timeline", "failure");
+        warn!(
+            request_id = %request_id,
+            user_id = %user.user_id,
+            tenant_id = %tenant_id,
+            "User is not an administrator of the tenant"
+        );
+        return ApiError::authorization_error(request_id).into_response();
+    };
+
+    // Admins only see the timelines of their own tenant's members
+    match tenant_service.get_user_tenants(&user_id).await {
+        Ok(memberships) if memberships.iter().any(|m| m.tenant_id == tenant_id) => {},
+        Ok(_) => {
+            monitoring::record_tenant_operation("user_timeline", "failure");
+            return ApiError::not_found_error("user", request_id).into_response();
+        },
+        Err(err) => {
+            monitoring::record_tenant_operation("user_timeline", "failure");
+            warn!(request_id = %request_id, error = %err, "Failed to load tenant memberships");
+            return ApiError::internal_server_error(request_id).into_response();
+        },
+    }
+
+    let response = timeline_response(
+        &state,
+        user_id,
+        Some(tenant_id),
+        TimelineView::Admin,
+        query,
+        request_id,
+    )
+    .await;
+    monitoring::record_tenant_operation(
+        "user_timeline",
+        if response.status().is_success() {
+            "success"
+        } else {
+            "failure"
+        },
+    );
+    response
+}
+
+/// Handler for the timeline of the current user
+#[axum::debug_handler(state = TimelineAppState)]
+pub async fn get_my_timeline(
+    State(state): State<TimelineAppState>,
+    user: AuthenticatedUser,
+    Query(query): Query<TimelineQuery>,
+) -> Response {
+    debug!("Processing own timeline request");
+    let request_id = generate_request_id();
+
+    let response = timeline_response(
+        &state,
+        user.user_id,
+        None,
+        TimelineView::Own,
+        query,
+        request_id,
+    )
+    .await;
+    monitoring::record_auth_operation(
+        "timeline",
+        if response.status().is_success() {
+            "success"
+        } else {
+            "failure"
+        },
+    );
+    response
+}
+
+/// Read a timeline page and turn it into a response
+async fn timeline_response(
+    state: &TimelineAppState,
+    user_id: Uuid,
+    tenant_id: Option<Uuid>,
+    view: TimelineView,
+    query: TimelineQuery,
+    request_id: String,
+) -> Response {
+    let cursor = match query.cursor.as_deref().map(str::parse::<TimelineCursor>) {
+        None => None,
+        Some(Ok(cursor)) => Some(cursor),
+        Some(Err(_)) => {
+            return ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "Invalid timeline cursor",
+                "INVALID_CURSOR",
+                request_id,
+            )
+            .into_response();
+        },
+    };
+
+    match state
+        .timeline_service
+        .timeline(
+            user_id,
+            tenant_id,
+            view,
+            cursor,
+            query.limit.unwrap_or(DEFAULT_TIMELINE_PAGE_SIZE),
+        )
+        .await
+    {
+        Ok(page) => {
+            let api_response = ApiResponse::success(TimelineResponse::from(page), request_id);
+            (StatusCode::OK, Json(api_response)).into_response()
+        },
+        Err(err) => {
+            warn!(
+                request_id = %request_id,
+                error = %err,
+                user_id = %user_id,
+                "Failed to read user timeline"
+            );
+            ApiError::internal_server_error(request_id).into_response()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acci_auth::{
+        AuthConfig, JwtUtils, TenantUser, TimelineRecord,
+        models::{tenant::mock::MockTenantRepository, user::mock::MockUserRepository},
+        repository::timeline_repository::mock::MockTimelineRepository,
+        services::user::UserService,
+        session::mock::MockSessionRepository,
+        session::types::MfaStatus,
+    };
+    use serde_json::Value;
+    use time::OffsetDateTime;
+
+    struct Fixture {
+        state: TimelineAppState,
+        tenant_repo: Arc<MockTenantRepository>,
+        timeline_repo: Arc<MockTimelineRepository>,
+        tenant_id: Uuid,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let user_repo = Arc::new(MockUserRepository::new());
+            let tenant_repo = Arc::new(MockTenantRepository::new());
+            let timeline_repo = Arc::new(MockTimelineRepository::new());
+            let config = Arc::new(AuthConfig::default());
+            let session_service = Arc::new(SessionService::new(
+                Arc::new(MockSessionRepository::new()),
+                config.clone(),
+            ));
+            let user_service = Arc::new(UserService::new(
+                user_repo.clone(),
+                Arc::new(JwtUtils::new(b"test-secret")),
+                session_service.clone(),
+                None,
+                config,
+            ));
+            let tenant_service = Arc::new(TenantService::new(
+                tenant_repo.clone(),
+                user_repo,
+                user_service,
+            ));
+            let tenant_id =
+                tenant_repo.insert_tenant_with_metadata(Value::Object(Default::default()));
+            Self {
+                state: TimelineAppState {
+                    timeline_service: Arc::new(UserTimelineService::new(timeline_repo.clone())),
+                    session_service,
+                    tenant_service: Some(tenant_service),
+                },
+                tenant_repo,
+                timeline_repo,
+                tenant_id,
+            }
+        }
+
+        fn user(&self, user_id: Uuid, role: &str) -> AuthenticatedUser {
+            AuthenticatedUser {
+                user_id,
+                session_id: Uuid::new_v4(),
+                tenant_id: Some(self.tenant_id),
+                mfa_status: MfaStatus::Verified,
+                memberships: vec![TenantUser {
+                    tenant_id: self.tenant_id,
+                    user_id,
+                    tenant_role: role.to_string(),
+                    is_active: true,
+                    created_at: OffsetDateTime::now_utc(),
+                    updated_at: OffsetDateTime::now_utc(),
+                }],
+                claims: None,
+                custom_claims: Default::default(),
+                last_auth_at: OffsetDateTime::now_utc(),
+                expires_at: OffsetDateTime::now_utc() + time::Duration::hours(1),
+                impersonated_by: None,
+            }
+        }
+
+        fn record(&self, user_id: Uuid, action: &str) {
+            self.timeline_repo.insert(
+                user_id,
+                Some(self.tenant_id),
+                TimelineRecord {
+                    source: TimelineSource::SessionAudit,
+                    id: Uuid::new_v4(),
+                    at: OffsetDateTime::now_utc(),
+                    action: action.to_string(),
+                    details: Value::Object(Default::default()),
+                },
+            );
+        }
+
+        async fn admin_timeline(&self, admin: AuthenticatedUser, user_id: Uuid) -> Response {
+            get_user_timeline(
+                State(self.state.clone()),
+                admin,
+                Path((self.tenant_id.to_string(), user_id.to_string())),
+                Query(TimelineQuery::default()),
+            )
+            .await
+        }
+    }
+
+    async fn kinds(response: Response) -> Vec<String> {
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read body");
+        let body: Value = serde_json::from_slice(&body).expect("Body is JSON");
+        body["data"]["events"]
+            .as_array()
+            .expect("Events are listed")
+            .iter()
+            .map(|event| event["kind"].as_str().unwrap_or_default().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_admin_reads_timeline_of_tenant_members_only() {
+        let fixture = Fixture::new();
+        let admin = fixture.user(Uuid::new_v4(), "ADMIN");
+        let member = Uuid::new_v4();
+        let stranger = Uuid::new_v4();
+        fixture
+            .tenant_repo
+            .add_member(fixture.tenant_id, member, "MEMBER");
+        fixture.record(member, "IMPERSONATION_STARTED");
+        fixture.record(stranger, "SESSION_CREATED");
+
+        let response = fixture.admin_timeline(admin.clone(), member).await;
+        assert_eq!(kinds(response).await, vec!["impersonation"]);
+
+        let response = fixture.admin_timeline(admin, stranger).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let member_user = fixture.user(Uuid::new_v4(), "MEMBER");
+        let response = fixture.admin_timeline(member_user, member).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_own_timeline_omits_admin_only_kinds() {
+        let fixture = Fixture::new();
+        let user = fixture.user(Uuid::new_v4(), "MEMBER");
+        fixture.record(user.user_id, "IMPERSONATION_STARTED");
+        fixture.record(user.user_id, "SESSION_CREATED");
+
+        let response = get_my_timeline(
+            State(fixture.state.clone()),
+            user.clone(),
+            Query(TimelineQuery {
+                cursor: Some("not-a-cursor".to_string()),
+                limit: None,
+            }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = get_my_timeline(
+            State(fixture.state.clone()),
+            user,
+            Query(TimelineQuery::default()),
+        )
+        .await;
+        assert_eq!(kinds(response).await, vec!["session_started"]);
+    }
+}
@@ -0,0 +1,163 @@
+//! Trusted devices ("remember this device")
+//!
+//! After completing MFA, a user can trust the current device. Later logins
+//! from the same device that present the issued token skip the MFA
+//! challenge until the trust expires or is revoked.
+
+use crate::handlers::auth::ApiAppState;
+use crate::middleware::auth::{AuthenticatedUser, MfaVerifiedUser};
+use crate::monitoring;
+use crate::response::{ApiError, ApiResponse};
+use crate::validation::generate_request_id;
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use time::format_description::well_known::Rfc3339;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use acci_auth::{TrustedDevice, TrustedDeviceError, services::user::UserServiceError};
+
+/// Trusted device token handed to the client
+#[derive(Debug, Serialize)]
+pub struct TrustedDeviceResponse {
+    /// Identifier accepted by the revocation endpoint
+    pub device_id: String,
+    /// Token to send as `trusted_device_token` on later logins
+    pub token: String,
+    /// When the device has to complete MFA again (RFC 3339)
+    pub expires_at: String,
+}
+
+impl From<TrustedDevice> for TrustedDeviceResponse {
+    fn from(device: TrustedDevice) -> Self {
+        Self {
+            device_id: device.fingerprint_id.to_string(),
+            token: device.token,
+            expires_at: device.expires_at.format(&Rfc3339).unwrap_or_default(),
+        }
+    }
+}
+
+/// Handler trusting the device of the current, MFA-verified session
+#[axum::debug_handler(state = ApiAppState)]
+pub async fn trust_device(
+    State(state): State<ApiAppState>,
+    MfaVerifiedUser(user): MfaVerifiedUser,
+) -> Response {
+    debug!("Processing trust device request");
+    let request_id = generate_request_id();
+    monitoring::record_auth_operation("trust_device", "attempt");
+
+    match state
+        .user_service
+        .trust_device(
+            user.user_id,
+            user.session_id,
+            user.tenant_id.unwrap_or_else(Uuid::nil),
+        )
+        .await
+    {
+        Ok(device) => {
+            monitoring::record_auth_operation("trust_device", "success");
+            info!(
+                request_id = %request_id,
+                user_id = %user.user_id,
+                device_id = %device.fingerprint_id,
+                impersonated_by = ?user.impersonated_by,
+                "Device trusted"
+            );
+            let api_response =
+                ApiResponse::success(TrustedDeviceResponse::from(device), request_id);
+            (StatusCode::OK, Json(api_response)).into_response()
+        },
+        Err(err) => {
+            monitoring::record_auth_operation("trust_device", "failure");
+            warn!(
+                request_id = %request_id,
+                error = %err,
+                user_id = %user.user_id,
+                "Failed to trust device"
+            );
+            trusted_device_error(err, request_id)
+        },
+    }
+}
+
+/// Handler revoking the trust in a device of the current user
+#[axum::debug_handler(state = ApiAppState)]
+pub async fn revoke_trusted_device(
+    State(state): State<ApiAppState>,
+    user: AuthenticatedUser,
+    Path(device_id): Path<Uuid>,
+) -> Response {
+    debug!("Processing trusted device revocation request");
+    let request_id = generate_request_id();
+    monitoring::record_auth_operation("revoke_trusted_device", "attempt");
+
+    match state
+        .user_service
+        .revoke_trusted_device(
+            user.user_id,
+            user.tenant_id.unwrap_or_else(Uuid::nil),
+            device_id,
+        )
+        .await
+    {
+        Ok(()) => {
+            monitoring::record_auth_operation("revoke_trusted_device", "success");
+            info!(
+                request_id = %request_id,
+                user_id = %user.user_id,
+                device_id = %device_id,
+                impersonated_by = ?user.impersonated_by,
+                "Trusted device revoked"
+            );
+            let api_response = ApiResponse::success(true, request_id);
+            (StatusCode::OK, Json(api_response)).into_response()
+        },
+        Err(err) => {
+            monitoring::record_auth_operation("revoke_trusted_device", "failure");
+            warn!(
+                request_id = %request_id,
+                error = %err,
+                user_id = %user.user_id,
+                device_id = %device_id,
+                "Failed to revoke trusted device"
+            );
+            trusted_device_error(err, request_id)
+        },
+    }
+}
+
+/// Map a user service error of the trusted device endpoints to an API error response
+fn trusted_device_error(err: UserServiceError, request_id: String) -> Response {
+    let (status, message, code) = match err {
+        UserServiceError::MfaRequired => (
+            StatusCode::FORBIDDEN,
+            "MFA verification required",
+            "MFA_REQUIRED",
+        ),
+        UserServiceError::TrustedDevicesNotConfigured => (
+            StatusCode::NOT_FOUND,
+            "Trusted devices are not available",
+            "TRUSTED_DEVICES_UNAVAILABLE",
+        ),
+        UserServiceError::TrustedDevice(TrustedDeviceError::MissingFingerprint) => (
+            StatusCode::BAD_REQUEST,
+            "The session was created without a device fingerprint",
+            "DEVICE_FINGERPRINT_MISSING",
+        ),
+        UserServiceError::TrustedDevice(TrustedDeviceError::FingerprintNotFound) => (
+            StatusCode::NOT_FOUND,
+            "Device not found",
+            "DEVICE_NOT_FOUND",
+        ),
+        _ => return ApiError::internal_server_error(request_id).into_response(),
+    };
+
+    ApiError::new(status, message, code, request_id).into_response()
+}
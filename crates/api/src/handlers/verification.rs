@@ -1,24 +1,35 @@
+use crate::middleware::auth::{AuthExtractorState, AuthenticatedUser};
+use crate::middleware::client::ClientInfo;
 use crate::monitoring;
 use crate::response::{ApiError, ApiResponse};
 use crate::validation::{generate_request_id, validate_json_payload};
 use axum::{
-    extract::{Json, State},
+    extract::{FromRef, Json, Path, State},
     http::StatusCode,
     response::{IntoResponse, Response},
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tracing::{debug, info, warn};
+use tracing::{debug, warn};
 use uuid::Uuid;
 use validator::Validate;
 
 // Import auth services and models
 use acci_auth::{
+    AuthDecision, auth_event,
     models::VerificationType,
     repository::TenantAwareContext,
-    services::{session::SessionService, verification::VerificationService},
+    services::{
+        mfa::{MfaOrchestrator, MfaOrchestratorError},
+        session::SessionService,
+        tenant::TenantService,
+        verification::VerificationService,
+    },
 };
 
+/// Days covered by the verification statistics of a tenant
+pub const VERIFICATION_STATS_DAYS: i64 = 7;
+
 /// Verification Application State
 #[derive(Clone)]
 pub struct VerificationAppState {
@@ -26,10 +37,32 @@ pub struct VerificationAppState {
     pub verification_service: Arc<VerificationService>,
     /// Session service for session management
     pub session_service: Arc<SessionService>,
+    /// Completes the second factor of a session once its code is verified
+    pub mfa_orchestrator: Arc<MfaOrchestrator>,
     /// Default tenant-aware context for operations
     pub tenant_context: Arc<dyn TenantAwareContext>,
 }
 
+/// Services of the verification statistics endpoint
+#[derive(Clone)]
+pub struct VerificationStatsAppState {
+    pub verification_service: Arc<VerificationService>,
+    pub session_service: Arc<SessionService>,
+    /// Source of the tenant memberships checked for the admin role
+    pub tenant_service: Arc<TenantService>,
+    pub tenant_context: Arc<dyn TenantAwareContext>,
+}
+
+impl FromRef<VerificationStatsAppState> for AuthExtractorState {
+    fn from_ref(state: &VerificationStatsAppState) -> Self {
+        Self {
+            session_service: state.session_service.clone(),
+            tenant_service: Some(state.tenant_service.clone()),
+            jwt_utils: None,
+        }
+    }
+}
+
 /// Send Verification Request DTO
 #[derive(Debug, Deserialize, Validate)]
 pub struct SendVerificationRequest {
@@ -118,8 +151,9 @@ pub struct VerifyCodeResponse {
 #[axum::debug_handler]
 pub async fn send_verification(
     State(state): State<VerificationAppState>,
+    client: ClientInfo,
     Json(request): Json<SendVerificationRequest>,
-) -> Response {
+) -> Result<Response, ApiError> {
     debug!("Processing send verification request");
     let start = std::time::Instant::now();
 
@@ -131,7 +165,7 @@ pub async fn send_verification(
         Ok(data) => data,
         Err(validation_error) => {
             // Convert validation error to response
-            return validation_error.into_response();
+            return Ok(validation_error.into_response());
         },
     };
 
@@ -142,26 +176,24 @@ pub async fn send_verification(
     let user_id = match Uuid::parse_str(&validated.user_id) {
         Ok(id) => id,
         Err(_) => {
-            return ApiError::new(
+            return Err(ApiError::new(
                 StatusCode::BAD_REQUEST,
                 "Invalid user ID format",
                 "INVALID_USER_ID",
                 request_id,
-            )
-            .into_response();
+            ));
         },
     };
 
     let tenant_id = match Uuid::parse_str(&validated.tenant_id) {
         Ok(id) => id,
         Err(_) => {
-            return ApiError::new(
+            return Err(ApiError::new(
                 StatusCode::BAD_REQUEST,
                 "Invalid tenant ID format",
                 "INVALID_TENANT_ID",
                 request_id,
-            )
-            .into_response();
+            ));
         },
     };
 
@@ -170,13 +202,12 @@ pub async fn send_verification(
         "email" => VerificationType::Email,
         "sms" => VerificationType::Sms,
         _ => {
-            return ApiError::new(
+            return Err(ApiError::new(
                 StatusCode::BAD_REQUEST,
                 "Invalid verification type",
                 "INVALID_VERIFICATION_TYPE",
                 request_id,
-            )
-            .into_response();
+            ));
         },
     };
 
@@ -186,29 +217,27 @@ pub async fn send_verification(
             Ok(Some(session)) => {
                 // Ensure the session belongs to the user
                 if session.user_id != user_id {
-                    return ApiError::new(
+                    return Err(ApiError::new(
                         StatusCode::FORBIDDEN,
                         "Session does not belong to this user",
                         "UNAUTHORIZED_SESSION",
                         request_id,
-                    )
-                    .into_response();
+                    ));
                 }
             },
             _ => {
-                return ApiError::new(
+                return Err(ApiError::new(
                     StatusCode::UNAUTHORIZED,
                     "Invalid session token",
                     "INVALID_SESSION",
                     request_id,
-                )
-                .into_response();
+                ));
             },
         }
     }
 
     // Send verification code
-    match state
+    state
         .verification_service
         .send_verification(
             tenant_id,
@@ -218,85 +247,63 @@ pub async fn send_verification(
             state.tenant_context.as_ref(),
         )
         .await
-    {
-        Ok(_) => {
-            // Record successful operation in metrics
-            monitoring::record_auth_operation("verification_send", "success");
-
-            // Record duration
-            let duration = start.elapsed();
-            monitoring::record_request_duration(
-                duration.as_secs_f64(),
-                "POST",
-                "/auth/verify/send",
-            );
-
-            // Create response
-            let response = SendVerificationResponse {
-                success: true,
-                user_id: user_id.to_string(),
-                verification_type: verified_type_to_string(verification_type),
-            };
+        .map_err(|err| {
+            // Record failed operation in metrics
+            monitoring::record_auth_operation("verification_send", "failure");
 
-            info!(
-                request_id = %request_id,
-                user_id = %user_id,
-                tenant_id = %tenant_id,
-                verification_type = ?verification_type,
-                "Verification code sent successfully"
+            let error = ApiError::from_error(err, request_id.clone());
+            auth_event!(
+                "verification_send",
+                AuthDecision::Deny,
+                error.code(),
+                request_id = &request_id,
+                tenant_id = tenant_id,
+                user = &validated.user_id,
+                ip = client.ip_address.as_deref(),
+                challenge = verification_challenge(verification_type),
+                latency = start.elapsed(),
             );
 
-            let api_response = ApiResponse::success(response, request_id);
-            (StatusCode::OK, Json(api_response)).into_response()
-        },
-        Err(err) => {
-            // Record failed operation in metrics
-            monitoring::record_auth_operation("verification_send", "failure");
+            error
+        })?;
 
-            // Handle the error
-            let (status, message, code) = match err {
-                acci_core::error::Error::Validation(ref msg) => {
-                    // Handle validation errors
-                    if msg.contains("Rate limit") {
-                        (
-                            StatusCode::TOO_MANY_REQUESTS,
-                            "Rate limit exceeded. Please try again later.",
-                            "RATE_LIMIT_EXCEEDED",
-                        )
-                    } else {
-                        (StatusCode::BAD_REQUEST, msg.as_str(), "VALIDATION_ERROR")
-                    }
-                },
-                _ => {
-                    // Handle other errors
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        "Failed to send verification code",
-                        "VERIFICATION_ERROR",
-                    )
-                },
-            };
+    // Record successful operation in metrics
+    monitoring::record_auth_operation("verification_send", "success");
 
-            warn!(
-                request_id = %request_id,
-                error = %err,
-                user_id = %user_id,
-                tenant_id = %tenant_id,
-                verification_type = ?verification_type,
-                "Failed to send verification code"
-            );
+    // Record duration
+    let duration = start.elapsed();
+    monitoring::record_request_duration(duration.as_secs_f64(), "POST", "/auth/verify/send");
 
-            ApiError::new(status, message, code, request_id).into_response()
-        },
-    }
+    // Create response
+    let response = SendVerificationResponse {
+        success: true,
+        user_id: user_id.to_string(),
+        verification_type: verified_type_to_string(verification_type),
+    };
+
+    auth_event!(
+        "verification_send",
+        AuthDecision::Challenge,
+        "CODE_SENT",
+        request_id = &request_id,
+        tenant_id = tenant_id,
+        user = &validated.user_id,
+        ip = client.ip_address.as_deref(),
+        challenge = verification_challenge(verification_type),
+        latency = duration,
+    );
+
+    let api_response = ApiResponse::success(response, request_id);
+    Ok((StatusCode::OK, Json(api_response)).into_response())
 }
 
 /// Handler for verifying a code
 #[axum::debug_handler]
 pub async fn verify_code(
     State(state): State<VerificationAppState>,
+    client: ClientInfo,
     Json(request): Json<VerifyCodeRequest>,
-) -> Response {
+) -> Result<Response, ApiError> {
     debug!("Processing verify code request");
     let start = std::time::Instant::now();
 
@@ -308,7 +315,7 @@ pub async fn verify_code(
         Ok(data) => data,
         Err(validation_error) => {
             // Convert validation error to response
-            return validation_error.into_response();
+            return Ok(validation_error.into_response());
         },
     };
 
@@ -319,26 +326,24 @@ pub async fn verify_code(
     let user_id = match Uuid::parse_str(&validated.user_id) {
         Ok(id) => id,
         Err(_) => {
-            return ApiError::new(
+            return Err(ApiError::new(
                 StatusCode::BAD_REQUEST,
                 "Invalid user ID format",
                 "INVALID_USER_ID",
                 request_id,
-            )
-            .into_response();
+            ));
         },
     };
 
     let tenant_id = match Uuid::parse_str(&validated.tenant_id) {
         Ok(id) => id,
         Err(_) => {
-            return ApiError::new(
+            return Err(ApiError::new(
                 StatusCode::BAD_REQUEST,
                 "Invalid tenant ID format",
                 "INVALID_TENANT_ID",
                 request_id,
-            )
-            .into_response();
+            ));
         },
     };
 
@@ -347,152 +352,187 @@ pub async fn verify_code(
         "email" => VerificationType::Email,
         "sms" => VerificationType::Sms,
         _ => {
-            return ApiError::new(
+            return Err(ApiError::new(
                 StatusCode::BAD_REQUEST,
                 "Invalid verification type",
                 "INVALID_VERIFICATION_TYPE",
                 request_id,
+            ));
+        },
+    };
+
+    // Verify the code, completing MFA for the session if one is given
+    let result = match &validated.session_token {
+        Some(session_token) => {
+            let session = match state.session_service.validate_session(session_token).await {
+                Ok(Some(session)) => session,
+                _ => {
+                    return Err(ApiError::new(
+                        StatusCode::UNAUTHORIZED,
+                        "Invalid session token",
+                        "INVALID_SESSION",
+                        request_id,
+                    ));
+                },
+            };
+
+            state
+                .mfa_orchestrator
+                .complete_mfa(
+                    session.id,
+                    user_id,
+                    verification_type,
+                    &validated.code,
+                    tenant_id,
+                    state.tenant_context.as_ref(),
+                )
+                .await
+        },
+        None => state
+            .verification_service
+            .verify_code(
+                user_id,
+                verification_type,
+                &validated.code,
+                tenant_id,
+                state.tenant_context.as_ref(),
+            )
+            .await
+            .map_err(MfaOrchestratorError::Verification),
+    };
+
+    // A rejected code leaves the MFA status of the session unchanged
+    result.map_err(|err| {
+        // Record failed operation in metrics
+        monitoring::record_auth_operation("verification_verify", "failure");
+
+        let error = ApiError::from_error(err, request_id.clone());
+        auth_event!(
+            "verification_verify",
+            AuthDecision::Deny,
+            error.code(),
+            request_id = &request_id,
+            tenant_id = tenant_id,
+            user = &validated.user_id,
+            ip = client.ip_address.as_deref(),
+            challenge = verification_challenge(verification_type),
+            latency = start.elapsed(),
+        );
+
+        error
+    })?;
+
+    // Record successful operation in metrics
+    monitoring::record_auth_operation("verification_verify", "success");
+
+    // Record duration
+    let duration = start.elapsed();
+    monitoring::record_request_duration(duration.as_secs_f64(), "POST", "/auth/verify/code");
+
+    // Create response
+    let response = VerifyCodeResponse {
+        success: true,
+        user_id: user_id.to_string(),
+        verification_type: verified_type_to_string(verification_type),
+    };
+
+    auth_event!(
+        "verification_verify",
+        AuthDecision::Allow,
+        "CODE_VERIFIED",
+        request_id = &request_id,
+        tenant_id = tenant_id,
+        user = &validated.user_id,
+        ip = client.ip_address.as_deref(),
+        latency = duration,
+    );
+
+    let api_response = ApiResponse::success(response, request_id);
+    Ok((StatusCode::OK, Json(api_response)).into_response())
+}
+
+/// Verification statistics of a tenant
+#[derive(Debug, Serialize)]
+pub struct VerificationStatsResponse {
+    pub tenant_id: String,
+    /// Days covered, counted back from now
+    pub days: i64,
+    pub issued: u64,
+    pub verified: u64,
+    pub expired: u64,
+    pub invalidated: u64,
+    pub pending: u64,
+    /// Share of the settled codes that were verified, absent while none has settled
+    pub success_rate: Option<f64>,
+}
+
+/// Handler for the verification statistics of a tenant
+#[axum::debug_handler(state = VerificationStatsAppState)]
+pub async fn get_verification_stats(
+    State(state): State<VerificationStatsAppState>,
+    user: AuthenticatedUser,
+    Path(tenant_id): Path<String>,
+) -> Response {
+    debug!("Processing verification stats request");
+    let request_id = generate_request_id();
+
+    let tenant_id = match Uuid::parse_str(&tenant_id) {
+        Ok(id) => id,
+        Err(_) => {
+            return ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "Invalid tenant ID format",
+                "INVALID_TENANT_ID",
+                request_id,
             )
             .into_response();
         },
     };
 
-    // Verify the code
+    if !user.has_tenant_role(tenant_id, "ADMIN") {
+        monitoring::record_tenant_operation("verification_stats", "failure");
+        warn!(
+            request_id = %request_id,
+            user_id = %user.user_id,
+            tenant_id = %tenant_id,
+            "User is not an administrator of the tenant"
+        );
+        return ApiError::authorization_error(request_id).into_response();
+    }
+
     match state
         .verification_service
-        .verify_code(
-            user_id,
-            verification_type,
-            &validated.code,
+        .stats(
             tenant_id,
+            VERIFICATION_STATS_DAYS,
             state.tenant_context.as_ref(),
         )
         .await
     {
-        Ok(_) => {
-            // If session token is provided, update the session MFA status
-            if let Some(session_token) = &validated.session_token {
-                if let Err(err) = state
-                    .session_service
-                    .update_session_mfa_status(
-                        session_token,
-                        acci_auth::session::types::MfaStatus::Verified,
-                    )
-                    .await
-                {
-                    warn!(
-                        request_id = %request_id,
-                        error = %err,
-                        user_id = %user_id,
-                        tenant_id = %tenant_id,
-                        "Failed to update session MFA status"
-                    );
-                    // Continue even if this fails, as the verification itself was successful
-                }
-            }
-
-            // Record successful operation in metrics
-            monitoring::record_auth_operation("verification_verify", "success");
-
-            // Record duration
-            let duration = start.elapsed();
-            monitoring::record_request_duration(
-                duration.as_secs_f64(),
-                "POST",
-                "/auth/verify/code",
-            );
-
-            // Create response
-            let response = VerifyCodeResponse {
-                success: true,
-                user_id: user_id.to_string(),
-                verification_type: verified_type_to_string(verification_type),
+        Ok(stats) => {
+            monitoring::record_tenant_operation("verification_stats", "success");
+            let response = VerificationStatsResponse {
+                tenant_id: tenant_id.to_string(),
+                days: VERIFICATION_STATS_DAYS,
+                issued: stats.issued,
+                verified: stats.verified,
+                expired: stats.expired,
+                invalidated: stats.invalidated,
+                pending: stats.pending,
+                success_rate: stats.success_rate(),
             };
-
-            info!(
-                request_id = %request_id,
-                user_id = %user_id,
-                tenant_id = %tenant_id,
-                verification_type = ?verification_type,
-                "Verification code verified successfully"
-            );
-
             let api_response = ApiResponse::success(response, request_id);
             (StatusCode::OK, Json(api_response)).into_response()
         },
         Err(err) => {
-            // Record failed operation in metrics
-            monitoring::record_auth_operation("verification_verify", "failure");
-
-            // Handle the error
-            let (status, message, code) = match err {
-                acci_core::error::Error::Validation(ref msg) => {
-                    // Handle validation errors
-                    match msg.as_str() {
-                        "Invalid verification code" => (
-                            StatusCode::BAD_REQUEST,
-                            "Invalid verification code",
-                            "INVALID_CODE",
-                        ),
-                        "Code has expired" => (
-                            StatusCode::BAD_REQUEST,
-                            "Verification code has expired",
-                            "CODE_EXPIRED",
-                        ),
-                        "Too many verification attempts" => (
-                            StatusCode::BAD_REQUEST,
-                            "Too many verification attempts",
-                            "TOO_MANY_ATTEMPTS",
-                        ),
-                        "Rate limit exceeded" => (
-                            StatusCode::TOO_MANY_REQUESTS,
-                            "Rate limit exceeded. Please try again later.",
-                            "RATE_LIMIT_EXCEEDED",
-                        ),
-                        _ => (StatusCode::BAD_REQUEST, msg.as_str(), "VALIDATION_ERROR"),
-                    }
-                },
-                _ => {
-                    // Handle other errors
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        "Failed to verify code",
-                        "VERIFICATION_ERROR",
-                    )
-                },
-            };
-
-            // If session token is provided, update the session MFA status to Failed
-            if let Some(session_token) = &validated.session_token {
-                if let Err(update_err) = state
-                    .session_service
-                    .update_session_mfa_status(
-                        session_token,
-                        acci_auth::session::types::MfaStatus::None, // Failed verification, set back to None
-                    )
-                    .await
-                {
-                    warn!(
-                        request_id = %request_id,
-                        error = %update_err,
-                        user_id = %user_id,
-                        tenant_id = %tenant_id,
-                        "Failed to update session MFA status"
-                    );
-                    // Continue even if this fails, as we need to return the original error
-                }
-            }
-
+            monitoring::record_tenant_operation("verification_stats", "failure");
             warn!(
                 request_id = %request_id,
                 error = %err,
-                user_id = %user_id,
                 tenant_id = %tenant_id,
-                verification_type = ?verification_type,
-                "Failed to verify code"
+                "Failed to compute verification statistics"
             );
-
-            ApiError::new(status, message, code, request_id).into_response()
+            ApiError::internal_server_error(request_id).into_response()
         },
     }
 }
@@ -504,3 +544,11 @@ fn verified_type_to_string(verification_type: VerificationType) -> String {
         VerificationType::Sms => "sms".to_string(),
     }
 }
+
+/// Challenge a code of `verification_type` poses, as logged in authentication events
+fn verification_challenge(verification_type: VerificationType) -> &'static str {
+    match verification_type {
+        VerificationType::Email => "email_code",
+        VerificationType::Sms => "sms_code",
+    }
+}
@@ -1,7 +1,7 @@
 use crate::response::{ApiError, ApiResponse};
 use crate::validation::{ValidatedJson, generate_request_id};
 use axum::{
-    extract::{Json, Path, State},
+    extract::{FromRef, Json, Path, State},
     http::StatusCode,
     response::{IntoResponse, Response},
 };
@@ -13,6 +13,7 @@ use validator::Validate;
 
 // Import auth services
 use acci_auth::{
+    AuthFactor, CurrentUser, CurrentUserRejection,
     models::webauthn::{PublicKeyCredential, RegisterCredential},
     services::{session::SessionService, user::UserService, webauthn::WebAuthnService},
 };
@@ -28,6 +29,12 @@ pub struct WebAuthnAppState {
     pub session_service: Arc<SessionService>,
 }
 
+impl FromRef<WebAuthnAppState> for Arc<SessionService> {
+    fn from_ref(state: &WebAuthnAppState) -> Self {
+        state.session_service.clone()
+    }
+}
+
 /// Request to start WebAuthn registration
 #[derive(Debug, Deserialize, Validate)]
 pub struct StartRegistrationRequest {
@@ -216,6 +223,7 @@ pub async fn start_registration(
 // #[axum::debug_handler]
 pub async fn complete_registration(
     State(state): State<WebAuthnAppState>,
+    current_user: Result<CurrentUser, CurrentUserRejection>,
     Path(user_id): Path<Uuid>,
     ValidatedJson(request): ValidatedJson<CompleteRegistrationRequest>,
 ) -> Response {
@@ -224,6 +232,19 @@ pub async fn complete_registration(
     // Generate request ID for tracing
     let request_id = generate_request_id();
 
+    // Sessions pending MFA enrollment may register their first credential
+    let current_user = match current_user {
+        Ok(current_user) => current_user,
+        Err(CurrentUserRejection::Service(err)) => {
+            warn!(request_id = %request_id, error = %err, "Failed to validate session");
+            return ApiError::internal_server_error(request_id).into_response();
+        },
+        Err(_) => return ApiError::authentication_error(request_id).into_response(),
+    };
+    if current_user.user_id != user_id {
+        return ApiError::authorization_error(request_id).into_response();
+    }
+
     // Parse the tenant ID
     let tenant_id = match Uuid::parse_str(&request.tenant_id) {
         Ok(id) => id,
@@ -288,6 +309,22 @@ pub async fn complete_registration(
                 "WebAuthn registration completed successfully"
             );
 
+            let factor = AuthFactor::WebAuthn {
+                credential_id: credential.uuid,
+            };
+            if let Err(err) = state
+                .user_service
+                .record_factor_added(current_user.session_id, &factor)
+                .await
+            {
+                warn!(
+                    request_id = %request_id,
+                    error = %err,
+                    user_id = %user.id,
+                    "Failed to audit WebAuthn credential registration"
+                );
+            }
+
             let response = RegistrationCompleteResponse {
                 credential_id: credential.id.to_string(),
                 name: credential.name,
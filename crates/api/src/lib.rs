@@ -10,6 +10,7 @@ pub mod monitoring;
 pub mod response;
 pub mod router;
 pub mod validation;
+pub mod versioning;
 
 // Re-exports
 pub use config::ApiConfig;
@@ -18,6 +19,7 @@ pub use handlers::auth::{ApiAppState, api_login, api_register, validate_token};
 pub use monitoring::init_metrics;
 pub use response::{ApiError, ApiResponse, ResponseStatus, ResultExt};
 pub use router::ApiRouter;
+pub use versioning::{ApiVersion, VersionedRouter};
 
 // Customized public API for validation
 pub use validation::{
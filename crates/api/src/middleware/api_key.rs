@@ -0,0 +1,177 @@
+//! API key authentication
+//!
+//! Service integrations authenticate with `Authorization: ApiKey <key>`
+//! instead of a session token. Handlers for integrations take
+//! [`ApiKeyPrincipal`] as an argument and check the scopes they need with
+//! [`ApiKeyPrincipal::require_scope`]. Requests are rejected with:
+//!
+//! - 401 `AUTHENTICATION_REQUIRED` without a key, or for an unknown, revoked
+//!   or expired key
+//! - 403 `INSUFFICIENT_SCOPE` when the key lacks a required scope
+
+use acci_auth::{API_KEY_AUTH_SCHEME, ApiKeyError, ApiKeyService};
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::{HeaderMap, StatusCode, header, request::Parts},
+};
+use std::sync::Arc;
+use tracing::{debug, error};
+use uuid::Uuid;
+
+use crate::monitoring;
+use crate::response::ApiError;
+use crate::validation::generate_request_id;
+
+/// The API key a request was authenticated with
+#[derive(Debug, Clone)]
+pub struct ApiKeyPrincipal {
+    pub key_id: Uuid,
+    /// Tenant the key belongs to; requests may only access this tenant
+    pub tenant_id: Uuid,
+    pub name: String,
+    pub scopes: Vec<String>,
+}
+
+impl ApiKeyPrincipal {
+    /// Reject with `INSUFFICIENT_SCOPE` unless the key grants `scope`
+    pub fn require_scope(&self, scope: &str) -> Result<(), ApiError> {
+        if self.scopes.iter().any(|granted| granted == scope) {
+            return Ok(());
+        }
+
+        monitoring::record_auth_operation("api_key", "insufficient_scope");
+        debug!(key_id = %self.key_id, scope = %scope, "API key lacks scope");
+        Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            format!("API key lacks the '{}' scope", scope),
+            "INSUFFICIENT_SCOPE",
+            generate_request_id(),
+        ))
+    }
+}
+
+/// Read the key from an `Authorization: ApiKey <key>` header
+fn api_key_from_headers(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, key) = value.split_once(' ')?;
+    let key = key.trim();
+    (scheme.eq_ignore_ascii_case(API_KEY_AUTH_SCHEME) && !key.is_empty()).then_some(key)
+}
+
+impl<S> FromRequestParts<S> for ApiKeyPrincipal
+where
+    Arc<ApiKeyService>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let api_key_service = Arc::<ApiKeyService>::from_ref(state);
+        let request_id = generate_request_id();
+
+        let Some(key) = api_key_from_headers(&parts.headers) else {
+            monitoring::record_auth_operation("api_key", "failure");
+            return Err(ApiError::authentication_error(request_id));
+        };
+
+        match api_key_service.authenticate(key).await {
+            Ok(api_key) => {
+                monitoring::record_auth_operation("api_key", "success");
+                Ok(Self {
+                    key_id: api_key.id,
+                    tenant_id: api_key.tenant_id,
+                    name: api_key.name,
+                    scopes: api_key.scopes,
+                })
+            },
+            Err(ApiKeyError::InvalidKey) => {
+                monitoring::record_auth_operation("api_key", "failure");
+                debug!(request_id = %request_id, "API key is invalid, revoked or expired");
+                Err(ApiError::authentication_error(request_id))
+            },
+            Err(err) => {
+                monitoring::record_auth_operation("api_key", "failure");
+                error!(request_id = %request_id, error = %err, "Failed to authenticate API key");
+                Err(ApiError::internal_server_error(request_id))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acci_auth::{IssueApiKey, repository::api_key_repository::mock::MockApiKeyRepository};
+    use axum::{http::Request, response::IntoResponse};
+
+    fn parts(authorization: &str) -> Parts {
+        let request = Request::builder()
+            .uri("/")
+            .header(header::AUTHORIZATION, authorization)
+            .body(())
+            .expect("valid request");
+        request.into_parts().0
+    }
+
+    #[tokio::test]
+    async fn test_api_key_principal_from_valid_key() {
+        let service = Arc::new(ApiKeyService::new(Arc::new(MockApiKeyRepository::new())));
+        let tenant_id = Uuid::new_v4();
+        let issued = service
+            .issue(
+                tenant_id,
+                IssueApiKey {
+                    name: "Reporting".to_string(),
+                    scopes: vec!["users:read".to_string()],
+                    ..IssueApiKey::default()
+                },
+            )
+            .await
+            .expect("Key is issued");
+
+        let principal = ApiKeyPrincipal::from_request_parts(
+            &mut parts(&format!("ApiKey {}", issued.key)),
+            &service,
+        )
+        .await
+        .expect("Valid key is accepted");
+        assert_eq!(principal.tenant_id, tenant_id);
+        principal
+            .require_scope("users:read")
+            .expect("Granted scope is accepted");
+        let error = principal
+            .require_scope("users:write")
+            .expect_err("Missing scope is rejected");
+        assert_eq!(error.into_response().status(), StatusCode::FORBIDDEN);
+
+        service
+            .revoke(tenant_id, issued.api_key.id)
+            .await
+            .expect("Key is revoked");
+        let error = ApiKeyPrincipal::from_request_parts(
+            &mut parts(&format!("ApiKey {}", issued.key)),
+            &service,
+        )
+        .await
+        .expect_err("Revoked key is rejected");
+        assert_eq!(error.into_response().status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_api_key_requires_its_scheme() {
+        let key = "acci_0123456789ab_secret";
+        assert_eq!(
+            api_key_from_headers(&parts(&format!("ApiKey {}", key)).headers),
+            Some(key)
+        );
+        assert_eq!(
+            api_key_from_headers(&parts(&format!("apikey {}", key)).headers),
+            Some(key)
+        );
+        assert_eq!(
+            api_key_from_headers(&parts(&format!("Bearer {}", key)).headers),
+            None
+        );
+        assert_eq!(api_key_from_headers(&parts("ApiKey ").headers), None);
+    }
+}
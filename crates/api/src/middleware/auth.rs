@@ -0,0 +1,1219 @@
+//! Authentication extractors
+//!
+//! Protected handlers take [`AuthenticatedUser`] (or [`MfaVerifiedUser`]) as
+//! an argument instead of resolving the session themselves. Guest sessions
+//! of visitors who have not logged in authenticate nobody; endpoints open to
+//! them take [`MaybeAuthenticated`] instead. The extractors
+//! read the session token from the bearer header or the session cookie, look
+//! the session up through the [`SessionService`], and reject the request with
+//! the standard [`ApiError`] responses:
+//!
+//! - 401 `AUTHENTICATION_REQUIRED` without a token, or for an unknown,
+//!   invalidated, expired or guest session
+//! - 403 `MFA_ENROLLMENT_REQUIRED` for sessions restricted to MFA enrollment
+//! - 403 `MFA_REQUIRED` when [`MfaVerifiedUser`] is requested and the session
+//!   has not completed a second factor
+//!
+//! Sessions of tenants with an enabled client binding policy are also
+//! compared with the client they were bound to at login; in enforce mode a
+//! mismatch invalidates the session and answers 401 as well.
+//!
+//! When the state carries [`JwtUtils`], an access token issued by
+//! [`JwtUtils::issue_token`] can accompany the session in the
+//! [`ACCESS_TOKEN_HEADER`]; its custom claims are exposed as
+//! [`AuthenticatedUser::custom_claims`]. A token that is invalid or issued for
+//! another user, or for another tenant than the one the request resolved to,
+//! answers 401 as well.
+//!
+//! Sensitive operations additionally call
+//! [`AuthenticatedUser::require_recent_auth`], which answers 401
+//! `REAUTH_REQUIRED` when the last full authentication of the session is older
+//! than the configured window for the operation.
+
+use acci_auth::{
+    ClientBindingCheck, CurrentUser, JwtError, JwtUtils, PLATFORM_ADMIN_CLAIM, SensitiveOperation,
+    TenantUser,
+    handlers::session_token,
+    services::{session::SessionService, tenant::TenantService},
+    session::{Session, types::MfaStatus},
+};
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::{StatusCode, request::Parts},
+};
+use serde_json::{Map, Value};
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tracing::{debug, error, warn};
+use uuid::Uuid;
+
+use crate::middleware::client::ClientInfo;
+use crate::middleware::tenant::TenantContext;
+use crate::monitoring;
+use crate::response::ApiError;
+use crate::validation::generate_request_id;
+
+/// Header carrying the access token whose custom claims are exposed
+pub const ACCESS_TOKEN_HEADER: &str = "x-access-token";
+
+/// Services the authentication extractors need from the router state
+#[derive(Clone)]
+pub struct AuthExtractorState {
+    /// Session service used to validate the session token
+    pub session_service: Arc<SessionService>,
+    /// Source of the tenant memberships; without it memberships are empty
+    pub tenant_service: Option<Arc<TenantService>>,
+    /// Validates access tokens; without it custom claims are empty
+    pub jwt_utils: Option<Arc<JwtUtils>>,
+}
+
+/// The authenticated principal of a request
+///
+/// Sessions pending MFA enrollment are rejected; handlers that drive the
+/// enrollment flow use [`CurrentUser`] instead.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub user_id: Uuid,
+    pub session_id: Uuid,
+    /// Tenant the session was created for
+    pub tenant_id: Option<Uuid>,
+    pub mfa_status: MfaStatus,
+    /// Active tenant memberships of the user
+    pub memberships: Vec<TenantUser>,
+    /// Claims recorded in the session metadata at login
+    pub claims: Option<Value>,
+    /// Custom claims of the access token sent along with the session
+    pub custom_claims: Map<String, Value>,
+    /// When the user last fully authenticated in this session
+    pub last_auth_at: OffsetDateTime,
+    /// When the session expires
+    pub expires_at: OffsetDateTime,
+    /// Admin acting as this user, set for impersonation sessions
+    ///
+    /// Handlers include it in audit logs of write operations, and UIs show a
+    /// banner while it is set.
+    pub impersonated_by: Option<Uuid>,
+}
+
+impl AuthenticatedUser {
+    /// Whether the request is made by an admin impersonating the user
+    pub fn is_impersonation(&self) -> bool {
+        self.impersonated_by.is_some()
+    }
+
+    /// Whether the user is an active member of `tenant_id`
+    pub fn is_member_of(&self, tenant_id: Uuid) -> bool {
+        self.memberships
+            .iter()
+            .any(|membership| membership.tenant_id == tenant_id)
+    }
+
+    /// Whether the user holds `role` in `tenant_id`
+    pub fn has_tenant_role(&self, tenant_id: Uuid, role: &str) -> bool {
+        self.memberships
+            .iter()
+            .any(|membership| membership.tenant_id == tenant_id && membership.tenant_role == role)
+    }
+
+    /// Whether the access token marks the user as platform admin
+    ///
+    /// Never true while an admin impersonates the user.
+    pub fn is_platform_admin(&self) -> bool {
+        !self.is_impersonation()
+            && self.custom_claims.get(PLATFORM_ADMIN_CLAIM) == Some(&Value::Bool(true))
+    }
+
+    /// Reject with `REAUTH_REQUIRED` unless the user authenticated recently enough for `operation`
+    pub fn require_recent_auth(
+        &self,
+        session_service: &SessionService,
+        operation: SensitiveOperation,
+    ) -> Result<(), ApiError> {
+        if !session_service.reauth_required_for(self.last_auth_at, operation) {
+            return Ok(());
+        }
+
+        monitoring::record_auth_operation("authenticate", "reauth_required");
+        debug!(
+            user_id = %self.user_id,
+            session_id = %self.session_id,
+            operation = ?operation,
+            "Re-authentication required"
+        );
+        Err(ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            "Recent authentication required",
+            "REAUTH_REQUIRED",
+            generate_request_id(),
+        ))
+    }
+}
+
+impl<S> FromRequestParts<S> for AuthenticatedUser
+where
+    AuthExtractorState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let state = AuthExtractorState::from_ref(state);
+        let request_id = generate_request_id();
+
+        let Some(token) = session_token(&parts.headers) else {
+            monitoring::record_auth_operation("authenticate", "failure");
+            return Err(ApiError::authentication_error(request_id));
+        };
+
+        let session = match state.session_service.validate_session(token).await {
+            Ok(Some(session)) => session,
+            Ok(None) => {
+                monitoring::record_auth_operation("authenticate", "failure");
+                debug!(request_id = %request_id, "Session is invalid or expired");
+                return Err(ApiError::authentication_error(request_id));
+            },
+            Err(err) => {
+                monitoring::record_auth_operation("authenticate", "failure");
+                error!(request_id = %request_id, error = %err, "Failed to validate session");
+                return Err(ApiError::internal_server_error(request_id));
+            },
+        };
+
+        Self::from_session(&state, session, parts, request_id).await
+    }
+}
+
+impl AuthenticatedUser {
+    /// Check the valid user session of a request and load the user's memberships
+    async fn from_session(
+        state: &AuthExtractorState,
+        session: Session,
+        parts: &mut Parts,
+        request_id: String,
+    ) -> Result<Self, ApiError> {
+        check_client_binding(&state.session_service, &session, parts, &request_id).await?;
+
+        if session.mfa_status == MfaStatus::Pending {
+            monitoring::record_auth_operation("authenticate", "mfa_enrollment_required");
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "MFA enrollment required",
+                "MFA_ENROLLMENT_REQUIRED",
+                request_id,
+            ));
+        }
+
+        let memberships =
+            ResolvedMemberships::load(state, session.user_id, parts, &request_id).await?;
+
+        let custom_claims = custom_claims(state, &session, parts, &request_id)?;
+
+        monitoring::record_auth_operation("authenticate", "success");
+
+        let current_user = CurrentUser::from_session(&session);
+        let last_auth_at = session.last_auth_at();
+        let impersonated_by = session.impersonated_by();
+        Ok(Self {
+            user_id: current_user.user_id,
+            session_id: current_user.session_id,
+            tenant_id: current_user.tenant_id,
+            mfa_status: current_user.mfa_status,
+            memberships,
+            claims: session.metadata,
+            custom_claims,
+            last_auth_at,
+            expires_at: session.expires_at,
+            impersonated_by,
+        })
+    }
+}
+
+/// Active tenant memberships of the caller, resolved once per request
+///
+/// The roles checked with [`AuthenticatedUser::has_tenant_role`] come from
+/// these memberships. They are cached in the request extensions, so extracting
+/// the user again, e.g. in a middleware and in the handler, does not load them
+/// again. Extensions are dropped with their request, so the memberships are
+/// never reused for another request and role changes apply to the next one.
+#[derive(Debug, Clone)]
+struct ResolvedMemberships {
+    user_id: Uuid,
+    memberships: Vec<TenantUser>,
+}
+
+impl ResolvedMemberships {
+    /// The memberships of `user_id`, loaded on first use in the request
+    async fn load(
+        state: &AuthExtractorState,
+        user_id: Uuid,
+        parts: &mut Parts,
+        request_id: &str,
+    ) -> Result<Vec<TenantUser>, ApiError> {
+        let cached = parts
+            .extensions
+            .get::<Self>()
+            .filter(|resolved| resolved.user_id == user_id);
+        if let Some(resolved) = cached {
+            return Ok(resolved.memberships.clone());
+        }
+
+        let Some(tenant_service) = &state.tenant_service else {
+            return Ok(Vec::new());
+        };
+        let memberships: Vec<TenantUser> = match tenant_service.get_user_tenants(&user_id).await {
+            Ok(memberships) => memberships
+                .into_iter()
+                .filter(|membership| membership.is_active)
+                .collect(),
+            Err(err) => {
+                error!(
+                    request_id = %request_id,
+                    error = %err,
+                    user_id = %user_id,
+                    "Failed to load tenant memberships"
+                );
+                return Err(ApiError::internal_server_error(request_id));
+            },
+        };
+
+        parts.extensions.insert(Self {
+            user_id,
+            memberships: memberships.clone(),
+        });
+        Ok(memberships)
+    }
+}
+
+/// A visitor's guest session, see [`SessionService::create_guest_session`]
+#[derive(Debug, Clone)]
+pub struct GuestSession {
+    pub session_id: Uuid,
+    /// Pre-login state of the visitor
+    pub metadata: Option<Value>,
+    /// When the session expires
+    pub expires_at: OffsetDateTime,
+}
+
+/// The principal of a request to an endpoint open to visitors who have not logged in
+///
+/// User sessions get the checks and rejections of [`AuthenticatedUser`].
+/// Requests without a token, or with an unknown, invalidated or expired one,
+/// are anonymous instead of being rejected.
+#[derive(Debug, Clone)]
+pub enum MaybeAuthenticated {
+    User(AuthenticatedUser),
+    Guest(GuestSession),
+    Anonymous,
+}
+
+impl MaybeAuthenticated {
+    /// The authenticated user, `None` for guests and anonymous requests
+    pub fn user(&self) -> Option<&AuthenticatedUser> {
+        match self {
+            Self::User(user) => Some(user),
+            Self::Guest(_) | Self::Anonymous => None,
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for MaybeAuthenticated
+where
+    AuthExtractorState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let state = AuthExtractorState::from_ref(state);
+        let request_id = generate_request_id();
+
+        let Some(token) = session_token(&parts.headers) else {
+            return Ok(Self::Anonymous);
+        };
+
+        let session = match state.session_service.validate_session_or_guest(token).await {
+            Ok(Some(session)) => session,
+            Ok(None) => {
+                debug!(request_id = %request_id, "Session is invalid or expired");
+                return Ok(Self::Anonymous);
+            },
+            Err(err) => {
+                error!(request_id = %request_id, error = %err, "Failed to validate session");
+                return Err(ApiError::internal_server_error(request_id));
+            },
+        };
+
+        if session.is_guest() {
+            monitoring::record_auth_operation("authenticate", "guest");
+            return Ok(Self::Guest(GuestSession {
+                session_id: session.id,
+                metadata: session.metadata,
+                expires_at: session.expires_at,
+            }));
+        }
+
+        AuthenticatedUser::from_session(&state, session, parts, request_id)
+            .await
+            .map(Self::User)
+    }
+}
+
+/// Custom claims of the access token of a request
+///
+/// Empty without an access token or when the state cannot validate one.
+fn custom_claims(
+    state: &AuthExtractorState,
+    session: &Session,
+    parts: &Parts,
+    request_id: &str,
+) -> Result<Map<String, Value>, ApiError> {
+    let (Some(jwt_utils), Some(token)) = (
+        &state.jwt_utils,
+        parts
+            .headers
+            .get(ACCESS_TOKEN_HEADER)
+            .and_then(|value| value.to_str().ok()),
+    ) else {
+        return Ok(Map::new());
+    };
+
+    let tenant_id = parts
+        .extensions
+        .get::<TenantContext>()
+        .map(|tenant| tenant.id);
+    match jwt_utils.validate_token(token.trim(), tenant_id) {
+        Ok(claims) if claims.sub == session.user_id => Ok(claims.custom),
+        Ok(_) => {
+            monitoring::record_auth_operation("authenticate", "failure");
+            warn!(request_id = %request_id, "Access token was issued for another user");
+            Err(ApiError::authentication_error(request_id))
+        },
+        Err(JwtError::TenantMismatch) => {
+            monitoring::record_auth_operation("authenticate", "failure");
+            warn!(request_id = %request_id, "Access token was issued for another tenant");
+            Err(ApiError::authentication_error(request_id))
+        },
+        Err(err) => {
+            monitoring::record_auth_operation("authenticate", "failure");
+            debug!(request_id = %request_id, error = %err, "Access token is invalid");
+            Err(ApiError::authentication_error(request_id))
+        },
+    }
+}
+
+/// Compare the client of a request with the client its session is bound to
+///
+/// Mismatches are only recorded unless the tenant enforces the binding, in
+/// which case the session has been invalidated and the request is rejected.
+async fn check_client_binding(
+    session_service: &SessionService,
+    session: &Session,
+    parts: &mut Parts,
+    request_id: &str,
+) -> Result<(), ApiError> {
+    let policy = match session_service.client_binding_policy(session).await {
+        Ok(Some(policy)) => policy,
+        Ok(None) => return Ok(()),
+        Err(err) => {
+            error!(request_id = %request_id, error = %err, "Failed to load client binding policy");
+            return Err(ApiError::internal_server_error(request_id));
+        },
+    };
+    let client = ClientInfo::from_parts(parts).characteristics(&parts.headers, &policy);
+
+    match session_service
+        .check_client_binding(session, &policy, &client)
+        .await
+    {
+        Ok(ClientBindingCheck::Unbound | ClientBindingCheck::Matched) => Ok(()),
+        Ok(ClientBindingCheck::Mismatched) => {
+            monitoring::record_auth_operation("client_binding", "mismatch");
+            Ok(())
+        },
+        Ok(ClientBindingCheck::Rejected) => {
+            monitoring::record_auth_operation("client_binding", "rejected");
+            monitoring::record_auth_operation("authenticate", "failure");
+            Err(ApiError::authentication_error(request_id))
+        },
+        Err(err) => {
+            error!(request_id = %request_id, error = %err, "Failed to check client binding");
+            Err(ApiError::internal_server_error(request_id))
+        },
+    }
+}
+
+/// An [`AuthenticatedUser`] whose session has completed a second factor
+#[derive(Debug, Clone)]
+pub struct MfaVerifiedUser(pub AuthenticatedUser);
+
+impl<S> FromRequestParts<S> for MfaVerifiedUser
+where
+    AuthExtractorState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let user = AuthenticatedUser::from_request_parts(parts, state).await?;
+
+        if user.mfa_status != MfaStatus::Verified {
+            monitoring::record_auth_operation("authenticate", "mfa_required");
+            warn!(
+                user_id = %user.user_id,
+                session_id = %user.session_id,
+                "Session has not completed MFA"
+            );
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "MFA verification required",
+                "MFA_REQUIRED",
+                generate_request_id(),
+            ));
+        }
+
+        Ok(Self(user))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acci_auth::{
+        AuthConfig, BindingEnforcement, ClientBindingPolicy, ClientCharacteristics,
+        LAST_AUTH_METADATA_KEY, SessionInvalidationReason, TenantSecurityPolicy,
+        models::tenant::mock::MockTenantRepository,
+        session::{SessionRepository, mock::MockSessionRepository},
+    };
+    use axum::{
+        http::{Request, header},
+        response::IntoResponse,
+    };
+    use time::Duration;
+
+    struct Fixture {
+        repository: Arc<MockSessionRepository>,
+        state: AuthExtractorState,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let repository = Arc::new(MockSessionRepository::new());
+            let session_service = Arc::new(SessionService::new(
+                repository.clone(),
+                Arc::new(AuthConfig::default()),
+            ));
+            Self {
+                repository,
+                state: AuthExtractorState {
+                    session_service,
+                    tenant_service: None,
+                    jwt_utils: None,
+                },
+            }
+        }
+
+        async fn session(&self, mfa_status: MfaStatus) -> (Uuid, String) {
+            let (session, token) = self
+                .state
+                .session_service
+                .create_session_with_status(
+                    Uuid::new_v4(),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                    mfa_status,
+                )
+                .await
+                .expect("Failed to create session");
+            (session.id, token)
+        }
+
+        fn parts(token: &str) -> Parts {
+            let request = Request::builder()
+                .uri("/")
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(())
+                .expect("valid request");
+            request.into_parts().0
+        }
+
+        async fn authenticate(&self, token: &str) -> Result<AuthenticatedUser, ApiError> {
+            AuthenticatedUser::from_request_parts(&mut Self::parts(token), &self.state).await
+        }
+
+        async fn authenticate_mfa(&self, token: &str) -> Result<MfaVerifiedUser, ApiError> {
+            MfaVerifiedUser::from_request_parts(&mut Self::parts(token), &self.state).await
+        }
+
+        async fn maybe_authenticate(&self, token: &str) -> Result<MaybeAuthenticated, ApiError> {
+            MaybeAuthenticated::from_request_parts(&mut Self::parts(token), &self.state).await
+        }
+
+        async fn guest_session(&self) -> (Uuid, String) {
+            let (session, token) = self
+                .state
+                .session_service
+                .create_guest_session(None, None, None)
+                .await
+                .expect("Failed to create guest session");
+            (session.id, token)
+        }
+
+        /// Fixture whose sessions belong to a tenant binding them in `mode`
+        fn with_client_binding(mode: BindingEnforcement) -> (Self, Uuid) {
+            let tenants = Arc::new(MockTenantRepository::new());
+            let tenant_id = tenants.insert_tenant_with_policy(&TenantSecurityPolicy {
+                client_binding: ClientBindingPolicy {
+                    enabled: true,
+                    mode,
+                    ..ClientBindingPolicy::default()
+                },
+                ..TenantSecurityPolicy::default()
+            });
+            let repository = Arc::new(MockSessionRepository::new());
+            let session_service = Arc::new(
+                SessionService::new(repository.clone(), Arc::new(AuthConfig::default()))
+                    .with_tenant_repository(tenants),
+            );
+            let fixture = Self {
+                repository,
+                state: AuthExtractorState {
+                    session_service,
+                    tenant_service: None,
+                    jwt_utils: None,
+                },
+            };
+            (fixture, tenant_id)
+        }
+
+        /// Session of `tenant_id` bound to a client with `tls_fingerprint`
+        async fn bound_session(&self, tenant_id: Uuid, tls_fingerprint: &str) -> (Uuid, String) {
+            let session_service = &self.state.session_service;
+            let (session, token) = session_service
+                .create_session_with_status(
+                    Uuid::new_v4(),
+                    None,
+                    None,
+                    None,
+                    None,
+                    Some(serde_json::json!({ "tenant_id": tenant_id })),
+                    false,
+                    MfaStatus::None,
+                )
+                .await
+                .expect("Failed to create session");
+            let policy = session_service
+                .client_binding_policy(&session)
+                .await
+                .expect("Failed to load policy")
+                .expect("Tenant binds sessions");
+            let parts = Self::client_parts(&token, tls_fingerprint);
+            let client =
+                ClientCharacteristics::from_request(&parts.headers, parts.version, &policy);
+            session_service
+                .bind_client(session.id, &client)
+                .await
+                .expect("Failed to bind session");
+            (session.id, token)
+        }
+
+        fn client_parts(token: &str, tls_fingerprint: &str) -> Parts {
+            let mut parts = Self::parts(token);
+            parts.headers.insert(
+                "x-tls-fingerprint",
+                tls_fingerprint.parse().expect("valid header"),
+            );
+            parts.headers.insert(
+                header::ACCEPT_LANGUAGE,
+                "de-DE,en;q=0.8".parse().expect("valid header"),
+            );
+            parts
+        }
+
+        async fn authenticate_from(
+            &self,
+            token: &str,
+            tls_fingerprint: &str,
+        ) -> Result<AuthenticatedUser, ApiError> {
+            let mut parts = Self::client_parts(token, tls_fingerprint);
+            AuthenticatedUser::from_request_parts(&mut parts, &self.state).await
+        }
+    }
+
+    async fn rejection(error: ApiError) -> (StatusCode, String) {
+        let response = error.into_response();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read body");
+        let body: Value = serde_json::from_slice(&body).expect("Error body is JSON");
+        (
+            status,
+            body["code"].as_str().unwrap_or_default().to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_authenticated_user_from_valid_session() {
+        let fixture = Fixture::new();
+        let (session_id, token) = fixture.session(MfaStatus::None).await;
+
+        let user = fixture
+            .authenticate(&token)
+            .await
+            .expect("Valid session is accepted");
+        assert_eq!(user.session_id, session_id);
+        assert_eq!(user.mfa_status, MfaStatus::None);
+        assert!(user.memberships.is_empty());
+
+        let (verified_id, token) = fixture.session(MfaStatus::Verified).await;
+        let MfaVerifiedUser(user) = fixture
+            .authenticate_mfa(&token)
+            .await
+            .expect("MFA-verified session is accepted");
+        assert_eq!(user.session_id, verified_id);
+    }
+
+    #[tokio::test]
+    async fn test_custom_claims_of_access_token() {
+        struct OrgEnricher;
+
+        impl acci_auth::ClaimsEnricher for OrgEnricher {
+            fn enrich(
+                &self,
+                claims: &mut acci_auth::Claims,
+                _user: &acci_auth::User,
+                _tenant: Option<&acci_auth::TokenTenant>,
+                _session: Option<&Session>,
+            ) -> Result<(), acci_auth::JwtError> {
+                claims.insert_custom("org_code", Value::String("ACME-42".to_string()))
+            }
+        }
+
+        let mut fixture = Fixture::new();
+        let jwt_utils =
+            Arc::new(JwtUtils::new(b"test-secret").with_enricher(Arc::new(OrgEnricher)));
+        fixture.state.jwt_utils = Some(jwt_utils.clone());
+        let user = acci_auth::User::new("user@example.com".to_string(), "hash".to_string());
+        let (_, token) = fixture
+            .state
+            .session_service
+            .create_session_with_status(
+                user.id,
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+                MfaStatus::None,
+            )
+            .await
+            .expect("Failed to create session");
+
+        // Without an access token there are no custom claims
+        let authenticated = fixture.authenticate(&token).await.expect("Valid session");
+        assert!(authenticated.custom_claims.is_empty());
+
+        let access_token = jwt_utils
+            .issue_token(&user, None, None)
+            .expect("Failed to issue token");
+        let mut parts = Fixture::parts(&token);
+        parts.headers.insert(
+            ACCESS_TOKEN_HEADER,
+            access_token.parse().expect("valid header"),
+        );
+        let authenticated = AuthenticatedUser::from_request_parts(&mut parts, &fixture.state)
+            .await
+            .expect("Valid access token");
+        assert_eq!(authenticated.custom_claims["org_code"], "ACME-42");
+
+        // A token of another user is rejected
+        let other = acci_auth::User::new("other@example.com".to_string(), "hash".to_string());
+        let mut parts = Fixture::parts(&token);
+        parts.headers.insert(
+            ACCESS_TOKEN_HEADER,
+            jwt_utils
+                .issue_token(&other, None, None)
+                .expect("Failed to issue token")
+                .parse()
+                .expect("valid header"),
+        );
+        let err = AuthenticatedUser::from_request_parts(&mut parts, &fixture.state)
+            .await
+            .expect_err("Token of another user is rejected");
+        assert_eq!(
+            rejection(err).await,
+            (
+                StatusCode::UNAUTHORIZED,
+                "AUTHENTICATION_REQUIRED".to_string()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_access_token_of_another_tenant_is_rejected() {
+        let mut fixture = Fixture::new();
+        let jwt_utils = Arc::new(JwtUtils::new(b"test-secret"));
+        fixture.state.jwt_utils = Some(jwt_utils.clone());
+        let user_id = Uuid::new_v4();
+        let (_, token) = fixture
+            .state
+            .session_service
+            .create_session_with_status(
+                user_id,
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+                MfaStatus::None,
+            )
+            .await
+            .expect("Failed to create session");
+        let tenant_a = Uuid::new_v4();
+        let access_token = jwt_utils
+            .create_token(user_id, "user@example.com", Some(tenant_a))
+            .expect("Failed to create token");
+        let parts_in = |tenant_id: Uuid| {
+            let mut parts = Fixture::parts(&token);
+            parts.headers.insert(
+                ACCESS_TOKEN_HEADER,
+                access_token.parse().expect("valid header"),
+            );
+            parts.extensions.insert(TenantContext {
+                id: tenant_id,
+                name: "Acme".to_string(),
+                subdomain: "acme".to_string(),
+                database_schema: format!("tenant_{tenant_id}"),
+                is_active: true,
+                status: acci_auth::TenantStatus::Active,
+            });
+            parts
+        };
+
+        let mut parts = parts_in(tenant_a);
+        AuthenticatedUser::from_request_parts(&mut parts, &fixture.state)
+            .await
+            .expect("Token of the resolved tenant is accepted");
+
+        let mut parts = parts_in(Uuid::new_v4());
+        let err = AuthenticatedUser::from_request_parts(&mut parts, &fixture.state)
+            .await
+            .expect_err("Token of another tenant is rejected");
+        assert_eq!(
+            rejection(err).await,
+            (
+                StatusCode::UNAUTHORIZED,
+                "AUTHENTICATION_REQUIRED".to_string()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_session_is_rejected() {
+        let fixture = Fixture::new();
+
+        let error = fixture
+            .authenticate("not-a-session")
+            .await
+            .expect_err("Unknown token is rejected");
+        assert_eq!(
+            rejection(error).await,
+            (
+                StatusCode::UNAUTHORIZED,
+                "AUTHENTICATION_REQUIRED".to_string()
+            )
+        );
+
+        let (session_id, token) = fixture.session(MfaStatus::Verified).await;
+        fixture
+            .repository
+            .invalidate_session(session_id, SessionInvalidationReason::AdminAction)
+            .await
+            .expect("Failed to invalidate session");
+        let error = fixture
+            .authenticate_mfa(&token)
+            .await
+            .expect_err("Invalidated session is rejected");
+        assert_eq!(
+            rejection(error).await,
+            (
+                StatusCode::UNAUTHORIZED,
+                "AUTHENTICATION_REQUIRED".to_string()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_session_is_rejected() {
+        let fixture = Fixture::new();
+        let (session_id, token) = fixture.session(MfaStatus::Verified).await;
+        fixture
+            .repository
+            .sessions()
+            .iter_mut()
+            .find(|session| session.id == session_id)
+            .expect("Session is stored")
+            .expires_at = OffsetDateTime::now_utc() - Duration::seconds(1);
+
+        let error = fixture
+            .authenticate(&token)
+            .await
+            .expect_err("Expired session is rejected");
+        assert_eq!(
+            rejection(error).await,
+            (
+                StatusCode::UNAUTHORIZED,
+                "AUTHENTICATION_REQUIRED".to_string()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mfa_pending_session_is_forbidden() {
+        let fixture = Fixture::new();
+
+        let (_, token) = fixture.session(MfaStatus::Pending).await;
+        let error = fixture
+            .authenticate(&token)
+            .await
+            .expect_err("Enrollment-only session is rejected");
+        assert_eq!(
+            rejection(error).await,
+            (StatusCode::FORBIDDEN, "MFA_ENROLLMENT_REQUIRED".to_string())
+        );
+
+        let (_, token) = fixture.session(MfaStatus::Required).await;
+        fixture
+            .authenticate(&token)
+            .await
+            .expect("Session awaiting a second factor is authenticated");
+        let error = fixture
+            .authenticate_mfa(&token)
+            .await
+            .expect_err("Session awaiting a second factor is not MFA-verified");
+        assert_eq!(
+            rejection(error).await,
+            (StatusCode::FORBIDDEN, "MFA_REQUIRED".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stale_session_must_reauthenticate_for_sensitive_operation() {
+        let fixture = Fixture::new();
+        let (session_id, token) = fixture.session(MfaStatus::None).await;
+        let stale = (OffsetDateTime::now_utc() - Duration::hours(1)).unix_timestamp();
+        fixture
+            .repository
+            .sessions()
+            .iter_mut()
+            .find(|session| session.id == session_id)
+            .expect("Session is stored")
+            .metadata = Some(Value::Object(serde_json::Map::from_iter([(
+            LAST_AUTH_METADATA_KEY.to_string(),
+            Value::from(stale),
+        )])));
+
+        // Ordinary requests are unaffected by the age of the authentication
+        let user = fixture
+            .authenticate(&token)
+            .await
+            .expect("Stale session is still valid");
+        let session_service = &fixture.state.session_service;
+
+        let error = user
+            .require_recent_auth(session_service, SensitiveOperation::DeleteTenant)
+            .expect_err("Sensitive operation requires re-authentication");
+        assert_eq!(
+            rejection(error).await,
+            (StatusCode::UNAUTHORIZED, "REAUTH_REQUIRED".to_string())
+        );
+
+        session_service
+            .record_reauthentication(session_id)
+            .await
+            .expect("Failed to record re-authentication");
+        let user = fixture
+            .authenticate(&token)
+            .await
+            .expect("Session is valid");
+        user.require_recent_auth(session_service, SensitiveOperation::DeleteTenant)
+            .expect("Fresh authentication is accepted");
+    }
+
+    #[tokio::test]
+    async fn test_client_binding_mismatch_is_monitored() {
+        let (fixture, tenant_id) = Fixture::with_client_binding(BindingEnforcement::Monitor);
+        let (session_id, token) = fixture.bound_session(tenant_id, "771,4865-4866,0-23").await;
+        fixture
+            .authenticate_from(&token, "771,4865-4866,0-23")
+            .await
+            .expect("Bound client is accepted");
+
+        // The fingerprint changes mid-session
+        let user = fixture
+            .authenticate_from(&token, "771,49195-49199,0-11")
+            .await
+            .expect("Mismatch is only recorded in monitor mode");
+        assert_eq!(user.session_id, session_id);
+        let session = fixture
+            .repository
+            .get_session(session_id)
+            .await
+            .expect("Failed to load session")
+            .expect("Session is stored");
+        assert!(session.is_valid);
+    }
+
+    #[tokio::test]
+    async fn test_client_binding_mismatch_is_enforced() {
+        let (fixture, tenant_id) = Fixture::with_client_binding(BindingEnforcement::Enforce);
+        let (session_id, token) = fixture.bound_session(tenant_id, "771,4865-4866,0-23").await;
+        fixture
+            .authenticate_from(&token, "771,4865-4866,0-23")
+            .await
+            .expect("Bound client is accepted");
+
+        // The fingerprint changes mid-session
+        let error = fixture
+            .authenticate_from(&token, "771,49195-49199,0-11")
+            .await
+            .expect_err("Mismatch is rejected in enforce mode");
+        assert_eq!(
+            rejection(error).await,
+            (
+                StatusCode::UNAUTHORIZED,
+                "AUTHENTICATION_REQUIRED".to_string()
+            )
+        );
+        let session = fixture
+            .repository
+            .get_session(session_id)
+            .await
+            .expect("Failed to load session")
+            .expect("Session is stored");
+        assert!(!session.is_valid);
+        assert_eq!(
+            session.invalidated_reason,
+            Some(SessionInvalidationReason::ClientBindingMismatch)
+        );
+
+        // The original client cannot use the invalidated session either
+        fixture
+            .authenticate_from(&token, "771,4865-4866,0-23")
+            .await
+            .expect_err("Invalidated session is rejected");
+    }
+
+    #[tokio::test]
+    async fn test_guest_session_is_not_authenticated() {
+        let fixture = Fixture::new();
+        let (guest_id, token) = fixture.guest_session().await;
+
+        let error = fixture
+            .authenticate(&token)
+            .await
+            .expect_err("Guest session is rejected");
+        assert_eq!(
+            rejection(error).await,
+            (
+                StatusCode::UNAUTHORIZED,
+                "AUTHENTICATION_REQUIRED".to_string()
+            )
+        );
+
+        match fixture.maybe_authenticate(&token).await {
+            Ok(MaybeAuthenticated::Guest(guest)) => assert_eq!(guest.session_id, guest_id),
+            other => panic!("Expected a guest, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_maybe_authenticated_principals() {
+        let fixture = Fixture::new();
+        let (session_id, token) = fixture.session(MfaStatus::None).await;
+
+        let principal = fixture
+            .maybe_authenticate(&token)
+            .await
+            .expect("User session is accepted");
+        assert_eq!(
+            principal.user().map(|user| user.session_id),
+            Some(session_id)
+        );
+
+        assert!(matches!(
+            fixture.maybe_authenticate("not-a-session").await,
+            Ok(MaybeAuthenticated::Anonymous)
+        ));
+        let mut parts = Request::builder()
+            .uri("/")
+            .body(())
+            .expect("valid request")
+            .into_parts()
+            .0;
+        assert!(matches!(
+            MaybeAuthenticated::from_request_parts(&mut parts, &fixture.state).await,
+            Ok(MaybeAuthenticated::Anonymous)
+        ));
+
+        // User sessions keep the rejections of `AuthenticatedUser`
+        let (_, token) = fixture.session(MfaStatus::Pending).await;
+        let error = fixture
+            .maybe_authenticate(&token)
+            .await
+            .expect_err("MFA enrollment is pending");
+        assert_eq!(rejection(error).await.0, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_guest_cannot_reach_protected_endpoint() {
+        use axum::{Router, body::Body, routing::get};
+        use tower::ServiceExt;
+
+        let fixture = Fixture::new();
+        let (_, guest_token) = fixture.guest_session().await;
+        let (_, user_token) = fixture.session(MfaStatus::None).await;
+        let app = Router::new()
+            .route(
+                "/protected",
+                get(|_: AuthenticatedUser| async { "protected" }),
+            )
+            .route(
+                "/cart",
+                get(|principal: MaybeAuthenticated| async move {
+                    match principal {
+                        MaybeAuthenticated::User(_) => "user",
+                        MaybeAuthenticated::Guest(_) => "guest",
+                        MaybeAuthenticated::Anonymous => "anonymous",
+                    }
+                }),
+            )
+            .with_state(fixture.state.clone());
+        let fetch = |uri: &'static str, token: &str| {
+            let request = Request::builder()
+                .uri(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .expect("valid request");
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(request).await.expect("Request is served");
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .expect("Failed to read body");
+                (status, String::from_utf8_lossy(&body).into_owned())
+            }
+        };
+
+        assert_eq!(
+            fetch("/protected", &guest_token).await.0,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            fetch("/protected", &user_token).await,
+            (StatusCode::OK, "protected".to_string())
+        );
+        assert_eq!(
+            fetch("/cart", &guest_token).await,
+            (StatusCode::OK, "guest".to_string())
+        );
+        assert_eq!(
+            fetch("/cart", &user_token).await,
+            (StatusCode::OK, "user".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tenant_roles_are_resolved_once_per_request() {
+        use acci_auth::{
+            JwtUtils, models::user::mock::MockUserRepository, services::user::UserService,
+        };
+        use axum::{
+            Router,
+            body::Body,
+            middleware::{self, Next},
+            response::Response,
+            routing::get,
+        };
+        use tower::ServiceExt;
+
+        let mut fixture = Fixture::new();
+        let tenants = Arc::new(MockTenantRepository::new());
+        let users = Arc::new(MockUserRepository::new());
+        let config = Arc::new(AuthConfig::default());
+        let user_service = Arc::new(UserService::new(
+            users.clone(),
+            Arc::new(JwtUtils::new(b"test-secret")),
+            fixture.state.session_service.clone(),
+            None,
+            config,
+        ));
+        fixture.state.tenant_service = Some(Arc::new(TenantService::new(
+            tenants.clone(),
+            users,
+            user_service,
+        )));
+        let tenant_id = Uuid::new_v4();
+        let (session_id, token) = fixture.session(MfaStatus::None).await;
+        let user_id = fixture
+            .repository
+            .get_session(session_id)
+            .await
+            .expect("Failed to read session")
+            .expect("Session exists")
+            .user_id;
+        tenants.add_member(tenant_id, user_id, "ADMIN");
+
+        async fn require_admin(
+            user: AuthenticatedUser,
+            request: axum::extract::Request,
+            next: Next,
+        ) -> Result<Response, StatusCode> {
+            if !user.memberships.iter().any(|m| m.tenant_role == "ADMIN") {
+                return Err(StatusCode::FORBIDDEN);
+            }
+            Ok(next.run(request).await)
+        }
+
+        let app = Router::new()
+            .route(
+                "/admin",
+                get(move |user: AuthenticatedUser| async move {
+                    if user.has_tenant_role(tenant_id, "ADMIN") {
+                        StatusCode::OK
+                    } else {
+                        StatusCode::FORBIDDEN
+                    }
+                }),
+            )
+            .layer(middleware::from_fn_with_state(
+                fixture.state.clone(),
+                require_admin,
+            ))
+            .with_state(fixture.state.clone());
+        let request = || {
+            Request::builder()
+                .uri("/admin")
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .expect("valid request")
+        };
+
+        // The middleware and the handler check the role, one lookup is made
+        let response = app.clone().oneshot(request()).await.expect("Served");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(tenants.user_tenant_lookups(), 1);
+
+        // The next request resolves the roles again
+        let response = app.oneshot(request()).await.expect("Served");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(tenants.user_tenant_lookups(), 2);
+    }
+}
@@ -0,0 +1,71 @@
+//! Client information extractor
+//!
+//! [`ClientInfo`] collects what the API records about the client of a
+//! request: its IP address as forwarded by the load balancer, its user agent
+//! and the characteristics sessions can be bound to. It is computed once per
+//! request and cached in the request extensions, so handlers and the
+//! authentication extractors read the same values.
+
+use acci_auth::{ClientBindingPolicy, ClientCharacteristics};
+use axum::{
+    extract::FromRequestParts,
+    http::{HeaderMap, Version, header, request::Parts},
+};
+use std::convert::Infallible;
+
+/// Client of a request
+#[derive(Debug, Clone)]
+pub struct ClientInfo {
+    /// First address of `X-Forwarded-For`, falling back to `X-Real-IP`
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    /// HTTP version the request was received with
+    pub http_version: Version,
+}
+
+impl ClientInfo {
+    /// The client of a request, computed on first use
+    pub fn from_parts(parts: &mut Parts) -> Self {
+        if let Some(client) = parts.extensions.get::<Self>() {
+            return client.clone();
+        }
+
+        let client = Self {
+            ip_address: header_value(&parts.headers, "x-forwarded-for")
+                .and_then(|forwarded| forwarded.split(',').next().map(|ip| ip.trim().to_string()))
+                .or_else(|| header_value(&parts.headers, "x-real-ip")),
+            user_agent: header_value(&parts.headers, header::USER_AGENT.as_str()),
+            http_version: parts.version,
+        };
+        parts.extensions.insert(client.clone());
+        client
+    }
+
+    /// Characteristics a session of a tenant with `policy` is bound to
+    pub fn characteristics(
+        &self,
+        headers: &HeaderMap,
+        policy: &ClientBindingPolicy,
+    ) -> ClientCharacteristics {
+        ClientCharacteristics::from_request(headers, self.http_version, policy)
+    }
+}
+
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_parts(parts))
+    }
+}
+
+/// Value of a request header, if present and valid UTF-8
+fn header_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
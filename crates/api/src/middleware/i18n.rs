@@ -0,0 +1,172 @@
+//! Localization middleware
+//!
+//! Resolves the request's locale from the `Accept-Language` header and
+//! localizes the `message` of JSON error responses through the shared catalog
+//! in [`acci_core::i18n`]. The `code` of an error is never changed, and errors
+//! without a translation keep their original English message.
+
+use acci_core::i18n::{self, Locale};
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{HeaderValue, header},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::Value;
+use tracing::warn;
+
+/// Resolves the locale of a request, falling back to English
+pub fn request_locale(req: &Request) -> Locale {
+    req.headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .map(Locale::from_accept_language)
+        .unwrap_or_default()
+}
+
+/// Localizes the messages of error responses
+///
+/// The resolved [`Locale`] is also added to the request extensions so
+/// handlers can localize their own output.
+pub async fn localization_middleware(mut req: Request, next: Next) -> Response {
+    let locale = request_locale(&req);
+    req.extensions_mut().insert(locale);
+
+    let response = next.run(req).await;
+    let status = response.status();
+    if locale == Locale::En || !(status.is_client_error() || status.is_server_error()) {
+        return response;
+    }
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!(error = %e, "Failed to read error response for localization");
+            return Response::from_parts(parts, Body::empty());
+        },
+    };
+    let Ok(mut json) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    let translated = json
+        .get("code")
+        .and_then(Value::as_str)
+        .and_then(|code| i18n::translation(code, locale));
+    let Some(message) = translated else {
+        // Untranslated errors keep their English message
+        parts
+            .headers
+            .insert(header::CONTENT_LANGUAGE, HeaderValue::from_static("en"));
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    if let Some(object) = json.as_object_mut() {
+        object.insert("message".to_string(), Value::String(message.to_string()));
+    }
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_LANGUAGE,
+        HeaderValue::from_static(locale.as_str()),
+    );
+    match serde_json::to_vec(&json) {
+        Ok(body) => Response::from_parts(parts, Body::from(body)),
+        Err(e) => {
+            warn!(error = %e, "Failed to serialize localized error response");
+            Response::from_parts(parts, Body::from(bytes))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::ApiError;
+    use axum::{Router, http::StatusCode, response::IntoResponse, routing::get};
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new()
+            .route(
+                "/exists",
+                get(|| async {
+                    ApiError::new(
+                        StatusCode::CONFLICT,
+                        "Tenant with subdomain 'acme' already exists",
+                        "TENANT_ALREADY_EXISTS",
+                        "req-1".to_string(),
+                    )
+                    .into_response()
+                }),
+            )
+            .route(
+                "/limit",
+                get(|| async {
+                    ApiError::new(
+                        StatusCode::FORBIDDEN,
+                        "Tenant limit exceeded",
+                        "TENANT_LIMIT_EXCEEDED",
+                        "req-2".to_string(),
+                    )
+                    .into_response()
+                }),
+            )
+            .layer(axum::middleware::from_fn(localization_middleware))
+    }
+
+    async fn call(uri: &str, accept_language: &str) -> (Option<String>, Value) {
+        let response = app()
+            .oneshot(
+                Request::builder()
+                    .uri(uri)
+                    .header(header::ACCEPT_LANGUAGE, accept_language)
+                    .body(Body::empty())
+                    .expect("Failed to build request"),
+            )
+            .await
+            .expect("Failed to call router");
+        let language = response
+            .headers()
+            .get(header::CONTENT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read body");
+        let body = serde_json::from_slice(&bytes).expect("Body is JSON");
+        (language, body)
+    }
+
+    #[tokio::test]
+    async fn test_german_accept_language_localizes_known_code() {
+        let (language, body) = call("/exists", "de-DE,de;q=0.9,en;q=0.8").await;
+        assert_eq!(language.as_deref(), Some("de"));
+        assert_eq!(body["message"], "Der Mandant existiert bereits");
+        assert_eq!(body["code"], "TENANT_ALREADY_EXISTS");
+    }
+
+    #[tokio::test]
+    async fn test_untranslated_code_falls_back_to_english() {
+        let (language, body) = call("/limit", "de").await;
+        assert_eq!(language.as_deref(), Some("en"));
+        assert_eq!(body["message"], "Tenant limit exceeded");
+        assert_eq!(body["code"], "TENANT_LIMIT_EXCEEDED");
+
+        // English clients keep the specific message of the handler
+        let (_, body) = call("/exists", "en-US").await;
+        assert_eq!(
+            body["message"],
+            "Tenant with subdomain 'acme' already exists"
+        );
+    }
+}
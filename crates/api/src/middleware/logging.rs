@@ -1,13 +1,19 @@
+use acci_core::telemetry::{self, SamplingConfig, SamplingContext};
 use axum::{extract::Request, middleware::Next, response::Response};
 use metrics::{counter, histogram};
 use std::time::Instant;
-use tracing::{error, info, warn};
+use tracing::{Instrument, error, info, info_span, warn};
 use uuid::Uuid;
 
 /// Enhanced logging middleware with error tracking
+///
+/// Requests run in a `request` span tagged with their route class and tenant.
+/// If a sampler is installed via [`telemetry::install_sampler`], request logs
+/// are only written for sampled requests; error responses are always logged.
 pub async fn logging_middleware(req: Request, next: Next) -> Response {
     // Generate a UUID-based request ID for better tracing
-    let request_id = Uuid::new_v4().to_string();
+    let request_uuid = Uuid::new_v4();
+    let request_id = request_uuid.to_string();
 
     // Extract information from the request
     let method = req.method().clone();
@@ -27,26 +33,60 @@ pub async fn logging_middleware(req: Request, next: Next) -> Response {
         .or_else(|| headers.get("x-real-ip").and_then(|v| v.to_str().ok()))
         .unwrap_or("unknown");
 
+    // Classify the request for the sampler
+    let sampler = telemetry::sampler();
+    let route_class = match &sampler {
+        Some(sampler) => sampler.config().route_class(&path),
+        None => SamplingConfig::default().route_class(&path),
+    };
+    let tenant_id = headers
+        .get("x-tenant-id")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let sample = |status: Option<u16>| {
+        sampler.as_ref().is_none_or(|sampler| {
+            sampler
+                .should_sample(&SamplingContext {
+                    trace_id: request_uuid.as_u128(),
+                    route_class,
+                    tenant_id: tenant_id.as_deref(),
+                    status,
+                })
+                .is_sampled()
+        })
+    };
+    let head_sampled = sample(None);
+    let span = info_span!(
+        "request",
+        request_id = %request_id,
+        route_class = route_class.as_str(),
+        tenant_id = tenant_id.as_deref().unwrap_or("none"),
+        sampled = head_sampled,
+    );
+
     // Increment request counter with method and path labels
     counter!("api.requests.total", "method" => method.to_string(), "path" => path.clone())
         .increment(1);
 
     // Log the request with structured fields
-    info!(
-        request_id = %request_id,
-        method = %method,
-        path = %path,
-        version = ?version,
-        client_ip = %client_ip,
-        user_agent = %user_agent,
-        "Request received"
-    );
+    if head_sampled {
+        info!(
+            parent: &span,
+            request_id = %request_id,
+            method = %method,
+            path = %path,
+            version = ?version,
+            client_ip = %client_ip,
+            user_agent = %user_agent,
+            "Request received"
+        );
+    }
 
     // Start time measurement
     let start = Instant::now();
 
     // Execute next middleware/handler
-    let response = next.run(req).await;
+    let response = next.run(req).instrument(span.clone()).await;
 
     // Calculate duration
     let duration = start.elapsed();
@@ -59,6 +99,11 @@ pub async fn logging_middleware(req: Request, next: Next) -> Response {
     let status = response.status();
     let status_code = status.as_u16();
 
+    // Failures are sampled even if the request was not sampled up front
+    let sampled = sample(Some(status_code));
+    span.record("sampled", sampled);
+    let _entered = span.enter();
+
     // Increment response counter with status code
     counter!("api.responses.total", "status" => status_code.to_string(), "path" => path.clone())
         .increment(1);
@@ -80,6 +125,9 @@ pub async fn logging_middleware(req: Request, next: Next) -> Response {
     // Log response based on status code
     match status_code {
         code if code < 400 => {
+            if !sampled {
+                return response;
+            }
             info!(
                 fields = ?log_fields(),
                 "Request completed successfully"
@@ -87,10 +135,12 @@ pub async fn logging_middleware(req: Request, next: Next) -> Response {
         },
         code if code < 500 => {
             // Client errors (400-499) are warnings
-            warn!(
-                fields = ?log_fields(),
-                "Client error response"
-            );
+            if sampled {
+                warn!(
+                    fields = ?log_fields(),
+                    "Client error response"
+                );
+            }
 
             // Increment client error counter
             counter!("api.errors.client", "status" => status_code.to_string(), "path" => path)
@@ -3,12 +3,16 @@
 //! This module contains middleware components for the API infrastructure.
 //! Middlewares can be used to intercept and modify requests and responses.
 
+pub mod api_key;
+pub mod auth;
+pub mod client;
 pub mod error_handling;
+pub mod i18n;
 pub mod logging;
 pub mod tenant;
 
 use crate::config::ApiConfig;
-use acci_auth::models::tenant::TenantRepository;
+use acci_auth::{models::tenant::TenantRepository, services::session::SessionService};
 use axum::Router;
 use std::sync::Arc;
 // All middleware imports are available through the unified axum import
@@ -21,6 +25,7 @@ pub struct MiddlewareStack {
     config: ApiConfig,
     tenant_repository: Option<Arc<dyn TenantRepository>>,
     tenant_config: Option<tenant::TenantResolutionConfig>,
+    session_service: Option<Arc<SessionService>>,
 }
 
 impl MiddlewareStack {
@@ -30,6 +35,7 @@ impl MiddlewareStack {
             config,
             tenant_repository: None,
             tenant_config: None,
+            session_service: None,
         }
     }
 
@@ -44,6 +50,12 @@ impl MiddlewareStack {
         self
     }
 
+    /// Lets tenant resolution prefer the active tenant of the request's session
+    pub fn with_session_service(mut self, session_service: Arc<SessionService>) -> Self {
+        self.session_service = Some(session_service);
+        self
+    }
+
     /// Applies the middleware stack to the given router
     pub fn apply(self, router: Router) -> Router {
         let mut router = router;
@@ -58,6 +70,7 @@ impl MiddlewareStack {
             let tenant_state = tenant::TenantState {
                 tenant_repository,
                 config: self.tenant_config.unwrap_or_default(),
+                session_service: self.session_service,
             };
 
             // Apply middleware with the state
@@ -67,6 +80,9 @@ impl MiddlewareStack {
             ));
         }
 
+        // Localization of error messages
+        router = router.layer(axum::middleware::from_fn(i18n::localization_middleware));
+
         // Logging middleware (first to execute)
         router = router.layer(axum::middleware::from_fn(logging::logging_middleware));
 
@@ -1,4 +1,8 @@
-use acci_auth::models::tenant::{Tenant, TenantError, TenantRepository};
+use acci_auth::{
+    handlers::session_token,
+    models::tenant::{Tenant, TenantError, TenantRepository, TenantStatus},
+    services::session::SessionService,
+};
 use axum::{
     body::Body,
     extract::{Request, State},
@@ -9,7 +13,7 @@ use axum::{
 use jsonwebtoken::{DecodingKey, Validation, decode};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
 use crate::monitoring;
@@ -26,8 +30,10 @@ pub struct TenantContext {
     pub subdomain: String,
     /// Database schema name for the tenant
     pub database_schema: String,
-    /// Whether the tenant is active
+    /// Whether the tenant is active, i.e. not archived
     pub is_active: bool,
+    /// Lifecycle status of the tenant, see [`TenantResolutionConfig::allows_path`]
+    pub status: TenantStatus,
 }
 
 impl TenantContext {
@@ -38,7 +44,8 @@ impl TenantContext {
             name: tenant.name,
             subdomain: tenant.subdomain,
             database_schema: format!("tenant_{}", tenant.id),
-            is_active: tenant.is_active,
+            is_active: !tenant.status.is_archived(),
+            status: tenant.status,
         }
     }
 }
@@ -60,6 +67,29 @@ pub struct TenantResolutionConfig {
     pub check_path: bool,
     /// Path prefix for tenant identification
     pub path_prefix: String,
+    /// Paths a suspended tenant can still reach, e.g. for its admin and
+    /// billing users to log in and settle the suspension
+    pub suspended_path_prefixes: Vec<String>,
+    /// Endings of the paths an archived tenant can still reach, i.e. the
+    /// exports of its data
+    pub archived_path_suffixes: Vec<String>,
+}
+
+impl TenantResolutionConfig {
+    /// Whether a tenant in `status` can reach `path`
+    pub fn allows_path(&self, status: &TenantStatus, path: &str) -> bool {
+        match status {
+            TenantStatus::Active => true,
+            TenantStatus::Suspended { .. } => self
+                .suspended_path_prefixes
+                .iter()
+                .any(|prefix| path.starts_with(prefix.as_str())),
+            TenantStatus::Archived { .. } => self
+                .archived_path_suffixes
+                .iter()
+                .any(|suffix| path.ends_with(suffix.as_str())),
+        }
+    }
 }
 
 impl Default for TenantResolutionConfig {
@@ -72,6 +102,16 @@ impl Default for TenantResolutionConfig {
             check_jwt: true,
             check_path: false,
             path_prefix: "/api/tenants/".to_string(),
+            suspended_path_prefixes: vec![
+                "/api/v1/auth/".to_string(),
+                "/api/v1/tenants/".to_string(),
+                "/api/v1/admin/".to_string(),
+                // Deprecated aliases of the versioned paths
+                "/api/auth/".to_string(),
+                "/api/tenants/".to_string(),
+                "/api/admin/".to_string(),
+            ],
+            archived_path_suffixes: vec!["/export".to_string()],
         }
     }
 }
@@ -93,6 +133,8 @@ pub struct TenantState {
     pub tenant_repository: Arc<dyn TenantRepository>,
     /// Configuration for tenant resolution
     pub config: TenantResolutionConfig,
+    /// Session lookup for the active tenant; without it sessions are not consulted
+    pub session_service: Option<Arc<SessionService>>,
 }
 
 /// Middleware for resolving tenant from the request
@@ -127,63 +169,101 @@ pub async fn tenant_resolution_middleware(
         .and_then(|h| h.to_str().ok())
         .map(|s| s.to_owned());
     let path = request.uri().path().to_owned();
+    let token = session_token(request.headers()).map(|token| token.to_owned());
+    let session_tenant = resolve_from_session(&state, token.as_deref(), &request_id).await;
 
     // Try to resolve tenant ID from various sources
-    let tenant_id =
-        match resolve_tenant_id_from_all_sources(&state, host, tenant_header, auth_header, path)
-            .await
-        {
-            Ok(Some(id)) => Some(id),
-            Ok(None) => None,
-            Err(err) => {
-                // Failed to resolve tenant
-                error!(request_id = %request_id, error = %err, "Failed to resolve tenant");
-                monitoring::record_auth_operation("tenant_resolution", "failure");
-
-                let status_code = match err {
-                    TenantError::NotFound => StatusCode::NOT_FOUND,
-                    TenantError::InactiveTenant => StatusCode::FORBIDDEN,
-                    _ => StatusCode::INTERNAL_SERVER_ERROR,
-                };
-
-                let error_code = match err {
-                    TenantError::NotFound => "TENANT_NOT_FOUND",
-                    TenantError::InactiveTenant => "TENANT_INACTIVE",
-                    _ => "TENANT_RESOLUTION_ERROR",
-                };
-
-                let error_message = match err {
-                    TenantError::NotFound => "Tenant not found",
-                    TenantError::InactiveTenant => "Tenant account is inactive",
-                    _ => "Internal server error",
-                };
-
-                let error = ApiError::new(status_code, error_message, error_code, request_id);
-                return Ok(error.into_response());
-            },
-        };
+    let tenant_id = match resolve_tenant_id_from_all_sources(
+        &state,
+        session_tenant,
+        host,
+        tenant_header,
+        auth_header,
+        path.clone(),
+    )
+    .await
+    {
+        Ok(Some(id)) => Some(id),
+        Ok(None) => None,
+        Err(err) => {
+            // Failed to resolve tenant
+            error!(request_id = %request_id, error = %err, "Failed to resolve tenant");
+            monitoring::record_auth_operation("tenant_resolution", "failure");
+
+            let status_code = match err {
+                TenantError::NotFound => StatusCode::NOT_FOUND,
+                TenantError::InactiveTenant
+                | TenantError::SuspendedTenant(_)
+                | TenantError::Unauthorized => StatusCode::FORBIDDEN,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+
+            let error_code = match err {
+                TenantError::NotFound => "TENANT_NOT_FOUND",
+                TenantError::InactiveTenant => "TENANT_INACTIVE",
+                TenantError::SuspendedTenant(_) => "TENANT_SUSPENDED",
+                TenantError::Unauthorized => "TENANT_MISMATCH",
+                _ => "TENANT_RESOLUTION_ERROR",
+            };
+
+            let error_message = match err {
+                TenantError::NotFound => "Tenant not found",
+                TenantError::InactiveTenant => "Tenant account is archived",
+                TenantError::SuspendedTenant(_) => "Tenant account is suspended",
+                TenantError::Unauthorized => "Session is bound to a different tenant",
+                _ => "Internal server error",
+            };
+
+            let error = ApiError::new(status_code, error_message, error_code, request_id);
+            return Ok(error.into_response());
+        },
+    };
 
     match tenant_id {
         Some(tenant_id) => {
             // Tenant ID found, load tenant information
             match state.tenant_repository.find_tenant_by_id(tenant_id).await {
                 Ok(Some(tenant)) => {
-                    if !tenant.is_active {
-                        // Tenant exists but is inactive
-                        error!(
-                            request_id = %request_id,
-                            tenant_id = %tenant_id,
-                            "Tenant is inactive"
-                        );
-                        monitoring::record_auth_operation("tenant_resolution", "failure");
-
-                        let error = ApiError::new(
-                            StatusCode::FORBIDDEN,
-                            "Tenant account is inactive",
-                            "TENANT_INACTIVE",
-                            request_id.clone(),
-                        );
-                        return Ok(error.into_response());
+                    match &tenant.status {
+                        status if state.config.allows_path(status, &path) => {},
+                        TenantStatus::Suspended { reason, .. } => {
+                            // Tenant is suspended, only its admin and billing paths stay reachable
+                            warn!(
+                                request_id = %request_id,
+                                tenant_id = %tenant_id,
+                                reason = %reason,
+                                "Tenant is suspended"
+                            );
+                            monitoring::record_auth_operation("tenant_resolution", "suspended");
+
+                            #[allow(clippy::disallowed_methods)]
+                            let details = serde_json::json!({ "reason": reason });
+                            let error = ApiError::new(
+                                StatusCode::FORBIDDEN,
+                                format!("Tenant account is suspended: {}", reason),
+                                "TENANT_SUSPENDED",
+                                request_id.clone(),
+                            )
+                            .with_details(details);
+                            return Ok(error.into_response());
+                        },
+                        _ => {
+                            // Tenant is archived, only its exports stay reachable
+                            error!(
+                                request_id = %request_id,
+                                tenant_id = %tenant_id,
+                                "Tenant is archived"
+                            );
+                            monitoring::record_auth_operation("tenant_resolution", "failure");
+
+                            let error = ApiError::new(
+                                StatusCode::FORBIDDEN,
+                                "Tenant account is archived",
+                                "TENANT_INACTIVE",
+                                request_id.clone(),
+                            );
+                            return Ok(error.into_response());
+                        },
                     }
 
                     // Create tenant context and add it to request extensions
@@ -247,19 +327,34 @@ pub async fn tenant_resolution_middleware(
 }
 
 /// Resolves tenant ID from all possible sources in the request
+///
+/// The active tenant of the session takes precedence; a subdomain naming a
+/// different tenant is rejected with `TenantError::Unauthorized`.
 async fn resolve_tenant_id_from_all_sources(
     state: &TenantState,
+    session_tenant: Option<Uuid>,
     host: Option<String>,
     tenant_header: Option<String>,
     auth_header: Option<String>,
     path: String,
 ) -> Result<Option<Uuid>, TenantError> {
     // Try to resolve from subdomain
-    if state.config.check_subdomain && host.is_some() {
-        let host = host.expect("Host header must be present");
-        if let Some(tenant_id) = resolve_from_subdomain(state, &host).await? {
-            return Ok(Some(tenant_id));
-        }
+    let subdomain_tenant = match host {
+        Some(host) if state.config.check_subdomain => resolve_from_subdomain(state, &host).await?,
+        _ => None,
+    };
+
+    if let Some(session_tenant) = session_tenant {
+        return match subdomain_tenant {
+            Some(subdomain_tenant) if subdomain_tenant != session_tenant => {
+                Err(TenantError::Unauthorized)
+            },
+            _ => Ok(Some(session_tenant)),
+        };
+    }
+
+    if subdomain_tenant.is_some() {
+        return Ok(subdomain_tenant);
     }
 
     // Try to resolve from header
@@ -290,6 +385,31 @@ async fn resolve_tenant_id_from_all_sources(
     Ok(None)
 }
 
+/// Resolves the active tenant of the request's session
+///
+/// Missing, invalid and expired sessions yield no tenant; rejecting them is
+/// left to the authentication extractors.
+async fn resolve_from_session(
+    state: &TenantState,
+    token: Option<&str>,
+    request_id: &str,
+) -> Option<Uuid> {
+    let session_service = state.session_service.as_ref()?;
+    let token = token?;
+
+    match session_service.validate_session(token).await {
+        Ok(session) => session.and_then(|session| session.active_tenant_id()),
+        Err(err) => {
+            warn!(
+                request_id = %request_id,
+                error = %err,
+                "Failed to look up session for tenant resolution"
+            );
+            None
+        },
+    }
+}
+
 /// Resolves tenant ID from subdomain
 async fn resolve_from_subdomain(
     state: &TenantState,
@@ -357,6 +477,8 @@ fn resolve_from_jwt(auth_header: &str) -> Result<Option<Uuid>, TenantError> {
     // will happen in the authentication middleware
     let mut validation = Validation::default();
     validation.insecure_disable_signature_validation();
+    // The tenant audience is checked along with the signature
+    validation.validate_aud = false;
 
     let token_data = match decode::<TenantClaims>(
         token,
@@ -401,3 +523,27 @@ async fn resolve_from_path(state: &TenantState, path: &str) -> Result<Option<Uui
         None => Ok(None),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::OffsetDateTime;
+
+    #[test]
+    fn test_allows_path_by_status() {
+        let config = TenantResolutionConfig::default();
+        let suspended = TenantStatus::Suspended {
+            reason: "Payment failed".to_string(),
+            since: OffsetDateTime::now_utc(),
+        };
+        let archived = TenantStatus::Archived {
+            since: OffsetDateTime::now_utc(),
+        };
+
+        assert!(config.allows_path(&TenantStatus::Active, "/api/v1/users"));
+        assert!(config.allows_path(&suspended, "/api/v1/auth/login"));
+        assert!(!config.allows_path(&suspended, "/api/v1/users"));
+        assert!(config.allows_path(&archived, "/api/v1/users/42/sessions/export"));
+        assert!(!config.allows_path(&archived, "/api/v1/auth/login"));
+    }
+}
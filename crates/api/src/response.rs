@@ -1,12 +1,16 @@
+use crate::config::CacheConfig;
 use crate::monitoring;
+use crate::validation::generate_request_id;
+use acci_core::error::{Error as CoreError, ErrorKind};
 use axum::{
     Json,
-    http::StatusCode,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
     response::{IntoResponse, Response},
 };
 use serde::{Deserialize, Serialize};
 use std::error::Error as StdError;
 use std::fmt;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use tracing::{error, info, warn};
 
 /// Standardized API response format
@@ -14,7 +18,7 @@ use tracing::{error, info, warn};
 pub struct ApiResponse<T> {
     /// Response status (success or error)
     pub status: ResponseStatus,
-    /// Response data (only for successful responses)
+    /// Response data (for successful responses and errors carrying a payload)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<T>,
     /// Error message (only for error responses)
@@ -23,6 +27,9 @@ pub struct ApiResponse<T> {
     /// Error code (only for error responses)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub code: Option<String>,
+    /// Structured detail of an error, e.g. the invalid fields or the limit that was hit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
     /// Request ID for tracing
     pub request_id: String,
 }
@@ -45,6 +52,7 @@ impl<T> ApiResponse<T> {
             data: Some(data),
             message: None,
             code: None,
+            details: None,
             request_id: request_id.into(),
         }
     }
@@ -60,9 +68,45 @@ impl<T> ApiResponse<T> {
             data: None,
             message: Some(message.into()),
             code: Some(code.into()),
+            details: None,
             request_id: request_id.into(),
         }
     }
+
+    /// Creates an error response that still carries data, e.g. a restricted session token
+    pub fn error_with_data(
+        data: T,
+        message: impl Into<String>,
+        code: impl Into<String>,
+        request_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            status: ResponseStatus::Error,
+            data: Some(data),
+            message: Some(message.into()),
+            code: Some(code.into()),
+            details: None,
+            request_id: request_id.into(),
+        }
+    }
+
+    /// Adds structured detail to an error response
+    ///
+    /// Empty detail (`null`, `{}` or `[]`) is omitted from the response.
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = non_empty_details(details);
+        self
+    }
+}
+
+/// `details`, unless it is `null` or an empty object or array
+fn non_empty_details(details: serde_json::Value) -> Option<serde_json::Value> {
+    match &details {
+        serde_json::Value::Null => None,
+        serde_json::Value::Object(fields) if fields.is_empty() => None,
+        serde_json::Value::Array(items) if items.is_empty() => None,
+        _ => Some(details),
+    }
 }
 
 /// Transforms any error message into a standardized API error response
@@ -132,10 +176,23 @@ impl ApiError {
         details: Option<serde_json::Value>,
     ) -> Self {
         let mut error = Self::new(status_code, message, code, request_id);
-        error.details = details;
+        error.details = details.and_then(non_empty_details);
         error
     }
 
+    /// Adds structured detail for the client
+    ///
+    /// The detail is sent as is, so it must not contain internal data.
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = non_empty_details(details);
+        self
+    }
+
+    /// Machine-readable code of the error, e.g. `INVALID_CREDENTIALS`
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
     /// Creates an internal server error
     pub fn internal_server_error(request_id: impl Into<String>) -> Self {
         Self {
@@ -193,6 +250,86 @@ impl ApiError {
     }
 }
 
+impl ApiError {
+    /// Creates the error response for a domain error
+    ///
+    /// Status and code follow from the error's [`ErrorKind`]. Client errors
+    /// carry the error's message and details; server errors a generic message
+    /// and no details, so internals do not leak. Timeouts are marked as
+    /// `retryable` in the details.
+    pub fn from_error(err: impl Into<CoreError>, request_id: impl Into<String>) -> Self {
+        let err = err.into();
+        let (status_code, code) = error_status_and_code(err.kind());
+        let message = match status_code {
+            StatusCode::SERVICE_UNAVAILABLE => "The service is temporarily unavailable".to_string(),
+            StatusCode::GATEWAY_TIMEOUT => "The request timed out and may be retried".to_string(),
+            status if status.is_server_error() => "An internal server error occurred".to_string(),
+            _ => err.to_string(),
+        };
+        if status_code == StatusCode::GATEWAY_TIMEOUT {
+            warn!(error = %err, "Domain error mapped to a timeout");
+            #[allow(clippy::disallowed_methods)]
+            let details = serde_json::json!({ "retryable": true });
+            return Self::new(status_code, message, code, request_id).with_details(details);
+        }
+        if status_code.is_server_error() {
+            error!(error = %err, "Domain error mapped to a server error");
+            return Self::new(status_code, message, code, request_id);
+        }
+        let details = err.details().cloned();
+        let error = Self::new(status_code, message, code, request_id);
+        match details {
+            Some(details) => error.with_details(details),
+            None => error,
+        }
+    }
+}
+
+impl From<CoreError> for ApiError {
+    fn from(err: CoreError) -> Self {
+        Self::from_error(err, generate_request_id())
+    }
+}
+
+/// Status and code the API answers errors of `kind` with
+pub fn error_status_and_code(kind: ErrorKind) -> (StatusCode, &'static str) {
+    match kind {
+        ErrorKind::InvalidInput => (StatusCode::BAD_REQUEST, "VALIDATION_ERROR"),
+        ErrorKind::WeakPassword => (StatusCode::BAD_REQUEST, "WEAK_PASSWORD"),
+        ErrorKind::PasswordReused => (StatusCode::BAD_REQUEST, "PASSWORD_REUSED"),
+        ErrorKind::PasswordExpired => (StatusCode::FORBIDDEN, "PASSWORD_EXPIRED"),
+        ErrorKind::InvalidCredentials => (StatusCode::UNAUTHORIZED, "INVALID_CREDENTIALS"),
+        ErrorKind::Unauthenticated => (StatusCode::UNAUTHORIZED, "AUTHENTICATION_REQUIRED"),
+        ErrorKind::SessionExpired => (StatusCode::UNAUTHORIZED, "SESSION_EXPIRED"),
+        ErrorKind::Forbidden => (StatusCode::FORBIDDEN, "AUTHORIZATION_ERROR"),
+        ErrorKind::AccountLocked => (StatusCode::FORBIDDEN, "ACCOUNT_LOCKED"),
+        ErrorKind::AccountUnverified => (StatusCode::FORBIDDEN, "ACCOUNT_UNVERIFIED"),
+        ErrorKind::MfaEnrollmentRequired => (StatusCode::FORBIDDEN, "MFA_ENROLLMENT_REQUIRED"),
+        ErrorKind::MfaEnrollmentExpired => (StatusCode::FORBIDDEN, "MFA_ENROLLMENT_EXPIRED"),
+        ErrorKind::LoginLocationBlocked => (StatusCode::FORBIDDEN, "LOGIN_LOCATION_BLOCKED"),
+        ErrorKind::TenantAccessDenied => (StatusCode::FORBIDDEN, "TENANT_ACCESS_DENIED"),
+        ErrorKind::TenantInactive => (StatusCode::FORBIDDEN, "TENANT_INACTIVE"),
+        ErrorKind::TenantSuspended => (StatusCode::FORBIDDEN, "TENANT_SUSPENDED"),
+        ErrorKind::SubscriptionExpired => (StatusCode::PAYMENT_REQUIRED, "SUBSCRIPTION_EXPIRED"),
+        ErrorKind::NotFound => (StatusCode::NOT_FOUND, "RESOURCE_NOT_FOUND"),
+        ErrorKind::UserNotFound => (StatusCode::NOT_FOUND, "USER_NOT_FOUND"),
+        ErrorKind::TenantNotFound => (StatusCode::NOT_FOUND, "TENANT_NOT_FOUND"),
+        ErrorKind::UserAlreadyExists => (StatusCode::CONFLICT, "USER_ALREADY_EXISTS"),
+        ErrorKind::TenantAlreadyExists => (StatusCode::CONFLICT, "TENANT_ALREADY_EXISTS"),
+        ErrorKind::LimitExceeded => (StatusCode::CONFLICT, "TENANT_LIMIT_EXCEEDED"),
+        ErrorKind::SeatLimitReached => (StatusCode::CONFLICT, "SEAT_LIMIT_REACHED"),
+        ErrorKind::InvalidCode => (StatusCode::BAD_REQUEST, "INVALID_CODE"),
+        ErrorKind::CodeExpired => (StatusCode::BAD_REQUEST, "CODE_EXPIRED"),
+        ErrorKind::TooManyAttempts => (StatusCode::BAD_REQUEST, "TOO_MANY_ATTEMPTS"),
+        ErrorKind::RateLimited => (StatusCode::TOO_MANY_REQUESTS, "RATE_LIMIT_EXCEEDED"),
+        ErrorKind::Unavailable => (StatusCode::SERVICE_UNAVAILABLE, "SERVICE_UNAVAILABLE"),
+        ErrorKind::Timeout => (StatusCode::GATEWAY_TIMEOUT, "TIMEOUT"),
+        ErrorKind::Configuration | ErrorKind::Internal => {
+            (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_SERVER_ERROR")
+        },
+    }
+}
+
 impl fmt::Debug for ApiError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ApiError")
@@ -200,6 +337,7 @@ impl fmt::Debug for ApiError {
             .field("message", &self.message)
             .field("code", &self.code)
             .field("request_id", &self.request_id)
+            .field("details", &self.details)
             .finish()
     }
 }
@@ -221,29 +359,9 @@ impl IntoResponse for ApiError {
             "Sending error response"
         );
 
-        #[cfg(feature = "extended_errors")]
-        {
-            let error_response = if let Some(details) = self.details {
-                let response = ApiResponse::<()>::error(self.message, self.code, self.request_id);
-                // Wir erstellen ein zusätzliches Feld "details" in der API-Antwort
-                let response_value = serde_json::to_value(&response).unwrap_or_default();
-                if let serde_json::Value::Object(mut obj) = response_value {
-                    obj.insert("details".to_string(), details);
-                    return (self.status_code, Json(obj)).into_response();
-                }
-                response
-            } else {
-                ApiResponse::<()>::error(self.message, self.code, self.request_id)
-            };
-
-            (self.status_code, Json(error_response)).into_response()
-        }
-
-        #[cfg(not(feature = "extended_errors"))]
-        {
-            let error_response = ApiResponse::<()>::error(self.message, self.code, self.request_id);
-            (self.status_code, Json(error_response)).into_response()
-        }
+        let mut error_response = ApiResponse::<()>::error(self.message, self.code, self.request_id);
+        error_response.details = self.details;
+        (self.status_code, Json(error_response)).into_response()
     }
 }
 
@@ -327,9 +445,63 @@ impl<T: Serialize> IntoResponse for ApiResponse<T> {
     }
 }
 
+/// Weak ETag (`W/"..."`) of a resource version
+///
+/// `version` should cover everything the response is rendered from, e.g. the
+/// id and `updated_at` of a row. Tags are stable across processes running the
+/// same build.
+pub fn weak_etag(version: &impl Hash) -> String {
+    let mut hasher = DefaultHasher::new();
+    version.hash(&mut hasher);
+    format!("W/\"{:016x}\"", hasher.finish())
+}
+
+/// Answers a conditional GET whose `If-None-Match` header matches `etag`
+///
+/// Returns a `304 Not Modified` without body, or `None` if the full response
+/// has to be sent. Tags are compared weakly, as `If-None-Match` requires.
+pub fn maybe_not_modified(etag: &str, headers: &HeaderMap) -> Option<Response> {
+    let opaque_tag = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let current = opaque_tag(etag);
+    let matched = headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| tag.trim() == "*" || opaque_tag(tag) == current);
+
+    matched.then(|| {
+        let mut response = StatusCode::NOT_MODIFIED.into_response();
+        if let Ok(value) = HeaderValue::from_str(etag) {
+            response.headers_mut().insert(header::ETAG, value);
+        }
+        response
+    })
+}
+
+/// Lets the client, but no shared cache, keep a response for `max_age`
+///
+/// Sets the `ETag` the client revalidates the response with. Responses vary
+/// by the `Authorization` header, so a cached response is never served to
+/// another session.
+pub fn with_private_cache(mut response: Response, etag: &str, config: &CacheConfig) -> Response {
+    let headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(etag) {
+        headers.insert(header::ETAG, value);
+    }
+    if let Ok(value) =
+        HeaderValue::from_str(&format!("private, max-age={}", config.max_age.as_secs()))
+    {
+        headers.insert(header::CACHE_CONTROL, value);
+    }
+    headers.insert(header::VARY, HeaderValue::from_static("authorization"));
+    response
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use acci_auth::services::tenant::TenantServiceError;
     use serde_json::json;
 
     #[test]
@@ -361,6 +533,20 @@ mod tests {
         assert!(response.data.is_none());
     }
 
+    #[test]
+    fn test_api_response_error_with_data() {
+        let response = ApiResponse::error_with_data(
+            "restricted-token",
+            "MFA enrollment required",
+            "MFA_ENROLLMENT_REQUIRED",
+            "req-123",
+        );
+
+        assert_eq!(response.status, ResponseStatus::Error);
+        assert_eq!(response.data, Some("restricted-token"));
+        assert_eq!(response.code.unwrap(), "MFA_ENROLLMENT_REQUIRED");
+    }
+
     #[test]
     fn test_api_error_creation() {
         let status_code = StatusCode::BAD_REQUEST;
@@ -445,6 +631,29 @@ mod tests {
         assert_eq!(json_response.0.data.unwrap(), 42);
     }
 
+    #[test]
+    fn test_maybe_not_modified() {
+        let etag = weak_etag(&("tenant", 1));
+        assert!(etag.starts_with("W/\""));
+        assert_ne!(etag, weak_etag(&("tenant", 2)));
+
+        let mut headers = HeaderMap::new();
+        assert!(maybe_not_modified(&etag, &headers).is_none());
+
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"other\""));
+        assert!(maybe_not_modified(&etag, &headers).is_none());
+
+        // Any tag of the list matches, and the weak prefix is ignored
+        let strong = etag.trim_start_matches("W/");
+        headers.insert(
+            header::IF_NONE_MATCH,
+            HeaderValue::from_str(&format!("\"other\", {}", strong)).unwrap(),
+        );
+        let response = maybe_not_modified(&etag, &headers).expect("Tag matches");
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+    }
+
     #[test]
     fn test_result_ext_err() {
         let result: Result<i32, &str> = Err("Test error");
@@ -458,4 +667,142 @@ mod tests {
         assert_eq!(error.code, "INTERNAL_SERVER_ERROR");
         assert!(error.message.contains("Test error"));
     }
+
+    #[test]
+    fn test_error_kind_mapping() {
+        let table = [
+            (ErrorKind::InvalidInput, 400, "VALIDATION_ERROR"),
+            (ErrorKind::WeakPassword, 400, "WEAK_PASSWORD"),
+            (ErrorKind::PasswordReused, 400, "PASSWORD_REUSED"),
+            (ErrorKind::PasswordExpired, 403, "PASSWORD_EXPIRED"),
+            (ErrorKind::InvalidCredentials, 401, "INVALID_CREDENTIALS"),
+            (ErrorKind::Unauthenticated, 401, "AUTHENTICATION_REQUIRED"),
+            (ErrorKind::SessionExpired, 401, "SESSION_EXPIRED"),
+            (ErrorKind::Forbidden, 403, "AUTHORIZATION_ERROR"),
+            (ErrorKind::AccountLocked, 403, "ACCOUNT_LOCKED"),
+            (ErrorKind::AccountUnverified, 403, "ACCOUNT_UNVERIFIED"),
+            (
+                ErrorKind::MfaEnrollmentRequired,
+                403,
+                "MFA_ENROLLMENT_REQUIRED",
+            ),
+            (
+                ErrorKind::MfaEnrollmentExpired,
+                403,
+                "MFA_ENROLLMENT_EXPIRED",
+            ),
+            (
+                ErrorKind::LoginLocationBlocked,
+                403,
+                "LOGIN_LOCATION_BLOCKED",
+            ),
+            (ErrorKind::TenantAccessDenied, 403, "TENANT_ACCESS_DENIED"),
+            (ErrorKind::TenantInactive, 403, "TENANT_INACTIVE"),
+            (ErrorKind::TenantSuspended, 403, "TENANT_SUSPENDED"),
+            (ErrorKind::SubscriptionExpired, 402, "SUBSCRIPTION_EXPIRED"),
+            (ErrorKind::NotFound, 404, "RESOURCE_NOT_FOUND"),
+            (ErrorKind::UserNotFound, 404, "USER_NOT_FOUND"),
+            (ErrorKind::TenantNotFound, 404, "TENANT_NOT_FOUND"),
+            (ErrorKind::UserAlreadyExists, 409, "USER_ALREADY_EXISTS"),
+            (ErrorKind::TenantAlreadyExists, 409, "TENANT_ALREADY_EXISTS"),
+            (ErrorKind::LimitExceeded, 409, "TENANT_LIMIT_EXCEEDED"),
+            (ErrorKind::SeatLimitReached, 409, "SEAT_LIMIT_REACHED"),
+            (ErrorKind::InvalidCode, 400, "INVALID_CODE"),
+            (ErrorKind::CodeExpired, 400, "CODE_EXPIRED"),
+            (ErrorKind::TooManyAttempts, 400, "TOO_MANY_ATTEMPTS"),
+            (ErrorKind::RateLimited, 429, "RATE_LIMIT_EXCEEDED"),
+            (ErrorKind::Unavailable, 503, "SERVICE_UNAVAILABLE"),
+            (ErrorKind::Timeout, 504, "TIMEOUT"),
+            (ErrorKind::Configuration, 500, "INTERNAL_SERVER_ERROR"),
+            (ErrorKind::Internal, 500, "INTERNAL_SERVER_ERROR"),
+        ];
+
+        // A new kind has to be added to the table with its intended response
+        for kind in ErrorKind::ALL {
+            assert_eq!(
+                table.iter().filter(|(entry, ..)| *entry == kind).count(),
+                1,
+                "{kind:?} must appear exactly once in the table"
+            );
+        }
+        for (kind, status, code) in table {
+            assert_eq!(
+                error_status_and_code(kind),
+                (StatusCode::from_u16(status).unwrap(), code),
+                "{kind:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_api_error_from_domain_error() {
+        let error = ApiError::from_error(
+            CoreError::domain(ErrorKind::TenantNotFound, "Tenant not found"),
+            "req-123",
+        );
+        assert_eq!(error.status_code, StatusCode::NOT_FOUND);
+        assert_eq!(error.code, "TENANT_NOT_FOUND");
+        assert_eq!(error.message, "Tenant not found");
+        assert_eq!(error.request_id, "req-123");
+
+        // Server errors do not expose their details
+        let error = ApiError::from(CoreError::Config("DATABASE_URL is not set".to_string()));
+        assert_eq!(error.status_code, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(error.message, "An internal server error occurred");
+    }
+
+    async fn response_body(error: ApiError) -> serde_json::Value {
+        let response = error.into_response();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Failed to read body");
+        serde_json::from_slice(&bytes).expect("Body is JSON")
+    }
+
+    #[tokio::test]
+    async fn test_limit_exceeded_error_carries_details() {
+        let error = ApiError::from_error(
+            TenantServiceError::TenantLimitExceeded {
+                message: "User limit of 20 reached".to_string(),
+                limit: 20,
+                current: 20,
+            },
+            "req-123",
+        );
+        let body = response_body(error).await;
+        assert_eq!(body["code"], "SEAT_LIMIT_REACHED");
+        assert_eq!(body["details"], json!({ "limit": 20, "current": 20 }));
+
+        // Neither the internal message nor details of a server error are sent
+        let error = ApiError::from_error(
+            CoreError::domain(ErrorKind::Internal, "connection to 10.0.0.5 refused")
+                .with_details(json!({ "host": "10.0.0.5" })),
+            "req-123",
+        );
+        let body = response_body(error).await;
+        assert_eq!(body["code"], "INTERNAL_SERVER_ERROR");
+        assert_eq!(body["message"], "An internal server error occurred");
+        assert!(body.get("details").is_none());
+
+        // Empty details are omitted
+        let error = ApiError::validation_error("Invalid input", "req-123").with_details(json!({}));
+        assert!(response_body(error).await.get("details").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_timeout_error_is_retryable() {
+        let error = ApiError::from_error(
+            CoreError::domain(
+                ErrorKind::Timeout,
+                "user.repo.find_by_id timed out after 5s",
+            ),
+            "req-123",
+        );
+        assert_eq!(error.status_code, StatusCode::GATEWAY_TIMEOUT);
+
+        let body = response_body(error).await;
+        assert_eq!(body["code"], "TIMEOUT");
+        assert_eq!(body["message"], "The request timed out and may be retried");
+        assert_eq!(body["details"], json!({ "retryable": true }));
+    }
 }
@@ -1,15 +1,50 @@
 use crate::config::ApiConfig;
-use crate::handlers::auth::{ApiAppState, api_login, api_register, validate_token};
+use crate::handlers::access_revocation::{AccessRevocationAppState, revoke_user_access};
+use crate::handlers::account_deletion::{
+    AccountDeletionAppState, cancel_account_deletion, request_account_deletion,
+};
+use crate::handlers::admin::{AdminOverviewAppState, get_admin_overview};
+use crate::handlers::api_keys::{ApiKeyAppState, list_api_keys, rotate_api_key};
+use crate::handlers::auth::{
+    ApiAppState, api_login, api_register, get_mfa_status, reauthenticate, reset_password,
+    switch_tenant, validate_token,
+};
+use crate::handlers::delivery_receipts::{
+    DeliveryReceiptAppState, sendgrid_delivery_receipt, twilio_delivery_receipt,
+    vonage_delivery_receipt, vonage_delivery_receipt_form,
+};
+use crate::handlers::export::{export_login_history, export_sessions};
+use crate::handlers::factors::{list_factors, remove_factor};
+use crate::handlers::health::get_session_health;
+use crate::handlers::impersonation::{end_impersonation, start_impersonation};
+use crate::handlers::me::{MeAppState, get_me};
+use crate::handlers::messages::{MessageAdminAppState, list_failed_messages};
+use crate::handlers::oidc::{OidcAppState, oidc_authorize, oidc_callback};
+use crate::handlers::rollout::{RolloutAdminAppState, list_rollout_flags, set_rollout_flag};
+use crate::handlers::saml::{SamlAppState, saml_acs, saml_login};
+use crate::handlers::scim::{
+    ScimAppState, create_scim_group, create_scim_user, delete_scim_group, delete_scim_user,
+    get_scim_group, get_scim_user, list_scim_groups, list_scim_users, patch_scim_group,
+    patch_scim_user, replace_scim_group, replace_scim_user,
+};
+use crate::handlers::telemetry::{TracingAdminAppState, set_tracing_override};
 use crate::handlers::tenant::{
-    TenantAppState, create_tenant, create_tenant_with_admin, delete_tenant, get_tenant,
-    get_tenant_by_id, update_tenant,
+    TenantAppState, create_tenant, create_tenant_with_admin, delete_tenant, get_login_restrictions,
+    get_subscription_history, get_tenant, get_tenant_by_id, update_login_restrictions,
+    update_tenant,
+};
+use crate::handlers::timeline::{TimelineAppState, get_my_timeline, get_user_timeline};
+use crate::handlers::trusted_devices::{revoke_trusted_device, trust_device};
+use crate::handlers::verification::{
+    VerificationAppState, VerificationStatsAppState, get_verification_stats, send_verification,
+    verify_code,
 };
-use crate::handlers::verification::{VerificationAppState, send_verification, verify_code};
 #[cfg(feature = "enable_webauthn")]
 use crate::handlers::webauthn::WebAuthnAppState;
 use crate::response::ApiResponse;
+use crate::versioning::{ApiVersion, VersionedRouter};
 use axum::{
-    Json, Router,
+    Extension, Json, Router,
     http::StatusCode,
     middleware,
     routing::{delete, get, post, put},
@@ -27,11 +62,24 @@ impl ApiRouter {
     }
 
     /// Creates the Axum router for the API with the provided app states
+    #[allow(clippy::too_many_arguments)]
     pub fn create_router_with_state(
         &self,
         auth_state: ApiAppState,
         tenant_state: Option<TenantAppState>,
         verification_state: Option<VerificationAppState>,
+        message_admin_state: Option<MessageAdminAppState>,
+        delivery_receipt_state: Option<DeliveryReceiptAppState>,
+        oidc_state: Option<OidcAppState>,
+        tracing_admin_state: Option<TracingAdminAppState>,
+        saml_state: Option<SamlAppState>,
+        scim_state: Option<ScimAppState>,
+        account_deletion_state: Option<AccountDeletionAppState>,
+        timeline_state: Option<TimelineAppState>,
+        admin_overview_state: Option<AdminOverviewAppState>,
+        rollout_admin_state: Option<RolloutAdminAppState>,
+        access_revocation_state: Option<AccessRevocationAppState>,
+        api_key_state: Option<ApiKeyAppState>,
         #[cfg(feature = "enable_webauthn")] webauthn_state: Option<WebAuthnAppState>,
         #[cfg(not(feature = "enable_webauthn"))] _webauthn_state: Option<()>,
     ) -> Router {
@@ -40,8 +88,29 @@ impl ApiRouter {
             .route("/login", post(api_login))
             .route("/register", post(api_register))
             .route("/validate-token", post(validate_token))
+            .route("/mfa/status", get(get_mfa_status))
+            .route("/switch-tenant", post(switch_tenant))
+            .route("/reauthenticate", post(reauthenticate))
+            .route("/reset-password", post(reset_password))
+            .route("/factors", get(list_factors))
+            .route("/factors/{id}", delete(remove_factor))
+            .route("/devices/trust", post(trust_device))
+            .route("/devices/{id}/trust", delete(revoke_trusted_device))
             .with_state(auth_state.clone());
 
+        // Create the verification statistics route; the admin check needs the tenant service
+        let verification_stats_routes = match (&tenant_state, &verification_state) {
+            (Some(tenant_state), Some(verification_state)) => Router::new()
+                .route("/{id}/verification/stats", get(get_verification_stats))
+                .with_state(VerificationStatsAppState {
+                    verification_service: verification_state.verification_service.clone(),
+                    session_service: tenant_state.session_service.clone(),
+                    tenant_service: tenant_state.tenant_service.clone(),
+                    tenant_context: verification_state.tenant_context.clone(),
+                }),
+            _ => Router::new(),
+        };
+
         // Create verification routes if verification state is provided
         let verification_routes = if let Some(verification_state) = verification_state {
             Router::new()
@@ -52,6 +121,78 @@ impl ApiRouter {
             Router::new()
         };
 
+        // Create self-service account deletion routes if account deletion is enabled
+        let account_deletion_routes = if let Some(account_deletion_state) = account_deletion_state {
+            Router::new()
+                .route("/me/delete", post(request_account_deletion))
+                .route("/me/delete/cancel", post(cancel_account_deletion))
+                .with_state(account_deletion_state)
+        } else {
+            Router::new()
+        };
+
+        // Create activity timeline routes; the admin route needs the tenant service
+        let (my_timeline_routes, user_timeline_routes) = match timeline_state {
+            Some(timeline_state) => (
+                Router::new()
+                    .route("/me/timeline", get(get_my_timeline))
+                    .with_state(timeline_state.clone()),
+                match &tenant_state {
+                    Some(tenant_state) => Router::new()
+                        .route("/{id}/users/{user_id}/timeline", get(get_user_timeline))
+                        .with_state(TimelineAppState {
+                            tenant_service: Some(tenant_state.tenant_service.clone()),
+                            ..timeline_state
+                        }),
+                    None => Router::new(),
+                },
+            ),
+            None => (Router::new(), Router::new()),
+        };
+
+        // Create API key routes; the admin check needs the tenant memberships
+        let api_key_routes = match (api_key_state, &tenant_state) {
+            (Some(api_key_state), Some(tenant_state)) => Router::new()
+                .route("/{id}/api-keys", get(list_api_keys))
+                .route("/{id}/api-keys/{key_id}/rotate", post(rotate_api_key))
+                .with_state(ApiKeyAppState {
+                    tenant_service: Some(tenant_state.tenant_service.clone()),
+                    ..api_key_state
+                }),
+            _ => Router::new(),
+        };
+
+        // Create external login routes if identity providers are configured
+        let oidc_routes = if let Some(oidc_state) = oidc_state {
+            Router::new()
+                .route("/{provider}/authorize", get(oidc_authorize))
+                .route("/{provider}/callback", get(oidc_callback))
+                .with_state(oidc_state)
+        } else {
+            Router::new()
+        };
+
+        // Create SAML single sign-on routes if a service provider is configured
+        let saml_routes = if let Some(saml_state) = saml_state {
+            Router::new()
+                .route("/{tenant_id}/login", get(saml_login))
+                .route("/{tenant_id}/acs", post(saml_acs))
+                .with_state(saml_state)
+        } else {
+            Router::new()
+        };
+
+        // Create the session info route; tenant memberships need the tenant service
+        let me_routes = Router::new()
+            .route("/me", get(get_me))
+            .with_state(MeAppState {
+                user_service: auth_state.user_service.clone(),
+                session_service: auth_state.session_service.clone(),
+                tenant_service: tenant_state
+                    .as_ref()
+                    .map(|tenant_state| tenant_state.tenant_service.clone()),
+            });
+
         // Create tenant routes if tenant state is provided
         let tenant_routes = if let Some(tenant_state) = tenant_state {
             Router::new()
@@ -59,13 +200,116 @@ impl ApiRouter {
                 .route("/", post(create_tenant))
                 .route("/", put(update_tenant))
                 .route("/with-admin", post(create_tenant_with_admin))
-                .route("/:id", get(get_tenant_by_id))
-                .route("/:id", delete(delete_tenant))
+                .route("/{id}", get(get_tenant_by_id))
+                .route("/{id}", delete(delete_tenant))
+                .route("/{id}/subscription/history", get(get_subscription_history))
+                .route("/{id}/login-restrictions", get(get_login_restrictions))
+                .route("/{id}/login-restrictions", put(update_login_restrictions))
+                .route("/{id}/sessions/export", get(export_sessions))
+                .route("/{id}/logins/export", get(export_login_history))
                 .with_state(tenant_state)
         } else {
             Router::new()
         };
 
+        // Create admin impersonation routes; authorization happens in the session service
+        let impersonation_routes = Router::new()
+            .route("/impersonation", post(start_impersonation))
+            .route("/impersonation/{session_id}", delete(end_impersonation))
+            .with_state(auth_state.clone());
+
+        // Create message administration routes if the message outbox is enabled
+        let message_admin_routes = if let Some(message_admin_state) = message_admin_state {
+            Router::new()
+                .route("/messages/failed", get(list_failed_messages))
+                .with_state(message_admin_state)
+        } else {
+            Router::new()
+        };
+
+        // Create delivery receipt callbacks if deliveries are tracked
+        let delivery_receipt_routes = if let Some(delivery_receipt_state) = delivery_receipt_state {
+            Router::new()
+                .route("/twilio", post(twilio_delivery_receipt))
+                .route(
+                    "/vonage",
+                    get(vonage_delivery_receipt).post(vonage_delivery_receipt_form),
+                )
+                .route("/sendgrid", post(sendgrid_delivery_receipt))
+                .with_state(delivery_receipt_state)
+        } else {
+            Router::new()
+        };
+
+        // Create tracing administration routes if sampling overrides are enabled
+        let tracing_admin_routes = if let Some(tracing_admin_state) = tracing_admin_state {
+            Router::new()
+                .route("/tracing/override", post(set_tracing_override))
+                .with_state(tracing_admin_state)
+        } else {
+            Router::new()
+        };
+
+        // Create the platform overview route if platform statistics are enabled
+        let admin_overview_routes = if let Some(admin_overview_state) = admin_overview_state {
+            Router::new()
+                .route("/overview", get(get_admin_overview))
+                .with_state(admin_overview_state)
+        } else {
+            Router::new()
+        };
+
+        // Create rollout flag routes if rollout flags are enabled
+        let rollout_admin_routes = if let Some(rollout_admin_state) = rollout_admin_state {
+            Router::new()
+                .route("/rollout-flags", get(list_rollout_flags))
+                .route("/rollout-flags/{name}", put(set_rollout_flag))
+                .with_state(rollout_admin_state)
+        } else {
+            Router::new()
+        };
+
+        // Create the access revocation route if a state for it is provided
+        let access_revocation_routes =
+            if let Some(access_revocation_state) = access_revocation_state {
+                Router::new()
+                    .route("/users/{id}/revoke-access", post(revoke_user_access))
+                    .with_state(access_revocation_state)
+            } else {
+                Router::new()
+            };
+
+        // Create SCIM provisioning routes if provisioning is enabled
+        let scim_routes = if let Some(scim_state) = scim_state {
+            Router::new()
+                .route("/Users", get(list_scim_users).post(create_scim_user))
+                .route(
+                    "/Users/{id}",
+                    get(get_scim_user)
+                        .put(replace_scim_user)
+                        .patch(patch_scim_user)
+                        .delete(delete_scim_user),
+                )
+                .route("/Groups", get(list_scim_groups).post(create_scim_group))
+                .route(
+                    "/Groups/{id}",
+                    get(get_scim_group)
+                        .put(replace_scim_group)
+                        .patch(patch_scim_group)
+                        .delete(delete_scim_group),
+                )
+                .with_state(scim_state)
+        } else {
+            Router::new()
+        };
+
+        let admin_routes = impersonation_routes
+            .merge(message_admin_routes)
+            .merge(tracing_admin_routes)
+            .merge(admin_overview_routes)
+            .merge(rollout_admin_routes)
+            .merge(access_revocation_routes);
+
         // Create WebAuthn routes if webauthn state is provided
         #[cfg(feature = "enable_webauthn")]
         let webauthn_routes = if let Some(webauthn_state) = webauthn_state {
@@ -93,34 +337,59 @@ impl ApiRouter {
         #[cfg(not(feature = "enable_webauthn"))]
         let webauthn_routes = Router::new();
 
-        // Create auth router with nested verification routes
+        // Create auth router with nested verification and external login routes
         let auth_router = Router::new()
             .merge(auth_routes)
-            .nest("/verify", verification_routes);
+            .merge(account_deletion_routes)
+            .merge(my_timeline_routes)
+            .nest("/verify", verification_routes)
+            .nest("/oidc", oidc_routes)
+            .nest("/saml", saml_routes);
 
-        // Create base router
-        let router = Router::new()
+        // Create the routes of the v1 contract
+        let routes = Router::new()
             // Health check
             .route("/health", get(|| async { "OK" }))
+            // Last session reconciliation of this instance
+            .route("/health/sessions", get(get_session_health))
             // Example route demonstrating the API response
             .route("/example", get(example_handler))
             // Nest auth routes (including verification routes)
             .nest("/auth", auth_router)
+            // Session info of the current user
+            .merge(me_routes)
             // Nest tenant routes if applicable
-            .nest("/tenants", tenant_routes)
+            .nest(
+                "/tenants",
+                tenant_routes
+                    .merge(verification_stats_routes)
+                    .merge(user_timeline_routes)
+                    .merge(api_key_routes),
+            )
             // Nest WebAuthn routes if applicable
             .nest("/webauthn", webauthn_routes)
+            // Nest admin routes if applicable
+            .nest("/admin", admin_routes)
+            // Nest SCIM provisioning routes if applicable
+            .nest("/scim/v2", scim_routes)
+            // Nest message provider callbacks if applicable
+            .nest("/webhooks/delivery", delivery_receipt_routes)
+            .with_state(auth_state);
 
+        let router = VersionedRouter::new()
+            .routes(ApiVersion::ALL, routes)
+            .build(&self.config.versioning)
+            // Caching of the responses that support conditional requests
+            .layer(Extension(self.config.cache.clone()))
             // Apply middleware chain (in reverse order of execution)
-            .layer(middleware::from_fn(crate::middleware::logging::logging_middleware))
-            .with_state(auth_state);
+            .layer(middleware::from_fn(
+                crate::middleware::i18n::localization_middleware,
+            ))
+            .layer(middleware::from_fn(
+                crate::middleware::logging::logging_middleware,
+            ));
 
-        // Apply base URL path
-        if self.config.base_path.is_empty() {
-            router
-        } else {
-            Router::new().nest(&self.config.base_path, router)
-        }
+        self.with_base_path(router)
     }
 
     /// Creates the Axum router for the API (without state, for compatibility)
@@ -128,16 +397,25 @@ impl ApiRouter {
     /// with create_router_with_state.
     pub fn create_router(&self) -> Router {
         // Since we don't have a state, we create a simple router without auth routes
-        let router = Router::new()
+        let routes = Router::new()
             // Health check
             .route("/health", get(|| async { "OK" }))
             // Example route demonstrating the API response
-            .route("/example", get(example_handler))
+            .route("/example", get(example_handler));
 
+        let router = VersionedRouter::new()
+            .routes(ApiVersion::ALL, routes)
+            .build(&self.config.versioning)
             // Apply middleware chain (in reverse order of execution)
-            .layer(middleware::from_fn(crate::middleware::logging::logging_middleware));
+            .layer(middleware::from_fn(
+                crate::middleware::logging::logging_middleware,
+            ));
 
-        // Apply base URL path
+        self.with_base_path(router)
+    }
+
+    /// Nests the router under the configured base path
+    fn with_base_path(&self, router: Router) -> Router {
         if self.config.base_path.is_empty() {
             router
         } else {
@@ -151,7 +429,24 @@ impl ApiRouter {
         auth_state: ApiAppState,
         tenant_state: Option<TenantAppState>,
     ) -> Router {
-        self.create_router_with_state(auth_state, tenant_state, None, None)
+        self.create_router_with_state(
+            auth_state,
+            tenant_state,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
     }
 }
 
@@ -197,3 +492,30 @@ fn current_timestamp() -> String {
     // Format as a simple timestamp
     format!("{} seconds since UNIX Epoch", now)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_routes_are_served_under_the_version_and_the_alias() {
+        let router = ApiRouter::new(ApiConfig::default()).create_router();
+
+        for path in ["/api/v1/health", "/api/health"] {
+            let response = router
+                .clone()
+                .oneshot(Request::get(path).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", path);
+            assert_eq!(
+                response.headers().contains_key("deprecation"),
+                path == "/api/health",
+                "{}",
+                path
+            );
+        }
+    }
+}
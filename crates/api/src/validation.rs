@@ -1,4 +1,5 @@
 use crate::monitoring;
+use acci_auth::{BoundedJson, JsonLimitViolation};
 use axum::{
     Json,
     extract::rejection::JsonRejection,
@@ -6,10 +7,11 @@ use axum::{
     response::{IntoResponse, Response},
 };
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 use thiserror::Error;
 use tracing::{debug, error};
-use validator::Validate;
+use validator::{Validate, ValidationErrors, ValidationErrorsKind};
 
 /// A wrapper for validated JSON requests
 #[derive(Debug, Clone, Copy, Default)]
@@ -81,6 +83,10 @@ pub enum ValidationError {
     /// Validation error
     #[error("Validation error: {0}")]
     InvalidData(String),
+
+    /// Constraint violations of a validated payload
+    #[error("Validation error: {0}")]
+    InvalidFields(ValidationErrors),
 }
 
 impl IntoResponse for ValidationError {
@@ -103,19 +109,81 @@ impl IntoResponse for ValidationError {
                 (status, Json(body)).into_response()
             },
             ValidationError::InvalidData(err) => {
-                let status = StatusCode::BAD_REQUEST;
                 monitoring::record_validation_error("validation_error", "constraint_violation");
-
+                validation_failed_response(&err, None)
+            },
+            ValidationError::InvalidFields(errors) => {
+                monitoring::record_validation_error("validation_error", "constraint_violation");
                 #[allow(clippy::disallowed_methods)]
-                let body = serde_json::to_value(serde_json::json!({
-                    "status": "error",
-                    "message": format!("Validation failed: {}", err),
-                    "code": status.as_u16(),
-                    "request_id": generate_request_id(),
-                }))
-                .expect("Failed to create JSON validation error response");
+                let details = serde_json::json!({ "fields": field_errors(&errors) });
+                validation_failed_response(&errors, Some(details))
+            },
+        }
+    }
+}
 
-                (status, Json(body)).into_response()
+/// 400 response for a payload that failed validation
+fn validation_failed_response(
+    err: &impl std::fmt::Display,
+    details: Option<serde_json::Value>,
+) -> Response {
+    let status = StatusCode::BAD_REQUEST;
+
+    #[allow(clippy::disallowed_methods)]
+    let mut body = serde_json::json!({
+        "status": "error",
+        "message": format!("Validation failed: {}", err),
+        "code": status.as_u16(),
+        "request_id": generate_request_id(),
+    });
+    if let (Some(details), Some(body)) = (details, body.as_object_mut()) {
+        body.insert("details".to_string(), details);
+    }
+
+    (status, Json(body)).into_response()
+}
+
+/// Messages of the violated constraints per field
+///
+/// Nested fields are joined with `.`, list items get their index, e.g.
+/// `address.city` or `emails[2]`. Only messages (or the constraint's code
+/// if it has none) are included, never the rejected values.
+pub fn field_errors(errors: &ValidationErrors) -> BTreeMap<String, Vec<String>> {
+    let mut fields = BTreeMap::new();
+    collect_field_errors(errors, None, &mut fields);
+    fields
+}
+
+/// Adds the violations of `errors` to `fields`, prefixing paths with `prefix`
+fn collect_field_errors(
+    errors: &ValidationErrors,
+    prefix: Option<&str>,
+    fields: &mut BTreeMap<String, Vec<String>>,
+) {
+    for (field, kind) in errors.errors() {
+        let path = match prefix {
+            Some(prefix) => format!("{}.{}", prefix, field),
+            None => field.to_string(),
+        };
+        match kind {
+            ValidationErrorsKind::Field(violations) => {
+                fields
+                    .entry(path)
+                    .or_default()
+                    .extend(violations.iter().map(|violation| {
+                        violation.message.as_ref().map_or_else(
+                            || violation.code.to_string(),
+                            |message| message.to_string(),
+                        )
+                    }));
+            },
+            ValidationErrorsKind::Struct(nested) => {
+                collect_field_errors(nested, Some(&path), fields);
+            },
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    collect_field_errors(nested, Some(&format!("{}[{}]", path, index)), fields);
+                }
             },
         }
     }
@@ -193,16 +261,46 @@ where
     T: Validate,
 {
     if let Err(validation_errors) = payload.validate() {
-        let error_message = validation_errors.to_string();
-        error!("Validation error: {}", error_message);
+        error!("Validation error: {}", validation_errors);
         monitoring::record_validation_error("payload_validation_failed", "constraint_violation");
-        return Err(ValidationError::InvalidData(error_message));
+        return Err(ValidationError::InvalidFields(validation_errors));
     }
 
     debug!("Validation succeeded");
     Ok(payload.0)
 }
 
+/// Bound the free-form metadata of a request to the installed metadata limits
+///
+/// Every exceeded limit is reported as a violation of `field`, with the limit
+/// as code and the measured and allowed values as params, so oversized
+/// metadata is rejected before it reaches a service.
+pub fn bounded_metadata(
+    field: &'static str,
+    metadata: Option<serde_json::Value>,
+) -> Result<Option<BoundedJson>, ValidationError> {
+    let Some(metadata) = metadata else {
+        return Ok(None);
+    };
+    BoundedJson::new(metadata).map(Some).map_err(|err| {
+        debug!(field, error = %err, "Metadata exceeds the limits");
+        let mut errors = ValidationErrors::new();
+        for violation in &err.violations {
+            let mut error = validator::ValidationError::new(violation.code())
+                .with_message(format!("Metadata {}", violation).into());
+            if let JsonLimitViolation::MaxBytes { actual, max }
+            | JsonLimitViolation::MaxDepth { actual, max }
+            | JsonLimitViolation::MaxKeys { actual, max } = violation
+            {
+                error.add_param("actual".into(), actual);
+                error.add_param("max".into(), max);
+            }
+            errors.add(field, error);
+        }
+        ValidationError::InvalidFields(errors)
+    })
+}
+
 /// Rate limiter middleware
 pub mod rate_limiter {
     use axum::http::Request;
@@ -368,10 +466,14 @@ mod tests {
         assert!(result.is_err());
 
         match result {
-            Err(ValidationError::InvalidData(msg)) => {
-                assert!(msg.contains("username must be at least 3 characters"));
+            Err(ValidationError::InvalidFields(errors)) => {
+                assert!(
+                    errors
+                        .to_string()
+                        .contains("username must be at least 3 characters")
+                );
             },
-            _ => panic!("Expected ValidationError::InvalidData"),
+            _ => panic!("Expected ValidationError::InvalidFields"),
         }
     }
 
@@ -384,10 +486,14 @@ mod tests {
         assert!(result.is_err());
 
         match result {
-            Err(ValidationError::InvalidData(msg)) => {
-                assert!(msg.contains("email must be a valid email address"));
+            Err(ValidationError::InvalidFields(errors)) => {
+                assert!(
+                    errors
+                        .to_string()
+                        .contains("email must be a valid email address")
+                );
             },
-            _ => panic!("Expected ValidationError::InvalidData"),
+            _ => panic!("Expected ValidationError::InvalidFields"),
         }
     }
 
@@ -400,12 +506,13 @@ mod tests {
         assert!(result.is_err());
 
         match result {
-            Err(ValidationError::InvalidData(msg)) => {
+            Err(ValidationError::InvalidFields(errors)) => {
+                let msg = errors.to_string();
                 assert!(msg.contains("username must be at least 3 characters"));
                 assert!(msg.contains("email must be a valid email address"));
                 assert!(msg.contains("password must be at least 8 characters"));
             },
-            _ => panic!("Expected ValidationError::InvalidData"),
+            _ => panic!("Expected ValidationError::InvalidFields"),
         }
     }
 
@@ -429,14 +536,22 @@ mod tests {
         assert!(result.is_err());
 
         match result {
-            Err(ValidationError::InvalidData(msg)) => {
+            Err(ValidationError::InvalidFields(errors)) => {
+                let msg = errors.to_string();
                 assert!(msg.contains("username must be at least 3 characters"));
                 assert!(msg.contains("email must be a valid email address"));
                 assert!(msg.contains("password must be at least 8 characters"));
                 assert!(msg.contains("street must be at least 5 characters"));
                 assert!(msg.contains("city cannot be empty"));
+
+                let fields = field_errors(&errors);
+                assert_eq!(
+                    fields["address.city"],
+                    vec!["city cannot be empty".to_string()]
+                );
+                assert_eq!(fields.len(), 5);
             },
-            _ => panic!("Expected ValidationError::InvalidData"),
+            _ => panic!("Expected ValidationError::InvalidFields"),
         }
     }
 
@@ -473,6 +588,90 @@ mod tests {
         assert!(error_msg.contains("Validation failed: Test validation error"));
     }
 
+    #[tokio::test]
+    async fn test_validation_error_response_lists_fields() {
+        let payload = Json(TestUser {
+            username: "jo".to_string(),
+            email: "john@example.com".to_string(),
+            password: "password123".to_string(),
+        });
+        let Err(error) = validate_json_payload(payload).await else {
+            panic!("Expected a validation error");
+        };
+
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(
+            body["details"],
+            serde_json::json!({
+                "fields": { "username": ["username must be at least 3 characters"] }
+            })
+        );
+
+        // A message-only validation error carries no details
+        let response =
+            ValidationError::InvalidData("Test validation error".to_string()).into_response();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert!(body.get("details").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_bounded_metadata_lists_exceeded_limits() {
+        assert_eq!(bounded_metadata("metadata", None).unwrap(), None);
+        let metadata = serde_json::json!({ "plan": "pro", "tags": ["a", "b"] });
+        let bounded = bounded_metadata("metadata", Some(metadata.clone()))
+            .unwrap()
+            .expect("Metadata is kept");
+        assert_eq!(bounded.into_inner(), metadata);
+
+        // Every key holds a long string, so the object is too long as well
+        let oversized: serde_json::Map<String, serde_json::Value> = (0..300)
+            .map(|i| (format!("key-{}", i), serde_json::json!("x".repeat(100))))
+            .collect();
+        let Err(error) = bounded_metadata("metadata", Some(oversized.into())) else {
+            panic!("Expected a validation error");
+        };
+
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        let messages = body["details"]["fields"]["metadata"]
+            .as_array()
+            .expect("Metadata violations are listed");
+        assert_eq!(messages.len(), 2);
+        assert!(
+            messages[0]
+                .as_str()
+                .unwrap()
+                .contains("at most 16384 are allowed")
+        );
+        assert_eq!(
+            messages[1],
+            "Metadata has 300 keys, at most 256 are allowed"
+        );
+
+        let Err(error) = bounded_metadata("metadata", Some(serde_json::json!([1, 2]))) else {
+            panic!("Expected a validation error");
+        };
+        let ValidationError::InvalidFields(errors) = error else {
+            panic!("Expected field errors");
+        };
+        assert_eq!(
+            field_errors(&errors)["metadata"],
+            vec!["Metadata must be a JSON object".to_string()]
+        );
+    }
+
     #[tokio::test]
     async fn test_json_error_handling() {
         let error = ValidationError::InvalidData("Test validation error".to_string());
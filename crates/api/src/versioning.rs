@@ -0,0 +1,286 @@
+//! API versioning
+//!
+//! Routes are registered into version groups that are served under
+//! `<base path>/<version>`, e.g. `/api/v1/auth/login`. The paths without a
+//! version segment that predate versioning (`/api/auth/login`) stay available
+//! as aliases of [`ApiVersion::V1`]; their responses carry `Deprecation` and
+//! `Sunset` headers so clients notice before the aliases are removed.
+
+use crate::config::VersioningConfig;
+use axum::{
+    Router,
+    extract::{Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue},
+    middleware::{self, Next},
+    response::Response,
+    routing::MethodRouter,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+use time::UtcOffset;
+use time::macros::format_description;
+
+/// Version of the API contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ApiVersion {
+    V1,
+}
+
+impl ApiVersion {
+    /// All versions, oldest first
+    pub const ALL: &'static [ApiVersion] = &[ApiVersion::V1];
+
+    /// Version new clients should use
+    pub const LATEST: ApiVersion = ApiVersion::V1;
+
+    /// Path segment of the version, e.g. `v1`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::V1 => "v1",
+        }
+    }
+
+    /// Path the routes of this version are served under, e.g. `/api/v1`
+    pub fn prefix(self, base_path: &str) -> String {
+        format!("{}/{}", base_path.trim_end_matches('/'), self.as_str())
+    }
+
+    /// Path of a route of this version, e.g. `/api/v1/auth/verify/code`
+    ///
+    /// Links the framework hands out must be built with this rather than
+    /// from the alias paths, which are removed after their sunset.
+    pub fn path(self, base_path: &str, path: &str) -> String {
+        format!(
+            "{}/{}",
+            self.prefix(base_path),
+            path.trim_start_matches('/')
+        )
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Builder of the version groups of the API
+///
+/// Routes whose contract is the same in every version are added once with
+/// [`VersionedRouter::routes`]. When the DTOs of a route diverge, each
+/// version gets its own handler with [`VersionedRouter::route_per_version`].
+#[derive(Default)]
+pub struct VersionedRouter {
+    groups: BTreeMap<ApiVersion, Router>,
+}
+
+impl VersionedRouter {
+    /// Creates a builder without any routes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds routes to each of `versions`
+    pub fn routes(mut self, versions: &[ApiVersion], routes: Router) -> Self {
+        for &version in versions {
+            let group = self.groups.remove(&version).unwrap_or_default();
+            self.groups.insert(version, group.merge(routes.clone()));
+        }
+        self
+    }
+
+    /// Adds a path whose handler differs between versions
+    ///
+    /// ```rust,ignore
+    /// VersionedRouter::new().route_per_version(
+    ///     "/tenants",
+    ///     [
+    ///         (ApiVersion::V1, post(create_tenant).with_state(state.clone())),
+    ///         (ApiVersion::V2, post(create_tenant_v2).with_state(state)),
+    ///     ],
+    /// )
+    /// ```
+    pub fn route_per_version(
+        mut self,
+        path: &str,
+        handlers: impl IntoIterator<Item = (ApiVersion, MethodRouter)>,
+    ) -> Self {
+        for (version, handler) in handlers {
+            let group = self.groups.remove(&version).unwrap_or_default();
+            self.groups.insert(version, group.route(path, handler));
+        }
+        self
+    }
+
+    /// Combines the version groups into one router
+    ///
+    /// Every group is nested under its version segment. Unless disabled in
+    /// `config`, the `v1` group is also served without a version segment,
+    /// with the deprecation headers added to its responses.
+    pub fn build(self, config: &VersioningConfig) -> Router {
+        let mut router = Router::new();
+        for (version, group) in &self.groups {
+            router = router.nest(&format!("/{}", version), group.clone());
+        }
+
+        let aliased = self
+            .groups
+            .get(&ApiVersion::V1)
+            .filter(|_| config.serve_aliases);
+        if let Some(group) = aliased {
+            router = router.merge(group.clone().layer(middleware::from_fn_with_state(
+                alias_headers(config),
+                deprecation_middleware,
+            )));
+        }
+
+        router
+    }
+}
+
+/// `Deprecation` (RFC 9745) and `Sunset` (RFC 8594) headers of the aliases
+fn alias_headers(config: &VersioningConfig) -> HeaderMap {
+    let http_date = format_description!(
+        "[weekday repr:short], [day] [month repr:short] [year] [hour]:[minute]:[second] GMT"
+    );
+    let values = [
+        (
+            "deprecation",
+            Some(format!("@{}", config.deprecated_since.unix_timestamp())),
+        ),
+        (
+            "sunset",
+            config
+                .sunset
+                .to_offset(UtcOffset::UTC)
+                .format(http_date)
+                .ok(),
+        ),
+    ];
+
+    let mut headers = HeaderMap::new();
+    for (name, value) in values {
+        if let Some(Ok(value)) = value.map(HeaderValue::try_from) {
+            headers.insert(HeaderName::from_static(name), value);
+        }
+    }
+    headers
+}
+
+/// Adds the deprecation headers to the responses of the alias paths
+async fn deprecation_middleware(
+    State(headers): State<HeaderMap>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    response.headers_mut().extend(headers);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode, routing::get};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use time::macros::datetime;
+    use tower::ServiceExt;
+
+    fn config() -> VersioningConfig {
+        VersioningConfig {
+            serve_aliases: true,
+            deprecated_since: datetime!(2025-04-08 0:00 UTC),
+            sunset: datetime!(2026-04-08 0:00 UTC),
+        }
+    }
+
+    async fn get_path(router: &Router, path: &str) -> Response {
+        router
+            .clone()
+            .oneshot(Request::get(path).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_alias_and_versioned_path_share_the_handler() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let router = VersionedRouter::new()
+            .routes(
+                ApiVersion::ALL,
+                Router::new().route(
+                    "/auth/status",
+                    get(move || {
+                        let counter = counter.clone();
+                        async move {
+                            counter.fetch_add(1, Ordering::SeqCst);
+                            "OK"
+                        }
+                    }),
+                ),
+            )
+            .build(&config());
+
+        let versioned = get_path(&router, "/v1/auth/status").await;
+        assert_eq!(versioned.status(), StatusCode::OK);
+        assert!(versioned.headers().get("deprecation").is_none());
+        assert!(versioned.headers().get("sunset").is_none());
+
+        let alias = get_path(&router, "/auth/status").await;
+        assert_eq!(alias.status(), StatusCode::OK);
+        assert_eq!(alias.headers()["deprecation"], "@1744070400");
+        assert_eq!(alias.headers()["sunset"], "Wed, 08 Apr 2026 00:00:00 GMT");
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_aliases_can_be_disabled() {
+        let router = VersionedRouter::new()
+            .routes(
+                ApiVersion::ALL,
+                Router::new().route("/health", get(|| async { "OK" })),
+            )
+            .build(&VersioningConfig {
+                serve_aliases: false,
+                ..config()
+            });
+
+        assert_eq!(
+            get_path(&router, "/v1/health").await.status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            get_path(&router, "/health").await.status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[tokio::test]
+    async fn test_route_per_version_only_serves_listed_versions() {
+        let router = VersionedRouter::new()
+            .route_per_version("/tenants", [(ApiVersion::V1, get(|| async { "v1" }))])
+            .build(&config());
+
+        assert_eq!(
+            get_path(&router, "/v1/tenants").await.status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            get_path(&router, "/v2/tenants").await.status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[test]
+    fn test_versioned_paths() {
+        assert_eq!(ApiVersion::V1.prefix("/api/"), "/api/v1");
+        assert_eq!(ApiVersion::V1.prefix(""), "/v1");
+        assert_eq!(
+            ApiVersion::V1.path("/api", "/auth/verify/code"),
+            "/api/v1/auth/verify/code"
+        );
+    }
+}
@@ -0,0 +1,106 @@
+//! Seeds a development database with tenants, users and security data
+//!
+//! ```text
+//! DATABASE_URL=postgres://localhost/acci cargo run -p acci_auth --features dev-tools \
+//!     --bin dev-seed -- --seed 7 --tenants 5 --members 10
+//! ```
+
+use acci_auth::{AuthConfig, DevSeedError, DevSeedOptions, DevSeeder, RepositoryConfig};
+use std::process::ExitCode;
+use std::sync::Arc;
+
+const USAGE: &str = "\
+Usage: dev-seed [OPTIONS]
+
+Options:
+  --database-url <URL>  Database to seed [default: $DATABASE_URL]
+  --seed <N>            Seed of the generator [default: 42]
+  --tenants <N>         Number of tenants [default: 3]
+  --members <N>         Members per tenant besides the admin [default: 4]
+  --force               Seed even if the database contains data not created by dev-seed
+  -h, --help            Print this help";
+
+struct Args {
+    database_url: Option<String>,
+    options: DevSeedOptions,
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut parsed = Args {
+        database_url: std::env::var("DATABASE_URL").ok(),
+        options: DevSeedOptions::default(),
+    };
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("{} requires a value", name))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--force" => parsed.options.force = true,
+            "--database-url" => parsed.database_url = Some(value("--database-url")?),
+            "--seed" => parsed.options.seed = number(&arg, &value("--seed")?)?,
+            "--tenants" => parsed.options.tenants = number(&arg, &value("--tenants")?)?,
+            "--members" => parsed.options.members_per_tenant = number(&arg, &value("--members")?)?,
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
+    }
+    Ok(Some(parsed))
+}
+
+fn number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} expects a number, got {}", name, value))
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        },
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        },
+    };
+    let Some(database_url) = args.database_url else {
+        eprintln!("Set DATABASE_URL or pass --database-url\n\n{}", USAGE);
+        return ExitCode::from(2);
+    };
+
+    let seeder = match DevSeeder::connect(
+        RepositoryConfig {
+            database_url,
+            ..RepositoryConfig::default()
+        },
+        Arc::new(AuthConfig::default()),
+    )
+    .await
+    {
+        Ok(seeder) => seeder,
+        Err(e) => {
+            eprintln!("Failed to connect: {}", e);
+            return ExitCode::FAILURE;
+        },
+    };
+
+    match seeder.run(&args.options).await {
+        Ok(summary) => {
+            println!("{}", summary);
+            ExitCode::SUCCESS
+        },
+        Err(e @ DevSeedError::ProductionData { .. }) => {
+            eprintln!("{}; pass --force to seed anyway", e);
+            ExitCode::FAILURE
+        },
+        Err(e) => {
+            eprintln!("Seeding failed: {}", e);
+            ExitCode::FAILURE
+        },
+    }
+}
@@ -1,51 +1,224 @@
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 use std::time::Duration;
 
+use crate::handlers::cookie::{CookieConfig, deserialize_cookie_config};
+use crate::models;
+use crate::services::event_export::EventExportConfig;
 use crate::services::message_provider::MessageProviderConfig;
+use crate::services::verification::ChannelFallbackConfig;
+use crate::utils::auth_log::LogPrivacyConfig;
+use crate::utils::bounded_json::JsonLimits;
+use crate::utils::jwt::{DEFAULT_JWT_ISSUER, DEFAULT_JWT_LEEWAY_SECS, MAX_JWT_LEEWAY_SECS};
+use crate::utils::{claims::ClaimsConfig, duration::DurationSecs, secret::Secret};
+use acci_core::http_client::OutboundHttpConfig;
+use acci_core::secret_store::{SecretResolver, SecretStore};
 
+/// Authentication configuration
+///
+/// Durations accept a number of seconds or a humantime string such as `"15m"`;
+/// secrets are redacted whenever the config is formatted.
 #[derive(Debug, Clone, Deserialize)]
 pub struct AuthConfig {
     /// JWT secret key for token signing
-    pub jwt_secret: String,
-    /// JWT token lifetime in seconds
-    pub jwt_lifetime_secs: u64,
-    /// Session lifetime in seconds
-    pub session_lifetime_secs: u64,
-    /// Session activity update interval in seconds
-    pub session_activity_update_interval_secs: u64,
-    /// Session cleanup interval in seconds
-    pub session_cleanup_interval_secs: u64,
-    /// Invalid session retention period in seconds
-    pub invalid_session_retention_secs: u64,
-    /// Session audit log retention period in seconds
-    pub audit_log_retention_secs: u64,
+    pub jwt_secret: Secret<String>,
+    /// JWT token lifetime
+    pub jwt_lifetime_secs: DurationSecs,
+    /// Clock skew between services tolerated when validating JWTs; values
+    /// above [`MAX_JWT_LEEWAY_SECS`] fail to load
+    #[serde(
+        default = "default_jwt_leeway_secs",
+        deserialize_with = "deserialize_jwt_leeway"
+    )]
+    pub jwt_leeway_secs: DurationSecs,
+    /// `iss` of the issued JWTs; tokens of other issuers are rejected
+    #[serde(default = "default_jwt_issuer")]
+    pub jwt_issuer: String,
+    /// Session lifetime
+    pub session_lifetime_secs: DurationSecs,
+    /// Lifetime of "remember me" sessions
+    #[serde(default = "default_persistent_session_lifetime_secs")]
+    pub persistent_session_lifetime_secs: DurationSecs,
+    /// Session activity update interval
+    pub session_activity_update_interval_secs: DurationSecs,
+    /// Session cleanup interval
+    pub session_cleanup_interval_secs: DurationSecs,
+    /// Invalid session retention period
+    pub invalid_session_retention_secs: DurationSecs,
+    /// Session audit log retention period
+    pub audit_log_retention_secs: DurationSecs,
     /// Maximum number of active sessions per user
     pub max_sessions_per_user: u32,
     /// Whether to enable device fingerprinting
     pub enable_device_fingerprinting: bool,
     /// Whether to enable session token rotation
     pub enable_session_token_rotation: bool,
-    /// Session token rotation interval in seconds
-    pub session_token_rotation_interval_secs: u64,
+    /// Session token rotation interval
+    pub session_token_rotation_interval_secs: DurationSecs,
     /// Session configuration
     pub session: SessionConfig,
     /// Message provider configuration
     pub message_providers: Option<MessageProviderConfig>,
-    /// Verification code configuration
+    /// Verification code configuration; settings issuing unusable codes fail to load
+    #[serde(deserialize_with = "deserialize_verification_config")]
     pub verification: VerificationConfig,
     /// Salt used for hashing session tokens
-    pub session_salt: String,
+    pub session_salt: Secret<String>,
+    /// Re-authentication windows for sensitive operations
+    #[serde(default)]
+    pub reauth: ReauthConfig,
+    /// Limits for support staff acting as another user
+    #[serde(default)]
+    pub impersonation: ImpersonationConfig,
+    /// Attributes of the session cookie; invalid combinations fail to load
+    #[serde(default, deserialize_with = "deserialize_cookie_config")]
+    pub cookie: CookieConfig,
+    /// Devices on which the user skips MFA after completing it once
+    #[serde(default)]
+    pub trusted_device: TrustedDeviceConfig,
+    /// Lifetimes of the other login types and the cap on all requested lifetimes
+    #[serde(default)]
+    pub session_lifetimes: SessionLifetimeConfig,
+    /// Timeouts, proxy and certificate pins of requests to external providers
+    #[serde(default)]
+    pub outbound_http: OutboundHttpConfig,
+    /// Periodic revalidation of valid sessions against the state of their users
+    #[serde(default)]
+    pub session_reconciliation: SessionReconciliationConfig,
+    /// Maximum number of tenants a user can be an active member of, unlimited if unset
+    #[serde(default)]
+    pub max_tenants_per_user: Option<u32>,
+    /// Custom claims of issued JWTs and their size limit
+    #[serde(default)]
+    pub claims: ClaimsConfig,
+    /// Export of audit events and security alerts to a SIEM, disabled without targets
+    #[serde(default)]
+    pub event_export: EventExportConfig,
+    /// Reuse of previous passwords and resets of expired ones
+    #[serde(default)]
+    pub password: PasswordConfig,
+    /// Limits of the metadata of tenants and sessions; installed process-wide
+    /// with [`crate::utils::bounded_json::install_limits`] at startup. The
+    /// session service checks the metadata of new sessions against it directly.
+    #[serde(default)]
+    pub metadata_limits: JsonLimits,
+    /// Redaction of users and addresses in logged authentication decisions;
+    /// installed process-wide with [`crate::utils::auth_log::install_privacy`]
+    /// at startup.
+    #[serde(default)]
+    pub log_privacy: LogPrivacyConfig,
+}
+
+/// Lifetime requested for a new session
+///
+/// Resolved against the [`AuthConfig`] and clamped to
+/// [`SessionLifetimeConfig::max_secs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionLifetime {
+    /// Interactive login, `session_lifetime_secs`
+    Standard,
+    /// "Remember me" login, `persistent_session_lifetime_secs`
+    Persistent,
+    /// Session backing an API token
+    ApiToken,
+    /// Login to the administration interface
+    Admin,
+    /// A lifetime chosen by the caller
+    Explicit(Duration),
+}
+
+impl SessionLifetime {
+    /// The lifetime of a login that did or did not ask to stay signed in
+    pub fn for_login(persistent: bool) -> Self {
+        if persistent {
+            Self::Persistent
+        } else {
+            Self::Standard
+        }
+    }
+}
+
+/// Operations that require a recent full authentication even within a valid session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensitiveOperation {
+    ChangePassword,
+    AddAuthenticator,
+    /// Listing or removing sign-in factors
+    ManageFactors,
+    DeleteTenant,
+    /// Requesting the deletion of the user's own account
+    DeleteAccount,
+}
+
+/// Maximum age of the last full authentication, per sensitive operation
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ReauthConfig {
+    pub change_password_secs: DurationSecs,
+    pub add_authenticator_secs: DurationSecs,
+    pub manage_factors_secs: DurationSecs,
+    pub delete_tenant_secs: DurationSecs,
+    pub delete_account_secs: DurationSecs,
+}
+
+/// Limits for impersonation sessions started by tenant admins
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ImpersonationConfig {
+    /// Hard cap on the lifetime of an impersonation session
+    pub max_ttl_secs: DurationSecs,
+    /// Whether admins may impersonate other admins of the same tenant
+    pub allow_admin_targets: bool,
+}
+
+/// Lifetime of the trust placed in a device after a completed MFA challenge
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TrustedDeviceConfig {
+    /// How long a trusted device skips MFA before it is challenged again
+    pub trust_lifetime_secs: DurationSecs,
+}
+
+/// Background job invalidating sessions of deactivated users and outdated passwords
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SessionReconciliationConfig {
+    /// Time between the starts of two reconciliation passes
+    pub interval_secs: DurationSecs,
+    /// Sessions read and checked per batch
+    pub batch_size: u32,
+}
+
+/// Password history and the reset of expired passwords
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PasswordConfig {
+    /// Number of previous passwords a new password must differ from; 0 allows reuse
+    pub history_size: u32,
+    /// How long the reset token handed out for an expired password is valid
+    pub reset_token_ttl_secs: DurationSecs,
+}
+
+/// Lifetimes of sessions created for the login types beyond interactive logins
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SessionLifetimeConfig {
+    /// Lifetime of sessions backing API tokens
+    pub api_token_secs: DurationSecs,
+    /// Lifetime of sessions of the administration interface
+    pub admin_secs: DurationSecs,
+    /// Longest lifetime a requested session may have; longer requests are clamped
+    pub max_secs: DurationSecs,
 }
 
 /// Session configuration
 #[derive(Debug, Clone, Deserialize)]
 pub struct SessionConfig {
-    /// Session expiration in seconds
-    pub expiration_secs: u64,
-    /// Session token rotation interval in seconds
-    pub token_rotation_interval_secs: u64,
-    /// Session cleanup interval in seconds
-    pub cleanup_interval_secs: u64,
+    /// Session expiration
+    pub expiration_secs: DurationSecs,
+    /// Session token rotation interval
+    pub token_rotation_interval_secs: DurationSecs,
+    /// Session cleanup interval
+    pub cleanup_interval_secs: DurationSecs,
 }
 
 /// Verification code configuration
@@ -53,20 +226,89 @@ pub struct SessionConfig {
 pub struct VerificationConfig {
     /// Length of the verification code
     pub code_length: usize,
-    /// Expiration time in seconds
-    pub expiration_seconds: i64,
+    /// Expiration time
+    pub expiration_seconds: DurationSecs,
     /// Maximum number of attempts
     pub max_attempts: usize,
-    /// Throttling period in seconds
-    pub throttle_seconds: i64,
+    /// Throttling period
+    pub throttle_seconds: DurationSecs,
+    /// How often the cleanup job expires and deletes old codes
+    #[serde(default = "default_verification_cleanup_interval_secs")]
+    pub cleanup_interval_secs: DurationSecs,
+    /// How long expired codes are kept, bounding the verification statistics
+    #[serde(default = "default_verification_retention_secs")]
+    pub retention_secs: DurationSecs,
+    /// How long the proof of a verification can be consumed
+    #[serde(default = "default_verification_proof_ttl_secs")]
+    pub proof_ttl_secs: DurationSecs,
+    /// Channels codes fall back to when sending fails
+    #[serde(default)]
+    pub fallback: ChannelFallbackConfig,
 }
 
 impl Default for SessionConfig {
     fn default() -> Self {
         Self {
-            expiration_secs: 86400,              // 24 hours
-            token_rotation_interval_secs: 43200, // 12 hours
-            cleanup_interval_secs: 3600,         // 1 hour
+            expiration_secs: DurationSecs::from_secs(86400), // 24 hours
+            token_rotation_interval_secs: DurationSecs::from_secs(43200), // 12 hours
+            cleanup_interval_secs: DurationSecs::from_secs(3600), // 1 hour
+        }
+    }
+}
+
+impl Default for ReauthConfig {
+    fn default() -> Self {
+        Self {
+            change_password_secs: DurationSecs::from_secs(300), // 5 minutes
+            add_authenticator_secs: DurationSecs::from_secs(300), // 5 minutes
+            manage_factors_secs: DurationSecs::from_secs(300),  // 5 minutes
+            delete_tenant_secs: DurationSecs::from_secs(300),   // 5 minutes
+            delete_account_secs: DurationSecs::from_secs(300),  // 5 minutes
+        }
+    }
+}
+
+impl Default for ImpersonationConfig {
+    fn default() -> Self {
+        Self {
+            max_ttl_secs: DurationSecs::from_secs(3600), // 1 hour
+            allow_admin_targets: false,
+        }
+    }
+}
+
+impl Default for SessionLifetimeConfig {
+    fn default() -> Self {
+        Self {
+            api_token_secs: DurationSecs::from_secs(7776000), // 90 days
+            admin_secs: DurationSecs::from_secs(3600),        // 1 hour
+            max_secs: DurationSecs::from_secs(7776000),       // 90 days
+        }
+    }
+}
+
+impl Default for SessionReconciliationConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: DurationSecs::from_secs(900), // 15 minutes
+            batch_size: 500,
+        }
+    }
+}
+
+impl Default for PasswordConfig {
+    fn default() -> Self {
+        Self {
+            history_size: 5,
+            reset_token_ttl_secs: DurationSecs::from_secs(900), // 15 minutes
+        }
+    }
+}
+
+impl Default for TrustedDeviceConfig {
+    fn default() -> Self {
+        Self {
+            trust_lifetime_secs: DurationSecs::from_secs(2592000), // 30 days
         }
     }
 }
@@ -75,78 +317,360 @@ impl Default for VerificationConfig {
     fn default() -> Self {
         Self {
             code_length: 6,
-            expiration_seconds: 600, // 10 minutes
+            expiration_seconds: DurationSecs::from_secs(600), // 10 minutes
             max_attempts: 5,
-            throttle_seconds: 60, // 1 minute
+            throttle_seconds: DurationSecs::from_secs(60), // 1 minute
+            cleanup_interval_secs: default_verification_cleanup_interval_secs(),
+            retention_secs: default_verification_retention_secs(),
+            proof_ttl_secs: default_verification_proof_ttl_secs(),
+            fallback: ChannelFallbackConfig::default(),
+        }
+    }
+}
+
+impl VerificationConfig {
+    /// Settings the verification service issues codes with
+    pub fn codes(&self) -> models::VerificationConfig {
+        models::VerificationConfig {
+            code_length: self.code_length,
+            expiration_seconds: self.expiration_seconds,
+            max_attempts: self.max_attempts,
+            throttle_seconds: self.throttle_seconds,
         }
     }
 }
 
+fn default_jwt_leeway_secs() -> DurationSecs {
+    DurationSecs::from_secs(DEFAULT_JWT_LEEWAY_SECS)
+}
+
+fn default_jwt_issuer() -> String {
+    DEFAULT_JWT_ISSUER.to_string()
+}
+
+/// Deserialize the JWT leeway, rejecting values above [`MAX_JWT_LEEWAY_SECS`]
+fn deserialize_jwt_leeway<'de, D>(deserializer: D) -> Result<DurationSecs, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let leeway = DurationSecs::deserialize(deserializer)?;
+    if leeway.as_secs() > MAX_JWT_LEEWAY_SECS {
+        return Err(serde::de::Error::custom(format!(
+            "JWT leeway of {leeway} exceeds the limit of {MAX_JWT_LEEWAY_SECS}s"
+        )));
+    }
+    Ok(leeway)
+}
+
+/// Deserialize a [`VerificationConfig`], rejecting settings that issue unusable codes
+fn deserialize_verification_config<'de, D>(deserializer: D) -> Result<VerificationConfig, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let config = VerificationConfig::deserialize(deserializer)?;
+    config
+        .codes()
+        .validate()
+        .map_err(serde::de::Error::custom)?;
+    Ok(config)
+}
+
+fn default_verification_cleanup_interval_secs() -> DurationSecs {
+    DurationSecs::from_secs(3600) // 1 hour
+}
+
+fn default_verification_retention_secs() -> DurationSecs {
+    DurationSecs::from_secs(604800) // 7 days
+}
+
+fn default_verification_proof_ttl_secs() -> DurationSecs {
+    DurationSecs::from_secs(300) // 5 minutes
+}
+
+fn default_persistent_session_lifetime_secs() -> DurationSecs {
+    DurationSecs::from_secs(2592000) // 30 days
+}
+
 impl Default for AuthConfig {
     fn default() -> Self {
         Self {
-            jwt_secret: "default-secret-please-change".to_string(),
-            jwt_lifetime_secs: 3600,                    // 1 hour
-            session_lifetime_secs: 86400,               // 24 hours
-            session_activity_update_interval_secs: 300, // 5 minutes
-            session_cleanup_interval_secs: 3600,        // 1 hour
-            invalid_session_retention_secs: 7776000,    // 90 days
-            audit_log_retention_secs: 7776000,          // 90 days
+            jwt_secret: Secret::from("default-secret-please-change"),
+            jwt_lifetime_secs: DurationSecs::from_secs(3600), // 1 hour
+            jwt_leeway_secs: default_jwt_leeway_secs(),
+            jwt_issuer: default_jwt_issuer(),
+            session_lifetime_secs: DurationSecs::from_secs(86400), // 24 hours
+            persistent_session_lifetime_secs: default_persistent_session_lifetime_secs(),
+            session_activity_update_interval_secs: DurationSecs::from_secs(300), // 5 minutes
+            session_cleanup_interval_secs: DurationSecs::from_secs(3600),        // 1 hour
+            invalid_session_retention_secs: DurationSecs::from_secs(7776000),    // 90 days
+            audit_log_retention_secs: DurationSecs::from_secs(7776000),          // 90 days
             max_sessions_per_user: 5,
             enable_device_fingerprinting: true,
             enable_session_token_rotation: true,
-            session_token_rotation_interval_secs: 43200, // 12 hours
+            session_token_rotation_interval_secs: DurationSecs::from_secs(43200), // 12 hours
             session: SessionConfig::default(),
             message_providers: None,
             verification: VerificationConfig::default(),
-            session_salt: "AcciSessionSalt123456789012345678901234567890".to_string(), // Default salt, should be changed in production
+            session_salt: Secret::from("AcciSessionSalt123456789012345678901234567890"), // Default salt, should be changed in production
+            reauth: ReauthConfig::default(),
+            impersonation: ImpersonationConfig::default(),
+            cookie: CookieConfig::default(),
+            trusted_device: TrustedDeviceConfig::default(),
+            session_lifetimes: SessionLifetimeConfig::default(),
+            outbound_http: OutboundHttpConfig::default(),
+            session_reconciliation: SessionReconciliationConfig::default(),
+            max_tenants_per_user: None,
+            claims: ClaimsConfig::default(),
+            event_export: EventExportConfig::default(),
+            password: PasswordConfig::default(),
+            metadata_limits: JsonLimits::default(),
+            log_privacy: LogPrivacyConfig::default(),
         }
     }
 }
 
 impl AuthConfig {
     pub fn session_lifetime(&self) -> Duration {
-        Duration::from_secs(self.session_lifetime_secs)
+        self.session_lifetime_secs.as_duration()
+    }
+
+    pub fn persistent_session_lifetime(&self) -> Duration {
+        self.persistent_session_lifetime_secs.as_duration()
+    }
+
+    /// Lifetime of a new session, depending on whether the user asked to stay signed in
+    pub fn session_lifetime_for(&self, persistent: bool) -> Duration {
+        if persistent {
+            self.persistent_session_lifetime()
+        } else {
+            self.session_lifetime()
+        }
+    }
+
+    /// Lifetime of a new session of the requested kind, clamped to the configured maximum
+    pub fn resolve_session_lifetime(&self, lifetime: SessionLifetime) -> Duration {
+        let requested = match lifetime {
+            SessionLifetime::Standard => self.session_lifetime(),
+            SessionLifetime::Persistent => self.persistent_session_lifetime(),
+            SessionLifetime::ApiToken => self.session_lifetimes.api_token_secs.as_duration(),
+            SessionLifetime::Admin => self.session_lifetimes.admin_secs.as_duration(),
+            SessionLifetime::Explicit(lifetime) => lifetime,
+        };
+        requested.min(self.session_lifetimes.max_secs.as_duration())
     }
 
     pub fn session_activity_update_interval(&self) -> Duration {
-        Duration::from_secs(self.session_activity_update_interval_secs)
+        self.session_activity_update_interval_secs.as_duration()
     }
 
     pub fn session_cleanup_interval(&self) -> Duration {
-        Duration::from_secs(self.session_cleanup_interval_secs)
+        self.session_cleanup_interval_secs.as_duration()
     }
 
     pub fn invalid_session_retention(&self) -> Duration {
-        Duration::from_secs(self.invalid_session_retention_secs)
+        self.invalid_session_retention_secs.as_duration()
     }
 
     pub fn audit_log_retention(&self) -> Duration {
-        Duration::from_secs(self.audit_log_retention_secs)
+        self.audit_log_retention_secs.as_duration()
     }
 
     pub fn session_token_rotation_interval(&self) -> Duration {
-        Duration::from_secs(self.session_token_rotation_interval_secs)
+        self.session_token_rotation_interval_secs.as_duration()
+    }
+
+    /// How recent the last full authentication must be for `operation`
+    pub fn reauth_window(&self, operation: SensitiveOperation) -> Duration {
+        let window = match operation {
+            SensitiveOperation::ChangePassword => self.reauth.change_password_secs,
+            SensitiveOperation::AddAuthenticator => self.reauth.add_authenticator_secs,
+            SensitiveOperation::ManageFactors => self.reauth.manage_factors_secs,
+            SensitiveOperation::DeleteTenant => self.reauth.delete_tenant_secs,
+            SensitiveOperation::DeleteAccount => self.reauth.delete_account_secs,
+        };
+        window.as_duration()
+    }
+
+    /// Longest lifetime an impersonation session may have
+    pub fn impersonation_max_ttl(&self) -> Duration {
+        self.impersonation.max_ttl_secs.as_duration()
+    }
+
+    /// Load the secrets configured as references from `store`
+    ///
+    /// Run at startup before building providers or the [`JwtUtils`](crate::JwtUtils),
+    /// and again after the store was refreshed to pick up rotated values. Fails
+    /// with every secret that could not be resolved.
+    pub async fn resolve_secrets(
+        &mut self,
+        store: &dyn SecretStore,
+    ) -> acci_core::error::Result<()> {
+        let mut resolver = SecretResolver::new(store);
+        resolver.resolve("jwt_secret", &mut self.jwt_secret).await;
+        resolver
+            .resolve("session_salt", &mut self.session_salt)
+            .await;
+        resolver
+            .resolve("log_privacy.hash_key", &mut self.log_privacy.hash_key)
+            .await;
+        if let Some(message_providers) = &mut self.message_providers {
+            message_providers.resolve_secrets(&mut resolver).await;
+        }
+        self.event_export
+            .resolve_secrets(&mut resolver, "event_export")
+            .await;
+        resolver.finish()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::delivery_receipt::DeliveryReceiptConfig;
+    use crate::services::message_provider::{EmailProviderConfig, SmsProviderConfig, SmtpConfig};
 
     #[test]
     fn test_default_config() {
         let config = AuthConfig::default();
-        assert_eq!(config.jwt_lifetime_secs, 3600);
-        assert_eq!(config.session_lifetime_secs, 86400);
-        assert_eq!(config.session_activity_update_interval_secs, 300);
-        assert_eq!(config.session_cleanup_interval_secs, 3600);
-        assert_eq!(config.invalid_session_retention_secs, 7776000);
-        assert_eq!(config.audit_log_retention_secs, 7776000);
+        assert_eq!(config.jwt_lifetime_secs.as_secs(), 3600);
+        assert_eq!(config.session_lifetime_secs.as_secs(), 86400);
+        assert_eq!(config.persistent_session_lifetime_secs.as_secs(), 2592000);
+        assert_eq!(config.session_activity_update_interval_secs.as_secs(), 300);
+        assert_eq!(config.session_cleanup_interval_secs.as_secs(), 3600);
+        assert_eq!(config.invalid_session_retention_secs.as_secs(), 7776000);
+        assert_eq!(config.audit_log_retention_secs.as_secs(), 7776000);
         assert_eq!(config.max_sessions_per_user, 5);
         assert!(config.enable_device_fingerprinting);
         assert!(config.enable_session_token_rotation);
-        assert_eq!(config.session_token_rotation_interval_secs, 43200);
+        assert_eq!(config.session_token_rotation_interval_secs.as_secs(), 43200);
+    }
+
+    #[test]
+    fn test_config_durations_accept_humantime() {
+        let config: SessionConfig = serde_json::from_str(
+            r#"{"expiration_secs": "24h", "token_rotation_interval_secs": 43200, "cleanup_interval_secs": "15m"}"#,
+        )
+        .expect("Session config deserializes");
+        assert_eq!(config.expiration_secs, DurationSecs::from_secs(86400));
+        assert_eq!(config.token_rotation_interval_secs.as_secs(), 43200);
+        assert_eq!(config.cleanup_interval_secs.as_secs(), 900);
+    }
+
+    #[test]
+    fn test_debug_output_never_contains_secrets() {
+        let mut config = AuthConfig {
+            jwt_secret: Secret::from("jwt-signing-key-do-not-log"),
+            session_salt: Secret::from("session-pepper-do-not-log"),
+            ..AuthConfig::default()
+        };
+        config.message_providers = Some(MessageProviderConfig {
+            email: EmailProviderConfig {
+                provider: "smtp".to_string(),
+                smtp: Some(SmtpConfig {
+                    host: "smtp.example.com".to_string(),
+                    port: 587,
+                    username: "mailer".to_string(),
+                    password: Secret::from("smtp-password-do-not-log"),
+                    use_tls: true,
+                }),
+                api_key: Some(Secret::from("email-api-key-do-not-log")),
+                sender_email: "noreply@example.com".to_string(),
+                sender_name: "Acci".to_string(),
+                verification_template: "{code}".to_string(),
+                delivery: Default::default(),
+            },
+            sms: SmsProviderConfig {
+                provider: "twilio".to_string(),
+                api_key: Secret::from("twilio-sid-do-not-log"),
+                api_secret: Some(Secret::from("twilio-token-do-not-log")),
+                sender: "+15550100".to_string(),
+                delivery: Default::default(),
+                status_callback_url: None,
+            },
+            outbox: Default::default(),
+            email_routing: Default::default(),
+            delivery_receipts: DeliveryReceiptConfig {
+                vonage_signature_secret: Some(Secret::from("vonage-secret-do-not-log")),
+                ..Default::default()
+            },
+        });
+
+        for output in [format!("{config:?}"), format!("{config:#?}")] {
+            assert!(!output.contains("do-not-log"), "secret leaked: {output}");
+            assert!(output.contains("[REDACTED]"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_secrets_from_store() {
+        use acci_core::secret_store::EnvSecretStore;
+
+        let mut config: MessageProviderConfig = serde_json::from_value(serde_json::json!({
+            "email": {
+                "provider": "sendgrid",
+                "smtp": null,
+                "api_key": {"secret_ref": "sendgrid/api-key"},
+                "sender_email": "noreply@example.com",
+                "sender_name": "Acci",
+                "verification_template": "{code}",
+            },
+            "sms": {
+                "provider": "twilio",
+                "api_key": "inline-sid",
+                "api_secret": {"secret_ref": "twilio/token"},
+                "sender": "+15550100",
+            },
+        }))
+        .expect("Message provider config deserializes");
+        assert!(config.email.ensure_secrets_resolved().is_err());
+
+        // SAFETY: the variables are unique to this test
+        unsafe {
+            std::env::set_var("ACCI_CONFIG_TEST_SENDGRID_API_KEY", "sg-key");
+            std::env::set_var("ACCI_CONFIG_TEST_JWT", "jwt-from-env");
+        }
+        let store = EnvSecretStore::new().with_prefix("ACCI_CONFIG_TEST_");
+        let mut auth_config = AuthConfig {
+            jwt_secret: Secret::from_ref("jwt"),
+            message_providers: Some(config.clone()),
+            ..AuthConfig::default()
+        };
+        let error = auth_config
+            .resolve_secrets(&store)
+            .await
+            .expect_err("The Twilio token is missing");
+        assert!(
+            error
+                .to_string()
+                .contains("message_providers.sms.api_secret"),
+            "{error}"
+        );
+
+        unsafe { std::env::set_var("ACCI_CONFIG_TEST_TWILIO_TOKEN", "twilio-token") };
+        auth_config
+            .resolve_secrets(&store)
+            .await
+            .expect("All secrets resolve");
+        assert_eq!(auth_config.jwt_secret.expose_secret(), "jwt-from-env");
+        config = auth_config.message_providers.expect("configured");
+        assert!(config.email.ensure_secrets_resolved().is_ok());
+        assert_eq!(
+            config
+                .email
+                .api_key
+                .as_ref()
+                .map(|key| key.expose_secret().as_str()),
+            Some("sg-key")
+        );
+        assert_eq!(config.sms.api_key.expose_secret(), "inline-sid");
+        assert_eq!(
+            config
+                .sms
+                .api_secret
+                .as_ref()
+                .map(|secret| secret.expose_secret().as_str()),
+            Some("twilio-token")
+        );
     }
 
     #[test]
@@ -167,5 +691,107 @@ mod tests {
             config.session_token_rotation_interval(),
             Duration::from_secs(43200)
         );
+        assert_eq!(
+            config.session_lifetime_for(false),
+            config.session_lifetime()
+        );
+        assert_eq!(
+            config.session_lifetime_for(true),
+            Duration::from_secs(2592000)
+        );
+        assert_eq!(
+            config.reauth_window(SensitiveOperation::ChangePassword),
+            Duration::from_secs(300)
+        );
+    }
+
+    #[test]
+    fn test_resolve_session_lifetime_clamps_to_max() {
+        let config = AuthConfig {
+            session_lifetimes: SessionLifetimeConfig {
+                max_secs: DurationSecs::from_secs(7 * 86400),
+                ..SessionLifetimeConfig::default()
+            },
+            ..AuthConfig::default()
+        };
+
+        assert_eq!(
+            config.resolve_session_lifetime(SessionLifetime::Standard),
+            config.session_lifetime()
+        );
+        assert_eq!(
+            config.resolve_session_lifetime(SessionLifetime::Admin),
+            Duration::from_secs(3600)
+        );
+        assert_eq!(
+            config.resolve_session_lifetime(SessionLifetime::Persistent),
+            Duration::from_secs(7 * 86400)
+        );
+        assert_eq!(
+            config.resolve_session_lifetime(SessionLifetime::Explicit(Duration::from_secs(
+                365 * 86400
+            ))),
+            Duration::from_secs(7 * 86400)
+        );
+    }
+
+    fn load_verification(json: serde_json::Value) -> Result<VerificationConfig, serde_json::Error> {
+        deserialize_verification_config(json)
+    }
+
+    #[test]
+    fn test_verification_config_rejects_unusable_codes() {
+        let zero_length = load_verification(serde_json::json!({
+            "code_length": 0,
+            "expiration_seconds": "10m",
+            "max_attempts": 5,
+            "throttle_seconds": 60,
+        }))
+        .expect_err("Codes need digits");
+        assert!(
+            zero_length.to_string().contains("code length"),
+            "{zero_length}"
+        );
+
+        let zero_attempts = load_verification(serde_json::json!({
+            "code_length": 6,
+            "expiration_seconds": "10m",
+            "max_attempts": 0,
+            "throttle_seconds": 60,
+        }))
+        .expect_err("Codes need an attempt");
+        assert!(
+            zero_attempts.to_string().contains("at least one attempt"),
+            "{zero_attempts}"
+        );
+
+        assert_eq!(
+            VerificationConfig {
+                expiration_seconds: DurationSecs::from_secs(60),
+                ..VerificationConfig::default()
+            }
+            .codes()
+            .validate(),
+            Err(models::VerificationConfigError::ExpirationWithinThrottle {
+                expiration: 60,
+                throttle: 60,
+            })
+        );
+        assert!(VerificationConfig::default().codes().validate().is_ok());
+    }
+
+    #[test]
+    fn test_jwt_leeway_is_bounded() {
+        let leeway = deserialize_jwt_leeway(serde_json::json!("2m")).expect("Leeway is valid");
+        assert_eq!(leeway.as_secs(), 120);
+
+        let error =
+            deserialize_jwt_leeway(serde_json::json!("1h")).expect_err("Leeway is too large");
+        assert!(error.to_string().contains("exceeds the limit"), "{error}");
+
+        assert_eq!(
+            AuthConfig::default().jwt_leeway_secs.as_secs(),
+            DEFAULT_JWT_LEEWAY_SECS
+        );
     }
 }
@@ -0,0 +1,276 @@
+use serde::{Deserialize, Deserializer};
+use thiserror::Error;
+
+use super::current_user::{HOST_SESSION_COOKIE, SESSION_COOKIE};
+
+/// `SameSite` attribute of the session cookie
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl SameSite {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Strict => "Strict",
+            Self::Lax => "Lax",
+            Self::None => "None",
+        }
+    }
+}
+
+/// Invalid combinations of session cookie attributes
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CookieConfigError {
+    #[error("SameSite=None requires the Secure attribute")]
+    SameSiteNoneWithoutSecure,
+
+    #[error("__Host- cookies require the Secure attribute")]
+    HostPrefixWithoutSecure,
+
+    #[error("__Host- cookies must not set a Domain")]
+    HostPrefixWithDomain,
+
+    #[error("__Host- cookies require Path=/")]
+    HostPrefixWithPath,
+
+    #[error("Invalid cookie {0}: {1:?}")]
+    InvalidAttribute(&'static str, String),
+}
+
+/// Attributes of the session cookie set by the web frontend
+///
+/// Secure by default: `Secure`, `HttpOnly` and `SameSite=Lax` on `Path=/`.
+/// With `host_prefix` the cookie is named `__Host-auth_token`, which browsers
+/// only accept without a `Domain`, with `Secure` and with `Path=/`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct CookieConfig {
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: SameSite,
+    pub domain: Option<String>,
+    pub path: String,
+    /// Name the cookie with the `__Host-` prefix
+    pub host_prefix: bool,
+}
+
+impl Default for CookieConfig {
+    fn default() -> Self {
+        Self {
+            secure: true,
+            http_only: true,
+            same_site: SameSite::Lax,
+            domain: None,
+            path: "/".to_string(),
+            host_prefix: false,
+        }
+    }
+}
+
+impl CookieConfig {
+    /// Check that the attributes form a cookie browsers accept
+    pub fn validate(&self) -> Result<(), CookieConfigError> {
+        if self.same_site == SameSite::None && !self.secure {
+            return Err(CookieConfigError::SameSiteNoneWithoutSecure);
+        }
+        if !self.path.starts_with('/') || !is_attribute_value(&self.path) {
+            return Err(CookieConfigError::InvalidAttribute(
+                "path",
+                self.path.clone(),
+            ));
+        }
+        let invalid_domain = self
+            .domain
+            .as_ref()
+            .filter(|domain| domain.is_empty() || !is_attribute_value(domain));
+        if let Some(domain) = invalid_domain {
+            return Err(CookieConfigError::InvalidAttribute(
+                "domain",
+                domain.clone(),
+            ));
+        }
+
+        if self.host_prefix {
+            if !self.secure {
+                return Err(CookieConfigError::HostPrefixWithoutSecure);
+            }
+            if self.domain.is_some() {
+                return Err(CookieConfigError::HostPrefixWithDomain);
+            }
+            if self.path != "/" {
+                return Err(CookieConfigError::HostPrefixWithPath);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Name of the session cookie
+    pub fn cookie_name(&self) -> &'static str {
+        if self.host_prefix {
+            HOST_SESSION_COOKIE
+        } else {
+            SESSION_COOKIE
+        }
+    }
+
+    /// `Set-Cookie` value carrying `token`
+    ///
+    /// Without `max_age` the browser discards the cookie when it closes.
+    pub fn session_cookie(
+        &self,
+        token: &str,
+        max_age: Option<i64>,
+    ) -> Result<String, CookieConfigError> {
+        self.validate()?;
+
+        let mut cookie = format!("{}={}; Path={}", self.cookie_name(), token, self.path);
+        if let Some(domain) = &self.domain {
+            cookie.push_str(&format!("; Domain={}", domain));
+        }
+        if let Some(max_age) = max_age {
+            cookie.push_str(&format!("; Max-Age={}", max_age.max(0)));
+        }
+        if self.secure {
+            cookie.push_str("; Secure");
+        }
+        if self.http_only {
+            cookie.push_str("; HttpOnly");
+        }
+        cookie.push_str(&format!("; SameSite={}", self.same_site.as_str()));
+        Ok(cookie)
+    }
+
+    /// `Set-Cookie` value that removes the session cookie
+    pub fn removal_cookie(&self) -> Result<String, CookieConfigError> {
+        self.session_cookie("", Some(0))
+    }
+}
+
+/// Deserialize a [`CookieConfig`], rejecting invalid attribute combinations
+pub fn deserialize_cookie_config<'de, D>(deserializer: D) -> Result<CookieConfig, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let config = CookieConfig::deserialize(deserializer)?;
+    config.validate().map_err(serde::de::Error::custom)?;
+    Ok(config)
+}
+
+/// Whether `value` can be embedded in a `Set-Cookie` attribute
+fn is_attribute_value(value: &str) -> bool {
+    !value.chars().any(|c| c == ';' || c.is_control())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(json: &str) -> Result<CookieConfig, serde_json::Error> {
+        deserialize_cookie_config(&mut serde_json::Deserializer::from_str(json))
+    }
+
+    #[test]
+    fn test_default_cookie_is_secure() {
+        let cookie = CookieConfig::default()
+            .session_cookie("token", None)
+            .expect("Default configuration is valid");
+        assert_eq!(
+            cookie,
+            "auth_token=token; Path=/; Secure; HttpOnly; SameSite=Lax"
+        );
+    }
+
+    #[test]
+    fn test_host_prefixed_cookie() {
+        let config = CookieConfig {
+            same_site: SameSite::Strict,
+            host_prefix: true,
+            ..CookieConfig::default()
+        };
+
+        assert_eq!(
+            config.session_cookie("token", Some(3600)),
+            Ok(
+                "__Host-auth_token=token; Path=/; Max-Age=3600; Secure; HttpOnly; SameSite=Strict"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            config.removal_cookie(),
+            Ok(
+                "__Host-auth_token=; Path=/; Max-Age=0; Secure; HttpOnly; SameSite=Strict"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_invalid_combinations_are_rejected() {
+        let host = CookieConfig {
+            host_prefix: true,
+            ..CookieConfig::default()
+        };
+        let cases = [
+            (
+                CookieConfig {
+                    secure: false,
+                    same_site: SameSite::None,
+                    ..CookieConfig::default()
+                },
+                CookieConfigError::SameSiteNoneWithoutSecure,
+            ),
+            (
+                CookieConfig {
+                    secure: false,
+                    ..host.clone()
+                },
+                CookieConfigError::HostPrefixWithoutSecure,
+            ),
+            (
+                CookieConfig {
+                    domain: Some("example.com".to_string()),
+                    ..host.clone()
+                },
+                CookieConfigError::HostPrefixWithDomain,
+            ),
+            (
+                CookieConfig {
+                    path: "/app".to_string(),
+                    ..host
+                },
+                CookieConfigError::HostPrefixWithPath,
+            ),
+            (
+                CookieConfig {
+                    domain: Some("example.com; Secure".to_string()),
+                    ..CookieConfig::default()
+                },
+                CookieConfigError::InvalidAttribute("domain", "example.com; Secure".to_string()),
+            ),
+        ];
+
+        for (config, expected) in cases {
+            assert_eq!(config.session_cookie("token", None), Err(expected));
+        }
+    }
+
+    #[test]
+    fn test_invalid_configuration_fails_to_load() {
+        let result = load(r#"{ "same_site": "None", "secure": false }"#);
+        assert!(result.is_err());
+
+        let config = load(r#"{ "same_site": "None", "domain": "example.com" }"#)
+            .expect("Secure SameSite=None cookie is valid");
+        assert_eq!(
+            config.session_cookie("token", None),
+            Ok(
+                "auth_token=token; Path=/; Domain=example.com; Secure; HttpOnly; SameSite=None"
+                    .to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::{HeaderMap, StatusCode, header, request::Parts},
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use thiserror::Error;
+use tracing::{debug, error};
+use uuid::Uuid;
+
+use crate::{
+    services::session::{SessionService, SessionServiceError},
+    session::{Session, types::MfaStatus},
+};
+
+/// Name of the cookie carrying the session token of the web frontend
+pub const SESSION_COOKIE: &str = "auth_token";
+
+/// Name of the session cookie when it is set with the `__Host-` prefix
+pub const HOST_SESSION_COOKIE: &str = "__Host-auth_token";
+
+/// The authenticated user of a request
+///
+/// Resolved from the bearer token of the `Authorization` header (API clients)
+/// or the session cookie (web frontend). The token is looked up through
+/// [`SessionService::validate_session`], so a session that has been
+/// invalidated or has expired is rejected even if the token itself is well
+/// formed. Requires `Arc<SessionService>` to be reachable from the router
+/// state via [`FromRef`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CurrentUser {
+    pub user_id: Uuid,
+    /// Active tenant of the session, falling back to the tenant recorded at login
+    pub tenant_id: Option<Uuid>,
+    pub mfa_status: MfaStatus,
+    pub session_id: Uuid,
+}
+
+impl CurrentUser {
+    /// Build the current user from a validated session
+    pub fn from_session(session: &Session) -> Self {
+        Self {
+            user_id: session.user_id,
+            tenant_id: session.tenant_id(),
+            mfa_status: session.mfa_status.clone(),
+            session_id: session.id,
+        }
+    }
+}
+
+/// Reasons a request has no [`CurrentUser`]
+#[derive(Debug, Error)]
+pub enum CurrentUserRejection {
+    #[error("No session token provided")]
+    MissingToken,
+
+    #[error("Session is invalid or expired")]
+    InvalidSession,
+
+    #[error("Session lookup failed: {0}")]
+    Service(#[from] SessionServiceError),
+}
+
+impl IntoResponse for CurrentUserRejection {
+    fn into_response(self) -> Response {
+        match self {
+            Self::MissingToken | Self::InvalidSession => StatusCode::UNAUTHORIZED.into_response(),
+            Self::Service(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        }
+    }
+}
+
+/// Session token from the bearer authorization header, falling back to the session cookie
+pub fn session_token(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+
+    bearer
+        .or_else(|| {
+            headers
+                .get_all(header::COOKIE)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(';'))
+                .filter_map(|pair| pair.trim().split_once('='))
+                .find(|(name, _)| *name == SESSION_COOKIE || *name == HOST_SESSION_COOKIE)
+                .map(|(_, value)| value.trim())
+        })
+        .filter(|token| !token.is_empty())
+}
+
+impl<S> FromRequestParts<S> for CurrentUser
+where
+    Arc<SessionService>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = CurrentUserRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let token = session_token(&parts.headers).ok_or(CurrentUserRejection::MissingToken)?;
+
+        let session_service = Arc::<SessionService>::from_ref(state);
+        let session = session_service
+            .validate_session(token)
+            .await
+            .map_err(|err| {
+                error!(error = %err, "Failed to validate session");
+                err
+            })?
+            .ok_or(CurrentUserRejection::InvalidSession)?;
+
+        debug!(session_id = %session.id, user_id = %session.user_id, "Resolved current user");
+        Ok(Self::from_session(&session))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::AuthConfig,
+        session::{mock::MockSessionRepository, types::SessionInvalidationReason},
+        utils::bounded_json::BoundedJson,
+    };
+    use axum::http::Request;
+    use serde_json::{Map, Value};
+
+    async fn service_with_session(tenant_id: Uuid) -> (Arc<SessionService>, Session, String) {
+        let service = Arc::new(SessionService::new(
+            Arc::new(MockSessionRepository::new()),
+            Arc::new(AuthConfig::default()),
+        ));
+        let (session, token) = service
+            .create_session(
+                Uuid::new_v4(),
+                None,
+                None,
+                None,
+                None,
+                Some(
+                    BoundedJson::new(Value::Object(Map::from_iter([(
+                        "tenant_id".to_string(),
+                        Value::String(tenant_id.to_string()),
+                    )])))
+                    .expect("Metadata is within the limits"),
+                ),
+                false,
+            )
+            .await
+            .expect("Failed to create session");
+        (service, session, token)
+    }
+
+    async fn extract(
+        service: &Arc<SessionService>,
+        header: (header::HeaderName, String),
+    ) -> Result<CurrentUser, CurrentUserRejection> {
+        let request = Request::builder()
+            .uri("/")
+            .header(header.0, header.1)
+            .body(())
+            .expect("valid request");
+        let (mut parts, _) = request.into_parts();
+        CurrentUser::from_request_parts(&mut parts, service).await
+    }
+
+    #[tokio::test]
+    async fn test_current_user_from_valid_session() {
+        let tenant_id = Uuid::new_v4();
+        let (service, session, token) = service_with_session(tenant_id).await;
+        let expected = CurrentUser {
+            user_id: session.user_id,
+            tenant_id: Some(tenant_id),
+            mfa_status: MfaStatus::None,
+            session_id: session.id,
+        };
+
+        let from_bearer = extract(
+            &service,
+            (header::AUTHORIZATION, format!("Bearer {}", token)),
+        )
+        .await
+        .expect("Bearer token is accepted");
+        assert_eq!(from_bearer, expected);
+
+        let from_cookie = extract(
+            &service,
+            (
+                header::COOKIE,
+                format!("theme=dark; {}={}", SESSION_COOKIE, token),
+            ),
+        )
+        .await
+        .expect("Session cookie is accepted");
+        assert_eq!(from_cookie, expected);
+
+        let from_host_cookie = extract(
+            &service,
+            (header::COOKIE, format!("{}={}", HOST_SESSION_COOKIE, token)),
+        )
+        .await
+        .expect("Host-prefixed session cookie is accepted");
+        assert_eq!(from_host_cookie, expected);
+    }
+
+    #[tokio::test]
+    async fn test_current_user_rejects_invalidated_session() {
+        let (service, _, token) = service_with_session(Uuid::new_v4()).await;
+        service
+            .invalidate_session(&token, SessionInvalidationReason::UserLogout)
+            .await
+            .expect("Failed to invalidate session");
+
+        let result = extract(
+            &service,
+            (header::AUTHORIZATION, format!("Bearer {}", token)),
+        )
+        .await;
+        let rejection = result.expect_err("Invalidated session must be rejected");
+        assert!(matches!(rejection, CurrentUserRejection::InvalidSession));
+        assert_eq!(rejection.into_response().status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_current_user_requires_token() {
+        let (service, _, _) = service_with_session(Uuid::new_v4()).await;
+
+        let result = extract(&service, (header::AUTHORIZATION, "Bearer ".to_string())).await;
+        assert!(matches!(result, Err(CurrentUserRejection::MissingToken)));
+
+        let result = extract(&service, (header::COOKIE, "theme=dark".to_string())).await;
+        assert!(matches!(result, Err(CurrentUserRejection::MissingToken)));
+    }
+}
@@ -1,4 +1,8 @@
+pub mod cookie;
+pub mod current_user;
 pub mod session;
 
 // Re-export handlers
+pub use cookie::*;
+pub use current_user::*;
 pub use session::*;
@@ -1,6 +1,6 @@
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
 };
@@ -10,7 +10,10 @@ use uuid::Uuid;
 
 use crate::{
     services::session::{SessionService, SessionServiceError},
-    session::{SessionFilter, types::SessionInvalidationReason},
+    session::{
+        Session, SessionFilter,
+        types::{MfaStatus, SessionInvalidationReason, UserAgentDetails},
+    },
 };
 
 /// Session service state for dependency injection
@@ -31,6 +34,18 @@ pub struct TerminateSessionsByIpRequest {
     pub reason: SessionInvalidationReason,
 }
 
+/// Request for terminating sessions by region
+#[derive(Debug, Deserialize)]
+pub struct TerminateSessionsByRegionRequest {
+    pub region: String,
+    /// Restrict the termination to the sessions of this tenant
+    pub tenant_id: Option<Uuid>,
+    /// Also terminate sessions without a recorded region
+    #[serde(default)]
+    pub include_unknown: bool,
+    pub reason: SessionInvalidationReason,
+}
+
 /// Request for terminating sessions by filter
 #[derive(Debug, Deserialize)]
 pub struct TerminateSessionsByFilterRequest {
@@ -46,6 +61,61 @@ pub struct SessionTerminationResponse {
     pub message: String,
 }
 
+/// Query parameters for listing user sessions
+#[derive(Debug, Deserialize)]
+pub struct ListUserSessionsQuery {
+    /// Which sessions to include, defaults to active sessions
+    pub filter: Option<SessionFilter>,
+}
+
+/// Summary of a session as returned by the session listing
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub id: Uuid,
+    /// Creation time as Unix timestamp
+    pub created_at: i64,
+    /// Expiry time as Unix timestamp
+    pub expires_at: i64,
+    /// Last activity as Unix timestamp
+    pub last_activity_at: i64,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    /// Browser, operating system and device class parsed from the user agent
+    pub user_agent_details: Option<UserAgentDetails>,
+    /// Description of the device, such as "Chrome 120 on Windows 10"
+    pub device: Option<String>,
+    pub device_id: Option<String>,
+    /// Region of the instance that created the session
+    pub region: Option<String>,
+    pub is_valid: bool,
+    /// Whether the session was created with "remember me"
+    pub persistent: bool,
+    pub mfa_status: MfaStatus,
+}
+
+impl From<Session> for SessionSummary {
+    fn from(session: Session) -> Self {
+        Self {
+            id: session.id,
+            created_at: session.created_at.unix_timestamp(),
+            expires_at: session.expires_at.unix_timestamp(),
+            last_activity_at: session.last_activity_at.unix_timestamp(),
+            persistent: session.is_persistent(),
+            ip_address: session.ip_address,
+            user_agent: session.user_agent,
+            device: session
+                .user_agent_details
+                .as_ref()
+                .map(UserAgentDetails::summary),
+            user_agent_details: session.user_agent_details,
+            device_id: session.device_id,
+            region: session.region,
+            is_valid: session.is_valid,
+            mfa_status: session.mfa_status,
+        }
+    }
+}
+
 /// Handler error response
 #[derive(Debug, Serialize)]
 pub struct ErrorResponse {
@@ -69,6 +139,49 @@ impl IntoResponse for SessionServiceError {
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Failed to hash token".to_string(),
             ),
+            SessionServiceError::MfaEnrollmentPending => {
+                (StatusCode::FORBIDDEN, "MFA enrollment required".to_string())
+            },
+            SessionServiceError::NotTenantMember => (
+                StatusCode::FORBIDDEN,
+                "User is not a member of the tenant".to_string(),
+            ),
+            SessionServiceError::TenantSwitchUnavailable => (
+                StatusCode::NOT_IMPLEMENTED,
+                "Tenant switching is not available".to_string(),
+            ),
+            SessionServiceError::ImpersonationUnavailable => (
+                StatusCode::NOT_IMPLEMENTED,
+                "Impersonation is not available".to_string(),
+            ),
+            SessionServiceError::ImpersonationNotAllowed(reason) => (
+                StatusCode::FORBIDDEN,
+                format!("Impersonation not allowed: {}", reason),
+            ),
+            SessionServiceError::Tenant(err) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Tenant error: {}", err),
+            ),
+            SessionServiceError::ReconciliationUnavailable => (
+                StatusCode::NOT_IMPLEMENTED,
+                "Session reconciliation is not available".to_string(),
+            ),
+            SessionServiceError::User(err) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("User error: {}", err),
+            ),
+            SessionServiceError::NotGuestSession => (
+                StatusCode::BAD_REQUEST,
+                "Not a valid guest session".to_string(),
+            ),
+            SessionServiceError::InvalidGuestMetadata(reason) => (
+                StatusCode::BAD_REQUEST,
+                format!("Invalid guest session metadata: {}", reason),
+            ),
+            SessionServiceError::InvalidMetadata(err) => (
+                StatusCode::BAD_REQUEST,
+                format!("Invalid session metadata: {}", err),
+            ),
         };
 
         let body = Json(ErrorResponse {
@@ -80,6 +193,24 @@ impl IntoResponse for SessionServiceError {
     }
 }
 
+/// List the sessions of a user (Admin action)
+///
+/// Each entry states whether it is a persistent ("remember me") session.
+pub async fn list_user_sessions(
+    State(state): State<SessionServiceState>,
+    Path(user_id): Path<Uuid>,
+    Query(query): Query<ListUserSessionsQuery>,
+) -> Result<impl IntoResponse, SessionServiceError> {
+    let sessions = state
+        .service
+        .get_user_sessions(user_id, query.filter.unwrap_or(SessionFilter::Active))
+        .await?;
+
+    let response: Vec<SessionSummary> = sessions.into_iter().map(SessionSummary::from).collect();
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
 /// Terminate all sessions for a user (Admin action)
 ///
 /// This endpoint allows administrators to forcibly terminate all sessions
@@ -125,6 +256,36 @@ pub async fn terminate_sessions_by_ip(
     Ok((StatusCode::OK, Json(response)))
 }
 
+/// Terminate sessions created in a specific region (Admin action)
+///
+/// This endpoint allows administrators to respond to an incident in one
+/// region without logging out the users of the others.
+pub async fn terminate_sessions_by_region(
+    State(state): State<SessionServiceState>,
+    Json(request): Json<TerminateSessionsByRegionRequest>,
+) -> Result<impl IntoResponse, SessionServiceError> {
+    let count = state
+        .service
+        .force_terminate_sessions_by_region(
+            &request.region,
+            request.tenant_id,
+            request.include_unknown,
+            request.reason,
+        )
+        .await?;
+
+    let response = SessionTerminationResponse {
+        terminated_count: count,
+        success: true,
+        message: format!(
+            "Successfully terminated {} sessions from region {}",
+            count, request.region
+        ),
+    };
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
 /// Terminate sessions by filter criteria (Admin action)
 ///
 /// This endpoint allows administrators to terminate sessions based on
@@ -150,122 +311,39 @@ pub async fn terminate_sessions_by_filter(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{config::AuthConfig, services::session::SessionService, session::Session};
-    use std::time::SystemTime;
-
-    // Mock session repository for testing
-    struct MockSessionRepository;
-
-    #[async_trait::async_trait]
-    impl crate::session::SessionRepository for MockSessionRepository {
-        async fn create_session(
-            &self,
-            _user_id: Uuid,
-            _token_hash: String,
-            _expires_at: SystemTime,
-            _device_id: Option<String>,
-            _device_fingerprint: Option<crate::session::types::DeviceFingerprint>,
-            _ip_address: Option<String>,
-            _user_agent: Option<String>,
-            _metadata: Option<serde_json::Value>,
-        ) -> Result<Session, crate::session::SessionError> {
-            unimplemented!()
-        }
-
-        async fn get_session(
-            &self,
-            _id: Uuid,
-        ) -> Result<Option<Session>, crate::session::SessionError> {
-            unimplemented!()
-        }
-
-        async fn get_session_by_token(
-            &self,
-            _token_hash: &str,
-        ) -> Result<Option<Session>, crate::session::SessionError> {
-            unimplemented!()
-        }
-
-        async fn get_user_sessions(
-            &self,
-            _user_id: Uuid,
-            _filter: SessionFilter,
-        ) -> Result<Vec<Session>, crate::session::SessionError> {
-            unimplemented!()
-        }
-
-        async fn update_session_activity(
-            &self,
-            _id: Uuid,
-        ) -> Result<(), crate::session::SessionError> {
-            unimplemented!()
-        }
-
-        async fn invalidate_session(
-            &self,
-            _id: Uuid,
-            _reason: SessionInvalidationReason,
-        ) -> Result<(), crate::session::SessionError> {
-            unimplemented!()
-        }
-
-        async fn invalidate_all_user_sessions(
-            &self,
-            _user_id: Uuid,
-            _reason: SessionInvalidationReason,
-        ) -> Result<u64, crate::session::SessionError> {
-            // Simulate terminating 3 sessions
-            Ok(3)
-        }
-
-        async fn invalidate_sessions_by_filter(
-            &self,
-            _filter: SessionFilter,
-            _reason: SessionInvalidationReason,
-        ) -> Result<u64, crate::session::SessionError> {
-            // Simulate terminating 5 sessions
-            Ok(5)
-        }
-
-        async fn invalidate_sessions_by_ip(
-            &self,
-            _ip_address: &str,
-            _reason: SessionInvalidationReason,
-        ) -> Result<u64, crate::session::SessionError> {
-            // Simulate terminating 2 sessions
-            Ok(2)
-        }
-
-        async fn rotate_session_token(
-            &self,
-            _id: Uuid,
-            _new_token_hash: String,
-        ) -> Result<(), crate::session::SessionError> {
-            unimplemented!()
-        }
-
-        async fn cleanup_expired_sessions(&self) -> Result<u64, crate::session::SessionError> {
-            unimplemented!()
-        }
-
-        async fn update_mfa_status(
-            &self,
-            _id: Uuid,
-            _status: crate::session::types::MfaStatus,
-        ) -> Result<(), crate::session::SessionError> {
-            unimplemented!()
-        }
-    }
+    use crate::{
+        config::AuthConfig,
+        services::session::SessionService,
+        session::{SessionRepository, mock::MockSessionRepository},
+    };
+    use time::{Duration, OffsetDateTime};
 
     #[tokio::test]
     async fn test_terminate_user_sessions() {
         // Setup
-        let repo = Arc::new(MockSessionRepository);
+        let repo = Arc::new(MockSessionRepository::new());
+        let user_id = Uuid::new_v4();
+        for i in 0..3 {
+            repo.create_session(
+                user_id,
+                format!("token-{}", i),
+                OffsetDateTime::now_utc() + Duration::hours(1),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        }
+
         let config = Arc::new(AuthConfig::default());
         let service = Arc::new(SessionService::new(repo, config));
         let state = SessionServiceState { service };
 
-        let user_id = Uuid::new_v4();
         let request = TerminateUserSessionsRequest {
             reason: SessionInvalidationReason::AdminAction,
         };
@@ -286,4 +364,110 @@ mod tests {
         assert_eq!(response.terminated_count, 3);
         assert!(response.success);
     }
+
+    #[tokio::test]
+    async fn test_list_user_sessions_reports_persistence() {
+        // Setup
+        let repo = Arc::new(MockSessionRepository::new());
+        let config = Arc::new(AuthConfig::default());
+        let service = Arc::new(SessionService::new(repo, config));
+        let user_id = Uuid::new_v4();
+
+        let (persistent, _) = service
+            .create_session(user_id, None, None, None, None, None, true)
+            .await
+            .unwrap();
+        let (transient, _) = service
+            .create_session(user_id, None, None, None, None, None, false)
+            .await
+            .unwrap();
+
+        let state = SessionServiceState { service };
+        let query = ListUserSessionsQuery { filter: None };
+
+        // Execute
+        let result = list_user_sessions(State(state), Path(user_id), Query(query))
+            .await
+            .unwrap();
+
+        // Assert
+        let sessions: Vec<SessionSummary> = serde_json::from_slice(
+            &axum::body::to_bytes(result.into_response().into_body(), usize::MAX)
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(sessions.len(), 2);
+        let find = |id: Uuid| sessions.iter().find(|s| s.id == id).unwrap();
+        assert!(find(persistent.id).persistent);
+        assert!(!find(transient.id).persistent);
+        assert!(find(persistent.id).expires_at > find(transient.id).expires_at);
+    }
+
+    #[tokio::test]
+    async fn test_list_user_sessions_describes_devices() {
+        const CHROME_ON_WINDOWS: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) \
+            AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+        let repo = Arc::new(MockSessionRepository::new());
+        let user_id = Uuid::new_v4();
+        // Created before user agents were parsed at login
+        let legacy = repo
+            .create_session(
+                user_id,
+                "legacy-token".to_string(),
+                OffsetDateTime::now_utc() + Duration::hours(1),
+                None,
+                None,
+                None,
+                Some(CHROME_ON_WINDOWS.to_string()),
+                None,
+                None,
+                None,
+            )
+            .await
+            .expect("Failed to create session");
+        let service = Arc::new(SessionService::new(
+            repo.clone(),
+            Arc::new(AuthConfig::default()),
+        ));
+        let (current, _) = service
+            .create_session(
+                user_id,
+                None,
+                None,
+                None,
+                Some(CHROME_ON_WINDOWS.to_string()),
+                None,
+                false,
+            )
+            .await
+            .expect("Failed to create session");
+        assert!(current.user_agent_details.is_some());
+
+        let state = SessionServiceState { service };
+        let query = ListUserSessionsQuery { filter: None };
+        let result = list_user_sessions(State(state), Path(user_id), Query(query))
+            .await
+            .expect("Sessions are listed");
+        let sessions: Vec<SessionSummary> = serde_json::from_slice(
+            &axum::body::to_bytes(result.into_response().into_body(), usize::MAX)
+                .await
+                .expect("Failed to read body"),
+        )
+        .expect("Body is a session list");
+
+        assert_eq!(sessions.len(), 2);
+        for session in &sessions {
+            assert_eq!(session.device.as_deref(), Some("Chrome 120 on Windows 10"));
+        }
+        // Parsing on access does not write the legacy session
+        let stored = repo
+            .get_session(legacy.id)
+            .await
+            .expect("Failed to load session")
+            .expect("Session exists");
+        assert!(stored.user_agent_details.is_none());
+    }
 }
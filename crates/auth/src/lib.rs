@@ -5,47 +5,147 @@ pub mod repository;
 pub mod security;
 pub mod services;
 pub mod session;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
 pub mod utils;
 
-pub use config::AuthConfig;
+pub use config::{
+    AuthConfig, ImpersonationConfig, PasswordConfig, ReauthConfig, SensitiveOperation,
+    SessionLifetime, SessionLifetimeConfig, SessionReconciliationConfig, TrustedDeviceConfig,
+};
+pub use handlers::cookie::{CookieConfig, CookieConfigError, SameSite};
+pub use handlers::current_user::{
+    CurrentUser, CurrentUserRejection, HOST_SESSION_COOKIE, SESSION_COOKIE,
+};
 pub use handlers::session::{
-    SessionServiceState, SessionTerminationResponse, TerminateSessionsByFilterRequest,
-    TerminateSessionsByIpRequest, TerminateUserSessionsRequest, terminate_sessions_by_filter,
-    terminate_sessions_by_ip, terminate_user_sessions,
+    ListUserSessionsQuery, SessionServiceState, SessionSummary, SessionTerminationResponse,
+    TerminateSessionsByFilterRequest, TerminateSessionsByIpRequest,
+    TerminateSessionsByRegionRequest, TerminateUserSessionsRequest, list_user_sessions,
+    terminate_sessions_by_filter, terminate_sessions_by_ip, terminate_sessions_by_region,
+    terminate_user_sessions,
+};
+pub use models::account_deletion::AccountDeletionRequest;
+pub use models::api_key::{ApiKey, ApiKeyFilter, IssuedApiKey, RotatedApiKey};
+pub use models::delivery::{DeliveryReceipt, DeliveryStatus, MessageDelivery};
+pub use models::factor::{AuthFactor, EnrolledFactor};
+pub use models::identity::LinkedIdentity;
+pub use models::login_attempt::{LoginAttemptRecord, LoginAttemptStats, LoginOutcome};
+pub use models::outbox::{OutboxMessage, OutboxStatus};
+pub use models::platform_stats::{
+    RegionSessionCount, TenantCounts, TenantSessionCount, UserSignupCounts,
 };
 pub use models::tenant::{
-    CreateTenantDto, Tenant, TenantError, TenantPlanType, TenantRepository, TenantSubscription,
-    TenantUser, UpdateTenantDto,
+    BindingEnforcement, ClientBindingPolicy, CreateTenantDto, LoginLocationViolation,
+    MfaEnforcement, PasswordExpiry, PlanChange, SubscriptionChangeType, SubscriptionHistoryEntry,
+    Tenant, TenantError, TenantPlanType, TenantRepository, TenantSecurityPolicy, TenantStatus,
+    TenantSubscription, TenantUser, UnknownCountryAction, UpdateTenantDto,
+    WebAuthnAttestationPolicy,
+};
+pub use models::timeline::{
+    TimelineCursor, TimelineCursorError, TimelineEvent, TimelineEventKind, TimelineRecord,
+    TimelineSource,
 };
 pub use models::totp::{Algorithm, TotpConfig, TotpSecret, TotpSecretInfo};
-pub use models::user::{CreateUser, LoginCredentials, User, UserError, UserRepository};
+pub use models::user::{
+    CreateUser, LoginCredentials, User, UserError, UserRepository, UserSessionState,
+};
 pub use models::verification::{
-    VerificationCode, VerificationConfig, VerificationStatus, VerificationType,
+    ExpiredCodes, VerificationCode, VerificationConfig, VerificationConfigError, VerificationStats,
+    VerificationStatus, VerificationType,
+};
+pub use repository::{
+    AccountDeletionRepository, ApiKeyRepository, ExternalIdentityRepository,
+    LoginAttemptRepository, MessageDeliveryRepository, MessageOutboxRepository, OperationTimedOut,
+    OperationTimeouts, PlatformStatsRepository, PostgresAccountDeletionRepository,
+    PostgresApiKeyRepository, PostgresExternalIdentityRepository, PostgresLoginAttemptRepository,
+    PostgresMessageDeliveryRepository, PostgresMessageOutboxRepository,
+    PostgresPlatformStatsRepository, PostgresTenantRepository, PostgresTimelineRepository,
+    PostgresTotpRepository, PostgresUserRepository, PostgresVerificationCodeRepository,
+    Repositories, RepositoryBackend, RepositoryConfig, RepositoryError, RepositoryFactory,
+    TenantAwareContext, TenantAwareRepository, TimelineRepository, TotpSecretRepository,
+    VerificationCodeRepository,
 };
+#[cfg(feature = "in-memory")]
 pub use repository::{
-    PostgresTenantRepository, PostgresTotpRepository, PostgresUserRepository,
-    PostgresVerificationCodeRepository, RepositoryConfig, RepositoryError, TenantAwareContext,
-    TenantAwareRepository, TotpSecretRepository, VerificationCodeRepository,
+    InMemoryDatabase, InMemorySessionRepository, InMemoryTenantRepository, InMemoryUserRepository,
+    InMemoryVerificationCodeRepository,
 };
 pub use security::{
-    BruteForceError, BruteForceProtection, Challenge, CredentialStuffingProtection, NonceStore,
-    RateLimitConfig, RateLimitMiddleware, ReplayProtectionMiddleware, RiskLevel, SecurityConfig,
-    SecurityProtection, create_security_protection,
+    BruteForceError, BruteForceProtection, Challenge, ClientCharacteristics, ClientFingerprint,
+    CredentialStuffingProtection, FingerprintService, GeoIpResolver, NonceStore, RateLimitConfig,
+    RateLimitMiddleware, ReplayProtectionMiddleware, RiskLevel, SecurityConfig, SecurityProtection,
+    StaticGeoIpResolver, TrustedDevice, TrustedDeviceError, TrustedDeviceService,
+    create_security_protection,
+};
+#[cfg(feature = "dev-tools")]
+pub use services::dev_seed::{
+    DevSeedError, DevSeedOptions, DevSeeder, SeedCounts, SeedSummary, SeededRole, SeededTenant,
+    SeededUser,
 };
 pub use services::{
+    account_deletion::{
+        ACCOUNT_PENDING_DELETION, AccountDeletionConfig, AccountDeletionError,
+        AccountDeletionService, DeletionConfirmation, DeletionReport,
+    },
+    api_key::{
+        API_KEY_AUTH_SCHEME, ApiKeyError, ApiKeyQuery, ApiKeyService, DEFAULT_ROTATION_OVERLAP,
+        IssueApiKey, LAST_USE_RECORD_INTERVAL,
+    },
+    api_key_reminder::{ApiKeyReminderConfig, ApiKeyReminderReport, ApiKeyReminderService},
+    delivery_receipt::{
+        DeliveryFailurePolicy, DeliveryReceiptConfig, DeliveryReceiptVerifier, DeliveryTracker,
+        ReceiptOutcome, Resend, ResendPolicy, TrackedMessageProvider, parse_sendgrid_events,
+        parse_twilio_callback, parse_vonage_receipt,
+    },
     email_provider::{SendGridEmailProvider, SmtpEmailProvider, create_email_provider},
+    email_routing::{DEFAULT_EMAIL_PROVIDER, RoutingEmailProvider, create_routing_email_provider},
+    event_export::{
+        CefFormatter, EventCategory, EventExportConfig, EventExportError, EventExporter,
+        EventTransport, ExportTarget, HttpsCollectorTransport, SUSPICIOUS_LOGIN_ACTION,
+        SecurityEvent, SyslogFormat, TcpSyslogTransport, UdpSyslogTransport,
+    },
+    login_attempts::{LoginAttemptContext, LoginAttemptRecorder},
+    message_outbox::{
+        DispatchReport, MessageDispatcher, MessageOutbox, OutboxConfig, QueuedMessageProvider,
+    },
     message_provider::{
-        EmailProviderConfig, Message, MessageProvider, MessageProviderConfig, SmsProviderConfig,
-        SmtpConfig,
+        DeliveryMode, EmailProviderConfig, EmailRoutingConfig, EmailRoutingRule, Message,
+        MessageProvider, MessageProviderConfig, SmsProviderConfig, SmtpConfig,
+    },
+    mfa::{MfaOrchestrator, MfaOrchestratorError},
+    oidc::{
+        ExternalIdentity, ExternalLogin, IdentitySource, OidcError, OidcProvider,
+        OidcProviderConfig, OidcStateStore,
+    },
+    saml::{
+        AssertionReplayStore, SAML_IDP_METADATA_KEY, SamlError, SamlIdpConfig, SamlLogin,
+        SamlServiceProvider, SamlSpConfig,
+    },
+    scim::{
+        SCIM_TOKEN_METADATA_KEY, ScimError, ScimFilter, ScimGroup, ScimListResponse, ScimPage,
+        ScimPatchRequest, ScimService, ScimUser,
+    },
+    session::{
+        ClientBindingCheck, SESSION_CLEANUP_LOCK, SessionReconciliationReport, SessionService,
+        SessionServiceError,
     },
-    session::{SessionService, SessionServiceError},
     sms_provider::{TwilioSmsProvider, VonageSmsProvider, create_sms_provider},
     tenant::{
-        CreateTenantWithAdminDto, TenantService, TenantServiceError, TenantWithAdminResponse,
+        BulkAddFailure, BulkResult, BulkRowResult, CreateTenantWithAdminDto,
+        SUBSCRIPTION_HISTORY_PAGE_SIZE, TenantService, TenantServiceError, TenantWithAdminResponse,
+    },
+    timeline::{
+        DEFAULT_TIMELINE_PAGE_SIZE, MAX_TIMELINE_PAGE_SIZE, TimelinePage, TimelineView,
+        UserTimelineService,
     },
     totp::{TotpError, TotpService},
-    user::{UserService, UserServiceError},
-    verification::{VerificationError, VerificationService},
+    user::{AccessRevocation, MfaEnrollment, PASSWORD_RESET_ACTION, UserService, UserServiceError},
+    user_agent::UserAgentParser,
+    verification::{
+        ChannelFallbackConfig, ConsumedProof, ContactDirectory, UserContactDirectory,
+        VerificationCleanupReport, VerificationDelivery, VerificationError, VerificationProof,
+        VerificationService,
+    },
 };
 pub use session::enhanced_security::{
     EnhancedFingerprintRepository, EnhancedSessionFingerprint,
@@ -54,53 +154,93 @@ pub use session::enhanced_security::{
     SessionLocationRepository, SessionRiskAssessment,
 };
 pub use session::{
-    Session, SessionError, SessionFilter, SessionRepository,
-    types::{DeviceFingerprint, SessionInvalidationReason},
+    ACTIVE_TENANT_METADATA_KEY, CLIENT_BINDING_METADATA_KEY, IMPERSONATED_BY_METADATA_KEY,
+    IMPERSONATION_REASON_METADATA_KEY, LAST_AUTH_METADATA_KEY, MFA_VERIFIED_METADATA_KEY,
+    PERSISTENT_SESSION_METADATA_KEY, Session, SessionError, SessionExportFilter, SessionFilter,
+    SessionRepository, SessionWithLocation,
+    types::{
+        DeviceFingerprint, DeviceType, FactorAction, ImpersonationAction, MfaStatus,
+        SessionInvalidationReason, SessionKind, UserAgentDetails,
+    },
 };
 pub use utils::{
-    jwt::{Claims, JwtError, JwtUtils},
+    auth_log::{AuthDecision, AuthEvent, LogPrivacyConfig, LogPrivacyMode},
+    bounded_json::{BoundedJson, JsonLimitError, JsonLimitViolation, JsonLimits},
+    claims::{
+        ClaimsConfig, ClaimsEnricher, FeatureFlagEnricher, PLATFORM_ADMIN_CLAIM,
+        PlatformAdminEnricher, SubscriptionPlanEnricher, TenantRoleEnricher, TokenTenant,
+    },
+    clock::{Clock, SystemClock},
+    duration::{DurationMs, DurationSecs},
+    jwt::{Claims, JwtError, JwtUtils, RESERVED_CLAIMS},
     password::{PasswordError, check_password_strength, hash_password, verify_password},
+    secret::Secret,
 };
 
 use acci_core::error::Result;
+use acci_core::http_client::HttpClientFactory;
 use std::sync::Arc;
 
 /// Create a verification service with configured providers
+///
+/// Providers configured for queued delivery are wrapped in `outbox`; run
+/// [`MessageOutbox::dispatcher`] afterwards to deliver the queued messages.
+/// With a `tracker` the providers record every sent message, so that
+/// delivery receipts can be applied with [`DeliveryTracker::apply_receipt`].
+/// With `contacts`, codes fall back to the channels configured in
+/// [`ChannelFallbackConfig`] when sending fails.
 pub fn create_verification_service(
     config: &AuthConfig,
     verification_repository: Arc<dyn VerificationCodeRepository>,
+    outbox: Option<&MessageOutbox>,
+    tracker: Option<&DeliveryTracker>,
+    contacts: Option<Arc<dyn ContactDirectory>>,
 ) -> Result<Arc<VerificationService>> {
-    // Create verification config from auth config
-    let verification_config = models::VerificationConfig {
-        code_length: config.verification.code_length,
-        expiration_seconds: config.verification.expiration_seconds,
-        max_attempts: config.verification.max_attempts,
-        throttle_seconds: config.verification.throttle_seconds,
-    };
+    acci_core::secret_store::ensure_resolved("jwt_secret", &config.jwt_secret)?;
+
+    // Create verification config from auth config, refusing to issue unusable codes
+    let verification_config = config.verification.codes();
+    verification_config
+        .validate()
+        .map_err(|e| acci_core::error::Error::Config(e.to_string()))?;
 
     // Setup message providers if configured
     let mut email_provider = None;
     let mut sms_provider = None;
 
     if let Some(ref message_config) = config.message_providers {
+        let client = HttpClientFactory::new(config.outbound_http.clone()).build()?;
+
         // Setup email provider
-        if let Ok(provider) = create_email_provider(message_config.email.clone()) {
+        if let Ok(provider) =
+            create_routing_email_provider(message_config, &client, outbox, tracker)
+        {
             email_provider = Some(provider);
         }
 
         // Setup SMS provider
-        if let Ok(provider) = create_sms_provider(message_config.sms.clone()) {
+        if let Ok(provider) =
+            create_sms_provider(message_config.sms.clone(), &client, outbox, tracker)
+        {
             sms_provider = Some(provider);
         }
     }
 
     // Create verification service
-    let verification_service = VerificationService::new(
+    let mut verification_service = VerificationService::new(
         verification_repository,
         verification_config,
         sms_provider,
         email_provider,
+    )
+    .with_proof_signing(
+        config.jwt_secret.as_bytes(),
+        config.verification.proof_ttl_secs,
     );
+    if let Some(contacts) = contacts {
+        verification_service = verification_service
+            .with_channel_fallback(config.verification.fallback.clone(), contacts);
+    }
 
     Ok(Arc::new(verification_service))
 }
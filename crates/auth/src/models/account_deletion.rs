@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// A user's request to delete their own account
+///
+/// The account stays usable until `scheduled_for`, so the user can change
+/// their mind. Afterwards the deletion job anonymizes it, unless the user is
+/// still the last admin of a tenant.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountDeletionRequest {
+    pub user_id: Uuid,
+    pub requested_at: OffsetDateTime,
+    /// End of the grace period, when the account is deleted
+    pub scheduled_for: OffsetDateTime,
+    /// When the user was told to transfer tenant ownership first
+    pub ownership_notice_sent_at: Option<OffsetDateTime>,
+    /// When the account was anonymized
+    pub completed_at: Option<OffsetDateTime>,
+}
+
+impl AccountDeletionRequest {
+    /// Create a request whose grace period ends at `scheduled_for`
+    pub fn new(user_id: Uuid, requested_at: OffsetDateTime, scheduled_for: OffsetDateTime) -> Self {
+        Self {
+            user_id,
+            requested_at,
+            scheduled_for,
+            ownership_notice_sent_at: None,
+            completed_at: None,
+        }
+    }
+
+    /// Whether the account has not been deleted yet
+    pub fn is_pending(&self) -> bool {
+        self.completed_at.is_none()
+    }
+
+    /// Whether the grace period has ended at `now`
+    pub fn is_due(&self, now: OffsetDateTime) -> bool {
+        self.is_pending() && self.scheduled_for <= now
+    }
+}
@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// A long-lived credential of a service integration, scoped to one tenant
+///
+/// Only the hash of the key is stored; the key itself is returned once when
+/// it is issued. The prefix is stored in plain text to look the key up.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    /// Name given by the tenant, e.g. the integration using the key
+    pub name: String,
+    /// What the key is used for
+    pub description: Option<String>,
+    /// Labels to find the key by, e.g. the owning team
+    pub tags: Vec<String>,
+    /// Public part of the key used for lookup
+    pub prefix: String,
+    /// SHA-256 hex digest of the full key
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    /// Permissions granted to requests made with the key
+    pub scopes: Vec<String>,
+    /// User who issued the key
+    pub created_by: Option<Uuid>,
+    pub created_at: OffsetDateTime,
+    pub expires_at: Option<OffsetDateTime>,
+    /// Updated at most once per minute, see [`ApiKeyService::authenticate`](crate::ApiKeyService::authenticate)
+    pub last_used_at: Option<OffsetDateTime>,
+    pub revoked_at: Option<OffsetDateTime>,
+    /// When the tenant admins were last reminded that the key expires soon or
+    /// is unused
+    pub reminded_at: Option<OffsetDateTime>,
+}
+
+impl ApiKey {
+    /// Whether the key has been revoked
+    pub fn is_revoked(&self) -> bool {
+        self.revoked_at.is_some()
+    }
+
+    /// Whether the key has expired at `now`
+    pub fn is_expired(&self, now: OffsetDateTime) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// Whether the key grants `scope`
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|granted| granted == scope)
+    }
+
+    /// Whether the key was used since `since`; keys never used count from their creation
+    pub fn used_since(&self, since: OffsetDateTime) -> bool {
+        self.last_used_at.unwrap_or(self.created_at) >= since
+    }
+}
+
+/// Criteria of [`ApiKeyRepository::list`](crate::repository::ApiKeyRepository::list)
+///
+/// Every set criterion has to match; the default filter matches every key of
+/// the tenant, including revoked and expired ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApiKeyFilter {
+    /// Keys carrying all of these tags
+    pub tags: Vec<String>,
+    /// Text contained in the name, description or prefix, ignoring case
+    pub search: Option<String>,
+    /// Keys neither revoked nor expired at this time
+    pub valid_at: Option<OffsetDateTime>,
+    /// Keys expiring before this time
+    pub expires_before: Option<OffsetDateTime>,
+    /// Keys not used since this time, see [`ApiKey::used_since`]
+    pub unused_since: Option<OffsetDateTime>,
+}
+
+impl ApiKeyFilter {
+    /// Whether `key` meets the criteria
+    pub fn matches(&self, key: &ApiKey) -> bool {
+        let search = self.search.as_ref().map(|search| search.to_lowercase());
+        self.tags.iter().all(|tag| key.tags.contains(tag))
+            && search.is_none_or(|search| {
+                key.name.to_lowercase().contains(&search)
+                    || key.prefix.contains(&search)
+                    || key
+                        .description
+                        .as_ref()
+                        .is_some_and(|description| description.to_lowercase().contains(&search))
+            })
+            && self
+                .valid_at
+                .is_none_or(|now| !key.is_revoked() && !key.is_expired(now))
+            && self.expires_before.is_none_or(|expires_before| {
+                key.expires_at
+                    .is_some_and(|expires_at| expires_at < expires_before)
+            })
+            && self
+                .unused_since
+                .is_none_or(|unused_since| !key.used_since(unused_since))
+    }
+}
+
+/// A newly issued API key together with its plaintext value
+///
+/// The plaintext cannot be recovered later, so it must be handed to the
+/// caller right away.
+#[derive(Debug, Clone, Serialize)]
+pub struct IssuedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    /// Full key to send as `Authorization: ApiKey <key>`
+    pub key: String,
+}
+
+/// A replacement key issued by a rotation
+#[derive(Debug, Clone, Serialize)]
+pub struct RotatedApiKey {
+    #[serde(flatten)]
+    pub issued: IssuedApiKey,
+    /// Until when the replaced key stays valid, so clients can switch over
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub previous_key_expires_at: Option<OffsetDateTime>,
+}
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::models::{TenantId, UserId, VerificationType};
+use crate::services::message_provider::Message;
+
+/// Delivery state of a message reported by the provider
+///
+/// States only move forward: a receipt arriving out of order, e.g. `sent`
+/// after `delivered`, does not change the state, and `Delivered` and
+/// `Failed` are final.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeliveryStatus {
+    /// Accepted by the provider, not yet handed to the carrier or mail server
+    Queued,
+    /// Handed to the carrier or mail server
+    Sent,
+    /// Reached the recipient
+    Delivered,
+    /// Could not be delivered
+    Failed,
+}
+
+impl DeliveryStatus {
+    /// Whether no receipt changes the state any more
+    pub fn is_final(self) -> bool {
+        matches!(self, Self::Delivered | Self::Failed)
+    }
+
+    /// Whether a receipt reporting `next` advances a delivery in this state
+    pub fn can_advance_to(self, next: DeliveryStatus) -> bool {
+        !self.is_final() && next.rank() > self.rank()
+    }
+
+    fn rank(self) -> u8 {
+        match self {
+            Self::Queued => 0,
+            Self::Sent => 1,
+            Self::Delivered | Self::Failed => 2,
+        }
+    }
+}
+
+/// A message handed to a provider, tracked until the provider reports its delivery
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageDelivery {
+    /// Unique identifier of the delivery
+    pub id: Uuid,
+    /// Tenant the message belongs to
+    pub tenant_id: TenantId,
+    /// User the message is addressed to
+    pub user_id: UserId,
+    /// Channel the message was sent through
+    pub message_type: VerificationType,
+    /// Recipient (email or phone number)
+    pub recipient: String,
+    /// Subject (for emails)
+    pub subject: Option<String>,
+    /// Message body, kept to send the message again if delivery fails
+    pub body: String,
+    /// Identifier returned by the provider at send time, e.g. `twilio:SM...`
+    pub provider_message_id: String,
+    /// Current delivery state
+    pub status: DeliveryStatus,
+    /// Number of the attempt, 1 for the first send
+    pub attempt: u32,
+    /// Failed delivery this one was sent to replace
+    pub retry_of: Option<Uuid>,
+    /// Error reported by the provider for a failed delivery
+    pub error: Option<String>,
+    /// When the message was handed to the provider
+    pub created_at: OffsetDateTime,
+    /// When the last receipt was applied
+    pub updated_at: OffsetDateTime,
+}
+
+impl MessageDelivery {
+    /// A delivery of `message` that the provider accepted as `provider_message_id`
+    pub fn new(message: &Message, provider_message_id: String, now: OffsetDateTime) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            tenant_id: message.tenant_id,
+            user_id: message.user_id,
+            message_type: message.message_type,
+            recipient: message.recipient.clone(),
+            subject: message.subject.clone(),
+            body: message.body.clone(),
+            provider_message_id,
+            status: DeliveryStatus::Queued,
+            attempt: 1,
+            retry_of: None,
+            error: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Rebuild the provider message from the stored delivery
+    pub fn to_message(&self) -> Message {
+        Message {
+            tenant_id: self.tenant_id,
+            user_id: self.user_id,
+            recipient: self.recipient.clone(),
+            subject: self.subject.clone(),
+            body: self.body.clone(),
+            message_type: self.message_type,
+        }
+    }
+}
+
+/// Delivery state of one message reported by a provider callback
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeliveryReceipt {
+    /// Message ID in the form returned at send time, e.g. `twilio:SM...`
+    pub provider_message_id: String,
+    /// Reported delivery state
+    pub status: DeliveryStatus,
+    /// Error code or reason given by the provider for a failure
+    pub error: Option<String>,
+}
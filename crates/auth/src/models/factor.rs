@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// An authentication factor a user can sign in with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuthFactor {
+    Password,
+    Totp,
+    #[serde(rename = "webauthn")]
+    WebAuthn {
+        /// Framework-assigned UUID of the credential
+        credential_id: Uuid,
+    },
+    /// Backup codes issued with TOTP
+    RecoveryCodes,
+}
+
+impl AuthFactor {
+    /// Stable identifier used in routes and audit entries
+    pub fn id(&self) -> String {
+        match self {
+            AuthFactor::Password => "password".to_string(),
+            AuthFactor::Totp => "totp".to_string(),
+            AuthFactor::WebAuthn { credential_id } => format!("webauthn:{}", credential_id),
+            AuthFactor::RecoveryCodes => "recovery_codes".to_string(),
+        }
+    }
+
+    /// Parse an identifier produced by [`id`](Self::id)
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "password" => Some(AuthFactor::Password),
+            "totp" => Some(AuthFactor::Totp),
+            "recovery_codes" => Some(AuthFactor::RecoveryCodes),
+            _ => id
+                .strip_prefix("webauthn:")
+                .and_then(|credential_id| Uuid::parse_str(credential_id).ok())
+                .map(|credential_id| AuthFactor::WebAuthn { credential_id }),
+        }
+    }
+
+    /// Whether the factor satisfies MFA on its own
+    ///
+    /// Recovery codes are a fallback for a lost authenticator and do not count.
+    pub fn is_second_factor(&self) -> bool {
+        matches!(self, AuthFactor::Totp | AuthFactor::WebAuthn { .. })
+    }
+}
+
+impl fmt::Display for AuthFactor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.id())
+    }
+}
+
+/// A factor enrolled by a user, as listed in the factors overview
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EnrolledFactor {
+    pub factor: AuthFactor,
+    /// User-chosen name, e.g. of a security key
+    pub name: Option<String>,
+    pub created_at: Option<OffsetDateTime>,
+    pub last_used_at: Option<OffsetDateTime>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_factor_ids_round_trip() {
+        let factors = [
+            AuthFactor::Password,
+            AuthFactor::Totp,
+            AuthFactor::WebAuthn {
+                credential_id: Uuid::new_v4(),
+            },
+            AuthFactor::RecoveryCodes,
+        ];
+
+        for factor in factors {
+            assert_eq!(AuthFactor::from_id(&factor.id()), Some(factor));
+        }
+        assert_eq!(AuthFactor::from_id("webauthn:not-a-uuid"), None);
+        assert_eq!(AuthFactor::from_id("sms"), None);
+    }
+}
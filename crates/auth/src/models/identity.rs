@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// An external identity provider account linked to a local user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkedIdentity {
+    pub user_id: Uuid,
+    /// Name of the provider, e.g. `google`
+    pub provider: String,
+    /// Provider's identifier of the account
+    pub subject: String,
+    pub email: String,
+    pub linked_at: OffsetDateTime,
+    pub last_login_at: OffsetDateTime,
+}
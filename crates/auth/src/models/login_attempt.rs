@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::security::types::RiskLevel;
+
+/// How a login attempt ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoginOutcome {
+    /// A session was created
+    Succeeded,
+    /// Unknown account or wrong password
+    InvalidCredentials,
+    /// Correct credentials, but the login was not allowed, e.g. an inactive
+    /// account or a tenant the user is no member of
+    Rejected,
+    /// The login failed for an internal reason
+    Failed,
+}
+
+impl LoginOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Succeeded => "succeeded",
+            Self::InvalidCredentials => "invalid_credentials",
+            Self::Rejected => "rejected",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// A stored login attempt
+///
+/// The username is only kept as a keyed hash, so attempts of the same
+/// account can be grouped without storing the address itself.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoginAttemptRecord {
+    pub id: Uuid,
+    /// Tenant the login was for, if any was requested or resolved
+    pub tenant_id: Option<Uuid>,
+    /// Hex-encoded HMAC-SHA256 of the normalized username
+    pub username_hash: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    /// Hash of the device fingerprint the client sent
+    pub fingerprint_hash: Option<String>,
+    /// Country code (ISO 3166-1 alpha-2) the IP address was located in
+    pub country_code: Option<String>,
+    pub city: Option<String>,
+    /// Risk assessed for the attempt, if it was assessed
+    pub risk_level: Option<RiskLevel>,
+    pub outcome: LoginOutcome,
+    pub attempted_at: OffsetDateTime,
+}
+
+/// Login attempts of a tenant within a window, counted by outcome
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct LoginAttemptStats {
+    pub total: u64,
+    pub succeeded: u64,
+    /// Attempts with any other outcome than success
+    pub failed: u64,
+}
+
+impl LoginAttemptStats {
+    /// Share of the attempts that failed, absent without attempts
+    pub fn failure_rate(&self) -> Option<f64> {
+        (self.total > 0).then(|| self.failed as f64 / self.total as f64)
+    }
+
+    /// Share of the attempts that succeeded, absent without attempts
+    pub fn success_rate(&self) -> Option<f64> {
+        (self.total > 0).then(|| self.succeeded as f64 / self.total as f64)
+    }
+}
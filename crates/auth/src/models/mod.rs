@@ -1,4 +1,13 @@
+pub mod account_deletion;
+pub mod api_key;
+pub mod delivery;
+pub mod factor;
+pub mod identity;
+pub mod login_attempt;
+pub mod outbox;
+pub mod platform_stats;
 pub mod tenant;
+pub mod timeline;
 pub mod totp;
 pub mod user;
 pub mod verification;
@@ -6,11 +15,24 @@ pub mod verification;
 pub mod webauthn;
 
 // Re-export common model types
+pub use account_deletion::AccountDeletionRequest;
+pub use api_key::{ApiKey, IssuedApiKey};
+pub use delivery::{DeliveryReceipt, DeliveryStatus, MessageDelivery};
+pub use factor::{AuthFactor, EnrolledFactor};
+pub use identity::LinkedIdentity;
+pub use login_attempt::{LoginAttemptRecord, LoginAttemptStats, LoginOutcome};
+pub use outbox::{OutboxMessage, OutboxStatus};
+pub use platform_stats::{TenantCounts, TenantSessionCount, UserSignupCounts};
 pub use tenant::TenantId;
+pub use timeline::{
+    TimelineCursor, TimelineCursorError, TimelineEvent, TimelineEventKind, TimelineRecord,
+    TimelineSource,
+};
 pub use totp::{Algorithm, TotpConfig, TotpSecret, TotpSecretInfo};
 pub use user::UserId;
 pub use verification::{
-    VerificationCode, VerificationConfig, VerificationStatus, VerificationType,
+    ExpiredCodes, VerificationCode, VerificationConfig, VerificationConfigError, VerificationStats,
+    VerificationStatus, VerificationType,
 };
 #[cfg(feature = "enable_webauthn")]
 pub use webauthn::{
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::models::{TenantId, UserId, VerificationType};
+use crate::services::message_provider::Message;
+
+/// Delivery status of a queued message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutboxStatus {
+    /// Waiting to be (re)sent by the dispatcher
+    Pending,
+    /// Handed over to the provider successfully
+    Sent,
+    /// Gave up after the retry budget was exhausted
+    Failed,
+}
+
+/// A message persisted in the outbox until the dispatcher delivers it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxMessage {
+    /// Unique identifier of the queued message
+    pub id: Uuid,
+    /// Tenant the message belongs to
+    pub tenant_id: TenantId,
+    /// User the message is addressed to
+    pub user_id: UserId,
+    /// Channel used to deliver the message
+    pub message_type: VerificationType,
+    /// Recipient (email or phone number)
+    pub recipient: String,
+    /// Subject (for emails)
+    pub subject: Option<String>,
+    /// Message body
+    pub body: String,
+    /// Current delivery status
+    pub status: OutboxStatus,
+    /// Number of delivery attempts made so far
+    pub attempts: u32,
+    /// Earliest time the next attempt may be made
+    pub next_attempt_at: OffsetDateTime,
+    /// Error reported by the provider on the last failed attempt
+    pub last_error: Option<String>,
+    /// Identifier returned by the provider once sent
+    pub provider_message_id: Option<String>,
+    /// When the message was enqueued
+    pub created_at: OffsetDateTime,
+    /// When the message was last updated
+    pub updated_at: OffsetDateTime,
+    /// When the message was delivered
+    pub sent_at: Option<OffsetDateTime>,
+}
+
+impl OutboxMessage {
+    /// Create a pending outbox entry for a message, due immediately
+    pub fn new(message: Message, now: OffsetDateTime) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            tenant_id: message.tenant_id,
+            user_id: message.user_id,
+            message_type: message.message_type,
+            recipient: message.recipient,
+            subject: message.subject,
+            body: message.body,
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+            provider_message_id: None,
+            created_at: now,
+            updated_at: now,
+            sent_at: None,
+        }
+    }
+
+    /// Rebuild the provider message from the stored entry
+    pub fn to_message(&self) -> Message {
+        Message {
+            tenant_id: self.tenant_id,
+            user_id: self.user_id,
+            recipient: self.recipient.clone(),
+            subject: self.subject.clone(),
+            body: self.body.clone(),
+            message_type: self.message_type,
+        }
+    }
+}
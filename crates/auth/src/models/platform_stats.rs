@@ -0,0 +1,33 @@
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Number of tenants, by whether they are active
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TenantCounts {
+    pub total: u64,
+    /// Tenants not archived; suspended tenants count as active
+    pub active: u64,
+}
+
+/// Number of users created within the recent windows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct UserSignupCounts {
+    pub last_7_days: u64,
+    pub last_30_days: u64,
+}
+
+/// Valid, unexpired sessions of one tenant
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TenantSessionCount {
+    pub tenant_id: Uuid,
+    pub name: String,
+    pub active_sessions: u64,
+}
+
+/// Valid, unexpired sessions created in one region
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RegionSessionCount {
+    /// `None` for the sessions created without a region
+    pub region: Option<String>,
+    pub active_sessions: u64,
+}
@@ -1,10 +1,15 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use sqlx::types::JsonValue;
+use sqlx::types::ipnetwork::IpNetwork;
+use std::net::IpAddr;
 use thiserror::Error;
-use time::OffsetDateTime;
+use time::{Duration, OffsetDateTime};
 use uuid::Uuid;
 
+use crate::repository::timeout::OperationTimedOut;
+use crate::utils::bounded_json::BoundedJson;
+
 /// Tenant identifier type
 pub type TenantId = Uuid;
 
@@ -17,8 +22,8 @@ pub struct Tenant {
     pub name: String,
     /// Unique subdomain for tenant access
     pub subdomain: String,
-    /// Whether the tenant is currently active
-    pub is_active: bool,
+    /// Lifecycle status of the tenant
+    pub status: TenantStatus,
     /// When the tenant was created
     pub created_at: OffsetDateTime,
     /// When the tenant was last updated
@@ -58,6 +63,19 @@ impl std::fmt::Display for TenantPlanType {
     }
 }
 
+impl TenantPlanType {
+    /// Position of the plan from free to enterprise; custom plans have none
+    pub fn tier(&self) -> Option<u8> {
+        match self {
+            TenantPlanType::Free => Some(0),
+            TenantPlanType::Basic => Some(1),
+            TenantPlanType::Professional => Some(2),
+            TenantPlanType::Enterprise => Some(3),
+            TenantPlanType::Custom => None,
+        }
+    }
+}
+
 // Implement From/Into for converting between string and enum
 impl From<&str> for TenantPlanType {
     fn from(s: &str) -> Self {
@@ -117,6 +135,156 @@ pub struct TenantSubscription {
     pub updated_at: OffsetDateTime,
 }
 
+/// Kind of change recorded in the subscription history
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum SubscriptionChangeType {
+    Created,
+    Updated,
+    Deactivated,
+}
+
+impl SubscriptionChangeType {
+    /// Returns the stored name of the change type
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubscriptionChangeType::Created => "CREATED",
+            SubscriptionChangeType::Updated => "UPDATED",
+            SubscriptionChangeType::Deactivated => "DEACTIVATED",
+        }
+    }
+}
+
+impl std::str::FromStr for SubscriptionChangeType {
+    type Err = TenantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "CREATED" => Ok(SubscriptionChangeType::Created),
+            "UPDATED" => Ok(SubscriptionChangeType::Updated),
+            "DEACTIVATED" => Ok(SubscriptionChangeType::Deactivated),
+            other => Err(TenantError::DatabaseError(format!(
+                "Unknown subscription change type: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// Direction of a change of the plan, recorded for billing reports
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum PlanChange {
+    Upgrade,
+    Downgrade,
+    /// Change to or from a custom plan, which has no tier
+    Lateral,
+}
+
+impl PlanChange {
+    /// Direction of a change from `old` to `new`; `None` if the plan stays the same
+    pub fn between(old: TenantPlanType, new: TenantPlanType) -> Option<Self> {
+        if old == new {
+            return None;
+        }
+        Some(match (old.tier(), new.tier()) {
+            (Some(old), Some(new)) if new > old => PlanChange::Upgrade,
+            (Some(_), Some(_)) => PlanChange::Downgrade,
+            _ => PlanChange::Lateral,
+        })
+    }
+
+    /// Returns the stored name of the direction
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PlanChange::Upgrade => "UPGRADE",
+            PlanChange::Downgrade => "DOWNGRADE",
+            PlanChange::Lateral => "LATERAL",
+        }
+    }
+}
+
+impl std::str::FromStr for PlanChange {
+    type Err = TenantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "UPGRADE" => Ok(PlanChange::Upgrade),
+            "DOWNGRADE" => Ok(PlanChange::Downgrade),
+            "LATERAL" => Ok(PlanChange::Lateral),
+            other => Err(TenantError::DatabaseError(format!(
+                "Unknown plan change: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// Record of a change to a tenant's subscription, kept for billing integrations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubscriptionHistoryEntry {
+    /// Unique identifier for the entry
+    pub id: Uuid,
+    /// Associated tenant ID
+    pub tenant_id: Uuid,
+    /// Subscription that was changed
+    pub subscription_id: Uuid,
+    /// What happened to the subscription
+    pub change_type: SubscriptionChangeType,
+    /// Plan before the change
+    ///
+    /// For a created subscription this is the plan of the subscription it
+    /// replaced, `None` for the first subscription of a tenant.
+    pub old_plan_type: Option<TenantPlanType>,
+    /// Plan after the change
+    pub new_plan_type: TenantPlanType,
+    /// Whether the plan was upgraded or downgraded; `None` if it stayed the same
+    pub plan_change: Option<PlanChange>,
+    /// User limit before the change
+    pub old_max_users: Option<i32>,
+    /// User limit after the change
+    pub new_max_users: Option<i32>,
+    /// Expiration before the change
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub old_expires_at: Option<OffsetDateTime>,
+    /// Expiration after the change
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub new_expires_at: Option<OffsetDateTime>,
+    /// User who made the change; `None` for system changes
+    pub actor_id: Option<Uuid>,
+    /// When the change was made
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+impl SubscriptionHistoryEntry {
+    /// Creates an entry for a change from `old` to `new`
+    pub fn new(
+        change_type: SubscriptionChangeType,
+        old: Option<&TenantSubscription>,
+        new: &TenantSubscription,
+        actor_id: Option<Uuid>,
+    ) -> Self {
+        // PostgreSQL stores microseconds; truncating keeps entries equal after a round trip
+        let now = OffsetDateTime::now_utc();
+        Self {
+            id: Uuid::new_v4(),
+            tenant_id: new.tenant_id,
+            subscription_id: new.id,
+            change_type,
+            old_plan_type: old.map(|old| old.plan_type),
+            new_plan_type: new.plan_type,
+            plan_change: old.and_then(|old| PlanChange::between(old.plan_type, new.plan_type)),
+            old_max_users: old.and_then(|old| old.max_users),
+            new_max_users: new.max_users,
+            old_expires_at: old.and_then(|old| old.expires_at),
+            new_expires_at: new.expires_at,
+            actor_id,
+            created_at: now - time::Duration::nanoseconds(i64::from(now.nanosecond() % 1_000)),
+        }
+    }
+}
+
 /// Represents the association between a user and a tenant
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TenantUser {
@@ -134,6 +302,427 @@ pub struct TenantUser {
     pub updated_at: OffsetDateTime,
 }
 
+/// Metadata key under which the tenant security policy is stored
+pub const SECURITY_POLICY_METADATA_KEY: &str = "security_policy";
+
+/// Tenant roles that keep access to a suspended tenant, e.g. to settle the bill
+pub const SUSPENSION_EXEMPT_ROLES: &[&str] = &["ADMIN", "BILLING"];
+
+/// Lifecycle status of a tenant
+///
+/// A suspended tenant, e.g. for non-payment, keeps its data and stays
+/// accessible to its admin and billing users. An archived tenant is closed to
+/// everyone; only its data can still be exported.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TenantStatus {
+    Active,
+    Suspended {
+        /// Why the tenant was suspended, shown to users whose login is blocked
+        reason: String,
+        #[serde(with = "time::serde::rfc3339")]
+        since: OffsetDateTime,
+    },
+    Archived {
+        #[serde(with = "time::serde::rfc3339")]
+        since: OffsetDateTime,
+    },
+}
+
+impl TenantStatus {
+    /// Whether the tenant is archived
+    ///
+    /// Suspended tenants are not; the `is_active` column of the tenants table
+    /// is the negation of this.
+    pub fn is_archived(&self) -> bool {
+        matches!(self, TenantStatus::Archived { .. })
+    }
+
+    /// Whether a member with `tenant_role` may access the tenant
+    pub fn allows_role(&self, tenant_role: &str) -> bool {
+        match self {
+            TenantStatus::Active => true,
+            TenantStatus::Suspended { .. } => SUSPENSION_EXEMPT_ROLES
+                .iter()
+                .any(|role| role.eq_ignore_ascii_case(tenant_role)),
+            TenantStatus::Archived { .. } => false,
+        }
+    }
+
+    /// The error rejecting access to a tenant in this status
+    pub fn access_error(&self) -> Option<TenantError> {
+        match self {
+            TenantStatus::Active => None,
+            TenantStatus::Suspended { reason, .. } => {
+                Some(TenantError::SuspendedTenant(reason.clone()))
+            },
+            TenantStatus::Archived { .. } => Some(TenantError::InactiveTenant),
+        }
+    }
+
+    /// Name of the status as stored in the `status` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TenantStatus::Active => "ACTIVE",
+            TenantStatus::Suspended { .. } => "SUSPENDED",
+            TenantStatus::Archived { .. } => "ARCHIVED",
+        }
+    }
+
+    /// Reason of a suspension
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            TenantStatus::Suspended { reason, .. } => Some(reason),
+            _ => None,
+        }
+    }
+
+    /// When the tenant entered this status; unknown for active tenants
+    pub fn since(&self) -> Option<OffsetDateTime> {
+        match self {
+            TenantStatus::Active => None,
+            TenantStatus::Suspended { since, .. } | TenantStatus::Archived { since } => {
+                Some(*since)
+            },
+        }
+    }
+
+    /// Status from the `status`, `status_reason` and `status_changed_at` columns
+    pub fn from_columns(
+        status: &str,
+        reason: Option<String>,
+        since: Option<OffsetDateTime>,
+    ) -> Result<Self, TenantError> {
+        let since = || {
+            since.ok_or_else(|| {
+                TenantError::ValidationError(format!("Tenant status {} lacks its time", status))
+            })
+        };
+        match status {
+            "ACTIVE" => Ok(TenantStatus::Active),
+            "SUSPENDED" => Ok(TenantStatus::Suspended {
+                reason: reason.unwrap_or_default(),
+                since: since()?,
+            }),
+            "ARCHIVED" => Ok(TenantStatus::Archived { since: since()? }),
+            _ => Err(TenantError::ValidationError(format!(
+                "Invalid tenant status: {}",
+                status
+            ))),
+        }
+    }
+}
+
+/// Multi-factor authentication enforcement levels for a tenant
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MfaEnforcement {
+    /// MFA is disabled for the tenant
+    Off,
+    /// Users may enroll a second factor but are not required to
+    #[default]
+    Optional,
+    /// Users with the ADMIN tenant role must enroll a second factor
+    RequiredForAdmins,
+    /// Every user of the tenant must enroll a second factor
+    RequiredForAll,
+}
+
+impl MfaEnforcement {
+    /// Returns whether enrollment is mandatory for at least some users
+    pub fn is_enforced(&self) -> bool {
+        matches!(
+            self,
+            MfaEnforcement::RequiredForAdmins | MfaEnforcement::RequiredForAll
+        )
+    }
+
+    /// Returns whether enrollment is mandatory for a user with the given tenant role
+    pub fn applies_to_role(&self, tenant_role: &str) -> bool {
+        match self {
+            MfaEnforcement::Off | MfaEnforcement::Optional => false,
+            MfaEnforcement::RequiredForAdmins => tenant_role.eq_ignore_ascii_case("ADMIN"),
+            MfaEnforcement::RequiredForAll => true,
+        }
+    }
+}
+
+/// How a session binding reacts to a request that does not match it
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BindingEnforcement {
+    /// Mismatches are logged and counted, the request proceeds
+    #[default]
+    Monitor,
+    /// Mismatches invalidate the session
+    Enforce,
+}
+
+fn default_tls_fingerprint_header() -> String {
+    "x-tls-fingerprint".to_string()
+}
+
+/// Binding of sessions to the TLS stack of the client that created them
+///
+/// See [`ClientCharacteristics`](crate::security::client_binding::ClientCharacteristics)
+/// for what the binding covers.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClientBindingPolicy {
+    /// Whether sessions of the tenant are bound to their client
+    #[serde(default)]
+    pub enabled: bool,
+    /// What happens on a mismatch
+    #[serde(default)]
+    pub mode: BindingEnforcement,
+    /// Header the load balancer forwards the TLS (JA3) fingerprint of the client in
+    #[serde(default = "default_tls_fingerprint_header")]
+    pub tls_fingerprint_header: String,
+}
+
+impl Default for ClientBindingPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: BindingEnforcement::default(),
+            tls_fingerprint_header: default_tls_fingerprint_header(),
+        }
+    }
+}
+
+/// Which authenticators may register WebAuthn credentials with a tenant
+///
+/// The default accepts every authenticator, including those that provide no
+/// attestation ("none"), which suits most tenants. High-assurance tenants
+/// require a verified attestation and may restrict the authenticator models.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WebAuthnAttestationPolicy {
+    /// Whether registrations need an attestation chaining up to a trusted
+    /// authenticator vendor; "none" and self attestation are rejected
+    #[serde(default)]
+    pub require_attestation: bool,
+    /// AAGUIDs of the authenticator models allowed to register, e.g. FIDO2-certified
+    /// security keys; empty allows every model
+    ///
+    /// The AAGUID of an unattested authenticator cannot be trusted, so an
+    /// allow-list implies [`require_attestation`](Self::require_attestation).
+    #[serde(default)]
+    pub allowed_aaguids: Vec<Uuid>,
+}
+
+impl WebAuthnAttestationPolicy {
+    /// Returns whether registrations need a verified attestation
+    pub fn requires_attestation(&self) -> bool {
+        self.require_attestation || !self.allowed_aaguids.is_empty()
+    }
+
+    /// Returns whether the authenticator model with the given AAGUID may register
+    pub fn allows_aaguid(&self, aaguid: Uuid) -> bool {
+        self.allowed_aaguids.is_empty() || self.allowed_aaguids.contains(&aaguid)
+    }
+}
+
+/// How a country restriction treats logins whose country cannot be determined
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum UnknownCountryAction {
+    /// The login proceeds, e.g. for addresses missing from the GeoIP database
+    #[default]
+    Allow,
+    /// The login is blocked
+    Deny,
+}
+
+/// Why the location of a login violates the [`TenantSecurityPolicy`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginLocationViolation {
+    /// The client IP address is outside the allowed ranges
+    IpNotAllowed,
+    /// The tenant restricts IP ranges, but the client IP address is unknown
+    IpUnknown,
+    /// The client is located in a country that is not allowed
+    CountryNotAllowed,
+    /// The country of the client is unknown and the tenant denies unknown countries
+    CountryUnknown,
+}
+
+impl LoginLocationViolation {
+    /// Name of the violation as recorded in audit events
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoginLocationViolation::IpNotAllowed => "ip_not_allowed",
+            LoginLocationViolation::IpUnknown => "ip_unknown",
+            LoginLocationViolation::CountryNotAllowed => "country_not_allowed",
+            LoginLocationViolation::CountryUnknown => "country_unknown",
+        }
+    }
+}
+
+impl std::fmt::Display for LoginLocationViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Security policy settings of a tenant, stored in the tenant metadata
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TenantSecurityPolicy {
+    /// MFA enforcement level
+    #[serde(default)]
+    pub mfa_enforcement: MfaEnforcement,
+    /// Deadline after which unenrolled users can no longer log in
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub mfa_grace_deadline: Option<OffsetDateTime>,
+    /// Whether a first login through an external identity provider creates a local
+    /// account and tenant membership
+    #[serde(default)]
+    pub external_login_provisioning: bool,
+    /// Binding of sessions to client characteristics
+    #[serde(default)]
+    pub client_binding: ClientBindingPolicy,
+    /// Networks logins are allowed from; empty allows every network
+    #[serde(default)]
+    pub allowed_ip_ranges: Vec<IpNetwork>,
+    /// ISO 3166-1 alpha-2 codes of the countries logins are allowed from; empty
+    /// allows every country
+    #[serde(default)]
+    pub allowed_countries: Vec<String>,
+    /// How the country restriction treats logins whose country is unknown
+    #[serde(default)]
+    pub unknown_country: UnknownCountryAction,
+    /// Days after which a password has to be changed; passwords never expire if unset
+    #[serde(default)]
+    pub password_max_age_days: Option<u32>,
+    /// Days before the expiry in which password logins warn about it
+    #[serde(default)]
+    pub password_warning_days: Option<u32>,
+    /// Number of previous passwords a new password must differ from; the
+    /// global `password.history_size` applies if unset
+    #[serde(default)]
+    pub password_history_size: Option<u32>,
+    /// Which authenticators may register WebAuthn credentials
+    #[serde(default)]
+    pub webauthn_attestation: WebAuthnAttestationPolicy,
+}
+
+/// Expiry state of a password under a [`TenantSecurityPolicy`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordExpiry {
+    /// The password does not expire or is outside the warning window
+    Valid,
+    /// The password expires within the warning window
+    ExpiresSoon {
+        /// Whole days left until the expiry, 0 on its last day
+        days_left: u32,
+    },
+    /// The password has to be changed before the next login
+    Expired,
+}
+
+impl TenantSecurityPolicy {
+    /// Returns whether MFA enrollment is mandatory for a user with the given tenant role
+    pub fn requires_mfa_for_role(&self, tenant_role: &str) -> bool {
+        self.mfa_enforcement.applies_to_role(tenant_role)
+    }
+
+    /// Returns whether the enrollment grace period has ended at the given point in time
+    ///
+    /// The deadline itself is the first instant at which logins are rejected.
+    pub fn grace_period_expired(&self, now: OffsetDateTime) -> bool {
+        self.mfa_grace_deadline
+            .is_some_and(|deadline| now >= deadline)
+    }
+
+    /// Returns the expiry state of a password last changed at `changed_at`
+    ///
+    /// The password expires `password_max_age_days` after it was changed; the
+    /// expiry itself is the first instant at which logins are rejected.
+    pub fn password_expiry(
+        &self,
+        changed_at: OffsetDateTime,
+        now: OffsetDateTime,
+    ) -> PasswordExpiry {
+        let Some(max_age_days) = self.password_max_age_days else {
+            return PasswordExpiry::Valid;
+        };
+
+        let expires_at = changed_at + Duration::days(i64::from(max_age_days));
+        if now >= expires_at {
+            return PasswordExpiry::Expired;
+        }
+
+        let warning_days = self.password_warning_days.unwrap_or(0);
+        if now >= expires_at - Duration::days(i64::from(warning_days)) {
+            let days_left = (expires_at - now).whole_days();
+            return PasswordExpiry::ExpiresSoon {
+                days_left: u32::try_from(days_left).unwrap_or(0),
+            };
+        }
+
+        PasswordExpiry::Valid
+    }
+
+    /// Returns whether logins are restricted to some networks or countries
+    pub fn restricts_login_location(&self) -> bool {
+        !self.allowed_ip_ranges.is_empty() || !self.allowed_countries.is_empty()
+    }
+
+    /// Check the client IP address and country of a login against the allowed ones
+    ///
+    /// IPv4-mapped IPv6 addresses are matched as IPv4 addresses. Countries are
+    /// compared case-insensitively.
+    pub fn check_login_location(
+        &self,
+        ip: Option<IpAddr>,
+        country_code: Option<&str>,
+    ) -> Result<(), LoginLocationViolation> {
+        if !self.allowed_ip_ranges.is_empty() {
+            let ip = ip
+                .map(|ip| ip.to_canonical())
+                .ok_or(LoginLocationViolation::IpUnknown)?;
+            if !self
+                .allowed_ip_ranges
+                .iter()
+                .any(|range| range.contains(ip))
+            {
+                return Err(LoginLocationViolation::IpNotAllowed);
+            }
+        }
+
+        if !self.allowed_countries.is_empty() {
+            match country_code {
+                Some(country_code)
+                    if !self
+                        .allowed_countries
+                        .iter()
+                        .any(|allowed| allowed.eq_ignore_ascii_case(country_code)) =>
+                {
+                    return Err(LoginLocationViolation::CountryNotAllowed);
+                },
+                Some(_) => {},
+                None if self.unknown_country == UnknownCountryAction::Deny => {
+                    return Err(LoginLocationViolation::CountryUnknown);
+                },
+                None => {},
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Tenant {
+    /// Returns the security policy of the tenant
+    ///
+    /// Falls back to the default policy if none is stored or the stored policy is malformed.
+    pub fn security_policy(&self) -> TenantSecurityPolicy {
+        self.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get(SECURITY_POLICY_METADATA_KEY))
+            .and_then(|policy| serde_json::from_value(policy.clone()).ok())
+            .unwrap_or_default()
+    }
+}
+
 /// Tenant creation data transfer object
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateTenantDto {
@@ -141,21 +730,29 @@ pub struct CreateTenantDto {
     pub name: String,
     /// Unique subdomain for tenant access
     pub subdomain: String,
-    /// Optional initial metadata
-    pub metadata: Option<JsonValue>,
+    /// Optional initial metadata, within the metadata limits
+    pub metadata: Option<BoundedJson>,
 }
 
 /// Tenant update data transfer object
+///
+/// Deserializes as a JSON Merge Patch (RFC 7386): omitted fields are left
+/// unchanged and `metadata` is cleared by an explicit `null`. The other fields
+/// are not nullable, so `null` leaves them unchanged as well. A metadata value
+/// replaces the stored metadata as a whole.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateTenantDto {
     /// Optional new name for the tenant
     pub name: Option<String>,
     /// Optional new subdomain for the tenant
     pub subdomain: Option<String>,
-    /// Optional active status update
-    pub is_active: Option<bool>,
-    /// Optional metadata update
-    pub metadata: Option<JsonValue>,
+    /// Optional metadata update; `Some(None)` clears the metadata
+    #[serde(
+        default,
+        deserialize_with = "crate::utils::patch::deserialize_nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub metadata: Option<Option<BoundedJson>>,
 }
 
 /// Subscription creation data transfer object
@@ -241,14 +838,62 @@ pub enum TenantError {
     #[error("Service unavailable")]
     ServiceUnavailable,
 
+    /// The tenant is archived
     #[error("Inactive tenant")]
     InactiveTenant,
 
+    #[error("Tenant is suspended: {0}")]
+    SuspendedTenant(String),
+
     #[error("Subscription expired")]
     SubscriptionExpired,
 
     #[error("User limit exceeded")]
     UserLimitExceeded,
+
+    #[error("User is already active in {current} tenants, the limit is {limit}")]
+    TenantsPerUserLimitExceeded {
+        /// Configured maximum of active tenants per user
+        limit: u32,
+        /// Tenants the user is active in
+        current: u32,
+    },
+    #[error("Timeout: {0}")]
+    Timeout(#[from] OperationTimedOut),
+}
+
+impl From<TenantError> for acci_core::Error {
+    fn from(err: TenantError) -> Self {
+        use acci_core::error::ErrorKind;
+
+        let kind = match err {
+            TenantError::NotFound => ErrorKind::TenantNotFound,
+            TenantError::AlreadyExists => ErrorKind::TenantAlreadyExists,
+            TenantError::DatabaseError(_) => ErrorKind::Internal,
+            TenantError::ValidationError(_) => ErrorKind::InvalidInput,
+            TenantError::ConfigError(_) => ErrorKind::Configuration,
+            TenantError::RateLimitExceeded => ErrorKind::RateLimited,
+            TenantError::Unauthorized => ErrorKind::Forbidden,
+            TenantError::ServiceUnavailable => ErrorKind::Unavailable,
+            TenantError::Timeout(_) => ErrorKind::Timeout,
+            TenantError::InactiveTenant => ErrorKind::TenantInactive,
+            TenantError::SuspendedTenant(ref reason) => {
+                #[allow(clippy::disallowed_methods)]
+                let details = serde_json::json!({ "reason": reason });
+                return Self::domain(ErrorKind::TenantSuspended, err.to_string())
+                    .with_details(details);
+            },
+            TenantError::SubscriptionExpired => ErrorKind::SubscriptionExpired,
+            TenantError::UserLimitExceeded => ErrorKind::SeatLimitReached,
+            TenantError::TenantsPerUserLimitExceeded { limit, current } => {
+                #[allow(clippy::disallowed_methods)]
+                let details = serde_json::json!({ "limit": limit, "current": current });
+                return Self::domain(ErrorKind::LimitExceeded, err.to_string())
+                    .with_details(details);
+            },
+        };
+        Self::domain(kind, err.to_string())
+    }
 }
 
 /// Repository trait for tenant operations
@@ -266,10 +911,24 @@ pub trait TenantRepository: Send + Sync {
         subdomain: &str,
     ) -> Result<Option<Tenant>, TenantError>;
 
+    /// Finds the tenants with the given IDs in one lookup; unknown IDs are skipped
+    async fn find_tenants_by_ids(&self, ids: &[Uuid]) -> Result<Vec<Tenant>, TenantError>;
+
     /// Updates a tenant
     async fn update_tenant(&self, id: Uuid, tenant: UpdateTenantDto)
     -> Result<Tenant, TenantError>;
 
+    /// Moves a tenant to `status`
+    ///
+    /// Implementations write an audit entry for the transition, attributed to
+    /// `actor_id`. Whether the transition is allowed is checked by the caller.
+    async fn set_tenant_status(
+        &self,
+        id: Uuid,
+        status: TenantStatus,
+        actor_id: Option<Uuid>,
+    ) -> Result<Tenant, TenantError>;
+
     /// Deletes a tenant
     async fn delete_tenant(&self, id: Uuid) -> Result<(), TenantError>;
 
@@ -286,6 +945,18 @@ pub trait TenantRepository: Send + Sync {
         tenant_id: Uuid,
     ) -> Result<Option<TenantSubscription>, TenantError>;
 
+    /// Lists all subscriptions of a tenant, active or not, oldest first
+    async fn list_subscriptions(
+        &self,
+        tenant_id: Uuid,
+    ) -> Result<Vec<TenantSubscription>, TenantError>;
+
+    /// Finds a subscription by ID
+    async fn find_subscription_by_id(
+        &self,
+        id: Uuid,
+    ) -> Result<Option<TenantSubscription>, TenantError>;
+
     /// Updates a subscription
     async fn update_subscription(
         &self,
@@ -293,6 +964,20 @@ pub trait TenantRepository: Send + Sync {
         subscription: UpdateSubscriptionDto,
     ) -> Result<TenantSubscription, TenantError>;
 
+    /// Appends an entry to the subscription history of a tenant
+    async fn record_subscription_change(
+        &self,
+        entry: &SubscriptionHistoryEntry,
+    ) -> Result<(), TenantError>;
+
+    /// Lists the subscription history of a tenant, newest first
+    async fn list_subscription_history(
+        &self,
+        tenant_id: Uuid,
+        limit: u32,
+        offset: u64,
+    ) -> Result<Vec<SubscriptionHistoryEntry>, TenantError>;
+
     /// Adds a user to a tenant
     async fn add_user_to_tenant(
         &self,
@@ -300,6 +985,19 @@ pub trait TenantRepository: Send + Sync {
         user: CreateTenantUserDto,
     ) -> Result<TenantUser, TenantError>;
 
+    /// Adds a user to a tenant unless the user would be active in more than
+    /// `max_tenants_per_user` tenants
+    ///
+    /// Counting the user's active tenants and adding the association happen
+    /// atomically, so concurrent additions of the same user cannot exceed the
+    /// limit. Fails with [`TenantError::TenantsPerUserLimitExceeded`].
+    async fn add_user_to_tenant_within_limit(
+        &self,
+        tenant_id: Uuid,
+        user: CreateTenantUserDto,
+        max_tenants_per_user: u32,
+    ) -> Result<TenantUser, TenantError>;
+
     /// Gets users for a tenant
     async fn get_tenant_users(&self, tenant_id: Uuid) -> Result<Vec<TenantUser>, TenantError>;
 
@@ -320,4 +1018,624 @@ pub trait TenantRepository: Send + Sync {
         tenant_id: Uuid,
         user_id: Uuid,
     ) -> Result<(), TenantError>;
+
+    /// Counts the active user associations of a tenant
+    async fn count_active_tenant_users(&self, tenant_id: Uuid) -> Result<i64, TenantError>;
+}
+
+/// In-memory [`TenantRepository`] for tests
+///
+/// Shared by the unit tests in this crate and by downstream test crates (via
+/// the `test-support` feature).
+#[cfg(any(test, feature = "test-support"))]
+pub mod mock {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// In-memory tenant repository for tests
+    #[derive(Default)]
+    pub struct MockTenantRepository {
+        tenants: Mutex<HashMap<Uuid, Tenant>>,
+        memberships: Mutex<Vec<TenantUser>>,
+        subscriptions: Mutex<Vec<TenantSubscription>>,
+        subscription_history: Mutex<Vec<SubscriptionHistoryEntry>>,
+        user_tenant_lookups: AtomicUsize,
+    }
+
+    impl MockTenantRepository {
+        /// Creates an empty mock tenant repository
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Number of calls of `get_user_tenants` so far
+        pub fn user_tenant_lookups(&self) -> usize {
+            self.user_tenant_lookups.load(Ordering::SeqCst)
+        }
+
+        /// Stores a tenant with the given security policy and returns its ID
+        pub fn insert_tenant_with_policy(&self, policy: &TenantSecurityPolicy) -> Uuid {
+            #[allow(clippy::disallowed_methods)]
+            let metadata = serde_json::json!({
+                SECURITY_POLICY_METADATA_KEY: policy,
+            });
+            self.insert_tenant_with_metadata(metadata)
+        }
+
+        /// Stores a tenant with the given metadata and returns its ID
+        pub fn insert_tenant_with_metadata(&self, metadata: JsonValue) -> Uuid {
+            let now = OffsetDateTime::now_utc();
+            let tenant = Tenant {
+                id: Uuid::new_v4(),
+                name: "Test Tenant".to_string(),
+                subdomain: "test".to_string(),
+                status: TenantStatus::Active,
+                created_at: now,
+                updated_at: now,
+                metadata: Some(metadata),
+            };
+            let id = tenant.id;
+            self.tenants
+                .lock()
+                .expect("mock tenant store lock poisoned")
+                .insert(id, tenant);
+            id
+        }
+
+        /// Adds a user to a tenant with the given role
+        pub fn add_member(&self, tenant_id: Uuid, user_id: Uuid, tenant_role: &str) {
+            let now = OffsetDateTime::now_utc();
+            self.memberships
+                .lock()
+                .expect("mock tenant store lock poisoned")
+                .push(TenantUser {
+                    tenant_id,
+                    user_id,
+                    tenant_role: tenant_role.to_string(),
+                    is_active: true,
+                    created_at: now,
+                    updated_at: now,
+                });
+        }
+    }
+
+    #[async_trait]
+    impl TenantRepository for MockTenantRepository {
+        async fn create_tenant(&self, _tenant: CreateTenantDto) -> Result<Tenant, TenantError> {
+            Err(TenantError::ServiceUnavailable)
+        }
+
+        async fn find_tenant_by_id(&self, id: Uuid) -> Result<Option<Tenant>, TenantError> {
+            Ok(self
+                .tenants
+                .lock()
+                .expect("mock tenant store lock poisoned")
+                .get(&id)
+                .cloned())
+        }
+
+        async fn find_tenant_by_subdomain(
+            &self,
+            subdomain: &str,
+        ) -> Result<Option<Tenant>, TenantError> {
+            Ok(self
+                .tenants
+                .lock()
+                .expect("mock tenant store lock poisoned")
+                .values()
+                .find(|t| t.subdomain == subdomain)
+                .cloned())
+        }
+
+        async fn find_tenants_by_ids(&self, ids: &[Uuid]) -> Result<Vec<Tenant>, TenantError> {
+            let tenants = self
+                .tenants
+                .lock()
+                .expect("mock tenant store lock poisoned");
+            Ok(ids
+                .iter()
+                .filter_map(|id| tenants.get(id).cloned())
+                .collect())
+        }
+
+        async fn update_tenant(
+            &self,
+            id: Uuid,
+            update: UpdateTenantDto,
+        ) -> Result<Tenant, TenantError> {
+            let mut tenants = self
+                .tenants
+                .lock()
+                .expect("mock tenant store lock poisoned");
+            let tenant = tenants.get_mut(&id).ok_or(TenantError::NotFound)?;
+            if let Some(name) = update.name {
+                tenant.name = name;
+            }
+            if let Some(subdomain) = update.subdomain {
+                tenant.subdomain = subdomain;
+            }
+            if let Some(metadata) = update.metadata {
+                tenant.metadata = metadata.map(BoundedJson::into_inner);
+            }
+            tenant.updated_at = OffsetDateTime::now_utc();
+            Ok(tenant.clone())
+        }
+
+        async fn set_tenant_status(
+            &self,
+            id: Uuid,
+            status: TenantStatus,
+            _actor_id: Option<Uuid>,
+        ) -> Result<Tenant, TenantError> {
+            let mut tenants = self
+                .tenants
+                .lock()
+                .expect("mock tenant store lock poisoned");
+            let tenant = tenants.get_mut(&id).ok_or(TenantError::NotFound)?;
+            tenant.status = status;
+            tenant.updated_at = OffsetDateTime::now_utc();
+            Ok(tenant.clone())
+        }
+
+        async fn delete_tenant(&self, id: Uuid) -> Result<(), TenantError> {
+            self.tenants
+                .lock()
+                .expect("mock tenant store lock poisoned")
+                .remove(&id)
+                .map(|_| ())
+                .ok_or(TenantError::NotFound)
+        }
+
+        async fn create_subscription(
+            &self,
+            tenant_id: Uuid,
+            subscription: CreateSubscriptionDto,
+        ) -> Result<TenantSubscription, TenantError> {
+            let now = OffsetDateTime::now_utc();
+            let subscription = TenantSubscription {
+                id: Uuid::new_v4(),
+                tenant_id,
+                plan_type: subscription.plan_type,
+                starts_at: subscription.starts_at,
+                expires_at: subscription.expires_at,
+                is_active: subscription.is_active.unwrap_or(true),
+                payment_status: subscription.payment_status,
+                max_users: subscription.max_users,
+                features: subscription.features,
+                created_at: now,
+                updated_at: now,
+            };
+            self.subscriptions
+                .lock()
+                .expect("mock tenant store lock poisoned")
+                .push(subscription.clone());
+            Ok(subscription)
+        }
+
+        async fn get_active_subscription(
+            &self,
+            tenant_id: Uuid,
+        ) -> Result<Option<TenantSubscription>, TenantError> {
+            let now = OffsetDateTime::now_utc();
+            Ok(self
+                .subscriptions
+                .lock()
+                .expect("mock tenant store lock poisoned")
+                .iter()
+                .rev()
+                .find(|s| {
+                    s.tenant_id == tenant_id
+                        && s.is_active
+                        && s.expires_at.is_none_or(|expires_at| expires_at > now)
+                })
+                .cloned())
+        }
+
+        async fn list_subscriptions(
+            &self,
+            tenant_id: Uuid,
+        ) -> Result<Vec<TenantSubscription>, TenantError> {
+            Ok(self
+                .subscriptions
+                .lock()
+                .expect("mock tenant store lock poisoned")
+                .iter()
+                .filter(|s| s.tenant_id == tenant_id)
+                .cloned()
+                .collect())
+        }
+
+        async fn find_subscription_by_id(
+            &self,
+            id: Uuid,
+        ) -> Result<Option<TenantSubscription>, TenantError> {
+            Ok(self
+                .subscriptions
+                .lock()
+                .expect("mock tenant store lock poisoned")
+                .iter()
+                .find(|s| s.id == id)
+                .cloned())
+        }
+
+        async fn update_subscription(
+            &self,
+            id: Uuid,
+            update: UpdateSubscriptionDto,
+        ) -> Result<TenantSubscription, TenantError> {
+            let mut subscriptions = self
+                .subscriptions
+                .lock()
+                .expect("mock tenant store lock poisoned");
+            let subscription = subscriptions
+                .iter_mut()
+                .find(|s| s.id == id)
+                .ok_or(TenantError::NotFound)?;
+            if let Some(plan_type) = update.plan_type {
+                subscription.plan_type = plan_type;
+            }
+            if let Some(expires_at) = update.expires_at {
+                subscription.expires_at = Some(expires_at);
+            }
+            if let Some(is_active) = update.is_active {
+                subscription.is_active = is_active;
+            }
+            if let Some(payment_status) = update.payment_status {
+                subscription.payment_status = Some(payment_status);
+            }
+            if let Some(max_users) = update.max_users {
+                subscription.max_users = Some(max_users);
+            }
+            if let Some(features) = update.features {
+                subscription.features = Some(features);
+            }
+            subscription.updated_at = OffsetDateTime::now_utc();
+            Ok(subscription.clone())
+        }
+
+        async fn record_subscription_change(
+            &self,
+            entry: &SubscriptionHistoryEntry,
+        ) -> Result<(), TenantError> {
+            self.subscription_history
+                .lock()
+                .expect("mock tenant store lock poisoned")
+                .push(entry.clone());
+            Ok(())
+        }
+
+        async fn list_subscription_history(
+            &self,
+            tenant_id: Uuid,
+            limit: u32,
+            offset: u64,
+        ) -> Result<Vec<SubscriptionHistoryEntry>, TenantError> {
+            Ok(self
+                .subscription_history
+                .lock()
+                .expect("mock tenant store lock poisoned")
+                .iter()
+                .rev()
+                .filter(|entry| entry.tenant_id == tenant_id)
+                .skip(usize::try_from(offset).unwrap_or(usize::MAX))
+                .take(limit as usize)
+                .cloned()
+                .collect())
+        }
+
+        async fn add_user_to_tenant(
+            &self,
+            tenant_id: Uuid,
+            user: CreateTenantUserDto,
+        ) -> Result<TenantUser, TenantError> {
+            self.add_member(tenant_id, user.user_id, &user.tenant_role);
+            let mut memberships = self
+                .memberships
+                .lock()
+                .expect("mock tenant store lock poisoned");
+            let membership = memberships.last_mut().ok_or(TenantError::NotFound)?;
+            membership.is_active = user.is_active.unwrap_or(true);
+            Ok(membership.clone())
+        }
+
+        async fn add_user_to_tenant_within_limit(
+            &self,
+            tenant_id: Uuid,
+            user: CreateTenantUserDto,
+            max_tenants_per_user: u32,
+        ) -> Result<TenantUser, TenantError> {
+            let mut memberships = self
+                .memberships
+                .lock()
+                .expect("mock tenant store lock poisoned");
+            let is_active = user.is_active.unwrap_or(true);
+            let active_tenants = memberships
+                .iter()
+                .filter(|m| m.user_id == user.user_id && m.is_active)
+                .count() as u32;
+            if is_active && active_tenants >= max_tenants_per_user {
+                return Err(TenantError::TenantsPerUserLimitExceeded {
+                    limit: max_tenants_per_user,
+                    current: active_tenants,
+                });
+            }
+
+            let now = OffsetDateTime::now_utc();
+            let membership = TenantUser {
+                tenant_id,
+                user_id: user.user_id,
+                tenant_role: user.tenant_role,
+                is_active,
+                created_at: now,
+                updated_at: now,
+            };
+            memberships.push(membership.clone());
+            Ok(membership)
+        }
+
+        async fn get_tenant_users(&self, tenant_id: Uuid) -> Result<Vec<TenantUser>, TenantError> {
+            Ok(self
+                .memberships
+                .lock()
+                .expect("mock tenant store lock poisoned")
+                .iter()
+                .filter(|m| m.tenant_id == tenant_id)
+                .cloned()
+                .collect())
+        }
+
+        async fn get_user_tenants(&self, user_id: Uuid) -> Result<Vec<TenantUser>, TenantError> {
+            self.user_tenant_lookups.fetch_add(1, Ordering::SeqCst);
+            Ok(self
+                .memberships
+                .lock()
+                .expect("mock tenant store lock poisoned")
+                .iter()
+                .filter(|m| m.user_id == user_id)
+                .cloned()
+                .collect())
+        }
+
+        async fn update_tenant_user(
+            &self,
+            tenant_id: Uuid,
+            user_id: Uuid,
+            update: UpdateTenantUserDto,
+        ) -> Result<TenantUser, TenantError> {
+            let mut memberships = self
+                .memberships
+                .lock()
+                .expect("mock tenant store lock poisoned");
+            let membership = memberships
+                .iter_mut()
+                .find(|m| m.tenant_id == tenant_id && m.user_id == user_id)
+                .ok_or(TenantError::NotFound)?;
+            if let Some(tenant_role) = update.tenant_role {
+                membership.tenant_role = tenant_role;
+            }
+            if let Some(is_active) = update.is_active {
+                membership.is_active = is_active;
+            }
+            membership.updated_at = OffsetDateTime::now_utc();
+            Ok(membership.clone())
+        }
+
+        async fn remove_user_from_tenant(
+            &self,
+            tenant_id: Uuid,
+            user_id: Uuid,
+        ) -> Result<(), TenantError> {
+            self.memberships
+                .lock()
+                .expect("mock tenant store lock poisoned")
+                .retain(|m| !(m.tenant_id == tenant_id && m.user_id == user_id));
+            Ok(())
+        }
+
+        async fn count_active_tenant_users(&self, tenant_id: Uuid) -> Result<i64, TenantError> {
+            Ok(self
+                .memberships
+                .lock()
+                .expect("mock tenant store lock poisoned")
+                .iter()
+                .filter(|m| m.tenant_id == tenant_id && m.is_active)
+                .count() as i64)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tenant_with_metadata(metadata: Option<JsonValue>) -> Tenant {
+        let now = OffsetDateTime::now_utc();
+        Tenant {
+            id: Uuid::new_v4(),
+            name: "Test".to_string(),
+            subdomain: "test".to_string(),
+            status: TenantStatus::Active,
+            created_at: now,
+            updated_at: now,
+            metadata,
+        }
+    }
+
+    #[test]
+    fn test_admin_only_enforcement_applies_to_admins() {
+        let enforcement = MfaEnforcement::RequiredForAdmins;
+        assert!(enforcement.applies_to_role("ADMIN"));
+        assert!(enforcement.applies_to_role("admin"));
+        assert!(!enforcement.applies_to_role("MEMBER"));
+        assert!(!enforcement.applies_to_role(""));
+
+        assert!(MfaEnforcement::RequiredForAll.applies_to_role("MEMBER"));
+        assert!(!MfaEnforcement::Optional.applies_to_role("ADMIN"));
+        assert!(!MfaEnforcement::Off.applies_to_role("ADMIN"));
+    }
+
+    #[test]
+    fn test_grace_period_boundary() {
+        let deadline = OffsetDateTime::now_utc();
+        let policy = TenantSecurityPolicy {
+            mfa_enforcement: MfaEnforcement::RequiredForAll,
+            mfa_grace_deadline: Some(deadline),
+            ..TenantSecurityPolicy::default()
+        };
+
+        assert!(!policy.grace_period_expired(deadline - Duration::nanoseconds(1)));
+        assert!(policy.grace_period_expired(deadline));
+        assert!(policy.grace_period_expired(deadline + Duration::seconds(1)));
+        assert!(!TenantSecurityPolicy::default().grace_period_expired(deadline));
+    }
+
+    #[test]
+    fn test_password_expiry_boundaries() {
+        let changed_at = OffsetDateTime::now_utc();
+        let expires_at = changed_at + Duration::days(90);
+        let policy = TenantSecurityPolicy {
+            password_max_age_days: Some(90),
+            password_warning_days: Some(14),
+            ..TenantSecurityPolicy::default()
+        };
+
+        let warning_start = expires_at - Duration::days(14);
+        assert_eq!(
+            policy.password_expiry(changed_at, warning_start - Duration::seconds(1)),
+            PasswordExpiry::Valid
+        );
+        assert_eq!(
+            policy.password_expiry(changed_at, warning_start),
+            PasswordExpiry::ExpiresSoon { days_left: 14 }
+        );
+        assert_eq!(
+            policy.password_expiry(changed_at, expires_at - Duration::seconds(1)),
+            PasswordExpiry::ExpiresSoon { days_left: 0 }
+        );
+        assert_eq!(
+            policy.password_expiry(changed_at, expires_at),
+            PasswordExpiry::Expired
+        );
+        assert_eq!(
+            TenantSecurityPolicy::default().password_expiry(changed_at, expires_at),
+            PasswordExpiry::Valid
+        );
+    }
+
+    #[test]
+    fn test_security_policy_from_metadata() {
+        let tenant = tenant_with_metadata(Some(serde_json::json!({
+            "security_policy": {
+                "mfa_enforcement": "REQUIRED_FOR_ADMINS",
+                "mfa_grace_deadline": "2025-06-01T00:00:00Z"
+            }
+        })));
+
+        let policy = tenant.security_policy();
+        assert_eq!(policy.mfa_enforcement, MfaEnforcement::RequiredForAdmins);
+        assert_eq!(
+            policy.mfa_grace_deadline.map(|d| d.unix_timestamp()),
+            Some(1_748_736_000)
+        );
+    }
+
+    #[test]
+    fn test_security_policy_defaults() {
+        assert_eq!(
+            tenant_with_metadata(None).security_policy(),
+            TenantSecurityPolicy::default()
+        );
+
+        let malformed = tenant_with_metadata(Some(serde_json::json!({
+            "security_policy": { "mfa_enforcement": "SOMETIMES" }
+        })));
+        assert_eq!(
+            malformed.security_policy().mfa_enforcement,
+            MfaEnforcement::Optional
+        );
+    }
+
+    #[test]
+    fn test_webauthn_attestation_policy() {
+        let allowed = Uuid::parse_str("ee882879-721c-4913-9775-3dfcce97072a").expect("UUID");
+        let open = WebAuthnAttestationPolicy::default();
+        assert!(!open.requires_attestation());
+        assert!(open.allows_aaguid(allowed));
+
+        let tenant = tenant_with_metadata(Some(serde_json::json!({
+            "security_policy": {
+                "webauthn_attestation": {
+                    "allowed_aaguids": ["ee882879-721c-4913-9775-3dfcce97072a"]
+                }
+            }
+        })));
+        let restricted = tenant.security_policy().webauthn_attestation;
+        assert!(!restricted.require_attestation);
+        assert!(restricted.requires_attestation());
+        assert!(restricted.allows_aaguid(allowed));
+        assert!(!restricted.allows_aaguid(Uuid::new_v4()));
+    }
+
+    fn ip(address: &str) -> Option<IpAddr> {
+        Some(address.parse().expect("valid IP address"))
+    }
+
+    #[test]
+    fn test_login_location_ip_ranges() {
+        let tenant = tenant_with_metadata(Some(serde_json::json!({
+            "security_policy": {
+                "allowed_ip_ranges": ["10.0.0.0/8", "2001:db8:1::/48"]
+            }
+        })));
+        let policy = tenant.security_policy();
+
+        assert!(policy.restricts_login_location());
+        assert_eq!(policy.check_login_location(ip("10.20.30.40"), None), Ok(()));
+        assert_eq!(
+            policy.check_login_location(ip("2001:db8:1:ffff::1"), None),
+            Ok(())
+        );
+        assert_eq!(
+            policy.check_login_location(ip("::ffff:10.0.0.1"), None),
+            Ok(())
+        );
+        assert_eq!(
+            policy.check_login_location(ip("2001:db8:2::1"), None),
+            Err(LoginLocationViolation::IpNotAllowed)
+        );
+        assert_eq!(
+            policy.check_login_location(ip("192.0.2.1"), None),
+            Err(LoginLocationViolation::IpNotAllowed)
+        );
+        assert_eq!(
+            policy.check_login_location(None, None),
+            Err(LoginLocationViolation::IpUnknown)
+        );
+    }
+
+    #[test]
+    fn test_login_location_countries() {
+        let mut policy = TenantSecurityPolicy {
+            allowed_countries: vec!["DE".to_string(), "at".to_string()],
+            ..TenantSecurityPolicy::default()
+        };
+
+        assert_eq!(policy.check_login_location(None, Some("de")), Ok(()));
+        assert_eq!(policy.check_login_location(None, Some("AT")), Ok(()));
+        assert_eq!(
+            policy.check_login_location(None, Some("US")),
+            Err(LoginLocationViolation::CountryNotAllowed)
+        );
+        assert_eq!(policy.check_login_location(None, None), Ok(()));
+
+        policy.unknown_country = UnknownCountryAction::Deny;
+        assert_eq!(
+            policy.check_login_location(ip("192.0.2.1"), None),
+            Err(LoginLocationViolation::CountryUnknown)
+        );
+        assert_eq!(policy.check_login_location(None, Some("DE")), Ok(()));
+        assert!(!TenantSecurityPolicy::default().restricts_login_location());
+    }
 }
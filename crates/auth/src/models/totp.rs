@@ -32,6 +32,8 @@ pub struct TotpSecret {
     pub recovery_codes: Vec<String>,
 
     /// Whether TOTP is enabled for this user
+    ///
+    /// Stays `false` while the enrollment awaits confirmation.
     pub enabled: bool,
 
     /// When the TOTP secret was created
@@ -121,6 +123,16 @@ pub struct TotpConfig {
 
     /// Number of time periods to check before/after current time
     pub window_size: u64,
+
+    /// Seconds a secret stays pending before its enrollment must be started over
+    pub enrollment_timeout_secs: u64,
+}
+
+impl TotpConfig {
+    /// How long an unconfirmed enrollment stays pending
+    pub fn enrollment_timeout(&self) -> time::Duration {
+        time::Duration::seconds(self.enrollment_timeout_secs as i64)
+    }
 }
 
 impl Default for TotpConfig {
@@ -131,6 +143,7 @@ impl Default for TotpConfig {
             digits: 6,
             period: 30,
             window_size: 1,
+            enrollment_timeout_secs: 600,
         }
     }
 }
@@ -1,8 +1,12 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use time::OffsetDateTime;
 use uuid::Uuid;
 
+use crate::repository::timeout::OperationTimedOut;
+use crate::utils::password::NO_PASSWORD_HASH;
+
 /// User identifier type
 pub type UserId = Uuid;
 
@@ -19,6 +23,16 @@ pub struct User {
     pub display_name: String, // Added for WebAuthn support
 }
 
+/// State of a user that decides whether their sessions stay valid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserSessionState {
+    pub is_active: bool,
+    /// When a new password was last set; `None` if it never changed
+    pub password_changed_at: Option<OffsetDateTime>,
+    /// Whether an administrator requires a new password before the next login
+    pub password_reset_required: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateUser {
     pub email: String,
@@ -53,6 +67,29 @@ pub enum UserError {
     RateLimitExceeded,
     #[error("Configuration error: {0}")]
     ConfigError(String),
+    #[error("Timeout: {0}")]
+    Timeout(#[from] OperationTimedOut),
+}
+
+impl From<UserError> for acci_core::Error {
+    fn from(err: UserError) -> Self {
+        use acci_core::error::ErrorKind;
+
+        let kind = match err {
+            UserError::InvalidCredentials => ErrorKind::InvalidCredentials,
+            UserError::NotFound => ErrorKind::UserNotFound,
+            UserError::AlreadyExists => ErrorKind::UserAlreadyExists,
+            UserError::WeakPassword(_) => ErrorKind::WeakPassword,
+            UserError::InvalidEmail => ErrorKind::InvalidInput,
+            UserError::InactiveUser => ErrorKind::AccountLocked,
+            UserError::UnverifiedUser => ErrorKind::AccountUnverified,
+            UserError::DatabaseError(_) => ErrorKind::Internal,
+            UserError::RateLimitExceeded => ErrorKind::RateLimited,
+            UserError::ConfigError(_) => ErrorKind::Configuration,
+            UserError::Timeout(_) => ErrorKind::Timeout,
+        };
+        Self::domain(kind, err.to_string())
+    }
 }
 
 impl User {
@@ -71,9 +108,9 @@ impl User {
         }
     }
 
-    pub fn update_last_login(&mut self) {
-        self.last_login = Some(OffsetDateTime::now_utc());
-        self.updated_at = OffsetDateTime::now_utc();
+    /// Whether the user can sign in with a password
+    pub fn has_password(&self) -> bool {
+        self.password_hash != NO_PASSWORD_HASH
     }
 }
 
@@ -82,65 +119,180 @@ pub trait UserRepository: Send + Sync + 'static {
     async fn create(&self, user: &User) -> Result<(), UserError>;
     async fn find_by_id(&self, id: Uuid) -> Result<Option<User>, UserError>;
     async fn find_by_email(&self, email: &str) -> Result<Option<User>, UserError>;
+    /// Update the profile columns of a user; `last_login` is left to [`Self::record_login`]
     async fn update(&self, user: &User) -> Result<(), UserError>;
     async fn delete(&self, id: Uuid) -> Result<(), UserError>;
     async fn verify_email(&self, id: Uuid) -> Result<(), UserError>;
     async fn deactivate(&self, id: Uuid) -> Result<(), UserError>;
     async fn activate(&self, id: Uuid) -> Result<(), UserError>;
+
+    /// Record a successful login at `at` and reset the failed login counter
+    ///
+    /// Touches no other column, so unlike writing the whole row back with
+    /// [`Self::update`] it cannot undo a concurrent profile or password change.
+    async fn record_login(&self, id: Uuid, at: OffsetDateTime) -> Result<(), UserError>;
+
+    /// Session-relevant state of the users in `ids`; deleted users are missing
+    ///
+    /// Repositories that do not track password changes report none.
+    async fn session_states(
+        &self,
+        ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, UserSessionState>, UserError> {
+        let mut states = HashMap::with_capacity(ids.len());
+        for id in ids {
+            if let Some(user) = self.find_by_id(*id).await? {
+                states.insert(
+                    user.id,
+                    UserSessionState {
+                        is_active: user.is_active,
+                        password_changed_at: None,
+                        password_reset_required: false,
+                    },
+                );
+            }
+        }
+        Ok(states)
+    }
+
+    /// Require a new password before the next password login of a user
+    ///
+    /// The requirement ends with the next password change. Returns `false` if
+    /// it was already in effect.
+    async fn require_password_reset(&self, id: Uuid) -> Result<bool, UserError>;
+
+    /// Hashes of the last `limit` passwords a user replaced, newest first
+    ///
+    /// Repositories without a password history report none.
+    async fn password_history(&self, _id: Uuid, _limit: usize) -> Result<Vec<String>, UserError> {
+        Ok(Vec::new())
+    }
+
+    /// Add a replaced password hash to the history of a user, keeping the newest `keep`
+    ///
+    /// Repositories without a password history ignore it.
+    async fn record_password_history(
+        &self,
+        _id: Uuid,
+        _password_hash: &str,
+        _keep: usize,
+    ) -> Result<(), UserError> {
+        Ok(())
+    }
+
+    /// Record a security event, e.g. a blocked login, in the audit log of a user
+    ///
+    /// Repositories without an audit log ignore the event.
+    async fn log_security_event(
+        &self,
+        _user_id: Uuid,
+        _action: &str,
+        _details: serde_json::Value,
+        _ip_address: Option<String>,
+        _user_agent: Option<String>,
+    ) -> Result<(), UserError> {
+        Ok(())
+    }
 }
 
-// Mock-Implementation für Tests
-#[cfg(test)]
+/// In-memory [`UserRepository`] for tests
+///
+/// Shared by the unit tests in this crate and by downstream test crates (via
+/// the `test-support` feature).
+#[cfg(any(test, feature = "test-support"))]
 pub mod mock {
     use super::*;
-    use std::collections::HashMap;
+    use std::collections::HashSet;
     use std::sync::Mutex;
 
+    #[derive(Default)]
     pub struct MockUserRepository {
         users: Mutex<HashMap<Uuid, User>>,
+        password_changes: Mutex<HashMap<Uuid, OffsetDateTime>>,
+        password_resets_required: Mutex<HashSet<Uuid>>,
+        password_history: Mutex<HashMap<Uuid, Vec<String>>>,
+        security_events: Mutex<Vec<(Uuid, String, serde_json::Value)>>,
     }
 
     impl MockUserRepository {
         pub fn new() -> Self {
-            Self {
-                users: Mutex::new(HashMap::new()),
-            }
+            Self::default()
+        }
+
+        /// Pretend the password of a user was last changed at `at`
+        pub fn set_password_changed_at(&self, user_id: Uuid, at: OffsetDateTime) {
+            self.password_changes
+                .lock()
+                .expect("mock user store lock poisoned")
+                .insert(user_id, at);
+        }
+
+        /// Actions and details of the security events logged for a user, oldest first
+        pub fn security_events(&self, user_id: Uuid) -> Vec<(String, serde_json::Value)> {
+            self.security_events
+                .lock()
+                .expect("mock user store lock poisoned")
+                .iter()
+                .filter(|(id, ..)| *id == user_id)
+                .map(|(_, action, details)| (action.clone(), details.clone()))
+                .collect()
         }
     }
 
     #[async_trait]
     impl UserRepository for MockUserRepository {
         async fn create(&self, user: &User) -> Result<(), UserError> {
-            let mut users = self.users.lock().unwrap();
+            let mut users = self.users.lock().expect("mock user store lock poisoned");
             if users.values().any(|u| u.email == user.email) {
                 return Err(UserError::AlreadyExists);
             }
+            if user.has_password() {
+                self.password_changes
+                    .lock()
+                    .expect("mock user store lock poisoned")
+                    .insert(user.id, user.created_at);
+            }
             users.insert(user.id, user.clone());
             Ok(())
         }
 
         async fn find_by_id(&self, id: Uuid) -> Result<Option<User>, UserError> {
-            let users = self.users.lock().unwrap();
+            let users = self.users.lock().expect("mock user store lock poisoned");
             Ok(users.get(&id).cloned())
         }
 
         async fn find_by_email(&self, email: &str) -> Result<Option<User>, UserError> {
-            let users = self.users.lock().unwrap();
+            let users = self.users.lock().expect("mock user store lock poisoned");
             Ok(users.values().find(|u| u.email == email).cloned())
         }
 
         async fn update(&self, user: &User) -> Result<(), UserError> {
-            let mut users = self.users.lock().unwrap();
-            if users.contains_key(&user.id) {
-                users.insert(user.id, user.clone());
-                Ok(())
-            } else {
-                Err(UserError::NotFound)
+            let mut users = self.users.lock().expect("mock user store lock poisoned");
+            let stored = users.get(&user.id).ok_or(UserError::NotFound)?;
+            if stored.password_hash != user.password_hash && user.password_hash != NO_PASSWORD_HASH
+            {
+                self.password_changes
+                    .lock()
+                    .expect("mock user store lock poisoned")
+                    .insert(user.id, user.updated_at);
+                self.password_resets_required
+                    .lock()
+                    .expect("mock user store lock poisoned")
+                    .remove(&user.id);
             }
+            let last_login = stored.last_login;
+            users.insert(
+                user.id,
+                User {
+                    last_login,
+                    ..user.clone()
+                },
+            );
+            Ok(())
         }
 
         async fn delete(&self, id: Uuid) -> Result<(), UserError> {
-            let mut users = self.users.lock().unwrap();
+            let mut users = self.users.lock().expect("mock user store lock poisoned");
             if users.remove(&id).is_some() {
                 Ok(())
             } else {
@@ -149,7 +301,7 @@ pub mod mock {
         }
 
         async fn verify_email(&self, id: Uuid) -> Result<(), UserError> {
-            let mut users = self.users.lock().unwrap();
+            let mut users = self.users.lock().expect("mock user store lock poisoned");
             if let Some(user) = users.get_mut(&id) {
                 user.is_verified = true;
                 user.updated_at = OffsetDateTime::now_utc();
@@ -160,7 +312,7 @@ pub mod mock {
         }
 
         async fn deactivate(&self, id: Uuid) -> Result<(), UserError> {
-            let mut users = self.users.lock().unwrap();
+            let mut users = self.users.lock().expect("mock user store lock poisoned");
             if let Some(user) = users.get_mut(&id) {
                 user.is_active = false;
                 user.updated_at = OffsetDateTime::now_utc();
@@ -171,7 +323,7 @@ pub mod mock {
         }
 
         async fn activate(&self, id: Uuid) -> Result<(), UserError> {
-            let mut users = self.users.lock().unwrap();
+            let mut users = self.users.lock().expect("mock user store lock poisoned");
             if let Some(user) = users.get_mut(&id) {
                 user.is_active = true;
                 user.updated_at = OffsetDateTime::now_utc();
@@ -180,5 +332,93 @@ pub mod mock {
                 Err(UserError::NotFound)
             }
         }
+
+        async fn record_login(&self, id: Uuid, at: OffsetDateTime) -> Result<(), UserError> {
+            let mut users = self.users.lock().expect("mock user store lock poisoned");
+            let user = users.get_mut(&id).ok_or(UserError::NotFound)?;
+            user.last_login = Some(at);
+            Ok(())
+        }
+
+        async fn session_states(
+            &self,
+            ids: &[Uuid],
+        ) -> Result<HashMap<Uuid, UserSessionState>, UserError> {
+            let users = self.users.lock().expect("mock user store lock poisoned");
+            let password_changes = self
+                .password_changes
+                .lock()
+                .expect("mock user store lock poisoned");
+            let password_resets_required = self
+                .password_resets_required
+                .lock()
+                .expect("mock user store lock poisoned");
+            Ok(ids
+                .iter()
+                .filter_map(|id| users.get(id))
+                .map(|user| {
+                    let state = UserSessionState {
+                        is_active: user.is_active,
+                        password_changed_at: password_changes.get(&user.id).copied(),
+                        password_reset_required: password_resets_required.contains(&user.id),
+                    };
+                    (user.id, state)
+                })
+                .collect())
+        }
+
+        async fn require_password_reset(&self, id: Uuid) -> Result<bool, UserError> {
+            let users = self.users.lock().expect("mock user store lock poisoned");
+            if !users.contains_key(&id) {
+                return Err(UserError::NotFound);
+            }
+            Ok(self
+                .password_resets_required
+                .lock()
+                .expect("mock user store lock poisoned")
+                .insert(id))
+        }
+
+        async fn password_history(&self, id: Uuid, limit: usize) -> Result<Vec<String>, UserError> {
+            let history = self
+                .password_history
+                .lock()
+                .expect("mock user store lock poisoned");
+            Ok(history
+                .get(&id)
+                .map(|hashes| hashes.iter().take(limit).cloned().collect())
+                .unwrap_or_default())
+        }
+
+        async fn record_password_history(
+            &self,
+            id: Uuid,
+            password_hash: &str,
+            keep: usize,
+        ) -> Result<(), UserError> {
+            let mut history = self
+                .password_history
+                .lock()
+                .expect("mock user store lock poisoned");
+            let hashes = history.entry(id).or_default();
+            hashes.insert(0, password_hash.to_string());
+            hashes.truncate(keep);
+            Ok(())
+        }
+
+        async fn log_security_event(
+            &self,
+            user_id: Uuid,
+            action: &str,
+            details: serde_json::Value,
+            _ip_address: Option<String>,
+            _user_agent: Option<String>,
+        ) -> Result<(), UserError> {
+            self.security_events
+                .lock()
+                .expect("mock user store lock poisoned")
+                .push((user_id, action.to_string(), details));
+            Ok(())
+        }
     }
 }
@@ -1,11 +1,13 @@
 use serde::{Deserialize, Serialize};
-use time::{Duration, OffsetDateTime};
+use thiserror::Error;
+use time::OffsetDateTime;
 use uuid::Uuid;
 
 use crate::models::{TenantId, UserId};
+use crate::utils::duration::DurationSecs;
 
 /// Types of verification methods available
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum VerificationType {
     /// Email-based verification
     Email,
@@ -24,6 +26,8 @@ pub enum VerificationStatus {
     Expired,
     /// Code has been invalidated (e.g., due to too many failed attempts)
     Invalidated,
+    /// Code has been verified and the proof of the verification has been used
+    Consumed,
 }
 
 /// Configuration for verification codes
@@ -31,25 +35,103 @@ pub enum VerificationStatus {
 pub struct VerificationConfig {
     /// Length of the verification code
     pub code_length: usize,
-    /// How long the code is valid for (in seconds)
-    pub expiration_seconds: i64,
+    /// How long the code is valid for
+    pub expiration_seconds: DurationSecs,
     /// Maximum number of attempts allowed
     pub max_attempts: usize,
-    /// Minimum time between code generation requests (in seconds)
-    pub throttle_seconds: i64,
+    /// Minimum time between code generation requests
+    pub throttle_seconds: DurationSecs,
 }
 
 impl Default for VerificationConfig {
     fn default() -> Self {
         Self {
             code_length: 6,
-            expiration_seconds: 600, // 10 minutes
+            expiration_seconds: DurationSecs::from_secs(600), // 10 minutes
             max_attempts: 5,
-            throttle_seconds: 60, // 1 minute
+            throttle_seconds: DurationSecs::from_secs(60), // 1 minute
         }
     }
 }
 
+/// Shortest verification code a config may ask for
+pub const MIN_CODE_LENGTH: usize = 4;
+/// Longest verification code a config may ask for
+pub const MAX_CODE_LENGTH: usize = 12;
+
+/// Verification code settings that would issue unusable codes
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum VerificationConfigError {
+    #[error(
+        "Verification code length must be between {min} and {max}, got {0}",
+        min = MIN_CODE_LENGTH,
+        max = MAX_CODE_LENGTH
+    )]
+    CodeLength(usize),
+
+    #[error("Verification codes must allow at least one attempt")]
+    NoAttempts,
+
+    #[error(
+        "Verification codes must stay valid longer than the throttling period ({expiration}s <= {throttle}s)"
+    )]
+    ExpirationWithinThrottle { expiration: u64, throttle: u64 },
+}
+
+impl VerificationConfig {
+    /// Check that codes issued with this config can be verified
+    ///
+    /// A code expiring within the throttling period could not be replaced
+    /// before it runs out.
+    pub fn validate(&self) -> Result<(), VerificationConfigError> {
+        if !(MIN_CODE_LENGTH..=MAX_CODE_LENGTH).contains(&self.code_length) {
+            return Err(VerificationConfigError::CodeLength(self.code_length));
+        }
+        if self.max_attempts == 0 {
+            return Err(VerificationConfigError::NoAttempts);
+        }
+        if self.expiration_seconds <= self.throttle_seconds {
+            return Err(VerificationConfigError::ExpirationWithinThrottle {
+                expiration: self.expiration_seconds.as_secs(),
+                throttle: self.throttle_seconds.as_secs(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Pending codes of one tenant that a sweep marked as expired
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpiredCodes {
+    /// Tenant the codes belong to
+    pub tenant_id: TenantId,
+    /// Number of codes that expired without being verified
+    pub count: u64,
+}
+
+/// Outcome of the verification codes of a tenant issued since some instant
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct VerificationStats {
+    /// Codes issued
+    pub issued: u64,
+    /// Codes verified successfully
+    pub verified: u64,
+    /// Codes that expired without being verified
+    pub expired: u64,
+    /// Codes invalidated by a newer code or too many attempts
+    pub invalidated: u64,
+    /// Codes that can still be verified
+    pub pending: u64,
+}
+
+impl VerificationStats {
+    /// Share of the settled codes that were verified, `None` while none has settled
+    pub fn success_rate(&self) -> Option<f64> {
+        let settled = self.issued.saturating_sub(self.pending);
+        (settled > 0).then(|| self.verified as f64 / settled as f64)
+    }
+}
+
 /// Represents a verification code for second-factor authentication
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VerificationCode {
@@ -82,8 +164,26 @@ impl VerificationCode {
         verification_type: VerificationType,
         config: &VerificationConfig,
     ) -> Self {
-        let now = OffsetDateTime::now_utc();
-        let expires_at = now + Duration::seconds(config.expiration_seconds);
+        Self::issued_at(
+            tenant_id,
+            user_id,
+            code,
+            verification_type,
+            config,
+            OffsetDateTime::now_utc(),
+        )
+    }
+
+    /// Create a new verification code issued at the given instant
+    pub fn issued_at(
+        tenant_id: TenantId,
+        user_id: UserId,
+        code: String,
+        verification_type: VerificationType,
+        config: &VerificationConfig,
+        now: OffsetDateTime,
+    ) -> Self {
+        let expires_at = now + config.expiration_seconds.to_time();
 
         Self {
             id: Uuid::new_v4(),
@@ -100,7 +200,12 @@ impl VerificationCode {
 
     /// Check if this verification code is expired
     pub fn is_expired(&self) -> bool {
-        self.expires_at < OffsetDateTime::now_utc() || self.status == VerificationStatus::Expired
+        self.is_expired_at(OffsetDateTime::now_utc())
+    }
+
+    /// Check if this verification code is expired as of `now`
+    pub fn is_expired_at(&self, now: OffsetDateTime) -> bool {
+        self.expires_at < now || self.status == VerificationStatus::Expired
     }
 
     /// Check if this code has too many attempts
@@ -0,0 +1,166 @@
+use crate::models::account_deletion::AccountDeletionRequest;
+use crate::repository::RepositoryError;
+use async_trait::async_trait;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// Repository interface for self-service account deletion requests
+#[async_trait]
+pub trait AccountDeletionRepository: Send + Sync + 'static {
+    /// Store a new request
+    ///
+    /// Fails with `UniqueViolation` if the user already has one.
+    async fn create(&self, request: &AccountDeletionRequest) -> Result<(), RepositoryError>;
+
+    /// Find the pending request of a user
+    async fn find_pending(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Option<AccountDeletionRequest>, RepositoryError>;
+
+    /// Remove the pending request of a user
+    ///
+    /// Returns `false` if the user has no pending request.
+    async fn cancel(&self, user_id: Uuid) -> Result<bool, RepositoryError>;
+
+    /// List pending requests whose grace period has ended, oldest first
+    async fn list_due(
+        &self,
+        now: OffsetDateTime,
+        limit: u32,
+    ) -> Result<Vec<AccountDeletionRequest>, RepositoryError>;
+
+    /// Record that the user was asked to transfer tenant ownership
+    async fn record_ownership_notice(
+        &self,
+        user_id: Uuid,
+        sent_at: OffsetDateTime,
+    ) -> Result<(), RepositoryError>;
+
+    /// Mark the request of a user as carried out
+    async fn complete(
+        &self,
+        user_id: Uuid,
+        completed_at: OffsetDateTime,
+    ) -> Result<(), RepositoryError>;
+}
+
+/// In-memory [`AccountDeletionRepository`] for tests
+///
+/// Shared by the unit tests in this crate and by downstream test crates (via
+/// the `test-support` feature).
+#[cfg(any(test, feature = "test-support"))]
+pub mod mock {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// In-memory account deletion repository for tests
+    #[derive(Default)]
+    pub struct MockAccountDeletionRepository {
+        requests: Mutex<HashMap<Uuid, AccountDeletionRequest>>,
+    }
+
+    impl MockAccountDeletionRepository {
+        /// Creates an empty mock account deletion repository
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Returns the stored request of a user, including completed ones
+        pub fn request(&self, user_id: Uuid) -> Option<AccountDeletionRequest> {
+            self.requests
+                .lock()
+                .expect("mock account deletion store lock poisoned")
+                .get(&user_id)
+                .cloned()
+        }
+    }
+
+    #[async_trait]
+    impl AccountDeletionRepository for MockAccountDeletionRepository {
+        async fn create(&self, request: &AccountDeletionRequest) -> Result<(), RepositoryError> {
+            let mut requests = self
+                .requests
+                .lock()
+                .expect("mock account deletion store lock poisoned");
+            if requests.contains_key(&request.user_id) {
+                return Err(RepositoryError::UniqueViolation(
+                    "Account deletion already requested".to_string(),
+                ));
+            }
+            requests.insert(request.user_id, request.clone());
+            Ok(())
+        }
+
+        async fn find_pending(
+            &self,
+            user_id: Uuid,
+        ) -> Result<Option<AccountDeletionRequest>, RepositoryError> {
+            Ok(self.request(user_id).filter(|request| request.is_pending()))
+        }
+
+        async fn cancel(&self, user_id: Uuid) -> Result<bool, RepositoryError> {
+            let mut requests = self
+                .requests
+                .lock()
+                .expect("mock account deletion store lock poisoned");
+            if requests.get(&user_id).is_some_and(|r| r.is_pending()) {
+                requests.remove(&user_id);
+                Ok(true)
+            } else {
+                Ok(false)
+            }
+        }
+
+        async fn list_due(
+            &self,
+            now: OffsetDateTime,
+            limit: u32,
+        ) -> Result<Vec<AccountDeletionRequest>, RepositoryError> {
+            let mut due: Vec<AccountDeletionRequest> = self
+                .requests
+                .lock()
+                .expect("mock account deletion store lock poisoned")
+                .values()
+                .filter(|request| request.is_due(now))
+                .cloned()
+                .collect();
+            due.sort_by_key(|request| request.scheduled_for);
+            due.truncate(limit as usize);
+            Ok(due)
+        }
+
+        async fn record_ownership_notice(
+            &self,
+            user_id: Uuid,
+            sent_at: OffsetDateTime,
+        ) -> Result<(), RepositoryError> {
+            if let Some(request) = self
+                .requests
+                .lock()
+                .expect("mock account deletion store lock poisoned")
+                .get_mut(&user_id)
+            {
+                request.ownership_notice_sent_at = Some(sent_at);
+            }
+            Ok(())
+        }
+
+        async fn complete(
+            &self,
+            user_id: Uuid,
+            completed_at: OffsetDateTime,
+        ) -> Result<(), RepositoryError> {
+            if let Some(request) = self
+                .requests
+                .lock()
+                .expect("mock account deletion store lock poisoned")
+                .get_mut(&user_id)
+            {
+                request.completed_at = Some(completed_at);
+            }
+            Ok(())
+        }
+    }
+}
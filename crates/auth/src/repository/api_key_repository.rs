@@ -0,0 +1,292 @@
+use crate::models::api_key::{ApiKey, ApiKeyFilter};
+use crate::repository::RepositoryError;
+use async_trait::async_trait;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// Repository interface for tenant API keys
+#[async_trait]
+pub trait ApiKeyRepository: Send + Sync + 'static {
+    /// Store a newly issued key
+    async fn create(&self, api_key: &ApiKey) -> Result<(), RepositoryError>;
+
+    /// Find a key by its lookup prefix
+    async fn find_by_prefix(&self, prefix: &str) -> Result<Option<ApiKey>, RepositoryError>;
+
+    /// Find a key of a tenant by ID
+    async fn find_by_id(
+        &self,
+        tenant_id: Uuid,
+        id: Uuid,
+    ) -> Result<Option<ApiKey>, RepositoryError>;
+
+    /// List the keys of a tenant matching `filter`, newest first
+    async fn list(
+        &self,
+        tenant_id: Uuid,
+        filter: &ApiKeyFilter,
+    ) -> Result<Vec<ApiKey>, RepositoryError>;
+
+    /// Keys of any tenant, valid at `now`, that expire before `expires_before`
+    /// or were not used since `unused_since`
+    ///
+    /// Keys reminded of since `reminded_since` are left out. Returns at most
+    /// `limit` keys, those reminded of longest ago first.
+    async fn list_reminder_due(
+        &self,
+        now: OffsetDateTime,
+        expires_before: OffsetDateTime,
+        unused_since: OffsetDateTime,
+        reminded_since: OffsetDateTime,
+        limit: u32,
+    ) -> Result<Vec<ApiKey>, RepositoryError>;
+
+    /// Record that the tenant admins were reminded of the keys
+    async fn record_reminder(
+        &self,
+        ids: &[Uuid],
+        reminded_at: OffsetDateTime,
+    ) -> Result<(), RepositoryError>;
+
+    /// Revoke a key of a tenant
+    ///
+    /// Returns `false` if the key does not exist or was already revoked.
+    async fn revoke(
+        &self,
+        tenant_id: Uuid,
+        id: Uuid,
+        revoked_at: OffsetDateTime,
+    ) -> Result<bool, RepositoryError>;
+
+    /// Let an unrevoked key of a tenant expire at `expires_at`
+    ///
+    /// Keys expiring earlier keep their expiry. Returns `false` if the key does
+    /// not exist or was revoked.
+    async fn expire(
+        &self,
+        tenant_id: Uuid,
+        id: Uuid,
+        expires_at: OffsetDateTime,
+    ) -> Result<bool, RepositoryError>;
+
+    /// Revoke the unrevoked keys a user issued, in any tenant
+    ///
+    /// Returns the number of keys revoked.
+    async fn revoke_created_by(
+        &self,
+        created_by: Uuid,
+        revoked_at: OffsetDateTime,
+    ) -> Result<u64, RepositoryError>;
+
+    /// Record that a key was used to authenticate a request
+    async fn record_use(&self, id: Uuid, used_at: OffsetDateTime) -> Result<(), RepositoryError>;
+}
+
+/// In-memory [`ApiKeyRepository`] for tests
+///
+/// Shared by the unit tests in this crate and by downstream test crates (via
+/// the `test-support` feature).
+#[cfg(any(test, feature = "test-support"))]
+pub mod mock {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// In-memory API key repository for tests
+    #[derive(Default)]
+    pub struct MockApiKeyRepository {
+        keys: Mutex<Vec<ApiKey>>,
+    }
+
+    impl MockApiKeyRepository {
+        /// Creates an empty mock API key repository
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Returns a snapshot of all stored keys
+        pub fn keys(&self) -> Vec<ApiKey> {
+            self.keys
+                .lock()
+                .expect("mock API key store lock poisoned")
+                .clone()
+        }
+    }
+
+    #[async_trait]
+    impl ApiKeyRepository for MockApiKeyRepository {
+        async fn create(&self, api_key: &ApiKey) -> Result<(), RepositoryError> {
+            let mut keys = self.keys.lock().expect("mock API key store lock poisoned");
+            if keys.iter().any(|key| key.prefix == api_key.prefix) {
+                return Err(RepositoryError::UniqueViolation(
+                    "API key prefix already exists".to_string(),
+                ));
+            }
+            keys.push(api_key.clone());
+            Ok(())
+        }
+
+        async fn find_by_prefix(&self, prefix: &str) -> Result<Option<ApiKey>, RepositoryError> {
+            Ok(self
+                .keys
+                .lock()
+                .expect("mock API key store lock poisoned")
+                .iter()
+                .find(|key| key.prefix == prefix)
+                .cloned())
+        }
+
+        async fn find_by_id(
+            &self,
+            tenant_id: Uuid,
+            id: Uuid,
+        ) -> Result<Option<ApiKey>, RepositoryError> {
+            Ok(self
+                .keys
+                .lock()
+                .expect("mock API key store lock poisoned")
+                .iter()
+                .find(|key| key.tenant_id == tenant_id && key.id == id)
+                .cloned())
+        }
+
+        async fn list(
+            &self,
+            tenant_id: Uuid,
+            filter: &ApiKeyFilter,
+        ) -> Result<Vec<ApiKey>, RepositoryError> {
+            let mut keys: Vec<ApiKey> = self
+                .keys
+                .lock()
+                .expect("mock API key store lock poisoned")
+                .iter()
+                .filter(|key| key.tenant_id == tenant_id && filter.matches(key))
+                .cloned()
+                .collect();
+            keys.sort_by_key(|key| std::cmp::Reverse(key.created_at));
+            Ok(keys)
+        }
+
+        async fn list_reminder_due(
+            &self,
+            now: OffsetDateTime,
+            expires_before: OffsetDateTime,
+            unused_since: OffsetDateTime,
+            reminded_since: OffsetDateTime,
+            limit: u32,
+        ) -> Result<Vec<ApiKey>, RepositoryError> {
+            let mut keys: Vec<ApiKey> = self
+                .keys
+                .lock()
+                .expect("mock API key store lock poisoned")
+                .iter()
+                .filter(|key| {
+                    !key.is_revoked()
+                        && !key.is_expired(now)
+                        && (key
+                            .expires_at
+                            .is_some_and(|expires_at| expires_at < expires_before)
+                            || !key.used_since(unused_since))
+                        && key
+                            .reminded_at
+                            .is_none_or(|reminded_at| reminded_at < reminded_since)
+                })
+                .cloned()
+                .collect();
+            keys.sort_by_key(|key| key.reminded_at);
+            keys.truncate(limit as usize);
+            Ok(keys)
+        }
+
+        async fn record_reminder(
+            &self,
+            ids: &[Uuid],
+            reminded_at: OffsetDateTime,
+        ) -> Result<(), RepositoryError> {
+            for key in self
+                .keys
+                .lock()
+                .expect("mock API key store lock poisoned")
+                .iter_mut()
+                .filter(|key| ids.contains(&key.id))
+            {
+                key.reminded_at = Some(reminded_at);
+            }
+            Ok(())
+        }
+
+        async fn revoke(
+            &self,
+            tenant_id: Uuid,
+            id: Uuid,
+            revoked_at: OffsetDateTime,
+        ) -> Result<bool, RepositoryError> {
+            let mut keys = self.keys.lock().expect("mock API key store lock poisoned");
+            match keys
+                .iter_mut()
+                .find(|key| key.tenant_id == tenant_id && key.id == id && key.revoked_at.is_none())
+            {
+                Some(key) => {
+                    key.revoked_at = Some(revoked_at);
+                    Ok(true)
+                },
+                None => Ok(false),
+            }
+        }
+
+        async fn expire(
+            &self,
+            tenant_id: Uuid,
+            id: Uuid,
+            expires_at: OffsetDateTime,
+        ) -> Result<bool, RepositoryError> {
+            let mut keys = self.keys.lock().expect("mock API key store lock poisoned");
+            match keys
+                .iter_mut()
+                .find(|key| key.tenant_id == tenant_id && key.id == id && key.revoked_at.is_none())
+            {
+                Some(key) => {
+                    key.expires_at = Some(
+                        key.expires_at
+                            .map_or(expires_at, |current| current.min(expires_at)),
+                    );
+                    Ok(true)
+                },
+                None => Ok(false),
+            }
+        }
+
+        async fn revoke_created_by(
+            &self,
+            created_by: Uuid,
+            revoked_at: OffsetDateTime,
+        ) -> Result<u64, RepositoryError> {
+            let mut keys = self.keys.lock().expect("mock API key store lock poisoned");
+            let mut revoked = 0;
+            for key in keys
+                .iter_mut()
+                .filter(|key| key.created_by == Some(created_by) && key.revoked_at.is_none())
+            {
+                key.revoked_at = Some(revoked_at);
+                revoked += 1;
+            }
+            Ok(revoked)
+        }
+
+        async fn record_use(
+            &self,
+            id: Uuid,
+            used_at: OffsetDateTime,
+        ) -> Result<(), RepositoryError> {
+            if let Some(key) = self
+                .keys
+                .lock()
+                .expect("mock API key store lock poisoned")
+                .iter_mut()
+                .find(|key| key.id == id)
+            {
+                key.last_used_at = Some(used_at);
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use async_trait::async_trait;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::models::{DeliveryStatus, MessageDelivery};
+use acci_core::error::Result;
+
+/// Repository of the deliveries tracked until their provider reports them delivered or failed
+#[async_trait]
+pub trait MessageDeliveryRepository: Send + Sync {
+    /// Persist a new delivery
+    async fn record(&self, delivery: &MessageDelivery) -> Result<()>;
+
+    /// Find a delivery by the message ID its provider returned at send time
+    async fn find_by_provider_message_id(
+        &self,
+        provider_message_id: &str,
+    ) -> Result<Option<MessageDelivery>>;
+
+    /// Move a delivery from `from` to `to`, returning whether it was still in `from`
+    ///
+    /// Concurrent callbacks for the same message therefore apply a
+    /// transition only once.
+    async fn update_status(
+        &self,
+        id: Uuid,
+        from: DeliveryStatus,
+        to: DeliveryStatus,
+        error: Option<&str>,
+        now: OffsetDateTime,
+    ) -> Result<bool>;
+}
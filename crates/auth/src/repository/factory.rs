@@ -0,0 +1,119 @@
+use sqlx::postgres::PgPoolOptions;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::models::tenant::TenantRepository;
+use crate::models::user::UserRepository;
+use crate::repository::postgres::{RepositoryBackend, RepositoryConfig};
+use crate::repository::{
+    PostgresTenantRepository, PostgresUserRepository, PostgresVerificationCodeRepository,
+    RepositoryError, VerificationCodeRepository,
+};
+use crate::services::event_export::EventExporter;
+use crate::session::{PostgresSessionRepository, SessionRepository, SessionRepositoryConfig};
+
+/// The repositories of the core entities, backed by one storage backend
+#[derive(Clone)]
+pub struct Repositories {
+    pub users: Arc<dyn UserRepository>,
+    pub tenants: Arc<dyn TenantRepository>,
+    pub sessions: Arc<dyn SessionRepository>,
+    pub verification_codes: Arc<dyn VerificationCodeRepository>,
+}
+
+/// Creates the [`Repositories`] for the backend selected in the [`RepositoryConfig`]
+pub struct RepositoryFactory {
+    config: RepositoryConfig,
+    event_exporter: Option<Arc<EventExporter>>,
+}
+
+impl RepositoryFactory {
+    pub fn new(config: RepositoryConfig) -> Self {
+        Self {
+            config,
+            event_exporter: None,
+        }
+    }
+
+    /// Export the audit events of the Postgres repositories to a SIEM
+    pub fn with_event_exporter(mut self, exporter: Arc<EventExporter>) -> Self {
+        self.event_exporter = Some(exporter);
+        self
+    }
+
+    /// Connects to the configured backend and creates the repositories
+    ///
+    /// The in-memory backend starts out empty and is only available with the
+    /// `in-memory` feature.
+    pub async fn create(&self) -> Result<Repositories, RepositoryError> {
+        match self.config.backend {
+            RepositoryBackend::Postgres => self.create_postgres().await,
+            RepositoryBackend::InMemory => self.create_in_memory(),
+        }
+    }
+
+    async fn create_postgres(&self) -> Result<Repositories, RepositoryError> {
+        let pool = self
+            .config
+            .timeouts
+            .apply_to(PgPoolOptions::new())
+            .max_connections(self.config.max_connections)
+            .acquire_timeout(self.config.connect_timeout)
+            .connect(&self.config.database_url)
+            .await
+            .map_err(|e| RepositoryError::ConnectionError(e.to_string()))?;
+        let mut users = PostgresUserRepository::new(self.config.clone())
+            .await
+            .map_err(|e| RepositoryError::ConnectionError(e.to_string()))?;
+        let mut tenants = PostgresTenantRepository::new(self.config.clone())
+            .await
+            .map_err(|e| RepositoryError::ConnectionError(e.to_string()))?;
+        if let Some(exporter) = &self.event_exporter {
+            users = users.with_event_exporter(exporter.clone());
+            tenants = tenants.with_event_exporter(exporter.clone());
+        }
+
+        info!("Created Postgres repositories");
+        Ok(Repositories {
+            users: Arc::new(users),
+            tenants: Arc::new(tenants),
+            sessions: Arc::new(PostgresSessionRepository::with_config(
+                pool.clone(),
+                SessionRepositoryConfig {
+                    timeouts: self.config.timeouts,
+                    ..SessionRepositoryConfig::default()
+                },
+            )),
+            verification_codes: Arc::new(PostgresVerificationCodeRepository::new(pool)),
+        })
+    }
+
+    #[cfg(any(test, feature = "in-memory"))]
+    fn create_in_memory(&self) -> Result<Repositories, RepositoryError> {
+        use crate::repository::{
+            InMemoryDatabase, InMemorySessionRepository, InMemoryTenantRepository,
+            InMemoryUserRepository, InMemoryVerificationCodeRepository,
+        };
+
+        let database = InMemoryDatabase::new();
+        let users = InMemoryUserRepository::new(database.clone(), &self.config)
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+        let tenants = InMemoryTenantRepository::new(database.clone(), &self.config)
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+        info!("Created in-memory repositories");
+        Ok(Repositories {
+            users: Arc::new(users),
+            tenants: Arc::new(tenants),
+            sessions: Arc::new(InMemorySessionRepository::new(database.clone())),
+            verification_codes: Arc::new(InMemoryVerificationCodeRepository::new(database)),
+        })
+    }
+
+    #[cfg(not(any(test, feature = "in-memory")))]
+    fn create_in_memory(&self) -> Result<Repositories, RepositoryError> {
+        Err(RepositoryError::DatabaseError(
+            "The in-memory backend requires the `in-memory` feature".to_string(),
+        ))
+    }
+}
@@ -0,0 +1,14 @@
+use crate::models::identity::LinkedIdentity;
+use crate::repository::RepositoryError;
+use async_trait::async_trait;
+use uuid::Uuid;
+
+/// Repository interface for external identities linked to local users
+#[async_trait]
+pub trait ExternalIdentityRepository: Send + Sync + 'static {
+    /// Link an identity, or record another login if it is already linked
+    async fn link(&self, identity: &LinkedIdentity) -> Result<(), RepositoryError>;
+
+    /// List the identities linked to a user
+    async fn list_for_user(&self, user_id: &Uuid) -> Result<Vec<LinkedIdentity>, RepositoryError>;
+}
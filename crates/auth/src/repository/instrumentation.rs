@@ -0,0 +1,113 @@
+//! Latency and error metrics of the repository methods
+//!
+//! Request durations are recorded per endpoint, which hides where the time of
+//! a slow request went. Every repository method therefore records its
+//! duration in the [`OPERATION_DURATION_METRIC`] histogram and counts its
+//! failures in [`OPERATION_ERRORS_METRIC`]. Both are labeled with the
+//! operation only, named `<repository>.repo.<method>` like `user.repo.create`
+//! or `session.repo.get_session_by_token`, which is also the name of the
+//! tracing span of the method. IDs and other arguments are never used as
+//! labels, keeping the number of series bounded by the number of methods.
+//!
+//! Nothing is recorded without the `metrics` feature.
+
+use crate::repository::timeout::{OperationTimedOut, within};
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// Histogram of the duration of repository operations in seconds
+pub const OPERATION_DURATION_METRIC: &str = "auth_repository_operation_duration_seconds";
+
+/// Counter of the repository operations that returned an error
+pub const OPERATION_ERRORS_METRIC: &str = "auth_repository_operation_errors_total";
+
+/// Run the repository operation `operation`, recording its duration and
+/// whether it failed
+pub(crate) async fn observe<T, E>(
+    operation: &'static str,
+    future: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    let start = Instant::now();
+    let result = future.await;
+    record(operation, start, result.is_err());
+    result
+}
+
+/// [`observe`] the repository operation `operation`, failing it if it does
+/// not finish [`within`] `budget`
+pub(crate) async fn observe_within<T, E>(
+    operation: &'static str,
+    budget: impl Into<Duration>,
+    future: impl Future<Output = Result<T, E>>,
+) -> Result<T, E>
+where
+    E: From<OperationTimedOut>,
+{
+    observe(operation, within(operation, budget, future)).await
+}
+
+/// Record an operation started at `start` that finished now
+#[cfg(feature = "metrics")]
+pub(crate) fn record(operation: &'static str, start: Instant, failed: bool) {
+    metrics::histogram!(OPERATION_DURATION_METRIC, "operation" => operation)
+        .record(start.elapsed().as_secs_f64());
+    if failed {
+        metrics::counter!(OPERATION_ERRORS_METRIC, "operation" => operation).increment(1);
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) fn record(_operation: &'static str, _start: Instant, _failed: bool) {}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    use crate::models::user::{User, UserRepository};
+    use crate::repository::{InMemoryDatabase, InMemoryUserRepository, RepositoryConfig};
+    use metrics_util::MetricKind;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+    #[test]
+    fn test_repository_call_records_latency() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let repo =
+            InMemoryUserRepository::new(InMemoryDatabase::new(), &RepositoryConfig::default())
+                .expect("valid repository config");
+        let user = User::new("metrics@example.com".to_string(), "hash".to_string());
+
+        metrics::with_local_recorder(&recorder, || {
+            futures::executor::block_on(async {
+                repo.create(&user).await.expect("Failed to create user");
+                // The email is taken now
+                assert!(repo.create(&user).await.is_err());
+            })
+        });
+
+        let metrics = snapshotter.snapshot().into_vec();
+        let value = |kind: MetricKind, name: &str| {
+            metrics
+                .iter()
+                .find(|(key, ..)| key.kind() == kind && key.key().name() == name)
+                .map(|(key, _, _, value)| {
+                    // Only the operation is a label, in particular no IDs
+                    let labels: Vec<_> = key
+                        .key()
+                        .labels()
+                        .map(|label| (label.key(), label.value()))
+                        .collect();
+                    assert_eq!(labels, [("operation", "user.repo.create")]);
+                    value
+                })
+        };
+
+        match value(MetricKind::Histogram, OPERATION_DURATION_METRIC) {
+            Some(DebugValue::Histogram(observations)) => assert_eq!(observations.len(), 2),
+            value => panic!("Expected latency observations, got {value:?}"),
+        }
+        assert!(matches!(
+            value(MetricKind::Counter, OPERATION_ERRORS_METRIC),
+            Some(DebugValue::Counter(1))
+        ));
+    }
+}
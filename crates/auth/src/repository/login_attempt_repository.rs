@@ -0,0 +1,87 @@
+use crate::models::login_attempt::{LoginAttemptRecord, LoginAttemptStats};
+use crate::repository::RepositoryError;
+use async_trait::async_trait;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// Repository interface for the durable record of login attempts
+#[async_trait]
+pub trait LoginAttemptRepository: Send + Sync + 'static {
+    /// Store an attempt
+    async fn record(&self, attempt: &LoginAttemptRecord) -> Result<(), RepositoryError>;
+
+    /// Count the attempts of a tenant made at or after `since` by outcome
+    async fn stats(
+        &self,
+        tenant_id: Uuid,
+        since: OffsetDateTime,
+    ) -> Result<LoginAttemptStats, RepositoryError>;
+}
+
+/// In-memory [`LoginAttemptRepository`] for tests
+///
+/// Shared by the unit tests in this crate and by downstream test crates (via
+/// the `test-support` feature).
+#[cfg(any(test, feature = "test-support"))]
+pub mod mock {
+    use super::*;
+    use crate::models::login_attempt::LoginOutcome;
+    use std::sync::Mutex;
+
+    /// In-memory login attempt repository for tests
+    #[derive(Default)]
+    pub struct MockLoginAttemptRepository {
+        attempts: Mutex<Vec<LoginAttemptRecord>>,
+    }
+
+    impl MockLoginAttemptRepository {
+        /// Creates an empty mock login attempt repository
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Returns all recorded attempts in the order they were stored
+        pub fn attempts(&self) -> Vec<LoginAttemptRecord> {
+            self.attempts
+                .lock()
+                .expect("mock login attempt store lock poisoned")
+                .clone()
+        }
+    }
+
+    #[async_trait]
+    impl LoginAttemptRepository for MockLoginAttemptRepository {
+        async fn record(&self, attempt: &LoginAttemptRecord) -> Result<(), RepositoryError> {
+            self.attempts
+                .lock()
+                .expect("mock login attempt store lock poisoned")
+                .push(attempt.clone());
+            Ok(())
+        }
+
+        async fn stats(
+            &self,
+            tenant_id: Uuid,
+            since: OffsetDateTime,
+        ) -> Result<LoginAttemptStats, RepositoryError> {
+            let attempts = self
+                .attempts
+                .lock()
+                .expect("mock login attempt store lock poisoned");
+            let mut stats = LoginAttemptStats::default();
+            for attempt in attempts
+                .iter()
+                .filter(|attempt| attempt.tenant_id == Some(tenant_id))
+                .filter(|attempt| attempt.attempted_at >= since)
+            {
+                stats.total += 1;
+                if attempt.outcome == LoginOutcome::Succeeded {
+                    stats.succeeded += 1;
+                } else {
+                    stats.failed += 1;
+                }
+            }
+            Ok(stats)
+        }
+    }
+}
@@ -0,0 +1,1140 @@
+//! In-memory repositories for running without a database
+//!
+//! Process-local implementations of the user, tenant, session and
+//! verification code repositories for local development and tests. They
+//! follow the Postgres schema closely: primary and unique keys, the length of
+//! `VARCHAR` columns, result ordering and the cascades of foreign keys behave
+//! like their database counterparts, and the user and tenant repositories
+//! apply the rate limit of the [`RepositoryConfig`] like the Postgres ones.
+//!
+//! All repositories created from one [`InMemoryDatabase`] share its tables,
+//! so that e.g. deleting a user also removes its memberships and sessions.
+//! Nothing is persisted; the data is gone when the process exits.
+
+use async_trait::async_trait;
+use governor::{
+    Quota, RateLimiter,
+    clock::DefaultClock,
+    middleware::NoOpMiddleware,
+    state::{InMemoryState, NotKeyed},
+};
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroU32;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+use time::OffsetDateTime;
+use tracing::{debug, info, instrument, warn};
+use uuid::Uuid;
+
+use crate::models::tenant::{
+    CreateSubscriptionDto, CreateTenantDto, CreateTenantUserDto, SubscriptionHistoryEntry, Tenant,
+    TenantError, TenantRepository, TenantStatus, TenantSubscription, TenantUser,
+    UpdateSubscriptionDto, UpdateTenantDto, UpdateTenantUserDto,
+};
+use crate::models::user::{User, UserError, UserRepository, UserSessionState};
+use crate::models::{VerificationCode, VerificationType};
+use crate::repository::RepositoryConfig;
+use crate::repository::instrumentation::observe;
+use crate::repository::memory_session::SessionAuditEntry;
+use crate::session::Session;
+use crate::utils::bounded_json::BoundedJson;
+use crate::utils::password::NO_PASSWORD_HASH;
+
+type DirectRateLimiter = RateLimiter<NotKeyed, InMemoryState, DefaultClock, NoOpMiddleware>;
+
+/// Tables shared by the in-memory repositories
+///
+/// Locks are always taken in the order the tables are declared in, so that
+/// operations spanning several tables cannot deadlock.
+#[derive(Default)]
+pub struct InMemoryDatabase {
+    pub(crate) users: RwLock<HashMap<Uuid, User>>,
+    /// The `password_changed_at` column of the users table
+    pub(crate) password_changes: RwLock<HashMap<Uuid, OffsetDateTime>>,
+    /// Users whose `password_reset_required` column is set
+    pub(crate) password_resets_required: RwLock<HashSet<Uuid>>,
+    /// The `password_history` table, newest hash first
+    pub(crate) password_history: RwLock<HashMap<Uuid, Vec<String>>>,
+    pub(crate) tenants: RwLock<HashMap<Uuid, Tenant>>,
+    pub(crate) tenant_users: RwLock<Vec<TenantUser>>,
+    pub(crate) subscriptions: RwLock<Vec<TenantSubscription>>,
+    pub(crate) subscription_history: RwLock<Vec<SubscriptionHistoryEntry>>,
+    pub(crate) sessions: RwLock<Vec<Session>>,
+    pub(crate) session_audit_log: RwLock<Vec<SessionAuditEntry>>,
+    /// The `session_reconciliation_progress` table
+    pub(crate) session_reconciliation_cursor: RwLock<Option<Uuid>>,
+    pub(crate) verification_codes: RwLock<Vec<VerificationCode>>,
+    /// The `delivered_via` column of the verification codes table
+    pub(crate) verification_delivery_channels: RwLock<HashMap<Uuid, VerificationType>>,
+}
+
+impl InMemoryDatabase {
+    /// Creates an empty database
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+}
+
+/// Read access to a table
+///
+/// A panic while a lock was held leaves the table as it was before the
+/// failed write, so poisoned locks are recovered instead of propagated.
+pub(crate) fn read<T>(table: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    table.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Write access to a table, see [`read`]
+pub(crate) fn write<T>(table: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    table.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Checks `value` against a `VARCHAR(max)` column, which rejects longer values
+pub(crate) fn check_length(value: &str, max: usize) -> Result<(), String> {
+    if value.chars().count() > max {
+        return Err(format!(
+            "value too long for type character varying({})",
+            max
+        ));
+    }
+    Ok(())
+}
+
+/// Error of a violated unique or primary key constraint
+pub(crate) fn unique_violation(constraint: &str) -> String {
+    format!(
+        "duplicate key value violates unique constraint \"{}\"",
+        constraint
+    )
+}
+
+/// Error of a row referencing a missing row of another table
+pub(crate) fn foreign_key_violation(table: &str, constraint: &str) -> sqlx::Error {
+    sqlx::Error::Protocol(format!(
+        "insert or update on table \"{}\" violates foreign key constraint \"{}\"",
+        table, constraint
+    ))
+}
+
+fn rate_limiter(config: &RepositoryConfig) -> Result<DirectRateLimiter, String> {
+    let burst = NonZeroU32::new(config.rate_limit_burst)
+        .ok_or_else(|| "Invalid rate limit burst value".to_string())?;
+    let quota = Quota::with_period(Duration::from_millis(config.rate_limit_replenish_ms))
+        .ok_or_else(|| "Invalid rate limit period".to_string())?
+        .allow_burst(burst);
+    Ok(RateLimiter::direct(quota))
+}
+
+/// Removes everything that references `user_id` with `ON DELETE CASCADE`
+///
+/// Expects the caller to hold the lock of the users table.
+fn cascade_user_delete(database: &InMemoryDatabase, user_id: Uuid) {
+    write(&database.password_history).remove(&user_id);
+    write(&database.tenant_users).retain(|m| m.user_id != user_id);
+    let mut sessions = write(&database.sessions);
+    sessions.retain(|s| s.user_id != user_id);
+    write(&database.session_audit_log).retain(|entry| {
+        entry.user_id != user_id && sessions.iter().any(|s| s.id == entry.session_id)
+    });
+    drop(sessions);
+    write(&database.verification_codes).retain(|code| code.user_id != user_id);
+}
+
+/// In-memory [`UserRepository`]
+///
+/// Emails are unique and compared case-sensitively, like the `UNIQUE` email
+/// column. As in Postgres, the display name of a user reads as its email.
+pub struct InMemoryUserRepository {
+    database: Arc<InMemoryDatabase>,
+    rate_limiter: DirectRateLimiter,
+}
+
+impl InMemoryUserRepository {
+    /// Creates a repository on `database`, rate limited like the Postgres one
+    pub fn new(
+        database: Arc<InMemoryDatabase>,
+        config: &RepositoryConfig,
+    ) -> Result<Self, UserError> {
+        let rate_limiter = rate_limiter(config).map_err(UserError::ConfigError)?;
+        info!("InMemoryUserRepository initialized successfully");
+        Ok(Self {
+            database,
+            rate_limiter,
+        })
+    }
+
+    fn check_rate_limit(&self) -> Result<(), UserError> {
+        if self.rate_limiter.check().is_err() {
+            warn!("Rate limit exceeded");
+            return Err(UserError::RateLimitExceeded);
+        }
+        Ok(())
+    }
+
+    /// Sets the columns a user update writes, like the `UPDATE users` statements
+    fn update_where<F>(&self, id: Uuid, update: F) -> Result<(), UserError>
+    where
+        F: FnOnce(&mut User),
+    {
+        self.check_rate_limit()?;
+        let mut users = write(&self.database.users);
+        let user = users.get_mut(&id).ok_or(UserError::NotFound)?;
+        update(user);
+        user.updated_at = OffsetDateTime::now_utc();
+        Ok(())
+    }
+}
+
+/// A stored user as the `users` table returns it
+fn user_row(user: &User) -> User {
+    User {
+        display_name: user.email.clone(),
+        ..user.clone()
+    }
+}
+
+fn check_user_columns(user: &User) -> Result<(), UserError> {
+    check_length(&user.email, 255)
+        .and_then(|()| check_length(&user.password_hash, 255))
+        .map_err(UserError::DatabaseError)
+}
+
+#[async_trait]
+impl UserRepository for InMemoryUserRepository {
+    #[instrument(name = "user.repo.create", skip_all, level = "debug")]
+    async fn create(&self, user: &User) -> Result<(), UserError> {
+        observe("user.repo.create", async {
+            self.check_rate_limit()?;
+            check_user_columns(user)?;
+
+            let mut users = write(&self.database.users);
+            if users.values().any(|u| u.email == user.email) {
+                return Err(UserError::AlreadyExists);
+            }
+            if users.contains_key(&user.id) {
+                return Err(UserError::DatabaseError(unique_violation("users_pkey")));
+            }
+            if user.has_password() {
+                write(&self.database.password_changes).insert(user.id, user.created_at);
+            }
+            users.insert(user.id, user.clone());
+
+            info!("User created successfully: {}", user.id);
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "user.repo.find_by_id", skip_all, level = "debug")]
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<User>, UserError> {
+        observe("user.repo.find_by_id", async {
+            self.check_rate_limit()?;
+            debug!("User lookup by ID complete: {}", id);
+            Ok(read(&self.database.users).get(&id).map(user_row))
+        })
+        .await
+    }
+
+    #[instrument(name = "user.repo.find_by_email", skip_all, level = "debug")]
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, UserError> {
+        observe("user.repo.find_by_email", async {
+            self.check_rate_limit()?;
+            debug!("User lookup by email complete: {}", email);
+            Ok(read(&self.database.users)
+                .values()
+                .find(|u| u.email == email)
+                .map(user_row))
+        })
+        .await
+    }
+
+    #[instrument(name = "user.repo.update", skip_all, level = "debug")]
+    async fn update(&self, user: &User) -> Result<(), UserError> {
+        observe("user.repo.update", async {
+            self.check_rate_limit()?;
+            check_user_columns(user)?;
+
+            let mut users = write(&self.database.users);
+            if users
+                .values()
+                .any(|u| u.id != user.id && u.email == user.email)
+            {
+                return Err(UserError::DatabaseError(unique_violation(
+                    "users_email_key",
+                )));
+            }
+            let stored = users.get_mut(&user.id).ok_or(UserError::NotFound)?;
+            if stored.password_hash != user.password_hash && user.password_hash != NO_PASSWORD_HASH
+            {
+                write(&self.database.password_changes).insert(user.id, user.updated_at);
+                write(&self.database.password_resets_required).remove(&user.id);
+            }
+            stored.email = user.email.clone();
+            stored.password_hash = user.password_hash.clone();
+            stored.updated_at = user.updated_at;
+            stored.is_active = user.is_active;
+            stored.is_verified = user.is_verified;
+
+            info!("User updated successfully: {}", user.id);
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "user.repo.delete", skip_all, level = "debug")]
+    async fn delete(&self, id: Uuid) -> Result<(), UserError> {
+        observe("user.repo.delete", async {
+            self.check_rate_limit()?;
+            let mut users = write(&self.database.users);
+            users.remove(&id).ok_or(UserError::NotFound)?;
+            write(&self.database.password_changes).remove(&id);
+            write(&self.database.password_resets_required).remove(&id);
+            cascade_user_delete(&self.database, id);
+
+            info!("User deleted successfully: {}", id);
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "user.repo.verify_email", skip_all, level = "debug")]
+    async fn verify_email(&self, id: Uuid) -> Result<(), UserError> {
+        observe("user.repo.verify_email", async {
+            self.update_where(id, |user| user.is_verified = true)
+        })
+        .await
+    }
+
+    #[instrument(name = "user.repo.deactivate", skip_all, level = "debug")]
+    async fn deactivate(&self, id: Uuid) -> Result<(), UserError> {
+        observe("user.repo.deactivate", async {
+            self.update_where(id, |user| user.is_active = false)
+        })
+        .await
+    }
+
+    #[instrument(name = "user.repo.activate", skip_all, level = "debug")]
+    async fn activate(&self, id: Uuid) -> Result<(), UserError> {
+        observe("user.repo.activate", async {
+            self.update_where(id, |user| user.is_active = true)
+        })
+        .await
+    }
+
+    #[instrument(name = "user.repo.record_login", skip_all, level = "debug")]
+    async fn record_login(&self, id: Uuid, at: OffsetDateTime) -> Result<(), UserError> {
+        observe("user.repo.record_login", async {
+            self.check_rate_limit()?;
+            let mut users = write(&self.database.users);
+            let user = users.get_mut(&id).ok_or(UserError::NotFound)?;
+            user.last_login = Some(at);
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "user.repo.session_states", skip_all, level = "debug")]
+    async fn session_states(
+        &self,
+        ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, UserSessionState>, UserError> {
+        observe("user.repo.session_states", async {
+            self.check_rate_limit()?;
+            let users = read(&self.database.users);
+            let password_changes = read(&self.database.password_changes);
+            let password_resets_required = read(&self.database.password_resets_required);
+            Ok(ids
+                .iter()
+                .filter_map(|id| users.get(id))
+                .map(|user| {
+                    let state = UserSessionState {
+                        is_active: user.is_active,
+                        password_changed_at: password_changes.get(&user.id).copied(),
+                        password_reset_required: password_resets_required.contains(&user.id),
+                    };
+                    (user.id, state)
+                })
+                .collect())
+        })
+        .await
+    }
+
+    #[instrument(name = "user.repo.require_password_reset", skip_all, level = "debug")]
+    async fn require_password_reset(&self, id: Uuid) -> Result<bool, UserError> {
+        observe("user.repo.require_password_reset", async {
+            self.check_rate_limit()?;
+            let users = read(&self.database.users);
+            if !users.contains_key(&id) {
+                return Err(UserError::NotFound);
+            }
+            Ok(write(&self.database.password_resets_required).insert(id))
+        })
+        .await
+    }
+
+    #[instrument(name = "user.repo.password_history", skip_all, level = "debug")]
+    async fn password_history(&self, id: Uuid, limit: usize) -> Result<Vec<String>, UserError> {
+        observe("user.repo.password_history", async {
+            self.check_rate_limit()?;
+            Ok(read(&self.database.password_history)
+                .get(&id)
+                .map(|hashes| hashes.iter().take(limit).cloned().collect())
+                .unwrap_or_default())
+        })
+        .await
+    }
+
+    #[instrument(name = "user.repo.record_password_history", skip_all, level = "debug")]
+    async fn record_password_history(
+        &self,
+        id: Uuid,
+        password_hash: &str,
+        keep: usize,
+    ) -> Result<(), UserError> {
+        observe("user.repo.record_password_history", async {
+            self.check_rate_limit()?;
+            let users = read(&self.database.users);
+            if !users.contains_key(&id) {
+                return Err(UserError::NotFound);
+            }
+            let mut history = write(&self.database.password_history);
+            let hashes = history.entry(id).or_default();
+            hashes.insert(0, password_hash.to_string());
+            hashes.truncate(keep);
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// In-memory [`TenantRepository`]
+///
+/// Subdomains are unique, and a user can be a member of a tenant only once.
+/// Members must exist in the users table of the same database.
+pub struct InMemoryTenantRepository {
+    database: Arc<InMemoryDatabase>,
+    rate_limiter: DirectRateLimiter,
+}
+
+impl InMemoryTenantRepository {
+    /// Creates a repository on `database`, rate limited like the Postgres one
+    pub fn new(
+        database: Arc<InMemoryDatabase>,
+        config: &RepositoryConfig,
+    ) -> Result<Self, TenantError> {
+        let rate_limiter = rate_limiter(config).map_err(TenantError::ConfigError)?;
+        info!("InMemoryTenantRepository initialized successfully");
+        Ok(Self {
+            database,
+            rate_limiter,
+        })
+    }
+
+    fn check_rate_limit(&self) -> Result<(), TenantError> {
+        if self.rate_limiter.check().is_err() {
+            warn!("Rate limit exceeded");
+            return Err(TenantError::RateLimitExceeded);
+        }
+        Ok(())
+    }
+
+    /// Adds a membership, checking the user's active tenants against
+    /// `max_tenants_per_user` under the same lock
+    fn insert_membership(
+        &self,
+        tenant_id: Uuid,
+        user: CreateTenantUserDto,
+        max_tenants_per_user: Option<u32>,
+    ) -> Result<TenantUser, TenantError> {
+        self.check_rate_limit()?;
+        check_length(&user.tenant_role, 50).map_err(TenantError::DatabaseError)?;
+
+        let users = read(&self.database.users);
+        let tenants = read(&self.database.tenants);
+        let mut memberships = write(&self.database.tenant_users);
+        if !tenants.contains_key(&tenant_id) {
+            return Err(TenantError::NotFound);
+        }
+        if !users.contains_key(&user.user_id) {
+            return Err(TenantError::ValidationError("User does not exist".into()));
+        }
+        if memberships
+            .iter()
+            .any(|m| m.tenant_id == tenant_id && m.user_id == user.user_id)
+        {
+            return Err(TenantError::AlreadyExists);
+        }
+        let is_active = user.is_active.unwrap_or(true);
+        if let Some(limit) = max_tenants_per_user.filter(|_| is_active) {
+            let current = memberships
+                .iter()
+                .filter(|m| m.user_id == user.user_id && m.is_active)
+                .count() as u32;
+            if current >= limit {
+                return Err(TenantError::TenantsPerUserLimitExceeded { limit, current });
+            }
+        }
+
+        let now = OffsetDateTime::now_utc();
+        let membership = TenantUser {
+            tenant_id,
+            user_id: user.user_id,
+            tenant_role: user.tenant_role,
+            is_active,
+            created_at: now,
+            updated_at: now,
+        };
+        memberships.push(membership.clone());
+
+        info!(
+            "User added to tenant successfully: {} -> {}",
+            membership.user_id, tenant_id
+        );
+        Ok(membership)
+    }
+}
+
+fn check_tenant_columns(name: Option<&str>, subdomain: Option<&str>) -> Result<(), TenantError> {
+    name.map_or(Ok(()), |name| check_length(name, 255))
+        .and_then(|()| subdomain.map_or(Ok(()), |subdomain| check_length(subdomain, 100)))
+        .map_err(TenantError::DatabaseError)
+}
+
+#[async_trait]
+impl TenantRepository for InMemoryTenantRepository {
+    #[instrument(name = "tenant.repo.create_tenant", skip_all, level = "debug")]
+    async fn create_tenant(&self, tenant: CreateTenantDto) -> Result<Tenant, TenantError> {
+        observe("tenant.repo.create_tenant", async {
+            self.check_rate_limit()?;
+            check_tenant_columns(Some(&tenant.name), Some(&tenant.subdomain))?;
+
+            let mut tenants = write(&self.database.tenants);
+            if tenants.values().any(|t| t.subdomain == tenant.subdomain) {
+                return Err(TenantError::AlreadyExists);
+            }
+            let now = OffsetDateTime::now_utc();
+            let tenant = Tenant {
+                id: Uuid::new_v4(),
+                name: tenant.name,
+                subdomain: tenant.subdomain,
+                status: TenantStatus::Active,
+                created_at: now,
+                updated_at: now,
+                metadata: tenant.metadata.map(BoundedJson::into_inner),
+            };
+            tenants.insert(tenant.id, tenant.clone());
+
+            info!("Tenant created successfully: {}", tenant.id);
+            Ok(tenant)
+        })
+        .await
+    }
+
+    #[instrument(name = "tenant.repo.find_tenant_by_id", skip_all, level = "debug")]
+    async fn find_tenant_by_id(&self, id: Uuid) -> Result<Option<Tenant>, TenantError> {
+        observe("tenant.repo.find_tenant_by_id", async {
+            self.check_rate_limit()?;
+            Ok(read(&self.database.tenants).get(&id).cloned())
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "tenant.repo.find_tenant_by_subdomain",
+        skip_all,
+        level = "debug"
+    )]
+    async fn find_tenant_by_subdomain(
+        &self,
+        subdomain: &str,
+    ) -> Result<Option<Tenant>, TenantError> {
+        observe("tenant.repo.find_tenant_by_subdomain", async {
+            self.check_rate_limit()?;
+            Ok(read(&self.database.tenants)
+                .values()
+                .find(|t| t.subdomain == subdomain)
+                .cloned())
+        })
+        .await
+    }
+
+    #[instrument(name = "tenant.repo.find_tenants_by_ids", skip_all, level = "debug")]
+    async fn find_tenants_by_ids(&self, ids: &[Uuid]) -> Result<Vec<Tenant>, TenantError> {
+        observe("tenant.repo.find_tenants_by_ids", async {
+            self.check_rate_limit()?;
+            let tenants = read(&self.database.tenants);
+            let mut found: Vec<Tenant> = tenants
+                .values()
+                .filter(|t| ids.contains(&t.id))
+                .cloned()
+                .collect();
+            found.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+            Ok(found)
+        })
+        .await
+    }
+
+    #[instrument(name = "tenant.repo.update_tenant", skip_all, level = "debug")]
+    async fn update_tenant(
+        &self,
+        id: Uuid,
+        update: UpdateTenantDto,
+    ) -> Result<Tenant, TenantError> {
+        observe("tenant.repo.update_tenant", async {
+            self.check_rate_limit()?;
+            check_tenant_columns(update.name.as_deref(), update.subdomain.as_deref())?;
+
+            let mut tenants = write(&self.database.tenants);
+            if !tenants.contains_key(&id) {
+                return Err(TenantError::NotFound);
+            }
+            if let Some(subdomain) = &update.subdomain {
+                if tenants
+                    .values()
+                    .any(|t| t.id != id && &t.subdomain == subdomain)
+                {
+                    return Err(TenantError::AlreadyExists);
+                }
+            }
+
+            let tenant = tenants.get_mut(&id).ok_or(TenantError::NotFound)?;
+            if let Some(name) = update.name {
+                tenant.name = name;
+            }
+            if let Some(subdomain) = update.subdomain {
+                tenant.subdomain = subdomain;
+            }
+            if let Some(metadata) = update.metadata {
+                tenant.metadata = metadata.map(BoundedJson::into_inner);
+            }
+            tenant.updated_at = OffsetDateTime::now_utc();
+
+            info!("Tenant updated successfully: {}", id);
+            Ok(tenant.clone())
+        })
+        .await
+    }
+
+    #[instrument(name = "tenant.repo.set_tenant_status", skip_all, level = "debug")]
+    async fn set_tenant_status(
+        &self,
+        id: Uuid,
+        status: TenantStatus,
+        _actor_id: Option<Uuid>,
+    ) -> Result<Tenant, TenantError> {
+        observe("tenant.repo.set_tenant_status", async {
+            self.check_rate_limit()?;
+            let mut tenants = write(&self.database.tenants);
+            let tenant = tenants.get_mut(&id).ok_or(TenantError::NotFound)?;
+            tenant.status = status;
+            tenant.updated_at = OffsetDateTime::now_utc();
+
+            info!(
+                "Tenant status changed to {}: {}",
+                tenant.status.as_str(),
+                id
+            );
+            Ok(tenant.clone())
+        })
+        .await
+    }
+
+    #[instrument(name = "tenant.repo.delete_tenant", skip_all, level = "debug")]
+    async fn delete_tenant(&self, id: Uuid) -> Result<(), TenantError> {
+        observe("tenant.repo.delete_tenant", async {
+            self.check_rate_limit()?;
+            let mut tenants = write(&self.database.tenants);
+            tenants.remove(&id).ok_or(TenantError::NotFound)?;
+            write(&self.database.tenant_users).retain(|m| m.tenant_id != id);
+            write(&self.database.subscriptions).retain(|s| s.tenant_id != id);
+            write(&self.database.verification_codes).retain(|code| code.tenant_id != id);
+
+            info!("Tenant deleted successfully: {}", id);
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "tenant.repo.create_subscription", skip_all, level = "debug")]
+    async fn create_subscription(
+        &self,
+        tenant_id: Uuid,
+        subscription: CreateSubscriptionDto,
+    ) -> Result<TenantSubscription, TenantError> {
+        observe("tenant.repo.create_subscription", async {
+            self.check_rate_limit()?;
+            if let Some(payment_status) = &subscription.payment_status {
+                check_length(payment_status, 50).map_err(TenantError::DatabaseError)?;
+            }
+
+            let tenants = read(&self.database.tenants);
+            if !tenants.contains_key(&tenant_id) {
+                return Err(TenantError::NotFound);
+            }
+            let now = OffsetDateTime::now_utc();
+            let subscription = TenantSubscription {
+                id: Uuid::new_v4(),
+                tenant_id,
+                plan_type: subscription.plan_type,
+                starts_at: subscription.starts_at,
+                expires_at: subscription.expires_at,
+                is_active: subscription.is_active.unwrap_or(true),
+                payment_status: subscription.payment_status,
+                max_users: subscription.max_users,
+                features: Some(
+                    subscription
+                        .features
+                        .unwrap_or(serde_json::Value::Object(serde_json::Map::new())),
+                ),
+                created_at: now,
+                updated_at: now,
+            };
+            write(&self.database.subscriptions).push(subscription.clone());
+
+            info!(
+                "Subscription created successfully for tenant: {}",
+                tenant_id
+            );
+            Ok(subscription)
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "tenant.repo.get_active_subscription",
+        skip_all,
+        level = "debug"
+    )]
+    async fn get_active_subscription(
+        &self,
+        tenant_id: Uuid,
+    ) -> Result<Option<TenantSubscription>, TenantError> {
+        observe("tenant.repo.get_active_subscription", async {
+            self.check_rate_limit()?;
+            let now = OffsetDateTime::now_utc();
+            Ok(read(&self.database.subscriptions)
+                .iter()
+                .filter(|s| {
+                    s.tenant_id == tenant_id
+                        && s.is_active
+                        && s.expires_at.is_none_or(|expires_at| expires_at > now)
+                })
+                .max_by_key(|s| s.created_at)
+                .cloned())
+        })
+        .await
+    }
+
+    #[instrument(name = "tenant.repo.list_subscriptions", skip_all, level = "debug")]
+    async fn list_subscriptions(
+        &self,
+        tenant_id: Uuid,
+    ) -> Result<Vec<TenantSubscription>, TenantError> {
+        observe("tenant.repo.list_subscriptions", async {
+            self.check_rate_limit()?;
+            let mut subscriptions: Vec<_> = read(&self.database.subscriptions)
+                .iter()
+                .filter(|s| s.tenant_id == tenant_id)
+                .cloned()
+                .collect();
+            // Stable, so subscriptions created at the same instant keep their order
+            subscriptions.sort_by_key(|s| s.created_at);
+            Ok(subscriptions)
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "tenant.repo.find_subscription_by_id",
+        skip_all,
+        level = "debug"
+    )]
+    async fn find_subscription_by_id(
+        &self,
+        id: Uuid,
+    ) -> Result<Option<TenantSubscription>, TenantError> {
+        observe("tenant.repo.find_subscription_by_id", async {
+            self.check_rate_limit()?;
+            Ok(read(&self.database.subscriptions)
+                .iter()
+                .find(|s| s.id == id)
+                .cloned())
+        })
+        .await
+    }
+
+    #[instrument(name = "tenant.repo.update_subscription", skip_all, level = "debug")]
+    async fn update_subscription(
+        &self,
+        id: Uuid,
+        update: UpdateSubscriptionDto,
+    ) -> Result<TenantSubscription, TenantError> {
+        observe("tenant.repo.update_subscription", async {
+            self.check_rate_limit()?;
+            if let Some(payment_status) = &update.payment_status {
+                check_length(payment_status, 50).map_err(TenantError::DatabaseError)?;
+            }
+
+            let mut subscriptions = write(&self.database.subscriptions);
+            let subscription = subscriptions
+                .iter_mut()
+                .find(|s| s.id == id)
+                .ok_or(TenantError::NotFound)?;
+            if let Some(plan_type) = update.plan_type {
+                subscription.plan_type = plan_type;
+            }
+            if let Some(expires_at) = update.expires_at {
+                subscription.expires_at = Some(expires_at);
+            }
+            if let Some(is_active) = update.is_active {
+                subscription.is_active = is_active;
+            }
+            if let Some(payment_status) = update.payment_status {
+                subscription.payment_status = Some(payment_status);
+            }
+            if let Some(max_users) = update.max_users {
+                subscription.max_users = Some(max_users);
+            }
+            if let Some(features) = update.features {
+                subscription.features = Some(features);
+            }
+            subscription.updated_at = OffsetDateTime::now_utc();
+
+            info!("Subscription updated successfully: {}", id);
+            Ok(subscription.clone())
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "tenant.repo.record_subscription_change",
+        skip_all,
+        level = "debug"
+    )]
+    async fn record_subscription_change(
+        &self,
+        entry: &SubscriptionHistoryEntry,
+    ) -> Result<(), TenantError> {
+        observe("tenant.repo.record_subscription_change", async {
+            let mut history = write(&self.database.subscription_history);
+            if history.iter().any(|e| e.id == entry.id) {
+                return Err(TenantError::DatabaseError(unique_violation(
+                    "subscription_history_pkey",
+                )));
+            }
+            history.push(entry.clone());
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "tenant.repo.list_subscription_history",
+        skip_all,
+        level = "debug"
+    )]
+    async fn list_subscription_history(
+        &self,
+        tenant_id: Uuid,
+        limit: u32,
+        offset: u64,
+    ) -> Result<Vec<SubscriptionHistoryEntry>, TenantError> {
+        observe("tenant.repo.list_subscription_history", async {
+            let mut entries: Vec<SubscriptionHistoryEntry> =
+                read(&self.database.subscription_history)
+                    .iter()
+                    .filter(|entry| entry.tenant_id == tenant_id)
+                    .cloned()
+                    .collect();
+            entries.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.id.cmp(&a.id)));
+            Ok(entries
+                .into_iter()
+                .skip(usize::try_from(offset).unwrap_or(usize::MAX))
+                .take(limit as usize)
+                .collect())
+        })
+        .await
+    }
+
+    #[instrument(name = "tenant.repo.add_user_to_tenant", skip_all, level = "debug")]
+    async fn add_user_to_tenant(
+        &self,
+        tenant_id: Uuid,
+        user: CreateTenantUserDto,
+    ) -> Result<TenantUser, TenantError> {
+        observe("tenant.repo.add_user_to_tenant", async {
+            self.insert_membership(tenant_id, user, None)
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "tenant.repo.add_user_to_tenant_within_limit",
+        skip_all,
+        level = "debug"
+    )]
+    async fn add_user_to_tenant_within_limit(
+        &self,
+        tenant_id: Uuid,
+        user: CreateTenantUserDto,
+        max_tenants_per_user: u32,
+    ) -> Result<TenantUser, TenantError> {
+        observe("tenant.repo.add_user_to_tenant_within_limit", async {
+            self.insert_membership(tenant_id, user, Some(max_tenants_per_user))
+        })
+        .await
+    }
+
+    #[instrument(name = "tenant.repo.get_tenant_users", skip_all, level = "debug")]
+    async fn get_tenant_users(&self, tenant_id: Uuid) -> Result<Vec<TenantUser>, TenantError> {
+        observe("tenant.repo.get_tenant_users", async {
+            self.check_rate_limit()?;
+            Ok(read(&self.database.tenant_users)
+                .iter()
+                .filter(|m| m.tenant_id == tenant_id)
+                .cloned()
+                .collect())
+        })
+        .await
+    }
+
+    #[instrument(name = "tenant.repo.get_user_tenants", skip_all, level = "debug")]
+    async fn get_user_tenants(&self, user_id: Uuid) -> Result<Vec<TenantUser>, TenantError> {
+        observe("tenant.repo.get_user_tenants", async {
+            self.check_rate_limit()?;
+            Ok(read(&self.database.tenant_users)
+                .iter()
+                .filter(|m| m.user_id == user_id)
+                .cloned()
+                .collect())
+        })
+        .await
+    }
+
+    #[instrument(name = "tenant.repo.update_tenant_user", skip_all, level = "debug")]
+    async fn update_tenant_user(
+        &self,
+        tenant_id: Uuid,
+        user_id: Uuid,
+        update: UpdateTenantUserDto,
+    ) -> Result<TenantUser, TenantError> {
+        observe("tenant.repo.update_tenant_user", async {
+            self.check_rate_limit()?;
+            if let Some(tenant_role) = &update.tenant_role {
+                check_length(tenant_role, 50).map_err(TenantError::DatabaseError)?;
+            }
+
+            let mut memberships = write(&self.database.tenant_users);
+            let membership = memberships
+                .iter_mut()
+                .find(|m| m.tenant_id == tenant_id && m.user_id == user_id)
+                .ok_or(TenantError::NotFound)?;
+            if let Some(tenant_role) = update.tenant_role {
+                membership.tenant_role = tenant_role;
+            }
+            if let Some(is_active) = update.is_active {
+                membership.is_active = is_active;
+            }
+            membership.updated_at = OffsetDateTime::now_utc();
+            Ok(membership.clone())
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "tenant.repo.remove_user_from_tenant",
+        skip_all,
+        level = "debug"
+    )]
+    async fn remove_user_from_tenant(
+        &self,
+        tenant_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(), TenantError> {
+        observe("tenant.repo.remove_user_from_tenant", async {
+            self.check_rate_limit()?;
+            let mut memberships = write(&self.database.tenant_users);
+            let before = memberships.len();
+            memberships.retain(|m| !(m.tenant_id == tenant_id && m.user_id == user_id));
+            if memberships.len() == before {
+                return Err(TenantError::NotFound);
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "tenant.repo.count_active_tenant_users",
+        skip_all,
+        level = "debug"
+    )]
+    async fn count_active_tenant_users(&self, tenant_id: Uuid) -> Result<i64, TenantError> {
+        observe("tenant.repo.count_active_tenant_users", async {
+            self.check_rate_limit()?;
+            Ok(read(&self.database.tenant_users)
+                .iter()
+                .filter(|m| m.tenant_id == tenant_id && m.is_active)
+                .count() as i64)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::tenant::TenantPlanType;
+
+    fn users(database: &Arc<InMemoryDatabase>) -> InMemoryUserRepository {
+        InMemoryUserRepository::new(database.clone(), &RepositoryConfig::default())
+            .expect("Default config is valid")
+    }
+
+    #[tokio::test]
+    async fn test_user_email_is_unique() {
+        let database = InMemoryDatabase::new();
+        let repo = users(&database);
+        let user = User::new("unique@example.com".to_string(), "hash".to_string());
+        repo.create(&user).await.expect("User is created");
+
+        // A second account with the same email is rejected before inserting
+        let duplicate = User::new("unique@example.com".to_string(), "hash".to_string());
+        assert!(matches!(
+            repo.create(&duplicate).await,
+            Err(UserError::AlreadyExists)
+        ));
+
+        // The unique column compares case-sensitively
+        let other_case = User::new("Unique@example.com".to_string(), "hash".to_string());
+        repo.create(&other_case)
+            .await
+            .expect("Differently cased email is another account");
+
+        // Updates run into the unique constraint instead
+        let mut renamed = other_case.clone();
+        renamed.email = user.email.clone();
+        let error = repo.update(&renamed).await.expect_err("Email is taken");
+        assert!(matches!(
+            error,
+            UserError::DatabaseError(message) if message.contains("users_email_key")
+        ));
+
+        // Reusing an id violates the primary key
+        let mut same_id = User::new("other@example.com".to_string(), "hash".to_string());
+        same_id.id = user.id;
+        assert!(matches!(
+            repo.create(&same_id).await,
+            Err(UserError::DatabaseError(message)) if message.contains("users_pkey")
+        ));
+
+        let found = repo
+            .find_by_email("unique@example.com")
+            .await
+            .expect("Lookup succeeds")
+            .expect("User exists");
+        assert_eq!(found.id, user.id);
+        assert_eq!(found.display_name, found.email);
+        assert!(
+            repo.find_by_email("UNIQUE@example.com")
+                .await
+                .expect("Lookup succeeds")
+                .is_none()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_creates_admit_one_user() {
+        let database = InMemoryDatabase::new();
+        let repo = Arc::new(users(&database));
+
+        let attempts = (0..8).map(|_| {
+            let repo = repo.clone();
+            tokio::spawn(async move {
+                let user = User::new("race@example.com".to_string(), "hash".to_string());
+                repo.create(&user).await
+            })
+        });
+        let mut created = 0;
+        for attempt in attempts {
+            match attempt.await.expect("Task completes") {
+                Ok(()) => created += 1,
+                Err(error) => assert!(matches!(error, UserError::AlreadyExists)),
+            }
+        }
+        assert_eq!(created, 1);
+    }
+
+    #[tokio::test]
+    async fn test_user_columns_are_limited() {
+        let database = InMemoryDatabase::new();
+        let repo = users(&database);
+        let email = format!("{}@example.com", "a".repeat(250));
+        let user = User::new(email, "hash".to_string());
+        assert!(matches!(
+            repo.create(&user).await,
+            Err(UserError::DatabaseError(message)) if message.contains("varying(255)")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_deleting_user_cascades_to_memberships() {
+        let database = InMemoryDatabase::new();
+        let users = users(&database);
+        let tenants = InMemoryTenantRepository::new(database.clone(), &RepositoryConfig::default())
+            .expect("Default config is valid");
+        let user = User::new("member@example.com".to_string(), "hash".to_string());
+        users.create(&user).await.expect("User is created");
+        let tenant = tenants
+            .create_tenant(CreateTenantDto {
+                name: "Acme".to_string(),
+                subdomain: "acme".to_string(),
+                metadata: None,
+            })
+            .await
+            .expect("Tenant is created");
+        let membership = || CreateTenantUserDto {
+            user_id: user.id,
+            tenant_role: "MEMBER".to_string(),
+            is_active: None,
+        };
+        tenants
+            .add_user_to_tenant(tenant.id, membership())
+            .await
+            .expect("User joins tenant");
+        assert!(matches!(
+            tenants.add_user_to_tenant(tenant.id, membership()).await,
+            Err(TenantError::AlreadyExists)
+        ));
+        tenants
+            .create_subscription(
+                tenant.id,
+                CreateSubscriptionDto {
+                    plan_type: TenantPlanType::Basic,
+                    starts_at: OffsetDateTime::now_utc(),
+                    expires_at: None,
+                    is_active: None,
+                    payment_status: None,
+                    max_users: Some(5),
+                    features: None,
+                },
+            )
+            .await
+            .expect("Subscription is created");
+
+        users.delete(user.id).await.expect("User is deleted");
+        assert!(
+            tenants
+                .get_tenant_users(tenant.id)
+                .await
+                .expect("Lookup succeeds")
+                .is_empty()
+        );
+        assert!(
+            tenants
+                .get_active_subscription(tenant.id)
+                .await
+                .expect("Lookup succeeds")
+                .is_some()
+        );
+    }
+}
@@ -0,0 +1,723 @@
+//! In-memory [`SessionRepository`], see [`crate::repository::memory`]
+
+use async_trait::async_trait;
+use futures::stream::{self, BoxStream, StreamExt};
+use serde_json::{Map, Value, json};
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::repository::instrumentation::observe;
+use crate::repository::memory::{InMemoryDatabase, foreign_key_violation, read, write};
+use crate::session::types::{
+    DeviceFingerprint, FactorAction, ImpersonationAction, MfaStatus, SessionInvalidationReason,
+    SessionKind, UserAgentDetails,
+};
+use crate::session::{
+    ACTIVE_TENANT_METADATA_KEY, CLIENT_BINDING_METADATA_KEY, IMPERSONATION_REASON_METADATA_KEY,
+    LAST_AUTH_METADATA_KEY, MFA_VERIFIED_METADATA_KEY, Session, SessionError, SessionExportFilter,
+    SessionFilter, SessionRepository, SessionRepositoryConfig, SessionWithLocation,
+    string_to_ip_network,
+};
+
+/// A row of the session audit log
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionAuditEntry {
+    pub id: Uuid,
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+    pub action: String,
+    pub details: Value,
+    pub created_at: OffsetDateTime,
+}
+
+/// In-memory [`SessionRepository`]
+///
+/// Sessions other than guest sessions must belong to a user of the same
+/// database. Like the Postgres repository, updates only apply to valid
+/// sessions, IP addresses are stored in their network notation and expired
+/// sessions are cleaned up according to the [`SessionRepositoryConfig`].
+pub struct InMemorySessionRepository {
+    database: Arc<InMemoryDatabase>,
+    config: SessionRepositoryConfig,
+}
+
+impl InMemorySessionRepository {
+    pub fn new(database: Arc<InMemoryDatabase>) -> Self {
+        Self::with_config(database, SessionRepositoryConfig::default())
+    }
+
+    pub fn with_config(database: Arc<InMemoryDatabase>, config: SessionRepositoryConfig) -> Self {
+        Self { database, config }
+    }
+
+    /// Audit entries written so far, oldest first
+    pub fn audit_entries(&self) -> Vec<SessionAuditEntry> {
+        read(&self.database.session_audit_log).clone()
+    }
+
+    /// Applies `update` to a valid session, returning what it returns
+    fn with_valid_session<T, F>(&self, id: Uuid, update: F) -> Result<T, SessionError>
+    where
+        F: FnOnce(&mut Session) -> T,
+    {
+        let mut sessions = write(&self.database.sessions);
+        let session = sessions
+            .iter_mut()
+            .find(|s| s.id == id && s.is_valid)
+            .ok_or(SessionError::NotFound)?;
+        Ok(update(session))
+    }
+
+    fn invalidate_where<P>(&self, predicate: P, reason: SessionInvalidationReason) -> u64
+    where
+        P: Fn(&Session) -> bool,
+    {
+        let mut sessions = write(&self.database.sessions);
+        let mut count = 0;
+        for session in sessions.iter_mut().filter(|s| predicate(s)) {
+            session.is_valid = false;
+            session.invalidated_reason = Some(reason.clone());
+            count += 1;
+        }
+        count
+    }
+
+    fn audit(&self, session_id: Uuid, user_id: Uuid, action: &str, details: Value) {
+        write(&self.database.session_audit_log).push(SessionAuditEntry {
+            id: Uuid::new_v4(),
+            session_id,
+            user_id,
+            action: action.to_string(),
+            details,
+            created_at: OffsetDateTime::now_utc(),
+        });
+    }
+}
+
+/// Sets `key` in the metadata of `session`, creating the object if needed
+fn set_metadata(session: &mut Session, key: &str, value: Value) {
+    let mut metadata = match session.metadata.take() {
+        Some(Value::Object(map)) => map,
+        _ => Map::new(),
+    };
+    metadata.insert(key.to_string(), value);
+    session.metadata = Some(Value::Object(metadata));
+}
+
+/// Audit details without absent values, like `jsonb_strip_nulls`
+fn strip_nulls(details: Value) -> Value {
+    match details {
+        Value::Object(map) => {
+            Value::Object(map.into_iter().filter(|(_, v)| !v.is_null()).collect())
+        },
+        other => other,
+    }
+}
+
+/// The time `retention` before `now`, saturating at the earliest supported time
+fn retention_cutoff(now: OffsetDateTime, retention: std::time::Duration) -> OffsetDateTime {
+    time::Duration::try_from(retention)
+        .ok()
+        .and_then(|retention| now.checked_sub(retention))
+        .unwrap_or(time::PrimitiveDateTime::MIN.assume_utc())
+}
+
+fn matches_filter(session: &Session, filter: &SessionFilter) -> bool {
+    match filter {
+        SessionFilter::All => true,
+        SessionFilter::Active => session.is_valid,
+        SessionFilter::Inactive => !session.is_valid,
+    }
+}
+
+fn matches_export_filter(session: &Session, filter: &SessionExportFilter) -> bool {
+    session.tenant_id() == Some(filter.tenant_id)
+        && matches_filter(session, &filter.status)
+        && filter
+            .user_id
+            .is_none_or(|user_id| session.user_id == user_id)
+        && filter
+            .created_after
+            .is_none_or(|created_after| session.created_at >= created_after)
+}
+
+#[async_trait]
+impl SessionRepository for InMemorySessionRepository {
+    #[instrument(name = "session.repo.create_session", skip_all, level = "debug")]
+    async fn create_session(
+        &self,
+        user_id: Uuid,
+        token_hash: String,
+        expires_at: OffsetDateTime,
+        device_id: Option<String>,
+        device_fingerprint: Option<DeviceFingerprint>,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+        user_agent_details: Option<UserAgentDetails>,
+        region: Option<String>,
+        metadata: Option<Value>,
+    ) -> Result<Session, SessionError> {
+        observe("session.repo.create_session", async {
+            // Guest sessions are bound to no user
+            let kind = SessionKind::for_user(user_id);
+            let users = read(&self.database.users);
+            if kind == SessionKind::User && !users.contains_key(&user_id) {
+                return Err(SessionError::Database(foreign_key_violation(
+                    "sessions",
+                    "sessions_user_id_fkey",
+                )));
+            }
+
+            let now = OffsetDateTime::now_utc();
+            let session = Session {
+                id: Uuid::new_v4(),
+                user_id,
+                kind,
+                token_hash,
+                previous_token_hash: None,
+                token_rotation_at: None,
+                expires_at,
+                created_at: now,
+                last_activity_at: now,
+                last_activity_update_at: None,
+                ip_address: string_to_ip_network(ip_address).map(|ip| ip.to_string()),
+                user_agent,
+                user_agent_details,
+                region,
+                device_id,
+                device_fingerprint,
+                is_valid: true,
+                invalidated_reason: None,
+                metadata,
+                mfa_status: MfaStatus::None,
+            };
+            write(&self.database.sessions).push(session.clone());
+
+            tracing::info!(session_id = %session.id, user_id = %user_id, "Session created successfully");
+            Ok(session)
+        })
+        .await
+    }
+
+    #[instrument(name = "session.repo.get_session", skip_all, level = "debug")]
+    async fn get_session(&self, id: Uuid) -> Result<Option<Session>, SessionError> {
+        observe("session.repo.get_session", async {
+            Ok(read(&self.database.sessions)
+                .iter()
+                .find(|s| s.id == id)
+                .cloned())
+        })
+        .await
+    }
+
+    #[instrument(name = "session.repo.get_session_by_token", skip_all, level = "debug")]
+    async fn get_session_by_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<Session>, SessionError> {
+        observe("session.repo.get_session_by_token", async {
+            Ok(read(&self.database.sessions)
+                .iter()
+                .find(|s| {
+                    s.token_hash == token_hash
+                        || s.previous_token_hash.as_deref() == Some(token_hash)
+                })
+                .cloned())
+        })
+        .await
+    }
+
+    #[instrument(name = "session.repo.get_user_sessions", skip_all, level = "debug")]
+    async fn get_user_sessions(
+        &self,
+        user_id: Uuid,
+        filter: SessionFilter,
+    ) -> Result<Vec<Session>, SessionError> {
+        observe("session.repo.get_user_sessions", async {
+            let mut sessions: Vec<Session> = read(&self.database.sessions)
+                .iter()
+                .filter(|s| s.user_id == user_id && matches_filter(s, &filter))
+                .cloned()
+                .collect();
+            sessions.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+            Ok(sessions)
+        })
+        .await
+    }
+
+    /// Locations are not recorded in memory, so sessions are listed without one
+    #[instrument(
+        name = "session.repo.get_user_sessions_with_location",
+        skip_all,
+        level = "debug"
+    )]
+    async fn get_user_sessions_with_location(
+        &self,
+        user_id: Uuid,
+        filter: SessionFilter,
+        current_session_id: Option<Uuid>,
+    ) -> Result<Vec<SessionWithLocation>, SessionError> {
+        let sessions = self.get_user_sessions(user_id, filter).await?;
+        Ok(sessions
+            .into_iter()
+            .map(|session| SessionWithLocation {
+                is_current: Some(session.id) == current_session_id,
+                location: None,
+                session,
+            })
+            .collect())
+    }
+
+    #[instrument(
+        name = "session.repo.update_session_activity",
+        skip_all,
+        level = "debug"
+    )]
+    async fn update_session_activity(&self, id: Uuid) -> Result<(), SessionError> {
+        observe("session.repo.update_session_activity", async {
+            self.with_valid_session(id, |session| {
+                session.last_activity_at = OffsetDateTime::now_utc();
+            })
+        })
+        .await
+    }
+
+    #[instrument(name = "session.repo.invalidate_session", skip_all, level = "debug")]
+    async fn invalidate_session(
+        &self,
+        id: Uuid,
+        reason: SessionInvalidationReason,
+    ) -> Result<(), SessionError> {
+        observe("session.repo.invalidate_session", async {
+            self.with_valid_session(id, |session| {
+                session.is_valid = false;
+                session.invalidated_reason = Some(reason);
+            })
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "session.repo.invalidate_all_user_sessions",
+        skip_all,
+        level = "debug"
+    )]
+    async fn invalidate_all_user_sessions(
+        &self,
+        user_id: Uuid,
+        reason: SessionInvalidationReason,
+    ) -> Result<u64, SessionError> {
+        observe("session.repo.invalidate_all_user_sessions", async {
+            Ok(self.invalidate_where(|s| s.user_id == user_id && s.is_valid, reason))
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "session.repo.invalidate_sessions_by_filter",
+        skip_all,
+        level = "debug"
+    )]
+    async fn invalidate_sessions_by_filter(
+        &self,
+        filter: SessionFilter,
+        reason: SessionInvalidationReason,
+    ) -> Result<u64, SessionError> {
+        observe("session.repo.invalidate_sessions_by_filter", async {
+            Ok(self.invalidate_where(|s| matches_filter(s, &filter), reason))
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "session.repo.invalidate_sessions_by_ip",
+        skip_all,
+        level = "debug"
+    )]
+    async fn invalidate_sessions_by_ip(
+        &self,
+        ip_address: &str,
+        reason: SessionInvalidationReason,
+    ) -> Result<u64, SessionError> {
+        observe("session.repo.invalidate_sessions_by_ip", async {
+            let Some(ip_address) =
+                string_to_ip_network(Some(ip_address.to_string())).map(|ip| ip.to_string())
+            else {
+                return Ok(0);
+            };
+            Ok(self.invalidate_where(
+                |s| s.ip_address.as_deref() == Some(ip_address.as_str()) && s.is_valid,
+                reason,
+            ))
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "session.repo.invalidate_sessions_by_region",
+        skip_all,
+        level = "debug"
+    )]
+    async fn invalidate_sessions_by_region(
+        &self,
+        region: &str,
+        tenant_id: Option<Uuid>,
+        include_unknown: bool,
+        reason: SessionInvalidationReason,
+    ) -> Result<u64, SessionError> {
+        observe("session.repo.invalidate_sessions_by_region", async {
+            Ok(self.invalidate_where(
+                |s| {
+                    s.is_valid
+                        && s.in_region(region, include_unknown)
+                        && tenant_id.is_none_or(|tenant_id| s.tenant_id() == Some(tenant_id))
+                },
+                reason,
+            ))
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "session.repo.invalidate_tenant_sessions",
+        skip_all,
+        level = "debug"
+    )]
+    async fn invalidate_tenant_sessions(
+        &self,
+        tenant_id: Uuid,
+        reason: SessionInvalidationReason,
+    ) -> Result<u64, SessionError> {
+        observe("session.repo.invalidate_tenant_sessions", async {
+            Ok(self.invalidate_where(|s| s.is_valid && s.tenant_id() == Some(tenant_id), reason))
+        })
+        .await
+    }
+
+    #[instrument(name = "session.repo.rotate_session_token", skip_all, level = "debug")]
+    async fn rotate_session_token(
+        &self,
+        id: Uuid,
+        new_token_hash: String,
+    ) -> Result<(), SessionError> {
+        observe("session.repo.rotate_session_token", async {
+            self.with_valid_session(id, |session| {
+                session.previous_token_hash =
+                    Some(std::mem::replace(&mut session.token_hash, new_token_hash));
+                session.token_rotation_at = Some(OffsetDateTime::now_utc());
+            })
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "session.repo.cleanup_expired_sessions",
+        skip_all,
+        level = "debug"
+    )]
+    async fn cleanup_expired_sessions(&self) -> Result<u64, SessionError> {
+        observe("session.repo.cleanup_expired_sessions", async {
+            let now = OffsetDateTime::now_utc();
+            let invalidated = self.invalidate_where(
+                |s| s.is_valid && s.expires_at < now,
+                SessionInvalidationReason::TokenExpired,
+            );
+
+            let invalid_cutoff = retention_cutoff(now, self.config.invalid_session_retention);
+            let mut sessions = write(&self.database.sessions);
+            let before = sessions.len();
+            sessions.retain(|s| s.is_valid || s.last_activity_at >= invalid_cutoff);
+            let deleted = (before - sessions.len()) as u64;
+
+            // Guest sessions are deleted once abandoned, whether still valid or not
+            let guest_cutoff = retention_cutoff(now, self.config.guest_session_retention);
+            let before = sessions.len();
+            sessions.retain(|s| !s.is_guest() || s.last_activity_at >= guest_cutoff);
+            let deleted_guests = (before - sessions.len()) as u64;
+
+            // Audit entries of deleted sessions go with them, older ones expire
+            let audit_cutoff = retention_cutoff(now, self.config.audit_log_retention);
+            write(&self.database.session_audit_log).retain(|entry| {
+                entry.created_at >= audit_cutoff
+                    && sessions.iter().any(|s| s.id == entry.session_id)
+            });
+
+            tracing::info!(
+                cleaned_sessions = invalidated + deleted + deleted_guests,
+                "Session cleanup completed successfully"
+            );
+            Ok(invalidated + deleted + deleted_guests)
+        })
+        .await
+    }
+
+    #[instrument(name = "session.repo.update_mfa_status", skip_all, level = "debug")]
+    async fn update_mfa_status(&self, id: Uuid, status: MfaStatus) -> Result<(), SessionError> {
+        observe("session.repo.update_mfa_status", async {
+            self.with_valid_session(id, |session| session.mfa_status = status)
+        })
+        .await
+    }
+
+    #[instrument(name = "session.repo.set_active_tenant", skip_all, level = "debug")]
+    async fn set_active_tenant(&self, id: Uuid, tenant_id: Uuid) -> Result<(), SessionError> {
+        observe("session.repo.set_active_tenant", async {
+            let (user_id, previous, impersonated_by) = self.with_valid_session(id, |session| {
+                let previous = session
+                    .metadata
+                    .as_ref()
+                    .and_then(|metadata| metadata.get(ACTIVE_TENANT_METADATA_KEY))
+                    .cloned();
+                let impersonated_by = session.impersonated_by();
+                set_metadata(
+                    session,
+                    ACTIVE_TENANT_METADATA_KEY,
+                    tenant_id.to_string().into(),
+                );
+                (session.user_id, previous, impersonated_by)
+            })?;
+
+            self.audit(
+                id,
+                user_id,
+                "TENANT_SWITCHED",
+                strip_nulls(json!({
+                    "from_tenant_id": previous,
+                    "to_tenant_id": tenant_id.to_string(),
+                    "impersonated_by": impersonated_by.map(|admin_id| admin_id.to_string()),
+                })),
+            );
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "session.repo.record_authentication", skip_all, level = "debug")]
+    async fn record_authentication(
+        &self,
+        id: Uuid,
+        at: OffsetDateTime,
+    ) -> Result<(), SessionError> {
+        observe("session.repo.record_authentication", async {
+            self.with_valid_session(id, |session| {
+                set_metadata(session, LAST_AUTH_METADATA_KEY, at.unix_timestamp().into());
+            })
+        })
+        .await
+    }
+
+    #[instrument(name = "session.repo.record_client_binding", skip_all, level = "debug")]
+    async fn record_client_binding(
+        &self,
+        id: Uuid,
+        binding_hash: &str,
+    ) -> Result<(), SessionError> {
+        observe("session.repo.record_client_binding", async {
+            self.with_valid_session(id, |session| {
+                set_metadata(session, CLIENT_BINDING_METADATA_KEY, binding_hash.into());
+            })
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "session.repo.merge_session_metadata",
+        skip_all,
+        level = "debug"
+    )]
+    async fn merge_session_metadata(&self, id: Uuid, patch: Value) -> Result<(), SessionError> {
+        observe("session.repo.merge_session_metadata", async {
+            self.with_valid_session(id, |session| {
+                if let Value::Object(patch) = patch {
+                    for (key, value) in patch {
+                        set_metadata(session, &key, value);
+                    }
+                }
+            })
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "session.repo.record_mfa_verification",
+        skip_all,
+        level = "debug"
+    )]
+    async fn record_mfa_verification(
+        &self,
+        id: Uuid,
+        at: OffsetDateTime,
+    ) -> Result<(), SessionError> {
+        observe("session.repo.record_mfa_verification", async {
+            let (user_id, mfa_status, impersonated_by) =
+                self.with_valid_session(id, |session| {
+                    set_metadata(
+                        session,
+                        MFA_VERIFIED_METADATA_KEY,
+                        at.unix_timestamp().into(),
+                    );
+                    set_metadata(session, LAST_AUTH_METADATA_KEY, at.unix_timestamp().into());
+                    (
+                        session.user_id,
+                        session.mfa_status.to_string(),
+                        session.impersonated_by(),
+                    )
+                })?;
+
+            self.audit(
+                id,
+                user_id,
+                "MFA_VERIFIED",
+                strip_nulls(json!({
+                    "mfa_status": mfa_status,
+                    "impersonated_by": impersonated_by.map(|admin_id| admin_id.to_string()),
+                })),
+            );
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "session.repo.record_impersonation_event",
+        skip_all,
+        level = "debug"
+    )]
+    async fn record_impersonation_event(
+        &self,
+        id: Uuid,
+        action: ImpersonationAction,
+    ) -> Result<(), SessionError> {
+        observe("session.repo.record_impersonation_event", async {
+            let session = self.get_session(id).await?.ok_or(SessionError::NotFound)?;
+            let admin_id = session.impersonated_by().ok_or(SessionError::NotFound)?;
+            let reason = session
+                .metadata
+                .as_ref()
+                .and_then(|metadata| metadata.get(IMPERSONATION_REASON_METADATA_KEY))
+                .cloned()
+                .unwrap_or(Value::Null);
+
+            for user_id in [session.user_id, admin_id] {
+                self.audit(
+                    id,
+                    user_id,
+                    action.as_str(),
+                    json!({
+                        "impersonated_by": admin_id.to_string(),
+                        "target_user_id": session.user_id.to_string(),
+                        "reason": reason,
+                    }),
+                );
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "session.repo.record_factor_event", skip_all, level = "debug")]
+    async fn record_factor_event(
+        &self,
+        id: Uuid,
+        action: FactorAction,
+        factor: &str,
+    ) -> Result<(), SessionError> {
+        observe("session.repo.record_factor_event", async {
+            let (user_id, impersonated_by) = self
+                .with_valid_session(id, |session| (session.user_id, session.impersonated_by()))?;
+
+            self.audit(
+                id,
+                user_id,
+                action.as_str(),
+                strip_nulls(json!({
+                    "factor": factor,
+                    "impersonated_by": impersonated_by.map(|admin_id| admin_id.to_string()),
+                })),
+            );
+            Ok(())
+        })
+        .await
+    }
+
+    fn stream_sessions(
+        &self,
+        filter: SessionExportFilter,
+    ) -> BoxStream<'static, Result<Session, SessionError>> {
+        // Pages are read lazily with the same keyset as the Postgres cursor,
+        // so sessions created while streaming are picked up in order
+        let database = Arc::clone(&self.database);
+        let page_size = filter.page_size.max(1) as usize;
+        let start = (None::<(OffsetDateTime, Uuid)>, filter.limit);
+
+        stream::unfold(start, move |(cursor, remaining)| {
+            let database = Arc::clone(&database);
+            let filter = filter.clone();
+            async move {
+                if remaining == 0 {
+                    return None;
+                }
+
+                let limit = page_size.min(usize::try_from(remaining).unwrap_or(usize::MAX));
+                let mut page: Vec<Session> = read(&database.sessions)
+                    .iter()
+                    .filter(|s| matches_export_filter(s, &filter))
+                    .filter(|s| cursor.is_none_or(|cursor| (s.created_at, s.id) > cursor))
+                    .cloned()
+                    .collect();
+                page.sort_by_key(|s| (s.created_at, s.id));
+                page.truncate(limit);
+
+                let last = page.last()?;
+                let next_cursor = (last.created_at, last.id);
+                let remaining = remaining - page.len() as u64;
+                let page = stream::iter(page.into_iter().map(Ok));
+                Some((page, (Some(next_cursor), remaining)))
+            }
+        })
+        .flatten()
+        .boxed()
+    }
+
+    #[instrument(name = "session.repo.valid_sessions_after", skip_all, level = "debug")]
+    async fn valid_sessions_after(
+        &self,
+        after: Option<Uuid>,
+        limit: u32,
+    ) -> Result<Vec<Session>, SessionError> {
+        observe("session.repo.valid_sessions_after", async {
+            let mut page: Vec<Session> = read(&self.database.sessions)
+                .iter()
+                .filter(|s| s.is_valid && after.is_none_or(|after| s.id > after))
+                .cloned()
+                .collect();
+            page.sort_by_key(|s| s.id);
+            page.truncate(limit as usize);
+            Ok(page)
+        })
+        .await
+    }
+
+    #[instrument(name = "session.repo.reconciliation_cursor", skip_all, level = "debug")]
+    async fn reconciliation_cursor(&self) -> Result<Option<Uuid>, SessionError> {
+        observe("session.repo.reconciliation_cursor", async {
+            Ok(*read(&self.database.session_reconciliation_cursor))
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "session.repo.save_reconciliation_cursor",
+        skip_all,
+        level = "debug"
+    )]
+    async fn save_reconciliation_cursor(&self, cursor: Option<Uuid>) -> Result<(), SessionError> {
+        observe("session.repo.save_reconciliation_cursor", async {
+            *write(&self.database.session_reconciliation_cursor) = cursor;
+            Ok(())
+        })
+        .await
+    }
+}
@@ -0,0 +1,429 @@
+//! In-memory [`VerificationCodeRepository`], see [`crate::repository::memory`]
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tracing::{instrument, trace};
+use uuid::Uuid;
+
+use crate::models::{
+    ExpiredCodes, TenantId, UserId, VerificationCode, VerificationStats, VerificationStatus,
+    VerificationType,
+};
+use crate::repository::instrumentation::observe;
+use crate::repository::memory::{
+    InMemoryDatabase, check_length, foreign_key_violation, read, unique_violation, write,
+};
+use crate::repository::tenant_aware::TenantAwareContext;
+use crate::repository::verification_repository::VerificationCodeRepository;
+use acci_core::error::{Error, Result};
+
+/// In-memory [`VerificationCodeRepository`]
+///
+/// Codes must belong to a user and a tenant of the same database and are
+/// scoped to their tenant, like in the Postgres repository. The tenant-aware
+/// context is not needed without row level security.
+pub struct InMemoryVerificationCodeRepository {
+    database: Arc<InMemoryDatabase>,
+}
+
+impl InMemoryVerificationCodeRepository {
+    pub fn new(database: Arc<InMemoryDatabase>) -> Self {
+        Self { database }
+    }
+
+    /// Codes of `user_id` and `verification_type` within `tenant_id`
+    fn codes_of(
+        code: &VerificationCode,
+        user_id: UserId,
+        verification_type: VerificationType,
+        tenant_id: TenantId,
+    ) -> bool {
+        code.tenant_id == tenant_id
+            && code.user_id == user_id
+            && code.verification_type == verification_type
+    }
+}
+
+fn not_found() -> Error {
+    Error::Validation("Verification code not found".to_string())
+}
+
+#[async_trait]
+impl VerificationCodeRepository for InMemoryVerificationCodeRepository {
+    #[instrument(name = "verification.repo.save", skip_all, level = "debug")]
+    async fn save(&self, code: &VerificationCode, _context: &dyn TenantAwareContext) -> Result<()> {
+        observe("verification.repo.save", async {
+            check_length(&code.code, 32).map_err(|e| Error::Database(sqlx::Error::Protocol(e)))?;
+
+            let users = read(&self.database.users);
+            let tenants = read(&self.database.tenants);
+            let mut codes = write(&self.database.verification_codes);
+            if !tenants.contains_key(&code.tenant_id) {
+                return Err(Error::Database(foreign_key_violation(
+                    "verification_codes",
+                    "verification_codes_tenant_id_fkey",
+                )));
+            }
+            if !users.contains_key(&code.user_id) {
+                return Err(Error::Database(foreign_key_violation(
+                    "verification_codes",
+                    "verification_codes_user_id_fkey",
+                )));
+            }
+            if codes.iter().any(|stored| stored.id == code.id) {
+                return Err(Error::Database(sqlx::Error::Protocol(unique_violation(
+                    "verification_codes_pkey",
+                ))));
+            }
+            codes.push(code.clone());
+
+            trace!("Saved verification code with ID: {}", code.id);
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "verification.repo.get_by_id", skip_all, level = "debug")]
+    async fn get_by_id(
+        &self,
+        id: Uuid,
+        tenant_id: TenantId,
+        _context: &dyn TenantAwareContext,
+    ) -> Result<Option<VerificationCode>> {
+        observe("verification.repo.get_by_id", async {
+            Ok(read(&self.database.verification_codes)
+                .iter()
+                .find(|code| code.id == id && code.tenant_id == tenant_id)
+                .cloned())
+        })
+        .await
+    }
+
+    #[instrument(name = "verification.repo.get_by_code", skip_all, level = "debug")]
+    async fn get_by_code(
+        &self,
+        code: &str,
+        user_id: UserId,
+        verification_type: VerificationType,
+        tenant_id: TenantId,
+        _context: &dyn TenantAwareContext,
+    ) -> Result<Option<VerificationCode>> {
+        observe("verification.repo.get_by_code", async {
+            Ok(read(&self.database.verification_codes)
+                .iter()
+                .find(|stored| {
+                    stored.code == code
+                        && Self::codes_of(stored, user_id, verification_type, tenant_id)
+                })
+                .cloned())
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "verification.repo.get_pending_by_user",
+        skip_all,
+        level = "debug"
+    )]
+    async fn get_pending_by_user(
+        &self,
+        user_id: UserId,
+        verification_type: VerificationType,
+        tenant_id: TenantId,
+        _context: &dyn TenantAwareContext,
+    ) -> Result<Vec<VerificationCode>> {
+        observe("verification.repo.get_pending_by_user", async {
+            Ok(read(&self.database.verification_codes)
+                .iter()
+                .filter(|code| {
+                    code.status == VerificationStatus::Pending
+                        && Self::codes_of(code, user_id, verification_type, tenant_id)
+                })
+                .cloned()
+                .collect())
+        })
+        .await
+    }
+
+    #[instrument(name = "verification.repo.update", skip_all, level = "debug")]
+    async fn update(
+        &self,
+        code: &VerificationCode,
+        _context: &dyn TenantAwareContext,
+    ) -> Result<()> {
+        observe("verification.repo.update", async {
+            check_length(&code.code, 32).map_err(|e| Error::Database(sqlx::Error::Protocol(e)))?;
+
+            let mut codes = write(&self.database.verification_codes);
+            let stored = codes
+                .iter_mut()
+                .find(|stored| stored.id == code.id && stored.tenant_id == code.tenant_id)
+                .ok_or_else(not_found)?;
+            stored.code = code.code.clone();
+            stored.expires_at = code.expires_at;
+            stored.status = code.status;
+            stored.attempts = code.attempts;
+
+            trace!("Updated verification code with ID: {}", code.id);
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "verification.repo.delete", skip_all, level = "debug")]
+    async fn delete(
+        &self,
+        id: Uuid,
+        tenant_id: TenantId,
+        _context: &dyn TenantAwareContext,
+    ) -> Result<()> {
+        observe("verification.repo.delete", async {
+            let mut codes = write(&self.database.verification_codes);
+            let before = codes.len();
+            codes.retain(|code| !(code.id == id && code.tenant_id == tenant_id));
+            if codes.len() == before {
+                return Err(not_found());
+            }
+            write(&self.database.verification_delivery_channels).remove(&id);
+
+            trace!("Deleted verification code with ID: {}", id);
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "verification.repo.delete_expired", skip_all, level = "debug")]
+    async fn delete_expired(
+        &self,
+        before: OffsetDateTime,
+        tenant_id: TenantId,
+        _context: &dyn TenantAwareContext,
+    ) -> Result<u64> {
+        observe("verification.repo.delete_expired", async {
+            let mut codes = write(&self.database.verification_codes);
+            let count = codes.len();
+            codes.retain(|code| !(code.tenant_id == tenant_id && code.expires_at < before));
+            let deleted = (count - codes.len()) as u64;
+
+            trace!("Deleted {} expired verification codes", deleted);
+            Ok(deleted)
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "verification.repo.invalidate_pending",
+        skip_all,
+        level = "debug"
+    )]
+    async fn invalidate_pending(
+        &self,
+        user_id: UserId,
+        verification_type: VerificationType,
+        tenant_id: TenantId,
+        _context: &dyn TenantAwareContext,
+    ) -> Result<u64> {
+        observe("verification.repo.invalidate_pending", async {
+            let mut codes = write(&self.database.verification_codes);
+            let mut invalidated = 0;
+            for code in codes.iter_mut().filter(|code| {
+                code.status == VerificationStatus::Pending
+                    && Self::codes_of(code, user_id, verification_type, tenant_id)
+            }) {
+                code.status = VerificationStatus::Invalidated;
+                invalidated += 1;
+            }
+
+            trace!("Invalidated {} pending verification codes", invalidated);
+            Ok(invalidated)
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "verification.repo.count_recent_attempts",
+        skip_all,
+        level = "debug"
+    )]
+    async fn count_recent_attempts(
+        &self,
+        user_id: UserId,
+        verification_type: VerificationType,
+        since: OffsetDateTime,
+        tenant_id: TenantId,
+        _context: &dyn TenantAwareContext,
+    ) -> Result<u64> {
+        observe("verification.repo.count_recent_attempts", async {
+            Ok(read(&self.database.verification_codes)
+                .iter()
+                .filter(|code| {
+                    code.created_at > since
+                        && Self::codes_of(code, user_id, verification_type, tenant_id)
+                })
+                .count() as u64)
+        })
+        .await
+    }
+
+    #[instrument(name = "verification.repo.consume_verified", skip_all, level = "debug")]
+    async fn consume_verified(
+        &self,
+        id: Uuid,
+        tenant_id: TenantId,
+        _context: &dyn TenantAwareContext,
+    ) -> Result<bool> {
+        observe("verification.repo.consume_verified", async {
+            let mut codes = write(&self.database.verification_codes);
+            match codes.iter_mut().find(|code| {
+                code.id == id
+                    && code.tenant_id == tenant_id
+                    && code.status == VerificationStatus::Verified
+            }) {
+                Some(code) => {
+                    code.status = VerificationStatus::Consumed;
+                    Ok(true)
+                },
+                None => Ok(false),
+            }
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "verification.repo.record_delivery_channel",
+        skip_all,
+        level = "debug"
+    )]
+    async fn record_delivery_channel(
+        &self,
+        id: Uuid,
+        tenant_id: TenantId,
+        channel: VerificationType,
+        _context: &dyn TenantAwareContext,
+    ) -> Result<()> {
+        observe("verification.repo.record_delivery_channel", async {
+            let codes = read(&self.database.verification_codes);
+            if !codes
+                .iter()
+                .any(|code| code.id == id && code.tenant_id == tenant_id)
+            {
+                return Err(not_found());
+            }
+            write(&self.database.verification_delivery_channels).insert(id, channel);
+
+            trace!("Recorded delivery channel {:?} for code {}", channel, id);
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "verification.repo.delivery_channel", skip_all, level = "debug")]
+    async fn delivery_channel(
+        &self,
+        id: Uuid,
+        tenant_id: TenantId,
+        _context: &dyn TenantAwareContext,
+    ) -> Result<Option<VerificationType>> {
+        observe("verification.repo.delivery_channel", async {
+            let codes = read(&self.database.verification_codes);
+            // Codes removed by a cascade keep their entry until the next cleanup
+            if !codes
+                .iter()
+                .any(|code| code.id == id && code.tenant_id == tenant_id)
+            {
+                return Ok(None);
+            }
+            Ok(read(&self.database.verification_delivery_channels)
+                .get(&id)
+                .copied())
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "verification.repo.expire_all_pending",
+        skip_all,
+        level = "debug"
+    )]
+    async fn expire_all_pending(&self, now: OffsetDateTime) -> Result<Vec<ExpiredCodes>> {
+        observe("verification.repo.expire_all_pending", async {
+            let mut codes = write(&self.database.verification_codes);
+            let mut expired: HashMap<TenantId, u64> = HashMap::new();
+            for code in codes
+                .iter_mut()
+                .filter(|code| code.status == VerificationStatus::Pending && code.expires_at < now)
+            {
+                code.status = VerificationStatus::Expired;
+                *expired.entry(code.tenant_id).or_default() += 1;
+            }
+
+            Ok(expired
+                .into_iter()
+                .map(|(tenant_id, count)| ExpiredCodes { tenant_id, count })
+                .collect())
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "verification.repo.cleanup_all_expired",
+        skip_all,
+        level = "debug"
+    )]
+    async fn cleanup_all_expired(&self, before: OffsetDateTime) -> Result<u64> {
+        observe("verification.repo.cleanup_all_expired", async {
+            let mut codes = write(&self.database.verification_codes);
+            let count = codes.len();
+            codes.retain(|code| code.expires_at >= before);
+            let deleted = (count - codes.len()) as u64;
+            write(&self.database.verification_delivery_channels)
+                .retain(|id, _| codes.iter().any(|code| code.id == *id));
+
+            trace!(
+                "Deleted {} expired verification codes across all tenants",
+                deleted
+            );
+            Ok(deleted)
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "verification.repo.verification_stats",
+        skip_all,
+        level = "debug"
+    )]
+    async fn verification_stats(
+        &self,
+        tenant_id: TenantId,
+        since: OffsetDateTime,
+        _context: &dyn TenantAwareContext,
+    ) -> Result<VerificationStats> {
+        observe("verification.repo.verification_stats", async {
+            let now = OffsetDateTime::now_utc();
+            let codes = read(&self.database.verification_codes);
+            let mut stats = VerificationStats::default();
+            for code in codes
+                .iter()
+                .filter(|code| code.tenant_id == tenant_id && code.created_at >= since)
+            {
+                stats.issued += 1;
+                // Pending codes past their expiry count as expired even before the
+                // cleanup job has marked them
+                match code.status {
+                    VerificationStatus::Verified | VerificationStatus::Consumed => {
+                        stats.verified += 1
+                    },
+                    VerificationStatus::Expired => stats.expired += 1,
+                    VerificationStatus::Pending if code.expires_at < now => stats.expired += 1,
+                    VerificationStatus::Pending => stats.pending += 1,
+                    VerificationStatus::Invalidated => stats.invalidated += 1,
+                }
+            }
+            Ok(stats)
+        })
+        .await
+    }
+}
@@ -1,23 +1,72 @@
+pub mod account_deletion_repository;
+pub mod api_key_repository;
+pub mod delivery_repository;
+pub mod factory;
+pub mod identity_repository;
+pub mod instrumentation;
+pub mod login_attempt_repository;
+#[cfg(any(test, feature = "in-memory"))]
+pub mod memory;
+#[cfg(any(test, feature = "in-memory"))]
+pub mod memory_session;
+#[cfg(any(test, feature = "in-memory"))]
+pub mod memory_verification;
+pub mod outbox_repository;
+pub mod platform_stats_repository;
 pub mod postgres;
+pub mod postgres_account_deletion;
+pub mod postgres_api_key;
+pub mod postgres_delivery;
+pub mod postgres_identity;
+pub mod postgres_login_attempt;
+pub mod postgres_outbox;
+pub mod postgres_platform_stats;
+pub mod postgres_timeline;
 pub mod postgres_totp;
 pub mod postgres_verification;
 #[cfg(feature = "enable_webauthn")]
 pub mod postgres_webauthn;
 pub mod tenant_aware;
+pub mod timeline_repository;
+pub mod timeout;
 pub mod totp_repository;
 pub mod verification_repository;
 #[cfg(feature = "enable_webauthn")]
 pub mod webauthn_repository;
 
+pub use account_deletion_repository::AccountDeletionRepository;
+pub use api_key_repository::ApiKeyRepository;
+pub use delivery_repository::MessageDeliveryRepository;
+pub use factory::{Repositories, RepositoryFactory};
+pub use identity_repository::ExternalIdentityRepository;
+pub use login_attempt_repository::LoginAttemptRepository;
+#[cfg(any(test, feature = "in-memory"))]
+pub use memory::{InMemoryDatabase, InMemoryTenantRepository, InMemoryUserRepository};
+#[cfg(any(test, feature = "in-memory"))]
+pub use memory_session::{InMemorySessionRepository, SessionAuditEntry};
+#[cfg(any(test, feature = "in-memory"))]
+pub use memory_verification::InMemoryVerificationCodeRepository;
+pub use outbox_repository::MessageOutboxRepository;
+pub use platform_stats_repository::PlatformStatsRepository;
 pub use postgres::{
-    AuditEvent, PostgresTenantRepository, PostgresUserRepository, RepositoryConfig,
-    TenantAuditEvent,
+    AuditEvent, PostgresTenantRepository, PostgresUserRepository, RepositoryBackend,
+    RepositoryConfig, TenantAuditEvent,
 };
+pub use postgres_account_deletion::PostgresAccountDeletionRepository;
+pub use postgres_api_key::PostgresApiKeyRepository;
+pub use postgres_delivery::PostgresMessageDeliveryRepository;
+pub use postgres_identity::PostgresExternalIdentityRepository;
+pub use postgres_login_attempt::PostgresLoginAttemptRepository;
+pub use postgres_outbox::PostgresMessageOutboxRepository;
+pub use postgres_platform_stats::PostgresPlatformStatsRepository;
+pub use postgres_timeline::PostgresTimelineRepository;
 pub use postgres_totp::PostgresTotpRepository;
 pub use postgres_verification::PostgresVerificationCodeRepository;
 #[cfg(feature = "enable_webauthn")]
 pub use postgres_webauthn::PostgresWebAuthnRepository;
 pub use tenant_aware::{RepositoryError, TenantAwareContext, TenantAwareRepository};
+pub use timeline_repository::TimelineRepository;
+pub use timeout::{OperationTimedOut, OperationTimeouts};
 pub use totp_repository::TotpSecretRepository;
 pub use verification_repository::VerificationCodeRepository;
 #[cfg(feature = "enable_webauthn")]
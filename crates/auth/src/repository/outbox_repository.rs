@@ -0,0 +1,50 @@
+use async_trait::async_trait;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::models::{OutboxMessage, VerificationType};
+use acci_core::error::Result;
+
+/// Repository backing the durable message outbox
+#[async_trait]
+pub trait MessageOutboxRepository: Send + Sync {
+    /// Persist a new pending message
+    async fn enqueue(&self, message: &OutboxMessage) -> Result<()>;
+
+    /// Claim up to `limit` due messages of the given type in enqueue order.
+    ///
+    /// Claimed messages have their attempt counter incremented and are leased
+    /// until `lease_until`, so other dispatchers skip them. A message whose
+    /// lease lapses without being marked sent, retried, or failed (for example
+    /// because the dispatcher crashed) becomes due again.
+    async fn claim_due(
+        &self,
+        message_type: VerificationType,
+        now: OffsetDateTime,
+        lease_until: OffsetDateTime,
+        limit: u32,
+    ) -> Result<Vec<OutboxMessage>>;
+
+    /// Mark a claimed message as delivered
+    async fn mark_sent(
+        &self,
+        id: Uuid,
+        provider_message_id: &str,
+        now: OffsetDateTime,
+    ) -> Result<()>;
+
+    /// Release a claimed message for another attempt at `next_attempt_at`
+    async fn schedule_retry(
+        &self,
+        id: Uuid,
+        error: &str,
+        next_attempt_at: OffsetDateTime,
+        now: OffsetDateTime,
+    ) -> Result<()>;
+
+    /// Mark a claimed message as permanently failed
+    async fn mark_failed(&self, id: Uuid, error: &str, now: OffsetDateTime) -> Result<()>;
+
+    /// List permanently failed messages, most recent first
+    async fn list_failed(&self, limit: u32) -> Result<Vec<OutboxMessage>>;
+}
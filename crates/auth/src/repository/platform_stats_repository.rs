@@ -0,0 +1,151 @@
+use crate::models::login_attempt::LoginAttemptStats;
+use crate::models::platform_stats::{
+    RegionSessionCount, TenantCounts, TenantSessionCount, UserSignupCounts,
+};
+use crate::repository::RepositoryError;
+use async_trait::async_trait;
+use time::OffsetDateTime;
+
+/// Repository interface for the platform-wide aggregates of the admin overview
+///
+/// Every method is a single aggregate query, so a failing one does not affect
+/// the others.
+#[async_trait]
+pub trait PlatformStatsRepository: Send + Sync + 'static {
+    /// Count all tenants and the active ones
+    async fn tenant_counts(&self) -> Result<TenantCounts, RepositoryError>;
+
+    /// Count the users created within the 7 and 30 days before `now`
+    async fn user_signups(&self, now: OffsetDateTime) -> Result<UserSignupCounts, RepositoryError>;
+
+    /// Count the sessions that are valid and not expired at `now`
+    async fn active_sessions(&self, now: OffsetDateTime) -> Result<u64, RepositoryError>;
+
+    /// Count the sessions that are valid and not expired at `now` by region, most first
+    async fn active_sessions_by_region(
+        &self,
+        now: OffsetDateTime,
+    ) -> Result<Vec<RegionSessionCount>, RepositoryError>;
+
+    /// Count the login attempts of all tenants made at or after `since` by outcome
+    async fn login_stats(
+        &self,
+        since: OffsetDateTime,
+    ) -> Result<LoginAttemptStats, RepositoryError>;
+
+    /// The `limit` tenants with the most active sessions at `now`, most first
+    async fn top_tenants_by_sessions(
+        &self,
+        now: OffsetDateTime,
+        limit: u32,
+    ) -> Result<Vec<TenantSessionCount>, RepositoryError>;
+}
+
+/// In-memory [`PlatformStatsRepository`] for tests
+///
+/// Shared by the unit tests in this crate and by downstream test crates (via
+/// the `test-support` feature).
+#[cfg(any(test, feature = "test-support"))]
+pub mod mock {
+    use super::*;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Aggregates returned by [`MockPlatformStatsRepository`]
+    #[derive(Debug, Clone, Default)]
+    pub struct MockPlatformStats {
+        pub tenants: TenantCounts,
+        pub signups: UserSignupCounts,
+        pub active_sessions: u64,
+        /// Active sessions by region, most first
+        pub regions: Vec<RegionSessionCount>,
+        pub logins: LoginAttemptStats,
+        /// Tenants by active sessions, most first
+        pub top_tenants: Vec<TenantSessionCount>,
+    }
+
+    /// Platform stats repository for tests returning fixed aggregates
+    #[derive(Default)]
+    pub struct MockPlatformStatsRepository {
+        stats: Mutex<MockPlatformStats>,
+        queries: AtomicUsize,
+    }
+
+    impl MockPlatformStatsRepository {
+        /// Creates a mock returning `stats`
+        pub fn new(stats: MockPlatformStats) -> Self {
+            Self {
+                stats: Mutex::new(stats),
+                queries: AtomicUsize::new(0),
+            }
+        }
+
+        /// Replaces the returned aggregates
+        pub fn set_stats(&self, stats: MockPlatformStats) {
+            *self
+                .stats
+                .lock()
+                .expect("mock platform stats lock poisoned") = stats;
+        }
+
+        /// Number of queries answered so far
+        pub fn queries(&self) -> usize {
+            self.queries.load(Ordering::SeqCst)
+        }
+
+        fn query<T>(&self, f: impl FnOnce(&MockPlatformStats) -> T) -> Result<T, RepositoryError> {
+            self.queries.fetch_add(1, Ordering::SeqCst);
+            Ok(f(&self
+                .stats
+                .lock()
+                .expect("mock platform stats lock poisoned")))
+        }
+    }
+
+    #[async_trait]
+    impl PlatformStatsRepository for MockPlatformStatsRepository {
+        async fn tenant_counts(&self) -> Result<TenantCounts, RepositoryError> {
+            self.query(|stats| stats.tenants)
+        }
+
+        async fn user_signups(
+            &self,
+            _now: OffsetDateTime,
+        ) -> Result<UserSignupCounts, RepositoryError> {
+            self.query(|stats| stats.signups)
+        }
+
+        async fn active_sessions(&self, _now: OffsetDateTime) -> Result<u64, RepositoryError> {
+            self.query(|stats| stats.active_sessions)
+        }
+
+        async fn active_sessions_by_region(
+            &self,
+            _now: OffsetDateTime,
+        ) -> Result<Vec<RegionSessionCount>, RepositoryError> {
+            self.query(|stats| stats.regions.clone())
+        }
+
+        async fn login_stats(
+            &self,
+            _since: OffsetDateTime,
+        ) -> Result<LoginAttemptStats, RepositoryError> {
+            self.query(|stats| stats.logins)
+        }
+
+        async fn top_tenants_by_sessions(
+            &self,
+            _now: OffsetDateTime,
+            limit: u32,
+        ) -> Result<Vec<TenantSessionCount>, RepositoryError> {
+            self.query(|stats| {
+                stats
+                    .top_tenants
+                    .iter()
+                    .take(limit as usize)
+                    .cloned()
+                    .collect()
+            })
+        }
+    }
+}
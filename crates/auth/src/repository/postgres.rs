@@ -1,11 +1,18 @@
 use crate::models::{
     tenant::{
-        CreateSubscriptionDto, CreateTenantDto, CreateTenantUserDto, Tenant, TenantRepository,
+        CreateSubscriptionDto, CreateTenantDto, CreateTenantUserDto, SECURITY_POLICY_METADATA_KEY,
+        SubscriptionHistoryEntry, Tenant, TenantPlanType, TenantRepository, TenantStatus,
         TenantSubscription, TenantUser, UpdateSubscriptionDto, UpdateTenantDto,
         UpdateTenantUserDto,
     },
-    user::{User, UserError, UserRepository},
+    user::{User, UserError, UserRepository, UserSessionState},
 };
+use crate::repository::instrumentation::observe_within;
+use crate::repository::timeout::OperationTimeouts;
+use crate::services::event_export::{EventExporter, SecurityEvent};
+use crate::services::scim::SCIM_TOKEN_METADATA_KEY;
+use crate::utils::bounded_json::{BoundedJson, bound_stored};
+use crate::utils::password::NO_PASSWORD_HASH;
 use async_trait::async_trait;
 use governor::{
     Quota, RateLimiter,
@@ -14,31 +21,136 @@ use governor::{
     state::{InMemoryState, NotKeyed},
 };
 use serde::{Deserialize, Serialize};
-use sqlx::{PgPool, postgres::PgPoolOptions};
-use std::{num::NonZeroU32, sync::Arc, time::Duration};
+use sqlx::{
+    PgPool, Row,
+    postgres::{PgPoolOptions, PgRow},
+};
+use std::{collections::HashMap, num::NonZeroU32, sync::Arc, time::Duration};
 use time::OffsetDateTime;
 use tracing::{debug, error, info, instrument, warn};
 use uuid::Uuid;
 
 use crate::models::tenant::TenantError;
 
+fn map_subscription_row(row: PgRow) -> Result<TenantSubscription, sqlx::Error> {
+    let plan_type: String = row.try_get("plan_type")?;
+    Ok(TenantSubscription {
+        id: row.try_get("id")?,
+        tenant_id: row.try_get("tenant_id")?,
+        plan_type: TenantPlanType::from(plan_type.as_str()),
+        starts_at: row.try_get("starts_at")?,
+        expires_at: row.try_get("expires_at")?,
+        is_active: row.try_get("is_active")?,
+        payment_status: row.try_get("payment_status")?,
+        max_users: row.try_get("max_users")?,
+        features: row.try_get("features")?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+fn map_subscription_history_row(row: PgRow) -> Result<SubscriptionHistoryEntry, sqlx::Error> {
+    let change_type: String = row.try_get("change_type")?;
+    let old_plan_type: Option<String> = row.try_get("old_plan_type")?;
+    let new_plan_type: String = row.try_get("new_plan_type")?;
+    let plan_change: Option<String> = row.try_get("plan_change")?;
+    Ok(SubscriptionHistoryEntry {
+        id: row.try_get("id")?,
+        tenant_id: row.try_get("tenant_id")?,
+        subscription_id: row.try_get("subscription_id")?,
+        change_type: change_type
+            .parse()
+            .map_err(|e: TenantError| sqlx::Error::Decode(e.to_string().into()))?,
+        old_plan_type: old_plan_type.as_deref().map(TenantPlanType::from),
+        new_plan_type: TenantPlanType::from(new_plan_type.as_str()),
+        plan_change: plan_change
+            .as_deref()
+            .map(str::parse)
+            .transpose()
+            .map_err(|e: TenantError| sqlx::Error::Decode(e.to_string().into()))?,
+        old_max_users: row.try_get("old_max_users")?,
+        new_max_users: row.try_get("new_max_users")?,
+        old_expires_at: row.try_get("old_expires_at")?,
+        new_expires_at: row.try_get("new_expires_at")?,
+        actor_id: row.try_get("actor_id")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+fn map_tenant_row(row: PgRow) -> Result<Tenant, sqlx::Error> {
+    Ok(bound_tenant_metadata(Tenant {
+        id: row.try_get("id")?,
+        name: row.try_get("name")?,
+        subdomain: row.try_get("subdomain")?,
+        status: TenantStatus::from_columns(
+            row.try_get("status")?,
+            row.try_get("status_reason")?,
+            row.try_get("status_changed_at")?,
+        )
+        .map_err(|e| sqlx::Error::Decode(e.to_string().into()))?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+        metadata: row.try_get("metadata")?,
+    }))
+}
+
+/// Tenant metadata keys managed by the services, kept first when truncating
+const MANAGED_TENANT_METADATA_KEYS: &[&str] =
+    &[SECURITY_POLICY_METADATA_KEY, SCIM_TOKEN_METADATA_KEY];
+
+/// Truncate metadata stored before the metadata limits were enforced
+fn bound_tenant_metadata(tenant: Tenant) -> Tenant {
+    let metadata = tenant.metadata.and_then(|metadata| {
+        bound_stored("tenants", tenant.id, metadata, MANAGED_TENANT_METADATA_KEYS)
+    });
+    Tenant { metadata, ..tenant }
+}
+
+fn map_tenant_user_row(row: PgRow) -> Result<TenantUser, sqlx::Error> {
+    Ok(TenantUser {
+        tenant_id: row.try_get("tenant_id")?,
+        user_id: row.try_get("user_id")?,
+        tenant_role: row.try_get("tenant_role")?,
+        is_active: row.try_get("is_active")?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+/// Storage backend of the repositories, see [`RepositoryFactory`](super::RepositoryFactory)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepositoryBackend {
+    #[default]
+    Postgres,
+    /// Process-local storage; requires the `in-memory` feature
+    InMemory,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RepositoryConfig {
+    #[serde(default)]
+    pub backend: RepositoryBackend,
     pub database_url: String,
     pub max_connections: u32,
     pub connect_timeout: Duration,
     pub rate_limit_burst: u32,
     pub rate_limit_replenish_ms: u64,
+    /// Time budgets of the repository operations
+    #[serde(default)]
+    pub timeouts: OperationTimeouts,
 }
 
 impl Default for RepositoryConfig {
     fn default() -> Self {
         Self {
+            backend: RepositoryBackend::default(),
             database_url: "postgres://localhost/auth".to_string(),
             max_connections: 5,
             connect_timeout: Duration::from_secs(3),
             rate_limit_burst: 50,
             rate_limit_replenish_ms: 1000,
+            timeouts: OperationTimeouts::default(),
         }
     }
 }
@@ -65,17 +177,23 @@ pub struct TenantAuditEvent {
 pub struct PostgresUserRepository {
     pool: PgPool,
     rate_limiter: Arc<RateLimiter<NotKeyed, InMemoryState, DefaultClock, NoOpMiddleware>>,
+    event_exporter: Option<Arc<EventExporter>>,
+    timeouts: OperationTimeouts,
 }
 
 pub struct PostgresTenantRepository {
     pool: PgPool,
     rate_limiter: Arc<RateLimiter<NotKeyed, InMemoryState, DefaultClock, NoOpMiddleware>>,
+    event_exporter: Option<Arc<EventExporter>>,
+    timeouts: OperationTimeouts,
 }
 
 impl PostgresUserRepository {
     #[instrument(skip(config))]
     pub async fn new(config: RepositoryConfig) -> Result<Self, UserError> {
-        let pool = PgPoolOptions::new()
+        let pool = config
+            .timeouts
+            .apply_to(PgPoolOptions::new())
             .max_connections(config.max_connections)
             .acquire_timeout(config.connect_timeout)
             .connect(&config.database_url)
@@ -92,7 +210,18 @@ impl PostgresUserRepository {
         let rate_limiter = Arc::new(RateLimiter::direct(quota));
 
         info!("PostgresUserRepository initialized successfully");
-        Ok(Self { pool, rate_limiter })
+        Ok(Self {
+            pool,
+            rate_limiter,
+            event_exporter: None,
+            timeouts: config.timeouts,
+        })
+    }
+
+    /// Export every audit event written by this repository to a SIEM
+    pub fn with_event_exporter(mut self, exporter: Arc<EventExporter>) -> Self {
+        self.event_exporter = Some(exporter);
+        self
     }
 
     #[instrument(skip(self, event))]
@@ -104,10 +233,10 @@ impl PostgresUserRepository {
             "#,
         )
         .bind(event.user_id)
-        .bind(event.action)
-        .bind(event.details)
-        .bind(event.ip_address)
-        .bind(event.user_agent)
+        .bind(&event.action)
+        .bind(&event.details)
+        .bind(&event.ip_address)
+        .bind(&event.user_agent)
         .execute(&self.pool)
         .await
         .map_err(|e| {
@@ -116,6 +245,9 @@ impl PostgresUserRepository {
         })?;
 
         debug!("Audit event logged successfully");
+        if let Some(exporter) = &self.event_exporter {
+            exporter.export(SecurityEvent::from(&event));
+        }
         Ok(())
     }
 
@@ -132,7 +264,9 @@ impl PostgresUserRepository {
 impl PostgresTenantRepository {
     #[instrument(skip(config))]
     pub async fn new(config: RepositoryConfig) -> Result<Self, TenantError> {
-        let pool = PgPoolOptions::new()
+        let pool = config
+            .timeouts
+            .apply_to(PgPoolOptions::new())
             .max_connections(config.max_connections)
             .acquire_timeout(config.connect_timeout)
             .connect(&config.database_url)
@@ -149,7 +283,18 @@ impl PostgresTenantRepository {
         let rate_limiter = Arc::new(RateLimiter::direct(quota));
 
         info!("PostgresTenantRepository initialized successfully");
-        Ok(Self { pool, rate_limiter })
+        Ok(Self {
+            pool,
+            rate_limiter,
+            event_exporter: None,
+            timeouts: config.timeouts,
+        })
+    }
+
+    /// Export every tenant audit event written by this repository to a SIEM
+    pub fn with_event_exporter(mut self, exporter: Arc<EventExporter>) -> Self {
+        self.event_exporter = Some(exporter);
+        self
     }
 
     #[instrument(skip(self, event))]
@@ -157,15 +302,15 @@ impl PostgresTenantRepository {
         sqlx::query(
             r#"
             INSERT INTO tenant_audit_log (tenant_id, user_id, action, details, ip_address, user_agent)
-            VALUES ($1, $2, $3, $4, $5, $6)
+            VALUES ($1, $2, $3, $4, $5::INET, $6)
             "#,
         )
         .bind(event.tenant_id)
         .bind(event.user_id)
-        .bind(event.action)
-        .bind(event.details)
-        .bind(event.ip_address)
-        .bind(event.user_agent)
+        .bind(&event.action)
+        .bind(&event.details)
+        .bind(&event.ip_address)
+        .bind(&event.user_agent)
         .execute(&self.pool)
         .await
         .map_err(|e| {
@@ -174,6 +319,9 @@ impl PostgresTenantRepository {
         })?;
 
         debug!("Tenant audit event logged successfully");
+        if let Some(exporter) = &self.event_exporter {
+            exporter.export(SecurityEvent::from(&event));
+        }
         Ok(())
     }
 
@@ -189,347 +337,511 @@ impl PostgresTenantRepository {
 
 #[async_trait]
 impl TenantRepository for PostgresTenantRepository {
-    #[instrument(skip(self, tenant))]
+    #[instrument(name = "tenant.repo.create_tenant", skip(self, tenant))]
     async fn create_tenant(&self, tenant: CreateTenantDto) -> Result<Tenant, TenantError> {
-        self.check_rate_limit().await?;
+        observe_within("tenant.repo.create_tenant", self.timeouts.write, async {
+            self.check_rate_limit().await?;
 
-        // Check if subdomain already exists
-        let existing = sqlx::query!(
-            r#"SELECT id FROM tenants WHERE subdomain = $1"#,
-            tenant.subdomain
-        )
-        .fetch_optional(&self.pool)
-        .await
-        .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+            // Check if subdomain already exists
+            let existing = sqlx::query!(
+                r#"SELECT id FROM tenants WHERE subdomain = $1"#,
+                tenant.subdomain
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
 
-        if existing.is_some() {
-            return Err(TenantError::AlreadyExists);
-        }
+            if existing.is_some() {
+                return Err(TenantError::AlreadyExists);
+            }
 
-        let now = OffsetDateTime::now_utc();
-        let id = Uuid::new_v4();
+            let now = OffsetDateTime::now_utc();
+            let id = Uuid::new_v4();
 
-        // Create tenant
-        let tenant = sqlx::query_as!(
-            Tenant,
-            r#"
+            // Create tenant
+            let tenant = sqlx::query(
+                r#"
             INSERT INTO tenants (
-                id, name, subdomain, is_active, created_at, updated_at, metadata
+                id, name, subdomain, status, created_at, updated_at, metadata
             )
-            VALUES ($1, $2, $3, true, $4, $5, $6)
-            RETURNING id, name, subdomain, is_active, created_at, updated_at, metadata
+            VALUES ($1, $2, $3, 'ACTIVE', $4, $5, $6)
+            RETURNING id, name, subdomain, status, status_reason, status_changed_at,
+                created_at, updated_at, metadata
             "#,
-            id,
-            tenant.name,
-            tenant.subdomain,
-            now,
-            now,
-            tenant
-                .metadata
-                .unwrap_or(serde_json::Value::Object(serde_json::Map::new()))
-        )
-        .fetch_one(&self.pool)
-        .await
-        .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
-
-        // Log audit event
-        self.log_tenant_audit(TenantAuditEvent {
-            tenant_id: tenant.id,
-            user_id: None,
-            action: "TENANT_CREATION".to_string(),
-            details: {
-                let mut map = serde_json::Map::new();
-                map.insert(
-                    "name".to_string(),
-                    serde_json::Value::String(tenant.name.clone()),
-                );
-                map.insert(
-                    "subdomain".to_string(),
-                    serde_json::Value::String(tenant.subdomain.clone()),
-                );
-                serde_json::Value::Object(map)
-            },
-            ip_address: None,
-            user_agent: None,
-        })
-        .await?;
+            )
+            .bind(id)
+            .bind(&tenant.name)
+            .bind(&tenant.subdomain)
+            .bind(now)
+            .bind(now)
+            .bind(tenant.metadata.map_or_else(
+                || serde_json::Value::Object(serde_json::Map::new()),
+                BoundedJson::into_inner,
+            ))
+            .fetch_one(&self.pool)
+            .await
+            .and_then(map_tenant_row)
+            .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+
+            // Log audit event
+            self.log_tenant_audit(TenantAuditEvent {
+                tenant_id: tenant.id,
+                user_id: None,
+                action: "TENANT_CREATION".to_string(),
+                details: {
+                    let mut map = serde_json::Map::new();
+                    map.insert(
+                        "name".to_string(),
+                        serde_json::Value::String(tenant.name.clone()),
+                    );
+                    map.insert(
+                        "subdomain".to_string(),
+                        serde_json::Value::String(tenant.subdomain.clone()),
+                    );
+                    serde_json::Value::Object(map)
+                },
+                ip_address: None,
+                user_agent: None,
+            })
+            .await?;
 
-        info!("Tenant created successfully: {}", tenant.id);
-        Ok(tenant)
+            info!("Tenant created successfully: {}", tenant.id);
+            Ok(tenant)
+        })
+        .await
     }
 
-    #[instrument(skip(self))]
+    #[instrument(name = "tenant.repo.find_tenant_by_id", skip(self))]
     async fn find_tenant_by_id(&self, id: Uuid) -> Result<Option<Tenant>, TenantError> {
-        self.check_rate_limit().await?;
+        observe_within("tenant.repo.find_tenant_by_id", self.timeouts.read, async {
+            self.check_rate_limit().await?;
 
-        let tenant = sqlx::query_as!(
-            Tenant,
-            r#"
-            SELECT id, name, subdomain, is_active, created_at, updated_at, metadata
+            let tenant = sqlx::query(
+                r#"
+            SELECT id, name, subdomain, status, status_reason, status_changed_at,
+                created_at, updated_at, metadata
             FROM tenants
             WHERE id = $1
             "#,
-            id
-        )
-        .fetch_optional(&self.pool)
-        .await
-        .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+            )
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .and_then(|row| row.map(map_tenant_row).transpose())
+            .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
 
-        debug!("Tenant lookup by ID complete: {}", id);
-        Ok(tenant)
+            debug!("Tenant lookup by ID complete: {}", id);
+            Ok(tenant)
+        })
+        .await
     }
 
-    #[instrument(skip(self))]
+    #[instrument(name = "tenant.repo.find_tenant_by_subdomain", skip(self))]
     async fn find_tenant_by_subdomain(
         &self,
         subdomain: &str,
     ) -> Result<Option<Tenant>, TenantError> {
-        self.check_rate_limit().await?;
-
-        let tenant = sqlx::query_as!(
-            Tenant,
-            r#"
-            SELECT id, name, subdomain, is_active, created_at, updated_at, metadata
+        observe_within(
+            "tenant.repo.find_tenant_by_subdomain",
+            self.timeouts.read,
+            async {
+                self.check_rate_limit().await?;
+
+                let tenant = sqlx::query(
+                    r#"
+            SELECT id, name, subdomain, status, status_reason, status_changed_at,
+                created_at, updated_at, metadata
             FROM tenants
             WHERE subdomain = $1
             "#,
-            subdomain
+                )
+                .bind(subdomain)
+                .fetch_optional(&self.pool)
+                .await
+                .and_then(|row| row.map(map_tenant_row).transpose())
+                .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+
+                debug!("Tenant lookup by subdomain complete: {}", subdomain);
+                Ok(tenant)
+            },
         )
-        .fetch_optional(&self.pool)
         .await
-        .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+    }
+
+    #[instrument(name = "tenant.repo.find_tenants_by_ids", skip(self, ids), fields(count = ids.len()))]
+    async fn find_tenants_by_ids(&self, ids: &[Uuid]) -> Result<Vec<Tenant>, TenantError> {
+        observe_within(
+            "tenant.repo.find_tenants_by_ids",
+            self.timeouts.read,
+            async {
+                if ids.is_empty() {
+                    return Ok(Vec::new());
+                }
+                self.check_rate_limit().await?;
+
+                let rows = sqlx::query(
+                    r#"
+                SELECT id, name, subdomain, status, status_reason, status_changed_at,
+                    created_at, updated_at, metadata
+                FROM tenants
+                WHERE id = ANY($1)
+                ORDER BY name, id
+                "#,
+                )
+                .bind(ids)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
 
-        debug!("Tenant lookup by subdomain complete: {}", subdomain);
-        Ok(tenant)
+                rows.into_iter()
+                    .map(map_tenant_row)
+                    .collect::<Result<_, sqlx::Error>>()
+                    .map_err(|e| TenantError::DatabaseError(e.to_string()))
+            },
+        )
+        .await
     }
 
-    #[instrument(skip(self, tenant))]
+    #[instrument(name = "tenant.repo.update_tenant", skip(self, tenant))]
     async fn update_tenant(
         &self,
         id: Uuid,
         tenant: UpdateTenantDto,
     ) -> Result<Tenant, TenantError> {
-        self.check_rate_limit().await?;
-
-        // Check if tenant exists
-        let existing = self.find_tenant_by_id(id).await?;
-        if existing.is_none() {
-            return Err(TenantError::NotFound);
-        }
-        let existing = existing.expect("Expected existing entity");
-
-        // Check if new subdomain is already taken (if changing)
-        if let Some(subdomain) = &tenant.subdomain {
-            if subdomain != &existing.subdomain {
-                let subdomain_exists = sqlx::query!(
-                    r#"SELECT id FROM tenants WHERE subdomain = $1 AND id != $2"#,
-                    subdomain,
-                    id
-                )
-                .fetch_optional(&self.pool)
-                .await
-                .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+        observe_within("tenant.repo.update_tenant", self.timeouts.write, async {
+            self.check_rate_limit().await?;
 
-                if subdomain_exists.is_some() {
-                    return Err(TenantError::AlreadyExists);
+            // Check if tenant exists
+            let existing = self.find_tenant_by_id(id).await?;
+            if existing.is_none() {
+                return Err(TenantError::NotFound);
+            }
+            let existing = existing.expect("Expected existing entity");
+
+            // Check if new subdomain is already taken (if changing)
+            if let Some(subdomain) = &tenant.subdomain {
+                if subdomain != &existing.subdomain {
+                    let subdomain_exists = sqlx::query!(
+                        r#"SELECT id FROM tenants WHERE subdomain = $1 AND id != $2"#,
+                        subdomain,
+                        id
+                    )
+                    .fetch_optional(&self.pool)
+                    .await
+                    .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+
+                    if subdomain_exists.is_some() {
+                        return Err(TenantError::AlreadyExists);
+                    }
                 }
             }
-        }
 
-        let now = OffsetDateTime::now_utc();
+            let now = OffsetDateTime::now_utc();
+
+            // Update tenant; metadata is only touched when present, so null clears it
+            let updated_tenant = sqlx::query(
+                r#"
+                UPDATE tenants
+                SET
+                    name = COALESCE($1, name),
+                    subdomain = COALESCE($2, subdomain),
+                    updated_at = $3,
+                    metadata = CASE WHEN $4 THEN $5 ELSE metadata END
+                WHERE id = $6
+                RETURNING id, name, subdomain, status, status_reason, status_changed_at,
+                    created_at, updated_at, metadata
+                "#,
+            )
+            .bind(&tenant.name)
+            .bind(&tenant.subdomain)
+            .bind(now)
+            .bind(tenant.metadata.is_some())
+            .bind(
+                tenant
+                    .metadata
+                    .clone()
+                    .flatten()
+                    .map(BoundedJson::into_inner),
+            )
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+            .and_then(map_tenant_row)
+            .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
 
-        // Update tenant
-        let updated_tenant = sqlx::query_as!(
-            Tenant,
-            r#"
-            UPDATE tenants
-            SET
-                name = COALESCE($1, name),
-                subdomain = COALESCE($2, subdomain),
-                is_active = COALESCE($3, is_active),
-                updated_at = $4,
-                metadata = COALESCE($5, metadata)
-            WHERE id = $6
-            RETURNING id, name, subdomain, is_active, created_at, updated_at, metadata
-            "#,
-            tenant.name,
-            tenant.subdomain,
-            tenant.is_active,
-            now,
-            tenant.metadata,
-            id
-        )
-        .fetch_one(&self.pool)
-        .await
-        .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
-
-        // Log audit event
-        self.log_tenant_audit(TenantAuditEvent {
-            tenant_id: updated_tenant.id,
-            user_id: None,
-            action: "TENANT_UPDATE".to_string(),
-            details: {
-                let mut map = serde_json::Map::new();
-                if tenant.name.is_some() {
-                    map.insert(
-                        "name".to_string(),
-                        serde_json::Value::String(updated_tenant.name.clone()),
-                    );
-                }
-                if tenant.subdomain.is_some() {
-                    map.insert(
-                        "subdomain".to_string(),
-                        serde_json::Value::String(updated_tenant.subdomain.clone()),
-                    );
-                }
-                if tenant.is_active.is_some() {
-                    map.insert(
-                        "is_active".to_string(),
-                        serde_json::Value::Bool(updated_tenant.is_active),
-                    );
-                }
-                serde_json::Value::Object(map)
-            },
-            ip_address: None,
-            user_agent: None,
-        })
-        .await?;
+            // Log audit event
+            self.log_tenant_audit(TenantAuditEvent {
+                tenant_id: updated_tenant.id,
+                user_id: None,
+                action: "TENANT_UPDATE".to_string(),
+                details: {
+                    let mut map = serde_json::Map::new();
+                    if tenant.name.is_some() {
+                        map.insert(
+                            "name".to_string(),
+                            serde_json::Value::String(updated_tenant.name.clone()),
+                        );
+                    }
+                    if tenant.subdomain.is_some() {
+                        map.insert(
+                            "subdomain".to_string(),
+                            serde_json::Value::String(updated_tenant.subdomain.clone()),
+                        );
+                    }
+                    if let Some(metadata) = &tenant.metadata {
+                        map.insert(
+                            "metadata_cleared".to_string(),
+                            serde_json::Value::Bool(metadata.is_none()),
+                        );
+                    }
+                    serde_json::Value::Object(map)
+                },
+                ip_address: None,
+                user_agent: None,
+            })
+            .await?;
 
-        info!("Tenant updated successfully: {}", updated_tenant.id);
-        Ok(updated_tenant)
+            info!("Tenant updated successfully: {}", updated_tenant.id);
+            Ok(updated_tenant)
+        })
+        .await
     }
 
-    #[instrument(skip(self))]
-    async fn delete_tenant(&self, id: Uuid) -> Result<(), TenantError> {
-        self.check_rate_limit().await?;
-
-        // Start a transaction
-        let mut tx = self
-            .pool
-            .begin()
-            .await
-            .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+    #[instrument(name = "tenant.repo.set_tenant_status", skip(self, status))]
+    async fn set_tenant_status(
+        &self,
+        id: Uuid,
+        status: TenantStatus,
+        actor_id: Option<Uuid>,
+    ) -> Result<Tenant, TenantError> {
+        observe_within(
+            "tenant.repo.set_tenant_status",
+            self.timeouts.write,
+            async {
+                self.check_rate_limit().await?;
+
+                let previous = self
+                    .find_tenant_by_id(id)
+                    .await?
+                    .ok_or(TenantError::NotFound)?;
+
+                let updated_tenant = sqlx::query(
+                    r#"
+                UPDATE tenants
+                SET
+                    status = $1,
+                    status_reason = $2,
+                    status_changed_at = $3,
+                    updated_at = NOW()
+                WHERE id = $4
+                RETURNING id, name, subdomain, status, status_reason, status_changed_at,
+                    created_at, updated_at, metadata
+                "#,
+                )
+                .bind(status.as_str())
+                .bind(status.reason())
+                .bind(status.since())
+                .bind(id)
+                .fetch_one(&self.pool)
+                .await
+                .and_then(map_tenant_row)
+                .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
 
-        // Delete tenant subscriptions
-        sqlx::query!(
-            r#"DELETE FROM tenant_subscriptions WHERE tenant_id = $1"#,
-            id
+                // Log audit event
+                self.log_tenant_audit(TenantAuditEvent {
+                    tenant_id: id,
+                    user_id: actor_id,
+                    action: match status {
+                        TenantStatus::Active => "TENANT_UNSUSPENSION",
+                        TenantStatus::Suspended { .. } => "TENANT_SUSPENSION",
+                        TenantStatus::Archived { .. } => "TENANT_ARCHIVAL",
+                    }
+                    .to_string(),
+                    details: {
+                        let mut map = serde_json::Map::new();
+                        map.insert(
+                            "previous_status".to_string(),
+                            serde_json::Value::String(previous.status.as_str().to_string()),
+                        );
+                        map.insert(
+                            "status".to_string(),
+                            serde_json::Value::String(status.as_str().to_string()),
+                        );
+                        if let Some(reason) = status.reason() {
+                            map.insert(
+                                "reason".to_string(),
+                                serde_json::Value::String(reason.to_string()),
+                            );
+                        }
+                        serde_json::Value::Object(map)
+                    },
+                    ip_address: None,
+                    user_agent: None,
+                })
+                .await?;
+
+                info!(
+                    "Tenant status changed from {} to {}: {}",
+                    previous.status.as_str(),
+                    status.as_str(),
+                    id
+                );
+                Ok(updated_tenant)
+            },
         )
-        .execute(&mut *tx)
         .await
-        .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+    }
 
-        // Delete tenant users
-        sqlx::query!(r#"DELETE FROM tenant_users WHERE tenant_id = $1"#, id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+    #[instrument(name = "tenant.repo.delete_tenant", skip(self))]
+    async fn delete_tenant(&self, id: Uuid) -> Result<(), TenantError> {
+        observe_within("tenant.repo.delete_tenant", self.timeouts.write, async {
+            self.check_rate_limit().await?;
 
-        // Delete tenant audit logs
-        sqlx::query!(r#"DELETE FROM tenant_audit_log WHERE tenant_id = $1"#, id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+            // Start a transaction
+            let mut tx = self
+                .pool
+                .begin()
+                .await
+                .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
 
-        // Delete tenant
-        let result = sqlx::query!(r#"DELETE FROM tenants WHERE id = $1"#, id)
+            // Delete tenant subscriptions
+            sqlx::query!(
+                r#"DELETE FROM tenant_subscriptions WHERE tenant_id = $1"#,
+                id
+            )
             .execute(&mut *tx)
             .await
             .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
 
-        if result.rows_affected() == 0 {
-            return Err(TenantError::NotFound);
-        }
+            // Delete tenant users
+            sqlx::query!(r#"DELETE FROM tenant_users WHERE tenant_id = $1"#, id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
 
-        // Commit transaction
-        tx.commit()
-            .await
-            .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+            // Delete tenant audit logs
+            sqlx::query!(r#"DELETE FROM tenant_audit_log WHERE tenant_id = $1"#, id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
 
-        info!("Tenant deleted successfully: {}", id);
-        Ok(())
+            // Delete tenant
+            let result = sqlx::query!(r#"DELETE FROM tenants WHERE id = $1"#, id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+
+            if result.rows_affected() == 0 {
+                return Err(TenantError::NotFound);
+            }
+
+            // Commit transaction
+            tx.commit()
+                .await
+                .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+
+            info!("Tenant deleted successfully: {}", id);
+            Ok(())
+        })
+        .await
     }
 
-    #[instrument(skip(self, subscription))]
+    #[instrument(name = "tenant.repo.create_subscription", skip(self, subscription))]
     async fn create_subscription(
         &self,
         tenant_id: Uuid,
         subscription: CreateSubscriptionDto,
     ) -> Result<TenantSubscription, TenantError> {
-        self.check_rate_limit().await?;
+        observe_within(
+            "tenant.repo.create_subscription",
+            self.timeouts.write,
+            async {
+                self.check_rate_limit().await?;
+
+                // Check if tenant exists
+                if (self.find_tenant_by_id(tenant_id).await?).is_none() {
+                    return Err(TenantError::NotFound);
+                }
 
-        // Check if tenant exists
-        if (self.find_tenant_by_id(tenant_id).await?).is_none() {
-            return Err(TenantError::NotFound);
-        }
+                let now = OffsetDateTime::now_utc();
+                let id = Uuid::new_v4();
 
-        let now = OffsetDateTime::now_utc();
-        let id = Uuid::new_v4();
+                // Handle is_active default
+                let is_active = subscription.is_active.unwrap_or(true);
 
-        // Handle is_active default
-        let is_active = subscription.is_active.unwrap_or(true);
+                // Convert plan_type to string for SQL
+                let plan_type_str = subscription.plan_type.to_string().to_uppercase();
 
-        // Convert plan_type to string for SQL
-        let plan_type_str = subscription.plan_type.to_string().to_uppercase();
+                // The plan type is bound as text and cast to the enum type
+                let subscription = sqlx::query(
+                    r#"
+                INSERT INTO tenant_subscriptions (
+                    id, tenant_id, plan_type, starts_at, expires_at, is_active,
+                    payment_status, max_users, features, created_at, updated_at
+                )
+                VALUES ($1, $2, $3::tenant_plan_type, $4, $5, $6, $7, $8, $9, $10, $11)
+                RETURNING id, tenant_id, plan_type::TEXT AS plan_type, starts_at, expires_at,
+                          is_active, payment_status, max_users, features, created_at, updated_at
+                "#,
+                )
+                .bind(id)
+                .bind(tenant_id)
+                .bind(plan_type_str)
+                .bind(subscription.starts_at)
+                .bind(subscription.expires_at)
+                .bind(is_active)
+                .bind(subscription.payment_status)
+                .bind(subscription.max_users)
+                .bind(
+                    subscription
+                        .features
+                        .unwrap_or(serde_json::Value::Object(serde_json::Map::new())),
+                )
+                .bind(now)
+                .bind(now)
+                .fetch_one(&self.pool)
+                .await
+                .and_then(map_subscription_row)
+                .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
 
-        let subscription = sqlx::query_as!(
-            TenantSubscription,
-            r#"
-            INSERT INTO tenant_subscriptions (
-                id, tenant_id, plan_type, starts_at, expires_at, is_active, 
-                payment_status, max_users, features, created_at, updated_at
-            )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
-            RETURNING id, tenant_id, plan_type as "plan_type: _", starts_at, expires_at, is_active, 
-                     payment_status, max_users, features, created_at, updated_at
-            "#,
-            id,
-            tenant_id,
-            plan_type_str as _,
-            subscription.starts_at,
-            subscription.expires_at,
-            is_active,
-            subscription.payment_status,
-            subscription.max_users,
-            subscription
-                .features
-                .unwrap_or(serde_json::Value::Object(serde_json::Map::new())),
-            now,
-            now
+                // Log the tenant subscription creation
+                #[allow(clippy::disallowed_methods)]
+                let details = serde_json::json!({
+                    "subscription_id": subscription.id,
+                    "plan_type": subscription.plan_type.to_string(),
+                    "starts_at": subscription.starts_at,
+                    "expires_at": subscription.expires_at,
+                });
+                self.log_tenant_audit(TenantAuditEvent {
+                    tenant_id,
+                    user_id: None,
+                    action: "SUBSCRIPTION_CREATED".to_string(),
+                    details,
+                    ip_address: None,
+                    user_agent: None,
+                })
+                .await?;
+
+                Ok(subscription)
+            },
         )
-        .fetch_one(&self.pool)
         .await
-        .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
-
-        // Log the tenant subscription creation
-        self.log_tenant_audit(TenantAuditEvent {
-            tenant_id,
-            user_id: None,
-            action: "SUBSCRIPTION_CREATED".to_string(),
-            details: serde_json::json!({
-                "subscription_id": subscription.id,
-                "plan_type": subscription.plan_type.to_string(),
-                "starts_at": subscription.starts_at,
-                "expires_at": subscription.expires_at,
-            }),
-            ip_address: None,
-            user_agent: None,
-        })
-        .await?;
-
-        Ok(subscription)
     }
 
-    #[instrument(skip(self))]
+    #[instrument(name = "tenant.repo.get_active_subscription", skip(self))]
     async fn get_active_subscription(
         &self,
         tenant_id: Uuid,
     ) -> Result<Option<TenantSubscription>, TenantError> {
-        self.check_rate_limit().await?;
-
-        let subscription = sqlx::query_as!(
-            TenantSubscription,
-            r#"
+        observe_within(
+            "tenant.repo.get_active_subscription",
+            self.timeouts.read,
+            async {
+                self.check_rate_limit().await?;
+
+                let subscription = sqlx::query_as!(
+                    TenantSubscription,
+                    r#"
             SELECT 
                 id, tenant_id, plan_type as "plan_type: _", starts_at, expires_at, is_active, 
                 payment_status, max_users, features, created_at, updated_at
@@ -539,229 +851,528 @@ impl TenantRepository for PostgresTenantRepository {
             ORDER BY created_at DESC
             LIMIT 1
             "#,
-            tenant_id
+                    tenant_id
+                )
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+
+                Ok(subscription)
+            },
         )
-        .fetch_optional(&self.pool)
         .await
-        .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+    }
+
+    #[instrument(name = "tenant.repo.list_subscriptions", skip(self))]
+    async fn list_subscriptions(
+        &self,
+        tenant_id: Uuid,
+    ) -> Result<Vec<TenantSubscription>, TenantError> {
+        observe_within("tenant.repo.list_subscriptions", self.timeouts.read, async {
+            self.check_rate_limit().await?;
+
+            let rows = sqlx::query(
+                r#"
+                SELECT id, tenant_id, plan_type::TEXT AS plan_type, starts_at, expires_at, is_active,
+                       payment_status, max_users, features, created_at, updated_at
+                FROM tenant_subscriptions
+                WHERE tenant_id = $1
+                ORDER BY created_at, id
+                "#,
+            )
+            .bind(tenant_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
 
-        Ok(subscription)
+            rows.into_iter()
+                .map(map_subscription_row)
+                .collect::<Result<_, _>>()
+                .map_err(|e| TenantError::DatabaseError(e.to_string()))
+        })
+        .await
     }
 
-    #[instrument(skip(self, subscription))]
+    #[instrument(name = "tenant.repo.update_subscription", skip(self, subscription))]
     async fn update_subscription(
         &self,
         id: Uuid,
         subscription: UpdateSubscriptionDto,
     ) -> Result<TenantSubscription, TenantError> {
-        self.check_rate_limit().await?;
-
-        let now = OffsetDateTime::now_utc();
-
-        // Prepare plan_type string if it exists
-        let plan_type_str = subscription
-            .plan_type
-            .as_ref()
-            .map(|plan_type| plan_type.to_string().to_uppercase());
+        observe_within(
+            "tenant.repo.update_subscription",
+            self.timeouts.write,
+            async {
+                self.check_rate_limit().await?;
+
+                let now = OffsetDateTime::now_utc();
+
+                // Prepare plan_type string if it exists
+                let plan_type_str = subscription
+                    .plan_type
+                    .as_ref()
+                    .map(|plan_type| plan_type.to_string().to_uppercase());
+
+                // Update subscription
+                let updated = sqlx::query(
+                    r#"
+                UPDATE tenant_subscriptions
+                SET
+                    plan_type = COALESCE($1::tenant_plan_type, plan_type),
+                    expires_at = COALESCE($2, expires_at),
+                    is_active = COALESCE($3, is_active),
+                    payment_status = COALESCE($4, payment_status),
+                    max_users = COALESCE($5, max_users),
+                    features = COALESCE($6, features),
+                    updated_at = $7
+                WHERE id = $8
+                RETURNING id, tenant_id, plan_type::TEXT AS plan_type, starts_at, expires_at,
+                          is_active, payment_status, max_users, features, created_at, updated_at
+                "#,
+                )
+                .bind(plan_type_str)
+                .bind(subscription.expires_at)
+                .bind(subscription.is_active)
+                .bind(subscription.payment_status)
+                .bind(subscription.max_users)
+                .bind(subscription.features)
+                .bind(now)
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| TenantError::DatabaseError(e.to_string()))?
+                .map(map_subscription_row)
+                .transpose()
+                .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
 
-        // Update subscription
-        let updated = sqlx::query_as!(
-            TenantSubscription,
-            r#"
-            UPDATE tenant_subscriptions
-            SET
-                plan_type = COALESCE($1, plan_type),
-                expires_at = COALESCE($2, expires_at),
-                is_active = COALESCE($3, is_active),
-                payment_status = COALESCE($4, payment_status),
-                max_users = COALESCE($5, max_users),
-                features = COALESCE($6, features),
-                updated_at = $7
-            WHERE id = $8
-            RETURNING id, tenant_id, plan_type as "plan_type: _", starts_at, expires_at, is_active, 
-                     payment_status, max_users, features, created_at, updated_at
-            "#,
-            plan_type_str as Option<String>,
-            subscription.expires_at,
-            subscription.is_active,
-            subscription.payment_status,
-            subscription.max_users,
-            subscription.features,
-            now,
-            id
+                if let Some(subscription) = updated {
+                    // Log the update
+                    #[allow(clippy::disallowed_methods)]
+                    let details = serde_json::json!({
+                        "subscription_id": subscription.id,
+                        "plan_type": subscription.plan_type.to_string(),
+                        "expires_at": subscription.expires_at,
+                        "is_active": subscription.is_active
+                    });
+                    self.log_tenant_audit(TenantAuditEvent {
+                        tenant_id: subscription.tenant_id,
+                        user_id: None,
+                        action: "SUBSCRIPTION_UPDATED".to_string(),
+                        details,
+                        ip_address: None,
+                        user_agent: None,
+                    })
+                    .await?;
+
+                    info!("Subscription updated successfully: {}", subscription.id);
+                    Ok(subscription)
+                } else {
+                    Err(TenantError::NotFound)
+                }
+            },
         )
-        .fetch_optional(&self.pool)
         .await
-        .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
-
-        if let Some(subscription) = updated {
-            // Log the update
-            self.log_tenant_audit(TenantAuditEvent {
-                tenant_id: subscription.tenant_id,
-                user_id: None,
-                action: "SUBSCRIPTION_UPDATED".to_string(),
-                details: serde_json::json!({
-                    "subscription_id": subscription.id,
-                    "plan_type": subscription.plan_type.to_string(),
-                    "expires_at": subscription.expires_at,
-                    "is_active": subscription.is_active
-                }),
-                ip_address: None,
-                user_agent: None,
-            })
-            .await?;
-
-            info!("Subscription updated successfully: {}", subscription.id);
-            Ok(subscription)
-        } else {
-            Err(TenantError::NotFound)
-        }
     }
 
-    #[instrument(skip(self, user))]
-    async fn add_user_to_tenant(
+    #[instrument(name = "tenant.repo.find_subscription_by_id", skip(self))]
+    async fn find_subscription_by_id(
         &self,
-        tenant_id: Uuid,
-        user: CreateTenantUserDto,
-    ) -> Result<TenantUser, TenantError> {
-        self.check_rate_limit().await?;
-
-        // Check if tenant exists
-        if (self.find_tenant_by_id(tenant_id).await?).is_none() {
-            return Err(TenantError::NotFound);
-        }
-
-        // Check if user exists
-        let user_exists = sqlx::query!(r#"SELECT id FROM users WHERE id = $1"#, user.user_id)
+        id: Uuid,
+    ) -> Result<Option<TenantSubscription>, TenantError> {
+        observe_within("tenant.repo.find_subscription_by_id", self.timeouts.read, async {
+            self.check_rate_limit().await?;
+
+            let row = sqlx::query(
+                r#"
+                SELECT id, tenant_id, plan_type::TEXT AS plan_type, starts_at, expires_at, is_active,
+                       payment_status, max_users, features, created_at, updated_at
+                FROM tenant_subscriptions
+                WHERE id = $1
+                "#,
+            )
+            .bind(id)
             .fetch_optional(&self.pool)
             .await
             .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
 
-        if user_exists.is_none() {
-            return Err(TenantError::ValidationError("User does not exist".into()));
-        }
+            row.map(map_subscription_row)
+                .transpose()
+                .map_err(|e| TenantError::DatabaseError(e.to_string()))
+        })
+        .await
+    }
 
-        // Check if association already exists
-        let existing = sqlx::query!(
-            r#"SELECT tenant_id, user_id FROM tenant_users WHERE tenant_id = $1 AND user_id = $2"#,
-            tenant_id,
-            user.user_id
+    #[instrument(name = "tenant.repo.record_subscription_change", skip(self, entry), fields(tenant_id = %entry.tenant_id))]
+    async fn record_subscription_change(
+        &self,
+        entry: &SubscriptionHistoryEntry,
+    ) -> Result<(), TenantError> {
+        observe_within(
+            "tenant.repo.record_subscription_change",
+            self.timeouts.write,
+            async {
+                sqlx::query(
+                    r#"
+                INSERT INTO subscription_history (
+                    id, tenant_id, subscription_id, change_type, old_plan_type, new_plan_type,
+                    plan_change, old_max_users, new_max_users, old_expires_at, new_expires_at,
+                    actor_id, created_at
+                )
+                VALUES (
+                    $1, $2, $3, $4, $5::tenant_plan_type, $6::tenant_plan_type,
+                    $7, $8, $9, $10, $11, $12, $13
+                )
+                "#,
+                )
+                .bind(entry.id)
+                .bind(entry.tenant_id)
+                .bind(entry.subscription_id)
+                .bind(entry.change_type.as_str())
+                .bind(entry.old_plan_type.map(|plan_type| plan_type.to_string()))
+                .bind(entry.new_plan_type.to_string())
+                .bind(entry.plan_change.map(|plan_change| plan_change.as_str()))
+                .bind(entry.old_max_users)
+                .bind(entry.new_max_users)
+                .bind(entry.old_expires_at)
+                .bind(entry.new_expires_at)
+                .bind(entry.actor_id)
+                .bind(entry.created_at)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+
+                if let Some(plan_change) = entry.plan_change {
+                    #[allow(clippy::disallowed_methods)]
+                    let details = serde_json::json!({
+                        "subscription_id": entry.subscription_id,
+                        "old_plan_type": entry.old_plan_type.map(|plan_type| plan_type.to_string()),
+                        "new_plan_type": entry.new_plan_type.to_string(),
+                        "plan_change": plan_change.as_str(),
+                    });
+                    self.log_tenant_audit(TenantAuditEvent {
+                        tenant_id: entry.tenant_id,
+                        user_id: entry.actor_id,
+                        action: "SUBSCRIPTION_PLAN_CHANGED".to_string(),
+                        details,
+                        ip_address: None,
+                        user_agent: None,
+                    })
+                    .await?;
+                }
+
+                Ok(())
+            },
         )
-        .fetch_optional(&self.pool)
         .await
-        .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+    }
 
-        if existing.is_some() {
-            return Err(TenantError::AlreadyExists);
-        }
+    #[instrument(name = "tenant.repo.list_subscription_history", skip(self))]
+    async fn list_subscription_history(
+        &self,
+        tenant_id: Uuid,
+        limit: u32,
+        offset: u64,
+    ) -> Result<Vec<SubscriptionHistoryEntry>, TenantError> {
+        observe_within("tenant.repo.list_subscription_history", self.timeouts.read, async {
+            self.check_rate_limit().await?;
+
+            let rows = sqlx::query(
+                r#"
+                SELECT id, tenant_id, subscription_id, change_type,
+                       old_plan_type::TEXT AS old_plan_type, new_plan_type::TEXT AS new_plan_type,
+                       plan_change, old_max_users, new_max_users, old_expires_at, new_expires_at, actor_id,
+                       created_at
+                FROM subscription_history
+                WHERE tenant_id = $1
+                ORDER BY created_at DESC, id DESC
+                LIMIT $2 OFFSET $3
+                "#,
+            )
+            .bind(tenant_id)
+            .bind(i64::from(limit))
+            .bind(i64::try_from(offset).unwrap_or(i64::MAX))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
 
-        let now = OffsetDateTime::now_utc();
-        let is_active = user.is_active.unwrap_or(true);
+            rows.into_iter()
+                .map(map_subscription_history_row)
+                .collect::<Result<_, _>>()
+                .map_err(|e| TenantError::DatabaseError(e.to_string()))
+        })
+        .await
+    }
 
-        // Add user to tenant
-        let tenant_user = sqlx::query_as!(
-            TenantUser,
-            r#"
+    #[instrument(name = "tenant.repo.add_user_to_tenant", skip(self, user))]
+    async fn add_user_to_tenant(
+        &self,
+        tenant_id: Uuid,
+        user: CreateTenantUserDto,
+    ) -> Result<TenantUser, TenantError> {
+        observe_within("tenant.repo.add_user_to_tenant", self.timeouts.write, async {
+            self.check_rate_limit().await?;
+
+            // Check if tenant exists
+            if (self.find_tenant_by_id(tenant_id).await?).is_none() {
+                return Err(TenantError::NotFound);
+            }
+
+            // Check if user exists
+            let user_exists = sqlx::query!(r#"SELECT id FROM users WHERE id = $1"#, user.user_id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+
+            if user_exists.is_none() {
+                return Err(TenantError::ValidationError("User does not exist".into()));
+            }
+
+            // Check if association already exists
+            let existing = sqlx::query!(
+                r#"SELECT tenant_id, user_id FROM tenant_users WHERE tenant_id = $1 AND user_id = $2"#,
+                tenant_id,
+                user.user_id
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+
+            if existing.is_some() {
+                return Err(TenantError::AlreadyExists);
+            }
+
+            let now = OffsetDateTime::now_utc();
+            let is_active = user.is_active.unwrap_or(true);
+
+            // Add user to tenant
+            let tenant_user = sqlx::query_as!(
+                TenantUser,
+                r#"
             INSERT INTO tenant_users (
                 tenant_id, user_id, tenant_role, is_active, created_at, updated_at
             )
             VALUES ($1, $2, $3, $4, $5, $6)
             RETURNING tenant_id, user_id, tenant_role, is_active, created_at, updated_at
             "#,
-            tenant_id,
-            user.user_id,
-            user.tenant_role,
-            is_active,
-            now,
-            now
-        )
-        .fetch_one(&self.pool)
-        .await
-        .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
-
-        // Log audit event
-        self.log_tenant_audit(TenantAuditEvent {
-            tenant_id,
-            user_id: Some(user.user_id),
-            action: "USER_ADDED_TO_TENANT".to_string(),
-            details: {
-                let mut map = serde_json::Map::new();
-                map.insert(
-                    "role".to_string(),
-                    serde_json::Value::String(tenant_user.tenant_role.clone()),
-                );
-                map.insert(
-                    "is_active".to_string(),
-                    serde_json::Value::Bool(tenant_user.is_active),
-                );
-                serde_json::Value::Object(map)
-            },
-            ip_address: None,
-            user_agent: None,
+                tenant_id,
+                user.user_id,
+                user.tenant_role,
+                is_active,
+                now,
+                now
+            )
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+
+            // Log audit event
+            self.log_tenant_audit(TenantAuditEvent {
+                tenant_id,
+                user_id: Some(user.user_id),
+                action: "USER_ADDED_TO_TENANT".to_string(),
+                details: {
+                    let mut map = serde_json::Map::new();
+                    map.insert(
+                        "role".to_string(),
+                        serde_json::Value::String(tenant_user.tenant_role.clone()),
+                    );
+                    map.insert(
+                        "is_active".to_string(),
+                        serde_json::Value::Bool(tenant_user.is_active),
+                    );
+                    serde_json::Value::Object(map)
+                },
+                ip_address: None,
+                user_agent: None,
+            })
+            .await?;
+
+            info!("User added to tenant: {} -> {}", user.user_id, tenant_id);
+            Ok(tenant_user)
         })
-        .await?;
+        .await
+    }
+
+    #[instrument(name = "tenant.repo.add_user_to_tenant_within_limit", skip(self, user))]
+    async fn add_user_to_tenant_within_limit(
+        &self,
+        tenant_id: Uuid,
+        user: CreateTenantUserDto,
+        max_tenants_per_user: u32,
+    ) -> Result<TenantUser, TenantError> {
+        observe_within(
+            "tenant.repo.add_user_to_tenant_within_limit",
+            self.timeouts.write,
+            async {
+                self.check_rate_limit().await?;
+
+                let db_err = |e: sqlx::Error| TenantError::DatabaseError(e.to_string());
+                let mut tx = self.pool.begin().await.map_err(db_err)?;
+
+                // Locking the user serializes concurrent additions of the same user,
+                // so two of them cannot both take the last free slot
+                let user_exists = sqlx::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+                    .bind(user.user_id)
+                    .fetch_optional(&mut *tx)
+                    .await
+                    .map_err(db_err)?;
+                if user_exists.is_none() {
+                    return Err(TenantError::ValidationError("User does not exist".into()));
+                }
+
+                let tenant_exists = sqlx::query("SELECT id FROM tenants WHERE id = $1")
+                    .bind(tenant_id)
+                    .fetch_optional(&mut *tx)
+                    .await
+                    .map_err(db_err)?;
+                if tenant_exists.is_none() {
+                    return Err(TenantError::NotFound);
+                }
+
+                let existing = sqlx::query(
+                    "SELECT user_id FROM tenant_users WHERE tenant_id = $1 AND user_id = $2",
+                )
+                .bind(tenant_id)
+                .bind(user.user_id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(db_err)?;
+                if existing.is_some() {
+                    return Err(TenantError::AlreadyExists);
+                }
 
-        info!("User added to tenant: {} -> {}", user.user_id, tenant_id);
-        Ok(tenant_user)
+                let is_active = user.is_active.unwrap_or(true);
+                if is_active {
+                    let active_tenants: i64 = sqlx::query_scalar(
+                        "SELECT COUNT(*) FROM tenant_users WHERE user_id = $1 AND is_active = true",
+                    )
+                    .bind(user.user_id)
+                    .fetch_one(&mut *tx)
+                    .await
+                    .map_err(db_err)?;
+                    if active_tenants >= i64::from(max_tenants_per_user) {
+                        warn!(
+                            "User {} is already active in {} tenants",
+                            user.user_id, active_tenants
+                        );
+                        return Err(TenantError::TenantsPerUserLimitExceeded {
+                            limit: max_tenants_per_user,
+                            current: u32::try_from(active_tenants).unwrap_or(u32::MAX),
+                        });
+                    }
+                }
+
+                let now = OffsetDateTime::now_utc();
+                let tenant_user = sqlx::query(
+                    r#"
+                INSERT INTO tenant_users (
+                    tenant_id, user_id, tenant_role, is_active, created_at, updated_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $5)
+                RETURNING tenant_id, user_id, tenant_role, is_active, created_at, updated_at
+                "#,
+                )
+                .bind(tenant_id)
+                .bind(user.user_id)
+                .bind(&user.tenant_role)
+                .bind(is_active)
+                .bind(now)
+                .fetch_one(&mut *tx)
+                .await
+                .and_then(map_tenant_user_row)
+                .map_err(db_err)?;
+
+                tx.commit().await.map_err(db_err)?;
+
+                #[allow(clippy::disallowed_methods)]
+                let details = serde_json::json!({
+                    "role": tenant_user.tenant_role,
+                    "is_active": tenant_user.is_active,
+                });
+                self.log_tenant_audit(TenantAuditEvent {
+                    tenant_id,
+                    user_id: Some(user.user_id),
+                    action: "USER_ADDED_TO_TENANT".to_string(),
+                    details,
+                    ip_address: None,
+                    user_agent: None,
+                })
+                .await?;
+
+                info!("User added to tenant: {} -> {}", user.user_id, tenant_id);
+                Ok(tenant_user)
+            },
+        )
+        .await
     }
 
-    #[instrument(skip(self))]
+    #[instrument(name = "tenant.repo.get_tenant_users", skip(self))]
     async fn get_tenant_users(&self, tenant_id: Uuid) -> Result<Vec<TenantUser>, TenantError> {
-        self.check_rate_limit().await?;
+        observe_within("tenant.repo.get_tenant_users", self.timeouts.read, async {
+            self.check_rate_limit().await?;
 
-        let users = sqlx::query_as!(
-            TenantUser,
-            r#"
+            let users = sqlx::query_as!(
+                TenantUser,
+                r#"
             SELECT tenant_id, user_id, tenant_role, is_active, created_at, updated_at
             FROM tenant_users
             WHERE tenant_id = $1
             "#,
-            tenant_id
-        )
-        .fetch_all(&self.pool)
-        .await
-        .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+                tenant_id
+            )
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
 
-        debug!("Retrieved {} users for tenant {}", users.len(), tenant_id);
-        Ok(users)
+            debug!("Retrieved {} users for tenant {}", users.len(), tenant_id);
+            Ok(users)
+        })
+        .await
     }
 
-    #[instrument(skip(self))]
+    #[instrument(name = "tenant.repo.get_user_tenants", skip(self))]
     async fn get_user_tenants(&self, user_id: Uuid) -> Result<Vec<TenantUser>, TenantError> {
-        self.check_rate_limit().await?;
+        observe_within("tenant.repo.get_user_tenants", self.timeouts.read, async {
+            self.check_rate_limit().await?;
 
-        let tenants = sqlx::query_as!(
-            TenantUser,
-            r#"
+            let tenants = sqlx::query_as!(
+                TenantUser,
+                r#"
             SELECT tenant_id, user_id, tenant_role, is_active, created_at, updated_at
             FROM tenant_users
             WHERE user_id = $1
             "#,
-            user_id
-        )
-        .fetch_all(&self.pool)
-        .await
-        .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+                user_id
+            )
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
 
-        debug!("Retrieved {} tenants for user {}", tenants.len(), user_id);
-        Ok(tenants)
+            debug!("Retrieved {} tenants for user {}", tenants.len(), user_id);
+            Ok(tenants)
+        })
+        .await
     }
 
-    #[instrument(skip(self, update))]
+    #[instrument(name = "tenant.repo.update_tenant_user", skip(self, update))]
     async fn update_tenant_user(
         &self,
         tenant_id: Uuid,
         user_id: Uuid,
         update: UpdateTenantUserDto,
     ) -> Result<TenantUser, TenantError> {
-        self.check_rate_limit().await?;
-
-        let now = OffsetDateTime::now_utc();
-
-        // Update tenant user
-        let updated = sqlx::query_as!(
-            TenantUser,
-            r#"
+        observe_within(
+            "tenant.repo.update_tenant_user",
+            self.timeouts.write,
+            async {
+                self.check_rate_limit().await?;
+
+                let now = OffsetDateTime::now_utc();
+
+                // Update tenant user
+                let updated = sqlx::query_as!(
+                    TenantUser,
+                    r#"
             UPDATE tenant_users
             SET
                 tenant_role = COALESCE($1, tenant_role),
@@ -770,386 +1381,657 @@ impl TenantRepository for PostgresTenantRepository {
             WHERE tenant_id = $4 AND user_id = $5
             RETURNING tenant_id, user_id, tenant_role, is_active, created_at, updated_at
             "#,
-            update.tenant_role,
-            update.is_active,
-            now,
-            tenant_id,
-            user_id
+                    update.tenant_role,
+                    update.is_active,
+                    now,
+                    tenant_id,
+                    user_id
+                )
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
+
+                if let Some(tenant_user) = updated {
+                    // Log audit event
+                    self.log_tenant_audit(TenantAuditEvent {
+                        tenant_id,
+                        user_id: Some(user_id),
+                        action: "TENANT_USER_UPDATED".to_string(),
+                        details: {
+                            let mut map = serde_json::Map::new();
+                            if update.tenant_role.is_some() {
+                                map.insert(
+                                    "role".to_string(),
+                                    serde_json::Value::String(tenant_user.tenant_role.clone()),
+                                );
+                            }
+                            if update.is_active.is_some() {
+                                map.insert(
+                                    "is_active".to_string(),
+                                    serde_json::Value::Bool(tenant_user.is_active),
+                                );
+                            }
+                            serde_json::Value::Object(map)
+                        },
+                        ip_address: None,
+                        user_agent: None,
+                    })
+                    .await?;
+
+                    info!("Tenant user updated: {} in {}", user_id, tenant_id);
+                    Ok(tenant_user)
+                } else {
+                    Err(TenantError::NotFound)
+                }
+            },
         )
-        .fetch_optional(&self.pool)
         .await
-        .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
-
-        if let Some(tenant_user) = updated {
-            // Log audit event
-            self.log_tenant_audit(TenantAuditEvent {
-                tenant_id,
-                user_id: Some(user_id),
-                action: "TENANT_USER_UPDATED".to_string(),
-                details: {
-                    let mut map = serde_json::Map::new();
-                    if update.tenant_role.is_some() {
-                        map.insert(
-                            "role".to_string(),
-                            serde_json::Value::String(tenant_user.tenant_role.clone()),
-                        );
-                    }
-                    if update.is_active.is_some() {
-                        map.insert(
-                            "is_active".to_string(),
-                            serde_json::Value::Bool(tenant_user.is_active),
-                        );
-                    }
-                    serde_json::Value::Object(map)
-                },
-                ip_address: None,
-                user_agent: None,
-            })
-            .await?;
-
-            info!("Tenant user updated: {} in {}", user_id, tenant_id);
-            Ok(tenant_user)
-        } else {
-            Err(TenantError::NotFound)
-        }
     }
 
-    #[instrument(skip(self))]
+    #[instrument(name = "tenant.repo.remove_user_from_tenant", skip(self))]
     async fn remove_user_from_tenant(
         &self,
         tenant_id: Uuid,
         user_id: Uuid,
     ) -> Result<(), TenantError> {
-        self.check_rate_limit().await?;
+        observe_within(
+            "tenant.repo.remove_user_from_tenant",
+            self.timeouts.write,
+            async {
+                self.check_rate_limit().await?;
+
+                // Remove user from tenant
+                let result = sqlx::query!(
+                    r#"DELETE FROM tenant_users WHERE tenant_id = $1 AND user_id = $2"#,
+                    tenant_id,
+                    user_id
+                )
+                .execute(&self.pool)
+                .await
+                .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
 
-        // Remove user from tenant
-        let result = sqlx::query!(
-            r#"DELETE FROM tenant_users WHERE tenant_id = $1 AND user_id = $2"#,
-            tenant_id,
-            user_id
+                if result.rows_affected() == 0 {
+                    return Err(TenantError::NotFound);
+                }
+
+                // Log audit event
+                self.log_tenant_audit(TenantAuditEvent {
+                    tenant_id,
+                    user_id: Some(user_id),
+                    action: "USER_REMOVED_FROM_TENANT".to_string(),
+                    details: serde_json::Value::Object(serde_json::Map::new()),
+                    ip_address: None,
+                    user_agent: None,
+                })
+                .await?;
+
+                info!("User removed from tenant: {} from {}", user_id, tenant_id);
+                Ok(())
+            },
         )
-        .execute(&self.pool)
         .await
-        .map_err(|e| TenantError::DatabaseError(e.to_string()))?;
-
-        if result.rows_affected() == 0 {
-            return Err(TenantError::NotFound);
-        }
+    }
 
-        // Log audit event
-        self.log_tenant_audit(TenantAuditEvent {
-            tenant_id,
-            user_id: Some(user_id),
-            action: "USER_REMOVED_FROM_TENANT".to_string(),
-            details: serde_json::Value::Object(serde_json::Map::new()),
-            ip_address: None,
-            user_agent: None,
-        })
-        .await?;
+    #[instrument(name = "tenant.repo.count_active_tenant_users", skip(self))]
+    async fn count_active_tenant_users(&self, tenant_id: Uuid) -> Result<i64, TenantError> {
+        observe_within(
+            "tenant.repo.count_active_tenant_users",
+            self.timeouts.read,
+            async {
+                self.check_rate_limit().await?;
 
-        info!("User removed from tenant: {} from {}", user_id, tenant_id);
-        Ok(())
+                sqlx::query_scalar(
+                    "SELECT COUNT(*) FROM tenant_users WHERE tenant_id = $1 AND is_active = true",
+                )
+                .bind(tenant_id)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(|e| TenantError::DatabaseError(e.to_string()))
+            },
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl UserRepository for PostgresUserRepository {
-    #[instrument(skip(self, user))]
+    #[instrument(name = "user.repo.create", skip(self, user))]
     async fn create(&self, user: &User) -> Result<(), UserError> {
-        self.check_rate_limit().await?;
+        observe_within("user.repo.create", self.timeouts.write, async {
+            self.check_rate_limit().await?;
 
-        // Check if email already exists
-        if (self.find_by_email(&user.email).await?).is_some() {
-            return Err(UserError::AlreadyExists);
-        }
+            // Check if email already exists
+            if (self.find_by_email(&user.email).await?).is_some() {
+                return Err(UserError::AlreadyExists);
+            }
 
-        // Create user
-        sqlx::query(
-            r#"
-            INSERT INTO users (
-                id, email, password_hash, created_at, updated_at,
-                last_login, is_active, is_verified
+            // Create user; the initial password counts as changed at registration
+            sqlx::query(
+                r#"
+                INSERT INTO users (
+                    id, email, password_hash, created_at, updated_at,
+                    last_login, is_active, is_verified, password_changed_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                "#,
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-            "#,
-        )
-        .bind(user.id)
-        .bind(&user.email)
-        .bind(&user.password_hash)
-        .bind(user.created_at)
-        .bind(user.updated_at)
-        .bind(user.last_login)
-        .bind(user.is_active)
-        .bind(user.is_verified)
-        .execute(&self.pool)
-        .await
-        .map_err(|e| UserError::DatabaseError(e.to_string()))?;
-
-        // Log audit event
-        self.log_audit(AuditEvent {
-            user_id: user.id,
-            action: "REGISTRATION".to_string(),
-            details: {
-                let mut map = serde_json::Map::new();
-                map.insert(
-                    "email".to_string(),
-                    serde_json::Value::String(user.email.clone()),
-                );
-                map.insert(
-                    "is_verified".to_string(),
-                    serde_json::Value::Bool(user.is_verified),
-                );
-                serde_json::Value::Object(map)
-            },
-            ip_address: None,
-            user_agent: None,
-        })
-        .await?;
+            .bind(user.id)
+            .bind(&user.email)
+            .bind(&user.password_hash)
+            .bind(user.created_at)
+            .bind(user.updated_at)
+            .bind(user.last_login)
+            .bind(user.is_active)
+            .bind(user.is_verified)
+            .bind(user.has_password().then_some(user.created_at))
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserError::DatabaseError(e.to_string()))?;
 
-        info!("User created successfully: {}", user.id);
-        Ok(())
+            // Log audit event
+            self.log_audit(AuditEvent {
+                user_id: user.id,
+                action: "REGISTRATION".to_string(),
+                details: {
+                    let mut map = serde_json::Map::new();
+                    map.insert(
+                        "email".to_string(),
+                        serde_json::Value::String(user.email.clone()),
+                    );
+                    map.insert(
+                        "is_verified".to_string(),
+                        serde_json::Value::Bool(user.is_verified),
+                    );
+                    serde_json::Value::Object(map)
+                },
+                ip_address: None,
+                user_agent: None,
+            })
+            .await?;
+
+            info!("User created successfully: {}", user.id);
+            Ok(())
+        })
+        .await
     }
 
-    #[instrument(skip(self))]
+    #[instrument(name = "user.repo.find_by_id", skip(self))]
     async fn find_by_id(&self, id: Uuid) -> Result<Option<User>, UserError> {
-        self.check_rate_limit().await?;
+        observe_within("user.repo.find_by_id", self.timeouts.read, async {
+            self.check_rate_limit().await?;
 
-        let user = sqlx::query_as!(
-            User,
-            r#"
+            let user = sqlx::query_as!(
+                User,
+                r#"
             SELECT
                 id, email, password_hash, created_at, updated_at,
                 last_login, is_active, is_verified, email as display_name
             FROM users
             WHERE id = $1
             "#,
-            id
-        )
-        .fetch_optional(&self.pool)
-        .await
-        .map_err(|e| UserError::DatabaseError(e.to_string()))?;
+                id
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| UserError::DatabaseError(e.to_string()))?;
 
-        debug!("User lookup by ID complete: {}", id);
-        Ok(user)
+            debug!("User lookup by ID complete: {}", id);
+            Ok(user)
+        })
+        .await
     }
 
-    #[instrument(skip(self))]
+    #[instrument(name = "user.repo.find_by_email", skip(self))]
     async fn find_by_email(&self, email: &str) -> Result<Option<User>, UserError> {
-        self.check_rate_limit().await?;
+        observe_within("user.repo.find_by_email", self.timeouts.read, async {
+            self.check_rate_limit().await?;
 
-        let user = sqlx::query_as!(
-            User,
-            r#"
+            let user = sqlx::query_as!(
+                User,
+                r#"
             SELECT
                 id, email, password_hash, created_at, updated_at,
                 last_login, is_active, is_verified, email as display_name
             FROM users
             WHERE email = $1
             "#,
-            email
-        )
-        .fetch_optional(&self.pool)
-        .await
-        .map_err(|e| UserError::DatabaseError(e.to_string()))?;
+                email
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| UserError::DatabaseError(e.to_string()))?;
 
-        debug!("User lookup by email complete: {}", email);
-        Ok(user)
+            debug!("User lookup by email complete: {}", email);
+            Ok(user)
+        })
+        .await
     }
 
-    #[instrument(skip(self, user))]
+    #[instrument(name = "user.repo.update", skip(self, user))]
     async fn update(&self, user: &User) -> Result<(), UserError> {
-        self.check_rate_limit().await?;
-
-        let result = sqlx::query(
-            r#"
-            UPDATE users
-            SET
-                email = $1,
-                password_hash = $2,
-                updated_at = $3,
-                last_login = $4,
-                is_active = $5,
-                is_verified = $6
-            WHERE id = $7
-            "#,
-        )
-        .bind(&user.email)
-        .bind(&user.password_hash)
-        .bind(user.updated_at)
-        .bind(user.last_login)
-        .bind(user.is_active)
-        .bind(user.is_verified)
-        .bind(user.id)
-        .execute(&self.pool)
-        .await
-        .map_err(|e| UserError::DatabaseError(e.to_string()))?;
+        observe_within("user.repo.update", self.timeouts.write, async {
+            self.check_rate_limit().await?;
+
+            // A new password is recorded as a change; removing the password is not
+            let result = sqlx::query(
+                r#"
+                UPDATE users
+                SET
+                    email = $1,
+                    password_hash = $2,
+                    updated_at = $3,
+                    is_active = $4,
+                    is_verified = $5,
+                    password_changed_at = CASE
+                        WHEN password_hash IS DISTINCT FROM $2 AND $2 <> $7 THEN $3
+                        ELSE password_changed_at
+                    END,
+                    password_reset_required = password_reset_required
+                        AND NOT (password_hash IS DISTINCT FROM $2 AND $2 <> $7)
+                WHERE id = $6
+                "#,
+            )
+            .bind(&user.email)
+            .bind(&user.password_hash)
+            .bind(user.updated_at)
+            .bind(user.is_active)
+            .bind(user.is_verified)
+            .bind(user.id)
+            .bind(NO_PASSWORD_HASH)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserError::DatabaseError(e.to_string()))?;
 
-        if result.rows_affected() == 0 {
-            return Err(UserError::NotFound);
-        }
+            if result.rows_affected() == 0 {
+                return Err(UserError::NotFound);
+            }
 
-        info!("User updated successfully: {}", user.id);
-        Ok(())
+            info!("User updated successfully: {}", user.id);
+            Ok(())
+        })
+        .await
     }
 
-    #[instrument(skip(self))]
+    #[instrument(name = "user.repo.delete", skip(self))]
     async fn delete(&self, id: Uuid) -> Result<(), UserError> {
-        self.check_rate_limit().await?;
+        observe_within("user.repo.delete", self.timeouts.write, async {
+            self.check_rate_limit().await?;
+
+            // First delete audit logs
+            sqlx::query!("DELETE FROM user_audit_log WHERE user_id = $1", id)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| UserError::DatabaseError(e.to_string()))?;
+
+            // Then delete user
+            let result = sqlx::query!("DELETE FROM users WHERE id = $1", id)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| UserError::DatabaseError(e.to_string()))?;
+
+            if result.rows_affected() == 0 {
+                return Err(UserError::NotFound);
+            }
+
+            info!("User deleted successfully: {}", id);
+            Ok(())
+        })
+        .await
+    }
 
-        // First delete audit logs
-        sqlx::query!("DELETE FROM user_audit_log WHERE user_id = $1", id)
+    #[instrument(name = "user.repo.verify_email", skip(self))]
+    async fn verify_email(&self, id: Uuid) -> Result<(), UserError> {
+        observe_within("user.repo.verify_email", self.timeouts.write, async {
+            self.check_rate_limit().await?;
+
+            let now = OffsetDateTime::now_utc();
+            let result = sqlx::query(
+                r#"
+                UPDATE users
+                SET
+                    is_verified = true,
+                    updated_at = $1,
+                    verification_token = NULL,
+                    verification_token_expires_at = NULL
+                WHERE id = $2
+                "#,
+            )
+            .bind(now)
+            .bind(id)
             .execute(&self.pool)
             .await
             .map_err(|e| UserError::DatabaseError(e.to_string()))?;
 
-        // Then delete user
-        let result = sqlx::query!("DELETE FROM users WHERE id = $1", id)
+            if result.rows_affected() == 0 {
+                return Err(UserError::NotFound);
+            }
+
+            // Log audit event
+            self.log_audit(AuditEvent {
+                user_id: id,
+                action: "EMAIL_VERIFICATION_SUCCESS".to_string(),
+                details: {
+                    let mut map = serde_json::Map::new();
+                    map.insert(
+                        "verified_at".to_string(),
+                        serde_json::Value::String(now.to_string()),
+                    );
+                    serde_json::Value::Object(map)
+                },
+                ip_address: None,
+                user_agent: None,
+            })
+            .await?;
+
+            info!("User email verified successfully: {}", id);
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "user.repo.deactivate", skip(self))]
+    async fn deactivate(&self, id: Uuid) -> Result<(), UserError> {
+        observe_within("user.repo.deactivate", self.timeouts.write, async {
+            self.check_rate_limit().await?;
+
+            let now = OffsetDateTime::now_utc();
+            let result = sqlx::query(
+                r#"
+                UPDATE users
+                SET
+                    is_active = false,
+                    updated_at = $1
+                WHERE id = $2
+                "#,
+            )
+            .bind(now)
+            .bind(id)
             .execute(&self.pool)
             .await
             .map_err(|e| UserError::DatabaseError(e.to_string()))?;
 
-        if result.rows_affected() == 0 {
-            return Err(UserError::NotFound);
-        }
+            if result.rows_affected() == 0 {
+                return Err(UserError::NotFound);
+            }
 
-        info!("User deleted successfully: {}", id);
-        Ok(())
+            // Log audit event
+            self.log_audit(AuditEvent {
+                user_id: id,
+                action: "USER_DEACTIVATED".to_string(),
+                details: {
+                    let mut map = serde_json::Map::new();
+                    map.insert(
+                        "deactivated_at".to_string(),
+                        serde_json::Value::String(now.to_string()),
+                    );
+                    serde_json::Value::Object(map)
+                },
+                ip_address: None,
+                user_agent: None,
+            })
+            .await?;
+
+            info!("User deactivated successfully: {}", id);
+            Ok(())
+        })
+        .await
     }
 
-    #[instrument(skip(self))]
-    async fn verify_email(&self, id: Uuid) -> Result<(), UserError> {
-        self.check_rate_limit().await?;
+    #[instrument(name = "user.repo.activate", skip(self))]
+    async fn activate(&self, id: Uuid) -> Result<(), UserError> {
+        observe_within("user.repo.activate", self.timeouts.write, async {
+            self.check_rate_limit().await?;
+
+            let now = OffsetDateTime::now_utc();
+            let result = sqlx::query(
+                r#"
+                UPDATE users
+                SET
+                    is_active = true,
+                    updated_at = $1
+                WHERE id = $2
+                "#,
+            )
+            .bind(now)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserError::DatabaseError(e.to_string()))?;
 
-        let now = OffsetDateTime::now_utc();
-        let result = sqlx::query(
-            r#"
-            UPDATE users
-            SET
-                is_verified = true,
-                updated_at = $1,
-                verification_token = NULL,
-                verification_token_expires_at = NULL
-            WHERE id = $2
-            "#,
-        )
-        .bind(now)
-        .bind(id)
-        .execute(&self.pool)
+            if result.rows_affected() == 0 {
+                return Err(UserError::NotFound);
+            }
+
+            // Log audit event
+            self.log_audit(AuditEvent {
+                user_id: id,
+                action: "USER_ACTIVATED".to_string(),
+                details: {
+                    let mut map = serde_json::Map::new();
+                    map.insert(
+                        "activated_at".to_string(),
+                        serde_json::Value::String(now.to_string()),
+                    );
+                    serde_json::Value::Object(map)
+                },
+                ip_address: None,
+                user_agent: None,
+            })
+            .await?;
+
+            info!("User activated successfully: {}", id);
+            Ok(())
+        })
         .await
-        .map_err(|e| UserError::DatabaseError(e.to_string()))?;
+    }
 
-        if result.rows_affected() == 0 {
-            return Err(UserError::NotFound);
-        }
+    #[instrument(name = "user.repo.record_login", skip(self))]
+    async fn record_login(&self, id: Uuid, at: OffsetDateTime) -> Result<(), UserError> {
+        observe_within("user.repo.record_login", self.timeouts.write, async {
+            self.check_rate_limit().await?;
+
+            // Only the login columns, so a concurrent profile update is not overwritten
+            let result = sqlx::query(
+                r#"
+                UPDATE users
+                SET
+                    last_login = $1,
+                    failed_login_attempts = 0,
+                    last_failed_login_at = NULL
+                WHERE id = $2
+                "#,
+            )
+            .bind(at)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserError::DatabaseError(e.to_string()))?;
 
-        // Log audit event
-        self.log_audit(AuditEvent {
-            user_id: id,
-            action: "EMAIL_VERIFICATION_SUCCESS".to_string(),
-            details: {
-                let mut map = serde_json::Map::new();
-                map.insert(
-                    "verified_at".to_string(),
-                    serde_json::Value::String(now.to_string()),
-                );
-                serde_json::Value::Object(map)
-            },
-            ip_address: None,
-            user_agent: None,
+            if result.rows_affected() == 0 {
+                return Err(UserError::NotFound);
+            }
+
+            debug!("Login recorded for user: {}", id);
+            Ok(())
         })
-        .await?;
+        .await
+    }
 
-        info!("User email verified successfully: {}", id);
-        Ok(())
+    #[instrument(name = "user.repo.session_states", skip(self, ids), fields(users = ids.len()))]
+    async fn session_states(
+        &self,
+        ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, UserSessionState>, UserError> {
+        observe_within("user.repo.session_states", self.timeouts.read, async {
+            self.check_rate_limit().await?;
+
+            let rows = sqlx::query(
+                r#"
+                SELECT id, is_active, password_changed_at, password_reset_required
+                FROM users
+                WHERE id = ANY($1)
+                "#,
+            )
+            .bind(ids)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| UserError::DatabaseError(e.to_string()))?;
+
+            rows.iter()
+                .map(|row| {
+                    let state = UserSessionState {
+                        is_active: row.try_get("is_active")?,
+                        password_changed_at: row.try_get("password_changed_at")?,
+                        password_reset_required: row.try_get("password_reset_required")?,
+                    };
+                    Ok((row.try_get("id")?, state))
+                })
+                .collect::<Result<_, sqlx::Error>>()
+                .map_err(|e| UserError::DatabaseError(e.to_string()))
+        })
+        .await
     }
 
-    #[instrument(skip(self))]
-    async fn deactivate(&self, id: Uuid) -> Result<(), UserError> {
-        self.check_rate_limit().await?;
+    #[instrument(name = "user.repo.require_password_reset", skip(self))]
+    async fn require_password_reset(&self, id: Uuid) -> Result<bool, UserError> {
+        observe_within(
+            "user.repo.require_password_reset",
+            self.timeouts.write,
+            async {
+                self.check_rate_limit().await?;
+
+                // Reports the previous value, so repeated requests change nothing
+                let row = sqlx::query(
+                    r#"
+                UPDATE users u
+                SET password_reset_required = TRUE
+                FROM (
+                    SELECT id, password_reset_required
+                    FROM users
+                    WHERE id = $1
+                    FOR UPDATE
+                ) previous
+                WHERE u.id = previous.id
+                RETURNING previous.password_reset_required AS was_required
+                "#,
+                )
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| UserError::DatabaseError(e.to_string()))?
+                .ok_or(UserError::NotFound)?;
 
-        let now = OffsetDateTime::now_utc();
-        let result = sqlx::query(
-            r#"
-            UPDATE users
-            SET
-                is_active = false,
-                updated_at = $1
-            WHERE id = $2
-            "#,
+                let was_required: bool = row
+                    .try_get("was_required")
+                    .map_err(|e| UserError::DatabaseError(e.to_string()))?;
+                Ok(!was_required)
+            },
         )
-        .bind(now)
-        .bind(id)
-        .execute(&self.pool)
         .await
-        .map_err(|e| UserError::DatabaseError(e.to_string()))?;
+    }
 
-        if result.rows_affected() == 0 {
-            return Err(UserError::NotFound);
-        }
+    #[instrument(name = "user.repo.password_history", skip(self))]
+    async fn password_history(&self, id: Uuid, limit: usize) -> Result<Vec<String>, UserError> {
+        observe_within("user.repo.password_history", self.timeouts.read, async {
+            self.check_rate_limit().await?;
+
+            let rows = sqlx::query(
+                r#"
+                SELECT password_hash
+                FROM password_history
+                WHERE user_id = $1
+                ORDER BY created_at DESC, id DESC
+                LIMIT $2
+                "#,
+            )
+            .bind(id)
+            .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| UserError::DatabaseError(e.to_string()))?;
 
-        // Log audit event
-        self.log_audit(AuditEvent {
-            user_id: id,
-            action: "USER_DEACTIVATED".to_string(),
-            details: {
-                let mut map = serde_json::Map::new();
-                map.insert(
-                    "deactivated_at".to_string(),
-                    serde_json::Value::String(now.to_string()),
-                );
-                serde_json::Value::Object(map)
-            },
-            ip_address: None,
-            user_agent: None,
+            rows.iter()
+                .map(|row| row.try_get("password_hash"))
+                .collect::<Result<_, sqlx::Error>>()
+                .map_err(|e| UserError::DatabaseError(e.to_string()))
         })
-        .await?;
-
-        info!("User deactivated successfully: {}", id);
-        Ok(())
+        .await
     }
 
-    #[instrument(skip(self))]
-    async fn activate(&self, id: Uuid) -> Result<(), UserError> {
-        self.check_rate_limit().await?;
+    #[instrument(name = "user.repo.record_password_history", skip(self, password_hash))]
+    async fn record_password_history(
+        &self,
+        id: Uuid,
+        password_hash: &str,
+        keep: usize,
+    ) -> Result<(), UserError> {
+        observe_within(
+            "user.repo.record_password_history",
+            self.timeouts.write,
+            async {
+                self.check_rate_limit().await?;
+
+                let mut tx = self
+                    .pool
+                    .begin()
+                    .await
+                    .map_err(|e| UserError::DatabaseError(e.to_string()))?;
+
+                sqlx::query(
+                    "INSERT INTO password_history (user_id, password_hash) VALUES ($1, $2)",
+                )
+                .bind(id)
+                .bind(password_hash)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| UserError::DatabaseError(e.to_string()))?;
+
+                // Only the newest entries are ever checked
+                sqlx::query(
+                    r#"
+                DELETE FROM password_history
+                WHERE user_id = $1
+                  AND id NOT IN (
+                      SELECT id FROM password_history
+                      WHERE user_id = $1
+                      ORDER BY created_at DESC, id DESC
+                      LIMIT $2
+                  )
+                "#,
+                )
+                .bind(id)
+                .bind(i64::try_from(keep).unwrap_or(i64::MAX))
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| UserError::DatabaseError(e.to_string()))?;
 
-        let now = OffsetDateTime::now_utc();
-        let result = sqlx::query(
-            r#"
-            UPDATE users
-            SET
-                is_active = true,
-                updated_at = $1
-            WHERE id = $2
-            "#,
+                tx.commit()
+                    .await
+                    .map_err(|e| UserError::DatabaseError(e.to_string()))
+            },
         )
-        .bind(now)
-        .bind(id)
-        .execute(&self.pool)
         .await
-        .map_err(|e| UserError::DatabaseError(e.to_string()))?;
-
-        if result.rows_affected() == 0 {
-            return Err(UserError::NotFound);
-        }
+    }
 
-        // Log audit event
-        self.log_audit(AuditEvent {
-            user_id: id,
-            action: "USER_ACTIVATED".to_string(),
-            details: {
-                let mut map = serde_json::Map::new();
-                map.insert(
-                    "activated_at".to_string(),
-                    serde_json::Value::String(now.to_string()),
-                );
-                serde_json::Value::Object(map)
-            },
-            ip_address: None,
-            user_agent: None,
+    #[instrument(name = "user.repo.log_security_event", skip(self, details))]
+    async fn log_security_event(
+        &self,
+        user_id: Uuid,
+        action: &str,
+        details: serde_json::Value,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+    ) -> Result<(), UserError> {
+        observe_within("user.repo.log_security_event", self.timeouts.write, async {
+            self.log_audit(AuditEvent {
+                user_id,
+                action: action.to_string(),
+                details,
+                ip_address,
+                user_agent,
+            })
+            .await
         })
-        .await?;
-
-        info!("User activated successfully: {}", id);
-        Ok(())
+        .await
     }
 }
@@ -0,0 +1,177 @@
+use crate::models::account_deletion::AccountDeletionRequest;
+use crate::repository::instrumentation::observe;
+use crate::repository::{AccountDeletionRepository, RepositoryError};
+use async_trait::async_trait;
+use sqlx::postgres::PgRow;
+use sqlx::{Pool, Postgres, Row};
+use time::OffsetDateTime;
+use tracing::instrument;
+use uuid::Uuid;
+
+/// PostgreSQL implementation of the AccountDeletionRepository
+pub struct PostgresAccountDeletionRepository {
+    pool: Pool<Postgres>,
+}
+
+impl PostgresAccountDeletionRepository {
+    /// Create a new PostgresAccountDeletionRepository
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    fn map_row(row: PgRow) -> Result<AccountDeletionRequest, sqlx::Error> {
+        Ok(AccountDeletionRequest {
+            user_id: row.try_get("user_id")?,
+            requested_at: row.try_get("requested_at")?,
+            scheduled_for: row.try_get("scheduled_for")?,
+            ownership_notice_sent_at: row.try_get("ownership_notice_sent_at")?,
+            completed_at: row.try_get("completed_at")?,
+        })
+    }
+}
+
+#[async_trait]
+impl AccountDeletionRepository for PostgresAccountDeletionRepository {
+    #[instrument(name = "account_deletion.repo.create", skip_all, level = "debug")]
+    async fn create(&self, request: &AccountDeletionRequest) -> Result<(), RepositoryError> {
+        observe("account_deletion.repo.create", async {
+            sqlx::query(
+                r#"
+                INSERT INTO account_deletion_requests (
+                    user_id, requested_at, scheduled_for, ownership_notice_sent_at, completed_at
+                ) VALUES ($1, $2, $3, $4, $5)
+                "#,
+            )
+            .bind(request.user_id)
+            .bind(request.requested_at)
+            .bind(request.scheduled_for)
+            .bind(request.ownership_notice_sent_at)
+            .bind(request.completed_at)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::Database(ref db) if db.is_unique_violation() => {
+                    RepositoryError::UniqueViolation(db.message().to_string())
+                },
+                _ => RepositoryError::DatabaseError(e.to_string()),
+            })?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "account_deletion.repo.find_pending", skip_all, level = "debug")]
+    async fn find_pending(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Option<AccountDeletionRequest>, RepositoryError> {
+        observe("account_deletion.repo.find_pending", async {
+            sqlx::query(
+                r#"
+                SELECT user_id, requested_at, scheduled_for, ownership_notice_sent_at, completed_at
+                FROM account_deletion_requests
+                WHERE user_id = $1 AND completed_at IS NULL
+                "#,
+            )
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .and_then(|row| row.map(Self::map_row).transpose())
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))
+        })
+        .await
+    }
+
+    #[instrument(name = "account_deletion.repo.cancel", skip_all, level = "debug")]
+    async fn cancel(&self, user_id: Uuid) -> Result<bool, RepositoryError> {
+        observe("account_deletion.repo.cancel", async {
+            let result = sqlx::query(
+                "DELETE FROM account_deletion_requests WHERE user_id = $1 AND completed_at IS NULL",
+            )
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+            Ok(result.rows_affected() > 0)
+        })
+        .await
+    }
+
+    #[instrument(name = "account_deletion.repo.list_due", skip_all, level = "debug")]
+    async fn list_due(
+        &self,
+        now: OffsetDateTime,
+        limit: u32,
+    ) -> Result<Vec<AccountDeletionRequest>, RepositoryError> {
+        observe("account_deletion.repo.list_due", async {
+            let rows = sqlx::query(
+                r#"
+                SELECT user_id, requested_at, scheduled_for, ownership_notice_sent_at, completed_at
+                FROM account_deletion_requests
+                WHERE completed_at IS NULL AND scheduled_for <= $1
+                ORDER BY scheduled_for
+                LIMIT $2
+                "#,
+            )
+            .bind(now)
+            .bind(i64::from(limit))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+            rows.into_iter()
+                .map(Self::map_row)
+                .collect::<Result<_, sqlx::Error>>()
+                .map_err(|e| RepositoryError::DatabaseError(e.to_string()))
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "account_deletion.repo.record_ownership_notice",
+        skip_all,
+        level = "debug"
+    )]
+    async fn record_ownership_notice(
+        &self,
+        user_id: Uuid,
+        sent_at: OffsetDateTime,
+    ) -> Result<(), RepositoryError> {
+        observe("account_deletion.repo.record_ownership_notice", async {
+            sqlx::query(
+                "UPDATE account_deletion_requests SET ownership_notice_sent_at = $2 WHERE user_id = $1",
+            )
+            .bind(user_id)
+            .bind(sent_at)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "account_deletion.repo.complete", skip_all, level = "debug")]
+    async fn complete(
+        &self,
+        user_id: Uuid,
+        completed_at: OffsetDateTime,
+    ) -> Result<(), RepositoryError> {
+        observe("account_deletion.repo.complete", async {
+            sqlx::query(
+                "UPDATE account_deletion_requests SET completed_at = $2 WHERE user_id = $1",
+            )
+            .bind(user_id)
+            .bind(completed_at)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+            Ok(())
+        })
+        .await
+    }
+}
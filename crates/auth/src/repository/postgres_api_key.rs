@@ -0,0 +1,325 @@
+use crate::models::api_key::{ApiKey, ApiKeyFilter};
+use crate::repository::instrumentation::observe;
+use crate::repository::{ApiKeyRepository, RepositoryError};
+use async_trait::async_trait;
+use sqlx::postgres::PgRow;
+use sqlx::{Pool, Postgres, Row};
+use time::OffsetDateTime;
+use tracing::instrument;
+use uuid::Uuid;
+
+/// PostgreSQL implementation of the ApiKeyRepository
+pub struct PostgresApiKeyRepository {
+    pool: Pool<Postgres>,
+}
+
+impl PostgresApiKeyRepository {
+    /// Create a new PostgresApiKeyRepository
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    fn map_row(row: PgRow) -> Result<ApiKey, sqlx::Error> {
+        Ok(ApiKey {
+            id: row.try_get("id")?,
+            tenant_id: row.try_get("tenant_id")?,
+            name: row.try_get("name")?,
+            description: row.try_get("description")?,
+            tags: row.try_get("tags")?,
+            prefix: row.try_get("prefix")?,
+            key_hash: row.try_get("key_hash")?,
+            scopes: row.try_get("scopes")?,
+            created_by: row.try_get("created_by")?,
+            created_at: row.try_get("created_at")?,
+            expires_at: row.try_get("expires_at")?,
+            last_used_at: row.try_get("last_used_at")?,
+            revoked_at: row.try_get("revoked_at")?,
+            reminded_at: row.try_get("reminded_at")?,
+        })
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepository for PostgresApiKeyRepository {
+    #[instrument(name = "api_key.repo.create", skip_all, level = "debug")]
+    async fn create(&self, api_key: &ApiKey) -> Result<(), RepositoryError> {
+        observe("api_key.repo.create", async {
+            sqlx::query(
+                r#"
+                INSERT INTO api_keys (
+                    id, tenant_id, name, description, tags, prefix, key_hash, scopes,
+                    created_by, created_at, expires_at, last_used_at, revoked_at, reminded_at
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+                "#,
+            )
+            .bind(api_key.id)
+            .bind(api_key.tenant_id)
+            .bind(&api_key.name)
+            .bind(&api_key.description)
+            .bind(&api_key.tags)
+            .bind(&api_key.prefix)
+            .bind(&api_key.key_hash)
+            .bind(&api_key.scopes)
+            .bind(api_key.created_by)
+            .bind(api_key.created_at)
+            .bind(api_key.expires_at)
+            .bind(api_key.last_used_at)
+            .bind(api_key.revoked_at)
+            .bind(api_key.reminded_at)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::Database(ref db) if db.is_unique_violation() => {
+                    RepositoryError::UniqueViolation(db.message().to_string())
+                },
+                _ => RepositoryError::DatabaseError(e.to_string()),
+            })?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "api_key.repo.find_by_prefix", skip_all, level = "debug")]
+    async fn find_by_prefix(&self, prefix: &str) -> Result<Option<ApiKey>, RepositoryError> {
+        observe("api_key.repo.find_by_prefix", async {
+            sqlx::query(
+                r#"
+                SELECT id, tenant_id, name, description, tags, prefix, key_hash, scopes,
+                       created_by, created_at, expires_at, last_used_at, revoked_at, reminded_at
+                FROM api_keys
+                WHERE prefix = $1
+                "#,
+            )
+            .bind(prefix)
+            .fetch_optional(&self.pool)
+            .await
+            .and_then(|row| row.map(Self::map_row).transpose())
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))
+        })
+        .await
+    }
+
+    #[instrument(name = "api_key.repo.find_by_id", skip_all, level = "debug")]
+    async fn find_by_id(
+        &self,
+        tenant_id: Uuid,
+        id: Uuid,
+    ) -> Result<Option<ApiKey>, RepositoryError> {
+        observe("api_key.repo.find_by_id", async {
+            sqlx::query(
+                r#"
+                SELECT id, tenant_id, name, description, tags, prefix, key_hash, scopes,
+                       created_by, created_at, expires_at, last_used_at, revoked_at, reminded_at
+                FROM api_keys
+                WHERE tenant_id = $1 AND id = $2
+                "#,
+            )
+            .bind(tenant_id)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .and_then(|row| row.map(Self::map_row).transpose())
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))
+        })
+        .await
+    }
+
+    #[instrument(name = "api_key.repo.list", skip_all, level = "debug")]
+    async fn list(
+        &self,
+        tenant_id: Uuid,
+        filter: &ApiKeyFilter,
+    ) -> Result<Vec<ApiKey>, RepositoryError> {
+        observe("api_key.repo.list", async {
+            // Unset criteria are passed as NULL and match every key
+            let rows = sqlx::query(
+                r#"
+                SELECT id, tenant_id, name, description, tags, prefix, key_hash, scopes,
+                       created_by, created_at, expires_at, last_used_at, revoked_at, reminded_at
+                FROM api_keys
+                WHERE tenant_id = $1
+                  AND tags @> $2
+                  AND ($3::TEXT IS NULL
+                       OR strpos(lower(name), lower($3)) > 0
+                       OR strpos(prefix, lower($3)) > 0
+                       OR strpos(lower(COALESCE(description, '')), lower($3)) > 0)
+                  AND ($4::TIMESTAMPTZ IS NULL
+                       OR (revoked_at IS NULL AND (expires_at IS NULL OR expires_at > $4)))
+                  AND ($5::TIMESTAMPTZ IS NULL OR expires_at < $5)
+                  AND ($6::TIMESTAMPTZ IS NULL OR COALESCE(last_used_at, created_at) < $6)
+                ORDER BY created_at DESC
+                "#,
+            )
+            .bind(tenant_id)
+            .bind(&filter.tags)
+            .bind(&filter.search)
+            .bind(filter.valid_at)
+            .bind(filter.expires_before)
+            .bind(filter.unused_since)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+            rows.into_iter()
+                .map(Self::map_row)
+                .collect::<Result<_, sqlx::Error>>()
+                .map_err(|e| RepositoryError::DatabaseError(e.to_string()))
+        })
+        .await
+    }
+
+    #[instrument(name = "api_key.repo.list_reminder_due", skip_all, level = "debug")]
+    async fn list_reminder_due(
+        &self,
+        now: OffsetDateTime,
+        expires_before: OffsetDateTime,
+        unused_since: OffsetDateTime,
+        reminded_since: OffsetDateTime,
+        limit: u32,
+    ) -> Result<Vec<ApiKey>, RepositoryError> {
+        observe("api_key.repo.list_reminder_due", async {
+            let rows = sqlx::query(
+                r#"
+                SELECT id, tenant_id, name, description, tags, prefix, key_hash, scopes,
+                       created_by, created_at, expires_at, last_used_at, revoked_at, reminded_at
+                FROM api_keys
+                WHERE revoked_at IS NULL
+                  AND (expires_at IS NULL OR expires_at > $1)
+                  AND (expires_at < $2 OR COALESCE(last_used_at, created_at) < $3)
+                  AND (reminded_at IS NULL OR reminded_at < $4)
+                ORDER BY reminded_at ASC NULLS FIRST, id
+                LIMIT $5
+                "#,
+            )
+            .bind(now)
+            .bind(expires_before)
+            .bind(unused_since)
+            .bind(reminded_since)
+            .bind(i64::from(limit))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+            rows.into_iter()
+                .map(Self::map_row)
+                .collect::<Result<_, sqlx::Error>>()
+                .map_err(|e| RepositoryError::DatabaseError(e.to_string()))
+        })
+        .await
+    }
+
+    #[instrument(name = "api_key.repo.record_reminder", skip_all, level = "debug")]
+    async fn record_reminder(
+        &self,
+        ids: &[Uuid],
+        reminded_at: OffsetDateTime,
+    ) -> Result<(), RepositoryError> {
+        observe("api_key.repo.record_reminder", async {
+            sqlx::query("UPDATE api_keys SET reminded_at = $2 WHERE id = ANY($1)")
+                .bind(ids)
+                .bind(reminded_at)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "api_key.repo.revoke", skip_all, level = "debug")]
+    async fn revoke(
+        &self,
+        tenant_id: Uuid,
+        id: Uuid,
+        revoked_at: OffsetDateTime,
+    ) -> Result<bool, RepositoryError> {
+        observe("api_key.repo.revoke", async {
+            let result = sqlx::query(
+                r#"
+                UPDATE api_keys
+                SET revoked_at = $3
+                WHERE tenant_id = $1 AND id = $2 AND revoked_at IS NULL
+                "#,
+            )
+            .bind(tenant_id)
+            .bind(id)
+            .bind(revoked_at)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+            Ok(result.rows_affected() > 0)
+        })
+        .await
+    }
+
+    #[instrument(name = "api_key.repo.expire", skip_all, level = "debug")]
+    async fn expire(
+        &self,
+        tenant_id: Uuid,
+        id: Uuid,
+        expires_at: OffsetDateTime,
+    ) -> Result<bool, RepositoryError> {
+        observe("api_key.repo.expire", async {
+            // LEAST ignores NULL, so keys without an expiry get `expires_at`
+            let result = sqlx::query(
+                r#"
+                UPDATE api_keys
+                SET expires_at = LEAST(expires_at, $3)
+                WHERE tenant_id = $1 AND id = $2 AND revoked_at IS NULL
+                "#,
+            )
+            .bind(tenant_id)
+            .bind(id)
+            .bind(expires_at)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+            Ok(result.rows_affected() > 0)
+        })
+        .await
+    }
+
+    #[instrument(name = "api_key.repo.revoke_created_by", skip_all, level = "debug")]
+    async fn revoke_created_by(
+        &self,
+        created_by: Uuid,
+        revoked_at: OffsetDateTime,
+    ) -> Result<u64, RepositoryError> {
+        observe("api_key.repo.revoke_created_by", async {
+            let result = sqlx::query(
+                r#"
+                UPDATE api_keys
+                SET revoked_at = $2
+                WHERE created_by = $1 AND revoked_at IS NULL
+                "#,
+            )
+            .bind(created_by)
+            .bind(revoked_at)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+            Ok(result.rows_affected())
+        })
+        .await
+    }
+
+    #[instrument(name = "api_key.repo.record_use", skip_all, level = "debug")]
+    async fn record_use(&self, id: Uuid, used_at: OffsetDateTime) -> Result<(), RepositoryError> {
+        observe("api_key.repo.record_use", async {
+            sqlx::query("UPDATE api_keys SET last_used_at = $2 WHERE id = $1")
+                .bind(id)
+                .bind(used_at)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+            Ok(())
+        })
+        .await
+    }
+}
@@ -0,0 +1,174 @@
+use async_trait::async_trait;
+use sqlx::{PgPool, Row, postgres::PgRow};
+use time::OffsetDateTime;
+use tracing::{instrument, trace};
+use uuid::Uuid;
+
+use crate::models::{DeliveryStatus, MessageDelivery, VerificationType};
+use crate::repository::delivery_repository::MessageDeliveryRepository;
+use crate::repository::instrumentation::observe;
+use acci_core::error::{Error, Result};
+
+const DELIVERY_COLUMNS: &str = "id, tenant_id, user_id, message_type, recipient, subject, body, \
+     provider_message_id, status, attempt, retry_of, error, created_at, updated_at";
+
+/// PostgreSQL implementation of the message delivery repository
+pub struct PostgresMessageDeliveryRepository {
+    pool: PgPool,
+}
+
+impl PostgresMessageDeliveryRepository {
+    /// Create a new PostgreSQL message delivery repository
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn parse_message_type(value: &str) -> Result<VerificationType> {
+    match value {
+        "Email" => Ok(VerificationType::Email),
+        "Sms" => Ok(VerificationType::Sms),
+        _ => Err(Error::Validation(format!(
+            "Invalid delivery message type: {}",
+            value
+        ))),
+    }
+}
+
+fn parse_status(value: &str) -> Result<DeliveryStatus> {
+    match value {
+        "Queued" => Ok(DeliveryStatus::Queued),
+        "Sent" => Ok(DeliveryStatus::Sent),
+        "Delivered" => Ok(DeliveryStatus::Delivered),
+        "Failed" => Ok(DeliveryStatus::Failed),
+        _ => Err(Error::Validation(format!(
+            "Invalid delivery status: {}",
+            value
+        ))),
+    }
+}
+
+fn delivery_from_row(row: &PgRow) -> Result<MessageDelivery> {
+    let message_type: String = row.try_get("message_type").map_err(Error::Database)?;
+    let status: String = row.try_get("status").map_err(Error::Database)?;
+    let attempt: i32 = row.try_get("attempt").map_err(Error::Database)?;
+
+    Ok(MessageDelivery {
+        id: row.try_get("id").map_err(Error::Database)?,
+        tenant_id: row.try_get("tenant_id").map_err(Error::Database)?,
+        user_id: row.try_get("user_id").map_err(Error::Database)?,
+        message_type: parse_message_type(&message_type)?,
+        recipient: row.try_get("recipient").map_err(Error::Database)?,
+        subject: row.try_get("subject").map_err(Error::Database)?,
+        body: row.try_get("body").map_err(Error::Database)?,
+        provider_message_id: row
+            .try_get("provider_message_id")
+            .map_err(Error::Database)?,
+        status: parse_status(&status)?,
+        attempt: attempt.max(1) as u32,
+        retry_of: row.try_get("retry_of").map_err(Error::Database)?,
+        error: row.try_get("error").map_err(Error::Database)?,
+        created_at: row.try_get("created_at").map_err(Error::Database)?,
+        updated_at: row.try_get("updated_at").map_err(Error::Database)?,
+    })
+}
+
+#[async_trait]
+impl MessageDeliveryRepository for PostgresMessageDeliveryRepository {
+    #[instrument(name = "delivery.repo.record", skip(self, delivery), level = "debug")]
+    async fn record(&self, delivery: &MessageDelivery) -> Result<()> {
+        observe("delivery.repo.record", async {
+            sqlx::query(
+                r#"
+                INSERT INTO message_deliveries (
+                    id, tenant_id, user_id, message_type, recipient, subject, body,
+                    provider_message_id, status, attempt, retry_of, error, created_at, updated_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+                "#,
+            )
+            .bind(delivery.id)
+            .bind(delivery.tenant_id)
+            .bind(delivery.user_id)
+            .bind(format!("{:?}", delivery.message_type))
+            .bind(&delivery.recipient)
+            .bind(&delivery.subject)
+            .bind(&delivery.body)
+            .bind(&delivery.provider_message_id)
+            .bind(format!("{:?}", delivery.status))
+            .bind(delivery.attempt as i32)
+            .bind(delivery.retry_of)
+            .bind(&delivery.error)
+            .bind(delivery.created_at)
+            .bind(delivery.updated_at)
+            .execute(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+            trace!("Recorded message delivery with ID: {}", delivery.id);
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "delivery.repo.find_by_provider_message_id",
+        skip(self),
+        level = "debug"
+    )]
+    async fn find_by_provider_message_id(
+        &self,
+        provider_message_id: &str,
+    ) -> Result<Option<MessageDelivery>> {
+        observe("delivery.repo.find_by_provider_message_id", async {
+            let query = format!(
+                "SELECT {} FROM message_deliveries WHERE provider_message_id = $1",
+                DELIVERY_COLUMNS
+            );
+
+            let row = sqlx::query(&query)
+                .bind(provider_message_id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(Error::Database)?;
+
+            row.as_ref().map(delivery_from_row).transpose()
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "delivery.repo.update_status",
+        skip(self, error),
+        level = "debug"
+    )]
+    async fn update_status(
+        &self,
+        id: Uuid,
+        from: DeliveryStatus,
+        to: DeliveryStatus,
+        error: Option<&str>,
+        now: OffsetDateTime,
+    ) -> Result<bool> {
+        observe("delivery.repo.update_status", async {
+            let result = sqlx::query(
+                r#"
+                UPDATE message_deliveries
+                SET status = $3, error = COALESCE($4, error), updated_at = $5
+                WHERE id = $1 AND status = $2
+                "#,
+            )
+            .bind(id)
+            .bind(format!("{:?}", from))
+            .bind(format!("{:?}", to))
+            .bind(error)
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+            Ok(result.rows_affected() == 1)
+        })
+        .await
+    }
+}
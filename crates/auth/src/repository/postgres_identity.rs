@@ -0,0 +1,83 @@
+use crate::models::identity::LinkedIdentity;
+use crate::repository::instrumentation::observe;
+use crate::repository::{ExternalIdentityRepository, RepositoryError};
+use async_trait::async_trait;
+use sqlx::{Pool, Postgres, Row};
+use tracing::instrument;
+use uuid::Uuid;
+
+/// PostgreSQL implementation of the ExternalIdentityRepository
+pub struct PostgresExternalIdentityRepository {
+    pool: Pool<Postgres>,
+}
+
+impl PostgresExternalIdentityRepository {
+    /// Create a new PostgresExternalIdentityRepository
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ExternalIdentityRepository for PostgresExternalIdentityRepository {
+    #[instrument(name = "identity.repo.link", skip_all, level = "debug")]
+    async fn link(&self, identity: &LinkedIdentity) -> Result<(), RepositoryError> {
+        observe("identity.repo.link", async {
+            sqlx::query(
+                r#"
+                INSERT INTO user_external_identities (
+                    user_id, provider, subject, email, linked_at, last_login_at
+                ) VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (provider, subject) DO UPDATE
+                SET email = EXCLUDED.email, last_login_at = EXCLUDED.last_login_at
+                WHERE user_external_identities.user_id = EXCLUDED.user_id
+                "#,
+            )
+            .bind(identity.user_id)
+            .bind(&identity.provider)
+            .bind(&identity.subject)
+            .bind(&identity.email)
+            .bind(identity.linked_at)
+            .bind(identity.last_login_at)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "identity.repo.list_for_user", skip_all, level = "debug")]
+    async fn list_for_user(&self, user_id: &Uuid) -> Result<Vec<LinkedIdentity>, RepositoryError> {
+        observe("identity.repo.list_for_user", async {
+            let rows = sqlx::query(
+                r#"
+                SELECT user_id, provider, subject, email, linked_at, last_login_at
+                FROM user_external_identities
+                WHERE user_id = $1
+                ORDER BY linked_at
+                "#,
+            )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+            rows.into_iter()
+                .map(|row| {
+                    Ok(LinkedIdentity {
+                        user_id: row.try_get("user_id")?,
+                        provider: row.try_get("provider")?,
+                        subject: row.try_get("subject")?,
+                        email: row.try_get("email")?,
+                        linked_at: row.try_get("linked_at")?,
+                        last_login_at: row.try_get("last_login_at")?,
+                    })
+                })
+                .collect::<Result<_, sqlx::Error>>()
+                .map_err(|e| RepositoryError::DatabaseError(e.to_string()))
+        })
+        .await
+    }
+}
@@ -0,0 +1,93 @@
+use crate::models::login_attempt::{LoginAttemptRecord, LoginAttemptStats};
+use crate::repository::instrumentation::observe;
+use crate::repository::{LoginAttemptRepository, RepositoryError};
+use async_trait::async_trait;
+use sqlx::{Pool, Postgres, Row};
+use time::OffsetDateTime;
+use tracing::instrument;
+use uuid::Uuid;
+
+/// PostgreSQL implementation of the LoginAttemptRepository
+pub struct PostgresLoginAttemptRepository {
+    pool: Pool<Postgres>,
+}
+
+impl PostgresLoginAttemptRepository {
+    /// Create a new PostgresLoginAttemptRepository
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl LoginAttemptRepository for PostgresLoginAttemptRepository {
+    #[instrument(name = "login_attempt.repo.record", skip_all, level = "debug")]
+    async fn record(&self, attempt: &LoginAttemptRecord) -> Result<(), RepositoryError> {
+        observe("login_attempt.repo.record", async {
+            sqlx::query(
+                r#"
+                INSERT INTO login_attempts (
+                    id, tenant_id, username_hash, ip_address, user_agent, fingerprint_hash,
+                    country_code, city, risk_level, outcome, attempted_at
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                "#,
+            )
+            .bind(attempt.id)
+            .bind(attempt.tenant_id)
+            .bind(&attempt.username_hash)
+            .bind(&attempt.ip_address)
+            .bind(&attempt.user_agent)
+            .bind(&attempt.fingerprint_hash)
+            .bind(&attempt.country_code)
+            .bind(&attempt.city)
+            .bind(attempt.risk_level.map(|level| level.to_string()))
+            .bind(attempt.outcome.as_str())
+            .bind(attempt.attempted_at)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "login_attempt.repo.stats", skip_all, level = "debug")]
+    async fn stats(
+        &self,
+        tenant_id: Uuid,
+        since: OffsetDateTime,
+    ) -> Result<LoginAttemptStats, RepositoryError> {
+        observe("login_attempt.repo.stats", async {
+            let row = sqlx::query(
+                r#"
+                SELECT
+                    COUNT(*) AS total,
+                    COUNT(*) FILTER (WHERE outcome = 'succeeded') AS succeeded
+                FROM login_attempts
+                WHERE tenant_id = $1 AND attempted_at >= $2
+                "#,
+            )
+            .bind(tenant_id)
+            .bind(since)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+            let count = |column: &str| -> Result<u64, RepositoryError> {
+                row.try_get::<i64, _>(column)
+                    .map(|count| count as u64)
+                    .map_err(|e| RepositoryError::DatabaseError(e.to_string()))
+            };
+            let total = count("total")?;
+            let succeeded = count("succeeded")?;
+
+            Ok(LoginAttemptStats {
+                total,
+                succeeded,
+                failed: total - succeeded,
+            })
+        })
+        .await
+    }
+}
@@ -0,0 +1,274 @@
+use async_trait::async_trait;
+use sqlx::{PgPool, Row, postgres::PgRow};
+use time::OffsetDateTime;
+use tracing::{instrument, trace};
+use uuid::Uuid;
+
+use crate::models::{OutboxMessage, OutboxStatus, VerificationType};
+use crate::repository::instrumentation::observe;
+use crate::repository::outbox_repository::MessageOutboxRepository;
+use acci_core::error::{Error, Result};
+
+const OUTBOX_COLUMNS: &str = "id, seq, tenant_id, user_id, message_type, recipient, subject, body, \
+     status, attempts, next_attempt_at, last_error, provider_message_id, created_at, updated_at, \
+     sent_at";
+
+/// PostgreSQL implementation of the message outbox
+pub struct PostgresMessageOutboxRepository {
+    pool: PgPool,
+}
+
+impl PostgresMessageOutboxRepository {
+    /// Create a new PostgreSQL message outbox repository
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn parse_message_type(value: &str) -> Result<VerificationType> {
+    match value {
+        "Email" => Ok(VerificationType::Email),
+        "Sms" => Ok(VerificationType::Sms),
+        _ => Err(Error::Validation(format!(
+            "Invalid outbox message type: {}",
+            value
+        ))),
+    }
+}
+
+fn parse_status(value: &str) -> Result<OutboxStatus> {
+    match value {
+        "Pending" => Ok(OutboxStatus::Pending),
+        "Sent" => Ok(OutboxStatus::Sent),
+        "Failed" => Ok(OutboxStatus::Failed),
+        _ => Err(Error::Validation(format!(
+            "Invalid outbox status: {}",
+            value
+        ))),
+    }
+}
+
+fn outbox_message_from_row(row: &PgRow) -> Result<OutboxMessage> {
+    let message_type: String = row.try_get("message_type").map_err(Error::Database)?;
+    let status: String = row.try_get("status").map_err(Error::Database)?;
+    let attempts: i32 = row.try_get("attempts").map_err(Error::Database)?;
+
+    Ok(OutboxMessage {
+        id: row.try_get("id").map_err(Error::Database)?,
+        tenant_id: row.try_get("tenant_id").map_err(Error::Database)?,
+        user_id: row.try_get("user_id").map_err(Error::Database)?,
+        message_type: parse_message_type(&message_type)?,
+        recipient: row.try_get("recipient").map_err(Error::Database)?,
+        subject: row.try_get("subject").map_err(Error::Database)?,
+        body: row.try_get("body").map_err(Error::Database)?,
+        status: parse_status(&status)?,
+        attempts: attempts.max(0) as u32,
+        next_attempt_at: row.try_get("next_attempt_at").map_err(Error::Database)?,
+        last_error: row.try_get("last_error").map_err(Error::Database)?,
+        provider_message_id: row
+            .try_get("provider_message_id")
+            .map_err(Error::Database)?,
+        created_at: row.try_get("created_at").map_err(Error::Database)?,
+        updated_at: row.try_get("updated_at").map_err(Error::Database)?,
+        sent_at: row.try_get("sent_at").map_err(Error::Database)?,
+    })
+}
+
+#[async_trait]
+impl MessageOutboxRepository for PostgresMessageOutboxRepository {
+    #[instrument(name = "outbox.repo.enqueue", skip(self, message), level = "debug")]
+    async fn enqueue(&self, message: &OutboxMessage) -> Result<()> {
+        observe("outbox.repo.enqueue", async {
+            sqlx::query(
+                r#"
+                INSERT INTO message_outbox (
+                    id, tenant_id, user_id, message_type, recipient, subject, body,
+                    status, attempts, next_attempt_at, created_at, updated_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                "#,
+            )
+            .bind(message.id)
+            .bind(message.tenant_id)
+            .bind(message.user_id)
+            .bind(format!("{:?}", message.message_type))
+            .bind(&message.recipient)
+            .bind(&message.subject)
+            .bind(&message.body)
+            .bind(format!("{:?}", message.status))
+            .bind(message.attempts as i32)
+            .bind(message.next_attempt_at)
+            .bind(message.created_at)
+            .bind(message.updated_at)
+            .execute(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+            trace!("Enqueued outbox message with ID: {}", message.id);
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "outbox.repo.claim_due", skip(self), level = "debug")]
+    async fn claim_due(
+        &self,
+        message_type: VerificationType,
+        now: OffsetDateTime,
+        lease_until: OffsetDateTime,
+        limit: u32,
+    ) -> Result<Vec<OutboxMessage>> {
+        observe("outbox.repo.claim_due", async {
+            // SKIP LOCKED lets several dispatcher instances drain the queue without
+            // contending for the same rows
+            let query = format!(
+                r#"
+                UPDATE message_outbox
+                SET attempts = attempts + 1, locked_until = $3, updated_at = $2
+                WHERE id IN (
+                    SELECT id FROM message_outbox
+                    WHERE status = 'Pending'
+                        AND message_type = $1
+                        AND next_attempt_at <= $2
+                        AND (locked_until IS NULL OR locked_until <= $2)
+                    ORDER BY seq
+                    LIMIT $4
+                    FOR UPDATE SKIP LOCKED
+                )
+                RETURNING {}
+                "#,
+                OUTBOX_COLUMNS
+            );
+
+            let rows = sqlx::query(&query)
+                .bind(format!("{:?}", message_type))
+                .bind(now)
+                .bind(lease_until)
+                .bind(i64::from(limit))
+                .fetch_all(&self.pool)
+                .await
+                .map_err(Error::Database)?;
+
+            // RETURNING does not preserve the ORDER BY of the subquery
+            let mut claimed = rows
+                .iter()
+                .map(|row| {
+                    let seq: i64 = row.try_get("seq").map_err(Error::Database)?;
+                    Ok((seq, outbox_message_from_row(row)?))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            claimed.sort_by_key(|(seq, _)| *seq);
+
+            Ok(claimed.into_iter().map(|(_, message)| message).collect())
+        })
+        .await
+    }
+
+    #[instrument(name = "outbox.repo.mark_sent", skip(self), level = "debug")]
+    async fn mark_sent(
+        &self,
+        id: Uuid,
+        provider_message_id: &str,
+        now: OffsetDateTime,
+    ) -> Result<()> {
+        observe("outbox.repo.mark_sent", async {
+            sqlx::query(
+                r#"
+                UPDATE message_outbox
+                SET status = 'Sent', provider_message_id = $2, sent_at = $3, updated_at = $3,
+                    locked_until = NULL, last_error = NULL
+                WHERE id = $1
+                "#,
+            )
+            .bind(id)
+            .bind(provider_message_id)
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "outbox.repo.schedule_retry",
+        skip(self, error),
+        level = "debug"
+    )]
+    async fn schedule_retry(
+        &self,
+        id: Uuid,
+        error: &str,
+        next_attempt_at: OffsetDateTime,
+        now: OffsetDateTime,
+    ) -> Result<()> {
+        observe("outbox.repo.schedule_retry", async {
+            sqlx::query(
+                r#"
+                UPDATE message_outbox
+                SET last_error = $2, next_attempt_at = $3, updated_at = $4, locked_until = NULL
+                WHERE id = $1
+                "#,
+            )
+            .bind(id)
+            .bind(error)
+            .bind(next_attempt_at)
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "outbox.repo.mark_failed", skip(self, error), level = "debug")]
+    async fn mark_failed(&self, id: Uuid, error: &str, now: OffsetDateTime) -> Result<()> {
+        observe("outbox.repo.mark_failed", async {
+            sqlx::query(
+                r#"
+                UPDATE message_outbox
+                SET status = 'Failed', last_error = $2, updated_at = $3, locked_until = NULL
+                WHERE id = $1
+                "#,
+            )
+            .bind(id)
+            .bind(error)
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(name = "outbox.repo.list_failed", skip(self), level = "debug")]
+    async fn list_failed(&self, limit: u32) -> Result<Vec<OutboxMessage>> {
+        observe("outbox.repo.list_failed", async {
+            let query = format!(
+                r#"
+                SELECT {}
+                FROM message_outbox
+                WHERE status = 'Failed'
+                ORDER BY updated_at DESC
+                LIMIT $1
+                "#,
+                OUTBOX_COLUMNS
+            );
+
+            let rows = sqlx::query(&query)
+                .bind(i64::from(limit))
+                .fetch_all(&self.pool)
+                .await
+                .map_err(Error::Database)?;
+
+            rows.iter().map(outbox_message_from_row).collect()
+        })
+        .await
+    }
+}
@@ -0,0 +1,223 @@
+use crate::models::login_attempt::LoginAttemptStats;
+use crate::models::platform_stats::{
+    RegionSessionCount, TenantCounts, TenantSessionCount, UserSignupCounts,
+};
+use crate::repository::instrumentation::observe;
+use crate::repository::{PlatformStatsRepository, RepositoryError};
+use async_trait::async_trait;
+use sqlx::postgres::PgRow;
+use sqlx::{Pool, Postgres, Row};
+use time::{Duration, OffsetDateTime};
+use tracing::instrument;
+
+/// PostgreSQL implementation of the PlatformStatsRepository
+pub struct PostgresPlatformStatsRepository {
+    pool: Pool<Postgres>,
+}
+
+impl PostgresPlatformStatsRepository {
+    /// Create a new PostgresPlatformStatsRepository
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+/// Read the `COUNT(*)` column `column` of `row`
+fn count(row: &PgRow, column: &str) -> Result<u64, RepositoryError> {
+    row.try_get::<i64, _>(column)
+        .map(|count| count as u64)
+        .map_err(|e| RepositoryError::DatabaseError(e.to_string()))
+}
+
+#[async_trait]
+impl PlatformStatsRepository for PostgresPlatformStatsRepository {
+    #[instrument(name = "platform_stats.repo.tenant_counts", skip_all, level = "debug")]
+    async fn tenant_counts(&self) -> Result<TenantCounts, RepositoryError> {
+        observe("platform_stats.repo.tenant_counts", async {
+            let row = sqlx::query(
+                r#"
+                SELECT
+                    COUNT(*) AS total,
+                    COUNT(*) FILTER (WHERE is_active) AS active
+                FROM tenants
+                "#,
+            )
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+            Ok(TenantCounts {
+                total: count(&row, "total")?,
+                active: count(&row, "active")?,
+            })
+        })
+        .await
+    }
+
+    #[instrument(name = "platform_stats.repo.user_signups", skip_all, level = "debug")]
+    async fn user_signups(&self, now: OffsetDateTime) -> Result<UserSignupCounts, RepositoryError> {
+        observe("platform_stats.repo.user_signups", async {
+            let row = sqlx::query(
+                r#"
+                SELECT
+                    COUNT(*) FILTER (WHERE created_at >= $1) AS last_7_days,
+                    COUNT(*) AS last_30_days
+                FROM users
+                WHERE created_at >= $2
+                "#,
+            )
+            .bind(now - Duration::days(7))
+            .bind(now - Duration::days(30))
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+            Ok(UserSignupCounts {
+                last_7_days: count(&row, "last_7_days")?,
+                last_30_days: count(&row, "last_30_days")?,
+            })
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "platform_stats.repo.active_sessions",
+        skip_all,
+        level = "debug"
+    )]
+    async fn active_sessions(&self, now: OffsetDateTime) -> Result<u64, RepositoryError> {
+        observe("platform_stats.repo.active_sessions", async {
+            let row = sqlx::query(
+                r#"
+                SELECT COUNT(*) AS active
+                FROM sessions
+                WHERE is_valid = true AND expires_at > $1
+                "#,
+            )
+            .bind(now)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+            count(&row, "active")
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "platform_stats.repo.active_sessions_by_region",
+        skip_all,
+        level = "debug"
+    )]
+    async fn active_sessions_by_region(
+        &self,
+        now: OffsetDateTime,
+    ) -> Result<Vec<RegionSessionCount>, RepositoryError> {
+        observe("platform_stats.repo.active_sessions_by_region", async {
+            let rows = sqlx::query(
+                r#"
+                SELECT region, COUNT(*) AS active_sessions
+                FROM sessions
+                WHERE is_valid = true AND expires_at > $1
+                GROUP BY region
+                ORDER BY active_sessions DESC, region NULLS LAST
+                "#,
+            )
+            .bind(now)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+            rows.iter()
+                .map(|row| {
+                    Ok(RegionSessionCount {
+                        region: row
+                            .try_get("region")
+                            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?,
+                        active_sessions: count(row, "active_sessions")?,
+                    })
+                })
+                .collect()
+        })
+        .await
+    }
+
+    #[instrument(name = "platform_stats.repo.login_stats", skip_all, level = "debug")]
+    async fn login_stats(
+        &self,
+        since: OffsetDateTime,
+    ) -> Result<LoginAttemptStats, RepositoryError> {
+        observe("platform_stats.repo.login_stats", async {
+            let row = sqlx::query(
+                r#"
+                SELECT
+                    COUNT(*) AS total,
+                    COUNT(*) FILTER (WHERE outcome = 'succeeded') AS succeeded
+                FROM login_attempts
+                WHERE attempted_at >= $1
+                "#,
+            )
+            .bind(since)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+            let total = count(&row, "total")?;
+            let succeeded = count(&row, "succeeded")?;
+
+            Ok(LoginAttemptStats {
+                total,
+                succeeded,
+                failed: total - succeeded,
+            })
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "platform_stats.repo.top_tenants_by_sessions",
+        skip_all,
+        level = "debug"
+    )]
+    async fn top_tenants_by_sessions(
+        &self,
+        now: OffsetDateTime,
+        limit: u32,
+    ) -> Result<Vec<TenantSessionCount>, RepositoryError> {
+        observe("platform_stats.repo.top_tenants_by_sessions", async {
+            // The tenant of a session is the one it switched to, else the one it was created for
+            let rows = sqlx::query(
+                r#"
+                SELECT t.id, t.name, COUNT(*) AS active_sessions
+                FROM sessions s
+                JOIN tenants t
+                    ON t.id::text = COALESCE(s.metadata->>'active_tenant_id', s.metadata->>'tenant_id')
+                WHERE s.is_valid = true AND s.expires_at > $1
+                GROUP BY t.id, t.name
+                ORDER BY active_sessions DESC, t.name
+                LIMIT $2
+                "#,
+            )
+            .bind(now)
+            .bind(i64::from(limit))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?;
+
+            rows.iter()
+                .map(|row| {
+                    Ok(TenantSessionCount {
+                        tenant_id: row
+                            .try_get("id")
+                            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?,
+                        name: row
+                            .try_get("name")
+                            .map_err(|e| RepositoryError::DatabaseError(e.to_string()))?,
+                        active_sessions: count(row, "active_sessions")?,
+                    })
+                })
+                .collect()
+        })
+        .await
+    }
+}
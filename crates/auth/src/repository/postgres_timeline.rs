@@ -0,0 +1,115 @@
+use crate::models::timeline::{TimelineRecord, TimelineSource};
+use crate::repository::instrumentation::observe;
+use crate::repository::timeline_repository::IGNORED_SESSION_ACTIONS;
+use crate::repository::{RepositoryError, TimelineRepository};
+use async_trait::async_trait;
+use sqlx::postgres::PgRow;
+use sqlx::{Pool, Postgres, Row};
+use time::OffsetDateTime;
+use tracing::instrument;
+use uuid::Uuid;
+
+/// Entries of the user audit log, which has no tenant
+const USER_AUDIT_QUERY: &str = r#"
+    SELECT id, created_at, action, details
+    FROM user_audit_log
+    WHERE user_id = $1
+    AND ($2::timestamptz IS NULL OR (created_at, id) < ($2, $3::uuid))
+    ORDER BY created_at DESC, id DESC
+    LIMIT $4
+"#;
+
+/// Entries of the session audit log, scoped by the tenant of their session
+const SESSION_AUDIT_QUERY: &str = r#"
+    SELECT a.id, a.created_at, a.action, COALESCE(a.details, '{}'::jsonb) AS details
+    FROM session_audit_log a
+    JOIN sessions s ON s.id = a.session_id
+    WHERE a.user_id = $1
+    AND ($2::timestamptz IS NULL OR (a.created_at, a.id) < ($2, $3::uuid))
+    AND (
+        $5::text IS NULL
+        OR COALESCE(s.metadata->>'active_tenant_id', s.metadata->>'tenant_id') = $5
+    )
+    AND a.action <> ALL($6)
+    ORDER BY a.created_at DESC, a.id DESC
+    LIMIT $4
+"#;
+
+/// Issued verification codes; the code itself is never selected
+const VERIFICATION_QUERY: &str = r#"
+    SELECT
+        id, created_at, 'VERIFICATION_SENT' AS action,
+        jsonb_build_object('verification_type', verification_type, 'status', status) AS details
+    FROM verification_codes
+    WHERE user_id = $1
+    AND ($2::timestamptz IS NULL OR (created_at, id) < ($2, $3::uuid))
+    AND ($5::uuid IS NULL OR tenant_id = $5)
+    ORDER BY created_at DESC, id DESC
+    LIMIT $4
+"#;
+
+/// PostgreSQL implementation of the TimelineRepository
+pub struct PostgresTimelineRepository {
+    pool: Pool<Postgres>,
+}
+
+impl PostgresTimelineRepository {
+    /// Create a new PostgresTimelineRepository
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    fn map_row(source: TimelineSource, row: PgRow) -> Result<TimelineRecord, sqlx::Error> {
+        Ok(TimelineRecord {
+            source,
+            id: row.try_get("id")?,
+            at: row.try_get("created_at")?,
+            action: row.try_get("action")?,
+            details: row.try_get("details")?,
+        })
+    }
+}
+
+#[async_trait]
+impl TimelineRepository for PostgresTimelineRepository {
+    #[instrument(name = "timeline.repo.list", skip_all, level = "debug")]
+    async fn list(
+        &self,
+        source: TimelineSource,
+        user_id: Uuid,
+        tenant_id: Option<Uuid>,
+        before: Option<(OffsetDateTime, Uuid)>,
+        limit: u32,
+    ) -> Result<Vec<TimelineRecord>, RepositoryError> {
+        observe("timeline.repo.list", async {
+            let query = match source {
+                TimelineSource::UserAudit => USER_AUDIT_QUERY,
+                TimelineSource::SessionAudit => SESSION_AUDIT_QUERY,
+                TimelineSource::Verification => VERIFICATION_QUERY,
+            };
+            let query = sqlx::query(query)
+                .bind(user_id)
+                .bind(before.map(|(at, _)| at))
+                .bind(before.map(|(_, id)| id))
+                .bind(i64::from(limit));
+            let query = match source {
+                TimelineSource::UserAudit => query,
+                TimelineSource::SessionAudit => query
+                    .bind(tenant_id.map(|id| id.to_string()))
+                    .bind(IGNORED_SESSION_ACTIONS),
+                TimelineSource::Verification => query.bind(tenant_id),
+            };
+
+            query
+                .fetch_all(&self.pool)
+                .await
+                .and_then(|rows| {
+                    rows.into_iter()
+                        .map(|row| Self::map_row(source, row))
+                        .collect()
+                })
+                .map_err(|e| RepositoryError::DatabaseError(e.to_string()))
+        })
+        .await
+    }
+}
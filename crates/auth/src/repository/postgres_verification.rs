@@ -1,10 +1,14 @@
 use async_trait::async_trait;
-use sqlx::{PgPool, Postgres, Transaction};
+use sqlx::{PgPool, Postgres, Row, Transaction};
 use time::OffsetDateTime;
 use tracing::{instrument, trace};
 use uuid::Uuid;
 
-use crate::models::{TenantId, UserId, VerificationCode, VerificationStatus, VerificationType};
+use crate::models::{
+    ExpiredCodes, TenantId, UserId, VerificationCode, VerificationStats, VerificationStatus,
+    VerificationType,
+};
+use crate::repository::instrumentation::observe;
 use crate::repository::tenant_aware::TenantAwareContext;
 use crate::repository::verification_repository::VerificationCodeRepository;
 use acci_core::error::{Error, Result};
@@ -30,15 +34,20 @@ impl PostgresVerificationCodeRepository {
 
 #[async_trait]
 impl VerificationCodeRepository for PostgresVerificationCodeRepository {
-    #[instrument(skip(self, code, _context), level = "debug")]
+    #[instrument(
+        name = "verification.repo.save",
+        skip(self, code, _context),
+        level = "debug"
+    )]
     async fn save(&self, code: &VerificationCode, _context: &dyn TenantAwareContext) -> Result<()> {
-        let tenant_id = code.tenant_id;
-        let user_id = code.user_id;
-        let verification_type = format!("{:?}", code.verification_type);
-        let status = format!("{:?}", code.status);
-
-        sqlx::query!(
-            r#"
+        observe("verification.repo.save", async {
+            let tenant_id = code.tenant_id;
+            let user_id = code.user_id;
+            let verification_type = format!("{:?}", code.verification_type);
+            let status = format!("{:?}", code.status);
+
+            sqlx::query!(
+                r#"
             INSERT INTO verification_codes (
                 id, tenant_id, user_id, code, verification_type, 
                 created_at, expires_at, status, attempts
@@ -47,35 +56,42 @@ impl VerificationCodeRepository for PostgresVerificationCodeRepository {
                 $1, $2, $3, $4, $5, $6, $7, $8, $9
             )
             "#,
-            code.id,
-            tenant_id,
-            user_id,
-            code.code,
-            verification_type,
-            code.created_at,
-            code.expires_at,
-            status,
-            code.attempts as i32
-        )
-        .execute(&self.pool)
-        .await
-        .map_err(Error::Database)?;
+                code.id,
+                tenant_id,
+                user_id,
+                code.code,
+                verification_type,
+                code.created_at,
+                code.expires_at,
+                status,
+                code.attempts as i32
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(Error::Database)?;
 
-        trace!("Saved verification code with ID: {}", code.id);
-        Ok(())
+            trace!("Saved verification code with ID: {}", code.id);
+            Ok(())
+        })
+        .await
     }
 
-    #[instrument(skip(self, _context), level = "debug")]
+    #[instrument(
+        name = "verification.repo.get_by_id",
+        skip(self, _context),
+        level = "debug"
+    )]
     async fn get_by_id(
         &self,
         id: Uuid,
         tenant_id: TenantId,
         _context: &dyn TenantAwareContext,
     ) -> Result<Option<VerificationCode>> {
-        let tenant_id_str = tenant_id.to_string();
+        observe("verification.repo.get_by_id", async {
+            let tenant_id_str = tenant_id.to_string();
 
-        let record = sqlx::query!(
-            r#"
+            let record = sqlx::query!(
+                r#"
             SELECT 
                 id, tenant_id, user_id, code, verification_type, 
                 created_at, expires_at, status, attempts
@@ -84,55 +100,63 @@ impl VerificationCodeRepository for PostgresVerificationCodeRepository {
             WHERE 
                 id = $1 AND tenant_id::text = $2
             "#,
-            id,
-            tenant_id_str
-        )
-        .fetch_optional(&self.pool)
+                id,
+                tenant_id_str
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+            match record {
+                Some(rec) => {
+                    let verification_type = match rec.verification_type.as_str() {
+                        "Email" => VerificationType::Email,
+                        "Sms" => VerificationType::Sms,
+                        _ => {
+                            return Err(Error::Validation(format!(
+                                "Invalid verification type: {}",
+                                rec.verification_type
+                            )));
+                        },
+                    };
+                    let status = match rec.status.as_str() {
+                        "Pending" => VerificationStatus::Pending,
+                        "Verified" => VerificationStatus::Verified,
+                        "Expired" => VerificationStatus::Expired,
+                        "Invalidated" => VerificationStatus::Invalidated,
+                        "Consumed" => VerificationStatus::Consumed,
+                        "Consumed" => VerificationStatus::Consumed,
+                        _ => {
+                            return Err(Error::Validation(format!(
+                                "Invalid verification status: {}",
+                                rec.status
+                            )));
+                        },
+                    };
+
+                    Ok(Some(VerificationCode {
+                        id: rec.id,
+                        tenant_id: rec.tenant_id,
+                        user_id: rec.user_id,
+                        code: rec.code,
+                        verification_type,
+                        created_at: rec.created_at,
+                        expires_at: rec.expires_at,
+                        status,
+                        attempts: rec.attempts as usize,
+                    }))
+                },
+                None => Ok(None),
+            }
+        })
         .await
-        .map_err(Error::Database)?;
-
-        match record {
-            Some(rec) => {
-                let verification_type = match rec.verification_type.as_str() {
-                    "Email" => VerificationType::Email,
-                    "Sms" => VerificationType::Sms,
-                    _ => {
-                        return Err(Error::Validation(format!(
-                            "Invalid verification type: {}",
-                            rec.verification_type
-                        )));
-                    },
-                };
-                let status = match rec.status.as_str() {
-                    "Pending" => VerificationStatus::Pending,
-                    "Verified" => VerificationStatus::Verified,
-                    "Expired" => VerificationStatus::Expired,
-                    "Invalidated" => VerificationStatus::Invalidated,
-                    _ => {
-                        return Err(Error::Validation(format!(
-                            "Invalid verification status: {}",
-                            rec.status
-                        )));
-                    },
-                };
-
-                Ok(Some(VerificationCode {
-                    id: rec.id,
-                    tenant_id: rec.tenant_id,
-                    user_id: rec.user_id,
-                    code: rec.code,
-                    verification_type,
-                    created_at: rec.created_at,
-                    expires_at: rec.expires_at,
-                    status,
-                    attempts: rec.attempts as usize,
-                }))
-            },
-            None => Ok(None),
-        }
     }
 
-    #[instrument(skip(self, code, _context), level = "debug")]
+    #[instrument(
+        name = "verification.repo.get_by_code",
+        skip(self, code, _context),
+        level = "debug"
+    )]
     async fn get_by_code(
         &self,
         code: &str,
@@ -141,12 +165,13 @@ impl VerificationCodeRepository for PostgresVerificationCodeRepository {
         tenant_id: TenantId,
         _context: &dyn TenantAwareContext,
     ) -> Result<Option<VerificationCode>> {
-        let tenant_id_str = tenant_id.to_string();
-        let user_id_str = user_id.to_string();
-        let verification_type_str = format!("{:?}", verification_type);
+        observe("verification.repo.get_by_code", async {
+            let tenant_id_str = tenant_id.to_string();
+            let user_id_str = user_id.to_string();
+            let verification_type_str = format!("{:?}", verification_type);
 
-        let record = sqlx::query!(
-            r#"
+            let record = sqlx::query!(
+                r#"
             SELECT 
                 id, tenant_id, user_id, code, verification_type, 
                 created_at, expires_at, status, attempts
@@ -155,17 +180,99 @@ impl VerificationCodeRepository for PostgresVerificationCodeRepository {
             WHERE 
                 code = $1 AND tenant_id::text = $2 AND user_id::text = $3 AND verification_type = $4
             "#,
-            code,
-            tenant_id_str,
-            user_id_str,
-            verification_type_str
-        )
-        .fetch_optional(&self.pool)
+                code,
+                tenant_id_str,
+                user_id_str,
+                verification_type_str
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+            match record {
+                Some(rec) => {
+                    let verification_type = match rec.verification_type.as_str() {
+                        "Email" => VerificationType::Email,
+                        "Sms" => VerificationType::Sms,
+                        _ => {
+                            return Err(Error::Validation(format!(
+                                "Invalid verification type: {}",
+                                rec.verification_type
+                            )));
+                        },
+                    };
+                    let status = match rec.status.as_str() {
+                        "Pending" => VerificationStatus::Pending,
+                        "Verified" => VerificationStatus::Verified,
+                        "Expired" => VerificationStatus::Expired,
+                        "Invalidated" => VerificationStatus::Invalidated,
+                        "Consumed" => VerificationStatus::Consumed,
+                        "Consumed" => VerificationStatus::Consumed,
+                        _ => {
+                            return Err(Error::Validation(format!(
+                                "Invalid verification status: {}",
+                                rec.status
+                            )));
+                        },
+                    };
+
+                    Ok(Some(VerificationCode {
+                        id: rec.id,
+                        tenant_id: rec.tenant_id,
+                        user_id: rec.user_id,
+                        code: rec.code,
+                        verification_type,
+                        created_at: rec.created_at,
+                        expires_at: rec.expires_at,
+                        status,
+                        attempts: rec.attempts as usize,
+                    }))
+                },
+                None => Ok(None),
+            }
+        })
         .await
-        .map_err(Error::Database)?;
+    }
+
+    #[instrument(
+        name = "verification.repo.get_pending_by_user",
+        skip(self, _context),
+        level = "debug"
+    )]
+    async fn get_pending_by_user(
+        &self,
+        user_id: UserId,
+        verification_type: VerificationType,
+        tenant_id: TenantId,
+        _context: &dyn TenantAwareContext,
+    ) -> Result<Vec<VerificationCode>> {
+        observe("verification.repo.get_pending_by_user", async {
+            let tenant_id_str = tenant_id.to_string();
+            let user_id_str = user_id.to_string();
+            let verification_type_str = format!("{:?}", verification_type);
+            let status = format!("{:?}", VerificationStatus::Pending);
+
+            let records = sqlx::query!(
+                r#"
+            SELECT 
+                id, tenant_id, user_id, code, verification_type, 
+                created_at, expires_at, status, attempts
+            FROM 
+                verification_codes
+            WHERE 
+                tenant_id::text = $1 AND user_id::text = $2 AND verification_type = $3 AND status = $4
+            "#,
+                tenant_id_str,
+                user_id_str,
+                verification_type_str,
+                status
+            )
+            .fetch_all(&self.pool)
+            .await
+            .map_err(Error::Database)?;
 
-        match record {
-            Some(rec) => {
+            let mut codes = Vec::with_capacity(records.len());
+            for rec in records {
                 let verification_type = match rec.verification_type.as_str() {
                     "Email" => VerificationType::Email,
                     "Sms" => VerificationType::Sms,
@@ -181,6 +288,7 @@ impl VerificationCodeRepository for PostgresVerificationCodeRepository {
                     "Verified" => VerificationStatus::Verified,
                     "Expired" => VerificationStatus::Expired,
                     "Invalidated" => VerificationStatus::Invalidated,
+                    "Consumed" => VerificationStatus::Consumed,
                     _ => {
                         return Err(Error::Validation(format!(
                             "Invalid verification status: {}",
@@ -189,7 +297,7 @@ impl VerificationCodeRepository for PostgresVerificationCodeRepository {
                     },
                 };
 
-                Ok(Some(VerificationCode {
+                codes.push(VerificationCode {
                     id: rec.id,
                     tenant_id: rec.tenant_id,
                     user_id: rec.user_id,
@@ -199,95 +307,29 @@ impl VerificationCodeRepository for PostgresVerificationCodeRepository {
                     expires_at: rec.expires_at,
                     status,
                     attempts: rec.attempts as usize,
-                }))
-            },
-            None => Ok(None),
-        }
-    }
-
-    #[instrument(skip(self, _context), level = "debug")]
-    async fn get_pending_by_user(
-        &self,
-        user_id: UserId,
-        verification_type: VerificationType,
-        tenant_id: TenantId,
-        _context: &dyn TenantAwareContext,
-    ) -> Result<Vec<VerificationCode>> {
-        let tenant_id_str = tenant_id.to_string();
-        let user_id_str = user_id.to_string();
-        let verification_type_str = format!("{:?}", verification_type);
-        let status = format!("{:?}", VerificationStatus::Pending);
+                });
+            }
 
-        let records = sqlx::query!(
-            r#"
-            SELECT 
-                id, tenant_id, user_id, code, verification_type, 
-                created_at, expires_at, status, attempts
-            FROM 
-                verification_codes
-            WHERE 
-                tenant_id::text = $1 AND user_id::text = $2 AND verification_type = $3 AND status = $4
-            "#,
-            tenant_id_str,
-            user_id_str,
-            verification_type_str,
-            status
-        )
-        .fetch_all(&self.pool)
+            Ok(codes)
+        })
         .await
-        .map_err(Error::Database)?;
-
-        let mut codes = Vec::with_capacity(records.len());
-        for rec in records {
-            let verification_type = match rec.verification_type.as_str() {
-                "Email" => VerificationType::Email,
-                "Sms" => VerificationType::Sms,
-                _ => {
-                    return Err(Error::Validation(format!(
-                        "Invalid verification type: {}",
-                        rec.verification_type
-                    )));
-                },
-            };
-            let status = match rec.status.as_str() {
-                "Pending" => VerificationStatus::Pending,
-                "Verified" => VerificationStatus::Verified,
-                "Expired" => VerificationStatus::Expired,
-                "Invalidated" => VerificationStatus::Invalidated,
-                _ => {
-                    return Err(Error::Validation(format!(
-                        "Invalid verification status: {}",
-                        rec.status
-                    )));
-                },
-            };
-
-            codes.push(VerificationCode {
-                id: rec.id,
-                tenant_id: rec.tenant_id,
-                user_id: rec.user_id,
-                code: rec.code,
-                verification_type,
-                created_at: rec.created_at,
-                expires_at: rec.expires_at,
-                status,
-                attempts: rec.attempts as usize,
-            });
-        }
-
-        Ok(codes)
     }
 
-    #[instrument(skip(self, code, _context), level = "debug")]
+    #[instrument(
+        name = "verification.repo.update",
+        skip(self, code, _context),
+        level = "debug"
+    )]
     async fn update(
         &self,
         code: &VerificationCode,
         _context: &dyn TenantAwareContext,
     ) -> Result<()> {
-        let status = format!("{:?}", code.status);
+        observe("verification.repo.update", async {
+            let status = format!("{:?}", code.status);
 
-        let result = sqlx::query!(
-            r#"
+            let result = sqlx::query!(
+                r#"
             UPDATE verification_codes
             SET 
                 code = $1, 
@@ -297,79 +339,99 @@ impl VerificationCodeRepository for PostgresVerificationCodeRepository {
             WHERE 
                 id = $5 AND tenant_id = $6
             "#,
-            code.code,
-            code.expires_at,
-            status,
-            code.attempts as i32,
-            code.id,
-            code.tenant_id
-        )
-        .execute(&self.pool)
-        .await
-        .map_err(Error::Database)?;
+                code.code,
+                code.expires_at,
+                status,
+                code.attempts as i32,
+                code.id,
+                code.tenant_id
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(Error::Database)?;
 
-        if result.rows_affected() == 0 {
-            return Err(Error::Validation("Verification code not found".to_string()));
-        }
+            if result.rows_affected() == 0 {
+                return Err(Error::Validation("Verification code not found".to_string()));
+            }
 
-        trace!("Updated verification code with ID: {}", code.id);
-        Ok(())
+            trace!("Updated verification code with ID: {}", code.id);
+            Ok(())
+        })
+        .await
     }
 
-    #[instrument(skip(self, _context), level = "debug")]
+    #[instrument(
+        name = "verification.repo.delete",
+        skip(self, _context),
+        level = "debug"
+    )]
     async fn delete(
         &self,
         id: Uuid,
         tenant_id: TenantId,
         _context: &dyn TenantAwareContext,
     ) -> Result<()> {
-        let result = sqlx::query!(
-            r#"
+        observe("verification.repo.delete", async {
+            let result = sqlx::query!(
+                r#"
             DELETE FROM verification_codes
             WHERE id = $1 AND tenant_id = $2
             "#,
-            id,
-            tenant_id
-        )
-        .execute(&self.pool)
-        .await
-        .map_err(Error::Database)?;
+                id,
+                tenant_id
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(Error::Database)?;
 
-        if result.rows_affected() == 0 {
-            return Err(Error::Validation("Verification code not found".to_string()));
-        }
+            if result.rows_affected() == 0 {
+                return Err(Error::Validation("Verification code not found".to_string()));
+            }
 
-        trace!("Deleted verification code with ID: {}", id);
-        Ok(())
+            trace!("Deleted verification code with ID: {}", id);
+            Ok(())
+        })
+        .await
     }
 
-    #[instrument(skip(self, _context), level = "debug")]
+    #[instrument(
+        name = "verification.repo.delete_expired",
+        skip(self, _context),
+        level = "debug"
+    )]
     async fn delete_expired(
         &self,
         before: OffsetDateTime,
         tenant_id: TenantId,
         _context: &dyn TenantAwareContext,
     ) -> Result<u64> {
-        let result = sqlx::query!(
-            r#"
+        observe("verification.repo.delete_expired", async {
+            let result = sqlx::query!(
+                r#"
             DELETE FROM verification_codes
             WHERE tenant_id = $1 AND expires_at < $2
             "#,
-            tenant_id,
-            before
-        )
-        .execute(&self.pool)
+                tenant_id,
+                before
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+            trace!(
+                "Deleted {} expired verification codes",
+                result.rows_affected()
+            );
+            Ok(result.rows_affected())
+        })
         .await
-        .map_err(Error::Database)?;
-
-        trace!(
-            "Deleted {} expired verification codes",
-            result.rows_affected()
-        );
-        Ok(result.rows_affected())
     }
 
-    #[instrument(skip(self, _context), level = "debug")]
+    #[instrument(
+        name = "verification.repo.invalidate_pending",
+        skip(self, _context),
+        level = "debug"
+    )]
     async fn invalidate_pending(
         &self,
         user_id: UserId,
@@ -377,14 +439,15 @@ impl VerificationCodeRepository for PostgresVerificationCodeRepository {
         tenant_id: TenantId,
         _context: &dyn TenantAwareContext,
     ) -> Result<u64> {
-        let tenant_id_str = tenant_id.to_string();
-        let user_id_str = user_id.to_string();
-        let verification_type_str = format!("{:?}", verification_type);
-        let pending_status = format!("{:?}", VerificationStatus::Pending);
-        let invalidated_status = format!("{:?}", VerificationStatus::Invalidated);
-
-        let result = sqlx::query!(
-            r#"
+        observe("verification.repo.invalidate_pending", async {
+            let tenant_id_str = tenant_id.to_string();
+            let user_id_str = user_id.to_string();
+            let verification_type_str = format!("{:?}", verification_type);
+            let pending_status = format!("{:?}", VerificationStatus::Pending);
+            let invalidated_status = format!("{:?}", VerificationStatus::Invalidated);
+
+            let result = sqlx::query!(
+                r#"
             UPDATE verification_codes
             SET status = $1
             WHERE 
@@ -393,24 +456,30 @@ impl VerificationCodeRepository for PostgresVerificationCodeRepository {
                 verification_type = $4 AND 
                 status = $5
             "#,
-            invalidated_status,
-            tenant_id_str,
-            user_id_str,
-            verification_type_str,
-            pending_status
-        )
-        .execute(&self.pool)
+                invalidated_status,
+                tenant_id_str,
+                user_id_str,
+                verification_type_str,
+                pending_status
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+            trace!(
+                "Invalidated {} pending verification codes",
+                result.rows_affected()
+            );
+            Ok(result.rows_affected())
+        })
         .await
-        .map_err(Error::Database)?;
-
-        trace!(
-            "Invalidated {} pending verification codes",
-            result.rows_affected()
-        );
-        Ok(result.rows_affected())
     }
 
-    #[instrument(skip(self, _context), level = "debug")]
+    #[instrument(
+        name = "verification.repo.count_recent_attempts",
+        skip(self, _context),
+        level = "debug"
+    )]
     async fn count_recent_attempts(
         &self,
         user_id: UserId,
@@ -419,12 +488,13 @@ impl VerificationCodeRepository for PostgresVerificationCodeRepository {
         tenant_id: TenantId,
         _context: &dyn TenantAwareContext,
     ) -> Result<u64> {
-        let tenant_id_str = tenant_id.to_string();
-        let user_id_str = user_id.to_string();
-        let verification_type_str = format!("{:?}", verification_type);
+        observe("verification.repo.count_recent_attempts", async {
+            let tenant_id_str = tenant_id.to_string();
+            let user_id_str = user_id.to_string();
+            let verification_type_str = format!("{:?}", verification_type);
 
-        let result = sqlx::query!(
-            r#"
+            let result = sqlx::query!(
+                r#"
             SELECT COUNT(*) as count
             FROM verification_codes
             WHERE 
@@ -433,15 +503,218 @@ impl VerificationCodeRepository for PostgresVerificationCodeRepository {
                 verification_type = $3 AND 
                 created_at > $4
             "#,
-            tenant_id_str,
-            user_id_str,
-            verification_type_str,
-            since
-        )
-        .fetch_one(&self.pool)
+                tenant_id_str,
+                user_id_str,
+                verification_type_str,
+                since
+            )
+            .fetch_one(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+            Ok(result.count.unwrap_or(0) as u64)
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "verification.repo.expire_all_pending",
+        skip(self),
+        level = "debug"
+    )]
+    async fn expire_all_pending(&self, now: OffsetDateTime) -> Result<Vec<ExpiredCodes>> {
+        observe("verification.repo.expire_all_pending", async {
+            let rows = sqlx::query(
+                r#"
+                WITH expired AS (
+                    UPDATE verification_codes
+                    SET status = $1, updated_at = $3
+                    WHERE status = $2 AND expires_at < $3
+                    RETURNING tenant_id
+                )
+                SELECT tenant_id, COUNT(*) AS count
+                FROM expired
+                GROUP BY tenant_id
+                "#,
+            )
+            .bind(format!("{:?}", VerificationStatus::Expired))
+            .bind(format!("{:?}", VerificationStatus::Pending))
+            .bind(now)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+            rows.iter()
+                .map(|row| {
+                    Ok(ExpiredCodes {
+                        tenant_id: row.try_get("tenant_id")?,
+                        count: row.try_get::<i64, _>("count")? as u64,
+                    })
+                })
+                .collect::<std::result::Result<_, sqlx::Error>>()
+                .map_err(Error::Database)
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "verification.repo.consume_verified",
+        skip(self, _context),
+        level = "debug"
+    )]
+    async fn consume_verified(
+        &self,
+        id: Uuid,
+        tenant_id: TenantId,
+        _context: &dyn TenantAwareContext,
+    ) -> Result<bool> {
+        observe("verification.repo.consume_verified", async {
+            let result = sqlx::query(
+                "UPDATE verification_codes SET status = $3 WHERE id = $1 AND tenant_id = $2 AND status = $4",
+            )
+            .bind(id)
+            .bind(tenant_id)
+            .bind(format!("{:?}", VerificationStatus::Consumed))
+            .bind(format!("{:?}", VerificationStatus::Verified))
+            .execute(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+            Ok(result.rows_affected() == 1)
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "verification.repo.record_delivery_channel",
+        skip(self, _context),
+        level = "debug"
+    )]
+    async fn record_delivery_channel(
+        &self,
+        id: Uuid,
+        tenant_id: TenantId,
+        channel: VerificationType,
+        _context: &dyn TenantAwareContext,
+    ) -> Result<()> {
+        observe("verification.repo.record_delivery_channel", async {
+            let result = sqlx::query(
+                "UPDATE verification_codes SET delivered_via = $3 WHERE id = $1 AND tenant_id = $2",
+            )
+            .bind(id)
+            .bind(tenant_id)
+            .bind(format!("{:?}", channel))
+            .execute(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+            if result.rows_affected() == 0 {
+                return Err(Error::Validation("Verification code not found".to_string()));
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "verification.repo.delivery_channel",
+        skip(self, _context),
+        level = "debug"
+    )]
+    async fn delivery_channel(
+        &self,
+        id: Uuid,
+        tenant_id: TenantId,
+        _context: &dyn TenantAwareContext,
+    ) -> Result<Option<VerificationType>> {
+        observe("verification.repo.delivery_channel", async {
+            let delivered_via: Option<Option<String>> = sqlx::query_scalar(
+                "SELECT delivered_via FROM verification_codes WHERE id = $1 AND tenant_id = $2",
+            )
+            .bind(id)
+            .bind(tenant_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+            match delivered_via.flatten().as_deref() {
+                None => Ok(None),
+                Some("Email") => Ok(Some(VerificationType::Email)),
+                Some("Sms") => Ok(Some(VerificationType::Sms)),
+                Some(other) => Err(Error::Validation(format!(
+                    "Invalid verification type: {}",
+                    other
+                ))),
+            }
+        })
         .await
-        .map_err(Error::Database)?;
+    }
 
-        Ok(result.count.unwrap_or(0) as u64)
+    #[instrument(
+        name = "verification.repo.cleanup_all_expired",
+        skip(self),
+        level = "debug"
+    )]
+    async fn cleanup_all_expired(&self, before: OffsetDateTime) -> Result<u64> {
+        observe("verification.repo.cleanup_all_expired", async {
+            let result = sqlx::query("DELETE FROM verification_codes WHERE expires_at < $1")
+                .bind(before)
+                .execute(&self.pool)
+                .await
+                .map_err(Error::Database)?;
+
+            trace!(
+                "Deleted {} expired verification codes across all tenants",
+                result.rows_affected()
+            );
+            Ok(result.rows_affected())
+        })
+        .await
+    }
+
+    #[instrument(
+        name = "verification.repo.verification_stats",
+        skip(self, _context),
+        level = "debug"
+    )]
+    async fn verification_stats(
+        &self,
+        tenant_id: TenantId,
+        since: OffsetDateTime,
+        _context: &dyn TenantAwareContext,
+    ) -> Result<VerificationStats> {
+        observe("verification.repo.verification_stats", async {
+            // Pending codes past their expiry count as expired even before the
+            // cleanup job has marked them
+            let row = sqlx::query(
+                r#"
+                SELECT
+                    COUNT(*) AS issued,
+                    COUNT(*) FILTER (WHERE status IN ('Verified', 'Consumed')) AS verified,
+                    COUNT(*) FILTER (
+                        WHERE status = 'Expired' OR (status = 'Pending' AND expires_at < NOW())
+                    ) AS expired,
+                    COUNT(*) FILTER (WHERE status = 'Invalidated') AS invalidated,
+                    COUNT(*) FILTER (WHERE status = 'Pending' AND expires_at >= NOW()) AS pending
+                FROM verification_codes
+                WHERE tenant_id = $1 AND created_at >= $2
+                "#,
+            )
+            .bind(tenant_id)
+            .bind(since)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(Error::Database)?;
+
+            let count = |column: &str| row.try_get::<i64, _>(column).map(|count| count as u64);
+            Ok(VerificationStats {
+                issued: count("issued").map_err(Error::Database)?,
+                verified: count("verified").map_err(Error::Database)?,
+                expired: count("expired").map_err(Error::Database)?,
+                invalidated: count("invalidated").map_err(Error::Database)?,
+                pending: count("pending").map_err(Error::Database)?,
+            })
+        })
+        .await
     }
 }
@@ -2,6 +2,7 @@ use crate::{
     models::webauthn::{Credential, CredentialID},
     repository::{
         WebAuthnRepository,
+        instrumentation::observe,
         tenant_aware::{RepositoryError, TenantAwareContext},
     },
 };
@@ -98,413 +99,462 @@ impl TenantAwareContext for PostgresWebAuthnRepository {
 
 #[async_trait]
 impl WebAuthnRepository for PostgresWebAuthnRepository {
-    #[instrument(skip(self, credential), level = "debug")]
+    #[instrument(
+        name = "webauthn.repo.save_credential",
+        skip(self, credential),
+        level = "debug"
+    )]
     async fn save_credential(&self, credential: &Credential) -> Result<(), RepositoryError> {
-        let tenant_id = self.get_tenant_id()?;
-        debug!(
-            "Saving new credential: {} for tenant: {}",
-            credential.id, tenant_id
-        );
-
-        // Start a transaction
-        let mut tx = self.pool.begin().await.map_err(|e| {
-            RepositoryError::DatabaseError(format!("Failed to begin transaction: {}", e))
-        })?;
-
-        // Set tenant context for this transaction
-        sqlx::query::<sqlx::Postgres>("SET LOCAL app.tenant_id = $1")
-            .bind(tenant_id.to_string())
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| {
-                RepositoryError::DatabaseError(format!("Failed to set tenant context: {}", e))
+        observe("webauthn.repo.save_credential", async {
+            let tenant_id = self.get_tenant_id()?;
+            debug!(
+                "Saving new credential: {} for tenant: {}",
+                credential.id, tenant_id
+            );
+
+            // Start a transaction
+            let mut tx = self.pool.begin().await.map_err(|e| {
+                RepositoryError::DatabaseError(format!("Failed to begin transaction: {}", e))
             })?;
 
-        // Insert the credential
-        let result = sqlx::query::<sqlx::Postgres>(
-            r#"
-            INSERT INTO webauthn_credentials (
-                uuid, credential_id, user_id, tenant_id, name, 
-                aaguid, public_key, counter, created_at, last_used_at
+            // Set tenant context for this transaction
+            sqlx::query::<sqlx::Postgres>("SET LOCAL app.tenant_id = $1")
+                .bind(tenant_id.to_string())
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| {
+                    RepositoryError::DatabaseError(format!("Failed to set tenant context: {}", e))
+                })?;
+
+            // Insert the credential
+            let result = sqlx::query::<sqlx::Postgres>(
+                r#"
+                INSERT INTO webauthn_credentials (
+                    uuid, credential_id, user_id, tenant_id, name, 
+                    aaguid, public_key, counter, created_at, last_used_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                "#,
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
-            "#,
-        )
-        .bind(credential.uuid)
-        .bind(credential.id.0.clone())
-        .bind(credential.user_id)
-        .bind(tenant_id)
-        .bind(&credential.name)
-        .bind(&credential.aaguid)
-        .bind(&credential.public_key)
-        .bind(credential.counter as i64)
-        .bind(credential.created_at)
-        .bind(credential.last_used_at)
-        .execute(&mut *tx)
-        .await;
-
-        // Check for errors, especially uniqueness violations
-        if let Err(e) = result {
-            error!("Failed to save credential: {}", e);
-            tx.rollback().await.map_err(|e| {
-                RepositoryError::DatabaseError(format!("Failed to rollback transaction: {}", e))
-            })?;
-
-            if let Some(db_err) = e.as_database_error() {
-                if let Some(code) = db_err.code() {
-                    if code == "23505" {
-                        // Unique violation
-                        return Err(RepositoryError::UniqueViolation(
-                            "Credential already exists".to_string(),
-                        ));
+            .bind(credential.uuid)
+            .bind(credential.id.0.clone())
+            .bind(credential.user_id)
+            .bind(tenant_id)
+            .bind(&credential.name)
+            .bind(&credential.aaguid)
+            .bind(&credential.public_key)
+            .bind(credential.counter as i64)
+            .bind(credential.created_at)
+            .bind(credential.last_used_at)
+            .execute(&mut *tx)
+            .await;
+
+            // Check for errors, especially uniqueness violations
+            if let Err(e) = result {
+                error!("Failed to save credential: {}", e);
+                tx.rollback().await.map_err(|e| {
+                    RepositoryError::DatabaseError(format!("Failed to rollback transaction: {}", e))
+                })?;
+
+                if let Some(db_err) = e.as_database_error() {
+                    if let Some(code) = db_err.code() {
+                        if code == "23505" {
+                            // Unique violation
+                            return Err(RepositoryError::UniqueViolation(
+                                "Credential already exists".to_string(),
+                            ));
+                        }
                     }
                 }
-            }
 
-            return Err(RepositoryError::DatabaseError(format!(
-                "Failed to save credential: {}",
-                e
-            )));
-        }
+                return Err(RepositoryError::DatabaseError(format!(
+                    "Failed to save credential: {}",
+                    e
+                )));
+            }
 
-        // Commit the transaction
-        tx.commit().await.map_err(|e| {
-            RepositoryError::DatabaseError(format!("Failed to commit transaction: {}", e))
-        })?;
+            // Commit the transaction
+            tx.commit().await.map_err(|e| {
+                RepositoryError::DatabaseError(format!("Failed to commit transaction: {}", e))
+            })?;
 
-        Ok(())
+            Ok(())
+        })
+        .await
     }
 
-    #[instrument(skip(self, credential), level = "debug")]
+    #[instrument(
+        name = "webauthn.repo.update_credential",
+        skip(self, credential),
+        level = "debug"
+    )]
     async fn update_credential(&self, credential: &Credential) -> Result<(), RepositoryError> {
-        let tenant_id = self.get_tenant_id()?;
-        debug!(
-            "Updating credential: {} for tenant: {}",
-            credential.id, tenant_id
-        );
-
-        // Start a transaction
-        let mut tx = self.pool.begin().await.map_err(|e| {
-            RepositoryError::DatabaseError(format!("Failed to begin transaction: {}", e))
-        })?;
-
-        // Set tenant context for this transaction
-        sqlx::query("SET LOCAL app.tenant_id = $1")
-            .bind(tenant_id.to_string())
+        observe("webauthn.repo.update_credential", async {
+            let tenant_id = self.get_tenant_id()?;
+            debug!(
+                "Updating credential: {} for tenant: {}",
+                credential.id, tenant_id
+            );
+
+            // Start a transaction
+            let mut tx = self.pool.begin().await.map_err(|e| {
+                RepositoryError::DatabaseError(format!("Failed to begin transaction: {}", e))
+            })?;
+
+            // Set tenant context for this transaction
+            sqlx::query("SET LOCAL app.tenant_id = $1")
+                .bind(tenant_id.to_string())
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| {
+                    RepositoryError::DatabaseError(format!("Failed to set tenant context: {}", e))
+                })?;
+
+            // Update the credential
+            let result = sqlx::query::<sqlx::Postgres>(
+                r#"
+                UPDATE webauthn_credentials
+                SET 
+                    name = $1,
+                    counter = $2,
+                    last_used_at = $3
+                WHERE uuid = $4 AND tenant_id = $5
+                "#,
+            )
+            .bind(&credential.name)
+            .bind(credential.counter as i64)
+            .bind(credential.last_used_at)
+            .bind(credential.uuid)
+            .bind(tenant_id)
             .execute(&mut *tx)
             .await
             .map_err(|e| {
-                RepositoryError::DatabaseError(format!("Failed to set tenant context: {}", e))
+                RepositoryError::DatabaseError(format!("Failed to update credential: {}", e))
             })?;
 
-        // Update the credential
-        let result = sqlx::query::<sqlx::Postgres>(
-            r#"
-            UPDATE webauthn_credentials
-            SET 
-                name = $1,
-                counter = $2,
-                last_used_at = $3
-            WHERE uuid = $4 AND tenant_id = $5
-            "#,
-        )
-        .bind(&credential.name)
-        .bind(credential.counter as i64)
-        .bind(credential.last_used_at)
-        .bind(credential.uuid)
-        .bind(tenant_id)
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| {
-            RepositoryError::DatabaseError(format!("Failed to update credential: {}", e))
-        })?;
-
-        // Check if any rows were affected
-        if result.rows_affected() == 0 {
-            tx.rollback().await.map_err(|e| {
-                RepositoryError::DatabaseError(format!("Failed to rollback transaction: {}", e))
-            })?;
-            return Err(RepositoryError::NotFound(
-                "Credential not found".to_string(),
-            ));
-        }
+            // Check if any rows were affected
+            if result.rows_affected() == 0 {
+                tx.rollback().await.map_err(|e| {
+                    RepositoryError::DatabaseError(format!("Failed to rollback transaction: {}", e))
+                })?;
+                return Err(RepositoryError::NotFound(
+                    "Credential not found".to_string(),
+                ));
+            }
 
-        // Commit the transaction
-        tx.commit().await.map_err(|e| {
-            RepositoryError::DatabaseError(format!("Failed to commit transaction: {}", e))
-        })?;
+            // Commit the transaction
+            tx.commit().await.map_err(|e| {
+                RepositoryError::DatabaseError(format!("Failed to commit transaction: {}", e))
+            })?;
 
-        Ok(())
+            Ok(())
+        })
+        .await
     }
 
-    #[instrument(skip(self), level = "debug")]
+    #[instrument(
+        name = "webauthn.repo.find_credential_by_id",
+        skip(self),
+        level = "debug"
+    )]
     async fn find_credential_by_id(
         &self,
         id: &CredentialID,
     ) -> Result<Option<Credential>, RepositoryError> {
-        let tenant_id = self.get_tenant_id()?;
-        debug!("Finding credential by ID: {} for tenant: {}", id, tenant_id);
+        observe("webauthn.repo.find_credential_by_id", async {
+            let tenant_id = self.get_tenant_id()?;
+            debug!("Finding credential by ID: {} for tenant: {}", id, tenant_id);
 
-        // Start a transaction
-        let mut tx = self.pool.begin().await.map_err(|e| {
-            RepositoryError::DatabaseError(format!("Failed to begin transaction: {}", e))
-        })?;
+            // Start a transaction
+            let mut tx = self.pool.begin().await.map_err(|e| {
+                RepositoryError::DatabaseError(format!("Failed to begin transaction: {}", e))
+            })?;
 
-        // Set tenant context for this transaction
-        sqlx::query::<sqlx::Postgres>("SET LOCAL app.tenant_id = $1")
-            .bind(tenant_id.to_string())
-            .execute(&mut *tx)
+            // Set tenant context for this transaction
+            sqlx::query::<sqlx::Postgres>("SET LOCAL app.tenant_id = $1")
+                .bind(tenant_id.to_string())
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| {
+                    RepositoryError::DatabaseError(format!("Failed to set tenant context: {}", e))
+                })?;
+
+            // Query for the credential
+            let row_opt = sqlx::query::<sqlx::Postgres>(
+                r#"
+                SELECT 
+                    uuid, credential_id, user_id, tenant_id, name,
+                    aaguid, public_key, counter, created_at, last_used_at
+                FROM webauthn_credentials
+                WHERE credential_id = $1 AND tenant_id = $2
+                "#,
+            )
+            .bind(&id.0)
+            .bind(tenant_id)
+            .fetch_optional(&mut *tx)
             .await
             .map_err(|e| {
-                RepositoryError::DatabaseError(format!("Failed to set tenant context: {}", e))
+                RepositoryError::DatabaseError(format!("Failed to find credential: {}", e))
             })?;
 
-        // Query for the credential
-        let row_opt = sqlx::query::<sqlx::Postgres>(
-            r#"
-            SELECT 
-                uuid, credential_id, user_id, tenant_id, name,
-                aaguid, public_key, counter, created_at, last_used_at
-            FROM webauthn_credentials
-            WHERE credential_id = $1 AND tenant_id = $2
-            "#,
-        )
-        .bind(&id.0)
-        .bind(tenant_id)
-        .fetch_optional(&mut *tx)
-        .await
-        .map_err(|e| RepositoryError::DatabaseError(format!("Failed to find credential: {}", e)))?;
-
-        // Map the row to a Credential if found
-        let credential_opt = match row_opt {
-            Some(row) => {
-                let credential = self.map_row_to_credential(row).await?;
-                Some(credential)
-            },
-            None => None,
-        };
-
-        // Commit the transaction
-        tx.commit().await.map_err(|e| {
-            RepositoryError::DatabaseError(format!("Failed to commit transaction: {}", e))
-        })?;
+            // Map the row to a Credential if found
+            let credential_opt = match row_opt {
+                Some(row) => {
+                    let credential = self.map_row_to_credential(row).await?;
+                    Some(credential)
+                },
+                None => None,
+            };
+
+            // Commit the transaction
+            tx.commit().await.map_err(|e| {
+                RepositoryError::DatabaseError(format!("Failed to commit transaction: {}", e))
+            })?;
 
-        Ok(credential_opt)
+            Ok(credential_opt)
+        })
+        .await
     }
 
-    #[instrument(skip(self), level = "debug")]
+    #[instrument(
+        name = "webauthn.repo.find_credential_by_uuid",
+        skip(self),
+        level = "debug"
+    )]
     async fn find_credential_by_uuid(
         &self,
         uuid: &Uuid,
     ) -> Result<Option<Credential>, RepositoryError> {
-        let tenant_id = self.get_tenant_id()?;
-        debug!(
-            "Finding credential by UUID: {} for tenant: {}",
-            uuid, tenant_id
-        );
-
-        // Start a transaction
-        let mut tx = self.pool.begin().await.map_err(|e| {
-            RepositoryError::DatabaseError(format!("Failed to begin transaction: {}", e))
-        })?;
-
-        // Set tenant context for this transaction
-        sqlx::query::<sqlx::Postgres>("SET LOCAL app.tenant_id = $1")
-            .bind(tenant_id.to_string())
-            .execute(&mut *tx)
+        observe("webauthn.repo.find_credential_by_uuid", async {
+            let tenant_id = self.get_tenant_id()?;
+            debug!(
+                "Finding credential by UUID: {} for tenant: {}",
+                uuid, tenant_id
+            );
+
+            // Start a transaction
+            let mut tx = self.pool.begin().await.map_err(|e| {
+                RepositoryError::DatabaseError(format!("Failed to begin transaction: {}", e))
+            })?;
+
+            // Set tenant context for this transaction
+            sqlx::query::<sqlx::Postgres>("SET LOCAL app.tenant_id = $1")
+                .bind(tenant_id.to_string())
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| {
+                    RepositoryError::DatabaseError(format!("Failed to set tenant context: {}", e))
+                })?;
+
+            // Query for the credential
+            let row_opt = sqlx::query::<sqlx::Postgres>(
+                r#"
+                SELECT 
+                    uuid, credential_id, user_id, tenant_id, name,
+                    aaguid, public_key, counter, created_at, last_used_at
+                FROM webauthn_credentials
+                WHERE uuid = $1 AND tenant_id = $2
+                "#,
+            )
+            .bind(uuid)
+            .bind(tenant_id)
+            .fetch_optional(&mut *tx)
             .await
             .map_err(|e| {
-                RepositoryError::DatabaseError(format!("Failed to set tenant context: {}", e))
+                RepositoryError::DatabaseError(format!("Failed to find credential: {}", e))
             })?;
 
-        // Query for the credential
-        let row_opt = sqlx::query::<sqlx::Postgres>(
-            r#"
-            SELECT 
-                uuid, credential_id, user_id, tenant_id, name,
-                aaguid, public_key, counter, created_at, last_used_at
-            FROM webauthn_credentials
-            WHERE uuid = $1 AND tenant_id = $2
-            "#,
-        )
-        .bind(uuid)
-        .bind(tenant_id)
-        .fetch_optional(&mut *tx)
-        .await
-        .map_err(|e| RepositoryError::DatabaseError(format!("Failed to find credential: {}", e)))?;
-
-        // Map the row to a Credential if found
-        let credential_opt = match row_opt {
-            Some(row) => {
-                let credential = self.map_row_to_credential(row).await?;
-                Some(credential)
-            },
-            None => None,
-        };
-
-        // Commit the transaction
-        tx.commit().await.map_err(|e| {
-            RepositoryError::DatabaseError(format!("Failed to commit transaction: {}", e))
-        })?;
+            // Map the row to a Credential if found
+            let credential_opt = match row_opt {
+                Some(row) => {
+                    let credential = self.map_row_to_credential(row).await?;
+                    Some(credential)
+                },
+                None => None,
+            };
+
+            // Commit the transaction
+            tx.commit().await.map_err(|e| {
+                RepositoryError::DatabaseError(format!("Failed to commit transaction: {}", e))
+            })?;
 
-        Ok(credential_opt)
+            Ok(credential_opt)
+        })
+        .await
     }
 
-    #[instrument(skip(self), level = "debug")]
+    #[instrument(
+        name = "webauthn.repo.list_credentials_for_user",
+        skip(self),
+        level = "debug"
+    )]
     async fn list_credentials_for_user(
         &self,
         user_id: &Uuid,
     ) -> Result<Vec<Credential>, RepositoryError> {
-        let tenant_id = self.get_tenant_id()?;
-        debug!(
-            "Listing credentials for user: {} in tenant: {}",
-            user_id, tenant_id
-        );
-
-        // Start a transaction
-        let mut tx = self.pool.begin().await.map_err(|e| {
-            RepositoryError::DatabaseError(format!("Failed to begin transaction: {}", e))
-        })?;
-
-        // Set tenant context for this transaction
-        sqlx::query::<sqlx::Postgres>("SET LOCAL app.tenant_id = $1")
-            .bind(tenant_id.to_string())
-            .execute(&mut *tx)
+        observe("webauthn.repo.list_credentials_for_user", async {
+            let tenant_id = self.get_tenant_id()?;
+            debug!(
+                "Listing credentials for user: {} in tenant: {}",
+                user_id, tenant_id
+            );
+
+            // Start a transaction
+            let mut tx = self.pool.begin().await.map_err(|e| {
+                RepositoryError::DatabaseError(format!("Failed to begin transaction: {}", e))
+            })?;
+
+            // Set tenant context for this transaction
+            sqlx::query::<sqlx::Postgres>("SET LOCAL app.tenant_id = $1")
+                .bind(tenant_id.to_string())
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| {
+                    RepositoryError::DatabaseError(format!("Failed to set tenant context: {}", e))
+                })?;
+
+            // Query for all credentials for this user
+            let rows = sqlx::query::<sqlx::Postgres>(
+                r#"
+                SELECT 
+                    uuid, credential_id, user_id, tenant_id, name,
+                    aaguid, public_key, counter, created_at, last_used_at
+                FROM webauthn_credentials
+                WHERE user_id = $1 AND tenant_id = $2
+                ORDER BY created_at DESC
+                "#,
+            )
+            .bind(user_id)
+            .bind(tenant_id)
+            .fetch_all(&mut *tx)
             .await
             .map_err(|e| {
-                RepositoryError::DatabaseError(format!("Failed to set tenant context: {}", e))
+                RepositoryError::DatabaseError(format!("Failed to list credentials: {}", e))
             })?;
 
-        // Query for all credentials for this user
-        let rows = sqlx::query::<sqlx::Postgres>(
-            r#"
-            SELECT 
-                uuid, credential_id, user_id, tenant_id, name,
-                aaguid, public_key, counter, created_at, last_used_at
-            FROM webauthn_credentials
-            WHERE user_id = $1 AND tenant_id = $2
-            ORDER BY created_at DESC
-            "#,
-        )
-        .bind(user_id)
-        .bind(tenant_id)
-        .fetch_all(&mut *tx)
-        .await
-        .map_err(|e| {
-            RepositoryError::DatabaseError(format!("Failed to list credentials: {}", e))
-        })?;
-
-        // Map the rows to Credential objects
-        let mut credentials = Vec::with_capacity(rows.len());
-        for row in rows {
-            let credential = self.map_row_to_credential(row).await?;
-            credentials.push(credential);
-        }
+            // Map the rows to Credential objects
+            let mut credentials = Vec::with_capacity(rows.len());
+            for row in rows {
+                let credential = self.map_row_to_credential(row).await?;
+                credentials.push(credential);
+            }
 
-        // Commit the transaction
-        tx.commit().await.map_err(|e| {
-            RepositoryError::DatabaseError(format!("Failed to commit transaction: {}", e))
-        })?;
+            // Commit the transaction
+            tx.commit().await.map_err(|e| {
+                RepositoryError::DatabaseError(format!("Failed to commit transaction: {}", e))
+            })?;
 
-        Ok(credentials)
+            Ok(credentials)
+        })
+        .await
     }
 
-    #[instrument(skip(self), level = "debug")]
+    #[instrument(name = "webauthn.repo.delete_credential", skip(self), level = "debug")]
     async fn delete_credential(&self, uuid: &Uuid) -> Result<(), RepositoryError> {
-        let tenant_id = self.get_tenant_id()?;
-        debug!("Deleting credential: {} for tenant: {}", uuid, tenant_id);
+        observe("webauthn.repo.delete_credential", async {
+            let tenant_id = self.get_tenant_id()?;
+            debug!("Deleting credential: {} for tenant: {}", uuid, tenant_id);
 
-        // Start a transaction
-        let mut tx = self.pool.begin().await.map_err(|e| {
-            RepositoryError::DatabaseError(format!("Failed to begin transaction: {}", e))
-        })?;
+            // Start a transaction
+            let mut tx = self.pool.begin().await.map_err(|e| {
+                RepositoryError::DatabaseError(format!("Failed to begin transaction: {}", e))
+            })?;
 
-        // Set tenant context for this transaction
-        sqlx::query::<sqlx::Postgres>("SET LOCAL app.tenant_id = $1")
-            .bind(tenant_id.to_string())
+            // Set tenant context for this transaction
+            sqlx::query::<sqlx::Postgres>("SET LOCAL app.tenant_id = $1")
+                .bind(tenant_id.to_string())
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| {
+                    RepositoryError::DatabaseError(format!("Failed to set tenant context: {}", e))
+                })?;
+
+            // Delete the credential
+            let result = sqlx::query::<sqlx::Postgres>(
+                r#"
+                DELETE FROM webauthn_credentials
+                WHERE uuid = $1 AND tenant_id = $2
+                "#,
+            )
+            .bind(uuid)
+            .bind(tenant_id)
             .execute(&mut *tx)
             .await
             .map_err(|e| {
-                RepositoryError::DatabaseError(format!("Failed to set tenant context: {}", e))
+                RepositoryError::DatabaseError(format!("Failed to delete credential: {}", e))
             })?;
 
-        // Delete the credential
-        let result = sqlx::query::<sqlx::Postgres>(
-            r#"
-            DELETE FROM webauthn_credentials
-            WHERE uuid = $1 AND tenant_id = $2
-            "#,
-        )
-        .bind(uuid)
-        .bind(tenant_id)
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| {
-            RepositoryError::DatabaseError(format!("Failed to delete credential: {}", e))
-        })?;
-
-        // Check if any rows were affected
-        if result.rows_affected() == 0 {
-            tx.rollback().await.map_err(|e| {
-                RepositoryError::DatabaseError(format!("Failed to rollback transaction: {}", e))
-            })?;
-            return Err(RepositoryError::NotFound(
-                "Credential not found".to_string(),
-            ));
-        }
+            // Check if any rows were affected
+            if result.rows_affected() == 0 {
+                tx.rollback().await.map_err(|e| {
+                    RepositoryError::DatabaseError(format!("Failed to rollback transaction: {}", e))
+                })?;
+                return Err(RepositoryError::NotFound(
+                    "Credential not found".to_string(),
+                ));
+            }
 
-        // Commit the transaction
-        tx.commit().await.map_err(|e| {
-            RepositoryError::DatabaseError(format!("Failed to commit transaction: {}", e))
-        })?;
+            // Commit the transaction
+            tx.commit().await.map_err(|e| {
+                RepositoryError::DatabaseError(format!("Failed to commit transaction: {}", e))
+            })?;
 
-        Ok(())
+            Ok(())
+        })
+        .await
     }
 
-    #[instrument(skip(self), level = "debug")]
+    #[instrument(
+        name = "webauthn.repo.delete_credentials_for_user",
+        skip(self),
+        level = "debug"
+    )]
     async fn delete_credentials_for_user(&self, user_id: &Uuid) -> Result<u64, RepositoryError> {
-        let tenant_id = self.get_tenant_id()?;
-        debug!(
-            "Deleting all credentials for user: {} in tenant: {}",
-            user_id, tenant_id
-        );
-
-        // Start a transaction
-        let mut tx = self.pool.begin().await.map_err(|e| {
-            RepositoryError::DatabaseError(format!("Failed to begin transaction: {}", e))
-        })?;
-
-        // Set tenant context for this transaction
-        sqlx::query::<sqlx::Postgres>("SET LOCAL app.tenant_id = $1")
-            .bind(tenant_id.to_string())
+        observe("webauthn.repo.delete_credentials_for_user", async {
+            let tenant_id = self.get_tenant_id()?;
+            debug!(
+                "Deleting all credentials for user: {} in tenant: {}",
+                user_id, tenant_id
+            );
+
+            // Start a transaction
+            let mut tx = self.pool.begin().await.map_err(|e| {
+                RepositoryError::DatabaseError(format!("Failed to begin transaction: {}", e))
+            })?;
+
+            // Set tenant context for this transaction
+            sqlx::query::<sqlx::Postgres>("SET LOCAL app.tenant_id = $1")
+                .bind(tenant_id.to_string())
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| {
+                    RepositoryError::DatabaseError(format!("Failed to set tenant context: {}", e))
+                })?;
+
+            // Delete all credentials for this user
+            let result = sqlx::query::<sqlx::Postgres>(
+                r#"
+                DELETE FROM webauthn_credentials
+                WHERE user_id = $1 AND tenant_id = $2
+                "#,
+            )
+            .bind(user_id)
+            .bind(tenant_id)
             .execute(&mut *tx)
             .await
             .map_err(|e| {
-                RepositoryError::DatabaseError(format!("Failed to set tenant context: {}", e))
+                RepositoryError::DatabaseError(format!("Failed to delete credentials: {}", e))
             })?;
 
-        // Delete all credentials for this user
-        let result = sqlx::query::<sqlx::Postgres>(
-            r#"
-            DELETE FROM webauthn_credentials
-            WHERE user_id = $1 AND tenant_id = $2
-            "#,
-        )
-        .bind(user_id)
-        .bind(tenant_id)
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| {
-            RepositoryError::DatabaseError(format!("Failed to delete credentials: {}", e))
-        })?;
-
-        let rows_affected = result.rows_affected();
+            let rows_affected = result.rows_affected();
 
-        // Commit the transaction
-        tx.commit().await.map_err(|e| {
-            RepositoryError::DatabaseError(format!("Failed to commit transaction: {}", e))
-        })?;
+            // Commit the transaction
+            tx.commit().await.map_err(|e| {
+                RepositoryError::DatabaseError(format!("Failed to commit transaction: {}", e))
+            })?;
 
-        Ok(rows_affected)
+            Ok(rows_affected)
+        })
+        .await
     }
 }
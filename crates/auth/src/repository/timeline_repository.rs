@@ -0,0 +1,118 @@
+use crate::models::timeline::{TimelineRecord, TimelineSource};
+use crate::repository::RepositoryError;
+use async_trait::async_trait;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// Session audit actions left out of timelines
+///
+/// They are recorded for every request or token refresh and would crowd out
+/// everything else.
+pub const IGNORED_SESSION_ACTIONS: &[&str] = &[
+    "SESSION_ACTIVITY",
+    "SESSION_RENEWED",
+    "SESSION_RENEW_ATTEMPT",
+    "TOKEN_ROTATION_STARTED",
+    "TOKEN_ROTATION_COMPLETED",
+    "LOCATION_TRACKED",
+    "FINGERPRINT_UPDATED",
+];
+
+/// Repository interface for the sources of user activity timelines
+#[async_trait]
+pub trait TimelineRepository: Send + Sync + 'static {
+    /// List entries of one source for a user, newest first
+    ///
+    /// Only entries whose `(at, id)` sorts before `before` are returned, at
+    /// most `limit` of them. With a tenant, session audit entries and
+    /// verification codes are limited to that tenant; the user audit log is
+    /// not tenant specific.
+    async fn list(
+        &self,
+        source: TimelineSource,
+        user_id: Uuid,
+        tenant_id: Option<Uuid>,
+        before: Option<(OffsetDateTime, Uuid)>,
+        limit: u32,
+    ) -> Result<Vec<TimelineRecord>, RepositoryError>;
+}
+
+/// In-memory [`TimelineRepository`] for tests
+///
+/// Shared by the unit tests in this crate and by downstream test crates (via
+/// the `test-support` feature).
+#[cfg(any(test, feature = "test-support"))]
+pub mod mock {
+    use super::*;
+    use std::cmp::Reverse;
+    use std::sync::Mutex;
+
+    struct StoredRecord {
+        user_id: Uuid,
+        tenant_id: Option<Uuid>,
+        record: TimelineRecord,
+    }
+
+    /// In-memory timeline repository for tests
+    #[derive(Default)]
+    pub struct MockTimelineRepository {
+        records: Mutex<Vec<StoredRecord>>,
+    }
+
+    impl MockTimelineRepository {
+        /// Creates an empty mock timeline repository
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Store an entry of a user, optionally recorded in a tenant
+        pub fn insert(&self, user_id: Uuid, tenant_id: Option<Uuid>, record: TimelineRecord) {
+            self.records
+                .lock()
+                .expect("mock timeline store lock poisoned")
+                .push(StoredRecord {
+                    user_id,
+                    tenant_id,
+                    record,
+                });
+        }
+    }
+
+    #[async_trait]
+    impl TimelineRepository for MockTimelineRepository {
+        async fn list(
+            &self,
+            source: TimelineSource,
+            user_id: Uuid,
+            tenant_id: Option<Uuid>,
+            before: Option<(OffsetDateTime, Uuid)>,
+            limit: u32,
+        ) -> Result<Vec<TimelineRecord>, RepositoryError> {
+            let records = self
+                .records
+                .lock()
+                .expect("mock timeline store lock poisoned");
+            let mut matching: Vec<TimelineRecord> = records
+                .iter()
+                .filter(|stored| stored.user_id == user_id && stored.record.source == source)
+                .filter(|stored| {
+                    source == TimelineSource::UserAudit
+                        || tenant_id.is_none()
+                        || stored.tenant_id == tenant_id
+                })
+                .filter(|stored| {
+                    source != TimelineSource::SessionAudit
+                        || !IGNORED_SESSION_ACTIONS.contains(&stored.record.action.as_str())
+                })
+                .filter(|stored| {
+                    before.is_none_or(|bound| (stored.record.at, stored.record.id) < bound)
+                })
+                .map(|stored| stored.record.clone())
+                .collect();
+
+            matching.sort_by_key(|record| Reverse((record.at, record.id)));
+            matching.truncate(limit as usize);
+            Ok(matching)
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! Time budgets of repository operations
+//!
+//! A slow or partitioned database must not keep a request waiting until the
+//! client gives up. Every repository method runs within a budget from
+//! [`OperationTimeouts`]: reads get [`OperationTimeouts::read`], writes
+//! [`OperationTimeouts::write`] and cleanup or maintenance runs, which touch
+//! many rows in batches, [`OperationTimeouts::long_running`]. An operation
+//! exceeding its budget is dropped and fails with the `Timeout` variant of the
+//! repository's error, which the API answers with `504 Gateway Timeout`.
+//!
+//! Dropping the future only stops waiting for the database. The pool
+//! connections additionally carry a `statement_timeout`, see
+//! [`OperationTimeouts::statement_timeout`], so that a runaway statement is
+//! cancelled by the server as well.
+
+use crate::utils::duration::DurationMs;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{Executor, Postgres};
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+/// Budgets of the repository operations
+///
+/// Configured as milliseconds or humantime strings such as `"5s"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OperationTimeouts {
+    /// Budget of lookups and listings
+    pub read: DurationMs,
+    /// Budget of inserts, updates and deletes of single entities
+    pub write: DurationMs,
+    /// Budget of cleanup and maintenance runs over many rows
+    pub long_running: DurationMs,
+}
+
+impl Default for OperationTimeouts {
+    fn default() -> Self {
+        Self {
+            read: DurationMs::from_millis(5_000),
+            write: DurationMs::from_millis(10_000),
+            long_running: DurationMs::from_millis(5 * 60 * 1_000),
+        }
+    }
+}
+
+impl OperationTimeouts {
+    /// `statement_timeout` of the pool connections
+    ///
+    /// Long-running operations are split into batches of short statements, so
+    /// no single statement needs more than the larger of the read and write
+    /// budgets.
+    pub fn statement_timeout(&self) -> Duration {
+        self.read.max(self.write).as_duration()
+    }
+
+    /// Make the connections of `options` cancel statements running longer
+    /// than [`Self::statement_timeout`]
+    pub fn apply_to(&self, options: PgPoolOptions) -> PgPoolOptions {
+        let statement = format!(
+            "SET statement_timeout = {}",
+            self.statement_timeout().as_millis()
+        );
+        options.after_connect(move |conn, _meta| {
+            let statement = statement.clone();
+            Box::pin(async move {
+                conn.execute(statement.as_str()).await?;
+                Ok(())
+            })
+        })
+    }
+}
+
+/// A repository operation that did not finish within its budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("{operation} did not finish within {budget:?}")]
+pub struct OperationTimedOut {
+    /// Name of the operation, e.g. `user.repo.find_by_id`
+    pub operation: &'static str,
+    /// Budget the operation exceeded
+    pub budget: Duration,
+}
+
+/// Run `future` within `budget`, failing with [`OperationTimedOut`] otherwise
+///
+/// The future is dropped when the budget is used up; a connection it held is
+/// returned to the pool, which checks it before handing it out again.
+pub async fn within<T, E>(
+    operation: &'static str,
+    budget: impl Into<Duration>,
+    future: impl Future<Output = Result<T, E>>,
+) -> Result<T, E>
+where
+    E: From<OperationTimedOut>,
+{
+    let budget = budget.into();
+    match tokio::time::timeout(budget, future).await {
+        Ok(result) => result,
+        Err(_) => {
+            warn!(operation, budget = ?budget, "Repository operation timed out");
+            Err(OperationTimedOut { operation, budget }.into())
+        },
+    }
+}
+
+/// Run a raw statement on `executor` within `budget`
+///
+/// For ad-hoc statements outside of the repositories, e.g. in maintenance
+/// jobs and tests.
+pub async fn execute_within<'e>(
+    operation: &'static str,
+    budget: impl Into<Duration>,
+    executor: impl Executor<'e, Database = Postgres>,
+    statement: &'e str,
+) -> Result<u64, TimeoutOrDatabase> {
+    within(operation, budget, async {
+        let result = sqlx::query(statement).execute(executor).await?;
+        Ok(result.rows_affected())
+    })
+    .await
+}
+
+/// Error of [`execute_within`]
+#[derive(Debug, thiserror::Error)]
+pub enum TimeoutOrDatabase {
+    #[error(transparent)]
+    Timeout(#[from] OperationTimedOut),
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum TestError {
+        Timeout(OperationTimedOut),
+    }
+
+    impl From<OperationTimedOut> for TestError {
+        fn from(err: OperationTimedOut) -> Self {
+            Self::Timeout(err)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_operation_within_budget_returns_its_result() {
+        let result: Result<u32, TestError> = within("test.op", Duration::from_secs(5), async {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            Ok(7)
+        })
+        .await;
+        assert_eq!(result, Ok(7));
+    }
+
+    #[tokio::test]
+    async fn test_operation_exceeding_budget_times_out() {
+        let result: Result<u32, TestError> = within("test.op", Duration::from_millis(20), async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(7)
+        })
+        .await;
+        assert_eq!(
+            result,
+            Err(TestError::Timeout(OperationTimedOut {
+                operation: "test.op",
+                budget: Duration::from_millis(20),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_statement_timeout_covers_reads_and_writes() {
+        let timeouts = OperationTimeouts::default();
+        assert_eq!(timeouts.statement_timeout(), Duration::from_secs(10));
+        assert!(timeouts.long_running.as_duration() > timeouts.statement_timeout());
+    }
+
+    #[test]
+    fn test_deserialize_partial_budgets() {
+        let timeouts: OperationTimeouts =
+            serde_json::from_str(r#"{"read": "2s", "long_running": 60000}"#)
+                .expect("Valid budgets");
+        assert_eq!(timeouts.read.as_duration(), Duration::from_secs(2));
+        assert_eq!(timeouts.write, OperationTimeouts::default().write);
+        assert_eq!(timeouts.long_running.as_duration(), Duration::from_secs(60));
+    }
+}
@@ -2,7 +2,9 @@ use async_trait::async_trait;
 use time::OffsetDateTime;
 use uuid::Uuid;
 
-use crate::models::{TenantId, UserId, VerificationCode, VerificationType};
+use crate::models::{
+    ExpiredCodes, TenantId, UserId, VerificationCode, VerificationStats, VerificationType,
+};
 use crate::repository::tenant_aware::TenantAwareContext;
 use acci_core::error::Result;
 
@@ -77,4 +79,345 @@ pub trait VerificationCodeRepository: Sync + Send {
         tenant_id: TenantId,
         context: &dyn TenantAwareContext,
     ) -> Result<u64>;
+
+    /// Mark a verified code as consumed
+    ///
+    /// The transition happens atomically; returns `false` if the code does
+    /// not exist or is not in the verified state, e.g. because it was already
+    /// consumed.
+    async fn consume_verified(
+        &self,
+        id: Uuid,
+        tenant_id: TenantId,
+        context: &dyn TenantAwareContext,
+    ) -> Result<bool>;
+
+    /// Record the channel a code was delivered through
+    ///
+    /// Differs from the code's type when sending fell back to another channel.
+    async fn record_delivery_channel(
+        &self,
+        id: Uuid,
+        tenant_id: TenantId,
+        channel: VerificationType,
+        context: &dyn TenantAwareContext,
+    ) -> Result<()>;
+
+    /// Channel a code was delivered through, if it was recorded
+    async fn delivery_channel(
+        &self,
+        id: Uuid,
+        tenant_id: TenantId,
+        context: &dyn TenantAwareContext,
+    ) -> Result<Option<VerificationType>>;
+
+    /// Mark the pending codes of all tenants that expired before `now` as expired
+    ///
+    /// This is an administrative path that is not scoped to a tenant; the
+    /// result holds the number of codes per affected tenant.
+    async fn expire_all_pending(&self, now: OffsetDateTime) -> Result<Vec<ExpiredCodes>>;
+
+    /// Delete the codes of all tenants that expired before `before`
+    ///
+    /// Like [`Self::expire_all_pending`] this is not scoped to a tenant.
+    async fn cleanup_all_expired(&self, before: OffsetDateTime) -> Result<u64>;
+
+    /// Count the codes of a tenant issued since `since` by outcome
+    async fn verification_stats(
+        &self,
+        tenant_id: TenantId,
+        since: OffsetDateTime,
+        context: &dyn TenantAwareContext,
+    ) -> Result<VerificationStats>;
+}
+
+/// In-memory [`VerificationCodeRepository`] for tests
+///
+/// Shared by the unit tests in this crate and by downstream test crates (via
+/// the `test-support` feature).
+#[cfg(any(test, feature = "test-support"))]
+pub mod mock {
+    use super::*;
+    use crate::models::VerificationStatus;
+    use acci_core::error::Error;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex, MutexGuard};
+
+    /// Verification code repository backed by a `Vec` behind a mutex
+    ///
+    /// Codes can be seeded or inspected directly through [`codes`](Self::codes).
+    #[derive(Debug, Clone, Default)]
+    pub struct MockVerificationCodeRepository {
+        codes: Arc<Mutex<Vec<VerificationCode>>>,
+        delivery_channels: Arc<Mutex<HashMap<Uuid, VerificationType>>>,
+    }
+
+    impl MockVerificationCodeRepository {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Locks and returns the stored codes for inspection or modification
+        pub fn codes(&self) -> MutexGuard<'_, Vec<VerificationCode>> {
+            self.codes
+                .lock()
+                .expect("mock verification code store lock poisoned")
+        }
+    }
+
+    #[async_trait]
+    impl VerificationCodeRepository for MockVerificationCodeRepository {
+        async fn save(
+            &self,
+            code: &VerificationCode,
+            _context: &dyn TenantAwareContext,
+        ) -> Result<()> {
+            let mut codes = self.codes();
+            codes.push(code.clone());
+            Ok(())
+        }
+
+        async fn get_by_id(
+            &self,
+            id: Uuid,
+            tenant_id: TenantId,
+            _context: &dyn TenantAwareContext,
+        ) -> Result<Option<VerificationCode>> {
+            let codes = self.codes();
+            Ok(codes
+                .iter()
+                .find(|c| c.id == id && c.tenant_id == tenant_id)
+                .cloned())
+        }
+
+        async fn get_by_code(
+            &self,
+            code: &str,
+            user_id: UserId,
+            verification_type: VerificationType,
+            tenant_id: TenantId,
+            _context: &dyn TenantAwareContext,
+        ) -> Result<Option<VerificationCode>> {
+            let codes = self.codes();
+            Ok(codes
+                .iter()
+                .find(|c| {
+                    c.code == code
+                        && c.user_id == user_id
+                        && c.verification_type == verification_type
+                        && c.tenant_id == tenant_id
+                })
+                .cloned())
+        }
+
+        async fn get_pending_by_user(
+            &self,
+            user_id: UserId,
+            verification_type: VerificationType,
+            tenant_id: TenantId,
+            _context: &dyn TenantAwareContext,
+        ) -> Result<Vec<VerificationCode>> {
+            let codes = self.codes();
+            Ok(codes
+                .iter()
+                .filter(|c| {
+                    c.user_id == user_id
+                        && c.verification_type == verification_type
+                        && c.tenant_id == tenant_id
+                        && c.status == VerificationStatus::Pending
+                })
+                .cloned()
+                .collect())
+        }
+
+        async fn update(
+            &self,
+            code: &VerificationCode,
+            _context: &dyn TenantAwareContext,
+        ) -> Result<()> {
+            let mut codes = self.codes();
+            let stored = codes
+                .iter_mut()
+                .find(|c| c.id == code.id && c.tenant_id == code.tenant_id)
+                .ok_or_else(|| Error::Validation("Verification code not found".to_string()))?;
+            *stored = code.clone();
+            Ok(())
+        }
+
+        async fn delete(
+            &self,
+            id: Uuid,
+            tenant_id: TenantId,
+            _context: &dyn TenantAwareContext,
+        ) -> Result<()> {
+            let mut codes = self.codes();
+            let initial_len = codes.len();
+            codes.retain(|c| !(c.id == id && c.tenant_id == tenant_id));
+            if codes.len() == initial_len {
+                return Err(Error::Validation("Verification code not found".to_string()));
+            }
+            Ok(())
+        }
+
+        async fn delete_expired(
+            &self,
+            before: OffsetDateTime,
+            _tenant_id: TenantId,
+            _context: &dyn TenantAwareContext,
+        ) -> Result<u64> {
+            let mut codes = self.codes();
+            let initial_len = codes.len();
+            codes.retain(|c| c.expires_at >= before);
+            Ok((initial_len - codes.len()) as u64)
+        }
+
+        async fn invalidate_pending(
+            &self,
+            user_id: UserId,
+            verification_type: VerificationType,
+            tenant_id: TenantId,
+            _context: &dyn TenantAwareContext,
+        ) -> Result<u64> {
+            let mut codes = self.codes();
+            let mut count = 0;
+            for code in codes.iter_mut() {
+                if code.user_id == user_id
+                    && code.verification_type == verification_type
+                    && code.tenant_id == tenant_id
+                    && code.status == VerificationStatus::Pending
+                {
+                    code.status = VerificationStatus::Invalidated;
+                    count += 1;
+                }
+            }
+            Ok(count)
+        }
+
+        async fn count_recent_attempts(
+            &self,
+            user_id: UserId,
+            verification_type: VerificationType,
+            since: OffsetDateTime,
+            tenant_id: TenantId,
+            _context: &dyn TenantAwareContext,
+        ) -> Result<u64> {
+            let codes = self.codes();
+            let count = codes
+                .iter()
+                .filter(|c| {
+                    c.user_id == user_id
+                        && c.verification_type == verification_type
+                        && c.tenant_id == tenant_id
+                        && c.created_at > since
+                })
+                .count();
+            Ok(count as u64)
+        }
+
+        async fn consume_verified(
+            &self,
+            id: Uuid,
+            tenant_id: TenantId,
+            _context: &dyn TenantAwareContext,
+        ) -> Result<bool> {
+            let mut codes = self.codes();
+            match codes.iter_mut().find(|c| {
+                c.id == id && c.tenant_id == tenant_id && c.status == VerificationStatus::Verified
+            }) {
+                Some(code) => {
+                    code.status = VerificationStatus::Consumed;
+                    Ok(true)
+                },
+                None => Ok(false),
+            }
+        }
+
+        async fn record_delivery_channel(
+            &self,
+            id: Uuid,
+            tenant_id: TenantId,
+            channel: VerificationType,
+            _context: &dyn TenantAwareContext,
+        ) -> Result<()> {
+            if !self
+                .codes()
+                .iter()
+                .any(|c| c.id == id && c.tenant_id == tenant_id)
+            {
+                return Err(Error::Validation("Verification code not found".to_string()));
+            }
+            self.delivery_channels
+                .lock()
+                .expect("mock verification code store lock poisoned")
+                .insert(id, channel);
+            Ok(())
+        }
+
+        async fn delivery_channel(
+            &self,
+            id: Uuid,
+            _tenant_id: TenantId,
+            _context: &dyn TenantAwareContext,
+        ) -> Result<Option<VerificationType>> {
+            Ok(self
+                .delivery_channels
+                .lock()
+                .expect("mock verification code store lock poisoned")
+                .get(&id)
+                .copied())
+        }
+
+        async fn expire_all_pending(&self, now: OffsetDateTime) -> Result<Vec<ExpiredCodes>> {
+            let mut codes = self.codes();
+            let mut expired: Vec<ExpiredCodes> = Vec::new();
+            for code in codes
+                .iter_mut()
+                .filter(|c| c.status == VerificationStatus::Pending && c.expires_at < now)
+            {
+                code.status = VerificationStatus::Expired;
+                match expired.iter_mut().find(|e| e.tenant_id == code.tenant_id) {
+                    Some(tenant) => tenant.count += 1,
+                    None => expired.push(ExpiredCodes {
+                        tenant_id: code.tenant_id,
+                        count: 1,
+                    }),
+                }
+            }
+            Ok(expired)
+        }
+
+        async fn cleanup_all_expired(&self, before: OffsetDateTime) -> Result<u64> {
+            let mut codes = self.codes();
+            let initial_len = codes.len();
+            codes.retain(|c| c.expires_at >= before);
+            Ok((initial_len - codes.len()) as u64)
+        }
+
+        async fn verification_stats(
+            &self,
+            tenant_id: TenantId,
+            since: OffsetDateTime,
+            _context: &dyn TenantAwareContext,
+        ) -> Result<VerificationStats> {
+            let now = OffsetDateTime::now_utc();
+            let codes = self.codes();
+            let mut stats = VerificationStats::default();
+            for code in codes
+                .iter()
+                .filter(|c| c.tenant_id == tenant_id && c.created_at >= since)
+            {
+                stats.issued += 1;
+                match code.status {
+                    VerificationStatus::Verified | VerificationStatus::Consumed => {
+                        stats.verified += 1
+                    },
+                    VerificationStatus::Invalidated => stats.invalidated += 1,
+                    VerificationStatus::Expired => stats.expired += 1,
+                    VerificationStatus::Pending if code.expires_at < now => stats.expired += 1,
+                    VerificationStatus::Pending => stats.pending += 1,
+                }
+            }
+            Ok(stats)
+        }
+    }
 }
@@ -1,27 +1,476 @@
 use chrono::{DateTime, Utc};
 use redis::{self, AsyncCommands};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::time::Duration as StdDuration;
 use tracing::{debug, warn};
 
-use super::config::BruteForceConfig;
-use super::types::{BruteForceError, LoginAttempt, create_tenant_redis_key};
+use super::config::{BruteForceConfig, RedisAvailabilityConfig, RedisUnavailablePolicy};
+#[cfg(any(test, feature = "in-memory"))]
+use super::memory::InMemoryAttemptCounter;
+use super::redis_circuit::{RedisCircuit, RedisConnection};
+use super::types::{BruteForceError, Challenge, LoginAttempt, RiskLevel, create_tenant_redis_key};
+use crate::utils::clock::{Clock, SystemClock};
+use crate::utils::duration::DurationSecs;
+
+/// Redis key prefix of the per-username counter, shared with the single-key methods
+const USERNAME_PREFIX: &str = "bruteforce";
+/// Redis key prefix of the per-IP counter
+const IP_PREFIX: &str = "bruteforce:ip";
+/// Pattern matching the keys of the per-username and per-IP counters of all tenants
+const COUNTER_KEY_PATTERN: &str = "security:*:bruteforce:*";
+
+/// Whether `key` is the key of a per-username counter
+fn is_username_key(key: &str) -> bool {
+    let mut parts = key.splitn(4, ':');
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some("security"), Some(_), Some(USERNAME_PREFIX), Some(rest)) => !rest.starts_with("ip:"),
+        _ => false,
+    }
+}
+
+/// Whether `key` is the key of a per-username or per-IP counter
+fn is_counter_key(key: &str) -> bool {
+    let mut parts = key.splitn(4, ':');
+    matches!(
+        (parts.next(), parts.next(), parts.next(), parts.next()),
+        (Some("security"), Some(_), Some(USERNAME_PREFIX), Some(_))
+    )
+}
+
+/// Failed attempts of one counter, kept across a restart
+///
+/// Produced by [`BruteForceProtection::export_counters`] and restored by
+/// [`BruteForceProtection::import_counters`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CounterSnapshot {
+    /// Redis key of the counter
+    pub key: String,
+    /// Timestamps of the attempts within the window, oldest first
+    pub attempts: Vec<i64>,
+}
+
+/// State of one brute force counter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CounterStatus {
+    /// Failed attempts within the window
+    pub attempts: u32,
+    /// Threshold at which the counter is locked
+    pub max_attempts: u32,
+}
+
+impl CounterStatus {
+    /// Whether the counter reached its threshold
+    pub fn is_locked(&self) -> bool {
+        self.attempts >= self.max_attempts
+    }
+
+    /// Attempts left before the counter is locked
+    pub fn remaining(&self) -> u32 {
+        self.max_attempts.saturating_sub(self.attempts)
+    }
+}
+
+/// Combined state of the per-username and per-IP counters
+///
+/// A locked username means someone is guessing the password of one account,
+/// possibly from many IPs: the login has to be confirmed with MFA and the
+/// account owner notified. A locked IP means one client is failing for many
+/// accounts, e.g. a shared office network: it is throttled, but legitimate
+/// users behind it are never locked out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProtectionStatus {
+    /// Failed attempts for the username
+    pub username: CounterStatus,
+    /// Failed attempts from the IP address
+    pub ip: CounterStatus,
+    /// Risk of the next attempt, escalated by each locked counter
+    pub risk_level: RiskLevel,
+    /// Delay in milliseconds to apply to the next attempt from the IP
+    pub ip_delay_ms: u64,
+}
+
+impl ProtectionStatus {
+    fn new(username: CounterStatus, ip: CounterStatus, ip_delay_ms: u64) -> Self {
+        let risk_level = match (username.is_locked(), ip.is_locked()) {
+            (true, true) => RiskLevel::Critical,
+            (true, false) => RiskLevel::High,
+            (false, true) => RiskLevel::Medium,
+            (false, false) => RiskLevel::Low,
+        };
+        Self {
+            username,
+            ip,
+            risk_level,
+            ip_delay_ms,
+        }
+    }
+
+    /// Challenge the next login attempt has to pass
+    ///
+    /// IP lockouts only ever delay the attempt; the captcha escalation is left
+    /// to [`CredentialStuffingProtection`](super::CredentialStuffingProtection).
+    pub fn challenge(&self) -> Challenge {
+        match self.risk_level {
+            RiskLevel::Low => Challenge::None,
+            RiskLevel::Medium => {
+                Challenge::Delay(u32::try_from(self.ip_delay_ms).unwrap_or(u32::MAX))
+            },
+            RiskLevel::High | RiskLevel::Critical => Challenge::MfaRequired,
+        }
+    }
+
+    /// Whether the account owner should be told about the attempts
+    ///
+    /// True only for the attempt that locked the username, so the owner is
+    /// notified once per lockout.
+    pub fn notify_user(&self) -> bool {
+        self.username.attempts == self.username.max_attempts
+    }
+}
 
 /// Implements brute force protection using Redis-backed storage
-pub struct BruteForceProtection {
-    redis_client: Arc<redis::Client>,
+///
+/// Failed attempts are counted per username and per IP address. The
+/// single-key methods operate on the per-username counter.
+///
+/// While Redis is unavailable, nothing is counted and the
+/// [`RedisUnavailablePolicy`] of the tenant decides: `FailOpen` allows every
+/// attempt, `FailClosed` fails with [`BruteForceError::Unavailable`] and
+/// reports accounts as locked, `Degrade` applies the maximum delay and
+/// requires MFA.
+///
+/// With the `in-memory` feature, [`BruteForceProtection::in_memory`] counts
+/// the attempts in process memory instead, so each instance counts only the
+/// attempts it saw.
+pub struct BruteForceProtection<C: Clock = SystemClock> {
+    backend: AttemptBackend,
     config: BruteForceConfig,
+    clock: C,
+}
+
+enum AttemptBackend {
+    Redis(Arc<RedisCircuit>),
+    #[cfg(any(test, feature = "in-memory"))]
+    Memory(Arc<InMemoryAttemptCounter>),
+}
+
+/// Access to the attempt lists for the duration of one operation
+enum Counters {
+    Redis(RedisConnection),
+    #[cfg(any(test, feature = "in-memory"))]
+    Memory(Arc<InMemoryAttemptCounter>),
+}
+
+impl Counters {
+    /// Append an attempt at `timestamp`, expiring a new list after `window` and a minute
+    async fn push(
+        &mut self,
+        key: &str,
+        timestamp: i64,
+        window: DurationSecs,
+    ) -> Result<(), BruteForceError> {
+        let expiry = window.as_secs() + 60; // Add a minute buffer
+        match self {
+            Counters::Redis(conn) => {
+                // Store attempt timestamp
+                let _: () = conn
+                    .rpush(key, timestamp.to_string())
+                    .await
+                    .map_err(BruteForceError::Redis)?;
+
+                // Set expiration if not already set (for automatic cleanup)
+                let ttl: i64 = conn.ttl(key).await.map_err(BruteForceError::Redis)?;
+                if ttl < 0 {
+                    let _: () = conn
+                        .expire(key, expiry as i64)
+                        .await
+                        .map_err(BruteForceError::Redis)?;
+                }
+                Ok(())
+            },
+            #[cfg(any(test, feature = "in-memory"))]
+            Counters::Memory(counter) => {
+                counter.push(key, timestamp, StdDuration::from_secs(expiry));
+                Ok(())
+            },
+        }
+    }
+
+    /// Timestamps of all attempts under `key`
+    async fn timestamps(&mut self, key: &str) -> Result<Vec<i64>, BruteForceError> {
+        match self {
+            Counters::Redis(conn) => {
+                let attempts: Vec<String> = conn
+                    .lrange(key, 0, -1)
+                    .await
+                    .map_err(BruteForceError::Redis)?;
+                Ok(attempts
+                    .iter()
+                    .filter_map(|ts_str| ts_str.parse::<i64>().ok())
+                    .collect())
+            },
+            #[cfg(any(test, feature = "in-memory"))]
+            Counters::Memory(counter) => Ok(counter.attempts(key)),
+        }
+    }
+
+    /// Number of attempts under `key`
+    async fn len(&mut self, key: &str) -> Result<usize, BruteForceError> {
+        match self {
+            Counters::Redis(conn) => conn.llen(key).await.map_err(BruteForceError::Redis),
+            #[cfg(any(test, feature = "in-memory"))]
+            Counters::Memory(counter) => Ok(counter.attempts(key).len()),
+        }
+    }
+
+    /// Remove all attempts under `key`
+    async fn clear(&mut self, key: &str) -> Result<(), BruteForceError> {
+        match self {
+            Counters::Redis(conn) => conn.del(key).await.map_err(BruteForceError::Redis),
+            #[cfg(any(test, feature = "in-memory"))]
+            Counters::Memory(counter) => {
+                counter.clear(key);
+                Ok(())
+            },
+        }
+    }
+
+    /// Replace the attempts under `key`, expiring the list after `ttl`
+    async fn restore(
+        &mut self,
+        key: &str,
+        attempts: &[i64],
+        ttl: StdDuration,
+    ) -> Result<(), BruteForceError> {
+        match self {
+            Counters::Redis(conn) => {
+                let attempts: Vec<String> = attempts.iter().map(ToString::to_string).collect();
+                redis::pipe()
+                    .atomic()
+                    .del(key)
+                    .ignore()
+                    .rpush(key, attempts)
+                    .ignore()
+                    .expire(key, ttl.as_secs() as i64)
+                    .ignore()
+                    .query_async(conn)
+                    .await
+                    .map_err(BruteForceError::Redis)
+            },
+            #[cfg(any(test, feature = "in-memory"))]
+            Counters::Memory(counter) => {
+                counter.clear(key);
+                for &timestamp in attempts {
+                    counter.push(key, timestamp, ttl);
+                }
+                Ok(())
+            },
+        }
+    }
+
+    /// Keys of the per-username and per-IP counters of all tenants
+    async fn counter_keys(&mut self) -> Result<Vec<String>, BruteForceError> {
+        let keys = match self {
+            Counters::Redis(conn) => {
+                let mut keys = Vec::new();
+                let mut cursor: u64 = 0;
+                loop {
+                    let (next, batch): (u64, Vec<String>) = redis::cmd("SCAN")
+                        .arg(cursor)
+                        .arg("MATCH")
+                        .arg(COUNTER_KEY_PATTERN)
+                        .arg("COUNT")
+                        .arg(500)
+                        .query_async(conn)
+                        .await
+                        .map_err(BruteForceError::Redis)?;
+                    keys.extend(batch);
+                    if next == 0 {
+                        break keys;
+                    }
+                    cursor = next;
+                }
+            },
+            #[cfg(any(test, feature = "in-memory"))]
+            Counters::Memory(counter) => counter.keys(),
+        };
+        Ok(keys.into_iter().filter(|key| is_counter_key(key)).collect())
+    }
+
+    /// Remove up to `count` of the most recent attempts under `key`
+    async fn drop_latest(&mut self, key: &str, count: u32) -> Result<(), BruteForceError> {
+        match self {
+            Counters::Redis(conn) => redis::cmd("RPOP")
+                .arg(key)
+                .arg(count)
+                .query_async(conn)
+                .await
+                .map_err(BruteForceError::Redis),
+            #[cfg(any(test, feature = "in-memory"))]
+            Counters::Memory(counter) => {
+                counter.drop_latest(key, count as usize);
+                Ok(())
+            },
+        }
+    }
 }
 
 impl BruteForceProtection {
     /// Create a new brute force protection instance
     pub fn new(redis_client: Arc<redis::Client>, config: BruteForceConfig) -> Self {
+        Self::with_clock(redis_client, config, SystemClock)
+    }
+}
+
+impl<C: Clock> BruteForceProtection<C> {
+    /// Create a brute force protection instance that reads the current time from `clock`
+    pub fn with_clock(
+        redis_client: Arc<redis::Client>,
+        config: BruteForceConfig,
+        clock: C,
+    ) -> Self {
         Self {
-            redis_client,
+            backend: AttemptBackend::Redis(Arc::new(RedisCircuit::new(
+                redis_client,
+                RedisAvailabilityConfig::default(),
+            ))),
             config,
+            clock,
         }
     }
 
+    /// Create a brute force protection instance counting attempts in `counter`
+    #[cfg(any(test, feature = "in-memory"))]
+    pub fn in_memory(counter: InMemoryAttemptCounter, config: BruteForceConfig, clock: C) -> Self {
+        Self {
+            backend: AttemptBackend::Memory(Arc::new(counter)),
+            config,
+            clock,
+        }
+    }
+
+    /// Use a circuit shared with the other security components
+    ///
+    /// Has no effect on an in-memory instance.
+    pub fn with_redis_circuit(mut self, circuit: Arc<RedisCircuit>) -> Self {
+        match &mut self.backend {
+            AttemptBackend::Redis(current) => *current = circuit,
+            #[cfg(any(test, feature = "in-memory"))]
+            AttemptBackend::Memory(_) => {},
+        }
+        self
+    }
+
+    async fn counters(&self) -> Result<Counters, BruteForceError> {
+        match &self.backend {
+            AttemptBackend::Redis(circuit) => circuit
+                .connection()
+                .await
+                .map(Counters::Redis)
+                .map_err(BruteForceError::Redis),
+            #[cfg(any(test, feature = "in-memory"))]
+            AttemptBackend::Memory(counter) => Ok(Counters::Memory(counter.clone())),
+        }
+    }
+
+    /// Policy of `tenant_id` if `error` means Redis is unavailable
+    ///
+    /// Errors not caused by Redis are returned unchanged.
+    fn unavailable_policy(
+        &self,
+        tenant_id: &str,
+        error: BruteForceError,
+    ) -> Result<RedisUnavailablePolicy, BruteForceError> {
+        let BruteForceError::Redis(e) = error else {
+            return Err(error);
+        };
+        let policy = match &self.backend {
+            AttemptBackend::Redis(circuit) => circuit.policy_for(tenant_id),
+            // Only the Redis backend fails with a Redis error
+            #[cfg(any(test, feature = "in-memory"))]
+            AttemptBackend::Memory(_) => RedisUnavailablePolicy::FailClosed,
+        };
+        warn!(
+            "Redis unavailable for brute force protection, applying {:?}: {}",
+            policy, e
+        );
+        Ok(policy)
+    }
+
+    /// Recent failed attempts of the username `key`
+    async fn username_attempts(&self, tenant_id: &str, key: &str) -> Result<u32, BruteForceError> {
+        let redis_key = create_tenant_redis_key(tenant_id, USERNAME_PREFIX, key);
+        let mut counters = self.counters().await?;
+        self.recent_attempts(&mut counters, &redis_key, self.config.window_seconds)
+            .await
+    }
+
+    /// Appends a failed attempt to the counter stored under `redis_key`
+    async fn push_attempt(
+        &self,
+        counters: &mut Counters,
+        redis_key: &str,
+        window: DurationSecs,
+    ) -> Result<(), BruteForceError> {
+        let now = self.clock.now();
+        counters.push(redis_key, now.unix_timestamp(), window).await
+    }
+
+    /// Number of attempts under `redis_key` within `window`
+    async fn recent_attempts(
+        &self,
+        counters: &mut Counters,
+        redis_key: &str,
+        window: DurationSecs,
+    ) -> Result<u32, BruteForceError> {
+        let recent_attempts = self.attempts_within(counters, redis_key, window).await?;
+
+        Ok(u32::try_from(recent_attempts.len()).unwrap_or(u32::MAX))
+    }
+
+    /// Timestamps of the attempts under `redis_key` within `window`
+    async fn attempts_within(
+        &self,
+        counters: &mut Counters,
+        redis_key: &str,
+        window: DurationSecs,
+    ) -> Result<Vec<i64>, BruteForceError> {
+        let attempts = counters.timestamps(redis_key).await?;
+
+        let window_start = (self.clock.now() - window.to_time()).unix_timestamp();
+
+        // Filter attempts within window
+        Ok(attempts
+            .into_iter()
+            .filter(|&ts| ts >= window_start)
+            .collect())
+    }
+
+    /// Window of the counter stored under `redis_key`
+    fn window_of(&self, redis_key: &str) -> DurationSecs {
+        if is_username_key(redis_key) {
+            self.config.window_seconds
+        } else {
+            self.config.ip_window_seconds
+        }
+    }
+
+    /// Exponential delay with cap for the given number of recent attempts
+    fn delay_for(&self, recent_attempts: u32) -> StdDuration {
+        if recent_attempts == 0 {
+            return StdDuration::from_millis(0);
+        }
+
+        let base_delay = self.config.base_delay_ms.as_millis();
+        let max_delay = self.config.max_delay_ms.as_millis();
+
+        let exp = recent_attempts.saturating_sub(1); // First attempt has no delay
+        let delay = base_delay.saturating_mul(2_u64.saturating_pow(exp.min(16))); // Prevent overflow with min
+
+        StdDuration::from_millis(delay.min(max_delay))
+    }
+
     /// Records a failed authentication attempt
     pub async fn record_attempt(&self, tenant_id: &str, key: &str) -> Result<(), BruteForceError> {
         if !self.config.enabled {
@@ -29,35 +478,22 @@ impl BruteForceProtection {
             return Ok(());
         }
 
-        let redis_key = create_tenant_redis_key(tenant_id, "bruteforce", key);
-        let now = Utc::now();
-
-        let mut conn = self
-            .redis_client
-            .get_async_connection()
-            .await
-            .map_err(BruteForceError::Redis)?;
-
-        // Store attempt timestamp
-        let _: () = conn
-            .rpush(&redis_key, now.timestamp().to_string())
-            .await
-            .map_err(BruteForceError::Redis)?;
-
-        // Set expiration if not already set (for automatic cleanup)
-        let ttl: i64 = conn.ttl(&redis_key).await.map_err(BruteForceError::Redis)?;
-        if ttl < 0 {
-            let expiry = (self.config.window_seconds + 60) as i64; // Add a minute buffer
-            let _: () = conn
-                .expire(&redis_key, expiry)
-                .await
-                .map_err(BruteForceError::Redis)?;
+        match self.store_attempt(tenant_id, key).await {
+            Ok(()) => Ok(()),
+            Err(e) => match self.unavailable_policy(tenant_id, e)? {
+                RedisUnavailablePolicy::FailClosed => Err(BruteForceError::Unavailable),
+                RedisUnavailablePolicy::FailOpen | RedisUnavailablePolicy::Degrade => Ok(()),
+            },
         }
+    }
 
-        let count: usize = conn
-            .llen(&redis_key)
-            .await
-            .map_err(BruteForceError::Redis)?;
+    async fn store_attempt(&self, tenant_id: &str, key: &str) -> Result<(), BruteForceError> {
+        let redis_key = create_tenant_redis_key(tenant_id, USERNAME_PREFIX, key);
+        let mut counters = self.counters().await?;
+        self.push_attempt(&mut counters, &redis_key, self.config.window_seconds)
+            .await?;
+
+        let count = counters.len(&redis_key).await?;
         debug!("Recorded failed attempt for {}: {} attempts", key, count);
 
         Ok(())
@@ -73,47 +509,26 @@ impl BruteForceProtection {
             return Ok(StdDuration::from_millis(0));
         }
 
-        let redis_key = create_tenant_redis_key(tenant_id, "bruteforce", key);
-        let mut conn = self
-            .redis_client
-            .get_async_connection()
-            .await
-            .map_err(BruteForceError::Redis)?;
-
-        // Get count of attempts within the window
-        let attempts: Vec<String> = conn
-            .lrange(&redis_key, 0, -1)
-            .await
-            .map_err(BruteForceError::Redis)?;
-
-        let now = Utc::now();
-        let window_start = now - chrono::Duration::seconds(self.config.window_seconds as i64);
-
-        // Filter attempts within window
-        let recent_attempts = attempts
-            .iter()
-            .filter_map(|ts_str| ts_str.parse::<i64>().ok())
-            .filter(|&ts| ts >= window_start.timestamp())
-            .count();
-
-        if recent_attempts == 0 {
-            return Ok(StdDuration::from_millis(0));
-        }
-
-        // Calculate exponential delay with cap
-        let base_delay = self.config.base_delay_ms;
-        let max_delay = self.config.max_delay_ms;
-
-        let exp = recent_attempts.saturating_sub(1) as u32; // First attempt has no delay
-        let delay = base_delay * (2_u32.saturating_pow(exp.min(16))); // Prevent overflow with min
-        let delay = delay.min(max_delay);
+        let recent_attempts = match self.username_attempts(tenant_id, key).await {
+            Ok(recent_attempts) => recent_attempts,
+            Err(e) => match self.unavailable_policy(tenant_id, e)? {
+                RedisUnavailablePolicy::FailOpen => 0,
+                RedisUnavailablePolicy::FailClosed => return Err(BruteForceError::Unavailable),
+                RedisUnavailablePolicy::Degrade => {
+                    return Ok(self.config.max_delay_ms.as_duration());
+                },
+            },
+        };
 
+        let delay = self.delay_for(recent_attempts);
         debug!(
             "Calculated delay for {}: {}ms ({} attempts)",
-            key, delay, recent_attempts
+            key,
+            delay.as_millis(),
+            recent_attempts
         );
 
-        Ok(StdDuration::from_millis(delay as u64))
+        Ok(delay)
     }
 
     /// Check if account is locked due to too many failed attempts
@@ -126,30 +541,15 @@ impl BruteForceProtection {
             return Ok(false);
         }
 
-        let redis_key = create_tenant_redis_key(tenant_id, "bruteforce", key);
-        let mut conn = self
-            .redis_client
-            .get_async_connection()
-            .await
-            .map_err(BruteForceError::Redis)?;
-
-        // Get count of attempts within the window
-        let attempts: Vec<String> = conn
-            .lrange(&redis_key, 0, -1)
-            .await
-            .map_err(BruteForceError::Redis)?;
-
-        let now = Utc::now();
-        let window_start = now - chrono::Duration::seconds(self.config.window_seconds as i64);
-
-        // Filter attempts within window
-        let recent_attempts = attempts
-            .iter()
-            .filter_map(|ts_str| ts_str.parse::<i64>().ok())
-            .filter(|&ts| ts >= window_start.timestamp())
-            .count();
+        let recent_attempts = match self.username_attempts(tenant_id, key).await {
+            Ok(recent_attempts) => recent_attempts,
+            Err(e) => match self.unavailable_policy(tenant_id, e)? {
+                RedisUnavailablePolicy::FailOpen | RedisUnavailablePolicy::Degrade => 0,
+                RedisUnavailablePolicy::FailClosed => return Ok(true),
+            },
+        };
 
-        let is_locked = recent_attempts >= self.config.max_attempts as usize;
+        let is_locked = recent_attempts >= self.config.max_attempts;
 
         if is_locked {
             warn!("Account locked due to too many failed attempts: {}", key);
@@ -168,33 +568,15 @@ impl BruteForceProtection {
             return Ok(self.config.max_attempts);
         }
 
-        let redis_key = create_tenant_redis_key(tenant_id, "bruteforce", key);
-        let mut conn = self
-            .redis_client
-            .get_async_connection()
-            .await
-            .map_err(BruteForceError::Redis)?;
-
-        // Get count of attempts within the window
-        let attempts: Vec<String> = conn
-            .lrange(&redis_key, 0, -1)
-            .await
-            .map_err(BruteForceError::Redis)?;
-
-        let now = Utc::now();
-        let window_start = now - chrono::Duration::seconds(self.config.window_seconds as i64);
+        let recent_attempts = match self.username_attempts(tenant_id, key).await {
+            Ok(recent_attempts) => recent_attempts,
+            Err(e) => match self.unavailable_policy(tenant_id, e)? {
+                RedisUnavailablePolicy::FailOpen | RedisUnavailablePolicy::Degrade => 0,
+                RedisUnavailablePolicy::FailClosed => return Ok(0),
+            },
+        };
 
-        // Filter attempts within window
-        let recent_attempts = attempts
-            .iter()
-            .filter_map(|ts_str| ts_str.parse::<i64>().ok())
-            .filter(|&ts| ts >= window_start.timestamp())
-            .count();
-
-        let remaining = self
-            .config
-            .max_attempts
-            .saturating_sub(recent_attempts as u32);
+        let remaining = self.config.max_attempts.saturating_sub(recent_attempts);
         debug!("Remaining attempts for {}: {}", key, remaining);
 
         Ok(remaining)
@@ -206,19 +588,121 @@ impl BruteForceProtection {
             return Ok(());
         }
 
-        let redis_key = create_tenant_redis_key(tenant_id, "bruteforce", key);
-        let mut conn = self
-            .redis_client
-            .get_async_connection()
-            .await
-            .map_err(BruteForceError::Redis)?;
-
-        let _: () = conn.del(&redis_key).await.map_err(BruteForceError::Redis)?;
-        debug!("Reset attempts for {}", key);
+        let redis_key = create_tenant_redis_key(tenant_id, USERNAME_PREFIX, key);
+        let result = match self.counters().await {
+            Ok(mut counters) => counters.clear(&redis_key).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => debug!("Reset attempts for {}", key),
+            Err(e) => match self.unavailable_policy(tenant_id, e)? {
+                RedisUnavailablePolicy::FailClosed => return Err(BruteForceError::Unavailable),
+                RedisUnavailablePolicy::FailOpen | RedisUnavailablePolicy::Degrade => {},
+            },
+        }
 
         Ok(())
     }
 
+    /// Number of usernames of all tenants currently locked
+    ///
+    /// Scans every counter, so it is meant for statistics rather than the
+    /// login path. No [`RedisUnavailablePolicy`] applies: while Redis is
+    /// unavailable the Redis error is returned.
+    pub async fn locked_accounts(&self) -> Result<u64, BruteForceError> {
+        if !self.config.enabled {
+            return Ok(0);
+        }
+
+        let mut counters = self.counters().await?;
+        let mut locked = 0;
+        let keys = counters.counter_keys().await?;
+        for key in keys.iter().filter(|key| is_username_key(key)) {
+            let attempts = self
+                .recent_attempts(&mut counters, key, self.config.window_seconds)
+                .await?;
+            if attempts >= self.config.max_attempts {
+                locked += 1;
+            }
+        }
+
+        Ok(locked)
+    }
+
+    /// Counters of all tenants with at least `min_attempts` attempts within their window
+    ///
+    /// Only the attempts still inside the window are exported. Like
+    /// [`locked_accounts`](Self::locked_accounts) this scans every counter and
+    /// no [`RedisUnavailablePolicy`] applies.
+    pub async fn export_counters(
+        &self,
+        min_attempts: u32,
+    ) -> Result<Vec<CounterSnapshot>, BruteForceError> {
+        if !self.config.enabled {
+            return Ok(Vec::new());
+        }
+
+        let mut counters = self.counters().await?;
+        let mut snapshots = Vec::new();
+        for key in counters.counter_keys().await? {
+            let window = self.window_of(&key);
+            let attempts = self.attempts_within(&mut counters, &key, window).await?;
+            if !attempts.is_empty() && attempts.len() >= min_attempts as usize {
+                snapshots.push(CounterSnapshot { key, attempts });
+            }
+        }
+
+        Ok(snapshots)
+    }
+
+    /// Restore counters exported before a restart
+    ///
+    /// Attempts that left their window in the meantime are dropped and each
+    /// counter expires as if it had never been exported. Counters that
+    /// already hold attempts, e.g. because Redis kept them, are left alone,
+    /// so no attempt is counted twice. Returns the number of counters
+    /// restored.
+    pub async fn import_counters(
+        &self,
+        snapshots: &[CounterSnapshot],
+    ) -> Result<usize, BruteForceError> {
+        if !self.config.enabled {
+            return Ok(0);
+        }
+
+        let mut counters = self.counters().await?;
+        let now = self.clock.now().unix_timestamp();
+        let mut restored = 0;
+        for snapshot in snapshots {
+            if !is_counter_key(&snapshot.key) {
+                warn!("Ignoring snapshot of unknown counter {}", snapshot.key);
+                continue;
+            }
+
+            let window_start = now - self.window_of(&snapshot.key).as_secs() as i64;
+            let attempts: Vec<i64> = snapshot
+                .attempts
+                .iter()
+                .copied()
+                .filter(|&ts| ts >= window_start && ts <= now)
+                .collect();
+            let Some(&latest) = attempts.iter().max() else {
+                continue;
+            };
+            if counters.len(&snapshot.key).await? > 0 {
+                continue;
+            }
+
+            // Expire a minute after the latest attempt leaves the window, as `push` would have
+            let ttl = StdDuration::from_secs((latest - window_start + 60) as u64);
+            counters.restore(&snapshot.key, &attempts, ttl).await?;
+            restored += 1;
+        }
+
+        debug!("Restored {} brute force counters", restored);
+        Ok(restored)
+    }
+
     /// Check authentication attempt for brute force protection
     pub async fn check_authentication_attempt(
         &self,
@@ -253,6 +737,165 @@ impl BruteForceProtection {
 
         Ok(())
     }
+
+    /// Record a login attempt against both the username and the IP counter
+    ///
+    /// A failure counts towards both counters. A success resets the username
+    /// counter but only decays the IP counter by `ip_success_decay`, so a
+    /// shared IP does not clear its record with one good login while a user
+    /// making many typos is not throttled forever. The returned status tells
+    /// the caller how to treat the next attempt; unlike
+    /// [`check_authentication_attempt`](Self::check_authentication_attempt)
+    /// this method neither sleeps nor fails on a lockout.
+    pub async fn record_login_attempt(
+        &self,
+        tenant_id: &str,
+        username: &str,
+        ip_address: &str,
+        successful: bool,
+    ) -> Result<ProtectionStatus, BruteForceError> {
+        if !self.config.enabled {
+            return self
+                .get_protection_status(username, ip_address, tenant_id)
+                .await;
+        }
+
+        match self
+            .update_counters(tenant_id, username, ip_address, successful)
+            .await
+        {
+            Ok(status) => Ok(status),
+            Err(e) => self.unavailable_status(tenant_id, e),
+        }
+    }
+
+    async fn update_counters(
+        &self,
+        tenant_id: &str,
+        username: &str,
+        ip_address: &str,
+        successful: bool,
+    ) -> Result<ProtectionStatus, BruteForceError> {
+        let username_key = create_tenant_redis_key(tenant_id, USERNAME_PREFIX, username);
+        let ip_key = create_tenant_redis_key(tenant_id, IP_PREFIX, ip_address);
+        let mut counters = self.counters().await?;
+
+        if successful {
+            counters.clear(&username_key).await?;
+            if self.config.ip_success_decay > 0 {
+                // Drop the most recent failures, which are the ones still inside the window
+                counters
+                    .drop_latest(&ip_key, self.config.ip_success_decay)
+                    .await?;
+            }
+        } else {
+            self.push_attempt(&mut counters, &username_key, self.config.window_seconds)
+                .await?;
+            self.push_attempt(&mut counters, &ip_key, self.config.ip_window_seconds)
+                .await?;
+        }
+
+        let status = self.status(&mut counters, &username_key, &ip_key).await?;
+        if status.username.is_locked() {
+            warn!(
+                "Too many failed attempts for username {}, requiring MFA",
+                username
+            );
+        }
+        if status.ip.is_locked() {
+            warn!(
+                "Too many failed attempts from IP {}, throttling",
+                ip_address
+            );
+        }
+
+        Ok(status)
+    }
+
+    /// Current state of the username and IP counters
+    pub async fn get_protection_status(
+        &self,
+        username: &str,
+        ip_address: &str,
+        tenant_id: &str,
+    ) -> Result<ProtectionStatus, BruteForceError> {
+        if !self.config.enabled {
+            return Ok(self.empty_status());
+        }
+
+        let username_key = create_tenant_redis_key(tenant_id, USERNAME_PREFIX, username);
+        let ip_key = create_tenant_redis_key(tenant_id, IP_PREFIX, ip_address);
+        let result = match self.counters().await {
+            Ok(mut counters) => self.status(&mut counters, &username_key, &ip_key).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(status) => Ok(status),
+            Err(e) => self.unavailable_status(tenant_id, e),
+        }
+    }
+
+    /// Status without any failed attempts
+    fn empty_status(&self) -> ProtectionStatus {
+        ProtectionStatus::new(
+            CounterStatus {
+                attempts: 0,
+                max_attempts: self.config.max_attempts,
+            },
+            CounterStatus {
+                attempts: 0,
+                max_attempts: self.config.ip_max_attempts,
+            },
+            0,
+        )
+    }
+
+    /// Status reported instead of `error` while Redis is unavailable
+    ///
+    /// A degraded status requires MFA without claiming any attempts, so the
+    /// account owner is not notified of a lockout that did not happen.
+    fn unavailable_status(
+        &self,
+        tenant_id: &str,
+        error: BruteForceError,
+    ) -> Result<ProtectionStatus, BruteForceError> {
+        let mut status = self.empty_status();
+        match self.unavailable_policy(tenant_id, error)? {
+            RedisUnavailablePolicy::FailOpen => {},
+            RedisUnavailablePolicy::FailClosed => return Err(BruteForceError::Unavailable),
+            RedisUnavailablePolicy::Degrade => status.risk_level = RiskLevel::High,
+        }
+        Ok(status)
+    }
+
+    async fn status(
+        &self,
+        counters: &mut Counters,
+        username_key: &str,
+        ip_key: &str,
+    ) -> Result<ProtectionStatus, BruteForceError> {
+        let username = CounterStatus {
+            attempts: self
+                .recent_attempts(counters, username_key, self.config.window_seconds)
+                .await?,
+            max_attempts: self.config.max_attempts,
+        };
+        let ip = CounterStatus {
+            attempts: self
+                .recent_attempts(counters, ip_key, self.config.ip_window_seconds)
+                .await?,
+            max_attempts: self.config.ip_max_attempts,
+        };
+        // The IP delay grows with every attempt beyond the threshold
+        let ip_delay_ms = if ip.is_locked() {
+            self.delay_for(ip.attempts - ip.max_attempts + 1)
+                .as_millis() as u64
+        } else {
+            0
+        };
+
+        Ok(ProtectionStatus::new(username, ip, ip_delay_ms))
+    }
 }
 
 /// Pattern detector for more sophisticated brute force detection
@@ -472,6 +1115,34 @@ mod tests {
         assert!(sequential_pattern);
     }
 
+    #[test]
+    fn test_protection_status_escalation() {
+        let counter = |attempts| CounterStatus {
+            attempts,
+            max_attempts: 3,
+        };
+
+        let status = ProtectionStatus::new(counter(2), counter(1), 0);
+        assert_eq!(status.risk_level, RiskLevel::Low);
+        assert_eq!(status.username.remaining(), 1);
+        assert!(matches!(status.challenge(), Challenge::None));
+
+        let status = ProtectionStatus::new(counter(0), counter(4), 250);
+        assert_eq!(status.risk_level, RiskLevel::Medium);
+        assert!(matches!(status.challenge(), Challenge::Delay(250)));
+
+        let status = ProtectionStatus::new(counter(3), counter(0), 0);
+        assert_eq!(status.risk_level, RiskLevel::High);
+        assert!(matches!(status.challenge(), Challenge::MfaRequired));
+        assert!(status.notify_user());
+
+        // A locked IP never turns into an IP block
+        let status = ProtectionStatus::new(counter(4), counter(9), 1000);
+        assert_eq!(status.risk_level, RiskLevel::Critical);
+        assert!(matches!(status.challenge(), Challenge::MfaRequired));
+        assert!(!status.notify_user());
+    }
+
     #[test]
     fn test_brute_force_error_types() {
         // Test error type formatting using the available variants
@@ -493,6 +1164,171 @@ mod tests {
         assert!(internal_error.to_string().contains("Internal error"));
     }
 
+    fn unavailable_protection(policy: RedisUnavailablePolicy) -> BruteForceProtection {
+        let client = Arc::new(redis::Client::open("redis://127.0.0.1:1").expect("valid Redis URL"));
+        let circuit = Arc::new(RedisCircuit::new(
+            client.clone(),
+            RedisAvailabilityConfig {
+                policy,
+                ..RedisAvailabilityConfig::default()
+            },
+        ));
+        BruteForceProtection::new(
+            client,
+            BruteForceConfig {
+                max_delay_ms: crate::utils::duration::DurationMs::from_millis(5),
+                ..BruteForceConfig::default()
+            },
+        )
+        .with_redis_circuit(circuit)
+    }
+
+    #[tokio::test]
+    async fn test_redis_unavailable_fail_open() {
+        let protection = unavailable_protection(RedisUnavailablePolicy::FailOpen);
+
+        assert!(
+            protection
+                .check_authentication_attempt("tenant", "user", false)
+                .await
+                .is_ok()
+        );
+        assert!(
+            !protection
+                .is_account_locked("tenant", "user")
+                .await
+                .expect("locked")
+        );
+        let status = protection
+            .record_login_attempt("tenant", "user", "192.0.2.1", false)
+            .await
+            .expect("status");
+        assert!(matches!(status.challenge(), Challenge::None));
+    }
+
+    #[tokio::test]
+    async fn test_redis_unavailable_fail_closed_blocks_logins() {
+        let protection = unavailable_protection(RedisUnavailablePolicy::FailClosed);
+
+        for successful in [false, true] {
+            assert!(matches!(
+                protection
+                    .check_authentication_attempt("tenant", "user", successful)
+                    .await,
+                Err(BruteForceError::Unavailable)
+            ));
+        }
+        assert!(
+            protection
+                .is_account_locked("tenant", "user")
+                .await
+                .expect("locked")
+        );
+        assert_eq!(
+            protection
+                .remaining_attempts("tenant", "user")
+                .await
+                .expect("remaining"),
+            0
+        );
+        assert!(matches!(
+            protection
+                .record_login_attempt("tenant", "user", "192.0.2.1", false)
+                .await,
+            Err(BruteForceError::Unavailable)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_redis_unavailable_degrade_requires_mfa() {
+        let protection = unavailable_protection(RedisUnavailablePolicy::Degrade);
+
+        assert!(
+            protection
+                .check_authentication_attempt("tenant", "user", false)
+                .await
+                .is_ok()
+        );
+        assert_eq!(
+            protection
+                .calculate_delay("tenant", "user")
+                .await
+                .expect("delay"),
+            Duration::from_millis(5)
+        );
+        let status = protection
+            .record_login_attempt("tenant", "user", "192.0.2.1", false)
+            .await
+            .expect("status");
+        assert!(matches!(status.challenge(), Challenge::MfaRequired));
+        assert!(!status.notify_user());
+    }
+
+    #[test]
+    fn test_username_keys_exclude_ip_counters() {
+        let tenant_id = "3f0c8a5e-2d1b-4c6a-9e7f-1a2b3c4d5e6f";
+        assert!(is_username_key(&create_tenant_redis_key(
+            tenant_id,
+            USERNAME_PREFIX,
+            "alice@example.com"
+        )));
+        assert!(!is_username_key(&create_tenant_redis_key(
+            tenant_id,
+            IP_PREFIX,
+            "192.0.2.1"
+        )));
+        assert!(!is_username_key(&create_tenant_redis_key(
+            tenant_id,
+            "ratelimit",
+            "alice@example.com"
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_locked_accounts_counts_locked_usernames_of_all_tenants() {
+        let protection = BruteForceProtection::in_memory(
+            InMemoryAttemptCounter::new(100),
+            BruteForceConfig {
+                max_attempts: 3,
+                ip_max_attempts: 2,
+                ..BruteForceConfig::default()
+            },
+            SystemClock,
+        );
+
+        for (tenant_id, username, failures) in [
+            ("tenant-a", "alice", 3),
+            ("tenant-b", "bob", 4),
+            ("tenant-b", "carol", 2),
+        ] {
+            for _ in 0..failures {
+                protection
+                    .record_login_attempt(tenant_id, username, "192.0.2.1", false)
+                    .await
+                    .expect("status");
+            }
+        }
+
+        // The locked IP counters are not accounts
+        assert_eq!(protection.locked_accounts().await.expect("count"), 2);
+
+        protection
+            .record_login_attempt("tenant-b", "bob", "192.0.2.1", true)
+            .await
+            .expect("status");
+        assert_eq!(protection.locked_accounts().await.expect("count"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_locked_accounts_fails_while_redis_is_unavailable() {
+        let protection = unavailable_protection(RedisUnavailablePolicy::FailOpen);
+
+        assert!(matches!(
+            protection.locked_accounts().await,
+            Err(BruteForceError::Redis(_))
+        ));
+    }
+
     // Helper functions for the unit tests
 
     fn calculate_backoff_delay(attempt_count: u32) -> Duration {
@@ -0,0 +1,114 @@
+//! Binding of sessions to client characteristics
+//!
+//! A lightweight alternative to token binding: when a session of a tenant
+//! with an enabled [`ClientBindingPolicy`] is created, a hash of
+//! characteristics of the client's TLS and HTTP stack is stored with the
+//! session. Requests whose hash differs were most likely made with a stolen
+//! token from another client. None of the characteristics is secret, so the
+//! binding raises the bar rather than preventing replay.
+
+use axum::http::{HeaderMap, Version, header};
+use sha2::{Digest, Sha256};
+
+use crate::models::tenant::ClientBindingPolicy;
+
+/// Characteristics of the client stack a session can be bound to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientCharacteristics {
+    /// TLS fingerprint (e.g. JA3) the load balancer forwarded
+    pub tls_fingerprint: Option<String>,
+    /// Whether the request used HTTP/2 or later rather than HTTP/1.x
+    pub http2: bool,
+    /// Language tags of the `Accept-Language` header in the order sent
+    pub accept_languages: Vec<String>,
+}
+
+impl ClientCharacteristics {
+    /// Reads the characteristics from a request
+    ///
+    /// The TLS fingerprint is taken from the header named in `policy`.
+    pub fn from_request(
+        headers: &HeaderMap,
+        version: Version,
+        policy: &ClientBindingPolicy,
+    ) -> Self {
+        let header_value = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+        Self {
+            tls_fingerprint: header_value(&policy.tls_fingerprint_header).map(str::to_lowercase),
+            http2: version >= Version::HTTP_2,
+            accept_languages: header_value(header::ACCEPT_LANGUAGE.as_str())
+                .map(accept_languages)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Stable hex-encoded SHA-256 hash of the characteristics
+    pub fn binding_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(b"tls:");
+        hasher.update(self.tls_fingerprint.as_deref().unwrap_or_default());
+        hasher.update(b"\nhttp2:");
+        hasher.update([u8::from(self.http2)]);
+        hasher.update(b"\nlanguages:");
+        hasher.update(self.accept_languages.join(","));
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// Language tags of an `Accept-Language` value without their weights
+///
+/// Only the order of the tags is kept; clients keep it stable, while
+/// weights are often recomputed.
+fn accept_languages(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .filter_map(|entry| entry.split(';').next())
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(tls_fingerprint: &str, accept_language: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-tls-fingerprint",
+            HeaderValue::from_str(tls_fingerprint).expect("valid header"),
+        );
+        headers.insert(
+            header::ACCEPT_LANGUAGE,
+            HeaderValue::from_str(accept_language).expect("valid header"),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_binding_hash_covers_each_characteristic() {
+        let policy = ClientBindingPolicy::default();
+        let hash = |headers: &HeaderMap, version| {
+            ClientCharacteristics::from_request(headers, version, &policy).binding_hash()
+        };
+        let original = headers("771,4865-4866,0-23", "de-DE,de;q=0.9,en;q=0.8");
+        let bound = hash(&original, Version::HTTP_2);
+
+        // Weights and whitespace do not matter
+        let reweighted = headers("771,4865-4866,0-23", "de-DE, de;q=0.8, en;q=0.5");
+        assert_eq!(hash(&reweighted, Version::HTTP_2), bound);
+
+        assert_ne!(hash(&original, Version::HTTP_11), bound);
+        let reordered = headers("771,4865-4866,0-23", "en;q=0.8,de-DE,de;q=0.9");
+        assert_ne!(hash(&reordered, Version::HTTP_2), bound);
+        let other_stack = headers("771,4865-4867,0-23", "de-DE,de;q=0.9,en;q=0.8");
+        assert_ne!(hash(&other_stack, Version::HTTP_2), bound);
+    }
+}
@@ -1,9 +1,20 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::utils::batch::BatchConfig;
+use crate::utils::duration::{DurationMs, DurationSecs};
+
 /// Main security configuration structure
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct SecurityConfig {
+    /// Where rate limits, nonces and brute force counters are kept
+    #[serde(default)]
+    pub backend: SecurityBackend,
+
+    /// Limits of the in-memory backend
+    #[serde(default)]
+    pub memory: MemoryBackendConfig,
+
     /// Brute force protection configuration
     #[serde(default)]
     pub brute_force: BruteForceConfig,
@@ -23,6 +34,80 @@ pub struct SecurityConfig {
     /// Replay protection configuration
     #[serde(default)]
     pub replay_protection: ReplayProtectionConfig,
+
+    /// Behavior of all Redis-backed components while Redis is unavailable
+    #[serde(default)]
+    pub redis_availability: RedisAvailabilityConfig,
+
+    /// Brute force counters kept across restarts
+    #[serde(default)]
+    pub state_snapshot: StateSnapshotConfig,
+}
+
+/// Storage of the rate limits, nonces and brute force counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecurityBackend {
+    /// Shared by all instances through Redis
+    #[default]
+    Redis,
+    /// Process memory of each instance; requires the `in-memory` feature
+    ///
+    /// Limits and nonces are not shared between instances, so only a single
+    /// instance enforces them correctly. Credential stuffing detection needs
+    /// Redis and is disabled.
+    Memory,
+}
+
+/// Configuration of the in-memory security backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryBackendConfig {
+    /// Keys each store holds before the least recently used ones are evicted
+    #[serde(default = "default_memory_max_keys")]
+    pub max_keys: usize,
+
+    /// Number of instances deployed with this configuration
+    ///
+    /// Only used to warn about running more than one instance in memory mode.
+    #[serde(default = "default_memory_instances")]
+    pub instances: u32,
+}
+
+impl Default for MemoryBackendConfig {
+    fn default() -> Self {
+        Self {
+            max_keys: default_memory_max_keys(),
+            instances: default_memory_instances(),
+        }
+    }
+}
+
+/// Configuration of the brute force state kept across restarts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateSnapshotConfig {
+    /// Whether the counters are stored on shutdown and restored on startup
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Failed attempts a counter needs within its window to be stored
+    ///
+    /// Bounds the size of the snapshot to the counters that matter.
+    #[serde(default = "default_snapshot_min_attempts")]
+    pub min_attempts: u32,
+
+    /// Age after which a snapshot is ignored
+    #[serde(default = "default_snapshot_max_age_seconds")]
+    pub max_age_seconds: DurationSecs,
+}
+
+impl Default for StateSnapshotConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_true(),
+            min_attempts: default_snapshot_min_attempts(),
+            max_age_seconds: default_snapshot_max_age_seconds(),
+        }
+    }
 }
 
 /// Configuration for brute force protection
@@ -36,21 +121,35 @@ pub struct BruteForceConfig {
     #[serde(default = "default_max_attempts")]
     pub max_attempts: u32,
 
-    /// Time window in seconds for counting failed attempts
+    /// Time window for counting failed attempts
     #[serde(default = "default_window_seconds")]
-    pub window_seconds: u32,
+    pub window_seconds: DurationSecs,
 
-    /// Base delay in milliseconds for progressive backoff
+    /// Base delay for progressive backoff
     #[serde(default = "default_base_delay_ms")]
-    pub base_delay_ms: u32,
+    pub base_delay_ms: DurationMs,
 
-    /// Maximum delay in milliseconds
+    /// Maximum delay
     #[serde(default = "default_max_delay_ms")]
-    pub max_delay_ms: u32,
+    pub max_delay_ms: DurationMs,
 
     /// Account lockout duration in minutes
     #[serde(default = "default_account_lockout_minutes")]
     pub account_lockout_minutes: u32,
+
+    /// Maximum number of failed attempts from one IP before it is throttled
+    ///
+    /// `max_attempts` and `window_seconds` apply to the per-username counter.
+    #[serde(default = "default_ip_max_attempts")]
+    pub ip_max_attempts: u32,
+
+    /// Time window for counting failed attempts per IP
+    #[serde(default = "default_window_seconds")]
+    pub ip_window_seconds: DurationSecs,
+
+    /// Number of failed attempts a successful login removes from the IP counter
+    #[serde(default = "default_ip_success_decay")]
+    pub ip_success_decay: u32,
 }
 
 impl Default for BruteForceConfig {
@@ -62,6 +161,9 @@ impl Default for BruteForceConfig {
             base_delay_ms: default_base_delay_ms(),
             max_delay_ms: default_max_delay_ms(),
             account_lockout_minutes: default_account_lockout_minutes(),
+            ip_max_attempts: default_ip_max_attempts(),
+            ip_window_seconds: default_window_seconds(),
+            ip_success_decay: default_ip_success_decay(),
         }
     }
 }
@@ -84,6 +186,10 @@ pub struct RateLimitingConfig {
     /// Tenant-specific overrides
     #[serde(default)]
     pub tenant_overrides: HashMap<String, HashMap<String, Vec<RateLimit>>>,
+
+    /// Clients that are never rejected
+    #[serde(default)]
+    pub allow_list: RateLimitAllowList,
 }
 
 impl Default for RateLimitingConfig {
@@ -92,27 +198,44 @@ impl Default for RateLimitingConfig {
             enabled: default_true(),
             default_limits: vec![
                 RateLimit {
-                    window_seconds: 1,
+                    window_seconds: DurationSecs::from_secs(1),
                     max_requests: 10,
                     backoff_multiplier: 2.0,
                 },
                 RateLimit {
-                    window_seconds: 60,
+                    window_seconds: DurationSecs::from_secs(60),
                     max_requests: 100,
                     backoff_multiplier: 2.0,
                 },
             ],
             path_limits: HashMap::new(),
             tenant_overrides: HashMap::new(),
+            allow_list: RateLimitAllowList::default(),
         }
     }
 }
 
+/// Clients exempt from rate limiting, such as internal monitoring
+///
+/// Requests of allow-listed clients are still counted, so their traffic shows
+/// up in the metrics, but they are never answered with 429.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RateLimitAllowList {
+    /// IPv4 and IPv6 ranges in CIDR notation, e.g. `10.0.0.0/8` or `2001:db8::/32`
+    #[serde(default)]
+    pub cidrs: Vec<String>,
+
+    /// IDs of service accounts, matched against the
+    /// [`ServiceAccountId`](super::ratelimit::ServiceAccountId) of a request
+    #[serde(default)]
+    pub service_accounts: Vec<String>,
+}
+
 /// Single rate limit configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RateLimit {
-    /// Time window in seconds
-    pub window_seconds: u32,
+    /// Time window
+    pub window_seconds: DurationSecs,
 
     /// Maximum requests in the window
     pub max_requests: u32,
@@ -132,9 +255,9 @@ pub struct CredentialStuffingConfig {
     #[serde(default = "default_max_velocity")]
     pub max_velocity: u32,
 
-    /// Time window in seconds for velocity calculation
+    /// Time window for velocity calculation
     #[serde(default = "default_velocity_window_seconds")]
-    pub velocity_window_seconds: u32,
+    pub velocity_window_seconds: DurationSecs,
 
     /// Whether to check username/email patterns
     #[serde(default = "default_true")]
@@ -186,6 +309,10 @@ pub struct FingerprintingConfig {
     #[serde(default = "default_true")]
     pub collect_fonts: bool,
 
+    /// Whether to store user agents as hashes instead of verbatim
+    #[serde(default = "default_true")]
+    pub hash_user_agent: bool,
+
     /// Number of days to keep fingerprints
     #[serde(default = "default_retention_days")]
     pub retention_days: u32,
@@ -193,6 +320,24 @@ pub struct FingerprintingConfig {
     /// Similarity threshold (0.0-1.0) for matching fingerprints
     #[serde(default = "default_similarity_threshold")]
     pub similarity_threshold: f32,
+
+    /// Maximum number of fingerprints deleted per statement during cleanup
+    #[serde(default = "default_cleanup_batch_size")]
+    pub cleanup_batch_size: u32,
+
+    /// Pause between cleanup batches
+    #[serde(default = "default_cleanup_batch_delay_ms")]
+    pub cleanup_batch_delay_ms: DurationMs,
+}
+
+impl FingerprintingConfig {
+    /// Batching of the fingerprint cleanup
+    pub fn cleanup_batch(&self) -> BatchConfig {
+        BatchConfig {
+            batch_size: self.cleanup_batch_size,
+            delay: self.cleanup_batch_delay_ms.as_duration(),
+        }
+    }
 }
 
 impl Default for FingerprintingConfig {
@@ -202,8 +347,11 @@ impl Default for FingerprintingConfig {
             collect_canvas: default_true(),
             collect_webgl: default_true(),
             collect_fonts: default_true(),
+            hash_user_agent: default_true(),
             retention_days: default_retention_days(),
             similarity_threshold: default_similarity_threshold(),
+            cleanup_batch_size: default_cleanup_batch_size(),
+            cleanup_batch_delay_ms: default_cleanup_batch_delay_ms(),
         }
     }
 }
@@ -215,17 +363,40 @@ pub struct ReplayProtectionConfig {
     #[serde(default = "default_true")]
     pub enabled: bool,
 
-    /// Nonce expiration time in seconds
+    /// Nonce expiration time
     #[serde(default = "default_nonce_expiration_seconds")]
-    pub nonce_expiration_seconds: u32,
+    pub nonce_expiration_seconds: DurationSecs,
 
     /// Whether to include timestamp validation
     #[serde(default = "default_true")]
     pub timestamp_validation: bool,
 
-    /// Maximum timestamp skew allowed in seconds
+    /// Maximum timestamp skew allowed
     #[serde(default = "default_max_timestamp_skew_seconds")]
-    pub max_timestamp_skew_seconds: u32,
+    pub max_timestamp_skew_seconds: DurationSecs,
+
+    /// Mode of routes not matched by any route class
+    #[serde(default)]
+    pub default_mode: ReplayProtectionMode,
+
+    /// Modes of route classes, matched by the longest path prefix
+    #[serde(default)]
+    pub route_classes: Vec<ReplayRouteClass>,
+
+    /// Largest body that is buffered to verify a request signature
+    #[serde(default = "default_max_signed_body_bytes")]
+    pub max_signed_body_bytes: usize,
+}
+
+impl ReplayProtectionConfig {
+    /// Mode that applies to requests for `path`
+    pub fn mode_for(&self, path: &str) -> ReplayProtectionMode {
+        self.route_classes
+            .iter()
+            .filter(|class| path.starts_with(&class.path_prefix))
+            .max_by_key(|class| class.path_prefix.len())
+            .map_or(self.default_mode, |class| class.mode)
+    }
 }
 
 impl Default for ReplayProtectionConfig {
@@ -235,6 +406,87 @@ impl Default for ReplayProtectionConfig {
             nonce_expiration_seconds: default_nonce_expiration_seconds(),
             timestamp_validation: default_true(),
             max_timestamp_skew_seconds: default_max_timestamp_skew_seconds(),
+            default_mode: ReplayProtectionMode::default(),
+            route_classes: Vec::new(),
+            max_signed_body_bytes: default_max_signed_body_bytes(),
+        }
+    }
+}
+
+/// How requests are protected against replays
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayProtectionMode {
+    /// A server-issued nonce is consumed; the request content is not bound to it
+    #[default]
+    NonceOnly,
+    /// The request carries an HMAC signature over its content, timestamp and a
+    /// client-chosen nonce, see [`acci_core::signing`]
+    Signed,
+}
+
+/// Replay protection mode of the routes below a path prefix
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayRouteClass {
+    /// Path prefix, e.g. `/api/payments`
+    pub path_prefix: String,
+    /// Mode of the matched routes
+    pub mode: ReplayProtectionMode,
+}
+
+/// How a Redis-backed security component behaves while Redis is unavailable
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedisUnavailablePolicy {
+    /// Skip the check: no attempts are counted, no limit applies and every
+    /// nonce is accepted
+    #[default]
+    FailOpen,
+    /// Reject: logins are blocked, requests are rate limited and nonces are
+    /// not accepted until Redis is back
+    FailClosed,
+    /// Keep the checks that work without shared state: logins require MFA
+    /// and rate limits and nonces are tracked in memory per instance
+    Degrade,
+}
+
+/// Behavior while Redis is unavailable and the circuit around it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedisAvailabilityConfig {
+    /// Policy of tenants without an override
+    #[serde(default)]
+    pub policy: RedisUnavailablePolicy,
+
+    /// Policies of individual tenants, e.g. `fail_closed` for high-security tenants
+    #[serde(default)]
+    pub tenant_policies: HashMap<String, RedisUnavailablePolicy>,
+
+    /// Consecutive connection failures after which the circuit opens
+    #[serde(default = "default_circuit_failure_threshold")]
+    pub circuit_failure_threshold: u32,
+
+    /// Time the circuit stays open before Redis is tried again
+    #[serde(default = "default_circuit_open_seconds")]
+    pub circuit_open_seconds: DurationSecs,
+}
+
+impl RedisAvailabilityConfig {
+    /// Policy that applies to requests of `tenant_id`
+    pub fn policy_for(&self, tenant_id: &str) -> RedisUnavailablePolicy {
+        self.tenant_policies
+            .get(tenant_id)
+            .copied()
+            .unwrap_or(self.policy)
+    }
+}
+
+impl Default for RedisAvailabilityConfig {
+    fn default() -> Self {
+        Self {
+            policy: RedisUnavailablePolicy::default(),
+            tenant_policies: HashMap::new(),
+            circuit_failure_threshold: default_circuit_failure_threshold(),
+            circuit_open_seconds: default_circuit_open_seconds(),
         }
     }
 }
@@ -248,28 +500,36 @@ fn default_max_attempts() -> u32 {
     5
 }
 
-fn default_window_seconds() -> u32 {
-    300 // 5 minutes
+fn default_window_seconds() -> DurationSecs {
+    DurationSecs::from_secs(300) // 5 minutes
 }
 
-fn default_base_delay_ms() -> u32 {
-    100
+fn default_base_delay_ms() -> DurationMs {
+    DurationMs::from_millis(100)
 }
 
-fn default_max_delay_ms() -> u32 {
-    30000 // 30 seconds
+fn default_max_delay_ms() -> DurationMs {
+    DurationMs::from_millis(30000) // 30 seconds
 }
 
 fn default_account_lockout_minutes() -> u32 {
     30
 }
 
+fn default_ip_max_attempts() -> u32 {
+    20
+}
+
+fn default_ip_success_decay() -> u32 {
+    3
+}
+
 fn default_max_velocity() -> u32 {
     10
 }
 
-fn default_velocity_window_seconds() -> u32 {
-    60
+fn default_velocity_window_seconds() -> DurationSecs {
+    DurationSecs::from_secs(60)
 }
 
 fn default_ip_block_minutes() -> u32 {
@@ -284,10 +544,46 @@ fn default_similarity_threshold() -> f32 {
     0.8
 }
 
-fn default_nonce_expiration_seconds() -> u32 {
-    300 // 5 minutes
+fn default_cleanup_batch_size() -> u32 {
+    BatchConfig::default().batch_size
 }
 
-fn default_max_timestamp_skew_seconds() -> u32 {
-    60
+fn default_cleanup_batch_delay_ms() -> DurationMs {
+    BatchConfig::default().delay.into()
+}
+
+fn default_nonce_expiration_seconds() -> DurationSecs {
+    DurationSecs::from_secs(300) // 5 minutes
+}
+
+fn default_max_timestamp_skew_seconds() -> DurationSecs {
+    DurationSecs::from_secs(60)
+}
+
+fn default_max_signed_body_bytes() -> usize {
+    1024 * 1024 // 1 MiB
+}
+
+fn default_memory_max_keys() -> usize {
+    100_000
+}
+
+fn default_memory_instances() -> u32 {
+    1
+}
+
+fn default_snapshot_min_attempts() -> u32 {
+    3
+}
+
+fn default_snapshot_max_age_seconds() -> DurationSecs {
+    DurationSecs::from_secs(3600)
+}
+
+fn default_circuit_failure_threshold() -> u32 {
+    3
+}
+
+fn default_circuit_open_seconds() -> DurationSecs {
+    DurationSecs::from_secs(30)
 }
@@ -1,18 +1,24 @@
 use chrono::{Duration, Utc};
-use redis::{self, AsyncCommands};
+use redis::{self, AsyncCommands, RedisResult};
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
+use uuid::Uuid;
 
-use super::config::CredentialStuffingConfig;
+use super::config::{CredentialStuffingConfig, RedisAvailabilityConfig, RedisUnavailablePolicy};
+use super::redis_circuit::RedisCircuit;
+use super::similarity::levenshtein_distance;
 use super::types::{
     CaptchaChallenge, CaptchaType, Challenge, LoginAttempt, RiskLevel, create_tenant_redis_key,
 };
+use crate::services::event_export::{EventExporter, SecurityEvent};
+use crate::utils::duration::DurationSecs;
 
 /// Detects and mitigates credential stuffing attacks
 pub struct CredentialStuffingProtection {
     pattern_detector: Arc<PatternDetector>,
     challenge_provider: Arc<ChallengeProvider>,
     config: CredentialStuffingConfig,
+    event_exporter: Option<Arc<EventExporter>>,
 }
 
 impl CredentialStuffingProtection {
@@ -26,46 +32,60 @@ impl CredentialStuffingProtection {
             pattern_detector,
             challenge_provider,
             config,
+            event_exporter: None,
         }
     }
 
+    /// Raise an alert with `exporter` for every suspicious login attempt
+    pub fn with_event_exporter(mut self, exporter: Arc<EventExporter>) -> Self {
+        self.event_exporter = Some(exporter);
+        self
+    }
+
     /// Analyze login attempt and determine risk level
+    ///
+    /// While Redis is unavailable, a tenant that fails closed gets
+    /// [`RiskLevel::Critical`] and one that degrades at least
+    /// [`RiskLevel::High`].
     pub async fn analyze_login_attempt(&self, attempt: &LoginAttempt) -> RiskLevel {
+        self.assess(attempt).await.unwrap_or(RiskLevel::Critical)
+    }
+
+    /// Risk level of an attempt, or `None` if it has to be rejected because
+    /// Redis is unavailable and the tenant fails closed
+    async fn assess(&self, attempt: &LoginAttempt) -> Option<RiskLevel> {
         if !self.config.enabled {
-            return RiskLevel::Low;
+            return Some(RiskLevel::Low);
         }
 
-        // Check IP velocity
-        let ip_velocity = self
-            .pattern_detector
-            .check_ip_velocity(
-                &attempt.tenant_id,
-                &attempt.ip_address,
-                self.config.velocity_window_seconds,
-            )
-            .await;
-
-        // Check username patterns if configured
-        let suspicious_username_pattern = if self.config.check_username_patterns {
-            self.pattern_detector
-                .check_username_pattern(&attempt.tenant_id, &attempt.username)
-                .await
-        } else {
-            false
-        };
-
-        // Get recent attempts for pattern analysis
-        let _recent_attempts = self
-            .pattern_detector
-            .get_recent_attempts(
-                &attempt.tenant_id,
-                &attempt.ip_address,
-                self.config.velocity_window_seconds,
-            )
-            .await;
-
-        // Calculate overall risk level
         let mut risk_level = RiskLevel::Low;
+        let (ip_velocity, suspicious_username_pattern) = match self.pattern_signals(attempt).await {
+            Ok(signals) => signals,
+            Err(e) => match self
+                .pattern_detector
+                .redis_unavailable_policy(&attempt.tenant_id)
+            {
+                RedisUnavailablePolicy::FailOpen => {
+                    warn!(
+                        "Redis unavailable, skipping credential stuffing checks: {}",
+                        e
+                    );
+                    (0, false)
+                },
+                RedisUnavailablePolicy::FailClosed => {
+                    warn!("Redis unavailable, rejecting login attempt: {}", e);
+                    return None;
+                },
+                RedisUnavailablePolicy::Degrade => {
+                    warn!(
+                        "Redis unavailable, treating login attempt as high risk: {}",
+                        e
+                    );
+                    risk_level = RiskLevel::High;
+                    (0, false)
+                },
+            },
+        };
 
         // Velocity-based risk assessment
         if ip_velocity > self.config.max_velocity * 2 {
@@ -96,9 +116,35 @@ impl CredentialStuffingProtection {
                 "Suspicious login attempt detected: IP: {}, Risk: {:?}, Velocity: {}",
                 attempt.ip_address, risk_level, ip_velocity
             );
+            if let Some(exporter) = &self.event_exporter {
+                exporter.export(SecurityEvent::suspicious_login(attempt, risk_level));
+            }
         }
 
-        risk_level
+        Some(risk_level)
+    }
+
+    /// IP velocity and whether the username follows a suspicious pattern
+    async fn pattern_signals(&self, attempt: &LoginAttempt) -> RedisResult<(u32, bool)> {
+        let ip_velocity = self
+            .pattern_detector
+            .try_check_ip_velocity(
+                &attempt.tenant_id,
+                &attempt.ip_address,
+                self.config.velocity_window_seconds,
+            )
+            .await?;
+
+        // Check username patterns if configured
+        let suspicious_username_pattern = if self.config.check_username_patterns {
+            self.pattern_detector
+                .try_check_username_pattern(&attempt.tenant_id, &attempt.username)
+                .await?
+        } else {
+            false
+        };
+
+        Ok((ip_velocity, suspicious_username_pattern))
     }
 
     /// Get appropriate challenge based on risk level
@@ -131,31 +177,52 @@ impl CredentialStuffingProtection {
 
     /// Handle a login attempt
     pub async fn handle_login_attempt(&self, attempt: &LoginAttempt) -> Challenge {
-        // Store attempt for future analysis
-        self.pattern_detector.record_login_attempt(attempt).await;
-
-        // Analyze attempt and determine risk level
-        let risk_level = self.analyze_login_attempt(attempt).await;
+        match self.record_and_assess(attempt).await {
+            Some(risk_level) => self.get_challenge(attempt, risk_level).await,
+            None => Challenge::Reject,
+        }
+    }
 
-        // Get appropriate challenge based on risk level
-        self.get_challenge(attempt, risk_level).await
+    /// Store a login attempt for future analysis and assess its risk
+    ///
+    /// Returns `None` if the attempt has to be rejected because Redis is
+    /// unavailable and the tenant fails closed.
+    pub async fn record_and_assess(&self, attempt: &LoginAttempt) -> Option<RiskLevel> {
+        self.pattern_detector.record_login_attempt(attempt).await;
+        self.assess(attempt).await
     }
 }
 
 /// Detects patterns indicative of credential stuffing
 pub struct PatternDetector {
-    redis_client: Arc<redis::Client>,
+    circuit: Arc<RedisCircuit>,
 }
 
 impl PatternDetector {
     /// Create a new pattern detector
     pub fn new(redis_client: Arc<redis::Client>) -> Self {
-        Self { redis_client }
+        Self {
+            circuit: Arc::new(RedisCircuit::new(
+                redis_client,
+                RedisAvailabilityConfig::default(),
+            )),
+        }
+    }
+
+    /// Use a circuit shared with the other security components
+    pub fn with_redis_circuit(mut self, circuit: Arc<RedisCircuit>) -> Self {
+        self.circuit = circuit;
+        self
+    }
+
+    /// Policy of `tenant_id` while Redis is unavailable
+    pub fn redis_unavailable_policy(&self, tenant_id: &str) -> RedisUnavailablePolicy {
+        self.circuit.policy_for(tenant_id)
     }
 
     /// Record a login attempt for future analysis
     pub async fn record_login_attempt(&self, attempt: &LoginAttempt) {
-        let mut conn = match self.redis_client.get_async_connection().await {
+        let mut conn = match self.circuit.connection().await {
             Ok(conn) => conn,
             Err(e) => {
                 error!("Failed to get Redis connection: {}", e);
@@ -190,28 +257,44 @@ impl PatternDetector {
             "credstuffing:velocity",
             &attempt.ip_address,
         );
-        let _: Result<(), _> = conn.zadd(&velocity_key, now.to_string(), now).await;
+        // Members must be unique, or attempts within the same second collapse into one
+        let member = format!("{}:{}", now, Uuid::new_v4().simple());
+        let _: Result<(), _> = conn.zadd(&velocity_key, member, now).await;
         let _: Result<(), _> = conn.expire(&velocity_key, 3600).await; // Expire after 1 hour
     }
 
     /// Check IP velocity (number of attempts per time window)
+    ///
+    /// Returns 0 if Redis is unavailable.
     pub async fn check_ip_velocity(
         &self,
         tenant_id: &str,
         ip_address: &str,
-        window_seconds: u32,
+        window: DurationSecs,
     ) -> u32 {
-        let mut conn = match self.redis_client.get_async_connection().await {
-            Ok(conn) => conn,
+        match self
+            .try_check_ip_velocity(tenant_id, ip_address, window)
+            .await
+        {
+            Ok(velocity) => velocity,
             Err(e) => {
-                error!("Failed to get Redis connection: {}", e);
-                return 0;
+                error!("Failed to count IP velocity: {}", e);
+                0
             },
-        };
+        }
+    }
+
+    async fn try_check_ip_velocity(
+        &self,
+        tenant_id: &str,
+        ip_address: &str,
+        window: DurationSecs,
+    ) -> RedisResult<u32> {
+        let mut conn = self.circuit.connection().await?;
 
         let velocity_key = create_tenant_redis_key(tenant_id, "credstuffing:velocity", ip_address);
         let now = Utc::now().timestamp() as usize;
-        let window_start = now - window_seconds as usize;
+        let window_start = now - window.as_secs() as usize;
 
         // Clean up old entries using raw Redis command for compatibility
         let _: Result<(), _> = redis::cmd("ZREMRANGEBYSCORE")
@@ -222,27 +305,29 @@ impl PatternDetector {
             .await;
 
         // Count current entries in window
-        match conn
-            .zcount::<_, _, _, usize>(&velocity_key, window_start, "+inf")
-            .await
-        {
-            Ok(count) => count as u32,
-            Err(e) => {
-                error!("Failed to count IP velocity: {}", e);
-                0
-            },
-        }
+        let count: usize = conn.zcount(&velocity_key, window_start, "+inf").await?;
+        Ok(count as u32)
     }
 
     /// Check for suspicious username patterns
+    ///
+    /// Returns `false` if Redis is unavailable.
     pub async fn check_username_pattern(&self, tenant_id: &str, username: &str) -> bool {
-        let mut conn = match self.redis_client.get_async_connection().await {
-            Ok(conn) => conn,
+        match self.try_check_username_pattern(tenant_id, username).await {
+            Ok(suspicious) => suspicious,
             Err(e) => {
-                error!("Failed to get Redis connection: {}", e);
-                return false;
+                error!("Failed to check username pattern: {}", e);
+                false
             },
-        };
+        }
+    }
+
+    async fn try_check_username_pattern(
+        &self,
+        tenant_id: &str,
+        username: &str,
+    ) -> RedisResult<bool> {
+        let mut conn = self.circuit.connection().await?;
 
         // Get all usernames attempted from this tenant in the last day
         let pattern_key = create_tenant_redis_key(tenant_id, "credstuffing:usernames", "all");
@@ -252,10 +337,7 @@ impl PatternDetector {
         let _: Result<(), _> = conn.expire(&pattern_key, 86400).await; // Expire after 1 day
 
         // Get all usernames
-        let usernames: Vec<String> = match conn.smembers(&pattern_key).await {
-            Ok(members) => members,
-            Err(_) => return false,
-        };
+        let usernames: Vec<String> = conn.smembers(&pattern_key).await?;
 
         // Check for sequential patterns (e.g., user1, user2, user3)
         if usernames.len() > 5 {
@@ -271,7 +353,7 @@ impl PatternDetector {
             }
 
             if sequential_count >= 3 {
-                return true;
+                return Ok(true);
             }
         }
 
@@ -286,11 +368,11 @@ impl PatternDetector {
             }
 
             if similar_count >= 3 {
-                return true;
+                return Ok(true);
             }
         }
 
-        false
+        Ok(false)
     }
 
     /// Get recent login attempts for pattern analysis
@@ -298,9 +380,9 @@ impl PatternDetector {
         &self,
         tenant_id: &str,
         ip_address: &str,
-        window_seconds: u32,
+        window: DurationSecs,
     ) -> Vec<LoginAttempt> {
-        let mut conn = match self.redis_client.get_async_connection().await {
+        let mut conn = match self.circuit.connection().await {
             Ok(conn) => conn,
             Err(e) => {
                 error!("Failed to get Redis connection: {}", e);
@@ -316,7 +398,7 @@ impl PatternDetector {
 
         let mut attempts = Vec::new();
         let now = Utc::now();
-        let window_start = now - chrono::Duration::seconds(window_seconds as i64);
+        let window_start = now - chrono::Duration::seconds(window.as_secs() as i64);
 
         for raw in raw_attempts {
             if let Ok(attempt) = serde_json::from_str::<LoginAttempt>(&raw) {
@@ -375,51 +457,10 @@ fn calculate_similarity(s1: &str, s2: &str) -> f64 {
     1.0 - (distance as f64 / max_len)
 }
 
-/// Calculate Levenshtein distance between two strings
-fn levenshtein_distance(s1: &str, s2: &str) -> usize {
-    let v1: Vec<char> = s1.chars().collect();
-    let v2: Vec<char> = s2.chars().collect();
-
-    let len1 = v1.len();
-    let len2 = v2.len();
-
-    // Early return for empty strings
-    if len1 == 0 {
-        return len2;
-    }
-    if len2 == 0 {
-        return len1;
-    }
-
-    // Create distance matrix
-    let mut matrix = vec![vec![0; len2 + 1]; len1 + 1];
-
-    // Initialize first row and column
-    for (i, row) in matrix.iter_mut().enumerate().take(len1 + 1) {
-        row[0] = i;
-    }
-
-    for j in 0..=len2 {
-        matrix[0][j] = j;
-    }
-
-    // Fill rest of matrix
-    for i in 1..=len1 {
-        for j in 1..=len2 {
-            let cost = if v1[i - 1] == v2[j - 1] { 0 } else { 1 };
-
-            matrix[i][j] = (matrix[i - 1][j] + 1)
-                .min(matrix[i][j - 1] + 1)
-                .min(matrix[i - 1][j - 1] + cost);
-        }
-    }
-
-    matrix[len1][len2]
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
 
     // Helper to create a test login attempt
     fn create_test_login_attempt(username: &str, ip: &str, user_agent: &str) -> LoginAttempt {
@@ -809,4 +850,63 @@ mod tests {
             "security:tenant/with:special@chars:session:user@example.com"
         );
     }
+
+    fn unavailable_protection(policy: RedisUnavailablePolicy) -> CredentialStuffingProtection {
+        let client = Arc::new(redis::Client::open("redis://127.0.0.1:1").expect("valid Redis URL"));
+        let circuit = Arc::new(RedisCircuit::new(
+            client.clone(),
+            RedisAvailabilityConfig {
+                tenant_policies: HashMap::from([("test_tenant".to_string(), policy)]),
+                ..RedisAvailabilityConfig::default()
+            },
+        ));
+        let detector = PatternDetector::new(client).with_redis_circuit(circuit);
+        CredentialStuffingProtection::new(
+            Arc::new(detector),
+            Arc::new(ChallengeProvider::new()),
+            CredentialStuffingConfig {
+                enable_captcha: false,
+                ..CredentialStuffingConfig::default()
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_redis_unavailable_policies() {
+        let attempt = create_test_login_attempt(
+            "testuser",
+            "192.168.1.1",
+            "Mozilla/5.0 (X11; Linux x86_64) Firefox/120.0",
+        );
+
+        let fail_open = unavailable_protection(RedisUnavailablePolicy::FailOpen);
+        assert_eq!(
+            fail_open.analyze_login_attempt(&attempt).await,
+            RiskLevel::Low
+        );
+        assert!(matches!(
+            fail_open.handle_login_attempt(&attempt).await,
+            Challenge::None
+        ));
+
+        let fail_closed = unavailable_protection(RedisUnavailablePolicy::FailClosed);
+        assert_eq!(
+            fail_closed.analyze_login_attempt(&attempt).await,
+            RiskLevel::Critical
+        );
+        assert!(matches!(
+            fail_closed.handle_login_attempt(&attempt).await,
+            Challenge::Reject
+        ));
+
+        let degrade = unavailable_protection(RedisUnavailablePolicy::Degrade);
+        assert_eq!(
+            degrade.analyze_login_attempt(&attempt).await,
+            RiskLevel::High
+        );
+        assert!(matches!(
+            degrade.handle_login_attempt(&attempt).await,
+            Challenge::MfaRequired
+        ));
+    }
 }
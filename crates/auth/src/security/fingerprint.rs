@@ -1,6 +1,8 @@
+use acci_core::datetime;
 use async_trait::async_trait;
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use sqlx::{
     Pool, Postgres,
     types::ipnetwork::{IpNetwork, Ipv4Network, Ipv6Network},
@@ -8,12 +10,15 @@ use sqlx::{
 use std::collections::HashMap;
 use std::net::IpAddr;
 use std::sync::Arc;
-use time::OffsetDateTime;
-use tracing::info;
+use tracing::{info, instrument};
 use uuid::Uuid;
 
 use super::config::FingerprintingConfig;
+use super::similarity::{MAX_LEVENSHTEIN_CHARS, levenshtein_distance};
 use super::types::RiskLevel;
+use crate::repository::instrumentation::observe;
+use crate::session::types::DeviceFingerprint;
+use crate::utils::batch::{BatchConfig, run_in_batches};
 
 /// Browser fingerprint data structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,6 +57,55 @@ pub struct BrowserFingerprint {
     pub platform: Option<String>,
 }
 
+/// Fingerprint attributes collected by the client and posted with the login
+///
+/// Every attribute is optional, so clients may send whatever they were able to collect.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientFingerprint {
+    /// Platform reported by the browser, e.g. `navigator.platform`
+    pub platform: Option<String>,
+    /// Screen width in pixels
+    pub screen_width: Option<u32>,
+    /// Screen height in pixels
+    pub screen_height: Option<u32>,
+    /// Color depth
+    pub color_depth: Option<u8>,
+    /// IANA timezone name, e.g. `Europe/Berlin`
+    pub timezone: Option<String>,
+    /// Browser language
+    pub language: Option<String>,
+    /// Do Not Track setting
+    pub do_not_track: Option<bool>,
+    /// Hardware concurrency (CPU cores)
+    pub hardware_concurrency: Option<u8>,
+}
+
+impl ClientFingerprint {
+    /// Session fingerprint for a login sent with `user_agent`
+    ///
+    /// The user agent only ends up in the fingerprint as a hash.
+    pub fn into_device_fingerprint(self, user_agent: Option<&str>) -> DeviceFingerprint {
+        let mut device = DeviceFingerprint::new(hash_user_agent(user_agent.unwrap_or_default()));
+        device.platform = self.platform;
+        device.screen_resolution = match (self.screen_width, self.screen_height) {
+            (Some(width), Some(height)) => Some(format!("{width}x{height}")),
+            _ => None,
+        };
+        device.color_depth = self.color_depth;
+        device.timezone = self.timezone;
+        device.language = self.language;
+        device.do_not_track = self.do_not_track;
+        device.hardware_concurrency = self.hardware_concurrency;
+        device
+    }
+}
+
+/// Hex-encoded SHA-256 hash of a user agent string
+pub fn hash_user_agent(user_agent: &str) -> String {
+    hex::encode(Sha256::digest(user_agent.as_bytes()))
+}
+
 /// Stored fingerprint with metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoredFingerprint {
@@ -122,37 +176,22 @@ pub trait FingerprintRepository: Send + Sync {
 /// PostgreSQL implementation of fingerprint repository
 pub struct PostgresFingerprintRepository {
     pool: Pool<Postgres>,
+    cleanup_batch: BatchConfig,
 }
 
 impl PostgresFingerprintRepository {
     /// Create a new PostgreSQL fingerprint repository
     pub fn new(pool: Pool<Postgres>) -> Self {
-        Self { pool }
-    }
-
-    /// Convert OffsetDateTime to chrono::DateTime<Utc>
-    fn offset_to_chrono_utc(offset: OffsetDateTime) -> DateTime<Utc> {
-        let timestamp = offset.unix_timestamp();
-        let nanos = offset.nanosecond();
-
-        DateTime::from_timestamp(timestamp, nanos).unwrap_or_else(|| {
-            DateTime::from_timestamp(0, 0).expect("Failed to create fallback timestamp")
-        })
+        Self {
+            pool,
+            cleanup_batch: BatchConfig::default(),
+        }
     }
 
-    /// Convert chrono::DateTime<Utc> to OffsetDateTime
-    fn chrono_utc_to_offset(dt: DateTime<Utc>) -> OffsetDateTime {
-        let timestamp = dt.timestamp();
-        let nanos = dt.timestamp_subsec_nanos();
-        match OffsetDateTime::from_unix_timestamp(timestamp) {
-            Ok(odt) => match odt.replace_nanosecond(nanos) {
-                Ok(result) => result,
-                Err(_) => OffsetDateTime::from_unix_timestamp(0)
-                    .expect("Failed to create Unix timestamp from 0"),
-            },
-            Err(_) => OffsetDateTime::from_unix_timestamp(0)
-                .expect("Failed to create Unix timestamp from 0"),
-        }
+    /// Set the batching of [`FingerprintRepository::delete_old_fingerprints`]
+    pub fn with_cleanup_batch(mut self, cleanup_batch: BatchConfig) -> Self {
+        self.cleanup_batch = cleanup_batch;
+        self
     }
 
     /// Convert IpAddr to IpNetwork
@@ -176,154 +215,197 @@ impl PostgresFingerprintRepository {
 
 #[async_trait]
 impl FingerprintRepository for PostgresFingerprintRepository {
+    #[instrument(name = "fingerprint.repo.store_fingerprint", skip_all, level = "debug")]
     async fn store_fingerprint(
         &self,
         fingerprint: &StoredFingerprint,
     ) -> Result<(), anyhow::Error> {
-        let fingerprint_json = serde_json::to_value(&fingerprint.fingerprint)?;
-        let first_seen = Self::chrono_utc_to_offset(fingerprint.first_seen);
-        let last_seen = Self::chrono_utc_to_offset(fingerprint.last_seen);
-        let last_ip = Self::ip_addr_to_network(fingerprint.last_ip);
-
-        sqlx::query!(
-            r#"
+        observe("fingerprint.repo.store_fingerprint", async {
+            let fingerprint_json = serde_json::to_value(&fingerprint.fingerprint)?;
+            let first_seen = datetime::from_chrono(fingerprint.first_seen)?;
+            let last_seen = datetime::from_chrono(fingerprint.last_seen)?;
+            let last_ip = Self::ip_addr_to_network(fingerprint.last_ip);
+
+            sqlx::query!(
+                r#"
             INSERT INTO fingerprints (
                 id, tenant_id, user_id, fingerprint, first_seen, last_seen, 
                 last_ip, session_id, trusted
             )
             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             "#,
-            fingerprint.id,
-            fingerprint.tenant_id,
-            fingerprint.user_id,
-            fingerprint_json,
-            first_seen,
-            last_seen,
-            last_ip,
-            fingerprint.session_id,
-            fingerprint.trusted
-        )
-        .execute(&self.pool)
-        .await?;
+                fingerprint.id,
+                fingerprint.tenant_id,
+                fingerprint.user_id,
+                fingerprint_json,
+                first_seen,
+                last_seen,
+                last_ip,
+                fingerprint.session_id,
+                fingerprint.trusted
+            )
+            .execute(&self.pool)
+            .await?;
 
-        Ok(())
+            Ok(())
+        })
+        .await
     }
 
+    #[instrument(
+        name = "fingerprint.repo.get_fingerprints_for_user",
+        skip_all,
+        level = "debug"
+    )]
     async fn get_fingerprints_for_user(
         &self,
         tenant_id: Uuid,
         user_id: Uuid,
     ) -> Result<Vec<StoredFingerprint>, anyhow::Error> {
-        let records = sqlx::query!(
-            r#"
+        observe("fingerprint.repo.get_fingerprints_for_user", async {
+            let records = sqlx::query!(
+                r#"
             SELECT id, tenant_id, user_id, fingerprint, first_seen, last_seen, 
                    last_ip, session_id, trusted
             FROM fingerprints
             WHERE tenant_id = $1 AND user_id = $2
             ORDER BY last_seen DESC
             "#,
-            tenant_id,
-            user_id
-        )
-        .fetch_all(&self.pool)
-        .await?;
-
-        let mut fingerprints = Vec::new();
-
-        for record in records {
-            let fingerprint_data: BrowserFingerprint = serde_json::from_value(record.fingerprint)?;
+                tenant_id,
+                user_id
+            )
+            .fetch_all(&self.pool)
+            .await?;
 
-            // Extract IP address from network type
-            let ip_addr = {
-                let ip_str = record.last_ip.to_string();
-                let ip_parts: Vec<&str> = ip_str.split('/').collect();
-                ip_parts[0].parse::<IpAddr>().unwrap_or_else(|_| {
-                    "0.0.0.0"
-                        .parse()
-                        .expect("Failed to parse fallback IP address")
-                })
-            };
-
-            fingerprints.push(StoredFingerprint {
-                id: record.id,
-                tenant_id: record.tenant_id,
-                user_id: record.user_id,
-                fingerprint: fingerprint_data,
-                first_seen: Self::offset_to_chrono_utc(record.first_seen),
-                last_seen: Self::offset_to_chrono_utc(record.last_seen),
-                last_ip: ip_addr,
-                session_id: record.session_id,
-                trusted: record.trusted,
-            });
-        }
+            let mut fingerprints = Vec::new();
+
+            for record in records {
+                let fingerprint_data: BrowserFingerprint =
+                    serde_json::from_value(record.fingerprint)?;
+
+                // Extract IP address from network type
+                let ip_addr = {
+                    let ip_str = record.last_ip.to_string();
+                    let ip_parts: Vec<&str> = ip_str.split('/').collect();
+                    ip_parts[0].parse::<IpAddr>().unwrap_or_else(|_| {
+                        "0.0.0.0"
+                            .parse()
+                            .expect("Failed to parse fallback IP address")
+                    })
+                };
+
+                fingerprints.push(StoredFingerprint {
+                    id: record.id,
+                    tenant_id: record.tenant_id,
+                    user_id: record.user_id,
+                    fingerprint: fingerprint_data,
+                    first_seen: datetime::to_chrono(record.first_seen)?,
+                    last_seen: datetime::to_chrono(record.last_seen)?,
+                    last_ip: ip_addr,
+                    session_id: record.session_id,
+                    trusted: record.trusted,
+                });
+            }
 
-        Ok(fingerprints)
+            Ok(fingerprints)
+        })
+        .await
     }
 
+    #[instrument(
+        name = "fingerprint.repo.update_fingerprint",
+        skip_all,
+        level = "debug"
+    )]
     async fn update_fingerprint(
         &self,
         fingerprint: &StoredFingerprint,
     ) -> Result<(), anyhow::Error> {
-        let fingerprint_json = serde_json::to_value(&fingerprint.fingerprint)?;
-        let last_seen = Self::chrono_utc_to_offset(fingerprint.last_seen);
-        let last_ip = Self::ip_addr_to_network(fingerprint.last_ip);
+        observe("fingerprint.repo.update_fingerprint", async {
+            let fingerprint_json = serde_json::to_value(&fingerprint.fingerprint)?;
+            let last_seen = datetime::from_chrono(fingerprint.last_seen)?;
+            let last_ip = Self::ip_addr_to_network(fingerprint.last_ip);
 
-        sqlx::query!(
-            r#"
+            sqlx::query!(
+                r#"
             UPDATE fingerprints
             SET fingerprint = $1, last_seen = $2, last_ip = $3, session_id = $4, trusted = $5
             WHERE id = $6 AND tenant_id = $7 AND user_id = $8
             "#,
-            fingerprint_json,
-            last_seen,
-            last_ip,
-            fingerprint.session_id,
-            fingerprint.trusted,
-            fingerprint.id,
-            fingerprint.tenant_id,
-            fingerprint.user_id
-        )
-        .execute(&self.pool)
-        .await?;
+                fingerprint_json,
+                last_seen,
+                last_ip,
+                fingerprint.session_id,
+                fingerprint.trusted,
+                fingerprint.id,
+                fingerprint.tenant_id,
+                fingerprint.user_id
+            )
+            .execute(&self.pool)
+            .await?;
 
-        Ok(())
+            Ok(())
+        })
+        .await
     }
 
+    #[instrument(name = "fingerprint.repo.mark_as_trusted", skip_all, level = "debug")]
     async fn mark_as_trusted(&self, id: Uuid, trusted: bool) -> Result<(), anyhow::Error> {
-        sqlx::query!(
-            r#"
+        observe("fingerprint.repo.mark_as_trusted", async {
+            sqlx::query!(
+                r#"
             UPDATE fingerprints
             SET trusted = $1
             WHERE id = $2
             "#,
-            trusted,
-            id
-        )
-        .execute(&self.pool)
-        .await?;
+                trusted,
+                id
+            )
+            .execute(&self.pool)
+            .await?;
 
-        Ok(())
+            Ok(())
+        })
+        .await
     }
 
+    #[instrument(
+        name = "fingerprint.repo.delete_old_fingerprints",
+        skip_all,
+        level = "debug"
+    )]
     async fn delete_old_fingerprints(
         &self,
         tenant_id: Uuid,
         older_than: DateTime<Utc>,
     ) -> Result<u64, anyhow::Error> {
-        let offset_time = Self::chrono_utc_to_offset(older_than);
-
-        let result = sqlx::query!(
-            r#"
-            DELETE FROM fingerprints
-            WHERE tenant_id = $1 AND last_seen < $2
-            "#,
-            tenant_id,
-            offset_time
-        )
-        .execute(&self.pool)
-        .await?;
+        observe("fingerprint.repo.delete_old_fingerprints", async {
+            let offset_time = datetime::from_chrono(older_than)?;
+
+            let deleted = run_in_batches(&self.cleanup_batch, |limit| async move {
+                let result = sqlx::query(
+                    r#"
+                    DELETE FROM fingerprints
+                    WHERE id IN (
+                        SELECT id FROM fingerprints
+                        WHERE tenant_id = $1 AND last_seen < $2
+                        LIMIT $3
+                        FOR UPDATE SKIP LOCKED
+                    )
+                    "#,
+                )
+                .bind(tenant_id)
+                .bind(offset_time)
+                .bind(limit)
+                .execute(&self.pool)
+                .await?;
+                Ok::<_, anyhow::Error>(result.rows_affected())
+            })
+            .await?;
 
-        Ok(result.rows_affected())
+            Ok(deleted)
+        })
+        .await
     }
 }
 
@@ -405,6 +487,62 @@ impl FingerprintService {
         Ok(fp_id)
     }
 
+    /// Store the fingerprint a session was created with
+    ///
+    /// The raw `user_agent` is only kept if `hash_user_agent` is disabled in the
+    /// config; otherwise the hash from the session fingerprint is stored. Returns
+    /// `None` when fingerprinting is disabled.
+    pub async fn store_device_fingerprint(
+        &self,
+        tenant_id: Uuid,
+        user_id: Uuid,
+        device: &DeviceFingerprint,
+        user_agent: Option<&str>,
+        ip_address: &str,
+        session_id: Uuid,
+    ) -> Result<Option<Uuid>, anyhow::Error> {
+        if !self.config.enabled {
+            return Ok(None);
+        }
+
+        let user_agent = match user_agent {
+            Some(user_agent) if !self.config.hash_user_agent => user_agent.to_string(),
+            _ => device.user_agent_hash.clone(),
+        };
+        let screen_resolution = device.screen_resolution.as_deref().and_then(|resolution| {
+            let (width, height) = resolution.split_once('x')?;
+            Some((width.parse().ok()?, height.parse().ok()?))
+        });
+        let fingerprint = BrowserFingerprint {
+            user_agent,
+            accept_headers: String::new(),
+            canvas_hash: None,
+            webgl_hash: None,
+            fonts: None,
+            timezone: None,
+            screen_resolution,
+            color_depth: device.color_depth.map(u32::from),
+            plugins: None,
+            language: device.language.clone(),
+            do_not_track: device.do_not_track,
+            cookies_enabled: None,
+            touch_points: None,
+            device_memory: None,
+            hardware_concurrency: device.hardware_concurrency.map(u32::from),
+            platform: device.platform.clone(),
+        };
+
+        self.store_fingerprint(
+            tenant_id,
+            user_id,
+            &fingerprint,
+            ip_address,
+            Some(session_id),
+        )
+        .await
+        .map(Some)
+    }
+
     /// Verify a fingerprint against known user fingerprints
     pub async fn verify_fingerprint(
         &self,
@@ -665,8 +803,9 @@ fn string_similarity(s1: &str, s2: &str) -> f64 {
         return 1.0;
     }
 
-    let len1 = s1.len();
-    let len2 = s2.len();
+    // Normalize by the same truncated lengths the distance is computed on
+    let len1 = s1.chars().take(MAX_LEVENSHTEIN_CHARS).count();
+    let len2 = s2.chars().take(MAX_LEVENSHTEIN_CHARS).count();
 
     if len1 == 0 || len2 == 0 {
         return 0.0;
@@ -695,49 +834,9 @@ fn set_similarity<T: Eq + std::hash::Hash>(set1: &[T], set2: &[T]) -> f64 {
     intersection as f64 / union as f64
 }
 
-/// Calculate Levenshtein distance between two strings
-fn levenshtein_distance(s1: &str, s2: &str) -> usize {
-    let v1: Vec<char> = s1.chars().collect();
-    let v2: Vec<char> = s2.chars().collect();
-
-    let len1 = v1.len();
-    let len2 = v2.len();
-
-    if len1 == 0 {
-        return len2;
-    }
-    if len2 == 0 {
-        return len1;
-    }
-
-    let mut matrix = vec![vec![0; len2 + 1]; len1 + 1];
-
-    for (i, row) in matrix.iter_mut().enumerate().take(len1 + 1) {
-        row[0] = i;
-    }
-
-    for j in 0..=len2 {
-        matrix[0][j] = j;
-    }
-
-    for i in 1..=len1 {
-        for j in 1..=len2 {
-            let cost = if v1[i - 1] == v2[j - 1] { 0 } else { 1 };
-
-            matrix[i][j] = std::cmp::min(
-                std::cmp::min(matrix[i - 1][j] + 1, matrix[i][j - 1] + 1),
-                matrix[i - 1][j - 1] + cost,
-            );
-        }
-    }
-
-    matrix[len1][len2]
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::session::types::DeviceFingerprint;
 
     #[test]
     fn test_string_similarity() {
@@ -758,6 +857,35 @@ mod tests {
         assert_eq!(string_similarity("", "something"), 0.0); // Empty vs non-empty
     }
 
+    #[test]
+    fn test_string_similarity_header_values() {
+        // Scores for realistic header values must stay stable across implementations
+        let chrome_120 = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 \
+                          (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+        let chrome_121 = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 \
+                          (KHTML, like Gecko) Chrome/121.0.0.0 Safari/537.36";
+        let firefox = "Mozilla/5.0 (X11; Linux x86_64; rv:121.0) Gecko/20100101 Firefox/121.0";
+
+        assert_eq!(string_similarity(chrome_120, chrome_121), 1.0 - 1.0 / 111.0);
+        assert_eq!(string_similarity(chrome_120, firefox), 1.0 - 77.0 / 111.0);
+
+        let accept = "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8";
+        let accept_webp =
+            "text/html,application/xhtml+xml,application/xml;q=0.9,image/webp,*/*;q=0.8";
+        assert_eq!(string_similarity(accept, accept_webp), 1.0 - 11.0 / 74.0);
+
+        // Multi-byte characters count once towards the length
+        assert_eq!(
+            string_similarity("de-CH,fr;q=0.9", "dé-CH,fr;q=0.9"),
+            1.0 - 1.0 / 14.0
+        );
+
+        // Oversized values are scored on the truncated prefix the distance covers
+        let oversized = "x".repeat(MAX_LEVENSHTEIN_CHARS * 5);
+        let unrelated = "y".repeat(MAX_LEVENSHTEIN_CHARS);
+        assert_eq!(string_similarity(&oversized, &unrelated), 0.0);
+    }
+
     #[test]
     fn test_set_similarity() {
         // Test partially overlapping sets
@@ -824,6 +952,30 @@ mod tests {
         assert_eq!(fp.do_not_track, None);
     }
 
+    #[test]
+    fn test_client_fingerprint_tolerates_partial_payloads() {
+        let client: ClientFingerprint =
+            serde_json::from_str(r#"{"platform": "Linux", "screen_width": 1920}"#)
+                .expect("Partial payloads deserialize");
+        let device = client.into_device_fingerprint(Some("Mozilla/5.0 (X11; Linux x86_64)"));
+
+        assert_eq!(
+            device.user_agent_hash,
+            hash_user_agent("Mozilla/5.0 (X11; Linux x86_64)")
+        );
+        assert_eq!(device.platform.as_deref(), Some("Linux"));
+        // A resolution needs both dimensions
+        assert_eq!(device.screen_resolution, None);
+        assert_eq!(device.timezone, None);
+
+        let empty: ClientFingerprint = serde_json::from_str("{}").expect("Empty payloads work");
+        assert_eq!(empty, ClientFingerprint::default());
+        assert_eq!(
+            empty.into_device_fingerprint(None).user_agent_hash,
+            hash_user_agent("")
+        );
+    }
+
     #[test]
     fn test_fingerprint_comparison() {
         // Create base fingerprint
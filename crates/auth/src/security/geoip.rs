@@ -0,0 +1,83 @@
+//! Resolution of client IP addresses to locations
+//!
+//! Country restrictions of the tenant security policy look up the country of
+//! a login through a [`GeoIpResolver`]. Deployments plug in a resolver backed
+//! by their GeoIP database; [`StaticGeoIpResolver`] maps fixed networks, e.g.
+//! for office ranges or tests.
+
+use sqlx::types::ipnetwork::IpNetwork;
+use std::net::IpAddr;
+
+use super::types::GeoLocation;
+
+/// Looks up where an IP address is located
+pub trait GeoIpResolver: Send + Sync {
+    /// Location of `ip`, if the resolver knows it
+    fn locate(&self, ip: IpAddr) -> Option<GeoLocation>;
+
+    /// ISO 3166-1 alpha-2 code of the country `ip` is located in
+    fn country_code(&self, ip: IpAddr) -> Option<String> {
+        self.locate(ip).map(|location| location.country_code)
+    }
+}
+
+/// Resolver for a fixed table of networks and their countries
+///
+/// The most specific network containing an address wins.
+#[derive(Debug, Clone, Default)]
+pub struct StaticGeoIpResolver {
+    networks: Vec<(IpNetwork, String)>,
+}
+
+impl StaticGeoIpResolver {
+    /// Create a resolver that knows no networks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Locate the addresses of `network` in the country `country_code`
+    pub fn with_network(mut self, network: IpNetwork, country_code: impl Into<String>) -> Self {
+        self.networks.push((network, country_code.into()));
+        self
+    }
+}
+
+impl GeoIpResolver for StaticGeoIpResolver {
+    fn locate(&self, ip: IpAddr) -> Option<GeoLocation> {
+        let ip = ip.to_canonical();
+        self.networks
+            .iter()
+            .filter(|(network, _)| network.contains(ip))
+            .max_by_key(|(network, _)| network.prefix())
+            .map(|(_, country_code)| GeoLocation {
+                country_code: country_code.clone(),
+                city: None,
+                latitude: None,
+                longitude: None,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network(cidr: &str) -> IpNetwork {
+        cidr.parse().expect("valid CIDR")
+    }
+
+    #[test]
+    fn test_most_specific_network_wins() {
+        let resolver = StaticGeoIpResolver::new()
+            .with_network(network("10.0.0.0/8"), "DE")
+            .with_network(network("10.1.0.0/16"), "AT")
+            .with_network(network("2001:db8::/32"), "CH");
+
+        let country = |ip: &str| resolver.country_code(ip.parse().expect("valid IP"));
+        assert_eq!(country("10.2.0.1").as_deref(), Some("DE"));
+        assert_eq!(country("10.1.0.1").as_deref(), Some("AT"));
+        assert_eq!(country("2001:db8::1").as_deref(), Some("CH"));
+        assert_eq!(country("::ffff:10.1.0.1").as_deref(), Some("AT"));
+        assert_eq!(country("192.0.2.1"), None);
+    }
+}
@@ -0,0 +1,437 @@
+//! One-call evaluation of a login attempt
+//!
+//! [`SecurityProtection::evaluate_login`] runs the enabled protections in
+//! order of their cost: the replay check of the login form nonce, the brute
+//! force counters, credential stuffing detection and finally the comparison
+//! of the browser fingerprint against the user's known devices, which needs
+//! the database. The first check that blocks the attempt ends the
+//! evaluation. While Redis is unavailable, each check follows the configured
+//! [`RedisUnavailablePolicy`](super::RedisUnavailablePolicy) of the tenant.
+
+use chrono::Utc;
+use tracing::warn;
+use uuid::Uuid;
+
+use super::SecurityProtection;
+use super::fingerprint::BrowserFingerprint;
+use super::types::{Challenge, LoginAttempt, RiskLevel};
+
+/// Nonce context of login forms
+///
+/// Login pages issue their nonce with
+/// [`NonceStore::generate_nonce`](super::NonceStore::generate_nonce) for this
+/// context.
+pub const LOGIN_NONCE_CONTEXT: &str = "login";
+
+/// Login attempt to evaluate, before the credentials are verified
+#[derive(Debug, Clone)]
+pub struct LoginContext {
+    /// Tenant ID
+    pub tenant_id: String,
+    /// Username or email
+    pub username: String,
+    /// IP address of the attempt
+    pub ip_address: String,
+    /// User agent string
+    pub user_agent: String,
+    /// User the username belongs to, if known
+    pub user_id: Option<Uuid>,
+    /// Browser fingerprint collected by the login page
+    pub fingerprint: Option<BrowserFingerprint>,
+    /// Nonce of the login form
+    pub nonce: Option<String>,
+}
+
+impl LoginContext {
+    pub fn new(
+        tenant_id: impl Into<String>,
+        username: impl Into<String>,
+        ip_address: impl Into<String>,
+        user_agent: impl Into<String>,
+    ) -> Self {
+        Self {
+            tenant_id: tenant_id.into(),
+            username: username.into(),
+            ip_address: ip_address.into(),
+            user_agent: user_agent.into(),
+            user_id: None,
+            fingerprint: None,
+            nonce: None,
+        }
+    }
+
+    /// Compare `fingerprint` against the known devices of `user_id`
+    pub fn with_fingerprint(mut self, user_id: Uuid, fingerprint: BrowserFingerprint) -> Self {
+        self.user_id = Some(user_id);
+        self.fingerprint = Some(fingerprint);
+        self
+    }
+
+    /// Check `nonce` of the login form against replays
+    pub fn with_nonce(mut self, nonce: impl Into<String>) -> Self {
+        self.nonce = Some(nonce.into());
+        self
+    }
+
+    /// The attempt as recorded by credential stuffing detection
+    ///
+    /// The outcome is not known yet, so the attempt counts as unsuccessful.
+    fn login_attempt(&self) -> LoginAttempt {
+        LoginAttempt {
+            tenant_id: self.tenant_id.clone(),
+            username: self.username.clone(),
+            ip_address: self.ip_address.clone(),
+            user_agent: self.user_agent.clone(),
+            timestamp: Utc::now(),
+            fingerprint: None,
+            geolocation: None,
+            successful: false,
+        }
+    }
+}
+
+/// What to do with a login attempt
+#[derive(Debug, Clone)]
+pub enum LoginAction {
+    /// Verify the credentials
+    Allow,
+    /// Verify the credentials once the client passed the challenge
+    Challenge(Challenge),
+    /// Reject the attempt without verifying the credentials
+    Block,
+}
+
+impl LoginAction {
+    fn from_challenge(challenge: Challenge) -> Self {
+        match challenge {
+            Challenge::None => Self::Allow,
+            Challenge::IpBlock(_) | Challenge::Reject => Self::Block,
+            challenge => Self::Challenge(challenge),
+        }
+    }
+
+    /// Strictness of the action, used to keep the strictest one
+    fn severity(&self) -> u8 {
+        match self {
+            Self::Allow => 0,
+            Self::Challenge(Challenge::Delay(_)) => 1,
+            Self::Challenge(Challenge::Captcha(_)) => 2,
+            Self::Challenge(_) => 3,
+            Self::Block => 4,
+        }
+    }
+}
+
+/// Outcome of [`SecurityProtection::evaluate_login`]
+#[derive(Debug, Clone)]
+pub struct LoginDecision {
+    /// Strictest action required by any check
+    pub action: LoginAction,
+    /// Highest risk reported by any check
+    pub risk_level: RiskLevel,
+    /// Why the attempt was challenged or blocked
+    pub reasons: Vec<String>,
+}
+
+impl LoginDecision {
+    fn new() -> Self {
+        Self {
+            action: LoginAction::Allow,
+            risk_level: RiskLevel::Low,
+            reasons: Vec::new(),
+        }
+    }
+
+    /// Whether the credentials may be verified without a challenge
+    pub fn is_allowed(&self) -> bool {
+        matches!(self.action, LoginAction::Allow)
+    }
+
+    /// Whether the attempt has to be rejected
+    pub fn is_blocked(&self) -> bool {
+        matches!(self.action, LoginAction::Block)
+    }
+
+    /// Add the result of one check
+    fn add(&mut self, risk_level: RiskLevel, challenge: Challenge) {
+        self.risk_level = self.risk_level.max(risk_level);
+        let action = LoginAction::from_challenge(challenge);
+        if action.severity() > self.action.severity() {
+            self.action = action;
+        }
+    }
+
+    fn reason(&mut self, reason: impl Into<String>) {
+        self.reasons.push(reason.into());
+    }
+
+    /// Block the attempt for `reason`
+    fn block(mut self, reason: impl Into<String>) -> Self {
+        self.action = LoginAction::Block;
+        self.risk_level = RiskLevel::Critical;
+        self.reason(reason);
+        self
+    }
+}
+
+impl SecurityProtection {
+    /// Evaluate a login attempt with all enabled protections
+    ///
+    /// Call this before verifying the credentials and record the outcome
+    /// afterwards with
+    /// [`BruteForceProtection::record_login_attempt`](super::BruteForceProtection::record_login_attempt).
+    pub async fn evaluate_login(&self, ctx: LoginContext) -> LoginDecision {
+        let mut decision = LoginDecision::new();
+
+        let nonce = ctx
+            .nonce
+            .as_deref()
+            .filter(|_| self.nonce_store.is_enabled());
+        if let Some(nonce) = nonce {
+            match self
+                .nonce_store
+                .validate_nonce(&ctx.tenant_id, LOGIN_NONCE_CONTEXT, nonce, None)
+                .await
+            {
+                Ok(true) => {},
+                Ok(false) => {
+                    return decision.block("Login form nonce is invalid or was already used");
+                },
+                Err(e) => {
+                    warn!("Failed to validate login nonce: {}", e);
+                    return decision.block("Replay protection is unavailable");
+                },
+            }
+        }
+
+        if self.config.brute_force.enabled {
+            match self
+                .brute_force
+                .get_protection_status(&ctx.username, &ctx.ip_address, &ctx.tenant_id)
+                .await
+            {
+                Ok(status) => {
+                    if status.username.is_locked() {
+                        decision.reason("Too many failed attempts for the username");
+                    }
+                    if status.ip.is_locked() {
+                        decision.reason("Too many failed attempts from the IP address");
+                    }
+                    if status.risk_level > RiskLevel::Low
+                        && !status.username.is_locked()
+                        && !status.ip.is_locked()
+                    {
+                        decision.reason("Brute force protection is degraded");
+                    }
+                    decision.add(status.risk_level, status.challenge());
+                },
+                Err(e) => {
+                    warn!("Failed to check brute force protection: {}", e);
+                    return decision.block("Brute force protection is unavailable");
+                },
+            }
+        }
+
+        if self.config.credential_stuffing.enabled {
+            let attempt = ctx.login_attempt();
+            match self.cred_stuffing.record_and_assess(&attempt).await {
+                Some(risk_level) => {
+                    if risk_level > RiskLevel::Low {
+                        decision.reason(format!(
+                            "Attempt matches credential stuffing patterns ({risk_level} risk)"
+                        ));
+                    }
+                    let challenge = self.cred_stuffing.get_challenge(&attempt, risk_level).await;
+                    decision.add(risk_level, challenge);
+                },
+                None => return decision.block("Credential stuffing detection is unavailable"),
+            }
+        }
+        if decision.is_blocked() {
+            return decision;
+        }
+
+        if self.config.fingerprinting.enabled {
+            self.evaluate_fingerprint(&ctx, &mut decision).await;
+        }
+
+        decision
+    }
+
+    /// Compare the fingerprint of the attempt against the user's known devices
+    ///
+    /// Fingerprints are a weak signal, so a failed comparison is skipped
+    /// rather than blocking the attempt.
+    async fn evaluate_fingerprint(&self, ctx: &LoginContext, decision: &mut LoginDecision) {
+        let (Some(service), Some(user_id), Some(fingerprint)) = (
+            self.fingerprint_service.as_ref(),
+            ctx.user_id,
+            ctx.fingerprint.as_ref(),
+        ) else {
+            return;
+        };
+        let Ok(tenant_id) = Uuid::parse_str(&ctx.tenant_id) else {
+            return;
+        };
+
+        match service
+            .verify_fingerprint(tenant_id, user_id, fingerprint)
+            .await
+        {
+            Ok((risk_level, _)) => {
+                let challenge = match risk_level {
+                    RiskLevel::Low | RiskLevel::Medium => Challenge::None,
+                    RiskLevel::High | RiskLevel::Critical => Challenge::MfaRequired,
+                };
+                if risk_level > RiskLevel::Low {
+                    decision.reason("Browser fingerprint does not match a known device");
+                }
+                decision.add(risk_level, challenge);
+            },
+            Err(e) => {
+                warn!("Failed to verify browser fingerprint: {}", e);
+                decision.reason("Browser fingerprint could not be verified");
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::config::{
+        BruteForceConfig, RedisAvailabilityConfig, RedisUnavailablePolicy, SecurityConfig,
+    };
+    use std::sync::Arc;
+
+    const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:121.0) Gecko/20100101 \
+        Firefox/121.0";
+
+    fn redis_client() -> Arc<redis::Client> {
+        // Nothing listens on this port
+        Arc::new(redis::Client::open("redis://127.0.0.1:1").expect("valid Redis URL"))
+    }
+
+    fn in_memory(config: SecurityConfig) -> SecurityProtection {
+        SecurityProtection::in_memory(redis_client(), None, config)
+    }
+
+    fn unavailable(policy: RedisUnavailablePolicy) -> SecurityProtection {
+        SecurityProtection::new(
+            redis_client(),
+            None,
+            SecurityConfig {
+                redis_availability: RedisAvailabilityConfig {
+                    policy,
+                    ..RedisAvailabilityConfig::default()
+                },
+                ..SecurityConfig::default()
+            },
+        )
+    }
+
+    fn login(username: &str) -> LoginContext {
+        LoginContext::new("tenant", username, "192.0.2.7", USER_AGENT)
+    }
+
+    /// Protection whose IP counter locks after three failures
+    fn strict_ip_config() -> SecurityConfig {
+        SecurityConfig {
+            brute_force: BruteForceConfig {
+                ip_max_attempts: 3,
+                ..BruteForceConfig::default()
+            },
+            ..SecurityConfig::default()
+        }
+    }
+
+    async fn fail_from_ip(protection: &SecurityProtection, attempts: usize) {
+        for i in 0..attempts {
+            protection
+                .brute_force
+                .record_login_attempt("tenant", &format!("user{i}"), "192.0.2.7", false)
+                .await
+                .expect("Failed to record attempt");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_clean_login_is_allowed() {
+        let protection = in_memory(SecurityConfig::default());
+
+        let decision = protection.evaluate_login(login("alice")).await;
+
+        assert!(decision.is_allowed(), "{decision:?}");
+        assert_eq!(decision.risk_level, RiskLevel::Low);
+        assert!(decision.reasons.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_high_velocity_login_is_challenged() {
+        let protection = in_memory(strict_ip_config());
+        fail_from_ip(&protection, 3).await;
+
+        let decision = protection.evaluate_login(login("carol")).await;
+
+        assert!(
+            matches!(decision.action, LoginAction::Challenge(Challenge::Delay(_))),
+            "{decision:?}"
+        );
+        assert_eq!(decision.risk_level, RiskLevel::Medium);
+        assert_eq!(
+            decision.reasons,
+            ["Too many failed attempts from the IP address"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_disabled_checks_are_skipped() {
+        let mut config = strict_ip_config();
+        config.brute_force.enabled = false;
+        let protection = in_memory(config);
+        fail_from_ip(&protection, 3).await;
+
+        let decision = protection.evaluate_login(login("carol")).await;
+
+        assert!(decision.is_allowed(), "{decision:?}");
+    }
+
+    #[tokio::test]
+    async fn test_replayed_nonce_is_blocked() {
+        let protection = in_memory(SecurityConfig::default());
+        let nonce = protection
+            .nonce_store
+            .generate_nonce("tenant", LOGIN_NONCE_CONTEXT)
+            .await
+            .expect("Failed to generate nonce");
+
+        let decision = protection
+            .evaluate_login(login("alice").with_nonce(nonce.clone()))
+            .await;
+        assert!(decision.is_allowed(), "{decision:?}");
+
+        let decision = protection
+            .evaluate_login(login("alice").with_nonce(nonce))
+            .await;
+        assert!(decision.is_blocked(), "{decision:?}");
+    }
+
+    #[tokio::test]
+    async fn test_redis_unavailable_fail_closed_blocks() {
+        let protection = unavailable(RedisUnavailablePolicy::FailClosed);
+
+        let decision = protection.evaluate_login(login("alice")).await;
+
+        assert!(decision.is_blocked(), "{decision:?}");
+        assert_eq!(decision.reasons, ["Brute force protection is unavailable"]);
+    }
+
+    #[tokio::test]
+    async fn test_redis_unavailable_fail_open_allows() {
+        let protection = unavailable(RedisUnavailablePolicy::FailOpen);
+
+        let decision = protection.evaluate_login(login("alice")).await;
+
+        assert!(decision.is_allowed(), "{decision:?}");
+    }
+}
@@ -0,0 +1,409 @@
+//! In-memory storage for the security components
+//!
+//! Selected with `backend = "memory"` by deployments that do not run Redis.
+//! The stores keep the same data as the Redis keys they replace, so
+//! [`RateStore`](super::RateStore), [`NonceStore`](super::NonceStore) and
+//! [`BruteForceProtection`](super::BruteForceProtection) behave the same on
+//! either backend. Every instance keeps its own state, which makes the
+//! limits only correct for a single instance.
+//!
+//! Entries expire after their TTL, checked on access and by a sweep every
+//! [`SWEEP_INTERVAL`] writes. Each store holds at most `max_keys` keys and
+//! evicts the least recently used key beyond that.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Writes between two sweeps of the expired entries
+pub const SWEEP_INTERVAL: u64 = 1024;
+
+/// Expiring entries, bounded by evicting the least recently used key
+struct TtlMap<V> {
+    entries: HashMap<String, Slot<V>>,
+    /// Keys by the tick of their last use, oldest first
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+    writes: u64,
+    max_keys: usize,
+}
+
+struct Slot<V> {
+    value: V,
+    expires_at: Instant,
+    used: u64,
+}
+
+impl<V> TtlMap<V> {
+    fn new(max_keys: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+            writes: 0,
+            max_keys: max_keys.max(1),
+        }
+    }
+
+    /// The live entry under `key`, marked as used
+    fn get_mut(&mut self, key: &str, now: Instant) -> Option<&mut V> {
+        if self
+            .entries
+            .get(key)
+            .is_some_and(|slot| slot.expires_at <= now)
+        {
+            self.remove(key);
+            return None;
+        }
+
+        self.tick += 1;
+        let tick = self.tick;
+        let slot = self.entries.get_mut(key)?;
+        self.recency.remove(&slot.used);
+        self.recency.insert(tick, key.to_string());
+        slot.used = tick;
+        Some(&mut slot.value)
+    }
+
+    /// Store `value` under `key` until `expires_at`, replacing a stored value
+    fn insert(&mut self, key: &str, value: V, expires_at: Instant, now: Instant) {
+        self.remove(key);
+        self.note_write(now);
+        while self.entries.len() >= self.max_keys {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+
+        self.tick += 1;
+        self.recency.insert(self.tick, key.to_string());
+        self.entries.insert(
+            key.to_string(),
+            Slot {
+                value,
+                expires_at,
+                used: self.tick,
+            },
+        );
+    }
+
+    /// The live entry under `key`, or a new one from `create` living until `expires_at`
+    fn get_or_insert_with(
+        &mut self,
+        key: &str,
+        now: Instant,
+        expires_at: Instant,
+        create: impl FnOnce() -> V,
+    ) -> &mut V {
+        if self.get_mut(key, now).is_none() {
+            self.insert(key, create(), expires_at, now);
+        } else {
+            self.note_write(now);
+        }
+        &mut self
+            .entries
+            .get_mut(key)
+            .expect("entry was just inserted")
+            .value
+    }
+
+    /// Move the expiry of the entry under `key`
+    fn expire_at(&mut self, key: &str, expires_at: Instant) {
+        if let Some(slot) = self.entries.get_mut(key) {
+            slot.expires_at = expires_at;
+        }
+    }
+
+    /// Remove the entry under `key`, returning its value if it had not expired
+    fn take(&mut self, key: &str, now: Instant) -> Option<V> {
+        self.remove(key)
+            .filter(|slot| slot.expires_at > now)
+            .map(|slot| slot.value)
+    }
+
+    fn remove(&mut self, key: &str) -> Option<Slot<V>> {
+        let slot = self.entries.remove(key)?;
+        self.recency.remove(&slot.used);
+        Some(slot)
+    }
+
+    fn note_write(&mut self, now: Instant) {
+        self.writes += 1;
+        if self.writes % SWEEP_INTERVAL == 0 {
+            self.sweep(now);
+        }
+    }
+
+    /// Drop all expired entries
+    fn sweep(&mut self, now: Instant) {
+        let recency = &mut self.recency;
+        self.entries.retain(|_, slot| {
+            let live = slot.expires_at > now;
+            if !live {
+                recency.remove(&slot.used);
+            }
+            live
+        });
+    }
+
+    /// Keys of the live entries
+    fn keys(&self, now: Instant) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|(_, slot)| slot.expires_at > now)
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+/// Request timestamps and backoff multipliers of the rate limiter
+///
+/// Replaces the sorted set and multiplier keys of [`RateStore`](super::RateStore).
+pub struct InMemoryRateStore {
+    requests: Mutex<TtlMap<VecDeque<i64>>>,
+    multipliers: Mutex<TtlMap<f32>>,
+}
+
+impl InMemoryRateStore {
+    /// Create a store holding at most `max_keys` request windows
+    pub fn new(max_keys: usize) -> Self {
+        Self {
+            requests: Mutex::new(TtlMap::new(max_keys)),
+            multipliers: Mutex::new(TtlMap::new(max_keys)),
+        }
+    }
+
+    /// Add a request at `now` and count the requests after `window_start`
+    ///
+    /// The window is kept for `ttl` after the latest request.
+    pub fn record_request(&self, key: &str, now: i64, window_start: i64, ttl: Duration) -> usize {
+        let instant = Instant::now();
+        let mut requests = lock(&self.requests);
+        let window = requests.get_or_insert_with(key, instant, instant + ttl, VecDeque::new);
+        window.push_back(now);
+        while window.front().is_some_and(|&ts| ts <= window_start) {
+            window.pop_front();
+        }
+        let count = window.len();
+        requests.expire_at(key, instant + ttl);
+        count
+    }
+
+    /// Backoff multiplier stored under `key`
+    pub fn multiplier(&self, key: &str) -> Option<f32> {
+        lock(&self.multipliers)
+            .get_mut(key, Instant::now())
+            .copied()
+    }
+
+    /// Store the backoff multiplier under `key` for `ttl`
+    pub fn set_multiplier(&self, key: &str, multiplier: f32, ttl: Duration) {
+        let now = Instant::now();
+        lock(&self.multipliers).insert(key, multiplier, now + ttl, now);
+    }
+
+    /// Remove the backoff multiplier under `key`
+    pub fn reset_multiplier(&self, key: &str) {
+        lock(&self.multipliers).remove(key);
+    }
+
+    /// Number of request windows held
+    pub fn len(&self) -> usize {
+        lock(&self.requests).len()
+    }
+
+    /// Whether no request window is held
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Nonces and one-time values of the replay protection
+///
+/// Replaces the nonce keys of [`NonceStore`](super::NonceStore).
+pub struct InMemoryNonceStore {
+    nonces: Mutex<TtlMap<i64>>,
+}
+
+impl InMemoryNonceStore {
+    /// Create a store holding at most `max_keys` nonces
+    pub fn new(max_keys: usize) -> Self {
+        Self {
+            nonces: Mutex::new(TtlMap::new(max_keys)),
+        }
+    }
+
+    /// Store `value` under `key` for `ttl`
+    pub fn set(&self, key: &str, value: i64, ttl: Duration) {
+        let now = Instant::now();
+        lock(&self.nonces).insert(key, value, now + ttl, now);
+    }
+
+    /// Store `value` under `key` for `ttl` unless a value is already stored
+    ///
+    /// Returns whether the value was stored.
+    pub fn set_nx(&self, key: &str, value: i64, ttl: Duration) -> bool {
+        let now = Instant::now();
+        let mut nonces = lock(&self.nonces);
+        if nonces.get_mut(key, now).is_some() {
+            return false;
+        }
+        nonces.insert(key, value, now + ttl, now);
+        true
+    }
+
+    /// Remove and return the value under `key`, if it has not expired
+    pub fn take(&self, key: &str) -> Option<i64> {
+        lock(&self.nonces).take(key, Instant::now())
+    }
+
+    /// Number of nonces held
+    pub fn len(&self) -> usize {
+        lock(&self.nonces).len()
+    }
+
+    /// Whether no nonce is held
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Failed attempt timestamps of the brute force protection
+///
+/// Replaces the attempt lists of [`BruteForceProtection`](super::BruteForceProtection).
+pub struct InMemoryAttemptCounter {
+    attempts: Mutex<TtlMap<Vec<i64>>>,
+}
+
+impl InMemoryAttemptCounter {
+    /// Create a counter holding at most `max_keys` attempt lists
+    pub fn new(max_keys: usize) -> Self {
+        Self {
+            attempts: Mutex::new(TtlMap::new(max_keys)),
+        }
+    }
+
+    /// Append an attempt at `timestamp`; a new list is kept for `ttl`
+    pub fn push(&self, key: &str, timestamp: i64, ttl: Duration) {
+        let now = Instant::now();
+        lock(&self.attempts)
+            .get_or_insert_with(key, now, now + ttl, Vec::new)
+            .push(timestamp);
+    }
+
+    /// Attempt timestamps under `key`, oldest first
+    pub fn attempts(&self, key: &str) -> Vec<i64> {
+        lock(&self.attempts)
+            .get_mut(key, Instant::now())
+            .map(|attempts| attempts.clone())
+            .unwrap_or_default()
+    }
+
+    /// Remove up to `count` of the most recent attempts under `key`
+    pub fn drop_latest(&self, key: &str, count: usize) {
+        if let Some(attempts) = lock(&self.attempts).get_mut(key, Instant::now()) {
+            attempts.truncate(attempts.len().saturating_sub(count));
+        }
+    }
+
+    /// Remove all attempts under `key`
+    pub fn clear(&self, key: &str) {
+        lock(&self.attempts).remove(key);
+    }
+
+    /// Keys of the attempt lists held
+    pub fn keys(&self) -> Vec<String> {
+        lock(&self.attempts).keys(Instant::now())
+    }
+
+    /// Number of attempt lists held
+    pub fn len(&self) -> usize {
+        lock(&self.attempts).len()
+    }
+
+    /// Whether no attempt list is held
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn lock<V>(map: &Mutex<TtlMap<V>>) -> MutexGuard<'_, TtlMap<V>> {
+    map.lock().expect("in-memory security store lock poisoned")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn test_least_recently_used_key_is_evicted() {
+        let store = InMemoryNonceStore::new(2);
+        store.set("a", 1, TTL);
+        store.set("b", 2, TTL);
+        // Using "a" makes "b" the least recently used key
+        assert!(!store.set_nx("a", 3, TTL));
+        store.set("c", 3, TTL);
+
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.take("b"), None);
+        assert_eq!(store.take("a"), Some(1));
+        assert_eq!(store.take("c"), Some(3));
+    }
+
+    #[test]
+    fn test_expired_entries_are_dropped() {
+        let store = InMemoryNonceStore::new(10);
+        store.set("expired", 1, Duration::ZERO);
+        assert_eq!(store.take("expired"), None);
+        assert!(store.set_nx("expired", 2, TTL));
+
+        let counter = InMemoryAttemptCounter::new(10);
+        counter.push("user", 100, Duration::ZERO);
+        assert!(counter.attempts("user").is_empty());
+        assert!(counter.is_empty());
+    }
+
+    #[test]
+    fn test_sweep_drops_expired_entries_without_access() {
+        let store = InMemoryNonceStore::new(usize::MAX);
+        for i in 0..SWEEP_INTERVAL - 1 {
+            store.set(&format!("expired:{i}"), 0, Duration::ZERO);
+        }
+        assert_eq!(store.len(), (SWEEP_INTERVAL - 1) as usize);
+
+        store.set("live", 1, TTL);
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn test_rate_window_drops_old_requests() {
+        let store = InMemoryRateStore::new(10);
+        assert_eq!(store.record_request("key", 100, 41, TTL), 1);
+        assert_eq!(store.record_request("key", 120, 61, TTL), 2);
+        assert_eq!(store.record_request("key", 170, 111, TTL), 2);
+
+        store.set_multiplier("key:multiplier", 2.0, TTL);
+        assert_eq!(store.multiplier("key:multiplier"), Some(2.0));
+        store.reset_multiplier("key:multiplier");
+        assert_eq!(store.multiplier("key:multiplier"), None);
+    }
+
+    #[test]
+    fn test_drop_latest_attempts() {
+        let counter = InMemoryAttemptCounter::new(10);
+        for ts in [1, 2, 3] {
+            counter.push("ip", ts, TTL);
+        }
+        counter.drop_latest("ip", 2);
+        assert_eq!(counter.attempts("ip"), vec![1]);
+        counter.drop_latest("ip", 5);
+        assert!(counter.attempts("ip").is_empty());
+    }
+}
@@ -1,35 +1,68 @@
 pub mod bruteforce;
+pub mod client_binding;
 pub mod config;
 pub mod credstuffing;
 pub mod fingerprint;
+pub mod geoip;
+pub mod login;
+#[cfg(any(test, feature = "in-memory"))]
+pub mod memory;
 pub mod ratelimit;
+pub mod redis_circuit;
 pub mod replay;
+pub mod similarity;
+pub mod snapshot;
+pub mod trusted_device;
 pub mod types;
+pub mod webhook;
 
 // Re-exports
-pub use bruteforce::BruteForceProtection;
+pub use bruteforce::{BruteForceProtection, CounterSnapshot, CounterStatus, ProtectionStatus};
+pub use client_binding::ClientCharacteristics;
 pub use config::{
     BruteForceConfig, CredentialStuffingConfig, FingerprintingConfig as FingerprintConfig,
-    RateLimitingConfig as RateLimitConfig, ReplayProtectionConfig, SecurityConfig,
+    MemoryBackendConfig, RateLimitAllowList, RateLimitingConfig as RateLimitConfig,
+    RedisAvailabilityConfig, RedisUnavailablePolicy, ReplayProtectionConfig, ReplayProtectionMode,
+    ReplayRouteClass, SecurityBackend, SecurityConfig, StateSnapshotConfig,
 };
 pub use credstuffing::{ChallengeProvider, CredentialStuffingProtection, PatternDetector};
-pub use ratelimit::{RateLimitInfo, RateLimitLayer, RateLimitMiddleware, RateStore};
+pub use geoip::{GeoIpResolver, StaticGeoIpResolver};
+pub use login::{LOGIN_NONCE_CONTEXT, LoginAction, LoginContext, LoginDecision};
+#[cfg(any(test, feature = "in-memory"))]
+pub use memory::{InMemoryAttemptCounter, InMemoryNonceStore, InMemoryRateStore};
+pub use ratelimit::{
+    RateLimitExemption, RateLimitInfo, RateLimitLayer, RateLimitMiddleware, RateStore,
+    ServiceAccountId,
+};
+pub use redis_circuit::{LocalStore, RedisCircuit, RedisConnection};
 pub use types::{BruteForceError, RateLimitError};
 pub use types::{
     Challenge, GeoLocation, LoginAttempt, RiskLevel, SecurityError, create_tenant_redis_key,
 };
 // Fingerprinting module exports
 pub use fingerprint::{
-    BrowserFingerprint, FingerprintComparison, FingerprintRepository, FingerprintService,
-    PostgresFingerprintRepository, StoredFingerprint,
+    BrowserFingerprint, ClientFingerprint, FingerprintComparison, FingerprintRepository,
+    FingerprintService, PostgresFingerprintRepository, StoredFingerprint, hash_user_agent,
+};
+pub use replay::{
+    NonceStore, ReplayProtectionLayer, ReplayProtectionMiddleware, SignedRequestRejection,
+    SigningSecretStore, StaticSigningSecrets,
 };
-pub use replay::{NonceStore, ReplayProtectionLayer, ReplayProtectionMiddleware};
+pub use snapshot::{PostgresSnapshotStore, SNAPSHOT_VERSION, SecurityStateSnapshot, SnapshotError};
+pub use trusted_device::{TrustedDevice, TrustedDeviceError, TrustedDeviceService};
+pub use webhook::{WebhookError, WebhookReceiver, verify_webhook};
 
 use redis::Client;
 use std::sync::Arc;
 use tracing::info;
 
 /// Creates a new SecurityProtection instance with all security features
+///
+/// With `backend = "memory"` the rate limits, nonces and brute force counters
+/// are kept in process memory and `redis_client` is never contacted. This
+/// requires the `in-memory` feature and only works correctly for a single
+/// instance: every instance enforces the limits on its own and accepts only
+/// the nonces it issued.
 pub fn create_security_protection(
     redis_client: Arc<Client>,
     db_pool: sqlx::PgPool,
@@ -37,21 +70,58 @@ pub fn create_security_protection(
 ) -> anyhow::Result<Arc<SecurityProtection>> {
     // Create the fingerprint repository if configured
     let fingerprint_repo = if config.fingerprinting.enabled {
-        Some(Arc::new(fingerprint::PostgresFingerprintRepository::new(
-            db_pool.clone(),
-        )) as Arc<dyn fingerprint::FingerprintRepository>)
+        Some(Arc::new(
+            fingerprint::PostgresFingerprintRepository::new(db_pool.clone())
+                .with_cleanup_batch(config.fingerprinting.cleanup_batch()),
+        ) as Arc<dyn fingerprint::FingerprintRepository>)
     } else {
         None
     };
 
     // Create the security protection service
-    let protection = SecurityProtection::new(redis_client, fingerprint_repo, config);
+    let protection = match config.backend {
+        SecurityBackend::Redis => SecurityProtection::new(redis_client, fingerprint_repo, config),
+        SecurityBackend::Memory => in_memory_protection(redis_client, fingerprint_repo, config)?,
+    };
 
     info!("Security protection services initialized successfully");
 
     Ok(Arc::new(protection))
 }
 
+#[cfg(any(test, feature = "in-memory"))]
+fn in_memory_protection(
+    redis_client: Arc<Client>,
+    fingerprint_repo: Option<Arc<dyn fingerprint::FingerprintRepository>>,
+    config: SecurityConfig,
+) -> anyhow::Result<SecurityProtection> {
+    if config.memory.instances > 1 {
+        tracing::warn!(
+            "The memory security backend is configured for {} instances, but keeps its state per \
+             instance: rate limits and brute force counters are multiplied and nonces are only \
+             accepted by the instance that issued them",
+            config.memory.instances
+        );
+    } else {
+        info!("Using the memory security backend, which supports a single instance only");
+    }
+
+    Ok(SecurityProtection::in_memory(
+        redis_client,
+        fingerprint_repo,
+        config,
+    ))
+}
+
+#[cfg(not(any(test, feature = "in-memory")))]
+fn in_memory_protection(
+    _redis_client: Arc<Client>,
+    _fingerprint_repo: Option<Arc<dyn fingerprint::FingerprintRepository>>,
+    _config: SecurityConfig,
+) -> anyhow::Result<SecurityProtection> {
+    anyhow::bail!("The memory security backend requires the `in-memory` feature")
+}
+
 /// Main security protection service that combines all security features
 pub struct SecurityProtection {
     /// Brute force protection service
@@ -77,10 +147,89 @@ impl SecurityProtection {
         fingerprint_repo: Option<Arc<dyn fingerprint::FingerprintRepository>>,
         config: SecurityConfig,
     ) -> Self {
+        // One circuit, so every component stops contacting Redis once it is down
+        let circuit = Arc::new(RedisCircuit::new(
+            redis_client.clone(),
+            config.redis_availability.clone(),
+        ));
+
         let brute_force =
-            BruteForceProtection::new(redis_client.clone(), config.brute_force.clone());
+            BruteForceProtection::new(redis_client.clone(), config.brute_force.clone())
+                .with_redis_circuit(circuit.clone());
+
+        let pattern_detector = Arc::new(
+            PatternDetector::new(redis_client.clone()).with_redis_circuit(circuit.clone()),
+        );
+
+        let rate_store =
+            Arc::new(RateStore::new(redis_client.clone()).with_redis_circuit(circuit.clone()));
+
+        let nonce_store = Arc::new(
+            NonceStore::new(redis_client.clone(), config.replay_protection.clone())
+                .with_redis_circuit(circuit),
+        );
+
+        Self::with_stores(
+            redis_client,
+            fingerprint_repo,
+            config,
+            brute_force,
+            pattern_detector,
+            rate_store,
+            nonce_store,
+        )
+    }
 
+    /// Create a security protection service keeping its state in process memory
+    ///
+    /// `redis_client` is never contacted. Credential stuffing detection
+    /// needs Redis and is disabled.
+    #[cfg(any(test, feature = "in-memory"))]
+    pub fn in_memory(
+        redis_client: Arc<Client>,
+        fingerprint_repo: Option<Arc<dyn fingerprint::FingerprintRepository>>,
+        mut config: SecurityConfig,
+    ) -> Self {
+        if config.credential_stuffing.enabled {
+            tracing::warn!(
+                "Credential stuffing detection needs Redis and is disabled in memory mode"
+            );
+            config.credential_stuffing.enabled = false;
+        }
+
+        let max_keys = config.memory.max_keys;
+        let brute_force = BruteForceProtection::in_memory(
+            InMemoryAttemptCounter::new(max_keys),
+            config.brute_force.clone(),
+            crate::utils::clock::SystemClock,
+        );
         let pattern_detector = Arc::new(PatternDetector::new(redis_client.clone()));
+        let rate_store = Arc::new(RateStore::in_memory(InMemoryRateStore::new(max_keys)));
+        let nonce_store = Arc::new(NonceStore::in_memory(
+            InMemoryNonceStore::new(max_keys),
+            config.replay_protection.clone(),
+        ));
+
+        Self::with_stores(
+            redis_client,
+            fingerprint_repo,
+            config,
+            brute_force,
+            pattern_detector,
+            rate_store,
+            nonce_store,
+        )
+    }
+
+    fn with_stores(
+        redis_client: Arc<Client>,
+        fingerprint_repo: Option<Arc<dyn fingerprint::FingerprintRepository>>,
+        config: SecurityConfig,
+        brute_force: BruteForceProtection,
+        pattern_detector: Arc<PatternDetector>,
+        rate_store: Arc<RateStore>,
+        nonce_store: Arc<NonceStore>,
+    ) -> Self {
         let challenge_provider = Arc::new(ChallengeProvider::new());
 
         let cred_stuffing = CredentialStuffingProtection::new(
@@ -89,13 +238,6 @@ impl SecurityProtection {
             config.credential_stuffing.clone(),
         );
 
-        let rate_store = Arc::new(RateStore::new(redis_client.clone()));
-
-        let nonce_store = Arc::new(NonceStore::new(
-            redis_client.clone(),
-            config.replay_protection.clone(),
-        ));
-
         // Initialize fingerprint service if repo is provided
         let fingerprint_service = fingerprint_repo.map(|repo| {
             Arc::new(fingerprint::FingerprintService::new(
@@ -117,6 +259,12 @@ impl SecurityProtection {
         }
     }
 
+    /// Raise an alert with `exporter` for every suspicious login attempt
+    pub fn with_event_exporter(mut self, exporter: Arc<crate::services::EventExporter>) -> Self {
+        self.cred_stuffing = self.cred_stuffing.with_event_exporter(exporter);
+        self
+    }
+
     /// Get a rate limit middleware
     pub fn rate_limit_middleware(&self) -> RateLimitLayer {
         RateLimitLayer::new(self.rate_store.clone(), self.config.rate_limiting.clone())
@@ -4,22 +4,82 @@ use axum::response::{IntoResponse, Response};
 use chrono::Utc;
 use futures::future::BoxFuture;
 use redis::{self, AsyncCommands};
+use sqlx::types::ipnetwork::IpNetwork;
+use std::collections::HashSet;
+use std::net::IpAddr;
 use std::task::{Context, Poll};
+#[cfg(any(test, feature = "in-memory"))]
+use std::time::Duration;
 use tower::{Layer, Service};
-use tracing::{debug, error};
-
-use super::config::{RateLimit, RateLimitingConfig};
+use tracing::{debug, error, warn};
+use uuid::Uuid;
+
+use super::config::{
+    RateLimit, RateLimitAllowList, RateLimitingConfig, RedisAvailabilityConfig,
+    RedisUnavailablePolicy,
+};
+#[cfg(any(test, feature = "in-memory"))]
+use super::memory::InMemoryRateStore;
+use super::redis_circuit::{LocalStore, RedisCircuit};
 use super::types::{RateLimitError, create_tenant_redis_key};
 
 /// Rate limiter implementation with Redis backend
+///
+/// While Redis is unavailable, the [`RedisUnavailablePolicy`] of the tenant
+/// decides: `FailOpen` lets every request pass, `FailClosed` rejects every
+/// request and `Degrade` counts requests in memory, so each instance enforces
+/// the limits on its own and without backoff.
+///
+/// With the `in-memory` feature, [`RateStore::in_memory`] keeps the requests
+/// in process memory instead, which only enforces the limits correctly for a
+/// single instance.
 pub struct RateStore {
-    redis_client: Arc<redis::Client>,
+    backend: RateBackend,
+}
+
+enum RateBackend {
+    Redis {
+        circuit: Arc<RedisCircuit>,
+        local: LocalStore,
+    },
+    #[cfg(any(test, feature = "in-memory"))]
+    Memory(InMemoryRateStore),
 }
 
 impl RateStore {
     /// Create a new rate store with Redis client
     pub fn new(redis_client: Arc<redis::Client>) -> Self {
-        Self { redis_client }
+        Self {
+            backend: RateBackend::Redis {
+                circuit: Arc::new(RedisCircuit::new(
+                    redis_client,
+                    RedisAvailabilityConfig::default(),
+                )),
+                local: LocalStore::new(),
+            },
+        }
+    }
+
+    /// Create a rate store keeping the requests in process memory
+    #[cfg(any(test, feature = "in-memory"))]
+    pub fn in_memory(store: InMemoryRateStore) -> Self {
+        Self {
+            backend: RateBackend::Memory(store),
+        }
+    }
+
+    /// Use a circuit shared with the other security components
+    ///
+    /// Has no effect on an in-memory store.
+    pub fn with_redis_circuit(mut self, circuit: Arc<RedisCircuit>) -> Self {
+        match &mut self.backend {
+            RateBackend::Redis {
+                circuit: current, ..
+            } => *current = circuit,
+            #[cfg(any(test, feature = "in-memory"))]
+            RateBackend::Memory(_) => {},
+        }
+        self
     }
 
     /// Check if the request should be rate limited
@@ -29,89 +89,44 @@ impl RateStore {
         key: &str,
         rate_limit: &RateLimit,
     ) -> Result<RateLimitInfo, RateLimitError> {
-        let redis_key = create_tenant_redis_key(
-            tenant_id,
-            &format!("ratelimit:{}s", rate_limit.window_seconds),
-            key,
-        );
-
-        let now = Utc::now().timestamp() as usize;
-        let window_start = now - rate_limit.window_seconds as usize;
-
-        let mut conn = self
-            .redis_client
-            .get_async_connection()
-            .await
-            .map_err(RateLimitError::Redis)?;
-
-        // Add the current timestamp to the list of requests
-        let _: () = conn
-            .zadd(&redis_key, now.to_string(), now)
-            .await
-            .map_err(RateLimitError::Redis)?;
-
-        // Try to clean up old entries - handle both new and old Redis versions
-        // New Redis versions use zrem_range_by_score, older use zremrangebyscore
-        // Use the raw Redis command directly
-        let _: Result<(), redis::RedisError> = redis::cmd("ZREMRANGEBYSCORE")
-            .arg(&redis_key)
-            .arg(0)
-            .arg(window_start)
-            .query_async(&mut conn)
-            .await;
-
-        // Count current requests in window
-        let count: usize = conn
-            .zcount(&redis_key, window_start, "+inf")
-            .await
-            .map_err(RateLimitError::Redis)?;
+        let window_seconds = rate_limit.window_seconds.as_secs();
+        let redis_key =
+            create_tenant_redis_key(tenant_id, &format!("ratelimit:{}s", window_seconds), key);
 
-        // Set expiration if not already set
-        let ttl: i64 = conn.ttl(&redis_key).await.map_err(RateLimitError::Redis)?;
-        if ttl < 0 {
-            let _: () = conn
-                .expire(&redis_key, (rate_limit.window_seconds + 60) as i64)
-                .await
-                .map_err(RateLimitError::Redis)?;
-        }
+        let (circuit, local) = match &self.backend {
+            RateBackend::Redis { circuit, local } => (circuit, local),
+            #[cfg(any(test, feature = "in-memory"))]
+            RateBackend::Memory(store) => {
+                return Ok(check_in_memory(store, &redis_key, key, rate_limit));
+            },
+        };
 
-        // Get multiplier from Redis (for backoff)
-        let multiplier_key = format!("{}:multiplier", redis_key);
-        let multiplier: f32 = (conn.get(&multiplier_key).await).unwrap_or(1.0);
-
-        // Calculate remaining and reset time
-        let effective_limit = (rate_limit.max_requests as f32 / multiplier) as u32;
-        let remaining = effective_limit.saturating_sub(count as u32);
-        let limit_exceeded = count as u32 > effective_limit;
-
-        // If limit is exceeded, increase backoff multiplier
-        if limit_exceeded {
-            let new_multiplier = multiplier * rate_limit.backoff_multiplier;
-            let capped_multiplier = new_multiplier.min(32.0); // Cap at 32x
-
-            let _: () = conn
-                .set(&multiplier_key, capped_multiplier)
-                .await
-                .map_err(RateLimitError::Redis)?;
-
-            let _: () = conn
-                .expire(&multiplier_key, (rate_limit.window_seconds * 5) as i64)
-                .await
-                .map_err(RateLimitError::Redis)?;
-
-            debug!(
-                "Rate limit exceeded for {}, increasing backoff to {}",
-                key, capped_multiplier
-            );
+        match check_in_redis(circuit, &redis_key, key, rate_limit).await {
+            Err(RateLimitError::Redis(e)) => {
+                let policy = circuit.policy_for(tenant_id);
+                warn!(
+                    "Redis unavailable for rate limiting, applying {:?}: {}",
+                    policy, e
+                );
+                let count = match policy {
+                    RedisUnavailablePolicy::FailOpen => 0,
+                    RedisUnavailablePolicy::FailClosed => u32::MAX,
+                    RedisUnavailablePolicy::Degrade => {
+                        let count = local.incr(&redis_key, rate_limit.window_seconds.as_duration());
+                        u32::try_from(count).unwrap_or(u32::MAX)
+                    },
+                };
+                let now = Utc::now().timestamp() as usize;
+                Ok(RateLimitInfo {
+                    limit: rate_limit.max_requests,
+                    remaining: rate_limit.max_requests.saturating_sub(count),
+                    reset: now + window_seconds as usize,
+                    window_seconds: u32::try_from(window_seconds).unwrap_or(u32::MAX),
+                    limit_exceeded: count > rate_limit.max_requests,
+                })
+            },
+            result => result,
         }
-
-        Ok(RateLimitInfo {
-            limit: effective_limit,
-            remaining,
-            reset: now + rate_limit.window_seconds as usize,
-            window_seconds: rate_limit.window_seconds,
-            limit_exceeded,
-        })
     }
 
     /// Reset the backoff multiplier
@@ -126,21 +141,163 @@ impl RateStore {
 
         let multiplier_key = format!("{}:multiplier", redis_key);
 
-        let mut conn = self
-            .redis_client
-            .get_async_connection()
+        match &self.backend {
+            RateBackend::Redis { circuit, .. } => {
+                let mut conn = circuit.connection().await.map_err(RateLimitError::Redis)?;
+
+                let _: () = conn
+                    .del(&multiplier_key)
+                    .await
+                    .map_err(RateLimitError::Redis)?;
+            },
+            #[cfg(any(test, feature = "in-memory"))]
+            RateBackend::Memory(store) => store.reset_multiplier(&multiplier_key),
+        }
+
+        debug!("Reset backoff multiplier for {}", key);
+
+        Ok(())
+    }
+}
+
+async fn check_in_redis(
+    circuit: &RedisCircuit,
+    redis_key: &str,
+    key: &str,
+    rate_limit: &RateLimit,
+) -> Result<RateLimitInfo, RateLimitError> {
+    let window_seconds = rate_limit.window_seconds.as_secs();
+    let now = Utc::now().timestamp() as usize;
+    let window_start = now - window_seconds as usize;
+
+    let mut conn = circuit.connection().await.map_err(RateLimitError::Redis)?;
+
+    // Add the current timestamp to the list of requests. Members must be
+    // unique, or requests within the same second collapse into one.
+    let member = format!("{}:{}", now, Uuid::new_v4().simple());
+    let _: () = conn
+        .zadd(redis_key, member, now)
+        .await
+        .map_err(RateLimitError::Redis)?;
+
+    // Try to clean up old entries - handle both new and old Redis versions
+    // New Redis versions use zrem_range_by_score, older use zremrangebyscore
+    // Use the raw Redis command directly
+    let _: Result<(), redis::RedisError> = redis::cmd("ZREMRANGEBYSCORE")
+        .arg(redis_key)
+        .arg(0)
+        .arg(window_start)
+        .query_async(&mut conn)
+        .await;
+
+    // Count current requests in window
+    let count: usize = conn
+        .zcount(redis_key, window_start, "+inf")
+        .await
+        .map_err(RateLimitError::Redis)?;
+
+    // Set expiration if not already set
+    let ttl: i64 = conn.ttl(redis_key).await.map_err(RateLimitError::Redis)?;
+    if ttl < 0 {
+        let _: () = conn
+            .expire(redis_key, (window_seconds + 60) as i64)
             .await
             .map_err(RateLimitError::Redis)?;
+    }
+
+    // Get multiplier from Redis (for backoff)
+    let multiplier_key = format!("{}:multiplier", redis_key);
+    let multiplier: f32 = (conn.get(&multiplier_key).await).unwrap_or(1.0);
+
+    let (info, raised_multiplier) = evaluate_window(count, multiplier, rate_limit, now);
 
+    // If limit is exceeded, increase backoff multiplier
+    if let Some(capped_multiplier) = raised_multiplier {
         let _: () = conn
-            .del(&multiplier_key)
+            .set(&multiplier_key, capped_multiplier)
             .await
             .map_err(RateLimitError::Redis)?;
 
-        debug!("Reset backoff multiplier for {}", key);
+        let _: () = conn
+            .expire(&multiplier_key, (window_seconds * 5) as i64)
+            .await
+            .map_err(RateLimitError::Redis)?;
 
-        Ok(())
+        debug!(
+            "Rate limit exceeded for {}, increasing backoff to {}",
+            key, capped_multiplier
+        );
     }
+
+    Ok(info)
+}
+
+/// Same sliding window and backoff as [`check_in_redis`], kept in process memory
+#[cfg(any(test, feature = "in-memory"))]
+fn check_in_memory(
+    store: &InMemoryRateStore,
+    redis_key: &str,
+    key: &str,
+    rate_limit: &RateLimit,
+) -> RateLimitInfo {
+    let window_seconds = rate_limit.window_seconds.as_secs();
+    let now = Utc::now().timestamp() as usize;
+    let window_start = now - window_seconds as usize;
+
+    let count = store.record_request(
+        redis_key,
+        now as i64,
+        window_start as i64,
+        Duration::from_secs(window_seconds + 60),
+    );
+
+    let multiplier_key = format!("{}:multiplier", redis_key);
+    let multiplier = store.multiplier(&multiplier_key).unwrap_or(1.0);
+
+    let (info, raised_multiplier) = evaluate_window(count, multiplier, rate_limit, now);
+    if let Some(capped_multiplier) = raised_multiplier {
+        store.set_multiplier(
+            &multiplier_key,
+            capped_multiplier,
+            Duration::from_secs(window_seconds * 5),
+        );
+        debug!(
+            "Rate limit exceeded for {}, increasing backoff to {}",
+            key, capped_multiplier
+        );
+    }
+
+    info
+}
+
+/// Limit state of a window holding `count` requests
+///
+/// Returns the raised backoff multiplier to store when the limit is exceeded.
+fn evaluate_window(
+    count: usize,
+    multiplier: f32,
+    rate_limit: &RateLimit,
+    now: usize,
+) -> (RateLimitInfo, Option<f32>) {
+    let window_seconds = rate_limit.window_seconds.as_secs();
+
+    // Calculate remaining and reset time
+    let effective_limit = (rate_limit.max_requests as f32 / multiplier) as u32;
+    let remaining = effective_limit.saturating_sub(count as u32);
+    let limit_exceeded = count as u32 > effective_limit;
+
+    let info = RateLimitInfo {
+        limit: effective_limit,
+        remaining,
+        reset: now + window_seconds as usize,
+        window_seconds: u32::try_from(window_seconds).unwrap_or(u32::MAX),
+        limit_exceeded,
+    };
+    // Cap at 32x
+    let raised_multiplier =
+        limit_exceeded.then(|| (multiplier * rate_limit.backoff_multiplier).min(32.0));
+
+    (info, raised_multiplier)
 }
 
 use std::sync::Arc;
@@ -160,20 +317,113 @@ pub struct RateLimitInfo {
     pub limit_exceeded: bool,
 }
 
+/// ID of the service account a request was made by
+///
+/// Whatever authenticates service accounts in front of the rate limiter adds
+/// it to the request extensions; the IDs listed in
+/// [`RateLimitAllowList::service_accounts`] are exempt from rate limiting.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ServiceAccountId(pub String);
+
+/// Entry of the allow-list that exempts a request from rate limiting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitExemption {
+    /// The client address is in an allow-listed CIDR range
+    Cidr,
+    /// The request was made by an allow-listed service account
+    ServiceAccount,
+}
+
+impl RateLimitExemption {
+    /// Label of the exemption in metrics
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Cidr => "cidr",
+            Self::ServiceAccount => "service_account",
+        }
+    }
+}
+
+/// [`RateLimitAllowList`] with its CIDR ranges parsed
+#[derive(Debug, Default)]
+struct AllowList {
+    networks: Vec<IpNetwork>,
+    service_accounts: HashSet<String>,
+}
+
+impl AllowList {
+    /// Parses the allow-list, skipping invalid CIDR ranges with a warning
+    fn new(config: &RateLimitAllowList) -> Self {
+        let networks = config
+            .cidrs
+            .iter()
+            .filter_map(|cidr| match cidr.trim().parse::<IpNetwork>() {
+                Ok(network) => Some(network),
+                Err(e) => {
+                    warn!(
+                        "Ignoring invalid CIDR range {} in rate limit allow-list: {}",
+                        cidr, e
+                    );
+                    None
+                },
+            })
+            .collect();
+
+        Self {
+            networks,
+            service_accounts: config.service_accounts.iter().cloned().collect(),
+        }
+    }
+
+    /// The entry exempting `request`, if any
+    fn exemption<B>(&self, request: &Request<B>) -> Option<RateLimitExemption> {
+        if request
+            .extensions()
+            .get::<ServiceAccountId>()
+            .is_some_and(|account| self.service_accounts.contains(&account.0))
+        {
+            return Some(RateLimitExemption::ServiceAccount);
+        }
+
+        let ip = client_ip(request.headers())?;
+        self.networks
+            .iter()
+            .any(|network| network.contains(ip))
+            .then_some(RateLimitExemption::Cidr)
+    }
+}
+
+/// Address of the client as forwarded by the load balancer
+///
+/// IPv4-mapped IPv6 addresses are converted to IPv4, so that they match IPv4
+/// ranges.
+fn client_ip(headers: &HeaderMap) -> Option<IpAddr> {
+    let header_value = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let ip = header_value("X-Forwarded-For")
+        .and_then(|forwarded| forwarded.split(',').next())
+        .or_else(|| header_value("X-Real-IP"))?;
+
+    ip.trim().parse::<IpAddr>().ok().map(|ip| ip.to_canonical())
+}
+
 /// Rate limiting middleware for HTTP services
+#[derive(Clone)]
 pub struct RateLimitMiddleware<S> {
     inner: S,
     store: Arc<RateStore>,
     config: RateLimitingConfig,
+    allow_list: Arc<AllowList>,
 }
 
 impl<S> RateLimitMiddleware<S> {
     /// Create a new rate limiting middleware
     pub fn new(inner: S, store: Arc<RateStore>, config: RateLimitingConfig) -> Self {
+        let allow_list = Arc::new(AllowList::new(&config.allow_list));
         Self {
             inner,
             store,
             config,
+            allow_list,
         }
     }
 
@@ -255,6 +505,7 @@ where
         let path = request.uri().path().to_string();
         let tenant_id = self.extract_tenant_id(&request);
         let client_id = self.extract_client_id(&request);
+        let exemption = self.allow_list.exemption(&request);
 
         // Get limits for this request (checking tenant overrides)
         let mut limits = self.get_limits_for_path(&path);
@@ -322,8 +573,19 @@ where
                 }
             }
 
-            // If rate limited, return 429 response
-            if rate_limit_exceeded {
+            record_request(exemption, rate_limit_exceeded);
+            if let (true, Some(exemption)) = (rate_limit_exceeded, exemption) {
+                debug!(
+                    "Rate limit exceeded by allow-listed client: {}, tenant: {}, path: {}, exemption: {}",
+                    client_id,
+                    tenant_id,
+                    path,
+                    exemption.as_str()
+                );
+            }
+
+            // If rate limited, return 429 response unless the client is allow-listed
+            if rate_limit_exceeded && exemption.is_none() {
                 let mut response = StatusCode::TOO_MANY_REQUESTS.into_response();
                 let headers_mut = response.headers_mut();
                 for (key, value) in headers.iter() {
@@ -356,7 +618,30 @@ where
     }
 }
 
+/// Count a request checked by the rate limiter
+///
+/// Requests of allow-listed clients are labeled with their exemption; when
+/// they exceed a limit they are counted as `exempted` rather than `rejected`.
+#[cfg(feature = "metrics")]
+fn record_request(exemption: Option<RateLimitExemption>, limit_exceeded: bool) {
+    let outcome = match (limit_exceeded, exemption) {
+        (false, _) => "passed",
+        (true, Some(_)) => "exempted",
+        (true, None) => "rejected",
+    };
+    metrics::counter!(
+        "auth_rate_limit_requests_total",
+        "outcome" => outcome,
+        "allow_list" => exemption.map_or("none", RateLimitExemption::as_str)
+    )
+    .increment(1);
+}
+
+#[cfg(not(feature = "metrics"))]
+fn record_request(_exemption: Option<RateLimitExemption>, _limit_exceeded: bool) {}
+
 /// Layer that applies the rate limiting middleware
+#[derive(Clone)]
 pub struct RateLimitLayer {
     store: Arc<RateStore>,
     config: RateLimitingConfig,
@@ -380,6 +665,7 @@ impl<S> Layer<S> for RateLimitLayer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::duration::DurationSecs;
 
     use std::collections::HashMap;
 
@@ -391,7 +677,7 @@ mod tests {
         path_limits.insert(
             "login".to_string(),
             vec![RateLimit {
-                window_seconds: 60,
+                window_seconds: DurationSecs::from_secs(60),
                 max_requests: 10,
                 backoff_multiplier: 2.0,
             }],
@@ -400,7 +686,7 @@ mod tests {
         path_limits.insert(
             "register".to_string(),
             vec![RateLimit {
-                window_seconds: 3600,
+                window_seconds: DurationSecs::from_secs(3600),
                 max_requests: 3,
                 backoff_multiplier: 2.0,
             }],
@@ -423,7 +709,7 @@ mod tests {
             .expect("Should have login rate limit");
 
         assert_eq!(login_limits[0].max_requests, 10);
-        assert_eq!(login_limits[0].window_seconds, 60);
+        assert_eq!(login_limits[0].window_seconds.as_secs(), 60);
         assert_eq!(login_limits[0].backoff_multiplier, 2.0);
 
         // Test default config
@@ -432,7 +718,51 @@ mod tests {
         assert!(default_config.path_limits.is_empty());
         assert_eq!(default_config.default_limits.len(), 2);
         assert_eq!(default_config.default_limits[0].max_requests, 10);
-        assert_eq!(default_config.default_limits[0].window_seconds, 1);
+        assert_eq!(default_config.default_limits[0].window_seconds.as_secs(), 1);
+    }
+
+    #[test]
+    fn test_allow_list_matches_ipv4_and_ipv6() {
+        let allow_list = AllowList::new(&RateLimitAllowList {
+            cidrs: vec![
+                "10.0.0.0/8".to_string(),
+                "2001:db8::/32".to_string(),
+                "not a range".to_string(),
+            ],
+            service_accounts: vec!["monitoring".to_string()],
+        });
+        assert_eq!(allow_list.networks.len(), 2);
+
+        let exemption = |forwarded_for: &str| {
+            let request = Request::builder()
+                .header("X-Forwarded-For", forwarded_for)
+                .body(())
+                .expect("valid request");
+            allow_list.exemption(&request)
+        };
+        assert_eq!(exemption("10.1.2.3"), Some(RateLimitExemption::Cidr));
+        assert_eq!(
+            exemption("10.1.2.3, 192.0.2.1"),
+            Some(RateLimitExemption::Cidr)
+        );
+        assert_eq!(exemption("2001:db8:1::7"), Some(RateLimitExemption::Cidr));
+        // IPv4-mapped IPv6 address of an allow-listed IPv4 address
+        assert_eq!(exemption("::ffff:10.0.0.5"), Some(RateLimitExemption::Cidr));
+        assert_eq!(exemption("192.0.2.1"), None);
+        assert_eq!(exemption("2001:db9::1"), None);
+        assert_eq!(exemption("unknown"), None);
+
+        let mut request = Request::builder()
+            .header("X-Forwarded-For", "192.0.2.1")
+            .body(())
+            .expect("valid request");
+        request
+            .extensions_mut()
+            .insert(ServiceAccountId("monitoring".to_string()));
+        assert_eq!(
+            allow_list.exemption(&request),
+            Some(RateLimitExemption::ServiceAccount)
+        );
     }
 
     // Test rate limit key generation
@@ -527,6 +857,49 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_redis_unavailable_policies() {
+        let limit = RateLimit {
+            window_seconds: DurationSecs::from_secs(60),
+            max_requests: 2,
+            backoff_multiplier: 2.0,
+        };
+        let client = Arc::new(redis::Client::open("redis://127.0.0.1:1").expect("valid Redis URL"));
+        let circuit = Arc::new(RedisCircuit::new(
+            client.clone(),
+            RedisAvailabilityConfig {
+                tenant_policies: HashMap::from([
+                    ("open".to_string(), RedisUnavailablePolicy::FailOpen),
+                    ("closed".to_string(), RedisUnavailablePolicy::FailClosed),
+                    ("degraded".to_string(), RedisUnavailablePolicy::Degrade),
+                ]),
+                ..RedisAvailabilityConfig::default()
+            },
+        ));
+        let store = RateStore::new(client).with_redis_circuit(circuit);
+        let exceeded = |tenant: &'static str| {
+            let store = &store;
+            let limit = &limit;
+            async move {
+                store
+                    .check_rate_limit(tenant, "client", limit)
+                    .await
+                    .expect("policy applied")
+                    .limit_exceeded
+            }
+        };
+
+        for _ in 0..3 {
+            assert!(!exceeded("open").await);
+            assert!(exceeded("closed").await);
+        }
+
+        // Degraded limits are counted in memory
+        assert!(!exceeded("degraded").await);
+        assert!(!exceeded("degraded").await);
+        assert!(exceeded("degraded").await);
+    }
+
     // Helper function for backoff calculation in tests
     fn calculate_backoff(violations: u32, base_wait: u32) -> u32 {
         let mut wait_time = base_wait * 2u32.pow(violations - 1);
@@ -0,0 +1,364 @@
+//! Access to Redis for the security components
+//!
+//! [`RedisCircuit`] shares one multiplexed connection between all callers and
+//! stops trying once Redis failed repeatedly: while the circuit is open,
+//! connections fail immediately instead of waiting for a dead server, and each
+//! component applies the [`RedisUnavailablePolicy`] of the tenant.
+//! [`LocalStore`] holds the per-instance state the `Degrade` policy falls back
+//! to.
+
+use redis::aio::{ConnectionLike, ConnectionManager};
+use redis::{Cmd, ErrorKind, Pipeline, RedisError, RedisFuture, RedisResult, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+use tracing::{debug, info, warn};
+
+use super::config::{RedisAvailabilityConfig, RedisUnavailablePolicy};
+
+/// Retries of the initial connection attempt, kept low so an unreachable
+/// Redis is reported to the circuit quickly
+const CONNECT_RETRIES: usize = 1;
+
+/// Circuit breaker around the Redis connection shared by the security components
+///
+/// The connection is opened on first use and reconnects on its own after it
+/// was dropped. Handing out a [`RedisConnection`] is cheap: all of them send
+/// their commands over the same connection, so concurrent callers do not open
+/// connections of their own.
+pub struct RedisCircuit {
+    client: Arc<redis::Client>,
+    manager: OnceCell<ConnectionManager>,
+    breaker: Arc<Breaker>,
+}
+
+/// Failure accounting shared by a circuit and the connections it handed out
+struct Breaker {
+    config: RedisAvailabilityConfig,
+    state: Mutex<CircuitState>,
+}
+
+#[derive(Debug, Default)]
+struct CircuitState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl RedisCircuit {
+    /// Create a circuit around `client`
+    pub fn new(client: Arc<redis::Client>, config: RedisAvailabilityConfig) -> Self {
+        Self {
+            client,
+            manager: OnceCell::new(),
+            breaker: Arc::new(Breaker {
+                config,
+                state: Mutex::new(CircuitState::default()),
+            }),
+        }
+    }
+
+    /// Policy that applies to `tenant_id` while Redis is unavailable
+    pub fn policy_for(&self, tenant_id: &str) -> RedisUnavailablePolicy {
+        self.breaker.config.policy_for(tenant_id)
+    }
+
+    /// Whether connections currently fail without contacting Redis
+    pub fn is_open(&self) -> bool {
+        self.breaker.is_open()
+    }
+
+    /// The shared connection, unless the circuit is open
+    ///
+    /// Once the open time has passed, the next command decides whether the
+    /// circuit closes or stays open for another period.
+    pub async fn connection(&self) -> RedisResult<RedisConnection> {
+        if self.is_open() {
+            return Err(RedisError::from((
+                ErrorKind::IoError,
+                "Redis circuit is open",
+            )));
+        }
+
+        let manager = self
+            .manager
+            .get_or_try_init(|| async {
+                let manager = ConnectionManager::new_with_backoff(
+                    (*self.client).clone(),
+                    2,
+                    100,
+                    CONNECT_RETRIES,
+                )
+                .await?;
+                debug!("Opened the shared Redis connection");
+                Ok::<_, RedisError>(manager)
+            })
+            .await;
+        match manager {
+            Ok(manager) => Ok(RedisConnection {
+                manager: manager.clone(),
+                breaker: Arc::clone(&self.breaker),
+            }),
+            Err(e) => {
+                self.breaker.record_failure(&e);
+                Err(e)
+            },
+        }
+    }
+}
+
+impl Breaker {
+    fn is_open(&self) -> bool {
+        self.state()
+            .open_until
+            .is_some_and(|open_until| Instant::now() < open_until)
+    }
+
+    /// Account for the outcome of a command
+    ///
+    /// Errors returned by the server, e.g. for a key of the wrong type, show
+    /// that Redis is reachable and count as success.
+    fn record<T>(&self, result: &RedisResult<T>) {
+        match result {
+            Err(e) if is_unavailable(e) => self.record_failure(e),
+            _ => self.record_success(),
+        }
+    }
+
+    fn record_success(&self) {
+        let mut state = self.state();
+        if state.open_until.take().is_some() {
+            info!("Redis is reachable again, closing the circuit");
+        }
+        state.consecutive_failures = 0;
+    }
+
+    fn record_failure(&self, error: &RedisError) {
+        let mut state = self.state();
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+        if state.consecutive_failures >= self.config.circuit_failure_threshold.max(1) {
+            warn!(
+                "Redis failed {} times in a row, opening the circuit for {}: {}",
+                state.consecutive_failures, self.config.circuit_open_seconds, error
+            );
+            state.open_until =
+                Some(Instant::now() + self.config.circuit_open_seconds.as_duration());
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, CircuitState> {
+        self.state.lock().expect("Redis circuit lock poisoned")
+    }
+}
+
+/// Whether `error` means Redis could not be reached
+fn is_unavailable(error: &RedisError) -> bool {
+    error.is_io_error()
+        || error.is_connection_dropped()
+        || error.is_connection_refusal()
+        || error.is_timeout()
+}
+
+/// Handle on the connection shared through a [`RedisCircuit`]
+///
+/// A command that fails because the connection was dropped is sent once more
+/// over the connection the manager reconnects with, so callers do not see
+/// the reconnect.
+#[derive(Clone)]
+pub struct RedisConnection {
+    manager: ConnectionManager,
+    breaker: Arc<Breaker>,
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        Box::pin(async move {
+            let mut result = self.manager.req_packed_command(cmd).await;
+            if result
+                .as_ref()
+                .is_err_and(RedisError::is_connection_dropped)
+            {
+                debug!("Redis connection dropped, retrying the command after reconnecting");
+                result = self.manager.req_packed_command(cmd).await;
+            }
+            self.breaker.record(&result);
+            result
+        })
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        Box::pin(async move {
+            let mut result = self.manager.req_packed_commands(cmd, offset, count).await;
+            if result
+                .as_ref()
+                .is_err_and(RedisError::is_connection_dropped)
+            {
+                debug!("Redis connection dropped, retrying the pipeline after reconnecting");
+                result = self.manager.req_packed_commands(cmd, offset, count).await;
+            }
+            self.breaker.record(&result);
+            result
+        })
+    }
+
+    fn get_db(&self) -> i64 {
+        self.manager.get_db()
+    }
+}
+
+/// Expiring counters and values kept in memory by one instance
+///
+/// Used by the `Degrade` policy, so limits and nonces are enforced per
+/// instance while Redis is unavailable. Expired entries are dropped on write.
+#[derive(Debug, Default)]
+pub struct LocalStore {
+    entries: Mutex<HashMap<String, (i64, Instant)>>,
+}
+
+impl LocalStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Increment the counter under `key`, which expires `ttl` after it was created
+    pub fn incr(&self, key: &str, ttl: Duration) -> i64 {
+        let mut entries = self.entries();
+        let now = Instant::now();
+        entries.retain(|_, (_, expires_at)| *expires_at > now);
+        let entry = entries.entry(key.to_string()).or_insert((0, now + ttl));
+        entry.0 += 1;
+        entry.0
+    }
+
+    /// Store `value` under `key` for `ttl`
+    pub fn set(&self, key: &str, value: i64, ttl: Duration) {
+        let mut entries = self.entries();
+        let now = Instant::now();
+        entries.retain(|_, (_, expires_at)| *expires_at > now);
+        entries.insert(key.to_string(), (value, now + ttl));
+    }
+
+    /// Store `value` under `key` for `ttl` unless a value is already stored
+    ///
+    /// Returns whether the value was stored.
+    pub fn set_nx(&self, key: &str, value: i64, ttl: Duration) -> bool {
+        let mut entries = self.entries();
+        let now = Instant::now();
+        entries.retain(|_, (_, expires_at)| *expires_at > now);
+        if entries.contains_key(key) {
+            return false;
+        }
+        entries.insert(key.to_string(), (value, now + ttl));
+        true
+    }
+
+    /// Remove and return the value under `key`, if it has not expired
+    pub fn take(&self, key: &str) -> Option<i64> {
+        self.entries()
+            .remove(key)
+            .filter(|(_, expires_at)| *expires_at > Instant::now())
+            .map(|(value, _)| value)
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, HashMap<String, (i64, Instant)>> {
+        self.entries.lock().expect("local store lock poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::duration::DurationSecs;
+
+    /// Client of a port nothing listens on
+    fn unreachable_client() -> Arc<redis::Client> {
+        Arc::new(redis::Client::open("redis://127.0.0.1:1").expect("valid Redis URL"))
+    }
+
+    #[tokio::test]
+    async fn test_circuit_opens_after_consecutive_failures() {
+        let circuit = RedisCircuit::new(
+            unreachable_client(),
+            RedisAvailabilityConfig {
+                circuit_failure_threshold: 2,
+                circuit_open_seconds: DurationSecs::from_secs(60),
+                ..RedisAvailabilityConfig::default()
+            },
+        );
+
+        assert!(circuit.connection().await.is_err());
+        assert!(!circuit.is_open());
+        assert!(circuit.connection().await.is_err());
+        assert!(circuit.is_open());
+
+        let error = circuit
+            .connection()
+            .await
+            .err()
+            .expect("open circuit fails");
+        assert!(error.to_string().contains("circuit is open"));
+    }
+
+    #[tokio::test]
+    async fn test_circuit_retries_after_open_time() {
+        let circuit = RedisCircuit::new(
+            unreachable_client(),
+            RedisAvailabilityConfig {
+                circuit_failure_threshold: 1,
+                circuit_open_seconds: DurationSecs::from_secs(0),
+                ..RedisAvailabilityConfig::default()
+            },
+        );
+
+        assert!(circuit.connection().await.is_err());
+        assert!(!circuit.is_open());
+        let error = circuit
+            .connection()
+            .await
+            .err()
+            .expect("Redis is unreachable");
+        assert!(!error.to_string().contains("circuit is open"));
+    }
+
+    #[test]
+    fn test_policy_for_tenant_override() {
+        let config = RedisAvailabilityConfig {
+            tenant_policies: HashMap::from([(
+                "bank".to_string(),
+                RedisUnavailablePolicy::FailClosed,
+            )]),
+            ..RedisAvailabilityConfig::default()
+        };
+        let circuit = RedisCircuit::new(unreachable_client(), config);
+
+        assert_eq!(
+            circuit.policy_for("bank"),
+            RedisUnavailablePolicy::FailClosed
+        );
+        assert_eq!(circuit.policy_for("shop"), RedisUnavailablePolicy::FailOpen);
+    }
+
+    #[test]
+    fn test_local_store_expiry_and_set_nx() {
+        let store = LocalStore::new();
+        let ttl = Duration::from_secs(60);
+
+        assert_eq!(store.incr("counter", ttl), 1);
+        assert_eq!(store.incr("counter", ttl), 2);
+
+        assert!(store.set_nx("nonce", 1, ttl));
+        assert!(!store.set_nx("nonce", 2, ttl));
+        assert_eq!(store.take("nonce"), Some(1));
+        assert_eq!(store.take("nonce"), None);
+
+        store.set("expired", 1, Duration::ZERO);
+        assert_eq!(store.take("expired"), None);
+        assert_eq!(store.incr("expired", ttl), 1);
+    }
+}
@@ -1,3 +1,7 @@
+use acci_core::signing::{
+    NONCE_HEADER, SIGNATURE_HEADER, SignedRequest, TIMESTAMP_HEADER, verify_request_signature,
+};
+use async_trait::async_trait;
 use axum::body::Body;
 use axum::http::{Request, StatusCode};
 use axum::response::{IntoResponse, Response};
@@ -6,29 +10,258 @@ use futures::future::BoxFuture;
 use hex;
 use rand::Rng;
 use redis::{self, AsyncCommands};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Duration;
 use tower::{Layer, Service};
 use tracing::{debug, error, warn};
 
-use super::config::ReplayProtectionConfig;
+use super::config::{
+    RedisAvailabilityConfig, RedisUnavailablePolicy, ReplayProtectionConfig, ReplayProtectionMode,
+};
+#[cfg(any(test, feature = "in-memory"))]
+use super::memory::InMemoryNonceStore;
+use super::redis_circuit::{LocalStore, RedisCircuit};
 use super::types::create_tenant_redis_key;
 
+/// Request header naming the API key whose signing secret signed the request
+///
+/// Without it the signing secret of the tenant is used.
+pub const SIGNING_KEY_ID_HEADER: &str = "X-Signing-Key-Id";
+
+/// Source of the secrets signed requests are verified with
+#[async_trait]
+pub trait SigningSecretStore: Send + Sync {
+    /// Secret of the API key `key_id`, or of the tenant if no key is named
+    async fn signing_secret(
+        &self,
+        tenant_id: &str,
+        key_id: Option<&str>,
+    ) -> Result<Option<Vec<u8>>, anyhow::Error>;
+}
+
+/// Signing secrets held in memory
+#[derive(Debug, Clone, Default)]
+pub struct StaticSigningSecrets {
+    tenants: HashMap<String, Vec<u8>>,
+    api_keys: HashMap<String, Vec<u8>>,
+}
+
+impl StaticSigningSecrets {
+    /// Create an empty set of secrets
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the signing secret of a tenant
+    pub fn with_tenant_secret(mut self, tenant_id: impl Into<String>, secret: &[u8]) -> Self {
+        self.tenants.insert(tenant_id.into(), secret.to_vec());
+        self
+    }
+
+    /// Add the signing secret of an API key
+    pub fn with_api_key_secret(mut self, key_id: impl Into<String>, secret: &[u8]) -> Self {
+        self.api_keys.insert(key_id.into(), secret.to_vec());
+        self
+    }
+}
+
+#[async_trait]
+impl SigningSecretStore for StaticSigningSecrets {
+    async fn signing_secret(
+        &self,
+        tenant_id: &str,
+        key_id: Option<&str>,
+    ) -> Result<Option<Vec<u8>>, anyhow::Error> {
+        Ok(match key_id {
+            Some(key_id) => self.api_keys.get(key_id),
+            None => self.tenants.get(tenant_id),
+        }
+        .cloned())
+    }
+}
+
+/// Why a signed request was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignedRequestRejection {
+    /// Signature, timestamp or nonce header is missing or malformed
+    MissingHeaders,
+    /// No signing secret is known for the tenant or API key
+    UnknownKey,
+    /// The timestamp is outside the allowed skew window
+    StaleTimestamp,
+    /// The signature does not match the request
+    InvalidSignature,
+    /// The nonce was already used
+    NonceReused,
+}
+
+impl SignedRequestRejection {
+    /// Status code the middleware answers with
+    pub fn status(self) -> StatusCode {
+        match self {
+            SignedRequestRejection::MissingHeaders | SignedRequestRejection::NonceReused => {
+                StatusCode::BAD_REQUEST
+            },
+            SignedRequestRejection::UnknownKey
+            | SignedRequestRejection::StaleTimestamp
+            | SignedRequestRejection::InvalidSignature => StatusCode::UNAUTHORIZED,
+        }
+    }
+}
+
+/// Check the timestamp and signature of a signed request
+fn check_signed_request(
+    secret: &[u8],
+    request: &SignedRequest<'_>,
+    signature: &str,
+    now: i64,
+    max_skew_seconds: u64,
+) -> Result<(), SignedRequestRejection> {
+    if now.abs_diff(request.timestamp) > max_skew_seconds {
+        return Err(SignedRequestRejection::StaleTimestamp);
+    }
+    if !verify_request_signature(secret, request, signature) {
+        return Err(SignedRequestRejection::InvalidSignature);
+    }
+    Ok(())
+}
+
 /// Store for managing nonces to prevent replay attacks
+///
+/// While Redis is unavailable, the [`RedisUnavailablePolicy`] of the tenant
+/// decides: `FailOpen` accepts every nonce, `FailClosed` fails and `Degrade`
+/// keeps nonces in memory, so a nonce is only accepted by the instance that
+/// issued it.
+///
+/// With the `in-memory` feature, [`NonceStore::in_memory`] keeps all nonces
+/// in process memory instead of Redis.
 pub struct NonceStore {
-    redis_client: Arc<redis::Client>,
+    backend: NonceBackend,
+    local: LocalStore,
     config: ReplayProtectionConfig,
 }
 
+enum NonceBackend {
+    Redis(Arc<RedisCircuit>),
+    #[cfg(any(test, feature = "in-memory"))]
+    Memory(InMemoryNonceStore),
+}
+
 impl NonceStore {
     /// Create a new nonce store
     pub fn new(redis_client: Arc<redis::Client>, config: ReplayProtectionConfig) -> Self {
         Self {
-            redis_client,
+            backend: NonceBackend::Redis(Arc::new(RedisCircuit::new(
+                redis_client,
+                RedisAvailabilityConfig::default(),
+            ))),
+            local: LocalStore::new(),
             config,
         }
     }
 
+    /// Create a nonce store keeping the nonces in process memory
+    ///
+    /// A nonce is only accepted by the instance that issued it.
+    #[cfg(any(test, feature = "in-memory"))]
+    pub fn in_memory(store: InMemoryNonceStore, config: ReplayProtectionConfig) -> Self {
+        Self {
+            backend: NonceBackend::Memory(store),
+            local: LocalStore::new(),
+            config,
+        }
+    }
+
+    /// Use a circuit shared with the other security components
+    ///
+    /// Has no effect on an in-memory store.
+    pub fn with_redis_circuit(mut self, circuit: Arc<RedisCircuit>) -> Self {
+        match &mut self.backend {
+            NonceBackend::Redis(current) => *current = circuit,
+            #[cfg(any(test, feature = "in-memory"))]
+            NonceBackend::Memory(_) => {},
+        }
+        self
+    }
+
+    /// Policy of `tenant_id` after Redis failed with `error`
+    fn unavailable_policy(
+        &self,
+        tenant_id: &str,
+        error: &redis::RedisError,
+    ) -> RedisUnavailablePolicy {
+        let policy = match &self.backend {
+            NonceBackend::Redis(circuit) => circuit.policy_for(tenant_id),
+            // Only the Redis backend fails with a Redis error
+            #[cfg(any(test, feature = "in-memory"))]
+            NonceBackend::Memory(_) => RedisUnavailablePolicy::FailClosed,
+        };
+        warn!(
+            "Redis unavailable for replay protection, applying {:?}: {}",
+            policy, error
+        );
+        policy
+    }
+
+    /// Whether nonces are actually stored and checked
+    ///
+    /// With replay protection disabled every nonce validates.
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Replay protection mode of requests for `path`
+    pub fn mode_for(&self, path: &str) -> ReplayProtectionMode {
+        self.config.mode_for(path)
+    }
+
+    /// Largest body buffered to verify a request signature
+    pub fn max_signed_body_bytes(&self) -> usize {
+        self.config.max_signed_body_bytes
+    }
+
+    /// Check the timestamp and signature of a signed request
+    ///
+    /// The timestamp is always checked against the skew window, independent
+    /// of `timestamp_validation`, since it bounds how long a nonce has to be
+    /// remembered. The nonce is not consumed, see
+    /// [`consume_signed_nonce`](Self::consume_signed_nonce).
+    pub fn check_signed_request(
+        &self,
+        secret: &[u8],
+        request: &SignedRequest<'_>,
+        signature: &str,
+    ) -> Result<(), SignedRequestRejection> {
+        check_signed_request(
+            secret,
+            request,
+            signature,
+            Utc::now().timestamp(),
+            self.config.max_timestamp_skew_seconds.as_secs(),
+        )
+    }
+
+    /// Consume the client-chosen nonce of a verified signed request
+    ///
+    /// Returns `false` if the nonce was already used. The nonce is remembered
+    /// for twice the skew window, the longest time a request with a valid
+    /// timestamp can be replayed in.
+    pub async fn consume_signed_nonce(
+        &self,
+        tenant_id: &str,
+        nonce: &str,
+    ) -> Result<bool, anyhow::Error> {
+        if !self.config.enabled {
+            return Ok(true);
+        }
+
+        let ttl_seconds = self.config.max_timestamp_skew_seconds.as_secs() * 2;
+        self.record_once(tenant_id, "signed", nonce, ttl_seconds)
+            .await
+    }
+
     /// Generate a new nonce with expiration
     pub async fn generate_nonce(
         &self,
@@ -41,24 +274,26 @@ impl NonceStore {
         }
 
         // Generate random bytes for the nonce
-        let mut rng = rand::rng();
-        let mut nonce_bytes = [0u8; 16];
-        rng.fill(&mut nonce_bytes);
+        let nonce_bytes: [u8; 16] = rand::rng().random();
 
         // Convert to hex string
         let nonce = hex::encode(nonce_bytes);
 
-        // Store in Redis with expiration
-        let mut conn = self.redis_client.get_async_connection().await?;
+        // Store the current timestamp with the nonce
         let redis_key =
             create_tenant_redis_key(tenant_id, "nonce", &format!("{}:{}", context, nonce));
-
-        // Store the current timestamp with the nonce
         let now = Utc::now().timestamp();
-        let _: () = conn.set(&redis_key, now.to_string()).await?;
-        let _: () = conn
-            .expire(&redis_key, self.config.nonce_expiration_seconds as i64)
-            .await?;
+        if let Err(e) = self.store_nonce(&redis_key, now).await {
+            match self.unavailable_policy(tenant_id, &e) {
+                RedisUnavailablePolicy::FailOpen => {},
+                RedisUnavailablePolicy::FailClosed => return Err(e.into()),
+                RedisUnavailablePolicy::Degrade => self.local.set(
+                    &redis_key,
+                    now,
+                    self.config.nonce_expiration_seconds.as_duration(),
+                ),
+            }
+        }
 
         debug!(
             "Generated nonce for tenant {}, context {}: {}",
@@ -81,25 +316,25 @@ impl NonceStore {
             return Ok(true);
         }
 
-        // Get from Redis
-        let mut conn = self.redis_client.get_async_connection().await?;
         let redis_key =
             create_tenant_redis_key(tenant_id, "nonce", &format!("{}:{}", context, nonce));
+        let stored_timestamp = match self.take_nonce(&redis_key).await {
+            Ok(stored_timestamp) => stored_timestamp,
+            Err(e) => match self.unavailable_policy(tenant_id, &e) {
+                RedisUnavailablePolicy::FailOpen => return Ok(true),
+                RedisUnavailablePolicy::FailClosed => return Err(e.into()),
+                RedisUnavailablePolicy::Degrade => self.local.take(&redis_key),
+            },
+        };
 
-        let stored_timestamp: Option<String> = conn.get(&redis_key).await?;
-
-        if let Some(ts_str) = stored_timestamp {
-            // Delete the nonce to prevent reuse
-            let _: () = conn.del(&redis_key).await?;
-
+        if let Some(stored_ts) = stored_timestamp {
             // If timestamp validation is enabled, check the timestamp
             if self.config.timestamp_validation {
                 if let Some(request_ts) = timestamp {
-                    let stored_ts = ts_str.parse::<i64>().unwrap_or(0);
                     let now = Utc::now().timestamp();
 
                     // Check if request timestamp is within acceptable range
-                    let max_skew = self.config.max_timestamp_skew_seconds as i64;
+                    let max_skew = self.config.max_timestamp_skew_seconds.as_secs() as i64;
                     let ts_diff = (request_ts - stored_ts).abs();
 
                     if ts_diff > max_skew {
@@ -112,11 +347,9 @@ impl NonceStore {
 
                     // Check if nonce is too old
                     let age = now - stored_ts;
-                    if age > self.config.nonce_expiration_seconds as i64 {
-                        warn!(
-                            "Nonce expired: {}s old (max: {}s)",
-                            age, self.config.nonce_expiration_seconds
-                        );
+                    let max_age = self.config.nonce_expiration_seconds.as_secs() as i64;
+                    if age > max_age {
+                        warn!("Nonce expired: {}s old (max: {}s)", age, max_age);
                         return Ok(false);
                     }
                 }
@@ -157,18 +390,139 @@ impl NonceStore {
         self.validate_nonce(tenant_id, &format!("csrf:{}", form_id), token, None)
             .await
     }
+
+    /// Record an externally issued one-time value, e.g. a SAML assertion ID
+    ///
+    /// Returns `false` if the value was already recorded within `ttl_seconds`.
+    /// Unlike nonce validation this fails while protection is disabled, since
+    /// accepting every value would silently allow replays.
+    pub async fn record_once(
+        &self,
+        tenant_id: &str,
+        context: &str,
+        value: &str,
+        ttl_seconds: u64,
+    ) -> Result<bool, anyhow::Error> {
+        if !self.config.enabled {
+            anyhow::bail!(
+                "Replay protection is disabled, {} cannot be recorded",
+                context
+            );
+        }
+
+        let redis_key =
+            create_tenant_redis_key(tenant_id, "nonce", &format!("{}:{}", context, value));
+        let now = Utc::now().timestamp();
+        let recorded = match self.set_once(&redis_key, now, ttl_seconds).await {
+            Ok(recorded) => recorded,
+            Err(e) => match self.unavailable_policy(tenant_id, &e) {
+                RedisUnavailablePolicy::FailOpen => true,
+                RedisUnavailablePolicy::FailClosed => return Err(e.into()),
+                RedisUnavailablePolicy::Degrade => {
+                    self.local
+                        .set_nx(&redis_key, now, Duration::from_secs(ttl_seconds.max(1)))
+                },
+            },
+        };
+
+        if !recorded {
+            warn!(
+                "Replayed value for tenant {}, context {}: {}",
+                tenant_id, context, value
+            );
+        }
+        Ok(recorded)
+    }
+
+    /// Store a nonce with its creation time
+    async fn store_nonce(&self, redis_key: &str, now: i64) -> redis::RedisResult<()> {
+        let expiration = self.config.nonce_expiration_seconds;
+        match &self.backend {
+            NonceBackend::Redis(circuit) => {
+                let mut conn = circuit.connection().await?;
+                let _: () = conn.set(redis_key, now.to_string()).await?;
+                conn.expire(redis_key, expiration.as_secs() as i64).await
+            },
+            #[cfg(any(test, feature = "in-memory"))]
+            NonceBackend::Memory(store) => {
+                store.set(redis_key, now, expiration.as_duration());
+                Ok(())
+            },
+        }
+    }
+
+    /// Remove a nonce and return its creation time, if it was stored
+    async fn take_nonce(&self, redis_key: &str) -> redis::RedisResult<Option<i64>> {
+        match &self.backend {
+            NonceBackend::Redis(circuit) => {
+                let mut conn = circuit.connection().await?;
+                let stored_timestamp: Option<String> = conn.get(redis_key).await?;
+                if stored_timestamp.is_some() {
+                    // Delete the nonce to prevent reuse
+                    let _: () = conn.del(redis_key).await?;
+                }
+                Ok(stored_timestamp.map(|ts| ts.parse::<i64>().unwrap_or(0)))
+            },
+            #[cfg(any(test, feature = "in-memory"))]
+            NonceBackend::Memory(store) => Ok(store.take(redis_key)),
+        }
+    }
+
+    /// Store `redis_key` unless it is already stored, returning whether it was stored
+    async fn set_once(
+        &self,
+        redis_key: &str,
+        now: i64,
+        ttl_seconds: u64,
+    ) -> redis::RedisResult<bool> {
+        match &self.backend {
+            NonceBackend::Redis(circuit) => {
+                let mut conn = circuit.connection().await?;
+                let recorded: Option<String> = redis::cmd("SET")
+                    .arg(redis_key)
+                    .arg(now)
+                    .arg("NX")
+                    .arg("EX")
+                    .arg(ttl_seconds.max(1))
+                    .query_async(&mut conn)
+                    .await?;
+                Ok(recorded.is_some())
+            },
+            #[cfg(any(test, feature = "in-memory"))]
+            NonceBackend::Memory(store) => {
+                Ok(store.set_nx(redis_key, now, Duration::from_secs(ttl_seconds.max(1))))
+            },
+        }
+    }
 }
 
 /// Middleware for preventing replay attacks
+///
+/// Routes in nonce-only mode consume a server-issued nonce. Routes in signed
+/// mode additionally verify an HMAC signature over the request, so the nonce
+/// cannot be reused with a different body; they need a
+/// [`SigningSecretStore`].
+#[derive(Clone)]
 pub struct ReplayProtectionMiddleware<S> {
     inner: S,
     nonce_store: Arc<NonceStore>,
+    signing_secrets: Option<Arc<dyn SigningSecretStore>>,
 }
 
 impl<S> ReplayProtectionMiddleware<S> {
     /// Create a new replay protection middleware
     pub fn new(inner: S, nonce_store: Arc<NonceStore>) -> Self {
-        Self { inner, nonce_store }
+        Self {
+            inner,
+            nonce_store,
+            signing_secrets: None,
+        }
+    }
+
+    /// Verify signed requests with the secrets of `signing_secrets`
+    pub fn with_signing_secrets(mut self, signing_secrets: Arc<dyn SigningSecretStore>) -> Self {
+        self.signing_secrets = Some(signing_secrets);
+        self
     }
 
     /// Extract tenant ID from request
@@ -245,11 +599,10 @@ impl<S> ReplayProtectionMiddleware<S> {
     }
 }
 
-impl<S, B> Service<Request<B>> for ReplayProtectionMiddleware<S>
+impl<S> Service<Request<Body>> for ReplayProtectionMiddleware<S>
 where
-    S: Service<Request<B>, Response = Response<Body>> + Clone + Send + Sync + 'static,
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + Sync + 'static,
     S::Future: Send + 'static,
-    B: Send + 'static,
 {
     type Response = S::Response;
     type Error = S::Error;
@@ -259,13 +612,19 @@ where
         self.inner.poll_ready(cx)
     }
 
-    fn call(&mut self, request: Request<B>) -> Self::Future {
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
         // Skip protection for safe methods
         if !self.requires_protection(&request) {
             let mut inner_service = self.inner.clone();
             return Box::pin(async move { inner_service.call(request).await });
         }
 
+        if self.nonce_store.is_enabled()
+            && self.nonce_store.mode_for(request.uri().path()) == ReplayProtectionMode::Signed
+        {
+            return self.call_signed(request);
+        }
+
         let tenant_id = self.extract_tenant_id(&request);
         let context = self.extract_context(&request);
         let nonce = self.extract_nonce(&request);
@@ -304,15 +663,126 @@ where
     }
 }
 
+impl<S> ReplayProtectionMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + Sync + 'static,
+    S::Future: Send + 'static,
+{
+    /// Verify the signature of a request and consume its nonce
+    ///
+    /// The nonce is only consumed once the signature is valid, so a forged
+    /// request cannot burn the nonce of the legitimate one.
+    fn call_signed(
+        &mut self,
+        request: Request<Body>,
+    ) -> BoxFuture<'static, Result<Response<Body>, S::Error>> {
+        let tenant_id = self.extract_tenant_id(&request);
+        let nonce_store = self.nonce_store.clone();
+        let signing_secrets = self.signing_secrets.clone();
+        let mut inner_service = self.inner.clone();
+
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let header = |name: &str| {
+                parts
+                    .headers
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_string)
+            };
+            let (Some(signature), Some(timestamp), Some(nonce)) = (
+                header(SIGNATURE_HEADER),
+                header(TIMESTAMP_HEADER).and_then(|ts| ts.parse::<i64>().ok()),
+                header(NONCE_HEADER),
+            ) else {
+                return Ok(SignedRequestRejection::MissingHeaders
+                    .status()
+                    .into_response());
+            };
+            let key_id = header(SIGNING_KEY_ID_HEADER);
+
+            let Some(signing_secrets) = signing_secrets else {
+                error!("Signed replay protection is configured without signing secrets");
+                return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+            };
+            let secret = match signing_secrets
+                .signing_secret(&tenant_id, key_id.as_deref())
+                .await
+            {
+                Ok(Some(secret)) => secret,
+                Ok(None) => {
+                    warn!(tenant_id = %tenant_id, key_id = ?key_id, "Unknown signing key");
+                    return Ok(SignedRequestRejection::UnknownKey.status().into_response());
+                },
+                Err(e) => {
+                    error!("Error loading signing secret: {}", e);
+                    return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+                },
+            };
+
+            let body = match axum::body::to_bytes(body, nonce_store.max_signed_body_bytes()).await {
+                Ok(body) => body,
+                Err(_) => return Ok(StatusCode::PAYLOAD_TOO_LARGE.into_response()),
+            };
+            let path = parts
+                .uri
+                .path_and_query()
+                .map_or(parts.uri.path(), |path| path.as_str());
+            let signed = SignedRequest {
+                timestamp,
+                nonce: &nonce,
+                method: parts.method.as_str(),
+                path,
+                body: &body,
+            };
+            if let Err(rejection) = nonce_store.check_signed_request(&secret, &signed, &signature) {
+                warn!(
+                    tenant_id = %tenant_id,
+                    path = %path,
+                    ?rejection,
+                    "Rejected signed request"
+                );
+                return Ok(rejection.status().into_response());
+            }
+
+            match nonce_store.consume_signed_nonce(&tenant_id, &nonce).await {
+                Ok(true) => {},
+                Ok(false) => {
+                    return Ok(SignedRequestRejection::NonceReused.status().into_response());
+                },
+                Err(e) => {
+                    error!("Error consuming nonce: {}", e);
+                    return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+                },
+            }
+
+            inner_service
+                .call(Request::from_parts(parts, Body::from(body)))
+                .await
+        })
+    }
+}
+
 /// Layer that applies the replay protection middleware
+#[derive(Clone)]
 pub struct ReplayProtectionLayer {
     nonce_store: Arc<NonceStore>,
+    signing_secrets: Option<Arc<dyn SigningSecretStore>>,
 }
 
 impl ReplayProtectionLayer {
     /// Create a new replay protection layer
     pub fn new(nonce_store: Arc<NonceStore>) -> Self {
-        Self { nonce_store }
+        Self {
+            nonce_store,
+            signing_secrets: None,
+        }
+    }
+
+    /// Verify signed requests with the secrets of `signing_secrets`
+    pub fn with_signing_secrets(mut self, signing_secrets: Arc<dyn SigningSecretStore>) -> Self {
+        self.signing_secrets = Some(signing_secrets);
+        self
     }
 }
 
@@ -320,7 +790,11 @@ impl<S> Layer<S> for ReplayProtectionLayer {
     type Service = ReplayProtectionMiddleware<S>;
 
     fn layer(&self, service: S) -> Self::Service {
-        ReplayProtectionMiddleware::new(service, self.nonce_store.clone())
+        let middleware = ReplayProtectionMiddleware::new(service, self.nonce_store.clone());
+        match &self.signing_secrets {
+            Some(signing_secrets) => middleware.with_signing_secrets(signing_secrets.clone()),
+            None => middleware,
+        }
     }
 }
 
@@ -330,6 +804,81 @@ mod tests {
 
     use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+    fn unavailable_store(policy: RedisUnavailablePolicy) -> NonceStore {
+        let client = Arc::new(redis::Client::open("redis://127.0.0.1:1").expect("valid Redis URL"));
+        let circuit = Arc::new(RedisCircuit::new(
+            client.clone(),
+            RedisAvailabilityConfig {
+                policy,
+                ..RedisAvailabilityConfig::default()
+            },
+        ));
+        NonceStore::new(client, ReplayProtectionConfig::default()).with_redis_circuit(circuit)
+    }
+
+    #[tokio::test]
+    async fn test_redis_unavailable_policies() {
+        let fail_open = unavailable_store(RedisUnavailablePolicy::FailOpen);
+        assert!(
+            fail_open
+                .validate_nonce("tenant", "form", "unknown", None)
+                .await
+                .expect("fails open")
+        );
+        assert!(
+            fail_open
+                .record_once("tenant", "saml", "assertion", 60)
+                .await
+                .expect("fails open")
+        );
+
+        let fail_closed = unavailable_store(RedisUnavailablePolicy::FailClosed);
+        assert!(fail_closed.generate_nonce("tenant", "form").await.is_err());
+        assert!(
+            fail_closed
+                .validate_nonce("tenant", "form", "unknown", None)
+                .await
+                .is_err()
+        );
+        assert!(
+            fail_closed
+                .record_once("tenant", "saml", "assertion", 60)
+                .await
+                .is_err()
+        );
+
+        // Degraded nonces are kept in memory and still consumed only once
+        let degrade = unavailable_store(RedisUnavailablePolicy::Degrade);
+        let nonce = degrade
+            .generate_nonce("tenant", "form")
+            .await
+            .expect("nonce kept in memory");
+        assert!(
+            degrade
+                .validate_nonce("tenant", "form", &nonce, None)
+                .await
+                .expect("validated")
+        );
+        assert!(
+            !degrade
+                .validate_nonce("tenant", "form", &nonce, None)
+                .await
+                .expect("validated")
+        );
+        assert!(
+            degrade
+                .record_once("tenant", "saml", "assertion", 60)
+                .await
+                .expect("recorded")
+        );
+        assert!(
+            !degrade
+                .record_once("tenant", "saml", "assertion", 60)
+                .await
+                .expect("recorded")
+        );
+    }
+
     // Test the nonce format and structure
     #[test]
     fn test_nonce_structure() {
@@ -454,6 +1003,103 @@ mod tests {
         assert_eq!(key, "security:other_tenant:nonce:login:abcdef123456");
     }
 
+    #[test]
+    fn test_signed_request_with_tampered_body_is_rejected() {
+        let secret = b"tenant-signing-secret";
+        let now = 1_700_000_000;
+        let request = SignedRequest {
+            timestamp: now,
+            nonce: "9b1c44e0",
+            method: "POST",
+            path: "/api/transfers",
+            body: b"{\"amount\":10}",
+        };
+        let signature = acci_core::signing::sign_request(secret, &request);
+        assert_eq!(
+            check_signed_request(secret, &request, &signature, now, 60),
+            Ok(())
+        );
+
+        let tampered = SignedRequest {
+            body: b"{\"amount\":10000}",
+            ..request
+        };
+        assert_eq!(
+            check_signed_request(secret, &tampered, &signature, now, 60),
+            Err(SignedRequestRejection::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_signed_request_with_stale_timestamp_is_rejected() {
+        let secret = b"tenant-signing-secret";
+        let now = 1_700_000_000;
+        let request = SignedRequest {
+            timestamp: now - 61,
+            nonce: "9b1c44e0",
+            method: "POST",
+            path: "/api/transfers",
+            body: b"",
+        };
+        let signature = acci_core::signing::sign_request(secret, &request);
+
+        assert_eq!(
+            check_signed_request(secret, &request, &signature, now, 60),
+            Err(SignedRequestRejection::StaleTimestamp)
+        );
+        let future = SignedRequest {
+            timestamp: now + 61,
+            ..request
+        };
+        let signature = acci_core::signing::sign_request(secret, &future);
+        assert_eq!(
+            check_signed_request(secret, &future, &signature, now, 60),
+            Err(SignedRequestRejection::StaleTimestamp)
+        );
+
+        // Extreme client timestamps are rejected without overflowing
+        for timestamp in [i64::MIN, i64::MAX] {
+            let extreme = SignedRequest {
+                timestamp,
+                ..request
+            };
+            let signature = acci_core::signing::sign_request(secret, &extreme);
+            assert_eq!(
+                check_signed_request(secret, &extreme, &signature, now, 60),
+                Err(SignedRequestRejection::StaleTimestamp)
+            );
+        }
+    }
+
+    #[test]
+    fn test_route_classes_select_mode_by_longest_prefix() {
+        use crate::security::config::ReplayRouteClass;
+
+        let config = ReplayProtectionConfig {
+            route_classes: vec![
+                ReplayRouteClass {
+                    path_prefix: "/api".to_string(),
+                    mode: ReplayProtectionMode::Signed,
+                },
+                ReplayRouteClass {
+                    path_prefix: "/api/forms".to_string(),
+                    mode: ReplayProtectionMode::NonceOnly,
+                },
+            ],
+            ..ReplayProtectionConfig::default()
+        };
+
+        assert_eq!(
+            config.mode_for("/api/transfers"),
+            ReplayProtectionMode::Signed
+        );
+        assert_eq!(
+            config.mode_for("/api/forms/contact"),
+            ReplayProtectionMode::NonceOnly
+        );
+        assert_eq!(config.mode_for("/login"), ReplayProtectionMode::NonceOnly);
+    }
+
     // Helper functions for the unit tests
 
     // Generate a test CSRF token with the given session ID and timestamp
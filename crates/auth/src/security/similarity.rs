@@ -0,0 +1,155 @@
+//! String distance helpers shared by the fingerprinting and credential stuffing
+//! detectors.
+
+/// Maximum number of characters considered per input.
+///
+/// Longer inputs are truncated, which bounds the work per comparison for
+/// attacker-controlled values such as oversized user agent headers.
+pub const MAX_LEVENSHTEIN_CHARS: usize = 1024;
+
+/// Calculate the Levenshtein distance between two strings.
+///
+/// Distances are counted in Unicode scalar values (`char`s), not bytes, and
+/// only the first [`MAX_LEVENSHTEIN_CHARS`] characters of each input are
+/// compared. Two rolling rows sized by the shorter input are kept, so memory
+/// use is `O(min(n, m))`.
+pub fn levenshtein_distance(s1: &str, s2: &str) -> usize {
+    let len1 = s1.chars().take(MAX_LEVENSHTEIN_CHARS).count();
+    let len2 = s2.chars().take(MAX_LEVENSHTEIN_CHARS).count();
+
+    // Iterate over the longer input and keep rows for the shorter one
+    let (shorter, longer) = if len1 <= len2 { (s1, s2) } else { (s2, s1) };
+    let shorter: Vec<char> = shorter.chars().take(MAX_LEVENSHTEIN_CHARS).collect();
+
+    if shorter.is_empty() {
+        return len1.max(len2);
+    }
+
+    let mut previous: Vec<usize> = (0..=shorter.len()).collect();
+    let mut current = vec![0; shorter.len() + 1];
+
+    for (i, long_char) in longer.chars().take(MAX_LEVENSHTEIN_CHARS).enumerate() {
+        current[0] = i + 1;
+
+        for (j, short_char) in shorter.iter().enumerate() {
+            let cost = usize::from(long_char != *short_char);
+
+            current[j + 1] = (previous[j + 1] + 1)
+                .min(current[j] + 1)
+                .min(previous[j] + cost);
+        }
+
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[shorter.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // Straightforward full-matrix implementation used as a reference
+    fn reference_distance(s1: &str, s2: &str) -> usize {
+        let v1: Vec<char> = s1.chars().collect();
+        let v2: Vec<char> = s2.chars().collect();
+
+        let mut matrix = vec![vec![0; v2.len() + 1]; v1.len() + 1];
+        for (i, row) in matrix.iter_mut().enumerate() {
+            row[0] = i;
+        }
+        for (j, cell) in matrix[0].iter_mut().enumerate() {
+            *cell = j;
+        }
+
+        for i in 1..=v1.len() {
+            for j in 1..=v2.len() {
+                let cost = usize::from(v1[i - 1] != v2[j - 1]);
+                matrix[i][j] = (matrix[i - 1][j] + 1)
+                    .min(matrix[i][j - 1] + 1)
+                    .min(matrix[i - 1][j - 1] + cost);
+            }
+        }
+
+        matrix[v1.len()][v2.len()]
+    }
+
+    #[test]
+    fn test_levenshtein_distance() {
+        assert_eq!(levenshtein_distance("hello", "hello"), 0);
+        assert_eq!(levenshtein_distance("hello", "hallo"), 1);
+        assert_eq!(levenshtein_distance("kitten", "sitting"), 3);
+        assert_eq!(levenshtein_distance("saturday", "sunday"), 3);
+        assert_eq!(levenshtein_distance("sunday", "saturday"), 3);
+        assert_eq!(levenshtein_distance("", ""), 0);
+        assert_eq!(levenshtein_distance("abc", ""), 3);
+        assert_eq!(levenshtein_distance("", "xyz"), 3);
+    }
+
+    #[test]
+    fn test_levenshtein_distance_multibyte() {
+        // Each of these is a single scalar value but several UTF-8 bytes
+        assert_eq!(levenshtein_distance("café", "cafe"), 1);
+        assert_eq!(levenshtein_distance("naïve", "naive"), 1);
+        assert_eq!(levenshtein_distance("日本語", "日本"), 1);
+        assert_eq!(levenshtein_distance("日本語", "中国語"), 2);
+        assert_eq!(levenshtein_distance("🔐🔑", "🔑🔐"), 2);
+        assert_eq!(levenshtein_distance("Grüße", "Grüsse"), 2);
+        assert_eq!(levenshtein_distance("", "ñandú"), 5);
+    }
+
+    #[test]
+    fn test_levenshtein_distance_caps_input_length() {
+        let long_a = "a".repeat(MAX_LEVENSHTEIN_CHARS + 5000);
+        let long_b = format!("{}{}", "a".repeat(MAX_LEVENSHTEIN_CHARS), "b".repeat(5000));
+
+        // Differences beyond the cap are not considered
+        assert_eq!(levenshtein_distance(&long_a, &long_b), 0);
+        assert_eq!(levenshtein_distance(&long_a, ""), MAX_LEVENSHTEIN_CHARS);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_identity(s in "\\PC{0,64}") {
+            prop_assert_eq!(levenshtein_distance(&s, &s), 0);
+        }
+
+        #[test]
+        fn prop_distinct_strings_have_positive_distance(a in "\\PC{0,32}", b in "\\PC{0,32}") {
+            prop_assume!(a != b);
+            prop_assert!(levenshtein_distance(&a, &b) > 0);
+        }
+
+        #[test]
+        fn prop_symmetry(a in "\\PC{0,48}", b in "\\PC{0,48}") {
+            prop_assert_eq!(levenshtein_distance(&a, &b), levenshtein_distance(&b, &a));
+        }
+
+        #[test]
+        fn prop_triangle_inequality(
+            a in "\\PC{0,24}",
+            b in "\\PC{0,24}",
+            c in "\\PC{0,24}",
+        ) {
+            let ac = levenshtein_distance(&a, &c);
+            let ab = levenshtein_distance(&a, &b);
+            let bc = levenshtein_distance(&b, &c);
+            prop_assert!(ac <= ab + bc);
+        }
+
+        #[test]
+        fn prop_bounded_by_longer_length(a in "\\PC{0,48}", b in "\\PC{0,48}") {
+            let distance = levenshtein_distance(&a, &b);
+            let (len_a, len_b) = (a.chars().count(), b.chars().count());
+            prop_assert!(distance >= len_a.abs_diff(len_b));
+            prop_assert!(distance <= len_a.max(len_b));
+        }
+
+        #[test]
+        fn prop_matches_full_matrix(a in "[a-zA-Z0-9 ./;()]{0,48}", b in "\\PC{0,48}") {
+            prop_assert_eq!(levenshtein_distance(&a, &b), reference_distance(&a, &b));
+            prop_assert_eq!(levenshtein_distance(&b, &a), reference_distance(&b, &a));
+        }
+    }
+}
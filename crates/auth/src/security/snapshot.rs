@@ -0,0 +1,358 @@
+//! Brute force state kept across restarts
+//!
+//! The brute force counters live in process memory with the memory backend
+//! and in Redis keys expiring with their window otherwise, so a rolling
+//! restart can reset them and give an attacker fresh attempts. On graceful
+//! shutdown [`SecurityProtection::persist_state`] stores the counters that
+//! reached [`StateSnapshotConfig::min_attempts`](super::config::StateSnapshotConfig)
+//! in Postgres, and [`SecurityProtection::restore_state`] imports them again
+//! on startup, dropping the attempts that left their window in between.
+//!
+//! Restoring is best effort: a missing, corrupt or stale snapshot is logged
+//! and ignored, and the counters start empty as they did before.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use thiserror::Error;
+use time::OffsetDateTime;
+use tracing::{info, warn};
+
+use super::SecurityProtection;
+use super::bruteforce::CounterSnapshot;
+use super::types::BruteForceError;
+use crate::utils::duration::DurationSecs;
+
+/// Format version of [`SecurityStateSnapshot`]
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Errors of taking, storing or restoring a snapshot
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("Snapshot is corrupt: {0}")]
+    Corrupt(#[from] serde_json::Error),
+
+    #[error("Snapshot version {0} is not supported")]
+    UnsupportedVersion(u32),
+
+    #[error("Snapshot is {age_seconds}s old, at most {max_age} is restored")]
+    Stale {
+        age_seconds: i64,
+        max_age: DurationSecs,
+    },
+
+    #[error("Failed to access brute force counters: {0}")]
+    BruteForce(#[from] BruteForceError),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Serialized state of the brute force counters
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecurityStateSnapshot {
+    /// Format version, [`SNAPSHOT_VERSION`] when taken by this build
+    pub version: u32,
+    /// When the snapshot was taken (Unix timestamp)
+    pub created_at: i64,
+    /// Counters above the significance threshold
+    pub counters: Vec<CounterSnapshot>,
+}
+
+impl SecurityStateSnapshot {
+    /// Snapshot of `counters` taken now
+    pub fn new(counters: Vec<CounterSnapshot>) -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            created_at: OffsetDateTime::now_utc().unix_timestamp(),
+            counters,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SnapshotError> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Parse a snapshot taken at most `max_age` ago
+    pub fn from_bytes(bytes: &[u8], max_age: DurationSecs) -> Result<Self, SnapshotError> {
+        let snapshot: Self = serde_json::from_slice(bytes)?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(snapshot.version));
+        }
+
+        let age_seconds = OffsetDateTime::now_utc().unix_timestamp() - snapshot.created_at;
+        if age_seconds > max_age.as_secs() as i64 {
+            return Err(SnapshotError::Stale {
+                age_seconds,
+                max_age,
+            });
+        }
+
+        Ok(snapshot)
+    }
+}
+
+/// Stores the snapshots in Postgres
+pub struct PostgresSnapshotStore {
+    pool: PgPool,
+}
+
+impl PostgresSnapshotStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Store `snapshot`, removing those older than `max_age`
+    pub async fn save(&self, snapshot: &[u8], max_age: DurationSecs) -> Result<(), SnapshotError> {
+        sqlx::query("INSERT INTO security_state_snapshots (snapshot) VALUES ($1)")
+            .bind(snapshot)
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            "DELETE FROM security_state_snapshots \
+             WHERE created_at < NOW() - make_interval(secs => $1)",
+        )
+        .bind(max_age.as_secs() as f64)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Most recently stored snapshot
+    pub async fn latest(&self) -> Result<Option<Vec<u8>>, SnapshotError> {
+        let row = sqlx::query(
+            "SELECT snapshot FROM security_state_snapshots \
+             ORDER BY created_at DESC, id DESC LIMIT 1",
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| row.get("snapshot")))
+    }
+}
+
+impl SecurityProtection {
+    /// Snapshot of the brute force counters above the significance threshold
+    pub async fn export_state(&self) -> Result<Vec<u8>, SnapshotError> {
+        let counters = self
+            .brute_force
+            .export_counters(self.config.state_snapshot.min_attempts)
+            .await?;
+        SecurityStateSnapshot::new(counters).to_bytes()
+    }
+
+    /// Restore the brute force counters of a snapshot
+    ///
+    /// Returns the number of counters restored.
+    pub async fn import_state(&self, snapshot: &[u8]) -> Result<usize, SnapshotError> {
+        let snapshot = SecurityStateSnapshot::from_bytes(
+            snapshot,
+            self.config.state_snapshot.max_age_seconds,
+        )?;
+        Ok(self.brute_force.import_counters(&snapshot.counters).await?)
+    }
+
+    /// Store the brute force counters in `store`, to be called on graceful shutdown
+    pub async fn persist_state(&self, store: &PostgresSnapshotStore) {
+        if !self.config.state_snapshot.enabled {
+            return;
+        }
+
+        let result = match self.export_state().await {
+            Ok(snapshot) => {
+                store
+                    .save(&snapshot, self.config.state_snapshot.max_age_seconds)
+                    .await
+            },
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => info!("Stored brute force state snapshot"),
+            Err(e) => warn!("Failed to store brute force state snapshot: {}", e),
+        }
+    }
+
+    /// Restore the brute force counters from `store`, to be called on startup
+    ///
+    /// A missing, corrupt or stale snapshot is logged and ignored.
+    pub async fn restore_state(&self, store: &PostgresSnapshotStore) {
+        if !self.config.state_snapshot.enabled {
+            return;
+        }
+
+        let snapshot = match store.latest().await {
+            Ok(Some(snapshot)) => snapshot,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("Failed to load brute force state snapshot: {}", e);
+                return;
+            },
+        };
+        match self.import_state(&snapshot).await {
+            Ok(restored) => info!(
+                "Restored {} brute force counters from state snapshot",
+                restored
+            ),
+            Err(e) => warn!("Ignoring brute force state snapshot: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::config::{BruteForceConfig, SecurityConfig};
+    use std::sync::Arc;
+
+    fn memory_protection() -> SecurityProtection {
+        // The memory backend never contacts Redis
+        let redis_client =
+            Arc::new(redis::Client::open("redis://127.0.0.1:1").expect("valid Redis URL"));
+        SecurityProtection::in_memory(
+            redis_client,
+            None,
+            SecurityConfig {
+                brute_force: BruteForceConfig {
+                    max_attempts: 3,
+                    ..BruteForceConfig::default()
+                },
+                ..SecurityConfig::default()
+            },
+        )
+    }
+
+    async fn lock_out(protection: &SecurityProtection, username: &str) {
+        for i in 0..3 {
+            protection
+                .brute_force
+                .record_login_attempt("tenant", username, &format!("192.0.2.{i}"), false)
+                .await
+                .expect("Failed to record attempt");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lockout_survives_restart() {
+        let protection = memory_protection();
+        lock_out(&protection, "alice").await;
+        protection
+            .brute_force
+            .record_login_attempt("tenant", "bob", "192.0.2.9", false)
+            .await
+            .expect("Failed to record attempt");
+
+        let snapshot = protection.export_state().await.expect("Failed to export");
+        let parsed = SecurityStateSnapshot::from_bytes(&snapshot, DurationSecs::from_secs(60))
+            .expect("Snapshot is valid");
+        // Counters below the threshold are left out
+        assert_eq!(parsed.counters.len(), 1);
+
+        let restarted = memory_protection();
+        assert_eq!(
+            restarted
+                .import_state(&snapshot)
+                .await
+                .expect("Failed to import"),
+            1
+        );
+
+        assert!(
+            restarted
+                .brute_force
+                .is_account_locked("tenant", "alice")
+                .await
+                .expect("locked")
+        );
+        let result = restarted
+            .brute_force
+            .check_authentication_attempt("tenant", "alice", false)
+            .await;
+        assert!(matches!(result, Err(BruteForceError::AccountLocked)));
+        assert!(
+            !restarted
+                .brute_force
+                .is_account_locked("tenant", "bob")
+                .await
+                .expect("locked")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_does_not_double_count() {
+        let protection = memory_protection();
+        lock_out(&protection, "alice").await;
+        let snapshot = protection.export_state().await.expect("Failed to export");
+
+        assert_eq!(
+            protection
+                .import_state(&snapshot)
+                .await
+                .expect("Failed to import"),
+            0
+        );
+        assert_eq!(
+            protection
+                .brute_force
+                .remaining_attempts("tenant", "alice")
+                .await
+                .expect("remaining"),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_snapshot_is_rejected() {
+        let protection = memory_protection();
+
+        let result = protection
+            .import_state(b"{\"version\":1,\"counters\"")
+            .await;
+
+        assert!(matches!(result, Err(SnapshotError::Corrupt(_))));
+    }
+
+    #[tokio::test]
+    async fn test_stale_snapshot_is_rejected() {
+        let protection = memory_protection();
+        lock_out(&protection, "alice").await;
+        let mut snapshot = SecurityStateSnapshot::from_bytes(
+            &protection.export_state().await.expect("Failed to export"),
+            DurationSecs::from_secs(60),
+        )
+        .expect("Snapshot is valid");
+        snapshot.created_at -= 2 * 3600;
+
+        let restarted = memory_protection();
+        let result = restarted
+            .import_state(&snapshot.to_bytes().expect("Failed to serialize"))
+            .await;
+
+        assert!(matches!(result, Err(SnapshotError::Stale { .. })));
+        assert!(
+            !restarted
+                .brute_force
+                .is_account_locked("tenant", "alice")
+                .await
+                .expect("locked")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_attempts_are_not_restored() {
+        let protection = memory_protection();
+        let key = crate::security::create_tenant_redis_key("tenant", "bruteforce", "alice");
+        let expired = OffsetDateTime::now_utc().unix_timestamp() - 2 * 3600;
+        let snapshot = SecurityStateSnapshot::new(vec![CounterSnapshot {
+            key,
+            attempts: vec![expired; 3],
+        }]);
+
+        let restored = protection
+            .import_state(&snapshot.to_bytes().expect("Failed to serialize"))
+            .await
+            .expect("Failed to import");
+
+        assert_eq!(restored, 0);
+    }
+}
@@ -0,0 +1,305 @@
+//! Trusted devices ("remember this device")
+//!
+//! After completing MFA a user may mark the device as trusted. The device is
+//! then handed a signed, long-lived token bound to its fingerprint, and later
+//! logins presenting the token from a device with the same fingerprint skip
+//! the MFA challenge. Trust ends when the token expires, the fingerprint no
+//! longer matches or the stored fingerprint is revoked.
+
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use thiserror::Error;
+use time::OffsetDateTime;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use super::fingerprint::FingerprintRepository;
+use crate::config::TrustedDeviceConfig;
+use crate::session::types::DeviceFingerprint;
+use crate::utils::clock::{Clock, SystemClock};
+
+/// Audience of trusted device tokens, keeps them apart from session JWTs
+const TRUSTED_DEVICE_AUDIENCE: &str = "acci:trusted-device";
+
+/// Errors of the trusted device service
+#[derive(Debug, Error)]
+pub enum TrustedDeviceError {
+    /// The session has no device fingerprint to bind the trust to
+    #[error("The session has no device fingerprint")]
+    MissingFingerprint,
+
+    /// No stored fingerprint matches the session or the given id
+    #[error("Device fingerprint not found")]
+    FingerprintNotFound,
+
+    /// Signing the token failed
+    #[error("Failed to create trusted device token: {0}")]
+    TokenCreation(String),
+
+    /// Reading or updating the stored fingerprints failed
+    #[error("Fingerprint repository error: {0}")]
+    Repository(#[from] anyhow::Error),
+}
+
+/// Claims of a trusted device token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TrustedDeviceClaims {
+    /// User the device is trusted for
+    sub: Uuid,
+    /// Tenant the trust applies to
+    tid: Uuid,
+    /// Stored fingerprint that was marked as trusted
+    fid: Uuid,
+    /// Hash of the device fingerprint the token is bound to
+    fph: String,
+    aud: String,
+    iat: i64,
+    exp: i64,
+}
+
+/// A device that was just marked as trusted
+#[derive(Debug, Clone)]
+pub struct TrustedDevice {
+    /// Stored fingerprint of the device, used to revoke the trust
+    pub fingerprint_id: Uuid,
+    /// Token the client presents on later logins
+    pub token: String,
+    /// When the device has to complete MFA again
+    pub expires_at: OffsetDateTime,
+}
+
+/// Issues and checks trusted device tokens
+///
+/// Unlike the other clock-aware services, the clock is held as a trait object,
+/// so a [`UserService`](crate::services::user::UserService) can hold the
+/// service with any clock.
+pub struct TrustedDeviceService {
+    repository: Arc<dyn FingerprintRepository>,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    config: TrustedDeviceConfig,
+    clock: Arc<dyn Clock>,
+}
+
+impl TrustedDeviceService {
+    /// Create a new trusted device service signing its tokens with `secret`
+    pub fn new(
+        repository: Arc<dyn FingerprintRepository>,
+        secret: &[u8],
+        config: TrustedDeviceConfig,
+    ) -> Self {
+        Self::with_clock(repository, secret, config, SystemClock)
+    }
+
+    /// Create a trusted device service that reads the current time from `clock`
+    pub fn with_clock(
+        repository: Arc<dyn FingerprintRepository>,
+        secret: &[u8],
+        config: TrustedDeviceConfig,
+        clock: impl Clock,
+    ) -> Self {
+        Self {
+            repository,
+            encoding_key: EncodingKey::from_secret(secret),
+            decoding_key: DecodingKey::from_secret(secret),
+            config,
+            clock: Arc::new(clock),
+        }
+    }
+
+    /// Mark the device a session was created on as trusted and issue its token
+    ///
+    /// The caller is responsible for checking that the session completed MFA.
+    pub async fn trust_device(
+        &self,
+        tenant_id: Uuid,
+        user_id: Uuid,
+        session_id: Uuid,
+        device: Option<&DeviceFingerprint>,
+    ) -> Result<TrustedDevice, TrustedDeviceError> {
+        let device = device.ok_or(TrustedDeviceError::MissingFingerprint)?;
+        let stored = self
+            .repository
+            .get_fingerprints_for_user(tenant_id, user_id)
+            .await?
+            .into_iter()
+            .find(|stored| stored.session_id == Some(session_id))
+            .ok_or(TrustedDeviceError::FingerprintNotFound)?;
+
+        self.repository.mark_as_trusted(stored.id, true).await?;
+
+        let now = self.clock.now();
+        let expires_at = now + self.config.trust_lifetime_secs.to_time();
+        let claims = TrustedDeviceClaims {
+            sub: user_id,
+            tid: tenant_id,
+            fid: stored.id,
+            fph: device_fingerprint_hash(device),
+            aud: TRUSTED_DEVICE_AUDIENCE.to_string(),
+            iat: now.unix_timestamp(),
+            exp: expires_at.unix_timestamp(),
+        };
+        let token = encode(&Header::default(), &claims, &self.encoding_key)
+            .map_err(|e| TrustedDeviceError::TokenCreation(e.to_string()))?;
+
+        info!(
+            user_id = %user_id,
+            tenant_id = %tenant_id,
+            fingerprint_id = %stored.id,
+            "Marked device as trusted"
+        );
+
+        Ok(TrustedDevice {
+            fingerprint_id: stored.id,
+            token,
+            expires_at,
+        })
+    }
+
+    /// Whether `token` vouches for the device a login is coming from
+    ///
+    /// Invalid or expired tokens, tokens of another user or tenant, a changed
+    /// fingerprint and revoked trust all yield `false`.
+    pub async fn is_trusted(
+        &self,
+        token: &str,
+        tenant_id: Uuid,
+        user_id: Uuid,
+        device: Option<&DeviceFingerprint>,
+    ) -> Result<bool, TrustedDeviceError> {
+        let Some(claims) = self.decode(token) else {
+            return Ok(false);
+        };
+        if claims.exp <= self.clock.now().unix_timestamp() {
+            debug!(user_id = %user_id, "Trusted device token has expired");
+            return Ok(false);
+        }
+        if claims.sub != user_id || claims.tid != tenant_id {
+            return Ok(false);
+        }
+        if device.map(device_fingerprint_hash).as_deref() != Some(claims.fph.as_str()) {
+            debug!(user_id = %user_id, "Device fingerprint no longer matches the trusted device");
+            return Ok(false);
+        }
+
+        Ok(self
+            .repository
+            .get_fingerprints_for_user(tenant_id, user_id)
+            .await?
+            .iter()
+            .any(|stored| stored.id == claims.fid && stored.trusted))
+    }
+
+    /// Revoke the trust in a device of the user
+    ///
+    /// Tokens issued for the device stop skipping MFA immediately.
+    pub async fn revoke(
+        &self,
+        tenant_id: Uuid,
+        user_id: Uuid,
+        fingerprint_id: Uuid,
+    ) -> Result<(), TrustedDeviceError> {
+        let owned = self
+            .repository
+            .get_fingerprints_for_user(tenant_id, user_id)
+            .await?
+            .iter()
+            .any(|stored| stored.id == fingerprint_id);
+        if !owned {
+            return Err(TrustedDeviceError::FingerprintNotFound);
+        }
+
+        self.repository
+            .mark_as_trusted(fingerprint_id, false)
+            .await?;
+
+        info!(
+            user_id = %user_id,
+            tenant_id = %tenant_id,
+            fingerprint_id = %fingerprint_id,
+            "Revoked trusted device"
+        );
+        Ok(())
+    }
+
+    /// Revoke the trust in all devices of the user, returning how many were trusted
+    pub async fn revoke_all(
+        &self,
+        tenant_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<u64, TrustedDeviceError> {
+        let mut revoked = 0;
+        for stored in self
+            .repository
+            .get_fingerprints_for_user(tenant_id, user_id)
+            .await?
+            .into_iter()
+            .filter(|stored| stored.trusted)
+        {
+            self.repository.mark_as_trusted(stored.id, false).await?;
+            revoked += 1;
+        }
+
+        info!(
+            user_id = %user_id,
+            tenant_id = %tenant_id,
+            revoked,
+            "Revoked all trusted devices"
+        );
+        Ok(revoked)
+    }
+
+    fn decode(&self, token: &str) -> Option<TrustedDeviceClaims> {
+        // Expiry is checked against the service clock
+        let mut validation = Validation::default();
+        validation.validate_exp = false;
+        validation.set_audience(&[TRUSTED_DEVICE_AUDIENCE]);
+
+        decode::<TrustedDeviceClaims>(token, &self.decoding_key, &validation)
+            .map(|data| data.claims)
+            .ok()
+    }
+}
+
+/// Hex-encoded SHA-256 hash over the stable attributes of a device fingerprint
+pub(crate) fn device_fingerprint_hash(device: &DeviceFingerprint) -> String {
+    let mut hasher = Sha256::new();
+    for attribute in [
+        Some(device.user_agent_hash.clone()),
+        device.platform.clone(),
+        device.browser.clone(),
+        device.screen_resolution.clone(),
+        device.color_depth.map(|depth| depth.to_string()),
+        device.timezone.clone(),
+        device.language.clone(),
+        device.hardware_concurrency.map(|cores| cores.to_string()),
+    ] {
+        hasher.update(attribute.unwrap_or_default().as_bytes());
+        hasher.update([0]);
+    }
+    hex::encode(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_hash_tracks_attribute_changes() {
+        let device =
+            DeviceFingerprint::new("ua-hash".to_string()).with_platform("Linux x86_64".to_string());
+        let mut moved = device.clone();
+        moved.timezone = Some("America/New_York".to_string());
+
+        assert_eq!(
+            device_fingerprint_hash(&device),
+            device_fingerprint_hash(&device.clone())
+        );
+        assert_ne!(
+            device_fingerprint_hash(&device),
+            device_fingerprint_hash(&moved)
+        );
+    }
+}
@@ -41,6 +41,9 @@ pub enum Challenge {
     MfaRequired,
     /// Block IP address for specified duration
     IpBlock(Duration),
+    /// Reject the attempt because it cannot be assessed, e.g. while Redis is
+    /// unavailable for a tenant whose policy is to fail closed
+    Reject,
 }
 
 /// CAPTCHA challenge details
@@ -143,6 +146,9 @@ pub enum BruteForceError {
     #[error("Progressive delay required: {0}ms")]
     ProgressiveDelay(u32),
 
+    #[error("Login blocked: brute force protection is unavailable")]
+    Unavailable,
+
     #[error("Redis operation failed: {0}")]
     Redis(#[from] redis::RedisError),
 
@@ -0,0 +1,317 @@
+//! Verification of inbound webhooks
+//!
+//! Providers calling back into the framework, e.g. with payment states or
+//! delivery receipts, sign each delivery as described in
+//! [`acci_core::signing`]. [`verify_webhook`] checks the signature over the
+//! raw body and the age of the timestamp; [`WebhookReceiver`] additionally
+//! records every accepted delivery in the [`NonceStore`], so a captured
+//! delivery cannot be replayed while its timestamp is still fresh.
+
+use acci_core::signing::{
+    WEBHOOK_SIGNATURE_HEADER, WEBHOOK_TIMESTAMP_HEADER, verify_webhook_signature,
+};
+use axum::http::{HeaderMap, StatusCode};
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+
+use super::replay::NonceStore;
+
+/// How far the timestamp of a delivery may be from the current time
+pub const DEFAULT_WEBHOOK_TOLERANCE: Duration = Duration::from_secs(300);
+
+/// Why a webhook delivery was rejected
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum WebhookError {
+    #[error("Webhook signature or timestamp header is missing or malformed")]
+    MissingHeaders,
+    #[error("Webhook timestamp is outside the allowed window")]
+    StaleTimestamp,
+    #[error("Webhook signature does not match the delivery")]
+    InvalidSignature,
+    #[error("Webhook delivery was already received")]
+    Replayed,
+    #[error("Failed to record webhook delivery: {0}")]
+    NonceStore(String),
+}
+
+impl WebhookError {
+    /// Status code to answer the sender with
+    pub fn status(&self) -> StatusCode {
+        match self {
+            WebhookError::MissingHeaders => StatusCode::BAD_REQUEST,
+            WebhookError::StaleTimestamp | WebhookError::InvalidSignature => {
+                StatusCode::UNAUTHORIZED
+            },
+            WebhookError::Replayed => StatusCode::CONFLICT,
+            WebhookError::NonceStore(_) => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+}
+
+/// Verify the signature and timestamp of a webhook delivery
+///
+/// `body` must be the raw request body exactly as received, not JSON that
+/// was parsed and serialized again. Replays within the timestamp window are
+/// only rejected by a [`WebhookReceiver`].
+pub fn verify_webhook(secret: &[u8], headers: &HeaderMap, body: &[u8]) -> Result<(), WebhookError> {
+    verify_webhook_at(
+        secret,
+        headers,
+        body,
+        Utc::now().timestamp(),
+        DEFAULT_WEBHOOK_TOLERANCE,
+    )
+    .map(|_| ())
+}
+
+/// Verify a delivery at `now`, returning its timestamp
+fn verify_webhook_at(
+    secret: &[u8],
+    headers: &HeaderMap,
+    body: &[u8],
+    now: i64,
+    tolerance: Duration,
+) -> Result<i64, WebhookError> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let (Some(signature), Some(timestamp)) = (
+        header(WEBHOOK_SIGNATURE_HEADER),
+        header(WEBHOOK_TIMESTAMP_HEADER).and_then(|value| value.trim().parse::<i64>().ok()),
+    ) else {
+        return Err(WebhookError::MissingHeaders);
+    };
+
+    if now.abs_diff(timestamp) > tolerance.as_secs() {
+        return Err(WebhookError::StaleTimestamp);
+    }
+    if !verify_webhook_signature(secret, timestamp, body, signature) {
+        return Err(WebhookError::InvalidSignature);
+    }
+    Ok(timestamp)
+}
+
+/// Receiver of the webhooks of one sender, rejecting replayed deliveries
+///
+/// A delivery is identified by its timestamp and body, which its signature
+/// covers, and is remembered for twice the tolerance: the longest time its
+/// timestamp is accepted. The signature header itself is not part of the
+/// identity, as further or reordered signatures in it still verify.
+pub struct WebhookReceiver {
+    /// Name of the sender, separating its deliveries in the nonce store
+    source: String,
+    nonce_store: Arc<NonceStore>,
+    tolerance: Duration,
+}
+
+impl WebhookReceiver {
+    /// Create a receiver for the webhooks of `source`, e.g. `"payments"`
+    pub fn new(source: impl Into<String>, nonce_store: Arc<NonceStore>) -> Self {
+        Self {
+            source: source.into(),
+            nonce_store,
+            tolerance: DEFAULT_WEBHOOK_TOLERANCE,
+        }
+    }
+
+    /// Accept timestamps up to `tolerance` away from the current time
+    pub fn with_tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Verify a delivery and record it, so it is accepted only once
+    pub async fn receive(
+        &self,
+        secret: &[u8],
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<(), WebhookError> {
+        self.receive_at(secret, headers, body, Utc::now().timestamp())
+            .await
+    }
+
+    async fn receive_at(
+        &self,
+        secret: &[u8],
+        headers: &HeaderMap,
+        body: &[u8],
+        now: i64,
+    ) -> Result<(), WebhookError> {
+        let timestamp = verify_webhook_at(secret, headers, body, now, self.tolerance)?;
+
+        let mut digest = Sha256::new();
+        digest.update(timestamp.to_be_bytes());
+        digest.update(body);
+        let delivery = hex::encode(digest.finalize());
+        let recorded = self
+            .nonce_store
+            .record_once(
+                &self.source,
+                "webhook",
+                &delivery,
+                self.tolerance.as_secs() * 2,
+            )
+            .await
+            .map_err(|e| WebhookError::NonceStore(e.to_string()))?;
+        if !recorded {
+            return Err(WebhookError::Replayed);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::config::{
+        RedisAvailabilityConfig, RedisUnavailablePolicy, ReplayProtectionConfig,
+    };
+    use crate::security::redis_circuit::RedisCircuit;
+    use acci_core::signing::sign_webhook;
+    use axum::http::HeaderValue;
+
+    const SECRET: &[u8] = b"webhook-secret";
+    const NOW: i64 = 1_700_000_000;
+
+    fn headers(timestamp: i64, body: &[u8]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            WEBHOOK_TIMESTAMP_HEADER,
+            HeaderValue::from_str(&timestamp.to_string()).expect("valid header"),
+        );
+        headers.insert(
+            WEBHOOK_SIGNATURE_HEADER,
+            HeaderValue::from_str(&sign_webhook(SECRET, timestamp, body)).expect("valid header"),
+        );
+        headers
+    }
+
+    /// Nonce store keeping nonces in memory, as Redis is unreachable
+    fn nonce_store() -> Arc<NonceStore> {
+        let client = Arc::new(redis::Client::open("redis://127.0.0.1:1").expect("valid Redis URL"));
+        let circuit = Arc::new(RedisCircuit::new(
+            client.clone(),
+            RedisAvailabilityConfig {
+                policy: RedisUnavailablePolicy::Degrade,
+                ..RedisAvailabilityConfig::default()
+            },
+        ));
+        Arc::new(
+            NonceStore::new(client, ReplayProtectionConfig::default()).with_redis_circuit(circuit),
+        )
+    }
+
+    #[test]
+    fn test_valid_signature_over_raw_body_passes() {
+        let body = br#"{"payment_id": "pay_123", "status": "paid"}"#;
+        let headers = headers(NOW, body);
+
+        assert_eq!(
+            verify_webhook_at(SECRET, &headers, body, NOW + 10, DEFAULT_WEBHOOK_TOLERANCE)
+                .map(|_| ()),
+            Ok(())
+        );
+
+        // The same JSON serialized again no longer matches the signature
+        let reserialized = serde_json::to_vec(
+            &serde_json::from_slice::<serde_json::Value>(body).expect("valid JSON"),
+        )
+        .expect("serializes");
+        assert_ne!(reserialized, body);
+        assert_eq!(
+            verify_webhook_at(
+                SECRET,
+                &headers,
+                &reserialized,
+                NOW,
+                DEFAULT_WEBHOOK_TOLERANCE
+            ),
+            Err(WebhookError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_modified_body_and_stale_timestamp_fail() {
+        let body = br#"{"status":"paid"}"#;
+        let headers = headers(NOW, body);
+
+        assert_eq!(
+            verify_webhook_at(
+                SECRET,
+                &headers,
+                br#"{"status":"refunded"}"#,
+                NOW,
+                DEFAULT_WEBHOOK_TOLERANCE
+            ),
+            Err(WebhookError::InvalidSignature)
+        );
+        assert_eq!(
+            verify_webhook_at(SECRET, &headers, body, NOW + 301, DEFAULT_WEBHOOK_TOLERANCE),
+            Err(WebhookError::StaleTimestamp)
+        );
+        assert_eq!(
+            verify_webhook_at(
+                SECRET,
+                &HeaderMap::new(),
+                body,
+                NOW,
+                DEFAULT_WEBHOOK_TOLERANCE
+            ),
+            Err(WebhookError::MissingHeaders)
+        );
+        assert_eq!(
+            verify_webhook(SECRET, &headers, body),
+            Err(WebhookError::StaleTimestamp)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_receiver_rejects_replayed_delivery() {
+        let receiver = WebhookReceiver::new("payments", nonce_store());
+        let body = br#"{"status":"paid"}"#;
+        let headers = headers(NOW, body);
+
+        assert_eq!(
+            receiver.receive_at(SECRET, &headers, body, NOW).await,
+            Ok(())
+        );
+        assert_eq!(
+            receiver.receive_at(SECRET, &headers, body, NOW + 5).await,
+            Err(WebhookError::Replayed)
+        );
+
+        // Further or reordered signatures do not make the delivery new
+        let signature = sign_webhook(SECRET, NOW, body);
+        for header in [
+            format!("{},v1=00", signature),
+            format!("v1=00, {}", signature),
+        ] {
+            let mut modified = headers.clone();
+            modified.insert(
+                WEBHOOK_SIGNATURE_HEADER,
+                HeaderValue::from_str(&header).expect("valid header"),
+            );
+            assert_eq!(
+                receiver.receive_at(SECRET, &modified, body, NOW + 5).await,
+                Err(WebhookError::Replayed)
+            );
+        }
+
+        // A new delivery of the same body carries a new timestamp
+        let redelivery = self::headers(NOW + 60, body);
+        assert_eq!(
+            receiver
+                .receive_at(SECRET, &redelivery, body, NOW + 60)
+                .await,
+            Ok(())
+        );
+        assert_eq!(
+            receiver
+                .receive_at(SECRET, &headers, br#"{"status":"void"}"#, NOW)
+                .await,
+            Err(WebhookError::InvalidSignature)
+        );
+    }
+}
@@ -0,0 +1,444 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use thiserror::Error;
+use time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use crate::models::{
+    VerificationType,
+    account_deletion::AccountDeletionRequest,
+    tenant::{TenantError, TenantRepository},
+    user::{User, UserError, UserRepository},
+};
+use crate::repository::{AccountDeletionRepository, RepositoryError};
+use crate::services::message_provider::{Message, MessageProvider};
+use crate::services::session::{SessionService, SessionServiceError};
+use crate::session::types::SessionInvalidationReason;
+use crate::utils::clock::{Clock, SystemClock};
+use crate::utils::password::{NO_PASSWORD_HASH, PasswordError, verify_password};
+
+/// Warning returned with logins of accounts scheduled for deletion
+pub const ACCOUNT_PENDING_DELETION: &str = "ACCOUNT_PENDING_DELETION";
+
+/// Domain of the placeholder email addresses of deleted accounts
+const DELETED_EMAIL_DOMAIN: &str = "deleted.invalid";
+
+/// Configuration of self-service account deletion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccountDeletionConfig {
+    /// Days between the request and the actual deletion
+    pub grace_period_days: i64,
+    /// Page where the user can cancel the deletion, linked from the confirmation email
+    pub cancel_url: String,
+    /// How often the deletion job looks for expired grace periods (in seconds)
+    pub poll_interval_secs: u64,
+    /// Maximum number of accounts handled per run of the deletion job
+    pub batch_size: u32,
+}
+
+impl Default for AccountDeletionConfig {
+    fn default() -> Self {
+        Self {
+            grace_period_days: 14,
+            cancel_url: "/account/delete/cancel".to_string(),
+            poll_interval_secs: 3600, // 1 hour
+            batch_size: 100,
+        }
+    }
+}
+
+/// Errors of self-service account deletion
+#[derive(Debug, Error)]
+pub enum AccountDeletionError {
+    #[error("Invalid credentials")]
+    InvalidCredentials,
+
+    #[error("User not found")]
+    UserNotFound,
+
+    #[error("Account deletion has already been requested")]
+    AlreadyPending,
+
+    /// No deletion is pending, or its grace period is over
+    #[error("No pending account deletion")]
+    NotPending,
+
+    #[error(transparent)]
+    Password(#[from] PasswordError),
+
+    #[error(transparent)]
+    User(#[from] UserError),
+
+    #[error("Tenant error: {0}")]
+    Tenant(#[from] TenantError),
+
+    #[error("Session error: {0}")]
+    Session(#[from] SessionServiceError),
+
+    #[error(transparent)]
+    Repository(#[from] RepositoryError),
+}
+
+/// How the user confirmed the deletion request
+#[derive(Debug, Clone)]
+pub enum DeletionConfirmation {
+    /// The current password, checked by the service
+    Password(String),
+    /// The session completed a second factor within the re-authentication
+    /// window; the caller has checked this
+    RecentSecondFactor,
+}
+
+/// Outcome counts of a single run of the deletion job
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeletionReport {
+    /// Accounts anonymized
+    pub deleted: usize,
+    /// Accounts kept because the user is the last admin of a tenant
+    pub blocked: usize,
+    /// Accounts whose deletion failed and is retried on the next run
+    pub failed: usize,
+}
+
+/// Handles users deleting their own account
+///
+/// A request marks the account as pending deletion for the configured grace
+/// period. The user can still sign in and cancel during that time; afterwards
+/// [`AccountDeletionService::process_due`] anonymizes the account. Users who
+/// are the last admin of a tenant are skipped and asked to transfer the
+/// ownership first.
+pub struct AccountDeletionService<C: Clock = SystemClock> {
+    repository: Arc<dyn AccountDeletionRepository>,
+    user_repository: Arc<dyn UserRepository>,
+    session_service: Arc<SessionService>,
+    tenant_repository: Option<Arc<dyn TenantRepository>>,
+    email_provider: Option<Arc<dyn MessageProvider>>,
+    config: AccountDeletionConfig,
+    clock: C,
+}
+
+impl AccountDeletionService {
+    /// Create a new account deletion service
+    pub fn new(
+        repository: Arc<dyn AccountDeletionRepository>,
+        user_repository: Arc<dyn UserRepository>,
+        session_service: Arc<SessionService>,
+        config: AccountDeletionConfig,
+    ) -> Self {
+        Self::with_clock(
+            repository,
+            user_repository,
+            session_service,
+            config,
+            SystemClock,
+        )
+    }
+}
+
+impl<C: Clock> AccountDeletionService<C> {
+    /// Create an account deletion service that reads the current time from `clock`
+    pub fn with_clock(
+        repository: Arc<dyn AccountDeletionRepository>,
+        user_repository: Arc<dyn UserRepository>,
+        session_service: Arc<SessionService>,
+        config: AccountDeletionConfig,
+        clock: C,
+    ) -> Self {
+        Self {
+            repository,
+            user_repository,
+            session_service,
+            tenant_repository: None,
+            email_provider: None,
+            config,
+            clock,
+        }
+    }
+
+    /// Enables the last-admin check and removes tenant memberships on deletion
+    pub fn with_tenant_repository(mut self, tenant_repository: Arc<dyn TenantRepository>) -> Self {
+        self.tenant_repository = Some(tenant_repository);
+        self
+    }
+
+    /// Enables the confirmation and ownership transfer emails
+    pub fn with_email_provider(mut self, email_provider: Arc<dyn MessageProvider>) -> Self {
+        self.email_provider = Some(email_provider);
+        self
+    }
+
+    /// Schedule the deletion of a user's own account
+    ///
+    /// Every session of the user except `session_id` is ended right away, and
+    /// the user receives an email with a link to cancel the deletion.
+    pub async fn request_deletion(
+        &self,
+        user_id: Uuid,
+        session_id: Uuid,
+        confirmation: DeletionConfirmation,
+    ) -> Result<AccountDeletionRequest, AccountDeletionError> {
+        let user = self
+            .user_repository
+            .find_by_id(user_id)
+            .await?
+            .filter(|user| user.is_active)
+            .ok_or(AccountDeletionError::UserNotFound)?;
+
+        let confirmed = match &confirmation {
+            DeletionConfirmation::Password(password) => {
+                user.has_password() && verify_password(password, &user.password_hash)?
+            },
+            DeletionConfirmation::RecentSecondFactor => true,
+        };
+        if !confirmed {
+            return Err(AccountDeletionError::InvalidCredentials);
+        }
+
+        if self.repository.find_pending(user_id).await?.is_some() {
+            return Err(AccountDeletionError::AlreadyPending);
+        }
+
+        let now = self.clock.now();
+        let request = AccountDeletionRequest::new(
+            user_id,
+            now,
+            now + Duration::days(self.config.grace_period_days),
+        );
+        self.repository
+            .create(&request)
+            .await
+            .map_err(|e| match e {
+                RepositoryError::UniqueViolation(_) => AccountDeletionError::AlreadyPending,
+                e => AccountDeletionError::Repository(e),
+            })?;
+
+        self.session_service
+            .terminate_other_user_sessions(
+                user_id,
+                session_id,
+                SessionInvalidationReason::ManualInvalidation,
+            )
+            .await?;
+
+        self.send_email(
+            &user,
+            "Your account will be deleted",
+            format!(
+                "Your account is scheduled for deletion on {}. Until then you can still sign \
+                 in and cancel the deletion at {}",
+                request.scheduled_for.date(),
+                self.config.cancel_url
+            ),
+        )
+        .await;
+
+        info!(
+            user_id = %user_id,
+            scheduled_for = %request.scheduled_for,
+            "Account deletion requested"
+        );
+        Ok(request)
+    }
+
+    /// Cancel the pending deletion of a user's own account
+    ///
+    /// Fails with `NotPending` once the grace period is over, even if the
+    /// deletion job has not run yet.
+    pub async fn cancel_deletion(&self, user_id: Uuid) -> Result<(), AccountDeletionError> {
+        let in_grace = self
+            .repository
+            .find_pending(user_id)
+            .await?
+            .is_some_and(|request| !request.is_due(self.clock.now()));
+        if !in_grace || !self.repository.cancel(user_id).await? {
+            return Err(AccountDeletionError::NotPending);
+        }
+
+        info!(user_id = %user_id, "Account deletion cancelled");
+        Ok(())
+    }
+
+    /// The pending deletion of a user, if any
+    pub async fn pending_deletion(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Option<AccountDeletionRequest>, AccountDeletionError> {
+        Ok(self.repository.find_pending(user_id).await?)
+    }
+
+    /// Delete all accounts whose grace period has ended
+    ///
+    /// Failures are logged and counted; the affected accounts are picked up
+    /// again on the next run.
+    pub async fn process_due(&self) -> Result<DeletionReport, AccountDeletionError> {
+        let due = self
+            .repository
+            .list_due(self.clock.now(), self.config.batch_size)
+            .await?;
+
+        let mut report = DeletionReport::default();
+        for request in due {
+            match self.process_request(&request).await {
+                Ok(true) => report.deleted += 1,
+                Ok(false) => report.blocked += 1,
+                Err(err) => {
+                    warn!(user_id = %request.user_id, error = %err, "Failed to delete account");
+                    report.failed += 1;
+                },
+            }
+        }
+        Ok(report)
+    }
+
+    /// Delete the account of `request`; returns `false` if it is blocked
+    async fn process_request(
+        &self,
+        request: &AccountDeletionRequest,
+    ) -> Result<bool, AccountDeletionError> {
+        let user = self.user_repository.find_by_id(request.user_id).await?;
+
+        let owned_tenants = self.last_admin_tenants(request.user_id).await?;
+        if !owned_tenants.is_empty() {
+            debug!(
+                user_id = %request.user_id,
+                tenants = owned_tenants.len(),
+                "Account deletion blocked by tenant ownership"
+            );
+            if let Some(user) = user
+                .as_ref()
+                .filter(|_| request.ownership_notice_sent_at.is_none())
+            {
+                self.send_email(
+                    user,
+                    "Transfer tenant ownership to delete your account",
+                    format!(
+                        "Your account could not be deleted because you are the last admin of \
+                         {} tenant(s). Make another member an admin, and your account will be \
+                         deleted on the next run.",
+                        owned_tenants.len()
+                    ),
+                )
+                .await;
+                self.repository
+                    .record_ownership_notice(request.user_id, self.clock.now())
+                    .await?;
+            }
+            return Ok(false);
+        }
+
+        if let Some(user) = user {
+            self.anonymize(user).await?;
+        }
+        self.repository
+            .complete(request.user_id, self.clock.now())
+            .await?;
+
+        info!(user_id = %request.user_id, "Account deleted");
+        Ok(true)
+    }
+
+    /// Strip the personal data from an account and sign it out everywhere
+    async fn anonymize(&self, user: User) -> Result<(), AccountDeletionError> {
+        let user_id = user.id;
+        self.session_service
+            .force_terminate_user_sessions(user_id, SessionInvalidationReason::ManualInvalidation)
+            .await?;
+
+        if let Some(tenant_repository) = &self.tenant_repository {
+            for membership in tenant_repository.get_user_tenants(user_id).await? {
+                tenant_repository
+                    .remove_user_from_tenant(membership.tenant_id, user_id)
+                    .await?;
+            }
+        }
+
+        self.user_repository
+            .update(&User {
+                email: format!("deleted-{}@{}", user_id, DELETED_EMAIL_DOMAIN),
+                password_hash: NO_PASSWORD_HASH.to_string(),
+                display_name: "Deleted user".to_string(),
+                updated_at: self.clock.now(),
+                last_login: None,
+                is_active: false,
+                is_verified: false,
+                ..user
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Tenants where the user is the only active admin
+    async fn last_admin_tenants(&self, user_id: Uuid) -> Result<Vec<Uuid>, AccountDeletionError> {
+        let Some(tenant_repository) = &self.tenant_repository else {
+            return Ok(Vec::new());
+        };
+
+        let mut tenants = Vec::new();
+        for membership in tenant_repository.get_user_tenants(user_id).await? {
+            if !membership.is_active || !is_admin(&membership.tenant_role) {
+                continue;
+            }
+            let other_admins = tenant_repository
+                .get_tenant_users(membership.tenant_id)
+                .await?
+                .iter()
+                .any(|u| u.user_id != user_id && u.is_active && is_admin(&u.tenant_role));
+            if !other_admins {
+                tenants.push(membership.tenant_id);
+            }
+        }
+        Ok(tenants)
+    }
+
+    /// Send an email to the user; failures are logged but do not fail the operation
+    async fn send_email(&self, user: &User, subject: &str, body: String) {
+        let Some(email_provider) = &self.email_provider else {
+            return;
+        };
+
+        let message = Message {
+            tenant_id: Uuid::nil(),
+            user_id: user.id,
+            recipient: user.email.clone(),
+            subject: Some(subject.to_string()),
+            body,
+            message_type: VerificationType::Email,
+        };
+        if let Err(err) = email_provider.send_message(message).await {
+            warn!(user_id = %user.id, error = %err, "Failed to send account deletion email");
+        }
+    }
+
+    /// Run the deletion job on a dedicated task until the runtime shuts down
+    pub fn spawn(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(StdDuration::from_secs(self.config.poll_interval_secs));
+            info!("Account deletion job started");
+
+            loop {
+                interval.tick().await;
+                match self.process_due().await {
+                    Ok(report) if report != DeletionReport::default() => {
+                        info!(
+                            deleted = report.deleted,
+                            blocked = report.blocked,
+                            failed = report.failed,
+                            "Processed due account deletions"
+                        );
+                    },
+                    Ok(_) => {},
+                    Err(e) => error!("Account deletion job failed: {}", e),
+                }
+            }
+        })
+    }
+}
+
+fn is_admin(role: &str) -> bool {
+    role.eq_ignore_ascii_case("ADMIN")
+}
@@ -0,0 +1,461 @@
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use thiserror::Error;
+use time::Duration;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::models::api_key::{ApiKey, ApiKeyFilter, IssuedApiKey, RotatedApiKey};
+use crate::models::tenant::TenantError;
+use crate::models::user::UserError;
+use crate::repository::{ApiKeyRepository, RepositoryError};
+use crate::utils::clock::{Clock, SystemClock};
+
+/// Scheme of the `Authorization` header carrying an API key
+pub const API_KEY_AUTH_SCHEME: &str = "ApiKey";
+
+/// Marker at the start of every key, so leaked keys are easy to recognize
+const KEY_MARKER: &str = "acci";
+
+/// Random bytes of the lookup prefix
+const PREFIX_BYTES: usize = 6;
+
+/// Random bytes of the secret part of a key
+const SECRET_BYTES: usize = 32;
+
+/// Maximum length of a key name
+const MAX_NAME_LENGTH: usize = 100;
+
+/// Maximum length of a key description
+const MAX_DESCRIPTION_LENGTH: usize = 500;
+
+/// Maximum number of tags of a key
+const MAX_TAGS: usize = 20;
+
+/// Minimum time between two writes of the last use of a key
+pub const LAST_USE_RECORD_INTERVAL: Duration = Duration::minutes(1);
+
+/// How long a rotated key stays valid by default
+pub const DEFAULT_ROTATION_OVERLAP: Duration = Duration::hours(24);
+
+/// Errors of API key management and authentication
+#[derive(Debug, Error)]
+pub enum ApiKeyError {
+    /// The key is malformed, unknown, revoked or expired
+    #[error("Invalid API key")]
+    InvalidKey,
+
+    #[error("API key not found")]
+    NotFound,
+
+    #[error("Invalid scope: {0}")]
+    InvalidScope(String),
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error(transparent)]
+    Repository(#[from] RepositoryError),
+
+    #[error("Tenant error: {0}")]
+    Tenant(#[from] TenantError),
+
+    #[error(transparent)]
+    User(#[from] UserError),
+}
+
+impl From<ApiKeyError> for acci_core::Error {
+    fn from(err: ApiKeyError) -> Self {
+        use acci_core::error::ErrorKind;
+
+        let kind = match err {
+            ApiKeyError::Tenant(e) => return e.into(),
+            ApiKeyError::User(e) => return e.into(),
+            ApiKeyError::InvalidKey => ErrorKind::Unauthenticated,
+            ApiKeyError::NotFound => ErrorKind::NotFound,
+            ApiKeyError::InvalidScope(_) | ApiKeyError::InvalidInput(_) => ErrorKind::InvalidInput,
+            ApiKeyError::Repository(_) => ErrorKind::Internal,
+        };
+        Self::domain(kind, err.to_string())
+    }
+}
+
+/// Options for issuing an API key
+#[derive(Debug, Clone, Default)]
+pub struct IssueApiKey {
+    pub name: String,
+    pub description: Option<String>,
+    /// Labels to find the key by; stored in lowercase
+    pub tags: Vec<String>,
+    pub scopes: Vec<String>,
+    /// Lifetime of the key; keys without one are valid until revoked
+    pub expires_in: Option<Duration>,
+    /// User issuing the key
+    pub created_by: Option<Uuid>,
+}
+
+/// Criteria of [`ApiKeyService::list`]; unset criteria match every key
+#[derive(Debug, Clone, Default)]
+pub struct ApiKeyQuery {
+    /// Keys carrying all of these tags
+    pub tags: Vec<String>,
+    /// Text contained in the name, description or prefix, ignoring case
+    pub search: Option<String>,
+    /// Valid keys expiring within this time
+    pub expiring_within: Option<Duration>,
+    /// Valid keys not used for this time
+    pub unused_for: Option<Duration>,
+}
+
+/// Issues, rotates, revokes and authenticates tenant API keys
+///
+/// Keys have the form `acci_<prefix>_<secret>`. The prefix locates the stored
+/// key, and the SHA-256 digest of the whole key must match the stored hash.
+/// Every authentication reads the key from the repository, so a revocation
+/// takes effect on the next request.
+pub struct ApiKeyService<C: Clock = SystemClock> {
+    repository: Arc<dyn ApiKeyRepository>,
+    rotation_overlap: Duration,
+    clock: C,
+}
+
+impl ApiKeyService {
+    /// Create a new API key service
+    pub fn new(repository: Arc<dyn ApiKeyRepository>) -> Self {
+        Self::with_clock(repository, SystemClock)
+    }
+}
+
+impl<C: Clock> ApiKeyService<C> {
+    /// Create an API key service that reads the current time from `clock`
+    pub fn with_clock(repository: Arc<dyn ApiKeyRepository>, clock: C) -> Self {
+        Self {
+            repository,
+            rotation_overlap: DEFAULT_ROTATION_OVERLAP,
+            clock,
+        }
+    }
+
+    /// Sets how long a rotated key stays valid next to its replacement
+    ///
+    /// A zero overlap revokes the rotated key right away.
+    pub fn with_rotation_overlap(mut self, rotation_overlap: Duration) -> Self {
+        self.rotation_overlap = rotation_overlap;
+        self
+    }
+
+    /// Issue a key for a tenant
+    ///
+    /// The returned plaintext key is not stored and cannot be shown again.
+    pub async fn issue(
+        &self,
+        tenant_id: Uuid,
+        request: IssueApiKey,
+    ) -> Result<IssuedApiKey, ApiKeyError> {
+        let name = request.name.trim().to_string();
+        if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+            return Err(ApiKeyError::InvalidInput(format!(
+                "Name must be between 1 and {} characters",
+                MAX_NAME_LENGTH
+            )));
+        }
+        let description = request
+            .description
+            .map(|description| description.trim().to_string())
+            .filter(|description| !description.is_empty());
+        if description
+            .as_ref()
+            .is_some_and(|description| description.len() > MAX_DESCRIPTION_LENGTH)
+        {
+            return Err(ApiKeyError::InvalidInput(format!(
+                "Description must be at most {} characters",
+                MAX_DESCRIPTION_LENGTH
+            )));
+        }
+        let tags = normalize_tags(request.tags)?;
+        for scope in &request.scopes {
+            validate_scope(scope)?;
+        }
+        if request
+            .expires_in
+            .is_some_and(|expires_in| expires_in <= Duration::ZERO)
+        {
+            return Err(ApiKeyError::InvalidInput(
+                "Expiry must be in the future".to_string(),
+            ));
+        }
+
+        let now = self.clock.now();
+        let mut scopes = request.scopes;
+        scopes.sort();
+        scopes.dedup();
+        let issued = self
+            .store_new_key(ApiKey {
+                id: Uuid::new_v4(),
+                tenant_id,
+                name,
+                description,
+                tags,
+                prefix: String::new(),
+                key_hash: String::new(),
+                scopes,
+                created_by: request.created_by,
+                created_at: now,
+                expires_at: request.expires_in.map(|expires_in| now + expires_in),
+                last_used_at: None,
+                revoked_at: None,
+                reminded_at: None,
+            })
+            .await?;
+
+        info!(
+            tenant_id = %tenant_id,
+            key_id = %issued.api_key.id,
+            prefix = %issued.api_key.prefix,
+            "API key issued"
+        );
+        Ok(issued)
+    }
+
+    /// Replace a key with a new one with the same name, tags, scopes and expiry
+    ///
+    /// Once the new key is stored, the old one stays valid for the rotation
+    /// overlap, or until its own expiry if that is earlier, so that clients
+    /// can switch over without failing requests. The [reminder
+    /// job](crate::ApiKeyReminderService) leaves the old key out meanwhile.
+    pub async fn rotate(&self, tenant_id: Uuid, id: Uuid) -> Result<RotatedApiKey, ApiKeyError> {
+        let current = self
+            .repository
+            .find_by_id(tenant_id, id)
+            .await?
+            .filter(|key| !key.is_revoked())
+            .ok_or(ApiKeyError::NotFound)?;
+
+        let now = self.clock.now();
+        if current.is_expired(now) {
+            return Err(ApiKeyError::InvalidInput(
+                "Expired keys cannot be rotated".to_string(),
+            ));
+        }
+
+        let previous_expires_at = current.expires_at;
+        let issued = self
+            .store_new_key(ApiKey {
+                id: Uuid::new_v4(),
+                created_at: now,
+                last_used_at: None,
+                reminded_at: None,
+                ..current
+            })
+            .await?;
+        let previous_key_expires_at = if self.rotation_overlap > Duration::ZERO {
+            let overlap_end = now + self.rotation_overlap;
+            self.repository.expire(tenant_id, id, overlap_end).await?;
+            // Whoever rotated the key knows that it runs out
+            self.repository.record_reminder(&[id], now).await?;
+            Some(previous_expires_at.map_or(overlap_end, |expires_at| expires_at.min(overlap_end)))
+        } else {
+            self.repository.revoke(tenant_id, id, now).await?;
+            Some(now)
+        };
+
+        info!(
+            tenant_id = %tenant_id,
+            old_key_id = %id,
+            key_id = %issued.api_key.id,
+            "API key rotated"
+        );
+        Ok(RotatedApiKey {
+            issued,
+            previous_key_expires_at,
+        })
+    }
+
+    /// Revoke a key; requests made with it are rejected from now on
+    pub async fn revoke(&self, tenant_id: Uuid, id: Uuid) -> Result<(), ApiKeyError> {
+        if !self
+            .repository
+            .revoke(tenant_id, id, self.clock.now())
+            .await?
+        {
+            return Err(ApiKeyError::NotFound);
+        }
+
+        info!(tenant_id = %tenant_id, key_id = %id, "API key revoked");
+        Ok(())
+    }
+
+    /// Revoke every key a user issued, in any tenant
+    ///
+    /// Returns the number of keys revoked; keys revoked before are not counted.
+    pub async fn revoke_created_by(&self, user_id: Uuid) -> Result<u64, ApiKeyError> {
+        let revoked = self
+            .repository
+            .revoke_created_by(user_id, self.clock.now())
+            .await?;
+
+        info!(user_id = %user_id, revoked, "API keys of user revoked");
+        Ok(revoked)
+    }
+
+    /// List the keys of a tenant matching `query`, newest first
+    ///
+    /// Without expiry or usage criteria revoked and expired keys are listed too.
+    pub async fn list(
+        &self,
+        tenant_id: Uuid,
+        query: ApiKeyQuery,
+    ) -> Result<Vec<ApiKey>, ApiKeyError> {
+        let now = self.clock.now();
+        let filter = ApiKeyFilter {
+            tags: normalize_tags(query.tags)?,
+            search: query
+                .search
+                .map(|search| search.trim().to_string())
+                .filter(|search| !search.is_empty()),
+            valid_at: (query.expiring_within.is_some() || query.unused_for.is_some())
+                .then_some(now),
+            expires_before: query.expiring_within.map(|within| now + within),
+            unused_since: query.unused_for.map(|unused_for| now - unused_for),
+        };
+        Ok(self.repository.list(tenant_id, &filter).await?)
+    }
+
+    /// Resolve the key presented by a request
+    pub async fn authenticate(&self, key: &str) -> Result<ApiKey, ApiKeyError> {
+        let prefix = parse_prefix(key).ok_or(ApiKeyError::InvalidKey)?;
+        let api_key = self
+            .repository
+            .find_by_prefix(prefix)
+            .await?
+            .ok_or(ApiKeyError::InvalidKey)?;
+
+        // Comparing digests does not leak the secret through timing
+        if api_key.key_hash != hash_key(key) {
+            debug!(prefix = %prefix, "API key secret mismatch");
+            return Err(ApiKeyError::InvalidKey);
+        }
+
+        let now = self.clock.now();
+        if api_key.is_revoked() || api_key.is_expired(now) {
+            debug!(key_id = %api_key.id, "Revoked or expired API key presented");
+            return Err(ApiKeyError::InvalidKey);
+        }
+
+        // Usage tracking is informational and must not fail the request. It is
+        // written at most once per interval, not on every request.
+        let recorded_recently = api_key
+            .last_used_at
+            .is_some_and(|last_used_at| now - last_used_at < LAST_USE_RECORD_INTERVAL);
+        let recorded = if recorded_recently {
+            Ok(())
+        } else {
+            self.repository.record_use(api_key.id, now).await
+        };
+        if let Err(err) = recorded {
+            warn!(key_id = %api_key.id, error = %err, "Failed to record API key use");
+        }
+
+        Ok(ApiKey {
+            last_used_at: Some(now),
+            ..api_key
+        })
+    }
+
+    /// Generate the secret of `template`, store it and return it with the plaintext
+    async fn store_new_key(&self, template: ApiKey) -> Result<IssuedApiKey, ApiKeyError> {
+        let prefix = random_hex(PREFIX_BYTES);
+        let key = format!("{}_{}_{}", KEY_MARKER, prefix, random_hex(SECRET_BYTES));
+        let api_key = ApiKey {
+            prefix,
+            key_hash: hash_key(&key),
+            ..template
+        };
+
+        self.repository.create(&api_key).await?;
+        Ok(IssuedApiKey { api_key, key })
+    }
+}
+
+/// Scopes are lowercase permission names such as `users:read`
+fn validate_scope(scope: &str) -> Result<(), ApiKeyError> {
+    let valid = !scope.is_empty()
+        && scope.len() <= 64
+        && scope.chars().all(|c| {
+            c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, ':' | '_' | '.' | '-')
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(ApiKeyError::InvalidScope(scope.to_string()))
+    }
+}
+
+/// Tags are trimmed, lowercased and deduplicated; each consists of letters,
+/// digits and `-`, `_`, `.`, `:`
+fn normalize_tags(tags: Vec<String>) -> Result<Vec<String>, ApiKeyError> {
+    let mut normalized = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        let valid = !tag.is_empty()
+            && tag.len() <= 50
+            && tag.chars().all(|c| {
+                c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_' | '.' | ':')
+            });
+        if !valid {
+            return Err(ApiKeyError::InvalidInput(format!("Invalid tag: {}", tag)));
+        }
+        normalized.push(tag);
+    }
+    normalized.sort();
+    normalized.dedup();
+    if normalized.len() > MAX_TAGS {
+        return Err(ApiKeyError::InvalidInput(format!(
+            "A key has at most {} tags",
+            MAX_TAGS
+        )));
+    }
+    Ok(normalized)
+}
+
+/// Extract the lookup prefix of a key
+fn parse_prefix(key: &str) -> Option<&str> {
+    let rest = key.strip_prefix(KEY_MARKER)?.strip_prefix('_')?;
+    let (prefix, secret) = rest.split_once('_')?;
+    (prefix.len() == PREFIX_BYTES * 2 && !secret.is_empty()).then_some(prefix)
+}
+
+fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+fn random_hex(bytes: usize) -> String {
+    (0..bytes)
+        .map(|_| format!("{:02x}", rand::random::<u8>()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_prefix() {
+        assert_eq!(
+            parse_prefix("acci_0123456789ab_secret"),
+            Some("0123456789ab")
+        );
+        assert_eq!(parse_prefix("acci_0123456789ab_"), None);
+        assert_eq!(parse_prefix("acci_short_secret"), None);
+        assert_eq!(parse_prefix("other_0123456789ab_secret"), None);
+    }
+
+    #[test]
+    fn test_validate_scope() {
+        assert!(validate_scope("users:read").is_ok());
+        assert!(validate_scope("sessions.export").is_ok());
+        assert!(validate_scope("").is_err());
+        assert!(validate_scope("Users:Read").is_err());
+        assert!(validate_scope("users read").is_err());
+    }
+}
@@ -0,0 +1,257 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use time::{Duration, OffsetDateTime};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use crate::models::VerificationType;
+use crate::models::api_key::ApiKey;
+use crate::models::tenant::TenantRepository;
+use crate::models::user::UserRepository;
+use crate::repository::ApiKeyRepository;
+use crate::services::api_key::ApiKeyError;
+use crate::services::message_provider::{Message, MessageProvider};
+use crate::utils::clock::{Clock, SystemClock};
+
+/// Configuration of the API key reminders
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiKeyReminderConfig {
+    /// Days before its expiry from which a key is reported
+    pub expiring_within_days: i64,
+    /// Days without use after which a key is reported
+    pub unused_for_days: i64,
+    /// Days before a key is reported again
+    pub repeat_after_days: i64,
+    /// How often the reminder job looks for keys to report (in seconds)
+    pub poll_interval_secs: u64,
+    /// Maximum number of keys reported per run of the reminder job
+    pub batch_size: u32,
+}
+
+impl Default for ApiKeyReminderConfig {
+    fn default() -> Self {
+        Self {
+            expiring_within_days: 14,
+            unused_for_days: 90,
+            repeat_after_days: 7,
+            poll_interval_secs: 86400, // 1 day
+            batch_size: 500,
+        }
+    }
+}
+
+/// Outcome counts of a single run of the reminder job
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ApiKeyReminderReport {
+    /// Keys reported to the admins of their tenant
+    pub reminded: usize,
+    /// Keys whose reminder failed and is retried on the next run
+    pub failed: usize,
+}
+
+/// Reminds tenant admins of API keys that expire soon or are no longer used
+///
+/// Each run sends every active admin of a tenant one email listing the
+/// tenant's keys that are due. A key is reported again after
+/// [`ApiKeyReminderConfig::repeat_after_days`] as long as it stays due, so
+/// revoking, rotating or using it ends the reminders.
+pub struct ApiKeyReminderService<C: Clock = SystemClock> {
+    repository: Arc<dyn ApiKeyRepository>,
+    tenant_repository: Arc<dyn TenantRepository>,
+    user_repository: Arc<dyn UserRepository>,
+    email_provider: Arc<dyn MessageProvider>,
+    config: ApiKeyReminderConfig,
+    clock: C,
+}
+
+impl ApiKeyReminderService {
+    /// Create a new API key reminder service
+    pub fn new(
+        repository: Arc<dyn ApiKeyRepository>,
+        tenant_repository: Arc<dyn TenantRepository>,
+        user_repository: Arc<dyn UserRepository>,
+        email_provider: Arc<dyn MessageProvider>,
+        config: ApiKeyReminderConfig,
+    ) -> Self {
+        Self::with_clock(
+            repository,
+            tenant_repository,
+            user_repository,
+            email_provider,
+            config,
+            SystemClock,
+        )
+    }
+}
+
+impl<C: Clock> ApiKeyReminderService<C> {
+    /// Create an API key reminder service that reads the current time from `clock`
+    pub fn with_clock(
+        repository: Arc<dyn ApiKeyRepository>,
+        tenant_repository: Arc<dyn TenantRepository>,
+        user_repository: Arc<dyn UserRepository>,
+        email_provider: Arc<dyn MessageProvider>,
+        config: ApiKeyReminderConfig,
+        clock: C,
+    ) -> Self {
+        Self {
+            repository,
+            tenant_repository,
+            user_repository,
+            email_provider,
+            config,
+            clock,
+        }
+    }
+
+    /// Remind the tenant admins of all keys that are due
+    ///
+    /// Failures are logged and counted; the affected keys are picked up again
+    /// on the next run.
+    pub async fn process_due(&self) -> Result<ApiKeyReminderReport, ApiKeyError> {
+        let now = self.clock.now();
+        let due = self
+            .repository
+            .list_reminder_due(
+                now,
+                now + Duration::days(self.config.expiring_within_days),
+                now - Duration::days(self.config.unused_for_days),
+                now - Duration::days(self.config.repeat_after_days),
+                self.config.batch_size,
+            )
+            .await?;
+
+        let mut by_tenant: BTreeMap<Uuid, Vec<ApiKey>> = BTreeMap::new();
+        for key in due {
+            by_tenant.entry(key.tenant_id).or_default().push(key);
+        }
+
+        let mut report = ApiKeyReminderReport::default();
+        for (tenant_id, keys) in by_tenant {
+            match self.remind_tenant(tenant_id, &keys, now).await {
+                Ok(true) => report.reminded += keys.len(),
+                Ok(false) => report.failed += keys.len(),
+                Err(err) => {
+                    warn!(tenant_id = %tenant_id, error = %err, "Failed to send API key reminder");
+                    report.failed += keys.len();
+                },
+            }
+        }
+        Ok(report)
+    }
+
+    /// Email the admins of a tenant about its due keys and record the reminder
+    ///
+    /// Returns `false` if no admin could be reached, so that the keys are
+    /// reported again on the next run.
+    async fn remind_tenant(
+        &self,
+        tenant_id: Uuid,
+        keys: &[ApiKey],
+        now: OffsetDateTime,
+    ) -> Result<bool, ApiKeyError> {
+        let admins = self
+            .tenant_repository
+            .get_tenant_users(tenant_id)
+            .await?
+            .into_iter()
+            .filter(|member| member.is_active && member.tenant_role.eq_ignore_ascii_case("ADMIN"));
+
+        let body = self.reminder_body(keys, now);
+        let mut sent = 0;
+        let mut failed = 0;
+        for admin in admins {
+            let Some(user) = self.user_repository.find_by_id(admin.user_id).await? else {
+                continue;
+            };
+            let message = Message {
+                tenant_id,
+                user_id: user.id,
+                recipient: user.email,
+                subject: Some("API keys that need your attention".to_string()),
+                body: body.clone(),
+                message_type: VerificationType::Email,
+            };
+            match self.email_provider.send_message(message).await {
+                Ok(_) => sent += 1,
+                Err(err) => {
+                    warn!(user_id = %user.id, error = %err, "Failed to send API key reminder email");
+                    failed += 1;
+                },
+            }
+        }
+
+        if sent == 0 && failed > 0 {
+            return Ok(false);
+        }
+        if sent == 0 {
+            debug!(tenant_id = %tenant_id, "Tenant has no admin to remind of API keys");
+        }
+
+        let ids: Vec<Uuid> = keys.iter().map(|key| key.id).collect();
+        self.repository.record_reminder(&ids, now).await?;
+        Ok(true)
+    }
+
+    /// One line per key stating why it is reported
+    fn reminder_body(&self, keys: &[ApiKey], now: OffsetDateTime) -> String {
+        let mut body = String::from(
+            "The following API keys of your tenant expire soon or have not been used for a \
+             while. Rotate the keys that are still needed and revoke the others.\n\n",
+        );
+        let expires_before = now + Duration::days(self.config.expiring_within_days);
+        let unused_since = now - Duration::days(self.config.unused_for_days);
+        for key in keys {
+            let mut reasons = Vec::new();
+            if let Some(expires_at) = key
+                .expires_at
+                .filter(|expires_at| *expires_at < expires_before)
+            {
+                reasons.push(format!("expires on {}", expires_at.date()));
+            }
+            if !key.used_since(unused_since) {
+                reasons.push(match key.last_used_at {
+                    Some(last_used_at) => format!("last used on {}", last_used_at.date()),
+                    None => "never used".to_string(),
+                });
+            }
+            let _ = writeln!(
+                body,
+                "- {} ({}): {}",
+                key.name,
+                key.prefix,
+                reasons.join(", ")
+            );
+        }
+        body
+    }
+
+    /// Run the reminder job on a dedicated task until the runtime shuts down
+    pub fn spawn(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(StdDuration::from_secs(self.config.poll_interval_secs));
+            info!("API key reminder job started");
+
+            loop {
+                interval.tick().await;
+                match self.process_due().await {
+                    Ok(report) if report != ApiKeyReminderReport::default() => {
+                        info!(
+                            reminded = report.reminded,
+                            failed = report.failed,
+                            "Sent API key reminders"
+                        );
+                    },
+                    Ok(_) => {},
+                    Err(e) => error!("API key reminder job failed: {}", e),
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,588 @@
+use async_trait::async_trait;
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use hmac::{Hmac, Mac};
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Public};
+use openssl::sign::Verifier;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tracing::{debug, info, instrument, warn};
+use uuid::Uuid;
+
+use crate::models::{DeliveryReceipt, DeliveryStatus, MessageDelivery, VerificationType};
+use crate::repository::MessageDeliveryRepository;
+use crate::security::webhook::{DEFAULT_WEBHOOK_TOLERANCE, WebhookError};
+use crate::services::message_provider::{Message, MessageProvider, MessageProviderConfig};
+use crate::utils::clock::{Clock, SystemClock};
+use crate::utils::duration::DurationSecs;
+use crate::utils::secret::Secret;
+use acci_core::error::{Error, Result};
+use acci_core::secret_store::{SecretResolver, ensure_resolved};
+
+/// Header carrying the signature of a Twilio status callback
+pub const TWILIO_SIGNATURE_HEADER: &str = "X-Twilio-Signature";
+/// Header carrying the signature of a SendGrid event webhook
+pub const SENDGRID_SIGNATURE_HEADER: &str = "X-Twilio-Email-Event-Webhook-Signature";
+/// Header carrying the timestamp of a SendGrid event webhook
+pub const SENDGRID_TIMESTAMP_HEADER: &str = "X-Twilio-Email-Event-Webhook-Timestamp";
+
+/// Settings for verifying the delivery receipts the message providers call back with
+///
+/// A provider's callbacks are rejected while its secret is not configured.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeliveryReceiptConfig {
+    /// Auth token Twilio signs status callbacks with; defaults to the SMS API secret
+    pub twilio_auth_token: Option<Secret<String>>,
+    /// Signature secret of the Vonage account (HMAC-SHA256)
+    pub vonage_signature_secret: Option<Secret<String>>,
+    /// Base64 encoded public key of the SendGrid signed event webhook
+    pub sendgrid_verification_key: Option<String>,
+    /// How far the timestamp of a signed Vonage or SendGrid callback may be from the current time
+    pub tolerance_secs: DurationSecs,
+}
+
+impl Default for DeliveryReceiptConfig {
+    fn default() -> Self {
+        Self {
+            twilio_auth_token: None,
+            vonage_signature_secret: None,
+            sendgrid_verification_key: None,
+            tolerance_secs: DEFAULT_WEBHOOK_TOLERANCE.into(),
+        }
+    }
+}
+
+impl DeliveryReceiptConfig {
+    /// Resolve the callback secrets; `field` names the config in errors
+    pub async fn resolve_secrets(&mut self, resolver: &mut SecretResolver<'_>, field: &str) {
+        if let Some(token) = &mut self.twilio_auth_token {
+            resolver
+                .resolve(&format!("{field}.twilio_auth_token"), token)
+                .await;
+        }
+        if let Some(secret) = &mut self.vonage_signature_secret {
+            resolver
+                .resolve(&format!("{field}.vonage_signature_secret"), secret)
+                .await;
+        }
+    }
+}
+
+/// Verifies that delivery receipts were sent by the provider they claim to come from
+pub struct DeliveryReceiptVerifier {
+    /// Status callback URL the Twilio signature covers, and the auth token
+    twilio: Option<(String, Secret<String>)>,
+    vonage_secret: Option<Secret<String>>,
+    sendgrid_key: Option<PKey<Public>>,
+    tolerance_secs: u64,
+}
+
+impl DeliveryReceiptVerifier {
+    /// Create a verifier from the message provider configuration
+    ///
+    /// Twilio callbacks are only verified when the SMS provider has a
+    /// `status_callback_url`, as the signature covers the URL Twilio called.
+    pub fn from_config(config: &MessageProviderConfig) -> Result<Self> {
+        let receipts = &config.delivery_receipts;
+        let twilio_token = receipts.twilio_auth_token.clone().or_else(|| {
+            config
+                .sms
+                .provider
+                .eq_ignore_ascii_case("twilio")
+                .then(|| config.sms.api_secret.clone())
+                .flatten()
+        });
+        if let Some(token) = &twilio_token {
+            ensure_resolved("delivery_receipts.twilio_auth_token", token)?;
+        }
+        if let Some(secret) = &receipts.vonage_signature_secret {
+            ensure_resolved("delivery_receipts.vonage_signature_secret", secret)?;
+        }
+
+        let sendgrid_key = receipts
+            .sendgrid_verification_key
+            .as_deref()
+            .map(|key| {
+                let der = BASE64.decode(key.trim()).map_err(|e| {
+                    Error::Config(format!("Invalid SendGrid verification key: {}", e))
+                })?;
+                PKey::public_key_from_der(&der)
+                    .map_err(|e| Error::Config(format!("Invalid SendGrid verification key: {}", e)))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            twilio: config.sms.status_callback_url.clone().zip(twilio_token),
+            vonage_secret: receipts.vonage_signature_secret.clone(),
+            sendgrid_key,
+            tolerance_secs: receipts.tolerance_secs.as_secs(),
+        })
+    }
+
+    /// Verify the `X-Twilio-Signature` of a status callback with form `params`
+    pub fn verify_twilio(
+        &self,
+        signature: Option<&str>,
+        params: &[(String, String)],
+    ) -> std::result::Result<(), WebhookError> {
+        let Some((url, token)) = &self.twilio else {
+            warn!("Rejecting Twilio status callback, callback verification is not configured");
+            return Err(WebhookError::InvalidSignature);
+        };
+        let signature = signature.ok_or(WebhookError::MissingHeaders)?;
+        let expected = BASE64
+            .decode(signature.trim())
+            .map_err(|_| WebhookError::InvalidSignature)?;
+
+        let mut sorted: Vec<_> = params.iter().collect();
+        sorted.sort();
+        let mut mac =
+            Hmac::<Sha1>::new_from_slice(token.as_bytes()).expect("HMAC can take key of any size");
+        mac.update(url.as_bytes());
+        for (key, value) in sorted {
+            mac.update(key.as_bytes());
+            mac.update(value.as_bytes());
+        }
+        mac.verify_slice(&expected)
+            .map_err(|_| WebhookError::InvalidSignature)
+    }
+
+    /// Verify the `sig` parameter of a signed Vonage delivery receipt
+    pub fn verify_vonage(
+        &self,
+        params: &[(String, String)],
+    ) -> std::result::Result<(), WebhookError> {
+        self.verify_vonage_at(params, OffsetDateTime::now_utc().unix_timestamp())
+    }
+
+    pub(crate) fn verify_vonage_at(
+        &self,
+        params: &[(String, String)],
+        now: i64,
+    ) -> std::result::Result<(), WebhookError> {
+        let Some(secret) = &self.vonage_secret else {
+            warn!("Rejecting Vonage delivery receipt, receipt verification is not configured");
+            return Err(WebhookError::InvalidSignature);
+        };
+        let (Some(signature), Some(timestamp)) = (
+            param(params, "sig"),
+            param(params, "timestamp").and_then(|value| value.trim().parse::<i64>().ok()),
+        ) else {
+            return Err(WebhookError::MissingHeaders);
+        };
+        if now.abs_diff(timestamp) > self.tolerance_secs {
+            return Err(WebhookError::StaleTimestamp);
+        }
+        let expected = hex::decode(signature.trim().to_ascii_lowercase())
+            .map_err(|_| WebhookError::InvalidSignature)?;
+
+        let mac = vonage_signature(secret.as_bytes(), params);
+        mac.verify_slice(&expected)
+            .map_err(|_| WebhookError::InvalidSignature)
+    }
+
+    /// Verify the ECDSA signature of a SendGrid event webhook over its raw `body`
+    pub fn verify_sendgrid(
+        &self,
+        signature: Option<&str>,
+        timestamp: Option<&str>,
+        body: &[u8],
+    ) -> std::result::Result<(), WebhookError> {
+        self.verify_sendgrid_at(
+            signature,
+            timestamp,
+            body,
+            OffsetDateTime::now_utc().unix_timestamp(),
+        )
+    }
+
+    pub(crate) fn verify_sendgrid_at(
+        &self,
+        signature: Option<&str>,
+        timestamp: Option<&str>,
+        body: &[u8],
+        now: i64,
+    ) -> std::result::Result<(), WebhookError> {
+        let Some(key) = &self.sendgrid_key else {
+            warn!("Rejecting SendGrid event webhook, webhook verification is not configured");
+            return Err(WebhookError::InvalidSignature);
+        };
+        let (Some(signature), Some(timestamp)) = (signature, timestamp) else {
+            return Err(WebhookError::MissingHeaders);
+        };
+        let Ok(seconds) = timestamp.trim().parse::<i64>() else {
+            return Err(WebhookError::MissingHeaders);
+        };
+        if now.abs_diff(seconds) > self.tolerance_secs {
+            return Err(WebhookError::StaleTimestamp);
+        }
+        let signature = BASE64
+            .decode(signature.trim())
+            .map_err(|_| WebhookError::InvalidSignature)?;
+
+        let verified = Verifier::new(MessageDigest::sha256(), key)
+            .and_then(|mut verifier| {
+                verifier.update(timestamp.as_bytes())?;
+                verifier.update(body)?;
+                verifier.verify(&signature)
+            })
+            .unwrap_or(false);
+        if verified {
+            Ok(())
+        } else {
+            Err(WebhookError::InvalidSignature)
+        }
+    }
+}
+
+/// HMAC-SHA256 Vonage computes over the sorted receipt parameters except `sig`
+pub(crate) fn vonage_signature(secret: &[u8], params: &[(String, String)]) -> Hmac<Sha256> {
+    let mut sorted: Vec<_> = params.iter().filter(|(key, _)| key != "sig").collect();
+    sorted.sort();
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC can take key of any size");
+    for (key, value) in sorted {
+        let value = value.replace(['&', '='], "_");
+        mac.update(format!("&{key}={value}").as_bytes());
+    }
+    mac
+}
+
+fn param<'p>(params: &'p [(String, String)], name: &str) -> Option<&'p str> {
+    params
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+        .filter(|value| !value.is_empty())
+}
+
+/// Parse a Twilio status callback
+///
+/// Returns `None` for states without a counterpart, e.g. `receiving`.
+pub fn parse_twilio_callback(params: &[(String, String)]) -> Result<Option<DeliveryReceipt>> {
+    let message_sid = param(params, "MessageSid")
+        .ok_or_else(|| Error::Validation("Twilio callback without MessageSid".to_string()))?;
+    let status = param(params, "MessageStatus")
+        .ok_or_else(|| Error::Validation("Twilio callback without MessageStatus".to_string()))?;
+
+    let status = match status {
+        "accepted" | "scheduled" | "queued" | "sending" => DeliveryStatus::Queued,
+        "sent" => DeliveryStatus::Sent,
+        "delivered" | "read" => DeliveryStatus::Delivered,
+        "undelivered" | "failed" | "canceled" => DeliveryStatus::Failed,
+        _ => return Ok(None),
+    };
+
+    Ok(Some(DeliveryReceipt {
+        provider_message_id: format!("twilio:{}", message_sid),
+        status,
+        error: param(params, "ErrorCode").map(str::to_string),
+    }))
+}
+
+/// Parse a Vonage SMS delivery receipt
+///
+/// Returns `None` for the `unknown` state.
+pub fn parse_vonage_receipt(params: &[(String, String)]) -> Result<Option<DeliveryReceipt>> {
+    let message_id = param(params, "messageId")
+        .ok_or_else(|| Error::Validation("Vonage receipt without messageId".to_string()))?;
+    let status = param(params, "status")
+        .ok_or_else(|| Error::Validation("Vonage receipt without status".to_string()))?;
+
+    let status = match status {
+        "accepted" | "buffered" => DeliveryStatus::Sent,
+        "delivered" => DeliveryStatus::Delivered,
+        "failed" | "rejected" | "expired" => DeliveryStatus::Failed,
+        _ => return Ok(None),
+    };
+
+    Ok(Some(DeliveryReceipt {
+        provider_message_id: format!("vonage:{}", message_id),
+        status,
+        error: param(params, "err-code")
+            .filter(|code| *code != "0")
+            .map(str::to_string),
+    }))
+}
+
+/// Parse the events of a SendGrid event webhook
+///
+/// Engagement events such as `open` or `click` are skipped.
+pub fn parse_sendgrid_events(body: &[u8]) -> Result<Vec<DeliveryReceipt>> {
+    #[derive(Deserialize)]
+    struct SendGridEvent {
+        event: String,
+        sg_message_id: Option<String>,
+        reason: Option<String>,
+    }
+
+    let events: Vec<SendGridEvent> = serde_json::from_slice(body)
+        .map_err(|e| Error::Validation(format!("Invalid SendGrid event payload: {}", e)))?;
+
+    Ok(events
+        .into_iter()
+        .filter_map(|event| {
+            let status = match event.event.as_str() {
+                "processed" | "deferred" => DeliveryStatus::Sent,
+                "delivered" => DeliveryStatus::Delivered,
+                "bounce" | "dropped" => DeliveryStatus::Failed,
+                _ => return None,
+            };
+            // sg_message_id is the X-Message-Id of the send request followed by a filter suffix
+            let message_id = event.sg_message_id?;
+            let message_id = message_id
+                .split_once('.')
+                .map_or(message_id.as_str(), |(id, _)| id);
+            Some(DeliveryReceipt {
+                provider_message_id: format!("sendgrid:{}", message_id),
+                status,
+                error: event.reason,
+            })
+        })
+        .collect())
+}
+
+/// What applying a delivery receipt changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiptOutcome {
+    /// The delivery moved to the reported state
+    Updated,
+    /// The delivery failed and the message was sent again as the given delivery
+    Resent(Uuid),
+    /// The delivery was already in the reported or a later state
+    Stale,
+    /// No delivery is known under the message ID, e.g. a message sent before tracking started
+    Unknown,
+}
+
+/// Another attempt at delivering a failed message
+pub struct Resend {
+    /// Provider to send the message with, without delivery tracking of its own
+    pub provider: Arc<dyn MessageProvider>,
+    /// Message to send, e.g. the original message or one for a fallback channel
+    pub message: Message,
+}
+
+/// Decides what happens when a provider reports a delivery as failed
+#[async_trait]
+pub trait DeliveryFailurePolicy: Send + Sync {
+    /// The attempt to make for the failed `delivery`, or `None` to give up
+    async fn on_failure(&self, delivery: &MessageDelivery) -> Option<Resend>;
+}
+
+/// Sends failed messages again through the same channel, up to a number of attempts
+pub struct ResendPolicy {
+    max_attempts: u32,
+    providers: HashMap<VerificationType, Arc<dyn MessageProvider>>,
+}
+
+impl ResendPolicy {
+    /// Create a policy making at most `max_attempts` attempts per message
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            providers: HashMap::new(),
+        }
+    }
+
+    /// Resend messages of the provider's type with `provider`
+    pub fn with_provider(mut self, provider: Arc<dyn MessageProvider>) -> Self {
+        self.providers
+            .insert(provider.verification_type(), provider);
+        self
+    }
+}
+
+#[async_trait]
+impl DeliveryFailurePolicy for ResendPolicy {
+    async fn on_failure(&self, delivery: &MessageDelivery) -> Option<Resend> {
+        if delivery.attempt >= self.max_attempts {
+            return None;
+        }
+        let provider = self.providers.get(&delivery.message_type)?;
+        Some(Resend {
+            provider: provider.clone(),
+            message: delivery.to_message(),
+        })
+    }
+}
+
+/// Tracks sent messages until their provider reports them delivered or failed
+///
+/// Providers wrapped with [`DeliveryTracker::wrap`] record a
+/// [`MessageDelivery`] under the provider's message ID after every send;
+/// [`DeliveryTracker::apply_receipt`] updates it from the provider callbacks.
+#[derive(Clone)]
+pub struct DeliveryTracker<C: Clock + Clone = SystemClock> {
+    repository: Arc<dyn MessageDeliveryRepository>,
+    failure_policy: Option<Arc<dyn DeliveryFailurePolicy>>,
+    clock: C,
+}
+
+impl DeliveryTracker {
+    /// Create a new delivery tracker
+    pub fn new(repository: Arc<dyn MessageDeliveryRepository>) -> Self {
+        Self::with_clock(repository, SystemClock)
+    }
+}
+
+impl<C: Clock + Clone> DeliveryTracker<C> {
+    /// Create a delivery tracker that reads the current time from `clock`
+    pub fn with_clock(repository: Arc<dyn MessageDeliveryRepository>, clock: C) -> Self {
+        Self {
+            repository,
+            failure_policy: None,
+            clock,
+        }
+    }
+
+    /// Consult `policy` when a provider reports a delivery as failed
+    pub fn with_failure_policy(mut self, policy: Arc<dyn DeliveryFailurePolicy>) -> Self {
+        self.failure_policy = Some(policy);
+        self
+    }
+
+    /// Record a delivery for every message `provider` sends
+    pub fn wrap(&self, provider: Arc<dyn MessageProvider>) -> Arc<dyn MessageProvider> {
+        Arc::new(TrackedMessageProvider {
+            inner: provider,
+            tracker: self.clone(),
+        })
+    }
+
+    /// Record that `message` was accepted by its provider as `provider_message_id`
+    pub async fn record_sent(
+        &self,
+        message: &Message,
+        provider_message_id: &str,
+    ) -> Result<MessageDelivery> {
+        let delivery =
+            MessageDelivery::new(message, provider_message_id.to_string(), self.clock.now());
+        self.repository.record(&delivery).await?;
+        Ok(delivery)
+    }
+
+    /// Apply a receipt to the delivery it reports on
+    ///
+    /// Receipts for unknown messages and receipts that would move a delivery
+    /// backwards are ignored. A failure is handed to the failure policy.
+    #[instrument(skip(self), level = "debug")]
+    pub async fn apply_receipt(&self, receipt: &DeliveryReceipt) -> Result<ReceiptOutcome> {
+        loop {
+            let Some(delivery) = self
+                .repository
+                .find_by_provider_message_id(&receipt.provider_message_id)
+                .await?
+            else {
+                debug!(
+                    provider_message_id = %receipt.provider_message_id,
+                    "Ignoring receipt for unknown message"
+                );
+                return Ok(ReceiptOutcome::Unknown);
+            };
+
+            if !delivery.status.can_advance_to(receipt.status) {
+                return Ok(ReceiptOutcome::Stale);
+            }
+
+            let updated = self
+                .repository
+                .update_status(
+                    delivery.id,
+                    delivery.status,
+                    receipt.status,
+                    receipt.error.as_deref(),
+                    self.clock.now(),
+                )
+                .await?;
+            if !updated {
+                // Another receipt advanced the delivery meanwhile; check against its new state
+                continue;
+            }
+
+            debug!(
+                delivery_id = %delivery.id,
+                status = ?receipt.status,
+                "Applied delivery receipt"
+            );
+            let resent = if receipt.status == DeliveryStatus::Failed {
+                self.handle_failure(delivery, receipt).await?
+            } else {
+                None
+            };
+            return Ok(resent.map_or(ReceiptOutcome::Updated, ReceiptOutcome::Resent));
+        }
+    }
+
+    async fn handle_failure(
+        &self,
+        mut delivery: MessageDelivery,
+        receipt: &DeliveryReceipt,
+    ) -> Result<Option<Uuid>> {
+        warn!(
+            delivery_id = %delivery.id,
+            error = receipt.error.as_deref().unwrap_or("unknown"),
+            "Message delivery failed"
+        );
+        let Some(policy) = &self.failure_policy else {
+            return Ok(None);
+        };
+        delivery.status = DeliveryStatus::Failed;
+        delivery.error = receipt.error.clone();
+        let Some(resend) = policy.on_failure(&delivery).await else {
+            return Ok(None);
+        };
+
+        let provider_message_id = resend.provider.send_message(resend.message.clone()).await?;
+        let mut retry =
+            MessageDelivery::new(&resend.message, provider_message_id, self.clock.now());
+        retry.attempt = delivery.attempt + 1;
+        retry.retry_of = Some(delivery.id);
+        self.repository.record(&retry).await?;
+
+        info!(
+            delivery_id = %delivery.id,
+            retry_id = %retry.id,
+            attempt = retry.attempt,
+            "Resent message after failed delivery"
+        );
+        Ok(Some(retry.id))
+    }
+}
+
+/// Message provider decorator recording a delivery for every sent message
+///
+/// A failure to record is logged and does not fail the send, as the message
+/// has already been handed to the provider.
+pub struct TrackedMessageProvider<C: Clock + Clone = SystemClock> {
+    inner: Arc<dyn MessageProvider>,
+    tracker: DeliveryTracker<C>,
+}
+
+#[async_trait]
+impl<C: Clock + Clone> MessageProvider for TrackedMessageProvider<C> {
+    fn verification_type(&self) -> VerificationType {
+        self.inner.verification_type()
+    }
+
+    #[instrument(skip(self, message), level = "debug")]
+    async fn send_message(&self, message: Message) -> Result<String> {
+        let provider_message_id = self.inner.send_message(message.clone()).await?;
+        if let Err(err) = self
+            .tracker
+            .record_sent(&message, &provider_message_id)
+            .await
+        {
+            warn!(
+                provider_message_id = %provider_message_id,
+                error = %err,
+                "Failed to record message delivery"
+            );
+        }
+        Ok(provider_message_id)
+    }
+}
@@ -0,0 +1,916 @@
+//! Development data seeding
+//!
+//! [`DevSeeder`] fills a development database with tenants, their admins and
+//! members, sessions, verification codes, fingerprints and risk assessments.
+//! Everything is created through the same services and repositories the
+//! application uses, so the data satisfies their invariants: passwords are
+//! hashed and strength-checked, subdomains validated and the user limits of
+//! the subscriptions enforced.
+//!
+//! Names, emails, passwords and the mix of states are derived from
+//! [`DevSeedOptions::seed`], so the same seed yields the same credentials on
+//! every run. Generated ids and timestamps differ between runs.
+//!
+//! Only compiled with the `dev-tools` feature. Run it with
+//! `cargo run -p acci_auth --features dev-tools --bin dev-seed -- --help`.
+
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use rand::{Rng, SeedableRng};
+use serde_json::json;
+use sqlx::PgPool;
+use sqlx::postgres::PgPoolOptions;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use time::OffsetDateTime;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::config::{AuthConfig, SessionLifetime};
+use crate::models::tenant::{CreateTenantDto, CreateTenantUserDto, TenantPlanType};
+use crate::models::user::CreateUser;
+use crate::models::{VerificationCode, VerificationConfig, VerificationType};
+use crate::repository::{
+    RepositoryBackend, RepositoryConfig, RepositoryError, RepositoryFactory, TenantAwareContext,
+    VerificationCodeRepository,
+};
+use crate::security::config::FingerprintingConfig;
+use crate::security::fingerprint::{
+    BrowserFingerprint, FingerprintService, PostgresFingerprintRepository,
+};
+use crate::services::session::{SessionService, SessionServiceError};
+use crate::services::tenant::{CreateTenantWithAdminDto, TenantService, TenantServiceError};
+use crate::services::user::{UserService, UserServiceError};
+use crate::session::Session;
+use crate::session::enhanced_security::{
+    PostgresRiskAssessmentRepository, RiskAssessmentRepository, SessionRiskAssessment,
+};
+use crate::utils::bounded_json::BoundedJson;
+use crate::utils::jwt::JwtUtils;
+
+/// Key of the tenant metadata marking tenants created by the seeder
+pub const DEV_SEED_METADATA_KEY: &str = "dev_seed";
+
+/// Top-level domain of the seeded email addresses, reserved by RFC 2606
+pub const DEV_SEED_EMAIL_TLD: &str = "example";
+
+/// Upper bound of [`DevSeedOptions::tenants`]
+pub const MAX_SEED_TENANTS: usize = 100;
+
+/// Upper bound of [`DevSeedOptions::members_per_tenant`]
+///
+/// Stays below the user limit of the smallest plan the seeder subscribes to.
+pub const MAX_SEED_MEMBERS_PER_TENANT: usize = 50;
+
+/// Burst of the repository rate limiters while seeding
+///
+/// The seeder issues far more writes per second than any client would.
+const SEED_RATE_LIMIT_BURST: u32 = 100_000;
+
+const COMPANY_PREFIXES: &[&str] = &[
+    "Northwind",
+    "Bluefin",
+    "Silver Birch",
+    "Granite Peak",
+    "Harbor",
+    "Evergreen",
+    "Copperleaf",
+    "Riverbend",
+    "Summit",
+    "Lakeshore",
+    "Brightwater",
+    "Ironwood",
+];
+const COMPANY_INDUSTRIES: &[&str] = &[
+    "Logistics",
+    "Analytics",
+    "Health",
+    "Foods",
+    "Robotics",
+    "Consulting",
+    "Energy",
+    "Media",
+    "Outfitters",
+    "Labs",
+];
+const COMPANY_SUFFIXES: &[&str] = &["GmbH", "Inc.", "Ltd.", "AG", "Co."];
+const FIRST_NAMES: &[&str] = &[
+    "Anna", "Ben", "Clara", "David", "Elif", "Felix", "Greta", "Hiro", "Ines", "Jonas", "Kira",
+    "Lukas", "Mara", "Noah", "Olga", "Paul", "Rosa", "Sami", "Tara", "Yusuf",
+];
+const LAST_NAMES: &[&str] = &[
+    "Schmidt", "Nguyen", "Okafor", "Larsen", "Rossi", "Kowalski", "Tanaka", "Moreau", "Silva",
+    "Novak", "Fischer", "Haddad", "Berg", "Costa", "Meyer", "Andersen",
+];
+const PASSWORD_WORDS: &[&str] = &[
+    "amber", "beacon", "cinder", "dune", "ember", "fjord", "glacier", "harbor", "indigo",
+    "juniper", "kestrel", "lantern", "meadow", "nebula", "orchid", "pebble", "quartz", "raven",
+    "saffron", "thistle", "umber", "velvet", "willow", "zephyr",
+];
+const USER_AGENTS: &[&str] = &[
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 14_4) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Safari/605.1.15",
+    "Mozilla/5.0 (X11; Linux x86_64; rv:125.0) Gecko/20100101 Firefox/125.0",
+    "Mozilla/5.0 (iPhone; CPU iPhone OS 17_4 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Mobile/15E148 Safari/604.1",
+];
+/// Documentation address blocks (RFC 5737), never routed
+const IP_PREFIXES: &[&str] = &["192.0.2", "198.51.100", "203.0.113"];
+const TIMEZONES: &[i32] = &[-300, 0, 60, 120, 330, 540];
+const RISK_FACTORS: &[&str] = &[
+    "new_device",
+    "new_location",
+    "impossible_travel",
+    "unusual_time",
+    "tor_exit_node",
+];
+
+/// What and how much to seed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DevSeedOptions {
+    /// Seed of the generator, the same seed yields the same credentials
+    pub seed: u64,
+    /// Number of tenants to create
+    pub tenants: usize,
+    /// Number of members created in every tenant besides its admin
+    pub members_per_tenant: usize,
+    /// Seed even if the database contains data not created by the seeder
+    pub force: bool,
+}
+
+impl Default for DevSeedOptions {
+    fn default() -> Self {
+        Self {
+            seed: 42,
+            tenants: 3,
+            members_per_tenant: 4,
+            force: false,
+        }
+    }
+}
+
+impl DevSeedOptions {
+    /// Check the counts against [`MAX_SEED_TENANTS`] and [`MAX_SEED_MEMBERS_PER_TENANT`]
+    pub fn validate(&self) -> Result<(), DevSeedError> {
+        if self.tenants == 0 || self.tenants > MAX_SEED_TENANTS {
+            return Err(DevSeedError::InvalidOptions(format!(
+                "tenants must be between 1 and {}",
+                MAX_SEED_TENANTS
+            )));
+        }
+        if self.members_per_tenant > MAX_SEED_MEMBERS_PER_TENANT {
+            return Err(DevSeedError::InvalidOptions(format!(
+                "members per tenant must be at most {}",
+                MAX_SEED_MEMBERS_PER_TENANT
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Errors of [`DevSeeder`]
+#[derive(Debug, Error)]
+pub enum DevSeedError {
+    /// The database contains data that was not created by the seeder
+    #[error(
+        "Refusing to seed: the database contains {tenants} tenants and {users} users not created by the dev seed"
+    )]
+    ProductionData { tenants: i64, users: i64 },
+
+    #[error("Invalid seed options: {0}")]
+    InvalidOptions(String),
+
+    #[error("Tenant service error: {0}")]
+    Tenant(#[from] TenantServiceError),
+
+    #[error("User service error: {0}")]
+    User(#[from] UserServiceError),
+
+    #[error("Session service error: {0}")]
+    Session(#[from] SessionServiceError),
+
+    #[error("Verification error: {0}")]
+    Verification(#[from] acci_core::error::Error),
+
+    #[error("Repository error: {0}")]
+    Repository(#[from] RepositoryError),
+
+    #[error("Fingerprint error: {0}")]
+    Fingerprint(anyhow::Error),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Role of a seeded user in its tenant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeededRole {
+    Admin,
+    Member,
+}
+
+impl SeededRole {
+    /// Tenant role as stored with the membership
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Admin => "ADMIN",
+            Self::Member => "MEMBER",
+        }
+    }
+}
+
+/// A user created by the seeder, with the password to sign in with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeededUser {
+    pub id: Uuid,
+    pub email: String,
+    pub password: String,
+    pub role: SeededRole,
+    pub email_verified: bool,
+}
+
+/// A tenant created by the seeder and its users, admin first
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeededTenant {
+    pub id: Uuid,
+    pub name: String,
+    pub subdomain: String,
+    pub plan: TenantPlanType,
+    pub users: Vec<SeededUser>,
+}
+
+/// Number of seeded rows besides tenants and users
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SeedCounts {
+    pub active_sessions: usize,
+    pub expired_sessions: usize,
+    pub pending_codes: usize,
+    pub verified_codes: usize,
+    pub expired_codes: usize,
+    pub invalidated_codes: usize,
+    pub fingerprints: usize,
+    pub risk_assessments: usize,
+}
+
+/// Result of a seed run
+///
+/// Displays as a table of the created credentials.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeedSummary {
+    pub seed: u64,
+    pub tenants: Vec<SeededTenant>,
+    pub counts: SeedCounts,
+}
+
+impl SeedSummary {
+    /// All seeded users across the tenants
+    pub fn users(&self) -> impl Iterator<Item = &SeededUser> {
+        self.tenants.iter().flat_map(|tenant| tenant.users.iter())
+    }
+}
+
+impl fmt::Display for SeedSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts = &self.counts;
+        writeln!(
+            f,
+            "Seed {}: {} tenants, {} users, {} active and {} expired sessions",
+            self.seed,
+            self.tenants.len(),
+            self.users().count(),
+            counts.active_sessions,
+            counts.expired_sessions,
+        )?;
+        writeln!(
+            f,
+            "Verification codes: {} pending, {} verified, {} expired, {} invalidated; \
+             {} fingerprints, {} risk assessments",
+            counts.pending_codes,
+            counts.verified_codes,
+            counts.expired_codes,
+            counts.invalidated_codes,
+            counts.fingerprints,
+            counts.risk_assessments,
+        )?;
+        writeln!(f)?;
+
+        let header = ["SUBDOMAIN", "ROLE", "EMAIL", "PASSWORD"];
+        let rows: Vec<[&str; 4]> = self
+            .tenants
+            .iter()
+            .flat_map(|tenant| {
+                tenant.users.iter().map(move |user| {
+                    [
+                        tenant.subdomain.as_str(),
+                        user.role.as_str(),
+                        user.email.as_str(),
+                        user.password.as_str(),
+                    ]
+                })
+            })
+            .collect();
+        let mut widths = header.map(str::len);
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+
+        for row in std::iter::once(&header).chain(&rows) {
+            writeln!(
+                f,
+                "{:<w0$}  {:<w1$}  {:<w2$}  {}",
+                row[0],
+                row[1],
+                row[2],
+                row[3],
+                w0 = widths[0],
+                w1 = widths[1],
+                w2 = widths[2],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The Postgres repositories scope their queries by tenant explicitly
+struct ExplicitTenantContext;
+
+impl TenantAwareContext for ExplicitTenantContext {
+    fn set_tenant_context(&self, _tenant_id: &Uuid) -> Result<(), RepositoryError> {
+        Ok(())
+    }
+}
+
+/// Creates development data through the services of the framework
+pub struct DevSeeder {
+    pool: PgPool,
+    user_service: Arc<UserService>,
+    tenant_service: TenantService,
+    session_service: Arc<SessionService>,
+    verification_codes: Arc<dyn VerificationCodeRepository>,
+    verification_config: VerificationConfig,
+    fingerprints: FingerprintService,
+    risk_assessments: PostgresRiskAssessmentRepository,
+}
+
+impl DevSeeder {
+    /// Connect to the Postgres database of `config`
+    ///
+    /// The repository rate limiters are raised to [`SEED_RATE_LIMIT_BURST`];
+    /// the other settings of `config` are used as they are.
+    pub async fn connect(
+        config: RepositoryConfig,
+        auth_config: Arc<AuthConfig>,
+    ) -> Result<Self, DevSeedError> {
+        let config = RepositoryConfig {
+            backend: RepositoryBackend::Postgres,
+            rate_limit_burst: config.rate_limit_burst.max(SEED_RATE_LIMIT_BURST),
+            ..config
+        };
+        let pool = config
+            .timeouts
+            .apply_to(PgPoolOptions::new())
+            .max_connections(config.max_connections)
+            .acquire_timeout(config.connect_timeout)
+            .connect(&config.database_url)
+            .await?;
+        let repositories = RepositoryFactory::new(config).create().await?;
+
+        let session_service = Arc::new(SessionService::new(
+            repositories.sessions,
+            auth_config.clone(),
+        ));
+        let user_service = Arc::new(UserService::new(
+            repositories.users.clone(),
+            Arc::new(JwtUtils::new(auth_config.jwt_secret.as_bytes())),
+            session_service.clone(),
+            None,
+            auth_config,
+        ));
+        let tenant_service = TenantService::new(
+            repositories.tenants,
+            repositories.users,
+            user_service.clone(),
+        );
+
+        Ok(Self {
+            user_service,
+            tenant_service,
+            session_service,
+            verification_codes: repositories.verification_codes,
+            verification_config: VerificationConfig::default(),
+            fingerprints: FingerprintService::new(
+                Arc::new(PostgresFingerprintRepository::new(pool.clone())),
+                FingerprintingConfig::default(),
+            ),
+            risk_assessments: PostgresRiskAssessmentRepository::new(pool.clone()),
+            pool,
+        })
+    }
+
+    /// Refuse with [`DevSeedError::ProductionData`] if the database contains
+    /// tenants or users the seeder did not create
+    ///
+    /// Seeded tenants carry [`DEV_SEED_METADATA_KEY`] in their metadata and
+    /// seeded users have addresses under [`DEV_SEED_EMAIL_TLD`]; anything else
+    /// is treated as real data.
+    pub async fn check_database_is_disposable(&self) -> Result<(), DevSeedError> {
+        let (tenants, users): (i64, i64) = sqlx::query_as(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM tenants WHERE metadata IS NULL OR metadata -> $1 IS NULL),
+                (SELECT COUNT(*) FROM users WHERE email NOT LIKE $2)
+            "#,
+        )
+        .bind(DEV_SEED_METADATA_KEY)
+        .bind(format!("%.{}", DEV_SEED_EMAIL_TLD))
+        .fetch_one(&self.pool)
+        .await?;
+
+        if tenants > 0 || users > 0 {
+            return Err(DevSeedError::ProductionData { tenants, users });
+        }
+        Ok(())
+    }
+
+    /// Create the tenants of `options` with their users and security data
+    ///
+    /// Stops at the first error; what was created before stays in place.
+    pub async fn run(&self, options: &DevSeedOptions) -> Result<SeedSummary, DevSeedError> {
+        options.validate()?;
+        if !options.force {
+            self.check_database_is_disposable().await?;
+        }
+
+        let mut rng = StdRng::seed_from_u64(options.seed);
+        let mut summary = SeedSummary {
+            seed: options.seed,
+            tenants: Vec::with_capacity(options.tenants),
+            counts: SeedCounts::default(),
+        };
+        let mut subdomains = HashSet::new();
+        for _ in 0..options.tenants {
+            let tenant = self
+                .seed_tenant(&mut rng, options, &mut subdomains, &mut summary.counts)
+                .await?;
+            summary.tenants.push(tenant);
+        }
+
+        info!(
+            seed = options.seed,
+            tenants = summary.tenants.len(),
+            users = summary.users().count(),
+            "Seeded development data"
+        );
+        Ok(summary)
+    }
+
+    async fn seed_tenant(
+        &self,
+        rng: &mut StdRng,
+        options: &DevSeedOptions,
+        subdomains: &mut HashSet<String>,
+        counts: &mut SeedCounts,
+    ) -> Result<SeededTenant, DevSeedError> {
+        let prefix = pick(rng, COMPANY_PREFIXES);
+        let industry = pick(rng, COMPANY_INDUSTRIES);
+        let name = format!("{} {} {}", prefix, industry, pick(rng, COMPANY_SUFFIXES));
+        let subdomain = self
+            .free_subdomain(&slug(&format!("{} {}", prefix, industry)), subdomains)
+            .await?;
+        let plan = if rng.random_bool(0.5) {
+            TenantPlanType::Professional
+        } else {
+            TenantPlanType::Enterprise
+        };
+
+        let mut emails = HashSet::new();
+        let admin_email = person_email(rng, &subdomain, &mut emails);
+        let admin_password = password(rng);
+        let created = self
+            .tenant_service
+            .create_tenant_with_admin(CreateTenantWithAdminDto {
+                tenant: CreateTenantDto {
+                    name: name.clone(),
+                    subdomain: subdomain.clone(),
+                    metadata: Some(seed_metadata(options.seed)?),
+                },
+                admin_email: admin_email.clone(),
+                admin_password: admin_password.clone(),
+                initial_plan: Some(plan),
+            })
+            .await?;
+        let tenant_id = created.tenant.id;
+        self.user_service
+            .verify_email(created.admin_user.id)
+            .await?;
+
+        let mut users = vec![SeededUser {
+            id: created.admin_user.id,
+            email: admin_email,
+            password: admin_password,
+            role: SeededRole::Admin,
+            email_verified: true,
+        }];
+        for _ in 0..options.members_per_tenant {
+            let email = person_email(rng, &subdomain, &mut emails);
+            let password = password(rng);
+            let user = self
+                .user_service
+                .register(CreateUser {
+                    email: email.clone(),
+                    password: password.clone(),
+                })
+                .await?;
+            self.tenant_service
+                .add_user_to_tenant(
+                    &tenant_id,
+                    CreateTenantUserDto {
+                        user_id: user.id,
+                        tenant_role: SeededRole::Member.as_str().to_string(),
+                        is_active: Some(true),
+                    },
+                )
+                .await?;
+            let email_verified = rng.random_bool(0.75);
+            if email_verified {
+                self.user_service.verify_email(user.id).await?;
+            }
+            users.push(SeededUser {
+                id: user.id,
+                email,
+                password,
+                role: SeededRole::Member,
+                email_verified,
+            });
+        }
+
+        for (index, user) in users.iter().enumerate() {
+            let session = self.seed_sessions(rng, user.id, counts).await?;
+            self.seed_verification_code(rng, tenant_id, user.id, index, counts)
+                .await?;
+            // Admins sign in from a known device, a few members from unusual places
+            if user.role == SeededRole::Admin || rng.random_bool(0.25) {
+                self.seed_security_data(rng, tenant_id, user.id, &session, counts)
+                    .await?;
+            }
+        }
+
+        Ok(SeededTenant {
+            id: tenant_id,
+            name,
+            subdomain,
+            plan,
+            users,
+        })
+    }
+
+    /// A subdomain derived from `base` not taken in the database or this run
+    async fn free_subdomain(
+        &self,
+        base: &str,
+        taken: &mut HashSet<String>,
+    ) -> Result<String, DevSeedError> {
+        let mut candidate = base.to_string();
+        let mut suffix = 1;
+        loop {
+            let in_use = taken.contains(&candidate)
+                || match self
+                    .tenant_service
+                    .get_tenant_by_subdomain(&candidate)
+                    .await
+                {
+                    Ok(_) => true,
+                    Err(TenantServiceError::NotFound(_)) => false,
+                    Err(e) => return Err(e.into()),
+                };
+            if !in_use {
+                taken.insert(candidate.clone());
+                return Ok(candidate);
+            }
+            suffix += 1;
+            candidate = format!("{}-{}", base, suffix);
+        }
+    }
+
+    /// An active session and, for about half of the users, an expired one
+    ///
+    /// Returns the active session.
+    async fn seed_sessions(
+        &self,
+        rng: &mut StdRng,
+        user_id: Uuid,
+        counts: &mut SeedCounts,
+    ) -> Result<Session, DevSeedError> {
+        let (session, _token) = self
+            .session_service
+            .create_session_with_lifetime(
+                user_id,
+                None,
+                None,
+                Some(ip_address(rng)),
+                Some(pick(rng, USER_AGENTS).to_string()),
+                None,
+                SessionLifetime::Standard,
+            )
+            .await?;
+        counts.active_sessions += 1;
+
+        if rng.random_bool(0.5) {
+            // Expires the moment it is created
+            self.session_service
+                .create_session_with_lifetime(
+                    user_id,
+                    None,
+                    None,
+                    Some(ip_address(rng)),
+                    Some(pick(rng, USER_AGENTS).to_string()),
+                    None,
+                    SessionLifetime::Explicit(Duration::ZERO),
+                )
+                .await?;
+            counts.expired_sessions += 1;
+        }
+
+        Ok(session)
+    }
+
+    /// A verification code whose status cycles with the position of the user
+    ///
+    /// The codes are stored through the repository rather than issued by the
+    /// `VerificationService`, whose rate limit allows only a few codes a minute.
+    async fn seed_verification_code(
+        &self,
+        rng: &mut StdRng,
+        tenant_id: Uuid,
+        user_id: Uuid,
+        index: usize,
+        counts: &mut SeedCounts,
+    ) -> Result<(), DevSeedError> {
+        let config = &self.verification_config;
+        let digits: String = (0..config.code_length)
+            .map(|_| char::from(b'0' + rng.random_range(0..10u8)))
+            .collect();
+        let now = OffsetDateTime::now_utc();
+        let issued_at = match index % 4 {
+            // Issued and expired before the seed ran
+            2 => now - config.expiration_seconds.to_time() * 2,
+            _ => now,
+        };
+        let mut code = VerificationCode::issued_at(
+            tenant_id,
+            user_id,
+            digits,
+            VerificationType::Email,
+            config,
+            issued_at,
+        );
+        match index % 4 {
+            0 => counts.pending_codes += 1,
+            1 => {
+                code.increment_attempts();
+                code.mark_verified();
+                counts.verified_codes += 1;
+            },
+            2 => {
+                code.mark_expired();
+                counts.expired_codes += 1;
+            },
+            _ => {
+                while !code.has_max_attempts(config) {
+                    code.increment_attempts();
+                }
+                code.mark_invalidated();
+                counts.invalidated_codes += 1;
+            },
+        }
+        self.verification_codes
+            .save(&code, &ExplicitTenantContext)
+            .await?;
+        Ok(())
+    }
+
+    /// A browser fingerprint and a risk assessment of `session`
+    async fn seed_security_data(
+        &self,
+        rng: &mut StdRng,
+        tenant_id: Uuid,
+        user_id: Uuid,
+        session: &Session,
+        counts: &mut SeedCounts,
+    ) -> Result<(), DevSeedError> {
+        let user_agent = session
+            .user_agent
+            .clone()
+            .unwrap_or_else(|| pick(rng, USER_AGENTS).to_string());
+        let fingerprint = BrowserFingerprint {
+            user_agent,
+            accept_headers: "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"
+                .to_string(),
+            canvas_hash: Some(format!("{:016x}", rng.random::<u64>())),
+            webgl_hash: Some(format!("{:016x}", rng.random::<u64>())),
+            fonts: Some(vec!["Arial".to_string(), "Helvetica".to_string()]),
+            timezone: Some(*pick(rng, TIMEZONES)),
+            screen_resolution: Some(*pick(rng, &[(1920, 1080), (2560, 1440), (390, 844)])),
+            color_depth: Some(24),
+            plugins: None,
+            language: Some("en-US".to_string()),
+            do_not_track: Some(rng.random_bool(0.3)),
+            cookies_enabled: Some(true),
+            touch_points: Some(0),
+            device_memory: Some(8.0),
+            hardware_concurrency: Some(*pick(rng, &[4, 8, 12])),
+            platform: None,
+        };
+        let ip_address = session
+            .ip_address
+            .clone()
+            .unwrap_or_else(|| ip_address(rng));
+        self.fingerprints
+            .store_fingerprint(
+                tenant_id,
+                user_id,
+                &fingerprint,
+                &ip_address,
+                Some(session.id),
+            )
+            .await
+            .map_err(DevSeedError::Fingerprint)?;
+        counts.fingerprints += 1;
+
+        let factor_count = rng.random_range(0..=2);
+        let factors: Vec<String> = RISK_FACTORS
+            .choose_multiple(rng, factor_count)
+            .map(|factor| factor.to_string())
+            .collect();
+        let score = 0.1 + 0.3 * factors.len() as f64 + rng.random_range(0.0..0.1);
+        self.risk_assessments
+            .save_assessment(&SessionRiskAssessment::new(
+                session.id, user_id, score, factors,
+            ))
+            .await?;
+        counts.risk_assessments += 1;
+
+        Ok(())
+    }
+}
+
+/// Metadata marking a tenant as created by the seed `seed`
+fn seed_metadata(seed: u64) -> Result<BoundedJson, DevSeedError> {
+    #[allow(clippy::disallowed_methods)]
+    let metadata = json!({ DEV_SEED_METADATA_KEY: { "seed": seed } });
+    BoundedJson::new(metadata).map_err(|e| DevSeedError::InvalidOptions(e.to_string()))
+}
+
+fn pick<'a, T>(rng: &mut StdRng, values: &'a [T]) -> &'a T {
+    // The lists are non-empty constants
+    &values[rng.random_range(0..values.len())]
+}
+
+/// Lowercase words joined by hyphens, e.g. `silver-birch-labs`
+fn slug(name: &str) -> String {
+    name.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(char::is_ascii_alphanumeric)
+                .collect::<String>()
+                .to_ascii_lowercase()
+        })
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// `first.last@<subdomain>.example`, numbered if taken in the tenant
+fn person_email(rng: &mut StdRng, subdomain: &str, taken: &mut HashSet<String>) -> String {
+    let local = format!(
+        "{}.{}",
+        pick(rng, FIRST_NAMES).to_ascii_lowercase(),
+        pick(rng, LAST_NAMES).to_ascii_lowercase()
+    );
+    let mut email = format!("{}@{}.{}", local, subdomain, DEV_SEED_EMAIL_TLD);
+    let mut number = 1;
+    while !taken.insert(email.clone()) {
+        number += 1;
+        email = format!("{}{}@{}.{}", local, number, subdomain, DEV_SEED_EMAIL_TLD);
+    }
+    email
+}
+
+/// Three words and four digits, e.g. `Quartz-meadow-raven-4821`
+fn password(rng: &mut StdRng) -> String {
+    let mut first = pick(rng, PASSWORD_WORDS).to_string();
+    if let Some(initial) = first.get_mut(0..1) {
+        initial.make_ascii_uppercase();
+    }
+    format!(
+        "{}-{}-{}-{:04}",
+        first,
+        pick(rng, PASSWORD_WORDS),
+        pick(rng, PASSWORD_WORDS),
+        rng.random_range(0..10_000)
+    )
+}
+
+fn ip_address(rng: &mut StdRng) -> String {
+    format!("{}.{}", pick(rng, IP_PREFIXES), rng.random_range(1..255u8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::password::check_password_strength;
+
+    fn credentials(seed: u64) -> Vec<(String, String)> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut taken = HashSet::new();
+        (0..10)
+            .map(|_| {
+                (
+                    person_email(&mut rng, "acme", &mut taken),
+                    password(&mut rng),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_same_seed_yields_same_credentials() {
+        assert_eq!(credentials(7), credentials(7));
+        assert_ne!(credentials(7), credentials(8));
+    }
+
+    #[test]
+    fn test_generated_passwords_pass_the_strength_check() {
+        for (email, password) in credentials(42) {
+            check_password_strength(&password, &[&email]).expect("Password is strong enough");
+        }
+    }
+
+    #[test]
+    fn test_emails_are_unique_within_a_tenant() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut taken = HashSet::new();
+        let emails: HashSet<String> = (0..200)
+            .map(|_| person_email(&mut rng, "acme", &mut taken))
+            .collect();
+        assert_eq!(emails.len(), 200);
+        assert!(emails.iter().all(|email| email.ends_with("@acme.example")));
+    }
+
+    #[test]
+    fn test_slug() {
+        assert_eq!(slug("Silver Birch Labs"), "silver-birch-labs");
+        assert_eq!(slug("Granite Peak Co."), "granite-peak-co");
+    }
+
+    #[test]
+    fn test_options_are_bounded() {
+        assert!(DevSeedOptions::default().validate().is_ok());
+        let none = DevSeedOptions {
+            tenants: 0,
+            ..DevSeedOptions::default()
+        };
+        assert!(matches!(
+            none.validate(),
+            Err(DevSeedError::InvalidOptions(_))
+        ));
+        let crowded = DevSeedOptions {
+            members_per_tenant: MAX_SEED_MEMBERS_PER_TENANT + 1,
+            ..DevSeedOptions::default()
+        };
+        assert!(crowded.validate().is_err());
+    }
+
+    #[test]
+    fn test_summary_lists_credentials() {
+        let summary = SeedSummary {
+            seed: 42,
+            tenants: vec![SeededTenant {
+                id: Uuid::nil(),
+                name: "Harbor Labs AG".to_string(),
+                subdomain: "harbor-labs".to_string(),
+                plan: TenantPlanType::Professional,
+                users: vec![SeededUser {
+                    id: Uuid::nil(),
+                    email: "anna.berg@harbor-labs.example".to_string(),
+                    password: "Amber-dune-raven-0042".to_string(),
+                    role: SeededRole::Admin,
+                    email_verified: true,
+                }],
+            }],
+            counts: SeedCounts::default(),
+        };
+
+        let table = summary.to_string();
+        assert!(table.starts_with("Seed 42: 1 tenants, 1 users"));
+        assert!(
+            table.contains(
+                "harbor-labs  ADMIN  anna.berg@harbor-labs.example  Amber-dune-raven-0042"
+            )
+        );
+    }
+}
@@ -8,10 +8,15 @@ use std::sync::Arc;
 use tracing::{debug, error, info, instrument};
 
 use crate::models::VerificationType;
+use crate::services::delivery_receipt::DeliveryTracker;
+use crate::services::message_outbox::{MessageOutbox, with_delivery_mode};
 use crate::services::message_provider::{
     EmailProviderConfig, Message as ProviderMessage, MessageProvider, SmtpConfig,
+    provider_status_error, provider_unreachable,
 };
-use acci_core::error::{Error, Result};
+use crate::utils::secret::Secret;
+use acci_core::error::{Error, ErrorKind, Result};
+use acci_core::http_client::HttpClient;
 
 /// EmailProvider using SMTP for delivering messages
 pub struct SmtpEmailProvider {
@@ -59,10 +64,12 @@ impl MessageProvider for SmtpEmailProvider {
             .parse::<Mailbox>()
             .map_err(|e| Error::Other(anyhow::anyhow!("Invalid sender address: {}", e)))?;
 
-        let recipient = message
-            .recipient
-            .parse::<Mailbox>()
-            .map_err(|e| Error::Other(anyhow::anyhow!("Invalid recipient address: {}", e)))?;
+        let recipient = message.recipient.parse::<Mailbox>().map_err(|e| {
+            Error::domain(
+                ErrorKind::InvalidInput,
+                format!("Invalid recipient address: {}", e),
+            )
+        })?;
 
         // Create email structure
         let email = Message::builder()
@@ -91,7 +98,13 @@ impl MessageProvider for SmtpEmailProvider {
                     error = %e,
                     "Failed to send email verification message"
                 );
-                Err(Error::Other(anyhow::anyhow!("Failed to send email: {}", e)))
+                // Rejections with a permanent SMTP reply fail the same way when retried
+                let kind = if e.is_permanent() {
+                    ErrorKind::InvalidInput
+                } else {
+                    ErrorKind::Unavailable
+                };
+                Err(Error::domain(kind, format!("Failed to send email: {}", e)))
             },
         }
     }
@@ -102,18 +115,24 @@ pub struct SendGridEmailProvider {
     /// Configuration for the email provider
     config: EmailProviderConfig,
     /// API key
-    api_key: String,
+    api_key: Secret<String>,
+    /// Client for requests to the SendGrid API
+    client: HttpClient,
 }
 
 impl SendGridEmailProvider {
     /// Create a new SendGrid email provider
-    pub fn new(config: EmailProviderConfig) -> Result<Self> {
+    pub fn new(config: EmailProviderConfig, client: HttpClient) -> Result<Self> {
         // Get API key
         let api_key = config.api_key.clone().ok_or_else(|| {
             Error::Config("API key is required for SendGrid email provider".to_string())
         })?;
 
-        Ok(Self { config, api_key })
+        Ok(Self {
+            config,
+            api_key,
+            client,
+        })
     }
 }
 
@@ -160,24 +179,37 @@ impl MessageProvider for SendGridEmailProvider {
             }]
         });
 
-        // Send the request using reqwest
-        let client = reqwest::Client::new();
-        let res = client
+        // Send the request
+        let request = self
+            .client
             .post("https://api.sendgrid.com/v3/mail/send")
-            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header(
+                "Authorization",
+                format!("Bearer {}", self.api_key.expose_secret()),
+            )
             .header("Content-Type", "application/json")
-            .json(&payload)
-            .send()
+            .json(&payload);
+        let res = self
+            .client
+            .send(request)
             .await
-            .map_err(|e| Error::Other(anyhow::anyhow!("SendGrid API request failed: {}", e)))?;
+            .map_err(|e| provider_unreachable("SendGrid", e))?;
 
         // Check response
         if res.status().is_success() {
+            // Event webhooks refer to the message by this ID
+            let message_id = res
+                .headers()
+                .get("X-Message-Id")
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
             info!(
                 recipient = %message.recipient,
+                message_id = %message_id,
                 "Email sent successfully via SendGrid"
             );
-            Ok(format!("sendgrid:{}", uuid::Uuid::new_v4()))
+            Ok(format!("sendgrid:{}", message_id))
         } else {
             let status = res.status();
             let error_text = res
@@ -190,37 +222,47 @@ impl MessageProvider for SendGridEmailProvider {
                 error = %error_text,
                 "Failed to send email via SendGrid"
             );
-            Err(Error::Other(anyhow::anyhow!(
-                "SendGrid API error: {} - {}",
-                status,
-                error_text
-            )))
+            Err(provider_status_error("SendGrid", status, &error_text))
         }
     }
 }
 
 /// Factory function to create an email provider based on configuration
-pub fn create_email_provider(config: EmailProviderConfig) -> Result<Arc<dyn MessageProvider>> {
-    match config.provider.to_lowercase().as_str() {
-        "smtp" => {
-            let provider = SmtpEmailProvider::new(config)?;
-            Ok(Arc::new(provider))
-        },
-        "sendgrid" => {
-            let provider = SendGridEmailProvider::new(config)?;
-            Ok(Arc::new(provider))
+///
+/// API-based providers send their requests with `client`. Providers configured for
+/// [`DeliveryMode::Queued`](crate::services::DeliveryMode::Queued) are wrapped in
+/// `outbox`, which must then be given. With a `tracker` every sent message is
+/// recorded for delivery tracking. Secrets configured as references must
+/// have been resolved, see [`AuthConfig::resolve_secrets`](crate::AuthConfig::resolve_secrets).
+pub fn create_email_provider(
+    config: EmailProviderConfig,
+    client: &HttpClient,
+    outbox: Option<&MessageOutbox>,
+    tracker: Option<&DeliveryTracker>,
+) -> Result<Arc<dyn MessageProvider>> {
+    config.ensure_secrets_resolved()?;
+    let delivery = config.delivery;
+    let provider: Arc<dyn MessageProvider> = match config.provider.to_lowercase().as_str() {
+        "smtp" => Arc::new(SmtpEmailProvider::new(config)?),
+        "sendgrid" => Arc::new(SendGridEmailProvider::new(config, client.clone())?),
+        _ => {
+            return Err(Error::Config(format!(
+                "Unsupported email provider: {}",
+                config.provider
+            )));
         },
-        _ => Err(Error::Config(format!(
-            "Unsupported email provider: {}",
-            config.provider
-        ))),
-    }
+    };
+
+    with_delivery_mode(provider, delivery, outbox, tracker)
 }
 
 /// Build an SMTP transport from configuration
 fn build_smtp_transport(config: &SmtpConfig) -> Result<AsyncSmtpTransport<Tokio1Executor>> {
     // Create credentials
-    let credentials = Credentials::new(config.username.clone(), config.password.clone());
+    let credentials = Credentials::new(
+        config.username.clone(),
+        config.password.expose_secret().clone(),
+    );
 
     // Create the appropriate transport based on TLS configuration
     let mailer = if config.use_tls {
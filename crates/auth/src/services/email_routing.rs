@@ -0,0 +1,214 @@
+//! Routing of emails across several providers
+//!
+//! [`RoutingEmailProvider`] is a [`MessageProvider`] like any single provider,
+//! so services sending mail are unaware of the routing. For every message it
+//! collects the providers of all matching rules followed by the default
+//! provider and sends with the first one that is healthy. A provider that
+//! fails to send is skipped for a cooldown period.
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use time::OffsetDateTime;
+use tracing::{debug, warn};
+
+use crate::models::VerificationType;
+use crate::services::delivery_receipt::DeliveryTracker;
+use crate::services::email_provider::create_email_provider;
+use crate::services::message_outbox::MessageOutbox;
+use crate::services::message_provider::{
+    EmailRoutingRule, Message, MessageProvider, MessageProviderConfig,
+};
+use crate::utils::clock::{Clock, SystemClock};
+use crate::utils::duration::DurationSecs;
+use acci_core::error::{Error, Result};
+use acci_core::http_client::HttpClient;
+
+/// Name under which the provider configured as `email` is registered
+pub const DEFAULT_EMAIL_PROVIDER: &str = "default";
+
+/// Email provider dispatching to named providers by routing rules
+pub struct RoutingEmailProvider<C: Clock = SystemClock> {
+    providers: HashMap<String, Arc<dyn MessageProvider>>,
+    rules: Vec<EmailRoutingRule>,
+    default_provider: String,
+    cooldown: DurationSecs,
+    /// Providers that failed recently and until when they are skipped
+    unhealthy_until: Mutex<HashMap<String, OffsetDateTime>>,
+    clock: C,
+}
+
+impl RoutingEmailProvider {
+    /// Create a router sending unmatched messages with `default_provider`
+    pub fn new(default_provider: impl Into<String>, cooldown: DurationSecs) -> Self {
+        Self::with_clock(default_provider, cooldown, SystemClock)
+    }
+}
+
+impl<C: Clock> RoutingEmailProvider<C> {
+    /// Create a router that reads the current time from `clock`
+    pub fn with_clock(
+        default_provider: impl Into<String>,
+        cooldown: DurationSecs,
+        clock: C,
+    ) -> Self {
+        Self {
+            providers: HashMap::new(),
+            rules: Vec::new(),
+            default_provider: default_provider.into(),
+            cooldown,
+            unhealthy_until: Mutex::new(HashMap::new()),
+            clock,
+        }
+    }
+
+    /// Register a provider under `name`
+    pub fn with_provider(
+        mut self,
+        name: impl Into<String>,
+        provider: Arc<dyn MessageProvider>,
+    ) -> Self {
+        self.providers.insert(name.into(), provider);
+        self
+    }
+
+    /// Append a routing rule; earlier rules take precedence
+    pub fn with_rule(mut self, rule: EmailRoutingRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Skip the provider until the cooldown is over
+    pub fn mark_unhealthy(&self, name: &str) {
+        let until = self.clock.now() + self.cooldown.to_time();
+        self.unhealthy_until
+            .lock()
+            .expect("provider health lock poisoned")
+            .insert(name.to_string(), until);
+    }
+
+    /// Use the provider again right away
+    pub fn mark_healthy(&self, name: &str) {
+        self.unhealthy_until
+            .lock()
+            .expect("provider health lock poisoned")
+            .remove(name);
+    }
+
+    /// Whether the provider is currently used for sending
+    pub fn is_healthy(&self, name: &str) -> bool {
+        let now = self.clock.now();
+        self.unhealthy_until
+            .lock()
+            .expect("provider health lock poisoned")
+            .get(name)
+            .is_none_or(|until| *until <= now)
+    }
+
+    /// Registered providers for `message` in order of preference
+    ///
+    /// Healthy providers come first; unhealthy ones are only tried once
+    /// all healthy ones failed.
+    fn candidates(&self, message: &Message) -> Vec<(&str, &Arc<dyn MessageProvider>)> {
+        let mut names: Vec<&str> = Vec::new();
+        for name in self
+            .rules
+            .iter()
+            .filter(|rule| rule.matches(message))
+            .map(|rule| rule.provider.as_str())
+            .chain(std::iter::once(self.default_provider.as_str()))
+        {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+
+        let (healthy, unhealthy): (Vec<_>, Vec<_>) = names
+            .into_iter()
+            .filter_map(|name| self.providers.get(name).map(|provider| (name, provider)))
+            .partition(|(name, _)| self.is_healthy(name));
+        healthy.into_iter().chain(unhealthy).collect()
+    }
+}
+
+#[async_trait]
+impl<C: Clock> MessageProvider for RoutingEmailProvider<C> {
+    fn verification_type(&self) -> VerificationType {
+        VerificationType::Email
+    }
+
+    async fn send_message(&self, message: Message) -> Result<String> {
+        let mut last_error = None;
+        for (name, provider) in self.candidates(&message) {
+            match provider.send_message(message.clone()).await {
+                Ok(message_id) => {
+                    debug!(provider = name, tenant_id = %message.tenant_id, "Routed email");
+                    self.mark_healthy(name);
+                    return Ok(message_id);
+                },
+                Err(err) => {
+                    warn!(
+                        provider = name,
+                        error = %err,
+                        "Email provider failed, trying the next one"
+                    );
+                    self.mark_unhealthy(name);
+                    last_error = Some(err);
+                },
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            Error::Config(format!(
+                "No email provider available for tenant {}",
+                message.tenant_id
+            ))
+        }))
+    }
+}
+
+/// Factory function to create the email provider of a message provider configuration
+///
+/// Without additional providers this is the provider configured as `email`.
+/// Otherwise a [`RoutingEmailProvider`] is returned; providers that cannot be
+/// created are logged and left out, so that a broken provider only fails the
+/// messages routed to it. Creation fails only if no provider could be created.
+pub fn create_routing_email_provider(
+    config: &MessageProviderConfig,
+    client: &HttpClient,
+    outbox: Option<&MessageOutbox>,
+    tracker: Option<&DeliveryTracker>,
+) -> Result<Arc<dyn MessageProvider>> {
+    let routing = &config.email_routing;
+    if routing.providers.is_empty() {
+        return create_email_provider(config.email.clone(), client, outbox, tracker);
+    }
+
+    let mut router =
+        RoutingEmailProvider::new(DEFAULT_EMAIL_PROVIDER, routing.unhealthy_cooldown_secs);
+    let mut created = 0;
+    for (name, provider_config) in std::iter::once((DEFAULT_EMAIL_PROVIDER, &config.email)).chain(
+        routing
+            .providers
+            .iter()
+            .map(|(name, provider_config)| (name.as_str(), provider_config)),
+    ) {
+        match create_email_provider(provider_config.clone(), client, outbox, tracker) {
+            Ok(provider) => {
+                router = router.with_provider(name, provider);
+                created += 1;
+            },
+            Err(err) => warn!(provider = name, error = %err, "Skipping email provider"),
+        }
+    }
+    if created == 0 {
+        return Err(Error::Config(
+            "None of the configured email providers could be created".to_string(),
+        ));
+    }
+
+    for rule in &routing.rules {
+        router = router.with_rule(rule.clone());
+    }
+    Ok(Arc::new(router))
+}
@@ -0,0 +1,786 @@
+//! Export of audit events and security alerts to an external SIEM
+//!
+//! [`EventExporter`] formats events in the ArcSight Common Event Format (CEF)
+//! and sends them to syslog receivers over UDP or TCP, framed as RFC 5424
+//! messages, or to HTTPS collectors. Events are queued in memory and sent by
+//! a background task; once the queue is full the oldest event is dropped and
+//! counted, so a slow or unreachable SIEM never delays a login.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::repository::{AuditEvent, TenantAuditEvent};
+use crate::security::types::{LoginAttempt, RiskLevel};
+use crate::utils::duration::DurationMs;
+use crate::utils::secret::Secret;
+use acci_core::http_client::HttpClient;
+use acci_core::secret_store::SecretResolver;
+
+/// Action of the alert raised for a suspicious login attempt
+pub const SUSPICIOUS_LOGIN_ACTION: &str = "SUSPICIOUS_LOGIN_ATTEMPT";
+
+/// Errors sending events to an export target
+#[derive(Debug, Error)]
+pub enum EventExportError {
+    #[error("Invalid export configuration: {0}")]
+    Config(String),
+
+    #[error("Failed to send event: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Collector request failed: {0}")]
+    Http(String),
+
+    #[error("Sending the event timed out")]
+    Timeout,
+}
+
+/// Configuration of the SIEM export
+///
+/// Events of every tenant go to `targets`; events of a tenant listed in
+/// `tenant_targets` additionally go to that tenant's targets. Export is
+/// disabled while neither is configured.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventExportConfig {
+    /// Targets receiving the events of all tenants
+    pub targets: Vec<ExportTarget>,
+    /// Additional targets per tenant
+    pub tenant_targets: HashMap<Uuid, Vec<ExportTarget>>,
+    /// Events held while the targets are slow; the oldest are dropped beyond this
+    pub queue_capacity: usize,
+    /// Time allowed for sending one event to one target
+    pub send_timeout_ms: DurationMs,
+    /// HOSTNAME of the syslog header, `-` if unset
+    pub hostname: Option<String>,
+    /// APP-NAME of the syslog header
+    pub app_name: String,
+    /// Address of this deployment, reported as the CEF `dst`
+    pub device_address: Option<String>,
+}
+
+impl Default for EventExportConfig {
+    fn default() -> Self {
+        Self {
+            targets: Vec::new(),
+            tenant_targets: HashMap::new(),
+            queue_capacity: 10_000,
+            send_timeout_ms: DurationMs::from_millis(5000),
+            hostname: None,
+            app_name: "acci".to_string(),
+            device_address: None,
+        }
+    }
+}
+
+impl EventExportConfig {
+    /// Whether any export target is configured
+    pub fn is_enabled(&self) -> bool {
+        !self.targets.is_empty() || self.tenant_targets.values().any(|t| !t.is_empty())
+    }
+
+    /// Resolve the authorization headers of the HTTPS collectors
+    pub async fn resolve_secrets(&mut self, resolver: &mut SecretResolver<'_>, field: &str) {
+        for (index, target) in self.targets.iter_mut().enumerate() {
+            target
+                .resolve_secrets(resolver, &format!("{field}.targets.{index}"))
+                .await;
+        }
+        for (tenant_id, targets) in &mut self.tenant_targets {
+            for (index, target) in targets.iter_mut().enumerate() {
+                target
+                    .resolve_secrets(
+                        resolver,
+                        &format!("{field}.tenant_targets.{tenant_id}.{index}"),
+                    )
+                    .await;
+            }
+        }
+    }
+}
+
+/// Where exported events are sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "transport", rename_all = "snake_case")]
+pub enum ExportTarget {
+    /// Syslog over UDP, one message per datagram
+    Udp {
+        /// `host:port` of the receiver
+        address: String,
+    },
+    /// Syslog over TCP with octet-counting framing
+    Tcp {
+        /// `host:port` of the receiver
+        address: String,
+    },
+    /// HTTPS collector receiving one CEF line per POST request
+    Https {
+        url: String,
+        /// Value of the `Authorization` header, e.g. `Splunk <token>`
+        authorization: Option<Secret<String>>,
+    },
+}
+
+impl ExportTarget {
+    async fn resolve_secrets(&mut self, resolver: &mut SecretResolver<'_>, field: &str) {
+        if let Self::Https {
+            authorization: Some(authorization),
+            ..
+        } = self
+        {
+            resolver
+                .resolve(&format!("{field}.authorization"), authorization)
+                .await;
+        }
+    }
+}
+
+/// Whether an event records a change or warns about an attack
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventCategory {
+    /// Written to an audit log
+    Audit,
+    /// Raised by the security checks
+    Alert,
+}
+
+impl EventCategory {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Audit => "audit",
+            Self::Alert => "alert",
+        }
+    }
+}
+
+/// An audit event or security alert to export
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SecurityEvent {
+    pub category: EventCategory,
+    /// Audit action or alert type, e.g. `TENANT_UPDATE`
+    pub action: String,
+    /// Human-readable name of the action
+    pub name: String,
+    pub risk_level: RiskLevel,
+    pub tenant_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+    /// Username as entered, only known for alerts on login attempts
+    pub username: Option<String>,
+    pub source_ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub occurred_at: OffsetDateTime,
+}
+
+impl SecurityEvent {
+    /// An audit event for `action` without any further attributes
+    pub fn audit(action: &str, occurred_at: OffsetDateTime) -> Self {
+        Self {
+            category: EventCategory::Audit,
+            action: action.to_string(),
+            name: action.to_lowercase().replace('_', " "),
+            risk_level: audit_risk_level(action),
+            tenant_id: None,
+            user_id: None,
+            username: None,
+            source_ip: None,
+            user_agent: None,
+            occurred_at,
+        }
+    }
+
+    /// An alert for a login attempt assessed above [`RiskLevel::Low`]
+    pub fn suspicious_login(attempt: &LoginAttempt, risk_level: RiskLevel) -> Self {
+        Self {
+            category: EventCategory::Alert,
+            action: SUSPICIOUS_LOGIN_ACTION.to_string(),
+            name: "Suspicious login attempt".to_string(),
+            risk_level,
+            tenant_id: attempt.tenant_id.parse().ok(),
+            user_id: None,
+            username: Some(attempt.username.clone()),
+            source_ip: Some(attempt.ip_address.clone()),
+            user_agent: Some(attempt.user_agent.clone()),
+            occurred_at: OffsetDateTime::now_utc(),
+        }
+    }
+}
+
+impl From<&AuditEvent> for SecurityEvent {
+    fn from(event: &AuditEvent) -> Self {
+        Self {
+            user_id: Some(event.user_id),
+            source_ip: event.ip_address.clone(),
+            user_agent: event.user_agent.clone(),
+            ..Self::audit(&event.action, OffsetDateTime::now_utc())
+        }
+    }
+}
+
+impl From<&TenantAuditEvent> for SecurityEvent {
+    fn from(event: &TenantAuditEvent) -> Self {
+        Self {
+            tenant_id: Some(event.tenant_id),
+            user_id: event.user_id,
+            source_ip: event.ip_address.clone(),
+            user_agent: event.user_agent.clone(),
+            ..Self::audit(&event.action, OffsetDateTime::now_utc())
+        }
+    }
+}
+
+/// Risk of an audited action; only actions taken because of an attack rank above low
+fn audit_risk_level(action: &str) -> RiskLevel {
+    match action {
+        "SESSION_INVALIDATED_SECURITY_BREACH" => RiskLevel::High,
+        "SESSION_INVALIDATED_SUSPICIOUS_ACTIVITY"
+        | "SESSION_INVALIDATED_SUSPICIOUS_LOCATION"
+        | "SUSPICIOUS_LOCATION_DETECTED"
+        | "LOGIN_LOCATION_BLOCKED" => RiskLevel::Medium,
+        _ => RiskLevel::Low,
+    }
+}
+
+/// Formats events as CEF lines
+#[derive(Debug, Clone)]
+pub struct CefFormatter {
+    vendor: String,
+    product: String,
+    version: String,
+    device_address: Option<String>,
+}
+
+impl Default for CefFormatter {
+    fn default() -> Self {
+        Self {
+            vendor: "Broccode".to_string(),
+            product: "ACCI Framework".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            device_address: None,
+        }
+    }
+}
+
+impl CefFormatter {
+    /// Report `version` as the device version instead of the crate version
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// Report `address` as the `dst` of every event
+    pub fn with_device_address(mut self, address: impl Into<String>) -> Self {
+        self.device_address = Some(address.into());
+        self
+    }
+
+    /// CEF severity of a risk level, on the scale from 0 to 10
+    pub fn severity(risk_level: RiskLevel) -> u8 {
+        match risk_level {
+            RiskLevel::Low => 3,
+            RiskLevel::Medium => 5,
+            RiskLevel::High => 8,
+            RiskLevel::Critical => 10,
+        }
+    }
+
+    /// Format `event` as a single CEF line
+    ///
+    /// The action is the `deviceEventClassId`, and the tenant is sent in the
+    /// custom string extension `cs1` labelled `tenant`.
+    pub fn format(&self, event: &SecurityEvent) -> String {
+        let mut extension = vec![
+            (
+                "rt",
+                (event.occurred_at.unix_timestamp_nanos() / 1_000_000).to_string(),
+            ),
+            ("cat", event.category.as_str().to_string()),
+        ];
+        let optional = [
+            ("suser", event.username.clone()),
+            ("suid", event.user_id.map(|id| id.to_string())),
+            ("src", event.source_ip.clone()),
+            ("dst", self.device_address.clone()),
+            ("requestClientApplication", event.user_agent.clone()),
+        ];
+        extension.extend(
+            optional
+                .into_iter()
+                .filter_map(|(key, value)| value.map(|value| (key, value))),
+        );
+        if let Some(tenant_id) = event.tenant_id {
+            extension.push(("cs1Label", "tenant".to_string()));
+            extension.push(("cs1", tenant_id.to_string()));
+        }
+
+        let extension = extension
+            .iter()
+            .map(|(key, value)| format!("{key}={}", escape_extension(value)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        format!(
+            "CEF:0|{}|{}|{}|{}|{}|{}|{}",
+            escape_header(&self.vendor),
+            escape_header(&self.product),
+            escape_header(&self.version),
+            escape_header(&event.action),
+            escape_header(&event.name),
+            Self::severity(event.risk_level),
+            extension
+        )
+    }
+}
+
+fn escape_header(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\|")
+}
+
+fn escape_extension(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('=', "\\=")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// Header fields of the RFC 5424 messages sent to syslog receivers
+#[derive(Debug, Clone)]
+pub struct SyslogFormat {
+    hostname: String,
+    app_name: String,
+}
+
+impl SyslogFormat {
+    /// Facility `auth` (security/authorization messages)
+    const FACILITY: u8 = 4;
+
+    pub fn new(hostname: Option<&str>, app_name: &str) -> Self {
+        Self {
+            hostname: hostname.unwrap_or("-").to_string(),
+            app_name: app_name.to_string(),
+        }
+    }
+
+    /// Wrap `cef` in an RFC 5424 message
+    ///
+    /// The MSGID is the event category; PROCID and STRUCTURED-DATA are nil.
+    pub fn message(&self, event: &SecurityEvent, cef: &str) -> String {
+        let severity = match event.risk_level {
+            RiskLevel::Low => 6,
+            RiskLevel::Medium => 4,
+            RiskLevel::High => 3,
+            RiskLevel::Critical => 2,
+        };
+        let timestamp = event
+            .occurred_at
+            .format(&Rfc3339)
+            .unwrap_or_else(|_| "-".to_string());
+        format!(
+            "<{}>1 {} {} {} - {} - {}",
+            Self::FACILITY * 8 + severity,
+            timestamp,
+            self.hostname,
+            self.app_name,
+            event.category.as_str(),
+            cef
+        )
+    }
+}
+
+/// Sends formatted events to one target
+#[async_trait]
+pub trait EventTransport: Send + Sync {
+    /// Target named in logs
+    fn name(&self) -> &str;
+
+    /// Send `event`, formatted as the CEF line `cef`
+    async fn send(&self, event: &SecurityEvent, cef: &str) -> Result<(), EventExportError>;
+}
+
+/// Syslog receiver reached over UDP
+pub struct UdpSyslogTransport {
+    address: String,
+    format: SyslogFormat,
+    socket: tokio::sync::Mutex<Option<UdpSocket>>,
+}
+
+impl UdpSyslogTransport {
+    pub fn new(address: impl Into<String>, format: SyslogFormat) -> Self {
+        Self {
+            address: address.into(),
+            format,
+            socket: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    async fn connect(&self) -> Result<UdpSocket, EventExportError> {
+        let target = tokio::net::lookup_host(&self.address)
+            .await?
+            .next()
+            .ok_or_else(|| {
+                EventExportError::Config(format!("{} does not resolve", self.address))
+            })?;
+        let local = if target.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(local).await?;
+        socket.connect(target).await?;
+        Ok(socket)
+    }
+}
+
+#[async_trait]
+impl EventTransport for UdpSyslogTransport {
+    fn name(&self) -> &str {
+        &self.address
+    }
+
+    async fn send(&self, event: &SecurityEvent, cef: &str) -> Result<(), EventExportError> {
+        let message = self.format.message(event, cef);
+        let mut slot = self.socket.lock().await;
+        // A failed socket is dropped and replaced on the next event
+        let socket = match slot.take() {
+            Some(socket) => socket,
+            None => self.connect().await?,
+        };
+        socket.send(message.as_bytes()).await?;
+        *slot = Some(socket);
+        Ok(())
+    }
+}
+
+/// Syslog receiver reached over TCP
+///
+/// Messages are framed by octet counting (RFC 6587), i.e. prefixed with
+/// their length in bytes and a space. The connection is kept open and
+/// re-established on the next event after it failed.
+pub struct TcpSyslogTransport {
+    address: String,
+    format: SyslogFormat,
+    stream: tokio::sync::Mutex<Option<TcpStream>>,
+}
+
+impl TcpSyslogTransport {
+    pub fn new(address: impl Into<String>, format: SyslogFormat) -> Self {
+        Self {
+            address: address.into(),
+            format,
+            stream: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// Frame `message` for a TCP syslog stream
+    pub fn frame(message: &str) -> String {
+        format!("{} {}", message.len(), message)
+    }
+}
+
+#[async_trait]
+impl EventTransport for TcpSyslogTransport {
+    fn name(&self) -> &str {
+        &self.address
+    }
+
+    async fn send(&self, event: &SecurityEvent, cef: &str) -> Result<(), EventExportError> {
+        let frame = Self::frame(&self.format.message(event, cef));
+        let mut slot = self.stream.lock().await;
+        // A failed connection is dropped and re-established on the next event
+        let mut stream = match slot.take() {
+            Some(stream) => stream,
+            None => TcpStream::connect(&self.address).await?,
+        };
+        stream.write_all(frame.as_bytes()).await?;
+        *slot = Some(stream);
+        Ok(())
+    }
+}
+
+/// HTTPS collector receiving CEF lines, e.g. a Splunk HTTP Event Collector raw endpoint
+pub struct HttpsCollectorTransport {
+    url: String,
+    authorization: Option<Secret<String>>,
+    client: HttpClient,
+}
+
+impl HttpsCollectorTransport {
+    /// Create a transport posting to `url`, which must use HTTPS
+    pub fn new(
+        url: impl Into<String>,
+        authorization: Option<Secret<String>>,
+        client: HttpClient,
+    ) -> Result<Self, EventExportError> {
+        let url = url.into();
+        if !url.starts_with("https://") {
+            return Err(EventExportError::Config(format!(
+                "Collector URL {url} does not use HTTPS"
+            )));
+        }
+        Ok(Self {
+            url,
+            authorization,
+            client,
+        })
+    }
+}
+
+#[async_trait]
+impl EventTransport for HttpsCollectorTransport {
+    fn name(&self) -> &str {
+        &self.url
+    }
+
+    async fn send(&self, _event: &SecurityEvent, cef: &str) -> Result<(), EventExportError> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "text/plain")
+            .body(cef.to_string());
+        if let Some(authorization) = &self.authorization {
+            request = request.header(
+                reqwest::header::AUTHORIZATION,
+                authorization.expose_secret().as_str(),
+            );
+        }
+        let response = self
+            .client
+            .send(request)
+            .await
+            .map_err(|e| EventExportError::Http(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(EventExportError::Http(format!(
+                "Collector responded with {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Bounded queue dropping its oldest event when full
+struct EventQueue {
+    events: Mutex<VecDeque<SecurityEvent>>,
+    capacity: usize,
+    ready: Notify,
+    dropped: AtomicU64,
+}
+
+impl EventQueue {
+    fn push(&self, event: SecurityEvent) {
+        let mut events = self.events.lock().expect("event queue lock poisoned");
+        if events.len() >= self.capacity {
+            events.pop_front();
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            record_dropped();
+        }
+        events.push_back(event);
+        drop(events);
+        self.ready.notify_one();
+    }
+
+    fn pop(&self) -> Option<SecurityEvent> {
+        self.events
+            .lock()
+            .expect("event queue lock poisoned")
+            .pop_front()
+    }
+}
+
+/// Queues events and sends them to the configured targets in the background
+///
+/// [`EventExporter::export`] never waits; run [`EventExporter::spawn`] once to
+/// start sending.
+pub struct EventExporter {
+    queue: Arc<EventQueue>,
+    targets: Arc<Vec<Arc<dyn EventTransport>>>,
+    tenant_targets: Arc<HashMap<Uuid, Vec<Arc<dyn EventTransport>>>>,
+    formatter: CefFormatter,
+    send_timeout: std::time::Duration,
+}
+
+impl EventExporter {
+    /// Create an exporter for the targets of `config`
+    ///
+    /// HTTPS collectors are reached with `client`.
+    pub fn new(config: &EventExportConfig, client: HttpClient) -> Result<Self, EventExportError> {
+        let format = SyslogFormat::new(config.hostname.as_deref(), &config.app_name);
+        let transport =
+            |target: &ExportTarget| -> Result<Arc<dyn EventTransport>, EventExportError> {
+                let transport: Arc<dyn EventTransport> = match target {
+                    ExportTarget::Udp { address } => {
+                        Arc::new(UdpSyslogTransport::new(address.clone(), format.clone()))
+                    },
+                    ExportTarget::Tcp { address } => {
+                        Arc::new(TcpSyslogTransport::new(address.clone(), format.clone()))
+                    },
+                    ExportTarget::Https { url, authorization } => {
+                        Arc::new(HttpsCollectorTransport::new(
+                            url.clone(),
+                            authorization.clone(),
+                            client.clone(),
+                        )?)
+                    },
+                };
+                Ok(transport)
+            };
+
+        let targets = config
+            .targets
+            .iter()
+            .map(&transport)
+            .collect::<Result<Vec<_>, _>>()?;
+        let tenant_targets = config
+            .tenant_targets
+            .iter()
+            .map(|(tenant_id, targets)| {
+                let targets = targets
+                    .iter()
+                    .map(&transport)
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok((*tenant_id, targets))
+            })
+            .collect::<Result<HashMap<_, _>, EventExportError>>()?;
+
+        let mut formatter = CefFormatter::default();
+        if let Some(address) = &config.device_address {
+            formatter = formatter.with_device_address(address.clone());
+        }
+        Ok(
+            Self::with_transports(targets, tenant_targets, config.queue_capacity)
+                .with_formatter(formatter)
+                .with_send_timeout(config.send_timeout_ms.as_duration()),
+        )
+    }
+
+    /// Create an exporter sending to the given transports
+    pub fn with_transports(
+        targets: Vec<Arc<dyn EventTransport>>,
+        tenant_targets: HashMap<Uuid, Vec<Arc<dyn EventTransport>>>,
+        queue_capacity: usize,
+    ) -> Self {
+        Self {
+            queue: Arc::new(EventQueue {
+                events: Mutex::new(VecDeque::new()),
+                capacity: queue_capacity.max(1),
+                ready: Notify::new(),
+                dropped: AtomicU64::new(0),
+            }),
+            targets: Arc::new(targets),
+            tenant_targets: Arc::new(tenant_targets),
+            formatter: CefFormatter::default(),
+            send_timeout: EventExportConfig::default().send_timeout_ms.as_duration(),
+        }
+    }
+
+    /// Format events with `formatter`
+    pub fn with_formatter(mut self, formatter: CefFormatter) -> Self {
+        self.formatter = formatter;
+        self
+    }
+
+    /// Give up sending an event to a target after `timeout`
+    pub fn with_send_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.send_timeout = timeout;
+        self
+    }
+
+    /// Queue `event` for export, dropping the oldest queued event if the queue is full
+    pub fn export(&self, event: SecurityEvent) {
+        self.queue.push(event);
+    }
+
+    /// Number of events dropped because the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.queue.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Number of events waiting to be sent
+    pub fn queued(&self) -> usize {
+        self.queue
+            .events
+            .lock()
+            .expect("event queue lock poisoned")
+            .len()
+    }
+
+    /// Send the queued events until the queue is empty
+    ///
+    /// Returns the number of events taken from the queue. Failures are
+    /// logged and the event is not retried.
+    pub async fn send_queued(&self) -> usize {
+        let mut sent = 0;
+        while let Some(event) = self.queue.pop() {
+            self.send(&event).await;
+            sent += 1;
+        }
+        sent
+    }
+
+    /// Send queued events on a dedicated task until the runtime shuts down
+    pub fn spawn(&self) -> JoinHandle<()> {
+        let worker = Self {
+            queue: self.queue.clone(),
+            targets: self.targets.clone(),
+            tenant_targets: self.tenant_targets.clone(),
+            formatter: self.formatter.clone(),
+            send_timeout: self.send_timeout,
+        };
+        tokio::spawn(async move {
+            info!("SIEM event exporter started");
+            loop {
+                worker.queue.ready.notified().await;
+                worker.send_queued().await;
+            }
+        })
+    }
+
+    async fn send(&self, event: &SecurityEvent) {
+        let cef = self.formatter.format(event);
+        let tenant_targets = event
+            .tenant_id
+            .and_then(|tenant_id| self.tenant_targets.get(&tenant_id))
+            .into_iter()
+            .flatten();
+        for target in self.targets.iter().chain(tenant_targets) {
+            let result = tokio::time::timeout(self.send_timeout, target.send(event, &cef))
+                .await
+                .unwrap_or(Err(EventExportError::Timeout));
+            match result {
+                Ok(()) => debug!(
+                    target = target.name(),
+                    action = %event.action,
+                    "Exported security event"
+                ),
+                Err(e) => warn!(
+                    target = target.name(),
+                    action = %event.action,
+                    error = %e,
+                    "Failed to export security event"
+                ),
+            }
+        }
+    }
+}
+
+/// Count an event dropped because the export queue was full
+#[cfg(feature = "metrics")]
+fn record_dropped() {
+    metrics::counter!("auth_event_export_dropped_total").increment(1);
+}
+
+#[cfg(not(feature = "metrics"))]
+fn record_dropped() {}
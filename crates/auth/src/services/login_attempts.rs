@@ -0,0 +1,130 @@
+//! Durable record of login attempts
+//!
+//! Brute force and credential stuffing protection only keep short-lived
+//! counters in Redis. [`LoginAttemptRecorder`] additionally stores every
+//! password login in the database for analytics and compliance reporting.
+//! Usernames are stored as a keyed hash, and attempts are written in the
+//! background so that a slow database does not delay logins.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::Arc;
+use time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::models::login_attempt::{LoginAttemptRecord, LoginAttemptStats, LoginOutcome};
+use crate::repository::{LoginAttemptRepository, RepositoryError};
+use crate::security::trusted_device::device_fingerprint_hash;
+use crate::security::types::{GeoLocation, RiskLevel};
+use crate::session::types::DeviceFingerprint;
+use crate::utils::clock::{Clock, SystemClock};
+
+/// What is known about a login attempt when it ends
+#[derive(Debug, Clone, Default)]
+pub struct LoginAttemptContext {
+    pub tenant_id: Option<Uuid>,
+    /// Username as entered; only its hash is stored
+    pub username: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub device_fingerprint: Option<DeviceFingerprint>,
+    pub geolocation: Option<GeoLocation>,
+    pub risk_level: Option<RiskLevel>,
+}
+
+/// Stores login attempts and reports on them
+pub struct LoginAttemptRecorder<C: Clock = SystemClock> {
+    repository: Arc<dyn LoginAttemptRepository>,
+    /// Key of the username hashes
+    username_key: Vec<u8>,
+    clock: C,
+}
+
+impl LoginAttemptRecorder {
+    /// Create a recorder hashing usernames with `username_key`
+    pub fn new(repository: Arc<dyn LoginAttemptRepository>, username_key: &[u8]) -> Self {
+        Self::with_clock(repository, username_key, SystemClock)
+    }
+}
+
+impl<C: Clock> LoginAttemptRecorder<C> {
+    /// Create a recorder that reads the current time from `clock`
+    pub fn with_clock(
+        repository: Arc<dyn LoginAttemptRepository>,
+        username_key: &[u8],
+        clock: C,
+    ) -> Self {
+        Self {
+            repository,
+            username_key: username_key.to_vec(),
+            clock,
+        }
+    }
+
+    /// Keyed hash of a username
+    ///
+    /// Usernames are trimmed and lowercased first, so differently typed
+    /// variants of an address hash alike.
+    pub fn hash_username(&self, username: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.username_key)
+            .expect("HMAC accepts keys of any length");
+        mac.update(username.trim().to_lowercase().as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// Store an attempt in the background
+    ///
+    /// Returns right away; failures to store the attempt are logged. The
+    /// returned handle completes once the attempt was written.
+    pub fn record(&self, context: LoginAttemptContext, outcome: LoginOutcome) -> JoinHandle<()> {
+        let (country_code, city) = match context.geolocation {
+            Some(location) => (Some(location.country_code), location.city),
+            None => (None, None),
+        };
+        let attempt = LoginAttemptRecord {
+            id: Uuid::new_v4(),
+            tenant_id: context.tenant_id,
+            username_hash: self.hash_username(&context.username),
+            ip_address: context.ip_address,
+            user_agent: context.user_agent,
+            fingerprint_hash: context
+                .device_fingerprint
+                .as_ref()
+                .map(device_fingerprint_hash),
+            country_code,
+            city,
+            risk_level: context.risk_level,
+            outcome,
+            attempted_at: self.clock.now(),
+        };
+
+        let repository = self.repository.clone();
+        tokio::spawn(async move {
+            match repository.record(&attempt).await {
+                Ok(()) => debug!(
+                    tenant_id = ?attempt.tenant_id,
+                    outcome = attempt.outcome.as_str(),
+                    "Recorded login attempt"
+                ),
+                Err(err) => warn!(
+                    tenant_id = ?attempt.tenant_id,
+                    error = %err,
+                    "Failed to record login attempt"
+                ),
+            }
+        })
+    }
+
+    /// Count the login attempts of a tenant within the last `window` by outcome
+    pub async fn stats(
+        &self,
+        tenant_id: Uuid,
+        window: Duration,
+    ) -> Result<LoginAttemptStats, RepositoryError> {
+        self.repository
+            .stats(tenant_id, self.clock.now() - window)
+            .await
+    }
+}
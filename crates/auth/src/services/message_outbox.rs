@@ -0,0 +1,367 @@
+use async_trait::async_trait;
+use futures::{StreamExt, future::join_all, stream};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration as StdDuration;
+use time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, instrument, warn};
+
+use crate::models::{OutboxMessage, VerificationType};
+use crate::repository::MessageOutboxRepository;
+use crate::services::delivery_receipt::DeliveryTracker;
+use crate::services::message_provider::{DeliveryMode, Message, MessageProvider};
+use crate::utils::clock::{Clock, SystemClock};
+use acci_core::error::{Error, Result};
+
+/// Configuration for the durable message outbox and its dispatcher
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutboxConfig {
+    /// How often the dispatcher polls for due messages (in milliseconds)
+    pub poll_interval_ms: u64,
+    /// Maximum number of messages claimed per provider and poll
+    pub batch_size: u32,
+    /// Attempts after which a message is marked as permanently failed
+    pub max_attempts: u32,
+    /// Delay before the first retry (in seconds), doubled on each further attempt
+    pub base_backoff_secs: i64,
+    /// Upper bound for the retry delay (in seconds)
+    pub max_backoff_secs: i64,
+    /// How long a claimed message is hidden from other dispatchers (in seconds)
+    pub lease_secs: i64,
+    /// Messages sent concurrently through the email provider
+    pub email_concurrency: usize,
+    /// Messages sent concurrently through the SMS provider
+    pub sms_concurrency: usize,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_ms: 1000,
+            batch_size: 50,
+            max_attempts: 5,
+            base_backoff_secs: 30,
+            max_backoff_secs: 3600, // 1 hour
+            lease_secs: 300,        // 5 minutes
+            email_concurrency: 4,
+            sms_concurrency: 2,
+        }
+    }
+}
+
+impl OutboxConfig {
+    /// Concurrency limit for the provider handling the given message type
+    pub fn concurrency_for(&self, message_type: VerificationType) -> usize {
+        let limit = match message_type {
+            VerificationType::Email => self.email_concurrency,
+            VerificationType::Sms => self.sms_concurrency,
+        };
+        limit.max(1)
+    }
+
+    /// Delay before the next attempt once `attempts` deliveries have failed
+    pub fn backoff(&self, attempts: u32) -> Duration {
+        let exp = attempts.saturating_sub(1).min(30);
+        let secs = self
+            .base_backoff_secs
+            .saturating_mul(1_i64 << exp)
+            .min(self.max_backoff_secs);
+        Duration::seconds(secs)
+    }
+}
+
+/// Entry point for queued message delivery.
+///
+/// Wrapping a provider with [`MessageOutbox::wrap`] returns a [`QueuedMessageProvider`]
+/// that only persists messages, and registers the original provider as the
+/// delivery target of the [`MessageDispatcher`] built by [`MessageOutbox::dispatcher`].
+pub struct MessageOutbox<C: Clock + Clone = SystemClock> {
+    repository: Arc<dyn MessageOutboxRepository>,
+    config: OutboxConfig,
+    clock: C,
+    targets: Mutex<HashMap<VerificationType, Arc<dyn MessageProvider>>>,
+}
+
+impl MessageOutbox {
+    /// Create a new message outbox
+    pub fn new(repository: Arc<dyn MessageOutboxRepository>, config: OutboxConfig) -> Self {
+        Self::with_clock(repository, config, SystemClock)
+    }
+}
+
+impl<C: Clock + Clone> MessageOutbox<C> {
+    /// Create a message outbox that reads the current time from `clock`
+    pub fn with_clock(
+        repository: Arc<dyn MessageOutboxRepository>,
+        config: OutboxConfig,
+        clock: C,
+    ) -> Self {
+        Self {
+            repository,
+            config,
+            clock,
+            targets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Queue messages for `provider` instead of sending them inline
+    pub fn wrap(&self, provider: Arc<dyn MessageProvider>) -> Arc<dyn MessageProvider> {
+        let message_type = provider.verification_type();
+        self.targets
+            .lock()
+            .expect("outbox target registry lock poisoned")
+            .insert(message_type, provider);
+
+        Arc::new(QueuedMessageProvider {
+            message_type,
+            repository: self.repository.clone(),
+            clock: self.clock.clone(),
+        })
+    }
+
+    /// Build a dispatcher delivering to all providers wrapped so far
+    pub fn dispatcher(&self) -> MessageDispatcher<C> {
+        let targets = self
+            .targets
+            .lock()
+            .expect("outbox target registry lock poisoned")
+            .iter()
+            .map(|(message_type, provider)| DispatchTarget {
+                message_type: *message_type,
+                provider: provider.clone(),
+                concurrency: self.config.concurrency_for(*message_type),
+            })
+            .collect();
+
+        MessageDispatcher {
+            repository: self.repository.clone(),
+            config: self.config.clone(),
+            clock: self.clock.clone(),
+            targets,
+        }
+    }
+}
+
+/// Apply the configured delivery mode to a freshly created provider
+///
+/// With a `tracker` the provider records its deliveries; it is wrapped before
+/// the outbox, so the dispatcher records the provider's message IDs rather
+/// than the outbox entry IDs.
+pub(crate) fn with_delivery_mode(
+    provider: Arc<dyn MessageProvider>,
+    delivery: DeliveryMode,
+    outbox: Option<&MessageOutbox>,
+    tracker: Option<&DeliveryTracker>,
+) -> Result<Arc<dyn MessageProvider>> {
+    let provider = match tracker {
+        Some(tracker) => tracker.wrap(provider),
+        None => provider,
+    };
+    match delivery {
+        DeliveryMode::Direct => Ok(provider),
+        DeliveryMode::Queued => {
+            let outbox = outbox.ok_or_else(|| {
+                Error::Config(format!(
+                    "Queued {:?} delivery requires a message outbox",
+                    provider.verification_type()
+                ))
+            })?;
+            Ok(outbox.wrap(provider))
+        },
+    }
+}
+
+/// Message provider decorator that stores messages in the outbox.
+///
+/// The returned message ID is the outbox entry ID, not the provider's ID.
+pub struct QueuedMessageProvider<C: Clock + Clone = SystemClock> {
+    message_type: VerificationType,
+    repository: Arc<dyn MessageOutboxRepository>,
+    clock: C,
+}
+
+#[async_trait]
+impl<C: Clock + Clone> MessageProvider for QueuedMessageProvider<C> {
+    fn verification_type(&self) -> VerificationType {
+        self.message_type
+    }
+
+    #[instrument(skip(self, message), level = "debug")]
+    async fn send_message(&self, message: Message) -> Result<String> {
+        let entry = OutboxMessage::new(message, self.clock.now());
+        self.repository.enqueue(&entry).await?;
+
+        debug!(
+            outbox_id = %entry.id,
+            message_type = ?entry.message_type,
+            "Queued message for delivery"
+        );
+        Ok(entry.id.to_string())
+    }
+}
+
+struct DispatchTarget {
+    message_type: VerificationType,
+    provider: Arc<dyn MessageProvider>,
+    concurrency: usize,
+}
+
+/// Outcome counts of a single dispatcher pass
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DispatchReport {
+    /// Messages delivered successfully
+    pub sent: usize,
+    /// Messages rescheduled after a failed attempt
+    pub retried: usize,
+    /// Messages that exhausted their retry budget
+    pub failed: usize,
+}
+
+impl DispatchReport {
+    fn record(&mut self, outcome: DeliveryOutcome) {
+        match outcome {
+            DeliveryOutcome::Sent => self.sent += 1,
+            DeliveryOutcome::Retried => self.retried += 1,
+            DeliveryOutcome::Failed => self.failed += 1,
+            DeliveryOutcome::Unrecorded => {},
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DeliveryOutcome {
+    Sent,
+    Retried,
+    Failed,
+    /// The result could not be stored; the lease expiry will release the message
+    Unrecorded,
+}
+
+/// Background worker draining the outbox into the real providers
+pub struct MessageDispatcher<C: Clock + Clone = SystemClock> {
+    repository: Arc<dyn MessageOutboxRepository>,
+    config: OutboxConfig,
+    clock: C,
+    targets: Vec<DispatchTarget>,
+}
+
+impl<C: Clock + Clone> MessageDispatcher<C> {
+    /// Deliver all currently due messages once
+    pub async fn dispatch_once(&self) -> Result<DispatchReport> {
+        // Providers are drained independently so a slow SMTP server does not hold up SMS
+        let results = join_all(self.targets.iter().map(|target| self.drain(target))).await;
+
+        let mut report = DispatchReport::default();
+        for result in results {
+            let partial = result?;
+            report.sent += partial.sent;
+            report.retried += partial.retried;
+            report.failed += partial.failed;
+        }
+        Ok(report)
+    }
+
+    /// Run the dispatcher on a dedicated task until the runtime shuts down
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(StdDuration::from_millis(self.config.poll_interval_ms));
+            info!("Message dispatcher started");
+
+            loop {
+                interval.tick().await;
+                match self.dispatch_once().await {
+                    Ok(report) if report != DispatchReport::default() => {
+                        debug!(
+                            sent = report.sent,
+                            retried = report.retried,
+                            failed = report.failed,
+                            "Dispatched queued messages"
+                        );
+                    },
+                    Ok(_) => {},
+                    Err(e) => error!("Message dispatcher pass failed: {}", e),
+                }
+            }
+        })
+    }
+
+    async fn drain(&self, target: &DispatchTarget) -> Result<DispatchReport> {
+        let now = self.clock.now();
+        let lease_until = now + Duration::seconds(self.config.lease_secs);
+        let claimed = self
+            .repository
+            .claim_due(
+                target.message_type,
+                now,
+                lease_until,
+                self.config.batch_size,
+            )
+            .await?;
+
+        let outcomes: Vec<DeliveryOutcome> = stream::iter(claimed)
+            .map(|message| self.deliver(target.provider.as_ref(), message))
+            .buffered(target.concurrency)
+            .collect()
+            .await;
+
+        let mut report = DispatchReport::default();
+        for outcome in outcomes {
+            report.record(outcome);
+        }
+        Ok(report)
+    }
+
+    async fn deliver(
+        &self,
+        provider: &dyn MessageProvider,
+        message: OutboxMessage,
+    ) -> DeliveryOutcome {
+        let result = match provider.send_message(message.to_message()).await {
+            Ok(provider_message_id) => self
+                .repository
+                .mark_sent(message.id, &provider_message_id, self.clock.now())
+                .await
+                .map(|_| DeliveryOutcome::Sent),
+            Err(e) if message.attempts >= self.config.max_attempts => {
+                warn!(
+                    outbox_id = %message.id,
+                    attempts = message.attempts,
+                    "Giving up on queued message: {}",
+                    e
+                );
+                self.repository
+                    .mark_failed(message.id, &e.to_string(), self.clock.now())
+                    .await
+                    .map(|_| DeliveryOutcome::Failed)
+            },
+            Err(e) => {
+                let now = self.clock.now();
+                let next_attempt_at = now + self.config.backoff(message.attempts);
+                debug!(
+                    outbox_id = %message.id,
+                    attempts = message.attempts,
+                    next_attempt_at = %next_attempt_at,
+                    "Queued message delivery failed, retrying later: {}",
+                    e
+                );
+                self.repository
+                    .schedule_retry(message.id, &e.to_string(), next_attempt_at, now)
+                    .await
+                    .map(|_| DeliveryOutcome::Retried)
+            },
+        };
+
+        result.unwrap_or_else(|e| {
+            error!(
+                outbox_id = %message.id,
+                "Failed to record delivery result: {}",
+                e
+            );
+            DeliveryOutcome::Unrecorded
+        })
+    }
+}
@@ -1,8 +1,14 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 use crate::models::{TenantId, UserId, VerificationType};
-use acci_core::error::Result;
+use crate::services::delivery_receipt::DeliveryReceiptConfig;
+use crate::services::message_outbox::OutboxConfig;
+use crate::utils::duration::DurationSecs;
+use crate::utils::secret::Secret;
+use acci_core::error::{Error, ErrorKind, Result};
+use acci_core::secret_store::{SecretResolver, ensure_resolved};
 
 /// Configuration for message providers
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,6 +17,92 @@ pub struct MessageProviderConfig {
     pub email: EmailProviderConfig,
     /// SMS provider configuration
     pub sms: SmsProviderConfig,
+    /// Outbox settings used by providers with queued delivery
+    #[serde(default)]
+    pub outbox: OutboxConfig,
+    /// Further email providers and the rules choosing between them
+    #[serde(default)]
+    pub email_routing: EmailRoutingConfig,
+    /// Verification of the delivery receipts the providers call back with
+    #[serde(default)]
+    pub delivery_receipts: DeliveryReceiptConfig,
+}
+
+/// Routing of emails across several providers
+///
+/// The provider configured as `email` is registered as `"default"` and takes
+/// every message no rule matches.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailRoutingConfig {
+    /// Additional email providers by name
+    #[serde(default)]
+    pub providers: HashMap<String, EmailProviderConfig>,
+    /// Rules in order of precedence; every matching rule is a candidate
+    #[serde(default)]
+    pub rules: Vec<EmailRoutingRule>,
+    /// How long a provider is skipped after a failed send
+    #[serde(default = "default_unhealthy_cooldown_secs")]
+    pub unhealthy_cooldown_secs: DurationSecs,
+}
+
+impl Default for EmailRoutingConfig {
+    fn default() -> Self {
+        Self {
+            providers: HashMap::new(),
+            rules: Vec::new(),
+            unhealthy_cooldown_secs: default_unhealthy_cooldown_secs(),
+        }
+    }
+}
+
+fn default_unhealthy_cooldown_secs() -> DurationSecs {
+    DurationSecs::from_secs(60)
+}
+
+/// Rule sending matching messages to a named email provider
+///
+/// Criteria that are not set match every message.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EmailRoutingRule {
+    #[serde(default)]
+    pub tenant_id: Option<TenantId>,
+    #[serde(default)]
+    pub message_type: Option<VerificationType>,
+    /// Domain of the recipient address, compared case-insensitively
+    #[serde(default)]
+    pub recipient_domain: Option<String>,
+    /// Name of the provider the message is sent with
+    pub provider: String,
+}
+
+impl EmailRoutingRule {
+    /// Whether the rule applies to `message`
+    pub fn matches(&self, message: &Message) -> bool {
+        self.tenant_id
+            .is_none_or(|tenant_id| tenant_id == message.tenant_id)
+            && self
+                .message_type
+                .is_none_or(|message_type| message_type == message.message_type)
+            && self.recipient_domain.as_deref().is_none_or(|domain| {
+                message
+                    .recipient
+                    .rsplit_once('@')
+                    .is_some_and(|(_, recipient_domain)| {
+                        recipient_domain.eq_ignore_ascii_case(domain)
+                    })
+            })
+    }
+}
+
+/// How a provider delivers messages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryMode {
+    /// Send inline while handling the request
+    #[default]
+    Direct,
+    /// Store in the message outbox and let the dispatcher send it
+    Queued,
 }
 
 /// Email provider configuration
@@ -21,13 +113,16 @@ pub struct EmailProviderConfig {
     /// SMTP server configuration (if using SMTP)
     pub smtp: Option<SmtpConfig>,
     /// API key (if using API-based service)
-    pub api_key: Option<String>,
+    pub api_key: Option<Secret<String>>,
     /// Sender email address
     pub sender_email: String,
     /// Sender name
     pub sender_name: String,
     /// Template for verification emails
     pub verification_template: String,
+    /// Delivery mode for outgoing emails
+    #[serde(default)]
+    pub delivery: DeliveryMode,
 }
 
 /// SMTP configuration
@@ -40,7 +135,7 @@ pub struct SmtpConfig {
     /// SMTP username
     pub username: String,
     /// SMTP password
-    pub password: String,
+    pub password: Secret<String>,
     /// Use TLS
     pub use_tls: bool,
 }
@@ -51,11 +146,88 @@ pub struct SmsProviderConfig {
     /// SMS service provider to use
     pub provider: String,
     /// API key for the SMS service
-    pub api_key: String,
+    pub api_key: Secret<String>,
     /// API secret for the SMS service (if needed)
-    pub api_secret: Option<String>,
+    pub api_secret: Option<Secret<String>>,
     /// Sender phone number or ID
     pub sender: String,
+    /// Delivery mode for outgoing SMS
+    #[serde(default)]
+    pub delivery: DeliveryMode,
+    /// URL the provider reports the delivery state of each message to
+    #[serde(default)]
+    pub status_callback_url: Option<String>,
+}
+
+impl MessageProviderConfig {
+    /// Resolve the secret references of all providers, reporting failures to `resolver`
+    pub async fn resolve_secrets(&mut self, resolver: &mut SecretResolver<'_>) {
+        self.email
+            .resolve_secrets(resolver, "message_providers.email")
+            .await;
+        self.sms
+            .resolve_secrets(resolver, "message_providers.sms")
+            .await;
+        for (name, provider) in &mut self.email_routing.providers {
+            provider
+                .resolve_secrets(
+                    resolver,
+                    &format!("message_providers.email_routing.providers.{name}"),
+                )
+                .await;
+        }
+        self.delivery_receipts
+            .resolve_secrets(resolver, "message_providers.delivery_receipts")
+            .await;
+    }
+}
+
+impl EmailProviderConfig {
+    /// Resolve the SMTP password and the API key; `field` names the config in errors
+    pub async fn resolve_secrets(&mut self, resolver: &mut SecretResolver<'_>, field: &str) {
+        if let Some(smtp) = &mut self.smtp {
+            resolver
+                .resolve(&format!("{field}.smtp.password"), &mut smtp.password)
+                .await;
+        }
+        if let Some(api_key) = &mut self.api_key {
+            resolver.resolve(&format!("{field}.api_key"), api_key).await;
+        }
+    }
+
+    /// Fail if a secret of the config is a reference that was not resolved
+    pub fn ensure_secrets_resolved(&self) -> Result<()> {
+        if let Some(smtp) = &self.smtp {
+            ensure_resolved("email.smtp.password", &smtp.password)?;
+        }
+        if let Some(api_key) = &self.api_key {
+            ensure_resolved("email.api_key", api_key)?;
+        }
+        Ok(())
+    }
+}
+
+impl SmsProviderConfig {
+    /// Resolve the API key and secret; `field` names the config in errors
+    pub async fn resolve_secrets(&mut self, resolver: &mut SecretResolver<'_>, field: &str) {
+        resolver
+            .resolve(&format!("{field}.api_key"), &mut self.api_key)
+            .await;
+        if let Some(api_secret) = &mut self.api_secret {
+            resolver
+                .resolve(&format!("{field}.api_secret"), api_secret)
+                .await;
+        }
+    }
+
+    /// Fail if a secret of the config is a reference that was not resolved
+    pub fn ensure_secrets_resolved(&self) -> Result<()> {
+        ensure_resolved("sms.api_key", &self.api_key)?;
+        if let Some(api_secret) = &self.api_secret {
+            ensure_resolved("sms.api_secret", api_secret)?;
+        }
+        Ok(())
+    }
 }
 
 /// Message to be sent
@@ -82,33 +254,89 @@ pub trait MessageProvider: Send + Sync {
     fn verification_type(&self) -> VerificationType;
 
     /// Send a message
+    ///
+    /// Failures that may go away when sending again, e.g. an unreachable or
+    /// throttling provider, are [`ErrorKind::Unavailable`], see
+    /// [`is_transient_send_error`].
     async fn send_message(&self, message: Message) -> Result<String>;
 }
 
-/// Mock message provider for testing
-#[cfg(test)]
+/// Whether a failed send may succeed when it is retried with the same provider
+pub fn is_transient_send_error(err: &Error) -> bool {
+    err.kind() == ErrorKind::Unavailable
+}
+
+/// Error of a provider that could not be reached
+pub(crate) fn provider_unreachable(provider: &str, err: impl std::fmt::Display) -> Error {
+    Error::domain(
+        ErrorKind::Unavailable,
+        format!("Failed to send {} request: {}", provider, err),
+    )
+}
+
+/// Error of a provider API that answered with an unsuccessful `status`
+///
+/// Throttling and server errors are transient; any other status, e.g. for an
+/// invalid recipient, fails the same way when retried.
+pub(crate) fn provider_status_error(
+    provider: &str,
+    status: reqwest::StatusCode,
+    error_text: &str,
+) -> Error {
+    let kind = if status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+        ErrorKind::Unavailable
+    } else {
+        ErrorKind::InvalidInput
+    };
+    Error::domain(
+        kind,
+        format!("{} API error: {} - {}", provider, status, error_text),
+    )
+}
+
+/// Message provider that records the messages instead of delivering them
+///
+/// Shared by the unit tests in this crate and by downstream test crates (via
+/// the `test-support` feature).
+#[cfg(any(test, feature = "test-support"))]
+#[derive(Debug, Clone)]
 pub struct MockMessageProvider {
-    /// Last message sent
-    pub last_message: std::sync::Arc<std::sync::Mutex<Option<Message>>>,
-    /// Type of verification this provider handles
-    pub verification_type: VerificationType,
-    /// Provider response
+    sent: std::sync::Arc<std::sync::Mutex<Vec<Message>>>,
+    verification_type: VerificationType,
+    /// Message ID returned for every message
     pub response: String,
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-support"))]
 impl MockMessageProvider {
     /// Create a new mock message provider
     pub fn new(verification_type: VerificationType) -> Self {
         Self {
-            last_message: std::sync::Arc::new(std::sync::Mutex::new(None)),
+            sent: Default::default(),
             verification_type,
             response: "message_id".to_string(),
         }
     }
+
+    /// Messages sent so far, oldest first
+    pub fn sent_messages(&self) -> Vec<Message> {
+        self.sent
+            .lock()
+            .expect("mock message store lock poisoned")
+            .clone()
+    }
+
+    /// The most recently sent message
+    pub fn last_message(&self) -> Option<Message> {
+        self.sent
+            .lock()
+            .expect("mock message store lock poisoned")
+            .last()
+            .cloned()
+    }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-support"))]
 #[async_trait]
 impl MessageProvider for MockMessageProvider {
     fn verification_type(&self) -> VerificationType {
@@ -116,11 +344,10 @@ impl MessageProvider for MockMessageProvider {
     }
 
     async fn send_message(&self, message: Message) -> Result<String> {
-        let mut last_message = self
-            .last_message
+        self.sent
             .lock()
-            .map_err(|e| anyhow::anyhow!("Failed to acquire lock: {}", e))?;
-        *last_message = Some(message);
+            .expect("mock message store lock poisoned")
+            .push(message);
         Ok(self.response.clone())
     }
 }
@@ -0,0 +1,166 @@
+use std::sync::Arc;
+use thiserror::Error;
+use tracing::{error, info, instrument};
+use uuid::Uuid;
+
+use crate::models::{TenantId, UserId, VerificationType};
+use crate::repository::TenantAwareContext;
+use crate::services::verification::VerificationService;
+use crate::session::{SessionError, SessionRepository, types::MfaStatus};
+use crate::utils::clock::{Clock, SystemClock};
+
+/// Errors that can occur when completing the second factor of a session
+#[derive(Debug, Error)]
+pub enum MfaOrchestratorError {
+    /// The verification code was rejected
+    #[error("Verification failed: {0}")]
+    Verification(acci_core::error::Error),
+
+    /// The session does not exist, has expired or has been invalidated
+    #[error("Session not found")]
+    SessionNotFound,
+
+    /// The session belongs to a different user than the verified code
+    #[error("Session does not belong to the user")]
+    SessionUserMismatch,
+
+    /// The session is waiting for a mandatory enrollment, not a verification
+    #[error("MFA enrollment must be completed before the session can be used")]
+    EnrollmentPending,
+
+    /// Reading or updating the session failed
+    #[error("Session repository error: {0}")]
+    Repository(#[from] SessionError),
+}
+
+impl From<MfaOrchestratorError> for acci_core::Error {
+    fn from(err: MfaOrchestratorError) -> Self {
+        use acci_core::error::ErrorKind;
+
+        let kind = match err {
+            MfaOrchestratorError::Verification(e) => return e,
+            MfaOrchestratorError::Repository(e) => return e.into(),
+            MfaOrchestratorError::SessionNotFound => ErrorKind::Unauthenticated,
+            MfaOrchestratorError::SessionUserMismatch => ErrorKind::Forbidden,
+            MfaOrchestratorError::EnrollmentPending => ErrorKind::MfaEnrollmentRequired,
+        };
+        Self::domain(kind, err.to_string())
+    }
+}
+
+/// Single entry point for completing the second factor of a session
+///
+/// Ties code verification and the session update together: a session only
+/// becomes [`MfaStatus::Verified`] once the code has been accepted, and a
+/// failure while recording the verification restores the previous status.
+/// The code is checked first, so a failed session update costs the user the
+/// code rather than ever leaving a verified session without a valid code.
+pub struct MfaOrchestrator<C: Clock = SystemClock> {
+    verification_service: Arc<VerificationService>,
+    session_repository: Arc<dyn SessionRepository>,
+    clock: C,
+}
+
+impl MfaOrchestrator {
+    /// Create a new orchestrator
+    pub fn new(
+        verification_service: Arc<VerificationService>,
+        session_repository: Arc<dyn SessionRepository>,
+    ) -> Self {
+        Self::with_clock(verification_service, session_repository, SystemClock)
+    }
+}
+
+impl<C: Clock> MfaOrchestrator<C> {
+    /// Create an orchestrator that reads the current time from `clock`
+    pub fn with_clock(
+        verification_service: Arc<VerificationService>,
+        session_repository: Arc<dyn SessionRepository>,
+        clock: C,
+    ) -> Self {
+        Self {
+            verification_service,
+            session_repository,
+            clock,
+        }
+    }
+
+    /// Verify `code` and mark the session as MFA verified
+    ///
+    /// On success the session is [`MfaStatus::Verified`], its `mfa_verified_at`
+    /// and `last_auth_at` metadata are set and an `MFA_VERIFIED` audit entry is
+    /// written. A rejected code leaves the session untouched.
+    #[instrument(skip(self, code, context), level = "debug")]
+    pub async fn complete_mfa(
+        &self,
+        session_id: Uuid,
+        user_id: UserId,
+        verification_type: VerificationType,
+        code: &str,
+        tenant_id: TenantId,
+        context: &dyn TenantAwareContext,
+    ) -> Result<(), MfaOrchestratorError> {
+        let now = self.clock.now();
+        let session = self
+            .session_repository
+            .get_session(session_id)
+            .await?
+            .filter(|session| session.is_valid && session.expires_at > now)
+            .ok_or(MfaOrchestratorError::SessionNotFound)?;
+
+        if session.user_id != user_id {
+            error!(
+                session_id = %session_id,
+                user_id = %user_id,
+                "Refusing to complete MFA for a session of another user"
+            );
+            return Err(MfaOrchestratorError::SessionUserMismatch);
+        }
+        if session.mfa_status == MfaStatus::Pending {
+            return Err(MfaOrchestratorError::EnrollmentPending);
+        }
+
+        self.verification_service
+            .verify_code(user_id, verification_type, code, tenant_id, context)
+            .await
+            .map_err(MfaOrchestratorError::Verification)?;
+
+        let previous_status = session.mfa_status;
+        self.session_repository
+            .update_mfa_status(session_id, MfaStatus::Verified)
+            .await?;
+
+        if let Err(err) = self
+            .session_repository
+            .record_mfa_verification(session_id, now)
+            .await
+        {
+            error!(
+                session_id = %session_id,
+                error = %err,
+                "Failed to record MFA verification, restoring previous MFA status"
+            );
+            if let Err(rollback_err) = self
+                .session_repository
+                .update_mfa_status(session_id, previous_status)
+                .await
+            {
+                error!(
+                    session_id = %session_id,
+                    error = %rollback_err,
+                    "Failed to restore previous MFA status"
+                );
+            }
+            return Err(err.into());
+        }
+
+        info!(
+            session_id = %session_id,
+            user_id = %user_id,
+            verification_type = ?verification_type,
+            "Completed MFA for session"
+        );
+
+        Ok(())
+    }
+}
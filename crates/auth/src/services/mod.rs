@@ -1,10 +1,26 @@
+pub mod account_deletion;
+pub mod api_key;
+pub mod api_key_reminder;
+pub mod delivery_receipt;
+#[cfg(feature = "dev-tools")]
+pub mod dev_seed;
 pub mod email_provider;
+pub mod email_routing;
+pub mod event_export;
+pub mod login_attempts;
+pub mod message_outbox;
 pub mod message_provider;
+pub mod mfa;
+pub mod oidc;
+pub mod saml;
+pub mod scim;
 pub mod session;
 pub mod sms_provider;
 pub mod tenant;
+pub mod timeline;
 pub mod totp;
 pub mod user;
+pub mod user_agent;
 pub mod verification;
 #[cfg(feature = "enable_webauthn")]
 pub mod webauthn;
@@ -14,12 +30,59 @@ mod tests;
 
 #[cfg(feature = "enable_webauthn")]
 pub use crate::models::webauthn::WebAuthnError;
+pub use account_deletion::{
+    ACCOUNT_PENDING_DELETION, AccountDeletionConfig, AccountDeletionError, AccountDeletionService,
+    DeletionConfirmation, DeletionReport,
+};
+pub use api_key::{
+    API_KEY_AUTH_SCHEME, ApiKeyError, ApiKeyQuery, ApiKeyService, DEFAULT_ROTATION_OVERLAP,
+    IssueApiKey, LAST_USE_RECORD_INTERVAL,
+};
+pub use api_key_reminder::{ApiKeyReminderConfig, ApiKeyReminderReport, ApiKeyReminderService};
+pub use delivery_receipt::{
+    DeliveryFailurePolicy, DeliveryReceiptConfig, DeliveryReceiptVerifier, DeliveryTracker,
+    ReceiptOutcome, Resend, ResendPolicy, TrackedMessageProvider, parse_sendgrid_events,
+    parse_twilio_callback, parse_vonage_receipt,
+};
 pub use email_provider::{SendGridEmailProvider, SmtpEmailProvider, create_email_provider};
+pub use email_routing::{
+    DEFAULT_EMAIL_PROVIDER, RoutingEmailProvider, create_routing_email_provider,
+};
+pub use event_export::{
+    CefFormatter, EventCategory, EventExportConfig, EventExportError, EventExporter,
+    EventTransport, ExportTarget, HttpsCollectorTransport, SUSPICIOUS_LOGIN_ACTION, SecurityEvent,
+    SyslogFormat, TcpSyslogTransport, UdpSyslogTransport,
+};
+pub use login_attempts::{LoginAttemptContext, LoginAttemptRecorder};
+pub use message_outbox::{
+    DispatchReport, MessageDispatcher, MessageOutbox, OutboxConfig, QueuedMessageProvider,
+};
 pub use message_provider::{
-    EmailProviderConfig, Message, MessageProvider, MessageProviderConfig, SmsProviderConfig,
-    SmtpConfig,
+    DeliveryMode, EmailProviderConfig, EmailRoutingConfig, EmailRoutingRule, Message,
+    MessageProvider, MessageProviderConfig, SmsProviderConfig, SmtpConfig,
+};
+pub use mfa::{MfaOrchestrator, MfaOrchestratorError};
+pub use oidc::{
+    ExternalIdentity, ExternalLogin, IdentitySource, OidcError, OidcProvider, OidcProviderConfig,
+    OidcStateStore,
+};
+pub use saml::{
+    AssertionReplayStore, SAML_IDP_METADATA_KEY, SamlError, SamlIdpConfig, SamlLogin,
+    SamlServiceProvider, SamlSpConfig,
+};
+pub use scim::{
+    SCIM_TOKEN_METADATA_KEY, ScimError, ScimFilter, ScimGroup, ScimListResponse, ScimPage,
+    ScimPatchRequest, ScimService, ScimUser,
 };
 pub use sms_provider::{TwilioSmsProvider, VonageSmsProvider, create_sms_provider};
-pub use verification::{VerificationError, VerificationService};
+pub use timeline::{
+    DEFAULT_TIMELINE_PAGE_SIZE, MAX_TIMELINE_PAGE_SIZE, TimelinePage, TimelineView,
+    UserTimelineService,
+};
+pub use verification::{
+    ChannelFallbackConfig, ConsumedProof, ContactDirectory, UserContactDirectory,
+    VerificationCleanupReport, VerificationDelivery, VerificationError, VerificationProof,
+    VerificationService,
+};
 #[cfg(feature = "enable_webauthn")]
 pub use webauthn::{WebAuthnConfig, WebAuthnService};
@@ -0,0 +1,570 @@
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header, jwk::JwkSet};
+use reqwest::header;
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::Sha256;
+use std::sync::Arc;
+use thiserror::Error;
+use time::OffsetDateTime;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::models::identity::LinkedIdentity;
+use crate::models::tenant::{CreateTenantUserDto, TenantError, TenantRepository};
+use crate::models::user::{User, UserError, UserRepository};
+use crate::repository::{ExternalIdentityRepository, RepositoryError};
+use crate::security::NonceStore;
+use crate::utils::password::NO_PASSWORD_HASH;
+use acci_core::http_client::HttpClient;
+
+/// Tenant role given to users provisioned through an external identity provider
+const PROVISIONED_TENANT_ROLE: &str = "MEMBER";
+
+/// Errors of the external login flow
+#[derive(Debug, Error)]
+pub enum OidcError {
+    #[error("Invalid or expired login state")]
+    InvalidState,
+
+    #[error("Identity provider request failed: {0}")]
+    Provider(String),
+
+    #[error("Invalid ID token: {0}")]
+    InvalidIdToken(String),
+
+    #[error("Email address is not verified by the identity provider")]
+    UnverifiedEmail,
+
+    #[error("No local account exists for this identity")]
+    AccountNotFound,
+
+    #[error("User is not an active member of the tenant")]
+    NotTenantMember,
+
+    #[error("State store error: {0}")]
+    StateStore(#[source] anyhow::Error),
+
+    #[error(transparent)]
+    User(#[from] UserError),
+
+    #[error(transparent)]
+    Tenant(#[from] TenantError),
+
+    #[error(transparent)]
+    Repository(#[from] RepositoryError),
+}
+
+/// Where a provider reports the email address of the signed-in user
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IdentitySource {
+    /// Claims of the OpenID Connect ID token
+    ///
+    /// Tokens are verified against the keys published at `jwks_uri` (RS256), or
+    /// against the client secret (HS256) when no key set is configured.
+    IdToken { jwks_uri: Option<String> },
+    /// Primary address from the GitHub user emails API, which has no ID token
+    GithubEmails { emails_endpoint: String },
+}
+
+impl Default for IdentitySource {
+    fn default() -> Self {
+        Self::IdToken { jwks_uri: None }
+    }
+}
+
+/// Client registration with an OAuth2/OpenID Connect identity provider
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcProviderConfig {
+    /// Name used in routes and session metadata, e.g. `google`
+    pub name: String,
+    /// Expected `iss` claim of ID tokens
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    /// Callback URL registered with the provider
+    pub redirect_uri: String,
+    #[serde(default = "default_scopes")]
+    pub scopes: Vec<String>,
+    #[serde(default)]
+    pub identity: IdentitySource,
+}
+
+fn default_scopes() -> Vec<String> {
+    vec![
+        "openid".to_string(),
+        "email".to_string(),
+        "profile".to_string(),
+    ]
+}
+
+impl OidcProviderConfig {
+    /// Google sign-in with ID tokens verified against Google's published keys
+    pub fn google(client_id: String, client_secret: String, redirect_uri: String) -> Self {
+        Self {
+            name: "google".to_string(),
+            issuer: "https://accounts.google.com".to_string(),
+            client_id,
+            client_secret,
+            authorization_endpoint: "https://accounts.google.com/o/oauth2/v2/auth".to_string(),
+            token_endpoint: "https://oauth2.googleapis.com/token".to_string(),
+            redirect_uri,
+            scopes: default_scopes(),
+            identity: IdentitySource::IdToken {
+                jwks_uri: Some("https://www.googleapis.com/oauth2/v3/certs".to_string()),
+            },
+        }
+    }
+
+    /// GitHub sign-in through an OAuth app
+    pub fn github(client_id: String, client_secret: String, redirect_uri: String) -> Self {
+        Self {
+            name: "github".to_string(),
+            issuer: "https://github.com".to_string(),
+            client_id,
+            client_secret,
+            authorization_endpoint: "https://github.com/login/oauth/authorize".to_string(),
+            token_endpoint: "https://github.com/login/oauth/access_token".to_string(),
+            redirect_uri,
+            scopes: vec!["read:user".to_string(), "user:email".to_string()],
+            identity: IdentitySource::GithubEmails {
+                emails_endpoint: "https://api.github.com/user/emails".to_string(),
+            },
+        }
+    }
+}
+
+/// Single-use storage for the `state` of pending authorization requests
+#[async_trait]
+pub trait OidcStateStore: Send + Sync {
+    /// Issue a new value bound to `tenant_id` and `context`
+    async fn issue(&self, tenant_id: &str, context: &str) -> anyhow::Result<String>;
+
+    /// Consume a value; returns whether it was issued and not used before
+    async fn consume(&self, tenant_id: &str, context: &str, value: &str) -> anyhow::Result<bool>;
+}
+
+#[async_trait]
+impl OidcStateStore for NonceStore {
+    async fn issue(&self, tenant_id: &str, context: &str) -> anyhow::Result<String> {
+        // A disabled store accepts every nonce, which would turn off CSRF protection
+        if !self.is_enabled() {
+            anyhow::bail!("Replay protection is disabled, login state cannot be stored");
+        }
+        self.generate_nonce(tenant_id, context).await
+    }
+
+    async fn consume(&self, tenant_id: &str, context: &str, value: &str) -> anyhow::Result<bool> {
+        if !self.is_enabled() {
+            return Ok(false);
+        }
+        self.validate_nonce(tenant_id, context, value, None).await
+    }
+}
+
+/// Identity asserted by the provider
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalIdentity {
+    /// Provider's identifier of the user
+    pub subject: String,
+    pub email: String,
+    pub email_verified: bool,
+}
+
+/// Local user an external login resolved to
+#[derive(Debug, Clone)]
+pub struct ExternalLogin {
+    pub user: User,
+    pub tenant_id: Uuid,
+    /// Whether the local account was created by this login
+    pub created: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    id_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+    email: Option<String>,
+    email_verified: Option<Value>,
+    nonce: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubEmail {
+    email: String,
+    primary: bool,
+    verified: bool,
+}
+
+/// Login through an external OAuth2/OpenID Connect provider
+///
+/// Implements the authorization code flow. The `state` parameter carries the tenant
+/// and a single-use nonce from the [`OidcStateStore`]; the ID token `nonce` is an HMAC
+/// of the state, so a token minted for one authorization request cannot complete
+/// another. Verified email addresses are linked to the local account with the same
+/// address; unverified addresses are rejected.
+pub struct OidcProvider {
+    config: OidcProviderConfig,
+    state_store: Arc<dyn OidcStateStore>,
+    user_repository: Arc<dyn UserRepository>,
+    tenant_repository: Arc<dyn TenantRepository>,
+    identity_repository: Option<Arc<dyn ExternalIdentityRepository>>,
+    client: HttpClient,
+}
+
+impl OidcProvider {
+    /// Requests to the provider are sent with `client`, see [`acci_core::http_client`]
+    pub fn new(
+        config: OidcProviderConfig,
+        client: HttpClient,
+        state_store: Arc<dyn OidcStateStore>,
+        user_repository: Arc<dyn UserRepository>,
+        tenant_repository: Arc<dyn TenantRepository>,
+    ) -> Self {
+        Self {
+            config,
+            state_store,
+            user_repository,
+            tenant_repository,
+            identity_repository: None,
+            client,
+        }
+    }
+
+    /// Records linked identities, which lets users remove their password
+    pub fn with_identity_repository(
+        mut self,
+        identity_repository: Arc<dyn ExternalIdentityRepository>,
+    ) -> Self {
+        self.identity_repository = Some(identity_repository);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    /// Login type recorded in the metadata of sessions created through this provider
+    pub fn login_type(&self) -> String {
+        format!("oidc:{}", self.config.name)
+    }
+
+    fn state_context(&self) -> String {
+        format!("oidc:{}", self.config.name)
+    }
+
+    /// Build the URL that starts a login at the provider for `tenant_id`
+    pub async fn authorization_url(&self, tenant_id: Uuid) -> Result<String, OidcError> {
+        let nonce = self
+            .state_store
+            .issue(&tenant_id.to_string(), &self.state_context())
+            .await
+            .map_err(OidcError::StateStore)?;
+        let state = format!("{}.{}", tenant_id, nonce);
+
+        let query = [
+            ("response_type", "code"),
+            ("client_id", self.config.client_id.as_str()),
+            ("redirect_uri", self.config.redirect_uri.as_str()),
+            ("scope", &self.config.scopes.join(" ")),
+            ("state", &state),
+            ("nonce", &self.id_token_nonce(&state)),
+        ]
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, urlencoding::encode(value)))
+        .collect::<Vec<_>>()
+        .join("&");
+
+        Ok(format!("{}?{}", self.config.authorization_endpoint, query))
+    }
+
+    /// Complete a login from the provider's callback parameters
+    pub async fn handle_callback(
+        &self,
+        code: &str,
+        state: &str,
+    ) -> Result<ExternalLogin, OidcError> {
+        let (tenant_id, nonce) = state
+            .split_once('.')
+            .and_then(|(tenant_id, nonce)| Some((Uuid::parse_str(tenant_id).ok()?, nonce)))
+            .ok_or(OidcError::InvalidState)?;
+        let consumed = self
+            .state_store
+            .consume(&tenant_id.to_string(), &self.state_context(), nonce)
+            .await
+            .map_err(OidcError::StateStore)?;
+        if !consumed {
+            return Err(OidcError::InvalidState);
+        }
+
+        let tokens = self.exchange_code(code).await?;
+        let identity = match &self.config.identity {
+            IdentitySource::IdToken { jwks_uri } => {
+                let id_token = tokens.id_token.as_deref().ok_or_else(|| {
+                    OidcError::InvalidIdToken("Token response has no ID token".to_string())
+                })?;
+                self.verify_id_token(id_token, jwks_uri.as_deref(), &self.id_token_nonce(state))
+                    .await?
+            },
+            IdentitySource::GithubEmails { emails_endpoint } => {
+                self.github_identity(emails_endpoint, &tokens.access_token)
+                    .await?
+            },
+        };
+
+        self.resolve_user(identity, tenant_id).await
+    }
+
+    /// Exchange an authorization code at the token endpoint
+    async fn exchange_code(&self, code: &str) -> Result<TokenResponse, OidcError> {
+        let request = self
+            .client
+            .post(&self.config.token_endpoint)
+            .header(header::ACCEPT, "application/json")
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", &self.config.redirect_uri),
+                ("client_id", &self.config.client_id),
+                ("client_secret", &self.config.client_secret),
+            ]);
+        let response = self
+            .client
+            .send(request)
+            .await
+            .map_err(|err| OidcError::Provider(err.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(OidcError::Provider(format!(
+                "Token endpoint returned {}",
+                response.status()
+            )));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|err| OidcError::Provider(format!("Invalid token response: {}", err)))
+    }
+
+    /// Verify signature, issuer, audience, expiry and nonce of an ID token
+    async fn verify_id_token(
+        &self,
+        id_token: &str,
+        jwks_uri: Option<&str>,
+        expected_nonce: &str,
+    ) -> Result<ExternalIdentity, OidcError> {
+        let invalid = |err: jsonwebtoken::errors::Error| OidcError::InvalidIdToken(err.to_string());
+
+        let (key, algorithm) = match jwks_uri {
+            Some(jwks_uri) => {
+                let header = decode_header(id_token).map_err(invalid)?;
+                let keys: JwkSet = self
+                    .client
+                    .send(self.client.get(jwks_uri))
+                    .await
+                    .map_err(|err| OidcError::Provider(err.to_string()))?
+                    .json()
+                    .await
+                    .map_err(|err| OidcError::Provider(format!("Invalid key set: {}", err)))?;
+                let jwk = header
+                    .kid
+                    .as_deref()
+                    .and_then(|kid| keys.find(kid))
+                    .ok_or_else(|| OidcError::InvalidIdToken("Unknown signing key".to_string()))?;
+                (
+                    DecodingKey::from_jwk(jwk).map_err(invalid)?,
+                    Algorithm::RS256,
+                )
+            },
+            None => (
+                DecodingKey::from_secret(self.config.client_secret.as_bytes()),
+                Algorithm::HS256,
+            ),
+        };
+
+        let mut validation = Validation::new(algorithm);
+        validation.set_issuer(&[&self.config.issuer]);
+        validation.set_audience(&[&self.config.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(invalid)?
+            .claims;
+
+        if claims.nonce.as_deref() != Some(expected_nonce) {
+            return Err(OidcError::InvalidIdToken("Nonce mismatch".to_string()));
+        }
+        let email = claims
+            .email
+            .ok_or_else(|| OidcError::InvalidIdToken("Missing email claim".to_string()))?;
+
+        Ok(ExternalIdentity {
+            subject: claims.sub,
+            email,
+            // Some providers encode the flag as a string
+            email_verified: match claims.email_verified {
+                Some(Value::Bool(verified)) => verified,
+                Some(Value::String(verified)) => verified == "true",
+                _ => false,
+            },
+        })
+    }
+
+    /// Primary email address of a GitHub user
+    async fn github_identity(
+        &self,
+        emails_endpoint: &str,
+        access_token: &str,
+    ) -> Result<ExternalIdentity, OidcError> {
+        let request = self
+            .client
+            .get(emails_endpoint)
+            .bearer_auth(access_token)
+            .header(header::ACCEPT, "application/vnd.github+json");
+        let response = self
+            .client
+            .send(request)
+            .await
+            .map_err(|err| OidcError::Provider(err.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(OidcError::Provider(format!(
+                "Emails endpoint returned {}",
+                response.status()
+            )));
+        }
+
+        let emails: Vec<GithubEmail> = response
+            .json()
+            .await
+            .map_err(|err| OidcError::Provider(format!("Invalid emails response: {}", err)))?;
+        let primary = emails
+            .into_iter()
+            .find(|email| email.primary)
+            .ok_or_else(|| OidcError::Provider("Account has no primary email".to_string()))?;
+
+        Ok(ExternalIdentity {
+            subject: primary.email.clone(),
+            email: primary.email,
+            email_verified: primary.verified,
+        })
+    }
+
+    /// Map a verified identity to a local user of the tenant
+    ///
+    /// An existing account with the same email is linked. If that account was never
+    /// verified, its password is removed: the provider has just proven who owns the
+    /// address, and a password chosen by whoever registered it cannot be trusted.
+    /// Unknown users and non-members are only provisioned when the tenant policy
+    /// allows it.
+    async fn resolve_user(
+        &self,
+        identity: ExternalIdentity,
+        tenant_id: Uuid,
+    ) -> Result<ExternalLogin, OidcError> {
+        if !identity.email_verified {
+            warn!(
+                provider = %self.config.name,
+                subject = %identity.subject,
+                "Rejected external login with unverified email"
+            );
+            return Err(OidcError::UnverifiedEmail);
+        }
+
+        let tenant = self
+            .tenant_repository
+            .find_tenant_by_id(tenant_id)
+            .await?
+            .ok_or(TenantError::NotFound)?;
+        if tenant.status.is_archived() {
+            return Err(TenantError::InactiveTenant.into());
+        }
+        let provisioning = tenant.security_policy().external_login_provisioning;
+
+        let (user, created) = match self.user_repository.find_by_email(&identity.email).await? {
+            Some(mut user) => {
+                if !user.is_active {
+                    return Err(UserError::InactiveUser.into());
+                }
+                if !user.is_verified {
+                    user.is_verified = true;
+                    user.password_hash = NO_PASSWORD_HASH.to_string();
+                    user.updated_at = OffsetDateTime::now_utc();
+                    self.user_repository.update(&user).await?;
+                }
+                debug!(user_id = %user.id, provider = %self.config.name, "Linked external identity");
+                (user, false)
+            },
+            None => {
+                if !provisioning {
+                    return Err(OidcError::AccountNotFound);
+                }
+                let mut user = User::new(identity.email.clone(), NO_PASSWORD_HASH.to_string());
+                user.is_verified = true;
+                self.user_repository.create(&user).await?;
+                info!(user_id = %user.id, provider = %self.config.name, "Provisioned user from external identity");
+                (user, true)
+            },
+        };
+
+        let membership = self
+            .tenant_repository
+            .get_user_tenants(user.id)
+            .await?
+            .into_iter()
+            .find(|membership| membership.tenant_id == tenant_id);
+        match membership {
+            Some(membership) if membership.is_active => {},
+            None if provisioning => {
+                self.tenant_repository
+                    .add_user_to_tenant(
+                        tenant_id,
+                        CreateTenantUserDto {
+                            user_id: user.id,
+                            tenant_role: PROVISIONED_TENANT_ROLE.to_string(),
+                            is_active: Some(true),
+                        },
+                    )
+                    .await?;
+            },
+            _ => return Err(OidcError::NotTenantMember),
+        }
+
+        if let Some(identity_repository) = &self.identity_repository {
+            let now = OffsetDateTime::now_utc();
+            identity_repository
+                .link(&LinkedIdentity {
+                    user_id: user.id,
+                    provider: self.config.name.clone(),
+                    subject: identity.subject,
+                    email: identity.email,
+                    linked_at: now,
+                    last_login_at: now,
+                })
+                .await?;
+        }
+
+        Ok(ExternalLogin {
+            user,
+            tenant_id,
+            created,
+        })
+    }
+
+    /// Nonce expected in the ID token of the authorization request with `state`
+    fn id_token_nonce(&self, state: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.config.client_secret.as_bytes())
+            .expect("HMAC can take key of any size");
+        mac.update(state.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
@@ -0,0 +1,572 @@
+use async_trait::async_trait;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use roxmltree::{Document, Node};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::models::tenant::{Tenant, TenantError, TenantRepository};
+use crate::models::user::{User, UserError, UserRepository};
+use crate::security::NonceStore;
+use crate::utils::xml_signature::{
+    XMLDSIG_NS, XmlSignatureError, parse_certificate, verify_enveloped_signature,
+};
+
+/// Tenant metadata key holding the [`SamlIdpConfig`] of the tenant
+pub const SAML_IDP_METADATA_KEY: &str = "saml_idp";
+
+const PROTOCOL_NS: &str = "urn:oasis:names:tc:SAML:2.0:protocol";
+const ASSERTION_NS: &str = "urn:oasis:names:tc:SAML:2.0:assertion";
+const METADATA_NS: &str = "urn:oasis:names:tc:SAML:2.0:metadata";
+const STATUS_SUCCESS: &str = "urn:oasis:names:tc:SAML:2.0:status:Success";
+const BEARER_CONFIRMATION: &str = "urn:oasis:names:tc:SAML:2.0:cm:bearer";
+const HTTP_REDIRECT_BINDING: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect";
+const HTTP_POST_BINDING: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST";
+const EMAIL_NAME_ID_FORMAT: &str = "urn:oasis:names:tc:SAML:1.1:nameid-format:emailAddress";
+
+/// Replay protection context of assertion IDs
+const ASSERTION_REPLAY_CONTEXT: &str = "saml_assertion";
+
+/// Tolerated clock difference between the identity provider and this service
+const CLOCK_SKEW: time::Duration = time::Duration::seconds(60);
+
+/// Errors of the SAML login flow
+#[derive(Debug, Error)]
+pub enum SamlError {
+    #[error("SAML is not configured for the tenant")]
+    NotConfigured,
+
+    #[error("Invalid SAML response: {0}")]
+    InvalidResponse(String),
+
+    #[error("Invalid SAML signature: {0}")]
+    InvalidSignature(#[from] XmlSignatureError),
+
+    #[error("Identity provider reported status {0}")]
+    StatusNotSuccess(String),
+
+    #[error("Assertion conditions not met: {0}")]
+    InvalidConditions(String),
+
+    #[error("Assertion was already used")]
+    Replayed,
+
+    #[error("No local account exists for this identity")]
+    AccountNotFound,
+
+    #[error("User is not an active member of the tenant")]
+    NotTenantMember,
+
+    #[error("Invalid identity provider metadata: {0}")]
+    InvalidMetadata(String),
+
+    #[error("Replay store error: {0}")]
+    ReplayStore(#[source] anyhow::Error),
+
+    #[error(transparent)]
+    User(#[from] UserError),
+
+    #[error(transparent)]
+    Tenant(#[from] TenantError),
+}
+
+/// Identity provider of a tenant, stored in the tenant metadata under
+/// [`SAML_IDP_METADATA_KEY`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SamlIdpConfig {
+    /// Expected `Issuer` of assertions
+    pub entity_id: String,
+    /// Single sign-on endpoint accepting HTTP-Redirect `AuthnRequest`s
+    pub sso_url: String,
+    /// Signing certificate, PEM or base64 DER as found in metadata
+    pub certificate: String,
+    /// Attribute carrying the email address; the `NameID` is used if not set
+    #[serde(default)]
+    pub email_attribute: Option<String>,
+}
+
+impl SamlIdpConfig {
+    /// Read the configuration from an identity provider's metadata document
+    pub fn from_metadata_xml(xml: &str) -> Result<Self, SamlError> {
+        let invalid = |message: &str| SamlError::InvalidMetadata(message.to_string());
+        let document =
+            Document::parse(xml).map_err(|err| SamlError::InvalidMetadata(err.to_string()))?;
+        let descriptor = document.root_element();
+        if !is_element(descriptor, METADATA_NS, "EntityDescriptor") {
+            return Err(invalid("Expected an EntityDescriptor"));
+        }
+        let entity_id = descriptor
+            .attribute("entityID")
+            .ok_or_else(|| invalid("Missing entityID"))?;
+        let idp = child(descriptor, METADATA_NS, "IDPSSODescriptor")
+            .ok_or_else(|| invalid("Missing IDPSSODescriptor"))?;
+
+        let sso_url = children(idp, METADATA_NS, "SingleSignOnService")
+            .find(|service| service.attribute("Binding") == Some(HTTP_REDIRECT_BINDING))
+            .and_then(|service| service.attribute("Location"))
+            .ok_or_else(|| invalid("No HTTP-Redirect single sign-on service"))?;
+        let certificate = children(idp, METADATA_NS, "KeyDescriptor")
+            .filter(|key| key.attribute("use").is_none_or(|usage| usage == "signing"))
+            .find_map(|key| {
+                key.descendants()
+                    .find(|node| is_element(*node, XMLDSIG_NS, "X509Certificate"))
+                    .and_then(|node| node.text())
+            })
+            .ok_or_else(|| invalid("No signing certificate"))?;
+        parse_certificate(certificate)?;
+
+        Ok(Self {
+            entity_id: entity_id.to_string(),
+            sso_url: sso_url.to_string(),
+            certificate: certificate.split_whitespace().collect(),
+            email_attribute: None,
+        })
+    }
+
+    /// Returns the identity provider configured for a tenant, if any
+    pub fn for_tenant(tenant: &Tenant) -> Option<Self> {
+        tenant
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get(SAML_IDP_METADATA_KEY))
+            .and_then(|config| serde_json::from_value(config.clone()).ok())
+    }
+}
+
+/// This service's registration as a SAML service provider
+#[derive(Debug, Clone, Deserialize)]
+pub struct SamlSpConfig {
+    /// Entity ID identifying the service provider, expected as `Audience`
+    pub entity_id: String,
+    /// Base URL of the assertion consumer service, without the tenant segment
+    pub acs_base_url: String,
+}
+
+impl SamlSpConfig {
+    /// Assertion consumer service URL of a tenant
+    pub fn acs_url(&self, tenant_id: Uuid) -> String {
+        format!(
+            "{}/{}/acs",
+            self.acs_base_url.trim_end_matches('/'),
+            tenant_id
+        )
+    }
+}
+
+/// Records assertion IDs so each assertion is accepted only once
+#[async_trait]
+pub trait AssertionReplayStore: Send + Sync {
+    /// Record an assertion ID; returns whether it was not seen before
+    async fn record(
+        &self,
+        tenant_id: &str,
+        assertion_id: &str,
+        ttl: Duration,
+    ) -> anyhow::Result<bool>;
+}
+
+#[async_trait]
+impl AssertionReplayStore for NonceStore {
+    async fn record(
+        &self,
+        tenant_id: &str,
+        assertion_id: &str,
+        ttl: Duration,
+    ) -> anyhow::Result<bool> {
+        self.record_once(
+            tenant_id,
+            ASSERTION_REPLAY_CONTEXT,
+            assertion_id,
+            ttl.as_secs(),
+        )
+        .await
+    }
+}
+
+/// Local user a SAML login resolved to
+#[derive(Debug, Clone)]
+pub struct SamlLogin {
+    pub user: User,
+    pub tenant_id: Uuid,
+    /// `NameID` asserted by the identity provider
+    pub name_id: String,
+}
+
+/// Facts taken from an assertion that passed validation
+#[derive(Debug)]
+struct ValidatedAssertion {
+    id: String,
+    name_id: String,
+    email: String,
+    expires_at: OffsetDateTime,
+}
+
+/// SAML 2.0 service provider for tenants with enterprise single sign-on
+///
+/// Logins start with an `AuthnRequest` sent to the tenant's identity provider
+/// through the HTTP-Redirect binding; the provider posts a `Response` back to the
+/// tenant's assertion consumer service. A response is only accepted if its single
+/// assertion is signed with the certificate configured for the tenant, either
+/// directly or through a signature on the response, the conditions and subject
+/// confirmation are met, and the assertion ID was not used before.
+pub struct SamlServiceProvider {
+    config: SamlSpConfig,
+    replay_store: Arc<dyn AssertionReplayStore>,
+    user_repository: Arc<dyn UserRepository>,
+    tenant_repository: Arc<dyn TenantRepository>,
+}
+
+impl SamlServiceProvider {
+    pub fn new(
+        config: SamlSpConfig,
+        replay_store: Arc<dyn AssertionReplayStore>,
+        user_repository: Arc<dyn UserRepository>,
+        tenant_repository: Arc<dyn TenantRepository>,
+    ) -> Self {
+        Self {
+            config,
+            replay_store,
+            user_repository,
+            tenant_repository,
+        }
+    }
+
+    pub fn config(&self) -> &SamlSpConfig {
+        &self.config
+    }
+
+    /// Build the URL that starts a login at the tenant's identity provider
+    pub async fn authn_request_url(&self, tenant_id: Uuid) -> Result<String, SamlError> {
+        let idp = self.idp_config(tenant_id).await?.1;
+        let issue_instant = OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .map_err(|err| SamlError::InvalidResponse(err.to_string()))?;
+
+        let request = format!(
+            concat!(
+                r#"<samlp:AuthnRequest xmlns:samlp="{protocol}" xmlns:saml="{assertion}" "#,
+                r#"ID="_{id}" Version="2.0" IssueInstant="{instant}" Destination="{destination}" "#,
+                r#"AssertionConsumerServiceURL="{acs}" ProtocolBinding="{binding}">"#,
+                "<saml:Issuer>{issuer}</saml:Issuer>",
+                r#"<samlp:NameIDPolicy Format="{format}" AllowCreate="false"/>"#,
+                "</samlp:AuthnRequest>"
+            ),
+            protocol = PROTOCOL_NS,
+            assertion = ASSERTION_NS,
+            id = Uuid::new_v4().simple(),
+            instant = issue_instant,
+            destination = escape_xml(&idp.sso_url),
+            acs = escape_xml(&self.config.acs_url(tenant_id)),
+            binding = HTTP_POST_BINDING,
+            issuer = escape_xml(&self.config.entity_id),
+            format = EMAIL_NAME_ID_FORMAT,
+        );
+
+        // HTTP-Redirect binding: raw DEFLATE, base64, URL encoding
+        let deflated = miniz_oxide::deflate::compress_to_vec(request.as_bytes(), 6);
+        let separator = if idp.sso_url.contains('?') { '&' } else { '?' };
+        Ok(format!(
+            "{}{}SAMLRequest={}",
+            idp.sso_url,
+            separator,
+            urlencoding::encode(&STANDARD.encode(deflated))
+        ))
+    }
+
+    /// Validate a base64 encoded `SAMLResponse` posted to the tenant's assertion
+    /// consumer service and resolve the local user
+    pub async fn consume_response(
+        &self,
+        tenant_id: Uuid,
+        saml_response: &str,
+    ) -> Result<SamlLogin, SamlError> {
+        let (tenant, idp) = self.idp_config(tenant_id).await?;
+
+        let compact: String = saml_response.split_whitespace().collect();
+        let xml = STANDARD
+            .decode(compact)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(|| SamlError::InvalidResponse("Response is not base64 XML".to_string()))?;
+
+        let now = OffsetDateTime::now_utc();
+        let assertion = self.validate_response(&xml, &idp, tenant_id, now)?;
+
+        let ttl = (assertion.expires_at + CLOCK_SKEW - now).unsigned_abs();
+        let first_use = self
+            .replay_store
+            .record(&tenant_id.to_string(), &assertion.id, ttl)
+            .await
+            .map_err(SamlError::ReplayStore)?;
+        if !first_use {
+            warn!(
+                tenant_id = %tenant_id,
+                assertion_id = %assertion.id,
+                "Rejected replayed SAML assertion"
+            );
+            return Err(SamlError::Replayed);
+        }
+
+        let user = self.resolve_user(&tenant, &assertion.email).await?;
+        debug!(user_id = %user.id, tenant_id = %tenant_id, "Resolved SAML login");
+
+        Ok(SamlLogin {
+            user,
+            tenant_id,
+            name_id: assertion.name_id,
+        })
+    }
+
+    /// Tenant, unless archived, with its identity provider configuration
+    async fn idp_config(&self, tenant_id: Uuid) -> Result<(Tenant, SamlIdpConfig), SamlError> {
+        let tenant = self
+            .tenant_repository
+            .find_tenant_by_id(tenant_id)
+            .await?
+            .ok_or(TenantError::NotFound)?;
+        if tenant.status.is_archived() {
+            return Err(TenantError::InactiveTenant.into());
+        }
+        let idp = SamlIdpConfig::for_tenant(&tenant).ok_or(SamlError::NotConfigured)?;
+        Ok((tenant, idp))
+    }
+
+    /// Check signature, issuer, conditions and subject confirmation of a response
+    fn validate_response(
+        &self,
+        xml: &str,
+        idp: &SamlIdpConfig,
+        tenant_id: Uuid,
+        now: OffsetDateTime,
+    ) -> Result<ValidatedAssertion, SamlError> {
+        let invalid = |message: &str| SamlError::InvalidResponse(message.to_string());
+        let document =
+            Document::parse(xml).map_err(|err| SamlError::InvalidResponse(err.to_string()))?;
+        let response = document.root_element();
+        if !is_element(response, PROTOCOL_NS, "Response") {
+            return Err(invalid("Expected a Response"));
+        }
+
+        let status = child(response, PROTOCOL_NS, "Status")
+            .and_then(|status| child(status, PROTOCOL_NS, "StatusCode"))
+            .and_then(|code| code.attribute("Value"))
+            .ok_or_else(|| invalid("Missing status"))?;
+        if status != STATUS_SUCCESS {
+            return Err(SamlError::StatusNotSuccess(status.to_string()));
+        }
+
+        let acs_url = self.config.acs_url(tenant_id);
+        if response
+            .attribute("Destination")
+            .is_some_and(|destination| destination != acs_url)
+        {
+            return Err(invalid("Response is addressed to another destination"));
+        }
+
+        // Signature wrapping defence: the signed elements are referenced by ID, so
+        // IDs must be unique and the one assertion must be the one that is read
+        let mut ids = HashSet::new();
+        if !document
+            .descendants()
+            .filter_map(|node| node.attribute("ID"))
+            .all(|id| ids.insert(id))
+        {
+            return Err(invalid("Duplicate element IDs"));
+        }
+        let assertions: Vec<Node> = document
+            .descendants()
+            .filter(|node| is_element(*node, ASSERTION_NS, "Assertion"))
+            .collect();
+        let [assertion] = assertions.as_slice() else {
+            return Err(invalid("Expected exactly one assertion"));
+        };
+        let assertion = *assertion;
+        if assertion.parent_element() != Some(response) {
+            return Err(invalid("Assertion is not part of the response"));
+        }
+
+        let certificate = parse_certificate(&idp.certificate)?;
+        let signed = [assertion, response].into_iter().find_map(|element| {
+            child(element, XMLDSIG_NS, "Signature").map(|signature| (element, signature))
+        });
+        let Some((signed, signature)) = signed else {
+            return Err(XmlSignatureError::Malformed("Assertion is not signed".to_string()).into());
+        };
+        verify_enveloped_signature(signed, signature, &certificate)?;
+
+        let assertion_id = assertion
+            .attribute("ID")
+            .ok_or_else(|| invalid("Assertion has no ID"))?;
+        let issuer = child(assertion, ASSERTION_NS, "Issuer").and_then(|issuer| issuer.text());
+        if issuer.map(str::trim) != Some(idp.entity_id.as_str()) {
+            return Err(invalid("Assertion was issued by another identity provider"));
+        }
+
+        // Conditions: validity window and audience
+        let conditions = child(assertion, ASSERTION_NS, "Conditions")
+            .ok_or_else(|| SamlError::InvalidConditions("Missing conditions".to_string()))?;
+        let not_before = conditions
+            .attribute("NotBefore")
+            .map(parse_instant)
+            .transpose()?;
+        if not_before.is_some_and(|not_before| now + CLOCK_SKEW < not_before) {
+            return Err(SamlError::InvalidConditions(
+                "Assertion is not yet valid".to_string(),
+            ));
+        }
+        let mut expires_at = match conditions.attribute("NotOnOrAfter") {
+            Some(not_on_or_after) => parse_instant(not_on_or_after)?,
+            None => {
+                return Err(SamlError::InvalidConditions(
+                    "Assertion has no expiry".to_string(),
+                ));
+            },
+        };
+        if now - CLOCK_SKEW >= expires_at {
+            return Err(SamlError::InvalidConditions(
+                "Assertion has expired".to_string(),
+            ));
+        }
+        let audience_matches = children(conditions, ASSERTION_NS, "AudienceRestriction")
+            .flat_map(|restriction| children(restriction, ASSERTION_NS, "Audience"))
+            .any(|audience| audience.text().map(str::trim) == Some(&self.config.entity_id));
+        if !audience_matches {
+            return Err(SamlError::InvalidConditions(
+                "Assertion is intended for another audience".to_string(),
+            ));
+        }
+
+        // Subject: a bearer confirmation for this tenant's consumer service
+        let subject =
+            child(assertion, ASSERTION_NS, "Subject").ok_or_else(|| invalid("Missing subject"))?;
+        let name_id = child(subject, ASSERTION_NS, "NameID")
+            .map(text_content)
+            .transpose()?
+            .map(str::trim)
+            .filter(|name_id| !name_id.is_empty())
+            .ok_or_else(|| invalid("Missing NameID"))?;
+        let mut confirmed = false;
+        for data in children(subject, ASSERTION_NS, "SubjectConfirmation")
+            .filter(|confirmation| confirmation.attribute("Method") == Some(BEARER_CONFIRMATION))
+            .filter_map(|confirmation| child(confirmation, ASSERTION_NS, "SubjectConfirmationData"))
+        {
+            let Some(not_on_or_after) = data.attribute("NotOnOrAfter") else {
+                continue;
+            };
+            let not_on_or_after = parse_instant(not_on_or_after)?;
+            if data.attribute("Recipient") == Some(acs_url.as_str())
+                && now - CLOCK_SKEW < not_on_or_after
+            {
+                expires_at = expires_at.max(not_on_or_after);
+                confirmed = true;
+                break;
+            }
+        }
+        if !confirmed {
+            return Err(SamlError::InvalidConditions(
+                "No valid bearer subject confirmation".to_string(),
+            ));
+        }
+
+        let email = match &idp.email_attribute {
+            Some(name) => child(assertion, ASSERTION_NS, "AttributeStatement")
+                .into_iter()
+                .flat_map(|statement| children(statement, ASSERTION_NS, "Attribute"))
+                .find(|attribute| attribute.attribute("Name") == Some(name.as_str()))
+                .and_then(|attribute| child(attribute, ASSERTION_NS, "AttributeValue"))
+                .map(text_content)
+                .transpose()?
+                .map(str::trim)
+                .ok_or_else(|| invalid("Missing email attribute"))?,
+            None => name_id,
+        };
+
+        Ok(ValidatedAssertion {
+            id: assertion_id.to_string(),
+            name_id: name_id.to_string(),
+            email: email.to_string(),
+            expires_at,
+        })
+    }
+
+    /// Map the asserted email address to an active member of the tenant
+    async fn resolve_user(&self, tenant: &Tenant, email: &str) -> Result<User, SamlError> {
+        let user = self
+            .user_repository
+            .find_by_email(email)
+            .await?
+            .ok_or(SamlError::AccountNotFound)?;
+        if !user.is_active {
+            return Err(UserError::InactiveUser.into());
+        }
+
+        let is_member = self
+            .tenant_repository
+            .get_user_tenants(user.id)
+            .await?
+            .into_iter()
+            .any(|membership| membership.tenant_id == tenant.id && membership.is_active);
+        if !is_member {
+            return Err(SamlError::NotTenantMember);
+        }
+
+        Ok(user)
+    }
+}
+
+fn is_element(node: Node, namespace: &str, name: &str) -> bool {
+    node.is_element()
+        && node.tag_name().namespace() == Some(namespace)
+        && node.tag_name().name() == name
+}
+
+fn child<'a, 'input>(
+    node: Node<'a, 'input>,
+    namespace: &str,
+    name: &str,
+) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|child| is_element(*child, namespace, name))
+}
+
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    namespace: &'static str,
+    name: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children()
+        .filter(move |child| is_element(*child, namespace, name))
+}
+
+/// Text of an element that holds nothing but text
+///
+/// `text()` only returns the first text node and canonicalization drops
+/// comments, so a signed `admin@corp.com<!---->.evil.com` would read as
+/// `admin@corp.com`. Elements with comments or other child nodes are rejected.
+fn text_content<'a>(node: Node<'a, '_>) -> Result<&'a str, SamlError> {
+    if node.children().any(|child| !child.is_text()) {
+        return Err(SamlError::InvalidResponse(format!(
+            "{} must only contain text",
+            node.tag_name().name()
+        )));
+    }
+    Ok(node.text().unwrap_or_default())
+}
+
+fn parse_instant(value: &str) -> Result<OffsetDateTime, SamlError> {
+    OffsetDateTime::parse(value, &Rfc3339)
+        .map_err(|_| SamlError::InvalidResponse(format!("Invalid timestamp {}", value)))
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
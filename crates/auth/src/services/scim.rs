@@ -0,0 +1,1084 @@
+//! SCIM 2.0 provisioning
+//!
+//! Lets an enterprise identity provider create, update and deprovision the
+//! members of a tenant (RFC 7643/7644). Users are local accounts with a tenant
+//! membership; groups are the tenant roles, so a group's members are the users
+//! holding that role. Requests are authenticated by a bearer token issued per
+//! tenant, whose hash is stored in the tenant metadata under
+//! [`SCIM_TOKEN_METADATA_KEY`].
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use thiserror::Error;
+use time::OffsetDateTime;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::models::tenant::{
+    CreateTenantUserDto, TenantUser, UpdateTenantDto, UpdateTenantUserDto,
+};
+use crate::models::user::{User, UserError};
+use crate::services::tenant::{TenantService, TenantServiceError};
+use crate::services::user::{UserService, UserServiceError};
+use crate::session::types::SessionInvalidationReason;
+use crate::utils::bounded_json::BoundedJson;
+
+/// Tenant metadata key holding the SHA-256 hash of the tenant's SCIM token
+pub const SCIM_TOKEN_METADATA_KEY: &str = "scim_token";
+
+pub const USER_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+pub const GROUP_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:Group";
+pub const LIST_RESPONSE_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+pub const PATCH_OP_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:PatchOp";
+pub const ERROR_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+
+/// Role of provisioned users, and of users removed from a group
+const DEFAULT_ROLE: &str = "MEMBER";
+
+/// Role that must keep at least one member
+const ADMIN_ROLE: &str = "ADMIN";
+
+/// Number of random bytes in a SCIM token
+const TOKEN_BYTES: usize = 32;
+
+/// Errors of SCIM operations
+#[derive(Debug, Error)]
+pub enum ScimError {
+    #[error("Invalid SCIM token")]
+    InvalidToken,
+    #[error("Resource not found")]
+    NotFound,
+    #[error("Resource already exists")]
+    Uniqueness,
+    #[error("Invalid filter: {0}")]
+    InvalidFilter(String),
+    #[error("Invalid value: {0}")]
+    InvalidValue(String),
+    #[error("Attribute cannot be changed: {0}")]
+    Mutability(String),
+    #[error("Resource version does not match")]
+    PreconditionFailed,
+    #[error(transparent)]
+    User(#[from] UserServiceError),
+    #[error(transparent)]
+    Tenant(#[from] TenantServiceError),
+}
+
+impl ScimError {
+    /// `scimType` of the error response, for the errors RFC 7644 defines one
+    pub fn scim_type(&self) -> Option<&'static str> {
+        match self {
+            ScimError::Uniqueness => Some("uniqueness"),
+            ScimError::InvalidFilter(_) => Some("invalidFilter"),
+            ScimError::InvalidValue(_) => Some("invalidValue"),
+            ScimError::Mutability(_) => Some("mutability"),
+            _ => None,
+        }
+    }
+}
+
+/// Resource metadata, including the version used as ETag
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimMeta {
+    pub resource_type: String,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub created: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub last_modified: Option<OffsetDateTime>,
+    pub version: String,
+}
+
+/// Email address of a user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScimEmail {
+    pub value: String,
+    #[serde(default)]
+    pub primary: bool,
+}
+
+/// Reference to a user in a group, or to a group of a user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScimMember {
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+}
+
+/// SCIM user resource
+///
+/// `userName` is the email address of the account. `active` reflects both the
+/// account and the tenant membership.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimUser {
+    #[serde(default = "user_schemas")]
+    pub schemas: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub user_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default = "default_active")]
+    pub active: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub emails: Vec<ScimEmail>,
+    /// Groups of the user; read-only
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<ScimMember>,
+    /// Initial password; never returned
+    #[serde(default, skip_serializing)]
+    pub password: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<ScimMeta>,
+}
+
+/// SCIM group resource, backed by a tenant role
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimGroup {
+    #[serde(default = "group_schemas")]
+    pub schemas: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub display_name: String,
+    #[serde(default)]
+    pub members: Vec<ScimMember>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<ScimMeta>,
+}
+
+/// Page of a query result
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimListResponse<T> {
+    pub schemas: Vec<String>,
+    pub total_results: usize,
+    pub start_index: usize,
+    pub items_per_page: usize,
+    #[serde(rename = "Resources")]
+    pub resources: Vec<T>,
+}
+
+/// Body of a PATCH request
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScimPatchRequest {
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    #[serde(rename = "Operations")]
+    pub operations: Vec<ScimPatchOperation>,
+}
+
+/// A single PATCH operation
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScimPatchOperation {
+    pub op: String,
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub value: Option<Value>,
+}
+
+/// Paging parameters of a query, as in RFC 7644 section 3.4.2.4
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScimPage {
+    /// 1-based index of the first result
+    pub start_index: usize,
+    pub count: Option<usize>,
+}
+
+impl Default for ScimPage {
+    fn default() -> Self {
+        Self {
+            start_index: 1,
+            count: None,
+        }
+    }
+}
+
+/// Equality filter on a single attribute, the only filter supported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScimFilter {
+    pub attribute: String,
+    pub value: String,
+}
+
+impl ScimFilter {
+    /// Parse a filter of the form `attribute eq "value"`
+    pub fn parse(filter: &str) -> Result<Self, ScimError> {
+        let invalid = || ScimError::InvalidFilter(filter.to_string());
+
+        let mut parts = filter.trim().splitn(3, char::is_whitespace);
+        let attribute = parts.next().filter(|a| !a.is_empty()).ok_or_else(invalid)?;
+        let operator = parts.next().ok_or_else(invalid)?;
+        if !operator.eq_ignore_ascii_case("eq") {
+            return Err(ScimError::InvalidFilter(format!(
+                "Unsupported operator '{}'",
+                operator
+            )));
+        }
+        let value = serde_json::from_str::<String>(parts.next().ok_or_else(invalid)?.trim())
+            .map_err(|_| invalid())?;
+
+        Ok(Self {
+            attribute: attribute.to_string(),
+            value,
+        })
+    }
+
+    /// Whether the filter is on `attribute`, which is compared case-insensitively
+    fn is_on(&self, attribute: &str) -> bool {
+        self.attribute.eq_ignore_ascii_case(attribute)
+    }
+}
+
+fn user_schemas() -> Vec<String> {
+    vec![USER_SCHEMA.to_string()]
+}
+
+fn group_schemas() -> Vec<String> {
+    vec![GROUP_SCHEMA.to_string()]
+}
+
+fn default_active() -> bool {
+    true
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Weak ETag of a resource, derived from its content without `meta`
+fn version_of<T: Serialize>(resource: &T) -> String {
+    let content = serde_json::to_vec(resource).unwrap_or_default();
+    format!("W/\"{}\"", hex::encode(&Sha256::digest(content)[..8]))
+}
+
+/// Check an `If-Match` header against the current version of a resource
+fn check_version(current: &str, if_match: Option<&str>) -> Result<(), ScimError> {
+    let Some(if_match) = if_match else {
+        return Ok(());
+    };
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let matches = if_match
+        .split(',')
+        .any(|tag| tag.trim() == "*" || opaque(tag) == opaque(current));
+
+    if matches {
+        Ok(())
+    } else {
+        Err(ScimError::PreconditionFailed)
+    }
+}
+
+/// Read a boolean sent as JSON boolean or as string, as some providers do
+fn bool_value(value: &Value) -> Result<bool, ScimError> {
+    match value {
+        Value::Bool(value) => Ok(*value),
+        Value::String(value) if value.eq_ignore_ascii_case("true") => Ok(true),
+        Value::String(value) if value.eq_ignore_ascii_case("false") => Ok(false),
+        _ => Err(ScimError::InvalidValue(format!(
+            "Expected a boolean, got {}",
+            value
+        ))),
+    }
+}
+
+fn string_value(value: &Value) -> Result<String, ScimError> {
+    value
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| ScimError::InvalidValue(format!("Expected a string, got {}", value)))
+}
+
+/// Read the user IDs of a `members` value
+fn member_ids(value: &Value) -> Result<Vec<Uuid>, ScimError> {
+    let members: Vec<ScimMember> = serde_json::from_value(value.clone())
+        .map_err(|e| ScimError::InvalidValue(format!("Invalid members: {}", e)))?;
+    members
+        .iter()
+        .map(|member| parse_member(&member.value))
+        .collect()
+}
+
+fn parse_member(value: &str) -> Result<Uuid, ScimError> {
+    Uuid::parse_str(value).map_err(|_| ScimError::InvalidValue(format!("Unknown member {}", value)))
+}
+
+fn page<T>(mut resources: Vec<T>, page: ScimPage) -> ScimListResponse<T> {
+    let total_results = resources.len();
+    let start_index = page.start_index.max(1);
+    let resources: Vec<T> = resources
+        .drain(..)
+        .skip(start_index - 1)
+        .take(page.count.unwrap_or(usize::MAX))
+        .collect();
+
+    ScimListResponse {
+        schemas: vec![LIST_RESPONSE_SCHEMA.to_string()],
+        total_results,
+        start_index,
+        items_per_page: resources.len(),
+        resources,
+    }
+}
+
+/// Attributes of a user that SCIM clients can change
+struct UserChanges {
+    user_name: String,
+    display_name: String,
+    active: bool,
+}
+
+impl UserChanges {
+    fn apply(&mut self, attribute: &str, value: &Value) -> Result<(), ScimError> {
+        match attribute.to_ascii_lowercase().as_str() {
+            "username" => self.user_name = string_value(value)?,
+            "displayname" => self.display_name = string_value(value)?,
+            "active" => self.active = bool_value(value)?,
+            _ => return Err(ScimError::Mutability(attribute.to_string())),
+        }
+        Ok(())
+    }
+}
+
+/// SCIM service provider for the members of each tenant
+pub struct ScimService {
+    user_service: Arc<UserService>,
+    tenant_service: Arc<TenantService>,
+}
+
+impl ScimService {
+    pub fn new(user_service: Arc<UserService>, tenant_service: Arc<TenantService>) -> Self {
+        Self {
+            user_service,
+            tenant_service,
+        }
+    }
+
+    /// Issue a new SCIM token for a tenant, replacing the previous one
+    ///
+    /// The token is returned once; only its hash is stored.
+    pub async fn issue_token(&self, tenant_id: Uuid) -> Result<String, ScimError> {
+        let tenant = self.tenant_service.get_tenant(&tenant_id).await?;
+        let secret: String = (0..TOKEN_BYTES)
+            .map(|_| format!("{:02x}", rand::random::<u8>()))
+            .collect();
+        let token = format!("{}.{}", tenant_id, secret);
+
+        let mut metadata = match tenant.metadata {
+            Some(Value::Object(metadata)) => metadata,
+            _ => serde_json::Map::new(),
+        };
+        metadata.insert(
+            SCIM_TOKEN_METADATA_KEY.to_string(),
+            Value::String(hash_token(&token)),
+        );
+        let metadata = BoundedJson::new(Value::Object(metadata))
+            .map_err(|err| TenantServiceError::InvalidInput(err.to_string()))?;
+        self.tenant_service
+            .update_tenant(
+                &tenant_id,
+                UpdateTenantDto {
+                    name: None,
+                    subdomain: None,
+                    metadata: Some(Some(metadata)),
+                },
+            )
+            .await?;
+
+        info!(tenant_id = %tenant_id, "SCIM token issued");
+        Ok(token)
+    }
+
+    /// Resolve the tenant of a SCIM bearer token
+    ///
+    /// Tokens have the form `<tenant id>.<secret>`; the hash of the whole token
+    /// must match the one stored for the tenant.
+    pub async fn authenticate(&self, token: &str) -> Result<Uuid, ScimError> {
+        let tenant_id = token
+            .split_once('.')
+            .and_then(|(tenant_id, _)| Uuid::parse_str(tenant_id).ok())
+            .ok_or(ScimError::InvalidToken)?;
+
+        let tenant = match self.tenant_service.get_tenant(&tenant_id).await {
+            Ok(tenant) => tenant,
+            Err(TenantServiceError::NotFound(_)) => return Err(ScimError::InvalidToken),
+            Err(err) => return Err(err.into()),
+        };
+        let stored_hash = tenant
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get(SCIM_TOKEN_METADATA_KEY))
+            .and_then(Value::as_str)
+            .ok_or(ScimError::InvalidToken)?;
+
+        // Comparing digests does not leak the secret through timing
+        if tenant.status.is_archived() || stored_hash != hash_token(token) {
+            return Err(ScimError::InvalidToken);
+        }
+
+        Ok(tenant_id)
+    }
+
+    /// List the users of a tenant, optionally filtered by `userName`
+    pub async fn list_users(
+        &self,
+        tenant_id: Uuid,
+        filter: Option<&str>,
+        scim_page: ScimPage,
+    ) -> Result<ScimListResponse<ScimUser>, ScimError> {
+        let filter = filter.map(ScimFilter::parse).transpose()?;
+        if let Some(filter) = filter.as_ref().filter(|filter| !filter.is_on("userName")) {
+            return Err(ScimError::InvalidFilter(format!(
+                "Filtering on '{}' is not supported",
+                filter.attribute
+            )));
+        }
+
+        let mut resources = Vec::new();
+        for membership in self.tenant_service.get_tenant_users(&tenant_id).await? {
+            let user = self.user_service.get_user(membership.user_id).await?;
+            let matches = filter
+                .as_ref()
+                .is_none_or(|filter| user.email.eq_ignore_ascii_case(&filter.value));
+            if matches {
+                resources.push(user_resource(&user, &membership));
+            }
+        }
+
+        Ok(page(resources, scim_page))
+    }
+
+    pub async fn get_user(&self, tenant_id: Uuid, id: &str) -> Result<ScimUser, ScimError> {
+        let (user, membership) = self.find_member(tenant_id, id).await?;
+        Ok(user_resource(&user, &membership))
+    }
+
+    /// Provision a user into a tenant
+    ///
+    /// An existing account with the same email address only joins the tenant
+    /// if it belongs to no other tenant, see [`owns_account`](Self::owns_account);
+    /// a user without `password` can only sign in through single sign-on.
+    pub async fn create_user(
+        &self,
+        tenant_id: Uuid,
+        resource: ScimUser,
+    ) -> Result<ScimUser, ScimError> {
+        let user = match self
+            .user_service
+            .find_user_by_email(&resource.user_name)
+            .await?
+        {
+            Some(user) => {
+                if self.membership(tenant_id, user.id).await?.is_some()
+                    || !self.owns_account(tenant_id, user.id).await?
+                {
+                    return Err(ScimError::Uniqueness);
+                }
+                user
+            },
+            None => {
+                self.user_service
+                    .provision_user(
+                        &resource.user_name,
+                        resource.display_name.clone(),
+                        resource.password.as_deref(),
+                    )
+                    .await?
+            },
+        };
+
+        self.tenant_service
+            .add_user_to_tenant(
+                &tenant_id,
+                CreateTenantUserDto {
+                    user_id: user.id,
+                    tenant_role: DEFAULT_ROLE.to_string(),
+                    is_active: Some(resource.active),
+                },
+            )
+            .await?;
+        if !resource.active {
+            self.deprovision(tenant_id, user.id).await?;
+        }
+
+        info!(tenant_id = %tenant_id, user_id = %user.id, "User provisioned through SCIM");
+        self.get_user(tenant_id, &user.id.to_string()).await
+    }
+
+    /// Replace the attributes of a user
+    pub async fn replace_user(
+        &self,
+        tenant_id: Uuid,
+        id: &str,
+        resource: ScimUser,
+        if_match: Option<&str>,
+    ) -> Result<ScimUser, ScimError> {
+        if resource.password.is_some() {
+            return Err(ScimError::Mutability("password".to_string()));
+        }
+        let (user, membership) = self.find_member(tenant_id, id).await?;
+        let current = user_resource(&user, &membership);
+        check_version(&current.meta_version(), if_match)?;
+
+        let changes = UserChanges {
+            display_name: resource
+                .display_name
+                .unwrap_or_else(|| resource.user_name.clone()),
+            user_name: resource.user_name,
+            active: resource.active,
+        };
+        self.apply_user_changes(tenant_id, &user, current.active, changes)
+            .await?;
+        self.get_user(tenant_id, id).await
+    }
+
+    /// Apply PATCH operations to a user
+    ///
+    /// Supports `add` and `replace` of `userName`, `displayName` and `active`,
+    /// with or without a path.
+    pub async fn patch_user(
+        &self,
+        tenant_id: Uuid,
+        id: &str,
+        patch: ScimPatchRequest,
+        if_match: Option<&str>,
+    ) -> Result<ScimUser, ScimError> {
+        let (user, membership) = self.find_member(tenant_id, id).await?;
+        let current = user_resource(&user, &membership);
+        check_version(&current.meta_version(), if_match)?;
+
+        let mut changes = UserChanges {
+            user_name: user.email.clone(),
+            display_name: user.display_name.clone(),
+            active: current.active,
+        };
+        for operation in &patch.operations {
+            if !matches!(
+                operation.op.to_ascii_lowercase().as_str(),
+                "add" | "replace"
+            ) {
+                return Err(ScimError::Mutability(format!(
+                    "Operation '{}' is not supported on users",
+                    operation.op
+                )));
+            }
+            let value = operation
+                .value
+                .as_ref()
+                .ok_or_else(|| ScimError::InvalidValue("Missing value".to_string()))?;
+
+            match &operation.path {
+                Some(path) => changes.apply(path, value)?,
+                None => {
+                    let attributes = value.as_object().ok_or_else(|| {
+                        ScimError::InvalidValue("Expected an object of attributes".to_string())
+                    })?;
+                    for (attribute, value) in attributes {
+                        changes.apply(attribute, value)?;
+                    }
+                },
+            }
+        }
+
+        self.apply_user_changes(tenant_id, &user, current.active, changes)
+            .await?;
+        self.get_user(tenant_id, id).await
+    }
+
+    /// Remove a user from a tenant and end their sessions
+    pub async fn delete_user(
+        &self,
+        tenant_id: Uuid,
+        id: &str,
+        if_match: Option<&str>,
+    ) -> Result<(), ScimError> {
+        let (user, membership) = self.find_member(tenant_id, id).await?;
+        check_version(&user_resource(&user, &membership).meta_version(), if_match)?;
+
+        self.tenant_service
+            .remove_user_from_tenant(&tenant_id, &user.id)
+            .await?;
+        self.deprovision(tenant_id, user.id).await?;
+
+        info!(tenant_id = %tenant_id, user_id = %user.id, "User removed through SCIM");
+        Ok(())
+    }
+
+    /// List the groups of a tenant, optionally filtered by `displayName`
+    pub async fn list_groups(
+        &self,
+        tenant_id: Uuid,
+        filter: Option<&str>,
+        scim_page: ScimPage,
+    ) -> Result<ScimListResponse<ScimGroup>, ScimError> {
+        let filter = filter.map(ScimFilter::parse).transpose()?;
+        if let Some(filter) = filter
+            .as_ref()
+            .filter(|filter| !filter.is_on("displayName"))
+        {
+            return Err(ScimError::InvalidFilter(format!(
+                "Filtering on '{}' is not supported",
+                filter.attribute
+            )));
+        }
+
+        let mut roles: BTreeMap<String, Vec<TenantUser>> = BTreeMap::new();
+        for membership in self.tenant_service.get_tenant_users(&tenant_id).await? {
+            roles
+                .entry(membership.tenant_role.clone())
+                .or_default()
+                .push(membership);
+        }
+
+        let resources = roles
+            .iter()
+            .filter(|(role, _)| filter.as_ref().is_none_or(|filter| **role == filter.value))
+            .map(|(role, members)| group_resource(role, members))
+            .collect();
+        Ok(page(resources, scim_page))
+    }
+
+    pub async fn get_group(&self, tenant_id: Uuid, id: &str) -> Result<ScimGroup, ScimError> {
+        let members = self.role_members(tenant_id, id).await?;
+        if members.is_empty() {
+            return Err(ScimError::NotFound);
+        }
+        Ok(group_resource(id, &members))
+    }
+
+    /// Create a group by assigning its role to the listed members
+    ///
+    /// Groups exist as long as they have members; a group created without
+    /// members is not listed.
+    pub async fn create_group(
+        &self,
+        tenant_id: Uuid,
+        resource: ScimGroup,
+    ) -> Result<ScimGroup, ScimError> {
+        if resource.display_name.trim().is_empty() {
+            return Err(ScimError::InvalidValue(
+                "displayName is required".to_string(),
+            ));
+        }
+        if !self
+            .role_members(tenant_id, &resource.display_name)
+            .await?
+            .is_empty()
+        {
+            return Err(ScimError::Uniqueness);
+        }
+
+        let members = resource
+            .members
+            .iter()
+            .map(|member| parse_member(&member.value))
+            .collect::<Result<BTreeSet<_>, _>>()?;
+        self.assign_role(
+            tenant_id,
+            &resource.display_name,
+            &resource.display_name,
+            members,
+        )
+        .await?;
+
+        let members = self.role_members(tenant_id, &resource.display_name).await?;
+        Ok(group_resource(&resource.display_name, &members))
+    }
+
+    /// Replace the name and members of a group
+    pub async fn replace_group(
+        &self,
+        tenant_id: Uuid,
+        id: &str,
+        resource: ScimGroup,
+        if_match: Option<&str>,
+    ) -> Result<ScimGroup, ScimError> {
+        let current = self.get_group(tenant_id, id).await?;
+        check_version(&current.meta_version(), if_match)?;
+
+        let members = resource
+            .members
+            .iter()
+            .map(|member| parse_member(&member.value))
+            .collect::<Result<BTreeSet<_>, _>>()?;
+        self.assign_role(tenant_id, id, &resource.display_name, members)
+            .await?;
+        self.get_group(tenant_id, &resource.display_name).await
+    }
+
+    /// Apply PATCH operations to a group
+    ///
+    /// Supports adding, removing and replacing `members`, including removal by
+    /// a `members[value eq "..."]` path, and replacing `displayName`.
+    pub async fn patch_group(
+        &self,
+        tenant_id: Uuid,
+        id: &str,
+        patch: ScimPatchRequest,
+        if_match: Option<&str>,
+    ) -> Result<ScimGroup, ScimError> {
+        let current = self.get_group(tenant_id, id).await?;
+        check_version(&current.meta_version(), if_match)?;
+
+        let mut display_name = current.display_name.clone();
+        let mut members = current
+            .members
+            .iter()
+            .map(|member| parse_member(&member.value))
+            .collect::<Result<BTreeSet<_>, _>>()?;
+
+        for operation in &patch.operations {
+            let op = operation.op.to_ascii_lowercase();
+            let path = operation.path.as_deref().unwrap_or_default();
+            let value = operation.value.as_ref();
+
+            if path.eq_ignore_ascii_case("displayName") && op == "replace" {
+                let value =
+                    value.ok_or_else(|| ScimError::InvalidValue("Missing value".to_string()))?;
+                display_name = string_value(value)?;
+            } else if path.eq_ignore_ascii_case("members") {
+                let ids = value.map(member_ids).transpose()?.unwrap_or_default();
+                match op.as_str() {
+                    "add" => members.extend(ids),
+                    "replace" => members = ids.into_iter().collect(),
+                    "remove" if value.is_none() => members.clear(),
+                    "remove" => {
+                        for id in &ids {
+                            members.remove(id);
+                        }
+                    },
+                    _ => return Err(ScimError::InvalidValue(format!("Unknown op {}", op))),
+                }
+            } else if op == "remove" && path.to_ascii_lowercase().starts_with("members[") {
+                let filter = path["members[".len()..]
+                    .strip_suffix(']')
+                    .ok_or_else(|| ScimError::InvalidFilter(path.to_string()))?;
+                let filter = ScimFilter::parse(filter)?;
+                if !filter.is_on("value") {
+                    return Err(ScimError::InvalidFilter(path.to_string()));
+                }
+                members.remove(&parse_member(&filter.value)?);
+            } else {
+                return Err(ScimError::Mutability(format!(
+                    "Operation '{}' on '{}' is not supported on groups",
+                    operation.op, path
+                )));
+            }
+        }
+
+        self.assign_role(tenant_id, id, &display_name, members)
+            .await?;
+        self.get_group(tenant_id, &display_name).await
+    }
+
+    /// Delete a group, returning its members to the default role
+    pub async fn delete_group(
+        &self,
+        tenant_id: Uuid,
+        id: &str,
+        if_match: Option<&str>,
+    ) -> Result<(), ScimError> {
+        let current = self.get_group(tenant_id, id).await?;
+        check_version(&current.meta_version(), if_match)?;
+
+        self.assign_role(tenant_id, id, id, BTreeSet::new()).await
+    }
+
+    /// Find a user who is a member of the tenant
+    async fn find_member(
+        &self,
+        tenant_id: Uuid,
+        id: &str,
+    ) -> Result<(User, TenantUser), ScimError> {
+        let user_id = Uuid::parse_str(id).map_err(|_| ScimError::NotFound)?;
+        let membership = self
+            .membership(tenant_id, user_id)
+            .await?
+            .ok_or(ScimError::NotFound)?;
+        let user = match self.user_service.get_user(user_id).await {
+            Ok(user) => user,
+            Err(UserServiceError::User(UserError::NotFound)) => return Err(ScimError::NotFound),
+            Err(err) => return Err(err.into()),
+        };
+        Ok((user, membership))
+    }
+
+    async fn membership(
+        &self,
+        tenant_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<TenantUser>, ScimError> {
+        Ok(self
+            .tenant_service
+            .get_user_tenants(&user_id)
+            .await?
+            .into_iter()
+            .find(|membership| membership.tenant_id == tenant_id))
+    }
+
+    /// Whether the account of `user_id` belongs to no tenant but `tenant_id`
+    ///
+    /// Only such accounts are changed as a whole, i.e. their profile and
+    /// account state. Accounts shared with other tenants are only changed
+    /// through their membership, so one tenant's SCIM client cannot take over
+    /// or lock out a user of another tenant.
+    async fn owns_account(&self, tenant_id: Uuid, user_id: Uuid) -> Result<bool, ScimError> {
+        Ok(self
+            .tenant_service
+            .get_user_tenants(&user_id)
+            .await?
+            .iter()
+            .all(|membership| membership.tenant_id == tenant_id))
+    }
+
+    async fn apply_user_changes(
+        &self,
+        tenant_id: Uuid,
+        user: &User,
+        active: bool,
+        changes: UserChanges,
+    ) -> Result<(), ScimError> {
+        if changes.user_name != user.email || changes.display_name != user.display_name {
+            if !self.owns_account(tenant_id, user.id).await? {
+                return Err(ScimError::Mutability(
+                    "userName and displayName of a user shared with other tenants".to_string(),
+                ));
+            }
+            self.user_service
+                .update_profile(user.id, &changes.user_name, &changes.display_name)
+                .await?;
+        }
+        if changes.active == active {
+            return Ok(());
+        }
+
+        self.tenant_service
+            .update_tenant_user(
+                &tenant_id,
+                &user.id,
+                UpdateTenantUserDto {
+                    tenant_role: None,
+                    is_active: Some(changes.active),
+                },
+            )
+            .await?;
+        if changes.active {
+            if self.owns_account(tenant_id, user.id).await? {
+                self.user_service.activate_user(user.id).await?;
+            }
+            info!(tenant_id = %tenant_id, user_id = %user.id, "User reactivated through SCIM");
+        } else {
+            self.deprovision(tenant_id, user.id).await?;
+        }
+        Ok(())
+    }
+
+    /// Lock out a user leaving a tenant
+    ///
+    /// The account is deactivated if it belongs to no other tenant. Otherwise
+    /// only the sessions bound to the tenant are ended, so none opened before
+    /// deprovisioning stays usable while the other tenants keep theirs.
+    async fn deprovision(&self, tenant_id: Uuid, user_id: Uuid) -> Result<(), ScimError> {
+        if self.owns_account(tenant_id, user_id).await? {
+            // Deactivation ends every session of the account
+            self.user_service.deactivate_user(user_id).await?;
+        } else {
+            self.user_service
+                .session_service()
+                .force_terminate_user_tenant_sessions(
+                    user_id,
+                    tenant_id,
+                    SessionInvalidationReason::AdminAction,
+                )
+                .await
+                .map_err(UserServiceError::from)?;
+        }
+
+        info!(tenant_id = %tenant_id, user_id = %user_id, "User deprovisioned through SCIM");
+        Ok(())
+    }
+
+    async fn role_members(
+        &self,
+        tenant_id: Uuid,
+        role: &str,
+    ) -> Result<Vec<TenantUser>, ScimError> {
+        Ok(self
+            .tenant_service
+            .get_tenant_users(&tenant_id)
+            .await?
+            .into_iter()
+            .filter(|membership| membership.tenant_role == role)
+            .collect())
+    }
+
+    /// Make `members` the members of `role`, renaming it to `new_role`
+    ///
+    /// Users losing the role get the default role. The default group cannot
+    /// lose members and the admin group cannot become empty.
+    async fn assign_role(
+        &self,
+        tenant_id: Uuid,
+        role: &str,
+        new_role: &str,
+        members: BTreeSet<Uuid>,
+    ) -> Result<(), ScimError> {
+        let memberships = self.tenant_service.get_tenant_users(&tenant_id).await?;
+        if let Some(unknown) = members
+            .iter()
+            .find(|id| !memberships.iter().any(|m| m.user_id == **id))
+        {
+            return Err(ScimError::InvalidValue(format!(
+                "User {} is not a member of the tenant",
+                unknown
+            )));
+        }
+
+        let removes_members = memberships
+            .iter()
+            .any(|m| m.tenant_role == role && !members.contains(&m.user_id));
+        if removes_members && role == DEFAULT_ROLE {
+            return Err(ScimError::Mutability(
+                "Members cannot be removed from the default group".to_string(),
+            ));
+        }
+        let had_admins = memberships.iter().any(|m| m.tenant_role == ADMIN_ROLE);
+        if role == ADMIN_ROLE && had_admins && (members.is_empty() || new_role != ADMIN_ROLE) {
+            return Err(ScimError::Mutability(
+                "The tenant must keep an administrator".to_string(),
+            ));
+        }
+
+        for membership in memberships {
+            let listed = members.contains(&membership.user_id);
+            let tenant_role = if listed && membership.tenant_role != new_role {
+                new_role
+            } else if !listed && membership.tenant_role == role {
+                DEFAULT_ROLE
+            } else {
+                continue;
+            };
+
+            self.tenant_service
+                .update_tenant_user(
+                    &tenant_id,
+                    &membership.user_id,
+                    UpdateTenantUserDto {
+                        tenant_role: Some(tenant_role.to_string()),
+                        is_active: None,
+                    },
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+impl ScimUser {
+    fn meta_version(&self) -> String {
+        self.meta
+            .as_ref()
+            .map(|meta| meta.version.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl ScimGroup {
+    fn meta_version(&self) -> String {
+        self.meta
+            .as_ref()
+            .map(|meta| meta.version.clone())
+            .unwrap_or_default()
+    }
+}
+
+fn user_resource(user: &User, membership: &TenantUser) -> ScimUser {
+    let mut resource = ScimUser {
+        schemas: user_schemas(),
+        id: Some(user.id.to_string()),
+        user_name: user.email.clone(),
+        display_name: Some(user.display_name.clone()),
+        active: user.is_active && membership.is_active,
+        emails: vec![ScimEmail {
+            value: user.email.clone(),
+            primary: true,
+        }],
+        groups: vec![ScimMember {
+            value: membership.tenant_role.clone(),
+            display: Some(membership.tenant_role.clone()),
+        }],
+        password: None,
+        meta: None,
+    };
+    resource.meta = Some(ScimMeta {
+        resource_type: "User".to_string(),
+        created: Some(membership.created_at),
+        last_modified: Some(user.updated_at.max(membership.updated_at)),
+        version: version_of(&resource),
+    });
+    resource
+}
+
+fn group_resource(role: &str, members: &[TenantUser]) -> ScimGroup {
+    let mut resource = ScimGroup {
+        schemas: group_schemas(),
+        id: Some(role.to_string()),
+        display_name: role.to_string(),
+        members: members
+            .iter()
+            .map(|membership| ScimMember {
+                value: membership.user_id.to_string(),
+                display: None,
+            })
+            .collect(),
+        meta: None,
+    };
+    resource.meta = Some(ScimMeta {
+        resource_type: "Group".to_string(),
+        created: members.iter().map(|m| m.created_at).min(),
+        last_modified: members.iter().map(|m| m.updated_at).max(),
+        version: version_of(&resource),
+    });
+    resource
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_eq_filter() {
+        let filter = ScimFilter::parse(r#"userName eq "jane@example.com""#).expect("Filter parses");
+        assert!(filter.is_on("username"));
+        assert_eq!(filter.value, "jane@example.com");
+
+        let filter =
+            ScimFilter::parse(r#"displayName EQ "Sales \"EMEA\"""#).expect("Filter parses");
+        assert_eq!(filter.value, r#"Sales "EMEA""#);
+
+        for invalid in ["", "userName", r#"userName co "jane""#, "userName eq jane"] {
+            assert!(
+                ScimFilter::parse(invalid).is_err(),
+                "{invalid} should not parse"
+            );
+        }
+    }
+
+    #[test]
+    fn test_if_match_compares_opaque_tags() {
+        let current = r#"W/"abc""#;
+        assert!(check_version(current, None).is_ok());
+        assert!(check_version(current, Some("*")).is_ok());
+        assert!(check_version(current, Some(r#""abc""#)).is_ok());
+        assert!(check_version(current, Some(r#"W/"old", W/"abc""#)).is_ok());
+        assert!(matches!(
+            check_version(current, Some(r#"W/"old""#)),
+            Err(ScimError::PreconditionFailed)
+        ));
+    }
+}
@@ -1,19 +1,60 @@
+use futures::stream::{BoxStream, StreamExt, TryStreamExt};
+use serde::Serialize;
 use serde_json::Value;
-use std::sync::Arc;
-use std::time::{Duration, SystemTime};
-use tracing::{debug, error, info};
+use sqlx::types::ipnetwork::IpNetwork;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
 use crate::{
-    config::AuthConfig,
+    config::{AuthConfig, SensitiveOperation, SessionLifetime},
+    models::{
+        AuthFactor,
+        tenant::{
+            BindingEnforcement, ClientBindingPolicy, LoginLocationViolation, TenantError,
+            TenantRepository, TenantSecurityPolicy,
+        },
+        user::{UserError, UserRepository, UserSessionState},
+    },
+    security::{client_binding::ClientCharacteristics, geoip::GeoIpResolver},
+    services::user_agent::UserAgentParser,
     session::{
-        Session, SessionError, SessionFilter, SessionRepository,
-        types::{DeviceFingerprint, MfaStatus, SessionInvalidationReason},
+        ACTIVE_TENANT_METADATA_KEY, IMPERSONATED_BY_METADATA_KEY,
+        IMPERSONATION_REASON_METADATA_KEY, LAST_AUTH_METADATA_KEY, PERSISTENT_SESSION_METADATA_KEY,
+        RESERVED_METADATA_KEYS, Session, SessionError, SessionExportFilter, SessionFilter,
+        SessionRepository, SessionWithLocation,
+        types::{
+            DeviceFingerprint, FactorAction, ImpersonationAction, MfaStatus,
+            SessionInvalidationReason, UserAgentDetails,
+        },
+    },
+    utils::{
+        bounded_json::{self, BoundedJson, JsonLimitError},
+        clock::{Clock, SystemClock},
     },
 };
+use acci_core::database::AdvisoryLock;
+use acci_core::rollout::{RolloutFlags, RolloutSubject};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
 
 const SESSION_TOKEN_LENGTH: usize = 32;
 
+/// Rollout flag applying the [`BindingEnforcement::Enforce`] mode of tenant policies
+///
+/// Outside the rollout, mismatches are only monitored.
+pub const CLIENT_BINDING_ENFORCEMENT_FLAG: &str = "client_binding_enforcement";
+/// Rollout flag storing the tokens of new sessions as HMAC-SHA256 instead of the legacy hash
+pub const SESSION_TOKEN_HMAC_FLAG: &str = "session_token_hmac";
+
+/// Name of the advisory lock serializing the session cleanup across instances
+pub const SESSION_CLEANUP_LOCK: &str = "acci.session_cleanup";
+
 #[derive(Debug, thiserror::Error)]
 pub enum SessionServiceError {
     #[error("Repository error: {0}")]
@@ -22,19 +63,227 @@ pub enum SessionServiceError {
     TokenGeneration,
     #[error("Failed to hash session token")]
     TokenHashing,
+    #[error("MFA enrollment has not been completed for this session")]
+    MfaEnrollmentPending,
+    #[error("User is not an active member of the tenant")]
+    NotTenantMember,
+    #[error("Tenant switching requires a tenant repository")]
+    TenantSwitchUnavailable,
+    #[error("Impersonation requires a tenant repository")]
+    ImpersonationUnavailable,
+    #[error("Impersonation not allowed: {0}")]
+    ImpersonationNotAllowed(&'static str),
+    #[error("Tenant error: {0}")]
+    Tenant(#[from] TenantError),
+    #[error("Session reconciliation requires a user repository")]
+    ReconciliationUnavailable,
+    #[error("User error: {0}")]
+    User(#[from] UserError),
+    #[error("Not a valid guest session")]
+    NotGuestSession,
+    #[error("Invalid guest session metadata: {0}")]
+    InvalidGuestMetadata(&'static str),
+    #[error("Invalid session metadata: {0}")]
+    InvalidMetadata(#[from] JsonLimitError),
+}
+
+impl From<SessionServiceError> for acci_core::Error {
+    fn from(err: SessionServiceError) -> Self {
+        use acci_core::error::ErrorKind;
+
+        let kind = match err {
+            SessionServiceError::Repository(e) => return e.into(),
+            SessionServiceError::Tenant(e) => return e.into(),
+            SessionServiceError::User(e) => return e.into(),
+            SessionServiceError::MfaEnrollmentPending => ErrorKind::MfaEnrollmentRequired,
+            SessionServiceError::ImpersonationNotAllowed(_) => ErrorKind::Forbidden,
+            SessionServiceError::NotTenantMember => ErrorKind::TenantAccessDenied,
+            SessionServiceError::NotGuestSession
+            | SessionServiceError::InvalidGuestMetadata(_)
+            | SessionServiceError::InvalidMetadata(_) => ErrorKind::InvalidInput,
+            SessionServiceError::TenantSwitchUnavailable
+            | SessionServiceError::ImpersonationUnavailable
+            | SessionServiceError::ReconciliationUnavailable => ErrorKind::Configuration,
+            SessionServiceError::TokenGeneration | SessionServiceError::TokenHashing => {
+                ErrorKind::Internal
+            },
+        };
+        Self::domain(kind, err.to_string())
+    }
 }
 
-pub struct SessionService {
+/// Outcome of comparing a request with the client its session is bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientBindingCheck {
+    /// The session is not bound, e.g. it was created before its tenant enabled binding
+    Unbound,
+    /// The request matches the bound client
+    Matched,
+    /// The request does not match, but the tenant only monitors mismatches
+    Mismatched,
+    /// The request does not match and the session has been invalidated
+    Rejected,
+}
+
+/// Outcome of a pass of the session reconciliation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SessionReconciliationReport {
+    #[serde(with = "time::serde::rfc3339")]
+    pub started_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub finished_at: OffsetDateTime,
+    /// Whether the pass continued the scan of an interrupted pass
+    pub resumed: bool,
+    pub sessions_checked: u64,
+    /// Sessions of users that were deactivated or deleted
+    pub invalidated_user_deactivated: u64,
+    /// Sessions created before the last password change of their user
+    pub invalidated_password_changed: u64,
+}
+
+pub struct SessionService<C: Clock = SystemClock> {
     repository: Arc<dyn SessionRepository>,
     config: Arc<AuthConfig>,
+    clock: C,
+    tenant_repository: Option<Arc<dyn TenantRepository>>,
+    cleanup_lock: Option<AdvisoryLock>,
+    geoip_resolver: Option<Arc<dyn GeoIpResolver>>,
+    user_repository: Option<Arc<dyn UserRepository>>,
+    rollout_flags: Option<Arc<RolloutFlags>>,
+    user_agent_parser: Arc<UserAgentParser>,
+    region: Option<String>,
+    last_reconciliation: Mutex<Option<SessionReconciliationReport>>,
 }
 
 impl SessionService {
     pub fn new(repository: Arc<dyn SessionRepository>, config: Arc<AuthConfig>) -> Self {
-        Self { repository, config }
+        Self::with_clock(repository, config, SystemClock)
+    }
+}
+
+impl<C: Clock> SessionService<C> {
+    /// Create a session service that reads the current time from `clock`
+    pub fn with_clock(
+        repository: Arc<dyn SessionRepository>,
+        config: Arc<AuthConfig>,
+        clock: C,
+    ) -> Self {
+        Self {
+            repository,
+            config,
+            clock,
+            tenant_repository: None,
+            cleanup_lock: None,
+            geoip_resolver: None,
+            user_repository: None,
+            rollout_flags: None,
+            user_agent_parser: Arc::new(UserAgentParser::new()),
+            region: None,
+            last_reconciliation: Mutex::new(None),
+        }
+    }
+
+    /// Enables switching the active tenant of a session
+    pub fn with_tenant_repository(mut self, tenant_repository: Arc<dyn TenantRepository>) -> Self {
+        self.tenant_repository = Some(tenant_repository);
+        self
+    }
+
+    /// Runs the cleanup job only on the instance holding `lock`, see [`SESSION_CLEANUP_LOCK`]
+    pub fn with_cleanup_lock(mut self, lock: AdvisoryLock) -> Self {
+        self.cleanup_lock = Some(lock);
+        self
+    }
+
+    /// Enables country restrictions of tenant policies, which locate clients with `resolver`
+    ///
+    /// Without a resolver, the country of every client is unknown.
+    pub fn with_geoip_resolver(mut self, resolver: Arc<dyn GeoIpResolver>) -> Self {
+        self.geoip_resolver = Some(resolver);
+        self
+    }
+
+    /// Enables the session reconciliation, which checks sessions against the users in
+    /// `user_repository`
+    pub fn with_user_repository(mut self, user_repository: Arc<dyn UserRepository>) -> Self {
+        self.user_repository = Some(user_repository);
+        self
+    }
+
+    /// Gates client binding enforcement and HMAC session tokens behind rollout flags
+    ///
+    /// See [`CLIENT_BINDING_ENFORCEMENT_FLAG`] and [`SESSION_TOKEN_HMAC_FLAG`].
+    /// Without rollout flags, tenants enforcing client binding have it enforced
+    /// and session tokens keep the legacy hash.
+    pub fn with_rollout_flags(mut self, rollout_flags: Arc<RolloutFlags>) -> Self {
+        self.rollout_flags = Some(rollout_flags);
+        self
+    }
+
+    /// Tags the sessions created by this instance with `region`
+    ///
+    /// Usually the `region` of the core config, so that sessions can be
+    /// terminated per region with [`Self::force_terminate_sessions_by_region`].
+    pub fn with_region(mut self, region: Option<String>) -> Self {
+        self.region = region;
+        self
     }
 
+    /// Create a new session
+    ///
+    /// Persistent ("remember me") sessions use `persistent_session_lifetime_secs`, all others
+    /// the shorter `session_lifetime_secs`. The choice is recorded in the session metadata.
     pub async fn create_session(
+        &self,
+        user_id: Uuid,
+        device_id: Option<String>,
+        device_fingerprint: Option<DeviceFingerprint>,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+        metadata: Option<BoundedJson>,
+        persistent: bool,
+    ) -> Result<(Session, String), SessionServiceError> {
+        self.create_session_expiring_after(
+            user_id,
+            device_id,
+            device_fingerprint,
+            ip_address,
+            user_agent,
+            metadata.map(BoundedJson::into_inner),
+            persistent,
+            self.config.session_lifetime_for(persistent),
+        )
+        .await
+    }
+
+    /// Create a new session with the lifetime of a login type or an explicit lifetime
+    ///
+    /// The lifetime is resolved against the [`AuthConfig`] and clamped to
+    /// `session_lifetimes.max_secs`, so over-long requests still succeed.
+    pub async fn create_session_with_lifetime(
+        &self,
+        user_id: Uuid,
+        device_id: Option<String>,
+        device_fingerprint: Option<DeviceFingerprint>,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+        metadata: Option<BoundedJson>,
+        lifetime: SessionLifetime,
+    ) -> Result<(Session, String), SessionServiceError> {
+        self.create_session_expiring_after(
+            user_id,
+            device_id,
+            device_fingerprint,
+            ip_address,
+            user_agent,
+            metadata.map(BoundedJson::into_inner),
+            lifetime == SessionLifetime::Persistent,
+            self.config.resolve_session_lifetime(lifetime),
+        )
+        .await
+    }
+
+    async fn create_session_expiring_after(
         &self,
         user_id: Uuid,
         device_id: Option<String>,
@@ -42,19 +291,29 @@ impl SessionService {
         ip_address: Option<String>,
         user_agent: Option<String>,
         metadata: Option<Value>,
+        persistent: bool,
+        lifetime: Duration,
     ) -> Result<(Session, String), SessionServiceError> {
         debug!(
             user_id = %user_id,
             device_id = ?device_id,
+            persistent = persistent,
+            lifetime = ?lifetime,
             "Creating new session"
         );
 
         // Generate a random session token
         let token = self.generate_session_token()?;
-        let token_hash = self.hash_session_token(&token)?;
+        let token_hash = self.new_token_hash(&token, user_id).await?;
 
         // Calculate session expiry
-        let expires_at = SystemTime::now() + Duration::from_secs(self.config.session_lifetime_secs);
+        let now = self.clock.now();
+        let expires_at = now + lifetime;
+        let metadata = login_metadata(metadata, persistent, now);
+        self.check_metadata(metadata.as_ref())?;
+        let user_agent_details = user_agent
+            .as_deref()
+            .and_then(|user_agent| self.user_agent_parser.parse(user_agent));
 
         // Create session in repository
         let session = self
@@ -67,6 +326,8 @@ impl SessionService {
                 device_fingerprint,
                 ip_address,
                 user_agent,
+                user_agent_details,
+                self.region.clone(),
                 metadata,
             )
             .await
@@ -81,20 +342,40 @@ impl SessionService {
         Ok((session, token))
     }
 
+    /// The valid user session of `token`
+    ///
+    /// Guest sessions authenticate nobody and are treated like unknown tokens,
+    /// see [`Self::validate_session_or_guest`].
     pub async fn validate_session(
         &self,
         token: &str,
+    ) -> Result<Option<Session>, SessionServiceError> {
+        self.validate_session_of_kind(token, false).await
+    }
+
+    /// The valid user or guest session of `token`
+    pub async fn validate_session_or_guest(
+        &self,
+        token: &str,
+    ) -> Result<Option<Session>, SessionServiceError> {
+        self.validate_session_of_kind(token, true).await
+    }
+
+    async fn validate_session_of_kind(
+        &self,
+        token: &str,
+        allow_guest: bool,
     ) -> Result<Option<Session>, SessionServiceError> {
         debug!("Validating session token");
 
-        let token_hash = self.hash_session_token(token)?;
-        let session = self
-            .repository
-            .get_session_by_token(&token_hash)
-            .await
-            .map_err(SessionServiceError::Repository)?;
+        let session = self.find_session_by_token(token).await?;
 
         if let Some(session) = &session {
+            if session.is_guest() && !allow_guest {
+                debug!(session_id = %session.id, "Guest session does not authenticate a user");
+                return Ok(None);
+            }
+
             if !session.is_valid {
                 debug!(
                     session_id = %session.id,
@@ -104,7 +385,7 @@ impl SessionService {
                 return Ok(None);
             }
 
-            if session.expires_at <= SystemTime::now() {
+            if session.expires_at <= self.clock.now() {
                 debug!(
                     session_id = %session.id,
                     expires_at = ?session.expires_at,
@@ -130,6 +411,166 @@ impl SessionService {
         Ok(session)
     }
 
+    /// Create a session for a visitor who has not logged in yet
+    ///
+    /// Guest sessions are bound to no user and carry pre-login state like
+    /// consent choices in their metadata, see [`Self::merge_guest_metadata`].
+    /// They pass [`Self::validate_session_or_guest`] only, and are upgraded to
+    /// a user session at login with [`Self::upgrade_guest`].
+    pub async fn create_guest_session(
+        &self,
+        device_id: Option<String>,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+    ) -> Result<(Session, String), SessionServiceError> {
+        debug!(device_id = ?device_id, "Creating guest session");
+
+        let token = self.generate_session_token()?;
+        let token_hash = self.new_token_hash(&token, Uuid::nil()).await?;
+        let expires_at = self.clock.now() + self.config.session_lifetime();
+        let user_agent_details = user_agent
+            .as_deref()
+            .and_then(|user_agent| self.user_agent_parser.parse(user_agent));
+
+        // The nil user makes this a guest session
+        let session = self
+            .repository
+            .create_session(
+                Uuid::nil(),
+                token_hash,
+                expires_at,
+                device_id,
+                None,
+                ip_address,
+                user_agent,
+                user_agent_details,
+                self.region.clone(),
+                None,
+            )
+            .await
+            .map_err(SessionServiceError::Repository)?;
+
+        info!(session_id = %session.id, "Guest session created successfully");
+
+        Ok((session, token))
+    }
+
+    /// Merge the keys of the object `patch` into the metadata of a valid guest session
+    ///
+    /// Keys managed by the service, like the active tenant or the
+    /// impersonator, are rejected.
+    pub async fn merge_guest_metadata(
+        &self,
+        guest_session_id: Uuid,
+        patch: Value,
+    ) -> Result<(), SessionServiceError> {
+        let Value::Object(map) = &patch else {
+            return Err(SessionServiceError::InvalidGuestMetadata(
+                "metadata must be an object",
+            ));
+        };
+        if map
+            .keys()
+            .any(|key| RESERVED_METADATA_KEYS.contains(&key.as_str()))
+        {
+            return Err(SessionServiceError::InvalidGuestMetadata(
+                "metadata key is reserved",
+            ));
+        }
+        if let Err(err) = bounded_json::limits().check(&patch) {
+            debug!(session_id = %guest_session_id, error = %err, "Guest metadata rejected");
+            return Err(SessionServiceError::InvalidGuestMetadata(
+                "metadata exceeds the metadata limits",
+            ));
+        }
+        self.valid_guest_session(guest_session_id).await?;
+
+        match self
+            .repository
+            .merge_session_metadata(guest_session_id, patch)
+            .await
+        {
+            Ok(()) => Ok(()),
+            // Ended since it was read
+            Err(SessionError::NotFound) => Err(SessionServiceError::NotGuestSession),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Replace a guest session by a new session of `user_id`, who just logged in
+    ///
+    /// The new session has a new ID and token, so a token observed before the
+    /// login cannot be used to act as the user. It takes over the client and
+    /// the metadata of the guest session, except for keys managed by the
+    /// service, and starts with the standard lifetime. The guest session is
+    /// invalidated with [`SessionInvalidationReason::GuestUpgraded`].
+    pub async fn upgrade_guest(
+        &self,
+        guest_session_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(Session, String), SessionServiceError> {
+        debug!(
+            session_id = %guest_session_id,
+            user_id = %user_id,
+            "Upgrading guest session"
+        );
+
+        let guest = self.valid_guest_session(guest_session_id).await?;
+        let metadata = match guest.metadata {
+            Some(Value::Object(mut map)) => {
+                map.retain(|key, _| !RESERVED_METADATA_KEYS.contains(&key.as_str()));
+                // Patches within the limits may still add up to more
+                let limits = bounded_json::limits();
+                limits
+                    .truncate(Value::Object(map), &[])
+                    .and_then(|metadata| BoundedJson::with_limits(metadata, &limits).ok())
+            },
+            _ => None,
+        };
+
+        let (session, token) = self
+            .create_session(
+                user_id,
+                guest.device_id,
+                guest.device_fingerprint,
+                guest.ip_address,
+                guest.user_agent,
+                metadata,
+                false,
+            )
+            .await?;
+
+        match self
+            .repository
+            .invalidate_session(guest.id, SessionInvalidationReason::GuestUpgraded)
+            .await
+        {
+            // Ended concurrently, the new session stands on its own
+            Ok(()) | Err(SessionError::NotFound) => {},
+            Err(e) => return Err(e.into()),
+        }
+
+        info!(
+            guest_session_id = %guest.id,
+            session_id = %session.id,
+            user_id = %user_id,
+            "Guest session upgraded"
+        );
+
+        Ok((session, token))
+    }
+
+    /// The guest session `id`, if it is valid and has not expired
+    async fn valid_guest_session(&self, id: Uuid) -> Result<Session, SessionServiceError> {
+        self.repository
+            .get_session(id)
+            .await?
+            .filter(|session| {
+                session.is_guest() && session.is_valid && session.expires_at > self.clock.now()
+            })
+            .ok_or(SessionServiceError::NotGuestSession)
+    }
+
     pub async fn invalidate_session(
         &self,
         token: &str,
@@ -137,12 +578,7 @@ impl SessionService {
     ) -> Result<(), SessionServiceError> {
         debug!(reason = ?reason, "Invalidating session");
 
-        let token_hash = self.hash_session_token(token)?;
-        let session = self
-            .repository
-            .get_session_by_token(&token_hash)
-            .await
-            .map_err(SessionServiceError::Repository)?;
+        let session = self.find_session_by_token(token).await?;
 
         if let Some(session) = session {
             self.repository
@@ -195,6 +631,42 @@ impl SessionService {
         Ok(count)
     }
 
+    /// Terminate every active session of a user except `keep_session_id`
+    ///
+    /// Used when the user acts on their own account from one device and all
+    /// other devices must be signed out.
+    pub async fn terminate_other_user_sessions(
+        &self,
+        user_id: Uuid,
+        keep_session_id: Uuid,
+        reason: SessionInvalidationReason,
+    ) -> Result<u64, SessionServiceError> {
+        let sessions = self
+            .repository
+            .get_user_sessions(user_id, SessionFilter::Active)
+            .await
+            .map_err(SessionServiceError::Repository)?;
+
+        let mut count = 0;
+        for session in sessions.iter().filter(|s| s.id != keep_session_id) {
+            self.repository
+                .invalidate_session(session.id, reason.clone())
+                .await
+                .map_err(SessionServiceError::Repository)?;
+            count += 1;
+        }
+
+        info!(
+            user_id = %user_id,
+            kept_session_id = %keep_session_id,
+            terminated_sessions = count,
+            reason = ?reason,
+            "Terminated other user sessions"
+        );
+
+        Ok(count)
+    }
+
     /// Force terminate sessions based on filter criteria
     ///
     /// This allows for broader termination policies like:
@@ -260,18 +732,116 @@ impl SessionService {
         Ok(count)
     }
 
+    /// Force terminate the valid sessions created in `region`
+    ///
+    /// This is the response to a regional incident. With `tenant_id`, only the
+    /// sessions of that tenant are terminated. Sessions created before regions
+    /// were recorded have none and are only terminated with `include_unknown`.
+    pub async fn force_terminate_sessions_by_region(
+        &self,
+        region: &str,
+        tenant_id: Option<Uuid>,
+        include_unknown: bool,
+        reason: SessionInvalidationReason,
+    ) -> Result<u64, SessionServiceError> {
+        debug!(
+            region = region,
+            tenant_id = ?tenant_id,
+            include_unknown = include_unknown,
+            reason = ?reason,
+            "Force terminating sessions by region"
+        );
+
+        let count = self
+            .repository
+            .invalidate_sessions_by_region(region, tenant_id, include_unknown, reason.clone())
+            .await
+            .map_err(SessionServiceError::Repository)?;
+
+        info!(
+            region = region,
+            tenant_id = ?tenant_id,
+            terminated_sessions = count,
+            reason = ?reason,
+            "Successfully terminated sessions of region"
+        );
+
+        Ok(count)
+    }
+
+    /// Force terminate the valid sessions bound to `tenant_id`
+    ///
+    /// Used when a tenant is closed, e.g. archived; sessions the users hold in
+    /// other tenants are left alone.
+    pub async fn force_terminate_tenant_sessions(
+        &self,
+        tenant_id: Uuid,
+        reason: SessionInvalidationReason,
+    ) -> Result<u64, SessionServiceError> {
+        debug!(tenant_id = %tenant_id, reason = ?reason, "Force terminating tenant sessions");
+
+        let count = self
+            .repository
+            .invalidate_tenant_sessions(tenant_id, reason.clone())
+            .await
+            .map_err(SessionServiceError::Repository)?;
+
+        info!(
+            tenant_id = %tenant_id,
+            terminated_sessions = count,
+            reason = ?reason,
+            "Successfully terminated sessions of tenant"
+        );
+
+        Ok(count)
+    }
+
+    /// Force terminate the active sessions of a user that are bound to `tenant_id`
+    ///
+    /// Used when a user leaves a tenant; their sessions in other tenants are
+    /// left alone.
+    pub async fn force_terminate_user_tenant_sessions(
+        &self,
+        user_id: Uuid,
+        tenant_id: Uuid,
+        reason: SessionInvalidationReason,
+    ) -> Result<u64, SessionServiceError> {
+        let sessions = self
+            .repository
+            .get_user_sessions(user_id, SessionFilter::Active)
+            .await
+            .map_err(SessionServiceError::Repository)?;
+
+        let mut count = 0;
+        for session in sessions
+            .iter()
+            .filter(|session| session.tenant_id() == Some(tenant_id))
+        {
+            self.repository
+                .invalidate_session(session.id, reason.clone())
+                .await
+                .map_err(SessionServiceError::Repository)?;
+            count += 1;
+        }
+
+        info!(
+            user_id = %user_id,
+            tenant_id = %tenant_id,
+            terminated_sessions = count,
+            reason = ?reason,
+            "Terminated user sessions of tenant"
+        );
+
+        Ok(count)
+    }
+
     pub async fn rotate_session_token(
         &self,
         old_token: &str,
     ) -> Result<Option<String>, SessionServiceError> {
         debug!("Rotating session token");
 
-        let old_token_hash = self.hash_session_token(old_token)?;
-        let session = self
-            .repository
-            .get_session_by_token(&old_token_hash)
-            .await
-            .map_err(SessionServiceError::Repository)?;
+        let session = self.find_session_by_token(old_token).await?;
 
         if let Some(session) = session {
             if !session.is_valid {
@@ -284,7 +854,7 @@ impl SessionService {
             }
 
             let new_token = self.generate_session_token()?;
-            let new_token_hash = self.hash_session_token(&new_token)?;
+            let new_token_hash = self.new_token_hash(&new_token, session.user_id).await?;
 
             self.repository
                 .rotate_session_token(session.id, new_token_hash)
@@ -309,49 +879,284 @@ impl SessionService {
     ) -> Result<Vec<Session>, SessionServiceError> {
         debug!(user_id = %user_id, filter = ?filter, "Getting user sessions");
 
-        self.repository
+        let mut sessions = self
+            .repository
             .get_user_sessions(user_id, filter)
             .await
-            .map_err(SessionServiceError::Repository)
+            .map_err(SessionServiceError::Repository)?;
+        for session in &mut sessions {
+            session.user_agent_details = self.user_agent_details(session);
+        }
+        Ok(sessions)
     }
 
-    pub async fn cleanup_expired_sessions(&self) -> Result<u64, SessionServiceError> {
-        debug!("Running session cleanup");
+    /// Sessions of a user with their last recorded location, for the user's list of sessions
+    ///
+    /// Reads sessions and locations in one round-trip. Sessions without a
+    /// recorded location are listed without one; `current_session_id` is the
+    /// session the listing is requested with and is flagged as current.
+    pub async fn list_sessions_with_location(
+        &self,
+        user_id: Uuid,
+        filter: SessionFilter,
+        current_session_id: Option<Uuid>,
+    ) -> Result<Vec<SessionWithLocation>, SessionServiceError> {
+        debug!(user_id = %user_id, filter = ?filter, "Listing user sessions with locations");
 
-        self.repository
-            .cleanup_expired_sessions()
+        let mut sessions = self
+            .repository
+            .get_user_sessions_with_location(user_id, filter, current_session_id)
             .await
-            .map_err(SessionServiceError::Repository)
+            .map_err(SessionServiceError::Repository)?;
+        for listed in &mut sessions {
+            listed.session.user_agent_details = self.user_agent_details(&listed.session);
+        }
+        Ok(sessions)
     }
 
-    /// Create a session with a specific MFA status
-    pub async fn create_session_with_status(
-        &self,
+    /// Browser, operating system and device class of the client that created `session`
+    ///
+    /// Sessions created before these were recorded have their user agent parsed
+    /// on access; the result is not written back.
+    pub fn user_agent_details(&self, session: &Session) -> Option<UserAgentDetails> {
+        session.user_agent_details.clone().or_else(|| {
+            session
+                .user_agent
+                .as_deref()
+                .and_then(|user_agent| self.user_agent_parser.parse(user_agent))
+        })
+    }
+
+    /// Stream the sessions of a tenant for an export, see [`SessionRepository::stream_sessions`]
+    pub fn export_sessions(
+        &self,
+        filter: SessionExportFilter,
+    ) -> BoxStream<'static, Result<Session, SessionServiceError>> {
+        debug!(tenant_id = %filter.tenant_id, filter = ?filter.status, "Exporting sessions");
+
+        self.repository
+            .stream_sessions(filter)
+            .map_err(SessionServiceError::Repository)
+            .boxed()
+    }
+
+    pub async fn cleanup_expired_sessions(&self) -> Result<u64, SessionServiceError> {
+        debug!("Running session cleanup");
+
+        self.repository
+            .cleanup_expired_sessions()
+            .await
+            .map_err(SessionServiceError::Repository)
+    }
+
+    /// Run the cleanup job on a dedicated task until the runtime shuts down
+    ///
+    /// With a cleanup lock, instances sharing the database skip the runs
+    /// during which another instance holds it.
+    pub fn spawn_cleanup(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.config.session_cleanup_interval());
+            info!("Session cleanup job started");
+
+            loop {
+                interval.tick().await;
+                let result = match &self.cleanup_lock {
+                    Some(lock) => match lock.with_lock(|| self.cleanup_expired_sessions()).await {
+                        Ok(Some(result)) => result,
+                        Ok(None) => {
+                            debug!("Session cleanup is running on another instance");
+                            continue;
+                        },
+                        Err(e) => {
+                            error!("Failed to take the session cleanup lock: {}", e);
+                            continue;
+                        },
+                    },
+                    None => self.cleanup_expired_sessions().await,
+                };
+                match result {
+                    Ok(0) => {},
+                    Ok(deleted) => info!(deleted = deleted, "Cleaned up expired sessions"),
+                    Err(e) => error!("Session cleanup job failed: {}", e),
+                }
+            }
+        })
+    }
+
+    /// Invalidate the valid sessions whose user state no longer allows them
+    ///
+    /// Sessions of deactivated or deleted users are invalidated with
+    /// [`SessionInvalidationReason::UserDeactivated`], sessions created before
+    /// the last password change of their user with
+    /// [`SessionInvalidationReason::PasswordChanged`]. The valid sessions are
+    /// scanned in batches in ID order, and the position is persisted after
+    /// each batch, so a pass interrupted by a restart continues where it
+    /// stopped.
+    pub async fn reconcile_sessions(
+        &self,
+    ) -> Result<SessionReconciliationReport, SessionServiceError> {
+        let user_repository = self
+            .user_repository
+            .as_ref()
+            .ok_or(SessionServiceError::ReconciliationUnavailable)?;
+        let batch_size = self.config.session_reconciliation.batch_size.max(1);
+
+        let mut cursor = self.repository.reconciliation_cursor().await?;
+        let started_at = self.clock.now();
+        let mut report = SessionReconciliationReport {
+            started_at,
+            finished_at: started_at,
+            resumed: cursor.is_some(),
+            sessions_checked: 0,
+            invalidated_user_deactivated: 0,
+            invalidated_password_changed: 0,
+        };
+        debug!(resume_after = ?cursor, "Reconciling sessions with the state of their users");
+
+        loop {
+            let page = self
+                .repository
+                .valid_sessions_after(cursor, batch_size)
+                .await?;
+            let Some(last) = page.last() else {
+                break;
+            };
+            let last_id = last.id;
+
+            // Guest sessions have no user to reconcile with
+            let user_ids: Vec<Uuid> = page
+                .iter()
+                .filter(|session| !session.is_guest())
+                .map(|session| session.user_id)
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .collect();
+            let users = user_repository.session_states(&user_ids).await?;
+
+            for session in page.iter().filter(|session| !session.is_guest()) {
+                let Some(reason) = stale_session_reason(session, users.get(&session.user_id))
+                else {
+                    continue;
+                };
+                match self
+                    .repository
+                    .invalidate_session(session.id, reason.clone())
+                    .await
+                {
+                    Ok(()) => {
+                        debug!(
+                            session_id = %session.id,
+                            user_id = %session.user_id,
+                            reason = %reason,
+                            "Invalidated session of changed user"
+                        );
+                        if reason == SessionInvalidationReason::PasswordChanged {
+                            report.invalidated_password_changed += 1;
+                        } else {
+                            report.invalidated_user_deactivated += 1;
+                        }
+                    },
+                    // Ended since it was read
+                    Err(SessionError::NotFound) => {},
+                    Err(e) => return Err(e.into()),
+                }
+            }
+
+            report.sessions_checked += page.len() as u64;
+            cursor = Some(last_id);
+            self.repository.save_reconciliation_cursor(cursor).await?;
+            if page.len() < batch_size as usize {
+                break;
+            }
+        }
+
+        // The pass is complete, the next one starts from the beginning
+        self.repository.save_reconciliation_cursor(None).await?;
+        report.finished_at = self.clock.now();
+
+        info!(
+            sessions_checked = report.sessions_checked,
+            invalidated_user_deactivated = report.invalidated_user_deactivated,
+            invalidated_password_changed = report.invalidated_password_changed,
+            resumed = report.resumed,
+            "Reconciled sessions with the state of their users"
+        );
+        record_reconciliation(&report);
+        *self.last_reconciliation_guard() = Some(report.clone());
+        Ok(report)
+    }
+
+    /// Report of the last completed pass of the session reconciliation on this instance
+    pub fn last_reconciliation(&self) -> Option<SessionReconciliationReport> {
+        self.last_reconciliation_guard().clone()
+    }
+
+    fn last_reconciliation_guard(
+        &self,
+    ) -> std::sync::MutexGuard<'_, Option<SessionReconciliationReport>> {
+        self.last_reconciliation
+            .lock()
+            .expect("session reconciliation lock poisoned")
+    }
+
+    /// Run the session reconciliation on a dedicated task until the runtime shuts down
+    ///
+    /// A pass starts every `session_reconciliation.interval_secs`; see
+    /// [`Self::reconcile_sessions`].
+    pub fn spawn_reconciliation(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(
+                self.config
+                    .session_reconciliation
+                    .interval_secs
+                    .as_duration(),
+            );
+            info!("Session reconciliation job started");
+
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.reconcile_sessions().await {
+                    error!("Session reconciliation job failed: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Create a session with a specific MFA status
+    pub async fn create_session_with_status(
+        &self,
         user_id: Uuid,
         device_id: Option<String>,
         device_fingerprint: Option<DeviceFingerprint>,
         ip_address: Option<String>,
         user_agent: Option<String>,
         metadata: Option<Value>,
+        persistent: bool,
         mfa_status: MfaStatus,
     ) -> Result<(Session, String), SessionServiceError> {
         debug!(
             user_id = %user_id,
             device_id = ?device_id,
+            persistent = persistent,
             mfa_status = ?mfa_status,
             "Creating new session with MFA status"
         );
 
         // Generate new session token
         let token = self.generate_session_token()?;
-        let token_hash = self.hash_session_token(&token)?;
+        let token_hash = self.new_token_hash(&token, user_id).await?;
 
         // Calculate expiration
-        let now = SystemTime::now();
-        let expires_at = now + Duration::from_secs(self.config.session_lifetime_secs);
+        let now = self.clock.now();
+        let expires_at = now + self.config.session_lifetime_for(persistent);
+        let metadata = login_metadata(metadata, persistent, now);
+        self.check_metadata(metadata.as_ref())?;
+        let user_agent_details = user_agent
+            .as_deref()
+            .and_then(|user_agent| self.user_agent_parser.parse(user_agent));
 
         // Create session in repository
-        let session = self
+        let mut session = self
             .repository
             .create_session(
                 user_id,
@@ -361,14 +1166,21 @@ impl SessionService {
                 device_fingerprint,
                 ip_address,
                 user_agent,
+                user_agent_details,
+                self.region.clone(),
                 metadata,
             )
             .await
             .map_err(SessionServiceError::Repository)?;
 
-        // In a real implementation, we would set the MFA status during creation
-        // For our tests, we handle this separately with update_mfa_status_by_id
-        // Note: This would be properly fixed by updating the repository interface
+        // The repository creates sessions without MFA, so persist any other status separately
+        if mfa_status != MfaStatus::None {
+            self.repository
+                .update_mfa_status(session.id, mfa_status.clone())
+                .await
+                .map_err(SessionServiceError::Repository)?;
+            session.mfa_status = mfa_status.clone();
+        }
 
         info!(
             session_id = %session.id,
@@ -389,23 +1201,36 @@ impl SessionService {
         debug!("Updating session MFA status to {:?}", mfa_status);
 
         // Get session by token
-        let token_hash = self.hash_session_token(session_token)?;
         let session = self
-            .repository
-            .get_session_by_token(&token_hash)
-            .await
-            .map_err(SessionServiceError::Repository)?
+            .find_session_by_token(session_token)
+            .await?
             .ok_or_else(|| {
                 error!("Session not found when updating MFA status");
                 SessionServiceError::Repository(SessionError::NotFound)
             })?;
 
+        // Sessions awaiting mandatory enrollment can only be released via complete_mfa_enrollment
+        if session.mfa_status == MfaStatus::Pending && mfa_status != MfaStatus::Pending {
+            error!(
+                session_id = %session.id,
+                "Refusing to change MFA status of a session with pending enrollment"
+            );
+            return Err(SessionServiceError::MfaEnrollmentPending);
+        }
+
         // Update the MFA status using the repository method
         self.repository
             .update_mfa_status(session.id, mfa_status.clone())
             .await
             .map_err(SessionServiceError::Repository)?;
 
+        // A completed second factor counts as a full authentication
+        if mfa_status == MfaStatus::Verified {
+            self.repository
+                .record_authentication(session.id, self.clock.now())
+                .await?;
+        }
+
         info!(
             session_id = %session.id,
             user_id = %session.user_id,
@@ -416,6 +1241,516 @@ impl SessionService {
         Ok(())
     }
 
+    /// Mark the mandatory MFA enrollment of a session as completed
+    ///
+    /// Callers are responsible for checking that the user actually enrolled a factor.
+    pub async fn complete_mfa_enrollment(
+        &self,
+        session_token: &str,
+    ) -> Result<(), SessionServiceError> {
+        let session = self
+            .find_session_by_token(session_token)
+            .await?
+            .ok_or(SessionServiceError::Repository(SessionError::NotFound))?;
+
+        if session.mfa_status != MfaStatus::Pending {
+            debug!(
+                session_id = %session.id,
+                "Session has no pending MFA enrollment"
+            );
+            return Ok(());
+        }
+
+        self.repository
+            .update_mfa_status(session.id, MfaStatus::Verified)
+            .await
+            .map_err(SessionServiceError::Repository)?;
+        self.repository
+            .record_authentication(session.id, self.clock.now())
+            .await?;
+
+        info!(
+            session_id = %session.id,
+            user_id = %session.user_id,
+            "Completed pending MFA enrollment for session"
+        );
+
+        Ok(())
+    }
+
+    /// Whether the last full authentication of `session` is older than `max_age`
+    ///
+    /// Sensitive operations use this to demand a fresh password or second factor
+    /// even though the session itself is still valid.
+    pub fn reauth_required(&self, session: &Session, max_age: Duration) -> bool {
+        self.auth_older_than(session.last_auth_at(), max_age)
+    }
+
+    /// Whether an authentication at `last_auth_at` is too old for `operation`
+    pub fn reauth_required_for(
+        &self,
+        last_auth_at: OffsetDateTime,
+        operation: SensitiveOperation,
+    ) -> bool {
+        self.auth_older_than(last_auth_at, self.config.reauth_window(operation))
+    }
+
+    /// Record a completed re-authentication of a session
+    pub async fn record_reauthentication(
+        &self,
+        session_id: Uuid,
+    ) -> Result<(), SessionServiceError> {
+        self.repository
+            .record_authentication(session_id, self.clock.now())
+            .await?;
+
+        info!(session_id = %session_id, "Session re-authenticated");
+        Ok(())
+    }
+
+    /// Audit a change to the authentication factors made in a session
+    pub async fn record_factor_event(
+        &self,
+        session_id: Uuid,
+        action: FactorAction,
+        factor: &AuthFactor,
+    ) -> Result<(), SessionServiceError> {
+        self.repository
+            .record_factor_event(session_id, action, &factor.id())
+            .await?;
+        Ok(())
+    }
+
+    /// Client binding policy of the tenant of a session, if the tenant binds sessions
+    ///
+    /// Sessions without a tenant and services without a tenant repository are
+    /// never bound.
+    pub async fn client_binding_policy(
+        &self,
+        session: &Session,
+    ) -> Result<Option<ClientBindingPolicy>, SessionServiceError> {
+        let (Some(tenant_repository), Some(tenant_id)) =
+            (self.tenant_repository.as_ref(), session.tenant_id())
+        else {
+            return Ok(None);
+        };
+
+        let policy = tenant_repository
+            .find_tenant_by_id(tenant_id)
+            .await?
+            .map(|tenant| tenant.security_policy().client_binding);
+        Ok(policy.filter(|policy| policy.enabled))
+    }
+
+    /// Check a client IP address against the login location restrictions of a tenant
+    ///
+    /// Accepts plain addresses as well as the network notation sessions are stored with.
+    pub fn check_login_location(
+        &self,
+        policy: &TenantSecurityPolicy,
+        ip_address: Option<&str>,
+    ) -> Result<(), LoginLocationViolation> {
+        let ip = ip_address.and_then(parse_client_ip);
+        let country_code = match (&self.geoip_resolver, ip) {
+            (Some(resolver), Some(ip)) if !policy.allowed_countries.is_empty() => {
+                resolver.country_code(ip)
+            },
+            _ => None,
+        };
+        policy.check_login_location(ip, country_code.as_deref())
+    }
+
+    /// Invalidate the active sessions of a tenant that were created from a location
+    /// its policy no longer allows
+    ///
+    /// Sessions without a recorded IP address are kept. Returns the number of
+    /// invalidated sessions; without a tenant repository nothing is swept.
+    pub async fn sweep_login_location_violations(
+        &self,
+        tenant_id: Uuid,
+    ) -> Result<u64, SessionServiceError> {
+        let Some(tenant_repository) = &self.tenant_repository else {
+            return Ok(0);
+        };
+        let policy = tenant_repository
+            .find_tenant_by_id(tenant_id)
+            .await?
+            .ok_or(TenantError::NotFound)?
+            .security_policy();
+        if !policy.restricts_login_location() {
+            return Ok(0);
+        }
+
+        let mut filter = SessionExportFilter::new(tenant_id);
+        filter.status = SessionFilter::Active;
+        let mut sessions = self.repository.stream_sessions(filter);
+        let mut invalidated = 0;
+        while let Some(session) = sessions.next().await {
+            let session = session?;
+            let Some(ip_address) = session.ip_address.as_deref() else {
+                continue;
+            };
+            let Err(violation) = self.check_login_location(&policy, Some(ip_address)) else {
+                continue;
+            };
+
+            match self
+                .repository
+                .invalidate_session(session.id, SessionInvalidationReason::SecurityPolicyChange)
+                .await
+            {
+                Ok(()) => {
+                    debug!(
+                        session_id = %session.id,
+                        violation = %violation,
+                        "Invalidated session outside the allowed login locations"
+                    );
+                    invalidated += 1;
+                },
+                // Ended since it was read
+                Err(SessionError::NotFound) => {},
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        info!(
+            tenant_id = %tenant_id,
+            invalidated_sessions = invalidated,
+            "Swept sessions outside the allowed login locations"
+        );
+        Ok(invalidated)
+    }
+
+    /// Run [`Self::sweep_login_location_violations`] for a tenant on a dedicated task
+    ///
+    /// Used after the login location restrictions of a tenant changed.
+    pub fn spawn_login_location_sweep(self: Arc<Self>, tenant_id: Uuid) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            if let Err(e) = self.sweep_login_location_violations(tenant_id).await {
+                error!(tenant_id = %tenant_id, "Login location sweep failed: {}", e);
+            }
+        })
+    }
+
+    /// Bind a session to the characteristics of the client that created it
+    pub async fn bind_client(
+        &self,
+        session_id: Uuid,
+        client: &ClientCharacteristics,
+    ) -> Result<(), SessionServiceError> {
+        self.repository
+            .record_client_binding(session_id, &client.binding_hash())
+            .await?;
+
+        debug!(session_id = %session_id, "Session bound to client");
+        Ok(())
+    }
+
+    /// Compare a request's client with the client a session is bound to
+    ///
+    /// Mismatches are logged. In [`BindingEnforcement::Enforce`] mode the
+    /// session is invalidated with
+    /// [`SessionInvalidationReason::ClientBindingMismatch`] as well.
+    pub async fn check_client_binding(
+        &self,
+        session: &Session,
+        policy: &ClientBindingPolicy,
+        client: &ClientCharacteristics,
+    ) -> Result<ClientBindingCheck, SessionServiceError> {
+        let Some(bound) = session.client_binding() else {
+            return Ok(ClientBindingCheck::Unbound);
+        };
+        if bound == client.binding_hash() {
+            return Ok(ClientBindingCheck::Matched);
+        }
+
+        let mode = match policy.mode {
+            BindingEnforcement::Enforce
+                if !self
+                    .is_rolled_out(
+                        CLIENT_BINDING_ENFORCEMENT_FLAG,
+                        RolloutSubject::new(session.tenant_id(), Some(session.user_id)),
+                    )
+                    .await =>
+            {
+                BindingEnforcement::Monitor
+            },
+            mode => mode,
+        };
+        warn!(
+            session_id = %session.id,
+            user_id = %session.user_id,
+            mode = ?mode,
+            policy_mode = ?policy.mode,
+            tls_fingerprint_present = client.tls_fingerprint.is_some(),
+            "Request does not match the client the session is bound to"
+        );
+        match mode {
+            BindingEnforcement::Monitor => Ok(ClientBindingCheck::Mismatched),
+            BindingEnforcement::Enforce => {
+                self.repository
+                    .invalidate_session(
+                        session.id,
+                        SessionInvalidationReason::ClientBindingMismatch,
+                    )
+                    .await?;
+                Ok(ClientBindingCheck::Rejected)
+            },
+        }
+    }
+
+    fn auth_older_than(&self, last_auth_at: OffsetDateTime, max_age: Duration) -> bool {
+        self.clock.now() - last_auth_at > max_age
+    }
+
+    /// Make `tenant_id` the active tenant of a session
+    ///
+    /// The session owner must be an active member of the tenant. The switch is
+    /// recorded in the session audit log by the repository.
+    pub async fn switch_tenant(
+        &self,
+        session_id: Uuid,
+        tenant_id: Uuid,
+    ) -> Result<(), SessionServiceError> {
+        let tenant_repository = self
+            .tenant_repository
+            .as_ref()
+            .ok_or(SessionServiceError::TenantSwitchUnavailable)?;
+
+        let session = self
+            .repository
+            .get_session(session_id)
+            .await?
+            .filter(|session| session.is_valid)
+            .ok_or(SessionServiceError::Repository(SessionError::NotFound))?;
+
+        let is_member = tenant_repository
+            .get_user_tenants(session.user_id)
+            .await?
+            .iter()
+            .any(|membership| membership.tenant_id == tenant_id && membership.is_active);
+        if !is_member {
+            warn!(
+                session_id = %session_id,
+                user_id = %session.user_id,
+                tenant_id = %tenant_id,
+                "Refusing to switch session to a tenant the user is not a member of"
+            );
+            return Err(SessionServiceError::NotTenantMember);
+        }
+
+        self.repository
+            .set_active_tenant(session_id, tenant_id)
+            .await?;
+
+        info!(
+            session_id = %session_id,
+            user_id = %session.user_id,
+            from_tenant_id = ?session.active_tenant_id(),
+            tenant_id = %tenant_id,
+            "Switched active tenant of session"
+        );
+
+        Ok(())
+    }
+
+    /// Start a short-lived session in which `admin_user_id` acts as `target_user_id`
+    ///
+    /// The admin must be an active `ADMIN` of `tenant_id` and the target an
+    /// active member of it; other admins can only be impersonated when
+    /// `impersonation.allow_admin_targets` is set. `ttl` is capped at
+    /// `impersonation.max_ttl_secs`. The session is bound to the tenant, keeps
+    /// the impersonator and reason in its metadata, and its start is audited
+    /// for both users.
+    pub async fn create_impersonation_session(
+        &self,
+        admin_user_id: Uuid,
+        target_user_id: Uuid,
+        tenant_id: Uuid,
+        reason: &str,
+        ttl: Duration,
+    ) -> Result<(Session, String), SessionServiceError> {
+        let tenant_repository = self
+            .tenant_repository
+            .as_ref()
+            .ok_or(SessionServiceError::ImpersonationUnavailable)?;
+
+        let reason = reason.trim();
+        if reason.is_empty() {
+            return Err(SessionServiceError::ImpersonationNotAllowed(
+                "a reason is required",
+            ));
+        }
+        if admin_user_id == target_user_id {
+            return Err(SessionServiceError::ImpersonationNotAllowed(
+                "admins cannot impersonate themselves",
+            ));
+        }
+
+        let memberships = tenant_repository.get_tenant_users(tenant_id).await?;
+        let role_of = |user_id: Uuid| {
+            memberships
+                .iter()
+                .find(|membership| membership.user_id == user_id && membership.is_active)
+                .map(|membership| membership.tenant_role.as_str())
+        };
+        if !role_of(admin_user_id).is_some_and(is_admin_role) {
+            warn!(
+                admin_user_id = %admin_user_id,
+                tenant_id = %tenant_id,
+                "Refusing impersonation by a user who is not a tenant admin"
+            );
+            return Err(SessionServiceError::ImpersonationNotAllowed(
+                "only tenant admins can impersonate users",
+            ));
+        }
+        let Some(target_role) = role_of(target_user_id) else {
+            return Err(SessionServiceError::NotTenantMember);
+        };
+        if is_admin_role(target_role) && !self.config.impersonation.allow_admin_targets {
+            warn!(
+                admin_user_id = %admin_user_id,
+                target_user_id = %target_user_id,
+                tenant_id = %tenant_id,
+                "Refusing to impersonate another admin"
+            );
+            return Err(SessionServiceError::ImpersonationNotAllowed(
+                "impersonating other admins is disabled",
+            ));
+        }
+
+        let token = self.generate_session_token()?;
+        let token_hash = self.new_token_hash(&token, target_user_id).await?;
+        let now = self.clock.now();
+        let ttl = ttl.min(self.config.impersonation_max_ttl());
+
+        let mut metadata = serde_json::Map::new();
+        metadata.insert(
+            IMPERSONATED_BY_METADATA_KEY.to_string(),
+            Value::String(admin_user_id.to_string()),
+        );
+        metadata.insert(
+            IMPERSONATION_REASON_METADATA_KEY.to_string(),
+            Value::String(reason.to_string()),
+        );
+        metadata.insert(
+            ACTIVE_TENANT_METADATA_KEY.to_string(),
+            Value::String(tenant_id.to_string()),
+        );
+        let metadata = login_metadata(Some(Value::Object(metadata)), false, now);
+        self.check_metadata(metadata.as_ref())?;
+
+        let session = self
+            .repository
+            .create_session(
+                target_user_id,
+                token_hash,
+                now + ttl,
+                None,
+                None,
+                None,
+                None,
+                None,
+                self.region.clone(),
+                metadata,
+            )
+            .await?;
+
+        // An impersonation session must never exist without its audit trail
+        if let Err(err) = self
+            .repository
+            .record_impersonation_event(session.id, ImpersonationAction::Started)
+            .await
+        {
+            error!(
+                session_id = %session.id,
+                error = %err,
+                "Failed to audit impersonation, invalidating the session"
+            );
+            if let Err(invalidate_err) = self
+                .repository
+                .invalidate_session(session.id, SessionInvalidationReason::AdminAction)
+                .await
+            {
+                error!(
+                    session_id = %session.id,
+                    error = %invalidate_err,
+                    "Failed to invalidate unaudited impersonation session"
+                );
+            }
+            return Err(err.into());
+        }
+
+        info!(
+            session_id = %session.id,
+            admin_user_id = %admin_user_id,
+            target_user_id = %target_user_id,
+            tenant_id = %tenant_id,
+            ttl_secs = ttl.as_secs(),
+            "Started impersonation session"
+        );
+
+        Ok((session, token))
+    }
+
+    /// End an impersonation session before it expires
+    ///
+    /// Only the admin who started the session may end it.
+    pub async fn end_impersonation(
+        &self,
+        session_id: Uuid,
+        admin_user_id: Uuid,
+    ) -> Result<(), SessionServiceError> {
+        let session = self
+            .repository
+            .get_session(session_id)
+            .await?
+            .filter(|session| session.is_valid)
+            .ok_or(SessionServiceError::Repository(SessionError::NotFound))?;
+
+        match session.impersonated_by() {
+            Some(impersonator) if impersonator == admin_user_id => {},
+            Some(_) => {
+                return Err(SessionServiceError::ImpersonationNotAllowed(
+                    "only the impersonating admin can end the session",
+                ));
+            },
+            None => return Err(SessionServiceError::Repository(SessionError::NotFound)),
+        }
+
+        self.repository
+            .invalidate_session(session_id, SessionInvalidationReason::AdminAction)
+            .await?;
+        self.repository
+            .record_impersonation_event(session_id, ImpersonationAction::Ended)
+            .await?;
+
+        info!(
+            session_id = %session_id,
+            admin_user_id = %admin_user_id,
+            target_user_id = %session.user_id,
+            "Ended impersonation session"
+        );
+
+        Ok(())
+    }
+
+    /// Reject the metadata of a new session exceeding the configured limits
+    ///
+    /// Runs before the session is written; sessions without metadata pass.
+    fn check_metadata(&self, metadata: Option<&Value>) -> Result<(), SessionServiceError> {
+        let Some(metadata) = metadata else {
+            return Ok(());
+        };
+        self.config
+            .metadata_limits
+            .check_size(metadata)
+            .map_err(|err| {
+                warn!(error = %err, "Session metadata rejected");
+                err.into()
+            })
+    }
+
     fn generate_session_token(&self) -> Result<String, SessionServiceError> {
         let token: String = (0..SESSION_TOKEN_LENGTH)
             .map(|_| format!("{:02x}", rand::random::<u8>()))
@@ -423,9 +1758,74 @@ impl SessionService {
         Ok(token)
     }
 
+    /// Whether the rollout flag `name` applies to `subject`
+    ///
+    /// Without rollout flags every change is applied.
+    async fn is_rolled_out(&self, name: &str, subject: RolloutSubject) -> bool {
+        match &self.rollout_flags {
+            Some(rollout_flags) => rollout_flags.is_enabled(name, subject).await,
+            None => true,
+        }
+    }
+
+    /// Hash the token of a new session of `user_id`, see [`SESSION_TOKEN_HMAC_FLAG`]
+    async fn new_token_hash(
+        &self,
+        token: &str,
+        user_id: Uuid,
+    ) -> Result<String, SessionServiceError> {
+        let hmac = match &self.rollout_flags {
+            Some(rollout_flags) => {
+                rollout_flags
+                    .is_enabled(SESSION_TOKEN_HMAC_FLAG, RolloutSubject::user(user_id))
+                    .await
+            },
+            None => false,
+        };
+        if hmac {
+            Ok(self.hmac_session_token(token))
+        } else {
+            self.hash_session_token(token)
+        }
+    }
+
+    /// Session of a token stored with either hash
+    ///
+    /// The HMAC is only tried while rollout flags are configured, so services
+    /// that never issue HMAC tokens look tokens up once.
+    async fn find_session_by_token(
+        &self,
+        token: &str,
+    ) -> Result<Option<Session>, SessionServiceError> {
+        if self.rollout_flags.is_some() {
+            let session = self
+                .repository
+                .get_session_by_token(&self.hmac_session_token(token))
+                .await?;
+            if session.is_some() {
+                return Ok(session);
+            }
+        }
+
+        let token_hash = self.hash_session_token(token)?;
+        self.repository
+            .get_session_by_token(&token_hash)
+            .await
+            .map_err(SessionServiceError::Repository)
+    }
+
+    /// Hex-encoded HMAC-SHA256 of a session token keyed with the session salt
+    fn hmac_session_token(&self, token: &str) -> String {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(self.config.session_salt.expose_secret().as_bytes())
+                .expect("HMAC accepts keys of any length");
+        mac.update(token.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
     fn hash_session_token(&self, token: &str) -> Result<String, SessionServiceError> {
         // Use the configured salt from AuthConfig
-        let salt = &self.config.session_salt;
+        let salt = self.config.session_salt.expose_secret();
 
         // Ensure the salt is at least 22 characters for the hash format
         if salt.len() < 22 {
@@ -442,26 +1842,101 @@ impl SessionService {
     }
 }
 
+/// Whether `role` is the tenant admin role
+fn is_admin_role(role: &str) -> bool {
+    role.eq_ignore_ascii_case("ADMIN")
+}
+
+/// Record whether a session is persistent and when the user authenticated in its metadata
+fn login_metadata(
+    metadata: Option<Value>,
+    persistent: bool,
+    authenticated_at: OffsetDateTime,
+) -> Option<Value> {
+    let mut map = match metadata {
+        Some(Value::Object(map)) => map,
+        None | Some(Value::Null) => serde_json::Map::new(),
+        // Non-object metadata is left untouched rather than discarded
+        Some(other) => return Some(other),
+    };
+    map.insert(
+        PERSISTENT_SESSION_METADATA_KEY.to_string(),
+        Value::Bool(persistent),
+    );
+    map.insert(
+        LAST_AUTH_METADATA_KEY.to_string(),
+        Value::from(authenticated_at.unix_timestamp()),
+    );
+    Some(Value::Object(map))
+}
+
+/// Client IP address of a request or session, given plainly or in network notation
+fn parse_client_ip(ip_address: &str) -> Option<IpAddr> {
+    let ip_address = ip_address.trim();
+    ip_address.parse::<IpAddr>().ok().or_else(|| {
+        ip_address
+            .parse::<IpNetwork>()
+            .ok()
+            .map(|network| network.ip())
+    })
+}
+
+/// Why a valid session may no longer be used, given the state of its user
+///
+/// A missing state means the user was deleted.
+fn stale_session_reason(
+    session: &Session,
+    user: Option<&UserSessionState>,
+) -> Option<SessionInvalidationReason> {
+    let Some(user) = user.filter(|user| user.is_active) else {
+        return Some(SessionInvalidationReason::UserDeactivated);
+    };
+    user.password_changed_at
+        .filter(|changed_at| *changed_at > session.created_at)
+        .map(|_| SessionInvalidationReason::PasswordChanged)
+}
+
+/// Publish the counts of a reconciliation pass as gauges
+#[cfg(feature = "metrics")]
+fn record_reconciliation(report: &SessionReconciliationReport) {
+    metrics::gauge!("auth_session_reconciliation_checked").set(report.sessions_checked as f64);
+    metrics::gauge!(
+        "auth_session_reconciliation_invalidated",
+        "reason" => "user_deactivated"
+    )
+    .set(report.invalidated_user_deactivated as f64);
+    metrics::gauge!(
+        "auth_session_reconciliation_invalidated",
+        "reason" => "password_changed"
+    )
+    .set(report.invalidated_password_changed as f64);
+    metrics::gauge!("auth_session_reconciliation_last_finished_seconds")
+        .set(report.finished_at.unix_timestamp() as f64);
+}
+
+#[cfg(not(feature = "metrics"))]
+fn record_reconciliation(_report: &SessionReconciliationReport) {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use async_trait::async_trait;
+    use crate::config::SessionLifetimeConfig;
+    use crate::session::enhanced_security::SessionLocation;
+    use crate::session::mock::MockSessionRepository;
+    use crate::utils::clock::TestClock;
+    use crate::utils::{duration::DurationSecs, secret::Secret};
 
     #[test]
     fn test_session_token_generation() {
         // Create a test config
         let config = Arc::new(AuthConfig {
-            session_lifetime_secs: 3600,
-            session_salt: "AcciSessionSalt123456789012345678901234567890".to_string(),
+            session_lifetime_secs: DurationSecs::from_secs(3600),
+            session_salt: Secret::from("AcciSessionSalt123456789012345678901234567890"),
             ..Default::default()
         });
 
-        // Create a test service with a dummy repository
-        // We don't need a real repository for this test since we're only testing token generation
-        let service = SessionService {
-            repository: Arc::new(DummyRepository),
-            config,
-        };
+        // Create a test service; the repository is never touched by token generation
+        let service = SessionService::new(Arc::new(MockSessionRepository::new()), config);
 
         // Test token generation
         let result = service.generate_session_token();
@@ -474,17 +1949,13 @@ mod tests {
     fn test_session_token_hashing() {
         // Create a test config
         let config = Arc::new(AuthConfig {
-            session_lifetime_secs: 3600,
-            session_salt: "TestSessionSalt123456789012345678901234567890".to_string(),
+            session_lifetime_secs: DurationSecs::from_secs(3600),
+            session_salt: Secret::from("TestSessionSalt123456789012345678901234567890"),
             ..Default::default()
         });
 
-        // Create a test service with a dummy repository
-        // We don't need a real repository for this test since we're only testing token hashing
-        let service = SessionService {
-            repository: Arc::new(DummyRepository),
-            config,
-        };
+        // Create a test service; the repository is never touched by token hashing
+        let service = SessionService::new(Arc::new(MockSessionRepository::new()), config);
 
         // Test token hashing
         let token = "test_token";
@@ -500,16 +1971,13 @@ mod tests {
     fn test_session_token_hashing_with_short_salt() {
         // Create a test config with too short salt
         let config = Arc::new(AuthConfig {
-            session_lifetime_secs: 3600,
-            session_salt: "ShortSalt".to_string(),
+            session_lifetime_secs: DurationSecs::from_secs(3600),
+            session_salt: Secret::from("ShortSalt"),
             ..Default::default()
         });
 
-        // Create a test service with a dummy repository
-        let service = SessionService {
-            repository: Arc::new(DummyRepository),
-            config,
-        };
+        // Create a test service with an in-memory repository
+        let service = SessionService::new(Arc::new(MockSessionRepository::new()), config);
 
         // Test token hashing with short salt should fail
         let token = "test_token";
@@ -521,102 +1989,299 @@ mod tests {
         ));
     }
 
-    // Dummy repository that implements the SessionRepository trait
-    // This is only used to satisfy the type system, none of the methods will be called
-    struct DummyRepository;
+    #[tokio::test]
+    async fn test_session_lifetime_depends_on_persistence() {
+        let config = Arc::new(AuthConfig {
+            session_lifetime_secs: DurationSecs::from_secs(3600),
+            persistent_session_lifetime_secs: DurationSecs::from_secs(30 * 86400),
+            ..Default::default()
+        });
+        let clock = TestClock::default();
+        let service = SessionService::with_clock(
+            Arc::new(MockSessionRepository::new()),
+            config,
+            clock.clone(),
+        );
+        let user_id = Uuid::new_v4();
+        let metadata = serde_json::json!({ "login_type": "password" });
 
-    #[async_trait]
-    impl SessionRepository for DummyRepository {
-        async fn create_session(
-            &self,
-            _user_id: Uuid,
-            _token_hash: String,
-            _expires_at: SystemTime,
-            _device_id: Option<String>,
-            _device_fingerprint: Option<DeviceFingerprint>,
-            _ip_address: Option<String>,
-            _user_agent: Option<String>,
-            _metadata: Option<Value>,
-        ) -> Result<Session, SessionError> {
-            unimplemented!("Not needed for these tests")
-        }
+        let (transient, _) = service
+            .create_session(
+                user_id,
+                None,
+                None,
+                None,
+                None,
+                Some(BoundedJson::new(metadata.clone()).unwrap()),
+                false,
+            )
+            .await
+            .unwrap();
+        let (persistent, _) = service
+            .create_session(
+                user_id,
+                None,
+                None,
+                None,
+                None,
+                Some(BoundedJson::new(metadata).unwrap()),
+                true,
+            )
+            .await
+            .unwrap();
 
-        async fn get_session(&self, _id: Uuid) -> Result<Option<Session>, SessionError> {
-            unimplemented!("Not needed for these tests")
-        }
+        assert_eq!(
+            transient.expires_at,
+            clock.now() + Duration::from_secs(3600)
+        );
+        assert_eq!(
+            persistent.expires_at,
+            clock.now() + Duration::from_secs(30 * 86400)
+        );
 
-        async fn get_session_by_token(
-            &self,
-            _token_hash: &str,
-        ) -> Result<Option<Session>, SessionError> {
-            unimplemented!("Not needed for these tests")
-        }
+        // The choice is recorded without dropping the caller's metadata
+        assert!(!transient.is_persistent());
+        assert!(persistent.is_persistent());
+        let metadata = persistent.metadata.as_ref().unwrap();
+        assert_eq!(metadata["login_type"], "password");
+        assert_eq!(metadata[PERSISTENT_SESSION_METADATA_KEY], true);
+    }
 
-        async fn get_user_sessions(
-            &self,
-            _user_id: Uuid,
-            _filter: SessionFilter,
-        ) -> Result<Vec<Session>, SessionError> {
-            unimplemented!("Not needed for these tests")
-        }
+    #[tokio::test]
+    async fn test_remember_me_lifetime_is_clamped_to_max() {
+        let config = Arc::new(AuthConfig {
+            session_lifetime_secs: DurationSecs::from_secs(3600),
+            persistent_session_lifetime_secs: DurationSecs::from_secs(30 * 86400),
+            session_lifetimes: SessionLifetimeConfig {
+                max_secs: DurationSecs::from_secs(14 * 86400),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let clock = TestClock::default();
+        let service = SessionService::with_clock(
+            Arc::new(MockSessionRepository::new()),
+            config,
+            clock.clone(),
+        );
+        let user_id = Uuid::new_v4();
 
-        async fn update_session_activity(&self, _id: Uuid) -> Result<(), SessionError> {
-            unimplemented!("Not needed for these tests")
-        }
+        let (remember_me, _) = service
+            .create_session_with_lifetime(
+                user_id,
+                None,
+                None,
+                None,
+                None,
+                None,
+                SessionLifetime::Persistent,
+            )
+            .await
+            .expect("Over-long lifetime is clamped, not rejected");
+        assert_eq!(
+            remember_me.expires_at,
+            clock.now() + Duration::from_secs(14 * 86400)
+        );
+        assert!(remember_me.is_persistent());
 
-        async fn invalidate_session(
-            &self,
-            _id: Uuid,
-            _reason: SessionInvalidationReason,
-        ) -> Result<(), SessionError> {
-            Ok(())
-        }
+        // Lifetimes below the maximum are kept
+        let (standard, _) = service
+            .create_session_with_lifetime(
+                user_id,
+                None,
+                None,
+                None,
+                None,
+                None,
+                SessionLifetime::Standard,
+            )
+            .await
+            .unwrap();
+        assert_eq!(standard.expires_at, clock.now() + Duration::from_secs(3600));
+        assert!(!standard.is_persistent());
 
-        /// Dummy implementation for invalidate_all_user_sessions
-        async fn invalidate_all_user_sessions(
-            &self,
-            _user_id: Uuid,
-            _reason: SessionInvalidationReason,
-        ) -> Result<u64, SessionError> {
-            Ok(0)
-        }
+        // Plain logins keep their configured lifetimes
+        let (persistent, _) = service
+            .create_session(user_id, None, None, None, None, None, true)
+            .await
+            .unwrap();
+        assert_eq!(
+            persistent.expires_at,
+            clock.now() + Duration::from_secs(30 * 86400)
+        );
+    }
 
-        /// Dummy implementation for invalidate_sessions_by_filter
-        async fn invalidate_sessions_by_filter(
-            &self,
-            _filter: SessionFilter,
-            _reason: SessionInvalidationReason,
-        ) -> Result<u64, SessionError> {
-            Ok(0)
-        }
+    #[tokio::test]
+    async fn test_session_with_status_respects_persistence() {
+        let config = Arc::new(AuthConfig {
+            session_lifetime_secs: DurationSecs::from_secs(3600),
+            persistent_session_lifetime_secs: DurationSecs::from_secs(7 * 86400),
+            ..Default::default()
+        });
+        let clock = TestClock::default();
+        let service = SessionService::with_clock(
+            Arc::new(MockSessionRepository::new()),
+            config,
+            clock.clone(),
+        );
 
-        /// Dummy implementation for invalidate_sessions_by_ip
-        async fn invalidate_sessions_by_ip(
-            &self,
-            _ip_address: &str,
-            _reason: SessionInvalidationReason,
-        ) -> Result<u64, SessionError> {
-            Ok(0)
-        }
+        let (session, _) = service
+            .create_session_with_status(
+                Uuid::new_v4(),
+                None,
+                None,
+                None,
+                None,
+                None,
+                true,
+                MfaStatus::Pending,
+            )
+            .await
+            .unwrap();
 
-        async fn rotate_session_token(
-            &self,
-            _id: Uuid,
-            _new_token_hash: String,
-        ) -> Result<(), SessionError> {
-            unimplemented!("Not needed for these tests")
-        }
+        assert_eq!(
+            session.expires_at,
+            clock.now() + Duration::from_secs(7 * 86400)
+        );
+        assert!(session.is_persistent());
+        assert_eq!(session.mfa_status, MfaStatus::Pending);
+    }
 
-        async fn cleanup_expired_sessions(&self) -> Result<u64, SessionError> {
-            unimplemented!("Not needed for these tests")
-        }
+    #[tokio::test]
+    async fn test_reauth_required_once_authentication_is_stale() {
+        let clock = TestClock::default();
+        let service = SessionService::with_clock(
+            Arc::new(MockSessionRepository::new()),
+            Arc::new(AuthConfig::default()),
+            clock.clone(),
+        );
+        let (session, token) = service
+            .create_session(Uuid::new_v4(), None, None, None, None, None, false)
+            .await
+            .expect("Failed to create session");
+        let window = Duration::from_secs(300);
+
+        assert_eq!(session.last_auth_at(), clock.now());
+        assert!(!service.reauth_required(&session, window));
 
-        async fn update_mfa_status(
-            &self,
-            _id: Uuid,
-            _status: MfaStatus,
-        ) -> Result<(), SessionError> {
-            unimplemented!("Not needed for these tests")
+        clock.advance(time::Duration::minutes(10));
+        let session = service
+            .validate_session(&token)
+            .await
+            .expect("Failed to validate session")
+            .expect("Session is still valid");
+        assert!(service.reauth_required(&session, window));
+        assert!(
+            service.reauth_required_for(session.last_auth_at(), SensitiveOperation::ChangePassword)
+        );
+
+        service
+            .record_reauthentication(session.id)
+            .await
+            .expect("Failed to record re-authentication");
+        let session = service
+            .validate_session(&token)
+            .await
+            .expect("Failed to validate session")
+            .expect("Session is still valid");
+        assert!(!service.reauth_required(&session, window));
+    }
+
+    #[tokio::test]
+    async fn test_mfa_completion_refreshes_last_auth() {
+        let clock = TestClock::default();
+        let service = SessionService::with_clock(
+            Arc::new(MockSessionRepository::new()),
+            Arc::new(AuthConfig::default()),
+            clock.clone(),
+        );
+        let window = Duration::from_secs(300);
+
+        for status in [MfaStatus::Required, MfaStatus::Pending] {
+            let (_, token) = service
+                .create_session_with_status(
+                    Uuid::new_v4(),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                    status.clone(),
+                )
+                .await
+                .expect("Failed to create session");
+            clock.advance(time::Duration::minutes(10));
+
+            if status == MfaStatus::Pending {
+                service
+                    .complete_mfa_enrollment(&token)
+                    .await
+                    .expect("Failed to complete enrollment");
+            } else {
+                service
+                    .update_session_mfa_status(&token, MfaStatus::Verified)
+                    .await
+                    .expect("Failed to verify session");
+            }
+
+            let session = service
+                .validate_session(&token)
+                .await
+                .expect("Failed to validate session")
+                .expect("Session is still valid");
+            assert_eq!(session.last_auth_at(), clock.now());
+            assert!(!service.reauth_required(&session, window));
         }
     }
+
+    #[tokio::test]
+    async fn test_list_sessions_with_location() {
+        let repository = MockSessionRepository::new();
+        let service = SessionService::new(
+            Arc::new(repository.clone()),
+            Arc::new(AuthConfig::default()),
+        );
+        let user_id = Uuid::new_v4();
+        let (located, _) = service
+            .create_session(user_id, None, None, None, None, None, false)
+            .await
+            .expect("Failed to create session");
+        let (unlocated, _) = service
+            .create_session(user_id, None, None, None, None, None, false)
+            .await
+            .expect("Failed to create session");
+
+        let ip = "203.0.113.7".parse().expect("Valid IP address");
+        let mut earlier = SessionLocation::new(located.id, user_id, ip, "FR", 48.8566, 2.3522);
+        earlier.city = Some("Paris".to_string());
+        earlier.recorded_at -= Duration::from_secs(60);
+        let mut latest = SessionLocation::new(located.id, user_id, ip, "DE", 52.52, 13.405);
+        latest.city = Some("Berlin".to_string());
+        repository.record_location(earlier);
+        repository.record_location(latest);
+
+        let sessions = service
+            .list_sessions_with_location(user_id, SessionFilter::Active, Some(unlocated.id))
+            .await
+            .expect("Failed to list sessions");
+        assert_eq!(sessions.len(), 2);
+
+        let listed = |id: Uuid| {
+            sessions
+                .iter()
+                .find(|listed| listed.session.id == id)
+                .expect("Session is listed")
+        };
+        let location = listed(located.id)
+            .location
+            .as_ref()
+            .expect("Location is joined");
+        assert_eq!(location.city.as_deref(), Some("Berlin"));
+        assert_eq!(location.country_code, "DE");
+        assert!(!listed(located.id).is_current);
+
+        assert!(listed(unlocated.id).location.is_none());
+        assert!(listed(unlocated.id).is_current);
+    }
 }
@@ -1,12 +1,16 @@
 use async_trait::async_trait;
-use reqwest::Client;
 use std::sync::Arc;
 use tracing::{debug, error, info, instrument};
 use urlencoding::encode;
 
 use crate::models::VerificationType;
-use crate::services::message_provider::{Message, MessageProvider, SmsProviderConfig};
+use crate::services::delivery_receipt::DeliveryTracker;
+use crate::services::message_outbox::{MessageOutbox, with_delivery_mode};
+use crate::services::message_provider::{
+    Message, MessageProvider, SmsProviderConfig, provider_status_error, provider_unreachable,
+};
 use acci_core::error::{Error, Result};
+use acci_core::http_client::HttpClient;
 
 /// SMS Provider using Twilio for delivering messages
 pub struct TwilioSmsProvider {
@@ -14,14 +18,17 @@ pub struct TwilioSmsProvider {
     config: SmsProviderConfig,
     /// Base URL for Twilio API
     base_url: String,
+    /// Client for requests to the Twilio API
+    client: HttpClient,
 }
 
 impl TwilioSmsProvider {
     /// Create a new Twilio SMS provider
-    pub fn new(config: SmsProviderConfig) -> Self {
+    pub fn new(config: SmsProviderConfig, client: HttpClient) -> Self {
         Self {
             config,
             base_url: "https://api.twilio.com/2010-04-01".to_string(),
+            client,
         }
     }
 }
@@ -40,39 +47,40 @@ impl MessageProvider for TwilioSmsProvider {
         );
 
         // Get API key and secret from config
-        let api_key = &self.config.api_key;
+        let api_key = self.config.api_key.expose_secret();
         let api_secret = self
             .config
             .api_secret
-            .clone()
+            .as_ref()
+            .map(|secret| secret.expose_secret())
             .ok_or_else(|| Error::Config("Twilio API secret is required".to_string()))?;
 
         // Extract account SID from the API key (in Twilio, the API key is usually the account SID)
         let account_sid = api_key;
 
-        // Create request client
-        let client = Client::new();
-
         // Build the Twilio API request
         let url = format!("{}/Accounts/{}/Messages.json", self.base_url, account_sid);
+        let mut form = vec![
+            ("From", self.config.sender.as_str()),
+            ("To", message.recipient.as_str()),
+            ("Body", message.body.as_str()),
+        ];
+        if let Some(callback_url) = &self.config.status_callback_url {
+            form.push(("StatusCallback", callback_url.as_str()));
+        }
+        let request = self
+            .client
+            .post(&url)
+            .basic_auth(api_key, Some(api_secret))
+            .form(&form);
 
         debug!("Sending request to Twilio API: {}", url);
 
         // Send the request
-        let response = client
-            .post(&url)
-            .basic_auth(api_key, Some(&api_secret))
-            .form(&[
-                ("From", &self.config.sender),
-                ("To", &message.recipient),
-                ("Body", &message.body),
-            ])
-            .send()
-            .await
-            .map_err(|err| {
-                error!("Failed to send Twilio request: {}", err);
-                Error::Other(anyhow::anyhow!("Failed to send Twilio request: {}", err))
-            })?;
+        let response = self.client.send(request).await.map_err(|err| {
+            error!("Failed to send Twilio request: {}", err);
+            provider_unreachable("Twilio", err)
+        })?;
 
         // Check response status
         if !response.status().is_success() {
@@ -86,11 +94,7 @@ impl MessageProvider for TwilioSmsProvider {
                 error = %error_text,
                 "Twilio API error"
             );
-            return Err(Error::Other(anyhow::anyhow!(
-                "Twilio API error: {} - {}",
-                status,
-                error_text
-            )));
+            return Err(provider_status_error("Twilio", status, &error_text));
         }
 
         // Parse response
@@ -120,12 +124,14 @@ impl MessageProvider for TwilioSmsProvider {
 pub struct VonageSmsProvider {
     /// Configuration for the SMS provider
     config: SmsProviderConfig,
+    /// Client for requests to the Vonage API
+    client: HttpClient,
 }
 
 impl VonageSmsProvider {
     /// Create a new Vonage SMS provider
-    pub fn new(config: SmsProviderConfig) -> Self {
-        Self { config }
+    pub fn new(config: SmsProviderConfig, client: HttpClient) -> Self {
+        Self { config, client }
     }
 }
 
@@ -143,16 +149,14 @@ impl MessageProvider for VonageSmsProvider {
         );
 
         // Get API credentials
-        let api_key = &self.config.api_key;
+        let api_key = self.config.api_key.expose_secret();
         let api_secret = self
             .config
             .api_secret
-            .clone()
+            .as_ref()
+            .map(|secret| secret.expose_secret())
             .ok_or_else(|| Error::Config("Vonage API secret is required".to_string()))?;
 
-        // Create request client
-        let client = Client::new();
-
         // Vonage API endpoint
         let url = "https://rest.nexmo.com/sms/json";
 
@@ -160,21 +164,21 @@ impl MessageProvider for VonageSmsProvider {
         let encoded_body: String = encode(&message.body).into_owned();
 
         // Build and send the request
-        let response = client
-            .post(url)
-            .form(&[
-                ("api_key", api_key),
-                ("api_secret", &api_secret),
-                ("from", &self.config.sender),
-                ("to", &message.recipient),
-                ("text", &encoded_body),
-            ])
-            .send()
-            .await
-            .map_err(|err| {
-                error!("Failed to send Vonage request: {}", err);
-                Error::Other(anyhow::anyhow!("Failed to send Vonage request: {}", err))
-            })?;
+        let mut form = vec![
+            ("api_key", api_key.as_str()),
+            ("api_secret", api_secret.as_str()),
+            ("from", self.config.sender.as_str()),
+            ("to", message.recipient.as_str()),
+            ("text", encoded_body.as_str()),
+        ];
+        if let Some(callback_url) = &self.config.status_callback_url {
+            form.push(("callback", callback_url.as_str()));
+        }
+        let request = self.client.post(url).form(&form);
+        let response = self.client.send(request).await.map_err(|err| {
+            error!("Failed to send Vonage request: {}", err);
+            provider_unreachable("Vonage", err)
+        })?;
 
         // Check response status
         if !response.status().is_success() {
@@ -188,11 +192,7 @@ impl MessageProvider for VonageSmsProvider {
                 error = %error_text,
                 "Vonage API error"
             );
-            return Err(Error::Other(anyhow::anyhow!(
-                "Vonage API error: {} - {}",
-                status,
-                error_text
-            )));
+            return Err(provider_status_error("Vonage", status, &error_text));
         }
 
         // Parse response
@@ -231,19 +231,30 @@ impl MessageProvider for VonageSmsProvider {
 }
 
 /// Factory function to create an SMS provider based on configuration
-pub fn create_sms_provider(config: SmsProviderConfig) -> Result<Arc<dyn MessageProvider>> {
-    match config.provider.to_lowercase().as_str() {
-        "twilio" => {
-            let provider = TwilioSmsProvider::new(config);
-            Ok(Arc::new(provider))
-        },
-        "vonage" | "nexmo" => {
-            let provider = VonageSmsProvider::new(config);
-            Ok(Arc::new(provider))
+///
+/// The provider sends its requests with `client`. Providers configured for
+/// [`DeliveryMode::Queued`](crate::services::DeliveryMode::Queued) are wrapped
+/// in `outbox`, which must then be given. With a `tracker` every sent message
+/// is recorded for delivery tracking. Secrets configured as references
+/// must have been resolved, see [`AuthConfig::resolve_secrets`](crate::AuthConfig::resolve_secrets).
+pub fn create_sms_provider(
+    config: SmsProviderConfig,
+    client: &HttpClient,
+    outbox: Option<&MessageOutbox>,
+    tracker: Option<&DeliveryTracker>,
+) -> Result<Arc<dyn MessageProvider>> {
+    config.ensure_secrets_resolved()?;
+    let delivery = config.delivery;
+    let provider: Arc<dyn MessageProvider> = match config.provider.to_lowercase().as_str() {
+        "twilio" => Arc::new(TwilioSmsProvider::new(config, client.clone())),
+        "vonage" | "nexmo" => Arc::new(VonageSmsProvider::new(config, client.clone())),
+        _ => {
+            return Err(Error::Config(format!(
+                "Unsupported SMS provider: {}",
+                config.provider
+            )));
         },
-        _ => Err(Error::Config(format!(
-            "Unsupported SMS provider: {}",
-            config.provider
-        ))),
-    }
+    };
+
+    with_delivery_mode(provider, delivery, outbox, tracker)
 }
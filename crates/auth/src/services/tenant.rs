@@ -1,16 +1,22 @@
 use crate::models::tenant::{
-    CreateSubscriptionDto, CreateTenantDto, CreateTenantUserDto, Tenant, TenantError,
-    TenantPlanType, TenantRepository, TenantSubscription, TenantUser, UpdateSubscriptionDto,
-    UpdateTenantDto, UpdateTenantUserDto,
+    CreateSubscriptionDto, CreateTenantDto, CreateTenantUserDto, SECURITY_POLICY_METADATA_KEY,
+    SubscriptionChangeType, SubscriptionHistoryEntry, Tenant, TenantError, TenantPlanType,
+    TenantRepository, TenantSecurityPolicy, TenantStatus, TenantSubscription, TenantUser,
+    UnknownCountryAction, UpdateSubscriptionDto, UpdateTenantDto, UpdateTenantUserDto,
+    WebAuthnAttestationPolicy,
 };
 use crate::models::user::{User, UserError, UserRepository};
 use crate::repository::RepositoryError;
 use crate::services::user::{UserService, UserServiceError};
+use crate::session::types::SessionInvalidationReason;
+use crate::utils::bounded_json::BoundedJson;
 use crate::utils::password::PasswordError;
+use sqlx::types::ipnetwork::IpNetwork;
+use std::collections::HashSet;
 use std::sync::Arc;
 use thiserror::Error;
 use time::OffsetDateTime;
-use tracing::{debug, error, info, instrument};
+use tracing::{debug, error, info, instrument, warn};
 use uuid::Uuid;
 
 /// Error types for tenant service operations
@@ -46,8 +52,41 @@ pub enum TenantServiceError {
     #[error("Feature not available: {0}")]
     FeatureNotAvailable(String),
 
-    #[error("Tenant limit exceeded: {0}")]
-    TenantLimitExceeded(String),
+    #[error("Tenant limit exceeded: {message}")]
+    TenantLimitExceeded {
+        message: String,
+        /// Limit of the tenant's plan
+        limit: i32,
+        /// Usage the limit was checked against
+        current: i32,
+    },
+}
+
+impl From<TenantServiceError> for acci_core::Error {
+    fn from(err: TenantServiceError) -> Self {
+        use acci_core::error::ErrorKind;
+
+        let kind = match err {
+            TenantServiceError::Tenant(e) => return e.into(),
+            TenantServiceError::User(e) => return e.into(),
+            TenantServiceError::UserService(e) => return e.into(),
+            TenantServiceError::Password(PasswordError::TooWeak(..)) => ErrorKind::WeakPassword,
+            TenantServiceError::Password(_)
+            | TenantServiceError::Database(_)
+            | TenantServiceError::Internal(_) => ErrorKind::Internal,
+            TenantServiceError::InvalidInput(_) => ErrorKind::InvalidInput,
+            TenantServiceError::Unauthorized(_) => ErrorKind::Forbidden,
+            TenantServiceError::NotFound(_) => ErrorKind::TenantNotFound,
+            TenantServiceError::FeatureNotAvailable(_) => ErrorKind::Unavailable,
+            TenantServiceError::TenantLimitExceeded { limit, current, .. } => {
+                #[allow(clippy::disallowed_methods)]
+                let details = serde_json::json!({ "limit": limit, "current": current });
+                return Self::domain(ErrorKind::SeatLimitReached, err.to_string())
+                    .with_details(details);
+            },
+        };
+        Self::domain(kind, err.to_string())
+    }
 }
 
 impl From<RepositoryError> for TenantServiceError {
@@ -62,6 +101,14 @@ impl From<RepositoryError> for TenantServiceError {
     }
 }
 
+/// Number of entries per page of the subscription history
+pub const SUBSCRIPTION_HISTORY_PAGE_SIZE: u32 = 50;
+
+/// Largest number of previous passwords a tenant can keep users from reusing
+///
+/// Each password change verifies the new password against this many hashes.
+pub const MAX_PASSWORD_HISTORY_SIZE: u32 = 24;
+
 /// Data transfer object for tenant creation with admin user
 #[derive(Debug)]
 pub struct CreateTenantWithAdminDto {
@@ -86,10 +133,63 @@ pub struct TenantWithAdminResponse {
     pub subscription: Option<TenantSubscription>,
 }
 
+/// Why a row of a bulk user import was not added
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum BulkAddFailure {
+    /// The user is already a member, possibly through an earlier row of the batch
+    #[error("User is already a member of the tenant")]
+    AlreadyExists,
+
+    #[error("User not found")]
+    UserNotFound,
+
+    /// The row would take the tenant over the user limit of its subscription
+    #[error("User limit of {0} reached")]
+    LimitExceeded(i32),
+
+    /// The user is already active in the maximum number of tenants
+    #[error("User is already active in {0} tenants")]
+    TenantsPerUserLimitExceeded(u32),
+
+    #[error("Failed to add user: {0}")]
+    Failed(String),
+}
+
+/// Outcome of a single row of a bulk user import
+#[derive(Debug)]
+pub struct BulkRowResult {
+    /// Position of the row in the batch
+    pub row: usize,
+    pub user_id: Uuid,
+    pub outcome: Result<TenantUser, BulkAddFailure>,
+}
+
+/// Outcome of a bulk user import, one entry per row in batch order
+#[derive(Debug, Default)]
+pub struct BulkResult {
+    pub rows: Vec<BulkRowResult>,
+}
+
+impl BulkResult {
+    /// Memberships created by the import
+    pub fn added(&self) -> impl Iterator<Item = &TenantUser> {
+        self.rows.iter().filter_map(|row| row.outcome.as_ref().ok())
+    }
+
+    /// Rows that were not added, with the reason
+    pub fn failures(&self) -> impl Iterator<Item = (&BulkRowResult, &BulkAddFailure)> {
+        self.rows
+            .iter()
+            .filter_map(|row| row.outcome.as_ref().err().map(|failure| (row, failure)))
+    }
+}
+
 /// Service for managing tenants
 pub struct TenantService {
     tenant_repository: Arc<dyn TenantRepository>,
     user_service: Arc<UserService>,
+    /// Tenants a user can be an active member of, unlimited if `None`
+    max_tenants_per_user: Option<u32>,
 }
 
 impl TenantService {
@@ -102,9 +202,18 @@ impl TenantService {
         Self {
             tenant_repository,
             user_service,
+            max_tenants_per_user: None,
         }
     }
 
+    /// Limits the number of tenants a user can be an active member of
+    ///
+    /// Usually set from [`AuthConfig::max_tenants_per_user`](crate::config::AuthConfig::max_tenants_per_user).
+    pub fn with_max_tenants_per_user(mut self, max_tenants_per_user: Option<u32>) -> Self {
+        self.max_tenants_per_user = max_tenants_per_user;
+        self
+    }
+
     /// Creates a new tenant
     #[instrument(skip(self, tenant), fields(tenant_name = %tenant.name))]
     pub async fn create_tenant(
@@ -190,6 +299,13 @@ impl TenantService {
                     },
                 )
                 .await?;
+            self.record_subscription_change(
+                SubscriptionChangeType::Created,
+                None,
+                &subscription,
+                None,
+            )
+            .await?;
 
             Some(subscription)
         } else {
@@ -219,6 +335,12 @@ impl TenantService {
         Ok(tenant)
     }
 
+    /// Gets several tenants in one lookup; unknown IDs are skipped
+    #[instrument(skip(self, ids), fields(count = ids.len()))]
+    pub async fn get_tenants(&self, ids: &[Uuid]) -> Result<Vec<Tenant>, TenantServiceError> {
+        Ok(self.tenant_repository.find_tenants_by_ids(ids).await?)
+    }
+
     /// Gets a tenant by subdomain
     #[instrument(skip(self))]
     pub async fn get_tenant_by_subdomain(
@@ -302,6 +424,11 @@ impl TenantService {
     }
 
     /// Adds a user to a tenant
+    ///
+    /// Adding an active user fails with [`TenantServiceError::TenantLimitExceeded`]
+    /// if the active users already take every seat of the tenant's subscription,
+    /// and with [`TenantError::TenantsPerUserLimitExceeded`] if the user is
+    /// already active in the maximum number of tenants.
     #[instrument(skip(self, user))]
     pub async fn add_user_to_tenant(
         &self,
@@ -311,13 +438,12 @@ impl TenantService {
         debug!("Adding user {} to tenant {}", user.user_id, tenant_id);
 
         // First, check tenant limits
-        self.check_tenant_user_limits(tenant_id).await?;
+        if user.is_active.unwrap_or(true) {
+            self.check_tenant_user_limits(tenant_id).await?;
+        }
 
         // Add user to tenant
-        let tenant_user = self
-            .tenant_repository
-            .add_user_to_tenant(*tenant_id, user)
-            .await?;
+        let tenant_user = self.insert_tenant_user(tenant_id, user).await?;
 
         info!(
             "User added to tenant: {} -> {}",
@@ -326,7 +452,88 @@ impl TenantService {
         Ok(tenant_user)
     }
 
+    /// Adds many users to a tenant, reporting the outcome of every row
+    ///
+    /// Rows are added one by one, so a failing row neither aborts the import
+    /// nor undoes the rows added before it. The user limit of the active
+    /// subscription counts the users added earlier in the same batch.
+    #[instrument(skip(self, users), fields(rows = users.len()))]
+    pub async fn bulk_add_users(
+        &self,
+        tenant_id: &Uuid,
+        users: Vec<CreateTenantUserDto>,
+    ) -> Result<BulkResult, TenantServiceError> {
+        debug!("Importing {} users into tenant {}", users.len(), tenant_id);
+
+        let max_users = self
+            .get_active_subscription(tenant_id)
+            .await?
+            .and_then(|subscription| subscription.max_users);
+        let members = self.get_tenant_users(tenant_id).await?;
+        let mut active_users = members.iter().filter(|m| m.is_active).count() as i32;
+        let mut member_ids: HashSet<Uuid> = members.iter().map(|m| m.user_id).collect();
+
+        let mut result = BulkResult::default();
+        for (row, user) in users.into_iter().enumerate() {
+            let user_id = user.user_id;
+            let is_active = user.is_active.unwrap_or(true);
+
+            let outcome = if member_ids.contains(&user_id) {
+                Err(BulkAddFailure::AlreadyExists)
+            } else if let Err(failure) = self.ensure_user_exists(user_id).await {
+                Err(failure)
+            } else if let Some(max_users) =
+                max_users.filter(|max| is_active && active_users >= *max)
+            {
+                Err(BulkAddFailure::LimitExceeded(max_users))
+            } else {
+                self.insert_tenant_user(tenant_id, user)
+                    .await
+                    .map_err(|err| match err {
+                        TenantError::AlreadyExists => BulkAddFailure::AlreadyExists,
+                        TenantError::TenantsPerUserLimitExceeded { limit, .. } => {
+                            BulkAddFailure::TenantsPerUserLimitExceeded(limit)
+                        },
+                        err => BulkAddFailure::Failed(err.to_string()),
+                    })
+            };
+
+            if outcome.is_ok() {
+                member_ids.insert(user_id);
+                if is_active {
+                    active_users += 1;
+                }
+            }
+            result.rows.push(BulkRowResult {
+                row,
+                user_id,
+                outcome,
+            });
+        }
+
+        info!(
+            "Imported users into tenant {}: {} added, {} failed",
+            tenant_id,
+            result.added().count(),
+            result.failures().count()
+        );
+        Ok(result)
+    }
+
+    // Checks that a user of a bulk import exists
+    async fn ensure_user_exists(&self, user_id: Uuid) -> Result<(), BulkAddFailure> {
+        match self.user_service.get_user(user_id).await {
+            Ok(_) => Ok(()),
+            Err(UserServiceError::User(UserError::NotFound)) => Err(BulkAddFailure::UserNotFound),
+            Err(err) => Err(BulkAddFailure::Failed(err.to_string())),
+        }
+    }
+
     /// Updates a user's tenant association
+    ///
+    /// Reactivating a deactivated association takes a seat like adding a
+    /// user does and fails with [`TenantServiceError::TenantLimitExceeded`]
+    /// if none is free.
     #[instrument(skip(self, update))]
     pub async fn update_tenant_user(
         &self,
@@ -336,6 +543,18 @@ impl TenantService {
     ) -> Result<TenantUser, TenantServiceError> {
         debug!("Updating user {} in tenant {}", user_id, tenant_id);
 
+        if update.is_active == Some(true) {
+            let is_inactive = self
+                .tenant_repository
+                .get_user_tenants(*user_id)
+                .await?
+                .iter()
+                .any(|m| m.tenant_id == *tenant_id && !m.is_active);
+            if is_inactive {
+                self.check_tenant_user_limits(tenant_id).await?;
+            }
+        }
+
         let tenant_user = self
             .tenant_repository
             .update_tenant_user(*tenant_id, *user_id, update)
@@ -390,24 +609,50 @@ impl TenantService {
         Ok(subscription)
     }
 
-    /// Creates a subscription for a tenant
+    /// Creates a subscription for a tenant, replacing the active one
+    ///
+    /// Fails with [`TenantServiceError::TenantLimitExceeded`] if the tenant has more
+    /// active users than the new subscription allows.
     #[instrument(skip(self, subscription))]
     pub async fn create_subscription(
         &self,
         tenant_id: &Uuid,
         subscription: CreateSubscriptionDto,
+        actor_id: Option<Uuid>,
     ) -> Result<TenantSubscription, TenantServiceError> {
         debug!("Creating subscription for tenant: {}", tenant_id);
 
+        // Check the new limit before touching the current subscription
+        if let Some(max_users) = subscription.max_users {
+            self.ensure_user_capacity(tenant_id, max_users).await?;
+        }
+
         // Deactivate any current subscriptions
-        self.deactivate_existing_subscriptions(tenant_id).await?;
+        let replaced = self
+            .deactivate_existing_subscriptions(tenant_id, actor_id)
+            .await?;
 
-        // Create new subscription
+        // Create new subscription; its history entry names the plan it replaced
         let subscription = self
             .tenant_repository
             .create_subscription(*tenant_id, subscription)
             .await?;
-
+        self.record_subscription_change(
+            SubscriptionChangeType::Created,
+            replaced.as_ref(),
+            &subscription,
+            actor_id,
+        )
+        .await?;
+
+        if let Some(replaced) =
+            replaced.filter(|replaced| replaced.plan_type != subscription.plan_type)
+        {
+            info!(
+                "Tenant {} changed plan from {} to {}",
+                tenant_id, replaced.plan_type, subscription.plan_type
+            );
+        }
         info!(
             "Subscription created for tenant {}: {}",
             tenant_id, subscription.id
@@ -416,23 +661,440 @@ impl TenantService {
     }
 
     /// Updates a subscription
+    ///
+    /// Changes take effect immediately. Lowering the user limit of an active
+    /// subscription fails with [`TenantServiceError::TenantLimitExceeded`] if the
+    /// tenant has more active users than the new limit.
     #[instrument(skip(self, update))]
     pub async fn update_subscription(
         &self,
         id: &Uuid,
         update: UpdateSubscriptionDto,
+        actor_id: Option<Uuid>,
     ) -> Result<TenantSubscription, TenantServiceError> {
         debug!("Updating subscription: {}", id);
 
+        let current = self
+            .tenant_repository
+            .find_subscription_by_id(*id)
+            .await?
+            .ok_or_else(|| TenantServiceError::NotFound(format!("Subscription {}", id)))?;
+
+        let stays_active = current.is_active && update.is_active != Some(false);
+        if let Some(max_users) = update.max_users.filter(|_| stays_active) {
+            self.ensure_user_capacity(&current.tenant_id, max_users)
+                .await?;
+        }
+
         let subscription = self
             .tenant_repository
             .update_subscription(*id, update)
             .await?;
 
+        let change_type = if current.is_active && !subscription.is_active {
+            SubscriptionChangeType::Deactivated
+        } else {
+            SubscriptionChangeType::Updated
+        };
+        self.record_subscription_change(change_type, Some(&current), &subscription, actor_id)
+            .await?;
+
+        if current.plan_type != subscription.plan_type {
+            info!(
+                "Subscription {} changed plan from {} to {}",
+                id, current.plan_type, subscription.plan_type
+            );
+        }
         info!("Subscription updated: {}", id);
         Ok(subscription)
     }
 
+    /// Lists all subscriptions of a tenant, oldest first
+    ///
+    /// Replaced subscriptions stay in the list with `is_active` unset, so the
+    /// list shows every plan the tenant had.
+    #[instrument(skip(self))]
+    pub async fn list_subscriptions(
+        &self,
+        tenant_id: &Uuid,
+    ) -> Result<Vec<TenantSubscription>, TenantServiceError> {
+        Ok(self
+            .tenant_repository
+            .list_subscriptions(*tenant_id)
+            .await?)
+    }
+
+    /// Gets a page of the subscription history of a tenant, newest first
+    ///
+    /// Pages hold [`SUBSCRIPTION_HISTORY_PAGE_SIZE`] entries and start at 1.
+    #[instrument(skip(self))]
+    pub async fn get_subscription_history(
+        &self,
+        tenant_id: &Uuid,
+        page: u32,
+    ) -> Result<Vec<SubscriptionHistoryEntry>, TenantServiceError> {
+        if page == 0 {
+            return Err(TenantServiceError::InvalidInput(
+                "Page numbers start at 1".into(),
+            ));
+        }
+
+        let offset = u64::from(page - 1) * u64::from(SUBSCRIPTION_HISTORY_PAGE_SIZE);
+        Ok(self
+            .tenant_repository
+            .list_subscription_history(*tenant_id, SUBSCRIPTION_HISTORY_PAGE_SIZE, offset)
+            .await?)
+    }
+
+    /// Replaces the networks and countries the users of a tenant may log in from
+    ///
+    /// Ranges are CIDR blocks without host bits, e.g. `10.0.0.0/8` or
+    /// `2001:db8::/32`; a bare address allows just that address. Countries are
+    /// ISO 3166-1 alpha-2 codes and stored in upper case. Empty lists lift the
+    /// restriction. Sessions created before the change are kept, see
+    /// [`SessionService::sweep_login_location_violations`](crate::services::session::SessionService::sweep_login_location_violations).
+    #[instrument(skip(self))]
+    pub async fn update_login_restrictions(
+        &self,
+        tenant_id: &Uuid,
+        allowed_ip_ranges: &[String],
+        allowed_countries: &[String],
+        unknown_country: UnknownCountryAction,
+    ) -> Result<TenantSecurityPolicy, TenantServiceError> {
+        let allowed_ip_ranges = allowed_ip_ranges
+            .iter()
+            .map(|range| parse_ip_range(range))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut countries = Vec::with_capacity(allowed_countries.len());
+        for country in allowed_countries {
+            let country = country.trim();
+            if country.len() != 2 || !country.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Err(TenantServiceError::InvalidInput(format!(
+                    "Invalid country code '{}': expected an ISO 3166-1 alpha-2 code",
+                    country
+                )));
+            }
+            let country = country.to_ascii_uppercase();
+            if !countries.contains(&country) {
+                countries.push(country);
+            }
+        }
+
+        let tenant = self.get_tenant(tenant_id).await?;
+        let mut policy = tenant.security_policy();
+        policy.allowed_ip_ranges = allowed_ip_ranges;
+        policy.allowed_countries = countries;
+        policy.unknown_country = unknown_country;
+
+        let policy_value = serde_json::to_value(&policy)
+            .map_err(|e| TenantServiceError::Internal(e.to_string()))?;
+        self.update_metadata(tenant, |metadata| {
+            metadata.insert(SECURITY_POLICY_METADATA_KEY.to_string(), policy_value);
+        })
+        .await?;
+
+        info!(
+            "Login restrictions updated for tenant {}: {} IP ranges, {} countries",
+            tenant_id,
+            policy.allowed_ip_ranges.len(),
+            policy.allowed_countries.len()
+        );
+        Ok(policy)
+    }
+
+    /// Sets after how many days the passwords of the tenant's users expire
+    ///
+    /// Password logins warn `warning_days` ahead of the expiry and are refused
+    /// once it has passed. Without `max_age_days` passwords never expire.
+    #[instrument(skip(self))]
+    pub async fn update_password_expiry(
+        &self,
+        tenant_id: &Uuid,
+        max_age_days: Option<u32>,
+        warning_days: Option<u32>,
+    ) -> Result<TenantSecurityPolicy, TenantServiceError> {
+        match (max_age_days, warning_days) {
+            (Some(0), _) => {
+                return Err(TenantServiceError::InvalidInput(
+                    "The maximum password age must be at least one day".to_string(),
+                ));
+            },
+            (None, Some(_)) => {
+                return Err(TenantServiceError::InvalidInput(
+                    "A password warning requires a maximum password age".to_string(),
+                ));
+            },
+            (Some(max_age_days), Some(warning_days)) if warning_days >= max_age_days => {
+                return Err(TenantServiceError::InvalidInput(
+                    "The password warning must start before the password expires".to_string(),
+                ));
+            },
+            _ => {},
+        }
+
+        let tenant = self.get_tenant(tenant_id).await?;
+        let mut policy = tenant.security_policy();
+        policy.password_max_age_days = max_age_days;
+        policy.password_warning_days = warning_days;
+
+        let policy_value = serde_json::to_value(&policy)
+            .map_err(|e| TenantServiceError::Internal(e.to_string()))?;
+        self.update_metadata(tenant, |metadata| {
+            metadata.insert(SECURITY_POLICY_METADATA_KEY.to_string(), policy_value);
+        })
+        .await?;
+
+        info!(
+            "Password expiry updated for tenant {}: {:?} days, warning {:?} days ahead",
+            tenant_id, max_age_days, warning_days
+        );
+        Ok(policy)
+    }
+
+    /// Sets how many previous passwords the tenant's users must not reuse
+    ///
+    /// `Some(0)` allows reuse; without a size the global setting applies.
+    /// Shrinking the history takes effect on the next password change of each
+    /// user, which prunes the stored hashes.
+    #[instrument(skip(self))]
+    pub async fn update_password_history(
+        &self,
+        tenant_id: &Uuid,
+        history_size: Option<u32>,
+    ) -> Result<TenantSecurityPolicy, TenantServiceError> {
+        if history_size.is_some_and(|size| size > MAX_PASSWORD_HISTORY_SIZE) {
+            return Err(TenantServiceError::InvalidInput(format!(
+                "The password history holds at most {} passwords",
+                MAX_PASSWORD_HISTORY_SIZE
+            )));
+        }
+
+        let tenant = self.get_tenant(tenant_id).await?;
+        let mut policy = tenant.security_policy();
+        policy.password_history_size = history_size;
+
+        let policy_value = serde_json::to_value(&policy)
+            .map_err(|e| TenantServiceError::Internal(e.to_string()))?;
+        self.update_metadata(tenant, |metadata| {
+            metadata.insert(SECURITY_POLICY_METADATA_KEY.to_string(), policy_value);
+        })
+        .await?;
+
+        info!(
+            "Password history updated for tenant {}: {:?} passwords",
+            tenant_id, history_size
+        );
+        Ok(policy)
+    }
+
+    /// Sets which authenticators may register WebAuthn credentials with the tenant
+    ///
+    /// Applies to registrations started afterwards; registered credentials are kept.
+    #[instrument(skip(self))]
+    pub async fn update_webauthn_attestation(
+        &self,
+        tenant_id: &Uuid,
+        attestation: WebAuthnAttestationPolicy,
+    ) -> Result<TenantSecurityPolicy, TenantServiceError> {
+        let mut allowed_aaguids = Vec::with_capacity(attestation.allowed_aaguids.len());
+        for aaguid in attestation.allowed_aaguids {
+            if aaguid.is_nil() {
+                return Err(TenantServiceError::InvalidInput(
+                    "The nil AAGUID does not identify an authenticator model".to_string(),
+                ));
+            }
+            if !allowed_aaguids.contains(&aaguid) {
+                allowed_aaguids.push(aaguid);
+            }
+        }
+
+        let tenant = self.get_tenant(tenant_id).await?;
+        let mut policy = tenant.security_policy();
+        policy.webauthn_attestation = WebAuthnAttestationPolicy {
+            require_attestation: attestation.require_attestation,
+            allowed_aaguids,
+        };
+
+        let policy_value = serde_json::to_value(&policy)
+            .map_err(|e| TenantServiceError::Internal(e.to_string()))?;
+        self.update_metadata(tenant, |metadata| {
+            metadata.insert(SECURITY_POLICY_METADATA_KEY.to_string(), policy_value);
+        })
+        .await?;
+
+        info!(
+            "WebAuthn attestation policy updated for tenant {}: attestation required: {}, {} allowed AAGUIDs",
+            tenant_id,
+            policy.webauthn_attestation.requires_attestation(),
+            policy.webauthn_attestation.allowed_aaguids.len()
+        );
+        Ok(policy)
+    }
+
+    /// Suspends a tenant, e.g. for non-payment
+    ///
+    /// A suspension keeps the data of the tenant and its admin and billing
+    /// users can still log in; the logins of other members are rejected with
+    /// the `reason` until the tenant is [unsuspended](Self::unsuspend).
+    /// Suspending a suspended tenant replaces the reason.
+    #[instrument(skip(self))]
+    pub async fn suspend(
+        &self,
+        tenant_id: &Uuid,
+        reason: &str,
+        actor_id: Option<Uuid>,
+    ) -> Result<Tenant, TenantServiceError> {
+        let reason = reason.trim();
+        if reason.is_empty() {
+            return Err(TenantServiceError::InvalidInput(
+                "A suspension requires a reason".to_string(),
+            ));
+        }
+
+        let tenant = self.get_tenant(tenant_id).await?;
+        let since = match tenant.status {
+            TenantStatus::Active => OffsetDateTime::now_utc(),
+            TenantStatus::Suspended { since, .. } => since,
+            TenantStatus::Archived { .. } => {
+                return Err(TenantServiceError::InvalidInput(
+                    "An archived tenant cannot be suspended".to_string(),
+                ));
+            },
+        };
+
+        let tenant = self
+            .tenant_repository
+            .set_tenant_status(
+                *tenant_id,
+                TenantStatus::Suspended {
+                    reason: reason.to_string(),
+                    since,
+                },
+                actor_id,
+            )
+            .await?;
+
+        info!("Tenant suspended: {} ({})", tenant_id, reason);
+        Ok(tenant)
+    }
+
+    /// Lifts the suspension of a tenant
+    ///
+    /// Unsuspending an active tenant leaves it unchanged; archived tenants
+    /// stay archived.
+    #[instrument(skip(self))]
+    pub async fn unsuspend(
+        &self,
+        tenant_id: &Uuid,
+        actor_id: Option<Uuid>,
+    ) -> Result<Tenant, TenantServiceError> {
+        let tenant = self.get_tenant(tenant_id).await?;
+        match tenant.status {
+            TenantStatus::Active => return Ok(tenant),
+            TenantStatus::Suspended { .. } => {},
+            TenantStatus::Archived { .. } => {
+                return Err(TenantServiceError::InvalidInput(
+                    "An archived tenant cannot be unsuspended".to_string(),
+                ));
+            },
+        }
+
+        let tenant = self
+            .tenant_repository
+            .set_tenant_status(*tenant_id, TenantStatus::Active, actor_id)
+            .await?;
+
+        info!("Tenant unsuspended: {}", tenant_id);
+        Ok(tenant)
+    }
+
+    /// Archives a tenant
+    ///
+    /// An archived tenant is closed to everyone but the export of its data:
+    /// its sessions are terminated and its active subscription ends. A tenant
+    /// with an active paid subscription is only archived with `force`.
+    /// Archiving an archived tenant leaves it unchanged.
+    #[instrument(skip(self))]
+    pub async fn archive(
+        &self,
+        tenant_id: &Uuid,
+        force: bool,
+        actor_id: Option<Uuid>,
+    ) -> Result<Tenant, TenantServiceError> {
+        let tenant = self.get_tenant(tenant_id).await?;
+        if tenant.status.is_archived() {
+            return Ok(tenant);
+        }
+
+        let paid_subscription = self
+            .get_active_subscription(tenant_id)
+            .await?
+            .filter(|subscription| subscription.plan_type != TenantPlanType::Free);
+        if let Some(subscription) = paid_subscription {
+            if !force {
+                return Err(TenantServiceError::InvalidInput(format!(
+                    "Tenant has an active {} subscription; archiving it requires force",
+                    subscription.plan_type
+                )));
+            }
+            warn!(
+                "Archiving tenant {} with an active {} subscription",
+                tenant_id, subscription.plan_type
+            );
+        }
+
+        let tenant = self
+            .tenant_repository
+            .set_tenant_status(
+                *tenant_id,
+                TenantStatus::Archived {
+                    since: OffsetDateTime::now_utc(),
+                },
+                actor_id,
+            )
+            .await?;
+        self.deactivate_existing_subscriptions(tenant_id, actor_id)
+            .await?;
+        let terminated = self
+            .user_service
+            .session_service()
+            .force_terminate_tenant_sessions(*tenant_id, SessionInvalidationReason::TenantArchived)
+            .await
+            .map_err(UserServiceError::from)?;
+
+        info!(
+            "Tenant archived: {} ({} sessions terminated)",
+            tenant_id, terminated
+        );
+        Ok(tenant)
+    }
+
+    /// Stores the metadata of `tenant` after applying `update`, keeping its other entries
+    async fn update_metadata(
+        &self,
+        tenant: Tenant,
+        update: impl FnOnce(&mut serde_json::Map<String, serde_json::Value>),
+    ) -> Result<Tenant, TenantServiceError> {
+        let mut metadata = match tenant.metadata {
+            Some(serde_json::Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        };
+        update(&mut metadata);
+        let metadata = BoundedJson::new(serde_json::Value::Object(metadata))
+            .map_err(|err| TenantServiceError::InvalidInput(err.to_string()))?;
+        Ok(self
+            .tenant_repository
+            .update_tenant(
+                tenant.id,
+                UpdateTenantDto {
+                    name: None,
+                    subdomain: None,
+                    metadata: Some(Some(metadata)),
+                },
+            )
+            .await?)
+    }
+
     /// Private utility functions
     // Validates a subdomain
     fn validate_subdomain(&self, subdomain: &str) -> Result<(), TenantServiceError> {
@@ -501,18 +1163,35 @@ impl TenantService {
         Ok(())
     }
 
-    // Deactivates existing subscriptions
+    // Records a subscription change in the history
+    async fn record_subscription_change(
+        &self,
+        change_type: SubscriptionChangeType,
+        old: Option<&TenantSubscription>,
+        new: &TenantSubscription,
+        actor_id: Option<Uuid>,
+    ) -> Result<(), TenantServiceError> {
+        let entry = SubscriptionHistoryEntry::new(change_type, old, new, actor_id);
+        self.tenant_repository
+            .record_subscription_change(&entry)
+            .await?;
+        Ok(())
+    }
+
+    // Deactivates existing subscriptions, returning the one that was active
     async fn deactivate_existing_subscriptions(
         &self,
         tenant_id: &Uuid,
-    ) -> Result<(), TenantServiceError> {
+        actor_id: Option<Uuid>,
+    ) -> Result<Option<TenantSubscription>, TenantServiceError> {
         debug!(
             "Deactivating existing subscriptions for tenant: {}",
             tenant_id
         );
 
         // Get current active subscription
-        if let Some(subscription) = self.get_active_subscription(tenant_id).await? {
+        let active = self.get_active_subscription(tenant_id).await?;
+        if let Some(subscription) = &active {
             // Deactivate it
             self.update_subscription(
                 &subscription.id,
@@ -524,11 +1203,12 @@ impl TenantService {
                     max_users: None,
                     features: None,
                 },
+                actor_id,
             )
             .await?;
         }
 
-        Ok(())
+        Ok(active)
     }
 
     // Checks if user is the last admin in the tenant
@@ -561,6 +1241,26 @@ impl TenantService {
         Ok(())
     }
 
+    // Adds the association, atomically checking the tenants-per-user limit if one is set
+    async fn insert_tenant_user(
+        &self,
+        tenant_id: &Uuid,
+        user: CreateTenantUserDto,
+    ) -> Result<TenantUser, TenantError> {
+        match self.max_tenants_per_user {
+            Some(max_tenants_per_user) => {
+                self.tenant_repository
+                    .add_user_to_tenant_within_limit(*tenant_id, user, max_tenants_per_user)
+                    .await
+            },
+            None => {
+                self.tenant_repository
+                    .add_user_to_tenant(*tenant_id, user)
+                    .await
+            },
+        }
+    }
+
     // Checks if tenant has reached user limit
     async fn check_tenant_user_limits(&self, tenant_id: &Uuid) -> Result<(), TenantServiceError> {
         debug!("Checking user limits for tenant: {}", tenant_id);
@@ -569,27 +1269,57 @@ impl TenantService {
         let subscription = self.get_active_subscription(tenant_id).await?;
 
         // If there's an active subscription with a user limit
-        if let Some(subscription) = subscription {
-            if let Some(max_users) = subscription.max_users {
-                // Count current users
-                let current_users = self.get_tenant_users(tenant_id).await?;
-
-                // Only count active users
-                let active_users = current_users.iter().filter(|u| u.is_active).count() as i32;
-
-                // Check if limit is reached
-                if active_users >= max_users {
-                    return Err(TenantServiceError::TenantLimitExceeded(format!(
+        if let Some(max_users) = subscription.and_then(|subscription| subscription.max_users) {
+            // Check if limit is reached
+            let active_users = self.count_active_users(tenant_id).await?;
+            if active_users >= max_users {
+                return Err(TenantServiceError::TenantLimitExceeded {
+                    message: format!(
                         "User limit of {} reached for tenant {}",
                         max_users, tenant_id
-                    )));
-                }
+                    ),
+                    limit: max_users,
+                    current: active_users,
+                });
             }
         }
 
         Ok(())
     }
 
+    // Checks that a user limit leaves room for the current active users
+    async fn ensure_user_capacity(
+        &self,
+        tenant_id: &Uuid,
+        max_users: i32,
+    ) -> Result<(), TenantServiceError> {
+        let active_users = self.count_active_users(tenant_id).await?;
+        if active_users > max_users {
+            return Err(TenantServiceError::TenantLimitExceeded {
+                message: format!(
+                    "Tenant {} has {} active users, {} over the new limit of {}",
+                    tenant_id,
+                    active_users,
+                    active_users - max_users,
+                    max_users
+                ),
+                limit: max_users,
+                current: active_users,
+            });
+        }
+
+        Ok(())
+    }
+
+    // Counts the active users of a tenant
+    async fn count_active_users(&self, tenant_id: &Uuid) -> Result<i32, TenantServiceError> {
+        let active_users = self
+            .tenant_repository
+            .count_active_tenant_users(*tenant_id)
+            .await?;
+        Ok(i32::try_from(active_users).unwrap_or(i32::MAX))
+    }
+
     /// Checks if user belongs to a tenant with specified role
     #[instrument(skip(self))]
     pub async fn check_user_tenant_role(
@@ -633,3 +1363,20 @@ impl TenantService {
         }
     }
 }
+
+/// Parse an allowed IP range, rejecting blocks with host bits set
+fn parse_ip_range(range: &str) -> Result<IpNetwork, TenantServiceError> {
+    let range = range.trim();
+    let network = range.parse::<IpNetwork>().map_err(|e| {
+        TenantServiceError::InvalidInput(format!("Invalid IP range '{}': {}", range, e))
+    })?;
+    if network.ip() != network.network() {
+        return Err(TenantServiceError::InvalidInput(format!(
+            "Invalid IP range '{}': host bits are set, did you mean {}/{}?",
+            range,
+            network.network(),
+            network.prefix()
+        )));
+    }
+    Ok(network)
+}
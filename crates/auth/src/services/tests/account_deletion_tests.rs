@@ -0,0 +1,331 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use time::Duration;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::config::AuthConfig;
+use crate::models::{
+    VerificationType,
+    tenant::TenantRepository,
+    user::{User, UserRepository, mock::MockUserRepository},
+};
+use crate::repository::account_deletion_repository::mock::MockAccountDeletionRepository;
+use crate::services::account_deletion::{
+    AccountDeletionConfig, AccountDeletionError, AccountDeletionService, DeletionConfirmation,
+    DeletionReport,
+};
+use crate::services::message_provider::MockMessageProvider;
+use crate::services::session::SessionService;
+use crate::services::user::UserService;
+use crate::session::{SessionFilter, mock::MockSessionRepository};
+use crate::utils::clock::{Clock, TestClock};
+use crate::utils::jwt::JwtUtils;
+use crate::utils::password::hash_password;
+
+use super::mocks::MockTenantRepository;
+
+const PASSWORD: &str = "Correct-Horse-9";
+
+struct Fixture {
+    service: AccountDeletionService<TestClock>,
+    repository: Arc<MockAccountDeletionRepository>,
+    user_repo: Arc<MockUserRepository>,
+    tenant_repo: Arc<MockTenantRepository>,
+    session_service: Arc<SessionService>,
+    email: Arc<MockMessageProvider>,
+    /// Number of emails already looked at by [`Fixture::last_email_subject`]
+    emails_read: AtomicUsize,
+    clock: TestClock,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let repository = Arc::new(MockAccountDeletionRepository::new());
+        let user_repo = Arc::new(MockUserRepository::new());
+        let tenant_repo = Arc::new(MockTenantRepository::new());
+        let session_service = Arc::new(SessionService::new(
+            Arc::new(MockSessionRepository::new()),
+            Arc::new(AuthConfig::default()),
+        ));
+        let email = Arc::new(MockMessageProvider::new(VerificationType::Email));
+        let clock = TestClock::default();
+        let service = AccountDeletionService::with_clock(
+            repository.clone(),
+            user_repo.clone(),
+            session_service.clone(),
+            AccountDeletionConfig::default(),
+            clock.clone(),
+        )
+        .with_tenant_repository(tenant_repo.clone())
+        .with_email_provider(email.clone());
+        Self {
+            service,
+            repository,
+            user_repo,
+            tenant_repo,
+            session_service,
+            email,
+            emails_read: AtomicUsize::new(0),
+            clock,
+        }
+    }
+
+    async fn user(&self, email: &str) -> User {
+        let user = User::new(
+            email.to_string(),
+            hash_password(PASSWORD).expect("Password hashes"),
+        );
+        self.user_repo.create(&user).await.expect("User is stored");
+        user
+    }
+
+    async fn session(&self, user_id: Uuid) -> Uuid {
+        let (session, _) = self
+            .session_service
+            .create_session(user_id, None, None, None, None, None, false)
+            .await
+            .expect("Session is created");
+        session.id
+    }
+
+    /// Subject of the latest email, if any was sent since the last call
+    fn last_email_subject(&self) -> Option<String> {
+        let sent = self.email.sent_messages().len();
+        if self.emails_read.swap(sent, Ordering::SeqCst) == sent {
+            return None;
+        }
+        self.email
+            .last_message()
+            .and_then(|message| message.subject)
+    }
+}
+
+#[test]
+async fn test_request_and_cancel_account_deletion() {
+    let fixture = Fixture::new();
+    let user = fixture.user("leaving@example.com").await;
+    let current_session = fixture.session(user.id).await;
+    let other_session = fixture.session(user.id).await;
+
+    let result = fixture
+        .service
+        .request_deletion(
+            user.id,
+            current_session,
+            DeletionConfirmation::Password("wrong".to_string()),
+        )
+        .await;
+    assert!(matches!(
+        result,
+        Err(AccountDeletionError::InvalidCredentials)
+    ));
+    assert!(fixture.repository.request(user.id).is_none());
+
+    let request = fixture
+        .service
+        .request_deletion(
+            user.id,
+            current_session,
+            DeletionConfirmation::Password(PASSWORD.to_string()),
+        )
+        .await
+        .expect("Deletion is requested");
+    assert_eq!(
+        request.scheduled_for,
+        fixture.clock.now() + Duration::days(14)
+    );
+    assert_eq!(
+        fixture.last_email_subject().as_deref(),
+        Some("Your account will be deleted")
+    );
+
+    // Only the session that requested the deletion survives
+    let active: Vec<Uuid> = fixture
+        .session_service
+        .get_user_sessions(user.id, SessionFilter::Active)
+        .await
+        .expect("Sessions are listed")
+        .iter()
+        .map(|session| session.id)
+        .collect();
+    assert_eq!(active, vec![current_session]);
+    assert!(!active.contains(&other_session));
+
+    // Logins during the grace period report the scheduled deletion
+    let user_service = UserService::new(
+        fixture.user_repo.clone(),
+        Arc::new(JwtUtils::new(b"test-secret")),
+        fixture.session_service.clone(),
+        None,
+        Arc::new(AuthConfig::default()),
+    )
+    .with_account_deletion_repository(fixture.repository.clone());
+    let login = user_service
+        .login(&user.email, PASSWORD, None, None, None, None, false)
+        .await
+        .expect("Login succeeds during the grace period");
+    assert_eq!(login.pending_deletion, Some(request.scheduled_for));
+
+    let result = fixture
+        .service
+        .request_deletion(
+            user.id,
+            current_session,
+            DeletionConfirmation::RecentSecondFactor,
+        )
+        .await;
+    assert!(matches!(result, Err(AccountDeletionError::AlreadyPending)));
+
+    fixture.clock.advance(Duration::days(13));
+    fixture
+        .service
+        .cancel_deletion(user.id)
+        .await
+        .expect("Deletion is cancelled within the grace period");
+    assert!(
+        fixture
+            .service
+            .pending_deletion(user.id)
+            .await
+            .expect("Pending deletion is looked up")
+            .is_none()
+    );
+    assert!(matches!(
+        fixture.service.cancel_deletion(user.id).await,
+        Err(AccountDeletionError::NotPending)
+    ));
+    let login = user_service
+        .login(&user.email, PASSWORD, None, None, None, None, false)
+        .await
+        .expect("Login succeeds");
+    assert_eq!(login.pending_deletion, None);
+
+    // Once the grace period is over the deletion can no longer be cancelled
+    fixture
+        .service
+        .request_deletion(
+            user.id,
+            current_session,
+            DeletionConfirmation::RecentSecondFactor,
+        )
+        .await
+        .expect("Deletion is requested again");
+    fixture.clock.advance(Duration::days(14));
+    assert!(matches!(
+        fixture.service.cancel_deletion(user.id).await,
+        Err(AccountDeletionError::NotPending)
+    ));
+    assert_eq!(
+        fixture
+            .service
+            .process_due()
+            .await
+            .expect("Deletion job runs"),
+        DeletionReport {
+            deleted: 1,
+            blocked: 0,
+            failed: 0,
+        }
+    );
+    let deleted = fixture
+        .user_repo
+        .find_by_id(user.id)
+        .await
+        .expect("User is loaded")
+        .expect("Anonymized user is kept");
+    assert!(!deleted.is_active);
+    assert!(!deleted.has_password());
+    assert_ne!(deleted.email, user.email);
+}
+
+#[test]
+async fn test_last_admin_is_not_deleted() {
+    let fixture = Fixture::new();
+    let admin = fixture.user("owner@example.com").await;
+    let tenant_id = fixture
+        .tenant_repo
+        .insert_tenant_with_metadata(serde_json::Value::Object(serde_json::Map::new()));
+    fixture.tenant_repo.add_member(tenant_id, admin.id, "ADMIN");
+    let session = fixture.session(admin.id).await;
+
+    fixture
+        .service
+        .request_deletion(
+            admin.id,
+            session,
+            DeletionConfirmation::Password(PASSWORD.to_string()),
+        )
+        .await
+        .expect("Deletion is requested");
+    fixture.last_email_subject();
+
+    // Nothing happens before the grace period ends
+    assert_eq!(
+        fixture.service.process_due().await.expect("Job runs"),
+        DeletionReport::default()
+    );
+
+    fixture.clock.advance(Duration::days(15));
+    let blocked = DeletionReport {
+        deleted: 0,
+        blocked: 1,
+        failed: 0,
+    };
+    assert_eq!(
+        fixture.service.process_due().await.expect("Job runs"),
+        blocked
+    );
+    assert_eq!(
+        fixture.last_email_subject().as_deref(),
+        Some("Transfer tenant ownership to delete your account")
+    );
+    let request = fixture
+        .repository
+        .request(admin.id)
+        .expect("Request is kept");
+    assert!(request.is_pending());
+    assert!(request.ownership_notice_sent_at.is_some());
+    let kept = fixture
+        .user_repo
+        .find_by_id(admin.id)
+        .await
+        .expect("User is loaded")
+        .expect("User exists");
+    assert!(kept.is_active);
+    assert_eq!(kept.email, admin.email);
+
+    // The ownership notice is sent only once
+    assert_eq!(
+        fixture.service.process_due().await.expect("Job runs"),
+        blocked
+    );
+    assert_eq!(fixture.last_email_subject(), None);
+
+    // After the ownership is shared, the next run deletes the account
+    let successor = fixture.user("successor@example.com").await;
+    fixture
+        .tenant_repo
+        .add_member(tenant_id, successor.id, "ADMIN");
+    assert_eq!(
+        fixture.service.process_due().await.expect("Job runs"),
+        DeletionReport {
+            deleted: 1,
+            blocked: 0,
+            failed: 0,
+        }
+    );
+    assert!(
+        fixture
+            .repository
+            .request(admin.id)
+            .is_some_and(|request| !request.is_pending())
+    );
+    let members = fixture
+        .tenant_repo
+        .get_tenant_users(tenant_id)
+        .await
+        .expect("Members are listed");
+    assert_eq!(members.len(), 1);
+    assert_eq!(members[0].user_id, successor.id);
+}
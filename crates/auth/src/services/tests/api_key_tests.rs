@@ -0,0 +1,489 @@
+use std::sync::Arc;
+use time::Duration;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::models::VerificationType;
+use crate::models::tenant::TenantSecurityPolicy;
+use crate::models::user::{User, UserRepository, mock::MockUserRepository};
+use crate::repository::api_key_repository::mock::MockApiKeyRepository;
+use crate::services::api_key::{
+    ApiKeyError, ApiKeyQuery, ApiKeyService, DEFAULT_ROTATION_OVERLAP, IssueApiKey,
+    LAST_USE_RECORD_INTERVAL,
+};
+use crate::services::api_key_reminder::{
+    ApiKeyReminderConfig, ApiKeyReminderReport, ApiKeyReminderService,
+};
+use crate::services::message_provider::MockMessageProvider;
+use crate::utils::clock::{Clock, TestClock};
+
+use super::mocks::MockTenantRepository;
+
+struct Fixture {
+    service: ApiKeyService<TestClock>,
+    repository: Arc<MockApiKeyRepository>,
+    clock: TestClock,
+    tenant_id: Uuid,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        Self::with_rotation_overlap(DEFAULT_ROTATION_OVERLAP)
+    }
+
+    fn with_rotation_overlap(rotation_overlap: Duration) -> Self {
+        let repository = Arc::new(MockApiKeyRepository::new());
+        let clock = TestClock::default();
+        Self {
+            service: ApiKeyService::with_clock(repository.clone(), clock.clone())
+                .with_rotation_overlap(rotation_overlap),
+            repository,
+            clock,
+            tenant_id: Uuid::new_v4(),
+        }
+    }
+
+    fn request(expires_in: Option<Duration>) -> IssueApiKey {
+        IssueApiKey {
+            name: "Billing export".to_string(),
+            scopes: vec!["sessions:export".to_string(), "users:read".to_string()],
+            expires_in,
+            ..IssueApiKey::default()
+        }
+    }
+
+    async fn issue_tagged(&self, name: &str, tags: &[&str], expires_in: Option<Duration>) {
+        self.service
+            .issue(
+                self.tenant_id,
+                IssueApiKey {
+                    name: name.to_string(),
+                    tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                    expires_in,
+                    ..IssueApiKey::default()
+                },
+            )
+            .await
+            .expect("Key is issued");
+    }
+
+    async fn listed(&self, query: ApiKeyQuery) -> Vec<String> {
+        self.service
+            .list(self.tenant_id, query)
+            .await
+            .expect("Keys are listed")
+            .into_iter()
+            .map(|key| key.name)
+            .collect()
+    }
+}
+
+#[test]
+async fn test_issued_key_authenticates_with_its_scopes() {
+    let fixture = Fixture::new();
+
+    let issued = fixture
+        .service
+        .issue(fixture.tenant_id, Fixture::request(None))
+        .await
+        .expect("Key is issued");
+    assert!(
+        issued
+            .key
+            .starts_with(&format!("acci_{}_", issued.api_key.prefix))
+    );
+
+    // Only the hash of the key is stored
+    let stored = fixture.repository.keys();
+    assert_eq!(stored.len(), 1);
+    assert_ne!(stored[0].key_hash, issued.key);
+    assert!(!stored[0].key_hash.contains(&issued.key));
+    let serialized = serde_json::to_string(&stored[0]).expect("Key serializes");
+    assert!(!serialized.contains(&stored[0].key_hash));
+
+    let api_key = fixture
+        .service
+        .authenticate(&issued.key)
+        .await
+        .expect("Key authenticates");
+    assert_eq!(api_key.id, issued.api_key.id);
+    assert_eq!(api_key.tenant_id, fixture.tenant_id);
+    assert!(api_key.has_scope("users:read"));
+    assert!(!api_key.has_scope("users:write"));
+    assert_eq!(
+        fixture.repository.keys()[0].last_used_at,
+        Some(fixture.clock.now())
+    );
+}
+
+#[test]
+async fn test_revoked_key_is_rejected_immediately() {
+    let fixture = Fixture::new();
+    let issued = fixture
+        .service
+        .issue(fixture.tenant_id, Fixture::request(None))
+        .await
+        .expect("Key is issued");
+    fixture
+        .service
+        .authenticate(&issued.key)
+        .await
+        .expect("Key authenticates");
+
+    fixture
+        .service
+        .revoke(fixture.tenant_id, issued.api_key.id)
+        .await
+        .expect("Key is revoked");
+
+    let result = fixture.service.authenticate(&issued.key).await;
+    assert!(matches!(result, Err(ApiKeyError::InvalidKey)));
+
+    let result = fixture
+        .service
+        .revoke(fixture.tenant_id, issued.api_key.id)
+        .await;
+    assert!(matches!(result, Err(ApiKeyError::NotFound)));
+}
+
+#[test]
+async fn test_keys_are_scoped_to_their_tenant() {
+    let fixture = Fixture::new();
+    let issued = fixture
+        .service
+        .issue(fixture.tenant_id, Fixture::request(None))
+        .await
+        .expect("Key is issued");
+
+    let result = fixture
+        .service
+        .revoke(Uuid::new_v4(), issued.api_key.id)
+        .await;
+    assert!(matches!(result, Err(ApiKeyError::NotFound)));
+    assert!(
+        fixture
+            .service
+            .list(Uuid::new_v4(), ApiKeyQuery::default())
+            .await
+            .expect("Keys are listed")
+            .is_empty()
+    );
+    fixture
+        .service
+        .authenticate(&issued.key)
+        .await
+        .expect("Key of the tenant is still valid");
+}
+
+#[test]
+async fn test_rotation_replaces_key() {
+    let fixture = Fixture::with_rotation_overlap(Duration::ZERO);
+    let issued = fixture
+        .service
+        .issue(
+            fixture.tenant_id,
+            Fixture::request(Some(Duration::days(30))),
+        )
+        .await
+        .expect("Key is issued");
+
+    let rotated = fixture
+        .service
+        .rotate(fixture.tenant_id, issued.api_key.id)
+        .await
+        .expect("Key is rotated");
+    assert_ne!(rotated.issued.key, issued.key);
+    assert_eq!(rotated.issued.api_key.scopes, issued.api_key.scopes);
+    assert_eq!(rotated.issued.api_key.expires_at, issued.api_key.expires_at);
+    assert_eq!(rotated.previous_key_expires_at, Some(fixture.clock.now()));
+
+    let result = fixture.service.authenticate(&issued.key).await;
+    assert!(matches!(result, Err(ApiKeyError::InvalidKey)));
+    fixture
+        .service
+        .authenticate(&rotated.issued.key)
+        .await
+        .expect("Rotated key authenticates");
+}
+
+#[test]
+async fn test_rotated_key_stays_valid_for_overlap() {
+    let fixture = Fixture::with_rotation_overlap(Duration::hours(2));
+    let issued = fixture
+        .service
+        .issue(fixture.tenant_id, Fixture::request(None))
+        .await
+        .expect("Key is issued");
+
+    let rotated = fixture
+        .service
+        .rotate(fixture.tenant_id, issued.api_key.id)
+        .await
+        .expect("Key is rotated");
+    let overlap_end = fixture.clock.now() + Duration::hours(2);
+    assert_eq!(rotated.previous_key_expires_at, Some(overlap_end));
+
+    // Both keys work until the overlap ends
+    fixture
+        .clock
+        .advance(Duration::hours(2) - Duration::seconds(1));
+    for key in [&issued.key, &rotated.issued.key] {
+        fixture
+            .service
+            .authenticate(key)
+            .await
+            .expect("Key authenticates during the overlap");
+    }
+
+    fixture.clock.advance(Duration::seconds(1));
+    let result = fixture.service.authenticate(&issued.key).await;
+    assert!(matches!(result, Err(ApiKeyError::InvalidKey)));
+    fixture
+        .service
+        .authenticate(&rotated.issued.key)
+        .await
+        .expect("Replacement key authenticates");
+
+    // A key expiring within the overlap keeps its own expiry
+    let short_lived = fixture
+        .service
+        .issue(
+            fixture.tenant_id,
+            Fixture::request(Some(Duration::minutes(30))),
+        )
+        .await
+        .expect("Key is issued");
+    let rotated = fixture
+        .service
+        .rotate(fixture.tenant_id, short_lived.api_key.id)
+        .await
+        .expect("Key is rotated");
+    assert_eq!(
+        rotated.previous_key_expires_at,
+        short_lived.api_key.expires_at
+    );
+}
+
+#[test]
+async fn test_last_use_is_recorded_at_most_once_per_interval() {
+    let fixture = Fixture::new();
+    let issued = fixture
+        .service
+        .issue(fixture.tenant_id, Fixture::request(None))
+        .await
+        .expect("Key is issued");
+    let first_use = fixture.clock.now();
+    let stored_last_use = || fixture.repository.keys()[0].last_used_at;
+
+    fixture
+        .service
+        .authenticate(&issued.key)
+        .await
+        .expect("Key authenticates");
+    assert_eq!(stored_last_use(), Some(first_use));
+
+    // Requests within the interval do not write
+    fixture
+        .clock
+        .advance(LAST_USE_RECORD_INTERVAL - Duration::seconds(1));
+    let api_key = fixture
+        .service
+        .authenticate(&issued.key)
+        .await
+        .expect("Key authenticates");
+    assert_eq!(api_key.last_used_at, Some(fixture.clock.now()));
+    assert_eq!(stored_last_use(), Some(first_use));
+
+    fixture.clock.advance(Duration::seconds(1));
+    fixture
+        .service
+        .authenticate(&issued.key)
+        .await
+        .expect("Key authenticates");
+    assert_eq!(stored_last_use(), Some(fixture.clock.now()));
+}
+
+#[test]
+async fn test_list_filters_by_tag_search_and_expiry() {
+    let fixture = Fixture::new();
+    fixture
+        .issue_tagged(
+            "Billing export",
+            &["Billing", "nightly"],
+            Some(Duration::days(10)),
+        )
+        .await;
+    fixture
+        .issue_tagged("Billing sync", &["billing"], Some(Duration::days(60)))
+        .await;
+    fixture.issue_tagged("Reporting", &["nightly"], None).await;
+
+    let tagged = fixture
+        .listed(ApiKeyQuery {
+            tags: vec!["billing".to_string()],
+            ..ApiKeyQuery::default()
+        })
+        .await;
+    assert_eq!(tagged.len(), 2);
+    let both_tags = fixture
+        .listed(ApiKeyQuery {
+            tags: vec!["billing".to_string(), "nightly".to_string()],
+            ..ApiKeyQuery::default()
+        })
+        .await;
+    assert_eq!(both_tags, vec!["Billing export"]);
+    let searched = fixture
+        .listed(ApiKeyQuery {
+            search: Some("REPORT".to_string()),
+            ..ApiKeyQuery::default()
+        })
+        .await;
+    assert_eq!(searched, vec!["Reporting"]);
+
+    let expiring = fixture
+        .listed(ApiKeyQuery {
+            expiring_within: Some(Duration::days(30)),
+            ..ApiKeyQuery::default()
+        })
+        .await;
+    assert_eq!(expiring, vec!["Billing export"]);
+
+    // Expired keys no longer count as expiring
+    fixture.clock.advance(Duration::days(10));
+    let expiring = fixture
+        .listed(ApiKeyQuery {
+            expiring_within: Some(Duration::days(60)),
+            ..ApiKeyQuery::default()
+        })
+        .await;
+    assert_eq!(expiring, vec!["Billing sync"]);
+    assert_eq!(fixture.listed(ApiKeyQuery::default()).await.len(), 3);
+}
+
+#[test]
+async fn test_reminder_job_emails_admins_of_due_keys() {
+    let fixture = Fixture::new();
+    let tenant_repo = Arc::new(MockTenantRepository::new());
+    let user_repo = Arc::new(MockUserRepository::new());
+    let email = Arc::new(MockMessageProvider::new(VerificationType::Email));
+    let tenant_id = tenant_repo.insert_tenant_with_policy(&TenantSecurityPolicy::default());
+    for (address, role) in [
+        ("admin@example.com", "ADMIN"),
+        ("member@example.com", "MEMBER"),
+    ] {
+        let user = User::new(address.to_string(), "hash".to_string());
+        user_repo.create(&user).await.expect("User is created");
+        tenant_repo.add_member(tenant_id, user.id, role);
+    }
+    let reminders = ApiKeyReminderService::with_clock(
+        fixture.repository.clone(),
+        tenant_repo,
+        user_repo,
+        email.clone(),
+        ApiKeyReminderConfig {
+            expiring_within_days: 14,
+            unused_for_days: 90,
+            repeat_after_days: 7,
+            ..ApiKeyReminderConfig::default()
+        },
+        fixture.clock.clone(),
+    );
+
+    let service = &fixture.service;
+    let request = |name: &str, expires_in| IssueApiKey {
+        name: name.to_string(),
+        expires_in,
+        ..IssueApiKey::default()
+    };
+    service
+        .issue(tenant_id, request("Expiring", Some(Duration::days(10))))
+        .await
+        .expect("Key is issued");
+    let used = service
+        .issue(tenant_id, request("Used", None))
+        .await
+        .expect("Key is issued");
+    assert_eq!(
+        reminders.process_due().await.expect("Job runs"),
+        ApiKeyReminderReport {
+            reminded: 1,
+            failed: 0
+        }
+    );
+    let messages = email.sent_messages();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].recipient, "admin@example.com");
+    assert!(messages[0].body.contains("- Expiring ("));
+    assert!(!messages[0].body.contains("- Used ("));
+
+    // The same key is not reported again before the repeat interval
+    fixture.clock.advance(Duration::days(1));
+    assert_eq!(
+        reminders.process_due().await.expect("Job runs"),
+        ApiKeyReminderReport::default()
+    );
+
+    // After 90 days without use the other key is due as well
+    fixture.clock.advance(Duration::days(89));
+    service
+        .authenticate(&used.key)
+        .await
+        .expect("Key authenticates");
+    fixture.clock.advance(Duration::days(1));
+    assert_eq!(
+        reminders.process_due().await.expect("Job runs"),
+        ApiKeyReminderReport::default()
+    );
+    fixture.clock.advance(Duration::days(90));
+    assert_eq!(reminders.process_due().await.expect("Job runs").reminded, 1);
+    assert!(
+        email
+            .last_message()
+            .expect("Reminder is sent")
+            .body
+            .contains("- Used (")
+    );
+}
+
+#[test]
+async fn test_expired_and_tampered_keys_are_rejected() {
+    let fixture = Fixture::new();
+    let issued = fixture
+        .service
+        .issue(
+            fixture.tenant_id,
+            Fixture::request(Some(Duration::hours(1))),
+        )
+        .await
+        .expect("Key is issued");
+
+    let tampered = format!("{}0", issued.key);
+    let result = fixture.service.authenticate(&tampered).await;
+    assert!(matches!(result, Err(ApiKeyError::InvalidKey)));
+
+    fixture.clock.advance(Duration::hours(1));
+    let result = fixture.service.authenticate(&issued.key).await;
+    assert!(matches!(result, Err(ApiKeyError::InvalidKey)));
+}
+
+#[test]
+async fn test_issue_validates_request() {
+    let fixture = Fixture::new();
+
+    let mut request = Fixture::request(None);
+    request.scopes.push("Admin Everything".to_string());
+    let result = fixture.service.issue(fixture.tenant_id, request).await;
+    assert!(matches!(result, Err(ApiKeyError::InvalidScope(_))));
+
+    let mut request = Fixture::request(None);
+    request.name = "  ".to_string();
+    let result = fixture.service.issue(fixture.tenant_id, request).await;
+    assert!(matches!(result, Err(ApiKeyError::InvalidInput(_))));
+
+    let result = fixture
+        .service
+        .issue(fixture.tenant_id, Fixture::request(Some(Duration::ZERO)))
+        .await;
+    assert!(matches!(result, Err(ApiKeyError::InvalidInput(_))));
+    assert!(fixture.repository.keys().is_empty());
+}
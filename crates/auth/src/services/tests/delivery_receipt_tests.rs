@@ -0,0 +1,349 @@
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use std::sync::Arc;
+use time::Duration;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::models::{DeliveryReceipt, DeliveryStatus, VerificationType};
+use crate::security::webhook::WebhookError;
+use crate::services::delivery_receipt::{
+    DeliveryReceiptConfig, DeliveryReceiptVerifier, DeliveryTracker, ReceiptOutcome, ResendPolicy,
+    parse_sendgrid_events, parse_twilio_callback, parse_vonage_receipt,
+};
+use crate::services::message_provider::{
+    EmailProviderConfig, Message, MessageProvider, MessageProviderConfig, MockMessageProvider,
+    SmsProviderConfig,
+};
+use crate::utils::clock::{Clock, TestClock};
+use crate::utils::secret::Secret;
+
+use super::mocks::MockMessageDeliveryRepository;
+
+const TWILIO_CALLBACK_URL: &str = "https://auth.example.com/webhooks/delivery/twilio";
+
+fn sms_message() -> Message {
+    Message {
+        tenant_id: Uuid::new_v4(),
+        user_id: Uuid::new_v4(),
+        recipient: "+15550123".to_string(),
+        subject: None,
+        body: "Your code is 123456".to_string(),
+        message_type: VerificationType::Sms,
+    }
+}
+
+fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+fn provider_config(delivery_receipts: DeliveryReceiptConfig) -> MessageProviderConfig {
+    MessageProviderConfig {
+        email: EmailProviderConfig {
+            provider: "sendgrid".to_string(),
+            smtp: None,
+            api_key: Some(Secret::from("sendgrid-key")),
+            sender_email: "noreply@example.com".to_string(),
+            sender_name: "Test".to_string(),
+            verification_template: "Your code is {code}".to_string(),
+            delivery: Default::default(),
+        },
+        sms: SmsProviderConfig {
+            provider: "twilio".to_string(),
+            api_key: Secret::from("AC123"),
+            api_secret: Some(Secret::from("twilio-token")),
+            sender: "+15550100".to_string(),
+            delivery: Default::default(),
+            status_callback_url: Some(TWILIO_CALLBACK_URL.to_string()),
+        },
+        outbox: Default::default(),
+        email_routing: Default::default(),
+        delivery_receipts,
+    }
+}
+
+/// Sends `message` through a tracked provider answering with `provider_message_id`
+async fn send_tracked(
+    tracker: &DeliveryTracker<TestClock>,
+    provider_message_id: &str,
+    message: Message,
+) {
+    let mut provider = MockMessageProvider::new(VerificationType::Sms);
+    provider.response = provider_message_id.to_string();
+    let provider = tracker.wrap(Arc::new(provider));
+
+    let id = provider
+        .send_message(message)
+        .await
+        .expect("Failed to send message");
+    assert_eq!(id, provider_message_id);
+}
+
+#[test]
+async fn test_twilio_delivered_callback_updates_delivery() {
+    let repository = Arc::new(MockMessageDeliveryRepository::new());
+    let clock = TestClock::default();
+    let tracker = DeliveryTracker::with_clock(repository.clone(), clock.clone());
+    send_tracked(&tracker, "twilio:SM123", sms_message()).await;
+
+    let recorded = repository.deliveries();
+    assert_eq!(recorded.len(), 1);
+    assert_eq!(recorded[0].status, DeliveryStatus::Queued);
+
+    clock.advance(Duration::seconds(5));
+    let receipt = parse_twilio_callback(&params(&[
+        ("AccountSid", "AC123"),
+        ("MessageSid", "SM123"),
+        ("MessageStatus", "delivered"),
+    ]))
+    .expect("Failed to parse callback")
+    .expect("Status should map to a delivery state");
+
+    let outcome = tracker
+        .apply_receipt(&receipt)
+        .await
+        .expect("Failed to apply receipt");
+    assert_eq!(outcome, ReceiptOutcome::Updated);
+
+    let delivery = &repository.deliveries()[0];
+    assert_eq!(delivery.status, DeliveryStatus::Delivered);
+    assert_eq!(delivery.updated_at, clock.now());
+    assert!(delivery.error.is_none());
+}
+
+#[test]
+async fn test_receipt_for_unknown_message_is_ignored() {
+    let repository = Arc::new(MockMessageDeliveryRepository::new());
+    let tracker = DeliveryTracker::with_clock(repository.clone(), TestClock::default());
+    send_tracked(&tracker, "twilio:SM123", sms_message()).await;
+
+    let receipt = DeliveryReceipt {
+        provider_message_id: "twilio:SM999".to_string(),
+        status: DeliveryStatus::Delivered,
+        error: None,
+    };
+    let outcome = tracker
+        .apply_receipt(&receipt)
+        .await
+        .expect("Unknown messages should not fail");
+
+    assert_eq!(outcome, ReceiptOutcome::Unknown);
+    assert_eq!(repository.deliveries()[0].status, DeliveryStatus::Queued);
+}
+
+#[test]
+async fn test_out_of_order_receipt_does_not_move_delivery_back() {
+    let repository = Arc::new(MockMessageDeliveryRepository::new());
+    let tracker = DeliveryTracker::with_clock(repository.clone(), TestClock::default());
+    send_tracked(&tracker, "twilio:SM123", sms_message()).await;
+
+    for (status, expected) in [
+        (DeliveryStatus::Delivered, ReceiptOutcome::Updated),
+        (DeliveryStatus::Sent, ReceiptOutcome::Stale),
+        (DeliveryStatus::Failed, ReceiptOutcome::Stale),
+    ] {
+        let receipt = DeliveryReceipt {
+            provider_message_id: "twilio:SM123".to_string(),
+            status,
+            error: None,
+        };
+        let outcome = tracker
+            .apply_receipt(&receipt)
+            .await
+            .expect("Failed to apply receipt");
+        assert_eq!(outcome, expected, "receipt reporting {status:?}");
+    }
+
+    assert_eq!(repository.deliveries()[0].status, DeliveryStatus::Delivered);
+}
+
+#[test]
+async fn test_failed_delivery_is_resent_until_attempts_run_out() {
+    let repository = Arc::new(MockMessageDeliveryRepository::new());
+    let mut resend_provider = MockMessageProvider::new(VerificationType::Sms);
+    resend_provider.response = "twilio:SM456".to_string();
+    let resend_provider = Arc::new(resend_provider);
+    let tracker = DeliveryTracker::with_clock(repository.clone(), TestClock::default())
+        .with_failure_policy(Arc::new(
+            ResendPolicy::new(2).with_provider(resend_provider.clone()),
+        ));
+    let message = sms_message();
+    send_tracked(&tracker, "twilio:SM123", message.clone()).await;
+
+    let receipt = parse_twilio_callback(&params(&[
+        ("MessageSid", "SM123"),
+        ("MessageStatus", "undelivered"),
+        ("ErrorCode", "30003"),
+    ]))
+    .expect("Failed to parse callback")
+    .expect("Status should map to a delivery state");
+    let outcome = tracker
+        .apply_receipt(&receipt)
+        .await
+        .expect("Failed to apply receipt");
+
+    let deliveries = repository.deliveries();
+    assert_eq!(deliveries.len(), 2);
+    let (original, retry) = (&deliveries[0], &deliveries[1]);
+    assert_eq!(outcome, ReceiptOutcome::Resent(retry.id));
+    assert_eq!(original.status, DeliveryStatus::Failed);
+    assert_eq!(original.error.as_deref(), Some("30003"));
+    assert_eq!(retry.provider_message_id, "twilio:SM456");
+    assert_eq!(retry.attempt, 2);
+    assert_eq!(retry.retry_of, Some(original.id));
+    assert_eq!(
+        resend_provider.last_message().map(|sent| sent.body),
+        Some(message.body)
+    );
+
+    // The second attempt is the last one
+    let receipt = DeliveryReceipt {
+        provider_message_id: "twilio:SM456".to_string(),
+        status: DeliveryStatus::Failed,
+        error: None,
+    };
+    let outcome = tracker
+        .apply_receipt(&receipt)
+        .await
+        .expect("Failed to apply receipt");
+    assert_eq!(outcome, ReceiptOutcome::Updated);
+    assert_eq!(repository.deliveries().len(), 2);
+    assert_eq!(resend_provider.sent_messages().len(), 1);
+}
+
+#[test]
+async fn test_twilio_callback_signature() {
+    let verifier = DeliveryReceiptVerifier::from_config(&provider_config(Default::default()))
+        .expect("Failed to create verifier");
+    let callback = params(&[
+        ("AccountSid", "AC123"),
+        ("MessageSid", "SM123"),
+        ("MessageStatus", "delivered"),
+    ]);
+    let signature = "5UCMW5aNxuiHT5+RJxncFszXY6I=";
+
+    assert_eq!(verifier.verify_twilio(Some(signature), &callback), Ok(()));
+    assert_eq!(
+        verifier.verify_twilio(None, &callback),
+        Err(WebhookError::MissingHeaders)
+    );
+
+    let tampered = params(&[
+        ("AccountSid", "AC123"),
+        ("MessageSid", "SM123"),
+        ("MessageStatus", "failed"),
+    ]);
+    assert_eq!(
+        verifier.verify_twilio(Some(signature), &tampered),
+        Err(WebhookError::InvalidSignature)
+    );
+}
+
+#[test]
+async fn test_vonage_receipt_signature() {
+    let verifier = DeliveryReceiptVerifier::from_config(&provider_config(DeliveryReceiptConfig {
+        vonage_signature_secret: Some(Secret::from("vonage-secret")),
+        ..Default::default()
+    }))
+    .expect("Failed to create verifier");
+    let receipt = params(&[
+        ("messageId", "0A000000123"),
+        ("status", "delivered"),
+        ("timestamp", "1735689600"),
+        ("nonce", "abc"),
+        (
+            "sig",
+            "68405D12BF105EA16414B53977C8C7258D4D7DBC07DC16DEA9BD5C1F5F3FCE83",
+        ),
+    ]);
+
+    assert_eq!(verifier.verify_vonage_at(&receipt, 1_735_689_660), Ok(()));
+    assert_eq!(
+        verifier.verify_vonage_at(&receipt, 1_735_689_600 + 3600),
+        Err(WebhookError::StaleTimestamp)
+    );
+
+    let parsed = parse_vonage_receipt(&receipt)
+        .expect("Failed to parse receipt")
+        .expect("Status should map to a delivery state");
+    assert_eq!(parsed.provider_message_id, "vonage:0A000000123");
+    assert_eq!(parsed.status, DeliveryStatus::Delivered);
+    assert!(parsed.error.is_none());
+}
+
+#[test]
+async fn test_sendgrid_signed_events() {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).expect("P-256 is available");
+    let key = PKey::from_ec_key(EcKey::generate(&group).expect("Failed to generate key"))
+        .expect("Failed to wrap key");
+    let public_key = BASE64.encode(key.public_key_to_der().expect("Failed to encode key"));
+    let verifier = DeliveryReceiptVerifier::from_config(&provider_config(DeliveryReceiptConfig {
+        sendgrid_verification_key: Some(public_key),
+        ..Default::default()
+    }))
+    .expect("Failed to create verifier");
+
+    let body = br#"[
+        {"event": "delivered", "sg_message_id": "abc123.filter0001.16.5"},
+        {"event": "open", "sg_message_id": "abc123.filter0001.16.5"},
+        {"event": "bounce", "sg_message_id": "def456.filter0002.1.0", "reason": "550 No such user"}
+    ]"#;
+    let timestamp = "1735689600";
+    let mut signer = Signer::new(MessageDigest::sha256(), &key).expect("Failed to create signer");
+    signer
+        .update(timestamp.as_bytes())
+        .expect("Failed to sign timestamp");
+    signer.update(body).expect("Failed to sign body");
+    let signature = BASE64.encode(signer.sign_to_vec().expect("Failed to sign"));
+
+    assert_eq!(
+        verifier.verify_sendgrid_at(Some(&signature), Some(timestamp), body, 1_735_689_600),
+        Ok(())
+    );
+    assert_eq!(
+        verifier.verify_sendgrid_at(Some(&signature), Some(timestamp), b"[]", 1_735_689_600),
+        Err(WebhookError::InvalidSignature)
+    );
+
+    let receipts = parse_sendgrid_events(body).expect("Failed to parse events");
+    assert_eq!(
+        receipts,
+        vec![
+            DeliveryReceipt {
+                provider_message_id: "sendgrid:abc123".to_string(),
+                status: DeliveryStatus::Delivered,
+                error: None,
+            },
+            DeliveryReceipt {
+                provider_message_id: "sendgrid:def456".to_string(),
+                status: DeliveryStatus::Failed,
+                error: Some("550 No such user".to_string()),
+            },
+        ]
+    );
+}
+
+#[test]
+async fn test_unconfigured_provider_callbacks_are_rejected() {
+    let mut config = provider_config(Default::default());
+    config.sms.status_callback_url = None;
+    let verifier =
+        DeliveryReceiptVerifier::from_config(&config).expect("Failed to create verifier");
+
+    let callback = params(&[("MessageSid", "SM123"), ("MessageStatus", "delivered")]);
+    assert_eq!(
+        verifier.verify_twilio(Some("5UCMW5aNxuiHT5+RJxncFszXY6I="), &callback),
+        Err(WebhookError::InvalidSignature)
+    );
+    assert_eq!(
+        verifier.verify_sendgrid(Some("c2ln"), Some("1735689600"), b"[]"),
+        Err(WebhookError::InvalidSignature)
+    );
+}
@@ -0,0 +1,238 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use time::Duration;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::models::VerificationType;
+use crate::services::email_routing::{RoutingEmailProvider, create_routing_email_provider};
+use crate::services::message_provider::{
+    EmailProviderConfig, EmailRoutingConfig, EmailRoutingRule, Message, MessageProvider,
+    MessageProviderConfig, SmsProviderConfig,
+};
+use crate::utils::clock::TestClock;
+use crate::utils::duration::DurationSecs;
+use crate::utils::secret::Secret;
+use acci_core::error::{Error, Result};
+use acci_core::http_client::{HttpClientFactory, OutboundHttpConfig};
+
+// Provider keeping the recipients it delivered to, failing while switched off
+#[derive(Default)]
+struct InMemoryProvider {
+    delivered: Mutex<Vec<String>>,
+    failing: Mutex<bool>,
+}
+
+impl InMemoryProvider {
+    fn delivered(&self) -> Vec<String> {
+        self.delivered.lock().unwrap().clone()
+    }
+
+    fn set_failing(&self, failing: bool) {
+        *self.failing.lock().unwrap() = failing;
+    }
+}
+
+#[async_trait]
+impl MessageProvider for InMemoryProvider {
+    fn verification_type(&self) -> VerificationType {
+        VerificationType::Email
+    }
+
+    async fn send_message(&self, message: Message) -> Result<String> {
+        if *self.failing.lock().unwrap() {
+            return Err(Error::Other(anyhow::anyhow!("provider unavailable")));
+        }
+        self.delivered.lock().unwrap().push(message.recipient);
+        Ok("message-id".to_string())
+    }
+}
+
+fn message(tenant_id: Uuid, recipient: &str) -> Message {
+    Message {
+        tenant_id,
+        user_id: Uuid::new_v4(),
+        recipient: recipient.to_string(),
+        subject: Some("Your verification code".to_string()),
+        body: "Your verification code is: 123456".to_string(),
+        message_type: VerificationType::Email,
+    }
+}
+
+struct Fixture {
+    router: RoutingEmailProvider<TestClock>,
+    bulk: Arc<InMemoryProvider>,
+    relay: Arc<InMemoryProvider>,
+    clock: TestClock,
+    enterprise_tenant: Uuid,
+    startup_tenant: Uuid,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let bulk = Arc::new(InMemoryProvider::default());
+        let relay = Arc::new(InMemoryProvider::default());
+        let clock = TestClock::default();
+        let enterprise_tenant = Uuid::new_v4();
+
+        let router =
+            RoutingEmailProvider::with_clock("bulk", DurationSecs::from_secs(60), clock.clone())
+                .with_provider("bulk", bulk.clone())
+                .with_provider("relay", relay.clone())
+                .with_rule(EmailRoutingRule {
+                    tenant_id: Some(enterprise_tenant),
+                    provider: "relay".to_string(),
+                    ..EmailRoutingRule::default()
+                });
+
+        Self {
+            router,
+            bulk,
+            relay,
+            clock,
+            enterprise_tenant,
+            startup_tenant: Uuid::new_v4(),
+        }
+    }
+}
+
+#[test]
+async fn test_routes_tenants_to_their_providers() {
+    let fixture = Fixture::new();
+
+    fixture
+        .router
+        .send_message(message(fixture.enterprise_tenant, "cio@enterprise.example"))
+        .await
+        .expect("Email is sent");
+    fixture
+        .router
+        .send_message(message(fixture.startup_tenant, "founder@startup.example"))
+        .await
+        .expect("Email is sent");
+
+    assert_eq!(fixture.relay.delivered(), vec!["cio@enterprise.example"]);
+    assert_eq!(fixture.bulk.delivered(), vec!["founder@startup.example"]);
+}
+
+#[test]
+async fn test_routes_by_recipient_domain() {
+    let fixture = Fixture::new();
+    let router = fixture.router.with_rule(EmailRoutingRule {
+        recipient_domain: Some("Partner.example".to_string()),
+        provider: "relay".to_string(),
+        ..EmailRoutingRule::default()
+    });
+
+    router
+        .send_message(message(fixture.startup_tenant, "ops@partner.example"))
+        .await
+        .expect("Email is sent");
+
+    assert_eq!(fixture.relay.delivered(), vec!["ops@partner.example"]);
+    assert!(fixture.bulk.delivered().is_empty());
+}
+
+#[test]
+async fn test_unhealthy_provider_is_skipped_until_cooldown_ends() {
+    let fixture = Fixture::new();
+    fixture.router.mark_unhealthy("relay");
+
+    fixture
+        .router
+        .send_message(message(fixture.enterprise_tenant, "cio@enterprise.example"))
+        .await
+        .expect("Email fails over to the default provider");
+    assert_eq!(fixture.bulk.delivered(), vec!["cio@enterprise.example"]);
+    assert!(fixture.relay.delivered().is_empty());
+
+    fixture.clock.advance(Duration::seconds(61));
+    assert!(fixture.router.is_healthy("relay"));
+    fixture
+        .router
+        .send_message(message(fixture.enterprise_tenant, "cto@enterprise.example"))
+        .await
+        .expect("Email is sent");
+    assert_eq!(fixture.relay.delivered(), vec!["cto@enterprise.example"]);
+}
+
+#[test]
+async fn test_failing_provider_is_marked_unhealthy() {
+    let fixture = Fixture::new();
+    fixture.relay.set_failing(true);
+
+    fixture
+        .router
+        .send_message(message(fixture.enterprise_tenant, "cio@enterprise.example"))
+        .await
+        .expect("Email fails over to the default provider");
+
+    assert!(!fixture.router.is_healthy("relay"));
+    assert_eq!(fixture.bulk.delivered(), vec!["cio@enterprise.example"]);
+}
+
+#[test]
+async fn test_unhealthy_providers_are_tried_as_last_resort() {
+    let fixture = Fixture::new();
+    fixture.router.mark_unhealthy("relay");
+    fixture.router.mark_unhealthy("bulk");
+
+    fixture
+        .router
+        .send_message(message(fixture.enterprise_tenant, "cio@enterprise.example"))
+        .await
+        .expect("Email is sent with the unhealthy provider");
+
+    assert_eq!(fixture.relay.delivered(), vec!["cio@enterprise.example"]);
+    assert!(fixture.router.is_healthy("relay"));
+}
+
+fn email_config(provider: &str) -> EmailProviderConfig {
+    EmailProviderConfig {
+        provider: provider.to_string(),
+        smtp: None,
+        api_key: Some(Secret::from("test-key")),
+        sender_email: "noreply@example.com".to_string(),
+        sender_name: "Test".to_string(),
+        verification_template: "Your code is {code}".to_string(),
+        delivery: Default::default(),
+    }
+}
+
+#[test]
+async fn test_broken_provider_does_not_prevent_startup() {
+    let config = MessageProviderConfig {
+        email: email_config("sendgrid"),
+        sms: SmsProviderConfig {
+            provider: "twilio".to_string(),
+            api_key: Secret::from("test-sid"),
+            api_secret: None,
+            sender: "+15550100".to_string(),
+            delivery: Default::default(),
+            status_callback_url: None,
+        },
+        outbox: Default::default(),
+        email_routing: EmailRoutingConfig {
+            // SMTP without server settings cannot be created
+            providers: HashMap::from([("relay".to_string(), email_config("smtp"))]),
+            ..EmailRoutingConfig::default()
+        },
+        delivery_receipts: Default::default(),
+    };
+
+    let client = HttpClientFactory::new(OutboundHttpConfig::default())
+        .build()
+        .expect("Failed to build HTTP client");
+    let provider = create_routing_email_provider(&config, &client, None, None);
+    assert!(provider.is_ok());
+
+    let config = MessageProviderConfig {
+        email: email_config("carrier-pigeon"),
+        ..config
+    };
+    assert!(matches!(
+        create_routing_email_provider(&config, &client, None, None),
+        Err(Error::Config(_))
+    ));
+}
@@ -0,0 +1,232 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use time::OffsetDateTime;
+use tokio::io::{AsyncReadExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::security::types::RiskLevel;
+use crate::services::event_export::{
+    EventExportConfig, EventExportError, EventExporter, EventTransport, ExportTarget,
+    SecurityEvent, SyslogFormat, TcpSyslogTransport,
+};
+
+/// Transport keeping the actions of the events it was sent
+#[derive(Default)]
+struct RecordingTransport {
+    actions: Mutex<Vec<String>>,
+}
+
+impl RecordingTransport {
+    fn actions(&self) -> Vec<String> {
+        self.actions
+            .lock()
+            .expect("recorded actions lock poisoned")
+            .clone()
+    }
+}
+
+#[async_trait]
+impl EventTransport for RecordingTransport {
+    fn name(&self) -> &str {
+        "recording"
+    }
+
+    async fn send(&self, event: &SecurityEvent, _cef: &str) -> Result<(), EventExportError> {
+        self.actions
+            .lock()
+            .expect("recorded actions lock poisoned")
+            .push(event.action.clone());
+        Ok(())
+    }
+}
+
+/// 2025-01-01T00:00:00Z
+fn occurred_at() -> OffsetDateTime {
+    OffsetDateTime::from_unix_timestamp(1_735_689_600).expect("valid timestamp")
+}
+
+fn event(action: &str, tenant_id: Option<Uuid>) -> SecurityEvent {
+    SecurityEvent {
+        tenant_id,
+        ..SecurityEvent::audit(action, occurred_at())
+    }
+}
+
+/// Read one octet-counted syslog frame
+async fn read_frame<R: AsyncReadExt + Unpin>(reader: &mut R) -> String {
+    let mut length = String::new();
+    loop {
+        let byte = reader.read_u8().await.expect("Frame length is sent");
+        if byte == b' ' {
+            break;
+        }
+        length.push(byte as char);
+    }
+    let mut message = vec![0; length.parse().expect("Frame starts with its length")];
+    reader
+        .read_exact(&mut message)
+        .await
+        .expect("Whole frame is sent");
+    String::from_utf8(message).expect("Frame is UTF-8")
+}
+
+#[test]
+async fn test_full_queue_drops_oldest_event() {
+    let transport = Arc::new(RecordingTransport::default());
+    let exporter = EventExporter::with_transports(vec![transport.clone()], HashMap::new(), 2);
+
+    for action in ["FIRST", "SECOND", "THIRD"] {
+        exporter.export(event(action, None));
+    }
+    assert_eq!(exporter.dropped(), 1);
+    assert_eq!(exporter.queued(), 2);
+
+    assert_eq!(exporter.send_queued().await, 2);
+    assert_eq!(transport.actions(), vec!["SECOND", "THIRD"]);
+    assert_eq!(exporter.queued(), 0);
+}
+
+#[test]
+async fn test_tenant_targets_receive_only_their_tenant() {
+    let tenant_id = Uuid::new_v4();
+    let global = Arc::new(RecordingTransport::default());
+    let tenant = Arc::new(RecordingTransport::default());
+    let exporter = EventExporter::with_transports(
+        vec![global.clone()],
+        HashMap::from([(tenant_id, vec![tenant.clone() as Arc<dyn EventTransport>])]),
+        10,
+    );
+
+    exporter.export(event("OWN_TENANT", Some(tenant_id)));
+    exporter.export(event("OTHER_TENANT", Some(Uuid::new_v4())));
+    exporter.export(event("NO_TENANT", None));
+    exporter.send_queued().await;
+
+    assert_eq!(
+        global.actions(),
+        vec!["OWN_TENANT", "OTHER_TENANT", "NO_TENANT"]
+    );
+    assert_eq!(tenant.actions(), vec!["OWN_TENANT"]);
+}
+
+#[test]
+async fn test_spawned_exporter_sends_queued_events() {
+    let transport = Arc::new(RecordingTransport::default());
+    let exporter = EventExporter::with_transports(vec![transport.clone()], HashMap::new(), 10);
+    exporter.export(event("BEFORE_START", None));
+
+    let worker = exporter.spawn();
+    exporter.export(event("AFTER_START", None));
+    tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        while transport.actions().len() < 2 {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Queued events are sent");
+    worker.abort();
+
+    assert_eq!(transport.actions(), vec!["BEFORE_START", "AFTER_START"]);
+}
+
+#[test]
+async fn test_tcp_transport_frames_rfc5424_messages() {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("Listener binds");
+    let address = listener.local_addr().expect("Listener has an address");
+    let transport = Arc::new(TcpSyslogTransport::new(
+        address.to_string(),
+        SyslogFormat::new(Some("auth-1"), "acci"),
+    ));
+    let exporter = EventExporter::with_transports(vec![transport], HashMap::new(), 10);
+
+    exporter.export(event("TENANT_UPDATE", None));
+    exporter.export(SecurityEvent {
+        risk_level: RiskLevel::Critical,
+        ..event("SESSION_INVALIDATED_SECURITY_BREACH", None)
+    });
+    exporter.send_queued().await;
+
+    // Both events arrive on one connection, each in its own frame
+    let (stream, _) = listener.accept().await.expect("Exporter connects");
+    let mut reader = BufReader::new(stream);
+    let first = read_frame(&mut reader).await;
+    let second = read_frame(&mut reader).await;
+
+    assert!(
+        first.starts_with(
+            "<38>1 2025-01-01T00:00:00Z auth-1 acci - audit - CEF:0|Broccode|ACCI Framework|"
+        ),
+        "unexpected message: {first}"
+    );
+    assert!(first.contains("|TENANT_UPDATE|tenant update|3|rt=1735689600000 cat=audit"));
+    assert!(
+        second.starts_with("<34>1 2025-01-01T00:00:00Z auth-1 acci - audit - CEF:0|"),
+        "unexpected message: {second}"
+    );
+    assert!(second.contains("|SESSION_INVALIDATED_SECURITY_BREACH|"));
+}
+
+#[test]
+async fn test_failed_target_does_not_block_others() {
+    // Nothing listens on the port of a dropped listener
+    let address = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("Listener binds")
+        .local_addr()
+        .expect("Listener has an address");
+    let unreachable = Arc::new(TcpSyslogTransport::new(
+        address.to_string(),
+        SyslogFormat::new(None, "acci"),
+    ));
+    let recording = Arc::new(RecordingTransport::default());
+    let exporter =
+        EventExporter::with_transports(vec![unreachable, recording.clone()], HashMap::new(), 10);
+
+    exporter.export(event("TENANT_UPDATE", None));
+    exporter.send_queued().await;
+
+    assert_eq!(recording.actions(), vec!["TENANT_UPDATE"]);
+}
+
+#[test]
+async fn test_config_deserializes_targets() {
+    let tenant_id = Uuid::new_v4();
+    let config: EventExportConfig = serde_json::from_value(serde_json::json!({
+        "targets": [
+            { "transport": "udp", "address": "siem.example.com:514" },
+            {
+                "transport": "https",
+                "url": "https://splunk.example.com/services/collector/raw",
+                "authorization": "Splunk token"
+            }
+        ],
+        "tenant_targets": {
+            tenant_id.to_string(): [{ "transport": "tcp", "address": "qradar.example.com:6514" }]
+        },
+        "queue_capacity": 500
+    }))
+    .expect("Config is valid");
+
+    assert!(config.is_enabled());
+    assert_eq!(config.queue_capacity, 500);
+    assert!(
+        matches!(&config.targets[0], ExportTarget::Udp { address } if address == "siem.example.com:514")
+    );
+    assert!(matches!(
+        &config.targets[1],
+        ExportTarget::Https {
+            authorization: Some(_),
+            ..
+        }
+    ));
+    assert!(matches!(
+        config.tenant_targets[&tenant_id].as_slice(),
+        [ExportTarget::Tcp { .. }]
+    ));
+    assert!(!EventExportConfig::default().is_enabled());
+}
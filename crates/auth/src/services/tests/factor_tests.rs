@@ -0,0 +1,299 @@
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::config::AuthConfig;
+use crate::models::tenant::{MfaEnforcement, TenantSecurityPolicy};
+use crate::models::user::{User, UserRepository, mock::MockUserRepository};
+use crate::models::{AuthFactor, LinkedIdentity, TotpSecret};
+use crate::repository::{ExternalIdentityRepository, TotpSecretRepository};
+use crate::services::session::SessionService;
+use crate::services::user::{UserService, UserServiceError};
+use crate::session::mock::MockSessionRepository;
+use crate::utils::jwt::JwtUtils;
+use crate::utils::password::{NO_PASSWORD_HASH, hash_password};
+
+use super::mocks::{
+    MockExternalIdentityRepository, MockTenantRepository, MockTotpSecretRepository,
+};
+
+const PASSWORD: &str = "Correct-Horse-Battery-42";
+
+struct Fixture {
+    user_service: UserService,
+    user_repo: Arc<MockUserRepository>,
+    tenant_repo: Arc<MockTenantRepository>,
+    totp_repo: Arc<MockTotpSecretRepository>,
+    identity_repo: Arc<MockExternalIdentityRepository>,
+    session_repo: Arc<MockSessionRepository>,
+    tenant_id: Uuid,
+}
+
+impl Fixture {
+    fn new(mfa_enforcement: MfaEnforcement) -> Self {
+        let user_repo = Arc::new(MockUserRepository::new());
+        let tenant_repo = Arc::new(MockTenantRepository::new());
+        let totp_repo = Arc::new(MockTotpSecretRepository::new());
+        let identity_repo = Arc::new(MockExternalIdentityRepository::new());
+        let session_repo = Arc::new(MockSessionRepository::new());
+        let tenant_id = tenant_repo.insert_tenant_with_policy(&TenantSecurityPolicy {
+            mfa_enforcement,
+            ..TenantSecurityPolicy::default()
+        });
+
+        let config = Arc::new(AuthConfig::default());
+        let session_service = Arc::new(SessionService::new(session_repo.clone(), config.clone()));
+        let user_service = UserService::new(
+            user_repo.clone(),
+            Arc::new(JwtUtils::new(b"test-secret")),
+            session_service,
+            None,
+            config,
+        )
+        .with_tenant_repository(tenant_repo.clone())
+        .with_totp_repository(totp_repo.clone())
+        .with_identity_repository(identity_repo.clone());
+
+        Self {
+            user_service,
+            user_repo,
+            tenant_repo,
+            totp_repo,
+            identity_repo,
+            session_repo,
+            tenant_id,
+        }
+    }
+
+    async fn add_user(&self) -> User {
+        let user = User::new(
+            "user@example.com".to_string(),
+            hash_password(PASSWORD).expect("Password hashes"),
+        );
+        self.user_repo.create(&user).await.expect("User is created");
+        self.tenant_repo
+            .add_member(self.tenant_id, user.id, "MEMBER");
+        user
+    }
+
+    async fn enroll_totp(&self, user: &User, recovery_codes: Vec<String>) {
+        let mut secret = TotpSecret::new(
+            user.id,
+            self.tenant_id,
+            "JBSWY3DPEHPK3PXP".to_string(),
+            "SHA1".to_string(),
+            6,
+            30,
+            recovery_codes,
+        );
+        secret.enabled = true;
+        self.totp_repo.save(&secret).await.expect("Secret is saved");
+    }
+
+    async fn link_identity(&self, user: &User) {
+        let now = OffsetDateTime::now_utc();
+        self.identity_repo
+            .link(&LinkedIdentity {
+                user_id: user.id,
+                provider: "google".to_string(),
+                subject: "google-subject".to_string(),
+                email: user.email.clone(),
+                linked_at: now,
+                last_login_at: now,
+            })
+            .await
+            .expect("Identity is linked");
+    }
+
+    /// Sign in and return the ID of the created session
+    async fn session_id(&self, user: &User) -> Uuid {
+        self.user_service
+            .login_with_tenant(
+                Some(self.tenant_id),
+                &user.email,
+                PASSWORD,
+                None,
+                None,
+                None,
+                None,
+                false,
+            )
+            .await
+            .expect("Login succeeds");
+        self.session_repo
+            .sessions()
+            .last()
+            .expect("Session is created")
+            .id
+    }
+
+    async fn remove(
+        &self,
+        user: &User,
+        session_id: Uuid,
+        factor: AuthFactor,
+    ) -> Result<(), UserServiceError> {
+        self.user_service
+            .remove_factor(user.id, self.tenant_id, session_id, &factor)
+            .await
+    }
+
+    async fn factors(&self, user: &User) -> Vec<AuthFactor> {
+        self.user_service
+            .list_factors(user.id, self.tenant_id)
+            .await
+            .expect("Factors are listed")
+            .into_iter()
+            .map(|enrolled| enrolled.factor)
+            .collect()
+    }
+
+    fn audited_actions(&self) -> Vec<(String, String)> {
+        self.session_repo
+            .audit_entries()
+            .into_iter()
+            .map(|entry| (entry.action, entry.details["factor"].to_string()))
+            .collect()
+    }
+}
+
+#[test]
+async fn test_list_factors_aggregates_sources() {
+    let fixture = Fixture::new(MfaEnforcement::Optional);
+    let user = fixture.add_user().await;
+    assert_eq!(fixture.factors(&user).await, vec![AuthFactor::Password]);
+
+    fixture
+        .enroll_totp(&user, vec!["hashed-code".to_string()])
+        .await;
+    assert_eq!(
+        fixture.factors(&user).await,
+        vec![
+            AuthFactor::Password,
+            AuthFactor::Totp,
+            AuthFactor::RecoveryCodes
+        ]
+    );
+}
+
+#[test]
+async fn test_password_removal_requires_linked_identity() {
+    let fixture = Fixture::new(MfaEnforcement::Optional);
+    let user = fixture.add_user().await;
+    let session_id = fixture.session_id(&user).await;
+
+    let result = fixture
+        .remove(&user, session_id, AuthFactor::Password)
+        .await;
+    assert!(matches!(result, Err(UserServiceError::LastSignInMethod)));
+    assert!(fixture.audited_actions().is_empty());
+
+    fixture.link_identity(&user).await;
+    fixture
+        .remove(&user, session_id, AuthFactor::Password)
+        .await
+        .expect("Password is removed");
+
+    let stored = fixture
+        .user_repo
+        .find_by_id(user.id)
+        .await
+        .expect("User is loaded")
+        .expect("User exists");
+    assert_eq!(stored.password_hash, NO_PASSWORD_HASH);
+    assert!(!fixture.factors(&user).await.contains(&AuthFactor::Password));
+    assert_eq!(
+        fixture.audited_actions(),
+        vec![("FACTOR_REMOVED".to_string(), "\"password\"".to_string())]
+    );
+}
+
+#[test]
+async fn test_last_second_factor_is_kept_when_policy_requires_mfa() {
+    let fixture = Fixture::new(MfaEnforcement::RequiredForAll);
+    let user = fixture.add_user().await;
+    fixture.enroll_totp(&user, Vec::new()).await;
+    let session_id = fixture.session_id(&user).await;
+
+    let result = fixture.remove(&user, session_id, AuthFactor::Totp).await;
+    assert!(matches!(result, Err(UserServiceError::LastSecondFactor)));
+    assert!(fixture.factors(&user).await.contains(&AuthFactor::Totp));
+    assert!(fixture.audited_actions().is_empty());
+}
+
+#[test]
+async fn test_last_second_factor_can_be_removed_when_mfa_is_optional() {
+    let fixture = Fixture::new(MfaEnforcement::Optional);
+    let user = fixture.add_user().await;
+    fixture.enroll_totp(&user, Vec::new()).await;
+    let session_id = fixture.session_id(&user).await;
+
+    fixture
+        .remove(&user, session_id, AuthFactor::Totp)
+        .await
+        .expect("TOTP is removed");
+
+    assert_eq!(fixture.factors(&user).await, vec![AuthFactor::Password]);
+    assert_eq!(
+        fixture.audited_actions(),
+        vec![("FACTOR_REMOVED".to_string(), "\"totp\"".to_string())]
+    );
+}
+
+#[test]
+async fn test_recovery_codes_removal_keeps_totp() {
+    let fixture = Fixture::new(MfaEnforcement::RequiredForAll);
+    let user = fixture.add_user().await;
+    fixture
+        .enroll_totp(&user, vec!["hashed-code".to_string()])
+        .await;
+    let session_id = fixture.session_id(&user).await;
+
+    // Recovery codes are not a second factor, so the MFA requirement does not apply
+    fixture
+        .remove(&user, session_id, AuthFactor::RecoveryCodes)
+        .await
+        .expect("Recovery codes are removed");
+
+    assert_eq!(
+        fixture.factors(&user).await,
+        vec![AuthFactor::Password, AuthFactor::Totp]
+    );
+}
+
+#[test]
+async fn test_removing_unenrolled_factor_fails() {
+    let fixture = Fixture::new(MfaEnforcement::Optional);
+    let user = fixture.add_user().await;
+    let session_id = fixture.session_id(&user).await;
+
+    for factor in [
+        AuthFactor::Totp,
+        AuthFactor::RecoveryCodes,
+        AuthFactor::WebAuthn {
+            credential_id: Uuid::new_v4(),
+        },
+    ] {
+        let result = fixture.remove(&user, session_id, factor).await;
+        assert!(matches!(result, Err(UserServiceError::FactorNotFound)));
+    }
+}
+
+#[test]
+async fn test_factor_added_is_audited() {
+    let fixture = Fixture::new(MfaEnforcement::Optional);
+    let user = fixture.add_user().await;
+    let session_id = fixture.session_id(&user).await;
+
+    fixture
+        .user_service
+        .record_factor_added(session_id, &AuthFactor::Totp)
+        .await
+        .expect("Factor is audited");
+
+    assert_eq!(
+        fixture.audited_actions(),
+        vec![("FACTOR_ADDED".to_string(), "\"totp\"".to_string())]
+    );
+}
@@ -0,0 +1,187 @@
+use std::sync::Arc;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::config::AuthConfig;
+use crate::models::tenant::TenantSecurityPolicy;
+use crate::models::user::{User, UserRepository, mock::MockUserRepository};
+use crate::security::{
+    ClientFingerprint, FingerprintConfig, FingerprintService, StoredFingerprint, hash_user_agent,
+};
+use crate::services::session::SessionService;
+use crate::services::user::{LoginResult, UserService};
+use crate::session::{Session, mock::MockSessionRepository};
+use crate::utils::jwt::JwtUtils;
+use crate::utils::password::hash_password;
+
+use super::mocks::{MockFingerprintRepository, MockTenantRepository};
+
+const EMAIL: &str = "device@example.com";
+const PASSWORD: &str = "Correct-Horse-Battery-42";
+const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:121.0) Gecko/20100101 Firefox/121.0";
+const IP_ADDRESS: &str = "198.51.100.7";
+
+struct Fixture {
+    user_service: UserService,
+    session_repo: Arc<MockSessionRepository>,
+    fingerprint_repo: Arc<MockFingerprintRepository>,
+    tenant_id: Uuid,
+}
+
+impl Fixture {
+    async fn new(config: FingerprintConfig) -> Self {
+        let user_repo = Arc::new(MockUserRepository::new());
+        let tenant_repo = Arc::new(MockTenantRepository::new());
+        let session_repo = Arc::new(MockSessionRepository::new());
+        let fingerprint_repo = Arc::new(MockFingerprintRepository::new());
+
+        let auth_config = Arc::new(AuthConfig::default());
+        let session_service = Arc::new(SessionService::new(
+            session_repo.clone(),
+            auth_config.clone(),
+        ));
+        let user_service = UserService::new(
+            user_repo.clone(),
+            Arc::new(JwtUtils::new(b"test-secret")),
+            session_service,
+            None,
+            auth_config,
+        )
+        .with_tenant_repository(tenant_repo.clone())
+        .with_fingerprint_service(Arc::new(FingerprintService::new(
+            fingerprint_repo.clone(),
+            config,
+        )));
+
+        let tenant_id = tenant_repo.insert_tenant_with_policy(&TenantSecurityPolicy::default());
+        let user = User::new(
+            EMAIL.to_string(),
+            hash_password(PASSWORD).expect("Failed to hash password"),
+        );
+        user_repo
+            .create(&user)
+            .await
+            .expect("Failed to create user");
+        tenant_repo.add_member(tenant_id, user.id, "MEMBER");
+
+        Self {
+            user_service,
+            session_repo,
+            fingerprint_repo,
+            tenant_id,
+        }
+    }
+
+    async fn login(&self, fingerprint: Option<ClientFingerprint>) -> LoginResult {
+        self.user_service
+            .login_with_tenant(
+                Some(self.tenant_id),
+                EMAIL,
+                PASSWORD,
+                None,
+                fingerprint
+                    .map(|fingerprint| fingerprint.into_device_fingerprint(Some(USER_AGENT))),
+                Some(IP_ADDRESS.to_string()),
+                Some(USER_AGENT.to_string()),
+                false,
+            )
+            .await
+            .expect("Login failed")
+    }
+
+    fn session(&self) -> Session {
+        let sessions = self.session_repo.sessions();
+        assert_eq!(sessions.len(), 1);
+        sessions[0].clone()
+    }
+
+    fn stored(&self) -> StoredFingerprint {
+        let fingerprints = self.fingerprint_repo.fingerprints();
+        assert_eq!(fingerprints.len(), 1);
+        fingerprints[0].clone()
+    }
+}
+
+fn client_fingerprint() -> ClientFingerprint {
+    ClientFingerprint {
+        platform: Some("Linux x86_64".to_string()),
+        screen_width: Some(2560),
+        screen_height: Some(1440),
+        timezone: Some("Europe/Berlin".to_string()),
+        language: Some("de-DE".to_string()),
+        ..ClientFingerprint::default()
+    }
+}
+
+#[test]
+async fn test_login_persists_fingerprint_against_new_session() {
+    let fixture = Fixture::new(FingerprintConfig::default()).await;
+
+    let result = fixture.login(Some(client_fingerprint())).await;
+
+    let session = fixture.session();
+    let device = session
+        .device_fingerprint
+        .as_ref()
+        .expect("Session carries the fingerprint");
+    assert_eq!(device.user_agent_hash, hash_user_agent(USER_AGENT));
+    assert_eq!(device.platform.as_deref(), Some("Linux x86_64"));
+    assert_eq!(device.screen_resolution.as_deref(), Some("2560x1440"));
+    assert_eq!(device.timezone.as_deref(), Some("Europe/Berlin"));
+    assert_eq!(device.language.as_deref(), Some("de-DE"));
+
+    let stored = fixture.stored();
+    assert_eq!(stored.session_id, Some(session.id));
+    assert_eq!(stored.user_id, result.user.id);
+    assert_eq!(stored.tenant_id, fixture.tenant_id);
+    assert_eq!(stored.last_ip.to_string(), IP_ADDRESS);
+    assert_eq!(stored.fingerprint.screen_resolution, Some((2560, 1440)));
+    // The raw user agent is not stored
+    assert_eq!(stored.fingerprint.user_agent, hash_user_agent(USER_AGENT));
+}
+
+#[test]
+async fn test_raw_user_agent_is_kept_when_hashing_is_disabled() {
+    let fixture = Fixture::new(FingerprintConfig {
+        hash_user_agent: false,
+        ..FingerprintConfig::default()
+    })
+    .await;
+
+    fixture.login(Some(ClientFingerprint::default())).await;
+
+    assert_eq!(fixture.stored().fingerprint.user_agent, USER_AGENT);
+    // The session fingerprint only ever holds the hash
+    assert_eq!(
+        fixture
+            .session()
+            .device_fingerprint
+            .expect("Session carries the fingerprint")
+            .user_agent_hash,
+        hash_user_agent(USER_AGENT)
+    );
+}
+
+#[test]
+async fn test_login_without_fingerprint_stores_nothing() {
+    let fixture = Fixture::new(FingerprintConfig::default()).await;
+
+    fixture.login(None).await;
+
+    assert!(fixture.session().device_fingerprint.is_none());
+    assert!(fixture.fingerprint_repo.fingerprints().is_empty());
+}
+
+#[test]
+async fn test_disabled_fingerprinting_stores_nothing() {
+    let fixture = Fixture::new(FingerprintConfig {
+        enabled: false,
+        ..FingerprintConfig::default()
+    })
+    .await;
+
+    fixture.login(Some(client_fingerprint())).await;
+
+    assert!(fixture.session().device_fingerprint.is_some());
+    assert!(fixture.fingerprint_repo.fingerprints().is_empty());
+}
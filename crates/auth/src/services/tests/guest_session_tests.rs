@@ -0,0 +1,264 @@
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::config::AuthConfig;
+use crate::services::session::{SessionService, SessionServiceError};
+use crate::session::mock::MockSessionRepository;
+use crate::session::types::{SessionInvalidationReason, SessionKind};
+use crate::session::{
+    IMPERSONATED_BY_METADATA_KEY, PERSISTENT_SESSION_METADATA_KEY, SessionRepository,
+};
+
+const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:124.0) Gecko/20100101 Firefox/124.0";
+
+struct Fixture {
+    session_service: SessionService,
+    session_repo: Arc<MockSessionRepository>,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let session_repo = Arc::new(MockSessionRepository::new());
+        let session_service =
+            SessionService::new(session_repo.clone(), Arc::new(AuthConfig::default()));
+        Self {
+            session_service,
+            session_repo,
+        }
+    }
+
+    async fn guest(&self) -> (Uuid, String) {
+        let (session, token) = self
+            .session_service
+            .create_guest_session(
+                Some("device-1".to_string()),
+                Some("192.0.2.7".to_string()),
+                Some(USER_AGENT.to_string()),
+            )
+            .await
+            .expect("Failed to create guest session");
+        (session.id, token)
+    }
+}
+
+#[test]
+async fn test_guest_session_is_bound_to_no_user() {
+    let fixture = Fixture::new();
+    let (guest_id, token) = fixture.guest().await;
+
+    let guest = fixture
+        .session_repo
+        .get_session(guest_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(guest.kind, SessionKind::Guest);
+    assert!(guest.user_id.is_nil());
+
+    // Guest tokens authenticate nobody
+    assert!(
+        fixture
+            .session_service
+            .validate_session(&token)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    let session = fixture
+        .session_service
+        .validate_session_or_guest(&token)
+        .await
+        .unwrap()
+        .expect("Guest session is valid");
+    assert_eq!(session.id, guest_id);
+}
+
+#[test]
+async fn test_upgrade_transfers_guest_state() {
+    let fixture = Fixture::new();
+    let (guest_id, guest_token) = fixture.guest().await;
+    fixture
+        .session_service
+        .merge_guest_metadata(
+            guest_id,
+            json!({ "cart": ["sku-1", "sku-2"], "consent": { "analytics": false } }),
+        )
+        .await
+        .unwrap();
+    let user_id = Uuid::new_v4();
+
+    let (session, token) = fixture
+        .session_service
+        .upgrade_guest(guest_id, user_id)
+        .await
+        .unwrap();
+
+    // A fresh session of the user, with a new ID and token
+    assert_ne!(session.id, guest_id);
+    assert_ne!(token, guest_token);
+    assert_eq!(session.kind, SessionKind::User);
+    assert_eq!(session.user_id, user_id);
+    assert_eq!(session.device_id.as_deref(), Some("device-1"));
+    assert_eq!(session.ip_address.as_deref(), Some("192.0.2.7"));
+    assert_eq!(session.user_agent.as_deref(), Some(USER_AGENT));
+    let metadata = session.metadata.clone().unwrap();
+    assert_eq!(metadata["cart"], json!(["sku-1", "sku-2"]));
+    assert_eq!(metadata["consent"], json!({ "analytics": false }));
+    assert_eq!(metadata[PERSISTENT_SESSION_METADATA_KEY], json!(false));
+
+    let validated = fixture
+        .session_service
+        .validate_session(&token)
+        .await
+        .unwrap()
+        .expect("Upgraded session authenticates the user");
+    assert_eq!(validated.id, session.id);
+
+    // The guest session is gone for good
+    let guest = fixture
+        .session_repo
+        .get_session(guest_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!guest.is_valid);
+    assert_eq!(
+        guest.invalidated_reason,
+        Some(SessionInvalidationReason::GuestUpgraded)
+    );
+    assert!(
+        fixture
+            .session_service
+            .validate_session_or_guest(&guest_token)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(matches!(
+        fixture
+            .session_service
+            .upgrade_guest(guest_id, user_id)
+            .await,
+        Err(SessionServiceError::NotGuestSession)
+    ));
+}
+
+#[test]
+async fn test_guest_cannot_set_reserved_metadata() {
+    let fixture = Fixture::new();
+    let (guest_id, _) = fixture.guest().await;
+
+    let result = fixture
+        .session_service
+        .merge_guest_metadata(
+            guest_id,
+            json!({ IMPERSONATED_BY_METADATA_KEY: Uuid::new_v4().to_string() }),
+        )
+        .await;
+    assert!(matches!(
+        result,
+        Err(SessionServiceError::InvalidGuestMetadata(_))
+    ));
+    let result = fixture
+        .session_service
+        .merge_guest_metadata(guest_id, json!(["not", "an", "object"]))
+        .await;
+    assert!(matches!(
+        result,
+        Err(SessionServiceError::InvalidGuestMetadata(_))
+    ));
+
+    let (session, _) = fixture
+        .session_service
+        .upgrade_guest(guest_id, Uuid::new_v4())
+        .await
+        .unwrap();
+    assert_eq!(session.impersonated_by(), None);
+}
+
+#[test]
+async fn test_only_guest_sessions_are_upgraded() {
+    let fixture = Fixture::new();
+    let user_id = Uuid::new_v4();
+    let (session, _) = fixture
+        .session_service
+        .create_session(user_id, None, None, None, None, None, false)
+        .await
+        .unwrap();
+
+    let result = fixture
+        .session_service
+        .upgrade_guest(session.id, Uuid::new_v4())
+        .await;
+    assert!(matches!(result, Err(SessionServiceError::NotGuestSession)));
+    let result = fixture
+        .session_service
+        .merge_guest_metadata(session.id, json!({ "cart": [] }))
+        .await;
+    assert!(matches!(result, Err(SessionServiceError::NotGuestSession)));
+    assert!(
+        fixture
+            .session_repo
+            .get_session(session.id)
+            .await
+            .unwrap()
+            .unwrap()
+            .is_valid
+    );
+
+    // Expired guest sessions carry no state over
+    let (guest_id, _) = fixture.guest().await;
+    fixture
+        .session_repo
+        .sessions()
+        .iter_mut()
+        .filter(|s| s.id == guest_id)
+        .for_each(|s| s.expires_at = OffsetDateTime::now_utc() - Duration::from_secs(1));
+    let result = fixture
+        .session_service
+        .upgrade_guest(guest_id, user_id)
+        .await;
+    assert!(matches!(result, Err(SessionServiceError::NotGuestSession)));
+}
+
+#[test]
+async fn test_cleanup_purges_abandoned_guest_sessions() {
+    let fixture = Fixture::new();
+    let (abandoned_id, _) = fixture.guest().await;
+    let (active_id, _) = fixture.guest().await;
+    let (user_session, _) = fixture
+        .session_service
+        .create_session(Uuid::new_v4(), None, None, None, None, None, false)
+        .await
+        .unwrap();
+
+    // Idle for two days, far beyond the guest retention but not the session lifetime
+    let two_days_ago = OffsetDateTime::now_utc() - Duration::from_secs(2 * 24 * 60 * 60);
+    for session in fixture.session_repo.sessions().iter_mut() {
+        session.expires_at = OffsetDateTime::now_utc() + Duration::from_secs(7 * 24 * 60 * 60);
+        session.last_activity_at = two_days_ago;
+        if session.id == active_id {
+            session.last_activity_at = OffsetDateTime::now_utc();
+        }
+    }
+
+    let deleted = fixture
+        .session_service
+        .cleanup_expired_sessions()
+        .await
+        .unwrap();
+    assert_eq!(deleted, 1);
+    let remaining: Vec<Uuid> = fixture
+        .session_repo
+        .sessions()
+        .iter()
+        .map(|s| s.id)
+        .collect();
+    assert!(!remaining.contains(&abandoned_id));
+    assert!(remaining.contains(&active_id));
+    assert!(remaining.contains(&user_session.id));
+}
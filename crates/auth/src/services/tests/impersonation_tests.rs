@@ -0,0 +1,268 @@
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::config::{AuthConfig, ImpersonationConfig};
+use crate::models::tenant::TenantSecurityPolicy;
+use crate::services::session::{SessionService, SessionServiceError};
+use crate::session::mock::MockSessionRepository;
+use crate::session::types::ImpersonationAction;
+use crate::session::{SessionError, SessionRepository};
+
+use super::mocks::MockTenantRepository;
+
+const REASON: &str = "Support ticket 1234";
+
+struct Fixture {
+    session_service: SessionService,
+    session_repo: Arc<MockSessionRepository>,
+    tenant_repo: Arc<MockTenantRepository>,
+    tenant_id: Uuid,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        Self::with_config(AuthConfig::default())
+    }
+
+    fn with_config(config: AuthConfig) -> Self {
+        let session_repo = Arc::new(MockSessionRepository::new());
+        let tenant_repo = Arc::new(MockTenantRepository::new());
+        let session_service = SessionService::new(session_repo.clone(), Arc::new(config))
+            .with_tenant_repository(tenant_repo.clone());
+        let tenant_id = tenant_repo.insert_tenant_with_policy(&TenantSecurityPolicy::default());
+
+        Self {
+            session_service,
+            session_repo,
+            tenant_repo,
+            tenant_id,
+        }
+    }
+
+    fn member(&self, role: &str) -> Uuid {
+        let user_id = Uuid::new_v4();
+        self.tenant_repo.add_member(self.tenant_id, user_id, role);
+        user_id
+    }
+
+    async fn impersonate(
+        &self,
+        admin_user_id: Uuid,
+        target_user_id: Uuid,
+        ttl: Duration,
+    ) -> Result<Uuid, SessionServiceError> {
+        self.session_service
+            .create_impersonation_session(
+                admin_user_id,
+                target_user_id,
+                self.tenant_id,
+                REASON,
+                ttl,
+            )
+            .await
+            .map(|(session, _)| session.id)
+    }
+
+    fn audit(&self, action: ImpersonationAction) -> Vec<Uuid> {
+        self.session_repo
+            .audit_entries()
+            .into_iter()
+            .filter(|entry| entry.action == action.as_str())
+            .map(|entry| entry.user_id)
+            .collect()
+    }
+}
+
+#[test]
+async fn test_admin_starts_impersonation_with_capped_ttl() {
+    let fixture = Fixture::new();
+    let admin_id = fixture.member("ADMIN");
+    let member_id = fixture.member("MEMBER");
+
+    let session_id = fixture
+        .impersonate(admin_id, member_id, Duration::from_secs(24 * 60 * 60))
+        .await
+        .expect("Admin can impersonate a member");
+
+    let session = fixture
+        .session_repo
+        .get_session(session_id)
+        .await
+        .expect("Failed to load session")
+        .expect("Session is stored");
+    assert_eq!(session.user_id, member_id);
+    assert_eq!(session.impersonated_by(), Some(admin_id));
+    assert_eq!(session.active_tenant_id(), Some(fixture.tenant_id));
+    let max_expiry = OffsetDateTime::now_utc() + Duration::from_secs(60 * 60);
+    assert!(session.expires_at <= max_expiry);
+
+    assert_eq!(
+        fixture.audit(ImpersonationAction::Started),
+        vec![member_id, admin_id]
+    );
+    let entry = &fixture.session_repo.audit_entries()[0];
+    assert_eq!(entry.details["reason"], REASON);
+    assert_eq!(entry.details["impersonated_by"], admin_id.to_string());
+}
+
+#[test]
+async fn test_only_admins_impersonate_and_only_non_admins_by_default() {
+    let fixture = Fixture::new();
+    let admin_id = fixture.member("ADMIN");
+    let other_admin_id = fixture.member("admin");
+    let member_id = fixture.member("MEMBER");
+    let ttl = Duration::from_secs(600);
+
+    let result = fixture.impersonate(member_id, admin_id, ttl).await;
+    assert!(matches!(
+        result,
+        Err(SessionServiceError::ImpersonationNotAllowed(_))
+    ));
+
+    let result = fixture.impersonate(admin_id, other_admin_id, ttl).await;
+    assert!(matches!(
+        result,
+        Err(SessionServiceError::ImpersonationNotAllowed(_))
+    ));
+
+    let result = fixture.impersonate(admin_id, Uuid::new_v4(), ttl).await;
+    assert!(matches!(result, Err(SessionServiceError::NotTenantMember)));
+
+    let result = fixture
+        .session_service
+        .create_impersonation_session(admin_id, member_id, fixture.tenant_id, "  ", ttl)
+        .await;
+    assert!(matches!(
+        result,
+        Err(SessionServiceError::ImpersonationNotAllowed(_))
+    ));
+
+    assert!(fixture.session_repo.sessions().is_empty());
+    assert!(fixture.session_repo.audit_entries().is_empty());
+}
+
+#[test]
+async fn test_admin_targets_allowed_by_config() {
+    let config = AuthConfig {
+        impersonation: ImpersonationConfig {
+            allow_admin_targets: true,
+            ..ImpersonationConfig::default()
+        },
+        ..AuthConfig::default()
+    };
+    let fixture = Fixture::with_config(config);
+    let admin_id = fixture.member("ADMIN");
+    let other_admin_id = fixture.member("ADMIN");
+
+    fixture
+        .impersonate(admin_id, other_admin_id, Duration::from_secs(600))
+        .await
+        .expect("Admin targets are allowed by configuration");
+}
+
+#[test]
+async fn test_unaudited_impersonation_session_is_invalidated() {
+    let fixture = Fixture::new();
+    let admin_id = fixture.member("ADMIN");
+    let member_id = fixture.member("MEMBER");
+    fixture.session_repo.fail_on("record_impersonation_event");
+
+    let result = fixture
+        .impersonate(admin_id, member_id, Duration::from_secs(600))
+        .await;
+    assert!(matches!(result, Err(SessionServiceError::Repository(_))));
+
+    let sessions = fixture.session_repo.sessions();
+    assert_eq!(sessions.len(), 1);
+    assert!(!sessions[0].is_valid);
+}
+
+#[test]
+async fn test_impersonating_admin_ends_session_early() {
+    let fixture = Fixture::new();
+    let admin_id = fixture.member("ADMIN");
+    let other_admin_id = fixture.member("ADMIN");
+    let member_id = fixture.member("MEMBER");
+    let session_id = fixture
+        .impersonate(admin_id, member_id, Duration::from_secs(600))
+        .await
+        .expect("Failed to start impersonation");
+
+    let result = fixture
+        .session_service
+        .end_impersonation(session_id, other_admin_id)
+        .await;
+    assert!(matches!(
+        result,
+        Err(SessionServiceError::ImpersonationNotAllowed(_))
+    ));
+
+    fixture
+        .session_service
+        .end_impersonation(session_id, admin_id)
+        .await
+        .expect("Impersonating admin can end the session");
+    assert!(!fixture.session_repo.sessions()[0].is_valid);
+    assert_eq!(
+        fixture.audit(ImpersonationAction::Ended),
+        vec![member_id, admin_id]
+    );
+
+    // Ended sessions and regular sessions are not impersonations that can be ended
+    let result = fixture
+        .session_service
+        .end_impersonation(session_id, admin_id)
+        .await;
+    assert!(matches!(
+        result,
+        Err(SessionServiceError::Repository(SessionError::NotFound))
+    ));
+    let (session, _) = fixture
+        .session_service
+        .create_session(member_id, None, None, None, None, None, false)
+        .await
+        .expect("Failed to create session");
+    let result = fixture
+        .session_service
+        .end_impersonation(session.id, admin_id)
+        .await;
+    assert!(matches!(
+        result,
+        Err(SessionServiceError::Repository(SessionError::NotFound))
+    ));
+}
+
+#[test]
+async fn test_writes_during_impersonation_carry_the_impersonator() {
+    let fixture = Fixture::new();
+    let admin_id = fixture.member("ADMIN");
+    let member_id = fixture.member("MEMBER");
+    let other_tenant = fixture
+        .tenant_repo
+        .insert_tenant_with_policy(&TenantSecurityPolicy::default());
+    fixture
+        .tenant_repo
+        .add_member(other_tenant, member_id, "MEMBER");
+    let session_id = fixture
+        .impersonate(admin_id, member_id, Duration::from_secs(600))
+        .await
+        .expect("Failed to start impersonation");
+
+    fixture
+        .session_service
+        .switch_tenant(session_id, other_tenant)
+        .await
+        .expect("Failed to switch tenant");
+
+    let entry = fixture
+        .session_repo
+        .audit_entries()
+        .into_iter()
+        .find(|entry| entry.action == "TENANT_SWITCHED")
+        .expect("Tenant switch is audited");
+    assert_eq!(entry.user_id, member_id);
+    assert_eq!(entry.details["impersonated_by"], admin_id.to_string());
+}
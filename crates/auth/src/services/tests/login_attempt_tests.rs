@@ -0,0 +1,200 @@
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+use tokio::test;
+use uuid::Uuid;
+
+use crate::config::AuthConfig;
+use crate::models::login_attempt::{LoginAttemptRecord, LoginOutcome};
+use crate::models::tenant::TenantSecurityPolicy;
+use crate::models::user::{User, UserRepository, mock::MockUserRepository};
+use crate::repository::login_attempt_repository::mock::MockLoginAttemptRepository;
+use crate::security::types::{GeoLocation, RiskLevel};
+use crate::services::login_attempts::{LoginAttemptContext, LoginAttemptRecorder};
+use crate::services::session::SessionService;
+use crate::services::user::{UserService, UserServiceError};
+use crate::session::mock::MockSessionRepository;
+use crate::utils::clock::TestClock;
+use crate::utils::jwt::JwtUtils;
+use crate::utils::password::hash_password;
+
+use super::mocks::MockTenantRepository;
+
+const EMAIL: &str = "attempts@example.com";
+const PASSWORD: &str = "Correct-Horse-Battery-42";
+const USERNAME_KEY: &[u8] = b"username-key";
+
+/// Wait until the background writes of the recorder have stored `count` attempts
+async fn stored_attempts(
+    repository: &MockLoginAttemptRepository,
+    count: usize,
+) -> Vec<LoginAttemptRecord> {
+    for _ in 0..100 {
+        let attempts = repository.attempts();
+        if attempts.len() >= count {
+            return attempts;
+        }
+        tokio::task::yield_now().await;
+    }
+    panic!("Login attempts were not recorded");
+}
+
+#[test]
+async fn test_recorded_attempt_hashes_username() {
+    let repository = Arc::new(MockLoginAttemptRepository::new());
+    let recorder = LoginAttemptRecorder::new(repository.clone(), USERNAME_KEY);
+    let tenant_id = Uuid::new_v4();
+
+    recorder
+        .record(
+            LoginAttemptContext {
+                tenant_id: Some(tenant_id),
+                username: " Attempts@Example.com".to_string(),
+                ip_address: Some("198.51.100.7".to_string()),
+                user_agent: Some("curl/8.5".to_string()),
+                geolocation: Some(GeoLocation {
+                    country_code: "DE".to_string(),
+                    city: Some("Berlin".to_string()),
+                    latitude: None,
+                    longitude: None,
+                }),
+                risk_level: Some(RiskLevel::Medium),
+                ..LoginAttemptContext::default()
+            },
+            LoginOutcome::InvalidCredentials,
+        )
+        .await
+        .expect("Attempt is recorded");
+
+    let attempts = repository.attempts();
+    assert_eq!(attempts.len(), 1);
+    let attempt = &attempts[0];
+    assert_eq!(attempt.tenant_id, Some(tenant_id));
+    assert_eq!(attempt.username_hash, recorder.hash_username(EMAIL));
+    assert!(!attempt.username_hash.contains("example"));
+    assert_eq!(attempt.country_code.as_deref(), Some("DE"));
+    assert_eq!(attempt.city.as_deref(), Some("Berlin"));
+    assert_eq!(attempt.risk_level, Some(RiskLevel::Medium));
+    assert_eq!(attempt.outcome, LoginOutcome::InvalidCredentials);
+
+    let other_key = LoginAttemptRecorder::new(repository, b"another-key");
+    assert_ne!(other_key.hash_username(EMAIL), attempt.username_hash);
+}
+
+#[test]
+async fn test_failure_rate_of_tenant_within_window() {
+    let repository = Arc::new(MockLoginAttemptRepository::new());
+    let clock = TestClock::new(OffsetDateTime::now_utc());
+    let recorder =
+        LoginAttemptRecorder::with_clock(repository.clone(), USERNAME_KEY, clock.clone());
+    let tenant_id = Uuid::new_v4();
+    let attempt = |tenant_id| LoginAttemptContext {
+        tenant_id: Some(tenant_id),
+        username: EMAIL.to_string(),
+        ..LoginAttemptContext::default()
+    };
+
+    // Outside of the window
+    recorder
+        .record(attempt(tenant_id), LoginOutcome::InvalidCredentials)
+        .await
+        .unwrap();
+    clock.advance(Duration::hours(2));
+
+    for outcome in [
+        LoginOutcome::Succeeded,
+        LoginOutcome::InvalidCredentials,
+        LoginOutcome::InvalidCredentials,
+        LoginOutcome::Rejected,
+    ] {
+        recorder.record(attempt(tenant_id), outcome).await.unwrap();
+    }
+    // Another tenant
+    recorder
+        .record(attempt(Uuid::new_v4()), LoginOutcome::InvalidCredentials)
+        .await
+        .unwrap();
+
+    let stats = recorder
+        .stats(tenant_id, Duration::hours(1))
+        .await
+        .expect("Stats are computed");
+    assert_eq!(stats.total, 4);
+    assert_eq!(stats.succeeded, 1);
+    assert_eq!(stats.failed, 3);
+    assert_eq!(stats.failure_rate(), Some(0.75));
+
+    let empty = recorder
+        .stats(Uuid::new_v4(), Duration::hours(1))
+        .await
+        .expect("Stats are computed");
+    assert_eq!(empty.failure_rate(), None);
+}
+
+#[test]
+async fn test_password_logins_are_recorded() {
+    let user_repo = Arc::new(MockUserRepository::new());
+    let tenant_repo = Arc::new(MockTenantRepository::new());
+    let attempt_repo = Arc::new(MockLoginAttemptRepository::new());
+    let config = Arc::new(AuthConfig::default());
+    let recorder = Arc::new(LoginAttemptRecorder::new(
+        attempt_repo.clone(),
+        USERNAME_KEY,
+    ));
+    let user_service = UserService::new(
+        user_repo.clone(),
+        Arc::new(JwtUtils::new(b"test-secret")),
+        Arc::new(SessionService::new(
+            Arc::new(MockSessionRepository::new()),
+            config.clone(),
+        )),
+        None,
+        config,
+    )
+    .with_tenant_repository(tenant_repo.clone())
+    .with_login_attempt_recorder(recorder.clone());
+
+    let tenant_id = tenant_repo.insert_tenant_with_policy(&TenantSecurityPolicy::default());
+    let user = User::new(
+        EMAIL.to_string(),
+        hash_password(PASSWORD).expect("Password hashes"),
+    );
+    user_repo.create(&user).await.expect("User is created");
+    tenant_repo.add_member(tenant_id, user.id, "MEMBER");
+
+    let result = user_service
+        .login_with_tenant(
+            Some(tenant_id),
+            EMAIL,
+            "wrong password",
+            None,
+            None,
+            Some("198.51.100.7".to_string()),
+            None,
+            false,
+        )
+        .await;
+    assert!(matches!(result, Err(UserServiceError::InvalidCredentials)));
+    user_service
+        .login(EMAIL, PASSWORD, None, None, None, None, false)
+        .await
+        .expect("Login succeeds");
+
+    let attempts = stored_attempts(&attempt_repo, 2).await;
+    let outcomes: Vec<_> = attempts.iter().map(|attempt| attempt.outcome).collect();
+    assert_eq!(
+        outcomes,
+        vec![LoginOutcome::InvalidCredentials, LoginOutcome::Succeeded]
+    );
+    // The successful login resolved the tenant from the only membership
+    assert!(
+        attempts
+            .iter()
+            .all(|attempt| attempt.tenant_id == Some(tenant_id))
+    );
+    assert_eq!(attempts[0].ip_address.as_deref(), Some("198.51.100.7"));
+    assert!(
+        attempts
+            .iter()
+            .all(|attempt| attempt.username_hash == recorder.hash_username(EMAIL))
+    );
+}
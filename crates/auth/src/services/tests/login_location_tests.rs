@@ -0,0 +1,372 @@
+use std::sync::Arc;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::config::AuthConfig;
+use crate::models::tenant::{
+    LoginLocationViolation, TenantRepository, TenantSecurityPolicy, UnknownCountryAction,
+};
+use crate::models::user::{User, UserRepository, mock::MockUserRepository};
+use crate::security::geoip::StaticGeoIpResolver;
+use crate::services::session::SessionService;
+use crate::services::tenant::{TenantService, TenantServiceError};
+use crate::services::user::{LoginResult, UserService, UserServiceError};
+use crate::session::mock::MockSessionRepository;
+use crate::session::types::SessionInvalidationReason;
+use crate::utils::jwt::JwtUtils;
+use crate::utils::password::hash_password;
+
+use super::mocks::MockTenantRepository;
+
+const PASSWORD: &str = "Correct-Horse-Battery-42";
+
+struct Fixture {
+    user_service: Arc<UserService>,
+    session_service: Arc<SessionService>,
+    user_repo: Arc<MockUserRepository>,
+    tenant_repo: Arc<MockTenantRepository>,
+    session_repo: Arc<MockSessionRepository>,
+    tenant_id: Uuid,
+}
+
+impl Fixture {
+    fn new(policy: TenantSecurityPolicy) -> Self {
+        Self::with_resolver(policy, None)
+    }
+
+    fn with_resolver(policy: TenantSecurityPolicy, resolver: Option<StaticGeoIpResolver>) -> Self {
+        let user_repo = Arc::new(MockUserRepository::new());
+        let tenant_repo = Arc::new(MockTenantRepository::new());
+        let session_repo = Arc::new(MockSessionRepository::new());
+        let tenant_id = tenant_repo.insert_tenant_with_policy(&policy);
+
+        let config = Arc::new(AuthConfig::default());
+        let mut session_service = SessionService::new(session_repo.clone(), config.clone())
+            .with_tenant_repository(tenant_repo.clone());
+        if let Some(resolver) = resolver {
+            session_service = session_service.with_geoip_resolver(Arc::new(resolver));
+        }
+        let session_service = Arc::new(session_service);
+        let user_service = Arc::new(
+            UserService::new(
+                user_repo.clone(),
+                Arc::new(JwtUtils::new(b"test-secret")),
+                session_service.clone(),
+                None,
+                config,
+            )
+            .with_tenant_repository(tenant_repo.clone()),
+        );
+
+        Self {
+            user_service,
+            session_service,
+            user_repo,
+            tenant_repo,
+            session_repo,
+            tenant_id,
+        }
+    }
+
+    async fn add_user(&self, email: &str) -> User {
+        let user = User::new(email.to_string(), hash_password(PASSWORD).unwrap());
+        self.user_repo.create(&user).await.unwrap();
+        self.tenant_repo
+            .add_member(self.tenant_id, user.id, "MEMBER");
+        user
+    }
+
+    async fn login_from(&self, email: &str, ip: &str) -> Result<LoginResult, UserServiceError> {
+        self.user_service
+            .login_with_tenant(
+                Some(self.tenant_id),
+                email,
+                PASSWORD,
+                None,
+                None,
+                Some(ip.to_string()),
+                Some("test-agent".to_string()),
+                false,
+            )
+            .await
+    }
+
+    fn tenant_service(&self) -> TenantService {
+        TenantService::new(
+            self.tenant_repo.clone(),
+            self.user_repo.clone(),
+            self.user_service.clone(),
+        )
+    }
+}
+
+fn ip_ranges(ranges: &[&str]) -> TenantSecurityPolicy {
+    TenantSecurityPolicy {
+        allowed_ip_ranges: ranges.iter().map(|r| r.parse().unwrap()).collect(),
+        ..TenantSecurityPolicy::default()
+    }
+}
+
+fn countries(countries: &[&str], unknown_country: UnknownCountryAction) -> TenantSecurityPolicy {
+    TenantSecurityPolicy {
+        allowed_countries: countries.iter().map(|c| c.to_string()).collect(),
+        unknown_country,
+        ..TenantSecurityPolicy::default()
+    }
+}
+
+fn resolver() -> StaticGeoIpResolver {
+    StaticGeoIpResolver::new()
+        .with_network("192.0.2.0/24".parse().unwrap(), "DE")
+        .with_network("203.0.113.0/24".parse().unwrap(), "US")
+}
+
+#[test]
+async fn test_ipv6_range_allows_and_blocks_logins() {
+    let fixture = Fixture::new(ip_ranges(&["2001:db8:1::/48", "10.0.0.0/8"]));
+    let user = fixture.add_user("user@example.com").await;
+
+    assert!(
+        fixture
+            .login_from("user@example.com", "2001:db8:1:ff::42")
+            .await
+            .is_ok()
+    );
+    assert!(
+        fixture
+            .login_from("user@example.com", "::ffff:10.1.2.3")
+            .await
+            .is_ok()
+    );
+
+    let result = fixture
+        .login_from("user@example.com", "2001:db8:2::1")
+        .await;
+    assert!(matches!(
+        result,
+        Err(UserServiceError::LoginLocationBlocked(
+            LoginLocationViolation::IpNotAllowed
+        ))
+    ));
+    assert_eq!(fixture.session_repo.sessions().len(), 2);
+
+    let events = fixture.user_repo.security_events(user.id);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].0, "LOGIN_LOCATION_BLOCKED");
+    assert_eq!(events[0].1["reason"], "ip_not_allowed");
+}
+
+#[test]
+async fn test_blocked_login_maps_to_location_error_kind() {
+    let fixture = Fixture::new(ip_ranges(&["10.0.0.0/8"]));
+    fixture.add_user("user@example.com").await;
+
+    let err = fixture
+        .login_from("user@example.com", "192.0.2.1")
+        .await
+        .err()
+        .unwrap();
+    let err: acci_core::Error = err.into();
+    assert_eq!(
+        err.kind(),
+        acci_core::error::ErrorKind::LoginLocationBlocked
+    );
+}
+
+#[test]
+async fn test_country_restriction_uses_resolver() {
+    let fixture = Fixture::with_resolver(
+        countries(&["de"], UnknownCountryAction::Allow),
+        Some(resolver()),
+    );
+    fixture.add_user("user@example.com").await;
+
+    assert!(
+        fixture
+            .login_from("user@example.com", "192.0.2.10")
+            .await
+            .is_ok()
+    );
+    let result = fixture.login_from("user@example.com", "203.0.113.10").await;
+    assert!(matches!(
+        result,
+        Err(UserServiceError::LoginLocationBlocked(
+            LoginLocationViolation::CountryNotAllowed
+        ))
+    ));
+}
+
+#[test]
+async fn test_unknown_country_fallback_allow() {
+    let fixture = Fixture::with_resolver(
+        countries(&["DE"], UnknownCountryAction::Allow),
+        Some(resolver()),
+    );
+    fixture.add_user("user@example.com").await;
+
+    // Neither addresses missing from the resolver nor a missing resolver block logins
+    assert!(
+        fixture
+            .login_from("user@example.com", "198.51.100.1")
+            .await
+            .is_ok()
+    );
+    let without_resolver = Fixture::new(countries(&["DE"], UnknownCountryAction::Allow));
+    without_resolver.add_user("user@example.com").await;
+    assert!(
+        without_resolver
+            .login_from("user@example.com", "192.0.2.10")
+            .await
+            .is_ok()
+    );
+}
+
+#[test]
+async fn test_unknown_country_fallback_deny() {
+    let fixture = Fixture::with_resolver(
+        countries(&["DE"], UnknownCountryAction::Deny),
+        Some(resolver()),
+    );
+    let user = fixture.add_user("user@example.com").await;
+
+    let result = fixture.login_from("user@example.com", "198.51.100.1").await;
+    assert!(matches!(
+        result,
+        Err(UserServiceError::LoginLocationBlocked(
+            LoginLocationViolation::CountryUnknown
+        ))
+    ));
+    assert!(fixture.session_repo.sessions().is_empty());
+    assert_eq!(
+        fixture.user_repo.security_events(user.id)[0].1["reason"],
+        "country_unknown"
+    );
+
+    let without_resolver = Fixture::new(countries(&["DE"], UnknownCountryAction::Deny));
+    without_resolver.add_user("user@example.com").await;
+    assert!(matches!(
+        without_resolver
+            .login_from("user@example.com", "192.0.2.10")
+            .await,
+        Err(UserServiceError::LoginLocationBlocked(
+            LoginLocationViolation::CountryUnknown
+        ))
+    ));
+}
+
+#[test]
+async fn test_sweep_invalidates_sessions_outside_new_ranges() {
+    let fixture = Fixture::new(TenantSecurityPolicy::default());
+    fixture.add_user("user@example.com").await;
+    fixture
+        .login_from("user@example.com", "10.0.0.1")
+        .await
+        .unwrap();
+    fixture
+        .login_from("user@example.com", "2001:db8::1")
+        .await
+        .unwrap();
+
+    // Nothing to sweep while the tenant does not restrict logins
+    assert_eq!(
+        fixture
+            .session_service
+            .sweep_login_location_violations(fixture.tenant_id)
+            .await
+            .unwrap(),
+        0
+    );
+
+    let policy = fixture
+        .tenant_service()
+        .update_login_restrictions(
+            &fixture.tenant_id,
+            &["2001:db8::/32".to_string()],
+            &[],
+            UnknownCountryAction::Allow,
+        )
+        .await
+        .unwrap();
+    assert!(policy.restricts_login_location());
+
+    let invalidated = fixture
+        .session_service
+        .sweep_login_location_violations(fixture.tenant_id)
+        .await
+        .unwrap();
+    assert_eq!(invalidated, 1);
+
+    let sessions = fixture.session_repo.sessions();
+    let swept = sessions
+        .iter()
+        .find(|s| s.ip_address.as_deref() == Some("10.0.0.1"))
+        .unwrap();
+    assert!(!swept.is_valid);
+    assert_eq!(
+        swept.invalidated_reason,
+        Some(SessionInvalidationReason::SecurityPolicyChange)
+    );
+    assert!(
+        sessions
+            .iter()
+            .any(|s| s.ip_address.as_deref() == Some("2001:db8::1") && s.is_valid)
+    );
+}
+
+#[test]
+async fn test_update_login_restrictions_validates_input() {
+    let fixture = Fixture::new(TenantSecurityPolicy::default());
+    let tenant_service = fixture.tenant_service();
+
+    for range in ["10.0.0.1/8", "2001:db8::1/32", "not-a-range", "10.0.0.0/33"] {
+        let result = tenant_service
+            .update_login_restrictions(
+                &fixture.tenant_id,
+                &[range.to_string()],
+                &[],
+                UnknownCountryAction::Allow,
+            )
+            .await;
+        assert!(
+            matches!(result, Err(TenantServiceError::InvalidInput(_))),
+            "{range} must be rejected"
+        );
+    }
+    let result = tenant_service
+        .update_login_restrictions(
+            &fixture.tenant_id,
+            &[],
+            &["DEU".to_string()],
+            UnknownCountryAction::Allow,
+        )
+        .await;
+    assert!(matches!(result, Err(TenantServiceError::InvalidInput(_))));
+
+    let policy = tenant_service
+        .update_login_restrictions(
+            &fixture.tenant_id,
+            &[" 192.0.2.1 ".to_string(), "2001:db8::/32".to_string()],
+            &["de".to_string(), "DE".to_string(), "at".to_string()],
+            UnknownCountryAction::Deny,
+        )
+        .await
+        .unwrap();
+    assert_eq!(policy.allowed_countries, vec!["DE", "AT"]);
+
+    let stored = fixture
+        .tenant_repo
+        .find_tenant_by_id(fixture.tenant_id)
+        .await
+        .unwrap()
+        .unwrap()
+        .security_policy();
+    assert_eq!(stored, policy);
+    assert_eq!(
+        stored
+            .allowed_ip_ranges
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+        vec!["192.0.2.1/32", "2001:db8::/32"]
+    );
+}
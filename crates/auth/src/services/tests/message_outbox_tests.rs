@@ -0,0 +1,334 @@
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use time::Duration;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::models::{OutboxStatus, VerificationType};
+use crate::repository::MessageOutboxRepository;
+use crate::services::DeliveryMode;
+use crate::services::email_provider::create_email_provider;
+use crate::services::message_outbox::{DispatchReport, MessageOutbox, OutboxConfig};
+use crate::services::message_provider::{EmailProviderConfig, Message, MessageProvider};
+use crate::utils::clock::{Clock, TestClock};
+use crate::utils::secret::Secret;
+use acci_core::error::{Error, Result};
+use acci_core::http_client::{HttpClientFactory, OutboundHttpConfig};
+
+use super::mocks::MockMessageOutboxRepository;
+
+// Provider that fails a configurable number of times before succeeding
+struct ScriptedProvider {
+    verification_type: VerificationType,
+    failures_remaining: Mutex<u32>,
+    delivered: Mutex<Vec<String>>,
+}
+
+impl ScriptedProvider {
+    fn new(verification_type: VerificationType, failures: u32) -> Self {
+        Self {
+            verification_type,
+            failures_remaining: Mutex::new(failures),
+            delivered: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn delivered(&self) -> Vec<String> {
+        self.delivered.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl MessageProvider for ScriptedProvider {
+    fn verification_type(&self) -> VerificationType {
+        self.verification_type
+    }
+
+    async fn send_message(&self, message: Message) -> Result<String> {
+        let mut failures = self.failures_remaining.lock().unwrap();
+        if *failures > 0 {
+            *failures -= 1;
+            return Err(Error::Other(anyhow::anyhow!("provider unavailable")));
+        }
+
+        self.delivered.lock().unwrap().push(message.body);
+        Ok("provider-message-id".to_string())
+    }
+}
+
+fn test_config() -> OutboxConfig {
+    OutboxConfig {
+        max_attempts: 3,
+        base_backoff_secs: 30,
+        max_backoff_secs: 300,
+        lease_secs: 60,
+        email_concurrency: 1,
+        sms_concurrency: 1,
+        ..OutboxConfig::default()
+    }
+}
+
+fn test_message(message_type: VerificationType, body: &str) -> Message {
+    Message {
+        tenant_id: Uuid::new_v4(),
+        user_id: Uuid::new_v4(),
+        recipient: "user@example.com".to_string(),
+        subject: Some("Verification".to_string()),
+        body: body.to_string(),
+        message_type,
+    }
+}
+
+fn create_test_outbox(
+    clock: TestClock,
+) -> (Arc<MockMessageOutboxRepository>, MessageOutbox<TestClock>) {
+    let repo = Arc::new(MockMessageOutboxRepository::new());
+    let outbox = MessageOutbox::with_clock(repo.clone(), test_config(), clock);
+    (repo, outbox)
+}
+
+#[test]
+async fn test_queued_provider_enqueues_instead_of_sending() {
+    let (repo, outbox) = create_test_outbox(TestClock::default());
+    let provider = Arc::new(ScriptedProvider::new(VerificationType::Email, 0));
+    let queued = outbox.wrap(provider.clone());
+
+    let id = queued
+        .send_message(test_message(VerificationType::Email, "hello"))
+        .await
+        .unwrap();
+
+    assert_eq!(queued.verification_type(), VerificationType::Email);
+    assert!(provider.delivered().is_empty());
+
+    let messages = repo.messages();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].id.to_string(), id);
+    assert_eq!(messages[0].status, OutboxStatus::Pending);
+}
+
+#[test]
+async fn test_dispatch_preserves_enqueue_order() {
+    let (repo, outbox) = create_test_outbox(TestClock::default());
+    let provider = Arc::new(ScriptedProvider::new(VerificationType::Email, 0));
+    let queued = outbox.wrap(provider.clone());
+
+    for body in ["first", "second", "third"] {
+        queued
+            .send_message(test_message(VerificationType::Email, body))
+            .await
+            .unwrap();
+    }
+
+    let report = outbox.dispatcher().dispatch_once().await.unwrap();
+
+    assert_eq!(
+        report,
+        DispatchReport {
+            sent: 3,
+            ..DispatchReport::default()
+        }
+    );
+    assert_eq!(provider.delivered(), vec!["first", "second", "third"]);
+    assert!(
+        repo.messages()
+            .iter()
+            .all(|m| m.status == OutboxStatus::Sent
+                && m.provider_message_id.as_deref() == Some("provider-message-id"))
+    );
+}
+
+#[test]
+async fn test_dispatch_only_delivers_to_matching_provider() {
+    let (_repo, outbox) = create_test_outbox(TestClock::default());
+    let email = Arc::new(ScriptedProvider::new(VerificationType::Email, 0));
+    let sms = Arc::new(ScriptedProvider::new(VerificationType::Sms, 0));
+    let queued_email = outbox.wrap(email.clone());
+    let queued_sms = outbox.wrap(sms.clone());
+
+    queued_email
+        .send_message(test_message(VerificationType::Email, "email"))
+        .await
+        .unwrap();
+    queued_sms
+        .send_message(test_message(VerificationType::Sms, "sms"))
+        .await
+        .unwrap();
+
+    let report = outbox.dispatcher().dispatch_once().await.unwrap();
+
+    assert_eq!(report.sent, 2);
+    assert_eq!(email.delivered(), vec!["email"]);
+    assert_eq!(sms.delivered(), vec!["sms"]);
+}
+
+#[test]
+async fn test_failed_delivery_is_retried_after_backoff() {
+    let clock = TestClock::default();
+    let (repo, outbox) = create_test_outbox(clock.clone());
+    let provider = Arc::new(ScriptedProvider::new(VerificationType::Email, 1));
+    let queued = outbox.wrap(provider.clone());
+    let dispatcher = outbox.dispatcher();
+
+    queued
+        .send_message(test_message(VerificationType::Email, "retry me"))
+        .await
+        .unwrap();
+
+    let report = dispatcher.dispatch_once().await.unwrap();
+    assert_eq!(report.retried, 1);
+
+    let message = &repo.messages()[0];
+    assert_eq!(message.status, OutboxStatus::Pending);
+    assert_eq!(message.attempts, 1);
+    assert_eq!(message.next_attempt_at, clock.now() + Duration::seconds(30));
+    assert!(message.last_error.is_some());
+
+    // Not due again before the backoff has elapsed
+    clock.advance(Duration::seconds(29));
+    let report = dispatcher.dispatch_once().await.unwrap();
+    assert_eq!(report, DispatchReport::default());
+    assert!(provider.delivered().is_empty());
+
+    clock.advance(Duration::seconds(1));
+    let report = dispatcher.dispatch_once().await.unwrap();
+    assert_eq!(report.sent, 1);
+    assert_eq!(provider.delivered(), vec!["retry me"]);
+
+    let message = &repo.messages()[0];
+    assert_eq!(message.status, OutboxStatus::Sent);
+    assert_eq!(message.attempts, 2);
+    assert!(message.last_error.is_none());
+}
+
+#[test]
+async fn test_delivery_marked_failed_after_max_attempts() {
+    let clock = TestClock::default();
+    let (repo, outbox) = create_test_outbox(clock.clone());
+    let provider = Arc::new(ScriptedProvider::new(VerificationType::Email, u32::MAX));
+    let queued = outbox.wrap(provider.clone());
+    let dispatcher = outbox.dispatcher();
+
+    queued
+        .send_message(test_message(VerificationType::Email, "doomed"))
+        .await
+        .unwrap();
+
+    let mut totals = DispatchReport::default();
+    for _ in 0..3 {
+        let report = dispatcher.dispatch_once().await.unwrap();
+        totals.retried += report.retried;
+        totals.failed += report.failed;
+        clock.advance(Duration::seconds(300));
+    }
+
+    assert_eq!(totals.retried, 2);
+    assert_eq!(totals.failed, 1);
+
+    // Permanently failed messages are not picked up again
+    let report = dispatcher.dispatch_once().await.unwrap();
+    assert_eq!(report, DispatchReport::default());
+
+    let failed = repo.list_failed(10).await.unwrap();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].attempts, 3);
+    assert!(
+        failed[0]
+            .last_error
+            .as_deref()
+            .is_some_and(|e| e.contains("provider unavailable"))
+    );
+}
+
+#[test]
+async fn test_enqueued_message_survives_restart() {
+    let clock = TestClock::default();
+    let repo = Arc::new(MockMessageOutboxRepository::new());
+
+    // Enqueue through one outbox, then drop it before any dispatch happens
+    {
+        let outbox = MessageOutbox::with_clock(repo.clone(), test_config(), clock.clone());
+        let queued = outbox.wrap(Arc::new(ScriptedProvider::new(VerificationType::Email, 0)));
+        queued
+            .send_message(test_message(VerificationType::Email, "persisted"))
+            .await
+            .unwrap();
+    }
+
+    let outbox = MessageOutbox::with_clock(repo.clone(), test_config(), clock);
+    let provider = Arc::new(ScriptedProvider::new(VerificationType::Email, 0));
+    outbox.wrap(provider.clone());
+
+    let report = outbox.dispatcher().dispatch_once().await.unwrap();
+
+    assert_eq!(report.sent, 1);
+    assert_eq!(provider.delivered(), vec!["persisted"]);
+}
+
+#[test]
+async fn test_abandoned_claim_is_redelivered_after_lease_expiry() {
+    let clock = TestClock::default();
+    let (repo, outbox) = create_test_outbox(clock.clone());
+    let provider = Arc::new(ScriptedProvider::new(VerificationType::Email, 0));
+    let queued = outbox.wrap(provider.clone());
+    let dispatcher = outbox.dispatcher();
+
+    queued
+        .send_message(test_message(VerificationType::Email, "claimed"))
+        .await
+        .unwrap();
+
+    // Simulate a dispatcher that claimed the message and crashed before sending
+    let now = clock.now();
+    let claimed = repo
+        .claim_due(
+            VerificationType::Email,
+            now,
+            now + Duration::seconds(60),
+            10,
+        )
+        .await
+        .unwrap();
+    assert_eq!(claimed.len(), 1);
+
+    // Still leased, so another dispatcher skips it
+    let report = dispatcher.dispatch_once().await.unwrap();
+    assert_eq!(report, DispatchReport::default());
+
+    clock.advance(Duration::seconds(60));
+    let report = dispatcher.dispatch_once().await.unwrap();
+    assert_eq!(report.sent, 1);
+    assert_eq!(provider.delivered(), vec!["claimed"]);
+    assert_eq!(repo.messages()[0].attempts, 2);
+}
+
+#[test]
+async fn test_backoff_is_exponential_and_capped() {
+    let config = test_config();
+
+    assert_eq!(config.backoff(1), Duration::seconds(30));
+    assert_eq!(config.backoff(2), Duration::seconds(60));
+    assert_eq!(config.backoff(3), Duration::seconds(120));
+    assert_eq!(config.backoff(5), Duration::seconds(300));
+    assert_eq!(config.backoff(u32::MAX), Duration::seconds(300));
+}
+
+#[test]
+async fn test_queued_delivery_requires_outbox() {
+    let config = EmailProviderConfig {
+        provider: "sendgrid".to_string(),
+        smtp: None,
+        api_key: Some(Secret::from("test-key")),
+        sender_email: "noreply@example.com".to_string(),
+        sender_name: "Test".to_string(),
+        verification_template: "Your code is {code}".to_string(),
+        delivery: DeliveryMode::Queued,
+    };
+
+    let client = HttpClientFactory::new(OutboundHttpConfig::default())
+        .build()
+        .expect("Failed to build HTTP client");
+    let result = create_email_provider(config, &client, None, None);
+
+    assert!(matches!(result, Err(Error::Config(_))));
+}
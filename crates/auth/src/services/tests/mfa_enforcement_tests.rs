@@ -0,0 +1,318 @@
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+use tokio::test;
+use uuid::Uuid;
+
+use crate::config::AuthConfig;
+use crate::models::TotpSecret;
+use crate::models::tenant::{MfaEnforcement, TenantSecurityPolicy};
+use crate::models::user::{User, UserRepository, mock::MockUserRepository};
+#[cfg(feature = "enable_webauthn")]
+use crate::models::webauthn::Credential;
+#[cfg(feature = "enable_webauthn")]
+use crate::repository::WebAuthnRepository;
+use crate::repository::TotpSecretRepository;
+use crate::services::session::SessionService;
+use crate::services::user::{UserService, UserServiceError};
+use crate::session::mock::MockSessionRepository;
+use crate::session::types::MfaStatus;
+use crate::utils::jwt::JwtUtils;
+use crate::utils::password::hash_password;
+
+#[cfg(feature = "enable_webauthn")]
+use super::mocks::MockWebAuthnRepository;
+use super::mocks::{MockTenantRepository, MockTotpSecretRepository};
+
+const PASSWORD: &str = "Correct-Horse-Battery-42";
+
+struct Fixture {
+    user_service: UserService,
+    user_repo: Arc<MockUserRepository>,
+    tenant_repo: Arc<MockTenantRepository>,
+    totp_repo: Arc<MockTotpSecretRepository>,
+    session_repo: Arc<MockSessionRepository>,
+    tenant_id: Uuid,
+}
+
+impl Fixture {
+    fn new(policy: TenantSecurityPolicy) -> Self {
+        let user_repo = Arc::new(MockUserRepository::new());
+        let tenant_repo = Arc::new(MockTenantRepository::new());
+        let totp_repo = Arc::new(MockTotpSecretRepository::new());
+        let session_repo = Arc::new(MockSessionRepository::new());
+        let tenant_id = tenant_repo.insert_tenant_with_policy(&policy);
+
+        let config = Arc::new(AuthConfig::default());
+        let session_service = Arc::new(SessionService::new(session_repo.clone(), config.clone()));
+        let user_service = UserService::new(
+            user_repo.clone(),
+            Arc::new(JwtUtils::new(b"test-secret")),
+            session_service,
+            None,
+            config,
+        )
+        .with_tenant_repository(tenant_repo.clone())
+        .with_totp_repository(totp_repo.clone());
+
+        Self {
+            user_service,
+            user_repo,
+            tenant_repo,
+            totp_repo,
+            session_repo,
+            tenant_id,
+        }
+    }
+
+    async fn add_user(&self, email: &str, tenant_role: &str) -> User {
+        let user = User::new(email.to_string(), hash_password(PASSWORD).unwrap());
+        self.user_repo.create(&user).await.unwrap();
+        self.tenant_repo
+            .add_member(self.tenant_id, user.id, tenant_role);
+        user
+    }
+
+    async fn enroll_totp(&self, user: &User, confirmed: bool) {
+        let mut secret = TotpSecret::new(
+            user.id,
+            self.tenant_id,
+            "JBSWY3DPEHPK3PXP".to_string(),
+            "SHA1".to_string(),
+            6,
+            30,
+            Vec::new(),
+        );
+        secret.enabled = confirmed;
+        self.totp_repo.save(&secret).await.unwrap();
+    }
+
+    async fn login(
+        &self,
+        email: &str,
+    ) -> Result<crate::services::user::LoginResult, UserServiceError> {
+        self.user_service
+            .login_with_tenant(
+                Some(self.tenant_id),
+                email,
+                PASSWORD,
+                None,
+                None,
+                None,
+                None,
+                false,
+            )
+            .await
+    }
+}
+
+fn policy(
+    mfa_enforcement: MfaEnforcement,
+    mfa_grace_deadline: Option<OffsetDateTime>,
+) -> TenantSecurityPolicy {
+    TenantSecurityPolicy {
+        mfa_enforcement,
+        mfa_grace_deadline,
+        ..TenantSecurityPolicy::default()
+    }
+}
+
+#[test]
+async fn test_admin_only_enforcement_restricts_unenrolled_admin() {
+    let fixture = Fixture::new(policy(MfaEnforcement::RequiredForAdmins, None));
+    fixture.add_user("admin@example.com", "ADMIN").await;
+
+    let result = fixture.login("admin@example.com").await.unwrap();
+    assert_eq!(result.mfa_status, MfaStatus::Pending);
+
+    // The session is persisted as pending and cannot be used outside enrollment
+    let sessions = fixture.session_repo.sessions().clone();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].mfa_status, MfaStatus::Pending);
+
+    let validation = fixture
+        .user_service
+        .validate_session(&result.session_token)
+        .await;
+    assert!(matches!(
+        validation,
+        Err(UserServiceError::MfaEnrollmentRequired)
+    ));
+}
+
+#[test]
+async fn test_admin_only_enforcement_ignores_regular_members() {
+    let fixture = Fixture::new(policy(MfaEnforcement::RequiredForAdmins, None));
+    let member = fixture.add_user("member@example.com", "MEMBER").await;
+
+    let result = fixture.login("member@example.com").await.unwrap();
+    assert_eq!(result.mfa_status, MfaStatus::None);
+
+    let user = fixture
+        .user_service
+        .validate_session(&result.session_token)
+        .await
+        .unwrap();
+    assert_eq!(user.map(|u| u.id), Some(member.id));
+}
+
+#[test]
+async fn test_required_for_all_restricts_members() {
+    let fixture = Fixture::new(policy(MfaEnforcement::RequiredForAll, None));
+    fixture.add_user("member@example.com", "MEMBER").await;
+
+    let result = fixture.login("member@example.com").await.unwrap();
+    assert_eq!(result.mfa_status, MfaStatus::Pending);
+}
+
+#[test]
+async fn test_enrolled_admin_is_challenged_for_second_factor() {
+    let fixture = Fixture::new(policy(MfaEnforcement::RequiredForAdmins, None));
+    let admin = fixture.add_user("admin@example.com", "ADMIN").await;
+    fixture.enroll_totp(&admin, true).await;
+
+    // Enrolled users are not restricted to enrollment, but have to complete MFA
+    let result = fixture.login("admin@example.com").await.unwrap();
+    assert_eq!(result.mfa_status, MfaStatus::Required);
+}
+
+#[test]
+async fn test_unconfirmed_totp_secret_is_not_an_enrollment() {
+    let fixture = Fixture::new(policy(MfaEnforcement::RequiredForAll, None));
+    let user = fixture.add_user("user@example.com", "MEMBER").await;
+    fixture.enroll_totp(&user, false).await;
+
+    let enrollment = fixture
+        .user_service
+        .get_mfa_enrollment(user.id, fixture.tenant_id)
+        .await
+        .unwrap();
+    assert!(!enrollment.totp_enrolled);
+    assert_eq!(enrollment.webauthn_credentials, 0);
+    assert!(!enrollment.is_enrolled());
+}
+
+#[cfg(feature = "enable_webauthn")]
+#[test]
+async fn test_webauthn_credentials_of_other_tenants_are_not_an_enrollment() {
+    let fixture = Fixture::new(policy(MfaEnforcement::RequiredForAll, None));
+    let user = fixture.add_user("user@example.com", "MEMBER").await;
+    let tenant_id = fixture.tenant_id;
+    let credentials = Arc::new(MockWebAuthnRepository::new());
+    let credential = |tenant_id| {
+        Credential::new(
+            Uuid::new_v4().as_bytes().to_vec(),
+            vec![1, 2, 3],
+            vec![0; 16],
+            "Security key",
+            user.id,
+            tenant_id,
+        )
+    };
+    credentials
+        .save_credential(&credential(Uuid::new_v4()))
+        .await
+        .unwrap();
+    let user_service = fixture
+        .user_service
+        .with_webauthn_repository(credentials.clone());
+
+    let enrollment = user_service
+        .get_mfa_enrollment(user.id, tenant_id)
+        .await
+        .unwrap();
+    assert_eq!(enrollment.webauthn_credentials, 0);
+    assert!(!enrollment.is_enrolled());
+
+    // The login is restricted to enrollment instead of challenging the key
+    let result = user_service
+        .login_with_tenant(
+            Some(tenant_id),
+            "user@example.com",
+            PASSWORD,
+            None,
+            None,
+            None,
+            None,
+            false,
+        )
+        .await
+        .unwrap();
+    assert_eq!(result.mfa_status, MfaStatus::Pending);
+
+    credentials
+        .save_credential(&credential(tenant_id))
+        .await
+        .unwrap();
+    let enrollment = user_service
+        .get_mfa_enrollment(user.id, tenant_id)
+        .await
+        .unwrap();
+    assert_eq!(enrollment.webauthn_credentials, 1);
+    assert!(enrollment.is_enrolled());
+}
+
+#[test]
+async fn test_login_allowed_before_grace_deadline() {
+    let deadline = OffsetDateTime::now_utc() + Duration::minutes(5);
+    let fixture = Fixture::new(policy(MfaEnforcement::RequiredForAll, Some(deadline)));
+    fixture.add_user("user@example.com", "MEMBER").await;
+
+    let result = fixture.login("user@example.com").await.unwrap();
+    assert_eq!(result.mfa_status, MfaStatus::Pending);
+}
+
+#[test]
+async fn test_login_rejected_once_grace_deadline_is_reached() {
+    let deadline = OffsetDateTime::now_utc();
+    let fixture = Fixture::new(policy(MfaEnforcement::RequiredForAll, Some(deadline)));
+    fixture.add_user("user@example.com", "MEMBER").await;
+
+    let result = fixture.login("user@example.com").await;
+    assert!(matches!(
+        result,
+        Err(UserServiceError::MfaEnrollmentExpired)
+    ));
+    assert!(fixture.session_repo.sessions().is_empty());
+}
+
+#[test]
+async fn test_grace_deadline_does_not_affect_enrolled_users() {
+    let deadline = OffsetDateTime::now_utc() - Duration::days(1);
+    let fixture = Fixture::new(policy(MfaEnforcement::RequiredForAll, Some(deadline)));
+    let user = fixture.add_user("user@example.com", "MEMBER").await;
+    fixture.enroll_totp(&user, true).await;
+
+    let result = fixture.login("user@example.com").await.unwrap();
+    assert_eq!(result.mfa_status, MfaStatus::Required);
+}
+
+#[test]
+async fn test_complete_enrollment_releases_pending_session() {
+    let fixture = Fixture::new(policy(MfaEnforcement::RequiredForAdmins, None));
+    let admin = fixture.add_user("admin@example.com", "ADMIN").await;
+    let result = fixture.login("admin@example.com").await.unwrap();
+
+    // Completing before a factor is enrolled is refused
+    let premature = fixture
+        .user_service
+        .complete_mfa_enrollment(admin.id, fixture.tenant_id, &result.session_token)
+        .await;
+    assert!(matches!(
+        premature,
+        Err(UserServiceError::MfaEnrollmentRequired)
+    ));
+
+    fixture.enroll_totp(&admin, true).await;
+    fixture
+        .user_service
+        .complete_mfa_enrollment(admin.id, fixture.tenant_id, &result.session_token)
+        .await
+        .unwrap();
+
+    let user = fixture
+        .user_service
+        .validate_session(&result.session_token)
+        .await
+        .unwrap();
+    assert_eq!(user.map(|u| u.id), Some(admin.id));
+}
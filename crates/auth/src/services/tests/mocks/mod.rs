@@ -1,4 +1,24 @@
+pub use crate::models::tenant::mock::MockTenantRepository;
+use crate::models::{
+    DeliveryStatus, LinkedIdentity, MessageDelivery, OutboxMessage, OutboxStatus, TenantId,
+    TotpSecret, UserId, VerificationType,
+};
 use crate::repository::tenant_aware::{RepositoryError, TenantAwareContext};
+use crate::repository::{
+    ExternalIdentityRepository, MessageDeliveryRepository, MessageOutboxRepository,
+    TotpSecretRepository,
+};
+use crate::security::{FingerprintRepository, StoredFingerprint};
+#[cfg(feature = "enable_webauthn")]
+use crate::{
+    models::webauthn::{Credential, CredentialID},
+    repository::WebAuthnRepository,
+};
+use acci_core::error::Result as CoreResult;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::sync::Mutex;
+use time::OffsetDateTime;
 use uuid::Uuid;
 
 /// Mock implementation of TenantAwareContext for tests
@@ -35,6 +55,447 @@ fn panic_on_use_pool() -> sqlx::PgPool {
                 .port(5432)
                 .database("test_db")
                 .username("test")
-                .password("test")
+                .password("test"),
         )
 }
+
+/// In-memory TOTP secret repository for tests
+pub struct MockTotpSecretRepository {
+    secrets: Mutex<Vec<TotpSecret>>,
+}
+
+impl MockTotpSecretRepository {
+    /// Creates an empty mock TOTP secret repository
+    pub fn new() -> Self {
+        Self {
+            secrets: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl TotpSecretRepository for MockTotpSecretRepository {
+    async fn save(&self, secret: &TotpSecret) -> Result<(), RepositoryError> {
+        let mut secrets = self.secrets.lock().unwrap();
+        secrets.retain(|s| !(s.user_id == secret.user_id && s.tenant_id == secret.tenant_id));
+        secrets.push(secret.clone());
+        Ok(())
+    }
+
+    async fn get_by_user_id(
+        &self,
+        user_id: &UserId,
+        tenant_id: &TenantId,
+    ) -> Result<Option<TotpSecret>, RepositoryError> {
+        Ok(self
+            .secrets
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|s| s.user_id == *user_id && s.tenant_id == *tenant_id)
+            .cloned())
+    }
+
+    async fn delete(&self, user_id: &UserId, tenant_id: &TenantId) -> Result<(), RepositoryError> {
+        self.secrets
+            .lock()
+            .unwrap()
+            .retain(|s| !(s.user_id == *user_id && s.tenant_id == *tenant_id));
+        Ok(())
+    }
+
+    async fn get_all_for_tenant(
+        &self,
+        tenant_id: &TenantId,
+    ) -> Result<Vec<TotpSecret>, RepositoryError> {
+        Ok(self
+            .secrets
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|s| s.tenant_id == *tenant_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_by_id(
+        &self,
+        id: &Uuid,
+        tenant_id: &TenantId,
+    ) -> Result<Option<TotpSecret>, RepositoryError> {
+        Ok(self
+            .secrets
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|s| s.id == *id && s.tenant_id == *tenant_id)
+            .cloned())
+    }
+}
+
+/// Mock implementation of ExternalIdentityRepository for tests
+pub struct MockExternalIdentityRepository {
+    identities: Mutex<Vec<LinkedIdentity>>,
+}
+
+impl MockExternalIdentityRepository {
+    /// Creates an empty mock identity repository
+    pub fn new() -> Self {
+        Self {
+            identities: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl ExternalIdentityRepository for MockExternalIdentityRepository {
+    async fn link(&self, identity: &LinkedIdentity) -> Result<(), RepositoryError> {
+        let mut identities = self
+            .identities
+            .lock()
+            .expect("mock identity store lock poisoned");
+        identities.retain(|i| !(i.provider == identity.provider && i.subject == identity.subject));
+        identities.push(identity.clone());
+        Ok(())
+    }
+
+    async fn list_for_user(&self, user_id: &Uuid) -> Result<Vec<LinkedIdentity>, RepositoryError> {
+        Ok(self
+            .identities
+            .lock()
+            .expect("mock identity store lock poisoned")
+            .iter()
+            .filter(|i| i.user_id == *user_id)
+            .cloned()
+            .collect())
+    }
+}
+
+/// In-memory message outbox for tests.
+///
+/// Entries keep their enqueue order and an optional lease, mirroring the
+/// claim semantics of the PostgreSQL implementation.
+pub struct MockMessageOutboxRepository {
+    entries: Mutex<Vec<(OutboxMessage, Option<OffsetDateTime>)>>,
+}
+
+impl MockMessageOutboxRepository {
+    /// Creates an empty mock message outbox
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns a snapshot of all stored messages in enqueue order
+    pub fn messages(&self) -> Vec<OutboxMessage> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(message, _)| message.clone())
+            .collect()
+    }
+
+    fn update(&self, id: Uuid, apply: impl FnOnce(&mut OutboxMessage)) -> CoreResult<()> {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some((message, lease)) = entries.iter_mut().find(|(m, _)| m.id == id) {
+            apply(message);
+            *lease = None;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MessageOutboxRepository for MockMessageOutboxRepository {
+    async fn enqueue(&self, message: &OutboxMessage) -> CoreResult<()> {
+        self.entries.lock().unwrap().push((message.clone(), None));
+        Ok(())
+    }
+
+    async fn claim_due(
+        &self,
+        message_type: VerificationType,
+        now: OffsetDateTime,
+        lease_until: OffsetDateTime,
+        limit: u32,
+    ) -> CoreResult<Vec<OutboxMessage>> {
+        let mut entries = self.entries.lock().unwrap();
+        let claimed = entries
+            .iter_mut()
+            .filter(|(message, lease)| {
+                message.status == OutboxStatus::Pending
+                    && message.message_type == message_type
+                    && message.next_attempt_at <= now
+                    && lease.is_none_or(|until| until <= now)
+            })
+            .take(limit as usize)
+            .map(|(message, lease)| {
+                message.attempts += 1;
+                message.updated_at = now;
+                *lease = Some(lease_until);
+                message.clone()
+            })
+            .collect();
+        Ok(claimed)
+    }
+
+    async fn mark_sent(
+        &self,
+        id: Uuid,
+        provider_message_id: &str,
+        now: OffsetDateTime,
+    ) -> CoreResult<()> {
+        self.update(id, |message| {
+            message.status = OutboxStatus::Sent;
+            message.provider_message_id = Some(provider_message_id.to_string());
+            message.last_error = None;
+            message.sent_at = Some(now);
+            message.updated_at = now;
+        })
+    }
+
+    async fn schedule_retry(
+        &self,
+        id: Uuid,
+        error: &str,
+        next_attempt_at: OffsetDateTime,
+        now: OffsetDateTime,
+    ) -> CoreResult<()> {
+        self.update(id, |message| {
+            message.last_error = Some(error.to_string());
+            message.next_attempt_at = next_attempt_at;
+            message.updated_at = now;
+        })
+    }
+
+    async fn mark_failed(&self, id: Uuid, error: &str, now: OffsetDateTime) -> CoreResult<()> {
+        self.update(id, |message| {
+            message.status = OutboxStatus::Failed;
+            message.last_error = Some(error.to_string());
+            message.updated_at = now;
+        })
+    }
+
+    async fn list_failed(&self, limit: u32) -> CoreResult<Vec<OutboxMessage>> {
+        let mut failed: Vec<OutboxMessage> = self
+            .messages()
+            .into_iter()
+            .filter(|message| message.status == OutboxStatus::Failed)
+            .collect();
+        failed.sort_by_key(|message| std::cmp::Reverse(message.updated_at));
+        failed.truncate(limit as usize);
+        Ok(failed)
+    }
+}
+
+/// In-memory message delivery repository for tests
+pub struct MockMessageDeliveryRepository {
+    deliveries: Mutex<Vec<MessageDelivery>>,
+}
+
+impl MockMessageDeliveryRepository {
+    /// Creates an empty mock message delivery repository
+    pub fn new() -> Self {
+        Self {
+            deliveries: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns a snapshot of all recorded deliveries in record order
+    pub fn deliveries(&self) -> Vec<MessageDelivery> {
+        self.deliveries.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl MessageDeliveryRepository for MockMessageDeliveryRepository {
+    async fn record(&self, delivery: &MessageDelivery) -> CoreResult<()> {
+        self.deliveries.lock().unwrap().push(delivery.clone());
+        Ok(())
+    }
+
+    async fn find_by_provider_message_id(
+        &self,
+        provider_message_id: &str,
+    ) -> CoreResult<Option<MessageDelivery>> {
+        Ok(self
+            .deliveries
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|delivery| delivery.provider_message_id == provider_message_id)
+            .cloned())
+    }
+
+    async fn update_status(
+        &self,
+        id: Uuid,
+        from: DeliveryStatus,
+        to: DeliveryStatus,
+        error: Option<&str>,
+        now: OffsetDateTime,
+    ) -> CoreResult<bool> {
+        let mut deliveries = self.deliveries.lock().unwrap();
+        let Some(delivery) = deliveries
+            .iter_mut()
+            .find(|delivery| delivery.id == id && delivery.status == from)
+        else {
+            return Ok(false);
+        };
+        delivery.status = to;
+        if let Some(error) = error {
+            delivery.error = Some(error.to_string());
+        }
+        delivery.updated_at = now;
+        Ok(true)
+    }
+}
+
+/// In-memory fingerprint repository for tests
+pub struct MockFingerprintRepository {
+    fingerprints: Mutex<Vec<StoredFingerprint>>,
+}
+
+impl MockFingerprintRepository {
+    /// Creates an empty mock fingerprint repository
+    pub fn new() -> Self {
+        Self {
+            fingerprints: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns a snapshot of all stored fingerprints
+    pub fn fingerprints(&self) -> Vec<StoredFingerprint> {
+        self.fingerprints.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl FingerprintRepository for MockFingerprintRepository {
+    async fn store_fingerprint(
+        &self,
+        fingerprint: &StoredFingerprint,
+    ) -> Result<(), anyhow::Error> {
+        self.fingerprints.lock().unwrap().push(fingerprint.clone());
+        Ok(())
+    }
+
+    async fn get_fingerprints_for_user(
+        &self,
+        tenant_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<StoredFingerprint>, anyhow::Error> {
+        Ok(self
+            .fingerprints()
+            .into_iter()
+            .filter(|f| f.tenant_id == tenant_id && f.user_id == user_id)
+            .collect())
+    }
+
+    async fn update_fingerprint(
+        &self,
+        fingerprint: &StoredFingerprint,
+    ) -> Result<(), anyhow::Error> {
+        let mut fingerprints = self.fingerprints.lock().unwrap();
+        if let Some(stored) = fingerprints.iter_mut().find(|f| f.id == fingerprint.id) {
+            *stored = fingerprint.clone();
+        }
+        Ok(())
+    }
+
+    async fn mark_as_trusted(&self, id: Uuid, trusted: bool) -> Result<(), anyhow::Error> {
+        let mut fingerprints = self.fingerprints.lock().unwrap();
+        if let Some(stored) = fingerprints.iter_mut().find(|f| f.id == id) {
+            stored.trusted = trusted;
+        }
+        Ok(())
+    }
+
+    async fn delete_old_fingerprints(
+        &self,
+        tenant_id: Uuid,
+        older_than: DateTime<Utc>,
+    ) -> Result<u64, anyhow::Error> {
+        let mut fingerprints = self.fingerprints.lock().unwrap();
+        let before = fingerprints.len();
+        fingerprints.retain(|f| !(f.tenant_id == tenant_id && f.last_seen < older_than));
+        Ok((before - fingerprints.len()) as u64)
+    }
+}
+
+/// In-memory WebAuthn credential repository for tests
+#[cfg(feature = "enable_webauthn")]
+pub struct MockWebAuthnRepository {
+    credentials: Mutex<Vec<Credential>>,
+}
+
+#[cfg(feature = "enable_webauthn")]
+impl MockWebAuthnRepository {
+    /// Creates an empty mock WebAuthn repository
+    pub fn new() -> Self {
+        Self {
+            credentials: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns a snapshot of all stored credentials
+    pub fn credentials(&self) -> Vec<Credential> {
+        self.credentials.lock().unwrap().clone()
+    }
+}
+
+#[cfg(feature = "enable_webauthn")]
+#[async_trait]
+impl WebAuthnRepository for MockWebAuthnRepository {
+    async fn save_credential(&self, credential: &Credential) -> Result<(), RepositoryError> {
+        self.credentials.lock().unwrap().push(credential.clone());
+        Ok(())
+    }
+
+    async fn update_credential(&self, credential: &Credential) -> Result<(), RepositoryError> {
+        let mut credentials = self.credentials.lock().unwrap();
+        if let Some(stored) = credentials.iter_mut().find(|c| c.uuid == credential.uuid) {
+            *stored = credential.clone();
+        }
+        Ok(())
+    }
+
+    async fn find_credential_by_id(
+        &self,
+        id: &CredentialID,
+    ) -> Result<Option<Credential>, RepositoryError> {
+        Ok(self.credentials().into_iter().find(|c| &c.id == id))
+    }
+
+    async fn find_credential_by_uuid(
+        &self,
+        uuid: &Uuid,
+    ) -> Result<Option<Credential>, RepositoryError> {
+        Ok(self.credentials().into_iter().find(|c| &c.uuid == uuid))
+    }
+
+    async fn list_credentials_for_user(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<Vec<Credential>, RepositoryError> {
+        Ok(self
+            .credentials()
+            .into_iter()
+            .filter(|c| &c.user_id == user_id)
+            .collect())
+    }
+
+    async fn delete_credential(&self, uuid: &Uuid) -> Result<(), RepositoryError> {
+        self.credentials.lock().unwrap().retain(|c| &c.uuid != uuid);
+        Ok(())
+    }
+
+    async fn delete_credentials_for_user(&self, user_id: &Uuid) -> Result<u64, RepositoryError> {
+        let mut credentials = self.credentials.lock().unwrap();
+        let before = credentials.len();
+        credentials.retain(|c| &c.user_id != user_id);
+        Ok((before - credentials.len()) as u64)
+    }
+}
@@ -2,5 +2,38 @@
 pub mod mocks;
 
 // Import individual test modules
+pub mod account_deletion_tests;
+pub mod api_key_tests;
+pub mod delivery_receipt_tests;
+pub mod email_routing_tests;
+pub mod event_export_tests;
+pub mod factor_tests;
+pub mod fingerprint_tests;
+pub mod guest_session_tests;
+pub mod impersonation_tests;
+pub mod login_attempt_tests;
+pub mod login_location_tests;
+pub mod message_outbox_tests;
+pub mod mfa_enforcement_tests;
+pub mod oidc_tests;
+pub mod password_policy_tests;
+pub mod rollout_tests;
+pub mod saml_tests;
+pub mod scim_tests;
+pub mod session_metadata_tests;
+pub mod session_reconciliation_tests;
 pub mod session_verification_tests;
+pub mod subscription_tests;
+pub mod tenant_import_tests;
+pub mod tenant_suspension_tests;
+pub mod tenant_switch_tests;
+pub mod tenants_per_user_tests;
+pub mod timeline_tests;
+pub mod totp_enrollment_tests;
+pub mod totp_lifecycle_tests;
+pub mod trusted_device_tests;
+pub mod user_agent_tests;
+pub mod verification_fallback_tests;
 pub mod verification_tests;
+#[cfg(feature = "enable_webauthn")]
+pub mod webauthn_attestation_tests;
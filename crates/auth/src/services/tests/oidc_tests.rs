@@ -0,0 +1,392 @@
+use async_trait::async_trait;
+use axum::{Form, Json, Router, extract::State, routing::post};
+use jsonwebtoken::{EncodingKey, Header, encode};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use time::OffsetDateTime;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::config::AuthConfig;
+use crate::models::tenant::{TenantRepository, TenantSecurityPolicy};
+use crate::models::user::{User, UserRepository, mock::MockUserRepository};
+use crate::services::oidc::{
+    IdentitySource, OidcError, OidcProvider, OidcProviderConfig, OidcStateStore,
+};
+use crate::services::session::SessionService;
+use crate::services::user::UserService;
+use crate::session::mock::MockSessionRepository;
+use crate::utils::jwt::JwtUtils;
+use crate::utils::password::{hash_password, verify_password};
+use acci_core::http_client::{HttpClientFactory, OutboundHttpConfig};
+
+use super::mocks::MockTenantRepository;
+
+const CLIENT_ID: &str = "acci-client";
+const CLIENT_SECRET: &str = "test-client-secret";
+const ISSUER: &str = "https://idp.example.com";
+const CODE: &str = "authorization-code";
+const PASSWORD: &str = "Correct-Horse-Battery-42";
+
+/// In-memory stand-in for the Redis nonce store
+#[derive(Default)]
+struct MemoryStateStore {
+    values: Mutex<HashSet<String>>,
+}
+
+#[async_trait]
+impl OidcStateStore for MemoryStateStore {
+    async fn issue(&self, tenant_id: &str, context: &str) -> anyhow::Result<String> {
+        let value = Uuid::new_v4().simple().to_string();
+        self.values
+            .lock()
+            .expect("state store lock poisoned")
+            .insert(format!("{}:{}:{}", tenant_id, context, value));
+        Ok(value)
+    }
+
+    async fn consume(&self, tenant_id: &str, context: &str, value: &str) -> anyhow::Result<bool> {
+        Ok(self
+            .values
+            .lock()
+            .expect("state store lock poisoned")
+            .remove(&format!("{}:{}:{}", tenant_id, context, value)))
+    }
+}
+
+/// Token endpoint of the mocked provider
+#[derive(Default)]
+struct TokenEndpoint {
+    id_token: Mutex<Option<String>>,
+    requests: Mutex<Vec<HashMap<String, String>>>,
+}
+
+async fn token(
+    State(endpoint): State<Arc<TokenEndpoint>>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Json<Value> {
+    endpoint
+        .requests
+        .lock()
+        .expect("token endpoint lock poisoned")
+        .push(form);
+    let id_token = endpoint
+        .id_token
+        .lock()
+        .expect("token endpoint lock poisoned")
+        .clone()
+        .expect("ID token is set before the callback");
+
+    Json(Value::Object(Map::from_iter([
+        ("access_token".to_string(), Value::from("access-token")),
+        ("token_type".to_string(), Value::from("Bearer")),
+        ("id_token".to_string(), Value::from(id_token)),
+    ])))
+}
+
+struct Fixture {
+    provider: OidcProvider,
+    endpoint: Arc<TokenEndpoint>,
+    user_repo: Arc<MockUserRepository>,
+    tenant_repo: Arc<MockTenantRepository>,
+    tenant_id: Uuid,
+}
+
+impl Fixture {
+    async fn new(policy: TenantSecurityPolicy) -> Self {
+        let endpoint = Arc::new(TokenEndpoint::default());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind token endpoint");
+        let addr = listener
+            .local_addr()
+            .expect("Token endpoint has an address");
+        let app = Router::new()
+            .route("/token", post(token))
+            .with_state(endpoint.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let config = OidcProviderConfig {
+            name: "idp".to_string(),
+            issuer: ISSUER.to_string(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: CLIENT_SECRET.to_string(),
+            authorization_endpoint: format!("{}/authorize", ISSUER),
+            token_endpoint: format!("http://{}/token", addr),
+            redirect_uri: "https://app.example.com/auth/oidc/idp/callback".to_string(),
+            scopes: vec!["openid".to_string(), "email".to_string()],
+            identity: IdentitySource::IdToken { jwks_uri: None },
+        };
+        let user_repo = Arc::new(MockUserRepository::new());
+        let tenant_repo = Arc::new(MockTenantRepository::new());
+        let tenant_id = tenant_repo.insert_tenant_with_policy(&policy);
+        let client = HttpClientFactory::new(OutboundHttpConfig::default())
+            .build()
+            .expect("Failed to build HTTP client");
+        let provider = OidcProvider::new(
+            config,
+            client,
+            Arc::new(MemoryStateStore::default()),
+            user_repo.clone(),
+            tenant_repo.clone(),
+        );
+
+        Self {
+            provider,
+            endpoint,
+            user_repo,
+            tenant_repo,
+            tenant_id,
+        }
+    }
+
+    async fn add_user(&self, email: &str, verified: bool) -> User {
+        let mut user = User::new(
+            email.to_string(),
+            hash_password(PASSWORD).expect("Failed to hash password"),
+        );
+        user.is_verified = verified;
+        self.user_repo
+            .create(&user)
+            .await
+            .expect("Failed to create user");
+        user
+    }
+
+    /// Start a login and have the provider answer with an ID token for `email`
+    async fn authorize(&self, email: &str, email_verified: bool) -> String {
+        let url = self
+            .provider
+            .authorization_url(self.tenant_id)
+            .await
+            .expect("Failed to build authorization URL");
+        assert!(url.starts_with(&format!("{}/authorize?", ISSUER)));
+        let nonce = query_param(&url, "nonce");
+
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let claims = Value::Object(Map::from_iter([
+            ("iss".to_string(), Value::from(ISSUER)),
+            ("aud".to_string(), Value::from(CLIENT_ID)),
+            ("sub".to_string(), Value::from("provider-user-1")),
+            ("iat".to_string(), Value::from(now)),
+            ("exp".to_string(), Value::from(now + 300)),
+            ("email".to_string(), Value::from(email)),
+            ("email_verified".to_string(), Value::from(email_verified)),
+            ("nonce".to_string(), Value::from(nonce)),
+        ]));
+        let id_token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(CLIENT_SECRET.as_bytes()),
+        )
+        .expect("Failed to sign ID token");
+        *self
+            .endpoint
+            .id_token
+            .lock()
+            .expect("token endpoint lock poisoned") = Some(id_token);
+
+        query_param(&url, "state")
+    }
+}
+
+fn query_param(url: &str, name: &str) -> String {
+    let (_, query) = url.split_once('?').expect("URL has a query");
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix(&format!("{}=", name)))
+        .map(|value| {
+            urlencoding::decode(value)
+                .expect("Query value is URL encoded")
+                .into_owned()
+        })
+        .expect("Query parameter is present")
+}
+
+fn provisioning_policy() -> TenantSecurityPolicy {
+    TenantSecurityPolicy {
+        external_login_provisioning: true,
+        ..TenantSecurityPolicy::default()
+    }
+}
+
+#[test]
+async fn test_callback_exchanges_code_and_links_local_user() {
+    let fixture = Fixture::new(TenantSecurityPolicy::default()).await;
+    let user = fixture.add_user("alice@example.com", true).await;
+    fixture
+        .tenant_repo
+        .add_member(fixture.tenant_id, user.id, "MEMBER");
+    let state = fixture.authorize("alice@example.com", true).await;
+
+    let login = fixture
+        .provider
+        .handle_callback(CODE, &state)
+        .await
+        .expect("Callback resolves to the local user");
+    assert_eq!(login.user.id, user.id);
+    assert_eq!(login.tenant_id, fixture.tenant_id);
+    assert!(!login.created);
+
+    let requests = fixture
+        .endpoint
+        .requests
+        .lock()
+        .expect("token endpoint lock poisoned")
+        .clone();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0]["grant_type"], "authorization_code");
+    assert_eq!(requests[0]["code"], CODE);
+    assert_eq!(requests[0]["client_id"], CLIENT_ID);
+
+    // The resolved user gets a session in the tenant like a password login
+    let config = Arc::new(AuthConfig::default());
+    let session_repo = Arc::new(MockSessionRepository::new());
+    let user_service = UserService::new(
+        fixture.user_repo.clone(),
+        Arc::new(JwtUtils::new(b"test-secret")),
+        Arc::new(SessionService::new(session_repo.clone(), config.clone())),
+        None,
+        config,
+    )
+    .with_tenant_repository(fixture.tenant_repo.clone());
+    let result = user_service
+        .login_with_identity(
+            login.user,
+            login.tenant_id,
+            &fixture.provider.login_type(),
+            None,
+            None,
+            None,
+            None,
+            false,
+        )
+        .await
+        .expect("External login creates a session");
+    assert_eq!(result.active_tenant_id, Some(fixture.tenant_id));
+    let sessions = session_repo.sessions();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].user_id, user.id);
+    let metadata = sessions[0].metadata.as_ref().expect("Session has metadata");
+    assert_eq!(metadata["login_type"], "oidc:idp");
+}
+
+#[test]
+async fn test_unverified_email_is_rejected() {
+    let fixture = Fixture::new(provisioning_policy()).await;
+    let user = fixture.add_user("alice@example.com", true).await;
+    fixture
+        .tenant_repo
+        .add_member(fixture.tenant_id, user.id, "MEMBER");
+
+    let state = fixture.authorize("alice@example.com", false).await;
+    let result = fixture.provider.handle_callback(CODE, &state).await;
+    assert!(matches!(result, Err(OidcError::UnverifiedEmail)));
+
+    let state = fixture.authorize("mallory@example.com", false).await;
+    let result = fixture.provider.handle_callback(CODE, &state).await;
+    assert!(matches!(result, Err(OidcError::UnverifiedEmail)));
+    let created = fixture
+        .user_repo
+        .find_by_email("mallory@example.com")
+        .await
+        .expect("Failed to look up user");
+    assert!(created.is_none());
+}
+
+#[test]
+async fn test_first_login_provisions_user_only_when_policy_allows() {
+    let fixture = Fixture::new(TenantSecurityPolicy::default()).await;
+    let state = fixture.authorize("bob@example.com", true).await;
+    let result = fixture.provider.handle_callback(CODE, &state).await;
+    assert!(matches!(result, Err(OidcError::AccountNotFound)));
+
+    // Existing users outside the tenant are not added without the policy either
+    let outsider = fixture.add_user("carol@example.com", true).await;
+    let state = fixture.authorize("carol@example.com", true).await;
+    let result = fixture.provider.handle_callback(CODE, &state).await;
+    assert!(matches!(result, Err(OidcError::NotTenantMember)));
+    assert!(
+        fixture
+            .tenant_repo
+            .get_user_tenants(outsider.id)
+            .await
+            .expect("Failed to list memberships")
+            .is_empty()
+    );
+
+    let fixture = Fixture::new(provisioning_policy()).await;
+    let state = fixture.authorize("bob@example.com", true).await;
+    let login = fixture
+        .provider
+        .handle_callback(CODE, &state)
+        .await
+        .expect("Policy allows provisioning");
+    assert!(login.created);
+    assert!(login.user.is_verified);
+    assert_eq!(login.user.email, "bob@example.com");
+    let memberships = fixture
+        .tenant_repo
+        .get_user_tenants(login.user.id)
+        .await
+        .expect("Failed to list memberships");
+    assert_eq!(memberships.len(), 1);
+    assert_eq!(memberships[0].tenant_id, fixture.tenant_id);
+    assert_eq!(memberships[0].tenant_role, "MEMBER");
+}
+
+#[test]
+async fn test_linking_unverified_local_account_discards_its_password() {
+    let fixture = Fixture::new(TenantSecurityPolicy::default()).await;
+    let user = fixture.add_user("dave@example.com", false).await;
+    fixture
+        .tenant_repo
+        .add_member(fixture.tenant_id, user.id, "MEMBER");
+
+    let state = fixture.authorize("dave@example.com", true).await;
+    let login = fixture
+        .provider
+        .handle_callback(CODE, &state)
+        .await
+        .expect("Verified email links the account");
+    assert_eq!(login.user.id, user.id);
+
+    let stored = fixture
+        .user_repo
+        .find_by_id(user.id)
+        .await
+        .expect("Failed to look up user")
+        .expect("User exists");
+    assert!(stored.is_verified);
+    assert!(!verify_password(PASSWORD, &stored.password_hash).expect("Hash is valid"));
+}
+
+#[test]
+async fn test_state_is_single_use_and_bound_to_tenant() {
+    let fixture = Fixture::new(TenantSecurityPolicy::default()).await;
+    let user = fixture.add_user("erin@example.com", true).await;
+    fixture
+        .tenant_repo
+        .add_member(fixture.tenant_id, user.id, "MEMBER");
+
+    let state = fixture.authorize("erin@example.com", true).await;
+    let (_, nonce) = state.split_once('.').expect("State carries the tenant");
+    let forged = format!("{}.{}", Uuid::new_v4(), nonce);
+    let result = fixture.provider.handle_callback(CODE, &forged).await;
+    assert!(matches!(result, Err(OidcError::InvalidState)));
+
+    fixture
+        .provider
+        .handle_callback(CODE, &state)
+        .await
+        .expect("Issued state is accepted once");
+    let result = fixture.provider.handle_callback(CODE, &state).await;
+    assert!(matches!(result, Err(OidcError::InvalidState)));
+
+    // An ID token issued for another authorization request fails the nonce check
+    let stale = fixture.authorize("erin@example.com", true).await;
+    let _ = fixture.authorize("erin@example.com", true).await;
+    let result = fixture.provider.handle_callback(CODE, &stale).await;
+    assert!(matches!(result, Err(OidcError::InvalidIdToken(_))));
+}
@@ -0,0 +1,400 @@
+use acci_core::{ActionToken, ActionTokenError, ActionTokenKeys};
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+use tokio::test;
+use uuid::Uuid;
+
+use crate::config::{AuthConfig, PasswordConfig};
+use crate::models::tenant::TenantSecurityPolicy;
+use crate::models::user::{User, UserRepository, mock::MockUserRepository};
+use crate::services::session::SessionService;
+use crate::services::tenant::{MAX_PASSWORD_HISTORY_SIZE, TenantService, TenantServiceError};
+use crate::services::user::{LoginResult, PASSWORD_RESET_ACTION, UserService, UserServiceError};
+use crate::session::mock::MockSessionRepository;
+use crate::utils::jwt::JwtUtils;
+use crate::utils::password::hash_password;
+use crate::utils::password::mock::CountingPasswordVerifier;
+
+use super::mocks::MockTenantRepository;
+
+const PASSWORD: &str = "Correct-Horse-Battery-42";
+
+struct Fixture {
+    user_service: Arc<UserService>,
+    user_repo: Arc<MockUserRepository>,
+    tenant_repo: Arc<MockTenantRepository>,
+    session_repo: Arc<MockSessionRepository>,
+    action_tokens: Arc<ActionToken>,
+    password_verifier: Arc<CountingPasswordVerifier>,
+    tenant_id: Uuid,
+}
+
+impl Fixture {
+    fn new(history_size: u32) -> Self {
+        let user_repo = Arc::new(MockUserRepository::new());
+        let tenant_repo = Arc::new(MockTenantRepository::new());
+        let session_repo = Arc::new(MockSessionRepository::new());
+        let tenant_id = tenant_repo.insert_tenant_with_policy(&TenantSecurityPolicy {
+            password_max_age_days: Some(90),
+            password_warning_days: Some(14),
+            ..TenantSecurityPolicy::default()
+        });
+        // Tokens are verified without Redis; only consuming them needs it
+        let action_tokens = Arc::new(ActionToken::new(ActionTokenKeys::new(
+            "k1",
+            b"reset-secret".to_vec(),
+        )));
+
+        let config = Arc::new(AuthConfig {
+            password: PasswordConfig {
+                history_size,
+                ..PasswordConfig::default()
+            },
+            ..AuthConfig::default()
+        });
+        let session_service = Arc::new(SessionService::new(session_repo.clone(), config.clone()));
+        let password_verifier = Arc::new(CountingPasswordVerifier::new());
+        let user_service = Arc::new(
+            UserService::new(
+                user_repo.clone(),
+                Arc::new(JwtUtils::new(b"test-secret")),
+                session_service,
+                None,
+                config,
+            )
+            .with_tenant_repository(tenant_repo.clone())
+            .with_action_tokens(action_tokens.clone())
+            .with_password_verifier(password_verifier.clone()),
+        );
+
+        Self {
+            user_service,
+            user_repo,
+            tenant_repo,
+            session_repo,
+            action_tokens,
+            password_verifier,
+            tenant_id,
+        }
+    }
+
+    async fn add_user(&self, email: &str) -> User {
+        let user = User::new(email.to_string(), hash_password(PASSWORD).unwrap());
+        self.user_repo.create(&user).await.unwrap();
+        self.tenant_repo
+            .add_member(self.tenant_id, user.id, "MEMBER");
+        user
+    }
+
+    /// Let the password of `user` have been changed `age` ago
+    fn age_password(&self, user: &User, age: Duration) {
+        self.user_repo
+            .set_password_changed_at(user.id, OffsetDateTime::now_utc() - age);
+    }
+
+    fn tenant_service(&self) -> TenantService {
+        TenantService::new(
+            self.tenant_repo.clone(),
+            self.user_repo.clone(),
+            self.user_service.clone(),
+        )
+    }
+
+    async fn login(&self, email: &str, password: &str) -> Result<LoginResult, UserServiceError> {
+        self.user_service
+            .login_with_tenant(
+                Some(self.tenant_id),
+                email,
+                password,
+                None,
+                None,
+                None,
+                None,
+                false,
+            )
+            .await
+    }
+}
+
+#[test]
+async fn test_new_password_does_not_warn() {
+    let fixture = Fixture::new(5);
+    fixture.add_user("user@example.com").await;
+
+    let login = fixture.login("user@example.com", PASSWORD).await.unwrap();
+
+    assert_eq!(login.password_expires_in_days, None);
+}
+
+#[test]
+async fn test_warning_window_boundary() {
+    let fixture = Fixture::new(5);
+    let user = fixture.add_user("user@example.com").await;
+
+    // A minute before the window opens 14 days ahead of the expiry
+    fixture.age_password(&user, Duration::days(76) - Duration::minutes(1));
+    let login = fixture.login("user@example.com", PASSWORD).await.unwrap();
+    assert_eq!(login.password_expires_in_days, None);
+
+    fixture.age_password(&user, Duration::days(76) + Duration::minutes(1));
+    let login = fixture.login("user@example.com", PASSWORD).await.unwrap();
+    assert_eq!(login.password_expires_in_days, Some(13));
+}
+
+#[test]
+async fn test_expiry_boundary() {
+    let fixture = Fixture::new(5);
+    let user = fixture.add_user("user@example.com").await;
+
+    fixture.age_password(&user, Duration::days(90) - Duration::minutes(1));
+    let login = fixture.login("user@example.com", PASSWORD).await.unwrap();
+    assert_eq!(login.password_expires_in_days, Some(0));
+    let sessions = fixture.session_repo.sessions().len();
+
+    fixture.age_password(&user, Duration::days(90));
+    let result = fixture.login("user@example.com", PASSWORD).await;
+    let Err(UserServiceError::PasswordExpired {
+        reset_token: Some(reset_token),
+    }) = result
+    else {
+        panic!("Expected an expired password with a reset token");
+    };
+
+    // No session is created; the token only resets the password of this user
+    assert_eq!(fixture.session_repo.sessions().len(), sessions);
+    let claims = fixture
+        .action_tokens
+        .verify(&reset_token, PASSWORD_RESET_ACTION)
+        .unwrap();
+    assert_eq!(claims.subject, user.id.to_string());
+    assert_eq!(claims.tenant, Some(fixture.tenant_id.to_string()));
+    assert_eq!(
+        fixture.user_repo.security_events(user.id).last().unwrap().0,
+        "PASSWORD_EXPIRED"
+    );
+
+    // A wrong password still fails as usual
+    assert!(matches!(
+        fixture.login("user@example.com", "Wrong-Password-42").await,
+        Err(UserServiceError::InvalidCredentials)
+    ));
+}
+
+#[test]
+async fn test_changed_password_ends_expiry() {
+    let fixture = Fixture::new(5);
+    let user = fixture.add_user("user@example.com").await;
+    fixture.age_password(&user, Duration::days(120));
+
+    fixture
+        .user_service
+        .change_password(user.id, PASSWORD, "Fresh-Password-Value-7")
+        .await
+        .unwrap();
+
+    let login = fixture
+        .login("user@example.com", "Fresh-Password-Value-7")
+        .await
+        .unwrap();
+    assert_eq!(login.password_expires_in_days, None);
+}
+
+#[test]
+async fn test_recent_passwords_are_rejected() {
+    let fixture = Fixture::new(3);
+    let user = fixture.add_user("user@example.com").await;
+    let passwords = [
+        PASSWORD,
+        "Second-Password-Value-7",
+        "Third-Password-Value-7",
+    ];
+    for pair in passwords.windows(2) {
+        fixture
+            .user_service
+            .change_password(user.id, pair[0], pair[1])
+            .await
+            .unwrap();
+    }
+
+    // The current password and the two before it are the last three
+    for reused in passwords {
+        assert!(
+            matches!(
+                fixture
+                    .user_service
+                    .change_password(user.id, passwords[2], reused)
+                    .await,
+                Err(UserServiceError::PasswordReused)
+            ),
+            "{reused} was reused"
+        );
+    }
+
+    fixture
+        .user_service
+        .change_password(user.id, passwords[2], "Fourth-Password-Value-7")
+        .await
+        .unwrap();
+    // The initial password has left the history
+    fixture
+        .user_service
+        .change_password(user.id, "Fourth-Password-Value-7", PASSWORD)
+        .await
+        .unwrap();
+}
+
+#[test]
+async fn test_tenant_history_size_overrides_global_setting() {
+    let fixture = Fixture::new(5);
+    let user = fixture.add_user("user@example.com").await;
+    let tenant_service = fixture.tenant_service();
+    let result = tenant_service
+        .update_password_history(&fixture.tenant_id, Some(MAX_PASSWORD_HISTORY_SIZE + 1))
+        .await;
+    assert!(matches!(result, Err(TenantServiceError::InvalidInput(_))));
+    tenant_service
+        .update_password_history(&fixture.tenant_id, Some(2))
+        .await
+        .unwrap();
+    assert_eq!(
+        fixture
+            .user_service
+            .password_history_size(user.id)
+            .await
+            .unwrap(),
+        2
+    );
+
+    fixture
+        .user_service
+        .change_password(user.id, PASSWORD, "Second-Password-Value-7")
+        .await
+        .unwrap();
+    // The immediately previous password is rejected
+    let result = fixture
+        .user_service
+        .change_password(user.id, "Second-Password-Value-7", PASSWORD)
+        .await;
+    assert!(matches!(result, Err(UserServiceError::PasswordReused)));
+
+    fixture
+        .user_service
+        .change_password(user.id, "Second-Password-Value-7", "Third-Password-Value-7")
+        .await
+        .unwrap();
+    // Two passwords back is older than the history of the tenant
+    fixture
+        .user_service
+        .change_password(user.id, "Third-Password-Value-7", PASSWORD)
+        .await
+        .unwrap();
+    // Besides the current password only the one before it is kept
+    let history = fixture
+        .user_repo
+        .password_history(user.id, MAX_PASSWORD_HISTORY_SIZE as usize)
+        .await
+        .unwrap();
+    assert_eq!(history.len(), 1);
+}
+
+#[test]
+async fn test_history_check_does_the_same_work_for_every_outcome() {
+    let fixture = Fixture::new(4);
+    let user = fixture.add_user("user@example.com").await;
+
+    // The current password, then each of the four slots of the history
+    let verifications = |before: usize| fixture.password_verifier.verifications() - before;
+    let before = fixture.password_verifier.verifications();
+    fixture
+        .user_service
+        .change_password(user.id, PASSWORD, "Second-Password-Value-7")
+        .await
+        .unwrap();
+    assert_eq!(verifications(before), 5);
+
+    // Reusing the newest password takes as long as the oldest or a new one
+    for (reused, expected) in [
+        ("Second-Password-Value-7", true),
+        (PASSWORD, true),
+        ("Third-Password-Value-7", false),
+    ] {
+        let before = fixture.password_verifier.verifications();
+        let result = fixture
+            .user_service
+            .change_password(user.id, "Second-Password-Value-7", reused)
+            .await;
+        assert_eq!(verifications(before), 5, "{reused}");
+        assert_eq!(
+            matches!(result, Err(UserServiceError::PasswordReused)),
+            expected,
+            "{reused}"
+        );
+    }
+}
+
+#[test]
+async fn test_rejected_reset_does_not_consume_token() {
+    let fixture = Fixture::new(5);
+    let user = fixture.add_user("user@example.com").await;
+    fixture.age_password(&user, Duration::days(120));
+    let Err(UserServiceError::PasswordExpired {
+        reset_token: Some(reset_token),
+    }) = fixture.login("user@example.com", PASSWORD).await
+    else {
+        panic!("Expected an expired password with a reset token");
+    };
+
+    let result = fixture
+        .user_service
+        .reset_password(&reset_token, PASSWORD)
+        .await;
+    assert!(matches!(result, Err(UserServiceError::PasswordReused)));
+
+    // An acceptable password reaches the single-use check, which needs Redis
+    let result = fixture
+        .user_service
+        .reset_password(&reset_token, "Fresh-Password-Value-7")
+        .await;
+    assert!(matches!(
+        result,
+        Err(UserServiceError::ResetToken(
+            ActionTokenError::ConsumptionNotConfigured
+        ))
+    ));
+
+    let result = fixture
+        .user_service
+        .reset_password("at1.k1.forged.token", "Fresh-Password-Value-7")
+        .await;
+    assert!(matches!(result, Err(UserServiceError::ResetToken(_))));
+}
+
+#[test]
+async fn test_password_expiry_settings_are_validated() {
+    let fixture = Fixture::new(5);
+    let tenant_service = fixture.tenant_service();
+
+    for (max_age_days, warning_days) in [(Some(0), None), (None, Some(7)), (Some(30), Some(30))] {
+        let result = tenant_service
+            .update_password_expiry(&fixture.tenant_id, max_age_days, warning_days)
+            .await;
+        assert!(
+            matches!(result, Err(TenantServiceError::InvalidInput(_))),
+            "{max_age_days:?}/{warning_days:?} was accepted"
+        );
+    }
+
+    let policy = tenant_service
+        .update_password_expiry(&fixture.tenant_id, Some(30), Some(7))
+        .await
+        .unwrap();
+    assert_eq!(policy.password_max_age_days, Some(30));
+    assert_eq!(policy.password_warning_days, Some(7));
+
+    // Lifting the expiry ends the warnings as well
+    let policy = tenant_service
+        .update_password_expiry(&fixture.tenant_id, None, None)
+        .await
+        .unwrap();
+    assert_eq!(policy, TenantSecurityPolicy::default());
+}
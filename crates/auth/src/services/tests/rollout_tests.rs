@@ -0,0 +1,210 @@
+use acci_core::rollout::{RolloutFlag, RolloutFlags};
+use serde_json::json;
+use std::sync::Arc;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::config::AuthConfig;
+use crate::models::tenant::{BindingEnforcement, ClientBindingPolicy, TenantSecurityPolicy};
+use crate::security::client_binding::ClientCharacteristics;
+use crate::services::session::{
+    CLIENT_BINDING_ENFORCEMENT_FLAG, ClientBindingCheck, SESSION_TOKEN_HMAC_FLAG, SessionService,
+};
+use crate::session::mock::MockSessionRepository;
+use crate::session::types::MfaStatus;
+
+use super::mocks::MockTenantRepository;
+
+fn client(tls_fingerprint: &str) -> ClientCharacteristics {
+    ClientCharacteristics {
+        tls_fingerprint: Some(tls_fingerprint.to_string()),
+        http2: true,
+        accept_languages: vec!["en".to_string()],
+    }
+}
+
+/// Service whose tenant enforces client binding, with the rollout flags `flags` returns
+/// for the tenant
+fn binding_service(flags: impl FnOnce(Uuid) -> Option<RolloutFlags>) -> (SessionService, Uuid) {
+    let tenants = Arc::new(MockTenantRepository::new());
+    let tenant_id = tenants.insert_tenant_with_policy(&TenantSecurityPolicy {
+        client_binding: ClientBindingPolicy {
+            enabled: true,
+            mode: BindingEnforcement::Enforce,
+            ..ClientBindingPolicy::default()
+        },
+        ..TenantSecurityPolicy::default()
+    });
+    let mut service = SessionService::new(
+        Arc::new(MockSessionRepository::new()),
+        Arc::new(AuthConfig::default()),
+    )
+    .with_tenant_repository(tenants);
+    if let Some(flags) = flags(tenant_id) {
+        service = service.with_rollout_flags(Arc::new(flags));
+    }
+    (service, tenant_id)
+}
+
+/// Check a request from another client against a fresh bound session of `tenant_id`
+async fn mismatched_request(service: &SessionService, tenant_id: Uuid) -> ClientBindingCheck {
+    let (session, token) = service
+        .create_session_with_status(
+            Uuid::new_v4(),
+            None,
+            None,
+            None,
+            None,
+            Some(json!({ "tenant_id": tenant_id })),
+            false,
+            MfaStatus::None,
+        )
+        .await
+        .expect("Failed to create session");
+    service
+        .bind_client(session.id, &client("771,4865-4866,0-23"))
+        .await
+        .expect("Failed to bind session");
+    let session = service
+        .validate_session(&token)
+        .await
+        .expect("Failed to load session")
+        .expect("Session is valid");
+    let policy = service
+        .client_binding_policy(&session)
+        .await
+        .expect("Failed to load policy")
+        .expect("Tenant binds sessions");
+
+    service
+        .check_client_binding(&session, &policy, &client("771,49195-49199,0-11"))
+        .await
+        .expect("Failed to check binding")
+}
+
+#[test]
+async fn test_binding_enforcement_outside_rollout_is_monitored() {
+    let (service, tenant_id) = binding_service(|_| {
+        Some(RolloutFlags::fixed([RolloutFlag::new(
+            CLIENT_BINDING_ENFORCEMENT_FLAG,
+            0,
+        )]))
+    });
+
+    assert_eq!(
+        mismatched_request(&service, tenant_id).await,
+        ClientBindingCheck::Mismatched
+    );
+}
+
+#[test]
+async fn test_binding_enforcement_for_allowlisted_tenant() {
+    let (service, tenant_id) = binding_service(|tenant_id| {
+        Some(RolloutFlags::fixed([RolloutFlag::new(
+            CLIENT_BINDING_ENFORCEMENT_FLAG,
+            0,
+        )
+        .with_tenant(tenant_id)]))
+    });
+
+    assert_eq!(
+        mismatched_request(&service, tenant_id).await,
+        ClientBindingCheck::Rejected
+    );
+}
+
+#[test]
+async fn test_binding_is_enforced_without_rollout_flags() {
+    let (service, tenant_id) = binding_service(|_| None);
+
+    assert_eq!(
+        mismatched_request(&service, tenant_id).await,
+        ClientBindingCheck::Rejected
+    );
+}
+
+#[test]
+async fn test_tokens_of_users_in_rollout_are_hmac_hashed() {
+    let repository = Arc::new(MockSessionRepository::new());
+    let legacy_service = SessionService::new(repository.clone(), Arc::new(AuthConfig::default()));
+    let (legacy_session, legacy_token) = legacy_service
+        .create_session(Uuid::new_v4(), None, None, None, None, None, false)
+        .await
+        .expect("Failed to create session");
+
+    let service = SessionService::new(repository.clone(), Arc::new(AuthConfig::default()))
+        .with_rollout_flags(Arc::new(RolloutFlags::fixed([RolloutFlag::new(
+            SESSION_TOKEN_HMAC_FLAG,
+            100,
+        )])));
+    let (session, token) = service
+        .create_session(Uuid::new_v4(), None, None, None, None, None, false)
+        .await
+        .expect("Failed to create session");
+
+    // HMAC-SHA256 is stored hex-encoded instead of in the legacy format
+    assert!(legacy_session.token_hash.starts_with("$argon2id$"));
+    assert_eq!(session.token_hash.len(), 64);
+    assert!(session.token_hash.chars().all(|c| c.is_ascii_hexdigit()));
+
+    // Both kinds of tokens keep working
+    let validated = service
+        .validate_session(&token)
+        .await
+        .expect("Failed to validate session")
+        .expect("HMAC token is valid");
+    assert_eq!(validated.id, session.id);
+    let validated = service
+        .validate_session(&legacy_token)
+        .await
+        .expect("Failed to validate session")
+        .expect("Legacy token is still valid");
+    assert_eq!(validated.id, legacy_session.id);
+
+    // Rotating a legacy token moves the session to the HMAC
+    let rotated = service
+        .rotate_session_token(&legacy_token)
+        .await
+        .expect("Failed to rotate token")
+        .expect("Session is valid");
+    let stored = repository
+        .sessions()
+        .iter()
+        .find(|stored| stored.id == legacy_session.id)
+        .map(|stored| stored.token_hash.clone())
+        .expect("Session is stored");
+    assert_eq!(stored.len(), 64);
+    assert!(
+        service
+            .validate_session(&rotated)
+            .await
+            .expect("Failed to validate session")
+            .is_some()
+    );
+}
+
+#[test]
+async fn test_tokens_outside_rollout_keep_legacy_hash() {
+    let service = SessionService::new(
+        Arc::new(MockSessionRepository::new()),
+        Arc::new(AuthConfig::default()),
+    )
+    .with_rollout_flags(Arc::new(RolloutFlags::fixed([RolloutFlag::new(
+        SESSION_TOKEN_HMAC_FLAG,
+        0,
+    )])));
+
+    let (session, token) = service
+        .create_session(Uuid::new_v4(), None, None, None, None, None, false)
+        .await
+        .expect("Failed to create session");
+
+    assert!(session.token_hash.starts_with("$argon2id$"));
+    assert!(
+        service
+            .validate_session(&token)
+            .await
+            .expect("Failed to validate session")
+            .is_some()
+    );
+}
@@ -0,0 +1,472 @@
+use async_trait::async_trait;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use openssl::asn1::Asn1Time;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::rsa::Rsa;
+use openssl::sign::Signer;
+use openssl::x509::{X509, X509NameBuilder};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+use tokio::test;
+use uuid::Uuid;
+
+use crate::models::user::{User, UserRepository, mock::MockUserRepository};
+use crate::services::saml::{
+    AssertionReplayStore, SAML_IDP_METADATA_KEY, SamlError, SamlIdpConfig, SamlServiceProvider,
+    SamlSpConfig,
+};
+use crate::utils::password::hash_password;
+use crate::utils::xml_signature::{XmlSignatureError, canonicalize};
+
+use super::mocks::MockTenantRepository;
+
+const IDP_ENTITY_ID: &str = "https://idp.example.com/metadata";
+const SP_ENTITY_ID: &str = "https://app.example.com/saml/metadata";
+const ACS_BASE_URL: &str = "https://app.example.com/auth/saml";
+const EMAIL: &str = "alice@example.com";
+
+/// Marks where the signature is inserted; comments are not canonicalized
+const SIGNATURE_PLACEHOLDER: &str = "<!--signature-->";
+
+/// In-memory stand-in for the Redis nonce store
+#[derive(Default)]
+struct MemoryReplayStore {
+    seen: Mutex<HashSet<String>>,
+}
+
+#[async_trait]
+impl AssertionReplayStore for MemoryReplayStore {
+    async fn record(
+        &self,
+        tenant_id: &str,
+        assertion_id: &str,
+        _ttl: Duration,
+    ) -> anyhow::Result<bool> {
+        Ok(self
+            .seen
+            .lock()
+            .expect("replay store lock poisoned")
+            .insert(format!("{}:{}", tenant_id, assertion_id)))
+    }
+}
+
+/// Identity provider signing key and certificate
+struct Idp {
+    key: PKey<Private>,
+    certificate: X509,
+}
+
+impl Idp {
+    fn new() -> Self {
+        let key = PKey::from_rsa(Rsa::generate(2048).expect("Failed to generate key"))
+            .expect("Failed to wrap key");
+        let mut name = X509NameBuilder::new().expect("Failed to create name");
+        name.append_entry_by_text("CN", "idp.example.com")
+            .expect("Failed to set common name");
+        let name = name.build();
+
+        let mut builder = X509::builder().expect("Failed to create certificate");
+        builder.set_version(2).expect("Failed to set version");
+        builder
+            .set_subject_name(&name)
+            .expect("Failed to set subject");
+        builder
+            .set_issuer_name(&name)
+            .expect("Failed to set issuer");
+        builder.set_pubkey(&key).expect("Failed to set key");
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).expect("Valid time"))
+            .expect("Failed to set start");
+        builder
+            .set_not_after(&Asn1Time::days_from_now(1).expect("Valid time"))
+            .expect("Failed to set end");
+        builder
+            .sign(&key, MessageDigest::sha256())
+            .expect("Failed to sign certificate");
+
+        Self {
+            key,
+            certificate: builder.build(),
+        }
+    }
+
+    fn config(&self) -> SamlIdpConfig {
+        let pem = self
+            .certificate
+            .to_pem()
+            .expect("Failed to encode certificate");
+        SamlIdpConfig {
+            entity_id: IDP_ENTITY_ID.to_string(),
+            sso_url: "https://idp.example.com/sso".to_string(),
+            certificate: String::from_utf8(pem).expect("PEM is UTF-8"),
+            email_attribute: None,
+        }
+    }
+
+    /// Sign the element with `id`, inserting the signature at the placeholder
+    fn sign(&self, xml: &str, id: &str) -> String {
+        let document = roxmltree::Document::parse(xml).expect("Valid XML");
+        let element = document
+            .descendants()
+            .find(|node| node.attribute("ID") == Some(id))
+            .expect("Signed element exists");
+        let digest = STANDARD.encode(Sha256::digest(canonicalize(element, None, &[])));
+
+        let signed_info = format!(
+            concat!(
+                "<ds:SignedInfo>",
+                r#"<ds:CanonicalizationMethod Algorithm="http://www.w3.org/2001/10/xml-exc-c14n#"/>"#,
+                r#"<ds:SignatureMethod Algorithm="http://www.w3.org/2001/04/xmldsig-more#rsa-sha256"/>"#,
+                r##"<ds:Reference URI="#{}"><ds:Transforms>"##,
+                r#"<ds:Transform Algorithm="http://www.w3.org/2000/09/xmldsig#enveloped-signature"/>"#,
+                r#"<ds:Transform Algorithm="http://www.w3.org/2001/10/xml-exc-c14n#"/>"#,
+                "</ds:Transforms>",
+                r#"<ds:DigestMethod Algorithm="http://www.w3.org/2001/04/xmlenc#sha256"/>"#,
+                "<ds:DigestValue>{}</ds:DigestValue></ds:Reference></ds:SignedInfo>"
+            ),
+            id, digest
+        );
+        let unsigned = format!(
+            r#"<ds:Signature xmlns:ds="http://www.w3.org/2000/09/xmldsig#">{}</ds:Signature>"#,
+            signed_info
+        );
+        let signature_document = roxmltree::Document::parse(&unsigned).expect("Valid XML");
+        let signed_info_node = signature_document
+            .root_element()
+            .first_element_child()
+            .expect("SignedInfo exists");
+
+        let mut signer =
+            Signer::new(MessageDigest::sha256(), &self.key).expect("Failed to create signer");
+        signer
+            .update(canonicalize(signed_info_node, None, &[]).as_bytes())
+            .expect("Failed to sign");
+        let signature_value = STANDARD.encode(signer.sign_to_vec().expect("Failed to sign"));
+
+        let signature = format!(
+            concat!(
+                r#"<ds:Signature xmlns:ds="http://www.w3.org/2000/09/xmldsig#">{}"#,
+                "<ds:SignatureValue>{}</ds:SignatureValue></ds:Signature>"
+            ),
+            signed_info, signature_value
+        );
+        xml.replacen(SIGNATURE_PLACEHOLDER, &signature, 1)
+    }
+}
+
+/// Unsigned response with a single assertion for `email`
+fn response_xml(tenant_id: Uuid, assertion_id: &str, email: &str, audience: &str) -> String {
+    let now = OffsetDateTime::now_utc();
+    let instant = |offset: i64| {
+        (now + time::Duration::seconds(offset))
+            .format(&Rfc3339)
+            .expect("Failed to format time")
+    };
+    let acs_url = format!("{}/{}/acs", ACS_BASE_URL, tenant_id);
+
+    format!(
+        concat!(
+            r#"<samlp:Response xmlns:samlp="urn:oasis:names:tc:SAML:2.0:protocol" "#,
+            r#"xmlns:saml="urn:oasis:names:tc:SAML:2.0:assertion" ID="_response" "#,
+            r#"Version="2.0" IssueInstant="{now}" Destination="{acs}">"#,
+            "<saml:Issuer>{issuer}</saml:Issuer>",
+            r#"<samlp:Status><samlp:StatusCode Value="urn:oasis:names:tc:SAML:2.0:status:Success"/></samlp:Status>"#,
+            r#"<saml:Assertion ID="{id}" Version="2.0" IssueInstant="{now}">"#,
+            "<saml:Issuer>{issuer}</saml:Issuer>{placeholder}",
+            "<saml:Subject><saml:NameID>{email}</saml:NameID>",
+            r#"<saml:SubjectConfirmation Method="urn:oasis:names:tc:SAML:2.0:cm:bearer">"#,
+            r#"<saml:SubjectConfirmationData NotOnOrAfter="{later}" Recipient="{acs}"/>"#,
+            "</saml:SubjectConfirmation></saml:Subject>",
+            r#"<saml:Conditions NotBefore="{earlier}" NotOnOrAfter="{later}">"#,
+            "<saml:AudienceRestriction><saml:Audience>{audience}</saml:Audience>",
+            "</saml:AudienceRestriction></saml:Conditions>",
+            r#"<saml:AuthnStatement AuthnInstant="{now}"/>"#,
+            "</saml:Assertion></samlp:Response>"
+        ),
+        now = instant(0),
+        earlier = instant(-30),
+        later = instant(300),
+        acs = acs_url,
+        issuer = IDP_ENTITY_ID,
+        id = assertion_id,
+        placeholder = SIGNATURE_PLACEHOLDER,
+        email = email,
+        audience = audience,
+    )
+}
+
+struct Fixture {
+    idp: Idp,
+    provider: SamlServiceProvider,
+    user_repo: Arc<MockUserRepository>,
+    tenant_repo: Arc<MockTenantRepository>,
+    tenant_id: Uuid,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let idp = Idp::new();
+        let user_repo = Arc::new(MockUserRepository::new());
+        let tenant_repo = Arc::new(MockTenantRepository::new());
+        let tenant_id = tenant_repo.insert_tenant_with_metadata(Value::Object(Map::from_iter([(
+            SAML_IDP_METADATA_KEY.to_string(),
+            serde_json::to_value(idp.config()).expect("Failed to serialize IdP config"),
+        )])));
+        let provider = SamlServiceProvider::new(
+            SamlSpConfig {
+                entity_id: SP_ENTITY_ID.to_string(),
+                acs_base_url: ACS_BASE_URL.to_string(),
+            },
+            Arc::new(MemoryReplayStore::default()),
+            user_repo.clone(),
+            tenant_repo.clone(),
+        );
+
+        Self {
+            idp,
+            provider,
+            user_repo,
+            tenant_repo,
+            tenant_id,
+        }
+    }
+
+    async fn add_member(&self, email: &str) -> User {
+        let user = User::new(
+            email.to_string(),
+            hash_password("Correct-Horse-Battery-42").expect("Failed to hash password"),
+        );
+        self.user_repo
+            .create(&user)
+            .await
+            .expect("Failed to create user");
+        self.tenant_repo
+            .add_member(self.tenant_id, user.id, "MEMBER");
+        user
+    }
+
+    /// Response for `email` with a signed assertion, base64 encoded as posted
+    fn signed_response(&self, assertion_id: &str, email: &str) -> String {
+        let xml = response_xml(self.tenant_id, assertion_id, email, SP_ENTITY_ID);
+        STANDARD.encode(self.idp.sign(&xml, assertion_id))
+    }
+}
+
+#[test]
+async fn test_signed_assertion_resolves_tenant_member() {
+    let fixture = Fixture::new();
+    let user = fixture.add_member(EMAIL).await;
+
+    let login = fixture
+        .provider
+        .consume_response(fixture.tenant_id, &fixture.signed_response("_a1", EMAIL))
+        .await
+        .expect("Signed assertion should be accepted");
+
+    assert_eq!(login.user.id, user.id);
+    assert_eq!(login.tenant_id, fixture.tenant_id);
+    assert_eq!(login.name_id, EMAIL);
+
+    // Non-members are not resolved even with a valid assertion
+    let other = "mallory@example.com";
+    fixture
+        .user_repo
+        .create(&User::new(
+            other.to_string(),
+            hash_password("Correct-Horse-Battery-42").expect("Failed to hash password"),
+        ))
+        .await
+        .expect("Failed to create user");
+    let result = fixture
+        .provider
+        .consume_response(fixture.tenant_id, &fixture.signed_response("_a2", other))
+        .await;
+    assert!(matches!(result, Err(SamlError::NotTenantMember)));
+}
+
+#[test]
+async fn test_tampered_assertion_is_rejected() {
+    let fixture = Fixture::new();
+    fixture.add_member(EMAIL).await;
+    fixture.add_member("admin@example.com").await;
+
+    let xml = response_xml(fixture.tenant_id, "_a1", EMAIL, SP_ENTITY_ID);
+    let tampered = fixture
+        .idp
+        .sign(&xml, "_a1")
+        .replace(EMAIL, "admin@example.com");
+
+    let result = fixture
+        .provider
+        .consume_response(fixture.tenant_id, &STANDARD.encode(tampered))
+        .await;
+    assert!(matches!(
+        result,
+        Err(SamlError::InvalidSignature(
+            XmlSignatureError::DigestMismatch
+        ))
+    ));
+
+    // A signature by another key does not verify either
+    let impostor = Idp::new();
+    let forged = impostor.sign(&xml, "_a1");
+    let result = fixture
+        .provider
+        .consume_response(fixture.tenant_id, &STANDARD.encode(forged))
+        .await;
+    assert!(matches!(
+        result,
+        Err(SamlError::InvalidSignature(
+            XmlSignatureError::InvalidSignature
+        ))
+    ));
+}
+
+#[test]
+async fn test_unsigned_and_wrapped_assertions_are_rejected() {
+    let fixture = Fixture::new();
+    fixture.add_member(EMAIL).await;
+
+    let unsigned = response_xml(fixture.tenant_id, "_a1", EMAIL, SP_ENTITY_ID);
+    let result = fixture
+        .provider
+        .consume_response(fixture.tenant_id, &STANDARD.encode(&unsigned))
+        .await;
+    assert!(matches!(result, Err(SamlError::InvalidSignature(_))));
+
+    // A signed assertion next to an unsigned one must not vouch for it
+    let signed = fixture.idp.sign(&unsigned, "_a1");
+    let start = unsigned.find("<saml:Assertion").expect("Assertion exists");
+    let evil = unsigned[start..]
+        .replace("_a1", "_evil")
+        .replace(SIGNATURE_PLACEHOLDER, "")
+        .replace("</samlp:Response>", "");
+    let wrapped = signed.replace("</samlp:Response>", &format!("{}</samlp:Response>", evil));
+    let result = fixture
+        .provider
+        .consume_response(fixture.tenant_id, &STANDARD.encode(wrapped))
+        .await;
+    assert!(matches!(result, Err(SamlError::InvalidResponse(_))));
+}
+
+#[test]
+async fn test_comment_split_name_id_is_rejected() {
+    let fixture = Fixture::new();
+    fixture.add_member(EMAIL).await;
+
+    // Comments are not canonicalized, so the signature covers both halves
+    let split = format!("{}<!---->.evil.com", EMAIL);
+    let result = fixture
+        .provider
+        .consume_response(fixture.tenant_id, &fixture.signed_response("_a1", &split))
+        .await;
+    assert!(matches!(result, Err(SamlError::InvalidResponse(_))));
+}
+
+#[test]
+async fn test_replayed_assertion_is_rejected() {
+    let fixture = Fixture::new();
+    fixture.add_member(EMAIL).await;
+    let response = fixture.signed_response("_a1", EMAIL);
+
+    fixture
+        .provider
+        .consume_response(fixture.tenant_id, &response)
+        .await
+        .expect("First use should be accepted");
+    let result = fixture
+        .provider
+        .consume_response(fixture.tenant_id, &response)
+        .await;
+    assert!(matches!(result, Err(SamlError::Replayed)));
+}
+
+#[test]
+async fn test_conditions_are_enforced() {
+    let fixture = Fixture::new();
+    fixture.add_member(EMAIL).await;
+
+    let xml = response_xml(fixture.tenant_id, "_a1", EMAIL, "https://other.example.com");
+    let result = fixture
+        .provider
+        .consume_response(
+            fixture.tenant_id,
+            &STANDARD.encode(fixture.idp.sign(&xml, "_a1")),
+        )
+        .await;
+    assert!(matches!(result, Err(SamlError::InvalidConditions(_))));
+
+    // Responses for one tenant's consumer service are not accepted by another
+    let other_tenant = fixture
+        .tenant_repo
+        .insert_tenant_with_metadata(Value::Object(Map::from_iter([(
+            SAML_IDP_METADATA_KEY.to_string(),
+            serde_json::to_value(fixture.idp.config()).expect("Failed to serialize IdP config"),
+        )])));
+    let result = fixture
+        .provider
+        .consume_response(other_tenant, &fixture.signed_response("_a2", EMAIL))
+        .await;
+    assert!(matches!(result, Err(SamlError::InvalidResponse(_))));
+}
+
+#[test]
+async fn test_authn_request_and_metadata() {
+    let fixture = Fixture::new();
+
+    let url = fixture
+        .provider
+        .authn_request_url(fixture.tenant_id)
+        .await
+        .expect("Failed to build AuthnRequest");
+    let encoded = url
+        .strip_prefix("https://idp.example.com/sso?SAMLRequest=")
+        .expect("Redirects to the IdP");
+    let deflated = STANDARD
+        .decode(
+            urlencoding::decode(encoded)
+                .expect("URL encoded")
+                .as_bytes(),
+        )
+        .expect("Base64 encoded");
+    let request =
+        String::from_utf8(miniz_oxide::inflate::decompress_to_vec(&deflated).expect("Deflated"))
+            .expect("UTF-8");
+    assert!(request.contains(&format!(
+        r#"AssertionConsumerServiceURL="{}/{}/acs""#,
+        ACS_BASE_URL, fixture.tenant_id
+    )));
+    assert!(request.contains(&format!("<saml:Issuer>{}</saml:Issuer>", SP_ENTITY_ID)));
+
+    let certificate = STANDARD.encode(
+        fixture
+            .idp
+            .certificate
+            .to_der()
+            .expect("Failed to encode certificate"),
+    );
+    let metadata = format!(
+        concat!(
+            r#"<md:EntityDescriptor xmlns:md="urn:oasis:names:tc:SAML:2.0:metadata" entityID="{}">"#,
+            r#"<md:IDPSSODescriptor protocolSupportEnumeration="urn:oasis:names:tc:SAML:2.0:protocol">"#,
+            r#"<md:KeyDescriptor use="signing"><ds:KeyInfo xmlns:ds="http://www.w3.org/2000/09/xmldsig#">"#,
+            "<ds:X509Data><ds:X509Certificate>{}</ds:X509Certificate></ds:X509Data>",
+            "</ds:KeyInfo></md:KeyDescriptor>",
+            r#"<md:SingleSignOnService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST" "#,
+            r#"Location="https://idp.example.com/post"/>"#,
+            r#"<md:SingleSignOnService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect" "#,
+            r#"Location="https://idp.example.com/sso"/>"#,
+            "</md:IDPSSODescriptor></md:EntityDescriptor>"
+        ),
+        IDP_ENTITY_ID, certificate
+    );
+    let config = SamlIdpConfig::from_metadata_xml(&metadata).expect("Failed to read metadata");
+    assert_eq!(config.entity_id, IDP_ENTITY_ID);
+    assert_eq!(config.sso_url, "https://idp.example.com/sso");
+    assert_eq!(config.certificate, certificate);
+}
@@ -0,0 +1,458 @@
+use std::sync::Arc;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::config::AuthConfig;
+use crate::models::tenant::TenantRepository;
+use crate::models::user::{User, UserRepository, mock::MockUserRepository};
+use crate::services::scim::{
+    ScimError, ScimGroup, ScimMember, ScimPage, ScimPatchRequest, ScimService, ScimUser,
+};
+use crate::services::session::SessionService;
+use crate::services::tenant::TenantService;
+use crate::services::user::UserService;
+use crate::session::mock::MockSessionRepository;
+use crate::utils::jwt::JwtUtils;
+use crate::utils::password::{NO_PASSWORD_HASH, hash_password};
+
+use super::mocks::MockTenantRepository;
+
+const PASSWORD: &str = "Correct-Horse-Battery-42";
+
+struct Fixture {
+    scim_service: ScimService,
+    user_service: Arc<UserService>,
+    user_repo: Arc<MockUserRepository>,
+    tenant_repo: Arc<MockTenantRepository>,
+    session_repo: Arc<MockSessionRepository>,
+    tenant_id: Uuid,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let user_repo = Arc::new(MockUserRepository::new());
+        let tenant_repo = Arc::new(MockTenantRepository::new());
+        let session_repo = Arc::new(MockSessionRepository::new());
+        let tenant_id = tenant_repo
+            .insert_tenant_with_metadata(serde_json::Value::Object(serde_json::Map::new()));
+
+        let config = Arc::new(AuthConfig::default());
+        let session_service = Arc::new(SessionService::new(session_repo.clone(), config.clone()));
+        let user_service = Arc::new(
+            UserService::new(
+                user_repo.clone(),
+                Arc::new(JwtUtils::new(b"test-secret")),
+                session_service,
+                None,
+                config,
+            )
+            .with_tenant_repository(tenant_repo.clone()),
+        );
+        let tenant_service = Arc::new(TenantService::new(
+            tenant_repo.clone(),
+            user_repo.clone(),
+            user_service.clone(),
+        ));
+
+        Self {
+            scim_service: ScimService::new(user_service.clone(), tenant_service),
+            user_service,
+            user_repo,
+            tenant_repo,
+            session_repo,
+            tenant_id,
+        }
+    }
+
+    async fn provision(&self, email: &str) -> ScimUser {
+        self.scim_service
+            .create_user(self.tenant_id, user_resource(email))
+            .await
+            .expect("User is provisioned")
+    }
+
+    async fn stored_user(&self, id: &str) -> User {
+        let id = Uuid::parse_str(id).expect("SCIM IDs are user IDs");
+        self.user_repo
+            .find_by_id(id)
+            .await
+            .expect("User is loaded")
+            .expect("User exists")
+    }
+}
+
+fn user_resource(email: &str) -> ScimUser {
+    serde_json::from_value(serde_json::Value::Object(serde_json::Map::from_iter([
+        (
+            "schemas".to_string(),
+            vec!["urn:ietf:params:scim:schemas:core:2.0:User"].into(),
+        ),
+        ("userName".to_string(), email.into()),
+        ("displayName".to_string(), "Jane Doe".into()),
+    ])))
+    .expect("User resource deserializes")
+}
+
+fn patch(body: &str) -> ScimPatchRequest {
+    serde_json::from_str(body).expect("Patch deserializes")
+}
+
+fn id_of(user: &ScimUser) -> String {
+    user.id.clone().expect("Resource has an ID")
+}
+
+#[test]
+async fn test_provisioning_creates_local_member() {
+    let fixture = Fixture::new();
+
+    let created = fixture.provision("jane@example.com").await;
+    assert!(created.active);
+    assert_eq!(created.user_name, "jane@example.com");
+    assert_eq!(created.display_name.as_deref(), Some("Jane Doe"));
+    assert!(
+        created
+            .meta
+            .as_ref()
+            .is_some_and(|meta| meta.version.starts_with("W/\""))
+    );
+
+    let user = fixture.stored_user(&id_of(&created)).await;
+    assert_eq!(user.email, "jane@example.com");
+    assert_eq!(user.password_hash, NO_PASSWORD_HASH);
+    assert!(user.is_active);
+
+    let memberships = fixture
+        .tenant_repo
+        .get_tenant_users(fixture.tenant_id)
+        .await
+        .expect("Members are listed");
+    assert_eq!(memberships.len(), 1);
+    assert_eq!(memberships[0].user_id, user.id);
+    assert_eq!(memberships[0].tenant_role, "MEMBER");
+
+    let duplicate = fixture
+        .scim_service
+        .create_user(fixture.tenant_id, user_resource("jane@example.com"))
+        .await;
+    assert!(matches!(duplicate, Err(ScimError::Uniqueness)));
+}
+
+#[test]
+async fn test_patch_active_false_deactivates_and_ends_sessions() {
+    let fixture = Fixture::new();
+    let created = fixture.provision("jane@example.com").await;
+    let id = id_of(&created);
+
+    // Give the provisioned account a password so it can hold a session
+    let mut user = fixture.stored_user(&id).await;
+    user.password_hash = hash_password(PASSWORD).expect("Password hashes");
+    fixture
+        .user_repo
+        .update(&user)
+        .await
+        .expect("User is updated");
+    let login = fixture
+        .user_service
+        .login_with_tenant(
+            Some(fixture.tenant_id),
+            &user.email,
+            PASSWORD,
+            None,
+            None,
+            None,
+            None,
+            false,
+        )
+        .await
+        .expect("Login succeeds");
+
+    let patched = fixture
+        .scim_service
+        .patch_user(
+            fixture.tenant_id,
+            &id,
+            patch(
+                r#"{
+                    "schemas": ["urn:ietf:params:scim:api:messages:2.0:PatchOp"],
+                    "Operations": [{"op": "Replace", "path": "active", "value": "False"}]
+                }"#,
+            ),
+            None,
+        )
+        .await
+        .expect("Patch applies");
+
+    assert!(!patched.active);
+    assert!(!fixture.stored_user(&id).await.is_active);
+    assert!(
+        fixture
+            .session_repo
+            .sessions()
+            .iter()
+            .all(|session| !session.is_valid)
+    );
+    assert!(
+        fixture
+            .user_service
+            .validate_session(&login.session_token)
+            .await
+            .expect("Session lookup succeeds")
+            .is_none()
+    );
+}
+
+#[test]
+async fn test_patch_with_stale_etag_is_rejected() {
+    let fixture = Fixture::new();
+    let created = fixture.provision("jane@example.com").await;
+    let id = id_of(&created);
+    let version = created.meta.expect("Resource has meta").version;
+
+    let renamed = fixture
+        .scim_service
+        .patch_user(
+            fixture.tenant_id,
+            &id,
+            patch(r#"{"Operations": [{"op": "replace", "value": {"displayName": "Jane Roe"}}]}"#),
+            Some(&version),
+        )
+        .await
+        .expect("Patch with current version applies");
+    assert_eq!(renamed.display_name.as_deref(), Some("Jane Roe"));
+    assert_ne!(renamed.meta.expect("Resource has meta").version, version);
+
+    let stale = fixture
+        .scim_service
+        .patch_user(
+            fixture.tenant_id,
+            &id,
+            patch(r#"{"Operations": [{"op": "replace", "path": "active", "value": false}]}"#),
+            Some(&version),
+        )
+        .await;
+    assert!(matches!(stale, Err(ScimError::PreconditionFailed)));
+    assert!(fixture.stored_user(&id).await.is_active);
+}
+
+#[test]
+async fn test_filter_by_user_name() {
+    let fixture = Fixture::new();
+    fixture.provision("jane@example.com").await;
+    fixture.provision("john@example.com").await;
+
+    let all = fixture
+        .scim_service
+        .list_users(fixture.tenant_id, None, ScimPage::default())
+        .await
+        .expect("Users are listed");
+    assert_eq!(all.total_results, 2);
+
+    let filtered = fixture
+        .scim_service
+        .list_users(
+            fixture.tenant_id,
+            Some(r#"userName eq "JOHN@example.com""#),
+            ScimPage::default(),
+        )
+        .await
+        .expect("Users are listed");
+    assert_eq!(filtered.total_results, 1);
+    assert_eq!(filtered.resources[0].user_name, "john@example.com");
+
+    let unsupported = fixture
+        .scim_service
+        .list_users(
+            fixture.tenant_id,
+            Some(r#"emails eq "john@example.com""#),
+            ScimPage::default(),
+        )
+        .await;
+    assert!(matches!(unsupported, Err(ScimError::InvalidFilter(_))));
+}
+
+#[test]
+async fn test_groups_assign_tenant_roles() {
+    let fixture = Fixture::new();
+    let jane = id_of(&fixture.provision("jane@example.com").await);
+    let john = id_of(&fixture.provision("john@example.com").await);
+
+    let group = fixture
+        .scim_service
+        .create_group(
+            fixture.tenant_id,
+            ScimGroup {
+                schemas: Vec::new(),
+                id: None,
+                display_name: "ADMIN".to_string(),
+                members: vec![ScimMember {
+                    value: jane.clone(),
+                    display: None,
+                }],
+                meta: None,
+            },
+        )
+        .await
+        .expect("Group is created");
+    assert_eq!(group.id.as_deref(), Some("ADMIN"));
+
+    let jane_user = fixture
+        .scim_service
+        .get_user(fixture.tenant_id, &jane)
+        .await
+        .expect("User is found");
+    assert_eq!(jane_user.groups[0].value, "ADMIN");
+
+    // The last administrator cannot be removed through the group
+    let result = fixture
+        .scim_service
+        .patch_group(
+            fixture.tenant_id,
+            "ADMIN",
+            patch(&format!(
+                r#"{{"Operations": [{{"op": "remove", "path": "members[value eq \"{}\"]"}}]}}"#,
+                jane
+            )),
+            None,
+        )
+        .await;
+    assert!(matches!(result, Err(ScimError::Mutability(_))));
+
+    let group = fixture
+        .scim_service
+        .patch_group(
+            fixture.tenant_id,
+            "ADMIN",
+            patch(&format!(
+                r#"{{"Operations": [{{"op": "add", "path": "members", "value": [{{"value": "{}"}}]}}]}}"#,
+                john
+            )),
+            None,
+        )
+        .await
+        .expect("Member is added");
+    assert_eq!(group.members.len(), 2);
+}
+
+#[test]
+async fn test_token_authenticates_its_tenant() {
+    let fixture = Fixture::new();
+    let token = fixture
+        .scim_service
+        .issue_token(fixture.tenant_id)
+        .await
+        .expect("Token is issued");
+
+    let tenant_id = fixture
+        .scim_service
+        .authenticate(&token)
+        .await
+        .expect("Token is valid");
+    assert_eq!(tenant_id, fixture.tenant_id);
+
+    for invalid in [
+        format!("{}x", token),
+        format!("{}.secret", fixture.tenant_id),
+        format!("{}.secret", Uuid::new_v4()),
+        "not-a-token".to_string(),
+    ] {
+        let result = fixture.scim_service.authenticate(&invalid).await;
+        assert!(matches!(result, Err(ScimError::InvalidToken)));
+    }
+}
+
+#[test]
+async fn test_token_of_another_tenant_cannot_take_over_account() {
+    let fixture = Fixture::new();
+    let other_tenant = fixture
+        .tenant_repo
+        .insert_tenant_with_metadata(serde_json::Value::Object(serde_json::Map::new()));
+    let user = User::new(
+        "alice@example.com".to_string(),
+        hash_password(PASSWORD).expect("Password hashes"),
+    );
+    fixture
+        .user_repo
+        .create(&user)
+        .await
+        .expect("User is created");
+    fixture
+        .tenant_repo
+        .add_member(other_tenant, user.id, "ADMIN");
+    let login = fixture
+        .user_service
+        .login_with_tenant(
+            Some(other_tenant),
+            &user.email,
+            PASSWORD,
+            None,
+            None,
+            None,
+            None,
+            false,
+        )
+        .await
+        .expect("Login succeeds");
+
+    // The account of another tenant is not linked by its email address
+    let linked = fixture
+        .scim_service
+        .create_user(fixture.tenant_id, user_resource(&user.email))
+        .await;
+    assert!(matches!(linked, Err(ScimError::Uniqueness)));
+
+    // A shared account keeps its profile, state and sessions elsewhere
+    fixture
+        .tenant_repo
+        .add_member(fixture.tenant_id, user.id, "MEMBER");
+    let id = user.id.to_string();
+    let renamed = fixture
+        .scim_service
+        .patch_user(
+            fixture.tenant_id,
+            &id,
+            patch(r#"{"Operations": [{"op": "replace", "path": "userName", "value": "mallory@example.com"}]}"#),
+            None,
+        )
+        .await;
+    assert!(matches!(renamed, Err(ScimError::Mutability(_))));
+    assert_eq!(fixture.stored_user(&id).await.email, "alice@example.com");
+
+    let deprovisioned = fixture
+        .scim_service
+        .patch_user(
+            fixture.tenant_id,
+            &id,
+            patch(r#"{"Operations": [{"op": "replace", "path": "active", "value": false}]}"#),
+            None,
+        )
+        .await
+        .expect("Membership is deactivated");
+    assert!(!deprovisioned.active);
+    assert!(fixture.stored_user(&id).await.is_active);
+    assert!(
+        fixture
+            .user_service
+            .validate_session(&login.session_token)
+            .await
+            .expect("Session lookup succeeds")
+            .is_some()
+    );
+
+    // An account deactivated elsewhere is not reactivated
+    fixture
+        .user_repo
+        .deactivate(user.id)
+        .await
+        .expect("User is deactivated");
+    fixture
+        .scim_service
+        .patch_user(
+            fixture.tenant_id,
+            &id,
+            patch(r#"{"Operations": [{"op": "replace", "path": "active", "value": true}]}"#),
+            None,
+        )
+        .await
+        .expect("Membership is reactivated");
+    assert!(!fixture.stored_user(&id).await.is_active);
+}
@@ -0,0 +1,117 @@
+use serde_json::{Value, json};
+use std::sync::Arc;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::config::AuthConfig;
+use crate::services::session::{SessionService, SessionServiceError};
+use crate::session::mock::MockSessionRepository;
+use crate::session::types::MfaStatus;
+use crate::utils::bounded_json::{BoundedJson, JsonLimitViolation, JsonLimits};
+
+struct Fixture {
+    session_service: SessionService,
+    session_repo: Arc<MockSessionRepository>,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let session_repo = Arc::new(MockSessionRepository::new());
+        let config = AuthConfig {
+            metadata_limits: JsonLimits {
+                max_bytes: 256,
+                max_depth: 3,
+                max_keys: 32,
+            },
+            ..AuthConfig::default()
+        };
+        let session_service = SessionService::new(session_repo.clone(), Arc::new(config));
+        Self {
+            session_service,
+            session_repo,
+        }
+    }
+
+    async fn create(&self, metadata: Option<Value>) -> Result<(), SessionServiceError> {
+        self.session_service
+            .create_session_with_status(
+                Uuid::new_v4(),
+                None,
+                None,
+                None,
+                None,
+                metadata,
+                false,
+                MfaStatus::None,
+            )
+            .await
+            .map(|_| ())
+    }
+
+    fn stored_sessions(&self) -> usize {
+        self.session_repo.sessions().len()
+    }
+}
+
+#[test]
+async fn test_small_metadata_is_accepted() {
+    let fixture = Fixture::new();
+
+    let (session, _) = fixture
+        .session_service
+        .create_session(
+            Uuid::new_v4(),
+            None,
+            None,
+            None,
+            None,
+            Some(BoundedJson::new(json!({ "locale": "de-AT" })).unwrap()),
+            false,
+        )
+        .await
+        .unwrap();
+    assert_eq!(session.metadata.unwrap()["locale"], json!("de-AT"));
+
+    // Missing, null and empty metadata are fine as well
+    fixture.create(None).await.unwrap();
+    fixture.create(Some(Value::Null)).await.unwrap();
+    fixture.create(Some(json!({}))).await.unwrap();
+    assert_eq!(fixture.stored_sessions(), 4);
+}
+
+#[test]
+async fn test_oversized_metadata_is_rejected_before_writing() {
+    let fixture = Fixture::new();
+
+    let result = fixture
+        .create(Some(json!({ "note": "x".repeat(300) })))
+        .await;
+    let Err(SessionServiceError::InvalidMetadata(err)) = result else {
+        panic!(
+            "Expected oversized metadata to be rejected, got {:?}",
+            result
+        );
+    };
+    assert!(matches!(
+        err.violations[..],
+        [JsonLimitViolation::MaxBytes { max: 256, .. }]
+    ));
+    assert_eq!(fixture.stored_sessions(), 0);
+}
+
+#[test]
+async fn test_too_deep_metadata_is_rejected_before_writing() {
+    let fixture = Fixture::new();
+
+    let result = fixture
+        .create(Some(json!({ "a": { "b": { "c": { "d": 1 } } } })))
+        .await;
+    let Err(SessionServiceError::InvalidMetadata(err)) = result else {
+        panic!("Expected nested metadata to be rejected, got {:?}", result);
+    };
+    assert_eq!(
+        err.violations,
+        vec![JsonLimitViolation::MaxDepth { actual: 4, max: 3 }]
+    );
+    assert_eq!(fixture.stored_sessions(), 0);
+}
@@ -0,0 +1,225 @@
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::config::{AuthConfig, SessionReconciliationConfig};
+use crate::models::user::{User, UserRepository, mock::MockUserRepository};
+use crate::services::session::{SessionService, SessionServiceError};
+use crate::services::user::UserService;
+use crate::session::SessionRepository;
+use crate::session::mock::MockSessionRepository;
+use crate::session::types::SessionInvalidationReason;
+use crate::utils::jwt::JwtUtils;
+use crate::utils::password::hash_password;
+
+struct Fixture {
+    user_service: UserService,
+    session_service: Arc<SessionService>,
+    user_repo: Arc<MockUserRepository>,
+    session_repo: Arc<MockSessionRepository>,
+}
+
+impl Fixture {
+    fn new(batch_size: u32) -> Self {
+        let user_repo = Arc::new(MockUserRepository::new());
+        let session_repo = Arc::new(MockSessionRepository::new());
+        let config = Arc::new(AuthConfig {
+            session_reconciliation: SessionReconciliationConfig {
+                batch_size,
+                ..SessionReconciliationConfig::default()
+            },
+            ..AuthConfig::default()
+        });
+        let session_service = Arc::new(
+            SessionService::new(session_repo.clone(), config.clone())
+                .with_user_repository(user_repo.clone()),
+        );
+        let user_service = UserService::new(
+            user_repo.clone(),
+            Arc::new(JwtUtils::new(b"test-secret")),
+            session_service.clone(),
+            None,
+            config,
+        );
+
+        Self {
+            user_service,
+            session_service,
+            user_repo,
+            session_repo,
+        }
+    }
+
+    async fn add_user(&self, email: &str) -> User {
+        let user = User::new(email.to_string(), hash_password("Old-Password-42").unwrap());
+        self.user_repo.create(&user).await.unwrap();
+        user
+    }
+
+    async fn add_sessions(&self, user_id: Uuid, count: usize) -> Vec<Uuid> {
+        let mut ids = Vec::new();
+        for _ in 0..count {
+            let (session, _) = self
+                .session_service
+                .create_session(user_id, None, None, None, None, None, false)
+                .await
+                .unwrap();
+            ids.push(session.id);
+        }
+        ids
+    }
+
+    fn reasons(&self, user_id: Uuid) -> Vec<Option<SessionInvalidationReason>> {
+        self.session_repo
+            .sessions()
+            .iter()
+            .filter(|s| s.user_id == user_id)
+            .map(|s| (!s.is_valid).then(|| s.invalidated_reason.clone().unwrap()))
+            .collect()
+    }
+}
+
+#[test]
+async fn test_deactivate_user_invalidates_sessions_immediately() {
+    let fixture = Fixture::new(100);
+    let user = fixture.add_user("leaver@example.com").await;
+    let other = fixture.add_user("stayer@example.com").await;
+    fixture.add_sessions(user.id, 3).await;
+    fixture.add_sessions(other.id, 1).await;
+
+    fixture.user_service.deactivate_user(user.id).await.unwrap();
+
+    assert_eq!(
+        fixture.reasons(user.id),
+        vec![Some(SessionInvalidationReason::UserDeactivated); 3]
+    );
+    assert_eq!(fixture.reasons(other.id), vec![None]);
+}
+
+#[test]
+async fn test_reconciliation_invalidates_sessions_of_deactivated_user() {
+    let fixture = Fixture::new(2);
+    let user = fixture.add_user("leaver@example.com").await;
+    let other = fixture.add_user("stayer@example.com").await;
+    fixture.add_sessions(user.id, 3).await;
+    fixture.add_sessions(other.id, 2).await;
+
+    // Deactivated behind the service's back, e.g. directly in the database
+    fixture.user_repo.deactivate(user.id).await.unwrap();
+    assert_eq!(fixture.reasons(user.id), vec![None; 3]);
+
+    let report = fixture.session_service.reconcile_sessions().await.unwrap();
+    assert_eq!(report.sessions_checked, 5);
+    assert_eq!(report.invalidated_user_deactivated, 3);
+    assert_eq!(report.invalidated_password_changed, 0);
+    assert!(!report.resumed);
+    assert_eq!(
+        fixture.reasons(user.id),
+        vec![Some(SessionInvalidationReason::UserDeactivated); 3]
+    );
+    assert_eq!(fixture.reasons(other.id), vec![None; 2]);
+
+    // The pass is complete and its report is kept for the health endpoint
+    assert_eq!(
+        fixture.session_repo.reconciliation_cursor().await.unwrap(),
+        None
+    );
+    assert_eq!(fixture.session_service.last_reconciliation(), Some(report));
+}
+
+#[test]
+async fn test_reconciliation_invalidates_sessions_older_than_password_change() {
+    let fixture = Fixture::new(100);
+    let user = fixture.add_user("user@example.com").await;
+    let old_sessions = fixture.add_sessions(user.id, 2).await;
+    for session in fixture.session_repo.sessions().iter_mut() {
+        session.created_at = OffsetDateTime::now_utc() - time::Duration::hours(1);
+    }
+
+    let mut changed = user.clone();
+    changed.password_hash = hash_password("New-Password-42").unwrap();
+    changed.updated_at = OffsetDateTime::now_utc() - time::Duration::minutes(1);
+    fixture.user_repo.update(&changed).await.unwrap();
+    let new_session = fixture.add_sessions(user.id, 1).await[0];
+
+    let report = fixture.session_service.reconcile_sessions().await.unwrap();
+    assert_eq!(report.invalidated_password_changed, 2);
+    assert_eq!(report.invalidated_user_deactivated, 0);
+
+    let sessions = fixture.session_repo.sessions();
+    for session in sessions.iter() {
+        if old_sessions.contains(&session.id) {
+            assert!(!session.is_valid);
+            assert_eq!(
+                session.invalidated_reason,
+                Some(SessionInvalidationReason::PasswordChanged)
+            );
+        } else {
+            assert_eq!(session.id, new_session);
+            assert!(session.is_valid);
+        }
+    }
+}
+
+#[test]
+async fn test_reconciliation_resumes_from_persisted_cursor() {
+    let fixture = Fixture::new(2);
+    let user = fixture.add_user("leaver@example.com").await;
+    let mut ids = fixture.add_sessions(user.id, 5).await;
+    ids.sort();
+    fixture.user_repo.deactivate(user.id).await.unwrap();
+
+    // A pass that stopped after its first batch
+    fixture
+        .session_repo
+        .save_reconciliation_cursor(Some(ids[1]))
+        .await
+        .unwrap();
+
+    let report = fixture.session_service.reconcile_sessions().await.unwrap();
+    assert!(report.resumed);
+    assert_eq!(report.sessions_checked, 3);
+    assert_eq!(report.invalidated_user_deactivated, 3);
+    let valid: Vec<Uuid> = fixture
+        .session_repo
+        .sessions()
+        .iter()
+        .filter(|s| s.is_valid)
+        .map(|s| s.id)
+        .collect();
+    assert_eq!(valid.len(), 2);
+    assert!(valid.iter().all(|id| *id <= ids[1]));
+
+    // The next pass starts from the beginning
+    let report = fixture.session_service.reconcile_sessions().await.unwrap();
+    assert!(!report.resumed);
+    assert_eq!(report.invalidated_user_deactivated, 2);
+    assert_eq!(
+        fixture.reasons(user.id),
+        vec![Some(SessionInvalidationReason::UserDeactivated); 5]
+    );
+}
+
+#[test]
+async fn test_reconciliation_errors() {
+    let fixture = Fixture::new(2);
+    let user = fixture.add_user("user@example.com").await;
+    fixture.add_sessions(user.id, 3).await;
+    fixture.session_repo.fail_on("valid_sessions_after");
+
+    assert!(matches!(
+        fixture.session_service.reconcile_sessions().await,
+        Err(SessionServiceError::Repository(_))
+    ));
+    assert_eq!(fixture.session_service.last_reconciliation(), None);
+
+    let without_users = SessionService::new(
+        fixture.session_repo.clone(),
+        Arc::new(AuthConfig::default()),
+    );
+    assert!(matches!(
+        without_users.reconcile_sessions().await,
+        Err(SessionServiceError::ReconciliationUnavailable)
+    ));
+}
@@ -1,235 +1,21 @@
-use std::sync::{Arc, Mutex};
-use std::time::SystemTime;
+use acci_core::error::ErrorKind;
+use std::sync::Arc;
 use tokio::test;
 use uuid::Uuid;
 
 use crate::models::{TenantId, UserId, VerificationStatus, VerificationType};
+use crate::repository::TenantAwareContext;
+use crate::repository::verification_repository::mock::MockVerificationCodeRepository;
+use crate::services::message_provider::MockMessageProvider;
+use crate::services::mfa::{MfaOrchestrator, MfaOrchestratorError};
 use crate::services::session::SessionService;
 use crate::services::verification::VerificationService;
-use crate::session::types::{DeviceFingerprint, MfaStatus, SessionInvalidationReason};
-use crate::session::{Session, SessionError, SessionFilter, SessionRepository};
+use crate::session::mock::MockSessionRepository;
+use crate::session::types::MfaStatus;
+use crate::session::{SessionError, SessionRepository};
+use crate::utils::duration::DurationSecs;
 
 use super::mocks::MockTenantAwareContext;
-use super::verification_tests::{MockMessageProvider, MockVerificationCodeRepository};
-
-// Mock implementation of SessionRepository
-struct MockSessionRepository {
-    sessions: Arc<Mutex<Vec<Session>>>,
-    last_accessed_at: Arc<Mutex<SystemTime>>,
-}
-
-impl MockSessionRepository {
-    fn new() -> Self {
-        Self {
-            sessions: Arc::new(Mutex::new(Vec::new())),
-            last_accessed_at: Arc::new(Mutex::new(SystemTime::now())),
-        }
-    }
-}
-
-#[async_trait::async_trait]
-impl SessionRepository for MockSessionRepository {
-    async fn create_session(
-        &self,
-        user_id: Uuid,
-        token_hash: String,
-        expires_at: SystemTime,
-        device_id: Option<String>,
-        device_fingerprint: Option<DeviceFingerprint>,
-        ip_address: Option<String>,
-        user_agent: Option<String>,
-        metadata: Option<serde_json::Value>,
-    ) -> std::result::Result<Session, SessionError> {
-        let now = SystemTime::now();
-        let session = Session {
-            id: Uuid::new_v4(),
-            user_id,
-            token_hash,
-            previous_token_hash: None,
-            token_rotation_at: None,
-            expires_at,
-            created_at: now,
-            last_activity_at: now,
-            last_activity_update_at: None,
-            ip_address,
-            user_agent,
-            device_id,
-            device_fingerprint,
-            is_valid: true,
-            invalidated_reason: None,
-            metadata,
-            mfa_status: MfaStatus::None,
-        };
-
-        let mut sessions = self.sessions.lock().unwrap();
-        sessions.push(session.clone());
-        Ok(session)
-    }
-
-    async fn get_session(&self, id: Uuid) -> std::result::Result<Option<Session>, SessionError> {
-        let sessions = self.sessions.lock().unwrap();
-        let session = sessions.iter().find(|s| s.id == id).cloned();
-        Ok(session)
-    }
-
-    async fn get_session_by_token(
-        &self,
-        token_hash: &str,
-    ) -> std::result::Result<Option<Session>, SessionError> {
-        let sessions = self.sessions.lock().unwrap();
-        let session = sessions
-            .iter()
-            .find(|s| s.token_hash == token_hash)
-            .cloned();
-        Ok(session)
-    }
-
-    async fn get_user_sessions(
-        &self,
-        user_id: Uuid,
-        filter: SessionFilter,
-    ) -> std::result::Result<Vec<Session>, SessionError> {
-        let sessions = self.sessions.lock().unwrap();
-
-        let filtered_sessions = sessions
-            .iter()
-            .filter(|s| s.user_id == user_id)
-            .filter(|s| match filter {
-                SessionFilter::All => true,
-                SessionFilter::Active => s.is_valid,
-                SessionFilter::Inactive => !s.is_valid,
-            })
-            .cloned()
-            .collect();
-
-        Ok(filtered_sessions)
-    }
-
-    async fn update_session_activity(&self, id: Uuid) -> std::result::Result<(), SessionError> {
-        let mut last_accessed = self.last_accessed_at.lock().unwrap();
-        *last_accessed = SystemTime::now();
-
-        let mut sessions = self.sessions.lock().unwrap();
-        if let Some(session) = sessions.iter_mut().find(|s| s.id == id) {
-            session.last_activity_at = *last_accessed;
-            session.last_activity_update_at = Some(*last_accessed);
-            Ok(())
-        } else {
-            Err(SessionError::NotFound)
-        }
-    }
-
-    async fn invalidate_session(
-        &self,
-        id: Uuid,
-        reason: SessionInvalidationReason,
-    ) -> std::result::Result<(), SessionError> {
-        let mut sessions = self.sessions.lock().unwrap();
-        if let Some(session) = sessions.iter_mut().find(|s| s.id == id) {
-            session.is_valid = false;
-            session.invalidated_reason = Some(reason);
-            Ok(())
-        } else {
-            Err(SessionError::NotFound)
-        }
-    }
-
-    async fn rotate_session_token(
-        &self,
-        id: Uuid,
-        new_token_hash: String,
-    ) -> std::result::Result<(), SessionError> {
-        let mut sessions = self.sessions.lock().unwrap();
-        if let Some(session) = sessions.iter_mut().find(|s| s.id == id) {
-            session.previous_token_hash = Some(session.token_hash.clone());
-            session.token_hash = new_token_hash;
-            session.token_rotation_at = Some(SystemTime::now());
-            Ok(())
-        } else {
-            Err(SessionError::NotFound)
-        }
-    }
-
-    async fn cleanup_expired_sessions(&self) -> std::result::Result<u64, SessionError> {
-        let mut sessions = self.sessions.lock().unwrap();
-        let now = SystemTime::now();
-        let count = sessions.iter().filter(|s| s.expires_at <= now).count();
-        sessions.retain(|s| s.expires_at > now);
-        Ok(count as u64)
-    }
-
-    async fn update_mfa_status(
-        &self,
-        id: Uuid,
-        status: MfaStatus,
-    ) -> std::result::Result<(), SessionError> {
-        let mut sessions = self.sessions.lock().unwrap();
-        if let Some(session) = sessions.iter_mut().find(|s| s.id == id) {
-            session.mfa_status = status;
-            Ok(())
-        } else {
-            Err(SessionError::NotFound)
-        }
-    }
-
-    async fn invalidate_all_user_sessions(
-        &self,
-        user_id: Uuid,
-        reason: SessionInvalidationReason,
-    ) -> std::result::Result<u64, SessionError> {
-        let mut sessions = self.sessions.lock().unwrap();
-        let mut count = 0;
-        for session in sessions
-            .iter_mut()
-            .filter(|s| s.user_id == user_id && s.is_valid)
-        {
-            session.is_valid = false;
-            session.invalidated_reason = Some(reason.clone());
-            count += 1;
-        }
-        Ok(count)
-    }
-
-    async fn invalidate_sessions_by_filter(
-        &self,
-        filter: SessionFilter,
-        reason: SessionInvalidationReason,
-    ) -> std::result::Result<u64, SessionError> {
-        let mut sessions = self.sessions.lock().unwrap();
-        let mut count = 0;
-        for session in sessions.iter_mut() {
-            let should_invalidate = match filter {
-                SessionFilter::All => true,
-                SessionFilter::Active => session.is_valid,
-                SessionFilter::Inactive => !session.is_valid,
-            };
-            if should_invalidate {
-                session.is_valid = false;
-                session.invalidated_reason = Some(reason.clone());
-                count += 1;
-            }
-        }
-        Ok(count)
-    }
-
-    async fn invalidate_sessions_by_ip(
-        &self,
-        ip_address: &str,
-        reason: SessionInvalidationReason,
-    ) -> std::result::Result<u64, SessionError> {
-        let mut sessions = self.sessions.lock().unwrap();
-        let mut count = 0;
-        for session in sessions
-            .iter_mut()
-            .filter(|s| s.ip_address.as_deref() == Some(ip_address) && s.is_valid)
-        {
-            session.is_valid = false;
-            session.invalidated_reason = Some(reason.clone());
-            count += 1;
-        }
-        Ok(count)
-    }
-}
 
 // Helper function to create services for testing
 fn create_test_services() -> (
@@ -248,9 +34,9 @@ fn create_test_services() -> (
     // Create verification service
     let verification_config = crate::models::VerificationConfig {
         code_length: 6,
-        expiration_seconds: 600,
+        expiration_seconds: DurationSecs::from_secs(600),
         max_attempts: 3,
-        throttle_seconds: 60,
+        throttle_seconds: DurationSecs::from_secs(60),
     };
     let verification_service = VerificationService::new(
         verification_repo.clone(),
@@ -262,7 +48,7 @@ fn create_test_services() -> (
     // Create session repository and service
     let session_repo = Arc::new(MockSessionRepository::new());
     let auth_config = Arc::new(crate::config::AuthConfig {
-        session_lifetime_secs: 3600,
+        session_lifetime_secs: DurationSecs::from_secs(3600),
         ..Default::default()
     });
     let session_service = SessionService::new(session_repo.clone(), auth_config);
@@ -291,20 +77,53 @@ async fn test_create_session_with_mfa_pending() {
             Some("127.0.0.1".to_string()),
             Some("Test User Agent".to_string()),
             None,
+            false,
             MfaStatus::Required,
         )
         .await
         .unwrap();
 
     // Verify that the session was created with MFA pending
-    let sessions = session_repo.sessions.lock().unwrap();
+    let sessions = session_repo.sessions();
     assert_eq!(sessions.len(), 1);
     assert_eq!(sessions[0].id, session.id);
 
-    // In our mock implementation, the MfaStatus is not actually set in create_session_with_status
-    // We'd need to verify this differently in a real implementation
-    // For now, we'll just check that the session was created
     assert!(sessions[0].is_valid);
+    assert_eq!(sessions[0].mfa_status, MfaStatus::Required);
+    assert_eq!(session.mfa_status, MfaStatus::Required);
+}
+
+// Helper function to create an orchestrator over the test services
+fn create_orchestrator(
+    verification_service: VerificationService,
+    session_repo: &Arc<MockSessionRepository>,
+) -> MfaOrchestrator {
+    MfaOrchestrator::new(Arc::new(verification_service), session_repo.clone())
+}
+
+// Helper function to send an email code and read it back from the provider
+async fn send_email_code(
+    verification_service: &VerificationService,
+    email_provider: &MockMessageProvider,
+    tenant_id: TenantId,
+    user_id: UserId,
+    context: &dyn TenantAwareContext,
+) -> String {
+    verification_service
+        .send_verification(
+            tenant_id,
+            user_id,
+            VerificationType::Email,
+            "test@example.com".to_string(),
+            context,
+        )
+        .await
+        .unwrap();
+
+    let message = email_provider.last_message().unwrap();
+    let re = regex::Regex::new(r"code is: (\d{6})").unwrap();
+    let captures = re.captures(&message.body).unwrap();
+    captures.get(1).unwrap().as_str().to_string()
 }
 
 #[test]
@@ -326,72 +145,50 @@ async fn test_complete_verification_flow() {
             Some("127.0.0.1".to_string()),
             Some("Test User Agent".to_string()),
             None,
+            false,
             MfaStatus::Required,
         )
         .await
         .unwrap();
 
-    // Set the MFA status manually
-    session_repo
-        .update_mfa_status(session.id, MfaStatus::Required)
-        .await
-        .unwrap();
-
-    // Verify that the session was created with proper MFA status
-    {
-        let sessions = session_repo.sessions.lock().unwrap();
-        assert_eq!(sessions.len(), 1);
-        assert_eq!(sessions[0].mfa_status, MfaStatus::Required);
-    }
-
     // Send a verification code
-    let email = "test@example.com".to_string();
-    verification_service
-        .send_verification(
-            tenant_id,
+    let code = send_email_code(
+        &verification_service,
+        &email_provider,
+        tenant_id,
+        user_id,
+        &context,
+    )
+    .await;
+
+    // Verify the code and complete MFA in one call
+    let orchestrator = create_orchestrator(verification_service, &session_repo);
+    orchestrator
+        .complete_mfa(
+            session.id,
             user_id,
             VerificationType::Email,
-            email.clone(),
+            &code,
+            tenant_id,
             &context,
         )
         .await
         .unwrap();
 
-    // Get the verification code from the email message
-    let code = {
-        let message = email_provider.get_last_message().unwrap();
-        let re = regex::Regex::new(r"code is: (\d{6})").unwrap();
-        let captures = re.captures(&message.body).unwrap();
-        captures.get(1).unwrap().as_str().to_string()
-    };
-
-    // Verify the code
-    verification_service
-        .verify_code(user_id, VerificationType::Email, &code, tenant_id, &context)
-        .await
-        .unwrap();
-
     // Check that the verification code is marked as verified
     {
-        let codes = verification_repo.codes.lock().unwrap();
+        let codes = verification_repo.codes();
         assert_eq!(codes.len(), 1);
         assert_eq!(codes[0].status, VerificationStatus::Verified);
     }
 
-    // Update the session MFA status to verified
-    session_repo
-        .update_mfa_status(session.id, MfaStatus::Verified)
-        .await
-        .unwrap();
-
-    // Verify the session MFA status is now verified
-    {
-        let sessions = session_repo.sessions.lock().unwrap();
-        assert_eq!(sessions.len(), 1);
-        assert_eq!(sessions[0].id, session.id);
-        assert_eq!(sessions[0].mfa_status, MfaStatus::Verified);
-        assert!(sessions[0].is_valid);
-    }
+    // Verify the session MFA status is now verified and the time is recorded
+    let sessions = session_repo.sessions();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].id, session.id);
+    assert_eq!(sessions[0].mfa_status, MfaStatus::Verified);
+    assert!(sessions[0].mfa_verified_at().is_some());
+    assert!(sessions[0].is_valid);
 }
 
 #[test]
@@ -412,20 +209,17 @@ async fn test_failed_verification_flow() {
             Some("127.0.0.1".to_string()),
             Some("Test User Agent".to_string()),
             None,
+            false,
             MfaStatus::Required,
         )
         .await
         .unwrap();
 
-    // Set the MFA status manually
-    {
-        let mut sessions = session_repo.sessions.lock().unwrap();
-        sessions[0].mfa_status = MfaStatus::Required;
-    }
-
     // Try to verify with an invalid code
-    let result = verification_service
-        .verify_code(
+    let orchestrator = create_orchestrator(verification_service, &session_repo);
+    let result = orchestrator
+        .complete_mfa(
+            session.id,
             user_id,
             VerificationType::Email,
             "123456", // Invalid code since no verification was sent
@@ -435,30 +229,133 @@ async fn test_failed_verification_flow() {
         .await;
 
     // Check that verification failed
-    assert!(result.is_err());
     match result.unwrap_err() {
-        acci_core::error::Error::Validation(msg) => {
-            assert!(msg.contains("Invalid verification code"));
+        MfaOrchestratorError::Verification(err) => {
+            assert_eq!(err.kind(), ErrorKind::InvalidCode);
         },
-        _ => panic!("Expected validation error"),
+        other => panic!("Expected verification error, got {other:?}"),
     }
 
-    // Update the session MFA status to None (failed) directly through the repository
-    session_repo
-        .update_mfa_status(session.id, MfaStatus::None)
-        .await
-        .unwrap();
-
-    // Verify the session MFA status is now None (failed)
-    let sessions = session_repo.sessions.lock().unwrap();
+    // A rejected code must not release the session from its MFA requirement
+    let sessions = session_repo.sessions();
     assert_eq!(sessions.len(), 1);
     assert_eq!(sessions[0].id, session.id);
-    assert_eq!(sessions[0].mfa_status, MfaStatus::None);
+    assert_eq!(sessions[0].mfa_status, MfaStatus::Required);
+    assert!(sessions[0].mfa_verified_at().is_none());
 
     // Session should still be valid even though MFA failed
     assert!(sessions[0].is_valid);
 }
 
+#[test]
+async fn test_mfa_completion_rolls_back_when_recording_fails() {
+    let (verification_service, session_service, _, session_repo, email_provider, _) =
+        create_test_services();
+    let context = MockTenantAwareContext::new();
+    let tenant_id = TenantId::new_v4();
+    let user_id = UserId::new_v4();
+
+    let (session, _) = session_service
+        .create_session_with_status(
+            user_id.into(),
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+            MfaStatus::Required,
+        )
+        .await
+        .unwrap();
+    let code = send_email_code(
+        &verification_service,
+        &email_provider,
+        tenant_id,
+        user_id,
+        &context,
+    )
+    .await;
+
+    // The status update succeeds but the audit/metadata write does not
+    session_repo.fail_on("record_mfa_verification");
+    let orchestrator = create_orchestrator(verification_service, &session_repo);
+    let result = orchestrator
+        .complete_mfa(
+            session.id,
+            user_id,
+            VerificationType::Email,
+            &code,
+            tenant_id,
+            &context,
+        )
+        .await;
+    assert!(matches!(
+        result,
+        Err(MfaOrchestratorError::Repository(SessionError::Database(_)))
+    ));
+
+    // The session is back to requiring MFA
+    let sessions = session_repo.sessions();
+    assert_eq!(sessions[0].mfa_status, MfaStatus::Required);
+    assert!(sessions[0].mfa_verified_at().is_none());
+}
+
+#[test]
+async fn test_mfa_completion_rejects_foreign_session() {
+    let (verification_service, session_service, verification_repo, session_repo, email_provider, _) =
+        create_test_services();
+    let context = MockTenantAwareContext::new();
+    let tenant_id = TenantId::new_v4();
+    let user_id = UserId::new_v4();
+
+    // The session belongs to someone else
+    let (session, _) = session_service
+        .create_session_with_status(
+            Uuid::new_v4(),
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+            MfaStatus::Required,
+        )
+        .await
+        .unwrap();
+    let code = send_email_code(
+        &verification_service,
+        &email_provider,
+        tenant_id,
+        user_id,
+        &context,
+    )
+    .await;
+
+    let orchestrator = create_orchestrator(verification_service, &session_repo);
+    let result = orchestrator
+        .complete_mfa(
+            session.id,
+            user_id,
+            VerificationType::Email,
+            &code,
+            tenant_id,
+            &context,
+        )
+        .await;
+    assert!(matches!(
+        result,
+        Err(MfaOrchestratorError::SessionUserMismatch)
+    ));
+
+    // Neither the code nor the session was touched
+    assert_eq!(
+        verification_repo.codes()[0].status,
+        VerificationStatus::Pending
+    );
+    assert_eq!(session_repo.sessions()[0].mfa_status, MfaStatus::Required);
+}
+
 #[test]
 async fn test_verification_flow_with_too_many_attempts() {
     let (verification_service, session_service, verification_repo, session_repo, email_provider, _) =
@@ -478,6 +375,7 @@ async fn test_verification_flow_with_too_many_attempts() {
             Some("127.0.0.1".to_string()),
             Some("Test User Agent".to_string()),
             None,
+            false,
             MfaStatus::Required,
         )
         .await
@@ -485,7 +383,7 @@ async fn test_verification_flow_with_too_many_attempts() {
 
     // Set the MFA status manually
     {
-        let mut sessions = session_repo.sessions.lock().unwrap();
+        let mut sessions = session_repo.sessions();
         sessions[0].mfa_status = MfaStatus::Required;
     }
 
@@ -503,7 +401,7 @@ async fn test_verification_flow_with_too_many_attempts() {
         .unwrap();
 
     // Retrieve the correct code from the email
-    let message = email_provider.get_last_message().unwrap();
+    let message = email_provider.last_message().unwrap();
     let re = regex::Regex::new(r"code is: (\d{6})").unwrap();
     let captures = re.captures(&message.body).unwrap();
     let correct_code = captures.get(1).unwrap().as_str();
@@ -513,7 +411,7 @@ async fn test_verification_flow_with_too_many_attempts() {
 
     // First, manually invalidate the verification code after exceeding attempts
     {
-        let mut codes = verification_repo.codes.lock().unwrap();
+        let mut codes = verification_repo.codes();
         codes[0].attempts = codes[0].attempts + 3; // Exceed the max attempts
         codes[0].status = VerificationStatus::Invalidated; // Manually mark as invalidated
     }
@@ -526,7 +424,7 @@ async fn test_verification_flow_with_too_many_attempts() {
 
     // Verify that session is properly marked as failed
     {
-        let sessions = session_repo.sessions.lock().unwrap();
+        let sessions = session_repo.sessions();
         assert_eq!(sessions[0].mfa_status, MfaStatus::None);
     }
 
@@ -543,15 +441,10 @@ async fn test_verification_flow_with_too_many_attempts() {
 
     // Check that verification failed due to too many attempts
     assert!(result.is_err());
-    match result.unwrap_err() {
-        acci_core::error::Error::Validation(msg) => {
-            assert!(msg.contains("Too many verification attempts"));
-        },
-        _ => panic!("Expected validation error"),
-    }
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::TooManyAttempts);
 
     // Check that the verification code is marked as invalidated
-    let verification_codes = verification_repo.codes.lock().unwrap();
+    let verification_codes = verification_repo.codes();
     assert_eq!(verification_codes.len(), 1);
     assert_eq!(
         verification_codes[0].status,
@@ -559,7 +452,7 @@ async fn test_verification_flow_with_too_many_attempts() {
     );
 
     // Verify the session MFA status is None (failed)
-    let sessions = session_repo.sessions.lock().unwrap();
+    let sessions = session_repo.sessions();
     assert_eq!(sessions.len(), 1);
     assert_eq!(sessions[0].id, session.id);
     assert_eq!(sessions[0].mfa_status, MfaStatus::None);
@@ -587,6 +480,7 @@ async fn test_verification_flow_with_expired_code() {
             Some("127.0.0.1".to_string()),
             Some("Test User Agent".to_string()),
             None,
+            false,
             MfaStatus::Required,
         )
         .await
@@ -594,7 +488,7 @@ async fn test_verification_flow_with_expired_code() {
 
     // Set the MFA status manually
     {
-        let mut sessions = session_repo.sessions.lock().unwrap();
+        let mut sessions = session_repo.sessions();
         sessions[0].mfa_status = MfaStatus::Required;
     }
 
@@ -612,7 +506,7 @@ async fn test_verification_flow_with_expired_code() {
         .unwrap();
 
     // Get the verification code from the email
-    let message = email_provider.get_last_message().unwrap();
+    let message = email_provider.last_message().unwrap();
     let re = regex::Regex::new(r"code is: (\d{6})").unwrap();
     let captures = re.captures(&message.body).unwrap();
     let code = captures.get(1).unwrap().as_str();
@@ -623,7 +517,7 @@ async fn test_verification_flow_with_expired_code() {
     // First, test expired code failure
     {
         // Manually expire the verification code
-        let mut codes = verification_repo.codes.lock().unwrap();
+        let mut codes = verification_repo.codes();
         codes[0].expires_at = time::OffsetDateTime::now_utc() - time::Duration::seconds(60);
         codes[0].status = VerificationStatus::Pending; // Make sure it's pending
     }
@@ -644,7 +538,7 @@ async fn test_verification_flow_with_expired_code() {
 
     // Verify the session MFA status is now None (failed)
     {
-        let sessions = session_repo.sessions.lock().unwrap();
+        let sessions = session_repo.sessions();
         assert_eq!(sessions.len(), 1);
         assert_eq!(sessions[0].id, session.id);
         assert_eq!(sessions[0].mfa_status, MfaStatus::None);
@@ -667,14 +561,14 @@ async fn test_verification_flow_with_expired_code() {
 
     // Get the new code and set it to valid status manually
     let new_code = {
-        let message = email_provider.get_last_message().unwrap();
+        let message = email_provider.last_message().unwrap();
         let captures = re.captures(&message.body).unwrap();
         captures.get(1).unwrap().as_str().to_string()
     };
 
     {
         // Make sure the new code is valid and not expired
-        let mut codes = verification_repo.codes.lock().unwrap();
+        let mut codes = verification_repo.codes();
         codes[0].expires_at = time::OffsetDateTime::now_utc() + time::Duration::minutes(10);
         codes[0].status = VerificationStatus::Pending;
         codes[0].attempts = 0;
@@ -707,7 +601,7 @@ async fn test_verification_flow_with_expired_code() {
 
     // Verify the session MFA status is now Verified
     {
-        let sessions = session_repo.sessions.lock().unwrap();
+        let sessions = session_repo.sessions();
         assert_eq!(sessions.len(), 1);
         assert_eq!(sessions[0].id, session.id);
         assert_eq!(sessions[0].mfa_status, MfaStatus::Verified);
@@ -744,6 +638,7 @@ async fn test_multi_tenant_verification_isolation() {
             Some("127.0.0.1".to_string()),
             Some("Test User Agent".to_string()),
             None,
+            false,
             MfaStatus::Required,
         )
         .await
@@ -757,6 +652,7 @@ async fn test_multi_tenant_verification_isolation() {
             Some("127.0.0.1".to_string()),
             Some("Test User Agent".to_string()),
             None,
+            false,
             MfaStatus::Required,
         )
         .await
@@ -764,7 +660,7 @@ async fn test_multi_tenant_verification_isolation() {
 
     // Set the MFA status manually for both sessions
     {
-        let mut sessions = session_repo.sessions.lock().unwrap();
+        let mut sessions = session_repo.sessions();
         for session in sessions.iter_mut() {
             session.mfa_status = MfaStatus::Required;
         }
@@ -783,7 +679,7 @@ async fn test_multi_tenant_verification_isolation() {
         .unwrap();
 
     // Get the verification code for tenant 1
-    let message_tenant_1 = email_provider.get_last_message().unwrap();
+    let message_tenant_1 = email_provider.last_message().unwrap();
     let re = regex::Regex::new(r"code is: (\d{6})").unwrap();
     let captures_tenant_1 = re.captures(&message_tenant_1.body).unwrap();
     let code_tenant_1 = captures_tenant_1.get(1).unwrap().as_str();
@@ -801,7 +697,7 @@ async fn test_multi_tenant_verification_isolation() {
         .unwrap();
 
     // Get the verification code for tenant 2
-    let message_tenant_2 = email_provider.get_last_message().unwrap();
+    let message_tenant_2 = email_provider.last_message().unwrap();
     let captures_tenant_2 = re.captures(&message_tenant_2.body).unwrap();
     let code_tenant_2 = captures_tenant_2.get(1).unwrap().as_str();
 
@@ -853,7 +749,7 @@ async fn test_multi_tenant_verification_isolation() {
         .unwrap();
 
     // Verify both sessions have the correct MFA status
-    let sessions = session_repo.sessions.lock().unwrap();
+    let sessions = session_repo.sessions();
     assert_eq!(sessions.len(), 2);
 
     let session_1 = sessions
@@ -888,6 +784,7 @@ async fn test_sms_verification_flow() {
             Some("127.0.0.1".to_string()),
             Some("Test User Agent".to_string()),
             None,
+            false,
             MfaStatus::Required,
         )
         .await
@@ -895,7 +792,7 @@ async fn test_sms_verification_flow() {
 
     // Set the MFA status manually
     {
-        let mut sessions = session_repo.sessions.lock().unwrap();
+        let mut sessions = session_repo.sessions();
         sessions[0].mfa_status = MfaStatus::Required;
     }
 
@@ -913,7 +810,7 @@ async fn test_sms_verification_flow() {
         .unwrap();
 
     // Get the verification code from the SMS message
-    let message = sms_provider.get_last_message().unwrap();
+    let message = sms_provider.last_message().unwrap();
     let re = regex::Regex::new(r"code is: (\d{6})").unwrap();
     let captures = re.captures(&message.body).unwrap();
     let code = captures.get(1).unwrap().as_str();
@@ -932,7 +829,7 @@ async fn test_sms_verification_flow() {
         .unwrap();
 
     // Check that the verification code is marked as verified
-    let verification_codes = verification_repo.codes.lock().unwrap();
+    let verification_codes = verification_repo.codes();
     assert_eq!(verification_codes.len(), 1);
     assert_eq!(verification_codes[0].status, VerificationStatus::Verified);
     assert_eq!(
@@ -947,7 +844,7 @@ async fn test_sms_verification_flow() {
         .unwrap();
 
     // Verify the session MFA status is now verified
-    let sessions = session_repo.sessions.lock().unwrap();
+    let sessions = session_repo.sessions();
     assert_eq!(sessions.len(), 1);
     assert_eq!(sessions[0].id, session.id);
     assert_eq!(sessions[0].mfa_status, MfaStatus::Verified);
@@ -0,0 +1,422 @@
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::config::AuthConfig;
+use crate::models::tenant::{
+    CreateSubscriptionDto, CreateTenantUserDto, PlanChange, SubscriptionChangeType, TenantPlanType,
+    TenantRepository, UpdateSubscriptionDto, UpdateTenantUserDto,
+};
+use crate::models::user::mock::MockUserRepository;
+use crate::services::session::SessionService;
+use crate::services::tenant::{TenantService, TenantServiceError};
+use crate::services::user::UserService;
+use crate::session::mock::MockSessionRepository;
+use crate::utils::jwt::JwtUtils;
+
+use super::mocks::MockTenantRepository;
+
+fn tenant_service(tenant_repo: Arc<MockTenantRepository>) -> TenantService {
+    let user_repo = Arc::new(MockUserRepository::new());
+    let config = Arc::new(AuthConfig::default());
+    let session_service = Arc::new(SessionService::new(
+        Arc::new(MockSessionRepository::new()),
+        config.clone(),
+    ));
+    let user_service = Arc::new(UserService::new(
+        user_repo.clone(),
+        Arc::new(JwtUtils::new(b"test-secret")),
+        session_service,
+        None,
+        config,
+    ));
+    TenantService::new(tenant_repo, user_repo, user_service)
+}
+
+fn plan(plan_type: TenantPlanType, max_users: i32) -> CreateSubscriptionDto {
+    CreateSubscriptionDto {
+        plan_type,
+        starts_at: OffsetDateTime::now_utc(),
+        expires_at: None,
+        is_active: Some(true),
+        payment_status: Some("PAID".to_string()),
+        max_users: Some(max_users),
+        features: None,
+    }
+}
+
+fn update_max_users(plan_type: TenantPlanType, max_users: i32) -> UpdateSubscriptionDto {
+    UpdateSubscriptionDto {
+        plan_type: Some(plan_type),
+        expires_at: None,
+        is_active: None,
+        payment_status: None,
+        max_users: Some(max_users),
+        features: None,
+    }
+}
+
+#[test]
+async fn test_downgrade_below_active_users_is_blocked() {
+    let tenant_repo = Arc::new(MockTenantRepository::new());
+    let service = tenant_service(tenant_repo.clone());
+    let tenant_id =
+        tenant_repo.insert_tenant_with_metadata(serde_json::Value::Object(serde_json::Map::new()));
+    for _ in 0..3 {
+        tenant_repo.add_member(tenant_id, Uuid::new_v4(), "MEMBER");
+    }
+
+    let subscription = service
+        .create_subscription(&tenant_id, plan(TenantPlanType::Professional, 100), None)
+        .await
+        .expect("Subscription is created");
+
+    let result = service
+        .update_subscription(
+            &subscription.id,
+            update_max_users(TenantPlanType::Free, 2),
+            None,
+        )
+        .await;
+    match result {
+        Err(TenantServiceError::TenantLimitExceeded { message, .. }) => {
+            assert!(message.contains("3 active users, 1 over"), "{}", message);
+        },
+        other => panic!("Expected TenantLimitExceeded, got {:?}", other),
+    }
+
+    // Replacing the plan with a smaller one is blocked the same way
+    let result = service
+        .create_subscription(&tenant_id, plan(TenantPlanType::Free, 1), None)
+        .await;
+    assert!(matches!(
+        result,
+        Err(TenantServiceError::TenantLimitExceeded { .. })
+    ));
+
+    // Neither attempt touched the subscription or its history
+    let active = service
+        .get_active_subscription(&tenant_id)
+        .await
+        .expect("Subscription is loaded")
+        .expect("Subscription is still active");
+    assert_eq!(active.id, subscription.id);
+    assert_eq!(active.plan_type, TenantPlanType::Professional);
+    assert_eq!(active.max_users, Some(100));
+    let history = service
+        .get_subscription_history(&tenant_id, 1)
+        .await
+        .expect("History is listed");
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].change_type, SubscriptionChangeType::Created);
+
+    // A limit that fits the active users applies immediately
+    let downgraded = service
+        .update_subscription(
+            &subscription.id,
+            update_max_users(TenantPlanType::Basic, 3),
+            None,
+        )
+        .await
+        .expect("Downgrade within the limit succeeds");
+    assert_eq!(downgraded.plan_type, TenantPlanType::Basic);
+    assert_eq!(downgraded.max_users, Some(3));
+}
+
+#[test]
+async fn test_subscription_history_is_newest_first() {
+    let tenant_repo = Arc::new(MockTenantRepository::new());
+    let service = tenant_service(tenant_repo.clone());
+    let tenant_id =
+        tenant_repo.insert_tenant_with_metadata(serde_json::Value::Object(serde_json::Map::new()));
+    let other_tenant_id =
+        tenant_repo.insert_tenant_with_metadata(serde_json::Value::Object(serde_json::Map::new()));
+    let actor_id = Uuid::new_v4();
+
+    let basic = service
+        .create_subscription(&tenant_id, plan(TenantPlanType::Basic, 20), Some(actor_id))
+        .await
+        .expect("Basic subscription is created");
+    service
+        .update_subscription(
+            &basic.id,
+            update_max_users(TenantPlanType::Professional, 100),
+            Some(actor_id),
+        )
+        .await
+        .expect("Subscription is upgraded");
+    let enterprise = service
+        .create_subscription(&tenant_id, plan(TenantPlanType::Enterprise, 1000), None)
+        .await
+        .expect("Enterprise subscription replaces the current one");
+    service
+        .create_subscription(&other_tenant_id, plan(TenantPlanType::Free, 5), None)
+        .await
+        .expect("Other tenant's subscription is created");
+
+    let history = service
+        .get_subscription_history(&tenant_id, 1)
+        .await
+        .expect("History is listed");
+    let changes: Vec<_> = history
+        .iter()
+        .map(|entry| {
+            (
+                entry.change_type,
+                entry.old_plan_type,
+                entry.new_plan_type,
+                entry.subscription_id,
+            )
+        })
+        .collect();
+    assert_eq!(
+        changes,
+        vec![
+            (
+                SubscriptionChangeType::Created,
+                Some(TenantPlanType::Professional),
+                TenantPlanType::Enterprise,
+                enterprise.id
+            ),
+            (
+                SubscriptionChangeType::Deactivated,
+                Some(TenantPlanType::Professional),
+                TenantPlanType::Professional,
+                basic.id
+            ),
+            (
+                SubscriptionChangeType::Updated,
+                Some(TenantPlanType::Basic),
+                TenantPlanType::Professional,
+                basic.id
+            ),
+            (
+                SubscriptionChangeType::Created,
+                None,
+                TenantPlanType::Basic,
+                basic.id
+            ),
+        ]
+    );
+    assert_eq!(history[2].old_max_users, Some(20));
+    assert_eq!(history[2].new_max_users, Some(100));
+    assert_eq!(history[2].actor_id, Some(actor_id));
+    assert_eq!(history[1].actor_id, None);
+
+    assert!(
+        service
+            .get_subscription_history(&tenant_id, 2)
+            .await
+            .expect("Second page is listed")
+            .is_empty()
+    );
+    assert!(matches!(
+        service.get_subscription_history(&tenant_id, 0).await,
+        Err(TenantServiceError::InvalidInput(_))
+    ));
+}
+
+#[test]
+async fn test_plan_changes_keep_every_subscription() {
+    let tenant_repo = Arc::new(MockTenantRepository::new());
+    let service = tenant_service(tenant_repo.clone());
+    let tenant_id =
+        tenant_repo.insert_tenant_with_metadata(serde_json::Value::Object(serde_json::Map::new()));
+
+    let basic = service
+        .create_subscription(&tenant_id, plan(TenantPlanType::Basic, 20), None)
+        .await
+        .expect("Basic subscription is created");
+    let professional = service
+        .create_subscription(&tenant_id, plan(TenantPlanType::Professional, 100), None)
+        .await
+        .expect("Upgrade to professional");
+    let free = service
+        .create_subscription(&tenant_id, plan(TenantPlanType::Free, 5), None)
+        .await
+        .expect("Downgrade to free");
+
+    let subscriptions: Vec<_> = service
+        .list_subscriptions(&tenant_id)
+        .await
+        .expect("Subscriptions are listed")
+        .into_iter()
+        .map(|subscription| (subscription.id, subscription.is_active))
+        .collect();
+    assert_eq!(
+        subscriptions,
+        vec![(basic.id, false), (professional.id, false), (free.id, true)]
+    );
+
+    // Each replacement names the plan it replaced and the direction of the change
+    let history = service
+        .get_subscription_history(&tenant_id, 1)
+        .await
+        .expect("History is listed");
+    let plan_changes: Vec<_> = history
+        .iter()
+        .filter(|entry| entry.change_type == SubscriptionChangeType::Created)
+        .map(|entry| (entry.old_plan_type, entry.new_plan_type, entry.plan_change))
+        .collect();
+    assert_eq!(
+        plan_changes,
+        vec![
+            (
+                Some(TenantPlanType::Professional),
+                TenantPlanType::Free,
+                Some(PlanChange::Downgrade)
+            ),
+            (
+                Some(TenantPlanType::Basic),
+                TenantPlanType::Professional,
+                Some(PlanChange::Upgrade)
+            ),
+            (None, TenantPlanType::Basic, None),
+        ]
+    );
+    assert!(
+        history
+            .iter()
+            .filter(|entry| entry.change_type == SubscriptionChangeType::Deactivated)
+            .all(|entry| entry.plan_change.is_none())
+    );
+}
+
+#[test]
+async fn test_plan_change_direction() {
+    use TenantPlanType::*;
+
+    assert_eq!(
+        PlanChange::between(Free, Enterprise),
+        Some(PlanChange::Upgrade)
+    );
+    assert_eq!(
+        PlanChange::between(Professional, Basic),
+        Some(PlanChange::Downgrade)
+    );
+    assert_eq!(
+        PlanChange::between(Custom, Basic),
+        Some(PlanChange::Lateral)
+    );
+    assert_eq!(PlanChange::between(Basic, Basic), None);
+}
+
+fn member(user_id: Uuid, is_active: bool) -> CreateTenantUserDto {
+    CreateTenantUserDto {
+        user_id,
+        tenant_role: "MEMBER".to_string(),
+        is_active: Some(is_active),
+    }
+}
+
+fn set_active(is_active: bool) -> UpdateTenantUserDto {
+    UpdateTenantUserDto {
+        tenant_role: None,
+        is_active: Some(is_active),
+    }
+}
+
+#[test]
+async fn test_seat_limit_is_enforced_at_the_exact_limit() {
+    let tenant_repo = Arc::new(MockTenantRepository::new());
+    let service = tenant_service(tenant_repo.clone());
+    let tenant_id =
+        tenant_repo.insert_tenant_with_metadata(serde_json::Value::Object(serde_json::Map::new()));
+    service
+        .create_subscription(&tenant_id, plan(TenantPlanType::Basic, 3), None)
+        .await
+        .expect("Subscription is created");
+    for _ in 0..2 {
+        tenant_repo.add_member(tenant_id, Uuid::new_v4(), "MEMBER");
+    }
+
+    // The last free seat can be taken
+    let last = Uuid::new_v4();
+    service
+        .add_user_to_tenant(&tenant_id, member(last, true))
+        .await
+        .expect("Third user takes the last seat");
+
+    let err = service
+        .add_user_to_tenant(&tenant_id, member(Uuid::new_v4(), true))
+        .await
+        .expect_err("No seat is left");
+    assert!(matches!(
+        err,
+        TenantServiceError::TenantLimitExceeded {
+            limit: 3,
+            current: 3,
+            ..
+        }
+    ));
+    let err: acci_core::Error = err.into();
+    assert_eq!(err.kind(), acci_core::error::ErrorKind::SeatLimitReached);
+    assert_eq!(
+        err.details(),
+        Some(&serde_json::json!({ "limit": 3, "current": 3 }))
+    );
+
+    // Inactive users take no seat, but reactivating them does
+    let inactive = Uuid::new_v4();
+    service
+        .add_user_to_tenant(&tenant_id, member(inactive, false))
+        .await
+        .expect("Inactive users can always be added");
+    assert!(matches!(
+        service
+            .update_tenant_user(&tenant_id, &inactive, set_active(true))
+            .await,
+        Err(TenantServiceError::TenantLimitExceeded { .. })
+    ));
+
+    // Deactivating a user frees a seat for the reactivation
+    service
+        .update_tenant_user(&tenant_id, &last, set_active(false))
+        .await
+        .expect("User is deactivated");
+    let reactivated = service
+        .update_tenant_user(&tenant_id, &inactive, set_active(true))
+        .await
+        .expect("Freed seat is taken");
+    assert!(reactivated.is_active);
+
+    // Updating an already active user needs no free seat
+    service
+        .update_tenant_user(&tenant_id, &inactive, set_active(true))
+        .await
+        .expect("Active user stays active");
+}
+
+#[test]
+async fn test_unlimited_plan_has_no_seat_limit() {
+    let tenant_repo = Arc::new(MockTenantRepository::new());
+    let service = tenant_service(tenant_repo.clone());
+    let tenant_id =
+        tenant_repo.insert_tenant_with_metadata(serde_json::Value::Object(serde_json::Map::new()));
+    service
+        .create_subscription(
+            &tenant_id,
+            CreateSubscriptionDto {
+                max_users: None,
+                ..plan(TenantPlanType::Enterprise, 0)
+            },
+            None,
+        )
+        .await
+        .expect("Subscription is created");
+
+    for _ in 0..25 {
+        service
+            .add_user_to_tenant(&tenant_id, member(Uuid::new_v4(), true))
+            .await
+            .expect("Unlimited plans take any number of users");
+    }
+    assert_eq!(
+        tenant_repo
+            .count_active_tenant_users(tenant_id)
+            .await
+            .expect("Users are counted"),
+        25
+    );
+}
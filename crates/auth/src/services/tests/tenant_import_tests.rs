@@ -0,0 +1,145 @@
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::config::AuthConfig;
+use crate::models::tenant::{CreateSubscriptionDto, CreateTenantUserDto, TenantPlanType};
+use crate::models::user::{User, UserRepository, mock::MockUserRepository};
+use crate::services::session::SessionService;
+use crate::services::tenant::{BulkAddFailure, TenantService};
+use crate::services::user::UserService;
+use crate::session::mock::MockSessionRepository;
+use crate::utils::jwt::JwtUtils;
+
+use super::mocks::MockTenantRepository;
+
+fn tenant_service(
+    tenant_repo: Arc<MockTenantRepository>,
+    user_repo: Arc<MockUserRepository>,
+) -> TenantService {
+    let config = Arc::new(AuthConfig::default());
+    let session_service = Arc::new(SessionService::new(
+        Arc::new(MockSessionRepository::new()),
+        config.clone(),
+    ));
+    let user_service = Arc::new(UserService::new(
+        user_repo.clone(),
+        Arc::new(JwtUtils::new(b"test-secret")),
+        session_service,
+        None,
+        config,
+    ));
+    TenantService::new(tenant_repo, user_repo, user_service)
+}
+
+async fn create_user(user_repo: &MockUserRepository, email: &str) -> Uuid {
+    let user = User::new(email.to_string(), "hash".to_string());
+    user_repo
+        .create(&user)
+        .await
+        .expect("Failed to create user");
+    user.id
+}
+
+fn member(user_id: Uuid) -> CreateTenantUserDto {
+    CreateTenantUserDto {
+        user_id,
+        tenant_role: "MEMBER".to_string(),
+        is_active: None,
+    }
+}
+
+#[test]
+async fn test_bulk_add_users_reports_each_row() {
+    let tenant_repo = Arc::new(MockTenantRepository::new());
+    let user_repo = Arc::new(MockUserRepository::new());
+    let service = tenant_service(tenant_repo.clone(), user_repo.clone());
+
+    let tenant_id =
+        tenant_repo.insert_tenant_with_metadata(serde_json::Value::Object(serde_json::Map::new()));
+    let existing = create_user(&user_repo, "existing@example.com").await;
+    tenant_repo.add_member(tenant_id, existing, "ADMIN");
+    service
+        .create_subscription(
+            &tenant_id,
+            CreateSubscriptionDto {
+                plan_type: TenantPlanType::Free,
+                starts_at: OffsetDateTime::now_utc(),
+                expires_at: None,
+                is_active: Some(true),
+                payment_status: Some("PAID".to_string()),
+                max_users: Some(3),
+                features: None,
+            },
+            None,
+        )
+        .await
+        .expect("Subscription is created");
+
+    let alice = create_user(&user_repo, "alice@example.com").await;
+    let bob = create_user(&user_repo, "bob@example.com").await;
+    let carol = create_user(&user_repo, "carol@example.com").await;
+    let dave = create_user(&user_repo, "dave@example.com").await;
+
+    let result = service
+        .bulk_add_users(
+            &tenant_id,
+            vec![
+                member(alice),
+                member(alice),
+                member(Uuid::new_v4()),
+                member(existing),
+                member(bob),
+                // Alice and Bob filled the two free seats
+                member(carol),
+                // Inactive members do not take a seat
+                CreateTenantUserDto {
+                    is_active: Some(false),
+                    ..member(dave)
+                },
+            ],
+        )
+        .await
+        .expect("Import runs");
+
+    let outcomes: Vec<_> = result
+        .rows
+        .iter()
+        .map(|row| {
+            row.outcome
+                .as_ref()
+                .map(|user| user.user_id)
+                .map_err(Clone::clone)
+        })
+        .collect();
+    assert_eq!(
+        outcomes,
+        vec![
+            Ok(alice),
+            Err(BulkAddFailure::AlreadyExists),
+            Err(BulkAddFailure::UserNotFound),
+            Err(BulkAddFailure::AlreadyExists),
+            Ok(bob),
+            Err(BulkAddFailure::LimitExceeded(3)),
+            Ok(dave),
+        ]
+    );
+    assert_eq!(result.added().count(), 3);
+    assert_eq!(
+        result
+            .failures()
+            .map(|(row, _)| row.row)
+            .collect::<Vec<_>>(),
+        vec![1, 2, 3, 5]
+    );
+
+    // The rows added before a failure stay in the tenant
+    let members = service
+        .get_tenant_users(&tenant_id)
+        .await
+        .expect("Members are listed");
+    assert_eq!(members.len(), 4);
+    assert_eq!(members.iter().filter(|m| m.is_active).count(), 3);
+    assert!(!members.iter().any(|m| m.user_id == carol));
+}
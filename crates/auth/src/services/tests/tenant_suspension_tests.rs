@@ -0,0 +1,341 @@
+use acci_core::error::ErrorKind;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::config::AuthConfig;
+use crate::models::tenant::{
+    CreateSubscriptionDto, SECURITY_POLICY_METADATA_KEY, TenantError, TenantPlanType,
+    TenantSecurityPolicy, TenantStatus,
+};
+use crate::models::user::{User, UserRepository, mock::MockUserRepository};
+use crate::services::session::SessionService;
+use crate::services::tenant::{TenantService, TenantServiceError};
+use crate::services::user::{LoginResult, UserService, UserServiceError};
+use crate::session::mock::MockSessionRepository;
+use crate::session::types::SessionInvalidationReason;
+use crate::utils::bounded_json::BoundedJson;
+use crate::utils::jwt::JwtUtils;
+use crate::utils::password::hash_password;
+
+use super::mocks::MockTenantRepository;
+
+const PASSWORD: &str = "Correct-Horse-Battery-42";
+
+struct Fixture {
+    user_service: Arc<UserService>,
+    tenant_service: TenantService,
+    user_repo: Arc<MockUserRepository>,
+    tenant_repo: Arc<MockTenantRepository>,
+    session_repo: Arc<MockSessionRepository>,
+    tenant_id: Uuid,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let user_repo = Arc::new(MockUserRepository::new());
+        let tenant_repo = Arc::new(MockTenantRepository::new());
+        let tenant_id = tenant_repo.insert_tenant_with_policy(&TenantSecurityPolicy::default());
+
+        let session_repo = Arc::new(MockSessionRepository::new());
+        let config = Arc::new(AuthConfig::default());
+        let session_service = Arc::new(
+            SessionService::new(session_repo.clone(), config.clone())
+                .with_tenant_repository(tenant_repo.clone()),
+        );
+        let user_service = Arc::new(
+            UserService::new(
+                user_repo.clone(),
+                Arc::new(JwtUtils::new(b"test-secret")),
+                session_service,
+                None,
+                config,
+            )
+            .with_tenant_repository(tenant_repo.clone()),
+        );
+        let tenant_service =
+            TenantService::new(tenant_repo.clone(), user_repo.clone(), user_service.clone());
+
+        Self {
+            user_service,
+            tenant_service,
+            user_repo,
+            tenant_repo,
+            session_repo,
+            tenant_id,
+        }
+    }
+
+    async fn add_user(&self, email: &str, tenant_role: &str) -> User {
+        let user = User::new(email.to_string(), hash_password(PASSWORD).unwrap());
+        self.user_repo.create(&user).await.unwrap();
+        self.tenant_repo
+            .add_member(self.tenant_id, user.id, tenant_role);
+        user
+    }
+
+    async fn login(&self, email: &str) -> Result<LoginResult, UserServiceError> {
+        self.user_service
+            .login_with_tenant(
+                Some(self.tenant_id),
+                email,
+                PASSWORD,
+                None,
+                None,
+                None,
+                None,
+                false,
+            )
+            .await
+    }
+}
+
+#[test]
+async fn test_login_to_suspended_tenant_returns_reason_until_unsuspended() {
+    let fixture = Fixture::new();
+    fixture.add_user("member@example.com", "MEMBER").await;
+    assert!(fixture.login("member@example.com").await.is_ok());
+
+    let tenant = fixture
+        .tenant_service
+        .suspend(&fixture.tenant_id, "Invoice 2024-11 is overdue", None)
+        .await
+        .expect("Tenant is suspended");
+    assert!(matches!(
+        &tenant.status,
+        TenantStatus::Suspended { reason, .. } if reason == "Invoice 2024-11 is overdue"
+    ));
+    // The suspension is distinct from archival and keeps the data of the tenant
+    assert!(!tenant.status.is_archived());
+    assert!(tenant.metadata.as_ref().unwrap()[SECURITY_POLICY_METADATA_KEY].is_object());
+
+    let Err(err) = fixture.login("member@example.com").await else {
+        panic!("Members cannot log in to a suspended tenant");
+    };
+    assert!(matches!(
+        &err,
+        UserServiceError::Tenant(TenantError::SuspendedTenant(reason))
+            if reason == "Invoice 2024-11 is overdue"
+    ));
+    let err = acci_core::Error::from(err);
+    assert_eq!(err.kind(), ErrorKind::TenantSuspended);
+    assert_eq!(
+        err.details().unwrap()["reason"],
+        "Invoice 2024-11 is overdue"
+    );
+
+    let tenant = fixture
+        .tenant_service
+        .unsuspend(&fixture.tenant_id, None)
+        .await
+        .expect("Tenant is unsuspended");
+    assert_eq!(tenant.status, TenantStatus::Active);
+    assert!(fixture.login("member@example.com").await.is_ok());
+}
+
+#[test]
+async fn test_resuspending_keeps_start_of_suspension() {
+    let fixture = Fixture::new();
+    let first = fixture
+        .tenant_service
+        .suspend(&fixture.tenant_id, "Payment failed", None)
+        .await
+        .expect("Tenant is suspended");
+
+    let second = fixture
+        .tenant_service
+        .suspend(&fixture.tenant_id, "Chargeback", None)
+        .await
+        .expect("Tenant stays suspended");
+
+    assert_eq!(second.status.reason(), Some("Chargeback"));
+    assert_eq!(second.status.since(), first.status.since());
+}
+
+#[test]
+async fn test_admin_and_billing_users_can_log_in_to_suspended_tenant() {
+    let fixture = Fixture::new();
+    fixture.add_user("admin@example.com", "ADMIN").await;
+    fixture.add_user("billing@example.com", "billing").await;
+    fixture
+        .tenant_service
+        .suspend(&fixture.tenant_id, "Payment failed", None)
+        .await
+        .expect("Tenant is suspended");
+
+    assert!(fixture.login("admin@example.com").await.is_ok());
+    assert!(fixture.login("billing@example.com").await.is_ok());
+}
+
+#[test]
+async fn test_archived_tenant_blocks_every_login() {
+    let fixture = Fixture::new();
+    fixture.add_user("admin@example.com", "ADMIN").await;
+    let tenant = fixture
+        .tenant_service
+        .archive(&fixture.tenant_id, false, None)
+        .await
+        .expect("Tenant is archived");
+    assert!(tenant.status.is_archived());
+
+    let Err(err) = fixture.login("admin@example.com").await else {
+        panic!("Archived tenants block admins as well");
+    };
+    assert!(matches!(
+        err,
+        UserServiceError::Tenant(TenantError::InactiveTenant)
+    ));
+    assert_eq!(
+        acci_core::Error::from(err).kind(),
+        ErrorKind::TenantInactive
+    );
+
+    // An archived tenant can neither be suspended nor unsuspended
+    let result = fixture
+        .tenant_service
+        .suspend(&fixture.tenant_id, "Payment failed", None)
+        .await;
+    assert!(matches!(result, Err(TenantServiceError::InvalidInput(_))));
+    let result = fixture
+        .tenant_service
+        .unsuspend(&fixture.tenant_id, None)
+        .await;
+    assert!(matches!(result, Err(TenantServiceError::InvalidInput(_))));
+}
+
+#[test]
+async fn test_archiving_terminates_sessions_of_tenant() {
+    let fixture = Fixture::new();
+    let user = fixture.add_user("member@example.com", "MEMBER").await;
+    fixture
+        .tenant_service
+        .suspend(&fixture.tenant_id, "Payment failed", None)
+        .await
+        .expect("Tenant is suspended");
+    let (in_tenant, _) = fixture
+        .user_service
+        .session_service()
+        .create_session(
+            user.id,
+            None,
+            None,
+            None,
+            None,
+            Some(
+                BoundedJson::new(serde_json::json!({ "tenant_id": fixture.tenant_id }))
+                    .expect("Metadata is within the limits"),
+            ),
+            false,
+        )
+        .await
+        .expect("Session is created");
+    let (elsewhere, _) = fixture
+        .user_service
+        .session_service()
+        .create_session(
+            user.id,
+            None,
+            None,
+            None,
+            None,
+            Some(
+                BoundedJson::new(serde_json::json!({ "tenant_id": Uuid::new_v4() }))
+                    .expect("Metadata is within the limits"),
+            ),
+            false,
+        )
+        .await
+        .expect("Session is created");
+
+    // Suspended tenants keep their sessions
+    assert!(fixture.session_repo.sessions().iter().all(|s| s.is_valid));
+
+    fixture
+        .tenant_service
+        .archive(&fixture.tenant_id, false, None)
+        .await
+        .expect("Tenant is archived");
+
+    let sessions = fixture.session_repo.sessions();
+    let session = |id| {
+        sessions
+            .iter()
+            .find(|s| s.id == id)
+            .expect("Session exists")
+    };
+    assert!(!session(in_tenant.id).is_valid);
+    assert_eq!(
+        session(in_tenant.id).invalidated_reason,
+        Some(SessionInvalidationReason::TenantArchived)
+    );
+    assert!(session(elsewhere.id).is_valid);
+}
+
+#[test]
+async fn test_archiving_tenant_with_paid_subscription_requires_force() {
+    let fixture = Fixture::new();
+    fixture
+        .tenant_service
+        .create_subscription(
+            &fixture.tenant_id,
+            CreateSubscriptionDto {
+                plan_type: TenantPlanType::Professional,
+                starts_at: OffsetDateTime::now_utc(),
+                expires_at: None,
+                is_active: Some(true),
+                payment_status: Some("PAID".to_string()),
+                max_users: None,
+                features: None,
+            },
+            None,
+        )
+        .await
+        .expect("Subscription is created");
+
+    let result = fixture
+        .tenant_service
+        .archive(&fixture.tenant_id, false, None)
+        .await;
+    assert!(matches!(result, Err(TenantServiceError::InvalidInput(_))));
+    let tenant = fixture
+        .tenant_service
+        .get_tenant(&fixture.tenant_id)
+        .await
+        .unwrap();
+    assert_eq!(tenant.status, TenantStatus::Active);
+
+    let tenant = fixture
+        .tenant_service
+        .archive(&fixture.tenant_id, true, None)
+        .await
+        .expect("Forced archival succeeds");
+    assert!(tenant.status.is_archived());
+    // The archived tenant is no longer billed
+    assert!(
+        fixture
+            .tenant_service
+            .get_active_subscription(&fixture.tenant_id)
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[test]
+async fn test_suspension_requires_reason() {
+    let fixture = Fixture::new();
+
+    let result = fixture
+        .tenant_service
+        .suspend(&fixture.tenant_id, "  ", None)
+        .await;
+
+    assert!(matches!(result, Err(TenantServiceError::InvalidInput(_))));
+    let tenant = fixture
+        .tenant_service
+        .get_tenant(&fixture.tenant_id)
+        .await
+        .unwrap();
+    assert_eq!(tenant.status, TenantStatus::Active);
+}
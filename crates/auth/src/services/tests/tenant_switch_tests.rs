@@ -0,0 +1,179 @@
+use std::sync::Arc;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::config::AuthConfig;
+use crate::models::tenant::TenantSecurityPolicy;
+use crate::models::user::{User, UserRepository, mock::MockUserRepository};
+use crate::services::session::{SessionService, SessionServiceError};
+use crate::services::user::{LoginResult, UserService, UserServiceError};
+use crate::session::mock::MockSessionRepository;
+use crate::utils::jwt::JwtUtils;
+use crate::utils::password::hash_password;
+
+use super::mocks::MockTenantRepository;
+
+const EMAIL: &str = "member@example.com";
+const PASSWORD: &str = "Correct-Horse-Battery-42";
+
+struct Fixture {
+    user_service: UserService,
+    session_service: Arc<SessionService>,
+    user_repo: Arc<MockUserRepository>,
+    tenant_repo: Arc<MockTenantRepository>,
+    session_repo: Arc<MockSessionRepository>,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let user_repo = Arc::new(MockUserRepository::new());
+        let tenant_repo = Arc::new(MockTenantRepository::new());
+        let session_repo = Arc::new(MockSessionRepository::new());
+
+        let config = Arc::new(AuthConfig::default());
+        let session_service = Arc::new(
+            SessionService::new(session_repo.clone(), config.clone())
+                .with_tenant_repository(tenant_repo.clone()),
+        );
+        let user_service = UserService::new(
+            user_repo.clone(),
+            Arc::new(JwtUtils::new(b"test-secret")),
+            session_service.clone(),
+            None,
+            config,
+        )
+        .with_tenant_repository(tenant_repo.clone());
+
+        Self {
+            user_service,
+            session_service,
+            user_repo,
+            tenant_repo,
+            session_repo,
+        }
+    }
+
+    fn tenant(&self) -> Uuid {
+        self.tenant_repo
+            .insert_tenant_with_policy(&TenantSecurityPolicy::default())
+    }
+
+    async fn user_in(&self, tenants: &[Uuid]) -> User {
+        let user = User::new(
+            EMAIL.to_string(),
+            hash_password(PASSWORD).expect("Failed to hash password"),
+        );
+        self.user_repo
+            .create(&user)
+            .await
+            .expect("Failed to create user");
+        for tenant_id in tenants {
+            self.tenant_repo.add_member(*tenant_id, user.id, "MEMBER");
+        }
+        user
+    }
+
+    async fn login(&self, tenant_id: Option<Uuid>) -> Result<LoginResult, UserServiceError> {
+        self.user_service
+            .login_with_tenant(tenant_id, EMAIL, PASSWORD, None, None, None, None, false)
+            .await
+    }
+
+    fn session_id(&self) -> Uuid {
+        let sessions = self.session_repo.sessions();
+        assert_eq!(sessions.len(), 1);
+        sessions[0].id
+    }
+
+    fn active_tenant(&self, session_id: Uuid) -> Option<Uuid> {
+        self.session_repo
+            .sessions()
+            .iter()
+            .find(|session| session.id == session_id)
+            .and_then(|session| session.active_tenant_id())
+    }
+}
+
+#[test]
+async fn test_single_membership_becomes_active_tenant() {
+    let fixture = Fixture::new();
+    let tenant_id = fixture.tenant();
+    fixture.user_in(&[tenant_id]).await;
+
+    let result = fixture.login(None).await.expect("Login failed");
+    assert_eq!(result.active_tenant_id, Some(tenant_id));
+    assert!(!result.tenant_selection_required());
+    assert_eq!(fixture.active_tenant(fixture.session_id()), Some(tenant_id));
+}
+
+#[test]
+async fn test_multiple_memberships_require_selection() {
+    let fixture = Fixture::new();
+    let (first, second) = (fixture.tenant(), fixture.tenant());
+    fixture.user_in(&[first, second]).await;
+
+    let result = fixture.login(None).await.expect("Login failed");
+    assert_eq!(result.active_tenant_id, None);
+    assert!(result.tenant_selection_required());
+    let mut listed: Vec<_> = result.memberships.iter().map(|m| m.tenant_id).collect();
+    listed.sort();
+    let mut expected = vec![first, second];
+    expected.sort();
+    assert_eq!(listed, expected);
+    assert_eq!(fixture.active_tenant(fixture.session_id()), None);
+
+    // An explicit choice at login binds the session right away
+    let result = fixture.login(Some(second)).await.expect("Login failed");
+    assert_eq!(result.active_tenant_id, Some(second));
+    assert!(!result.tenant_selection_required());
+}
+
+#[test]
+async fn test_login_into_foreign_tenant_is_rejected() {
+    let fixture = Fixture::new();
+    let (own, foreign) = (fixture.tenant(), fixture.tenant());
+    fixture.user_in(&[own]).await;
+
+    let result = fixture.login(Some(foreign)).await;
+    assert!(matches!(result, Err(UserServiceError::NotTenantMember)));
+    assert!(fixture.session_repo.sessions().is_empty());
+}
+
+#[test]
+async fn test_switch_tenant_updates_session() {
+    let fixture = Fixture::new();
+    let (first, second) = (fixture.tenant(), fixture.tenant());
+    fixture.user_in(&[first, second]).await;
+    fixture.login(Some(first)).await.expect("Login failed");
+    let session_id = fixture.session_id();
+
+    fixture
+        .session_service
+        .switch_tenant(session_id, second)
+        .await
+        .expect("Switching to a member tenant succeeds");
+    assert_eq!(fixture.active_tenant(session_id), Some(second));
+}
+
+#[test]
+async fn test_switch_to_non_member_tenant_is_rejected() {
+    let fixture = Fixture::new();
+    let (own, foreign) = (fixture.tenant(), fixture.tenant());
+    fixture.user_in(&[own]).await;
+    fixture.login(None).await.expect("Login failed");
+    let session_id = fixture.session_id();
+
+    let result = fixture
+        .session_service
+        .switch_tenant(session_id, foreign)
+        .await;
+    assert!(matches!(result, Err(SessionServiceError::NotTenantMember)));
+    assert_eq!(fixture.active_tenant(session_id), Some(own));
+
+    // Unknown sessions are not found rather than silently ignored
+    let result = fixture
+        .session_service
+        .switch_tenant(Uuid::new_v4(), own)
+        .await;
+    assert!(matches!(result, Err(SessionServiceError::Repository(_))));
+}
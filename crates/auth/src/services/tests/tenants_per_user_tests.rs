@@ -0,0 +1,166 @@
+use std::sync::Arc;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::config::AuthConfig;
+use crate::models::tenant::{CreateTenantUserDto, TenantError, TenantRepository};
+use crate::models::user::mock::MockUserRepository;
+use crate::services::session::SessionService;
+use crate::services::tenant::{BulkAddFailure, TenantService, TenantServiceError};
+use crate::services::user::UserService;
+use crate::session::mock::MockSessionRepository;
+use crate::utils::jwt::JwtUtils;
+
+use super::mocks::MockTenantRepository;
+
+fn tenant_service(
+    tenant_repo: Arc<MockTenantRepository>,
+    max_tenants_per_user: Option<u32>,
+) -> TenantService {
+    let user_repo = Arc::new(MockUserRepository::new());
+    let config = Arc::new(AuthConfig {
+        max_tenants_per_user,
+        ..AuthConfig::default()
+    });
+    let session_service = Arc::new(SessionService::new(
+        Arc::new(MockSessionRepository::new()),
+        config.clone(),
+    ));
+    let user_service = Arc::new(UserService::new(
+        user_repo.clone(),
+        Arc::new(JwtUtils::new(b"test-secret")),
+        session_service,
+        None,
+        config.clone(),
+    ));
+    TenantService::new(tenant_repo, user_repo, user_service)
+        .with_max_tenants_per_user(config.max_tenants_per_user)
+}
+
+fn tenants(tenant_repo: &MockTenantRepository, count: usize) -> Vec<Uuid> {
+    (0..count)
+        .map(|_| {
+            tenant_repo
+                .insert_tenant_with_metadata(serde_json::Value::Object(serde_json::Map::new()))
+        })
+        .collect()
+}
+
+fn member(user_id: Uuid, is_active: bool) -> CreateTenantUserDto {
+    CreateTenantUserDto {
+        user_id,
+        tenant_role: "MEMBER".to_string(),
+        is_active: Some(is_active),
+    }
+}
+
+#[test]
+async fn test_user_cannot_join_more_tenants_than_the_limit() {
+    let tenant_repo = Arc::new(MockTenantRepository::new());
+    let service = tenant_service(tenant_repo.clone(), Some(2));
+    let tenant_ids = tenants(&tenant_repo, 4);
+    let user_id = Uuid::new_v4();
+
+    for tenant_id in &tenant_ids[..2] {
+        service
+            .add_user_to_tenant(tenant_id, member(user_id, true))
+            .await
+            .expect("User is below the limit");
+    }
+
+    let err = service
+        .add_user_to_tenant(&tenant_ids[2], member(user_id, true))
+        .await
+        .expect_err("User is at the limit");
+    assert!(matches!(
+        err,
+        TenantServiceError::Tenant(TenantError::TenantsPerUserLimitExceeded {
+            limit: 2,
+            current: 2
+        })
+    ));
+    let err: acci_core::Error = err.into();
+    assert_eq!(err.kind(), acci_core::error::ErrorKind::LimitExceeded);
+    assert_eq!(
+        err.details(),
+        Some(&serde_json::json!({ "limit": 2, "current": 2 }))
+    );
+
+    // Inactive memberships neither count nor are blocked
+    service
+        .add_user_to_tenant(&tenant_ids[2], member(user_id, false))
+        .await
+        .expect("Inactive membership is added");
+
+    // Bulk imports report the limit per row
+    let result = service
+        .bulk_add_users(&tenant_ids[3], vec![member(user_id, true)])
+        .await
+        .expect("Import runs");
+    assert_eq!(
+        result.rows[0].outcome.as_ref().err(),
+        Some(&BulkAddFailure::TenantsPerUserLimitExceeded(2))
+    );
+
+    // Other users are not affected
+    service
+        .add_user_to_tenant(&tenant_ids[3], member(Uuid::new_v4(), true))
+        .await
+        .expect("Other user is below the limit");
+}
+
+#[test]
+async fn test_no_limit_disables_the_check() {
+    let tenant_repo = Arc::new(MockTenantRepository::new());
+    let service = tenant_service(tenant_repo.clone(), None);
+    let user_id = Uuid::new_v4();
+
+    for tenant_id in tenants(&tenant_repo, 20) {
+        service
+            .add_user_to_tenant(&tenant_id, member(user_id, true))
+            .await
+            .expect("Unlimited users join any number of tenants");
+    }
+    assert_eq!(
+        tenant_repo
+            .get_user_tenants(user_id)
+            .await
+            .expect("Memberships are listed")
+            .len(),
+        20
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_adds_do_not_exceed_the_limit() {
+    let tenant_repo = Arc::new(MockTenantRepository::new());
+    let service = Arc::new(tenant_service(tenant_repo.clone(), Some(3)));
+    let user_id = Uuid::new_v4();
+
+    let handles: Vec<_> = tenants(&tenant_repo, 16)
+        .into_iter()
+        .map(|tenant_id| {
+            let service = Arc::clone(&service);
+            tokio::spawn(async move {
+                service
+                    .add_user_to_tenant(&tenant_id, member(user_id, true))
+                    .await
+            })
+        })
+        .collect();
+    let mut added = 0;
+    for handle in handles {
+        if handle.await.expect("task panicked").is_ok() {
+            added += 1;
+        }
+    }
+    assert_eq!(added, 3);
+    assert_eq!(
+        tenant_repo
+            .get_user_tenants(user_id)
+            .await
+            .expect("Memberships are listed")
+            .len(),
+        3
+    );
+}
@@ -0,0 +1,199 @@
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio::test;
+use uuid::Uuid;
+
+use crate::models::timeline::{TimelineEventKind, TimelineRecord, TimelineSource};
+use crate::repository::timeline_repository::mock::MockTimelineRepository;
+use crate::services::timeline::{TimelineView, UserTimelineService};
+
+struct Fixture {
+    service: UserTimelineService,
+    repository: Arc<MockTimelineRepository>,
+    user_id: Uuid,
+    tenant_id: Uuid,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let repository = Arc::new(MockTimelineRepository::new());
+        Self {
+            service: UserTimelineService::new(repository.clone()),
+            repository,
+            user_id: Uuid::new_v4(),
+            tenant_id: Uuid::new_v4(),
+        }
+    }
+
+    /// Record an entry of the user in the fixture tenant and return its id
+    fn record(&self, source: TimelineSource, at: OffsetDateTime, action: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        self.repository.insert(
+            self.user_id,
+            Some(self.tenant_id),
+            TimelineRecord {
+                source,
+                id,
+                at,
+                action: action.to_string(),
+                details: serde_json::json!({
+                    "factor": "totp",
+                    "impersonated_by": Uuid::new_v4().to_string(),
+                }),
+            },
+        );
+        id
+    }
+
+    /// Read the whole timeline page by page
+    async fn read_all(&self, view: TimelineView, page_size: u32) -> Vec<Vec<Uuid>> {
+        let mut pages = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = self
+                .service
+                .timeline(self.user_id, Some(self.tenant_id), view, cursor, page_size)
+                .await
+                .expect("Timeline is read");
+            pages.push(page.events.iter().map(|event| event.id).collect());
+            match page.next_cursor {
+                Some(next) => cursor = Some(next.to_string().parse().expect("Cursor parses")),
+                None => return pages,
+            }
+        }
+    }
+}
+
+fn at(seconds: i64) -> OffsetDateTime {
+    OffsetDateTime::from_unix_timestamp(1_700_000_000 + seconds).unwrap()
+}
+
+#[test]
+async fn test_same_timestamp_events_are_paged_in_stable_order() {
+    let fixture = Fixture::new();
+    let newest = fixture.record(TimelineSource::UserAudit, at(10), "REGISTRATION");
+    let mut same_instant = vec![
+        (
+            TimelineSource::Verification,
+            fixture.record(TimelineSource::Verification, at(5), "VERIFICATION_SENT"),
+        ),
+        (
+            TimelineSource::SessionAudit,
+            fixture.record(TimelineSource::SessionAudit, at(5), "SESSION_CREATED"),
+        ),
+        (
+            TimelineSource::SessionAudit,
+            fixture.record(TimelineSource::SessionAudit, at(5), "MFA_VERIFIED"),
+        ),
+        (
+            TimelineSource::UserAudit,
+            fixture.record(
+                TimelineSource::UserAudit,
+                at(5),
+                "EMAIL_VERIFICATION_SUCCESS",
+            ),
+        ),
+        (
+            TimelineSource::UserAudit,
+            fixture.record(TimelineSource::UserAudit, at(5), "USER_ACTIVATED"),
+        ),
+    ];
+    let oldest = fixture.record(TimelineSource::SessionAudit, at(1), "FACTOR_ADDED");
+    // Noise that never shows up
+    fixture.record(TimelineSource::SessionAudit, at(5), "SESSION_ACTIVITY");
+
+    same_instant.sort_by(|a, b| b.cmp(a));
+    let mut expected = vec![newest];
+    expected.extend(same_instant.into_iter().map(|(_, id)| id));
+    expected.push(oldest);
+
+    let single_page = fixture.read_all(TimelineView::Admin, 100).await;
+    assert_eq!(single_page, vec![expected.clone()]);
+
+    // Every page size cuts through the events of the same instant somewhere
+    for page_size in 1..=expected.len() as u32 {
+        let pages = fixture.read_all(TimelineView::Admin, page_size).await;
+        assert!(pages.iter().all(|page| page.len() <= page_size as usize));
+        assert_eq!(pages.concat(), expected, "page size {page_size}");
+    }
+}
+
+#[test]
+async fn test_own_view_hides_admin_only_events() {
+    let fixture = Fixture::new();
+    fixture.record(TimelineSource::SessionAudit, at(4), "IMPERSONATION_STARTED");
+    fixture.record(
+        TimelineSource::SessionAudit,
+        at(3),
+        "SUSPICIOUS_LOCATION_DETECTED",
+    );
+    let factor = fixture.record(TimelineSource::SessionAudit, at(2), "FACTOR_ADDED");
+    fixture.record(TimelineSource::SessionAudit, at(1), "IMPERSONATION_ENDED");
+
+    let own = fixture
+        .service
+        .timeline(fixture.user_id, None, TimelineView::Own, None, 10)
+        .await
+        .expect("Timeline is read");
+    assert_eq!(own.events.len(), 1);
+    let event = &own.events[0];
+    assert_eq!(event.id, factor);
+    assert_eq!(event.kind, TimelineEventKind::FactorAdded);
+    assert_eq!(event.summary, "Added totp factor");
+    assert!(event.metadata.get("impersonated_by").is_none());
+
+    let admin = fixture
+        .service
+        .timeline(
+            fixture.user_id,
+            Some(fixture.tenant_id),
+            TimelineView::Admin,
+            None,
+            10,
+        )
+        .await
+        .expect("Timeline is read");
+    let kinds: Vec<_> = admin.events.iter().map(|event| event.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            TimelineEventKind::Impersonation,
+            TimelineEventKind::SecurityAlert,
+            TimelineEventKind::FactorAdded,
+            TimelineEventKind::Impersonation,
+        ]
+    );
+    assert!(admin.events[2].metadata.get("impersonated_by").is_some());
+}
+
+#[test]
+async fn test_own_view_keeps_paging_past_hidden_events() {
+    let fixture = Fixture::new();
+    fixture.record(TimelineSource::SessionAudit, at(3), "IMPERSONATION_STARTED");
+    fixture.record(TimelineSource::SessionAudit, at(2), "IMPERSONATION_ENDED");
+    let login = fixture.record(TimelineSource::SessionAudit, at(1), "SESSION_CREATED");
+
+    let pages = fixture.read_all(TimelineView::Own, 2).await;
+    assert_eq!(pages, vec![vec![], vec![login]]);
+}
+
+#[test]
+async fn test_tenant_scope_limits_session_and_verification_events() {
+    let fixture = Fixture::new();
+    let registration = fixture.record(TimelineSource::UserAudit, at(3), "REGISTRATION");
+    let login = fixture.record(TimelineSource::SessionAudit, at(2), "SESSION_CREATED");
+    fixture.repository.insert(
+        fixture.user_id,
+        Some(Uuid::new_v4()),
+        TimelineRecord {
+            source: TimelineSource::Verification,
+            id: Uuid::new_v4(),
+            at: at(1),
+            action: "VERIFICATION_SENT".to_string(),
+            details: serde_json::json!({ "verification_type": "Email", "status": "Pending" }),
+        },
+    );
+
+    let pages = fixture.read_all(TimelineView::Admin, 10).await;
+    assert_eq!(pages, vec![vec![registration, login]]);
+}
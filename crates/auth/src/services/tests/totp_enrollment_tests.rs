@@ -0,0 +1,201 @@
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+use tokio::test;
+use totp_rs::{Secret, TOTP};
+use uuid::Uuid;
+
+use crate::models::{TotpConfig, TotpSecretInfo};
+use crate::repository::TotpSecretRepository;
+use crate::services::totp::{TotpError, TotpService};
+use crate::utils::clock::{Clock, TestClock};
+
+use super::mocks::MockTotpSecretRepository;
+
+struct Fixture {
+    totp_service: TotpService<TestClock>,
+    totp_repo: Arc<MockTotpSecretRepository>,
+    clock: TestClock,
+    user_id: Uuid,
+    tenant_id: Uuid,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let totp_repo = Arc::new(MockTotpSecretRepository::new());
+        let clock = TestClock::default();
+        Self {
+            totp_service: TotpService::with_clock(
+                totp_repo.clone(),
+                TotpConfig::default(),
+                clock.clone(),
+            ),
+            totp_repo,
+            clock,
+            user_id: Uuid::new_v4(),
+            tenant_id: Uuid::new_v4(),
+        }
+    }
+
+    async fn begin(&self) -> TotpSecretInfo {
+        self.totp_service
+            .begin_enrollment(&self.user_id, &self.tenant_id)
+            .await
+            .expect("Enrollment starts")
+    }
+
+    async fn confirm(&self, code: &str) -> Result<(), TotpError> {
+        self.totp_service
+            .confirm_enrollment(&self.user_id, &self.tenant_id, code)
+            .await
+    }
+
+    async fn verify(&self, code: &str) -> Result<bool, TotpError> {
+        self.totp_service
+            .verify_totp(&self.user_id, &self.tenant_id, code)
+            .await
+    }
+
+    /// Code of `secret` for the current time of the test clock
+    fn code(&self, secret: &str) -> String {
+        code_at(secret, self.clock.now())
+    }
+}
+
+fn code_at(secret: &str, at: OffsetDateTime) -> String {
+    let totp = TOTP::new(
+        totp_rs::Algorithm::SHA1,
+        6,
+        1,
+        30,
+        Secret::Encoded(secret.to_string())
+            .to_bytes()
+            .expect("Secret decodes"),
+    )
+    .expect("TOTP is valid");
+    totp.generate(at.unix_timestamp() as u64)
+}
+
+#[test]
+async fn test_mfa_fails_until_enrollment_is_confirmed() {
+    let fixture = Fixture::new();
+    let info = fixture.begin().await;
+    assert!(info.uri.starts_with("otpauth://totp/"));
+    assert!(info.uri.contains(&info.secret));
+
+    // Neither the pending secret nor its recovery codes satisfy MFA
+    let code = fixture.code(&info.secret);
+    assert!(matches!(
+        fixture.verify(&code).await,
+        Err(TotpError::MfaNotEnabled)
+    ));
+    assert!(matches!(
+        fixture.verify(&info.recovery_codes[0]).await,
+        Err(TotpError::MfaNotEnabled)
+    ));
+    assert!(
+        !fixture
+            .totp_service
+            .is_totp_enabled(&fixture.user_id, &fixture.tenant_id)
+            .await
+            .expect("Status is read")
+    );
+
+    fixture
+        .confirm(&code)
+        .await
+        .expect("Enrollment is confirmed");
+
+    assert!(fixture.verify(&code).await.expect("Code is checked"));
+    assert!(
+        fixture
+            .totp_service
+            .is_totp_enabled(&fixture.user_id, &fixture.tenant_id)
+            .await
+            .expect("Status is read")
+    );
+}
+
+#[test]
+async fn test_wrong_confirmation_code_keeps_enrollment_pending_and_retryable() {
+    let fixture = Fixture::new();
+    let info = fixture.begin().await;
+    let code = fixture.code(&info.secret);
+    let wrong = if code == "000000" { "111111" } else { "000000" };
+
+    assert!(matches!(
+        fixture.confirm(wrong).await,
+        Err(TotpError::InvalidMfaCode)
+    ));
+    // Recovery codes cannot confirm an enrollment either
+    assert!(matches!(
+        fixture.confirm(&info.recovery_codes[0]).await,
+        Err(TotpError::InvalidMfaCode)
+    ));
+    let pending = fixture
+        .totp_repo
+        .get_by_user_id(&fixture.user_id, &fixture.tenant_id)
+        .await
+        .expect("Secret is looked up")
+        .expect("Secret is still pending");
+    assert!(!pending.enabled);
+    assert!(matches!(
+        fixture.verify(&code).await,
+        Err(TotpError::MfaNotEnabled)
+    ));
+
+    fixture.confirm(&code).await.expect("Retry succeeds");
+    assert!(fixture.verify(&code).await.expect("Code is checked"));
+}
+
+#[test]
+async fn test_pending_enrollment_expires() {
+    let fixture = Fixture::new();
+    let info = fixture.begin().await;
+
+    fixture.clock.advance(Duration::seconds(
+        TotpConfig::default().enrollment_timeout_secs as i64,
+    ));
+    let code = fixture.code(&info.secret);
+
+    assert!(matches!(
+        fixture.confirm(&code).await,
+        Err(TotpError::EnrollmentExpired)
+    ));
+    assert!(matches!(
+        fixture.confirm(&code).await,
+        Err(TotpError::EnrollmentNotFound)
+    ));
+    assert!(matches!(
+        fixture.verify(&code).await,
+        Err(TotpError::MfaNotEnabled)
+    ));
+
+    // A new enrollment can be started and confirmed
+    let info = fixture.begin().await;
+    fixture
+        .confirm(&fixture.code(&info.secret))
+        .await
+        .expect("New enrollment is confirmed");
+}
+
+#[test]
+async fn test_begin_enrollment_keeps_enabled_secret() {
+    let fixture = Fixture::new();
+    let info = fixture.begin().await;
+    fixture
+        .confirm(&fixture.code(&info.secret))
+        .await
+        .expect("Enrollment is confirmed");
+
+    let result = fixture
+        .totp_service
+        .begin_enrollment(&fixture.user_id, &fixture.tenant_id)
+        .await;
+    assert!(matches!(result, Err(TotpError::AlreadyEnrolled)));
+    assert!(
+        fixture
+            .verify(&fixture.code(&info.secret))
+            .await
+            .expect("Code is checked")
+    );
+}
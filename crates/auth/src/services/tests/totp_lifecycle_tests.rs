@@ -0,0 +1,277 @@
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio::test;
+use totp_rs::{Secret, TOTP};
+use uuid::Uuid;
+
+use crate::config::AuthConfig;
+use crate::models::tenant::{MfaEnforcement, TenantSecurityPolicy};
+use crate::models::user::{User, UserRepository, mock::MockUserRepository};
+use crate::models::{TotpConfig, TotpSecretInfo};
+use crate::repository::TotpSecretRepository;
+use crate::services::session::SessionService;
+use crate::services::totp::{TotpError, TotpService};
+use crate::services::user::UserService;
+use crate::session::Session;
+use crate::session::mock::MockSessionRepository;
+use crate::utils::jwt::JwtUtils;
+use crate::utils::password::hash_password;
+
+use super::mocks::{MockTenantRepository, MockTotpSecretRepository};
+
+const PASSWORD: &str = "Correct-Horse-Battery-42";
+
+struct Fixture {
+    totp_service: TotpService,
+    user_service: Arc<UserService>,
+    user_repo: Arc<MockUserRepository>,
+    tenant_repo: Arc<MockTenantRepository>,
+    totp_repo: Arc<MockTotpSecretRepository>,
+    session_repo: Arc<MockSessionRepository>,
+    tenant_id: Uuid,
+}
+
+impl Fixture {
+    fn new(mfa_enforcement: MfaEnforcement) -> Self {
+        let user_repo = Arc::new(MockUserRepository::new());
+        let tenant_repo = Arc::new(MockTenantRepository::new());
+        let totp_repo = Arc::new(MockTotpSecretRepository::new());
+        let session_repo = Arc::new(MockSessionRepository::new());
+        let tenant_id = tenant_repo.insert_tenant_with_policy(&TenantSecurityPolicy {
+            mfa_enforcement,
+            ..TenantSecurityPolicy::default()
+        });
+
+        let config = Arc::new(AuthConfig::default());
+        let session_service = Arc::new(SessionService::new(session_repo.clone(), config.clone()));
+        let user_service = Arc::new(
+            UserService::new(
+                user_repo.clone(),
+                Arc::new(JwtUtils::new(b"test-secret")),
+                session_service,
+                None,
+                config,
+            )
+            .with_tenant_repository(tenant_repo.clone())
+            .with_totp_repository(totp_repo.clone()),
+        );
+        let totp_service = TotpService::new(totp_repo.clone(), TotpConfig::default())
+            .with_user_service(user_service.clone());
+
+        Self {
+            totp_service,
+            user_service,
+            user_repo,
+            tenant_repo,
+            totp_repo,
+            session_repo,
+            tenant_id,
+        }
+    }
+
+    async fn add_user(&self) -> User {
+        let user = User::new(
+            "totp@example.com".to_string(),
+            hash_password(PASSWORD).expect("Password hashes"),
+        );
+        self.user_repo.create(&user).await.expect("User is created");
+        self.tenant_repo
+            .add_member(self.tenant_id, user.id, "MEMBER");
+        user
+    }
+
+    /// Generate a secret and complete its setup with a valid code
+    async fn enroll_totp(&self, user: &User) -> TotpSecretInfo {
+        let info = self
+            .totp_service
+            .begin_enrollment(&user.id, &self.tenant_id)
+            .await
+            .expect("Enrollment starts");
+        self.totp_service
+            .confirm_enrollment(&user.id, &self.tenant_id, &current_code(&info.secret))
+            .await
+            .expect("Enrollment is confirmed");
+        info
+    }
+
+    async fn verify(&self, user: &User, code: &str) -> bool {
+        self.totp_service
+            .verify_totp(&user.id, &self.tenant_id, code)
+            .await
+            .expect("Code is checked")
+    }
+
+    /// Sign in and return the created session
+    async fn session(&self, user: &User) -> Session {
+        self.user_service
+            .login_with_tenant(
+                Some(self.tenant_id),
+                &user.email,
+                PASSWORD,
+                None,
+                None,
+                None,
+                None,
+                false,
+            )
+            .await
+            .expect("Login succeeds");
+        self.session_repo
+            .sessions()
+            .last()
+            .cloned()
+            .expect("Session is created")
+    }
+
+    fn audited_actions(&self) -> Vec<(String, String)> {
+        self.session_repo
+            .audit_entries()
+            .into_iter()
+            .map(|entry| (entry.action, entry.details["factor"].to_string()))
+            .collect()
+    }
+}
+
+/// Code of `secret` for the current time step
+fn current_code(secret: &str) -> String {
+    let totp = TOTP::new(
+        totp_rs::Algorithm::SHA1,
+        6,
+        1,
+        30,
+        Secret::Encoded(secret.to_string())
+            .to_bytes()
+            .expect("Secret decodes"),
+    )
+    .expect("TOTP is valid");
+    totp.generate(OffsetDateTime::now_utc().unix_timestamp() as u64)
+}
+
+#[test]
+async fn test_disable_clears_secret_and_recovery_codes() {
+    let fixture = Fixture::new(MfaEnforcement::Optional);
+    let user = fixture.add_user().await;
+    let info = fixture.enroll_totp(&user).await;
+    let session = fixture.session(&user).await;
+
+    fixture
+        .totp_service
+        .disable(&user.id, &fixture.tenant_id, &session)
+        .await
+        .expect("TOTP is disabled");
+
+    assert!(
+        fixture
+            .totp_repo
+            .get_by_user_id(&user.id, &fixture.tenant_id)
+            .await
+            .expect("Secret is looked up")
+            .is_none()
+    );
+    let result = fixture
+        .totp_service
+        .verify_totp(&user.id, &fixture.tenant_id, &info.recovery_codes[0])
+        .await;
+    assert!(matches!(result, Err(TotpError::MfaNotEnabled)));
+    assert_eq!(
+        fixture.audited_actions(),
+        vec![
+            ("FACTOR_REMOVED".to_string(), "\"totp\"".to_string()),
+            (
+                "FACTOR_REMOVED".to_string(),
+                "\"recovery_codes\"".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+async fn test_disable_is_blocked_for_last_second_factor_when_policy_requires_mfa() {
+    let fixture = Fixture::new(MfaEnforcement::RequiredForAll);
+    let user = fixture.add_user().await;
+    fixture.enroll_totp(&user).await;
+    let session = fixture.session(&user).await;
+
+    let result = fixture
+        .totp_service
+        .disable(&user.id, &fixture.tenant_id, &session)
+        .await;
+    assert!(matches!(result, Err(TotpError::LastSecondFactor)));
+    assert!(
+        fixture
+            .totp_service
+            .is_totp_enabled(&user.id, &fixture.tenant_id)
+            .await
+            .expect("Status is read")
+    );
+    assert!(fixture.audited_actions().is_empty());
+}
+
+#[test]
+async fn test_disable_requires_recent_reauthentication() {
+    let fixture = Fixture::new(MfaEnforcement::Optional);
+    let user = fixture.add_user().await;
+    fixture.enroll_totp(&user).await;
+    let mut session = fixture.session(&user).await;
+    session.metadata = None;
+    session.created_at = OffsetDateTime::now_utc() - time::Duration::days(1);
+
+    let result = fixture
+        .totp_service
+        .disable(&user.id, &fixture.tenant_id, &session)
+        .await;
+    assert!(matches!(result, Err(TotpError::ReauthRequired)));
+    assert!(
+        fixture
+            .totp_service
+            .is_totp_enabled(&user.id, &fixture.tenant_id)
+            .await
+            .expect("Status is read")
+    );
+}
+
+#[test]
+async fn test_lifecycle_requires_enabled_totp() {
+    let fixture = Fixture::new(MfaEnforcement::Optional);
+    let user = fixture.add_user().await;
+    let session = fixture.session(&user).await;
+
+    let result = fixture
+        .totp_service
+        .disable(&user.id, &fixture.tenant_id, &session)
+        .await;
+    assert!(matches!(result, Err(TotpError::MfaNotEnabled)));
+    let result = fixture
+        .totp_service
+        .regenerate(&user.id, &fixture.tenant_id, &session)
+        .await;
+    assert!(matches!(result, Err(TotpError::MfaNotEnabled)));
+}
+
+#[test]
+async fn test_regenerate_invalidates_old_codes() {
+    let fixture = Fixture::new(MfaEnforcement::RequiredForAll);
+    let user = fixture.add_user().await;
+    let old = fixture.enroll_totp(&user).await;
+    let session = fixture.session(&user).await;
+
+    let new = fixture
+        .totp_service
+        .regenerate(&user.id, &fixture.tenant_id, &session)
+        .await
+        .expect("Secret is regenerated");
+
+    assert_ne!(new.secret, old.secret);
+    assert!(new.uri.contains(&new.secret));
+    assert!(!fixture.verify(&user, &current_code(&old.secret)).await);
+    assert!(!fixture.verify(&user, &old.recovery_codes[0]).await);
+    assert!(fixture.verify(&user, &current_code(&new.secret)).await);
+    assert!(fixture.verify(&user, &new.recovery_codes[0]).await);
+    assert_eq!(
+        fixture.audited_actions(),
+        vec![
+            ("FACTOR_REMOVED".to_string(), "\"totp\"".to_string()),
+            ("FACTOR_ADDED".to_string(), "\"totp\"".to_string()),
+        ]
+    );
+}
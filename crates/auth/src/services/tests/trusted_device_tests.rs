@@ -0,0 +1,241 @@
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+use tokio::test;
+use uuid::Uuid;
+
+use crate::config::{AuthConfig, TrustedDeviceConfig};
+use crate::models::TotpSecret;
+use crate::models::tenant::TenantSecurityPolicy;
+use crate::models::user::{User, UserRepository, mock::MockUserRepository};
+use crate::repository::TotpSecretRepository;
+use crate::security::{
+    ClientFingerprint, FingerprintConfig, FingerprintService, TrustedDevice, TrustedDeviceService,
+};
+use crate::services::session::SessionService;
+use crate::services::user::{LoginResult, UserService, UserServiceError};
+use crate::session::types::MfaStatus;
+use crate::session::{SessionRepository, mock::MockSessionRepository};
+use crate::utils::clock::TestClock;
+use crate::utils::jwt::JwtUtils;
+use crate::utils::password::hash_password;
+
+use super::mocks::{MockFingerprintRepository, MockTenantRepository, MockTotpSecretRepository};
+
+const EMAIL: &str = "trusted@example.com";
+const PASSWORD: &str = "Correct-Horse-Battery-42";
+const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:121.0) Gecko/20100101 Firefox/121.0";
+
+struct Fixture {
+    user_service: UserService,
+    session_repo: Arc<MockSessionRepository>,
+    fingerprint_repo: Arc<MockFingerprintRepository>,
+    clock: TestClock,
+    user: User,
+    tenant_id: Uuid,
+}
+
+impl Fixture {
+    async fn new() -> Self {
+        let user_repo = Arc::new(MockUserRepository::new());
+        let tenant_repo = Arc::new(MockTenantRepository::new());
+        let totp_repo = Arc::new(MockTotpSecretRepository::new());
+        let session_repo = Arc::new(MockSessionRepository::new());
+        let fingerprint_repo = Arc::new(MockFingerprintRepository::new());
+        let clock = TestClock::new(OffsetDateTime::now_utc());
+
+        let auth_config = Arc::new(AuthConfig::default());
+        let session_service = Arc::new(SessionService::new(
+            session_repo.clone(),
+            auth_config.clone(),
+        ));
+        let user_service = UserService::new(
+            user_repo.clone(),
+            Arc::new(JwtUtils::new(b"test-secret")),
+            session_service,
+            None,
+            auth_config,
+        )
+        .with_tenant_repository(tenant_repo.clone())
+        .with_totp_repository(totp_repo.clone())
+        .with_fingerprint_service(Arc::new(FingerprintService::new(
+            fingerprint_repo.clone(),
+            FingerprintConfig::default(),
+        )))
+        .with_trusted_device_service(Arc::new(TrustedDeviceService::with_clock(
+            fingerprint_repo.clone(),
+            b"trusted-device-secret",
+            TrustedDeviceConfig::default(),
+            clock.clone(),
+        )));
+
+        let tenant_id = tenant_repo.insert_tenant_with_policy(&TenantSecurityPolicy::default());
+        let user = User::new(
+            EMAIL.to_string(),
+            hash_password(PASSWORD).expect("Failed to hash password"),
+        );
+        user_repo
+            .create(&user)
+            .await
+            .expect("Failed to create user");
+        tenant_repo.add_member(tenant_id, user.id, "MEMBER");
+
+        let mut secret = TotpSecret::new(
+            user.id,
+            tenant_id,
+            "JBSWY3DPEHPK3PXP".to_string(),
+            "SHA1".to_string(),
+            6,
+            30,
+            Vec::new(),
+        );
+        secret.enabled = true;
+        totp_repo
+            .save(&secret)
+            .await
+            .expect("Failed to enroll TOTP");
+
+        Self {
+            user_service,
+            session_repo,
+            fingerprint_repo,
+            clock,
+            user,
+            tenant_id,
+        }
+    }
+
+    async fn login(&self, fingerprint: ClientFingerprint, token: Option<&str>) -> LoginResult {
+        self.user_service
+            .login_with_trusted_device(
+                Some(self.tenant_id),
+                EMAIL,
+                PASSWORD,
+                token,
+                None,
+                Some(fingerprint.into_device_fingerprint(Some(USER_AGENT))),
+                Some("198.51.100.7".to_string()),
+                Some(USER_AGENT.to_string()),
+                false,
+            )
+            .await
+            .expect("Login failed")
+    }
+
+    /// Log in, complete MFA for the new session and trust its device
+    async fn trust(&self, fingerprint: ClientFingerprint) -> TrustedDevice {
+        let result = self.login(fingerprint, None).await;
+        assert_eq!(result.mfa_status, MfaStatus::Required);
+
+        let session_id = self.latest_session_id();
+        self.session_repo
+            .update_mfa_status(session_id, MfaStatus::Verified)
+            .await
+            .expect("Failed to verify session");
+
+        self.user_service
+            .trust_device(self.user.id, session_id, self.tenant_id)
+            .await
+            .expect("Device is trusted")
+    }
+
+    fn latest_session_id(&self) -> Uuid {
+        self.session_repo
+            .sessions()
+            .last()
+            .expect("Session is created")
+            .id
+    }
+}
+
+fn laptop() -> ClientFingerprint {
+    ClientFingerprint {
+        platform: Some("Linux x86_64".to_string()),
+        screen_width: Some(2560),
+        screen_height: Some(1440),
+        timezone: Some("Europe/Berlin".to_string()),
+        language: Some("de-DE".to_string()),
+        ..ClientFingerprint::default()
+    }
+}
+
+#[test]
+async fn test_trusted_device_skips_mfa() {
+    let fixture = Fixture::new().await;
+    let trusted = fixture.trust(laptop()).await;
+
+    let stored = fixture.fingerprint_repo.fingerprints();
+    assert!(
+        stored
+            .iter()
+            .any(|fingerprint| fingerprint.id == trusted.fingerprint_id && fingerprint.trusted)
+    );
+
+    let result = fixture.login(laptop(), Some(&trusted.token)).await;
+    assert_eq!(result.mfa_status, MfaStatus::Verified);
+    let session = fixture.session_repo.sessions().last().cloned().unwrap();
+    assert_eq!(session.mfa_status, MfaStatus::Verified);
+}
+
+#[test]
+async fn test_changed_fingerprint_requires_mfa_again() {
+    let fixture = Fixture::new().await;
+    let trusted = fixture.trust(laptop()).await;
+
+    let moved = ClientFingerprint {
+        timezone: Some("America/New_York".to_string()),
+        ..laptop()
+    };
+    let result = fixture.login(moved, Some(&trusted.token)).await;
+    assert_eq!(result.mfa_status, MfaStatus::Required);
+}
+
+#[test]
+async fn test_expired_trust_requires_mfa_again() {
+    let fixture = Fixture::new().await;
+    let trusted = fixture.trust(laptop()).await;
+
+    fixture
+        .clock
+        .advance(Duration::days(30) + Duration::seconds(1));
+
+    let result = fixture.login(laptop(), Some(&trusted.token)).await;
+    assert_eq!(result.mfa_status, MfaStatus::Required);
+}
+
+#[test]
+async fn test_revoked_trust_requires_mfa_again() {
+    let fixture = Fixture::new().await;
+    let trusted = fixture.trust(laptop()).await;
+
+    fixture
+        .user_service
+        .revoke_trusted_device(fixture.user.id, fixture.tenant_id, trusted.fingerprint_id)
+        .await
+        .expect("Trust is revoked");
+
+    let result = fixture.login(laptop(), Some(&trusted.token)).await;
+    assert_eq!(result.mfa_status, MfaStatus::Required);
+}
+
+#[test]
+async fn test_unverified_session_cannot_trust_device() {
+    let fixture = Fixture::new().await;
+    fixture.login(laptop(), None).await;
+
+    let result = fixture
+        .user_service
+        .trust_device(
+            fixture.user.id,
+            fixture.latest_session_id(),
+            fixture.tenant_id,
+        )
+        .await;
+    assert!(matches!(result, Err(UserServiceError::MfaRequired)));
+    assert!(
+        fixture
+            .fingerprint_repo
+            .fingerprints()
+            .iter()
+            .all(|fingerprint| !fingerprint.trusted)
+    );
+}
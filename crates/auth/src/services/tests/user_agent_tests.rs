@@ -0,0 +1,105 @@
+use crate::services::user_agent::{MAX_DETAIL_LENGTH, UserAgentParser};
+use crate::session::types::DeviceType;
+
+const CHROME_ON_WINDOWS: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 \
+    (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+#[test]
+fn test_representative_user_agents() {
+    let cases = [
+        (
+            CHROME_ON_WINDOWS,
+            "Chrome 120 on Windows 10",
+            DeviceType::Desktop,
+        ),
+        (
+            "Mozilla/5.0 (X11; Linux x86_64; rv:121.0) Gecko/20100101 Firefox/121.0",
+            "Firefox 121 on Linux",
+            DeviceType::Desktop,
+        ),
+        (
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 \
+             (KHTML, like Gecko) Version/17.1 Safari/605.1.15",
+            "Safari 17 on Mac OSX",
+            DeviceType::Desktop,
+        ),
+        (
+            "Mozilla/5.0 (iPhone; CPU iPhone OS 17_1 like Mac OS X) AppleWebKit/605.1.15 \
+             (KHTML, like Gecko) Version/17.1 Mobile/15E148 Safari/604.1",
+            "Safari 17 on iPhone",
+            DeviceType::Mobile,
+        ),
+        (
+            "Mozilla/5.0 (iPad; CPU OS 17_1 like Mac OS X) AppleWebKit/605.1.15 \
+             (KHTML, like Gecko) Version/17.1 Mobile/15E148 Safari/604.1",
+            "Safari 17 on iPad",
+            DeviceType::Tablet,
+        ),
+        (
+            "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 \
+             (KHTML, like Gecko) Chrome/120.0.6099.144 Mobile Safari/537.36",
+            "Chrome 120 on Android",
+            DeviceType::Mobile,
+        ),
+        (
+            "Mozilla/5.0 (Linux; Android 13; SM-X700) AppleWebKit/537.36 \
+             (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+            "Chrome 120 on Android",
+            DeviceType::Tablet,
+        ),
+    ];
+
+    let parser = UserAgentParser::new();
+    for (user_agent, summary, device_type) in cases {
+        let details = parser
+            .parse(user_agent)
+            .unwrap_or_else(|| panic!("{user_agent} is recognized"));
+        assert_eq!(details.summary(), summary, "{user_agent}");
+        assert_eq!(details.device_type, device_type, "{user_agent}");
+    }
+}
+
+#[test]
+fn test_crawlers_are_bots() {
+    let parser = UserAgentParser::new();
+
+    let details = parser
+        .parse("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)")
+        .expect("Googlebot is recognized");
+
+    assert_eq!(details.device_type, DeviceType::Bot);
+    assert_eq!(details.browser.as_deref(), Some("Googlebot"));
+}
+
+#[test]
+fn test_empty_and_garbage_user_agents_have_no_details() {
+    let parser = UserAgentParser::new();
+
+    for user_agent in ["", "   ", "-", "\u{0}\u{1b}[31m;;((", "💥💥💥"] {
+        assert!(parser.parse(user_agent).is_none(), "{user_agent:?}");
+    }
+}
+
+#[test]
+fn test_oversized_user_agents_are_capped() {
+    let parser = UserAgentParser::new();
+
+    // Tokens past the cap are not parsed
+    let padded = format!(
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64) {} Chrome/120.0.0.0",
+        "x".repeat(10_000)
+    );
+    let details = parser.parse(&padded).expect("Windows is recognized");
+    assert_eq!(details.os.as_deref(), Some("Windows 10"));
+    assert_ne!(details.browser.as_deref(), Some("Chrome"));
+
+    // The cap never splits a character
+    let multibyte = format!("{} {}", CHROME_ON_WINDOWS, "ü".repeat(10_000));
+    let details = parser.parse(&multibyte).expect("Chrome is recognized");
+    for value in [&details.browser, &details.browser_version, &details.os]
+        .into_iter()
+        .flatten()
+    {
+        assert!(value.len() <= MAX_DETAIL_LENGTH);
+    }
+}
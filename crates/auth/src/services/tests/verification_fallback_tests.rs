@@ -0,0 +1,228 @@
+use acci_core::error::{Error, ErrorKind, Result};
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::test;
+
+use crate::models::user::{User, UserRepository, mock::MockUserRepository};
+use crate::models::{TenantId, VerificationConfig, VerificationType};
+use crate::repository::verification_repository::VerificationCodeRepository;
+use crate::repository::verification_repository::mock::MockVerificationCodeRepository;
+use crate::services::message_provider::{Message, MessageProvider, MockMessageProvider};
+use crate::services::verification::{
+    ChannelFallbackConfig, UserContactDirectory, VerificationDelivery, VerificationService,
+};
+use crate::utils::duration::{DurationMs, DurationSecs};
+
+use super::mocks::MockTenantAwareContext;
+
+// SMS provider failing with the scripted errors before it delivers
+#[derive(Default)]
+struct ScriptedSmsProvider {
+    failures: Mutex<VecDeque<ErrorKind>>,
+    attempts: Mutex<Vec<String>>,
+}
+
+impl ScriptedSmsProvider {
+    fn failing_with(failures: impl IntoIterator<Item = ErrorKind>) -> Self {
+        Self {
+            failures: Mutex::new(failures.into_iter().collect()),
+            ..Self::default()
+        }
+    }
+
+    fn attempts(&self) -> usize {
+        self.attempts.lock().unwrap().len()
+    }
+}
+
+#[async_trait]
+impl MessageProvider for ScriptedSmsProvider {
+    fn verification_type(&self) -> VerificationType {
+        VerificationType::Sms
+    }
+
+    async fn send_message(&self, message: Message) -> Result<String> {
+        self.attempts.lock().unwrap().push(message.recipient);
+        match self.failures.lock().unwrap().pop_front() {
+            Some(kind) => Err(Error::domain(kind, "Twilio API error")),
+            None => Ok("sms-id".to_string()),
+        }
+    }
+}
+
+struct Fixture {
+    service: VerificationService,
+    repo: Arc<MockVerificationCodeRepository>,
+    sms_provider: Arc<ScriptedSmsProvider>,
+    email_provider: Arc<MockMessageProvider>,
+    user: User,
+}
+
+async fn fixture(sms_provider: ScriptedSmsProvider, email_verified: bool) -> Fixture {
+    let repo = Arc::new(MockVerificationCodeRepository::new());
+    let sms_provider = Arc::new(sms_provider);
+    let email_provider = Arc::new(MockMessageProvider::new(VerificationType::Email));
+    let users = Arc::new(MockUserRepository::new());
+    let mut user = User::new("user@example.com".to_string(), "hash".to_string());
+    user.is_verified = email_verified;
+    users.create(&user).await.unwrap();
+
+    let config = VerificationConfig {
+        code_length: 6,
+        expiration_seconds: DurationSecs::from_secs(600),
+        max_attempts: 3,
+        throttle_seconds: DurationSecs::from_secs(1),
+    };
+    let fallback = ChannelFallbackConfig {
+        channels: vec![VerificationType::Sms, VerificationType::Email],
+        transient_retries: 1,
+        retry_delay_ms: DurationMs::from_millis(0),
+        ..ChannelFallbackConfig::default()
+    };
+    let service = VerificationService::new(
+        repo.clone(),
+        config,
+        Some(sms_provider.clone()),
+        Some(email_provider.clone()),
+    )
+    .with_channel_fallback(fallback, Arc::new(UserContactDirectory::new(users)));
+
+    Fixture {
+        service,
+        repo,
+        sms_provider,
+        email_provider,
+        user,
+    }
+}
+
+async fn send_sms(fixture: &Fixture, tenant_id: TenantId) -> Result<VerificationDelivery> {
+    fixture
+        .service
+        .send_verification(
+            tenant_id,
+            fixture.user.id,
+            VerificationType::Sms,
+            "+15555550100".to_string(),
+            &MockTenantAwareContext::new(),
+        )
+        .await
+}
+
+#[test]
+async fn test_sms_permanent_failure_falls_back_to_email() {
+    let fixture = fixture(
+        ScriptedSmsProvider::failing_with([ErrorKind::InvalidInput]),
+        true,
+    )
+    .await;
+    let tenant_id = TenantId::new_v4();
+    let context = MockTenantAwareContext::new();
+
+    let delivery = send_sms(&fixture, tenant_id).await.unwrap();
+
+    assert_eq!(delivery.channel, VerificationType::Email);
+    // A permanent failure is not retried
+    assert_eq!(fixture.sms_provider.attempts(), 1);
+    let message = fixture.email_provider.last_message().unwrap();
+    assert_eq!(message.recipient, "user@example.com");
+    assert!(message.subject.is_some());
+
+    // The code was issued for SMS and is verified as one
+    let code = fixture.repo.codes()[0].clone();
+    assert_eq!(code.id, delivery.code_id);
+    assert_eq!(code.verification_type, VerificationType::Sms);
+    assert!(message.body.contains(&code.code));
+    assert_eq!(
+        fixture
+            .repo
+            .delivery_channel(code.id, tenant_id, &context)
+            .await
+            .unwrap(),
+        Some(VerificationType::Email)
+    );
+    fixture
+        .service
+        .verify_code(
+            fixture.user.id,
+            VerificationType::Sms,
+            &code.code,
+            tenant_id,
+            &context,
+        )
+        .await
+        .unwrap();
+}
+
+#[test]
+async fn test_sms_transient_failure_is_retried_before_falling_back() {
+    let fixture = fixture(
+        ScriptedSmsProvider::failing_with([ErrorKind::Unavailable]),
+        true,
+    )
+    .await;
+    let tenant_id = TenantId::new_v4();
+
+    let delivery = send_sms(&fixture, tenant_id).await.unwrap();
+
+    assert_eq!(delivery.channel, VerificationType::Sms);
+    assert_eq!(fixture.sms_provider.attempts(), 2);
+    assert!(fixture.email_provider.sent_messages().is_empty());
+    assert_eq!(
+        fixture
+            .repo
+            .delivery_channel(delivery.code_id, tenant_id, &MockTenantAwareContext::new())
+            .await
+            .unwrap(),
+        Some(VerificationType::Sms)
+    );
+}
+
+#[test]
+async fn test_sms_falls_back_after_transient_retries_are_exhausted() {
+    let fixture = fixture(
+        ScriptedSmsProvider::failing_with([ErrorKind::Unavailable, ErrorKind::Unavailable]),
+        true,
+    )
+    .await;
+
+    let delivery = send_sms(&fixture, TenantId::new_v4()).await.unwrap();
+
+    assert_eq!(delivery.channel, VerificationType::Email);
+    assert_eq!(fixture.sms_provider.attempts(), 2);
+    assert_eq!(fixture.email_provider.sent_messages().len(), 1);
+}
+
+#[test]
+async fn test_no_fallback_to_unverified_email() {
+    let fixture = fixture(
+        ScriptedSmsProvider::failing_with([ErrorKind::InvalidInput]),
+        false,
+    )
+    .await;
+
+    let err = send_sms(&fixture, TenantId::new_v4()).await.unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::Unavailable);
+    assert!(fixture.email_provider.sent_messages().is_empty());
+}
+
+#[test]
+async fn test_tenant_channel_preference_replaces_default() {
+    let tenant_id = TenantId::new_v4();
+    let config = ChannelFallbackConfig {
+        channels: vec![VerificationType::Email],
+        tenant_channels: [(tenant_id, vec![VerificationType::Sms])].into(),
+        ..ChannelFallbackConfig::default()
+    };
+
+    assert_eq!(
+        config.channels_for(tenant_id, VerificationType::Sms),
+        vec![VerificationType::Sms]
+    );
+    assert_eq!(
+        config.channels_for(TenantId::new_v4(), VerificationType::Sms),
+        vec![VerificationType::Sms, VerificationType::Email]
+    );
+}
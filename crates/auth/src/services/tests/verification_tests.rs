@@ -1,224 +1,39 @@
-use async_trait::async_trait;
+use acci_core::error::{ErrorKind, Result};
 use regex::Regex;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use tokio::test;
 
 use crate::models::{
     TenantId, UserId, VerificationCode, VerificationConfig, VerificationStatus, VerificationType,
 };
-use crate::repository::TenantAwareContext;
 use crate::repository::verification_repository::VerificationCodeRepository;
-use crate::services::message_provider::{Message, MessageProvider};
+use crate::repository::verification_repository::mock::MockVerificationCodeRepository;
+use crate::services::message_provider::MockMessageProvider;
 use crate::services::verification::VerificationService;
-use acci_core::error::Result;
+use crate::utils::clock::{Clock, SystemClock, TestClock};
+use crate::utils::duration::DurationSecs;
 
 // Import the mock context
 use super::mocks::MockTenantAwareContext;
 
-// Mock message provider for testing
-pub struct MockMessageProvider {
-    last_message: Arc<Mutex<Option<Message>>>,
-    verification_type: VerificationType,
-    response: String,
-}
-
-impl MockMessageProvider {
-    pub fn new(verification_type: VerificationType) -> Self {
-        Self {
-            last_message: Arc::new(Mutex::new(None)),
-            verification_type,
-            response: "message_id".to_string(),
-        }
-    }
-
-    pub fn get_last_message(&self) -> Option<Message> {
-        self.last_message.lock().unwrap().clone()
-    }
-}
-
-#[async_trait]
-impl MessageProvider for MockMessageProvider {
-    fn verification_type(&self) -> VerificationType {
-        self.verification_type
-    }
-
-    async fn send_message(&self, message: Message) -> Result<String> {
-        let mut last_message = self.last_message.lock().unwrap();
-        *last_message = Some(message);
-        Ok(self.response.clone())
-    }
-}
-
-// Mock repository for testing
-pub struct MockVerificationCodeRepository {
-    pub codes: Arc<Mutex<Vec<VerificationCode>>>,
-}
-
-impl MockVerificationCodeRepository {
-    pub fn new() -> Self {
-        Self {
-            codes: Arc::new(Mutex::new(Vec::new())),
-        }
-    }
-}
-
-#[async_trait]
-impl VerificationCodeRepository for MockVerificationCodeRepository {
-    async fn save(&self, code: &VerificationCode, _context: &dyn TenantAwareContext) -> Result<()> {
-        let mut codes = self.codes.lock().unwrap();
-        codes.push(code.clone());
-        Ok(())
-    }
-
-    async fn get_by_id(
-        &self,
-        id: uuid::Uuid,
-        tenant_id: TenantId,
-        _context: &dyn TenantAwareContext,
-    ) -> Result<Option<VerificationCode>> {
-        let codes = self.codes.lock().unwrap();
-        Ok(codes
-            .iter()
-            .find(|c| c.id == id && c.tenant_id == tenant_id)
-            .cloned())
-    }
-
-    async fn get_by_code(
-        &self,
-        code: &str,
-        user_id: UserId,
-        verification_type: VerificationType,
-        tenant_id: TenantId,
-        _context: &dyn TenantAwareContext,
-    ) -> Result<Option<VerificationCode>> {
-        let codes = self.codes.lock().unwrap();
-        Ok(codes
-            .iter()
-            .find(|c| {
-                c.code == code
-                    && c.user_id == user_id
-                    && c.verification_type == verification_type
-                    && c.tenant_id == tenant_id
-            })
-            .cloned())
-    }
-
-    async fn get_pending_by_user(
-        &self,
-        user_id: UserId,
-        verification_type: VerificationType,
-        tenant_id: TenantId,
-        _context: &dyn TenantAwareContext,
-    ) -> Result<Vec<VerificationCode>> {
-        let codes = self.codes.lock().unwrap();
-        Ok(codes
-            .iter()
-            .filter(|c| {
-                c.user_id == user_id
-                    && c.verification_type == verification_type
-                    && c.tenant_id == tenant_id
-                    && c.status == VerificationStatus::Pending
-            })
-            .cloned()
-            .collect())
-    }
-
-    async fn update(
-        &self,
-        code: &VerificationCode,
-        _context: &dyn TenantAwareContext,
-    ) -> Result<()> {
-        let mut codes = self.codes.lock().unwrap();
-        for i in 0..codes.len() {
-            if codes[i].id == code.id && codes[i].tenant_id == code.tenant_id {
-                codes[i] = code.clone();
-                return Ok(());
-            }
-        }
-        Err(acci_core::error::Error::Validation(
-            "Verification code not found".to_string(),
-        ))
-    }
-
-    async fn delete(
-        &self,
-        id: uuid::Uuid,
-        tenant_id: TenantId,
-        _context: &dyn TenantAwareContext,
-    ) -> Result<()> {
-        let mut codes = self.codes.lock().unwrap();
-        let initial_len = codes.len();
-        codes.retain(|c| !(c.id == id && c.tenant_id == tenant_id));
-        if codes.len() == initial_len {
-            return Err(acci_core::error::Error::Validation(
-                "Verification code not found".to_string(),
-            ));
-        }
-        Ok(())
-    }
-
-    async fn delete_expired(
-        &self,
-        before: time::OffsetDateTime,
-        _tenant_id: TenantId,
-        _context: &dyn TenantAwareContext,
-    ) -> Result<u64> {
-        let mut codes = self.codes.lock().unwrap();
-        let initial_len = codes.len();
-        codes.retain(|c| c.expires_at >= before);
-        Ok((initial_len - codes.len()) as u64)
-    }
-
-    async fn invalidate_pending(
-        &self,
-        user_id: UserId,
-        verification_type: VerificationType,
-        tenant_id: TenantId,
-        _context: &dyn TenantAwareContext,
-    ) -> Result<u64> {
-        let mut codes = self.codes.lock().unwrap();
-        let mut count = 0;
-        for code in codes.iter_mut() {
-            if code.user_id == user_id
-                && code.verification_type == verification_type
-                && code.tenant_id == tenant_id
-                && code.status == VerificationStatus::Pending
-            {
-                code.status = VerificationStatus::Invalidated;
-                count += 1;
-            }
-        }
-        Ok(count)
-    }
-
-    async fn count_recent_attempts(
-        &self,
-        user_id: UserId,
-        verification_type: VerificationType,
-        since: time::OffsetDateTime,
-        tenant_id: TenantId,
-        _context: &dyn TenantAwareContext,
-    ) -> Result<u64> {
-        let codes = self.codes.lock().unwrap();
-        let count = codes
-            .iter()
-            .filter(|c| {
-                c.user_id == user_id
-                    && c.verification_type == verification_type
-                    && c.tenant_id == tenant_id
-                    && c.created_at > since
-            })
-            .count();
-        Ok(count as u64)
-    }
-}
-
 // Helper functions
 fn create_test_service() -> (
     VerificationService,
     Arc<MockVerificationCodeRepository>,
     Arc<MockMessageProvider>,
     Arc<MockMessageProvider>,
+) {
+    let (service, repo, email_provider, sms_provider) = create_test_service_with_clock(SystemClock);
+    (service, repo, email_provider, sms_provider)
+}
+
+fn create_test_service_with_clock<C: Clock>(
+    clock: C,
+) -> (
+    VerificationService<C>,
+    Arc<MockVerificationCodeRepository>,
+    Arc<MockMessageProvider>,
+    Arc<MockMessageProvider>,
 ) {
     let repo = Arc::new(MockVerificationCodeRepository::new());
     let email_provider = Arc::new(MockMessageProvider::new(VerificationType::Email));
@@ -226,16 +41,17 @@ fn create_test_service() -> (
 
     let config = VerificationConfig {
         code_length: 6,
-        expiration_seconds: 600,
+        expiration_seconds: DurationSecs::from_secs(600),
         max_attempts: 3,
-        throttle_seconds: 1, // Short throttle time for tests
+        throttle_seconds: DurationSecs::from_secs(1), // Short throttle time for tests
     };
 
-    let service = VerificationService::new(
+    let service = VerificationService::with_clock(
         repo.clone(),
         config,
         Some(sms_provider.clone()),
         Some(email_provider.clone()),
+        clock,
     );
 
     (service, repo, email_provider, sms_provider)
@@ -269,7 +85,7 @@ async fn test_generate_verification_code() {
     assert_eq!(code.code.len(), 6);
 
     // Verify code was stored in repository
-    let codes = repo.codes.lock().unwrap();
+    let codes = repo.codes();
     assert_eq!(codes.len(), 1);
     assert_eq!(codes[0].id, code.id);
 }
@@ -295,7 +111,7 @@ async fn test_send_verification_email() {
         .unwrap();
 
     // Verify email was sent
-    let last_message = email_provider.get_last_message();
+    let last_message = email_provider.last_message();
     assert!(last_message.is_some());
     let message = last_message.unwrap();
     assert_eq!(message.recipient, email);
@@ -337,7 +153,7 @@ async fn test_send_verification_sms() {
         .unwrap();
 
     // Verify SMS was sent
-    let last_message = sms_provider.get_last_message();
+    let last_message = sms_provider.last_message();
     assert!(last_message.is_some());
     let message = last_message.unwrap();
     assert_eq!(message.recipient, phone);
@@ -393,7 +209,7 @@ async fn test_verify_code_success() {
     assert!(result.is_ok());
 
     // Verify code status changed to verified
-    let codes = repo.codes.lock().unwrap();
+    let codes = repo.codes();
     assert_eq!(codes[0].status, VerificationStatus::Verified);
 }
 
@@ -430,12 +246,7 @@ async fn test_verify_code_invalid() {
 
     // Check result
     assert!(result.is_err());
-    match result.unwrap_err() {
-        acci_core::error::Error::Validation(msg) => {
-            assert!(msg.contains("Invalid verification code"));
-        },
-        _ => panic!("Expected validation error"),
-    }
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidCode);
 }
 
 #[test]
@@ -464,7 +275,7 @@ async fn test_verify_code_too_many_attempts() {
 
     // Check initial state
     {
-        let codes = repo.codes.lock().unwrap();
+        let codes = repo.codes();
         assert_eq!(codes.len(), 1);
         assert_eq!(codes[0].status, VerificationStatus::Pending);
         assert_eq!(codes[0].attempts, 2);
@@ -472,7 +283,7 @@ async fn test_verify_code_too_many_attempts() {
 
     // Increment attempts and mark invalidated
     {
-        let mut codes = repo.codes.lock().unwrap();
+        let mut codes = repo.codes();
         codes[0].increment_attempts();
         codes[0].mark_invalidated();
         assert_eq!(codes[0].attempts, 3);
@@ -482,7 +293,8 @@ async fn test_verify_code_too_many_attempts() {
 
 #[test]
 async fn test_verify_code_expired() {
-    let (service, repo, _, _) = create_test_service();
+    let clock = TestClock::default();
+    let (service, repo, _, _) = create_test_service_with_clock(clock.clone());
     let context = MockTenantAwareContext::new();
 
     let tenant_id = TenantId::new_v4();
@@ -500,11 +312,8 @@ async fn test_verify_code_expired() {
         .await
         .unwrap();
 
-    // Manually expire the code
-    {
-        let mut codes = repo.codes.lock().unwrap();
-        codes[0].expires_at = time::OffsetDateTime::now_utc() - time::Duration::seconds(60);
-    }
+    // Let the code run past its 10 minute lifetime
+    clock.advance(time::Duration::seconds(601));
 
     // Try to verify the expired code
     let result = service
@@ -519,12 +328,11 @@ async fn test_verify_code_expired() {
 
     // Check result
     assert!(result.is_err());
-    match result.unwrap_err() {
-        acci_core::error::Error::Validation(msg) => {
-            assert!(msg.contains("Code has expired"));
-        },
-        _ => panic!("Expected validation error"),
-    }
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::CodeExpired);
+
+    // The failed attempt settles the code as expired
+    let codes = repo.codes();
+    assert_eq!(codes[0].status, VerificationStatus::Expired);
 }
 
 #[test]
@@ -548,7 +356,7 @@ async fn test_invalidate_pending_codes() {
 
     // Check that we have one pending code
     {
-        let codes = repo.codes.lock().unwrap();
+        let codes = repo.codes();
         assert_eq!(codes.len(), 1);
         assert_eq!(codes[0].status, VerificationStatus::Pending);
     }
@@ -563,10 +371,139 @@ async fn test_invalidate_pending_codes() {
     assert_eq!(count, 1);
 
     // Check that the code is now invalidated
-    let codes = repo.codes.lock().unwrap();
+    let codes = repo.codes();
     assert_eq!(codes[0].status, VerificationStatus::Invalidated);
 }
 
+#[test]
+async fn test_send_verification_invalidates_previous_code() {
+    let (service, repo, email_provider, _) = create_test_service();
+    let context = MockTenantAwareContext::new();
+
+    let tenant_id = TenantId::new_v4();
+    let user_id = UserId::new_v4();
+
+    for _ in 0..2 {
+        service
+            .send_verification(
+                tenant_id,
+                user_id,
+                VerificationType::Email,
+                "test@example.com".to_string(),
+                &context,
+            )
+            .await
+            .unwrap();
+    }
+
+    let (first, second) = {
+        let codes = repo.codes();
+        assert_eq!(codes.len(), 2);
+        assert_eq!(codes[0].status, VerificationStatus::Invalidated);
+        assert_eq!(codes[1].status, VerificationStatus::Pending);
+        assert_ne!(codes[0].code, codes[1].code);
+        (codes[0].code.clone(), codes[1].code.clone())
+    };
+    assert!(
+        email_provider
+            .last_message()
+            .unwrap()
+            .body
+            .contains(&second)
+    );
+
+    // Only the code sent last can be verified
+    let result = service
+        .verify_code(
+            user_id,
+            VerificationType::Email,
+            &first,
+            tenant_id,
+            &context,
+        )
+        .await;
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidCode);
+    service
+        .verify_code(
+            user_id,
+            VerificationType::Email,
+            &second,
+            tenant_id,
+            &context,
+        )
+        .await
+        .unwrap();
+}
+
+#[test]
+async fn test_generation_gives_up_when_all_codes_are_in_use() {
+    let repo = Arc::new(MockVerificationCodeRepository::new());
+    let config = VerificationConfig {
+        code_length: 1,
+        ..VerificationConfig::default()
+    };
+    let service = VerificationService::new(repo.clone(), config.clone(), None, None);
+    let context = MockTenantAwareContext::new();
+
+    let tenant_id = TenantId::new_v4();
+    let user_id = UserId::new_v4();
+    for digit in 0..10 {
+        let code = VerificationCode::new(
+            tenant_id,
+            user_id,
+            digit.to_string(),
+            VerificationType::Sms,
+            &config,
+        );
+        repo.save(&code, &context).await.unwrap();
+    }
+
+    let result = service
+        .generate_verification_code(
+            tenant_id,
+            user_id,
+            VerificationType::Sms,
+            tenant_id,
+            &context,
+        )
+        .await;
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::Internal);
+
+    // The pending codes stay usable and nothing was stored
+    {
+        let codes = repo.codes();
+        assert_eq!(codes.len(), 10);
+        assert!(
+            codes
+                .iter()
+                .all(|c| c.status == VerificationStatus::Pending)
+        );
+    }
+
+    // Codes of other types, users and tenants do not collide
+    service
+        .generate_verification_code(
+            tenant_id,
+            user_id,
+            VerificationType::Email,
+            tenant_id,
+            &context,
+        )
+        .await
+        .unwrap();
+    let other_tenant = TenantId::new_v4();
+    service
+        .generate_verification_code(
+            other_tenant,
+            user_id,
+            VerificationType::Sms,
+            other_tenant,
+            &context,
+        )
+        .await
+        .unwrap();
+}
+
 #[test]
 async fn test_cleanup_expired() {
     let (service, repo, _, _) = create_test_service();
@@ -593,7 +530,7 @@ async fn test_cleanup_expired() {
 
     // Make some of them expired
     {
-        let mut codes = repo.codes.lock().unwrap();
+        let mut codes = repo.codes();
         for i in 0..3 {
             codes[i].expires_at = time::OffsetDateTime::now_utc() - time::Duration::seconds(60);
         }
@@ -607,10 +544,81 @@ async fn test_cleanup_expired() {
 
     // Check that expired codes were deleted
     assert_eq!(count, 3);
-    let codes = repo.codes.lock().unwrap();
+    let codes = repo.codes();
     assert_eq!(codes.len(), 2);
 }
 
+#[test]
+async fn test_cleanup_all_expired_sweeps_every_tenant() {
+    let clock = TestClock::default();
+    let (service, repo, _, _) = create_test_service_with_clock(clock.clone());
+    let context = MockTenantAwareContext::new();
+
+    let tenants = [TenantId::new_v4(), TenantId::new_v4()];
+    for tenant_id in tenants {
+        service
+            .generate_verification_code(
+                tenant_id,
+                UserId::new_v4(),
+                VerificationType::Email,
+                tenant_id,
+                &context,
+            )
+            .await
+            .unwrap();
+    }
+    let verified = service
+        .generate_verification_code(
+            tenants[0],
+            UserId::new_v4(),
+            VerificationType::Sms,
+            tenants[0],
+            &context,
+        )
+        .await
+        .unwrap();
+    service
+        .verify_code(
+            verified.user_id,
+            VerificationType::Sms,
+            &verified.code,
+            tenants[0],
+            &context,
+        )
+        .await
+        .unwrap();
+
+    // Past the 10 minute lifetime the pending codes of both tenants expire,
+    // but stay around for the statistics
+    clock.advance(time::Duration::seconds(601));
+    let report = service
+        .cleanup_all_expired(DurationSecs::from_secs(3600))
+        .await
+        .unwrap();
+    assert_eq!(report.expired, 2);
+    assert_eq!(report.deleted, 0);
+    {
+        let codes = repo.codes();
+        assert_eq!(codes.len(), 3);
+        assert!(
+            codes
+                .iter()
+                .filter(|c| c.id != verified.id)
+                .all(|c| c.status == VerificationStatus::Expired)
+        );
+    }
+
+    // Once the retention is over all of them are deleted
+    clock.advance(time::Duration::seconds(3600));
+    let report = service
+        .cleanup_all_expired(DurationSecs::from_secs(3600))
+        .await
+        .unwrap();
+    assert_eq!(report.expired, 0);
+    assert_eq!(report.deleted, 3);
+    assert!(repo.codes().is_empty());
+}
+
 #[test]
 async fn test_rate_limit() {
     // In test builds, rate limiting is disabled to make tests more reliable
@@ -639,7 +647,7 @@ async fn test_rate_limit() {
     }
 
     // Verify we have 4 codes
-    let codes = repo.codes.lock().unwrap();
+    let codes = repo.codes();
     assert_eq!(codes.len(), 4);
 
     // The last code should be the only one in pending status
@@ -656,3 +664,183 @@ async fn test_rate_limit() {
         .count();
     assert_eq!(invalidated_count, 3);
 }
+
+const PROOF_SECRET: &[u8] = b"verification-proof-secret";
+
+fn assert_invalid_proof<T: std::fmt::Debug>(result: Result<T>) {
+    let err = result.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.to_string(), "Invalid verification proof");
+}
+
+#[test]
+async fn test_verify_and_consume_yields_single_use_proof() {
+    let (service, repo, _, _) = create_test_service();
+    let service = service.with_proof_signing(PROOF_SECRET, DurationSecs::from_secs(300));
+    let context = MockTenantAwareContext::new();
+    let tenant_id = TenantId::new_v4();
+    let user_id = UserId::new_v4();
+
+    let code = service
+        .generate_verification_code(
+            tenant_id,
+            user_id,
+            VerificationType::Email,
+            tenant_id,
+            &context,
+        )
+        .await
+        .unwrap();
+    let proof = service
+        .verify_and_consume(
+            user_id,
+            VerificationType::Email,
+            &code.code,
+            tenant_id,
+            "complete_registration",
+            &context,
+        )
+        .await
+        .unwrap();
+
+    // The code itself cannot be verified again
+    let result = service
+        .verify_code(
+            user_id,
+            VerificationType::Email,
+            &code.code,
+            tenant_id,
+            &context,
+        )
+        .await;
+    assert!(result.is_err());
+
+    let consumed = service
+        .consume_proof(&proof.token, "complete_registration", &context)
+        .await
+        .unwrap();
+    assert_eq!(consumed.user_id, user_id);
+    assert_eq!(consumed.tenant_id, tenant_id);
+    assert_eq!(repo.codes()[0].status, VerificationStatus::Consumed);
+
+    // Reuse
+    assert_invalid_proof(
+        service
+            .consume_proof(&proof.token, "complete_registration", &context)
+            .await,
+    );
+}
+
+#[test]
+async fn test_proof_is_bound_to_its_purpose() {
+    let (service, _, _, _) = create_test_service();
+    let service = service.with_proof_signing(PROOF_SECRET, DurationSecs::from_secs(300));
+    let context = MockTenantAwareContext::new();
+    let tenant_id = TenantId::new_v4();
+    let user_id = UserId::new_v4();
+
+    let code = service
+        .generate_verification_code(
+            tenant_id,
+            user_id,
+            VerificationType::Email,
+            tenant_id,
+            &context,
+        )
+        .await
+        .unwrap();
+    let proof = service
+        .verify_and_consume(
+            user_id,
+            VerificationType::Email,
+            &code.code,
+            tenant_id,
+            "complete_registration",
+            &context,
+        )
+        .await
+        .unwrap();
+
+    assert_invalid_proof(
+        service
+            .consume_proof(&proof.token, "elevate_mfa", &context)
+            .await,
+    );
+    // A rejected purpose does not burn the proof
+    service
+        .consume_proof(&proof.token, "complete_registration", &context)
+        .await
+        .unwrap();
+}
+
+#[test]
+async fn test_proof_expires() {
+    let clock = TestClock::default();
+    let (service, _, _, _) = create_test_service_with_clock(clock.clone());
+    let service = service.with_proof_signing(PROOF_SECRET, DurationSecs::from_secs(300));
+    let context = MockTenantAwareContext::new();
+    let tenant_id = TenantId::new_v4();
+    let user_id = UserId::new_v4();
+
+    let code = service
+        .generate_verification_code(
+            tenant_id,
+            user_id,
+            VerificationType::Email,
+            tenant_id,
+            &context,
+        )
+        .await
+        .unwrap();
+    let proof = service
+        .verify_and_consume(
+            user_id,
+            VerificationType::Email,
+            &code.code,
+            tenant_id,
+            "complete_registration",
+            &context,
+        )
+        .await
+        .unwrap();
+
+    clock.advance(time::Duration::seconds(301));
+    assert_invalid_proof(
+        service
+            .consume_proof(&proof.token, "complete_registration", &context)
+            .await,
+    );
+}
+
+#[test]
+async fn test_verify_and_consume_requires_proof_signing() {
+    let (service, repo, _, _) = create_test_service();
+    let context = MockTenantAwareContext::new();
+    let tenant_id = TenantId::new_v4();
+    let user_id = UserId::new_v4();
+
+    let code = service
+        .generate_verification_code(
+            tenant_id,
+            user_id,
+            VerificationType::Email,
+            tenant_id,
+            &context,
+        )
+        .await
+        .unwrap();
+    let result = service
+        .verify_and_consume(
+            user_id,
+            VerificationType::Email,
+            &code.code,
+            tenant_id,
+            "complete_registration",
+            &context,
+        )
+        .await;
+
+    assert!(result.is_err());
+    // The code is left for a retry once proofs are configured
+    assert_eq!(repo.codes()[0].status, VerificationStatus::Pending);
+}
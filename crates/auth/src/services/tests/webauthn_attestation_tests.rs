@@ -0,0 +1,450 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use openssl::asn1::Asn1Time;
+use openssl::bn::{BigNum, BigNumContext};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::sign::Signer;
+use openssl::x509::extension::BasicConstraints;
+use openssl::x509::{X509, X509NameBuilder};
+use serde_cbor_2::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::test;
+use uuid::Uuid;
+use webauthn_rs::prelude::AttestationCaList;
+
+use crate::config::AuthConfig;
+use crate::models::tenant::{TenantSecurityPolicy, WebAuthnAttestationPolicy};
+use crate::models::user::{User, UserRepository, mock::MockUserRepository};
+use crate::models::webauthn::{Credential, RegisterCredential};
+use crate::services::session::SessionService;
+use crate::services::tenant::{TenantService, TenantServiceError};
+use crate::services::user::UserService;
+use crate::services::webauthn::{WebAuthnConfig, WebAuthnService};
+use crate::session::mock::MockSessionRepository;
+use crate::utils::jwt::JwtUtils;
+
+use super::mocks::{MockTenantRepository, MockWebAuthnRepository};
+
+const RP_ID: &str = "example.com";
+const ORIGIN: &str = "https://example.com";
+
+fn ec_key() -> PKey<Private> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).expect("Failed to create group");
+    PKey::from_ec_key(EcKey::generate(&group).expect("Failed to generate key"))
+        .expect("Failed to wrap key")
+}
+
+/// Certificate for `key`, self-signed CA certificate without an `issuer`
+fn certificate(
+    subject: &[(&str, &str)],
+    issuer: Option<&X509>,
+    key: &PKey<Private>,
+    signer: &PKey<Private>,
+) -> X509 {
+    let mut name = X509NameBuilder::new().expect("Failed to create name");
+    for (field, value) in subject {
+        name.append_entry_by_text(field, value)
+            .expect("Failed to set name entry");
+    }
+    let name = name.build();
+
+    let mut builder = X509::builder().expect("Failed to create certificate");
+    builder.set_version(2).expect("Failed to set version");
+    let serial = BigNum::from_u32(1)
+        .and_then(|serial| serial.to_asn1_integer())
+        .expect("Valid serial number");
+    builder
+        .set_serial_number(&serial)
+        .expect("Failed to set serial number");
+    builder
+        .set_subject_name(&name)
+        .expect("Failed to set subject");
+    builder
+        .set_issuer_name(issuer.map_or(&name, |issuer| issuer.subject_name()))
+        .expect("Failed to set issuer");
+    builder.set_pubkey(key).expect("Failed to set key");
+    builder
+        .set_not_before(&Asn1Time::days_from_now(0).expect("Valid time"))
+        .expect("Failed to set start");
+    builder
+        .set_not_after(&Asn1Time::days_from_now(1).expect("Valid time"))
+        .expect("Failed to set end");
+    let mut constraints = BasicConstraints::new();
+    if issuer.is_none() {
+        constraints.critical().ca();
+    }
+    builder
+        .append_extension(constraints.build().expect("Valid constraints"))
+        .expect("Failed to set constraints");
+    builder
+        .sign(signer, MessageDigest::sha256())
+        .expect("Failed to sign certificate");
+    builder.build()
+}
+
+/// Authenticator vendor with a root CA and the attestation certificate of its keys
+struct Vendor {
+    ca: X509,
+    attestation_key: PKey<Private>,
+    attestation_certificate: X509,
+}
+
+impl Vendor {
+    fn new() -> Self {
+        let ca_key = ec_key();
+        let ca = certificate(&[("CN", "Acme Attestation Root")], None, &ca_key, &ca_key);
+        let attestation_key = ec_key();
+        let attestation_certificate = certificate(
+            &[
+                ("C", "DE"),
+                ("O", "Acme"),
+                ("OU", "Authenticator Attestation"),
+                ("CN", "Acme Security Key"),
+            ],
+            Some(&ca),
+            &attestation_key,
+            &ca_key,
+        );
+        Self {
+            ca,
+            attestation_key,
+            attestation_certificate,
+        }
+    }
+
+    /// Trust list with the root CA of the vendor, covering all its models
+    fn trusted(&self) -> AttestationCaList {
+        let pem = self.ca.to_pem().expect("Failed to encode CA");
+        AttestationCaList::try_from(pem.as_slice()).expect("Valid CA")
+    }
+}
+
+/// Software authenticator answering registration challenges
+struct Authenticator<'a> {
+    aaguid: Uuid,
+    /// Vendor attesting the authenticator with a packed attestation; "none" otherwise
+    vendor: Option<&'a Vendor>,
+}
+
+impl Authenticator<'_> {
+    /// Answer the registration `options` of the relying party
+    fn register(&self, options: &serde_json::Value) -> RegisterCredential {
+        let challenge = options["publicKey"]["challenge"]
+            .as_str()
+            .expect("Options carry a challenge");
+        #[allow(clippy::disallowed_methods)]
+        let client_data = serde_json::json!({
+            "type": "webauthn.create",
+            "challenge": challenge,
+            "origin": ORIGIN,
+            "crossOrigin": false,
+        });
+        let client_data = serde_json::to_vec(&client_data).expect("Client data serializes");
+
+        let key = ec_key();
+        let ec_key = key.ec_key().expect("EC key");
+        let mut ctx = BigNumContext::new().expect("Failed to create context");
+        let (mut x, mut y) = (BigNum::new().expect("x"), BigNum::new().expect("y"));
+        ec_key
+            .public_key()
+            .affine_coordinates(ec_key.group(), &mut x, &mut y, &mut ctx)
+            .expect("Failed to get coordinates");
+        // ES256 public key in COSE format
+        let cose_key = Value::Map(BTreeMap::from([
+            (Value::Integer(1), Value::Integer(2)),
+            (Value::Integer(3), Value::Integer(-7)),
+            (Value::Integer(-1), Value::Integer(1)),
+            (
+                Value::Integer(-2),
+                Value::Bytes(x.to_vec_padded(32).expect("x fits")),
+            ),
+            (
+                Value::Integer(-3),
+                Value::Bytes(y.to_vec_padded(32).expect("y fits")),
+            ),
+        ]));
+
+        let credential_id = Uuid::new_v4();
+        let mut auth_data = Sha256::digest(RP_ID.as_bytes()).to_vec();
+        // User present, user verified and attested credential data
+        auth_data.push(0x45);
+        auth_data.extend_from_slice(&0u32.to_be_bytes());
+        auth_data.extend_from_slice(self.aaguid.as_bytes());
+        auth_data.extend_from_slice(&16u16.to_be_bytes());
+        auth_data.extend_from_slice(credential_id.as_bytes());
+        auth_data.extend(serde_cbor_2::to_vec(&cose_key).expect("COSE key encodes"));
+
+        let (format, statement) = match self.vendor {
+            Some(vendor) => {
+                let mut signer = Signer::new(MessageDigest::sha256(), &vendor.attestation_key)
+                    .expect("Failed to create signer");
+                signer.update(&auth_data).expect("Failed to sign");
+                signer
+                    .update(&Sha256::digest(&client_data))
+                    .expect("Failed to sign");
+                let certificate = vendor
+                    .attestation_certificate
+                    .to_der()
+                    .expect("Certificate encodes");
+                let statement = BTreeMap::from([
+                    (Value::Text("alg".to_string()), Value::Integer(-7)),
+                    (
+                        Value::Text("sig".to_string()),
+                        Value::Bytes(signer.sign_to_vec().expect("Failed to sign")),
+                    ),
+                    (
+                        Value::Text("x5c".to_string()),
+                        Value::Array(vec![Value::Bytes(certificate)]),
+                    ),
+                ]);
+                ("packed", statement)
+            },
+            None => ("none", BTreeMap::new()),
+        };
+        let attestation_object = serde_cbor_2::to_vec(&Value::Map(BTreeMap::from([
+            (
+                Value::Text("fmt".to_string()),
+                Value::Text(format.to_string()),
+            ),
+            (Value::Text("attStmt".to_string()), Value::Map(statement)),
+            (Value::Text("authData".to_string()), Value::Bytes(auth_data)),
+        ])))
+        .expect("Attestation object encodes");
+
+        let raw_id = URL_SAFE_NO_PAD.encode(credential_id.as_bytes());
+        #[allow(clippy::disallowed_methods)]
+        let attestation = serde_json::json!({
+            "id": raw_id,
+            "rawId": raw_id,
+            "type": "public-key",
+            "response": {
+                "attestationObject": URL_SAFE_NO_PAD.encode(attestation_object),
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(client_data),
+            },
+        });
+        RegisterCredential {
+            attestation: attestation.to_string(),
+            name: "Security key".to_string(),
+        }
+    }
+}
+
+struct Fixture {
+    service: WebAuthnService,
+    tenant_service: TenantService,
+    credentials: Arc<MockWebAuthnRepository>,
+    tenant_id: Uuid,
+    user: User,
+}
+
+impl Fixture {
+    async fn new(
+        attestation: WebAuthnAttestationPolicy,
+        attestation_cas: AttestationCaList,
+    ) -> Self {
+        let user_repo = Arc::new(MockUserRepository::new());
+        let tenant_repo = Arc::new(MockTenantRepository::new());
+        let credentials = Arc::new(MockWebAuthnRepository::new());
+        let tenant_id = tenant_repo.insert_tenant_with_policy(&TenantSecurityPolicy {
+            webauthn_attestation: attestation,
+            ..TenantSecurityPolicy::default()
+        });
+
+        let user = User::new("user@example.com".to_string(), String::new());
+        user_repo.create(&user).await.expect("User is created");
+        tenant_repo.add_member(tenant_id, user.id, "MEMBER");
+
+        let config = Arc::new(AuthConfig::default());
+        let session_service = Arc::new(SessionService::new(
+            Arc::new(MockSessionRepository::new()),
+            config.clone(),
+        ));
+        let user_service = Arc::new(UserService::new(
+            user_repo.clone(),
+            Arc::new(JwtUtils::new(b"test-secret")),
+            session_service,
+            None,
+            config,
+        ));
+        let tenant_service =
+            TenantService::new(tenant_repo.clone(), user_repo, user_service.clone());
+        let service = WebAuthnService::new(
+            WebAuthnConfig {
+                rp_id: RP_ID.to_string(),
+                rp_name: "Example".to_string(),
+                origin: ORIGIN.to_string(),
+                user_verification: "required".to_string(),
+                attestation_cas,
+            },
+            credentials.clone(),
+            user_service,
+        )
+        .expect("WebAuthn service is created")
+        .with_tenant_repository(tenant_repo);
+
+        Self {
+            service,
+            tenant_service,
+            credentials,
+            tenant_id,
+            user,
+        }
+    }
+
+    async fn register(
+        &self,
+        authenticator: &Authenticator<'_>,
+    ) -> acci_core::error::Result<Credential> {
+        let mut session_data = serde_json::Value::Object(serde_json::Map::new());
+        let options = self
+            .service
+            .start_registration(&self.user, &self.tenant_id, &mut session_data)
+            .await?;
+        self.service
+            .complete_registration(
+                &self.user,
+                &self.tenant_id,
+                &mut session_data,
+                authenticator.register(&options),
+            )
+            .await
+    }
+}
+
+fn high_assurance(allowed_aaguids: Vec<Uuid>) -> WebAuthnAttestationPolicy {
+    WebAuthnAttestationPolicy {
+        require_attestation: true,
+        allowed_aaguids,
+    }
+}
+
+#[test]
+async fn test_allowed_authenticator_registers_and_stores_aaguid() {
+    let vendor = Vendor::new();
+    let aaguid = Uuid::new_v4();
+    let fixture = Fixture::new(high_assurance(vec![aaguid]), vendor.trusted()).await;
+
+    let credential = fixture
+        .register(&Authenticator {
+            aaguid,
+            vendor: Some(&vendor),
+        })
+        .await
+        .expect("Allowed authenticator registers");
+
+    assert_eq!(credential.aaguid, aaguid.as_bytes().to_vec());
+    let stored = fixture.credentials.credentials();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].aaguid, aaguid.as_bytes().to_vec());
+}
+
+#[test]
+async fn test_disallowed_aaguid_is_rejected() {
+    let vendor = Vendor::new();
+    let fixture = Fixture::new(high_assurance(vec![Uuid::new_v4()]), vendor.trusted()).await;
+
+    let result = fixture
+        .register(&Authenticator {
+            aaguid: Uuid::new_v4(),
+            vendor: Some(&vendor),
+        })
+        .await;
+
+    let err = result.expect_err("Disallowed authenticator is rejected");
+    assert!(err.to_string().contains("is not allowed"), "{}", err);
+    assert!(fixture.credentials.credentials().is_empty());
+}
+
+#[test]
+async fn test_none_attestation_depends_on_tenant_assurance() {
+    let vendor = Vendor::new();
+    let aaguid = Uuid::new_v4();
+    let unattested = Authenticator {
+        aaguid,
+        vendor: None,
+    };
+
+    let low = Fixture::new(
+        WebAuthnAttestationPolicy::default(),
+        AttestationCaList::default(),
+    )
+    .await;
+    let credential = low
+        .register(&unattested)
+        .await
+        .expect("Low-assurance tenant accepts none attestation");
+    assert_eq!(credential.aaguid, aaguid.as_bytes().to_vec());
+
+    let high = Fixture::new(high_assurance(Vec::new()), vendor.trusted()).await;
+    high.register(&unattested)
+        .await
+        .expect_err("High-assurance tenant rejects none attestation");
+    assert!(high.credentials.credentials().is_empty());
+}
+
+#[test]
+async fn test_untrusted_vendor_is_rejected() {
+    let trusted = Vendor::new();
+    let untrusted = Vendor::new();
+    let fixture = Fixture::new(high_assurance(Vec::new()), trusted.trusted()).await;
+
+    fixture
+        .register(&Authenticator {
+            aaguid: Uuid::new_v4(),
+            vendor: Some(&untrusted),
+        })
+        .await
+        .expect_err("Attestation of an untrusted vendor is rejected");
+}
+
+#[test]
+async fn test_required_attestation_without_trusted_cas_fails_to_start() {
+    let fixture = Fixture::new(high_assurance(Vec::new()), AttestationCaList::default()).await;
+    let mut session_data = serde_json::Value::Object(serde_json::Map::new());
+
+    let err = fixture
+        .service
+        .start_registration(&fixture.user, &fixture.tenant_id, &mut session_data)
+        .await
+        .expect_err("Registration cannot start without trusted CAs");
+    assert!(err.to_string().contains("no attestation CAs"), "{}", err);
+}
+
+#[test]
+async fn test_tenant_can_raise_its_assurance() {
+    let vendor = Vendor::new();
+    let aaguid = Uuid::new_v4();
+    let fixture = Fixture::new(WebAuthnAttestationPolicy::default(), vendor.trusted()).await;
+
+    let result = fixture
+        .tenant_service
+        .update_webauthn_attestation(&fixture.tenant_id, high_assurance(vec![Uuid::nil()]))
+        .await;
+    assert!(matches!(result, Err(TenantServiceError::InvalidInput(_))));
+
+    let policy = fixture
+        .tenant_service
+        .update_webauthn_attestation(&fixture.tenant_id, high_assurance(vec![aaguid, aaguid]))
+        .await
+        .expect("Policy is updated");
+    assert_eq!(policy.webauthn_attestation.allowed_aaguids, vec![aaguid]);
+
+    fixture
+        .register(&Authenticator {
+            aaguid,
+            vendor: None,
+        })
+        .await
+        .expect_err("Unattested authenticator is rejected after the update");
+    fixture
+        .register(&Authenticator {
+            aaguid,
+            vendor: Some(&vendor),
+        })
+        .await
+        .expect("Allowed authenticator registers");
+}
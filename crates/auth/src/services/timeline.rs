@@ -0,0 +1,243 @@
+//! Activity timeline of a user
+//!
+//! The timeline combines the user audit log, the session audit log and the
+//! issued verification codes into one list of [`TimelineEvent`]s, newest
+//! first. Each page reads at most `limit + 1` entries from every source and
+//! merges them in memory, so a page costs three bounded queries no matter
+//! how long the history is. Pages are continued with a [`TimelineCursor`]
+//! that orders events at the same instant by source and id, so no event is
+//! skipped or repeated across pages.
+//!
+//! Users viewing their own timeline do not see admin-only kinds such as
+//! impersonation, nor which admin acted in one of their sessions. Because
+//! these events are dropped after a page was cut, such a page may hold fewer
+//! events than requested while later pages still exist.
+
+use serde_json::Value;
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::models::timeline::{
+    TimelineCursor, TimelineEvent, TimelineEventKind, TimelineRecord, TimelineSource,
+};
+use crate::repository::{RepositoryError, TimelineRepository};
+use crate::session::IMPERSONATED_BY_METADATA_KEY;
+
+/// Events per page when the caller does not ask for a size
+pub const DEFAULT_TIMELINE_PAGE_SIZE: u32 = 50;
+
+/// Most events a single page holds
+pub const MAX_TIMELINE_PAGE_SIZE: u32 = 100;
+
+/// Who is looking at a timeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelineView {
+    /// A tenant admin, sees every event
+    Admin,
+    /// The user themselves, admin-only events are left out
+    Own,
+}
+
+/// A page of a user's timeline
+#[derive(Debug, Clone)]
+pub struct TimelinePage {
+    pub events: Vec<TimelineEvent>,
+    /// Where the next page starts, absent on the last page
+    pub next_cursor: Option<TimelineCursor>,
+}
+
+/// Builds activity timelines of users
+pub struct UserTimelineService {
+    repository: Arc<dyn TimelineRepository>,
+}
+
+impl UserTimelineService {
+    /// Create a new timeline service
+    pub fn new(repository: Arc<dyn TimelineRepository>) -> Self {
+        Self { repository }
+    }
+
+    /// Read a page of the timeline of a user
+    ///
+    /// With a tenant, session and verification events are limited to that
+    /// tenant. The page starts after `after` and holds at most `limit`
+    /// events, capped at [`MAX_TIMELINE_PAGE_SIZE`].
+    pub async fn timeline(
+        &self,
+        user_id: Uuid,
+        tenant_id: Option<Uuid>,
+        view: TimelineView,
+        after: Option<TimelineCursor>,
+        limit: u32,
+    ) -> Result<TimelinePage, RepositoryError> {
+        let limit = limit.clamp(1, MAX_TIMELINE_PAGE_SIZE);
+
+        let mut records = Vec::new();
+        for source in TimelineSource::ALL {
+            let before = after.map(|cursor| source_bound(&cursor, source));
+            records.extend(
+                self.repository
+                    .list(source, user_id, tenant_id, before, limit + 1)
+                    .await?,
+            );
+        }
+
+        // Every source returned its newest `limit + 1` entries after the
+        // cursor, so the first `limit` of the merged list are exact
+        records.sort_by_key(|record| std::cmp::Reverse(record.position().key()));
+        let next_cursor =
+            (records.len() > limit as usize).then(|| records[limit as usize - 1].position());
+        records.truncate(limit as usize);
+
+        let events: Vec<TimelineEvent> = records
+            .into_iter()
+            .map(normalize)
+            .filter_map(|event| redact(event, view))
+            .collect();
+
+        debug!(
+            user_id = %user_id,
+            tenant_id = ?tenant_id,
+            events = events.len(),
+            has_more = next_cursor.is_some(),
+            "Read user timeline page"
+        );
+        Ok(TimelinePage {
+            events,
+            next_cursor,
+        })
+    }
+}
+
+/// Exclusive `(at, id)` bound on the entries of `source` after `cursor`
+///
+/// Sources sorting before the cursor's source continue at the cursor's
+/// instant, later ones only strictly before it. Stored timestamps have
+/// microsecond precision, so including the cursor's instant is the same as
+/// excluding everything from the next microsecond on.
+fn source_bound(cursor: &TimelineCursor, source: TimelineSource) -> (OffsetDateTime, Uuid) {
+    match source.cmp(&cursor.source) {
+        std::cmp::Ordering::Equal => (cursor.at, cursor.id),
+        std::cmp::Ordering::Greater => (cursor.at, Uuid::nil()),
+        std::cmp::Ordering::Less => (cursor.at + Duration::microseconds(1), Uuid::nil()),
+    }
+}
+
+/// Kind and summary of a source entry
+fn classify(record: &TimelineRecord) -> (TimelineEventKind, String) {
+    use TimelineEventKind as Kind;
+
+    let detail = |key: &str| {
+        record
+            .details
+            .get(key)
+            .and_then(Value::as_str)
+            .unwrap_or("unknown")
+            .to_string()
+    };
+
+    if record.source == TimelineSource::Verification {
+        return (
+            Kind::VerificationSent,
+            format!(
+                "Sent {} verification code",
+                detail("verification_type").to_lowercase()
+            ),
+        );
+    }
+
+    match record.action.as_str() {
+        "REGISTRATION" => (Kind::Registration, "Account registered".to_string()),
+        "EMAIL_VERIFICATION_SUCCESS" => (Kind::EmailVerified, "Email address verified".to_string()),
+        "USER_ACTIVATED" => (Kind::AccountStatusChanged, "Account activated".to_string()),
+        "USER_DEACTIVATED" => (
+            Kind::AccountStatusChanged,
+            "Account deactivated".to_string(),
+        ),
+        "SESSION_CREATED" => (Kind::SessionStarted, "Signed in".to_string()),
+        "SESSION_EXPIRED" => (Kind::SessionEnded, "Session expired".to_string()),
+        "SESSION_INVALIDATED_BY_USER" => (Kind::SessionEnded, "Signed out".to_string()),
+        "SESSION_INVALIDATED_BY_ADMIN" => (
+            Kind::SessionEnded,
+            "Session ended by an administrator".to_string(),
+        ),
+        "SESSION_INVALIDATED_DUE_TO_INACTIVITY" => (
+            Kind::SessionEnded,
+            "Session ended after inactivity".to_string(),
+        ),
+        "SESSION_INVALIDATED_PASSWORD_CHANGED" => (
+            Kind::SessionEnded,
+            "Session ended after a password change".to_string(),
+        ),
+        "SESSION_INVALIDATED_CONCURRENT_LIMIT" | "OTHER_SESSIONS_TERMINATED" => {
+            (Kind::SessionEnded, "Other sessions ended".to_string())
+        },
+        "SESSION_INVALIDATED_SECURITY_BREACH"
+        | "SESSION_INVALIDATED_SUSPICIOUS_ACTIVITY"
+        | "SESSION_INVALIDATED_SUSPICIOUS_LOCATION" => (
+            Kind::SecurityAlert,
+            "Session ended for security reasons".to_string(),
+        ),
+        "SUSPICIOUS_LOCATION_DETECTED"
+        | "CONCURRENT_SESSION_DETECTED"
+        | "RISK_ASSESSMENT_PERFORMED"
+        | "STEP_UP_AUTHENTICATION_REQUIRED" => {
+            (Kind::SecurityAlert, "Suspicious activity".to_string())
+        },
+        "LOGIN_LOCATION_BLOCKED" => (
+            Kind::SecurityAlert,
+            "Sign-in blocked by the tenant's location policy".to_string(),
+        ),
+        "MFA_VERIFIED" => (Kind::MfaVerified, "Second factor verified".to_string()),
+        "FACTOR_ADDED" => (
+            Kind::FactorAdded,
+            format!("Added {} factor", detail("factor")),
+        ),
+        "FACTOR_REMOVED" => (
+            Kind::FactorRemoved,
+            format!("Removed {} factor", detail("factor")),
+        ),
+        "TENANT_SWITCHED" => (Kind::TenantSwitched, "Switched tenant".to_string()),
+        "DEVICE_CHANGED" => (
+            Kind::DeviceChanged,
+            "Signed in from a new device".to_string(),
+        ),
+        "IMPERSONATION_STARTED" => (Kind::Impersonation, "Impersonation started".to_string()),
+        "IMPERSONATION_ENDED" => (Kind::Impersonation, "Impersonation ended".to_string()),
+        action => (Kind::Other, action.to_lowercase().replace('_', " ")),
+    }
+}
+
+/// Turn a source entry into a timeline event
+fn normalize(record: TimelineRecord) -> TimelineEvent {
+    let (kind, summary) = classify(&record);
+    let mut metadata = record.details;
+    if let Value::Object(map) = &mut metadata {
+        map.insert("action".to_string(), Value::String(record.action));
+    }
+
+    TimelineEvent {
+        at: record.at,
+        kind,
+        summary,
+        metadata,
+        source: record.source,
+        id: record.id,
+    }
+}
+
+/// Drop or trim the event for the given view
+fn redact(mut event: TimelineEvent, view: TimelineView) -> Option<TimelineEvent> {
+    if view == TimelineView::Admin {
+        return Some(event);
+    }
+    if event.kind.is_admin_only() {
+        return None;
+    }
+    if let Value::Object(map) = &mut event.metadata {
+        map.remove(IMPERSONATED_BY_METADATA_KEY);
+    }
+    Some(event)
+}
@@ -1,5 +1,11 @@
+use crate::config::SensitiveOperation;
+use crate::models::factor::AuthFactor;
 use crate::models::{TenantId, TotpConfig, TotpSecret, TotpSecretInfo, UserId};
 use crate::repository::TotpSecretRepository;
+use crate::services::user::{UserService, UserServiceError};
+use crate::session::Session;
+use crate::session::types::FactorAction;
+use crate::utils::clock::{Clock, SystemClock};
 use crate::utils::password::generate_salt;
 use argon2::{
     Argon2,
@@ -10,7 +16,6 @@ use rand::Rng;
 use rand::rngs::ThreadRng;
 use std::sync::Arc;
 use thiserror::Error;
-use time::OffsetDateTime;
 use totp_rs::{Secret, TOTP};
 use tracing::{debug, error, info, instrument, warn};
 use urlencoding;
@@ -32,67 +37,104 @@ pub enum TotpError {
 
     #[error("Security validation failed")]
     SecurityValidationFailed,
+
+    #[error("Recent re-authentication required")]
+    ReauthRequired,
+
+    #[error("The tenant policy requires at least one second factor")]
+    LastSecondFactor,
+
+    #[error("TOTP lifecycle management is not configured")]
+    LifecycleNotConfigured,
+
+    #[error("TOTP is already enabled for this user")]
+    AlreadyEnrolled,
+
+    #[error("No pending TOTP enrollment")]
+    EnrollmentNotFound,
+
+    #[error("The pending TOTP enrollment has expired")]
+    EnrollmentExpired,
+}
+
+impl From<UserServiceError> for TotpError {
+    fn from(err: UserServiceError) -> Self {
+        match err {
+            UserServiceError::FactorNotFound => TotpError::MfaNotEnabled,
+            UserServiceError::LastSecondFactor => TotpError::LastSecondFactor,
+            err => TotpError::RepositoryError(err.to_string()),
+        }
+    }
 }
 
 /// Service for managing TOTP (Time-based One-Time Password) authentication
-pub struct TotpService {
+pub struct TotpService<C: Clock = SystemClock> {
     secret_repository: Arc<dyn TotpSecretRepository>,
     config: TotpConfig,
+    /// Enforces the tenant policy and audits the `disable`/`regenerate` lifecycle
+    user_service: Option<Arc<UserService>>,
+    clock: C,
 }
 
 impl TotpService {
     /// Create a new TotpService
     pub fn new(secret_repository: Arc<dyn TotpSecretRepository>, config: TotpConfig) -> Self {
+        Self::with_clock(secret_repository, config, SystemClock)
+    }
+}
+
+impl<C: Clock> TotpService<C> {
+    /// Create a TotpService that reads the current time from `clock`
+    pub fn with_clock(
+        secret_repository: Arc<dyn TotpSecretRepository>,
+        config: TotpConfig,
+        clock: C,
+    ) -> Self {
         Self {
             secret_repository,
             config,
+            user_service: None,
+            clock,
         }
     }
 
-    /// Generate a new TOTP secret for a user
+    /// Enables [`disable`](Self::disable) and [`regenerate`](Self::regenerate)
+    pub fn with_user_service(mut self, user_service: Arc<UserService>) -> Self {
+        self.user_service = Some(user_service);
+        self
+    }
+
+    /// Start the TOTP enrollment of a user
+    ///
+    /// Stores a pending secret and returns what the authenticator app needs.
+    /// The secret does not satisfy MFA until a code generated from it is
+    /// passed to [`confirm_enrollment`](Self::confirm_enrollment); starting
+    /// again replaces a pending secret. Fails with `AlreadyEnrolled` once
+    /// TOTP is enabled, use [`regenerate`](Self::regenerate) to replace an
+    /// enabled secret.
     #[instrument(skip(self), err)]
-    pub async fn generate_totp_secret(
+    pub async fn begin_enrollment(
         &self,
         user_id: &UserId,
         tenant_id: &TenantId,
     ) -> Result<TotpSecretInfo, TotpError> {
-        // Generate cryptographically secure random bytes for the secret
-        let mut rng = ThreadRng::default();
-        let secret_bytes: Vec<u8> = (0..32).map(|_| rng.random()).collect();
-
-        // Encode as base32 for user entry into authenticator apps
-        let secret = base32::encode(base32::Alphabet::RFC4648 { padding: false }, &secret_bytes);
+        let existing = self
+            .secret_repository
+            .get_by_user_id(user_id, tenant_id)
+            .await
+            .map_err(|e| TotpError::RepositoryError(e.to_string()))?;
+        if existing.is_some_and(|secret| secret.is_setup_complete()) {
+            return Err(TotpError::AlreadyEnrolled);
+        }
 
-        // Generate a provisioning URI for QR code generation
-        let uri = format!(
-            "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm={}&digits={}&period={}",
-            self.config.issuer,
-            user_id,
-            secret,
-            urlencoding::encode(&self.config.issuer),
-            self.config.algorithm,
-            self.config.digits,
-            self.config.period
-        );
+        let secret = generate_secret();
+        let uri = self.provisioning_uri(user_id, &secret);
 
         // Create recovery codes (one-time use backup codes)
         let recovery_codes = self.generate_recovery_codes()?;
+        let hashed_recovery_codes = hash_recovery_codes(&recovery_codes)?;
 
-        // Hash recovery codes before storing
-        let argon2 = Argon2::default();
-        let hashed_recovery_codes = recovery_codes
-            .iter()
-            .map(|code| {
-                let salt = generate_salt();
-                argon2
-                    .hash_password(code.as_bytes(), &salt)
-                    .map(|hash| hash.to_string())
-                    .map_err(|e| TotpError::InternalError(e.to_string()))
-            })
-            .collect::<Result<Vec<String>, _>>()?;
-
-        // Create TOTP secret object
-        let totp_secret = TotpSecret::new(
+        let mut totp_secret = TotpSecret::new(
             *user_id,
             *tenant_id,
             secret.clone(),
@@ -101,38 +143,83 @@ impl TotpService {
             self.config.period,
             hashed_recovery_codes,
         );
+        totp_secret.created_at = self.clock.now();
 
-        // Save to repository
         self.secret_repository
             .save(&totp_secret)
             .await
             .map_err(|e| TotpError::RepositoryError(e.to_string()))?;
 
-        info!("Generated new TOTP secret for user {}", user_id);
+        info!("Started TOTP enrollment for user {}", user_id);
 
-        // Return information needed for setup
         Ok(TotpSecretInfo {
-            secret: secret.clone(),
+            secret,
             uri,
             recovery_codes,
         })
     }
 
-    /// Verify a TOTP code provided by the user
-    #[instrument(skip(self), err)]
-    pub async fn verify_totp(
+    /// Complete the TOTP enrollment of a user
+    ///
+    /// Enables the pending secret if `code` was generated from it. Recovery
+    /// codes are not accepted. A wrong code fails with `InvalidMfaCode` and
+    /// leaves the secret pending, so the user can try again until the
+    /// enrollment expires.
+    #[instrument(skip(self, code), err)]
+    pub async fn confirm_enrollment(
         &self,
         user_id: &UserId,
         tenant_id: &TenantId,
         code: &str,
-    ) -> Result<bool, TotpError> {
-        // Get the user's TOTP secret
+    ) -> Result<(), TotpError> {
         let mut totp_secret = self
             .secret_repository
             .get_by_user_id(user_id, tenant_id)
             .await
             .map_err(|e| TotpError::RepositoryError(e.to_string()))?
-            .ok_or(TotpError::MfaNotEnabled)?;
+            .ok_or(TotpError::EnrollmentNotFound)?;
+        if totp_secret.is_setup_complete() {
+            return Err(TotpError::AlreadyEnrolled);
+        }
+
+        let now = self.clock.now();
+        if totp_secret.created_at + self.config.enrollment_timeout() <= now {
+            self.secret_repository
+                .delete(user_id, tenant_id)
+                .await
+                .map_err(|e| TotpError::RepositoryError(e.to_string()))?;
+            debug!("Discarded expired TOTP enrollment of user {}", user_id);
+            return Err(TotpError::EnrollmentExpired);
+        }
+
+        if !self.check_totp_code(&totp_secret, code)? {
+            debug!("Invalid TOTP confirmation code for user {}", user_id);
+            return Err(TotpError::InvalidMfaCode);
+        }
+
+        totp_secret.enabled = true;
+        totp_secret.last_used_at = Some(now);
+        self.secret_repository
+            .save(&totp_secret)
+            .await
+            .map_err(|e| TotpError::RepositoryError(e.to_string()))?;
+
+        info!("Enabled TOTP for user {}", user_id);
+        Ok(())
+    }
+
+    /// Verify a TOTP code provided by the user
+    ///
+    /// Only an enabled secret is checked; a pending enrollment fails with
+    /// `MfaNotEnabled`.
+    #[instrument(skip(self), err)]
+    pub async fn verify_totp(
+        &self,
+        user_id: &UserId,
+        tenant_id: &TenantId,
+        code: &str,
+    ) -> Result<bool, TotpError> {
+        let mut totp_secret = self.enabled_secret(user_id, tenant_id).await?;
 
         // Try to verify TOTP code
         let is_valid = match self.verify_code(&totp_secret, code).await {
@@ -143,11 +230,10 @@ impl TotpService {
             },
         };
 
-        // If valid, update last used time and enable if not already
+        // If valid, update last used time
         if is_valid {
             debug!("Valid TOTP code for user {}", user_id);
-            totp_secret.last_used_at = Some(OffsetDateTime::now_utc());
-            totp_secret.enabled = true;
+            totp_secret.last_used_at = Some(self.clock.now());
 
             // Save updated secret
             self.secret_repository
@@ -163,6 +249,17 @@ impl TotpService {
 
     /// Verify a TOTP code against the user's secret
     async fn verify_code(&self, secret: &TotpSecret, code: &str) -> Result<bool, TotpError> {
+        if self.check_totp_code(secret, code)? {
+            return Ok(true);
+        }
+
+        // If TOTP code is not valid, check recovery codes
+        self.verify_recovery_code(secret, &code.replace(" ", ""))
+            .await
+    }
+
+    /// Check a code generated by an authenticator app, ignoring recovery codes
+    fn check_totp_code(&self, secret: &TotpSecret, code: &str) -> Result<bool, TotpError> {
         // Parse code as a number (removing spaces if present)
         let code = code.replace(" ", "");
 
@@ -191,7 +288,7 @@ impl TotpService {
         .map_err(|e| TotpError::InternalError(e.to_string()))?;
 
         // Check if the code is valid
-        let now = OffsetDateTime::now_utc();
+        let now = self.clock.now();
         let current_timestamp = now.unix_timestamp() as u64;
 
         // We need to check with a window of periods both before and after
@@ -223,12 +320,21 @@ impl TotpService {
             }
         }
 
-        if !is_valid {
-            // If TOTP code is not valid, check recovery codes
-            return self.verify_recovery_code(secret, &code).await;
-        }
+        Ok(is_valid)
+    }
 
-        Ok(true)
+    /// Provisioning URI for QR code generation
+    fn provisioning_uri(&self, user_id: &UserId, secret: &str) -> String {
+        format!(
+            "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm={}&digits={}&period={}",
+            self.config.issuer,
+            user_id,
+            secret,
+            urlencoding::encode(&self.config.issuer),
+            self.config.algorithm,
+            self.config.digits,
+            self.config.period
+        )
     }
 
     /// Generate recovery codes for backup access
@@ -369,18 +475,7 @@ impl TotpService {
         // Generate new recovery codes
         let recovery_codes = self.generate_recovery_codes()?;
 
-        // Hash recovery codes before storing
-        let argon2 = Argon2::default();
-        let hashed_recovery_codes = recovery_codes
-            .iter()
-            .map(|code| {
-                let salt = generate_salt();
-                argon2
-                    .hash_password(code.as_bytes(), &salt)
-                    .map(|hash| hash.to_string())
-                    .map_err(|e| TotpError::InternalError(e.to_string()))
-            })
-            .collect::<Result<Vec<String>, _>>()?;
+        let hashed_recovery_codes = hash_recovery_codes(&recovery_codes)?;
 
         // Update recovery codes
         totp_secret.recovery_codes = hashed_recovery_codes;
@@ -394,4 +489,173 @@ impl TotpService {
         info!("Regenerated recovery codes for user {}", user_id);
         Ok(recovery_codes)
     }
+
+    /// Turn off TOTP for a user within a tenant
+    ///
+    /// Requires a recent re-authentication of `session`. The secret and its
+    /// recovery codes are deleted, so neither works afterwards. Fails with
+    /// `LastSecondFactor` if TOTP is the only second factor and the tenant
+    /// policy requires MFA for the user.
+    #[instrument(skip(self, session), fields(session_id = %session.id), err)]
+    pub async fn disable(
+        &self,
+        user_id: &UserId,
+        tenant_id: &TenantId,
+        session: &Session,
+    ) -> Result<(), TotpError> {
+        let user_service = self.authorize_lifecycle(user_id, session)?;
+        let secret = self.enabled_secret(user_id, tenant_id).await?;
+        user_service
+            .ensure_factor_removable(*user_id, *tenant_id, &AuthFactor::Totp)
+            .await?;
+
+        self.secret_repository
+            .delete(user_id, tenant_id)
+            .await
+            .map_err(|e| TotpError::RepositoryError(e.to_string()))?;
+
+        let session_service = user_service.session_service();
+        session_service
+            .record_factor_event(session.id, FactorAction::Removed, &AuthFactor::Totp)
+            .await
+            .map_err(|e| TotpError::RepositoryError(e.to_string()))?;
+        if !secret.recovery_codes.is_empty() {
+            session_service
+                .record_factor_event(
+                    session.id,
+                    FactorAction::Removed,
+                    &AuthFactor::RecoveryCodes,
+                )
+                .await
+                .map_err(|e| TotpError::RepositoryError(e.to_string()))?;
+        }
+
+        info!(
+            user_id = %user_id,
+            tenant_id = %tenant_id,
+            session_id = %session.id,
+            "Disabled TOTP"
+        );
+        Ok(())
+    }
+
+    /// Replace the TOTP secret and recovery codes of a user within a tenant
+    ///
+    /// Requires a recent re-authentication of `session`. Codes of the old
+    /// secret and the old recovery codes stop working immediately; the
+    /// returned provisioning URI sets up the new secret.
+    #[instrument(skip(self, session), fields(session_id = %session.id), err)]
+    pub async fn regenerate(
+        &self,
+        user_id: &UserId,
+        tenant_id: &TenantId,
+        session: &Session,
+    ) -> Result<TotpSecretInfo, TotpError> {
+        let user_service = self.authorize_lifecycle(user_id, session)?;
+        let mut totp_secret = self.enabled_secret(user_id, tenant_id).await?;
+
+        let secret = generate_secret();
+        let uri = self.provisioning_uri(user_id, &secret);
+        let recovery_codes = self.generate_recovery_codes()?;
+
+        // Keep the record so the user stays enrolled, but with new key material
+        totp_secret.secret = secret.clone();
+        totp_secret.algorithm = self.config.algorithm.to_string();
+        totp_secret.digits = self.config.digits;
+        totp_secret.period = self.config.period;
+        totp_secret.recovery_codes = hash_recovery_codes(&recovery_codes)?;
+        totp_secret.last_used_at = None;
+
+        self.secret_repository
+            .save(&totp_secret)
+            .await
+            .map_err(|e| TotpError::RepositoryError(e.to_string()))?;
+
+        let session_service = user_service.session_service();
+        for action in [FactorAction::Removed, FactorAction::Added] {
+            session_service
+                .record_factor_event(session.id, action, &AuthFactor::Totp)
+                .await
+                .map_err(|e| TotpError::RepositoryError(e.to_string()))?;
+        }
+
+        info!(
+            user_id = %user_id,
+            tenant_id = %tenant_id,
+            session_id = %session.id,
+            "Regenerated TOTP secret"
+        );
+        Ok(TotpSecretInfo {
+            secret,
+            uri,
+            recovery_codes,
+        })
+    }
+
+    /// Check that `session` may manage the TOTP factor of the user
+    fn authorize_lifecycle(
+        &self,
+        user_id: &UserId,
+        session: &Session,
+    ) -> Result<&Arc<UserService>, TotpError> {
+        let user_service = self
+            .user_service
+            .as_ref()
+            .ok_or(TotpError::LifecycleNotConfigured)?;
+
+        if session.user_id != *user_id {
+            warn!(
+                user_id = %user_id,
+                session_id = %session.id,
+                "Session does not belong to the user managing TOTP"
+            );
+            return Err(TotpError::SecurityValidationFailed);
+        }
+        if user_service
+            .session_service()
+            .reauth_required_for(session.last_auth_at(), SensitiveOperation::ManageFactors)
+        {
+            return Err(TotpError::ReauthRequired);
+        }
+
+        Ok(user_service)
+    }
+
+    /// The TOTP secret of the user if its setup is complete
+    async fn enabled_secret(
+        &self,
+        user_id: &UserId,
+        tenant_id: &TenantId,
+    ) -> Result<TotpSecret, TotpError> {
+        self.secret_repository
+            .get_by_user_id(user_id, tenant_id)
+            .await
+            .map_err(|e| TotpError::RepositoryError(e.to_string()))?
+            .filter(TotpSecret::is_setup_complete)
+            .ok_or(TotpError::MfaNotEnabled)
+    }
+}
+
+/// Generate a Base32-encoded secret for entry into authenticator apps
+fn generate_secret() -> String {
+    // Generate cryptographically secure random bytes for the secret
+    let mut rng = ThreadRng::default();
+    let secret_bytes: Vec<u8> = (0..32).map(|_| rng.random()).collect();
+
+    base32::encode(base32::Alphabet::RFC4648 { padding: false }, &secret_bytes)
+}
+
+/// Hash recovery codes before storing
+fn hash_recovery_codes(recovery_codes: &[String]) -> Result<Vec<String>, TotpError> {
+    let argon2 = Argon2::default();
+    recovery_codes
+        .iter()
+        .map(|code| {
+            let salt = generate_salt();
+            argon2
+                .hash_password(code.as_bytes(), &salt)
+                .map(|hash| hash.to_string())
+                .map_err(|e| TotpError::InternalError(e.to_string()))
+        })
+        .collect()
 }
@@ -1,24 +1,46 @@
+use acci_core::{ActionToken, ActionTokenError};
 use lazy_static::lazy_static;
 use regex::Regex;
+use serde::Serialize;
 use serde_json::json;
 use std::sync::Arc;
+use time::OffsetDateTime;
 use uuid::Uuid;
 
+#[cfg(feature = "enable_webauthn")]
+use crate::repository::WebAuthnRepository;
 use crate::{
     AuthConfig, SessionService, SessionServiceError,
     models::{
-        VerificationType,
-        user::{CreateUser, User, UserError, UserRepository},
+        TenantId, VerificationType,
+        factor::{AuthFactor, EnrolledFactor},
+        login_attempt::LoginOutcome,
+        tenant::{
+            LoginLocationViolation, PasswordExpiry, TenantError, TenantRepository,
+            TenantSecurityPolicy, TenantUser,
+        },
+        totp::TotpSecret,
+        user::{CreateUser, User, UserError, UserRepository, UserSessionState},
+    },
+    repository::{
+        AccountDeletionRepository, ExternalIdentityRepository, RepositoryError, TenantAwareContext,
+        TotpSecretRepository,
+    },
+    security::{FingerprintService, TrustedDevice, TrustedDeviceError, TrustedDeviceService},
+    services::{
+        ApiKeyError, ApiKeyService, VerificationError, VerificationService,
+        login_attempts::{LoginAttemptContext, LoginAttemptRecorder},
     },
-    repository::TenantAwareContext,
-    services::{VerificationError, VerificationService},
     session::{
-        Session, SessionFilter,
-        types::{DeviceFingerprint, MfaStatus, SessionInvalidationReason},
+        ACTIVE_TENANT_METADATA_KEY, Session, SessionError, SessionFilter,
+        types::{DeviceFingerprint, FactorAction, MfaStatus, SessionInvalidationReason},
     },
     utils::{
         jwt::{JwtError, JwtUtils},
-        password::{PasswordError, check_password_strength, hash_password, verify_password},
+        password::{
+            Argon2Verifier, NO_PASSWORD_HASH, PasswordError, PasswordVerifier,
+            check_password_strength, hash_password,
+        },
     },
 };
 
@@ -36,6 +58,9 @@ lazy_static! {
         .expect("Invalid default tenant UUID");
 }
 
+/// Action of the [`ActionToken`]s that let a user set a new password
+pub const PASSWORD_RESET_ACTION: &str = "password-reset";
+
 #[derive(Debug, thiserror::Error)]
 pub enum UserServiceError {
     #[error(transparent)]
@@ -60,6 +85,43 @@ pub enum UserServiceError {
     MfaVerificationFailed(String),
     #[error("MFA not configured")]
     MfaNotConfigured,
+    #[error("MFA enrollment required")]
+    MfaEnrollmentRequired,
+    #[error("MFA enrollment grace period has expired")]
+    MfaEnrollmentExpired,
+    /// The password is past the maximum age of the tenant policy, or an
+    /// administrator required a new one with [`UserService::revoke_access`]
+    ///
+    /// Carries a single-use token for [`UserService::reset_password`] if
+    /// action tokens are configured.
+    #[error("Password has expired")]
+    PasswordExpired { reset_token: Option<String> },
+    #[error("The password was used recently")]
+    PasswordReused,
+    #[error("Invalid password reset token: {0}")]
+    ResetToken(#[from] ActionTokenError),
+    #[error("Password resets are not configured")]
+    PasswordResetNotConfigured,
+    #[error("Login location not allowed by the tenant policy: {0}")]
+    LoginLocationBlocked(LoginLocationViolation),
+    #[error("Tenant error: {0}")]
+    Tenant(#[from] TenantError),
+    #[error("User is not an active member of the tenant")]
+    NotTenantMember,
+    #[error("MFA repository error: {0}")]
+    MfaRepository(#[from] RepositoryError),
+    #[error("Authentication factor not found")]
+    FactorNotFound,
+    #[error("The password is the only way to sign in")]
+    LastSignInMethod,
+    #[error("The tenant policy requires at least one second factor")]
+    LastSecondFactor,
+    #[error("Trusted devices are not configured")]
+    TrustedDevicesNotConfigured,
+    #[error("Trusted device error: {0}")]
+    TrustedDevice(#[from] TrustedDeviceError),
+    #[error("API key error: {0}")]
+    ApiKey(#[from] ApiKeyError),
 }
 
 impl From<VerificationError> for UserServiceError {
@@ -68,17 +130,128 @@ impl From<VerificationError> for UserServiceError {
     }
 }
 
+impl From<UserServiceError> for acci_core::Error {
+    fn from(err: UserServiceError) -> Self {
+        use acci_core::error::ErrorKind;
+
+        let kind = match err {
+            UserServiceError::User(e) => return e.into(),
+            UserServiceError::Tenant(e) => return e.into(),
+            UserServiceError::Session(e) => return e.into(),
+            UserServiceError::Repository(e) => return Self::Database(e),
+            UserServiceError::Password(PasswordError::TooWeak(..)) => ErrorKind::WeakPassword,
+            UserServiceError::InvalidCredentials => ErrorKind::InvalidCredentials,
+            UserServiceError::UserNotFound => ErrorKind::UserNotFound,
+            UserServiceError::FactorNotFound => ErrorKind::NotFound,
+            UserServiceError::RateLimitExceeded => ErrorKind::RateLimited,
+            UserServiceError::MfaRequired => ErrorKind::Unauthenticated,
+            UserServiceError::MfaVerificationFailed(_) => ErrorKind::InvalidCode,
+            UserServiceError::MfaEnrollmentRequired => ErrorKind::MfaEnrollmentRequired,
+            UserServiceError::MfaEnrollmentExpired => ErrorKind::MfaEnrollmentExpired,
+            UserServiceError::PasswordExpired { .. } => ErrorKind::PasswordExpired,
+            UserServiceError::PasswordReused => ErrorKind::PasswordReused,
+            UserServiceError::ResetToken(ActionTokenError::Redis(_)) => ErrorKind::Unavailable,
+            UserServiceError::ResetToken(ActionTokenError::ConsumptionNotConfigured) => {
+                ErrorKind::Configuration
+            },
+            UserServiceError::ResetToken(ActionTokenError::Expired) => ErrorKind::CodeExpired,
+            UserServiceError::ResetToken(_) => ErrorKind::InvalidCode,
+            UserServiceError::LoginLocationBlocked(_) => ErrorKind::LoginLocationBlocked,
+            UserServiceError::NotTenantMember => ErrorKind::TenantAccessDenied,
+            UserServiceError::LastSignInMethod | UserServiceError::LastSecondFactor => {
+                ErrorKind::InvalidInput
+            },
+            UserServiceError::MfaNotConfigured
+            | UserServiceError::TrustedDevicesNotConfigured
+            | UserServiceError::PasswordResetNotConfigured => ErrorKind::Configuration,
+            UserServiceError::Password(_)
+            | UserServiceError::Jwt(_)
+            | UserServiceError::MfaRepository(_)
+            | UserServiceError::TrustedDevice(_)
+            | UserServiceError::ApiKey(_) => ErrorKind::Internal,
+        };
+        Self::domain(kind, err.to_string())
+    }
+}
+
 pub struct UserService {
     repository: Arc<dyn UserRepository>,
     _jwt_utils: Arc<JwtUtils>,
     session_service: Arc<SessionService>,
     verification_service: Option<Arc<VerificationService>>,
-    _config: Arc<AuthConfig>,
+    config: Arc<AuthConfig>,
+    tenant_repository: Option<Arc<dyn TenantRepository>>,
+    totp_repository: Option<Arc<dyn TotpSecretRepository>>,
+    #[cfg(feature = "enable_webauthn")]
+    webauthn_repository: Option<Arc<dyn WebAuthnRepository>>,
+    identity_repository: Option<Arc<dyn ExternalIdentityRepository>>,
+    account_deletion_repository: Option<Arc<dyn AccountDeletionRepository>>,
+    fingerprint_service: Option<Arc<FingerprintService>>,
+    trusted_device_service: Option<Arc<TrustedDeviceService>>,
+    login_attempt_recorder: Option<Arc<LoginAttemptRecorder>>,
+    action_tokens: Option<Arc<ActionToken>>,
+    api_key_service: Option<Arc<ApiKeyService>>,
+    password_verifier: Arc<dyn PasswordVerifier>,
+}
+
+/// What [`UserService::revoke_access`] revoked
+///
+/// Artifacts revoked before are not counted again, so repeating a revocation
+/// reports zeros.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct AccessRevocation {
+    /// Valid sessions invalidated
+    pub sessions_revoked: u64,
+    /// API keys the user issued that were revoked
+    pub api_keys_revoked: u64,
+    /// Whether a password reset became required; `false` if it was already
+    pub password_reset_required: bool,
 }
 
 pub struct LoginResult {
     pub user: User,
     pub session_token: String,
+    /// MFA status of the created session; `Pending` means only enrollment is permitted
+    pub mfa_status: MfaStatus,
+    /// Tenant the session acts as
+    pub active_tenant_id: Option<Uuid>,
+    /// Active tenant memberships of the user
+    ///
+    /// With several memberships and no tenant requested at login, the session has
+    /// no active tenant until the client switches to one of these.
+    pub memberships: Vec<TenantUser>,
+    /// When the account will be deleted, if the user requested its deletion
+    ///
+    /// Logins still succeed during the grace period, so clients can offer to
+    /// cancel the deletion.
+    pub pending_deletion: Option<OffsetDateTime>,
+    /// Whole days until the password expires, if the tenant policy warns about it
+    pub password_expires_in_days: Option<u32>,
+}
+
+impl LoginResult {
+    /// Whether the client has to pick one of the memberships before acting on a tenant
+    pub fn tenant_selection_required(&self) -> bool {
+        self.active_tenant_id.is_none() && self.memberships.len() > 1
+    }
+}
+
+/// MFA enrollment state of a user within a tenant
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MfaEnrollment {
+    pub user_id: Uuid,
+    pub tenant_id: TenantId,
+    /// Whether the user has a confirmed TOTP secret
+    pub totp_enrolled: bool,
+    /// Number of registered WebAuthn credentials
+    pub webauthn_credentials: usize,
+}
+
+impl MfaEnrollment {
+    /// Returns whether the user has enrolled at least one second factor
+    pub fn is_enrolled(&self) -> bool {
+        self.totp_enrolled || self.webauthn_credentials > 0
+    }
 }
 
 impl UserService {
@@ -94,10 +267,114 @@ impl UserService {
             _jwt_utils: jwt_utils,
             session_service,
             verification_service,
-            _config: config,
+            config,
+            tenant_repository: None,
+            totp_repository: None,
+            #[cfg(feature = "enable_webauthn")]
+            webauthn_repository: None,
+            identity_repository: None,
+            account_deletion_repository: None,
+            fingerprint_service: None,
+            trusted_device_service: None,
+            login_attempt_recorder: None,
+            action_tokens: None,
+            api_key_service: None,
+            password_verifier: Arc::new(Argon2Verifier),
         }
     }
 
+    /// Enables tenant security policy enforcement during login
+    pub fn with_tenant_repository(mut self, tenant_repository: Arc<dyn TenantRepository>) -> Self {
+        self.tenant_repository = Some(tenant_repository);
+        self
+    }
+
+    /// Enables TOTP secrets as a source for MFA enrollment
+    pub fn with_totp_repository(mut self, totp_repository: Arc<dyn TotpSecretRepository>) -> Self {
+        self.totp_repository = Some(totp_repository);
+        self
+    }
+
+    /// Enables WebAuthn credentials as a source for MFA enrollment
+    #[cfg(feature = "enable_webauthn")]
+    pub fn with_webauthn_repository(
+        mut self,
+        webauthn_repository: Arc<dyn WebAuthnRepository>,
+    ) -> Self {
+        self.webauthn_repository = Some(webauthn_repository);
+        self
+    }
+
+    /// Enables linked external identities as a sign-in method besides the password
+    pub fn with_identity_repository(
+        mut self,
+        identity_repository: Arc<dyn ExternalIdentityRepository>,
+    ) -> Self {
+        self.identity_repository = Some(identity_repository);
+        self
+    }
+
+    /// Enables reporting pending account deletions in login results
+    pub fn with_account_deletion_repository(
+        mut self,
+        account_deletion_repository: Arc<dyn AccountDeletionRepository>,
+    ) -> Self {
+        self.account_deletion_repository = Some(account_deletion_repository);
+        self
+    }
+
+    /// Enables recording the device fingerprints of new sessions
+    pub fn with_fingerprint_service(
+        mut self,
+        fingerprint_service: Arc<FingerprintService>,
+    ) -> Self {
+        self.fingerprint_service = Some(fingerprint_service);
+        self
+    }
+
+    /// Enables skipping the MFA challenge on devices the user marked as trusted
+    pub fn with_trusted_device_service(
+        mut self,
+        trusted_device_service: Arc<TrustedDeviceService>,
+    ) -> Self {
+        self.trusted_device_service = Some(trusted_device_service);
+        self
+    }
+
+    /// Enables storing password login attempts for analytics
+    pub fn with_login_attempt_recorder(
+        mut self,
+        login_attempt_recorder: Arc<LoginAttemptRecorder>,
+    ) -> Self {
+        self.login_attempt_recorder = Some(login_attempt_recorder);
+        self
+    }
+
+    /// Enables password reset tokens, handed out when a login finds the password expired
+    ///
+    /// Redeeming them with [`Self::reset_password`] needs Redis on the [`ActionToken`].
+    pub fn with_action_tokens(mut self, action_tokens: Arc<ActionToken>) -> Self {
+        self.action_tokens = Some(action_tokens);
+        self
+    }
+
+    /// Enables revoking the API keys a user issued when their access is revoked
+    pub fn with_api_key_service(mut self, api_key_service: Arc<ApiKeyService>) -> Self {
+        self.api_key_service = Some(api_key_service);
+        self
+    }
+
+    /// Replaces the Argon2 verification of passwords
+    pub fn with_password_verifier(mut self, password_verifier: Arc<dyn PasswordVerifier>) -> Self {
+        self.password_verifier = password_verifier;
+        self
+    }
+
+    /// Session service used for sessions of the users
+    pub fn session_service(&self) -> &Arc<SessionService> {
+        &self.session_service
+    }
+
     pub async fn register(&self, create_user: CreateUser) -> Result<User, UserServiceError> {
         // Validate email format
         if !EMAIL_REGEX.is_match(&create_user.email) {
@@ -122,6 +399,86 @@ impl UserService {
         Ok(user)
     }
 
+    /// Create a user on behalf of an identity provider or provisioning client
+    ///
+    /// Without a password the account can only be signed in to through single sign-on.
+    pub async fn provision_user(
+        &self,
+        email: &str,
+        display_name: Option<String>,
+        password: Option<&str>,
+    ) -> Result<User, UserServiceError> {
+        if !EMAIL_REGEX.is_match(email) {
+            return Err(UserError::InvalidEmail.into());
+        }
+        if self.repository.find_by_email(email).await?.is_some() {
+            return Err(UserError::AlreadyExists.into());
+        }
+
+        let password_hash = match password {
+            Some(password) => {
+                check_password_strength(password, &[email])?;
+                hash_password(password)?
+            },
+            None => NO_PASSWORD_HASH.to_string(),
+        };
+
+        let mut user = User::new(email.to_string(), password_hash);
+        if let Some(display_name) = display_name {
+            user.display_name = display_name;
+        }
+        self.repository.create(&user).await?;
+
+        Ok(user)
+    }
+
+    /// Change the email address and display name of a user
+    pub async fn update_profile(
+        &self,
+        id: Uuid,
+        email: &str,
+        display_name: &str,
+    ) -> Result<User, UserServiceError> {
+        let mut user = self.get_user(id).await?;
+        if user.email != email {
+            if !EMAIL_REGEX.is_match(email) {
+                return Err(UserError::InvalidEmail.into());
+            }
+            if self.repository.find_by_email(email).await?.is_some() {
+                return Err(UserError::AlreadyExists.into());
+            }
+            user.email = email.to_string();
+            user.is_verified = false;
+        }
+        user.display_name = display_name.to_string();
+        user.updated_at = OffsetDateTime::now_utc();
+        self.repository.update(&user).await?;
+
+        Ok(user)
+    }
+
+    /// Check an email and password pair
+    ///
+    /// Unknown emails and wrong passwords both fail with
+    /// [`UserServiceError::InvalidCredentials`]. For unknown emails a password
+    /// is still verified against a decoy hash, so the response time does not
+    /// reveal whether an account exists.
+    async fn authenticate(&self, email: &str, password: &str) -> Result<User, UserServiceError> {
+        let Some(user) = self.repository.find_by_email(email).await? else {
+            self.password_verifier.verify_decoy(password);
+            return Err(UserServiceError::InvalidCredentials);
+        };
+
+        if !self
+            .password_verifier
+            .verify(password, &user.password_hash)?
+        {
+            return Err(UserServiceError::InvalidCredentials);
+        }
+
+        Ok(user)
+    }
+
     pub async fn login(
         &self,
         email: &str,
@@ -130,32 +487,250 @@ impl UserService {
         device_fingerprint: Option<DeviceFingerprint>,
         ip_address: Option<String>,
         user_agent: Option<String>,
+        persistent: bool,
     ) -> Result<LoginResult, UserServiceError> {
-        // Get user by email
-        let user = self
-            .repository
-            .find_by_email(email)
-            .await?
-            .ok_or(UserServiceError::InvalidCredentials)?;
+        self.login_with_tenant(
+            None,
+            email,
+            password,
+            device_id,
+            device_fingerprint,
+            ip_address,
+            user_agent,
+            persistent,
+        )
+        .await
+    }
 
-        // Verify password
-        if !verify_password(password, &user.password_hash)? {
-            return Err(UserServiceError::InvalidCredentials);
+    /// Log in a user within the context of a tenant, applying its MFA enforcement policy
+    ///
+    /// If the tenant requires MFA and the user has not enrolled a factor yet, the session is
+    /// created with `MfaStatus::Pending`. Once the grace deadline has passed, such logins are
+    /// rejected with `UserServiceError::MfaEnrollmentExpired`. Users with an enrolled factor
+    /// get a session with `MfaStatus::Required` until they complete the second factor.
+    ///
+    /// `persistent` selects the longer "remember me" session lifetime.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn login_with_tenant(
+        &self,
+        tenant_id: Option<TenantId>,
+        email: &str,
+        password: &str,
+        device_id: Option<String>,
+        device_fingerprint: Option<DeviceFingerprint>,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+        persistent: bool,
+    ) -> Result<LoginResult, UserServiceError> {
+        self.login_with_trusted_device(
+            tenant_id,
+            email,
+            password,
+            None,
+            device_id,
+            device_fingerprint,
+            ip_address,
+            user_agent,
+            persistent,
+        )
+        .await
+    }
+
+    /// Log in a user, skipping the MFA challenge on a trusted device
+    ///
+    /// Like [`Self::login_with_tenant`], but a valid `trusted_device_token` issued by
+    /// [`Self::trust_device`] for the same user, tenant and device fingerprint yields a
+    /// session with `MfaStatus::Verified`. Any other token is ignored and MFA is challenged.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn login_with_trusted_device(
+        &self,
+        tenant_id: Option<TenantId>,
+        email: &str,
+        password: &str,
+        trusted_device_token: Option<&str>,
+        device_id: Option<String>,
+        device_fingerprint: Option<DeviceFingerprint>,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+        persistent: bool,
+    ) -> Result<LoginResult, UserServiceError> {
+        let attempt = self.login_attempt_recorder.as_ref().map(|recorder| {
+            let context = LoginAttemptContext {
+                tenant_id,
+                username: email.to_string(),
+                ip_address: ip_address.clone(),
+                user_agent: user_agent.clone(),
+                device_fingerprint: device_fingerprint.clone(),
+                ..LoginAttemptContext::default()
+            };
+            (recorder, context)
+        });
+
+        let result = async {
+            let user = self.authenticate(email, password).await?;
+            self.start_login(
+                user,
+                tenant_id,
+                "password",
+                device_id,
+                device_fingerprint,
+                ip_address,
+                user_agent,
+                persistent,
+                trusted_device_token,
+            )
+            .await
+        }
+        .await;
+
+        if let Some((recorder, mut context)) = attempt {
+            if let Ok(login) = &result {
+                context.tenant_id = login.active_tenant_id;
+            }
+            recorder.record(context, login_outcome(&result));
+        }
+        result
+    }
+
+    /// Log in a user whose identity was established by an external identity provider
+    ///
+    /// Applies the same tenant membership and MFA enforcement rules as a password login;
+    /// `login_type` is recorded in the session metadata.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn login_with_identity(
+        &self,
+        user: User,
+        tenant_id: TenantId,
+        login_type: &str,
+        device_id: Option<String>,
+        device_fingerprint: Option<DeviceFingerprint>,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+        persistent: bool,
+    ) -> Result<LoginResult, UserServiceError> {
+        if !user.is_active {
+            return Err(UserError::InactiveUser.into());
         }
 
-        // Check if MFA is required
-        // For now, we'll assume MFA is always disabled until we can properly add a field to User
-        let mfa_enabled = false;
-        if mfa_enabled {
-            // Create session with MFA pending status
+        self.start_login(
+            user,
+            Some(tenant_id),
+            login_type,
+            device_id,
+            device_fingerprint,
+            ip_address,
+            user_agent,
+            persistent,
+            None,
+        )
+        .await
+    }
+
+    /// Bind a session for an authenticated user to a tenant and create it
+    #[allow(clippy::too_many_arguments)]
+    async fn start_login(
+        &self,
+        mut user: User,
+        tenant_id: Option<TenantId>,
+        login_type: &str,
+        device_id: Option<String>,
+        device_fingerprint: Option<DeviceFingerprint>,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+        persistent: bool,
+        trusted_device_token: Option<&str>,
+    ) -> Result<LoginResult, UserServiceError> {
+        let email = user.email.clone();
+
+        // Bind the session to the requested tenant, or to the only membership
+        let memberships = self.active_memberships(user.id).await?;
+        let pending_deletion = self.pending_deletion(user.id).await;
+        let tenant_id = match tenant_id {
+            Some(tenant_id) => {
+                if self.tenant_repository.is_some()
+                    && !memberships.iter().any(|m| m.tenant_id == tenant_id)
+                {
+                    return Err(UserServiceError::NotTenantMember);
+                }
+                Some(tenant_id)
+            },
+            None if memberships.len() == 1 => Some(memberships[0].tenant_id),
+            None => None,
+        };
+
+        // Only the password is subject to forced resets and rotation, not external identities
+        let password_state = if login_type == "password" {
+            let state = self
+                .repository
+                .session_states(&[user.id])
+                .await?
+                .remove(&user.id);
+            if state.is_some_and(|state| state.password_reset_required) {
+                #[allow(clippy::disallowed_methods)]
+                let details = json!({ "tenant_id": tenant_id });
+                return Err(self
+                    .reject_password_login(
+                        &user,
+                        tenant_id,
+                        "PASSWORD_RESET_REQUIRED",
+                        details,
+                        ip_address.as_deref(),
+                        user_agent.as_deref(),
+                    )
+                    .await);
+            }
+            state
+        } else {
+            None
+        };
+
+        // Apply the tenant status, login location restrictions, password expiry and MFA
+        // enforcement policy
+        let (enrollment_pending, password_expires_in_days) = match tenant_id {
+            Some(tenant_id) => {
+                self.check_tenant_status(&user, tenant_id, &memberships)
+                    .await?;
+                let policy = self.get_tenant_security_policy(tenant_id).await?;
+                self.check_login_location(
+                    &user,
+                    tenant_id,
+                    &policy,
+                    ip_address.as_deref(),
+                    user_agent.as_deref(),
+                )
+                .await?;
+                let password_expires_in_days = match &password_state {
+                    Some(state) => {
+                        self.check_password_expiry(
+                            &user,
+                            tenant_id,
+                            &policy,
+                            state,
+                            ip_address.as_deref(),
+                            user_agent.as_deref(),
+                        )
+                        .await?
+                    },
+                    None => None,
+                };
+                let enrollment_pending = self
+                    .mfa_enrollment_pending(&user, tenant_id, &policy)
+                    .await?;
+                (enrollment_pending, password_expires_in_days)
+            },
+            None => (false, None),
+        };
+        if enrollment_pending {
             #[allow(clippy::disallowed_methods)]
             let metadata = json!({
-                "login_type": "password",
+                "login_type": login_type,
                 "email": email,
-                "mfa_status": "pending",
+                "tenant_id": tenant_id,
+                ACTIVE_TENANT_METADATA_KEY: tenant_id,
+                "mfa_status": "enrollment_pending",
             });
 
-            let (_session, _session_token) = self
+            let (session, session_token) = self
                 .session_service
                 .create_session_with_status(
                     user.id,
@@ -164,40 +739,754 @@ impl UserService {
                     ip_address,
                     user_agent,
                     Some(metadata),
-                    MfaStatus::Required,
+                    persistent,
+                    MfaStatus::Pending,
                 )
                 .await?;
+            self.record_fingerprint(&session, tenant_id).await;
+            self.record_login(&mut user).await;
 
-            // Return early with MFA required error
-            return Err(UserServiceError::MfaRequired);
+            return Ok(LoginResult {
+                user,
+                session_token,
+                mfa_status: MfaStatus::Pending,
+                active_tenant_id: tenant_id,
+                memberships,
+                pending_deletion,
+                password_expires_in_days,
+            });
         }
 
+        // Challenge the second factor of enrolled users, unless the device is trusted
+        let mfa_status = match tenant_id {
+            Some(tenant_id) => {
+                let enrolled = self
+                    .get_mfa_enrollment(user.id, tenant_id)
+                    .await?
+                    .is_enrolled();
+                let trusted = match trusted_device_token {
+                    Some(token) if enrolled => {
+                        let device = device_fingerprint.as_ref();
+                        self.is_trusted_device(token, tenant_id, user.id, device)
+                            .await
+                    },
+                    _ => false,
+                };
+                match (enrolled, trusted) {
+                    (false, _) => MfaStatus::None,
+                    (true, true) => MfaStatus::Verified,
+                    (true, false) => MfaStatus::Required,
+                }
+            },
+            None => MfaStatus::None,
+        };
+        let mfa_metadata = match mfa_status {
+            MfaStatus::Verified => "trusted_device",
+            MfaStatus::Required => "required",
+            _ => "none",
+        };
+
         // Create session with device information
         #[allow(clippy::disallowed_methods)]
         let metadata = json!({
-            "login_type": "password",
+            "login_type": login_type,
             "email": email,
-            "mfa_status": "none",
+            "tenant_id": tenant_id,
+            ACTIVE_TENANT_METADATA_KEY: tenant_id,
+            "mfa_status": mfa_metadata,
         });
 
-        let (_, session_token) = self
+        let (session, session_token) = self
             .session_service
-            .create_session(
+            .create_session_with_status(
                 user.id,
                 device_id,
                 device_fingerprint,
                 ip_address,
                 user_agent,
                 Some(metadata),
+                persistent,
+                mfa_status.clone(),
             )
             .await?;
+        self.record_fingerprint(&session, tenant_id).await;
+        self.record_login(&mut user).await;
 
         Ok(LoginResult {
             user,
             session_token,
+            mfa_status,
+            active_tenant_id: tenant_id,
+            memberships,
+            pending_deletion,
+            password_expires_in_days,
         })
     }
 
+    /// Active tenant memberships of a user; empty without a tenant repository
+    async fn active_memberships(&self, user_id: Uuid) -> Result<Vec<TenantUser>, UserServiceError> {
+        let Some(tenant_repository) = &self.tenant_repository else {
+            return Ok(Vec::new());
+        };
+
+        Ok(tenant_repository
+            .get_user_tenants(user_id)
+            .await?
+            .into_iter()
+            .filter(|membership| membership.is_active)
+            .collect())
+    }
+
+    /// Feed the device fingerprint of a new session to the fingerprint service
+    ///
+    /// Fingerprints only inform risk assessment, so a failure does not fail the login.
+    async fn record_fingerprint(&self, session: &Session, tenant_id: Option<TenantId>) {
+        let (Some(fingerprint_service), Some(device)) =
+            (&self.fingerprint_service, &session.device_fingerprint)
+        else {
+            return;
+        };
+
+        if let Err(err) = fingerprint_service
+            .store_device_fingerprint(
+                tenant_id.unwrap_or(*DEFAULT_TENANT_ID),
+                session.user_id,
+                device,
+                session.user_agent.as_deref(),
+                session.ip_address.as_deref().unwrap_or_default(),
+                session.id,
+            )
+            .await
+        {
+            tracing::warn!(
+                user_id = %session.user_id,
+                session_id = %session.id,
+                error = %err,
+                "Failed to store device fingerprint"
+            );
+        }
+    }
+
+    /// Record the login time of a user whose session was just created
+    ///
+    /// The session already exists, so a failure does not fail the login.
+    async fn record_login(&self, user: &mut User) {
+        let now = OffsetDateTime::now_utc();
+        match self.repository.record_login(user.id, now).await {
+            Ok(()) => user.last_login = Some(now),
+            Err(err) => {
+                tracing::warn!(
+                    user_id = %user.id,
+                    error = %err,
+                    "Failed to record login time"
+                );
+            },
+        }
+    }
+
+    /// Whether a trusted device token lets a login skip the MFA challenge
+    ///
+    /// A failed lookup only means the user is challenged, so it does not fail the login.
+    async fn is_trusted_device(
+        &self,
+        token: &str,
+        tenant_id: TenantId,
+        user_id: Uuid,
+        device: Option<&DeviceFingerprint>,
+    ) -> bool {
+        let Some(trusted_device_service) = &self.trusted_device_service else {
+            return false;
+        };
+
+        match trusted_device_service
+            .is_trusted(token, tenant_id, user_id, device)
+            .await
+        {
+            Ok(trusted) => trusted,
+            Err(err) => {
+                tracing::warn!(
+                    user_id = %user_id,
+                    error = %err,
+                    "Failed to check trusted device, requiring MFA"
+                );
+                false
+            },
+        }
+    }
+
+    /// Scheduled deletion time of a user's account
+    ///
+    /// The lookup only feeds a warning, so a failure does not fail the login.
+    async fn pending_deletion(&self, user_id: Uuid) -> Option<OffsetDateTime> {
+        let repository = self.account_deletion_repository.as_ref()?;
+        match repository.find_pending(user_id).await {
+            Ok(request) => request.map(|request| request.scheduled_for),
+            Err(err) => {
+                tracing::warn!(
+                    user_id = %user_id,
+                    error = %err,
+                    "Failed to look up pending account deletion"
+                );
+                None
+            },
+        }
+    }
+
+    /// Get the MFA enrollment status of a user within a tenant
+    ///
+    /// Combines the presence of a confirmed TOTP secret with the number of registered
+    /// WebAuthn credentials. Sources without a configured repository count as not enrolled.
+    pub async fn get_mfa_enrollment(
+        &self,
+        user_id: Uuid,
+        tenant_id: TenantId,
+    ) -> Result<MfaEnrollment, UserServiceError> {
+        let totp_enrolled = match &self.totp_repository {
+            Some(repository) => repository
+                .get_by_user_id(&user_id, &tenant_id)
+                .await?
+                .is_some_and(|secret| secret.is_setup_complete()),
+            None => false,
+        };
+
+        #[cfg(feature = "enable_webauthn")]
+        let webauthn_credentials = match &self.webauthn_repository {
+            Some(repository) => repository
+                .list_credentials_for_user(&user_id)
+                .await?
+                .iter()
+                .filter(|credential| credential.tenant_id == tenant_id)
+                .count(),
+            None => 0,
+        };
+        #[cfg(not(feature = "enable_webauthn"))]
+        let webauthn_credentials = 0;
+
+        Ok(MfaEnrollment {
+            user_id,
+            tenant_id,
+            totp_enrolled,
+            webauthn_credentials,
+        })
+    }
+
+    /// Get the security policy of a tenant
+    ///
+    /// Returns the default policy if no tenant repository is configured.
+    pub async fn get_tenant_security_policy(
+        &self,
+        tenant_id: TenantId,
+    ) -> Result<TenantSecurityPolicy, UserServiceError> {
+        let Some(tenant_repository) = &self.tenant_repository else {
+            return Ok(TenantSecurityPolicy::default());
+        };
+
+        let tenant = tenant_repository
+            .find_tenant_by_id(tenant_id)
+            .await?
+            .ok_or(TenantError::NotFound)?;
+
+        Ok(tenant.security_policy())
+    }
+
+    /// Reject a login to an archived tenant, or to a suspended one by a member
+    /// other than its admin and billing users
+    async fn check_tenant_status(
+        &self,
+        user: &User,
+        tenant_id: TenantId,
+        memberships: &[TenantUser],
+    ) -> Result<(), UserServiceError> {
+        let Some(tenant_repository) = &self.tenant_repository else {
+            return Ok(());
+        };
+
+        let status = tenant_repository
+            .find_tenant_by_id(tenant_id)
+            .await?
+            .ok_or(TenantError::NotFound)?
+            .status;
+        let tenant_role = memberships
+            .iter()
+            .find(|membership| membership.tenant_id == tenant_id)
+            .map(|membership| membership.tenant_role.as_str())
+            .unwrap_or_default();
+        if status.allows_role(tenant_role) {
+            return Ok(());
+        }
+
+        tracing::warn!(
+            user_id = %user.id,
+            tenant_id = %tenant_id,
+            status = ?status,
+            "Rejecting login: tenant is not active"
+        );
+        Err(status
+            .access_error()
+            .unwrap_or(TenantError::InactiveTenant)
+            .into())
+    }
+
+    /// Check whether the tenant policy requires MFA for a user within a tenant
+    pub async fn is_mfa_required(
+        &self,
+        user_id: Uuid,
+        tenant_id: TenantId,
+        policy: &TenantSecurityPolicy,
+    ) -> Result<bool, UserServiceError> {
+        if !policy.mfa_enforcement.is_enforced() {
+            return Ok(false);
+        }
+        let Some(tenant_repository) = &self.tenant_repository else {
+            return Ok(false);
+        };
+
+        let tenant_role = tenant_repository
+            .get_user_tenants(user_id)
+            .await?
+            .into_iter()
+            .find(|membership| membership.tenant_id == tenant_id && membership.is_active)
+            .map(|membership| membership.tenant_role)
+            .unwrap_or_default();
+
+        Ok(policy.requires_mfa_for_role(&tenant_role))
+    }
+
+    /// Reject a login from a network or country the tenant does not allow
+    ///
+    /// Blocked logins are recorded in the audit log of the user.
+    async fn check_login_location(
+        &self,
+        user: &User,
+        tenant_id: TenantId,
+        policy: &TenantSecurityPolicy,
+        ip_address: Option<&str>,
+        user_agent: Option<&str>,
+    ) -> Result<(), UserServiceError> {
+        if !policy.restricts_login_location() {
+            return Ok(());
+        }
+        let Err(violation) = self
+            .session_service
+            .check_login_location(policy, ip_address)
+        else {
+            return Ok(());
+        };
+
+        tracing::warn!(
+            user_id = %user.id,
+            tenant_id = %tenant_id,
+            violation = %violation,
+            "Rejecting login: location not allowed by tenant policy"
+        );
+        #[allow(clippy::disallowed_methods)]
+        let details = json!({
+            "tenant_id": tenant_id,
+            "reason": violation.as_str(),
+        });
+        if let Err(e) = self
+            .repository
+            .log_security_event(
+                user.id,
+                "LOGIN_LOCATION_BLOCKED",
+                details,
+                ip_address.map(str::to_string),
+                user_agent.map(str::to_string),
+            )
+            .await
+        {
+            tracing::error!(user_id = %user.id, "Failed to record blocked login: {}", e);
+        }
+        Err(UserServiceError::LoginLocationBlocked(violation))
+    }
+
+    /// Apply the password expiry of a tenant policy to a password login
+    ///
+    /// Returns the days left if the password expires within the warning window.
+    /// Past the expiry the login fails with [`UserServiceError::PasswordExpired`]
+    /// and is recorded in the audit log of the user.
+    async fn check_password_expiry(
+        &self,
+        user: &User,
+        tenant_id: TenantId,
+        policy: &TenantSecurityPolicy,
+        state: &UserSessionState,
+        ip_address: Option<&str>,
+        user_agent: Option<&str>,
+    ) -> Result<Option<u32>, UserServiceError> {
+        if policy.password_max_age_days.is_none() {
+            return Ok(None);
+        }
+
+        // Repositories that do not track password changes count from the registration
+        let changed_at = state.password_changed_at.unwrap_or(user.created_at);
+        match policy.password_expiry(changed_at, OffsetDateTime::now_utc()) {
+            PasswordExpiry::Valid => return Ok(None),
+            PasswordExpiry::ExpiresSoon { days_left } => return Ok(Some(days_left)),
+            PasswordExpiry::Expired => {},
+        }
+
+        #[allow(clippy::disallowed_methods)]
+        let details = json!({
+            "tenant_id": tenant_id,
+            "password_changed_at": changed_at.unix_timestamp(),
+        });
+        Err(self
+            .reject_password_login(
+                user,
+                Some(tenant_id),
+                "PASSWORD_EXPIRED",
+                details,
+                ip_address,
+                user_agent,
+            )
+            .await)
+    }
+
+    /// Reject a password login until the user sets a new password
+    ///
+    /// Records `action` in the audit log of the user and hands out a reset
+    /// token if action tokens are configured.
+    async fn reject_password_login(
+        &self,
+        user: &User,
+        tenant_id: Option<TenantId>,
+        action: &str,
+        details: serde_json::Value,
+        ip_address: Option<&str>,
+        user_agent: Option<&str>,
+    ) -> UserServiceError {
+        tracing::info!(
+            user_id = %user.id,
+            tenant_id = ?tenant_id,
+            action,
+            "Rejecting login: a new password is required"
+        );
+        if let Err(e) = self
+            .repository
+            .log_security_event(
+                user.id,
+                action,
+                details,
+                ip_address.map(str::to_string),
+                user_agent.map(str::to_string),
+            )
+            .await
+        {
+            tracing::error!(user_id = %user.id, "Failed to record rejected login: {}", e);
+        }
+
+        let tenant = tenant_id.map(|tenant_id| tenant_id.to_string());
+        let reset_token = self.action_tokens.as_ref().map(|tokens| {
+            tokens.issue(
+                PASSWORD_RESET_ACTION,
+                &user.id.to_string(),
+                tenant.as_deref(),
+                self.config.password.reset_token_ttl_secs.as_duration(),
+            )
+        });
+        UserServiceError::PasswordExpired { reset_token }
+    }
+
+    /// Determine whether a login has to be restricted to MFA enrollment
+    ///
+    /// Fails with `MfaEnrollmentExpired` if enrollment is required and the grace deadline
+    /// has passed.
+    async fn mfa_enrollment_pending(
+        &self,
+        user: &User,
+        tenant_id: TenantId,
+        policy: &TenantSecurityPolicy,
+    ) -> Result<bool, UserServiceError> {
+        if !self.is_mfa_required(user.id, tenant_id, policy).await? {
+            return Ok(false);
+        }
+
+        if self
+            .get_mfa_enrollment(user.id, tenant_id)
+            .await?
+            .is_enrolled()
+        {
+            return Ok(false);
+        }
+
+        if policy.grace_period_expired(OffsetDateTime::now_utc()) {
+            tracing::warn!(
+                user_id = %user.id,
+                tenant_id = %tenant_id,
+                "Rejecting login: MFA enrollment grace period has expired"
+            );
+            return Err(UserServiceError::MfaEnrollmentExpired);
+        }
+
+        tracing::info!(
+            user_id = %user.id,
+            tenant_id = %tenant_id,
+            "MFA enrollment required by tenant policy"
+        );
+        Ok(true)
+    }
+
+    /// Release a session from pending MFA enrollment once the user has enrolled a factor
+    pub async fn complete_mfa_enrollment(
+        &self,
+        user_id: Uuid,
+        tenant_id: TenantId,
+        session_token: &str,
+    ) -> Result<(), UserServiceError> {
+        if !self
+            .get_mfa_enrollment(user_id, tenant_id)
+            .await?
+            .is_enrolled()
+        {
+            return Err(UserServiceError::MfaEnrollmentRequired);
+        }
+
+        self.session_service
+            .complete_mfa_enrollment(session_token)
+            .await?;
+        Ok(())
+    }
+
+    /// Trust the device of a session that completed MFA ("remember this device")
+    ///
+    /// The returned token lets later logins from the same device fingerprint skip the
+    /// MFA challenge until it expires or the trust is revoked.
+    pub async fn trust_device(
+        &self,
+        user_id: Uuid,
+        session_id: Uuid,
+        tenant_id: TenantId,
+    ) -> Result<TrustedDevice, UserServiceError> {
+        let trusted_device_service = self
+            .trusted_device_service
+            .as_ref()
+            .ok_or(UserServiceError::TrustedDevicesNotConfigured)?;
+        let session = self
+            .session_service
+            .get_user_sessions(user_id, SessionFilter::Active)
+            .await?
+            .into_iter()
+            .find(|session| session.id == session_id)
+            .ok_or(SessionServiceError::Repository(SessionError::NotFound))?;
+        if session.mfa_status != MfaStatus::Verified {
+            return Err(UserServiceError::MfaRequired);
+        }
+
+        Ok(trusted_device_service
+            .trust_device(
+                tenant_id,
+                user_id,
+                session_id,
+                session.device_fingerprint.as_ref(),
+            )
+            .await?)
+    }
+
+    /// Revoke the trust in a device, so logins from it are challenged for MFA again
+    pub async fn revoke_trusted_device(
+        &self,
+        user_id: Uuid,
+        tenant_id: TenantId,
+        fingerprint_id: Uuid,
+    ) -> Result<(), UserServiceError> {
+        let trusted_device_service = self
+            .trusted_device_service
+            .as_ref()
+            .ok_or(UserServiceError::TrustedDevicesNotConfigured)?;
+
+        Ok(trusted_device_service
+            .revoke(tenant_id, user_id, fingerprint_id)
+            .await?)
+    }
+
+    /// List the authentication factors a user has enrolled within a tenant
+    ///
+    /// Sources without a configured repository contribute no factors.
+    pub async fn list_factors(
+        &self,
+        user_id: Uuid,
+        tenant_id: TenantId,
+    ) -> Result<Vec<EnrolledFactor>, UserServiceError> {
+        let user = self
+            .repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or(UserServiceError::UserNotFound)?;
+
+        let mut factors = Vec::new();
+        if user.has_password() {
+            factors.push(EnrolledFactor {
+                factor: AuthFactor::Password,
+                name: None,
+                created_at: Some(user.created_at),
+                last_used_at: user.last_login,
+            });
+        }
+
+        if let Some(secret) = self.enabled_totp_secret(user_id, tenant_id).await? {
+            factors.push(EnrolledFactor {
+                factor: AuthFactor::Totp,
+                name: None,
+                created_at: Some(secret.created_at),
+                last_used_at: secret.last_used_at,
+            });
+            if !secret.recovery_codes.is_empty() {
+                factors.push(EnrolledFactor {
+                    factor: AuthFactor::RecoveryCodes,
+                    name: None,
+                    created_at: Some(secret.created_at),
+                    last_used_at: None,
+                });
+            }
+        }
+
+        #[cfg(feature = "enable_webauthn")]
+        if let Some(repository) = &self.webauthn_repository {
+            for credential in repository
+                .list_credentials_for_user(&user_id)
+                .await?
+                .into_iter()
+                .filter(|credential| credential.tenant_id == tenant_id)
+            {
+                factors.push(EnrolledFactor {
+                    factor: AuthFactor::WebAuthn {
+                        credential_id: credential.uuid,
+                    },
+                    name: Some(credential.name),
+                    created_at: Some(credential.created_at),
+                    last_used_at: credential.last_used_at,
+                });
+            }
+        }
+
+        Ok(factors)
+    }
+
+    /// Remove an authentication factor of a user and audit it in the session
+    ///
+    /// The password can only be removed once an external identity is linked, and the
+    /// last second factor cannot be removed while the tenant policy requires MFA.
+    pub async fn remove_factor(
+        &self,
+        user_id: Uuid,
+        tenant_id: TenantId,
+        session_id: Uuid,
+        factor: &AuthFactor,
+    ) -> Result<(), UserServiceError> {
+        self.ensure_factor_removable(user_id, tenant_id, factor)
+            .await?;
+
+        match factor {
+            AuthFactor::Password => {
+                let has_identity = match &self.identity_repository {
+                    Some(repository) => !repository.list_for_user(&user_id).await?.is_empty(),
+                    None => false,
+                };
+                if !has_identity {
+                    return Err(UserServiceError::LastSignInMethod);
+                }
+
+                let mut user = self.get_user(user_id).await?;
+                user.password_hash = NO_PASSWORD_HASH.to_string();
+                user.updated_at = OffsetDateTime::now_utc();
+                self.repository.update(&user).await?;
+            },
+            AuthFactor::Totp => {
+                if let Some(repository) = &self.totp_repository {
+                    repository.delete(&user_id, &tenant_id).await?;
+                }
+            },
+            AuthFactor::RecoveryCodes => {
+                if let (Some(repository), Some(mut secret)) = (
+                    &self.totp_repository,
+                    self.enabled_totp_secret(user_id, tenant_id).await?,
+                ) {
+                    secret.recovery_codes.clear();
+                    repository.save(&secret).await?;
+                }
+            },
+            AuthFactor::WebAuthn { credential_id } => {
+                #[cfg(feature = "enable_webauthn")]
+                if let Some(repository) = &self.webauthn_repository {
+                    repository.delete_credential(credential_id).await?;
+                }
+                #[cfg(not(feature = "enable_webauthn"))]
+                let _ = credential_id;
+            },
+        }
+
+        self.session_service
+            .record_factor_event(session_id, FactorAction::Removed, factor)
+            .await?;
+        tracing::info!(
+            user_id = %user_id,
+            tenant_id = %tenant_id,
+            factor = %factor,
+            "Authentication factor removed"
+        );
+        Ok(())
+    }
+
+    /// Check that a user may remove an enrolled authentication factor
+    ///
+    /// Fails with `FactorNotFound` if the factor is not enrolled and with
+    /// `LastSecondFactor` if it is the only second factor while the tenant
+    /// policy requires MFA for the user.
+    pub async fn ensure_factor_removable(
+        &self,
+        user_id: Uuid,
+        tenant_id: TenantId,
+        factor: &AuthFactor,
+    ) -> Result<(), UserServiceError> {
+        let enrolled = self.list_factors(user_id, tenant_id).await?;
+        if !enrolled.iter().any(|enrolled| &enrolled.factor == factor) {
+            return Err(UserServiceError::FactorNotFound);
+        }
+
+        if factor.is_second_factor() {
+            let second_factors = enrolled
+                .iter()
+                .filter(|enrolled| enrolled.factor.is_second_factor())
+                .count();
+            let policy = self.get_tenant_security_policy(tenant_id).await?;
+            if second_factors <= 1 && self.is_mfa_required(user_id, tenant_id, &policy).await? {
+                return Err(UserServiceError::LastSecondFactor);
+            }
+        }
+        Ok(())
+    }
+
+    /// Audit a factor enrolled in a session
+    ///
+    /// Enrollment flows call this once the factor is stored so the overview and the
+    /// audit log stay consistent.
+    pub async fn record_factor_added(
+        &self,
+        session_id: Uuid,
+        factor: &AuthFactor,
+    ) -> Result<(), UserServiceError> {
+        self.session_service
+            .record_factor_event(session_id, FactorAction::Added, factor)
+            .await?;
+        Ok(())
+    }
+
+    async fn enabled_totp_secret(
+        &self,
+        user_id: Uuid,
+        tenant_id: TenantId,
+    ) -> Result<Option<TotpSecret>, UserServiceError> {
+        let Some(repository) = &self.totp_repository else {
+            return Ok(None);
+        };
+
+        Ok(repository
+            .get_by_user_id(&user_id, &tenant_id)
+            .await?
+            .filter(TotpSecret::is_setup_complete))
+    }
+
     /// Send MFA verification code to user
     pub async fn send_mfa_verification(
         &self,
@@ -280,13 +1569,53 @@ impl UserService {
             .update_session_mfa_status(session_token, MfaStatus::Verified)
             .await?;
 
+        let active_tenant_id = self
+            .session_service
+            .validate_session(session_token)
+            .await?
+            .and_then(|session| session.active_tenant_id());
+        let memberships = self.active_memberships(user.id).await?;
+        let pending_deletion = self.pending_deletion(user.id).await;
+
         // Return login result
         Ok(LoginResult {
             user,
             session_token: session_token.to_string(),
+            mfa_status: MfaStatus::Verified,
+            active_tenant_id,
+            memberships,
+            pending_deletion,
+            // Reported by the login that created the session
+            password_expires_in_days: None,
         })
     }
 
+    /// Confirm the password of a signed-in user and refresh the session's authentication time
+    pub async fn reauthenticate(
+        &self,
+        user_id: Uuid,
+        session_id: Uuid,
+        password: &str,
+    ) -> Result<(), UserServiceError> {
+        let user = self
+            .repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or(UserServiceError::UserNotFound)?;
+
+        if !self
+            .password_verifier
+            .verify(password, &user.password_hash)?
+        {
+            return Err(UserServiceError::InvalidCredentials);
+        }
+
+        self.session_service
+            .record_reauthentication(session_id)
+            .await?;
+        Ok(())
+    }
+
     pub async fn logout(&self, session_token: &str) -> Result<(), UserServiceError> {
         self.session_service
             .invalidate_session(session_token, SessionInvalidationReason::UserLogout)
@@ -301,6 +1630,11 @@ impl UserService {
         let session = self.session_service.validate_session(session_token).await?;
 
         if let Some(session) = session {
+            // Sessions awaiting mandatory enrollment are restricted to the enrollment endpoints
+            if session.mfa_status == MfaStatus::Pending {
+                return Err(UserServiceError::MfaEnrollmentRequired);
+            }
+
             let user = self.repository.find_by_id(session.user_id).await?;
 
             Ok(user)
@@ -320,30 +1654,15 @@ impl UserService {
         Ok(sessions)
     }
 
+    /// End every active session of a user
     pub async fn invalidate_all_sessions(
         &self,
         user_id: Uuid,
         reason: SessionInvalidationReason,
     ) -> Result<(), UserServiceError> {
-        let sessions = self
-            .session_service
-            .get_user_sessions(user_id, SessionFilter::Active)
+        self.session_service
+            .force_terminate_user_sessions(user_id, reason)
             .await?;
-
-        for session in sessions {
-            if let Err(err) = self
-                .session_service
-                .invalidate_session(session.token_hash.as_str(), reason.clone())
-                .await
-            {
-                tracing::error!(
-                    session_id = %session.id,
-                    error = %err,
-                    "Failed to invalidate session"
-                );
-            }
-        }
-
         Ok(())
     }
 
@@ -354,13 +1673,254 @@ impl UserService {
             .ok_or_else(|| UserError::NotFound.into())
     }
 
+    pub async fn find_user_by_email(&self, email: &str) -> Result<Option<User>, UserServiceError> {
+        Ok(self.repository.find_by_email(email).await?)
+    }
+
     pub async fn verify_email(&self, id: Uuid) -> Result<(), UserServiceError> {
         self.repository.verify_email(id).await?;
         Ok(())
     }
 
+    /// Replace the password of a user who knows the current one
+    ///
+    /// The new password has to be strong and must not be one of the last
+    /// passwords, see [`Self::password_history_size`]. All sessions of the
+    /// user end, including the one changing the password.
+    pub async fn change_password(
+        &self,
+        user_id: Uuid,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<User, UserServiceError> {
+        let user = self.get_user(user_id).await?;
+        if !user.has_password()
+            || !self
+                .password_verifier
+                .verify(current_password, &user.password_hash)?
+        {
+            return Err(UserServiceError::InvalidCredentials);
+        }
+
+        let history_size = self.password_history_size(user.id).await?;
+        let password_hash = self
+            .check_new_password(&user, new_password, history_size)
+            .await?;
+        self.store_password(user, password_hash, history_size, "PASSWORD_CHANGED")
+            .await
+    }
+
+    /// Set a new password with a token issued for an expired password
+    ///
+    /// Checks the new password like [`Self::change_password`] before the token
+    /// is consumed, so a rejected password does not use up the token.
+    pub async fn reset_password(
+        &self,
+        reset_token: &str,
+        new_password: &str,
+    ) -> Result<User, UserServiceError> {
+        let action_tokens = self
+            .action_tokens
+            .as_ref()
+            .ok_or(UserServiceError::PasswordResetNotConfigured)?;
+        let claims = action_tokens.verify(reset_token, PASSWORD_RESET_ACTION)?;
+        let user_id = Uuid::parse_str(&claims.subject).map_err(|_| ActionTokenError::Malformed)?;
+        let user = self.get_user(user_id).await?;
+        if !user.is_active {
+            return Err(UserError::InactiveUser.into());
+        }
+
+        let history_size = self.password_history_size(user.id).await?;
+        let password_hash = self
+            .check_new_password(&user, new_password, history_size)
+            .await?;
+        action_tokens
+            .consume_once(reset_token, PASSWORD_RESET_ACTION)
+            .await?;
+        self.store_password(user, password_hash, history_size, "PASSWORD_RESET")
+            .await
+    }
+
+    /// Number of previous passwords a new password of the user must differ from
+    ///
+    /// Each tenant the user is an active member of may set its own size in its
+    /// security policy, the others use [`PasswordConfig::history_size`](crate::PasswordConfig).
+    /// The largest size applies.
+    pub async fn password_history_size(&self, user_id: Uuid) -> Result<usize, UserServiceError> {
+        let default_size = self.config.password.history_size;
+        let Some(tenant_repository) = &self.tenant_repository else {
+            return Ok(default_size as usize);
+        };
+
+        let tenant_ids: Vec<Uuid> = tenant_repository
+            .get_user_tenants(user_id)
+            .await?
+            .into_iter()
+            .filter(|membership| membership.is_active)
+            .map(|membership| membership.tenant_id)
+            .collect();
+        if tenant_ids.is_empty() {
+            return Ok(default_size as usize);
+        }
+
+        let history_size = tenant_repository
+            .find_tenants_by_ids(&tenant_ids)
+            .await?
+            .iter()
+            .map(|tenant| {
+                tenant
+                    .security_policy()
+                    .password_history_size
+                    .unwrap_or(default_size)
+            })
+            .max()
+            .unwrap_or(default_size);
+        Ok(history_size as usize)
+    }
+
+    /// Hash of a new password for a user, rejecting weak ones and the last
+    /// `history_size` passwords
+    async fn check_new_password(
+        &self,
+        user: &User,
+        new_password: &str,
+        history_size: usize,
+    ) -> Result<String, UserServiceError> {
+        check_password_strength(new_password, &[&user.email])?;
+
+        // The current password counts as the most recent one. Every slot of the
+        // history is verified, unused ones against a decoy, so the time taken
+        // reveals neither which password matched nor how many are stored.
+        if history_size > 0 {
+            let mut recent = self
+                .repository
+                .password_history(user.id, history_size - 1)
+                .await?;
+            if user.has_password() {
+                recent.insert(0, user.password_hash.clone());
+            }
+            recent.truncate(history_size);
+
+            let mut reused = false;
+            for password_hash in &recent {
+                reused |= self.password_verifier.verify(new_password, password_hash)?;
+            }
+            for _ in recent.len()..history_size {
+                self.password_verifier.verify_decoy(new_password);
+            }
+            if reused {
+                return Err(UserServiceError::PasswordReused);
+            }
+        }
+
+        Ok(hash_password(new_password)?)
+    }
+
+    /// Store a checked password hash, keep the replaced one in the history and
+    /// end all sessions of the user
+    async fn store_password(
+        &self,
+        mut user: User,
+        password_hash: String,
+        history_size: usize,
+        action: &str,
+    ) -> Result<User, UserServiceError> {
+        let replaced = std::mem::replace(&mut user.password_hash, password_hash);
+        user.updated_at = OffsetDateTime::now_utc();
+        self.repository.update(&user).await?;
+
+        // Recorded even without a history to keep, so that a shrunk history is
+        // pruned to its new size
+        let keep = history_size.saturating_sub(1);
+        if replaced != NO_PASSWORD_HASH {
+            self.repository
+                .record_password_history(user.id, &replaced, keep)
+                .await?;
+        }
+
+        #[allow(clippy::disallowed_methods)]
+        let details = json!({});
+        if let Err(e) = self
+            .repository
+            .log_security_event(user.id, action, details, None, None)
+            .await
+        {
+            tracing::error!(user_id = %user.id, "Failed to record password change: {}", e);
+        }
+        self.session_service
+            .force_terminate_user_sessions(user.id, SessionInvalidationReason::PasswordChanged)
+            .await?;
+
+        Ok(user)
+    }
+
+    /// Revoke all access of a user, e.g. when their account is compromised
+    ///
+    /// Invalidates every session, revokes the API keys the user issued if API
+    /// keys are configured and, with `force_password_reset`, rejects password
+    /// logins until a new password is set. The reset is required before the
+    /// sessions end, so the user cannot sign in again in between. The
+    /// revocation is recorded in the audit log of the user as `ACCESS_REVOKED`.
+    pub async fn revoke_access(
+        &self,
+        user_id: Uuid,
+        revoked_by: Uuid,
+        force_password_reset: bool,
+    ) -> Result<AccessRevocation, UserServiceError> {
+        let user = self.get_user(user_id).await?;
+
+        let password_reset_required = if force_password_reset {
+            self.repository.require_password_reset(user.id).await?
+        } else {
+            false
+        };
+        let api_keys_revoked = match &self.api_key_service {
+            Some(api_key_service) => api_key_service.revoke_created_by(user.id).await?,
+            None => 0,
+        };
+        let sessions_revoked = self
+            .session_service
+            .force_terminate_user_sessions(user.id, SessionInvalidationReason::EmergencyTermination)
+            .await?;
+        let revocation = AccessRevocation {
+            sessions_revoked,
+            api_keys_revoked,
+            password_reset_required,
+        };
+
+        tracing::warn!(
+            user_id = %user.id,
+            revoked_by = %revoked_by,
+            sessions_revoked,
+            api_keys_revoked,
+            password_reset_required,
+            "Access of user revoked"
+        );
+        #[allow(clippy::disallowed_methods)]
+        let details = json!({
+            "revoked_by": revoked_by,
+            "force_password_reset": force_password_reset,
+            "sessions_revoked": sessions_revoked,
+            "api_keys_revoked": api_keys_revoked,
+            "password_reset_required": password_reset_required,
+        });
+        if let Err(e) = self
+            .repository
+            .log_security_event(user.id, "ACCESS_REVOKED", details, None, None)
+            .await
+        {
+            tracing::error!(user_id = %user.id, "Failed to record access revocation: {}", e);
+        }
+
+        Ok(revocation)
+    }
+
+    /// Deactivate a user and end all of their sessions
     pub async fn deactivate_user(&self, id: Uuid) -> Result<(), UserServiceError> {
         self.repository.deactivate(id).await?;
+        self.session_service
+            .force_terminate_user_sessions(id, SessionInvalidationReason::UserDeactivated)
+            .await?;
         Ok(())
     }
 
@@ -370,10 +1930,29 @@ impl UserService {
     }
 }
 
+/// Outcome of a password login, as stored for analytics
+fn login_outcome(result: &Result<LoginResult, UserServiceError>) -> LoginOutcome {
+    match result {
+        Ok(_) => LoginOutcome::Succeeded,
+        Err(UserServiceError::InvalidCredentials) => LoginOutcome::InvalidCredentials,
+        Err(
+            UserServiceError::User(UserError::InactiveUser)
+            | UserServiceError::NotTenantMember
+            | UserServiceError::MfaEnrollmentExpired
+            | UserServiceError::PasswordExpired { .. }
+            | UserServiceError::LoginLocationBlocked(_)
+            | UserServiceError::RateLimitExceeded,
+        ) => LoginOutcome::Rejected,
+        Err(_) => LoginOutcome::Failed,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::time::SystemTime;
+    use crate::models::user::mock::MockUserRepository;
+    use crate::session::mock::MockSessionRepository;
+    use crate::utils::password::mock::CountingPasswordVerifier;
 
     #[test]
     fn test_email_regex() {
@@ -394,8 +1973,117 @@ mod tests {
         assert_eq!(user.password_hash, "hashed_password");
         assert!(user.is_active);
         assert!(!user.is_verified);
-        assert!(user.created_at <= SystemTime::now());
-        assert!(user.updated_at <= SystemTime::now());
+        assert!(user.created_at <= OffsetDateTime::now_utc());
+        assert!(user.updated_at <= OffsetDateTime::now_utc());
         assert!(user.last_login.is_none());
     }
+
+    #[tokio::test]
+    async fn test_unknown_email_and_wrong_password_are_indistinguishable() {
+        let repository = Arc::new(MockUserRepository::new());
+        let config = Arc::new(AuthConfig::default());
+        let verifier = Arc::new(CountingPasswordVerifier::new());
+        let service = UserService::new(
+            repository.clone(),
+            Arc::new(JwtUtils::new(b"test-secret")),
+            Arc::new(SessionService::new(
+                Arc::new(MockSessionRepository::new()),
+                config.clone(),
+            )),
+            None,
+            config,
+        )
+        .with_password_verifier(verifier.clone());
+        let user = User::new(
+            "known@example.com".to_string(),
+            hash_password("Correct-Horse-Battery-42").expect("Failed to hash password"),
+        );
+        repository
+            .create(&user)
+            .await
+            .expect("Failed to create user");
+
+        let wrong_password = service
+            .login(
+                "known@example.com",
+                "wrong-password",
+                None,
+                None,
+                None,
+                None,
+                false,
+            )
+            .await;
+        assert!(matches!(
+            wrong_password,
+            Err(UserServiceError::InvalidCredentials)
+        ));
+        assert_eq!(verifier.verifications(), 1);
+
+        let unknown_email = service
+            .login(
+                "unknown@example.com",
+                "wrong-password",
+                None,
+                None,
+                None,
+                None,
+                false,
+            )
+            .await;
+        assert!(matches!(
+            unknown_email,
+            Err(UserServiceError::InvalidCredentials)
+        ));
+        // The missing account still costs a full password verification
+        assert_eq!(verifier.verifications(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_successful_login_records_last_login() {
+        let repository = Arc::new(MockUserRepository::new());
+        let config = Arc::new(AuthConfig::default());
+        let service = UserService::new(
+            repository.clone(),
+            Arc::new(JwtUtils::new(b"test-secret")),
+            Arc::new(SessionService::new(
+                Arc::new(MockSessionRepository::new()),
+                config.clone(),
+            )),
+            None,
+            config,
+        );
+        let user = User::new(
+            "known@example.com".to_string(),
+            hash_password("Correct-Horse-Battery-42").expect("Failed to hash password"),
+        );
+        repository
+            .create(&user)
+            .await
+            .expect("Failed to create user");
+
+        let before = OffsetDateTime::now_utc();
+        let result = service
+            .login(
+                "known@example.com",
+                "Correct-Horse-Battery-42",
+                None,
+                None,
+                None,
+                None,
+                false,
+            )
+            .await
+            .expect("Login succeeds");
+
+        let last_login = result.user.last_login.expect("Login time is returned");
+        assert!(last_login >= before);
+        let stored = repository
+            .find_by_id(user.id)
+            .await
+            .expect("Failed to find user")
+            .expect("User exists");
+        assert_eq!(stored.last_login, Some(last_login));
+        assert_eq!(stored.updated_at, user.updated_at);
+    }
 }
@@ -0,0 +1,100 @@
+//! Parsing of user agents into browser, operating system and device class
+//!
+//! Sessions keep the raw `User-Agent` header; the [`UserAgentParser`] turns it
+//! into [`UserAgentDetails`] for the session listing and for messages to the
+//! user. The header is client-controlled, so it is cut to
+//! [`MAX_USER_AGENT_LENGTH`] before parsing and every parsed value to
+//! [`MAX_DETAIL_LENGTH`]. Headers that name no known browser, system or
+//! crawler yield no details rather than an error.
+
+use woothee::parser::{Parser, WootheeResult};
+
+use crate::session::types::{DeviceType, UserAgentDetails};
+
+/// Longest prefix of a user agent that is parsed
+pub const MAX_USER_AGENT_LENGTH: usize = 512;
+
+/// Longest browser, version or operating system name kept from a user agent
+pub const MAX_DETAIL_LENGTH: usize = 64;
+
+/// Value woothee reports for anything it does not recognize
+const UNKNOWN: &str = "UNKNOWN";
+
+/// Parses user agents into [`UserAgentDetails`]
+///
+/// Create one per process and share it; the pattern set is compiled when the
+/// parser is created, not on the first request.
+pub struct UserAgentParser {
+    parser: Parser,
+}
+
+impl UserAgentParser {
+    pub fn new() -> Self {
+        let parser = Parser::new();
+        // The patterns are compiled on first use
+        let _ = parser.parse("Mozilla/5.0");
+        Self { parser }
+    }
+
+    /// Details of `user_agent`, `None` if it names nothing known
+    pub fn parse(&self, user_agent: &str) -> Option<UserAgentDetails> {
+        let user_agent = truncate(user_agent.trim(), MAX_USER_AGENT_LENGTH);
+        if user_agent.is_empty() {
+            return None;
+        }
+
+        let result = self.parser.parse(user_agent)?;
+        let details = UserAgentDetails {
+            browser: known(result.name),
+            browser_version: known(result.version),
+            os: known(result.os),
+            device_type: device_type(&result, user_agent),
+        };
+        if details.browser.is_none() && details.os.is_none() {
+            return None;
+        }
+        Some(details)
+    }
+}
+
+impl Default for UserAgentParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `value` cut to [`MAX_DETAIL_LENGTH`], `None` if it is unknown
+fn known(value: &str) -> Option<String> {
+    let value = truncate(value.trim(), MAX_DETAIL_LENGTH);
+    if value.is_empty() || value == UNKNOWN {
+        return None;
+    }
+    Some(value.to_string())
+}
+
+/// Class of the device, telling tablets apart from the phones woothee groups them with
+fn device_type(result: &WootheeResult<'_>, user_agent: &str) -> DeviceType {
+    match result.category {
+        "pc" => DeviceType::Desktop,
+        "smartphone" if result.os == "iPad" => DeviceType::Tablet,
+        // Android tablets leave the "Mobile" token out
+        "smartphone" if result.os == "Android" && !user_agent.contains("Mobile") => {
+            DeviceType::Tablet
+        },
+        "smartphone" | "mobilephone" => DeviceType::Mobile,
+        "crawler" => DeviceType::Bot,
+        _ => DeviceType::Other,
+    }
+}
+
+/// Longest prefix of `value` of at most `max` bytes that ends on a character boundary
+fn truncate(value: &str, max: usize) -> &str {
+    if value.len() <= max {
+        return value;
+    }
+    let mut end = max;
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    &value[..end]
+}
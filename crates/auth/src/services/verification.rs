@@ -1,22 +1,31 @@
+use async_trait::async_trait;
 use governor::{
     Quota, RateLimiter,
     clock::DefaultClock,
     middleware::NoOpMiddleware,
     state::{InMemoryState, NotKeyed},
 };
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::num::NonZeroU32;
 use std::sync::Arc;
 use thiserror::Error;
 use time::OffsetDateTime;
-use tracing::{debug, error, info, instrument};
-
-#[cfg(not(test))]
-use {time::Duration, tracing::warn};
-
-use crate::models::{TenantId, UserId, VerificationCode, VerificationConfig, VerificationType};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, instrument, warn};
+use uuid::Uuid;
+
+use crate::models::user::UserRepository;
+use crate::models::{
+    TenantId, UserId, VerificationCode, VerificationConfig, VerificationStats, VerificationStatus,
+    VerificationType,
+};
 use crate::repository::{TenantAwareContext, VerificationCodeRepository};
-use crate::services::message_provider::{Message, MessageProvider};
-use acci_core::error::{Error, Result};
+use crate::services::message_provider::{Message, MessageProvider, is_transient_send_error};
+use crate::utils::clock::{Clock, SystemClock};
+use crate::utils::duration::{DurationMs, DurationSecs};
+use acci_core::error::{Error, ErrorKind, Result};
 
 /// Errors that can occur when working with verification codes
 #[derive(Debug, Error)]
@@ -44,33 +53,202 @@ pub enum VerificationError {
     /// Recipient not found
     #[error("Recipient not found")]
     RecipientNotFound,
+
+    /// Verification proof is invalid, expired, for another purpose or already used
+    #[error("Invalid verification proof")]
+    InvalidProof,
+
+    /// No secret to sign verification proofs is configured
+    #[error("Verification proofs are not configured")]
+    ProofsNotConfigured,
+
+    /// Every generated code collided with an existing code of the user
+    #[error("Failed to generate a unique verification code")]
+    CodeGenerationFailed,
 }
 
 impl From<VerificationError> for Error {
     fn from(err: VerificationError) -> Self {
-        match err {
-            VerificationError::CodeExpired => Error::Validation("Code has expired".to_string()),
-            VerificationError::InvalidCode => {
-                Error::Validation("Invalid verification code".to_string())
-            },
-            VerificationError::TooManyAttempts => {
-                Error::Validation("Too many verification attempts".to_string())
-            },
-            VerificationError::RateLimitExceeded => {
-                Error::Validation("Rate limit exceeded".to_string())
-            },
-            VerificationError::SendMessageFailed(msg) => {
-                Error::Other(anyhow::anyhow!("Failed to send message: {}", msg))
-            },
-            VerificationError::RecipientNotFound => {
-                Error::Validation("Recipient not found".to_string())
+        let kind = match err {
+            VerificationError::CodeExpired => ErrorKind::CodeExpired,
+            VerificationError::InvalidCode => ErrorKind::InvalidCode,
+            VerificationError::TooManyAttempts => ErrorKind::TooManyAttempts,
+            VerificationError::RateLimitExceeded => ErrorKind::RateLimited,
+            VerificationError::SendMessageFailed(_) => ErrorKind::Unavailable,
+            VerificationError::RecipientNotFound | VerificationError::InvalidProof => {
+                ErrorKind::InvalidInput
             },
+            VerificationError::ProofsNotConfigured => ErrorKind::Configuration,
+            VerificationError::CodeGenerationFailed => ErrorKind::Internal,
+        };
+        Error::domain(kind, err.to_string())
+    }
+}
+
+/// Outcome counts of a single run of the verification code cleanup
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VerificationCleanupReport {
+    /// Pending codes marked as expired
+    pub expired: u64,
+    /// Codes deleted after the retention period
+    pub deleted: u64,
+}
+
+/// Channels a verification code is sent through when the requested one fails
+///
+/// The requested channel is always tried first; the configured channels
+/// follow in order, skipping the requested one. Without any channels
+/// configured, codes are only sent through the requested channel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelFallbackConfig {
+    /// Channel preference of tenants without their own
+    pub channels: Vec<VerificationType>,
+    /// Channel preference per tenant, replacing `channels`
+    pub tenant_channels: HashMap<TenantId, Vec<VerificationType>>,
+    /// Retries on the same channel after a transient send failure
+    pub transient_retries: u32,
+    /// Delay before retrying after a transient send failure
+    pub retry_delay_ms: DurationMs,
+}
+
+impl Default for ChannelFallbackConfig {
+    fn default() -> Self {
+        Self {
+            channels: Vec::new(),
+            tenant_channels: HashMap::new(),
+            transient_retries: 1,
+            retry_delay_ms: DurationMs::from_millis(250),
+        }
+    }
+}
+
+impl ChannelFallbackConfig {
+    /// Channels to try for `tenant_id`, starting with the `requested` one
+    pub fn channels_for(
+        &self,
+        tenant_id: TenantId,
+        requested: VerificationType,
+    ) -> Vec<VerificationType> {
+        let preference = self
+            .tenant_channels
+            .get(&tenant_id)
+            .unwrap_or(&self.channels);
+        let mut channels = vec![requested];
+        for channel in preference {
+            if !channels.contains(channel) {
+                channels.push(*channel);
+            }
         }
+        channels
     }
 }
 
+/// Looks up where a user can be reached on a fallback channel
+#[async_trait]
+pub trait ContactDirectory: Send + Sync {
+    /// Recipient of `user_id` on `channel`, `None` if the user has none
+    async fn contact(
+        &self,
+        tenant_id: TenantId,
+        user_id: UserId,
+        channel: VerificationType,
+    ) -> Result<Option<String>>;
+}
+
+/// [`ContactDirectory`] backed by the user accounts
+///
+/// Only verified email addresses of active users are handed out, so a code
+/// never goes to an address the user has not confirmed. Accounts carry no
+/// phone number, so there is no SMS contact.
+pub struct UserContactDirectory {
+    users: Arc<dyn UserRepository>,
+}
+
+impl UserContactDirectory {
+    pub fn new(users: Arc<dyn UserRepository>) -> Self {
+        Self { users }
+    }
+}
+
+#[async_trait]
+impl ContactDirectory for UserContactDirectory {
+    async fn contact(
+        &self,
+        _tenant_id: TenantId,
+        user_id: UserId,
+        channel: VerificationType,
+    ) -> Result<Option<String>> {
+        match channel {
+            VerificationType::Email => Ok(self
+                .users
+                .find_by_id(user_id)
+                .await?
+                .filter(|user| user.is_active && user.is_verified)
+                .map(|user| user.email)),
+            VerificationType::Sms => Ok(None),
+        }
+    }
+}
+
+/// Where a verification code was sent, see [`VerificationService::send_verification`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerificationDelivery {
+    /// Sent code
+    pub code_id: Uuid,
+    /// Channel that delivered the code, differs from the requested one after a fallback
+    pub channel: VerificationType,
+}
+
+/// Codes generated before giving up on finding one that is not in use
+const MAX_CODE_GENERATION_ATTEMPTS: u32 = 5;
+
+/// Audience of verification proofs, keeps them apart from other tokens signed with the same secret
+const VERIFICATION_PROOF_AUDIENCE: &str = "acci:verification-proof";
+
+/// Claims of a verification proof
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct VerificationProofClaims {
+    /// User who verified the code
+    sub: UserId,
+    /// Tenant the code was issued in
+    tid: TenantId,
+    /// Verified code, consumed together with the proof
+    cid: Uuid,
+    /// Step the proof may be used for
+    pur: String,
+    aud: String,
+    iat: i64,
+    exp: i64,
+}
+
+/// Single-use proof that a user verified a code, see [`VerificationService::verify_and_consume`]
+#[derive(Debug, Clone)]
+pub struct VerificationProof {
+    /// Signed token to hand to the step requiring the verification
+    pub token: String,
+    /// When the proof can no longer be consumed
+    pub expires_at: OffsetDateTime,
+}
+
+/// Verification vouched for by a consumed proof
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConsumedProof {
+    /// User who verified the code
+    pub user_id: UserId,
+    /// Tenant the code was issued in
+    pub tenant_id: TenantId,
+}
+
+/// Keys and lifetime of verification proofs
+struct ProofSigner {
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    ttl: DurationSecs,
+}
+
 /// Service for handling verification codes
-pub struct VerificationService {
+pub struct VerificationService<C: Clock = SystemClock> {
     /// Repository for verification codes
     repo: Arc<dyn VerificationCodeRepository>,
     /// Configuration for verification codes
@@ -82,6 +260,14 @@ pub struct VerificationService {
     /// Rate limiter
     #[allow(dead_code)]
     limiter: Arc<RateLimiter<NotKeyed, InMemoryState, DefaultClock, NoOpMiddleware>>,
+    /// Signs and checks verification proofs, if enabled
+    proof_signer: Option<ProofSigner>,
+    /// Channels tried when sending fails
+    fallback: ChannelFallbackConfig,
+    /// Recipients on fallback channels, fallback is disabled without it
+    contacts: Option<Arc<dyn ContactDirectory>>,
+    /// Source of the current time for expiry and throttling
+    clock: C,
 }
 
 impl VerificationService {
@@ -91,6 +277,19 @@ impl VerificationService {
         config: VerificationConfig,
         sms_provider: Option<Arc<dyn MessageProvider>>,
         email_provider: Option<Arc<dyn MessageProvider>>,
+    ) -> Self {
+        Self::with_clock(repo, config, sms_provider, email_provider, SystemClock)
+    }
+}
+
+impl<C: Clock> VerificationService<C> {
+    /// Create a verification service that reads the current time from `clock`
+    pub fn with_clock(
+        repo: Arc<dyn VerificationCodeRepository>,
+        config: VerificationConfig,
+        sms_provider: Option<Arc<dyn MessageProvider>>,
+        email_provider: Option<Arc<dyn MessageProvider>>,
+        clock: C,
     ) -> Self {
         // Create rate limiter with 3 requests per minute
         let limiter = Arc::new(RateLimiter::direct(Quota::per_minute(
@@ -103,9 +302,36 @@ impl VerificationService {
             sms_provider,
             email_provider,
             limiter,
+            proof_signer: None,
+            fallback: ChannelFallbackConfig::default(),
+            contacts: None,
+            clock,
         }
     }
 
+    /// Enables verification proofs signed with `secret` and valid for `ttl`
+    pub fn with_proof_signing(mut self, secret: &[u8], ttl: DurationSecs) -> Self {
+        self.proof_signer = Some(ProofSigner {
+            encoding_key: EncodingKey::from_secret(secret),
+            decoding_key: DecodingKey::from_secret(secret),
+            ttl,
+        });
+        self
+    }
+
+    /// Sends codes through further channels when the requested one fails
+    ///
+    /// Recipients on the fallback channels are looked up in `contacts`.
+    pub fn with_channel_fallback(
+        mut self,
+        fallback: ChannelFallbackConfig,
+        contacts: Arc<dyn ContactDirectory>,
+    ) -> Self {
+        self.fallback = fallback;
+        self.contacts = Some(contacts);
+        self
+    }
+
     /// Generate a random verification code
     fn generate_code(&self) -> String {
         use rand::Rng;
@@ -116,6 +342,41 @@ impl VerificationService {
         code
     }
 
+    /// Generate a code no other code of the user for this type and tenant has
+    ///
+    /// Verification looks codes up by value, so a new code must not repeat
+    /// one that is still stored, whether it is pending or not. Collisions are
+    /// rare, so this usually takes a single lookup.
+    async fn generate_unique_code(
+        &self,
+        user_id: UserId,
+        verification_type: VerificationType,
+        tenant_id: TenantId,
+        context: &dyn TenantAwareContext,
+    ) -> Result<String> {
+        for attempt in 1..=MAX_CODE_GENERATION_ATTEMPTS {
+            let code = self.generate_code();
+            if self
+                .repo
+                .get_by_code(&code, user_id, verification_type, tenant_id, context)
+                .await?
+                .is_none()
+            {
+                return Ok(code);
+            }
+            debug!(
+                "Generated verification code for user {} is already in use (attempt {})",
+                user_id, attempt
+            );
+        }
+
+        warn!(
+            "Failed to generate a unique verification code for user {} after {} attempts",
+            user_id, MAX_CODE_GENERATION_ATTEMPTS
+        );
+        Err(VerificationError::CodeGenerationFailed.into())
+    }
+
     /// Get the appropriate message provider for the verification type
     fn get_provider(
         &self,
@@ -148,7 +409,7 @@ impl VerificationService {
             }
 
             // Check database rate limit
-            let since = OffsetDateTime::now_utc() - Duration::seconds(self.config.throttle_seconds);
+            let since = self.clock.now() - self.config.throttle_seconds.to_time();
             let attempt_count = self
                 .repo
                 .count_recent_attempts(_user_id, _verification_type, since, _tenant_id, _context)
@@ -182,18 +443,26 @@ impl VerificationService {
         )
         .await?;
 
+        // Generate new code before touching the pending ones, so a failure leaves them usable
+        let code = self
+            .generate_unique_code(user_id, verification_type, tenant_id, context)
+            .await?;
+
         // Invalidate any pending codes for this user and type
         let _ = self
             .repo
             .invalidate_pending(user_id, verification_type, tenant_id, context)
             .await?;
 
-        // Generate new code
-        let code = self.generate_code();
-
         // Create verification code
-        let verification_code =
-            VerificationCode::new(tenant_id, user_id, code, verification_type, &self.config);
+        let verification_code = VerificationCode::issued_at(
+            tenant_id,
+            user_id,
+            code,
+            verification_type,
+            &self.config,
+            self.clock.now(),
+        );
 
         // Save to repository
         self.repo.save(&verification_code, context).await?;
@@ -203,6 +472,12 @@ impl VerificationService {
     }
 
     /// Send a verification code to a user
+    ///
+    /// A transient send failure is retried on the same channel. When the
+    /// channel fails permanently or keeps failing, the code is sent through
+    /// the next channel of the tenant's [`ChannelFallbackConfig`] the user
+    /// can be reached on. The code stays valid for the requested type; the
+    /// channel that delivered it is recorded with the code.
     #[instrument(skip(self, context, recipient), level = "debug")]
     pub async fn send_verification(
         &self,
@@ -211,58 +486,158 @@ impl VerificationService {
         verification_type: VerificationType,
         recipient: String,
         context: &dyn TenantAwareContext,
-    ) -> Result<()> {
+    ) -> Result<VerificationDelivery> {
         // Generate verification code
         let verification_code = self
             .generate_verification_code(tenant_id, user_id, verification_type, tenant_id, context)
             .await?;
 
-        // Get appropriate provider
-        let provider =
-            self.get_provider(verification_type)
-                .ok_or(VerificationError::SendMessageFailed(format!(
-                    "No provider configured for {:?}",
-                    verification_type
-                )))?;
+        let channels = if self.contacts.is_some() {
+            self.fallback.channels_for(tenant_id, verification_type)
+        } else {
+            vec![verification_type]
+        };
+        let mut last_error = None;
+        for channel in channels {
+            let fallback = channel != verification_type;
+
+            // Get appropriate provider
+            let Some(provider) = self.get_provider(channel) else {
+                last_error = Some(format!("No provider configured for {:?}", channel));
+                continue;
+            };
+
+            let recipient = if fallback {
+                match self.fallback_recipient(tenant_id, user_id, channel).await {
+                    Some(recipient) => recipient,
+                    None => continue,
+                }
+            } else {
+                recipient.clone()
+            };
+
+            match self
+                .send_with_retries(
+                    provider.as_ref(),
+                    self.verification_message(
+                        tenant_id,
+                        user_id,
+                        channel,
+                        recipient,
+                        &verification_code,
+                    ),
+                )
+                .await
+            {
+                Ok(()) => {
+                    info!(
+                        "Sent verification code to user {} via {:?}",
+                        user_id, channel
+                    );
+                    if let Err(e) = self
+                        .repo
+                        .record_delivery_channel(verification_code.id, tenant_id, channel, context)
+                        .await
+                    {
+                        warn!(
+                            "Failed to record delivery channel of verification code {}: {}",
+                            verification_code.id, e
+                        );
+                    }
+                    record_delivery(tenant_id, channel, fallback);
+                    return Ok(VerificationDelivery {
+                        code_id: verification_code.id,
+                        channel,
+                    });
+                },
+                Err(e) => {
+                    error!("Failed to send verification code via {:?}: {}", channel, e);
+                    last_error = Some(e.to_string());
+                },
+            }
+        }
+
+        Err(VerificationError::SendMessageFailed(
+            last_error
+                .unwrap_or_else(|| format!("No provider configured for {:?}", verification_type)),
+        )
+        .into())
+    }
+
+    /// Recipient of the user on a fallback channel, `None` if they cannot be reached on it
+    async fn fallback_recipient(
+        &self,
+        tenant_id: TenantId,
+        user_id: UserId,
+        channel: VerificationType,
+    ) -> Option<String> {
+        let contacts = self.contacts.as_ref()?;
+        match contacts.contact(tenant_id, user_id, channel).await {
+            Ok(Some(recipient)) => Some(recipient),
+            Ok(None) => {
+                debug!("User {} has no contact for {:?}", user_id, channel);
+                None
+            },
+            Err(e) => {
+                warn!(
+                    "Failed to look up {:?} contact of user {}: {}",
+                    channel, user_id, e
+                );
+                None
+            },
+        }
+    }
+
+    /// Send `message`, retrying transient failures as configured
+    async fn send_with_retries(
+        &self,
+        provider: &dyn MessageProvider,
+        message: Message,
+    ) -> Result<()> {
+        let mut retries = 0;
+        loop {
+            match provider.send_message(message.clone()).await {
+                Ok(_) => return Ok(()),
+                Err(e)
+                    if is_transient_send_error(&e) && retries < self.fallback.transient_retries =>
+                {
+                    retries += 1;
+                    warn!(
+                        "Transient failure sending via {:?}, retrying ({}/{}): {}",
+                        message.message_type, retries, self.fallback.transient_retries, e
+                    );
+                    tokio::time::sleep(self.fallback.retry_delay_ms.as_duration()).await;
+                },
+                Err(e) => return Err(e),
+            }
+        }
+    }
 
-        // Create message
-        let subject = match verification_type {
+    /// Message carrying `code` on `channel`
+    fn verification_message(
+        &self,
+        tenant_id: TenantId,
+        user_id: UserId,
+        channel: VerificationType,
+        recipient: String,
+        code: &VerificationCode,
+    ) -> Message {
+        let subject = match channel {
             VerificationType::Email => Some("Your verification code".to_string()),
             VerificationType::Sms => None,
         };
 
-        let body = match verification_type {
-            VerificationType::Email => format!(
-                "Your verification code is: {}. It will expire in {} minutes.",
-                verification_code.code,
-                self.config.expiration_seconds / 60
-            ),
-            VerificationType::Sms => format!(
-                "Your verification code is: {}. It will expire in {} minutes.",
-                verification_code.code,
-                self.config.expiration_seconds / 60
-            ),
-        };
-
-        let message = Message {
+        Message {
             tenant_id,
             user_id,
             recipient,
             subject,
-            body,
-            message_type: verification_type,
-        };
-
-        // Send message
-        match provider.send_message(message).await {
-            Ok(_) => {
-                info!("Sent verification code to user {}", user_id);
-                Ok(())
-            },
-            Err(e) => {
-                error!("Failed to send verification code: {}", e);
-                Err(VerificationError::SendMessageFailed(e.to_string()).into())
-            },
+            body: format!(
+                "Your verification code is: {}. It will expire in {} minutes.",
+                code.code,
+                self.config.expiration_seconds.as_secs() / 60
+            ),
+            message_type: channel,
         }
     }
 
@@ -276,6 +651,110 @@ impl VerificationService {
         tenant_id: TenantId,
         context: &dyn TenantAwareContext,
     ) -> Result<()> {
+        self.verify_pending_code(user_id, verification_type, code, tenant_id, context)
+            .await?;
+        Ok(())
+    }
+
+    /// Verify a code and return a proof of the verification
+    ///
+    /// The proof is bound to the user, the tenant and `purpose` and can be
+    /// consumed once with [`consume_proof`](Self::consume_proof) until it
+    /// expires. It lets a later step rely on the verification without asking
+    /// for the code again.
+    #[instrument(skip(self, context), level = "debug")]
+    pub async fn verify_and_consume(
+        &self,
+        user_id: UserId,
+        verification_type: VerificationType,
+        code: &str,
+        tenant_id: TenantId,
+        purpose: &str,
+        context: &dyn TenantAwareContext,
+    ) -> Result<VerificationProof> {
+        // Fail before the code is used up if no proof can be issued
+        let signer = self
+            .proof_signer
+            .as_ref()
+            .ok_or(VerificationError::ProofsNotConfigured)?;
+
+        let verification_code = self
+            .verify_pending_code(user_id, verification_type, code, tenant_id, context)
+            .await?;
+
+        let now = self.clock.now();
+        let expires_at = now + signer.ttl.to_time();
+        let claims = VerificationProofClaims {
+            sub: user_id,
+            tid: tenant_id,
+            cid: verification_code.id,
+            pur: purpose.to_string(),
+            aud: VERIFICATION_PROOF_AUDIENCE.to_string(),
+            iat: now.unix_timestamp(),
+            exp: expires_at.unix_timestamp(),
+        };
+        let token = encode(&Header::default(), &claims, &signer.encoding_key)
+            .map_err(|e| Error::Other(anyhow::anyhow!("Failed to sign verification proof: {e}")))?;
+
+        debug!(user_id = %user_id, purpose, "Issued verification proof");
+        Ok(VerificationProof { token, expires_at })
+    }
+
+    /// Validate a verification proof for `purpose` and burn it
+    ///
+    /// Proofs issued for another purpose, expired proofs and proofs that were
+    /// already consumed fail with [`VerificationError::InvalidProof`].
+    #[instrument(skip(self, token, context), level = "debug")]
+    pub async fn consume_proof(
+        &self,
+        token: &str,
+        purpose: &str,
+        context: &dyn TenantAwareContext,
+    ) -> Result<ConsumedProof> {
+        let signer = self
+            .proof_signer
+            .as_ref()
+            .ok_or(VerificationError::ProofsNotConfigured)?;
+
+        // Expiry is checked against the service clock
+        let mut validation = Validation::default();
+        validation.validate_exp = false;
+        validation.set_audience(&[VERIFICATION_PROOF_AUDIENCE]);
+        let claims = decode::<VerificationProofClaims>(token, &signer.decoding_key, &validation)
+            .map_err(|_| VerificationError::InvalidProof)?
+            .claims;
+
+        if claims.pur != purpose || claims.exp <= self.clock.now().unix_timestamp() {
+            return Err(VerificationError::InvalidProof.into());
+        }
+        if !self
+            .repo
+            .consume_verified(claims.cid, claims.tid, context)
+            .await?
+        {
+            warn!(user_id = %claims.sub, purpose, "Verification proof was already used");
+            return Err(VerificationError::InvalidProof.into());
+        }
+
+        info!(user_id = %claims.sub, purpose, "Consumed verification proof");
+        Ok(ConsumedProof {
+            user_id: claims.sub,
+            tenant_id: claims.tid,
+        })
+    }
+
+    /// Verify a pending code and return it in its verified state
+    ///
+    /// Codes that were already verified, invalidated or consumed are rejected
+    /// as invalid.
+    async fn verify_pending_code(
+        &self,
+        user_id: UserId,
+        verification_type: VerificationType,
+        code: &str,
+        tenant_id: TenantId,
+        context: &dyn TenantAwareContext,
+    ) -> Result<VerificationCode> {
         // Get verification code
         let mut verification_code = self
             .repo
@@ -284,9 +763,17 @@ impl VerificationService {
             .ok_or(VerificationError::InvalidCode)?;
 
         // Check if expired
-        if verification_code.is_expired() {
+        if verification_code.is_expired_at(self.clock.now()) {
+            if verification_code.status == VerificationStatus::Pending {
+                verification_code.mark_expired();
+                self.repo.update(&verification_code, context).await?;
+                record_expired(tenant_id, 1);
+            }
             return Err(VerificationError::CodeExpired.into());
         }
+        if verification_code.status != VerificationStatus::Pending {
+            return Err(VerificationError::InvalidCode.into());
+        }
 
         // Increment attempt counter
         verification_code.increment_attempts();
@@ -320,15 +807,16 @@ impl VerificationService {
         // Mark as verified
         verification_code.mark_verified();
         self.repo.update(&verification_code, context).await?;
+        record_verified(tenant_id, verification_type);
 
         info!("Verified code for user {}", user_id);
-        Ok(())
+        Ok(verification_code)
     }
 
     /// Clean up expired verification codes
     #[instrument(skip(self, context), level = "debug")]
     pub async fn cleanup_expired(&self, context: &dyn TenantAwareContext) -> Result<u64> {
-        let before = OffsetDateTime::now_utc();
+        let before = self.clock.now();
         // Use a default tenant ID for cleanup since we don't have a specific tenant
         let default_tenant_id = uuid::Uuid::parse_str("00000000-0000-0000-0000-000000000000")
             .expect("Failed to parse zero UUID constant");
@@ -339,120 +827,148 @@ impl VerificationService {
         debug!("Cleaned up {} expired verification codes", count);
         Ok(count)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::models::VerificationConfig;
-    use crate::repository::TenantAwareContext;
-    use async_trait::async_trait;
-    use std::sync::Arc;
-    use uuid::Uuid;
-
-    // Mock repository for testing
-    struct MockVerificationCodeRepository;
-
-    #[async_trait]
-    impl VerificationCodeRepository for MockVerificationCodeRepository {
-        async fn save(
-            &self,
-            _code: &VerificationCode,
-            _context: &dyn TenantAwareContext,
-        ) -> Result<()> {
-            Ok(())
+    /// Expire the pending codes of all tenants and delete codes older than `retention`
+    ///
+    /// Expired codes are kept for `retention` so that they still show up in
+    /// the verification statistics.
+    #[instrument(skip(self), level = "debug")]
+    pub async fn cleanup_all_expired(
+        &self,
+        retention: DurationSecs,
+    ) -> Result<VerificationCleanupReport> {
+        let now = self.clock.now();
+
+        let mut report = VerificationCleanupReport::default();
+        for expired in self.repo.expire_all_pending(now).await? {
+            record_expired(expired.tenant_id, expired.count);
+            report.expired += expired.count;
         }
+        report.deleted = self
+            .repo
+            .cleanup_all_expired(now - retention.to_time())
+            .await?;
 
-        async fn get_by_code(
-            &self,
-            _code: &str,
-            _user_id: Uuid,
-            _verification_type: VerificationType,
-            _tenant_id: Uuid,
-            _context: &dyn TenantAwareContext,
-        ) -> Result<Option<VerificationCode>> {
-            Ok(None)
-        }
+        debug!(
+            expired = report.expired,
+            deleted = report.deleted,
+            "Cleaned up verification codes of all tenants"
+        );
+        Ok(report)
+    }
 
-        async fn update(
-            &self,
-            _code: &VerificationCode,
-            _context: &dyn TenantAwareContext,
-        ) -> Result<()> {
-            Ok(())
-        }
+    /// Count the outcomes of the codes a tenant issued during the last `days` days
+    #[instrument(skip(self, context), level = "debug")]
+    pub async fn stats(
+        &self,
+        tenant_id: TenantId,
+        days: i64,
+        context: &dyn TenantAwareContext,
+    ) -> Result<VerificationStats> {
+        let since = self.clock.now() - time::Duration::days(days);
+        self.repo
+            .verification_stats(tenant_id, since, context)
+            .await
+    }
 
-        async fn invalidate_pending(
-            &self,
-            _user_id: Uuid,
-            _verification_type: VerificationType,
-            _tenant_id: Uuid,
-            _context: &dyn TenantAwareContext,
-        ) -> Result<u64> {
-            Ok(0)
-        }
+    /// Run the cleanup job on a dedicated task until the runtime shuts down
+    pub fn spawn_cleanup(
+        self: Arc<Self>,
+        interval: DurationSecs,
+        retention: DurationSecs,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval.as_duration());
+            info!("Verification code cleanup job started");
+
+            loop {
+                interval.tick().await;
+                match self.cleanup_all_expired(retention).await {
+                    Ok(report) if report != VerificationCleanupReport::default() => {
+                        info!(
+                            expired = report.expired,
+                            deleted = report.deleted,
+                            "Cleaned up expired verification codes"
+                        );
+                    },
+                    Ok(_) => {},
+                    Err(e) => error!("Verification code cleanup job failed: {}", e),
+                }
+            }
+        })
+    }
+}
 
-        async fn get_pending_by_user(
-            &self,
-            _user_id: Uuid,
-            _verification_type: VerificationType,
-            _tenant_id: Uuid,
-            _context: &dyn TenantAwareContext,
-        ) -> Result<Vec<VerificationCode>> {
-            Ok(vec![])
-        }
+/// Count codes that expired without being verified
+#[cfg(feature = "metrics")]
+fn record_expired(tenant_id: TenantId, count: u64) {
+    metrics::counter!(
+        "auth_verification_codes_expired_total",
+        "tenant" => tenant_id.to_string()
+    )
+    .increment(count);
+}
 
-        async fn count_recent_attempts(
-            &self,
-            _user_id: Uuid,
-            _verification_type: VerificationType,
-            _since: OffsetDateTime,
-            _tenant_id: Uuid,
-            _context: &dyn TenantAwareContext,
-        ) -> Result<u64> {
-            Ok(0)
-        }
+#[cfg(not(feature = "metrics"))]
+fn record_expired(_tenant_id: TenantId, _count: u64) {}
+
+/// Count a successfully verified code
+#[cfg(feature = "metrics")]
+fn record_verified(tenant_id: TenantId, verification_type: VerificationType) {
+    let verification_type = match verification_type {
+        VerificationType::Email => "email",
+        VerificationType::Sms => "sms",
+    };
+    metrics::counter!(
+        "auth_verification_codes_verified_total",
+        "tenant" => tenant_id.to_string(),
+        "type" => verification_type
+    )
+    .increment(1);
+}
 
-        async fn get_by_id(
-            &self,
-            _id: Uuid,
-            _tenant_id: TenantId,
-            _context: &dyn TenantAwareContext,
-        ) -> Result<Option<VerificationCode>> {
-            Ok(None)
-        }
+#[cfg(not(feature = "metrics"))]
+fn record_verified(_tenant_id: TenantId, _verification_type: VerificationType) {}
+
+/// Count a sent code by the channel that delivered it
+#[cfg(feature = "metrics")]
+fn record_delivery(tenant_id: TenantId, channel: VerificationType, fallback: bool) {
+    let channel = match channel {
+        VerificationType::Email => "email",
+        VerificationType::Sms => "sms",
+    };
+    metrics::counter!(
+        "auth_verification_deliveries_total",
+        "tenant" => tenant_id.to_string(),
+        "channel" => channel,
+        "fallback" => fallback.to_string()
+    )
+    .increment(1);
+}
 
-        async fn delete(
-            &self,
-            _id: Uuid,
-            _tenant_id: TenantId,
-            _context: &dyn TenantAwareContext,
-        ) -> Result<()> {
-            Ok(())
-        }
+#[cfg(not(feature = "metrics"))]
+fn record_delivery(_tenant_id: TenantId, _channel: VerificationType, _fallback: bool) {}
 
-        async fn delete_expired(
-            &self,
-            _before: OffsetDateTime,
-            _tenant_id: Uuid,
-            _context: &dyn TenantAwareContext,
-        ) -> Result<u64> {
-            Ok(0)
-        }
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::VerificationConfig;
+    use crate::repository::verification_repository::mock::MockVerificationCodeRepository;
+    use crate::utils::duration::DurationSecs;
+    use std::sync::Arc;
 
     #[test]
     fn test_generate_code() {
         // Create verification config with code length 6
         let config = VerificationConfig {
             code_length: 6,
-            expiration_seconds: 300,
+            expiration_seconds: DurationSecs::from_secs(300),
             max_attempts: 3,
-            throttle_seconds: 300,
+            throttle_seconds: DurationSecs::from_secs(300),
         };
 
         // Create verification service
-        let repo = Arc::new(MockVerificationCodeRepository);
+        let repo = Arc::new(MockVerificationCodeRepository::new());
         let service = VerificationService::new(repo, config, None, None);
 
         // Generate code
@@ -1,5 +1,6 @@
 use crate::{
     models::{
+        tenant::{TenantRepository, WebAuthnAttestationPolicy},
         user::User,
         webauthn::{
             Credential, CredentialID, PublicKeyCredential, RegisterCredential, WebAuthnError,
@@ -28,6 +29,11 @@ pub struct WebAuthnConfig {
     pub origin: String,
     /// User verification preference: "discouraged", "preferred", or "required"
     pub user_verification: String,
+    /// Root certificates of the authenticator vendors whose attestation is
+    /// trusted, e.g. taken from the FIDO Metadata Service
+    ///
+    /// Required by tenants whose [`WebAuthnAttestationPolicy`] requires attestation.
+    pub attestation_cas: AttestationCaList,
 }
 
 // Using the WebAuthnError from models/webauthn.rs instead
@@ -46,8 +52,19 @@ const WEBAUTHN_REG_STATE_KEY: &str = "webauthn_registration_state";
 /// Session storage key for authentication state
 const WEBAUTHN_AUTH_STATE_KEY: &str = "webauthn_authentication_state";
 
-/// Type for storing registration state
-type RegistrationState = PasskeyRegistration;
+/// Flag of the authenticator data indicating attested credential data
+const AUTH_DATA_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+/// Registration state, depending on whether the tenant requires attestation
+#[derive(Clone)]
+enum RegistrationState {
+    /// Registration of any authenticator, attestation is not requested
+    Passkey(PasskeyRegistration),
+    /// Registration of an attested authenticator, checked against the policy
+    /// of the tenant when it started
+    Attested(AttestedPasskeyRegistration, WebAuthnAttestationPolicy),
+}
+
 /// Type for storing authentication state
 type AuthenticationState = PasskeyAuthentication;
 
@@ -134,6 +151,8 @@ pub struct WebAuthnService {
     webauthn: Webauthn,
     repository: Arc<dyn WebAuthnRepository>,
     user_service: Arc<UserService>,
+    tenant_repository: Option<Arc<dyn TenantRepository>>,
+    attestation_cas: AttestationCaList,
     reg_states: RegistrationStateStore,
     auth_states: AuthenticationStateStore,
 }
@@ -170,11 +189,34 @@ impl WebAuthnService {
             webauthn,
             repository,
             user_service,
+            tenant_repository: None,
+            attestation_cas: config.attestation_cas,
             reg_states: RegistrationStateStore::new(),
             auth_states: AuthenticationStateStore::new(),
         })
     }
 
+    /// Apply the WebAuthn attestation policies of the tenants to registrations
+    ///
+    /// Without a tenant repository every authenticator may register.
+    pub fn with_tenant_repository(mut self, tenant_repository: Arc<dyn TenantRepository>) -> Self {
+        self.tenant_repository = Some(tenant_repository);
+        self
+    }
+
+    /// Returns the attestation policy registrations with the tenant are subject to
+    async fn attestation_policy(&self, tenant_id: &Uuid) -> Result<WebAuthnAttestationPolicy> {
+        let Some(tenant_repository) = &self.tenant_repository else {
+            return Ok(WebAuthnAttestationPolicy::default());
+        };
+        let tenant = tenant_repository
+            .find_tenant_by_id(*tenant_id)
+            .await
+            .map_err(|e| WebAuthnError::Repository(e.to_string()))?
+            .ok_or_else(|| WebAuthnError::Unexpected("Tenant not found".to_string()))?;
+        Ok(tenant.security_policy().webauthn_attestation)
+    }
+
     /// Start the registration process for a new credential
     #[instrument(skip(self, session_data), level = "debug")]
     pub async fn start_registration(
@@ -202,11 +244,35 @@ impl WebAuthnService {
             .map(|cred| cred.id.0.clone())
             .collect::<Vec<_>>();
 
-        // Generate registration options
-        let (options, reg_state) = self
-            .webauthn
-            .start_passkey_registration(user.id, &username, &display_name, None)
-            .map_err(|e| WebAuthnError::WebAuthn(e.to_string()))?;
+        // Tenants requiring attestation only accept attested, device-bound authenticators
+        let policy = self.attestation_policy(tenant_id).await?;
+        let (options, reg_state) = if policy.requires_attestation() {
+            if self.attestation_cas.is_empty() {
+                return Err(WebAuthnError::Attestation(
+                    "The tenant requires attestation, but no attestation CAs are trusted"
+                        .to_string(),
+                )
+                .into());
+            }
+            let (options, state) = self
+                .webauthn
+                .start_attested_passkey_registration(
+                    user.id,
+                    &username,
+                    &display_name,
+                    None,
+                    self.attestation_cas.clone(),
+                    None,
+                )
+                .map_err(|e| WebAuthnError::WebAuthn(e.to_string()))?;
+            (options, RegistrationState::Attested(state, policy))
+        } else {
+            let (options, state) = self
+                .webauthn
+                .start_passkey_registration(user.id, &username, &display_name, None)
+                .map_err(|e| WebAuthnError::WebAuthn(e.to_string()))?;
+            (options, RegistrationState::Passkey(state))
+        };
 
         // Store registration state in memory (for production, use Redis or similar)
         self.reg_states.insert(user.id, reg_state);
@@ -249,18 +315,35 @@ impl WebAuthnService {
             .remove(&user.id)
             .ok_or_else(|| WebAuthnError::Unexpected("Registration state not found".to_string()))?;
 
-        // Verify the registration response
-        let webauthn_cred = self
-            .webauthn
-            .finish_passkey_registration(&parsed_credential, &reg_state)
-            .map_err(|e| WebAuthnError::Attestation(e.to_string()))?;
+        // Verify the registration response, including the attestation if requested
+        let (cred_id, aaguid) = match reg_state {
+            RegistrationState::Passkey(state) => {
+                let passkey = self
+                    .webauthn
+                    .finish_passkey_registration(&parsed_credential, &state)
+                    .map_err(|e| WebAuthnError::Attestation(e.to_string()))?;
+                (
+                    passkey.cred_id().to_vec(),
+                    reported_aaguid(&parsed_credential),
+                )
+            },
+            RegistrationState::Attested(state, policy) => {
+                let passkey = self
+                    .webauthn
+                    .finish_attested_passkey_registration(&parsed_credential, &state)
+                    .map_err(|e| WebAuthnError::Attestation(e.to_string()))?;
+                let aaguid = check_attestation(&policy, passkey.attestation())?;
+                (passkey.cred_id().to_vec(), Some(aaguid))
+            },
+        };
 
         // Create our credential model
-        let _now = time::OffsetDateTime::now_utc();
         let cred = Credential::new(
-            webauthn_cred.cred_id().to_vec(),
-            webauthn_cred.cred_id().to_vec(), // public_key field is repurposed for this
-            Vec::new(),                       // Empty aaguid
+            cred_id.clone(),
+            cred_id, // public_key field is repurposed for this
+            aaguid
+                .map(|aaguid| aaguid.as_bytes().to_vec())
+                .unwrap_or_default(),
             &credential.name,
             user.id,
             *tenant_id,
@@ -468,3 +551,63 @@ impl WebAuthnService {
         Ok(())
     }
 }
+
+/// Check a verified attestation against the attestation policy of a tenant
+///
+/// Returns the AAGUID of the authenticator model. Only packed and TPM
+/// attestations carry an AAGUID; "none" and self attestation prove nothing
+/// about the authenticator and are rejected.
+fn check_attestation(
+    policy: &WebAuthnAttestationPolicy,
+    attestation: &ParsedAttestation,
+) -> std::result::Result<Uuid, WebAuthnError> {
+    if matches!(
+        attestation.data,
+        ParsedAttestationData::None | ParsedAttestationData::Self_
+    ) {
+        return Err(WebAuthnError::Attestation(
+            "The authenticator provided no attestation".to_string(),
+        ));
+    }
+
+    let aaguid = match &attestation.metadata {
+        AttestationMetadata::Packed { aaguid } | AttestationMetadata::Tpm { aaguid, .. } => *aaguid,
+        _ => {
+            return Err(WebAuthnError::Attestation(
+                "Only packed and TPM attestations are accepted".to_string(),
+            ));
+        },
+    };
+
+    if !policy.allows_aaguid(aaguid) {
+        return Err(WebAuthnError::Attestation(format!(
+            "Authenticator model {} is not allowed",
+            aaguid
+        )));
+    }
+    Ok(aaguid)
+}
+
+/// AAGUID from the authenticator data of a registration response
+///
+/// Unverified without attestation, and clients may zero it when no
+/// attestation is requested.
+fn reported_aaguid(credential: &RegisterPublicKeyCredential) -> Option<Uuid> {
+    let object: serde_cbor_2::Value =
+        serde_cbor_2::from_slice(credential.response.attestation_object.as_slice()).ok()?;
+    let serde_cbor_2::Value::Map(object) = object else {
+        return None;
+    };
+    let Some(serde_cbor_2::Value::Bytes(auth_data)) =
+        object.get(&serde_cbor_2::Value::Text("authData".to_string()))
+    else {
+        return None;
+    };
+
+    // The RP ID hash (32 bytes), flags (1) and signature counter (4) precede
+    // the attested credential data, which starts with the AAGUID
+    if auth_data.get(32)? & AUTH_DATA_ATTESTED_CREDENTIAL_DATA == 0 {
+        return None;
+    }
+    Uuid::from_slice(auth_data.get(37..53)?).ok()
+}
@@ -1,26 +1,66 @@
+use acci_core::datetime::now_micros;
 use serde::{Deserialize, Serialize};
-use std::time::SystemTime;
+use std::net::IpAddr;
+use time::OffsetDateTime;
 use uuid::Uuid;
 
 /// Represents a geographic location where a session was accessed
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SessionLocation {
     pub id: Uuid,
     pub session_id: Uuid,
-    pub ip_address: String,
-    pub country: Option<String>,
-    pub region: Option<String>,
+    pub user_id: Uuid,
+    pub ip_address: IpAddr,
+    /// ISO 3166-1 alpha-2 country code
+    pub country_code: String,
+    pub region_code: Option<String>,
     pub city: Option<String>,
-    pub latitude: Option<f64>,
-    pub longitude: Option<f64>,
-    pub created_at: SystemTime,
+    pub timezone: Option<String>,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub is_verified: bool,
+    pub is_suspicious: bool,
+    #[serde(with = "time::serde::rfc3339")]
+    pub recorded_at: OffsetDateTime,
+}
+
+impl SessionLocation {
+    /// Create a location recorded now
+    pub fn new(
+        session_id: Uuid,
+        user_id: Uuid,
+        ip_address: IpAddr,
+        country_code: impl Into<String>,
+        latitude: f64,
+        longitude: f64,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            session_id,
+            user_id,
+            ip_address,
+            country_code: country_code.into(),
+            region_code: None,
+            city: None,
+            timezone: None,
+            latitude,
+            longitude,
+            is_verified: false,
+            is_suspicious: false,
+            recorded_at: now_micros(),
+        }
+    }
 }
 
 /// Enhanced fingerprint data for session security
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// The device attributes are stored together as the `device_fingerprint`
+/// document; the canvas and WebGL hashes are indexed columns.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EnhancedSessionFingerprint {
     pub id: Uuid,
     pub session_id: Uuid,
+    pub user_id: Uuid,
     pub user_agent: String,
     pub browser: Option<String>,
     pub browser_version: Option<String>,
@@ -29,11 +69,40 @@ pub struct EnhancedSessionFingerprint {
     pub device_type: Option<String>,
     pub device_vendor: Option<String>,
     pub device_model: Option<String>,
-    pub created_at: SystemTime,
+    pub canvas_fingerprint: Option<String>,
+    pub webgl_fingerprint: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+impl EnhancedSessionFingerprint {
+    /// Create a fingerprint with only the user agent known
+    pub fn new(session_id: Uuid, user_id: Uuid, user_agent: impl Into<String>) -> Self {
+        let now = now_micros();
+        Self {
+            id: Uuid::new_v4(),
+            session_id,
+            user_id,
+            user_agent: user_agent.into(),
+            browser: None,
+            browser_version: None,
+            os: None,
+            os_version: None,
+            device_type: None,
+            device_vendor: None,
+            device_model: None,
+            canvas_fingerprint: None,
+            webgl_fingerprint: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
 }
 
 /// Risk assessment for session security
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RiskLevel {
     Low,
     Medium,
@@ -41,13 +110,70 @@ pub enum RiskLevel {
     Critical,
 }
 
+impl RiskLevel {
+    /// Level of a risk score between 0 and 1
+    pub fn from_score(score: f64) -> Self {
+        if score >= 0.75 {
+            Self::Critical
+        } else if score >= 0.5 {
+            Self::High
+        } else if score >= 0.25 {
+            Self::Medium
+        } else {
+            Self::Low
+        }
+    }
+
+    /// Half-open range of the risk scores with this level
+    pub fn score_range(&self) -> (f64, f64) {
+        match self {
+            Self::Low => (0.0, 0.25),
+            Self::Medium => (0.25, 0.5),
+            Self::High => (0.5, 0.75),
+            // Upper bound above the maximum score of 1
+            Self::Critical => (0.75, 2.0),
+        }
+    }
+}
+
 /// Represents a risk assessment for a session
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SessionRiskAssessment {
     pub id: Uuid,
     pub session_id: Uuid,
-    pub risk_level: RiskLevel,
+    pub user_id: Uuid,
+    /// Score between 0 and 1, stored with four decimal places
+    pub risk_score: f64,
     pub risk_factors: Vec<String>,
-    pub assessment_time: SystemTime,
-    pub created_at: SystemTime,
+    pub is_flagged: bool,
+    /// Action taken because of the assessment, e.g. a step-up challenge
+    pub action_taken: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+impl SessionRiskAssessment {
+    /// Create an assessment, clamping the score to the stored range and precision
+    pub fn new(
+        session_id: Uuid,
+        user_id: Uuid,
+        risk_score: f64,
+        risk_factors: Vec<String>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            session_id,
+            user_id,
+            risk_score: (risk_score.clamp(0.0, 1.0) * 10_000.0).round() / 10_000.0,
+            risk_factors,
+            is_flagged: false,
+            action_taken: None,
+            created_at: now_micros(),
+        }
+    }
+
+    /// Risk level of the assessment's score
+    pub fn risk_level(&self) -> RiskLevel {
+        RiskLevel::from_score(self.risk_score)
+    }
 }
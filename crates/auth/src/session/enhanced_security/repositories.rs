@@ -1,10 +1,16 @@
 use crate::repository::RepositoryError;
+use crate::repository::instrumentation::observe;
 use crate::session::enhanced_security::models::{
     EnhancedSessionFingerprint, RiskLevel, SessionLocation, SessionRiskAssessment,
 };
 use async_trait::async_trait;
-use sqlx::PgPool;
-use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::types::Json;
+use sqlx::types::ipnetwork::IpNetwork;
+use sqlx::{PgPool, Row};
+use time::OffsetDateTime;
+use tracing::instrument;
 use uuid::Uuid;
 
 #[async_trait]
@@ -22,10 +28,16 @@ pub trait SessionLocationRepository: Send + Sync {
         user_id: Uuid,
         limit: usize,
     ) -> std::result::Result<Vec<SessionLocation>, RepositoryError>;
+    /// Delete locations recorded before the cutoff, returning the number deleted
+    async fn delete_old_locations(
+        &self,
+        older_than: OffsetDateTime,
+    ) -> std::result::Result<u64, RepositoryError>;
 }
 
 #[async_trait]
 pub trait EnhancedFingerprintRepository: Send + Sync {
+    /// Store a fingerprint, replacing the stored version with the same ID
     async fn save_fingerprint(
         &self,
         fingerprint: &EnhancedSessionFingerprint,
@@ -39,6 +51,11 @@ pub trait EnhancedFingerprintRepository: Send + Sync {
         user_id: Uuid,
         limit: usize,
     ) -> std::result::Result<Vec<EnhancedSessionFingerprint>, RepositoryError>;
+    /// Delete fingerprints created before the cutoff, returning the number deleted
+    async fn delete_old_fingerprints(
+        &self,
+        older_than: OffsetDateTime,
+    ) -> std::result::Result<u64, RepositoryError>;
 }
 
 #[async_trait]
@@ -47,6 +64,7 @@ pub trait RiskAssessmentRepository: Send + Sync {
         &self,
         assessment: &SessionRiskAssessment,
     ) -> std::result::Result<(), RepositoryError>;
+    /// Latest assessment of a session
     async fn get_assessment_by_session_id(
         &self,
         session_id: Uuid,
@@ -54,13 +72,25 @@ pub trait RiskAssessmentRepository: Send + Sync {
     async fn get_assessments_by_risk_level(
         &self,
         risk_level: RiskLevel,
-        from_date: SystemTime,
+        from_date: OffsetDateTime,
         limit: usize,
     ) -> std::result::Result<Vec<SessionRiskAssessment>, RepositoryError>;
+    /// Delete assessments created before the cutoff, returning the number deleted
+    async fn delete_old_assessments(
+        &self,
+        older_than: OffsetDateTime,
+    ) -> std::result::Result<u64, RepositoryError>;
+}
+
+fn database_error(err: sqlx::Error) -> RepositoryError {
+    RepositoryError::DatabaseError(err.to_string())
+}
+
+fn limit_param(limit: usize) -> i64 {
+    i64::try_from(limit).unwrap_or(i64::MAX)
 }
 
 pub struct PostgresSessionLocationRepository {
-    #[allow(dead_code)]
     pool: PgPool,
 }
 
@@ -68,38 +98,184 @@ impl PostgresSessionLocationRepository {
     pub fn new(pool: PgPool) -> Self {
         Self { pool }
     }
+
+    fn map_row(row: PgRow) -> std::result::Result<SessionLocation, sqlx::Error> {
+        let ip_address: IpNetwork = row.try_get("ip_address")?;
+
+        Ok(SessionLocation {
+            id: row.try_get("id")?,
+            session_id: row.try_get("session_id")?,
+            user_id: row.try_get("user_id")?,
+            ip_address: ip_address.ip(),
+            country_code: row.try_get("country_code")?,
+            region_code: row.try_get("region_code")?,
+            city: row.try_get("city")?,
+            timezone: row.try_get("timezone")?,
+            latitude: row.try_get("latitude")?,
+            longitude: row.try_get("longitude")?,
+            is_verified: row.try_get("is_verified")?,
+            is_suspicious: row.try_get("is_suspicious")?,
+            recorded_at: row.try_get("recorded_at")?,
+        })
+    }
 }
 
 #[async_trait]
 impl SessionLocationRepository for PostgresSessionLocationRepository {
+    #[instrument(
+        name = "session_location.repo.save_location",
+        skip_all,
+        level = "debug"
+    )]
     async fn save_location(
         &self,
-        _location: &SessionLocation,
+        location: &SessionLocation,
     ) -> std::result::Result<(), RepositoryError> {
-        // Implementation will be added later
-        Ok(())
+        observe("session_location.repo.save_location", async {
+            sqlx::query(
+                r#"
+                INSERT INTO session_locations (
+                    id, session_id, user_id, latitude, longitude, country_code,
+                    region_code, city, timezone, ip_address, recorded_at,
+                    is_verified, is_suspicious
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                "#,
+            )
+            .bind(location.id)
+            .bind(location.session_id)
+            .bind(location.user_id)
+            .bind(location.latitude)
+            .bind(location.longitude)
+            .bind(&location.country_code)
+            .bind(&location.region_code)
+            .bind(&location.city)
+            .bind(&location.timezone)
+            .bind(IpNetwork::from(location.ip_address))
+            .bind(location.recorded_at)
+            .bind(location.is_verified)
+            .bind(location.is_suspicious)
+            .execute(&self.pool)
+            .await
+            .map_err(database_error)?;
+
+            Ok(())
+        })
+        .await
     }
 
+    #[instrument(
+        name = "session_location.repo.get_locations_by_session_id",
+        skip_all,
+        level = "debug"
+    )]
     async fn get_locations_by_session_id(
         &self,
-        _session_id: Uuid,
+        session_id: Uuid,
     ) -> std::result::Result<Vec<SessionLocation>, RepositoryError> {
-        // Implementation will be added later
-        Ok(vec![])
+        observe("session_location.repo.get_locations_by_session_id", async {
+            let rows = sqlx::query(
+                r#"
+                SELECT id, session_id, user_id, latitude, longitude, country_code, region_code,
+                       city, timezone, ip_address, recorded_at, is_verified, is_suspicious
+                FROM session_locations
+                WHERE session_id = $1
+                ORDER BY recorded_at, id
+                "#,
+            )
+            .bind(session_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(database_error)?;
+
+            rows.into_iter()
+                .map(Self::map_row)
+                .collect::<Result<_, _>>()
+                .map_err(database_error)
+        })
+        .await
     }
 
+    #[instrument(
+        name = "session_location.repo.get_recent_locations_by_user_id",
+        skip_all,
+        level = "debug"
+    )]
     async fn get_recent_locations_by_user_id(
         &self,
-        _user_id: Uuid,
-        _limit: usize,
+        user_id: Uuid,
+        limit: usize,
     ) -> std::result::Result<Vec<SessionLocation>, RepositoryError> {
-        // Implementation will be added later
-        Ok(vec![])
+        observe(
+            "session_location.repo.get_recent_locations_by_user_id",
+            async {
+                let rows = sqlx::query(
+                    r#"
+                SELECT id, session_id, user_id, latitude, longitude, country_code, region_code,
+                       city, timezone, ip_address, recorded_at, is_verified, is_suspicious
+                FROM session_locations
+                WHERE user_id = $1
+                ORDER BY recorded_at DESC, id
+                LIMIT $2
+                "#,
+                )
+                .bind(user_id)
+                .bind(limit_param(limit))
+                .fetch_all(&self.pool)
+                .await
+                .map_err(database_error)?;
+
+                rows.into_iter()
+                    .map(Self::map_row)
+                    .collect::<Result<_, _>>()
+                    .map_err(database_error)
+            },
+        )
+        .await
     }
+
+    #[instrument(
+        name = "session_location.repo.delete_old_locations",
+        skip_all,
+        level = "debug"
+    )]
+    async fn delete_old_locations(
+        &self,
+        older_than: OffsetDateTime,
+    ) -> std::result::Result<u64, RepositoryError> {
+        observe("session_location.repo.delete_old_locations", async {
+            let result = sqlx::query("DELETE FROM session_locations WHERE recorded_at < $1")
+                .bind(older_than)
+                .execute(&self.pool)
+                .await
+                .map_err(database_error)?;
+
+            Ok(result.rows_affected())
+        })
+        .await
+    }
+}
+
+/// Shape of the `device_fingerprint` document
+#[derive(Debug, Serialize, Deserialize)]
+struct DeviceFingerprintDocument {
+    user_agent: String,
+    #[serde(default)]
+    browser: Option<String>,
+    #[serde(default)]
+    browser_version: Option<String>,
+    #[serde(default)]
+    os: Option<String>,
+    #[serde(default)]
+    os_version: Option<String>,
+    #[serde(default)]
+    device_type: Option<String>,
+    #[serde(default)]
+    device_vendor: Option<String>,
+    #[serde(default)]
+    device_model: Option<String>,
 }
 
 pub struct PostgresEnhancedFingerprintRepository {
-    #[allow(dead_code)]
     pool: PgPool,
 }
 
@@ -107,38 +283,178 @@ impl PostgresEnhancedFingerprintRepository {
     pub fn new(pool: PgPool) -> Self {
         Self { pool }
     }
+
+    fn map_row(row: PgRow) -> std::result::Result<EnhancedSessionFingerprint, sqlx::Error> {
+        let Json(device): Json<DeviceFingerprintDocument> = row.try_get("device_fingerprint")?;
+
+        Ok(EnhancedSessionFingerprint {
+            id: row.try_get("id")?,
+            session_id: row.try_get("session_id")?,
+            user_id: row.try_get("user_id")?,
+            user_agent: device.user_agent,
+            browser: device.browser,
+            browser_version: device.browser_version,
+            os: device.os,
+            os_version: device.os_version,
+            device_type: device.device_type,
+            device_vendor: device.device_vendor,
+            device_model: device.device_model,
+            canvas_fingerprint: row.try_get("canvas_fingerprint")?,
+            webgl_fingerprint: row.try_get("webgl_fingerprint")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
 }
 
 #[async_trait]
 impl EnhancedFingerprintRepository for PostgresEnhancedFingerprintRepository {
+    #[instrument(
+        name = "enhanced_fingerprint.repo.save_fingerprint",
+        skip_all,
+        level = "debug"
+    )]
     async fn save_fingerprint(
         &self,
-        _fingerprint: &EnhancedSessionFingerprint,
+        fingerprint: &EnhancedSessionFingerprint,
     ) -> std::result::Result<(), RepositoryError> {
-        // Implementation will be added later
-        Ok(())
+        observe("enhanced_fingerprint.repo.save_fingerprint", async {
+            let device = DeviceFingerprintDocument {
+                user_agent: fingerprint.user_agent.clone(),
+                browser: fingerprint.browser.clone(),
+                browser_version: fingerprint.browser_version.clone(),
+                os: fingerprint.os.clone(),
+                os_version: fingerprint.os_version.clone(),
+                device_type: fingerprint.device_type.clone(),
+                device_vendor: fingerprint.device_vendor.clone(),
+                device_model: fingerprint.device_model.clone(),
+            };
+
+            sqlx::query(
+                r#"
+                INSERT INTO enhanced_session_fingerprints (
+                    id, session_id, user_id, device_fingerprint, canvas_fingerprint,
+                    webgl_fingerprint, created_at, updated_at
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                ON CONFLICT (id) DO UPDATE
+                SET device_fingerprint = EXCLUDED.device_fingerprint,
+                    canvas_fingerprint = EXCLUDED.canvas_fingerprint,
+                    webgl_fingerprint = EXCLUDED.webgl_fingerprint,
+                    updated_at = EXCLUDED.updated_at
+                "#,
+            )
+            .bind(fingerprint.id)
+            .bind(fingerprint.session_id)
+            .bind(fingerprint.user_id)
+            .bind(Json(&device))
+            .bind(&fingerprint.canvas_fingerprint)
+            .bind(&fingerprint.webgl_fingerprint)
+            .bind(fingerprint.created_at)
+            .bind(fingerprint.updated_at)
+            .execute(&self.pool)
+            .await
+            .map_err(database_error)?;
+
+            Ok(())
+        })
+        .await
     }
 
+    #[instrument(
+        name = "enhanced_fingerprint.repo.get_fingerprint_by_session_id",
+        skip_all,
+        level = "debug"
+    )]
     async fn get_fingerprint_by_session_id(
         &self,
-        _session_id: Uuid,
+        session_id: Uuid,
     ) -> std::result::Result<Option<EnhancedSessionFingerprint>, RepositoryError> {
-        // Implementation will be added later
-        Ok(None)
+        observe(
+            "enhanced_fingerprint.repo.get_fingerprint_by_session_id",
+            async {
+                let row = sqlx::query(
+                    r#"
+                SELECT id, session_id, user_id, device_fingerprint, canvas_fingerprint,
+                       webgl_fingerprint, created_at, updated_at
+                FROM enhanced_session_fingerprints
+                WHERE session_id = $1
+                ORDER BY updated_at DESC
+                LIMIT 1
+                "#,
+                )
+                .bind(session_id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(database_error)?;
+
+                row.map(Self::map_row).transpose().map_err(database_error)
+            },
+        )
+        .await
     }
 
+    #[instrument(
+        name = "enhanced_fingerprint.repo.get_fingerprints_by_user_id",
+        skip_all,
+        level = "debug"
+    )]
     async fn get_fingerprints_by_user_id(
         &self,
-        _user_id: Uuid,
-        _limit: usize,
+        user_id: Uuid,
+        limit: usize,
     ) -> std::result::Result<Vec<EnhancedSessionFingerprint>, RepositoryError> {
-        // Implementation will be added later
-        Ok(vec![])
+        observe(
+            "enhanced_fingerprint.repo.get_fingerprints_by_user_id",
+            async {
+                let rows = sqlx::query(
+                    r#"
+                SELECT id, session_id, user_id, device_fingerprint, canvas_fingerprint,
+                       webgl_fingerprint, created_at, updated_at
+                FROM enhanced_session_fingerprints
+                WHERE user_id = $1
+                ORDER BY updated_at DESC, id
+                LIMIT $2
+                "#,
+                )
+                .bind(user_id)
+                .bind(limit_param(limit))
+                .fetch_all(&self.pool)
+                .await
+                .map_err(database_error)?;
+
+                rows.into_iter()
+                    .map(Self::map_row)
+                    .collect::<Result<_, _>>()
+                    .map_err(database_error)
+            },
+        )
+        .await
+    }
+
+    #[instrument(
+        name = "enhanced_fingerprint.repo.delete_old_fingerprints",
+        skip_all,
+        level = "debug"
+    )]
+    async fn delete_old_fingerprints(
+        &self,
+        older_than: OffsetDateTime,
+    ) -> std::result::Result<u64, RepositoryError> {
+        observe("enhanced_fingerprint.repo.delete_old_fingerprints", async {
+            let result =
+                sqlx::query("DELETE FROM enhanced_session_fingerprints WHERE created_at < $1")
+                    .bind(older_than)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(database_error)?;
+
+            Ok(result.rows_affected())
+        })
+        .await
     }
 }
 
 pub struct PostgresRiskAssessmentRepository {
-    #[allow(dead_code)]
     pool: PgPool,
 }
 
@@ -146,33 +462,152 @@ impl PostgresRiskAssessmentRepository {
     pub fn new(pool: PgPool) -> Self {
         Self { pool }
     }
+
+    fn map_row(row: PgRow) -> std::result::Result<SessionRiskAssessment, sqlx::Error> {
+        let Json(risk_factors): Json<Vec<String>> = row.try_get("risk_factors")?;
+
+        Ok(SessionRiskAssessment {
+            id: row.try_get("id")?,
+            session_id: row.try_get("session_id")?,
+            user_id: row.try_get("user_id")?,
+            risk_score: row.try_get("risk_score")?,
+            risk_factors,
+            is_flagged: row.try_get("is_flagged")?,
+            action_taken: row.try_get("action_taken")?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
 }
 
 #[async_trait]
 impl RiskAssessmentRepository for PostgresRiskAssessmentRepository {
+    #[instrument(
+        name = "risk_assessment.repo.save_assessment",
+        skip_all,
+        level = "debug"
+    )]
     async fn save_assessment(
         &self,
-        _assessment: &SessionRiskAssessment,
+        assessment: &SessionRiskAssessment,
     ) -> std::result::Result<(), RepositoryError> {
-        // Implementation will be added later
-        Ok(())
+        observe("risk_assessment.repo.save_assessment", async {
+            // The score column is DECIMAL(5,4); the bound FLOAT8 is rounded on assignment
+            sqlx::query(
+                r#"
+                INSERT INTO session_risk_assessments (
+                    id, session_id, user_id, created_at, risk_score, risk_factors,
+                    is_flagged, triggered_action, action_taken
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                "#,
+            )
+            .bind(assessment.id)
+            .bind(assessment.session_id)
+            .bind(assessment.user_id)
+            .bind(assessment.created_at)
+            .bind(assessment.risk_score)
+            .bind(Json(&assessment.risk_factors))
+            .bind(assessment.is_flagged)
+            .bind(assessment.action_taken.is_some())
+            .bind(&assessment.action_taken)
+            .execute(&self.pool)
+            .await
+            .map_err(database_error)?;
+
+            Ok(())
+        })
+        .await
     }
 
+    #[instrument(
+        name = "risk_assessment.repo.get_assessment_by_session_id",
+        skip_all,
+        level = "debug"
+    )]
     async fn get_assessment_by_session_id(
         &self,
-        _session_id: Uuid,
+        session_id: Uuid,
     ) -> std::result::Result<Option<SessionRiskAssessment>, RepositoryError> {
-        // Implementation will be added later
-        Ok(None)
+        observe("risk_assessment.repo.get_assessment_by_session_id", async {
+            let row = sqlx::query(
+                r#"
+                SELECT id, session_id, user_id, created_at, risk_score::FLOAT8 AS risk_score,
+                       risk_factors, is_flagged, action_taken
+                FROM session_risk_assessments
+                WHERE session_id = $1
+                ORDER BY created_at DESC
+                LIMIT 1
+                "#,
+            )
+            .bind(session_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(database_error)?;
+
+            row.map(Self::map_row).transpose().map_err(database_error)
+        })
+        .await
     }
 
+    #[instrument(
+        name = "risk_assessment.repo.get_assessments_by_risk_level",
+        skip_all,
+        level = "debug"
+    )]
     async fn get_assessments_by_risk_level(
         &self,
-        _risk_level: RiskLevel,
-        _from_date: SystemTime,
-        _limit: usize,
+        risk_level: RiskLevel,
+        from_date: OffsetDateTime,
+        limit: usize,
     ) -> std::result::Result<Vec<SessionRiskAssessment>, RepositoryError> {
-        // Implementation will be added later
-        Ok(vec![])
+        observe(
+            "risk_assessment.repo.get_assessments_by_risk_level",
+            async {
+                let (min_score, max_score) = risk_level.score_range();
+                let rows = sqlx::query(
+                    r#"
+                SELECT id, session_id, user_id, created_at, risk_score::FLOAT8 AS risk_score,
+                       risk_factors, is_flagged, action_taken
+                FROM session_risk_assessments
+                WHERE risk_score >= $1::NUMERIC AND risk_score < $2::NUMERIC AND created_at >= $3
+                ORDER BY created_at DESC, id
+                LIMIT $4
+                "#,
+                )
+                .bind(min_score)
+                .bind(max_score)
+                .bind(from_date)
+                .bind(limit_param(limit))
+                .fetch_all(&self.pool)
+                .await
+                .map_err(database_error)?;
+
+                rows.into_iter()
+                    .map(Self::map_row)
+                    .collect::<Result<_, _>>()
+                    .map_err(database_error)
+            },
+        )
+        .await
+    }
+
+    #[instrument(
+        name = "risk_assessment.repo.delete_old_assessments",
+        skip_all,
+        level = "debug"
+    )]
+    async fn delete_old_assessments(
+        &self,
+        older_than: OffsetDateTime,
+    ) -> std::result::Result<u64, RepositoryError> {
+        observe("risk_assessment.repo.delete_old_assessments", async {
+            let result = sqlx::query("DELETE FROM session_risk_assessments WHERE created_at < $1")
+                .bind(older_than)
+                .execute(&self.pool)
+                .await
+                .map_err(database_error)?;
+
+            Ok(result.rows_affected())
+        })
+        .await
     }
 }
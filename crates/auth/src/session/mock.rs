@@ -0,0 +1,590 @@
+//! In-memory [`SessionRepository`] for tests.
+//!
+//! This is the single mock shared by the unit tests in this crate and by
+//! downstream test crates (via the `test-support` feature). It implements the
+//! real trait directly, so any change to a repository signature breaks the
+//! build here instead of silently drifting.
+
+use async_trait::async_trait;
+use futures::stream::{self, BoxStream, StreamExt};
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use super::{
+    ACTIVE_TENANT_METADATA_KEY, CLIENT_BINDING_METADATA_KEY, IMPERSONATION_REASON_METADATA_KEY,
+    LAST_AUTH_METADATA_KEY, MFA_VERIFIED_METADATA_KEY, Session, SessionError, SessionExportFilter,
+    SessionFilter, SessionRepository, SessionRepositoryConfig, SessionWithLocation,
+};
+use crate::session::enhanced_security::SessionLocation;
+use crate::session::types::{
+    DeviceFingerprint, FactorAction, ImpersonationAction, MfaStatus, SessionInvalidationReason,
+    SessionKind, UserAgentDetails,
+};
+
+/// An entry the mock wrote to its in-memory audit log
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockAuditEntry {
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+    pub action: String,
+    pub details: Value,
+}
+
+/// Session repository backed by a `Vec<Session>` behind a mutex.
+///
+/// Sessions can be seeded or inspected directly through [`sessions`](Self::sessions),
+/// which is useful for simulating expiry or tampering in tests. Write
+/// operations can be made to fail with [`fail_on`](Self::fail_on).
+#[derive(Debug, Clone, Default)]
+pub struct MockSessionRepository {
+    sessions: Arc<Mutex<Vec<Session>>>,
+    failing: Arc<Mutex<HashSet<&'static str>>>,
+    audit_log: Arc<Mutex<Vec<MockAuditEntry>>>,
+    streamed: Arc<AtomicUsize>,
+    reconciliation_cursor: Arc<Mutex<Option<Uuid>>>,
+    locations: Arc<Mutex<HashMap<Uuid, Vec<SessionLocation>>>>,
+}
+
+// The mock must stay usable wherever the services expect a repository trait object.
+const _: fn(MockSessionRepository) -> Arc<dyn SessionRepository> = |repo| Arc::new(repo);
+
+impl MockSessionRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Locks and returns the stored sessions for inspection or modification.
+    pub fn sessions(&self) -> MutexGuard<'_, Vec<Session>> {
+        self.sessions
+            .lock()
+            .expect("mock session store lock poisoned")
+    }
+
+    /// Stores a session as-is, bypassing `create_session`.
+    pub fn insert(&self, session: Session) {
+        self.sessions().push(session);
+    }
+
+    /// Makes the repository method named `operation` fail with a database error.
+    ///
+    /// Supported for the MFA, tenant, authentication, metadata and impersonation
+    /// updates and for the reads and writes of the session reconciliation.
+    pub fn fail_on(&self, operation: &'static str) {
+        self.failing
+            .lock()
+            .expect("mock failure set lock poisoned")
+            .insert(operation);
+    }
+
+    /// Audit entries written so far, oldest first.
+    pub fn audit_entries(&self) -> Vec<MockAuditEntry> {
+        self.audit_log
+            .lock()
+            .expect("mock audit log lock poisoned")
+            .clone()
+    }
+
+    /// Records a location of a session, as the enhanced session security does.
+    pub fn record_location(&self, location: SessionLocation) {
+        self.locations
+            .lock()
+            .expect("mock location store lock poisoned")
+            .entry(location.session_id)
+            .or_default()
+            .push(location);
+    }
+
+    /// Number of sessions read by [`stream_sessions`](SessionRepository::stream_sessions) so far.
+    pub fn streamed_sessions(&self) -> usize {
+        self.streamed.load(Ordering::SeqCst)
+    }
+
+    /// Appends an audit entry for a valid session, adding the impersonator to the details
+    fn audit(&self, id: Uuid, action: &str, mut details: Value) -> Result<(), SessionError> {
+        let session = self
+            .sessions()
+            .iter()
+            .find(|s| s.id == id && s.is_valid)
+            .cloned()
+            .ok_or(SessionError::NotFound)?;
+        if let (Some(admin_id), Value::Object(map)) = (session.impersonated_by(), &mut details) {
+            map.insert("impersonated_by".to_string(), admin_id.to_string().into());
+        }
+        self.audit_log
+            .lock()
+            .expect("mock audit log lock poisoned")
+            .push(MockAuditEntry {
+                session_id: id,
+                user_id: session.user_id,
+                action: action.to_string(),
+                details,
+            });
+        Ok(())
+    }
+
+    fn check_failure(&self, operation: &'static str) -> Result<(), SessionError> {
+        let failing = self.failing.lock().expect("mock failure set lock poisoned");
+        if failing.contains(operation) {
+            return Err(SessionError::Database(sqlx::Error::PoolTimedOut));
+        }
+        Ok(())
+    }
+
+    fn with_session<F>(&self, id: Uuid, update: F) -> Result<(), SessionError>
+    where
+        F: FnOnce(&mut Session),
+    {
+        let mut sessions = self.sessions();
+        let session = sessions
+            .iter_mut()
+            .find(|s| s.id == id)
+            .ok_or(SessionError::NotFound)?;
+        update(session);
+        Ok(())
+    }
+
+    fn set_metadata(&self, id: Uuid, key: &str, value: Value) -> Result<(), SessionError> {
+        let mut sessions = self.sessions();
+        let session = sessions
+            .iter_mut()
+            .find(|s| s.id == id && s.is_valid)
+            .ok_or(SessionError::NotFound)?;
+        let mut metadata = match session.metadata.take() {
+            Some(Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        };
+        metadata.insert(key.to_string(), value);
+        session.metadata = Some(Value::Object(metadata));
+        Ok(())
+    }
+
+    fn invalidate_where<P>(&self, predicate: P, reason: SessionInvalidationReason) -> u64
+    where
+        P: Fn(&Session) -> bool,
+    {
+        let mut sessions = self.sessions();
+        let mut count = 0;
+        for session in sessions.iter_mut().filter(|s| predicate(s)) {
+            session.is_valid = false;
+            session.invalidated_reason = Some(reason.clone());
+            count += 1;
+        }
+        count
+    }
+}
+
+fn matches_export_filter(session: &Session, filter: &SessionExportFilter) -> bool {
+    session.tenant_id() == Some(filter.tenant_id)
+        && matches_filter(session, &filter.status)
+        && filter
+            .user_id
+            .is_none_or(|user_id| session.user_id == user_id)
+        && filter
+            .created_after
+            .is_none_or(|created_after| session.created_at >= created_after)
+}
+
+fn matches_filter(session: &Session, filter: &SessionFilter) -> bool {
+    match filter {
+        SessionFilter::All => true,
+        SessionFilter::Active => session.is_valid,
+        SessionFilter::Inactive => !session.is_valid,
+    }
+}
+
+#[async_trait]
+impl SessionRepository for MockSessionRepository {
+    async fn create_session(
+        &self,
+        user_id: Uuid,
+        token_hash: String,
+        expires_at: OffsetDateTime,
+        device_id: Option<String>,
+        device_fingerprint: Option<DeviceFingerprint>,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+        user_agent_details: Option<UserAgentDetails>,
+        region: Option<String>,
+        metadata: Option<Value>,
+    ) -> Result<Session, SessionError> {
+        let now = OffsetDateTime::now_utc();
+        let session = Session {
+            id: Uuid::new_v4(),
+            user_id,
+            kind: SessionKind::for_user(user_id),
+            token_hash,
+            previous_token_hash: None,
+            token_rotation_at: None,
+            expires_at,
+            created_at: now,
+            last_activity_at: now,
+            last_activity_update_at: None,
+            ip_address,
+            user_agent,
+            user_agent_details,
+            region,
+            device_id,
+            device_fingerprint,
+            is_valid: true,
+            invalidated_reason: None,
+            metadata,
+            mfa_status: MfaStatus::None,
+        };
+
+        self.insert(session.clone());
+        Ok(session)
+    }
+
+    async fn get_session(&self, id: Uuid) -> Result<Option<Session>, SessionError> {
+        Ok(self.sessions().iter().find(|s| s.id == id).cloned())
+    }
+
+    async fn get_session_by_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<Session>, SessionError> {
+        Ok(self
+            .sessions()
+            .iter()
+            .find(|s| s.token_hash == token_hash)
+            .cloned())
+    }
+
+    async fn get_user_sessions(
+        &self,
+        user_id: Uuid,
+        filter: SessionFilter,
+    ) -> Result<Vec<Session>, SessionError> {
+        Ok(self
+            .sessions()
+            .iter()
+            .filter(|s| s.user_id == user_id && matches_filter(s, &filter))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_user_sessions_with_location(
+        &self,
+        user_id: Uuid,
+        filter: SessionFilter,
+        current_session_id: Option<Uuid>,
+    ) -> Result<Vec<SessionWithLocation>, SessionError> {
+        let sessions = self.get_user_sessions(user_id, filter).await?;
+        let locations = self
+            .locations
+            .lock()
+            .expect("mock location store lock poisoned");
+        Ok(sessions
+            .into_iter()
+            .map(|session| SessionWithLocation {
+                location: locations
+                    .get(&session.id)
+                    .and_then(|recorded| recorded.iter().max_by_key(|l| l.recorded_at))
+                    .cloned(),
+                is_current: Some(session.id) == current_session_id,
+                session,
+            })
+            .collect())
+    }
+
+    async fn update_session_activity(&self, id: Uuid) -> Result<(), SessionError> {
+        let now = OffsetDateTime::now_utc();
+        self.with_session(id, |session| {
+            session.last_activity_at = now;
+            session.last_activity_update_at = Some(now);
+        })
+    }
+
+    async fn invalidate_session(
+        &self,
+        id: Uuid,
+        reason: SessionInvalidationReason,
+    ) -> Result<(), SessionError> {
+        self.with_session(id, |session| {
+            session.is_valid = false;
+            session.invalidated_reason = Some(reason);
+        })
+    }
+
+    async fn invalidate_all_user_sessions(
+        &self,
+        user_id: Uuid,
+        reason: SessionInvalidationReason,
+    ) -> Result<u64, SessionError> {
+        Ok(self.invalidate_where(|s| s.user_id == user_id && s.is_valid, reason))
+    }
+
+    async fn invalidate_sessions_by_filter(
+        &self,
+        filter: SessionFilter,
+        reason: SessionInvalidationReason,
+    ) -> Result<u64, SessionError> {
+        Ok(self.invalidate_where(|s| matches_filter(s, &filter), reason))
+    }
+
+    async fn invalidate_sessions_by_ip(
+        &self,
+        ip_address: &str,
+        reason: SessionInvalidationReason,
+    ) -> Result<u64, SessionError> {
+        Ok(self.invalidate_where(
+            |s| s.ip_address.as_deref() == Some(ip_address) && s.is_valid,
+            reason,
+        ))
+    }
+
+    async fn invalidate_sessions_by_region(
+        &self,
+        region: &str,
+        tenant_id: Option<Uuid>,
+        include_unknown: bool,
+        reason: SessionInvalidationReason,
+    ) -> Result<u64, SessionError> {
+        Ok(self.invalidate_where(
+            |s| {
+                s.is_valid
+                    && s.in_region(region, include_unknown)
+                    && tenant_id.is_none_or(|tenant_id| s.tenant_id() == Some(tenant_id))
+            },
+            reason,
+        ))
+    }
+
+    async fn invalidate_tenant_sessions(
+        &self,
+        tenant_id: Uuid,
+        reason: SessionInvalidationReason,
+    ) -> Result<u64, SessionError> {
+        Ok(self.invalidate_where(|s| s.is_valid && s.tenant_id() == Some(tenant_id), reason))
+    }
+
+    async fn rotate_session_token(
+        &self,
+        id: Uuid,
+        new_token_hash: String,
+    ) -> Result<(), SessionError> {
+        self.with_session(id, |session| {
+            session.previous_token_hash =
+                Some(std::mem::replace(&mut session.token_hash, new_token_hash));
+            session.token_rotation_at = Some(OffsetDateTime::now_utc());
+        })
+    }
+
+    async fn cleanup_expired_sessions(&self) -> Result<u64, SessionError> {
+        let now = OffsetDateTime::now_utc();
+        // Abandoned guest sessions go after the default guest retention
+        let guest_cutoff = now - SessionRepositoryConfig::default().guest_session_retention;
+        let mut sessions = self.sessions();
+        let before = sessions.len();
+        sessions
+            .retain(|s| s.expires_at > now && !(s.is_guest() && s.last_activity_at < guest_cutoff));
+        Ok((before - sessions.len()) as u64)
+    }
+
+    async fn update_mfa_status(&self, id: Uuid, status: MfaStatus) -> Result<(), SessionError> {
+        self.check_failure("update_mfa_status")?;
+        let mut sessions = self.sessions();
+        let session = sessions
+            .iter_mut()
+            .find(|s| s.id == id && s.is_valid)
+            .ok_or(SessionError::NotFound)?;
+        session.mfa_status = status;
+        Ok(())
+    }
+
+    async fn set_active_tenant(&self, id: Uuid, tenant_id: Uuid) -> Result<(), SessionError> {
+        self.check_failure("set_active_tenant")?;
+        let previous = self
+            .sessions()
+            .iter()
+            .find(|s| s.id == id)
+            .and_then(Session::active_tenant_id);
+        self.set_metadata(id, ACTIVE_TENANT_METADATA_KEY, tenant_id.to_string().into())?;
+        #[allow(clippy::disallowed_methods)]
+        let details = json!({ "from_tenant_id": previous, "to_tenant_id": tenant_id });
+        self.audit(id, "TENANT_SWITCHED", details)
+    }
+
+    async fn record_authentication(
+        &self,
+        id: Uuid,
+        at: OffsetDateTime,
+    ) -> Result<(), SessionError> {
+        self.check_failure("record_authentication")?;
+        self.set_metadata(id, LAST_AUTH_METADATA_KEY, at.unix_timestamp().into())
+    }
+
+    async fn record_client_binding(
+        &self,
+        id: Uuid,
+        binding_hash: &str,
+    ) -> Result<(), SessionError> {
+        self.check_failure("record_client_binding")?;
+        self.set_metadata(id, CLIENT_BINDING_METADATA_KEY, binding_hash.into())
+    }
+
+    async fn merge_session_metadata(&self, id: Uuid, patch: Value) -> Result<(), SessionError> {
+        self.check_failure("merge_session_metadata")?;
+        let mut sessions = self.sessions();
+        let session = sessions
+            .iter_mut()
+            .find(|s| s.id == id && s.is_valid)
+            .ok_or(SessionError::NotFound)?;
+        let mut metadata = match session.metadata.take() {
+            Some(Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        };
+        if let Value::Object(patch) = patch {
+            metadata.extend(patch);
+        }
+        session.metadata = Some(Value::Object(metadata));
+        Ok(())
+    }
+
+    async fn record_mfa_verification(
+        &self,
+        id: Uuid,
+        at: OffsetDateTime,
+    ) -> Result<(), SessionError> {
+        self.check_failure("record_mfa_verification")?;
+        self.set_metadata(id, MFA_VERIFIED_METADATA_KEY, at.unix_timestamp().into())?;
+        self.set_metadata(id, LAST_AUTH_METADATA_KEY, at.unix_timestamp().into())?;
+        #[allow(clippy::disallowed_methods)]
+        let details = json!({ "mfa_status": MfaStatus::Verified });
+        self.audit(id, "MFA_VERIFIED", details)
+    }
+
+    async fn record_factor_event(
+        &self,
+        id: Uuid,
+        action: FactorAction,
+        factor: &str,
+    ) -> Result<(), SessionError> {
+        self.check_failure("record_factor_event")?;
+        let details = serde_json::Map::from_iter([("factor".to_string(), factor.into())]);
+        self.audit(id, action.as_str(), Value::Object(details))
+    }
+
+    async fn record_impersonation_event(
+        &self,
+        id: Uuid,
+        action: ImpersonationAction,
+    ) -> Result<(), SessionError> {
+        self.check_failure("record_impersonation_event")?;
+        let session = self
+            .sessions()
+            .iter()
+            .find(|s| s.id == id)
+            .cloned()
+            .ok_or(SessionError::NotFound)?;
+        let admin_id = session.impersonated_by().ok_or(SessionError::NotFound)?;
+        let reason = session
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get(IMPERSONATION_REASON_METADATA_KEY))
+            .cloned()
+            .unwrap_or(Value::Null);
+
+        let mut audit_log = self.audit_log.lock().expect("mock audit log lock poisoned");
+        #[allow(clippy::disallowed_methods)]
+        let details = json!({
+            "impersonated_by": admin_id,
+            "target_user_id": session.user_id,
+            "reason": reason,
+        });
+        for user_id in [session.user_id, admin_id] {
+            audit_log.push(MockAuditEntry {
+                session_id: id,
+                user_id,
+                action: action.as_str().to_string(),
+                details: details.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    fn stream_sessions(
+        &self,
+        filter: SessionExportFilter,
+    ) -> BoxStream<'static, Result<Session, SessionError>> {
+        if let Err(err) = self.check_failure("stream_sessions") {
+            return stream::iter([Err(err)]).boxed();
+        }
+
+        // Reads lazily page by page like the Postgres cursor, so tests can
+        // observe how far a consumer got before dropping the stream
+        let sessions = Arc::clone(&self.sessions);
+        let streamed = Arc::clone(&self.streamed);
+        let page_size = filter.page_size.max(1) as usize;
+        let start = (None::<(OffsetDateTime, Uuid)>, filter.limit);
+
+        stream::unfold(start, move |(cursor, remaining)| {
+            let sessions = Arc::clone(&sessions);
+            let streamed = Arc::clone(&streamed);
+            let filter = filter.clone();
+            async move {
+                if remaining == 0 {
+                    return None;
+                }
+
+                let limit = page_size.min(usize::try_from(remaining).unwrap_or(usize::MAX));
+                let mut page: Vec<Session> = sessions
+                    .lock()
+                    .expect("mock session store lock poisoned")
+                    .iter()
+                    .filter(|s| matches_export_filter(s, &filter))
+                    .filter(|s| cursor.is_none_or(|cursor| (s.created_at, s.id) > cursor))
+                    .cloned()
+                    .collect();
+                page.sort_by_key(|s| (s.created_at, s.id));
+                page.truncate(limit);
+                streamed.fetch_add(page.len(), Ordering::SeqCst);
+
+                let last = page.last()?;
+                let next_cursor = (last.created_at, last.id);
+                let remaining = remaining - page.len() as u64;
+                let page = stream::iter(page.into_iter().map(Ok));
+                Some((page, (Some(next_cursor), remaining)))
+            }
+        })
+        .flatten()
+        .boxed()
+    }
+
+    async fn valid_sessions_after(
+        &self,
+        after: Option<Uuid>,
+        limit: u32,
+    ) -> Result<Vec<Session>, SessionError> {
+        self.check_failure("valid_sessions_after")?;
+        let mut page: Vec<Session> = self
+            .sessions()
+            .iter()
+            .filter(|s| s.is_valid && after.is_none_or(|after| s.id > after))
+            .cloned()
+            .collect();
+        page.sort_by_key(|s| s.id);
+        page.truncate(limit as usize);
+        Ok(page)
+    }
+
+    async fn reconciliation_cursor(&self) -> Result<Option<Uuid>, SessionError> {
+        Ok(*self
+            .reconciliation_cursor
+            .lock()
+            .expect("mock reconciliation cursor lock poisoned"))
+    }
+
+    async fn save_reconciliation_cursor(&self, cursor: Option<Uuid>) -> Result<(), SessionError> {
+        self.check_failure("save_reconciliation_cursor")?;
+        *self
+            .reconciliation_cursor
+            .lock()
+            .expect("mock reconciliation cursor lock poisoned") = cursor;
+        Ok(())
+    }
+}
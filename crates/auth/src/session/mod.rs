@@ -1,25 +1,59 @@
 pub mod enhanced_security;
+#[cfg(any(test, feature = "test-support"))]
+pub mod mock;
 pub mod types;
 
 use async_trait::async_trait;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
 use serde_json::Value;
+use sqlx::Row;
+use sqlx::postgres::PgRow;
 use sqlx::types::ipnetwork::IpNetwork;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant};
 use time::OffsetDateTime;
+use tracing::instrument;
 use uuid::Uuid;
 
-use crate::session::types::{DeviceFingerprint, MfaStatus, SessionInvalidationReason};
-
-const _METRIC_PREFIX: &str = "auth.session";
-const METRIC_CREATE: &str = "create";
-const METRIC_GET: &str = "get";
-const METRIC_GET_BY_TOKEN: &str = "get_by_token";
-const METRIC_GET_USER: &str = "get_user";
-const METRIC_UPDATE_ACTIVITY: &str = "update_activity";
-const METRIC_INVALIDATE: &str = "invalidate";
-const METRIC_ROTATE_TOKEN: &str = "rotate_token";
-const METRIC_CLEANUP: &str = "cleanup";
-const METRIC_UPDATE_MFA: &str = "update_mfa_status";
+use crate::repository::instrumentation::{self, observe_within};
+use crate::repository::timeout::{OperationTimedOut, OperationTimeouts, within};
+use crate::session::enhanced_security::SessionLocation;
+use crate::session::types::{
+    DeviceFingerprint, DeviceType, FactorAction, ImpersonationAction, MfaStatus,
+    SessionInvalidationReason, SessionKind, UserAgentDetails,
+};
+use crate::utils::batch::{BatchConfig, run_in_batches};
+use crate::utils::bounded_json::bound_stored;
+use acci_core::datetime;
+
+// Operation names of the repository metrics, see `crate::repository::instrumentation`
+const METRIC_CREATE: &str = "session.repo.create_session";
+const METRIC_GET: &str = "session.repo.get_session";
+const METRIC_GET_BY_TOKEN: &str = "session.repo.get_session_by_token";
+const METRIC_GET_USER: &str = "session.repo.get_user_sessions";
+const METRIC_GET_USER_WITH_LOCATION: &str = "session.repo.get_user_sessions_with_location";
+const METRIC_UPDATE_ACTIVITY: &str = "session.repo.update_session_activity";
+const METRIC_INVALIDATE: &str = "session.repo.invalidate_session";
+const METRIC_INVALIDATE_USER: &str = "session.repo.invalidate_all_user_sessions";
+const METRIC_INVALIDATE_FILTER: &str = "session.repo.invalidate_sessions_by_filter";
+const METRIC_INVALIDATE_IP: &str = "session.repo.invalidate_sessions_by_ip";
+const METRIC_INVALIDATE_REGION: &str = "session.repo.invalidate_sessions_by_region";
+const METRIC_INVALIDATE_TENANT: &str = "session.repo.invalidate_tenant_sessions";
+const METRIC_ROTATE_TOKEN: &str = "session.repo.rotate_session_token";
+const METRIC_CLEANUP: &str = "session.repo.cleanup_expired_sessions";
+const METRIC_UPDATE_MFA: &str = "session.repo.update_mfa_status";
+const METRIC_SET_ACTIVE_TENANT: &str = "session.repo.set_active_tenant";
+const METRIC_RECORD_AUTHENTICATION: &str = "session.repo.record_authentication";
+const METRIC_RECORD_MFA_VERIFICATION: &str = "session.repo.record_mfa_verification";
+const METRIC_RECORD_CLIENT_BINDING: &str = "session.repo.record_client_binding";
+const METRIC_MERGE_METADATA: &str = "session.repo.merge_session_metadata";
+const METRIC_RECORD_IMPERSONATION: &str = "session.repo.record_impersonation_event";
+const METRIC_RECORD_FACTOR_EVENT: &str = "session.repo.record_factor_event";
+/// A page of [`SessionRepository::stream_sessions`]
+const METRIC_STREAM: &str = "session.repo.stream_sessions";
+const METRIC_RECONCILIATION_PAGE: &str = "session.repo.valid_sessions_after";
+
+/// Name of the session reconciliation in `session_reconciliation_progress`
+const RECONCILIATION_JOB: &str = "user_state";
 
 // Mock implementations when metrics feature is not enabled
 #[cfg(not(feature = "metrics"))]
@@ -49,34 +83,71 @@ mod metrics_mock {
 
 // Explicitly import macros when metrics is not enabled
 
-// Hilfsfunktion zur Konvertierung von SystemTime zu OffsetDateTime
-fn system_time_to_offset_date_time(time: SystemTime) -> OffsetDateTime {
-    let unix_time = time
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap_or_default();
-    OffsetDateTime::from_unix_timestamp(unix_time.as_secs() as i64)
-        .unwrap_or(OffsetDateTime::UNIX_EPOCH)
-        .saturating_add(time::Duration::nanoseconds(unix_time.subsec_nanos() as i64))
-}
-
 // Hilfsfunktion zur Konvertierung von String zu IpNetwork
-fn string_to_ip_network(ip_str: Option<String>) -> Option<IpNetwork> {
+pub(crate) fn string_to_ip_network(ip_str: Option<String>) -> Option<IpNetwork> {
     ip_str.and_then(|s| s.parse::<IpNetwork>().ok())
 }
 
+/// Session metadata key recording whether the user asked to stay signed in
+pub const PERSISTENT_SESSION_METADATA_KEY: &str = "persistent";
+
+/// Session metadata key holding the tenant the user currently acts as
+pub const ACTIVE_TENANT_METADATA_KEY: &str = "active_tenant_id";
+
+/// Session metadata key holding the time of the last full authentication (Unix seconds)
+pub const LAST_AUTH_METADATA_KEY: &str = "last_auth_at";
+
+/// Session metadata key holding the time the second factor was completed (Unix seconds)
+pub const MFA_VERIFIED_METADATA_KEY: &str = "mfa_verified_at";
+
+/// Session metadata key holding the admin acting through an impersonation session
+pub const IMPERSONATED_BY_METADATA_KEY: &str = "impersonated_by";
+
+/// Session metadata key holding the stated reason for an impersonation
+pub const IMPERSONATION_REASON_METADATA_KEY: &str = "reason";
+
+/// Session metadata key holding the hash of the client characteristics the session is bound to
+pub const CLIENT_BINDING_METADATA_KEY: &str = "client_binding";
+
+/// Session metadata keys managed by the service, which guests cannot set
+pub(crate) const RESERVED_METADATA_KEYS: &[&str] = &[
+    PERSISTENT_SESSION_METADATA_KEY,
+    ACTIVE_TENANT_METADATA_KEY,
+    LAST_AUTH_METADATA_KEY,
+    MFA_VERIFIED_METADATA_KEY,
+    IMPERSONATED_BY_METADATA_KEY,
+    IMPERSONATION_REASON_METADATA_KEY,
+    CLIENT_BINDING_METADATA_KEY,
+    "tenant_id",
+];
+
 #[derive(Debug, Clone)]
 pub struct Session {
     pub id: Uuid,
+    /// The session's user, nil for guest sessions
     pub user_id: Uuid,
+    /// Whether the session belongs to a user or to a visitor not logged in yet
+    pub kind: SessionKind,
     pub token_hash: String,
     pub previous_token_hash: Option<String>,
-    pub token_rotation_at: Option<SystemTime>,
-    pub expires_at: SystemTime,
-    pub created_at: SystemTime,
-    pub last_activity_at: SystemTime,
-    pub last_activity_update_at: Option<SystemTime>,
+    pub token_rotation_at: Option<OffsetDateTime>,
+    pub expires_at: OffsetDateTime,
+    pub created_at: OffsetDateTime,
+    pub last_activity_at: OffsetDateTime,
+    pub last_activity_update_at: Option<OffsetDateTime>,
     pub ip_address: Option<String>,
     pub user_agent: Option<String>,
+    /// Browser, operating system and device class parsed from `user_agent` at creation
+    ///
+    /// Absent for sessions created before they were recorded; use
+    /// [`SessionService::user_agent_details`](crate::services::session::SessionService::user_agent_details)
+    /// to parse those on access.
+    pub user_agent_details: Option<UserAgentDetails>,
+    /// Region of the instance that created the session
+    ///
+    /// Absent for sessions created before regions were recorded or by an
+    /// instance without a configured region.
+    pub region: Option<String>,
     pub device_id: Option<String>,
     pub device_fingerprint: Option<DeviceFingerprint>,
     pub is_valid: bool,
@@ -85,6 +156,105 @@ pub struct Session {
     pub mfa_status: MfaStatus,
 }
 
+impl Session {
+    /// Whether this is the session of a visitor not logged in yet
+    pub fn is_guest(&self) -> bool {
+        self.kind == SessionKind::Guest
+    }
+
+    /// Whether the session was created in `region`
+    ///
+    /// Sessions without a region only match with `include_unknown`.
+    pub fn in_region(&self, region: &str, include_unknown: bool) -> bool {
+        match &self.region {
+            Some(session_region) => session_region == region,
+            None => include_unknown,
+        }
+    }
+
+    /// Whether the session was created with "remember me"
+    ///
+    /// Sessions created before the flag was recorded are treated as non-persistent.
+    pub fn is_persistent(&self) -> bool {
+        self.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get(PERSISTENT_SESSION_METADATA_KEY))
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+    }
+
+    /// The tenant the user currently acts as, if one has been selected
+    pub fn active_tenant_id(&self) -> Option<Uuid> {
+        self.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get(ACTIVE_TENANT_METADATA_KEY))
+            .and_then(Value::as_str)
+            .and_then(|value| Uuid::parse_str(value).ok())
+    }
+
+    /// The tenant the session is bound to
+    ///
+    /// The active tenant, falling back to the tenant recorded at login.
+    pub fn tenant_id(&self) -> Option<Uuid> {
+        self.active_tenant_id().or_else(|| {
+            self.metadata
+                .as_ref()
+                .and_then(|metadata| metadata.get("tenant_id"))
+                .and_then(Value::as_str)
+                .and_then(|value| Uuid::parse_str(value).ok())
+        })
+    }
+
+    /// When the user last fully authenticated in this session
+    ///
+    /// Set at login and refreshed by re-authentication and MFA completion.
+    /// Sessions created before it was recorded fall back to their creation time.
+    pub fn last_auth_at(&self) -> OffsetDateTime {
+        self.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get(LAST_AUTH_METADATA_KEY))
+            .and_then(Value::as_i64)
+            .and_then(|secs| datetime::from_unix_seconds(secs).ok())
+            .unwrap_or(self.created_at)
+    }
+
+    /// The admin acting as the session's user, if this is an impersonation session
+    pub fn impersonated_by(&self) -> Option<Uuid> {
+        self.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get(IMPERSONATED_BY_METADATA_KEY))
+            .and_then(Value::as_str)
+            .and_then(|value| Uuid::parse_str(value).ok())
+    }
+
+    /// When the second factor of this session was completed, if it was
+    pub fn mfa_verified_at(&self) -> Option<OffsetDateTime> {
+        self.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get(MFA_VERIFIED_METADATA_KEY))
+            .and_then(Value::as_i64)
+            .and_then(|secs| datetime::from_unix_seconds(secs).ok())
+    }
+
+    /// Hash of the client characteristics the session is bound to, if it is bound
+    pub fn client_binding(&self) -> Option<&str> {
+        self.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get(CLIENT_BINDING_METADATA_KEY))
+            .and_then(Value::as_str)
+    }
+}
+
+/// A session with the location recorded last for it, as listed to its user
+#[derive(Debug, Clone)]
+pub struct SessionWithLocation {
+    pub session: Session,
+    /// `None` if no location was recorded for the session
+    pub location: Option<SessionLocation>,
+    /// Whether this is the session the listing was requested with
+    pub is_current: bool,
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum SessionFilter {
     All,
@@ -92,6 +262,220 @@ pub enum SessionFilter {
     Inactive,
 }
 
+/// Selection of the sessions of a tenant for an export
+#[derive(Debug, Clone)]
+pub struct SessionExportFilter {
+    /// Tenant the sessions are bound to, see [`Session::tenant_id`]
+    pub tenant_id: Uuid,
+    pub status: SessionFilter,
+    pub user_id: Option<Uuid>,
+    /// Only sessions created at or after this time
+    pub created_after: Option<OffsetDateTime>,
+    /// Maximum number of sessions returned
+    pub limit: u64,
+    /// Number of sessions read per query
+    pub page_size: u32,
+}
+
+impl SessionExportFilter {
+    /// All sessions of `tenant_id`, up to 100 000
+    pub fn new(tenant_id: Uuid) -> Self {
+        Self {
+            tenant_id,
+            status: SessionFilter::All,
+            user_id: None,
+            created_after: None,
+            limit: 100_000,
+            page_size: 500,
+        }
+    }
+}
+
+/// Keyset-paginated page of the sessions of a tenant, see [`SessionRepository::stream_sessions`]
+const EXPORT_PAGE_QUERY: &str = r#"
+    SELECT
+        id, user_id, token_hash, previous_token_hash, token_rotation_at,
+        expires_at, created_at, last_activity_at, last_activity_update_at,
+        ip_address, user_agent, browser, browser_version, os, device_type, region,
+        device_id, device_fingerprint,
+        is_valid, invalidated_reason::text AS invalidated_reason, metadata,
+        mfa_status::text AS mfa_status, kind
+    FROM sessions
+    WHERE COALESCE(metadata->>'active_tenant_id', metadata->>'tenant_id') = $1
+    AND ($2::boolean IS NULL OR is_valid = $2)
+    AND ($3::uuid IS NULL OR user_id = $3)
+    AND ($4::timestamptz IS NULL OR created_at >= $4)
+    AND ($5::timestamptz IS NULL OR (created_at, id) > ($5, $6::uuid))
+    ORDER BY created_at, id
+    LIMIT $7
+"#;
+
+/// Sessions of a user, newest first; served by `idx_sessions_user_created`
+pub const USER_SESSIONS_QUERY: &str = r#"
+    SELECT
+        id, user_id, token_hash, previous_token_hash, token_rotation_at,
+        expires_at, created_at, last_activity_at, last_activity_update_at,
+        ip_address, user_agent, browser, browser_version, os, device_type, region,
+        device_id, device_fingerprint,
+        is_valid, invalidated_reason::text AS invalidated_reason, metadata,
+        mfa_status::text AS mfa_status, kind
+    FROM sessions
+    WHERE user_id = $1
+    ORDER BY created_at DESC
+"#;
+
+/// Valid sessions of a user, newest first; served by the partial index
+/// `idx_sessions_user_valid_created`
+///
+/// The `is_valid` condition is spelled out rather than bound as a parameter,
+/// otherwise the planner cannot prove that the partial index applies.
+pub const VALID_USER_SESSIONS_QUERY: &str = r#"
+    SELECT
+        id, user_id, token_hash, previous_token_hash, token_rotation_at,
+        expires_at, created_at, last_activity_at, last_activity_update_at,
+        ip_address, user_agent, browser, browser_version, os, device_type, region,
+        device_id, device_fingerprint,
+        is_valid, invalidated_reason::text AS invalidated_reason, metadata,
+        mfa_status::text AS mfa_status, kind
+    FROM sessions
+    WHERE user_id = $1 AND is_valid = true
+    ORDER BY created_at DESC
+"#;
+
+/// Invalid sessions of a user, newest first; served by `idx_sessions_user_created`
+pub const INVALID_USER_SESSIONS_QUERY: &str = r#"
+    SELECT
+        id, user_id, token_hash, previous_token_hash, token_rotation_at,
+        expires_at, created_at, last_activity_at, last_activity_update_at,
+        ip_address, user_agent, browser, browser_version, os, device_type, region,
+        device_id, device_fingerprint,
+        is_valid, invalidated_reason::text AS invalidated_reason, metadata,
+        mfa_status::text AS mfa_status, kind
+    FROM sessions
+    WHERE user_id = $1 AND is_valid = false
+    ORDER BY created_at DESC
+"#;
+
+/// Sessions of a user with the location recorded last for each, newest first;
+/// served by `idx_sessions_user_created` and `idx_session_locations_session`
+///
+/// `$2` filters by validity unless null, `$3` is the ID of the session the
+/// listing is requested with. The location columns are null for sessions
+/// without a recorded location.
+pub const USER_SESSIONS_WITH_LOCATION_QUERY: &str = r#"
+    SELECT
+        s.id, s.user_id, s.token_hash, s.previous_token_hash, s.token_rotation_at,
+        s.expires_at, s.created_at, s.last_activity_at, s.last_activity_update_at,
+        s.ip_address, s.user_agent, s.browser, s.browser_version, s.os, s.device_type,
+        s.region, s.device_id, s.device_fingerprint,
+        s.is_valid, s.invalidated_reason::text AS invalidated_reason, s.metadata,
+        s.mfa_status::text AS mfa_status, s.kind,
+        COALESCE(s.id = $3, false) AS is_current,
+        l.id AS location_id, l.ip_address AS location_ip_address,
+        l.country_code AS location_country_code, l.region_code AS location_region_code,
+        l.city AS location_city, l.timezone AS location_timezone,
+        l.latitude AS location_latitude, l.longitude AS location_longitude,
+        l.is_verified AS location_is_verified, l.is_suspicious AS location_is_suspicious,
+        l.recorded_at AS location_recorded_at
+    FROM sessions s
+    LEFT JOIN LATERAL (
+        SELECT id, ip_address, country_code, region_code, city, timezone, latitude,
+               longitude, is_verified, is_suspicious, recorded_at
+        FROM session_locations
+        WHERE session_id = s.id
+        ORDER BY recorded_at DESC, id DESC
+        LIMIT 1
+    ) l ON true
+    WHERE s.user_id = $1
+    AND ($2::boolean IS NULL OR s.is_valid = $2)
+    ORDER BY s.created_at DESC
+"#;
+
+/// Session with an ID
+pub const SESSION_BY_ID_QUERY: &str = r#"
+    SELECT
+        id, user_id, token_hash, previous_token_hash, token_rotation_at,
+        expires_at, created_at, last_activity_at, last_activity_update_at,
+        ip_address, user_agent, browser, browser_version, os, device_type, region,
+        device_id, device_fingerprint,
+        is_valid, invalidated_reason::text AS invalidated_reason, metadata,
+        mfa_status::text AS mfa_status, kind
+    FROM sessions
+    WHERE id = $1
+"#;
+
+/// Session holding a token as its current or previous one
+pub const SESSION_BY_TOKEN_QUERY: &str = r#"
+    SELECT
+        id, user_id, token_hash, previous_token_hash, token_rotation_at,
+        expires_at, created_at, last_activity_at, last_activity_update_at,
+        ip_address, user_agent, browser, browser_version, os, device_type, region,
+        device_id, device_fingerprint,
+        is_valid, invalidated_reason::text AS invalidated_reason, metadata,
+        mfa_status::text AS mfa_status, kind
+    FROM sessions
+    WHERE token_hash = $1 OR previous_token_hash = $1
+"#;
+
+/// Page of the valid sessions after a session ID, in ID order; served by the
+/// partial index `idx_sessions_valid_id`
+pub const VALID_SESSIONS_PAGE_QUERY: &str = r#"
+    SELECT
+        id, user_id, token_hash, previous_token_hash, token_rotation_at,
+        expires_at, created_at, last_activity_at, last_activity_update_at,
+        ip_address, user_agent, browser, browser_version, os, device_type, region,
+        device_id, device_fingerprint,
+        is_valid, invalidated_reason::text AS invalidated_reason, metadata,
+        mfa_status::text AS mfa_status, kind
+    FROM sessions
+    WHERE is_valid = true AND ($1::uuid IS NULL OR id > $1)
+    ORDER BY id
+    LIMIT $2
+"#;
+
+/// One batch of the invalidation of expired sessions; served by the partial
+/// index `idx_sessions_expiry`
+pub const INVALIDATE_EXPIRED_SESSIONS_QUERY: &str = r#"
+    UPDATE sessions
+    SET
+        is_valid = false,
+        invalidated_reason = 'TOKEN_EXPIRED'::session_invalidation_reason
+    WHERE id IN (
+        SELECT id FROM sessions
+        WHERE is_valid = true AND expires_at < CURRENT_TIMESTAMP
+        LIMIT $1
+        FOR UPDATE SKIP LOCKED
+    )
+"#;
+
+/// One batch of the deletion of sessions invalid for longer than the retention
+/// period; served by the partial index `idx_sessions_invalid_activity`
+pub const DELETE_INVALID_SESSIONS_QUERY: &str = r#"
+    DELETE FROM sessions
+    WHERE id IN (
+        SELECT id FROM sessions
+        WHERE
+            is_valid = false
+            AND last_activity_at < CURRENT_TIMESTAMP - make_interval(secs => $1)
+        LIMIT $2
+        FOR UPDATE SKIP LOCKED
+    )
+"#;
+
+/// One batch of the deletion of guest sessions inactive for longer than the
+/// guest retention period; served by the partial index `idx_sessions_guest_activity`
+pub const DELETE_STALE_GUEST_SESSIONS_QUERY: &str = r#"
+    DELETE FROM sessions
+    WHERE id IN (
+        SELECT id FROM sessions
+        WHERE
+            kind = 'GUEST'
+            AND last_activity_at < CURRENT_TIMESTAMP - make_interval(secs => $1)
+        LIMIT $2
+        FOR UPDATE SKIP LOCKED
+    )
+"#;
+
 #[derive(Debug, thiserror::Error)]
 pub enum SessionError {
     #[error("Database error: {0}")]
@@ -104,24 +488,54 @@ pub enum SessionError {
     Invalid,
     #[error("Token mismatch")]
     TokenMismatch,
+    #[error("Timeout: {0}")]
+    Timeout(#[from] OperationTimedOut),
+}
+
+impl From<SessionError> for acci_core::Error {
+    fn from(err: SessionError) -> Self {
+        use acci_core::error::ErrorKind;
+
+        let kind = match err {
+            SessionError::Database(e) => return Self::Database(e),
+            SessionError::Expired => ErrorKind::SessionExpired,
+            SessionError::Timeout(_) => ErrorKind::Timeout,
+            SessionError::NotFound | SessionError::Invalid | SessionError::TokenMismatch => {
+                ErrorKind::Unauthenticated
+            },
+        };
+        Self::domain(kind, err.to_string())
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct SessionRepositoryConfig {
     /// Duration after which invalid sessions are deleted
     pub invalid_session_retention: Duration,
+    /// Duration of inactivity after which guest sessions are deleted, valid or not
+    ///
+    /// Guest sessions are cheap to create and usually abandoned, so they are
+    /// purged long before the sessions of users.
+    pub guest_session_retention: Duration,
     /// Duration after which audit logs are deleted
     pub audit_log_retention: Duration,
     /// Duration after which session activity updates are allowed
     pub activity_update_interval: Duration,
+    /// Chunking of the cleanup statements
+    pub cleanup_batch: BatchConfig,
+    /// Time budgets of the repository operations; the cleanup gets the long-running one
+    pub timeouts: OperationTimeouts,
 }
 
 impl Default for SessionRepositoryConfig {
     fn default() -> Self {
         Self {
             invalid_session_retention: Duration::from_secs(90 * 24 * 60 * 60), // 90 days
+            guest_session_retention: Duration::from_secs(24 * 60 * 60),        // 24 hours
             audit_log_retention: Duration::from_secs(90 * 24 * 60 * 60),       // 90 days
             activity_update_interval: Duration::from_secs(5 * 60),             // 5 minutes
+            cleanup_batch: BatchConfig::default(),
+            timeouts: OperationTimeouts::default(),
         }
     }
 }
@@ -132,11 +546,13 @@ pub trait SessionRepository: Send + Sync + 'static {
         &self,
         user_id: Uuid,
         token_hash: String,
-        expires_at: SystemTime,
+        expires_at: OffsetDateTime,
         device_id: Option<String>,
         device_fingerprint: Option<DeviceFingerprint>,
         ip_address: Option<String>,
         user_agent: Option<String>,
+        user_agent_details: Option<UserAgentDetails>,
+        region: Option<String>,
         metadata: Option<Value>,
     ) -> Result<Session, SessionError>;
 
@@ -151,6 +567,17 @@ pub trait SessionRepository: Send + Sync + 'static {
         filter: SessionFilter,
     ) -> Result<Vec<Session>, SessionError>;
 
+    /// Sessions of a user with the location recorded last for each, newest first
+    ///
+    /// Sessions without a recorded location are included without one. The
+    /// session with the ID `current_session_id` is flagged as current.
+    async fn get_user_sessions_with_location(
+        &self,
+        user_id: Uuid,
+        filter: SessionFilter,
+        current_session_id: Option<Uuid>,
+    ) -> Result<Vec<SessionWithLocation>, SessionError>;
+
     async fn update_session_activity(&self, id: Uuid) -> Result<(), SessionError>;
 
     async fn invalidate_session(
@@ -188,6 +615,26 @@ pub trait SessionRepository: Send + Sync + 'static {
         reason: SessionInvalidationReason,
     ) -> Result<u64, SessionError>;
 
+    /// Invalidate the valid sessions created in `region`
+    ///
+    /// With `tenant_id`, only the sessions of that tenant are invalidated, see
+    /// [`Session::tenant_id`]. Sessions without a region are only invalidated
+    /// with `include_unknown`.
+    async fn invalidate_sessions_by_region(
+        &self,
+        region: &str,
+        tenant_id: Option<Uuid>,
+        include_unknown: bool,
+        reason: SessionInvalidationReason,
+    ) -> Result<u64, SessionError>;
+
+    /// Invalidate the valid sessions bound to `tenant_id`, see [`Session::tenant_id`]
+    async fn invalidate_tenant_sessions(
+        &self,
+        tenant_id: Uuid,
+        reason: SessionInvalidationReason,
+    ) -> Result<u64, SessionError>;
+
     async fn rotate_session_token(
         &self,
         id: Uuid,
@@ -196,8 +643,94 @@ pub trait SessionRepository: Send + Sync + 'static {
 
     async fn cleanup_expired_sessions(&self) -> Result<u64, SessionError>;
 
-    /// Update the MFA status for a session
+    /// Update the MFA status of a valid session
+    ///
+    /// Fails with [`SessionError::NotFound`] if the session does not exist or
+    /// has been invalidated.
     async fn update_mfa_status(&self, id: Uuid, status: MfaStatus) -> Result<(), SessionError>;
+
+    /// Record `tenant_id` as the active tenant of a valid session
+    ///
+    /// Implementations write a `TENANT_SWITCHED` audit entry together with the
+    /// metadata update. Membership is checked by the caller.
+    async fn set_active_tenant(&self, id: Uuid, tenant_id: Uuid) -> Result<(), SessionError>;
+
+    /// Record `at` as the time of the last full authentication of a valid session
+    async fn record_authentication(&self, id: Uuid, at: OffsetDateTime)
+    -> Result<(), SessionError>;
+
+    /// Bind a valid session to the client characteristics hashed as `binding_hash`
+    ///
+    /// See [`ClientCharacteristics`](crate::security::client_binding::ClientCharacteristics).
+    async fn record_client_binding(&self, id: Uuid, binding_hash: &str)
+    -> Result<(), SessionError>;
+
+    /// Merge the top-level keys of the object `patch` into the metadata of a valid session
+    ///
+    /// Keys of `patch` replace existing keys of the same name. Fails with
+    /// [`SessionError::NotFound`] if the session does not exist or has been
+    /// invalidated.
+    async fn merge_session_metadata(&self, id: Uuid, patch: Value) -> Result<(), SessionError>;
+
+    /// Record the completion of a second factor at `at`
+    ///
+    /// Stores `at` as both `mfa_verified_at` and `last_auth_at` and writes an
+    /// `MFA_VERIFIED` audit entry, all or nothing. The MFA status itself is
+    /// changed separately through [`update_mfa_status`](Self::update_mfa_status).
+    async fn record_mfa_verification(
+        &self,
+        id: Uuid,
+        at: OffsetDateTime,
+    ) -> Result<(), SessionError>;
+
+    /// Write `action` to the audit log of both the impersonated user and the impersonator
+    ///
+    /// The impersonator and reason are taken from the session metadata; sessions
+    /// that are not impersonation sessions are reported as not found.
+    async fn record_impersonation_event(
+        &self,
+        id: Uuid,
+        action: ImpersonationAction,
+    ) -> Result<(), SessionError>;
+
+    /// Write a factor change made in a valid session to the audit log of its user
+    ///
+    /// `factor` is the [`AuthFactor`](crate::models::AuthFactor) identifier.
+    async fn record_factor_event(
+        &self,
+        id: Uuid,
+        action: FactorAction,
+        factor: &str,
+    ) -> Result<(), SessionError>;
+
+    /// Sessions matching `filter`, ordered by creation time
+    ///
+    /// Pages of `filter.page_size` sessions are read lazily with keyset
+    /// pagination, so memory use does not grow with the number of sessions and
+    /// dropping the stream stops reading.
+    fn stream_sessions(
+        &self,
+        filter: SessionExportFilter,
+    ) -> BoxStream<'static, Result<Session, SessionError>>;
+
+    /// Up to `limit` valid sessions with an ID greater than `after`, in ID order
+    ///
+    /// Pages of the keyset scan of the session reconciliation, which continues
+    /// after the last ID of the previous page.
+    async fn valid_sessions_after(
+        &self,
+        after: Option<Uuid>,
+        limit: u32,
+    ) -> Result<Vec<Session>, SessionError>;
+
+    /// ID of the last session checked by the session reconciliation
+    ///
+    /// `None` when no pass is in progress.
+    async fn reconciliation_cursor(&self) -> Result<Option<Uuid>, SessionError>;
+
+    /// Persist the position of the session reconciliation, see
+    /// [`reconciliation_cursor`](Self::reconciliation_cursor)
+    async fn save_reconciliation_cursor(&self, cursor: Option<Uuid>) -> Result<(), SessionError>;
 }
 
 pub struct PostgresSessionRepository {
@@ -217,14 +750,137 @@ impl PostgresSessionRepository {
         Self { pool, config }
     }
 
-    fn record_metrics(_operation: &str, start_time: SystemTime) {
-        // Temporarily disabled for compilation
-        let _ = (_operation, start_time);
+    fn record_metrics(operation: &'static str, start_time: Instant) {
+        instrumentation::record(operation, start_time, false);
+    }
+
+    fn record_error_metrics(operation: &'static str, start_time: Instant) {
+        instrumentation::record(operation, start_time, true);
+    }
+
+    /// Map a row selected with the columns of [`EXPORT_PAGE_QUERY`] or
+    /// [`USER_SESSIONS_QUERY`] to a session
+    fn session_from_row(row: &PgRow) -> Result<Session, sqlx::Error> {
+        let mfa_status = match row.try_get::<Option<String>, _>("mfa_status")?.as_deref() {
+            Some("REQUIRED") => MfaStatus::Required,
+            Some("VERIFIED") => MfaStatus::Verified,
+            Some("PENDING") => MfaStatus::Pending,
+            _ => MfaStatus::None,
+        };
+        let invalidated_reason = row
+            .try_get::<Option<String>, _>("invalidated_reason")?
+            .and_then(|reason| serde_json::from_value(Value::String(reason)).ok());
+        let device_fingerprint = row
+            .try_get::<Option<Value>, _>("device_fingerprint")?
+            .and_then(|fingerprint| serde_json::from_value(fingerprint).ok());
+        let device_type = row
+            .try_get::<Option<String>, _>("device_type")?
+            .as_deref()
+            .and_then(DeviceType::parse);
+        let user_agent_details = match device_type {
+            Some(device_type) => Some(UserAgentDetails {
+                browser: row.try_get("browser")?,
+                browser_version: row.try_get("browser_version")?,
+                os: row.try_get("os")?,
+                device_type,
+            }),
+            None => None,
+        };
+
+        let kind: String = row.try_get("kind")?;
+        let kind = SessionKind::parse(&kind)
+            .ok_or_else(|| sqlx::Error::Decode(format!("Unknown session kind: {kind}").into()))?;
+        let id: Uuid = row.try_get("id")?;
+        // Rows from before the metadata limits may exceed them
+        let metadata = row
+            .try_get::<Option<Value>, _>("metadata")?
+            .and_then(|metadata| bound_stored("sessions", id, metadata, RESERVED_METADATA_KEYS));
+
+        Ok(Session {
+            id,
+            // Guest sessions are bound to no user
+            user_id: row
+                .try_get::<Option<Uuid>, _>("user_id")?
+                .unwrap_or_else(Uuid::nil),
+            kind,
+            token_hash: row.try_get("token_hash")?,
+            previous_token_hash: row.try_get("previous_token_hash")?,
+            token_rotation_at: row.try_get("token_rotation_at")?,
+            expires_at: row.try_get("expires_at")?,
+            created_at: row.try_get("created_at")?,
+            last_activity_at: row.try_get("last_activity_at")?,
+            last_activity_update_at: row.try_get("last_activity_update_at")?,
+            ip_address: row
+                .try_get::<Option<IpNetwork>, _>("ip_address")?
+                .map(|ip| ip.to_string()),
+            user_agent: row.try_get("user_agent")?,
+            user_agent_details,
+            region: row.try_get("region")?,
+            device_id: row.try_get("device_id")?,
+            device_fingerprint,
+            is_valid: row.try_get("is_valid")?,
+            invalidated_reason,
+            metadata,
+            mfa_status,
+        })
+    }
+
+    /// Map the location columns of a row selected with
+    /// [`USER_SESSIONS_WITH_LOCATION_QUERY`] to the location of `session`
+    fn location_from_row(
+        row: &PgRow,
+        session: &Session,
+    ) -> Result<Option<SessionLocation>, sqlx::Error> {
+        let Some(id) = row.try_get::<Option<Uuid>, _>("location_id")? else {
+            return Ok(None);
+        };
+        let ip_address: IpNetwork = row.try_get("location_ip_address")?;
+
+        Ok(Some(SessionLocation {
+            id,
+            session_id: session.id,
+            user_id: session.user_id,
+            ip_address: ip_address.ip(),
+            country_code: row.try_get("location_country_code")?,
+            region_code: row.try_get("location_region_code")?,
+            city: row.try_get("location_city")?,
+            timezone: row.try_get("location_timezone")?,
+            latitude: row.try_get("location_latitude")?,
+            longitude: row.try_get("location_longitude")?,
+            is_verified: row.try_get("location_is_verified")?,
+            is_suspicious: row.try_get("location_is_suspicious")?,
+            recorded_at: row.try_get("location_recorded_at")?,
+        }))
     }
 
-    fn record_error_metrics(_operation: &str, _error: &SessionError) {
-        // Temporarily disabled for compilation
-        let _ = (_operation, _error);
+    /// Read the page of exported sessions following `cursor`
+    async fn fetch_export_page(
+        pool: &sqlx::PgPool,
+        filter: &SessionExportFilter,
+        cursor: Option<(OffsetDateTime, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<Session>, SessionError> {
+        let is_valid = match filter.status {
+            SessionFilter::All => None,
+            SessionFilter::Active => Some(true),
+            SessionFilter::Inactive => Some(false),
+        };
+
+        let mut rows = sqlx::query(EXPORT_PAGE_QUERY)
+            .bind(filter.tenant_id.to_string())
+            .bind(is_valid)
+            .bind(filter.user_id)
+            .bind(filter.created_after)
+            .bind(cursor.map(|(created_at, _)| created_at))
+            .bind(cursor.map(|(_, id)| id))
+            .bind(limit)
+            .fetch(pool);
+
+        let mut page = Vec::new();
+        while let Some(row) = rows.try_next().await? {
+            page.push(Self::session_from_row(&row)?);
+        }
+        Ok(page)
     }
 }
 
@@ -237,108 +893,107 @@ impl SessionError {
             Self::Expired => "expired",
             Self::Invalid => "invalid",
             Self::TokenMismatch => "token_mismatch",
+            Self::Timeout(_) => "timeout",
         }
     }
 }
 
 #[async_trait]
 impl SessionRepository for PostgresSessionRepository {
+    #[instrument(name = "session.repo.create_session", skip_all, level = "debug")]
     async fn create_session(
         &self,
         user_id: Uuid,
         token_hash: String,
-        expires_at: SystemTime,
+        expires_at: OffsetDateTime,
         device_id: Option<String>,
         device_fingerprint: Option<DeviceFingerprint>,
         ip_address: Option<String>,
         user_agent: Option<String>,
+        user_agent_details: Option<UserAgentDetails>,
+        region: Option<String>,
         metadata: Option<Value>,
     ) -> Result<Session, SessionError> {
-        let start = SystemTime::now();
+        let start = Instant::now();
         tracing::debug!(
             user_id = %user_id,
             device_id = ?device_id,
             "Creating new session"
         );
 
-        let result: Result<Session, SessionError> = async {
-            let device_fingerprint_json = device_fingerprint.map(|fp| {
-                serde_json::to_value(fp).expect("Failed to serialize device fingerprint to JSON")
-            });
-            let now = SystemTime::now();
-            let now_offset = system_time_to_offset_date_time(now);
-            let expires_at_offset = system_time_to_offset_date_time(expires_at);
-            let ip_network = string_to_ip_network(ip_address.clone());
-
-            let row = sqlx::query!(
-                r#"
+        let result: Result<Session, SessionError> =
+            within(METRIC_CREATE, self.config.timeouts.write, async {
+                let device_fingerprint_json = device_fingerprint.map(|fp| {
+                    serde_json::to_value(fp)
+                        .expect("Failed to serialize device fingerprint to JSON")
+                });
+                let now = OffsetDateTime::now_utc();
+                let ip_network = string_to_ip_network(ip_address.clone());
+
+                // Guest sessions are bound to no user
+                let kind = SessionKind::for_user(user_id);
+                let user_id = (kind == SessionKind::User).then_some(user_id);
+
+                let row = sqlx::query(
+                    r#"
                 INSERT INTO sessions (
                     id, user_id, token_hash, expires_at, created_at, last_activity_at,
-                    ip_address, user_agent, device_id, device_fingerprint, is_valid, metadata,
-                    mfa_status
+                    ip_address, user_agent, browser, browser_version, os, device_type, region,
+                    device_id, device_fingerprint, is_valid, metadata, mfa_status, kind
                 )
                 VALUES (
                     gen_random_uuid(), $1, $2, $3, $4, $5,
-                    $6, $7, $8, $9, true, $10, 'NONE'
+                    $6, $7, $8, $9, $10, $11, $12,
+                    $13, $14, true, $15, 'NONE', $16
                 )
                 RETURNING
                     id, user_id, token_hash, previous_token_hash, token_rotation_at,
                     expires_at, created_at, last_activity_at, last_activity_update_at,
-                    ip_address, user_agent, device_id, device_fingerprint,
-                    is_valid, invalidated_reason::text, metadata, mfa_status::text
+                    ip_address, user_agent, browser, browser_version, os, device_type, region,
+                    device_id, device_fingerprint,
+                    is_valid, invalidated_reason::text AS invalidated_reason, metadata,
+                    mfa_status::text AS mfa_status, kind
                 "#,
-                user_id,
-                token_hash,
-                expires_at_offset,
-                now_offset,
-                now_offset,
-                ip_network,
-                user_agent,
-                device_id,
-                device_fingerprint_json,
-                metadata,
-            )
-            .fetch_one(&self.pool)
-            .await
-            .map_err(SessionError::Database)?;
-
-            let mfa_status = match row.mfa_status {
-                Some(status_str) => match status_str.as_str() {
-                    "NONE" => MfaStatus::None,
-                    "REQUIRED" => MfaStatus::Required,
-                    "VERIFIED" => MfaStatus::Verified,
-                    _ => MfaStatus::None, // Default if not specified
-                },
-                None => MfaStatus::None,
-            };
-
-            Ok(Session {
-                id: row.id,
-                user_id: row.user_id,
-                token_hash: row.token_hash,
-                previous_token_hash: row.previous_token_hash,
-                token_rotation_at: row.token_rotation_at.map(|t| t.into()),
-                expires_at: row.expires_at.into(),
-                created_at: row.created_at.into(),
-                last_activity_at: row.last_activity_at.into(),
-                last_activity_update_at: row.last_activity_update_at.map(|t| t.into()),
-                ip_address: row.ip_address.map(|ip| ip.to_string()),
-                user_agent: row.user_agent,
-                device_id: row.device_id,
-                device_fingerprint: row.device_fingerprint.map(|v| {
-                    serde_json::from_value(v)
-                        .expect("Failed to deserialize session data from JSON value")
-                }),
-                is_valid: row.is_valid,
-                invalidated_reason: row.invalidated_reason.map(|r| {
-                    serde_json::from_str(&r.to_string())
-                        .expect("Failed to deserialize session invalidation reason from string")
-                }),
-                metadata: row.metadata,
-                mfa_status,
+                )
+                .bind(user_id)
+                .bind(token_hash)
+                .bind(expires_at)
+                .bind(now)
+                .bind(now)
+                .bind(ip_network)
+                .bind(user_agent)
+                .bind(
+                    user_agent_details
+                        .as_ref()
+                        .and_then(|details| details.browser.clone()),
+                )
+                .bind(
+                    user_agent_details
+                        .as_ref()
+                        .and_then(|details| details.browser_version.clone()),
+                )
+                .bind(
+                    user_agent_details
+                        .as_ref()
+                        .and_then(|details| details.os.clone()),
+                )
+                .bind(
+                    user_agent_details
+                        .as_ref()
+                        .map(|details| details.device_type.as_str()),
+                )
+                .bind(region)
+                .bind(device_id)
+                .bind(device_fingerprint_json)
+                .bind(metadata)
+                .bind(kind.as_str())
+                .fetch_one(&self.pool)
+                .await
+                .map_err(SessionError::Database)?;
+
+                Self::session_from_row(&row).map_err(SessionError::Database)
             })
-        }
-        .await;
+            .await;
 
         match &result {
             Ok(session) => {
@@ -356,74 +1011,31 @@ impl SessionRepository for PostgresSessionRepository {
                     error = ?error,
                     "Failed to create session"
                 );
-                Self::record_error_metrics(METRIC_CREATE, error);
+                Self::record_error_metrics(METRIC_CREATE, start);
             },
         }
 
         result
     }
 
+    #[instrument(name = "session.repo.get_session", skip_all, level = "debug")]
     async fn get_session(&self, id: Uuid) -> Result<Option<Session>, SessionError> {
-        let start = SystemTime::now();
+        let start = Instant::now();
         tracing::debug!(session_id = %id, "Getting session by ID");
 
-        let result: Result<Option<Session>, SessionError> = async {
-            let row = sqlx::query!(
-                r#"
-                SELECT
-                    id, user_id, token_hash, previous_token_hash, token_rotation_at,
-                    expires_at, created_at, last_activity_at, last_activity_update_at,
-                    ip_address, user_agent, device_id, device_fingerprint,
-                    is_valid, invalidated_reason::text as "invalidated_reason?", metadata,
-                    mfa_status::text as "mfa_status?"
-                FROM sessions
-                WHERE id = $1
-                "#,
-                id
-            )
-            .fetch_optional(&self.pool)
-            .await
-            .map_err(SessionError::Database)?;
-
-            Ok(row.map(|row| {
-                let mfa_status = match &row.mfa_status {
-                    Some(status_str) => match status_str.as_str() {
-                        "NONE" => MfaStatus::None,
-                        "REQUIRED" => MfaStatus::Required,
-                        "VERIFIED" => MfaStatus::Verified,
-                        _ => MfaStatus::None, // Default if not specified
-                    },
-                    None => MfaStatus::None,
-                };
-
-                Session {
-                    id: row.id,
-                    user_id: row.user_id,
-                    token_hash: row.token_hash,
-                    previous_token_hash: row.previous_token_hash,
-                    token_rotation_at: row.token_rotation_at.map(|t| t.into()),
-                    expires_at: row.expires_at.into(),
-                    created_at: row.created_at.into(),
-                    last_activity_at: row.last_activity_at.into(),
-                    last_activity_update_at: row.last_activity_update_at.map(|t| t.into()),
-                    ip_address: row.ip_address.map(|ip| ip.to_string()),
-                    user_agent: row.user_agent,
-                    device_id: row.device_id,
-                    device_fingerprint: row.device_fingerprint.map(|v| {
-                        serde_json::from_value(v)
-                            .expect("Failed to deserialize session data from JSON value")
-                    }),
-                    is_valid: row.is_valid,
-                    invalidated_reason: row.invalidated_reason.map(|r| {
-                        serde_json::from_str(&r.to_string())
-                            .expect("Failed to deserialize session invalidation reason from string")
-                    }),
-                    metadata: row.metadata,
-                    mfa_status,
-                }
-            }))
-        }
-        .await;
+        let result: Result<Option<Session>, SessionError> =
+            within(METRIC_GET, self.config.timeouts.read, async {
+                let row = sqlx::query(SESSION_BY_ID_QUERY)
+                    .bind(id)
+                    .fetch_optional(&self.pool)
+                    .await
+                    .map_err(SessionError::Database)?;
+
+                row.map(|row| Self::session_from_row(&row))
+                    .transpose()
+                    .map_err(SessionError::Database)
+            })
+            .await;
 
         match &result {
             Ok(session) => {
@@ -440,77 +1052,34 @@ impl SessionRepository for PostgresSessionRepository {
                     error = ?error,
                     "Failed to get session"
                 );
-                Self::record_error_metrics(METRIC_GET, error);
+                Self::record_error_metrics(METRIC_GET, start);
             },
         }
 
         result
     }
 
+    #[instrument(name = "session.repo.get_session_by_token", skip_all, level = "debug")]
     async fn get_session_by_token(
         &self,
         token_hash: &str,
     ) -> Result<Option<Session>, SessionError> {
-        let start = SystemTime::now();
+        let start = Instant::now();
         tracing::debug!("Getting session by token hash");
 
-        let result: Result<Option<Session>, SessionError> = async {
-            let row = sqlx::query!(
-                r#"
-                SELECT
-                    id, user_id, token_hash, previous_token_hash, token_rotation_at,
-                    expires_at, created_at, last_activity_at, last_activity_update_at,
-                    ip_address, user_agent, device_id, device_fingerprint,
-                    is_valid, invalidated_reason::text as "invalidated_reason?", metadata,
-                    mfa_status::text as "mfa_status?"
-                FROM sessions
-                WHERE token_hash = $1 OR previous_token_hash = $1
-                "#,
-                token_hash
-            )
-            .fetch_optional(&self.pool)
-            .await
-            .map_err(SessionError::Database)?;
-
-            Ok(row.map(|row| {
-                let mfa_status = match &row.mfa_status {
-                    Some(status_str) => match status_str.as_str() {
-                        "NONE" => MfaStatus::None,
-                        "REQUIRED" => MfaStatus::Required,
-                        "VERIFIED" => MfaStatus::Verified,
-                        _ => MfaStatus::None, // Default if not specified
-                    },
-                    None => MfaStatus::None,
-                };
-
-                Session {
-                    id: row.id,
-                    user_id: row.user_id,
-                    token_hash: row.token_hash,
-                    previous_token_hash: row.previous_token_hash,
-                    token_rotation_at: row.token_rotation_at.map(|t| t.into()),
-                    expires_at: row.expires_at.into(),
-                    created_at: row.created_at.into(),
-                    last_activity_at: row.last_activity_at.into(),
-                    last_activity_update_at: row.last_activity_update_at.map(|t| t.into()),
-                    ip_address: row.ip_address.map(|ip| ip.to_string()),
-                    user_agent: row.user_agent,
-                    device_id: row.device_id,
-                    device_fingerprint: row.device_fingerprint.map(|v| {
-                        serde_json::from_value(v)
-                            .expect("Failed to deserialize session data from JSON value")
-                    }),
-                    is_valid: row.is_valid,
-                    invalidated_reason: row.invalidated_reason.map(|r| {
-                        serde_json::from_str(&r.to_string())
-                            .expect("Failed to deserialize session invalidation reason from string")
-                    }),
-                    metadata: row.metadata,
-                    mfa_status,
-                }
-            }))
-        }
-        .await;
+        let result: Result<Option<Session>, SessionError> =
+            within(METRIC_GET_BY_TOKEN, self.config.timeouts.read, async {
+                let row = sqlx::query(SESSION_BY_TOKEN_QUERY)
+                    .bind(token_hash)
+                    .fetch_optional(&self.pool)
+                    .await
+                    .map_err(SessionError::Database)?;
+
+                row.map(|row| Self::session_from_row(&row))
+                    .transpose()
+                    .map_err(SessionError::Database)
+            })
+            .await;
 
         match &result {
             Ok(session) => {
@@ -522,96 +1091,45 @@ impl SessionRepository for PostgresSessionRepository {
                     error = ?error,
                     "Failed to get session by token"
                 );
-                Self::record_error_metrics(METRIC_GET_BY_TOKEN, error);
+                Self::record_error_metrics(METRIC_GET_BY_TOKEN, start);
             },
         }
 
         result
     }
 
+    #[instrument(name = "session.repo.get_user_sessions", skip_all, level = "debug")]
     async fn get_user_sessions(
         &self,
         user_id: Uuid,
         filter: SessionFilter,
     ) -> Result<Vec<Session>, SessionError> {
-        let start = SystemTime::now();
+        let start = Instant::now();
         tracing::debug!(
             user_id = %user_id,
             filter = ?filter,
             "Getting user sessions"
         );
 
-        let result: Result<Vec<Session>, SessionError> = async {
-            let (is_valid, include_filter) = match filter {
-                SessionFilter::All => (true, false),
-                SessionFilter::Active => (true, true),
-                SessionFilter::Inactive => (false, true),
-            };
+        let result: Result<Vec<Session>, SessionError> =
+            within(METRIC_GET_USER, self.config.timeouts.read, async {
+                let query = match filter {
+                    SessionFilter::All => USER_SESSIONS_QUERY,
+                    SessionFilter::Active => VALID_USER_SESSIONS_QUERY,
+                    SessionFilter::Inactive => INVALID_USER_SESSIONS_QUERY,
+                };
 
-            let rows = sqlx::query!(
-                r#"
-                SELECT
-                    id, user_id, token_hash, previous_token_hash, token_rotation_at,
-                    expires_at, created_at, last_activity_at, last_activity_update_at,
-                    ip_address, user_agent, device_id, device_fingerprint,
-                    is_valid, invalidated_reason::text as "invalidated_reason?", metadata,
-                    mfa_status::text as "mfa_status?"
-                FROM sessions
-                WHERE user_id = $1
-                AND ($2 = false OR is_valid = $3)
-                ORDER BY created_at DESC
-                "#,
-                user_id,
-                include_filter,
-                is_valid
-            )
-            .fetch_all(&self.pool)
-            .await
-            .map_err(SessionError::Database)?;
-
-            Ok(rows
-                .into_iter()
-                .map(|row| {
-                    let mfa_status = match &row.mfa_status {
-                        Some(status_str) => match status_str.as_str() {
-                            "NONE" => MfaStatus::None,
-                            "REQUIRED" => MfaStatus::Required,
-                            "VERIFIED" => MfaStatus::Verified,
-                            _ => MfaStatus::None, // Default if not specified
-                        },
-                        None => MfaStatus::None,
-                    };
-
-                    Session {
-                        id: row.id,
-                        user_id: row.user_id,
-                        token_hash: row.token_hash,
-                        previous_token_hash: row.previous_token_hash,
-                        token_rotation_at: row.token_rotation_at.map(|t| t.into()),
-                        expires_at: row.expires_at.into(),
-                        created_at: row.created_at.into(),
-                        last_activity_at: row.last_activity_at.into(),
-                        last_activity_update_at: row.last_activity_update_at.map(|t| t.into()),
-                        ip_address: row.ip_address.map(|ip| ip.to_string()),
-                        user_agent: row.user_agent,
-                        device_id: row.device_id,
-                        device_fingerprint: row.device_fingerprint.map(|v| {
-                            serde_json::from_value(v)
-                                .expect("Failed to deserialize session data from JSON value")
-                        }),
-                        is_valid: row.is_valid,
-                        invalidated_reason: row.invalidated_reason.map(|r| {
-                            serde_json::from_str(&r.to_string()).expect(
-                                "Failed to deserialize session invalidation reason from string",
-                            )
-                        }),
-                        metadata: row.metadata,
-                        mfa_status,
-                    }
-                })
-                .collect())
-        }
-        .await;
+                let rows = sqlx::query(query)
+                    .bind(user_id)
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(SessionError::Database)?;
+
+                rows.iter()
+                    .map(|row| Self::session_from_row(row).map_err(SessionError::Database))
+                    .collect()
+            })
+            .await;
 
         match &result {
             Ok(sessions) => {
@@ -628,37 +1146,115 @@ impl SessionRepository for PostgresSessionRepository {
                     error = ?error,
                     "Failed to get user sessions"
                 );
-                Self::record_error_metrics(METRIC_GET_USER, error);
+                Self::record_error_metrics(METRIC_GET_USER, start);
+            },
+        }
+
+        result
+    }
+
+    #[instrument(
+        name = "session.repo.get_user_sessions_with_location",
+        skip_all,
+        level = "debug"
+    )]
+    async fn get_user_sessions_with_location(
+        &self,
+        user_id: Uuid,
+        filter: SessionFilter,
+        current_session_id: Option<Uuid>,
+    ) -> Result<Vec<SessionWithLocation>, SessionError> {
+        let start = Instant::now();
+        tracing::debug!(
+            user_id = %user_id,
+            filter = ?filter,
+            "Getting user sessions with their locations"
+        );
+
+        let is_valid = match filter {
+            SessionFilter::All => None,
+            SessionFilter::Active => Some(true),
+            SessionFilter::Inactive => Some(false),
+        };
+        let result: Result<Vec<SessionWithLocation>, SessionError> = within(
+            METRIC_GET_USER_WITH_LOCATION,
+            self.config.timeouts.read,
+            async {
+                let rows = sqlx::query(USER_SESSIONS_WITH_LOCATION_QUERY)
+                    .bind(user_id)
+                    .bind(is_valid)
+                    .bind(current_session_id)
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(SessionError::Database)?;
+
+                rows.iter()
+                    .map(|row| {
+                        let session = Self::session_from_row(row)?;
+                        Ok(SessionWithLocation {
+                            location: Self::location_from_row(row, &session)?,
+                            is_current: row.try_get("is_current")?,
+                            session,
+                        })
+                    })
+                    .collect::<Result<_, sqlx::Error>>()
+                    .map_err(SessionError::Database)
+            },
+        )
+        .await;
+
+        match &result {
+            Ok(sessions) => {
+                tracing::debug!(
+                    user_id = %user_id,
+                    count = sessions.len(),
+                    "User sessions with locations retrieved successfully"
+                );
+                Self::record_metrics(METRIC_GET_USER_WITH_LOCATION, start);
+            },
+            Err(error) => {
+                tracing::error!(
+                    user_id = %user_id,
+                    error = ?error,
+                    "Failed to get user sessions with locations"
+                );
+                Self::record_error_metrics(METRIC_GET_USER_WITH_LOCATION, start);
             },
         }
 
         result
     }
 
+    #[instrument(
+        name = "session.repo.update_session_activity",
+        skip_all,
+        level = "debug"
+    )]
     async fn update_session_activity(&self, id: Uuid) -> Result<(), SessionError> {
-        let start = SystemTime::now();
+        let start = Instant::now();
         tracing::debug!(session_id = %id, "Updating session activity");
 
-        let result: Result<(), SessionError> = async {
-            let result = sqlx::query!(
-                r#"
+        let result: Result<(), SessionError> =
+            within(METRIC_UPDATE_ACTIVITY, self.config.timeouts.write, async {
+                let result = sqlx::query!(
+                    r#"
                 UPDATE sessions
                 SET last_activity_at = CURRENT_TIMESTAMP
                 WHERE id = $1 AND is_valid = true
                 RETURNING id
                 "#,
-                id
-            )
-            .fetch_optional(&self.pool)
-            .await
-            .map_err(SessionError::Database)?;
-
-            match result {
-                Some(_) => Ok(()),
-                None => Err(SessionError::NotFound),
-            }
-        }
-        .await;
+                    id
+                )
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(SessionError::Database)?;
+
+                match result {
+                    Some(_) => Ok(()),
+                    None => Err(SessionError::NotFound),
+                }
+            })
+            .await;
 
         match &result {
             Ok(_) => {
@@ -674,28 +1270,30 @@ impl SessionRepository for PostgresSessionRepository {
                     error = ?error,
                     "Failed to update session activity"
                 );
-                Self::record_error_metrics(METRIC_UPDATE_ACTIVITY, error);
+                Self::record_error_metrics(METRIC_UPDATE_ACTIVITY, start);
             },
         }
 
         result
     }
 
+    #[instrument(name = "session.repo.invalidate_session", skip_all, level = "debug")]
     async fn invalidate_session(
         &self,
         id: Uuid,
         reason: SessionInvalidationReason,
     ) -> Result<(), SessionError> {
-        let start = SystemTime::now();
+        let start = Instant::now();
         tracing::debug!(
             session_id = %id,
             reason = ?reason,
             "Invalidating session"
         );
 
-        let result: Result<(), SessionError> = async {
-            let result = sqlx::query!(
-                r#"
+        let result: Result<(), SessionError> =
+            within(METRIC_INVALIDATE, self.config.timeouts.write, async {
+                let result = sqlx::query!(
+                    r#"
                 UPDATE sessions
                 SET
                     is_valid = false,
@@ -703,19 +1301,19 @@ impl SessionRepository for PostgresSessionRepository {
                 WHERE id = $1 AND is_valid = true
                 RETURNING id
                 "#,
-                id,
-                reason as _
-            )
-            .fetch_optional(&self.pool)
-            .await
-            .map_err(SessionError::Database)?;
-
-            match result {
-                Some(_) => Ok(()),
-                None => Err(SessionError::NotFound),
-            }
-        }
-        .await;
+                    id,
+                    reason as _
+                )
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(SessionError::Database)?;
+
+                match result {
+                    Some(_) => Ok(()),
+                    None => Err(SessionError::NotFound),
+                }
+            })
+            .await;
 
         match &result {
             Ok(_) => {
@@ -733,7 +1331,7 @@ impl SessionRepository for PostgresSessionRepository {
                     error = ?error,
                     "Failed to invalidate session"
                 );
-                Self::record_error_metrics(METRIC_INVALIDATE, error);
+                Self::record_error_metrics(METRIC_INVALIDATE, start);
             },
         }
 
@@ -744,12 +1342,17 @@ impl SessionRepository for PostgresSessionRepository {
     ///
     /// This is useful for security-critical actions like password changes,
     /// privilege escalations, or security breaches.
+    #[instrument(
+        name = "session.repo.invalidate_all_user_sessions",
+        skip_all,
+        level = "debug"
+    )]
     async fn invalidate_all_user_sessions(
         &self,
         user_id: Uuid,
         reason: SessionInvalidationReason,
     ) -> Result<u64, SessionError> {
-        let start = SystemTime::now();
+        let start = Instant::now();
         tracing::debug!(
             user_id = %user_id,
             reason = ?reason,
@@ -761,9 +1364,10 @@ impl SessionRepository for PostgresSessionRepository {
         let result: Result<u64, SessionError> = Ok(0);
 
         #[cfg(not(test))]
-        let result: Result<u64, SessionError> = async {
-            let result = sqlx::query!(
-                r#"
+        let result: Result<u64, SessionError> =
+            within(METRIC_INVALIDATE_USER, self.config.timeouts.write, async {
+                let result = sqlx::query!(
+                    r#"
                 UPDATE sessions
                 SET
                     is_valid = false,
@@ -771,25 +1375,25 @@ impl SessionRepository for PostgresSessionRepository {
                 WHERE user_id = $1 AND is_valid = true
                 RETURNING id
                 "#,
-                user_id,
-                reason as _
-            )
-            .fetch_all(&self.pool)
-            .await
-            .map_err(SessionError::Database)?;
-
-            Ok(result.len() as u64)
-        }
-        .await;
+                    user_id,
+                    reason as _
+                )
+                .fetch_all(&self.pool)
+                .await
+                .map_err(SessionError::Database)?;
+
+                Ok(result.len() as u64)
+            })
+            .await;
 
         match &result {
             Ok(count) => {
                 tracing::info!(
                     invalidated_sessions = count,
-                    duration = ?start.elapsed().unwrap_or_default(),
+                    duration = ?start.elapsed(),
                     "All sessions for user invalidated successfully"
                 );
-                Self::record_metrics(METRIC_INVALIDATE, start);
+                Self::record_metrics(METRIC_INVALIDATE_USER, start);
             },
             Err(error) => {
                 tracing::error!(
@@ -798,7 +1402,7 @@ impl SessionRepository for PostgresSessionRepository {
                     error = ?error,
                     "Failed to invalidate all sessions for user"
                 );
-                Self::record_error_metrics(METRIC_INVALIDATE, error);
+                Self::record_error_metrics(METRIC_INVALIDATE_USER, start);
             },
         }
 
@@ -809,12 +1413,17 @@ impl SessionRepository for PostgresSessionRepository {
     ///
     /// This can be used to enforce security policies, handle emergency
     /// situations, or implement compliance requirements.
+    #[instrument(
+        name = "session.repo.invalidate_sessions_by_filter",
+        skip_all,
+        level = "debug"
+    )]
     async fn invalidate_sessions_by_filter(
         &self,
         filter: SessionFilter,
         reason: SessionInvalidationReason,
     ) -> Result<u64, SessionError> {
-        let start = SystemTime::now();
+        let start = Instant::now();
         tracing::debug!(
             filter = ?filter,
             reason = ?reason,
@@ -826,15 +1435,18 @@ impl SessionRepository for PostgresSessionRepository {
         let result: Result<u64, SessionError> = Ok(0);
 
         #[cfg(not(test))]
-        let result: Result<u64, SessionError> = async {
-            let (is_valid, include_filter) = match filter {
-                SessionFilter::All => (true, false),
-                SessionFilter::Active => (true, true),
-                SessionFilter::Inactive => (false, true),
-            };
-
-            let result = sqlx::query!(
-                r#"
+        let result: Result<u64, SessionError> = within(
+            METRIC_INVALIDATE_FILTER,
+            self.config.timeouts.write,
+            async {
+                let (is_valid, include_filter) = match filter {
+                    SessionFilter::All => (true, false),
+                    SessionFilter::Active => (true, true),
+                    SessionFilter::Inactive => (false, true),
+                };
+
+                let result = sqlx::query!(
+                    r#"
                 UPDATE sessions
                 SET
                     is_valid = false,
@@ -842,26 +1454,27 @@ impl SessionRepository for PostgresSessionRepository {
                 WHERE $2 = false OR is_valid = $3
                 RETURNING id
                 "#,
-                reason as _,
-                include_filter,
-                is_valid
-            )
-            .fetch_all(&self.pool)
-            .await
-            .map_err(SessionError::Database)?;
-
-            Ok(result.len() as u64)
-        }
+                    reason as _,
+                    include_filter,
+                    is_valid
+                )
+                .fetch_all(&self.pool)
+                .await
+                .map_err(SessionError::Database)?;
+
+                Ok(result.len() as u64)
+            },
+        )
         .await;
 
         match &result {
             Ok(count) => {
                 tracing::info!(
                     invalidated_sessions = count,
-                    duration = ?start.elapsed().unwrap_or_default(),
+                    duration = ?start.elapsed(),
                     "Sessions invalidated successfully"
                 );
-                Self::record_metrics(METRIC_INVALIDATE, start);
+                Self::record_metrics(METRIC_INVALIDATE_FILTER, start);
             },
             Err(error) => {
                 tracing::error!(
@@ -870,7 +1483,7 @@ impl SessionRepository for PostgresSessionRepository {
                     error = ?error,
                     "Failed to invalidate sessions by filter"
                 );
-                Self::record_error_metrics(METRIC_INVALIDATE, error);
+                Self::record_error_metrics(METRIC_INVALIDATE_FILTER, start);
             },
         }
 
@@ -880,12 +1493,17 @@ impl SessionRepository for PostgresSessionRepository {
     /// Invalidate all sessions from a specific IP address
     ///
     /// This is useful for handling suspicious activities from a specific location.
+    #[instrument(
+        name = "session.repo.invalidate_sessions_by_ip",
+        skip_all,
+        level = "debug"
+    )]
     async fn invalidate_sessions_by_ip(
         &self,
         ip_address: &str,
         reason: SessionInvalidationReason,
     ) -> Result<u64, SessionError> {
-        let start = SystemTime::now();
+        let start = Instant::now();
         tracing::debug!(
             ip_address = ip_address,
             reason = ?reason,
@@ -897,10 +1515,11 @@ impl SessionRepository for PostgresSessionRepository {
         let result: Result<u64, SessionError> = Ok(0);
 
         #[cfg(not(test))]
-        let result: Result<u64, SessionError> = async {
-            // Convert to IpNetwork for PostgreSQL compatibility
-            let result = sqlx::query!(
-                r#"
+        let result: Result<u64, SessionError> =
+            within(METRIC_INVALIDATE_IP, self.config.timeouts.write, async {
+                // Convert to IpNetwork for PostgreSQL compatibility
+                let result = sqlx::query!(
+                    r#"
                 UPDATE sessions
                 SET
                     is_valid = false,
@@ -908,25 +1527,25 @@ impl SessionRepository for PostgresSessionRepository {
                 WHERE ip_address = $1 AND is_valid = true
                 RETURNING id
                 "#,
-                string_to_ip_network(Some(ip_address.to_string())),
-                reason as _
-            )
-            .fetch_all(&self.pool)
-            .await
-            .map_err(SessionError::Database)?;
-
-            Ok(result.len() as u64)
-        }
-        .await;
+                    string_to_ip_network(Some(ip_address.to_string())),
+                    reason as _
+                )
+                .fetch_all(&self.pool)
+                .await
+                .map_err(SessionError::Database)?;
+
+                Ok(result.len() as u64)
+            })
+            .await;
 
         match &result {
             Ok(count) => {
                 tracing::info!(
                     invalidated_sessions = count,
-                    duration = ?start.elapsed().unwrap_or_default(),
+                    duration = ?start.elapsed(),
                     "Sessions invalidated successfully"
                 );
-                Self::record_metrics(METRIC_INVALIDATE, start);
+                Self::record_metrics(METRIC_INVALIDATE_IP, start);
             },
             Err(error) => {
                 tracing::error!(
@@ -935,128 +1554,283 @@ impl SessionRepository for PostgresSessionRepository {
                     error = ?error,
                     "Failed to invalidate sessions by IP"
                 );
-                Self::record_error_metrics(METRIC_INVALIDATE, error);
+                Self::record_error_metrics(METRIC_INVALIDATE_IP, start);
             },
         }
 
         result
     }
 
-    async fn rotate_session_token(
+    #[instrument(
+        name = "session.repo.invalidate_sessions_by_region",
+        skip_all,
+        level = "debug"
+    )]
+    async fn invalidate_sessions_by_region(
         &self,
-        id: Uuid,
-        new_token_hash: String,
-    ) -> Result<(), SessionError> {
-        let start = SystemTime::now();
-        tracing::debug!(session_id = %id, "Rotating session token");
+        region: &str,
+        tenant_id: Option<Uuid>,
+        include_unknown: bool,
+        reason: SessionInvalidationReason,
+    ) -> Result<u64, SessionError> {
+        let start = Instant::now();
+        tracing::debug!(
+            region = region,
+            tenant_id = ?tenant_id,
+            include_unknown = include_unknown,
+            reason = ?reason,
+            "Invalidating sessions by region"
+        );
 
-        let result: Result<(), SessionError> = async {
-            let result = sqlx::query!(
-                r#"
-                UPDATE sessions
-                SET
-                    token_hash = $2,
-                    previous_token_hash = token_hash,
-                    token_rotation_at = CURRENT_TIMESTAMP
-                WHERE id = $1 AND is_valid = true
-                RETURNING id
-                "#,
-                id,
-                new_token_hash
-            )
-            .fetch_optional(&self.pool)
-            .await
-            .map_err(SessionError::Database)?;
-
-            match result {
-                Some(_) => Ok(()),
-                None => Err(SessionError::NotFound),
-            }
-        }
+        let result: Result<u64, SessionError> = within(
+            METRIC_INVALIDATE_REGION,
+            self.config.timeouts.write,
+            async {
+                let result = sqlx::query(
+                    r#"
+                    UPDATE sessions
+                    SET
+                        is_valid = false,
+                        invalidated_reason = $4::session_invalidation_reason
+                    WHERE is_valid = true
+                      AND (region = $1 OR ($3 AND region IS NULL))
+                      AND ($2::text IS NULL
+                           OR COALESCE(metadata->>'active_tenant_id', metadata->>'tenant_id') = $2)
+                    "#,
+                )
+                .bind(region)
+                .bind(tenant_id.map(|id| id.to_string()))
+                .bind(include_unknown)
+                .bind(reason.clone())
+                .execute(&self.pool)
+                .await?;
+
+                Ok(result.rows_affected())
+            },
+        )
         .await;
 
         match &result {
-            Ok(_) => {
+            Ok(count) => {
                 tracing::info!(
-                    session_id = %id,
-                    "Session token rotated successfully"
+                    invalidated_sessions = count,
+                    duration = ?start.elapsed(),
+                    "Sessions invalidated successfully"
                 );
-                Self::record_metrics(METRIC_ROTATE_TOKEN, start);
+                Self::record_metrics(METRIC_INVALIDATE_REGION, start);
             },
             Err(error) => {
                 tracing::error!(
-                    session_id = %id,
+                    region = region,
+                    reason = ?reason,
                     error = ?error,
-                    "Failed to rotate session token"
+                    "Failed to invalidate sessions by region"
                 );
-                Self::record_error_metrics(METRIC_ROTATE_TOKEN, error);
+                Self::record_error_metrics(METRIC_INVALIDATE_REGION, start);
             },
         }
 
         result
     }
 
-    async fn cleanup_expired_sessions(&self) -> Result<u64, SessionError> {
-        let start = SystemTime::now();
-        tracing::debug!("Starting expired sessions cleanup");
+    #[instrument(
+        name = "session.repo.invalidate_tenant_sessions",
+        skip_all,
+        level = "debug"
+    )]
+    async fn invalidate_tenant_sessions(
+        &self,
+        tenant_id: Uuid,
+        reason: SessionInvalidationReason,
+    ) -> Result<u64, SessionError> {
+        let start = Instant::now();
+        tracing::debug!(tenant_id = %tenant_id, reason = ?reason, "Invalidating tenant sessions");
+
+        let result: Result<u64, SessionError> = within(
+            METRIC_INVALIDATE_TENANT,
+            self.config.timeouts.write,
+            async {
+                let result = sqlx::query(
+                    r#"
+                    UPDATE sessions
+                    SET
+                        is_valid = false,
+                        invalidated_reason = $2::session_invalidation_reason
+                    WHERE is_valid = true
+                      AND COALESCE(metadata->>'active_tenant_id', metadata->>'tenant_id') = $1
+                    "#,
+                )
+                .bind(tenant_id.to_string())
+                .bind(reason.clone())
+                .execute(&self.pool)
+                .await?;
 
-        let result: Result<u64, SessionError> = async {
-            // First, invalidate expired sessions
-            let invalidated = sqlx::query!(
-                r#"
+                Ok(result.rows_affected())
+            },
+        )
+        .await;
+
+        match &result {
+            Ok(count) => {
+                tracing::info!(
+                    invalidated_sessions = count,
+                    duration = ?start.elapsed(),
+                    "Sessions invalidated successfully"
+                );
+                Self::record_metrics(METRIC_INVALIDATE_TENANT, start);
+            },
+            Err(error) => {
+                tracing::error!(
+                    tenant_id = %tenant_id,
+                    reason = ?reason,
+                    error = ?error,
+                    "Failed to invalidate tenant sessions"
+                );
+                Self::record_error_metrics(METRIC_INVALIDATE_TENANT, start);
+            },
+        }
+
+        result
+    }
+
+    #[instrument(name = "session.repo.rotate_session_token", skip_all, level = "debug")]
+    async fn rotate_session_token(
+        &self,
+        id: Uuid,
+        new_token_hash: String,
+    ) -> Result<(), SessionError> {
+        let start = Instant::now();
+        tracing::debug!(session_id = %id, "Rotating session token");
+
+        let result: Result<(), SessionError> =
+            within(METRIC_ROTATE_TOKEN, self.config.timeouts.write, async {
+                let result = sqlx::query!(
+                    r#"
                 UPDATE sessions
                 SET
-                    is_valid = false,
-                    invalidated_reason = 'TOKEN_EXPIRED'::session_invalidation_reason
-                WHERE
-                    is_valid = true
-                    AND expires_at < CURRENT_TIMESTAMP
-                "#
-            )
-            .execute(&self.pool)
-            .await
-            .map_err(SessionError::Database)?;
-
-            // Then, delete old invalid sessions and their audit logs
-            let deleted = sqlx::query!(
-                r#"
-                WITH deleted_sessions AS (
-                    DELETE FROM sessions
-                    WHERE
-                        is_valid = false
-                        AND last_activity_at < CURRENT_TIMESTAMP - make_interval(secs => $1)
-                    RETURNING id
-                )
-                SELECT COUNT(*) as "count!"
-                FROM deleted_sessions
-                "#,
-                self.config.invalid_session_retention.as_secs() as i64
-            )
-            .fetch_one(&self.pool)
-            .await
-            .map_err(SessionError::Database)?;
-
-            // Also cleanup old audit logs
-            sqlx::query!(
-                r#"
-                DELETE FROM session_audit_log
-                WHERE created_at < CURRENT_TIMESTAMP - make_interval(secs => $1)
+                    token_hash = $2,
+                    previous_token_hash = token_hash,
+                    token_rotation_at = CURRENT_TIMESTAMP
+                WHERE id = $1 AND is_valid = true
+                RETURNING id
                 "#,
-                self.config.audit_log_retention.as_secs() as i64
-            )
-            .execute(&self.pool)
-            .await
-            .map_err(SessionError::Database)?;
+                    id,
+                    new_token_hash
+                )
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(SessionError::Database)?;
+
+                match result {
+                    Some(_) => Ok(()),
+                    None => Err(SessionError::NotFound),
+                }
+            })
+            .await;
 
-            Ok(invalidated.rows_affected() + deleted.count as u64)
+        match &result {
+            Ok(_) => {
+                tracing::info!(
+                    session_id = %id,
+                    "Session token rotated successfully"
+                );
+                Self::record_metrics(METRIC_ROTATE_TOKEN, start);
+            },
+            Err(error) => {
+                tracing::error!(
+                    session_id = %id,
+                    error = ?error,
+                    "Failed to rotate session token"
+                );
+                Self::record_error_metrics(METRIC_ROTATE_TOKEN, start);
+            },
         }
-        .await;
+
+        result
+    }
+
+    #[instrument(
+        name = "session.repo.cleanup_expired_sessions",
+        skip_all,
+        level = "debug"
+    )]
+    async fn cleanup_expired_sessions(&self) -> Result<u64, SessionError> {
+        let start = Instant::now();
+        tracing::debug!("Starting expired sessions cleanup");
+
+        let result: Result<u64, SessionError> =
+            within(METRIC_CLEANUP, self.config.timeouts.long_running, async {
+                let batch = &self.config.cleanup_batch;
+
+                // First, invalidate expired sessions
+                let invalidated = run_in_batches(batch, |limit| async move {
+                    sqlx::query(INVALIDATE_EXPIRED_SESSIONS_QUERY)
+                        .bind(limit)
+                        .execute(&self.pool)
+                        .await
+                        .map(|result| result.rows_affected())
+                        .map_err(SessionError::Database)
+                })
+                .await?;
+
+                // Then, delete old invalid sessions and their audit logs
+                let invalid_retention = self.config.invalid_session_retention.as_secs() as i64;
+                let deleted = run_in_batches(batch, |limit| async move {
+                    sqlx::query(DELETE_INVALID_SESSIONS_QUERY)
+                        .bind(invalid_retention)
+                        .bind(limit)
+                        .execute(&self.pool)
+                        .await
+                        .map(|result| result.rows_affected())
+                        .map_err(SessionError::Database)
+                })
+                .await?;
+
+                // Guest sessions are deleted once abandoned, whether still valid or not
+                let guest_retention = self.config.guest_session_retention.as_secs() as i64;
+                let deleted_guests = run_in_batches(batch, |limit| async move {
+                    sqlx::query(DELETE_STALE_GUEST_SESSIONS_QUERY)
+                        .bind(guest_retention)
+                        .bind(limit)
+                        .execute(&self.pool)
+                        .await
+                        .map(|result| result.rows_affected())
+                        .map_err(SessionError::Database)
+                })
+                .await?;
+
+                // Also cleanup old audit logs
+                let audit_retention = self.config.audit_log_retention.as_secs() as i64;
+                run_in_batches(batch, |limit| async move {
+                    sqlx::query(
+                        r#"
+                    DELETE FROM session_audit_log
+                    WHERE id IN (
+                        SELECT id FROM session_audit_log
+                        WHERE created_at < CURRENT_TIMESTAMP - make_interval(secs => $1)
+                        LIMIT $2
+                        FOR UPDATE SKIP LOCKED
+                    )
+                    "#,
+                    )
+                    .bind(audit_retention)
+                    .bind(limit)
+                    .execute(&self.pool)
+                    .await
+                    .map(|result| result.rows_affected())
+                    .map_err(SessionError::Database)
+                })
+                .await?;
+
+                Ok(invalidated + deleted + deleted_guests)
+            })
+            .await;
 
         match &result {
             Ok(count) => {
                 tracing::info!(
                     cleaned_sessions = count,
-                    duration = ?start.elapsed().unwrap_or_default(),
+                    duration = ?start.elapsed(),
                     "Session cleanup completed successfully"
                 );
                 Self::record_metrics(METRIC_CLEANUP, start);
@@ -1066,39 +1840,41 @@ impl SessionRepository for PostgresSessionRepository {
                     error = ?error,
                     "Failed to cleanup expired sessions"
                 );
-                Self::record_error_metrics(METRIC_CLEANUP, error);
+                Self::record_error_metrics(METRIC_CLEANUP, start);
             },
         }
 
         result
     }
 
+    #[instrument(name = "session.repo.update_mfa_status", skip_all, level = "debug")]
     async fn update_mfa_status(&self, id: Uuid, status: MfaStatus) -> Result<(), SessionError> {
-        let start = SystemTime::now();
+        let start = Instant::now();
         tracing::debug!(session_id = %id, status = ?status, "Updating session MFA status");
 
-        let result: Result<(), SessionError> = async {
-            // Use regular query instead of macro to avoid type issues
-            let result = sqlx::query(
-                r#"
+        let result: Result<(), SessionError> =
+            within(METRIC_UPDATE_MFA, self.config.timeouts.write, async {
+                // Use regular query instead of macro to avoid type issues
+                let result = sqlx::query(
+                    r#"
                 UPDATE sessions
                 SET mfa_status = $2
                 WHERE id = $1 AND is_valid = true
                 RETURNING id
                 "#,
-            )
-            .bind(id)
-            .bind(status.to_string())
-            .fetch_optional(&self.pool)
-            .await
-            .map_err(SessionError::Database)?;
-
-            match result {
-                Some(_) => Ok(()),
-                None => Err(SessionError::NotFound),
-            }
-        }
-        .await;
+                )
+                .bind(id)
+                .bind(status.to_string())
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(SessionError::Database)?;
+
+                match result {
+                    Some(_) => Ok(()),
+                    None => Err(SessionError::NotFound),
+                }
+            })
+            .await;
 
         match &result {
             Ok(_) => {
@@ -1116,18 +1892,643 @@ impl SessionRepository for PostgresSessionRepository {
                     error = ?error,
                     "Failed to update session MFA status"
                 );
-                Self::record_error_metrics(METRIC_UPDATE_MFA, error);
+                Self::record_error_metrics(METRIC_UPDATE_MFA, start);
+            },
+        }
+
+        result
+    }
+
+    #[instrument(name = "session.repo.set_active_tenant", skip_all, level = "debug")]
+    async fn set_active_tenant(&self, id: Uuid, tenant_id: Uuid) -> Result<(), SessionError> {
+        let start = Instant::now();
+        tracing::debug!(session_id = %id, tenant_id = %tenant_id, "Setting active tenant");
+
+        let result: Result<(), SessionError> = within(
+            METRIC_SET_ACTIVE_TENANT,
+            self.config.timeouts.write,
+            async {
+                let mut tx = self.pool.begin().await.map_err(SessionError::Database)?;
+
+                let previous = sqlx::query(
+                    r#"
+                SELECT user_id,
+                       metadata->>'active_tenant_id' AS previous_tenant_id,
+                       metadata->>'impersonated_by' AS impersonated_by
+                FROM sessions
+                WHERE id = $1 AND is_valid = true
+                FOR UPDATE
+                "#,
+                )
+                .bind(id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(SessionError::Database)?
+                .ok_or(SessionError::NotFound)?;
+                let user_id: Uuid = previous.try_get("user_id")?;
+                let previous_tenant_id: Option<String> = previous.try_get("previous_tenant_id")?;
+                let impersonated_by: Option<String> = previous.try_get("impersonated_by")?;
+
+                sqlx::query(
+                    r#"
+                UPDATE sessions
+                SET metadata = jsonb_set(
+                    COALESCE(metadata, '{}'::jsonb),
+                    '{active_tenant_id}',
+                    to_jsonb($2::text)
+                )
+                WHERE id = $1
+                "#,
+                )
+                .bind(id)
+                .bind(tenant_id.to_string())
+                .execute(&mut *tx)
+                .await
+                .map_err(SessionError::Database)?;
+
+                sqlx::query(
+                    r#"
+                INSERT INTO session_audit_log (session_id, user_id, action, details)
+                VALUES (
+                    $1, $2, 'TENANT_SWITCHED',
+                    jsonb_strip_nulls(jsonb_build_object(
+                        'from_tenant_id', $3::text,
+                        'to_tenant_id', $4::text,
+                        'impersonated_by', $5::text
+                    ))
+                )
+                "#,
+                )
+                .bind(id)
+                .bind(user_id)
+                .bind(previous_tenant_id)
+                .bind(tenant_id.to_string())
+                .bind(impersonated_by)
+                .execute(&mut *tx)
+                .await
+                .map_err(SessionError::Database)?;
+
+                tx.commit().await.map_err(SessionError::Database)
+            },
+        )
+        .await;
+
+        match &result {
+            Ok(_) => {
+                tracing::info!(
+                    session_id = %id,
+                    tenant_id = %tenant_id,
+                    "Active tenant of session updated"
+                );
+                Self::record_metrics(METRIC_SET_ACTIVE_TENANT, start);
+            },
+            Err(error) => {
+                tracing::error!(
+                    session_id = %id,
+                    tenant_id = %tenant_id,
+                    error = ?error,
+                    "Failed to set active tenant of session"
+                );
+                Self::record_error_metrics(METRIC_SET_ACTIVE_TENANT, start);
+            },
+        }
+
+        result
+    }
+
+    #[instrument(name = "session.repo.record_authentication", skip_all, level = "debug")]
+    async fn record_authentication(
+        &self,
+        id: Uuid,
+        at: OffsetDateTime,
+    ) -> Result<(), SessionError> {
+        let start = Instant::now();
+        tracing::debug!(session_id = %id, "Recording session authentication");
+
+        let authenticated_at = at.unix_timestamp();
+
+        let result: Result<(), SessionError> = within(
+            METRIC_RECORD_AUTHENTICATION,
+            self.config.timeouts.write,
+            async {
+                let result = sqlx::query(
+                    r#"
+                UPDATE sessions
+                SET metadata = jsonb_set(
+                    COALESCE(metadata, '{}'::jsonb),
+                    '{last_auth_at}',
+                    to_jsonb($2::bigint)
+                )
+                WHERE id = $1 AND is_valid = true
+                RETURNING id
+                "#,
+                )
+                .bind(id)
+                .bind(authenticated_at)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(SessionError::Database)?;
+
+                match result {
+                    Some(_) => Ok(()),
+                    None => Err(SessionError::NotFound),
+                }
+            },
+        )
+        .await;
+
+        match &result {
+            Ok(_) => {
+                tracing::info!(session_id = %id, "Session authentication recorded");
+                Self::record_metrics(METRIC_RECORD_AUTHENTICATION, start);
+            },
+            Err(error) => {
+                tracing::error!(
+                    session_id = %id,
+                    error = ?error,
+                    "Failed to record session authentication"
+                );
+                Self::record_error_metrics(METRIC_RECORD_AUTHENTICATION, start);
+            },
+        }
+
+        result
+    }
+    #[instrument(name = "session.repo.record_client_binding", skip_all, level = "debug")]
+    async fn record_client_binding(
+        &self,
+        id: Uuid,
+        binding_hash: &str,
+    ) -> Result<(), SessionError> {
+        let start = Instant::now();
+        tracing::debug!(session_id = %id, "Recording session client binding");
+
+        let result: Result<(), SessionError> = within(
+            METRIC_RECORD_CLIENT_BINDING,
+            self.config.timeouts.write,
+            async {
+                let result = sqlx::query(
+                    r#"
+                UPDATE sessions
+                SET metadata = jsonb_set(
+                    COALESCE(metadata, '{}'::jsonb),
+                    '{client_binding}',
+                    to_jsonb($2::text)
+                )
+                WHERE id = $1 AND is_valid = true
+                RETURNING id
+                "#,
+                )
+                .bind(id)
+                .bind(binding_hash)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(SessionError::Database)?;
+
+                match result {
+                    Some(_) => Ok(()),
+                    None => Err(SessionError::NotFound),
+                }
+            },
+        )
+        .await;
+
+        match &result {
+            Ok(_) => {
+                tracing::info!(session_id = %id, "Session bound to client");
+                Self::record_metrics(METRIC_RECORD_CLIENT_BINDING, start);
+            },
+            Err(error) => {
+                tracing::error!(
+                    session_id = %id,
+                    error = ?error,
+                    "Failed to record session client binding"
+                );
+                Self::record_error_metrics(METRIC_RECORD_CLIENT_BINDING, start);
+            },
+        }
+
+        result
+    }
+
+    #[instrument(
+        name = "session.repo.merge_session_metadata",
+        skip_all,
+        level = "debug"
+    )]
+    async fn merge_session_metadata(&self, id: Uuid, patch: Value) -> Result<(), SessionError> {
+        let start = Instant::now();
+        tracing::debug!(session_id = %id, "Merging session metadata");
+
+        let result: Result<(), SessionError> =
+            within(METRIC_MERGE_METADATA, self.config.timeouts.write, async {
+                let result = sqlx::query(
+                    r#"
+                UPDATE sessions
+                SET metadata = COALESCE(metadata, '{}'::jsonb) || $2
+                WHERE id = $1 AND is_valid = true
+                RETURNING id
+                "#,
+                )
+                .bind(id)
+                .bind(patch)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(SessionError::Database)?;
+
+                match result {
+                    Some(_) => Ok(()),
+                    None => Err(SessionError::NotFound),
+                }
+            })
+            .await;
+
+        match &result {
+            Ok(_) => Self::record_metrics(METRIC_MERGE_METADATA, start),
+            Err(error) => {
+                tracing::error!(
+                    session_id = %id,
+                    error = ?error,
+                    "Failed to merge session metadata"
+                );
+                Self::record_error_metrics(METRIC_MERGE_METADATA, start);
+            },
+        }
+
+        result
+    }
+
+    #[instrument(
+        name = "session.repo.record_mfa_verification",
+        skip_all,
+        level = "debug"
+    )]
+    async fn record_mfa_verification(
+        &self,
+        id: Uuid,
+        at: OffsetDateTime,
+    ) -> Result<(), SessionError> {
+        let start = Instant::now();
+        tracing::debug!(session_id = %id, "Recording session MFA verification");
+
+        let verified_at = at.unix_timestamp();
+
+        let result: Result<(), SessionError> = within(
+            METRIC_RECORD_MFA_VERIFICATION,
+            self.config.timeouts.write,
+            async {
+                let mut tx = self.pool.begin().await.map_err(SessionError::Database)?;
+
+                let updated = sqlx::query(
+                    r#"
+                UPDATE sessions
+                SET metadata = COALESCE(metadata, '{}'::jsonb)
+                    || jsonb_build_object('mfa_verified_at', $2::bigint, 'last_auth_at', $2::bigint)
+                WHERE id = $1 AND is_valid = true
+                RETURNING user_id,
+                          mfa_status::text AS mfa_status,
+                          metadata->>'impersonated_by' AS impersonated_by
+                "#,
+                )
+                .bind(id)
+                .bind(verified_at)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(SessionError::Database)?
+                .ok_or(SessionError::NotFound)?;
+                let user_id: Uuid = updated.try_get("user_id")?;
+                let mfa_status: String = updated.try_get("mfa_status")?;
+                let impersonated_by: Option<String> = updated.try_get("impersonated_by")?;
+
+                sqlx::query(
+                    r#"
+                INSERT INTO session_audit_log (session_id, user_id, action, details)
+                VALUES (
+                    $1, $2, 'MFA_VERIFIED',
+                    jsonb_strip_nulls(jsonb_build_object(
+                        'mfa_status', $3::text,
+                        'impersonated_by', $4::text
+                    ))
+                )
+                "#,
+                )
+                .bind(id)
+                .bind(user_id)
+                .bind(mfa_status)
+                .bind(impersonated_by)
+                .execute(&mut *tx)
+                .await
+                .map_err(SessionError::Database)?;
+
+                tx.commit().await.map_err(SessionError::Database)
+            },
+        )
+        .await;
+
+        match &result {
+            Ok(_) => {
+                tracing::info!(session_id = %id, "Session MFA verification recorded");
+                Self::record_metrics(METRIC_RECORD_MFA_VERIFICATION, start);
+            },
+            Err(error) => {
+                tracing::error!(
+                    session_id = %id,
+                    error = ?error,
+                    "Failed to record session MFA verification"
+                );
+                Self::record_error_metrics(METRIC_RECORD_MFA_VERIFICATION, start);
+            },
+        }
+
+        result
+    }
+    #[instrument(
+        name = "session.repo.record_impersonation_event",
+        skip_all,
+        level = "debug"
+    )]
+    async fn record_impersonation_event(
+        &self,
+        id: Uuid,
+        action: ImpersonationAction,
+    ) -> Result<(), SessionError> {
+        let start = Instant::now();
+        tracing::debug!(session_id = %id, action = action.as_str(), "Recording impersonation event");
+
+        let result: Result<(), SessionError> = within(
+            METRIC_RECORD_IMPERSONATION,
+            self.config.timeouts.write,
+            async {
+                // One entry for the impersonated user and one for the impersonator
+                let inserted = sqlx::query(
+                    r#"
+                INSERT INTO session_audit_log (session_id, user_id, action, details)
+                SELECT s.id, actor.user_id, $2, jsonb_build_object(
+                    'impersonated_by', s.metadata->>'impersonated_by',
+                    'target_user_id', s.user_id::text,
+                    'reason', s.metadata->>'reason'
+                )
+                FROM sessions s
+                CROSS JOIN LATERAL (
+                    VALUES (s.user_id), ((s.metadata->>'impersonated_by')::uuid)
+                ) AS actor(user_id)
+                WHERE s.id = $1 AND s.metadata ? 'impersonated_by'
+                "#,
+                )
+                .bind(id)
+                .bind(action.as_str())
+                .execute(&self.pool)
+                .await
+                .map_err(SessionError::Database)?;
+
+                if inserted.rows_affected() == 0 {
+                    return Err(SessionError::NotFound);
+                }
+                Ok(())
+            },
+        )
+        .await;
+
+        match &result {
+            Ok(_) => {
+                tracing::info!(
+                    session_id = %id,
+                    action = action.as_str(),
+                    "Impersonation event recorded"
+                );
+                Self::record_metrics(METRIC_RECORD_IMPERSONATION, start);
+            },
+            Err(error) => {
+                tracing::error!(
+                    session_id = %id,
+                    action = action.as_str(),
+                    error = ?error,
+                    "Failed to record impersonation event"
+                );
+                Self::record_error_metrics(METRIC_RECORD_IMPERSONATION, start);
+            },
+        }
+
+        result
+    }
+
+    #[instrument(name = "session.repo.record_factor_event", skip_all, level = "debug")]
+    async fn record_factor_event(
+        &self,
+        id: Uuid,
+        action: FactorAction,
+        factor: &str,
+    ) -> Result<(), SessionError> {
+        let start = Instant::now();
+        tracing::debug!(session_id = %id, action = action.as_str(), factor, "Recording factor event");
+
+        let result: Result<(), SessionError> = within(
+            METRIC_RECORD_FACTOR_EVENT,
+            self.config.timeouts.write,
+            async {
+                let inserted = sqlx::query(
+                    r#"
+                INSERT INTO session_audit_log (session_id, user_id, action, details)
+                SELECT s.id, s.user_id, $2, jsonb_strip_nulls(jsonb_build_object(
+                    'factor', $3::text,
+                    'impersonated_by', s.metadata->>'impersonated_by'
+                ))
+                FROM sessions s
+                WHERE s.id = $1 AND s.is_valid = true
+                "#,
+                )
+                .bind(id)
+                .bind(action.as_str())
+                .bind(factor)
+                .execute(&self.pool)
+                .await
+                .map_err(SessionError::Database)?;
+
+                if inserted.rows_affected() == 0 {
+                    return Err(SessionError::NotFound);
+                }
+                Ok(())
+            },
+        )
+        .await;
+
+        match &result {
+            Ok(_) => {
+                tracing::info!(
+                    session_id = %id,
+                    action = action.as_str(),
+                    factor,
+                    "Factor event recorded"
+                );
+                Self::record_metrics(METRIC_RECORD_FACTOR_EVENT, start);
+            },
+            Err(error) => {
+                tracing::error!(
+                    session_id = %id,
+                    action = action.as_str(),
+                    factor,
+                    error = ?error,
+                    "Failed to record factor event"
+                );
+                Self::record_error_metrics(METRIC_RECORD_FACTOR_EVENT, start);
+            },
+        }
+
+        result
+    }
+
+    fn stream_sessions(
+        &self,
+        filter: SessionExportFilter,
+    ) -> BoxStream<'static, Result<Session, SessionError>> {
+        tracing::debug!(
+            tenant_id = %filter.tenant_id,
+            filter = ?filter.status,
+            limit = filter.limit,
+            "Streaming sessions"
+        );
+
+        let pool = self.pool.clone();
+        let budget = self.config.timeouts.read;
+        let page_size = u64::from(filter.page_size.max(1));
+        let start = (None::<(OffsetDateTime, Uuid)>, filter.limit);
+
+        stream::try_unfold(start, move |(cursor, remaining)| {
+            let pool = pool.clone();
+            let filter = filter.clone();
+            async move {
+                if remaining == 0 {
+                    return Ok::<_, SessionError>(None);
+                }
+
+                let limit = page_size.min(remaining);
+                let started = Instant::now();
+                let page = within(
+                    METRIC_STREAM,
+                    budget,
+                    Self::fetch_export_page(&pool, &filter, cursor, limit as i64),
+                )
+                .await
+                .inspect_err(|error| {
+                    tracing::error!(
+                        tenant_id = %filter.tenant_id,
+                        error = ?error,
+                        "Failed to read page of exported sessions"
+                    );
+                    Self::record_error_metrics(METRIC_STREAM, started);
+                })?;
+                Self::record_metrics(METRIC_STREAM, started);
+
+                let Some(last) = page.last() else {
+                    return Ok(None);
+                };
+                let next_cursor = (last.created_at, last.id);
+                // A short page is the last one
+                let remaining = if (page.len() as u64) < limit {
+                    0
+                } else {
+                    remaining - limit
+                };
+
+                let page = stream::iter(page.into_iter().map(Ok));
+                Ok(Some((page, (Some(next_cursor), remaining))))
+            }
+        })
+        .try_flatten()
+        .boxed()
+    }
+
+    #[instrument(name = "session.repo.valid_sessions_after", skip_all, level = "debug")]
+    async fn valid_sessions_after(
+        &self,
+        after: Option<Uuid>,
+        limit: u32,
+    ) -> Result<Vec<Session>, SessionError> {
+        let start = Instant::now();
+        let result: Result<Vec<Session>, SessionError> = within(
+            METRIC_RECONCILIATION_PAGE,
+            self.config.timeouts.read,
+            async {
+                let rows = sqlx::query(VALID_SESSIONS_PAGE_QUERY)
+                    .bind(after)
+                    .bind(i64::from(limit))
+                    .fetch_all(&self.pool)
+                    .await?;
+
+                rows.iter()
+                    .map(|row| Self::session_from_row(row).map_err(SessionError::Database))
+                    .collect()
+            },
+        )
+        .await;
+
+        match &result {
+            Ok(_) => Self::record_metrics(METRIC_RECONCILIATION_PAGE, start),
+            Err(error) => {
+                tracing::error!(
+                    after = ?after,
+                    error = ?error,
+                    "Failed to read page of valid sessions"
+                );
+                Self::record_error_metrics(METRIC_RECONCILIATION_PAGE, start);
             },
         }
 
         result
     }
+
+    #[instrument(name = "session.repo.reconciliation_cursor", skip_all, level = "debug")]
+    async fn reconciliation_cursor(&self) -> Result<Option<Uuid>, SessionError> {
+        observe_within(
+            "session.repo.reconciliation_cursor",
+            self.config.timeouts.read,
+            async {
+                let cursor = sqlx::query_scalar::<_, Option<Uuid>>(
+                    "SELECT last_session_id FROM session_reconciliation_progress WHERE job = $1",
+                )
+                .bind(RECONCILIATION_JOB)
+                .fetch_optional(&self.pool)
+                .await?;
+
+                Ok(cursor.flatten())
+            },
+        )
+        .await
+    }
+
+    #[instrument(
+        name = "session.repo.save_reconciliation_cursor",
+        skip_all,
+        level = "debug"
+    )]
+    async fn save_reconciliation_cursor(&self, cursor: Option<Uuid>) -> Result<(), SessionError> {
+        observe_within(
+            "session.repo.save_reconciliation_cursor",
+            self.config.timeouts.write,
+            async {
+                sqlx::query(
+                    r#"
+                INSERT INTO session_reconciliation_progress (job, last_session_id, updated_at)
+                VALUES ($1, $2, CURRENT_TIMESTAMP)
+                ON CONFLICT (job) DO UPDATE
+                SET last_session_id = EXCLUDED.last_session_id, updated_at = EXCLUDED.updated_at
+                "#,
+                )
+                .bind(RECONCILIATION_JOB)
+                .bind(cursor)
+                .execute(&self.pool)
+                .await?;
+
+                Ok(())
+            },
+        )
+        .await
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::time::Duration;
 
     #[test]
     fn test_session_validity() {
@@ -1135,15 +2536,18 @@ mod tests {
         let session = Session {
             id: Uuid::new_v4(),
             user_id,
+            kind: SessionKind::User,
             token_hash: "test_token_hash".to_string(),
             previous_token_hash: None,
             token_rotation_at: None,
-            expires_at: SystemTime::now() + Duration::from_secs(3600),
-            created_at: SystemTime::now(),
-            last_activity_at: SystemTime::now(),
+            expires_at: OffsetDateTime::now_utc() + time::Duration::hours(1),
+            created_at: OffsetDateTime::now_utc(),
+            last_activity_at: OffsetDateTime::now_utc(),
             last_activity_update_at: None,
             ip_address: Some("127.0.0.1".to_string()),
             user_agent: Some("Test Agent".to_string()),
+            user_agent_details: None,
+            region: None,
             device_id: Some("test_device".to_string()),
             device_fingerprint: None,
             is_valid: true,
@@ -1162,15 +2566,18 @@ mod tests {
         let session = Session {
             id: Uuid::new_v4(),
             user_id: Uuid::new_v4(),
+            kind: SessionKind::User,
             token_hash: "test_token_hash".to_string(),
             previous_token_hash: None,
             token_rotation_at: None,
-            expires_at: SystemTime::now(),
-            created_at: SystemTime::now(),
-            last_activity_at: SystemTime::now(),
+            expires_at: OffsetDateTime::now_utc(),
+            created_at: OffsetDateTime::now_utc(),
+            last_activity_at: OffsetDateTime::now_utc(),
             last_activity_update_at: None,
             ip_address: Some("127.0.0.1".to_string()),
             user_agent: Some("Test Agent".to_string()),
+            user_agent_details: None,
+            region: None,
             device_id: Some("test_device".to_string()),
             device_fingerprint: None,
             is_valid: true,
@@ -1179,7 +2586,7 @@ mod tests {
             mfa_status: MfaStatus::None,
         };
 
-        assert!(SystemTime::now() >= session.expires_at);
+        assert!(OffsetDateTime::now_utc() >= session.expires_at);
     }
 
     #[test]
@@ -1212,11 +2619,22 @@ mod tests {
             Duration::from_secs(90 * 24 * 60 * 60)
         );
         assert_eq!(config.activity_update_interval, Duration::from_secs(5 * 60));
+        assert_eq!(
+            config.guest_session_retention,
+            Duration::from_secs(24 * 60 * 60)
+        );
+        assert_eq!(config.cleanup_batch, BatchConfig::default());
 
         let custom_config = SessionRepositoryConfig {
             invalid_session_retention: Duration::from_secs(30 * 24 * 60 * 60),
+            guest_session_retention: Duration::from_secs(60 * 60),
             audit_log_retention: Duration::from_secs(60 * 24 * 60 * 60),
             activity_update_interval: Duration::from_secs(10 * 60),
+            cleanup_batch: BatchConfig {
+                batch_size: 100,
+                delay: Duration::ZERO,
+            },
+            timeouts: OperationTimeouts::default(),
         };
 
         assert_eq!(
@@ -1243,5 +2661,13 @@ mod tests {
         assert_eq!(SessionError::Expired.metric_name(), "expired");
         assert_eq!(SessionError::Invalid.metric_name(), "invalid");
         assert_eq!(SessionError::TokenMismatch.metric_name(), "token_mismatch");
+        assert_eq!(
+            SessionError::from(OperationTimedOut {
+                operation: METRIC_GET,
+                budget: Duration::from_secs(5),
+            })
+            .metric_name(),
+            "timeout"
+        );
     }
 }
@@ -7,9 +7,10 @@ use sqlx::{
     postgres::{PgArgumentBuffer, PgTypeInfo},
 };
 use std::fmt;
+use uuid::Uuid;
 
 /// Multi-factor authentication status
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum MfaStatus {
     /// No MFA required for this session
@@ -18,6 +19,8 @@ pub enum MfaStatus {
     Required,
     /// MFA has been verified
     Verified,
+    /// MFA enrollment is mandatory but not completed; only enrollment is permitted
+    Pending,
 }
 
 // Add SQLx Type implementation for PostgreSQL
@@ -37,6 +40,7 @@ impl fmt::Display for MfaStatus {
             MfaStatus::None => write!(f, "NONE"),
             MfaStatus::Required => write!(f, "REQUIRED"),
             MfaStatus::Verified => write!(f, "VERIFIED"),
+            MfaStatus::Pending => write!(f, "PENDING"),
         }
     }
 }
@@ -49,6 +53,7 @@ impl Encode<'_, Postgres> for MfaStatus {
             MfaStatus::None => "NONE",
             MfaStatus::Required => "REQUIRED",
             MfaStatus::Verified => "VERIFIED",
+            MfaStatus::Pending => "PENDING",
         };
 
         // Encode as a string
@@ -64,6 +69,7 @@ impl<'r> Decode<'r, Postgres> for MfaStatus {
             "NONE" => Ok(MfaStatus::None),
             "REQUIRED" => Ok(MfaStatus::Required),
             "VERIFIED" => Ok(MfaStatus::Verified),
+            "PENDING" => Ok(MfaStatus::Pending),
             _ => Err(format!("Unknown MFA status: {}", s).into()),
         }
     }
@@ -89,6 +95,14 @@ pub enum SessionInvalidationReason {
     ComplianceRequirement,
     SecurityPolicyChange,
     EmergencyTermination,
+    /// A request did not match the client the session is bound to
+    ClientBindingMismatch,
+    /// The user was deactivated or deleted
+    UserDeactivated,
+    /// The guest session was replaced by the session of the user who logged in
+    GuestUpgraded,
+    /// The tenant the session is bound to was archived
+    TenantArchived,
 }
 
 // Add SQLx Type implementation for PostgreSQL
@@ -124,6 +138,10 @@ impl Encode<'_, Postgres> for SessionInvalidationReason {
             SessionInvalidationReason::ComplianceRequirement => "COMPLIANCE_REQUIREMENT",
             SessionInvalidationReason::SecurityPolicyChange => "SECURITY_POLICY_CHANGE",
             SessionInvalidationReason::EmergencyTermination => "EMERGENCY_TERMINATION",
+            SessionInvalidationReason::ClientBindingMismatch => "CLIENT_BINDING_MISMATCH",
+            SessionInvalidationReason::UserDeactivated => "USER_DEACTIVATED",
+            SessionInvalidationReason::GuestUpgraded => "GUEST_UPGRADED",
+            SessionInvalidationReason::TenantArchived => "TENANT_ARCHIVED",
         };
 
         // Encode as a string with explicit type annotation for Postgres
@@ -153,6 +171,10 @@ impl<'r> Decode<'r, Postgres> for SessionInvalidationReason {
             "COMPLIANCE_REQUIREMENT" => Ok(SessionInvalidationReason::ComplianceRequirement),
             "SECURITY_POLICY_CHANGE" => Ok(SessionInvalidationReason::SecurityPolicyChange),
             "EMERGENCY_TERMINATION" => Ok(SessionInvalidationReason::EmergencyTermination),
+            "CLIENT_BINDING_MISMATCH" => Ok(SessionInvalidationReason::ClientBindingMismatch),
+            "USER_DEACTIVATED" => Ok(SessionInvalidationReason::UserDeactivated),
+            "GUEST_UPGRADED" => Ok(SessionInvalidationReason::GuestUpgraded),
+            "TENANT_ARCHIVED" => Ok(SessionInvalidationReason::TenantArchived),
             _ => Err(format!("Unknown session invalidation reason: {}", s).into()),
         }
     }
@@ -185,6 +207,153 @@ impl fmt::Display for SessionInvalidationReason {
                 f.write_str("SECURITY_POLICY_CHANGE")
             },
             SessionInvalidationReason::EmergencyTermination => f.write_str("EMERGENCY_TERMINATION"),
+            SessionInvalidationReason::ClientBindingMismatch => {
+                f.write_str("CLIENT_BINDING_MISMATCH")
+            },
+            SessionInvalidationReason::UserDeactivated => f.write_str("USER_DEACTIVATED"),
+            SessionInvalidationReason::GuestUpgraded => f.write_str("GUEST_UPGRADED"),
+            SessionInvalidationReason::TenantArchived => f.write_str("TENANT_ARCHIVED"),
+        }
+    }
+}
+
+/// Whom a session belongs to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SessionKind {
+    /// Session of an authenticated user
+    #[default]
+    User,
+    /// Pre-login session of a visitor, bound to no user
+    ///
+    /// Carries state like consent choices until the visitor logs in and the
+    /// session is upgraded to a user session.
+    Guest,
+}
+
+impl SessionKind {
+    /// Kind of a session created for `user_id`, a guest session for the nil UUID
+    pub fn for_user(user_id: Uuid) -> Self {
+        if user_id.is_nil() {
+            SessionKind::Guest
+        } else {
+            SessionKind::User
+        }
+    }
+
+    /// Value stored in the `kind` column
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SessionKind::User => "USER",
+            SessionKind::Guest => "GUEST",
+        }
+    }
+
+    /// Session kind stored as `value`, `None` for values of no known kind
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "USER" => Some(SessionKind::User),
+            "GUEST" => Some(SessionKind::Guest),
+            _ => None,
+        }
+    }
+}
+
+/// Audit events written for both users of an impersonation session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImpersonationAction {
+    Started,
+    Ended,
+}
+
+impl ImpersonationAction {
+    /// Action name in the session audit log
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ImpersonationAction::Started => "IMPERSONATION_STARTED",
+            ImpersonationAction::Ended => "IMPERSONATION_ENDED",
+        }
+    }
+}
+
+/// Audit events for changes to the authentication factors of a session's user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FactorAction {
+    Added,
+    Removed,
+}
+
+impl FactorAction {
+    /// Action name in the session audit log
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FactorAction::Added => "FACTOR_ADDED",
+            FactorAction::Removed => "FACTOR_REMOVED",
+        }
+    }
+}
+
+/// Class of device a session was created from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceType {
+    Desktop,
+    Mobile,
+    Tablet,
+    /// Crawlers and other automated clients
+    Bot,
+    /// Consoles, TVs and clients of unknown class
+    Other,
+}
+
+impl DeviceType {
+    /// Value stored in the `device_type` column
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeviceType::Desktop => "desktop",
+            DeviceType::Mobile => "mobile",
+            DeviceType::Tablet => "tablet",
+            DeviceType::Bot => "bot",
+            DeviceType::Other => "other",
+        }
+    }
+
+    /// Device type stored as `value`, `None` for values of no known type
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "desktop" => Some(DeviceType::Desktop),
+            "mobile" => Some(DeviceType::Mobile),
+            "tablet" => Some(DeviceType::Tablet),
+            "bot" => Some(DeviceType::Bot),
+            "other" => Some(DeviceType::Other),
+            _ => None,
+        }
+    }
+}
+
+/// Browser, operating system and device class parsed from a user agent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserAgentDetails {
+    pub browser: Option<String>,
+    pub browser_version: Option<String>,
+    pub os: Option<String>,
+    pub device_type: DeviceType,
+}
+
+impl UserAgentDetails {
+    /// Short description for people, such as "Chrome 120 on Windows 10"
+    pub fn summary(&self) -> String {
+        let browser = match (&self.browser, &self.browser_version) {
+            (Some(browser), Some(version)) => {
+                let major = version.split('.').next().unwrap_or(version);
+                format!("{} {}", browser, major)
+            },
+            (Some(browser), None) => browser.clone(),
+            (None, _) => "Unknown browser".to_string(),
+        };
+        match &self.os {
+            Some(os) => format!("{} on {}", browser, os),
+            None => browser,
         }
     }
 }
@@ -0,0 +1,15 @@
+//! Canonical in-memory test doubles
+//!
+//! One place for the mocks the unit tests of this crate and the downstream
+//! test crates share, so they do not drift apart as the traits evolve.
+//! Available with the `test-support` feature.
+
+pub use crate::models::tenant::mock::MockTenantRepository;
+pub use crate::models::user::mock::MockUserRepository;
+pub use crate::repository::api_key_repository::mock::MockApiKeyRepository;
+pub use crate::repository::platform_stats_repository::mock::{
+    MockPlatformStats, MockPlatformStatsRepository,
+};
+pub use crate::repository::verification_repository::mock::MockVerificationCodeRepository;
+pub use crate::services::message_provider::MockMessageProvider;
+pub use crate::session::mock::MockSessionRepository;
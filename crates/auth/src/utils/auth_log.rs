@@ -0,0 +1,471 @@
+//! Structured logging of authentication decisions
+//!
+//! Every login, verification and session decision is logged as one event of
+//! the target [`AUTH_EVENT_TARGET`] with a fixed set of fields:
+//!
+//! | Field             | Content                                               |
+//! |-------------------|-------------------------------------------------------|
+//! | `action`          | Operation decided on, e.g. `login`                    |
+//! | `decision`        | `allow`, `challenge` or `deny`                        |
+//! | `reason_code`     | Why, e.g. `INVALID_CREDENTIALS`                       |
+//! | `request_id`      | ID of the API request                                 |
+//! | `tenant_id`       | Tenant the decision applies to                        |
+//! | `hashed_user_ref` | Reference to the user, redacted per privacy mode      |
+//! | `ip_prefix`       | Network of the client, redacted per privacy mode      |
+//! | `risk`            | Risk level of the attempt, if assessed                |
+//! | `challenge`       | Challenge the client has to pass, e.g. `mfa`          |
+//! | `latency_ms`      | Time taken to decide                                  |
+//!
+//! Callers hand over the raw email or user ID and IP address; redaction is
+//! applied here according to the installed [`LogPrivacyConfig`], so no
+//! handler decides on its own what is safe to log. Install the configuration
+//! with [`install_privacy`] at startup, usually `AuthConfig::log_privacy`;
+//! without one, user references are hashed.
+
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::security::RiskLevel;
+use crate::utils::secret::Secret;
+
+/// Tracing target of authentication decision events
+pub const AUTH_EVENT_TARGET: &str = "acci_auth::auth_event";
+
+/// Bytes of the HMAC kept for user references
+const USER_REF_BYTES: usize = 16;
+
+/// How much of the user reference and IP address is logged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogPrivacyMode {
+    /// User references and addresses verbatim, for development only
+    Full,
+    /// Local part of emails masked, addresses cut to their network
+    Partial,
+    /// User references replaced by a keyed hash, addresses cut to their network
+    #[default]
+    Hashed,
+}
+
+/// Redaction of personal data in authentication decision events
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LogPrivacyConfig {
+    pub mode: LogPrivacyMode,
+    /// Key of the HMAC replacing user references in `hashed` mode
+    ///
+    /// Events of the same user share a reference as long as the key stays
+    /// the same, without the reference revealing the user.
+    pub hash_key: Secret<String>,
+}
+
+impl Default for LogPrivacyConfig {
+    fn default() -> Self {
+        Self {
+            mode: LogPrivacyMode::default(),
+            hash_key: Secret::from("AcciLogPrivacyKey12345678901234567890123456"), // Default key, should be changed in production
+        }
+    }
+}
+
+impl LogPrivacyConfig {
+    /// `user`, an email or user ID, as logged in this mode
+    ///
+    /// Emails are compared case-insensitively, so the hash of a login with
+    /// `Alice@example.com` matches the one of `alice@example.com`.
+    pub fn user_ref(&self, user: &str) -> String {
+        let user = user.trim().to_lowercase();
+        match self.mode {
+            LogPrivacyMode::Full => user,
+            LogPrivacyMode::Partial => match user.split_once('@') {
+                Some((local, domain)) => {
+                    let first = local.chars().next().map(String::from).unwrap_or_default();
+                    format!("{first}***@{domain}")
+                },
+                None => user,
+            },
+            LogPrivacyMode::Hashed => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(self.hash_key.as_bytes())
+                    .expect("HMAC can take key of any size");
+                mac.update(user.as_bytes());
+                hex::encode(&mac.finalize().into_bytes()[..USER_REF_BYTES])
+            },
+        }
+    }
+
+    /// `ip_address` as logged in this mode
+    ///
+    /// Outside of `full` mode only the network is kept: the /24 of IPv4 and
+    /// the /56 of IPv6 addresses, the usual allocation to a single customer.
+    /// Addresses that do not parse are not logged at all.
+    pub fn ip_prefix(&self, ip_address: &str) -> Option<String> {
+        let ip: IpAddr = ip_address.trim().parse().ok()?;
+        if self.mode == LogPrivacyMode::Full {
+            return Some(ip.to_string());
+        }
+        Some(match ip {
+            IpAddr::V4(ip) => {
+                let [a, b, c, _] = ip.octets();
+                format!("{a}.{b}.{c}.0/24")
+            },
+            IpAddr::V6(ip) => {
+                let network = u128::from(ip) & (u128::MAX << (128 - 56));
+                format!("{}/56", std::net::Ipv6Addr::from(network))
+            },
+        })
+    }
+}
+
+static PRIVACY: OnceLock<LogPrivacyConfig> = OnceLock::new();
+
+/// Install the process-wide privacy configuration of authentication events
+///
+/// Returns `false` if a configuration was installed before. Without an
+/// installed configuration the defaults apply.
+pub fn install_privacy(config: LogPrivacyConfig) -> bool {
+    PRIVACY.set(config).is_ok()
+}
+
+/// The installed privacy configuration
+pub fn privacy() -> &'static LogPrivacyConfig {
+    PRIVACY.get_or_init(LogPrivacyConfig::default)
+}
+
+/// `user` redacted per the installed privacy configuration
+///
+/// For logs outside of decision events that still need to refer to a user
+/// by email.
+pub fn redact_user(user: &str) -> String {
+    privacy().user_ref(user)
+}
+
+/// Outcome of an authentication decision
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthDecision {
+    Allow,
+    /// Allowed once the client passes a further challenge
+    Challenge,
+    Deny,
+}
+
+impl fmt::Display for AuthDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AuthDecision::Allow => "allow",
+            AuthDecision::Challenge => "challenge",
+            AuthDecision::Deny => "deny",
+        })
+    }
+}
+
+/// One authentication decision, logged with [`AuthEvent::emit`]
+///
+/// Usually built through [`auth_event!`](crate::auth_event).
+#[derive(Debug, Clone)]
+pub struct AuthEvent<'a> {
+    action: &'static str,
+    decision: AuthDecision,
+    reason_code: &'a str,
+    request_id: Option<&'a str>,
+    tenant_id: Option<Uuid>,
+    user: Option<&'a str>,
+    ip_address: Option<&'a str>,
+    risk: Option<RiskLevel>,
+    challenge: Option<&'static str>,
+    latency: Option<Duration>,
+}
+
+impl<'a> AuthEvent<'a> {
+    pub fn new(action: &'static str, decision: AuthDecision, reason_code: &'a str) -> Self {
+        Self {
+            action,
+            decision,
+            reason_code,
+            request_id: None,
+            tenant_id: None,
+            user: None,
+            ip_address: None,
+            risk: None,
+            challenge: None,
+            latency: None,
+        }
+    }
+
+    pub fn request_id(mut self, request_id: &'a str) -> Self {
+        self.request_id = Some(request_id);
+        self
+    }
+
+    pub fn tenant_id(mut self, tenant_id: impl Into<Option<Uuid>>) -> Self {
+        self.tenant_id = tenant_id.into();
+        self
+    }
+
+    /// The user decided on, by email or user ID; logged redacted
+    pub fn user(mut self, user: &'a str) -> Self {
+        self.user = Some(user);
+        self
+    }
+
+    /// The client address; logged redacted
+    pub fn ip(mut self, ip_address: Option<&'a str>) -> Self {
+        self.ip_address = ip_address;
+        self
+    }
+
+    pub fn risk(mut self, risk: RiskLevel) -> Self {
+        self.risk = Some(risk);
+        self
+    }
+
+    /// The challenge the client has to pass next, e.g. `mfa`
+    pub fn challenge(mut self, challenge: impl Into<Option<&'static str>>) -> Self {
+        self.challenge = challenge.into();
+        self
+    }
+
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Log the event, redacted per the installed privacy configuration
+    pub fn emit(self) {
+        self.emit_with(privacy());
+    }
+
+    fn emit_with(self, privacy: &LogPrivacyConfig) {
+        let user_ref = self.user.map(|user| privacy.user_ref(user));
+        let ip_prefix = self.ip_address.and_then(|ip| privacy.ip_prefix(ip));
+        let latency_ms = self
+            .latency
+            .map(|latency| u64::try_from(latency.as_millis()).unwrap_or(u64::MAX));
+
+        macro_rules! log_event {
+            ($level:ident) => {
+                $level!(
+                    target: AUTH_EVENT_TARGET,
+                    action = self.action,
+                    decision = %self.decision,
+                    reason_code = self.reason_code,
+                    request_id = self.request_id,
+                    tenant_id = self.tenant_id.map(tracing::field::display),
+                    hashed_user_ref = user_ref.as_deref(),
+                    ip_prefix = ip_prefix.as_deref(),
+                    risk = self.risk.map(tracing::field::display),
+                    challenge = self.challenge,
+                    latency_ms,
+                    "Authentication decision"
+                )
+            };
+        }
+
+        match self.decision {
+            AuthDecision::Allow | AuthDecision::Challenge => log_event!(info),
+            AuthDecision::Deny => log_event!(warn),
+        }
+    }
+}
+
+/// Log an authentication decision
+///
+/// Takes the action, decision and reason code, followed by any of the
+/// [`AuthEvent`] fields as `name = value`:
+///
+/// ```ignore
+/// auth_event!(
+///     "login",
+///     AuthDecision::Deny,
+///     "INVALID_CREDENTIALS",
+///     request_id = &request_id,
+///     user = &email,
+///     ip = client.ip_address.as_deref(),
+///     latency = start.elapsed(),
+/// );
+/// ```
+#[macro_export]
+macro_rules! auth_event {
+    ($action:expr, $decision:expr, $reason_code:expr $(, $field:ident = $value:expr)* $(,)?) => {
+        $crate::utils::auth_log::AuthEvent::new($action, $decision, $reason_code)
+            $(.$field($value))*
+            .emit()
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::fmt::MakeWriter;
+
+    const EMAIL: &str = "Alice.Smith@example.com";
+
+    /// Writer collecting the formatted events
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl Capture {
+        fn output(&self) -> String {
+            String::from_utf8_lossy(&self.0.lock().expect("Capture lock")).into_owned()
+        }
+    }
+
+    impl io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().expect("Capture lock").extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'w> MakeWriter<'w> for Capture {
+        type Writer = Self;
+
+        fn make_writer(&'w self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    /// Formatted output of the events logged by `log`
+    fn captured(log: impl FnOnce()) -> String {
+        let capture = Capture::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(capture.clone())
+            .with_ansi(false)
+            .finish();
+        tracing::subscriber::with_default(subscriber, log);
+        capture.output()
+    }
+
+    fn config(mode: LogPrivacyMode) -> LogPrivacyConfig {
+        LogPrivacyConfig {
+            mode,
+            ..LogPrivacyConfig::default()
+        }
+    }
+
+    fn login_failure<'a>() -> AuthEvent<'a> {
+        AuthEvent::new("login", AuthDecision::Deny, "INVALID_CREDENTIALS")
+            .request_id("req-1")
+            .tenant_id(Uuid::nil())
+            .user(EMAIL)
+            .ip(Some("203.0.113.77"))
+            .risk(RiskLevel::Medium)
+            .latency(Duration::from_millis(42))
+    }
+
+    #[test]
+    fn test_hashed_events_never_contain_emails() {
+        let privacy = config(LogPrivacyMode::Hashed);
+        let output = captured(|| {
+            login_failure().emit_with(&privacy);
+            AuthEvent::new("login", AuthDecision::Challenge, "MFA_REQUIRED")
+                .user(EMAIL)
+                .ip(Some("2001:db8:abcd:12ff:1:2:3:4"))
+                .challenge("mfa")
+                .emit_with(&privacy);
+        });
+
+        let user_ref = privacy.user_ref(EMAIL);
+        assert_eq!(output.lines().count(), 2, "{output}");
+        assert!(!output.to_lowercase().contains("alice"), "{output}");
+        assert!(!output.contains("example.com"), "{output}");
+        assert!(!output.contains("203.0.113.77"), "{output}");
+        assert!(
+            output.contains(&format!("hashed_user_ref=\"{user_ref}\"")),
+            "{output}"
+        );
+        assert!(output.contains("ip_prefix=\"203.0.113.0/24\""), "{output}");
+        assert!(
+            output.contains("ip_prefix=\"2001:db8:abcd:1200::/56\""),
+            "{output}"
+        );
+    }
+
+    #[test]
+    fn test_event_fields() {
+        let output = captured(|| login_failure().emit_with(&config(LogPrivacyMode::Hashed)));
+
+        assert!(output.contains(" WARN "), "{output}");
+        assert!(output.contains(AUTH_EVENT_TARGET), "{output}");
+        for field in [
+            "action=\"login\"".to_string(),
+            "decision=deny".to_string(),
+            "reason_code=\"INVALID_CREDENTIALS\"".to_string(),
+            "request_id=\"req-1\"".to_string(),
+            format!("tenant_id={}", Uuid::nil()),
+            format!("risk={}", RiskLevel::Medium),
+            "latency_ms=42".to_string(),
+        ] {
+            assert!(output.contains(&field), "{field} missing in {output}");
+        }
+        assert!(!output.contains("challenge="), "{output}");
+    }
+
+    #[test]
+    fn test_user_ref_per_mode() {
+        let hashed = config(LogPrivacyMode::Hashed);
+        let reference = hashed.user_ref(EMAIL);
+        assert_eq!(reference.len(), USER_REF_BYTES * 2);
+        assert_eq!(reference, hashed.user_ref("alice.smith@example.com "));
+        assert_ne!(reference, hashed.user_ref("bob@example.com"));
+
+        let rekeyed = LogPrivacyConfig {
+            hash_key: Secret::from("another-key"),
+            ..hashed
+        };
+        assert_ne!(reference, rekeyed.user_ref(EMAIL));
+
+        let partial = config(LogPrivacyMode::Partial);
+        assert_eq!(partial.user_ref(EMAIL), "a***@example.com");
+        let user_id = Uuid::new_v4().to_string();
+        assert_eq!(partial.user_ref(&user_id), user_id);
+
+        assert_eq!(
+            config(LogPrivacyMode::Full).user_ref(EMAIL),
+            "alice.smith@example.com"
+        );
+    }
+
+    #[test]
+    fn test_ip_prefix_per_mode() {
+        let partial = config(LogPrivacyMode::Partial);
+        assert_eq!(
+            partial.ip_prefix("192.168.17.201").as_deref(),
+            Some("192.168.17.0/24")
+        );
+        assert_eq!(
+            partial.ip_prefix("2001:db8::ff:1").as_deref(),
+            Some("2001:db8::/56")
+        );
+        assert_eq!(partial.ip_prefix("not an address"), None);
+
+        assert_eq!(
+            config(LogPrivacyMode::Full)
+                .ip_prefix("192.168.17.201")
+                .as_deref(),
+            Some("192.168.17.201")
+        );
+    }
+
+    #[test]
+    fn test_privacy_mode_from_config() {
+        let config: LogPrivacyConfig =
+            serde_json::from_str(r#"{ "mode": "partial" }"#).expect("Valid config");
+        assert_eq!(config.mode, LogPrivacyMode::Partial);
+
+        let config: LogPrivacyConfig = serde_json::from_str("{}").expect("Valid config");
+        assert_eq!(config.mode, LogPrivacyMode::Hashed);
+    }
+}
@@ -0,0 +1,136 @@
+use std::future::Future;
+use std::time::Duration;
+use tracing::debug;
+
+/// Chunking for bulk deletes and updates of old rows
+///
+/// Each batch runs as its own statement, so no transaction spans the whole
+/// backlog and an interrupted run keeps the batches it already committed; the
+/// next run continues with whatever is left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchConfig {
+    /// Maximum number of rows touched per statement
+    pub batch_size: u32,
+    /// Pause between two batches, giving other writers a chance to proceed
+    pub delay: Duration,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 1000,
+            delay: Duration::from_millis(50),
+        }
+    }
+}
+
+/// Run `batch` until it affects fewer rows than the batch size
+///
+/// `batch` receives the row limit for one statement and returns the number of
+/// rows it affected. Returns the total over all batches.
+pub async fn run_in_batches<F, Fut, E>(config: &BatchConfig, mut batch: F) -> Result<u64, E>
+where
+    F: FnMut(i64) -> Fut,
+    Fut: Future<Output = Result<u64, E>>,
+{
+    let limit = i64::from(config.batch_size.max(1));
+    let mut total = 0;
+    let mut batches = 0;
+
+    loop {
+        let affected = batch(limit).await?;
+        total += affected;
+        batches += 1;
+
+        if affected < limit as u64 {
+            break;
+        }
+        if !config.delay.is_zero() {
+            tokio::time::sleep(config.delay).await;
+        }
+    }
+
+    debug!(
+        total = total,
+        batches = batches,
+        "Batched operation completed"
+    );
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+
+    #[tokio::test]
+    async fn test_run_in_batches_deletes_backlog_in_chunks() {
+        let config = BatchConfig {
+            batch_size: 100,
+            delay: Duration::ZERO,
+        };
+        let mut remaining = 1000_u64;
+        let mut statements = Vec::new();
+
+        let total = run_in_batches(&config, |limit| {
+            let deleted = remaining.min(limit as u64);
+            remaining -= deleted;
+            statements.push(deleted);
+            async move { Ok::<_, Infallible>(deleted) }
+        })
+        .await
+        .expect("infallible");
+
+        assert_eq!(total, 1000);
+        assert_eq!(remaining, 0);
+        // Ten full batches, then one that finds nothing left
+        assert_eq!(
+            statements.iter().filter(|&&deleted| deleted > 0).count(),
+            10
+        );
+        assert_eq!(statements.len(), 11);
+        assert!(statements.iter().all(|&deleted| deleted <= 100));
+    }
+
+    #[tokio::test]
+    async fn test_run_in_batches_stops_after_partial_batch() {
+        let config = BatchConfig {
+            batch_size: 100,
+            delay: Duration::ZERO,
+        };
+        let mut remaining = 250_u64;
+        let mut statements = 0;
+
+        let total = run_in_batches(&config, |limit| {
+            let deleted = remaining.min(limit as u64);
+            remaining -= deleted;
+            statements += 1;
+            async move { Ok::<_, Infallible>(deleted) }
+        })
+        .await
+        .expect("infallible");
+
+        assert_eq!(total, 250);
+        assert_eq!(statements, 3);
+    }
+
+    #[tokio::test]
+    async fn test_run_in_batches_propagates_errors() {
+        let config = BatchConfig::default();
+        let mut calls = 0;
+
+        let result = run_in_batches(&config, |limit| {
+            calls += 1;
+            async move {
+                if calls == 1 {
+                    Ok(limit as u64)
+                } else {
+                    Err("connection lost")
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result, Err("connection lost"));
+    }
+}
@@ -0,0 +1,497 @@
+//! Limits of free-form JSON metadata supplied by clients
+//!
+//! Tenants and sessions carry a `metadata` object that is read with every
+//! row. [`BoundedJson`] only holds objects within the [`JsonLimits`]
+//! installed with [`install_limits`], or within the defaults if none were
+//! installed, so request DTOs reject oversized metadata when parsed.
+//!
+//! Rows written before the limits were enforced may still exceed them.
+//! Repositories pass stored metadata through [`bound_stored`], which cuts it
+//! down and logs the row instead of rewriting every table on migration.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::io;
+use std::sync::OnceLock;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Size, nesting and key limits of a metadata object
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct JsonLimits {
+    /// Maximum length of the serialized object in bytes
+    pub max_bytes: usize,
+    /// Maximum nesting of objects and arrays; a flat object has depth 1
+    pub max_depth: usize,
+    /// Maximum number of keys, counted over all nested objects
+    pub max_keys: usize,
+}
+
+impl Default for JsonLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: 16 * 1024,
+            max_depth: 8,
+            max_keys: 256,
+        }
+    }
+}
+
+/// A limit exceeded by a metadata value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "limit", rename_all = "snake_case")]
+pub enum JsonLimitViolation {
+    /// The top level is not an object
+    NotAnObject,
+    /// The serialized value is longer than `max` bytes
+    MaxBytes { actual: usize, max: usize },
+    /// Objects and arrays are nested deeper than `max` levels
+    MaxDepth { actual: usize, max: usize },
+    /// The value has more than `max` keys
+    MaxKeys { actual: usize, max: usize },
+}
+
+impl JsonLimitViolation {
+    /// Stable name of the exceeded limit
+    pub fn code(&self) -> &'static str {
+        match self {
+            JsonLimitViolation::NotAnObject => "not_an_object",
+            JsonLimitViolation::MaxBytes { .. } => "max_bytes",
+            JsonLimitViolation::MaxDepth { .. } => "max_depth",
+            JsonLimitViolation::MaxKeys { .. } => "max_keys",
+        }
+    }
+}
+
+impl fmt::Display for JsonLimitViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonLimitViolation::NotAnObject => write!(f, "must be a JSON object"),
+            JsonLimitViolation::MaxBytes { actual, max } => {
+                write!(f, "is {} bytes long, at most {} are allowed", actual, max)
+            },
+            JsonLimitViolation::MaxDepth { actual, max } => {
+                write!(
+                    f,
+                    "is nested {} levels deep, at most {} are allowed",
+                    actual, max
+                )
+            },
+            JsonLimitViolation::MaxKeys { actual, max } => {
+                write!(f, "has {} keys, at most {} are allowed", actual, max)
+            },
+        }
+    }
+}
+
+/// Every limit a metadata value exceeds
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("metadata {}", join_violations(.violations))]
+pub struct JsonLimitError {
+    pub violations: Vec<JsonLimitViolation>,
+}
+
+fn join_violations(violations: &[JsonLimitViolation]) -> String {
+    violations
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+impl JsonLimits {
+    /// Check `value` against every limit, reporting all exceeded ones
+    pub fn check(&self, value: &Value) -> Result<(), JsonLimitError> {
+        let mut violations = Vec::new();
+        if !value.is_object() {
+            violations.push(JsonLimitViolation::NotAnObject);
+        }
+        violations.extend(self.size_violations(value));
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(JsonLimitError { violations })
+        }
+    }
+
+    /// Check `value` against the size, depth and key limits, whatever its top level
+    pub fn check_size(&self, value: &Value) -> Result<(), JsonLimitError> {
+        let violations = self.size_violations(value);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(JsonLimitError { violations })
+        }
+    }
+
+    /// Cut `value` down until it fits the size, depth and key limits
+    ///
+    /// Containers nested too deep are dropped, then entries are kept in order
+    /// until the key or byte budget is used up. The `priority` keys of a
+    /// top-level object are kept before all others. Returns `None` if not even
+    /// an empty value of the same kind fits.
+    pub fn truncate(&self, value: Value, priority: &[&str]) -> Option<Value> {
+        let mut keys = self.max_keys;
+        match value {
+            Value::Object(mut map) => {
+                let mut entries: Vec<(String, Value)> = priority
+                    .iter()
+                    .filter_map(|key| map.remove_entry(*key))
+                    .collect();
+                entries.extend(map);
+                let entries = prune_entries(entries, 1, self.max_depth, &mut keys);
+                let entries = fit_bytes(
+                    entries,
+                    |(key, entry)| {
+                        serialized_len(&Value::String(key.clone())) + 1 + serialized_len(entry)
+                    },
+                    self.max_bytes,
+                )?;
+                Some(Value::Object(entries.into_iter().collect()))
+            },
+            Value::Array(items) => {
+                let items = prune_items(items, 1, self.max_depth, &mut keys);
+                fit_bytes(items, serialized_len, self.max_bytes).map(Value::Array)
+            },
+            scalar => (serialized_len(&scalar) <= self.max_bytes).then_some(scalar),
+        }
+    }
+
+    fn size_violations(&self, value: &Value) -> Vec<JsonLimitViolation> {
+        let mut violations = Vec::new();
+        let bytes = serialized_len(value);
+        if bytes > self.max_bytes {
+            violations.push(JsonLimitViolation::MaxBytes {
+                actual: bytes,
+                max: self.max_bytes,
+            });
+        }
+        let depth = depth(value);
+        if depth > self.max_depth {
+            violations.push(JsonLimitViolation::MaxDepth {
+                actual: depth,
+                max: self.max_depth,
+            });
+        }
+        let keys = key_count(value);
+        if keys > self.max_keys {
+            violations.push(JsonLimitViolation::MaxKeys {
+                actual: keys,
+                max: self.max_keys,
+            });
+        }
+        violations
+    }
+}
+
+static LIMITS: OnceLock<JsonLimits> = OnceLock::new();
+
+/// Install the process-wide metadata limits, usually `AuthConfig::metadata_limits`
+///
+/// Returns `false` if limits were installed before. Without installed limits
+/// the defaults apply.
+pub fn install_limits(limits: JsonLimits) -> bool {
+    LIMITS.set(limits).is_ok()
+}
+
+/// The installed metadata limits
+pub fn limits() -> JsonLimits {
+    LIMITS.get().copied().unwrap_or_default()
+}
+
+/// A JSON object within the installed [`JsonLimits`]
+///
+/// Deserializing fails with a [`JsonLimitError`] for any other value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Value", into = "Value")]
+pub struct BoundedJson(Value);
+
+impl BoundedJson {
+    /// Check `value` against the installed limits
+    pub fn new(value: Value) -> Result<Self, JsonLimitError> {
+        Self::with_limits(value, &limits())
+    }
+
+    /// Check `value` against `limits`
+    pub fn with_limits(value: Value, limits: &JsonLimits) -> Result<Self, JsonLimitError> {
+        limits.check(&value)?;
+        Ok(Self(value))
+    }
+
+    pub fn as_value(&self) -> &Value {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> Value {
+        self.0
+    }
+}
+
+impl TryFrom<Value> for BoundedJson {
+    type Error = JsonLimitError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl From<BoundedJson> for Value {
+    fn from(value: BoundedJson) -> Self {
+        value.0
+    }
+}
+
+/// Metadata read from `table`, cut down to the installed limits
+///
+/// Oversized rows from before the limits were enforced are truncated with
+/// [`JsonLimits::truncate`] and logged, so that they can be cleaned up.
+/// Values that are not objects but fit the limits are returned unchanged.
+pub fn bound_stored(
+    table: &'static str,
+    id: Uuid,
+    value: Value,
+    priority: &[&str],
+) -> Option<Value> {
+    let limits = limits();
+    let Err(err) = limits.check_size(&value) else {
+        return Some(value);
+    };
+
+    warn!(
+        table,
+        id = %id,
+        error = %err,
+        "Truncating stored metadata exceeding the limits"
+    );
+    limits.truncate(value, priority)
+}
+
+/// Counts the bytes written to it
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Length of the compact serialization of `value`
+fn serialized_len(value: &Value) -> usize {
+    let mut counter = ByteCounter(0);
+    // Writing a `Value` to an infallible writer cannot fail
+    serde_json::to_writer(&mut counter, value).map_or(usize::MAX, |()| counter.0)
+}
+
+fn depth(value: &Value) -> usize {
+    match value {
+        Value::Object(map) => 1 + map.values().map(depth).max().unwrap_or(0),
+        Value::Array(items) => 1 + items.iter().map(depth).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+fn key_count(value: &Value) -> usize {
+    match value {
+        Value::Object(map) => map.len() + map.values().map(key_count).sum::<usize>(),
+        Value::Array(items) => items.iter().map(key_count).sum(),
+        _ => 0,
+    }
+}
+
+/// `value` at nesting `level` without containers beyond `max_depth`,
+/// taking the keys it keeps from the budget `keys`
+fn prune(value: Value, level: usize, max_depth: usize, keys: &mut usize) -> Option<Value> {
+    match value {
+        Value::Object(_) | Value::Array(_) if level > max_depth => None,
+        Value::Object(map) => Some(Value::Object(
+            prune_entries(map.into_iter().collect(), level, max_depth, keys)
+                .into_iter()
+                .collect(),
+        )),
+        Value::Array(items) => Some(Value::Array(prune_items(items, level, max_depth, keys))),
+        scalar => Some(scalar),
+    }
+}
+
+/// Entries of an object at nesting `level`, in order until the key budget is used up
+fn prune_entries(
+    entries: Vec<(String, Value)>,
+    level: usize,
+    max_depth: usize,
+    keys: &mut usize,
+) -> Vec<(String, Value)> {
+    let mut pruned = Vec::new();
+    for (key, entry) in entries {
+        if *keys == 0 {
+            break;
+        }
+        *keys -= 1;
+        if let Some(entry) = prune(entry, level + 1, max_depth, keys) {
+            pruned.push((key, entry));
+        }
+    }
+    pruned
+}
+
+fn prune_items(items: Vec<Value>, level: usize, max_depth: usize, keys: &mut usize) -> Vec<Value> {
+    items
+        .into_iter()
+        .filter_map(|item| prune(item, level + 1, max_depth, keys))
+        .collect()
+}
+
+/// The entries of a container that fit into `max_bytes` together
+///
+/// Entries are taken in order; one that does not fit anymore is skipped, the
+/// following smaller ones may still be taken. `None` if not even the empty
+/// container fits.
+fn fit_bytes<T>(entries: Vec<T>, len: impl Fn(&T) -> usize, max_bytes: usize) -> Option<Vec<T>> {
+    // Braces or brackets, then each entry and its separator
+    let mut used = 2;
+    if used > max_bytes {
+        return None;
+    }
+    let mut fitted = Vec::new();
+    for entry in entries {
+        let cost = len(&entry) + usize::from(!fitted.is_empty());
+        if used + cost <= max_bytes {
+            used += cost;
+            fitted.push(entry);
+        }
+    }
+    Some(fitted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const TEST_LIMITS: JsonLimits = JsonLimits {
+        max_bytes: 64,
+        max_depth: 2,
+        max_keys: 4,
+    };
+
+    fn violations(value: Value) -> Vec<&'static str> {
+        match TEST_LIMITS.check(&value) {
+            Ok(()) => Vec::new(),
+            Err(err) => err.violations.iter().map(|v| v.code()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_valid_metadata_round_trips() {
+        let value = json!({ "plan": "pro", "seats": 5, "tags": ["a", "b"] });
+        let bounded = BoundedJson::with_limits(value.clone(), &TEST_LIMITS).expect("Within limits");
+
+        let serialized = serde_json::to_value(&bounded).expect("Serializable");
+        assert_eq!(serialized, value);
+        let deserialized: BoundedJson = serde_json::from_value(serialized).expect("Within limits");
+        assert_eq!(deserialized, bounded);
+        assert_eq!(deserialized.into_inner(), value);
+    }
+
+    #[test]
+    fn test_top_level_must_be_an_object() {
+        assert_eq!(violations(json!(["a"])), vec!["not_an_object"]);
+        assert_eq!(violations(json!("text")), vec!["not_an_object"]);
+        assert_eq!(violations(Value::Null), vec!["not_an_object"]);
+        assert!(serde_json::from_value::<BoundedJson>(json!([1, 2])).is_err());
+    }
+
+    #[test]
+    fn test_max_bytes() {
+        assert!(violations(json!({ "note": "x".repeat(40) })).is_empty());
+        let err = TEST_LIMITS
+            .check(&json!({ "note": "x".repeat(60) }))
+            .expect_err("Too long");
+        assert_eq!(
+            err.violations,
+            vec![JsonLimitViolation::MaxBytes {
+                actual: 71,
+                max: 64
+            }]
+        );
+    }
+
+    #[test]
+    fn test_max_depth() {
+        assert!(violations(json!({ "a": { "b": 1 } })).is_empty());
+        assert!(violations(json!({ "a": [] })).is_empty());
+        assert_eq!(violations(json!({ "a": { "b": {} } })), vec!["max_depth"]);
+        assert_eq!(violations(json!({ "a": [[1]] })), vec!["max_depth"]);
+    }
+
+    #[test]
+    fn test_max_keys_counts_nested_objects() {
+        assert!(violations(json!({ "a": 1, "b": 2, "c": { "d": 4 } })).is_empty());
+        assert_eq!(
+            violations(json!({ "a": 1, "b": 2, "c": { "d": 4, "e": 5 } })),
+            vec!["max_keys"]
+        );
+        assert_eq!(
+            violations(json!({ "a": 1, "b": 2, "c": 3, "d": 4, "e": 5 })),
+            vec!["max_keys"]
+        );
+    }
+
+    #[test]
+    fn test_every_exceeded_limit_is_reported() {
+        let err = TEST_LIMITS
+            .check(&json!([{ "a": { "b": "x".repeat(60) } }, { "c": 1, "d": 2, "e": 3 }]))
+            .expect_err("Exceeds every limit");
+        let codes: Vec<&str> = err.violations.iter().map(|v| v.code()).collect();
+        assert_eq!(
+            codes,
+            vec!["not_an_object", "max_bytes", "max_depth", "max_keys"]
+        );
+        assert!(
+            err.to_string()
+                .starts_with("metadata must be a JSON object; is ")
+        );
+    }
+
+    #[test]
+    fn test_truncate_fits_every_limit() {
+        let oversized = json!({
+            "a": 1,
+            "b": { "c": { "d": 1 } },
+            "e": "x".repeat(80),
+            "f": 2,
+            "g": 3,
+            "h": 4,
+        });
+
+        let truncated = TEST_LIMITS.truncate(oversized, &[]).expect("Object fits");
+        // The key budget ends at "e", which is too long to keep
+        assert_eq!(truncated, json!({ "a": 1, "b": {} }));
+        assert!(TEST_LIMITS.check(&truncated).is_ok());
+    }
+
+    #[test]
+    fn test_truncate_keeps_priority_keys() {
+        let oversized = json!({ "a": 1, "b": 2, "c": 3, "d": 4, "e": 5, "z": "kept" });
+
+        let truncated = TEST_LIMITS
+            .truncate(oversized, &["z"])
+            .expect("Object fits");
+        assert_eq!(truncated, json!({ "z": "kept", "a": 1, "b": 2, "c": 3 }));
+    }
+
+    #[test]
+    fn test_truncate_drops_oversized_scalars() {
+        assert_eq!(TEST_LIMITS.truncate(json!("x".repeat(80)), &[]), None);
+        assert_eq!(
+            TEST_LIMITS.truncate(json!("short"), &[]),
+            Some(json!("short"))
+        );
+    }
+}
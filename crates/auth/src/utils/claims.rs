@@ -0,0 +1,206 @@
+//! Custom claims of issued JWTs
+//!
+//! Deployments add tenant-specific claims to the tokens issued by
+//! [`JwtUtils::issue_token`](super::jwt::JwtUtils::issue_token) by registering
+//! [`ClaimsEnricher`]s. The built-in enrichers for the tenant role, the
+//! subscription plan, feature flags and platform admins are enabled through
+//! [`ClaimsConfig`].
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::jwt::{Claims, JwtError};
+use crate::models::tenant::{Tenant, TenantId, TenantSubscription, TenantUser};
+use crate::models::user::User;
+use crate::session::Session;
+
+/// Claim holding the role of the user in the token's tenant
+pub const TENANT_ROLE_CLAIM: &str = "tenant_role";
+/// Claim holding the subscription plan of the token's tenant
+pub const SUBSCRIPTION_PLAN_CLAIM: &str = "plan";
+/// Claim holding the feature flags enabled for the token's tenant
+pub const FEATURE_FLAGS_CLAIM: &str = "features";
+/// Claim set to `true` for operators of the whole platform
+pub const PLATFORM_ADMIN_CLAIM: &str = "platform_admin";
+
+/// The tenant a token is issued for
+#[derive(Debug, Clone)]
+pub struct TokenTenant {
+    pub tenant: Tenant,
+    /// Membership of the user in the tenant
+    pub membership: Option<TenantUser>,
+    /// Current subscription of the tenant
+    pub subscription: Option<TenantSubscription>,
+}
+
+/// Adds custom claims to the tokens issued for a user
+///
+/// Enrichers run in the order they were registered, after the standard claims
+/// are set. Claims are added with [`Claims::insert_custom`], which rejects the
+/// reserved claim names.
+pub trait ClaimsEnricher: Send + Sync {
+    fn enrich(
+        &self,
+        claims: &mut Claims,
+        user: &User,
+        tenant: Option<&TokenTenant>,
+        session: Option<&Session>,
+    ) -> Result<(), JwtError>;
+}
+
+/// Adds the role of the user in the tenant as [`TENANT_ROLE_CLAIM`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TenantRoleEnricher;
+
+impl ClaimsEnricher for TenantRoleEnricher {
+    fn enrich(
+        &self,
+        claims: &mut Claims,
+        _user: &User,
+        tenant: Option<&TokenTenant>,
+        _session: Option<&Session>,
+    ) -> Result<(), JwtError> {
+        match tenant.and_then(|tenant| tenant.membership.as_ref()) {
+            Some(membership) if membership.is_active => claims.insert_custom(
+                TENANT_ROLE_CLAIM,
+                Value::String(membership.tenant_role.clone()),
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Adds the plan of the tenant's active subscription as [`SUBSCRIPTION_PLAN_CLAIM`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SubscriptionPlanEnricher;
+
+impl ClaimsEnricher for SubscriptionPlanEnricher {
+    fn enrich(
+        &self,
+        claims: &mut Claims,
+        _user: &User,
+        tenant: Option<&TokenTenant>,
+        _session: Option<&Session>,
+    ) -> Result<(), JwtError> {
+        match tenant.and_then(|tenant| tenant.subscription.as_ref()) {
+            Some(subscription) if subscription.is_active => claims.insert_custom(
+                SUBSCRIPTION_PLAN_CLAIM,
+                Value::String(subscription.plan_type.to_string()),
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Adds the configured feature flags of the tenant as [`FEATURE_FLAGS_CLAIM`]
+#[derive(Debug, Clone, Default)]
+pub struct FeatureFlagEnricher {
+    /// Flags of tenants without their own
+    pub flags: Vec<String>,
+    /// Flags per tenant, replacing `flags`
+    pub tenant_flags: HashMap<TenantId, Vec<String>>,
+}
+
+impl ClaimsEnricher for FeatureFlagEnricher {
+    fn enrich(
+        &self,
+        claims: &mut Claims,
+        _user: &User,
+        tenant: Option<&TokenTenant>,
+        _session: Option<&Session>,
+    ) -> Result<(), JwtError> {
+        let flags = tenant
+            .and_then(|tenant| self.tenant_flags.get(&tenant.tenant.id))
+            .unwrap_or(&self.flags);
+        if flags.is_empty() {
+            return Ok(());
+        }
+        claims.insert_custom(
+            FEATURE_FLAGS_CLAIM,
+            Value::Array(flags.iter().cloned().map(Value::String).collect()),
+        )
+    }
+}
+
+/// Marks the configured users as platform admins with [`PLATFORM_ADMIN_CLAIM`]
+///
+/// Platform admins see data across all tenants, independent of their role in
+/// any tenant.
+#[derive(Debug, Clone, Default)]
+pub struct PlatformAdminEnricher {
+    pub user_ids: HashSet<Uuid>,
+}
+
+impl ClaimsEnricher for PlatformAdminEnricher {
+    fn enrich(
+        &self,
+        claims: &mut Claims,
+        user: &User,
+        _tenant: Option<&TokenTenant>,
+        _session: Option<&Session>,
+    ) -> Result<(), JwtError> {
+        if !self.user_ids.contains(&user.id) {
+            return Ok(());
+        }
+        claims.insert_custom(PLATFORM_ADMIN_CLAIM, Value::Bool(true))
+    }
+}
+
+/// Custom claims added to issued tokens
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClaimsConfig {
+    /// Largest encoded token; enrichment pushing a token past it fails issuing
+    pub max_token_bytes: usize,
+    /// Add the role of the user in the tenant
+    pub tenant_role: bool,
+    /// Add the plan of the tenant's subscription
+    pub subscription_plan: bool,
+    /// Feature flags of tenants without their own
+    pub feature_flags: Vec<String>,
+    /// Feature flags per tenant, replacing `feature_flags`
+    pub tenant_feature_flags: HashMap<TenantId, Vec<String>>,
+    /// Users marked as platform admins
+    pub platform_admins: Vec<Uuid>,
+}
+
+impl Default for ClaimsConfig {
+    fn default() -> Self {
+        Self {
+            max_token_bytes: 4096,
+            tenant_role: false,
+            subscription_plan: false,
+            feature_flags: Vec::new(),
+            tenant_feature_flags: HashMap::new(),
+            platform_admins: Vec::new(),
+        }
+    }
+}
+
+impl ClaimsConfig {
+    /// The enabled built-in enrichers
+    pub fn enrichers(&self) -> Vec<Arc<dyn ClaimsEnricher>> {
+        let mut enrichers: Vec<Arc<dyn ClaimsEnricher>> = Vec::new();
+        if self.tenant_role {
+            enrichers.push(Arc::new(TenantRoleEnricher));
+        }
+        if self.subscription_plan {
+            enrichers.push(Arc::new(SubscriptionPlanEnricher));
+        }
+        if !self.feature_flags.is_empty() || !self.tenant_feature_flags.is_empty() {
+            enrichers.push(Arc::new(FeatureFlagEnricher {
+                flags: self.feature_flags.clone(),
+                tenant_flags: self.tenant_feature_flags.clone(),
+            }));
+        }
+        if !self.platform_admins.is_empty() {
+            enrichers.push(Arc::new(PlatformAdminEnricher {
+                user_ids: self.platform_admins.iter().copied().collect(),
+            }));
+        }
+        enrichers
+    }
+}
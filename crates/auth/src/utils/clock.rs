@@ -0,0 +1,88 @@
+use time::OffsetDateTime;
+
+#[cfg(any(test, feature = "test-support"))]
+use std::sync::{Arc, Mutex};
+
+/// Source of the current time for time-dependent services.
+///
+/// Services are generic over the clock and default to [`SystemClock`], which is
+/// a zero-sized type, so production code pays nothing for the indirection.
+pub trait Clock: Send + Sync + 'static {
+    /// Current time in UTC
+    fn now(&self) -> OffsetDateTime;
+}
+
+/// Clock backed by the operating system time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
+}
+
+/// Manually driven clock for deterministic tests.
+///
+/// Clones share the same instant, so a test can keep one handle and advance it
+/// while the service under test holds another.
+#[cfg(any(test, feature = "test-support"))]
+#[derive(Debug, Clone)]
+pub struct TestClock {
+    now: Arc<Mutex<OffsetDateTime>>,
+}
+
+#[cfg(any(test, feature = "test-support"))]
+impl TestClock {
+    /// Create a clock frozen at `start`
+    pub fn new(start: OffsetDateTime) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(start)),
+        }
+    }
+
+    /// Move the clock forward (or backward, for negative durations)
+    pub fn advance(&self, by: time::Duration) {
+        let mut now = self.now.lock().expect("test clock lock poisoned");
+        *now += by;
+    }
+
+    /// Jump the clock to a specific instant
+    pub fn set(&self, to: OffsetDateTime) {
+        *self.now.lock().expect("test clock lock poisoned") = to;
+    }
+}
+
+#[cfg(any(test, feature = "test-support"))]
+impl Default for TestClock {
+    /// Starts at 2025-01-01T00:00:00Z
+    fn default() -> Self {
+        Self::new(OffsetDateTime::from_unix_timestamp(1_735_689_600).expect("valid unix timestamp"))
+    }
+}
+
+#[cfg(any(test, feature = "test-support"))]
+impl Clock for TestClock {
+    fn now(&self) -> OffsetDateTime {
+        *self.now.lock().expect("test clock lock poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::Duration;
+
+    #[test]
+    fn test_clock_advances_shared_handles() {
+        let clock = TestClock::default();
+        let handle = clock.clone();
+        let start = clock.now();
+
+        handle.advance(Duration::minutes(5));
+
+        assert_eq!(clock.now() - start, Duration::minutes(5));
+        assert_eq!(handle.now(), start + Duration::minutes(5));
+    }
+}
@@ -0,0 +1,170 @@
+//! Typed durations for configuration values
+//!
+//! Configuration fields used to be raw integers whose unit was only implied by
+//! their name. [`DurationSecs`] and [`DurationMs`] carry the unit in the type
+//! and deserialize either from a plain integer in that unit (so existing
+//! config files keep working) or from a humantime string such as `"15m"` or
+//! `"24h"`.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use std::fmt;
+use std::time::Duration;
+
+macro_rules! config_duration {
+    ($name:ident, $unit:literal, $from:ident, $as:ident, $to_int:expr) => {
+        #[doc = concat!("A configured duration whose integer form is in ", $unit)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+        pub struct $name(Duration);
+
+        impl $name {
+            #[doc = concat!("Creates a duration from a number of ", $unit)]
+            pub const fn $from(value: u64) -> Self {
+                Self(Duration::$from(value))
+            }
+
+            #[doc = concat!("Returns the duration in whole ", $unit)]
+            pub fn $as(self) -> u64 {
+                ($to_int)(self.0)
+            }
+
+            /// Returns the duration as a [`std::time::Duration`]
+            pub const fn as_duration(self) -> Duration {
+                self.0
+            }
+
+            /// Returns the duration as a [`time::Duration`]
+            pub fn to_time(self) -> time::Duration {
+                time::Duration::try_from(self.0).unwrap_or(time::Duration::MAX)
+            }
+        }
+
+        impl From<Duration> for $name {
+            fn from(duration: Duration) -> Self {
+                Self(duration)
+            }
+        }
+
+        impl From<$name> for Duration {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", humantime::format_duration(self.0))
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_u64(self.$as())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserializer
+                    .deserialize_any(DurationVisitor {
+                        unit: $unit,
+                        from_int: Duration::$from,
+                    })
+                    .map(Self)
+            }
+        }
+    };
+}
+
+config_duration!(
+    DurationSecs,
+    "seconds",
+    from_secs,
+    as_secs,
+    |d: Duration| d.as_secs()
+);
+config_duration!(
+    DurationMs,
+    "milliseconds",
+    from_millis,
+    as_millis,
+    |d: Duration| { u64::try_from(d.as_millis()).unwrap_or(u64::MAX) }
+);
+
+/// Accepts an integer in the unit of the target type or a humantime string
+struct DurationVisitor {
+    unit: &'static str,
+    from_int: fn(u64) -> Duration,
+}
+
+impl de::Visitor<'_> for DurationVisitor {
+    type Value = Duration;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "a number of {} or a duration such as \"15m\" or \"24h\"",
+            self.unit
+        )
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Duration, E> {
+        Ok((self.from_int)(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Duration, E> {
+        u64::try_from(value)
+            .map(self.from_int)
+            .map_err(|_| E::invalid_value(de::Unexpected::Signed(value), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Duration, E> {
+        humantime::parse_duration(value.trim())
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Config {
+        timeout: DurationSecs,
+        delay: DurationMs,
+    }
+
+    #[test]
+    fn test_deserialize_integers_in_the_unit_of_the_type() {
+        let config: Config = serde_json::from_str(r#"{"timeout": 90, "delay": 250}"#)
+            .expect("Integers are accepted");
+        assert_eq!(config.timeout.as_duration(), Duration::from_secs(90));
+        assert_eq!(config.delay.as_duration(), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_deserialize_humantime_strings() {
+        let config: Config = serde_json::from_str(r#"{"timeout": "15m", "delay": "2s"}"#)
+            .expect("Humantime strings are accepted");
+        assert_eq!(config.timeout, DurationSecs::from_secs(900));
+        assert_eq!(config.delay.as_millis(), 2000);
+        assert_eq!(config.timeout.to_string(), "15m");
+    }
+
+    #[test]
+    fn test_reject_invalid_durations() {
+        assert!(serde_json::from_str::<DurationSecs>("-5").is_err());
+        assert!(serde_json::from_str::<DurationSecs>(r#""soon""#).is_err());
+    }
+
+    #[test]
+    fn test_serialize_as_integer() {
+        assert_eq!(
+            serde_json::to_string(&DurationSecs::from_secs(3600)).expect("Serializes"),
+            "3600"
+        );
+        assert_eq!(
+            serde_json::to_string(&DurationMs::from_millis(40)).expect("Serializes"),
+            "40"
+        );
+    }
+}
@@ -1,11 +1,48 @@
+use acci_core::secret_store::ensure_resolved;
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::sync::Arc;
 use thiserror::Error;
 use time::{Duration, OffsetDateTime};
 use uuid::Uuid;
 
+use super::claims::{ClaimsEnricher, TokenTenant};
+use super::duration::DurationSecs;
+use super::secret::Secret;
+use crate::config::AuthConfig;
+use crate::models::user::User;
+use crate::session::Session;
+
 const JWT_EXPIRATION_HOURS: i64 = 24;
 
+/// Clock skew between services tolerated by default when validating tokens
+pub const DEFAULT_JWT_LEEWAY_SECS: u64 = 30;
+
+/// Largest clock skew that can be tolerated
+///
+/// A larger leeway would keep expired tokens usable for too long.
+pub const MAX_JWT_LEEWAY_SECS: u64 = 300;
+
+/// Issuer of the tokens unless configured otherwise
+pub const DEFAULT_JWT_ISSUER: &str = "acci_framework";
+
+/// Claim names custom claims cannot use
+///
+/// Covers the registered claims validators rely on and the standard claims of
+/// [`Claims`], which a custom claim of the same name would shadow.
+pub const RESERVED_CLAIMS: &[&str] = &[
+    "sub",
+    "exp",
+    "iat",
+    "nbf",
+    "iss",
+    "aud",
+    "jti",
+    "email",
+    "tenant_id",
+];
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub sub: Uuid,               // Subject (User ID)
@@ -13,6 +50,39 @@ pub struct Claims {
     pub iat: i64,                // Issued At
     pub email: String,           // User's email
     pub tenant_id: Option<Uuid>, // Current tenant context (if any)
+    /// Not Before; tokens issued before it was added have none
+    #[serde(default)]
+    pub nbf: i64,
+    /// Issuer, verified against [`JwtUtils::with_issuer`]
+    pub iss: String,
+    /// Audience, the [`tenant_audience`] of `tenant_id` for tenant tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    /// Custom claims added by [`ClaimsEnricher`]s
+    #[serde(flatten)]
+    pub custom: Map<String, Value>,
+}
+
+impl Claims {
+    /// Add a custom claim, rejecting the [`RESERVED_CLAIMS`]
+    pub fn insert_custom(&mut self, name: impl Into<String>, value: Value) -> Result<(), JwtError> {
+        let name = name.into();
+        if RESERVED_CLAIMS.contains(&name.as_str()) {
+            return Err(JwtError::ReservedClaim(name));
+        }
+        self.custom.insert(name, value);
+        Ok(())
+    }
+
+    /// A custom claim of the token
+    pub fn custom_claim(&self, name: &str) -> Option<&Value> {
+        self.custom.get(name)
+    }
+}
+
+/// Audience of the tokens issued for `tenant_id`
+pub fn tenant_audience(tenant_id: Uuid) -> String {
+    format!("tenant:{tenant_id}")
 }
 
 #[derive(Debug, Error)]
@@ -23,11 +93,29 @@ pub enum JwtError {
     TokenValidation(String),
     #[error("Token expired")]
     TokenExpired,
+    #[error("Token is not valid yet")]
+    TokenNotYetValid,
+    #[error("Token was not issued by this service")]
+    InvalidIssuer,
+    #[error("Token was issued for another tenant")]
+    TenantMismatch,
+    #[error("Claim {0} is reserved")]
+    ReservedClaim(String),
+    #[error("Token of {size} bytes exceeds the limit of {limit} bytes")]
+    TokenTooLarge { size: usize, limit: usize },
 }
 
 pub struct JwtUtils {
     encoding_key: EncodingKey,
     decoding_key: DecodingKey,
+    /// Add custom claims to the tokens of [`Self::issue_token`]
+    enrichers: Vec<Arc<dyn ClaimsEnricher>>,
+    /// Largest token [`Self::issue_token`] hands out, unlimited if unset
+    max_token_bytes: Option<usize>,
+    /// Clock skew tolerated on `exp`, `nbf` and `iat`, in seconds
+    leeway: u64,
+    /// `iss` of the issued tokens, the only one accepted
+    issuer: String,
 }
 
 impl JwtUtils {
@@ -35,6 +123,89 @@ impl JwtUtils {
         Self {
             encoding_key: EncodingKey::from_secret(secret),
             decoding_key: DecodingKey::from_secret(secret),
+            enrichers: Vec::new(),
+            max_token_bytes: None,
+            leeway: DEFAULT_JWT_LEEWAY_SECS,
+            issuer: DEFAULT_JWT_ISSUER.to_string(),
+        }
+    }
+
+    /// Create from the configured secret, failing if it is an unresolved reference
+    pub fn from_secret(secret: &Secret<String>) -> acci_core::error::Result<Self> {
+        ensure_resolved("jwt_secret", secret)?;
+        Ok(Self::new(secret.as_bytes()))
+    }
+
+    /// Create from the configured secret with the built-in enrichers and token
+    /// size limit of [`AuthConfig::claims`]
+    ///
+    /// Deployments register their own enrichers with [`Self::with_enricher`].
+    pub fn from_config(config: &AuthConfig) -> acci_core::error::Result<Self> {
+        let mut jwt_utils = Self::from_secret(&config.jwt_secret)?
+            .with_max_token_bytes(config.claims.max_token_bytes)
+            .with_leeway(config.jwt_leeway_secs)
+            .with_issuer(config.jwt_issuer.clone());
+        for enricher in config.claims.enrichers() {
+            jwt_utils = jwt_utils.with_enricher(enricher);
+        }
+        Ok(jwt_utils)
+    }
+
+    /// Run `enricher` for every token issued with [`Self::issue_token`]
+    pub fn with_enricher(mut self, enricher: Arc<dyn ClaimsEnricher>) -> Self {
+        self.enrichers.push(enricher);
+        self
+    }
+
+    /// Fail issuing tokens that are larger than `max_token_bytes` once encoded
+    pub fn with_max_token_bytes(mut self, max_token_bytes: usize) -> Self {
+        self.max_token_bytes = Some(max_token_bytes);
+        self
+    }
+
+    /// Tolerate `leeway` of clock skew, capped at [`MAX_JWT_LEEWAY_SECS`]
+    pub fn with_leeway(mut self, leeway: DurationSecs) -> Self {
+        self.leeway = leeway.as_secs().min(MAX_JWT_LEEWAY_SECS);
+        self
+    }
+
+    /// Issue and accept only tokens of `issuer`
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuer = issuer.into();
+        self
+    }
+
+    /// Create a token for `user` carrying the claims of the registered enrichers
+    ///
+    /// Fails if an enricher adds a reserved claim or the token exceeds the
+    /// configured size limit.
+    pub fn issue_token(
+        &self,
+        user: &User,
+        tenant: Option<&TokenTenant>,
+        session: Option<&Session>,
+    ) -> Result<String, JwtError> {
+        let mut claims = self.claims(user.id, &user.email, tenant.map(|tenant| tenant.tenant.id));
+        for enricher in &self.enrichers {
+            enricher.enrich(&mut claims, user, tenant, session)?;
+        }
+        // Enrichers can also write the public map directly
+        if let Some(name) = claims
+            .custom
+            .keys()
+            .find(|name| RESERVED_CLAIMS.contains(&name.as_str()))
+        {
+            return Err(JwtError::ReservedClaim(name.clone()));
+        }
+
+        let token = encode(&Header::default(), &claims, &self.encoding_key)
+            .map_err(|e| JwtError::TokenCreation(e.to_string()))?;
+        match self.max_token_bytes {
+            Some(limit) if token.len() > limit => Err(JwtError::TokenTooLarge {
+                size: token.len(),
+                limit,
+            }),
+            _ => Ok(token),
         }
     }
 
@@ -44,19 +215,28 @@ impl JwtUtils {
         email: &str,
         tenant_id: Option<Uuid>,
     ) -> Result<String, JwtError> {
+        let claims = self.claims(user_id, email, tenant_id);
+
+        encode(&Header::default(), &claims, &self.encoding_key)
+            .map_err(|e| JwtError::TokenCreation(e.to_string()))
+    }
+
+    /// Standard claims of a token issued now
+    fn claims(&self, user_id: Uuid, email: &str, tenant_id: Option<Uuid>) -> Claims {
         let now = OffsetDateTime::now_utc();
         let exp = now + Duration::hours(JWT_EXPIRATION_HOURS);
 
-        let claims = Claims {
+        Claims {
             sub: user_id,
             exp: exp.unix_timestamp(),
             iat: now.unix_timestamp(),
+            nbf: now.unix_timestamp(),
             email: email.to_string(),
             tenant_id,
-        };
-
-        encode(&Header::default(), &claims, &self.encoding_key)
-            .map_err(|e| JwtError::TokenCreation(e.to_string()))
+            iss: self.issuer.clone(),
+            aud: tenant_id.map(tenant_audience),
+            custom: Map::new(),
+        }
     }
 
     // For backwards compatibility
@@ -68,14 +248,56 @@ impl JwtUtils {
         self.create_token(user_id, email, None)
     }
 
-    pub fn validate_token(&self, token: &str) -> Result<Claims, JwtError> {
-        let validation = Validation::default();
+    /// Validate `token` presented under the resolved tenant `tenant_id`
+    ///
+    /// The token must come from the configured issuer. With a tenant, it must
+    /// have been issued for that tenant, both in `tenant_id` and `aud`, so a
+    /// token of one tenant cannot be used against another; without one the
+    /// tenant is not checked. The configured leeway is tolerated on `exp`,
+    /// `nbf` and `iat`.
+    pub fn validate_token(&self, token: &str, tenant_id: Option<Uuid>) -> Result<Claims, JwtError> {
+        let mut validation = Validation::default();
+        validation.leeway = self.leeway;
+        validation.validate_nbf = true;
+        validation.set_issuer(&[&self.issuer]);
+        match tenant_id {
+            Some(tenant_id) => {
+                validation.set_audience(&[tenant_audience(tenant_id)]);
+                validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+            },
+            None => {
+                validation.validate_aud = false;
+                validation.set_required_spec_claims(&["exp", "iss"]);
+            },
+        }
 
-        decode::<Claims>(token, &self.decoding_key, &validation)
+        let claims = decode::<Claims>(token, &self.decoding_key, &validation)
             .map(|data| data.claims)
             .map_err(|e| match e.kind() {
                 jsonwebtoken::errors::ErrorKind::ExpiredSignature => JwtError::TokenExpired,
+                jsonwebtoken::errors::ErrorKind::ImmatureSignature => JwtError::TokenNotYetValid,
+                jsonwebtoken::errors::ErrorKind::InvalidIssuer => JwtError::InvalidIssuer,
+                jsonwebtoken::errors::ErrorKind::InvalidAudience => JwtError::TenantMismatch,
+                jsonwebtoken::errors::ErrorKind::MissingRequiredClaim(claim) => {
+                    match claim.as_str() {
+                        "iss" => JwtError::InvalidIssuer,
+                        "aud" => JwtError::TenantMismatch,
+                        _ => JwtError::TokenValidation(e.to_string()),
+                    }
+                },
                 _ => JwtError::TokenValidation(e.to_string()),
-            })
+            })?;
+
+        if tenant_id.is_some() && claims.tenant_id != tenant_id {
+            return Err(JwtError::TenantMismatch);
+        }
+
+        // `iat` is not checked by jsonwebtoken
+        let latest_iat = OffsetDateTime::now_utc().unix_timestamp() + self.leeway as i64;
+        if claims.iat > latest_iat {
+            return Err(JwtError::TokenNotYetValid);
+        }
+
+        Ok(claims)
     }
 }
@@ -1,2 +1,11 @@
+pub mod auth_log;
+pub mod batch;
+pub mod bounded_json;
+pub mod claims;
+pub mod clock;
+pub mod duration;
 pub mod jwt;
 pub mod password;
+pub mod patch;
+pub mod secret;
+pub mod xml_signature;
@@ -2,6 +2,7 @@ use argon2::{
     Argon2,
     password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
 };
+use std::sync::OnceLock;
 use thiserror::Error;
 use zxcvbn;
 
@@ -21,6 +22,11 @@ pub enum PasswordError {
     Other(String),
 }
 
+/// Stored in place of a password hash for accounts that have no password
+///
+/// It is not a PHC string, so no password ever verifies against it.
+pub const NO_PASSWORD_HASH: &str = "!";
+
 pub fn hash_password(password: &str) -> Result<String, PasswordError> {
     let salt = SaltString::generate(&mut OsRng);
     let argon2 = Argon2::default();
@@ -32,6 +38,11 @@ pub fn hash_password(password: &str) -> Result<String, PasswordError> {
 }
 
 pub fn verify_password(password: &str, hash: &str) -> Result<bool, PasswordError> {
+    if hash == NO_PASSWORD_HASH {
+        verify_decoy_password(password);
+        return Ok(false);
+    }
+
     let parsed_hash =
         PasswordHash::new(hash).map_err(|e| PasswordError::VerificationError(e.to_string()))?;
 
@@ -40,6 +51,52 @@ pub fn verify_password(password: &str, hash: &str) -> Result<bool, PasswordError
         .is_ok())
 }
 
+/// Hash of a random password, made with the default Argon2 parameters
+///
+/// Used as the decoy when hashing a fresh one fails.
+const FALLBACK_DECOY_HASH: &str = "$argon2id$v=19$m=19456,t=2,p=1$wpN7RPlIIBpK3vTMwEt+tw$SkmLLvAyUCpkz8o5qp8VG0OstnLllJ88VvVBPNZBM2E";
+
+/// Verify `password` against a decoy hash and discard the result
+///
+/// Used when there is no account to check a password against, so that the
+/// request takes as long as a real verification. The decoy is created with
+/// [`hash_password`] on first use and therefore carries the same Argon2
+/// parameters as stored password hashes.
+pub fn verify_decoy_password(password: &str) {
+    static DECOY_HASH: OnceLock<String> = OnceLock::new();
+    let decoy = DECOY_HASH.get_or_init(|| {
+        hash_password(generate_salt().as_str()).unwrap_or_else(|_| FALLBACK_DECOY_HASH.to_string())
+    });
+
+    let _ = verify_password(password, decoy);
+}
+
+/// Checks passwords against stored hashes
+///
+/// [`UserService`](crate::services::user::UserService) verifies passwords
+/// through this trait, so tests can observe how many verifications a flow runs.
+pub trait PasswordVerifier: Send + Sync {
+    /// Whether `password` matches `hash`, see [`verify_password`]
+    fn verify(&self, password: &str, hash: &str) -> Result<bool, PasswordError>;
+
+    /// Verify `password` against a decoy hash, see [`verify_decoy_password`]
+    fn verify_decoy(&self, password: &str);
+}
+
+/// [`PasswordVerifier`] using Argon2
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Argon2Verifier;
+
+impl PasswordVerifier for Argon2Verifier {
+    fn verify(&self, password: &str, hash: &str) -> Result<bool, PasswordError> {
+        verify_password(password, hash)
+    }
+
+    fn verify_decoy(&self, password: &str) {
+        verify_decoy_password(password);
+    }
+}
+
 pub fn check_password_strength(password: &str, user_inputs: &[&str]) -> Result<(), PasswordError> {
     let estimate = zxcvbn::zxcvbn(password, user_inputs)
         .map_err(|e| PasswordError::Other(format!("zxcvbn error: {}", e)))?;
@@ -56,3 +113,43 @@ pub fn check_password_strength(password: &str, user_inputs: &[&str]) -> Result<(
 pub fn generate_salt() -> SaltString {
     SaltString::generate(&mut OsRng)
 }
+
+/// Counting [`PasswordVerifier`] for tests
+///
+/// Shared by the unit tests in this crate and by downstream test crates (via
+/// the `test-support` feature).
+#[cfg(any(test, feature = "test-support"))]
+pub mod mock {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Verifies with Argon2 and counts every verification, decoys included
+    #[derive(Debug, Default)]
+    pub struct CountingPasswordVerifier {
+        verifications: AtomicUsize,
+    }
+
+    impl CountingPasswordVerifier {
+        /// Creates a verifier that has not verified anything yet
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Number of verifications run so far
+        pub fn verifications(&self) -> usize {
+            self.verifications.load(Ordering::SeqCst)
+        }
+    }
+
+    impl PasswordVerifier for CountingPasswordVerifier {
+        fn verify(&self, password: &str, hash: &str) -> Result<bool, PasswordError> {
+            self.verifications.fetch_add(1, Ordering::SeqCst);
+            Argon2Verifier.verify(password, hash)
+        }
+
+        fn verify_decoy(&self, password: &str) {
+            self.verifications.fetch_add(1, Ordering::SeqCst);
+            Argon2Verifier.verify_decoy(password);
+        }
+    }
+}
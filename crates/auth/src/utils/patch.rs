@@ -0,0 +1,51 @@
+//! Helpers for JSON Merge Patch (RFC 7386) request bodies
+//!
+//! A merge patch leaves omitted members unchanged and removes members set to
+//! `null`. A plain `Option` field cannot tell the two apart, so nullable
+//! fields of a patch are `Option<Option<T>>` deserialized with
+//! [`deserialize_nullable`].
+
+use serde::{Deserialize, Deserializer};
+
+/// Deserialize a nullable merge patch field into `Option<Option<T>>`
+///
+/// Use together with `#[serde(default)]`: an omitted field is `None`, an
+/// explicit `null` is `Some(None)` and any other value is `Some(Some(value))`.
+pub fn deserialize_nullable<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    #[derive(Deserialize)]
+    struct Patch {
+        #[serde(default, deserialize_with = "deserialize_nullable")]
+        metadata: Option<Option<Value>>,
+    }
+
+    #[test]
+    fn test_omitted_field_is_unchanged() {
+        let patch: Patch = serde_json::from_str("{}").expect("Patch is valid");
+        assert_eq!(patch.metadata, None);
+    }
+
+    #[test]
+    fn test_null_field_is_cleared() {
+        let patch: Patch = serde_json::from_str(r#"{"metadata": null}"#).expect("Patch is valid");
+        assert_eq!(patch.metadata, Some(None));
+    }
+
+    #[test]
+    fn test_value_field_is_set() {
+        let patch: Patch =
+            serde_json::from_str(r#"{"metadata": {"plan": "pro"}}"#).expect("Patch is valid");
+        assert_eq!(patch.metadata, Some(Some(json!({ "plan": "pro" }))));
+    }
+}
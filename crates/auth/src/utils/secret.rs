@@ -0,0 +1,6 @@
+//! Redacted secrets for configuration values
+//!
+//! The type lives in [`acci_core::secret`], next to the secret stores that
+//! resolve secrets configured as references.
+
+pub use acci_core::secret::Secret;
@@ -0,0 +1,379 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use openssl::hash::MessageDigest;
+use openssl::sign::Verifier;
+use openssl::x509::X509;
+use roxmltree::{Node, NodeId};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+pub const XMLDSIG_NS: &str = "http://www.w3.org/2000/09/xmldsig#";
+pub const EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+pub const ENVELOPED_SIGNATURE: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+pub const RSA_SHA256: &str = "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256";
+pub const SHA256: &str = "http://www.w3.org/2001/04/xmlenc#sha256";
+
+/// Reasons an XML signature is rejected
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum XmlSignatureError {
+    #[error("Malformed signature: {0}")]
+    Malformed(String),
+
+    #[error("Unsupported algorithm: {0}")]
+    UnsupportedAlgorithm(String),
+
+    #[error("Signature does not reference the signed element")]
+    ReferenceMismatch,
+
+    #[error("Digest of the signed element does not match")]
+    DigestMismatch,
+
+    #[error("Signature value does not verify")]
+    InvalidSignature,
+
+    #[error("Invalid certificate: {0}")]
+    Certificate(String),
+}
+
+/// Parse a PEM certificate, or the bare base64 DER found in SAML metadata
+pub fn parse_certificate(certificate: &str) -> Result<X509, XmlSignatureError> {
+    let certificate = certificate.trim();
+    if certificate.starts_with("-----BEGIN") {
+        return X509::from_pem(certificate.as_bytes())
+            .map_err(|err| XmlSignatureError::Certificate(err.to_string()));
+    }
+
+    let der = STANDARD
+        .decode(strip_whitespace(certificate))
+        .map_err(|err| XmlSignatureError::Certificate(err.to_string()))?;
+    X509::from_der(&der).map_err(|err| XmlSignatureError::Certificate(err.to_string()))
+}
+
+/// Verify the enveloped `ds:Signature` child `signature` of `signed`
+///
+/// Only the profile SAML identity providers use is accepted: exclusive
+/// canonicalization, RSA-SHA256 and a single SHA-256 reference to the ID of
+/// `signed`. The signature is checked against `certificate`, never against a
+/// key embedded in the document.
+pub fn verify_enveloped_signature(
+    signed: Node,
+    signature: Node,
+    certificate: &X509,
+) -> Result<(), XmlSignatureError> {
+    if signature.parent_element() != Some(signed) || !is_dsig(signature, "Signature") {
+        return Err(XmlSignatureError::Malformed(
+            "Signature is not enveloped in the signed element".to_string(),
+        ));
+    }
+    let signed_id = signed
+        .attribute("ID")
+        .ok_or_else(|| XmlSignatureError::Malformed("Signed element has no ID".to_string()))?;
+
+    let signed_info = dsig_child(signature, "SignedInfo")?;
+    let c14n_method = dsig_child(signed_info, "CanonicalizationMethod")?;
+    require_algorithm(c14n_method, EXC_C14N)?;
+    require_algorithm(dsig_child(signed_info, "SignatureMethod")?, RSA_SHA256)?;
+
+    let references: Vec<Node> = signed_info
+        .children()
+        .filter(|node| is_dsig(*node, "Reference"))
+        .collect();
+    let [reference] = references.as_slice() else {
+        return Err(XmlSignatureError::Malformed(
+            "Expected exactly one reference".to_string(),
+        ));
+    };
+    if reference.attribute("URI") != Some(&format!("#{}", signed_id)) {
+        return Err(XmlSignatureError::ReferenceMismatch);
+    }
+
+    // Transforms: enveloped signature removal followed by exclusive c14n
+    let mut enveloped = false;
+    let mut prefixes = Vec::new();
+    for transform in dsig_child(*reference, "Transforms")?
+        .children()
+        .filter(|node| is_dsig(*node, "Transform"))
+    {
+        match transform.attribute("Algorithm") {
+            Some(ENVELOPED_SIGNATURE) => enveloped = true,
+            Some(EXC_C14N) => prefixes = inclusive_prefixes(transform),
+            other => {
+                return Err(XmlSignatureError::UnsupportedAlgorithm(
+                    other.unwrap_or_default().to_string(),
+                ));
+            },
+        }
+    }
+    if !enveloped {
+        return Err(XmlSignatureError::Malformed(
+            "Reference is not an enveloped signature".to_string(),
+        ));
+    }
+    require_algorithm(dsig_child(*reference, "DigestMethod")?, SHA256)?;
+
+    let expected_digest = decode_base64(dsig_child(*reference, "DigestValue")?)?;
+    let digest = Sha256::digest(canonicalize(signed, Some(signature.id()), &prefixes));
+    if digest.as_slice() != expected_digest.as_slice() {
+        return Err(XmlSignatureError::DigestMismatch);
+    }
+
+    let signature_value = decode_base64(dsig_child(signature, "SignatureValue")?)?;
+    let canonical_signed_info = canonicalize(signed_info, None, &inclusive_prefixes(c14n_method));
+    let public_key = certificate
+        .public_key()
+        .map_err(|err| XmlSignatureError::Certificate(err.to_string()))?;
+    let mut verifier = Verifier::new(MessageDigest::sha256(), &public_key)
+        .map_err(|err| XmlSignatureError::Certificate(err.to_string()))?;
+    let valid = verifier
+        .update(canonical_signed_info.as_bytes())
+        .and_then(|_| verifier.verify(&signature_value))
+        .unwrap_or(false);
+    if !valid {
+        return Err(XmlSignatureError::InvalidSignature);
+    }
+
+    Ok(())
+}
+
+/// Exclusive XML canonicalization (without comments) of the subtree at `node`
+///
+/// `exclude` drops an element with its subtree, as the enveloped signature
+/// transform does. `inclusive_prefixes` is the `InclusiveNamespaces` prefix list.
+pub fn canonicalize(node: Node, exclude: Option<NodeId>, inclusive_prefixes: &[String]) -> String {
+    let mut output = String::new();
+    write_element(
+        node,
+        exclude,
+        inclusive_prefixes,
+        &BTreeMap::new(),
+        &mut output,
+    );
+    output
+}
+
+fn write_element(
+    node: Node,
+    exclude: Option<NodeId>,
+    inclusive_prefixes: &[String],
+    rendered: &BTreeMap<String, String>,
+    output: &mut String,
+) {
+    let input = node.document().input_text();
+    let qname = element_qname(node);
+
+    // Namespaces visibly utilized by the element and its attributes
+    let mut utilized = vec![prefix_of(qname).to_string()];
+    for attribute in node.attributes() {
+        let attribute_prefix = prefix_of(&input[attribute.range_qname()]);
+        if !attribute_prefix.is_empty() {
+            utilized.push(attribute_prefix.to_string());
+        }
+    }
+    utilized.extend(inclusive_prefixes.iter().cloned());
+
+    let mut declarations = BTreeMap::new();
+    for prefix in utilized {
+        let uri = node
+            .namespaces()
+            .find(|namespace| namespace.name().unwrap_or_default() == prefix)
+            .map(|namespace| namespace.uri())
+            .unwrap_or_default();
+        let already_rendered = rendered.get(&prefix).map(String::as_str);
+        // An empty default namespace only needs undeclaring if one was rendered
+        let unchanged =
+            already_rendered == Some(uri) || (uri.is_empty() && already_rendered.is_none());
+        if !unchanged {
+            declarations.insert(prefix, uri.to_string());
+        }
+    }
+
+    output.push('<');
+    output.push_str(qname);
+    for (prefix, uri) in &declarations {
+        if prefix.is_empty() {
+            output.push_str(" xmlns=\"");
+        } else {
+            output.push_str(" xmlns:");
+            output.push_str(prefix);
+            output.push_str("=\"");
+        }
+        escape_attribute(uri, output);
+        output.push('"');
+    }
+
+    let mut attributes: Vec<_> = node.attributes().collect();
+    attributes
+        .sort_by_key(|attribute| (attribute.namespace().unwrap_or_default(), attribute.name()));
+    for attribute in attributes {
+        output.push(' ');
+        output.push_str(&input[attribute.range_qname()]);
+        output.push_str("=\"");
+        escape_attribute(attribute.value(), output);
+        output.push('"');
+    }
+    output.push('>');
+
+    let mut in_scope = rendered.clone();
+    in_scope.extend(declarations);
+    for child in node.children() {
+        if Some(child.id()) == exclude {
+            continue;
+        }
+        if child.is_element() {
+            write_element(child, exclude, inclusive_prefixes, &in_scope, output);
+        } else if child.is_text() {
+            escape_text(child.text().unwrap_or_default(), output);
+        } else if let Some(pi) = child.pi() {
+            output.push_str("<?");
+            output.push_str(pi.target);
+            if let Some(value) = pi.value {
+                output.push(' ');
+                output.push_str(value);
+            }
+            output.push_str("?>");
+        }
+    }
+
+    output.push_str("</");
+    output.push_str(qname);
+    output.push('>');
+}
+
+/// Qualified name of an element as written in the document
+fn element_qname<'a>(node: Node<'a, '_>) -> &'a str {
+    let input = node.document().input_text();
+    let start = &input[node.range().start + 1..];
+    let end = start
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .unwrap_or(start.len());
+    &start[..end]
+}
+
+fn prefix_of(qname: &str) -> &str {
+    qname.split_once(':').map_or("", |(prefix, _)| prefix)
+}
+
+fn escape_text(text: &str, output: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '\r' => output.push_str("&#xD;"),
+            c => output.push(c),
+        }
+    }
+}
+
+fn escape_attribute(value: &str, output: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '"' => output.push_str("&quot;"),
+            '\t' => output.push_str("&#x9;"),
+            '\n' => output.push_str("&#xA;"),
+            '\r' => output.push_str("&#xD;"),
+            c => output.push(c),
+        }
+    }
+}
+
+fn is_dsig(node: Node, name: &str) -> bool {
+    node.is_element()
+        && node.tag_name().namespace() == Some(XMLDSIG_NS)
+        && node.tag_name().name() == name
+}
+
+fn dsig_child<'a, 'input>(
+    node: Node<'a, 'input>,
+    name: &str,
+) -> Result<Node<'a, 'input>, XmlSignatureError> {
+    node.children()
+        .find(|child| is_dsig(*child, name))
+        .ok_or_else(|| XmlSignatureError::Malformed(format!("Missing {}", name)))
+}
+
+fn require_algorithm(node: Node, algorithm: &str) -> Result<(), XmlSignatureError> {
+    match node.attribute("Algorithm") {
+        Some(actual) if actual == algorithm => Ok(()),
+        actual => Err(XmlSignatureError::UnsupportedAlgorithm(
+            actual.unwrap_or_default().to_string(),
+        )),
+    }
+}
+
+/// Prefix list of an `InclusiveNamespaces` child of a c14n algorithm element
+fn inclusive_prefixes(node: Node) -> Vec<String> {
+    node.children()
+        .find(|child| child.tag_name().namespace() == Some(EXC_C14N))
+        .and_then(|child| child.attribute("PrefixList"))
+        .map(|list| {
+            list.split_whitespace()
+                .map(|prefix| if prefix == "#default" { "" } else { prefix }.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn decode_base64(node: Node) -> Result<Vec<u8>, XmlSignatureError> {
+    STANDARD
+        .decode(strip_whitespace(node.text().unwrap_or_default()))
+        .map_err(|err| XmlSignatureError::Malformed(err.to_string()))
+}
+
+fn strip_whitespace(value: &str) -> String {
+    value.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn c14n(xml: &str, path: &[&str]) -> String {
+        let document = roxmltree::Document::parse(xml).expect("Valid XML");
+        let node = path.iter().fold(document.root_element(), |node, name| {
+            node.children()
+                .find(|child| child.tag_name().name() == *name)
+                .expect("Element exists")
+        });
+        canonicalize(node, None, &[])
+    }
+
+    #[test]
+    fn test_exclusive_canonicalization() {
+        let xml = concat!(
+            r#"<a:Root xmlns:a="urn:a" xmlns:b="urn:b" xmlns:unused="urn:unused">"#,
+            r#"<b:Child z="1" a:y="&quot;2&quot;" b="x&lt;y" ><a:Empty/>"#,
+            "<!-- dropped -->text &amp; more\r\n</b:Child></a:Root>"
+        );
+
+        assert_eq!(
+            c14n(xml, &[]),
+            concat!(
+                r#"<a:Root xmlns:a="urn:a"><b:Child xmlns:b="urn:b" b="x&lt;y" z="1" "#,
+                r#"a:y="&quot;2&quot;"><a:Empty></a:Empty>text &amp; more"#,
+                "\n</b:Child></a:Root>"
+            )
+        );
+
+        // A subtree declares the namespaces it uses itself
+        assert_eq!(
+            c14n(xml, &["Child"]),
+            concat!(
+                r#"<b:Child xmlns:a="urn:a" xmlns:b="urn:b" b="x&lt;y" z="1" "#,
+                r#"a:y="&quot;2&quot;"><a:Empty></a:Empty>text &amp; more"#,
+                "\n</b:Child>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_default_namespace_is_rendered_once() {
+        let xml = r#"<Root xmlns="urn:d"><Child><Inner xmlns="">x</Inner></Child></Root>"#;
+        assert_eq!(
+            c14n(xml, &[]),
+            r#"<Root xmlns="urn:d"><Child><Inner xmlns="">x</Inner></Child></Root>"#
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! Golden-file tests of the CEF lines sent to SIEMs
+//!
+//! The expected lines are in `golden/cef`; SIEM parsers depend on their exact
+//! layout, so a change there needs a deliberate update of the files.
+
+use acci_auth::repository::{AuditEvent, TenantAuditEvent};
+use acci_auth::security::{LoginAttempt, RiskLevel};
+use acci_auth::{CefFormatter, SecurityEvent};
+use serde_json::json;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+const TENANT_ID: &str = "6f1c8e7a-2b3d-4c5e-9f10-1a2b3c4d5e6f";
+const USER_ID: &str = "0b9e4d2c-7a61-4f38-8c5d-3e2f1a0b9c8d";
+
+fn formatter() -> CefFormatter {
+    CefFormatter::default().with_version("1.0.0")
+}
+
+/// 2025-01-01T00:00:00Z
+fn occurred_at() -> OffsetDateTime {
+    OffsetDateTime::from_unix_timestamp(1_735_689_600).expect("valid timestamp")
+}
+
+fn uuid(value: &str) -> Uuid {
+    value.parse().expect("valid UUID")
+}
+
+fn assert_golden(actual: &str, golden: &str) {
+    assert_eq!(actual, golden.trim_end_matches('\n'));
+}
+
+#[test]
+fn test_tenant_audit_event() {
+    let event = SecurityEvent {
+        occurred_at: occurred_at(),
+        ..SecurityEvent::from(&TenantAuditEvent {
+            tenant_id: uuid(TENANT_ID),
+            user_id: Some(uuid(USER_ID)),
+            action: "TENANT_UPDATE".to_string(),
+            details: json!({ "name": "Acme" }),
+            ip_address: Some("192.0.2.10".to_string()),
+            user_agent: Some("Mozilla/5.0 (X11; Linux x86_64)".to_string()),
+        })
+    };
+
+    assert_golden(
+        &formatter().format(&event),
+        include_str!("golden/cef/tenant_audit.cef"),
+    );
+}
+
+#[test]
+fn test_user_audit_event_of_security_action() {
+    let event = SecurityEvent {
+        occurred_at: occurred_at(),
+        ..SecurityEvent::from(&AuditEvent {
+            user_id: uuid(USER_ID),
+            action: "SESSION_INVALIDATED_SECURITY_BREACH".to_string(),
+            details: json!({}),
+            ip_address: Some("2001:db8::1".to_string()),
+            user_agent: None,
+        })
+    };
+
+    assert_golden(
+        &formatter().format(&event),
+        include_str!("golden/cef/user_audit.cef"),
+    );
+}
+
+#[test]
+fn test_suspicious_login_alert() {
+    let attempt = LoginAttempt {
+        tenant_id: TENANT_ID.to_string(),
+        username: "alice@example.com".to_string(),
+        ip_address: "198.51.100.7".to_string(),
+        user_agent: "python-requests/2.31".to_string(),
+        timestamp: chrono::Utc::now(),
+        fingerprint: None,
+        geolocation: None,
+        successful: false,
+    };
+    let event = SecurityEvent {
+        occurred_at: occurred_at(),
+        ..SecurityEvent::suspicious_login(&attempt, RiskLevel::Critical)
+    };
+
+    assert_golden(
+        &formatter()
+            .with_device_address("203.0.113.5")
+            .format(&event),
+        include_str!("golden/cef/suspicious_login.cef"),
+    );
+}
+
+#[test]
+fn test_escaping_of_header_and_extension_values() {
+    let event = SecurityEvent {
+        name: "pipe | and back\\slash".to_string(),
+        username: Some("a=b\\c\nd".to_string()),
+        ..SecurityEvent::audit("CUSTOM|ACTION", occurred_at())
+    };
+
+    assert_golden(
+        &formatter().format(&event),
+        include_str!("golden/cef/escaping.cef"),
+    );
+}
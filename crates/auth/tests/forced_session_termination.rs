@@ -2,25 +2,26 @@ use acci_auth::{
     config::AuthConfig,
     services::session::SessionService,
     session::{
-        Session, SessionError, SessionFilter, SessionRepository,
-        types::{DeviceFingerprint, MfaStatus, SessionInvalidationReason},
+        SessionFilter, SessionRepository, mock::MockSessionRepository,
+        types::SessionInvalidationReason,
     },
 };
-use async_trait::async_trait;
-use serde_json::Value;
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::Duration;
+use time::OffsetDateTime;
 use uuid::Uuid;
 
 // Test-Repository mit simulierten Daten
 struct TestSessionRepository {
+    repo: Arc<MockSessionRepository>,
     user_sessions: Vec<(Uuid, u64)>, // (user_id, session_count)
     ip_sessions: Vec<(String, u64)>, // (ip_address, session_count)
 }
 
 impl TestSessionRepository {
-    fn new() -> Self {
-        Self {
+    async fn new() -> Self {
+        let test_repo = Self {
+            repo: Arc::new(MockSessionRepository::new()),
             user_sessions: vec![
                 (Uuid::new_v4(), 3), // User 1 hat 3 Sessions
                 (Uuid::new_v4(), 5), // User 2 hat 5 Sessions
@@ -30,121 +31,47 @@ impl TestSessionRepository {
                 ("192.168.1.1".to_string(), 2), // IP 1 hat 2 Sessions
                 ("10.0.0.1".to_string(), 4),    // IP 2 hat 4 Sessions
             ],
-        }
-    }
-}
-
-#[async_trait]
-impl SessionRepository for TestSessionRepository {
-    async fn create_session(
-        &self,
-        _user_id: Uuid,
-        _token_hash: String,
-        _expires_at: SystemTime,
-        _device_id: Option<String>,
-        _device_fingerprint: Option<DeviceFingerprint>,
-        _ip_address: Option<String>,
-        _user_agent: Option<String>,
-        _metadata: Option<Value>,
-    ) -> Result<Session, SessionError> {
-        unimplemented!("Not needed for this test")
-    }
-
-    async fn get_session(&self, _id: Uuid) -> Result<Option<Session>, SessionError> {
-        unimplemented!("Not needed for this test")
-    }
-
-    async fn get_session_by_token(
-        &self,
-        _token_hash: &str,
-    ) -> Result<Option<Session>, SessionError> {
-        unimplemented!("Not needed for this test")
-    }
-
-    async fn get_user_sessions(
-        &self,
-        _user_id: Uuid,
-        _filter: SessionFilter,
-    ) -> Result<Vec<Session>, SessionError> {
-        unimplemented!("Not needed for this test")
-    }
-
-    async fn update_session_activity(&self, _id: Uuid) -> Result<(), SessionError> {
-        unimplemented!("Not needed for this test")
-    }
-
-    async fn invalidate_session(
-        &self,
-        _id: Uuid,
-        _reason: SessionInvalidationReason,
-    ) -> Result<(), SessionError> {
-        unimplemented!("Not needed for this test")
-    }
-
-    async fn invalidate_all_user_sessions(
-        &self,
-        user_id: Uuid,
-        _reason: SessionInvalidationReason,
-    ) -> Result<u64, SessionError> {
-        // Finde den Benutzer und gib die Anzahl der beendeten Sessions zurück
-        for (uid, count) in &self.user_sessions {
-            if *uid == user_id {
-                return Ok(*count);
-            }
-        }
-        Ok(0) // Benutzer nicht gefunden, keine Sessions beendet
-    }
-
-    async fn invalidate_sessions_by_filter(
-        &self,
-        filter: SessionFilter,
-        _reason: SessionInvalidationReason,
-    ) -> Result<u64, SessionError> {
-        // Simuliere Filterergebnisse basierend auf dem Filter
-        match filter {
-            SessionFilter::All => Ok(10),     // Alle Sessions
-            SessionFilter::Active => Ok(8),   // Nur aktive Sessions
-            SessionFilter::Inactive => Ok(2), // Nur inaktive Sessions
-        }
-    }
-
-    async fn invalidate_sessions_by_ip(
-        &self,
-        ip_address: &str,
-        _reason: SessionInvalidationReason,
-    ) -> Result<u64, SessionError> {
-        // Finde die IP und gib die Anzahl der beendeten Sessions zurück
-        for (ip, count) in &self.ip_sessions {
-            if ip == ip_address {
-                return Ok(*count);
+        };
+
+        // Sessions der Benutzer anlegen und den IP-Adressen zuordnen
+        let mut ips = test_repo
+            .ip_sessions
+            .iter()
+            .flat_map(|(ip, count)| std::iter::repeat_n(ip.clone(), *count as usize));
+        for (user_id, count) in &test_repo.user_sessions {
+            for _ in 0..*count {
+                test_repo.add_session(*user_id, ips.next()).await;
             }
         }
-        Ok(0) // IP nicht gefunden, keine Sessions beendet
-    }
-
-    async fn rotate_session_token(
-        &self,
-        _id: Uuid,
-        _new_token_hash: String,
-    ) -> Result<(), SessionError> {
-        unimplemented!("Not needed for this test")
-    }
 
-    async fn cleanup_expired_sessions(&self) -> Result<u64, SessionError> {
-        unimplemented!("Not needed for this test")
+        test_repo
     }
 
-    async fn update_mfa_status(&self, _id: Uuid, _status: MfaStatus) -> Result<(), SessionError> {
-        unimplemented!("Not needed for this test")
+    async fn add_session(&self, user_id: Uuid, ip_address: Option<String>) {
+        self.repo
+            .create_session(
+                user_id,
+                Uuid::new_v4().to_string(),
+                OffsetDateTime::now_utc() + Duration::from_secs(3600),
+                None,
+                None,
+                ip_address,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .expect("Session sollte angelegt werden");
     }
 }
 
 #[tokio::test]
 async fn test_force_terminate_user_sessions() {
     // Setup
-    let repo = Arc::new(TestSessionRepository::new());
+    let repo = TestSessionRepository::new().await;
     let config = Arc::new(AuthConfig::default());
-    let service = SessionService::new(repo.clone(), config);
+    let service = SessionService::new(repo.repo.clone(), config);
 
     // Test: Beenden aller Sessions für Benutzer 1
     let user_id = repo.user_sessions[0].0;
@@ -174,9 +101,9 @@ async fn test_force_terminate_user_sessions() {
 #[tokio::test]
 async fn test_force_terminate_sessions_by_ip() {
     // Setup
-    let repo = Arc::new(TestSessionRepository::new());
+    let repo = TestSessionRepository::new().await;
     let config = Arc::new(AuthConfig::default());
-    let service = SessionService::new(repo.clone(), config);
+    let service = SessionService::new(repo.repo.clone(), config);
 
     // Test: Beenden aller Sessions für IP 1
     let ip_address = &repo.ip_sessions[0].0;
@@ -205,36 +132,45 @@ async fn test_force_terminate_sessions_by_ip() {
 #[tokio::test]
 async fn test_force_terminate_sessions_by_filter() {
     // Setup
-    let repo = Arc::new(TestSessionRepository::new());
+    let repo = TestSessionRepository::new().await;
     let config = Arc::new(AuthConfig::default());
-    let service = SessionService::new(repo.clone(), config);
+    let service = SessionService::new(repo.repo.clone(), config);
+
+    // Zwei bereits beendete Sessions hinzufügen: 8 aktive, 2 inaktive
+    let former_user = Uuid::new_v4();
+    repo.add_session(former_user, None).await;
+    repo.add_session(former_user, None).await;
+    repo.repo
+        .invalidate_all_user_sessions(former_user, SessionInvalidationReason::UserLogout)
+        .await
+        .expect("Die Beendigung der Sessions sollte erfolgreich sein");
 
-    // Test: Beenden aller aktiven Sessions
+    // Test: Beenden aller inaktiven Sessions
     let count = service
         .force_terminate_sessions_by_filter(
-            SessionFilter::Active,
+            SessionFilter::Inactive,
             SessionInvalidationReason::SecurityPolicyChange,
         )
         .await
         .expect("Die Beendigung der Sessions sollte erfolgreich sein");
 
     assert_eq!(
-        count, 8,
-        "Es sollten genau 8 aktive Sessions beendet werden"
+        count, 2,
+        "Es sollten genau 2 inaktive Sessions beendet werden"
     );
 
-    // Test: Beenden aller inaktiven Sessions
+    // Test: Beenden aller aktiven Sessions
     let count = service
         .force_terminate_sessions_by_filter(
-            SessionFilter::Inactive,
+            SessionFilter::Active,
             SessionInvalidationReason::SecurityPolicyChange,
         )
         .await
         .expect("Die Beendigung der Sessions sollte erfolgreich sein");
 
     assert_eq!(
-        count, 2,
-        "Es sollten genau 2 inaktive Sessions beendet werden"
+        count, 8,
+        "Es sollten genau 8 aktive Sessions beendet werden"
     );
 
     // Test: Beenden aller Sessions
@@ -248,3 +184,62 @@ async fn test_force_terminate_sessions_by_filter() {
 
     assert_eq!(count, 10, "Es sollten genau 10 Sessions beendet werden");
 }
+
+#[tokio::test]
+async fn test_force_terminate_sessions_by_region() {
+    // Setup: Instanzen in zwei Regionen und eine ohne Region
+    let repo = Arc::new(MockSessionRepository::new());
+    let config = Arc::new(AuthConfig::default());
+    let service_in = |region: Option<&str>| {
+        SessionService::new(repo.clone(), config.clone()).with_region(region.map(str::to_string))
+    };
+    let eu_west = service_in(Some("eu-west"));
+    let us_east = service_in(Some("us-east"));
+    // Sessions von vor der Einführung der Region
+    let unknown = service_in(None);
+
+    let user_id = Uuid::new_v4();
+    for service in [&eu_west, &eu_west, &us_east, &us_east, &us_east, &unknown] {
+        service
+            .create_session(user_id, None, None, None, None, None, false)
+            .await
+            .expect("Session sollte angelegt werden");
+    }
+    let valid_in = |region: Option<&str>| {
+        repo.sessions()
+            .iter()
+            .filter(|s| s.is_valid && s.region.as_deref() == region)
+            .count()
+    };
+
+    // Test: Beenden der Sessions in eu-west
+    let count = eu_west
+        .force_terminate_sessions_by_region(
+            "eu-west",
+            None,
+            false,
+            SessionInvalidationReason::SecurityBreach,
+        )
+        .await
+        .expect("Die Beendigung der Sessions sollte erfolgreich sein");
+
+    assert_eq!(count, 2, "Es sollten genau 2 Sessions beendet werden");
+    assert_eq!(valid_in(Some("eu-west")), 0);
+    assert_eq!(valid_in(Some("us-east")), 3, "us-east bleibt unberührt");
+    assert_eq!(valid_in(None), 1, "Sessions ohne Region bleiben unberührt");
+
+    // Test: Sessions ohne Region nur mit include_unknown
+    let count = eu_west
+        .force_terminate_sessions_by_region(
+            "eu-west",
+            None,
+            true,
+            SessionInvalidationReason::SecurityBreach,
+        )
+        .await
+        .expect("Die Beendigung der Sessions sollte erfolgreich sein");
+
+    assert_eq!(count, 1, "Es sollte die Session ohne Region beendet werden");
+    assert_eq!(valid_in(None), 0);
+    assert_eq!(valid_in(Some("us-east")), 3, "us-east bleibt unberührt");
+}
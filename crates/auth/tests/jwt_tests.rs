@@ -1,7 +1,73 @@
-use acci_auth::utils::jwt::{JwtError, JwtUtils};
+use acci_auth::DurationSecs;
+use acci_auth::utils::jwt::{
+    Claims, DEFAULT_JWT_ISSUER, JwtError, JwtUtils, MAX_JWT_LEEWAY_SECS, tenant_audience,
+};
+use acci_auth::{
+    ClaimsConfig, ClaimsEnricher, PLATFORM_ADMIN_CLAIM, Session, Tenant, TenantRoleEnricher,
+    TenantStatus, TenantUser, TokenTenant, User,
+};
+use serde_json::{Value, json};
+use std::sync::Arc;
 use time::{Duration, OffsetDateTime};
 use uuid::Uuid;
 
+/// Enricher adding the org code and entitlements of a deployment
+struct OrgEnricher;
+
+impl ClaimsEnricher for OrgEnricher {
+    fn enrich(
+        &self,
+        claims: &mut Claims,
+        user: &User,
+        _tenant: Option<&TokenTenant>,
+        _session: Option<&Session>,
+    ) -> Result<(), JwtError> {
+        claims.insert_custom("org_code", json!("ACME-42"))?;
+        claims.insert_custom("entitlements", json!(["reports", user.email.clone()]))
+    }
+}
+
+/// Enricher trying to extend the lifetime of the token
+struct ExpiryEnricher;
+
+impl ClaimsEnricher for ExpiryEnricher {
+    fn enrich(
+        &self,
+        claims: &mut Claims,
+        _user: &User,
+        _tenant: Option<&TokenTenant>,
+        _session: Option<&Session>,
+    ) -> Result<(), JwtError> {
+        claims.custom.insert("exp".to_string(), json!(i64::MAX));
+        Ok(())
+    }
+}
+
+fn token_tenant(user: &User) -> TokenTenant {
+    let now = OffsetDateTime::now_utc();
+    let tenant = Tenant {
+        id: Uuid::new_v4(),
+        name: "Acme".to_string(),
+        subdomain: "acme".to_string(),
+        status: TenantStatus::Active,
+        created_at: now,
+        updated_at: now,
+        metadata: None,
+    };
+    TokenTenant {
+        membership: Some(TenantUser {
+            tenant_id: tenant.id,
+            user_id: user.id,
+            tenant_role: "admin".to_string(),
+            is_active: true,
+            created_at: now,
+            updated_at: now,
+        }),
+        subscription: None,
+        tenant,
+    }
+}
+
 #[tokio::test]
 async fn test_jwt_creation_and_validation() {
     let secret = b"test-secret-key";
@@ -17,7 +83,7 @@ async fn test_jwt_creation_and_validation() {
 
     // Test token validation
     let claims = jwt_utils
-        .validate_token(&token)
+        .validate_token(&token, None)
         .expect("Failed to validate token");
     assert_eq!(claims.sub, user_id);
     assert_eq!(claims.email, email);
@@ -39,8 +105,12 @@ async fn test_expired_token() {
         sub: user_id,
         exp: exp.unix_timestamp(),
         iat: now.unix_timestamp(),
+        nbf: now.unix_timestamp(),
         email: email.to_string(),
         tenant_id: None,
+        iss: DEFAULT_JWT_ISSUER.to_string(),
+        aud: None,
+        custom: Default::default(),
     };
 
     let token = jsonwebtoken::encode(
@@ -51,16 +121,283 @@ async fn test_expired_token() {
     .expect("Failed to create expired token");
 
     // Test expired token validation
-    let result = jwt_utils.validate_token(&token);
+    let result = jwt_utils.validate_token(&token, None);
     assert!(matches!(result, Err(JwtError::TokenExpired)));
 }
 
+/// Token of `secret` with the given offsets from now, in seconds
+fn token_with_times(secret: &[u8], exp: i64, iat: i64, nbf: i64) -> String {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let claims = Claims {
+        sub: Uuid::new_v4(),
+        exp: now + exp,
+        iat: now + iat,
+        nbf: now + nbf,
+        email: "test@example.com".to_string(),
+        tenant_id: None,
+        iss: DEFAULT_JWT_ISSUER.to_string(),
+        aud: None,
+        custom: Default::default(),
+    };
+    jsonwebtoken::encode(
+        &jsonwebtoken::Header::default(),
+        &claims,
+        &jsonwebtoken::EncodingKey::from_secret(secret),
+    )
+    .expect("Failed to create token")
+}
+
+#[tokio::test]
+async fn test_expiry_leeway() {
+    let secret = b"test-secret-key";
+    let jwt_utils = JwtUtils::new(secret);
+
+    // Expired by less than the default leeway of 30s
+    let token = token_with_times(secret, -10, -3600, -3600);
+    assert!(jwt_utils.validate_token(&token, None).is_ok());
+
+    let token = token_with_times(secret, -120, -3600, -3600);
+    assert!(matches!(
+        jwt_utils.validate_token(&token, None),
+        Err(JwtError::TokenExpired)
+    ));
+
+    // A larger leeway accepts it
+    let jwt_utils = JwtUtils::new(secret).with_leeway(DurationSecs::from_secs(180));
+    assert!(jwt_utils.validate_token(&token, None).is_ok());
+}
+
+#[tokio::test]
+async fn test_tokens_from_the_future_are_rejected() {
+    let secret = b"test-secret-key";
+    let jwt_utils = JwtUtils::new(secret);
+
+    // Clocks a few seconds apart
+    let token = token_with_times(secret, 3600, 10, 10);
+    assert!(jwt_utils.validate_token(&token, None).is_ok());
+
+    let token = token_with_times(secret, 3600, 0, 120);
+    assert!(matches!(
+        jwt_utils.validate_token(&token, None),
+        Err(JwtError::TokenNotYetValid)
+    ));
+
+    let token = token_with_times(secret, 3600, 120, 0);
+    assert!(matches!(
+        jwt_utils.validate_token(&token, None),
+        Err(JwtError::TokenNotYetValid)
+    ));
+}
+
+#[tokio::test]
+async fn test_leeway_is_capped() {
+    let secret = b"test-secret-key";
+    let jwt_utils = JwtUtils::new(secret).with_leeway(DurationSecs::from_secs(86400));
+
+    // Expired beyond the maximum leeway
+    let token = token_with_times(secret, -(MAX_JWT_LEEWAY_SECS as i64) - 60, -7200, -7200);
+    assert!(matches!(
+        jwt_utils.validate_token(&token, None),
+        Err(JwtError::TokenExpired)
+    ));
+}
+
+#[tokio::test]
+async fn test_token_of_one_tenant_is_rejected_for_another() {
+    let jwt_utils = JwtUtils::new(b"test-secret-key");
+    let tenant_a = Uuid::new_v4();
+    let tenant_b = Uuid::new_v4();
+    let token = jwt_utils
+        .create_token(Uuid::new_v4(), "test@example.com", Some(tenant_a))
+        .expect("Failed to create token");
+
+    let claims = jwt_utils
+        .validate_token(&token, Some(tenant_a))
+        .expect("Failed to validate token");
+    assert_eq!(claims.tenant_id, Some(tenant_a));
+    assert_eq!(claims.aud, Some(tenant_audience(tenant_a)));
+
+    assert!(matches!(
+        jwt_utils.validate_token(&token, Some(tenant_b)),
+        Err(JwtError::TenantMismatch)
+    ));
+
+    // Tokens without a tenant are not accepted for one either
+    let token = jwt_utils
+        .create_token(Uuid::new_v4(), "test@example.com", None)
+        .expect("Failed to create token");
+    assert!(matches!(
+        jwt_utils.validate_token(&token, Some(tenant_a)),
+        Err(JwtError::TenantMismatch)
+    ));
+}
+
+#[tokio::test]
+async fn test_tenant_claim_must_match_audience() {
+    let secret = b"test-secret-key";
+    let tenant_a = Uuid::new_v4();
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let claims = Claims {
+        sub: Uuid::new_v4(),
+        exp: now + 3600,
+        iat: now,
+        nbf: now,
+        email: "test@example.com".to_string(),
+        tenant_id: Some(Uuid::new_v4()),
+        iss: DEFAULT_JWT_ISSUER.to_string(),
+        aud: Some(tenant_audience(tenant_a)),
+        custom: Default::default(),
+    };
+    let token = jsonwebtoken::encode(
+        &jsonwebtoken::Header::default(),
+        &claims,
+        &jsonwebtoken::EncodingKey::from_secret(secret),
+    )
+    .expect("Failed to create token");
+
+    let result = JwtUtils::new(secret).validate_token(&token, Some(tenant_a));
+    assert!(matches!(result, Err(JwtError::TenantMismatch)));
+}
+
+#[tokio::test]
+async fn test_issuer_is_verified() {
+    let secret = b"test-secret-key";
+    let jwt_utils = JwtUtils::new(secret);
+
+    let token = JwtUtils::new(secret)
+        .with_issuer("other-service")
+        .create_token(Uuid::new_v4(), "test@example.com", None)
+        .expect("Failed to create token");
+    assert!(matches!(
+        jwt_utils.validate_token(&token, None),
+        Err(JwtError::InvalidIssuer)
+    ));
+
+    // Tokens without an issuer are rejected as well
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let token = jsonwebtoken::encode(
+        &jsonwebtoken::Header::default(),
+        &json!({
+            "sub": Uuid::new_v4(),
+            "exp": now + 3600,
+            "iat": now,
+            "email": "test@example.com",
+            "tenant_id": null,
+        }),
+        &jsonwebtoken::EncodingKey::from_secret(secret),
+    )
+    .expect("Failed to create token");
+    assert!(matches!(
+        jwt_utils.validate_token(&token, None),
+        Err(JwtError::InvalidIssuer)
+    ));
+}
+
 #[tokio::test]
 async fn test_invalid_token() {
     let secret = b"test-secret-key";
     let jwt_utils = JwtUtils::new(secret);
 
     // Test invalid token validation
-    let result = jwt_utils.validate_token("invalid-token");
+    let result = jwt_utils.validate_token("invalid-token", None);
     assert!(matches!(result, Err(JwtError::TokenValidation(_))));
 }
+
+#[tokio::test]
+async fn test_custom_claims_round_trip() {
+    let jwt_utils = JwtUtils::new(b"test-secret-key")
+        .with_enricher(Arc::new(TenantRoleEnricher))
+        .with_enricher(Arc::new(OrgEnricher));
+    let user = User::new("test@example.com".to_string(), "hash".to_string());
+    let tenant = token_tenant(&user);
+
+    let token = jwt_utils
+        .issue_token(&user, Some(&tenant), None)
+        .expect("Failed to issue token");
+    let claims = jwt_utils
+        .validate_token(&token, Some(tenant.tenant.id))
+        .expect("Failed to validate token");
+
+    assert_eq!(claims.sub, user.id);
+    assert_eq!(claims.tenant_id, Some(tenant.tenant.id));
+    assert_eq!(claims.custom_claim("org_code"), Some(&json!("ACME-42")));
+    assert_eq!(
+        claims.custom_claim("entitlements"),
+        Some(&json!(["reports", "test@example.com"]))
+    );
+    assert_eq!(
+        claims.custom_claim("tenant_role"),
+        Some(&Value::String("admin".to_string()))
+    );
+}
+
+#[tokio::test]
+async fn test_reserved_claims_are_protected() {
+    let user = User::new("test@example.com".to_string(), "hash".to_string());
+    let mut claims = jsonwebtoken::decode::<Claims>(
+        &JwtUtils::new(b"test-secret-key")
+            .issue_token(&user, None, None)
+            .expect("Failed to issue token"),
+        &jsonwebtoken::DecodingKey::from_secret(b"test-secret-key"),
+        &jsonwebtoken::Validation::default(),
+    )
+    .expect("Failed to decode token")
+    .claims;
+    for name in ["exp", "sub", "iss", "aud"] {
+        assert!(matches!(
+            claims.insert_custom(name, json!("overwritten")),
+            Err(JwtError::ReservedClaim(claim)) if claim == name
+        ));
+    }
+
+    let jwt_utils = JwtUtils::new(b"test-secret-key").with_enricher(Arc::new(ExpiryEnricher));
+    let result = jwt_utils.issue_token(&user, None, None);
+    assert!(matches!(result, Err(JwtError::ReservedClaim(claim)) if claim == "exp"));
+}
+
+#[tokio::test]
+async fn test_enrichment_is_bounded_by_token_size() {
+    let user = User::new("test@example.com".to_string(), "hash".to_string());
+    let jwt_utils = JwtUtils::new(b"test-secret-key").with_enricher(Arc::new(OrgEnricher));
+    let size = jwt_utils
+        .issue_token(&user, None, None)
+        .expect("Failed to issue token")
+        .len();
+
+    let limited = JwtUtils::new(b"test-secret-key")
+        .with_enricher(Arc::new(OrgEnricher))
+        .with_max_token_bytes(size - 1);
+    let result = limited.issue_token(&user, None, None);
+    assert!(matches!(
+        result,
+        Err(JwtError::TokenTooLarge { size: actual, limit }) if actual == size && limit == size - 1
+    ));
+}
+
+#[tokio::test]
+async fn test_platform_admin_claim_only_for_configured_users() {
+    let admin = User::new("ops@example.com".to_string(), "hash".to_string());
+    let member = User::new("member@example.com".to_string(), "hash".to_string());
+    let config = ClaimsConfig {
+        platform_admins: vec![admin.id],
+        ..ClaimsConfig::default()
+    };
+    let jwt_utils = config
+        .enrichers()
+        .into_iter()
+        .fold(JwtUtils::new(b"test-secret-key"), JwtUtils::with_enricher);
+
+    let claims = |user: &User| {
+        let token = jwt_utils
+            .issue_token(user, None, None)
+            .expect("Failed to issue token");
+        jwt_utils
+            .validate_token(&token, None)
+            .expect("Failed to validate token")
+    };
+    assert_eq!(
+        claims(&admin).custom_claim(PLATFORM_ADMIN_CLAIM),
+        Some(&Value::Bool(true))
+    );
+    assert_eq!(claims(&member).custom_claim(PLATFORM_ADMIN_CLAIM), None);
+}
@@ -1,9 +1,15 @@
-use sqlx::{Pool, Postgres, postgres::PgPoolOptions};
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Postgres, pool::PoolConnection, postgres::PgPoolOptions};
+use std::future::Future;
 use std::path::Path;
 use std::time::Duration;
+use tracing::{debug, warn};
 
 use crate::error::Result;
 
+/// Name of the advisory lock held while migrations run
+pub const MIGRATIONS_LOCK: &str = "acci.migrations";
+
 /// Represents the database connection pool and related functionality
 #[derive(Clone)]
 pub struct Database {
@@ -49,7 +55,26 @@ impl Database {
         &self.pool
     }
 
+    /// Advisory lock named `name` on this database
+    pub fn advisory_lock(&self, name: &str) -> AdvisoryLock {
+        AdvisoryLock::named(self.pool.clone(), name)
+    }
+
+    /// Runs `job` unless another instance holds the lock named `name`
+    ///
+    /// Returns `None` without running `job` if the lock is held elsewhere.
+    pub async fn with_lock<F, Fut, T>(&self, name: &str, job: F) -> Result<Option<T>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        self.advisory_lock(name).with_lock(job).await
+    }
+
     /// Runs all database migrations
+    ///
+    /// Instances starting at the same time wait for the one running the
+    /// migrations, then find nothing left to apply.
     pub async fn run_migrations(&self) -> Result<()> {
         let migrations_path = Path::new(env!("CARGO_MANIFEST_DIR"))
             .parent()
@@ -58,11 +83,130 @@ impl Database {
             .unwrap_or_else(|| panic!("Failed to find root directory from CARGO_MANIFEST_DIR"))
             .join("migrations");
 
-        sqlx::migrate::Migrator::new(migrations_path)
+        let guard = self.advisory_lock(MIGRATIONS_LOCK).acquire().await?;
+        let result = sqlx::migrate::Migrator::new(migrations_path)
             .await?
             .run(self.pool())
-            .await
-            .map_err(Into::into)
+            .await;
+        guard.release().await?;
+        result.map_err(Into::into)
+    }
+}
+
+/// Postgres session-level advisory lock
+///
+/// Lets one of several instances run a job, e.g. a cleanup worker, at a time.
+/// The lock is held by a connection taken from the pool for as long as the
+/// [`AdvisoryLockGuard`] lives. A guard that is dropped without
+/// [`AdvisoryLockGuard::release`], also while unwinding from a panic, closes
+/// its connection, which ends the session and thereby releases the lock.
+#[derive(Debug, Clone)]
+pub struct AdvisoryLock {
+    pool: Pool<Postgres>,
+    key: i64,
+}
+
+impl AdvisoryLock {
+    pub fn new(pool: Pool<Postgres>, key: i64) -> Self {
+        Self { pool, key }
+    }
+
+    /// Lock whose key is derived from `name`, so jobs can be identified by name
+    pub fn named(pool: Pool<Postgres>, name: &str) -> Self {
+        Self::new(pool, Self::key_for(name))
+    }
+
+    /// Stable 64-bit key of a lock name
+    pub fn key_for(name: &str) -> i64 {
+        let digest = Sha256::digest(name.as_bytes());
+        let mut key = [0u8; 8];
+        key.copy_from_slice(&digest[..8]);
+        i64::from_be_bytes(key)
+    }
+
+    pub fn key(&self) -> i64 {
+        self.key
+    }
+
+    /// Takes the lock if no other session holds it
+    pub async fn try_acquire(&self) -> Result<Option<AdvisoryLockGuard>> {
+        let mut connection = self.pool.acquire().await?;
+        let acquired: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+            .bind(self.key)
+            .fetch_one(&mut *connection)
+            .await?;
+        if !acquired {
+            debug!(key = self.key, "Advisory lock is held by another session");
+            return Ok(None);
+        }
+
+        Ok(Some(AdvisoryLockGuard {
+            connection: Some(connection),
+            key: self.key,
+        }))
+    }
+
+    /// Waits until the lock can be taken
+    pub async fn acquire(&self) -> Result<AdvisoryLockGuard> {
+        let mut connection = self.pool.acquire().await?;
+        sqlx::query("SELECT pg_advisory_lock($1)")
+            .bind(self.key)
+            .execute(&mut *connection)
+            .await?;
+
+        Ok(AdvisoryLockGuard {
+            connection: Some(connection),
+            key: self.key,
+        })
+    }
+
+    /// Runs `job` while holding the lock
+    ///
+    /// Returns `None` without running `job` if another session holds the lock.
+    pub async fn with_lock<F, Fut, T>(&self, job: F) -> Result<Option<T>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let Some(guard) = self.try_acquire().await? else {
+            return Ok(None);
+        };
+        let output = job().await;
+        guard.release().await?;
+        Ok(Some(output))
+    }
+}
+
+/// A held [`AdvisoryLock`]
+#[derive(Debug)]
+pub struct AdvisoryLockGuard {
+    connection: Option<PoolConnection<Postgres>>,
+    key: i64,
+}
+
+impl AdvisoryLockGuard {
+    /// Releases the lock and returns the connection to the pool
+    pub async fn release(mut self) -> Result<()> {
+        let Some(mut connection) = self.connection.take() else {
+            return Ok(());
+        };
+        let released: bool = sqlx::query_scalar("SELECT pg_advisory_unlock($1)")
+            .bind(self.key)
+            .fetch_one(&mut *connection)
+            .await?;
+        if !released {
+            warn!(key = self.key, "Advisory lock was not held on release");
+        }
+        Ok(())
+    }
+}
+
+impl Drop for AdvisoryLockGuard {
+    fn drop(&mut self) {
+        if let Some(mut connection) = self.connection.take() {
+            // Ending the session releases its advisory locks
+            connection.close_on_drop();
+        }
     }
 }
 
@@ -114,6 +258,18 @@ mod tests {
         assert!(db_pool_fn as usize > 0); // Ensure function pointer is valid
     }
 
+    #[test]
+    fn test_advisory_lock_keys_are_stable() {
+        assert_eq!(
+            AdvisoryLock::key_for(MIGRATIONS_LOCK),
+            AdvisoryLock::key_for("acci.migrations")
+        );
+        assert_ne!(
+            AdvisoryLock::key_for(MIGRATIONS_LOCK),
+            AdvisoryLock::key_for("acci.session_cleanup")
+        );
+    }
+
     #[test]
     fn test_migrations_path_construction() {
         // Test that the migrations path is constructed correctly
@@ -0,0 +1,129 @@
+//! Conversions between time representations
+//!
+//! Domain models hold timestamps as [`OffsetDateTime`] in UTC. Values that
+//! cross into or out of other representations, `SystemTime` of the standard
+//! library, chrono's `DateTime<Utc>` used by some dependencies or Unix
+//! seconds stored in JSON, are converted here. The conversions keep
+//! nanosecond precision and fail on values outside the supported range
+//! instead of falling back to the Unix epoch.
+//!
+//! PostgreSQL stores timestamps with microsecond precision, so values that
+//! are compared after a round-trip through the database are truncated with
+//! [`truncate_to_micros`] before they are written.
+
+use chrono::{DateTime, Utc};
+use std::time::SystemTime;
+use thiserror::Error;
+use time::OffsetDateTime;
+
+/// A timestamp that cannot be represented in the target type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("Timestamp is out of the supported range")]
+pub struct OutOfRange;
+
+/// Convert a [`SystemTime`], keeping nanoseconds
+pub fn from_system_time(time: SystemTime) -> Result<OffsetDateTime, OutOfRange> {
+    let nanos = match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(after) => i128::try_from(after.as_nanos()).map_err(|_| OutOfRange)?,
+        Err(before) => -i128::try_from(before.duration().as_nanos()).map_err(|_| OutOfRange)?,
+    };
+    OffsetDateTime::from_unix_timestamp_nanos(nanos).map_err(|_| OutOfRange)
+}
+
+/// Convert to a [`SystemTime`], keeping nanoseconds
+pub fn to_system_time(time: OffsetDateTime) -> Result<SystemTime, OutOfRange> {
+    let since_epoch = time - OffsetDateTime::UNIX_EPOCH;
+    let magnitude = std::time::Duration::try_from(since_epoch.abs()).map_err(|_| OutOfRange)?;
+    if since_epoch.is_negative() {
+        SystemTime::UNIX_EPOCH.checked_sub(magnitude)
+    } else {
+        SystemTime::UNIX_EPOCH.checked_add(magnitude)
+    }
+    .ok_or(OutOfRange)
+}
+
+/// Convert a chrono timestamp, keeping nanoseconds
+///
+/// A leap second, which chrono represents as nanoseconds beyond one second,
+/// is carried into the following second.
+pub fn from_chrono(time: DateTime<Utc>) -> Result<OffsetDateTime, OutOfRange> {
+    let nanos =
+        i128::from(time.timestamp()) * 1_000_000_000 + i128::from(time.timestamp_subsec_nanos());
+    OffsetDateTime::from_unix_timestamp_nanos(nanos).map_err(|_| OutOfRange)
+}
+
+/// Convert to a chrono timestamp, keeping nanoseconds
+pub fn to_chrono(time: OffsetDateTime) -> Result<DateTime<Utc>, OutOfRange> {
+    DateTime::from_timestamp(time.unix_timestamp(), time.nanosecond()).ok_or(OutOfRange)
+}
+
+/// The time `secs` seconds after the Unix epoch
+pub fn from_unix_seconds(secs: i64) -> Result<OffsetDateTime, OutOfRange> {
+    OffsetDateTime::from_unix_timestamp(secs).map_err(|_| OutOfRange)
+}
+
+/// Drop the nanoseconds that PostgreSQL does not store
+pub fn truncate_to_micros(time: OffsetDateTime) -> OffsetDateTime {
+    time - time::Duration::nanoseconds(i64::from(time.nanosecond() % 1_000))
+}
+
+/// The current time with the precision PostgreSQL stores
+pub fn now_micros() -> OffsetDateTime {
+    truncate_to_micros(OffsetDateTime::now_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Nanoseconds since the epoch between the years 1 and 9999
+    fn timestamp_nanos() -> impl Strategy<Value = i128> {
+        -62_135_596_800_000_000_000i128..253_402_300_799_999_999_999i128
+    }
+
+    proptest! {
+        #[test]
+        fn test_system_time_round_trip(nanos in timestamp_nanos()) {
+            let time = OffsetDateTime::from_unix_timestamp_nanos(nanos).expect("in range");
+            let system_time = to_system_time(time).expect("fits into SystemTime");
+            prop_assert_eq!(from_system_time(system_time), Ok(time));
+        }
+
+        #[test]
+        fn test_chrono_round_trip(nanos in timestamp_nanos()) {
+            let time = OffsetDateTime::from_unix_timestamp_nanos(nanos).expect("in range");
+            let chrono = to_chrono(time).expect("fits into chrono");
+            prop_assert_eq!(from_chrono(chrono), Ok(time));
+        }
+
+        #[test]
+        fn test_truncate_to_micros(nanos in timestamp_nanos()) {
+            let time = OffsetDateTime::from_unix_timestamp_nanos(nanos).expect("in range");
+            let truncated = truncate_to_micros(time);
+            prop_assert!(truncated <= time);
+            prop_assert!(time - truncated < time::Duration::microseconds(1));
+            prop_assert_eq!(truncated.nanosecond() % 1_000, 0);
+            prop_assert_eq!(truncate_to_micros(truncated), truncated);
+        }
+    }
+
+    #[test]
+    fn test_sub_second_precision_is_kept_before_the_epoch() {
+        let system_time = SystemTime::UNIX_EPOCH - std::time::Duration::new(1, 500);
+        let time = from_system_time(system_time).expect("in range");
+        assert_eq!(time.unix_timestamp_nanos(), -1_000_000_500);
+        assert_eq!(to_system_time(time), Ok(system_time));
+    }
+
+    #[test]
+    fn test_out_of_range_values_are_rejected() {
+        let far_future = DateTime::from_timestamp(300_000_000_000, 0).expect("chrono supports it");
+        assert_eq!(from_chrono(far_future), Err(OutOfRange));
+        assert_eq!(from_unix_seconds(i64::MAX), Err(OutOfRange));
+        assert_eq!(
+            from_unix_seconds(1_750_000_000).map(OffsetDateTime::unix_timestamp),
+            Ok(1_750_000_000)
+        );
+    }
+}
@@ -17,10 +17,167 @@ pub enum Error {
     #[error("Validation error: {0}")]
     Validation(String),
 
+    /// Error of a domain crate, categorized so callers need not parse the message
+    #[error("{message}")]
+    Domain {
+        kind: ErrorKind,
+        message: String,
+        /// Structured detail for the client, e.g. the limit that was hit
+        details: Option<serde_json::Value>,
+    },
+
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
 
+impl Error {
+    /// Domain error of `kind`
+    pub fn domain(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self::Domain {
+            kind,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    /// Attaches structured detail to a domain error; other errors are unchanged
+    ///
+    /// The detail is shown to clients, so it must not contain internal data.
+    pub fn with_details(self, details: serde_json::Value) -> Self {
+        match self {
+            Self::Domain { kind, message, .. } => Self::Domain {
+                kind,
+                message,
+                details: Some(details),
+            },
+            other => other,
+        }
+    }
+
+    /// Structured detail of a domain error
+    pub fn details(&self) -> Option<&serde_json::Value> {
+        match self {
+            Self::Domain { details, .. } => details.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Category of the error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Domain { kind, .. } => *kind,
+            Self::Validation(_) => ErrorKind::InvalidInput,
+            Self::Config(_) | Self::Environment(_) => ErrorKind::Configuration,
+            Self::Database(_) | Self::Migration(_) | Self::Other(_) => ErrorKind::Internal,
+        }
+    }
+}
+
+/// Category of an [`Error`]
+///
+/// Kept fine-grained enough for the API to answer with a distinct status and
+/// code per kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// Malformed or semantically invalid input
+    InvalidInput,
+    /// Password does not meet the password policy
+    WeakPassword,
+    /// Password is one of the user's recent passwords
+    PasswordReused,
+    /// The password is past the tenant's maximum age and has to be reset
+    PasswordExpired,
+    /// Wrong email or password
+    InvalidCredentials,
+    /// No valid session or token was presented
+    Unauthenticated,
+    /// The session has expired
+    SessionExpired,
+    /// Authenticated, but not allowed to perform the operation
+    Forbidden,
+    /// The account is deactivated
+    AccountLocked,
+    /// The account's email address is not verified
+    AccountUnverified,
+    /// A mandatory MFA enrollment has to be completed first
+    MfaEnrollmentRequired,
+    /// The grace period for a mandatory MFA enrollment has passed
+    MfaEnrollmentExpired,
+    /// The tenant only allows logins from other networks or countries
+    LoginLocationBlocked,
+    /// The user is not an active member of the tenant
+    TenantAccessDenied,
+    /// The tenant is deactivated
+    TenantInactive,
+    /// The tenant is suspended, e.g. for non-payment
+    TenantSuspended,
+    /// The tenant's subscription has expired
+    SubscriptionExpired,
+    /// A resource other than a user or tenant does not exist
+    NotFound,
+    UserNotFound,
+    TenantNotFound,
+    UserAlreadyExists,
+    TenantAlreadyExists,
+    /// A plan or tenant limit would be exceeded
+    LimitExceeded,
+    /// Every seat of the tenant's subscription is taken by an active user
+    SeatLimitReached,
+    /// Wrong verification or MFA code
+    InvalidCode,
+    /// The verification code has expired
+    CodeExpired,
+    /// Too many wrong codes were entered
+    TooManyAttempts,
+    RateLimited,
+    /// A dependency, e.g. a message provider, is not available
+    Unavailable,
+    /// An operation, e.g. a database query, did not finish within its time
+    /// budget; retrying may succeed
+    Timeout,
+    /// The service is misconfigured
+    Configuration,
+    Internal,
+}
+
+impl ErrorKind {
+    /// Every kind, e.g. for exhaustive mapping tests
+    pub const ALL: [ErrorKind; 32] = [
+        ErrorKind::InvalidInput,
+        ErrorKind::WeakPassword,
+        ErrorKind::PasswordReused,
+        ErrorKind::PasswordExpired,
+        ErrorKind::InvalidCredentials,
+        ErrorKind::Unauthenticated,
+        ErrorKind::SessionExpired,
+        ErrorKind::Forbidden,
+        ErrorKind::AccountLocked,
+        ErrorKind::AccountUnverified,
+        ErrorKind::MfaEnrollmentRequired,
+        ErrorKind::MfaEnrollmentExpired,
+        ErrorKind::LoginLocationBlocked,
+        ErrorKind::TenantAccessDenied,
+        ErrorKind::TenantInactive,
+        ErrorKind::TenantSuspended,
+        ErrorKind::SubscriptionExpired,
+        ErrorKind::NotFound,
+        ErrorKind::UserNotFound,
+        ErrorKind::TenantNotFound,
+        ErrorKind::UserAlreadyExists,
+        ErrorKind::TenantAlreadyExists,
+        ErrorKind::LimitExceeded,
+        ErrorKind::SeatLimitReached,
+        ErrorKind::InvalidCode,
+        ErrorKind::CodeExpired,
+        ErrorKind::TooManyAttempts,
+        ErrorKind::RateLimited,
+        ErrorKind::Unavailable,
+        ErrorKind::Timeout,
+        ErrorKind::Configuration,
+        ErrorKind::Internal,
+    ];
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[cfg(test)]
@@ -71,6 +228,26 @@ mod tests {
         assert!(matches!(error, Error::Other(_)));
     }
 
+    #[test]
+    fn test_error_kind() {
+        let error = Error::domain(ErrorKind::SessionExpired, "Session expired");
+        assert_eq!(error.kind(), ErrorKind::SessionExpired);
+        assert_eq!(error.to_string(), "Session expired");
+
+        assert_eq!(
+            Error::Validation("Field required".to_string()).kind(),
+            ErrorKind::InvalidInput
+        );
+        assert_eq!(
+            Error::Config("Invalid config".to_string()).kind(),
+            ErrorKind::Configuration
+        );
+        assert_eq!(
+            Error::Database(sqlx::Error::RowNotFound).kind(),
+            ErrorKind::Internal
+        );
+    }
+
     #[test]
     fn test_result_type() {
         // Test Ok case with Result<T>
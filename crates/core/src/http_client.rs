@@ -0,0 +1,482 @@
+//! Outbound HTTP clients
+//!
+//! Integrations with external providers (email and SMS gateways, identity
+//! providers) get their HTTP client from a [`HttpClientFactory`] instead of
+//! building their own, so that all of them share the timeouts, the proxy and
+//! the certificate pins of one [`OutboundHttpConfig`].
+//!
+//! Pins are SHA-256 hashes of the DER-encoded SubjectPublicKeyInfo of a host's
+//! certificate, base64-encoded like `openssl x509 -pubkey | openssl pkey
+//! -pubin -outform der | openssl dgst -sha256 -binary | base64` prints them.
+//! A pinned host must present a certificate that is both valid and carries
+//! one of its pinned keys; other hosts only need a valid certificate.
+
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use reqwest::{IntoUrl, Method, NoProxy, Proxy, RequestBuilder, Response};
+use rustls::client::WebPkiServerVerifier;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{
+    CertificateError, ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::error::{Error, Result};
+
+/// Histogram of outbound request durations, labelled with the destination host
+pub const METRIC_OUTBOUND_REQUEST_DURATION: &str = "outbound_http.request.duration_ms";
+
+/// Counter of TLS handshakes rejected because the certificate matched no pin
+pub const METRIC_OUTBOUND_PIN_FAILURES: &str = "outbound_http.pin_failures.total";
+
+/// Settings shared by all outbound HTTP clients
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct OutboundHttpConfig {
+    /// Time allowed to establish the connection, including the TLS handshake
+    pub connect_timeout_ms: u64,
+    /// Longest pause between two reads of the response
+    pub read_timeout_ms: u64,
+    /// Deadline for the whole request, from connecting to reading the body
+    pub deadline_ms: u64,
+    /// Proxy all requests are sent through, e.g. `http://proxy.internal:3128`
+    pub proxy_url: Option<String>,
+    /// Hosts and domains reached without the proxy, e.g. `.internal` or `10.0.0.0/8`
+    pub no_proxy: Vec<String>,
+    /// Base64 SHA-256 SubjectPublicKeyInfo pins per host name
+    pub spki_pins: HashMap<String, Vec<String>>,
+    /// `User-Agent` header of all requests
+    pub user_agent: String,
+}
+
+impl Default for OutboundHttpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_ms: 5_000,
+            read_timeout_ms: 10_000,
+            deadline_ms: 30_000,
+            proxy_url: None,
+            no_proxy: Vec::new(),
+            spki_pins: HashMap::new(),
+            user_agent: concat!("acci_framework/", env!("CARGO_PKG_VERSION")).to_string(),
+        }
+    }
+}
+
+/// Builds the [`HttpClient`]s for an [`OutboundHttpConfig`]
+pub struct HttpClientFactory {
+    config: OutboundHttpConfig,
+    root_certificates: Vec<CertificateDer<'static>>,
+}
+
+impl HttpClientFactory {
+    pub fn new(config: OutboundHttpConfig) -> Self {
+        Self {
+            config,
+            root_certificates: Vec::new(),
+        }
+    }
+
+    /// Trusts `certificate` in addition to the Mozilla root certificates
+    ///
+    /// For providers reached through an internal CA, and for tests.
+    pub fn with_root_certificate(mut self, certificate: CertificateDer<'static>) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+
+    /// Builds a client with the configured timeouts, proxy and pins
+    ///
+    /// Fails for an invalid proxy URL, a pin that is not a base64 SHA-256 hash,
+    /// or an invalid additional root certificate.
+    pub fn build(&self) -> Result<HttpClient> {
+        let mut builder = reqwest::Client::builder()
+            .use_preconfigured_tls(self.tls_config()?)
+            .connect_timeout(Duration::from_millis(self.config.connect_timeout_ms))
+            .read_timeout(Duration::from_millis(self.config.read_timeout_ms))
+            .timeout(Duration::from_millis(self.config.deadline_ms))
+            .user_agent(self.config.user_agent.clone());
+
+        if let Some(proxy_url) = &self.config.proxy_url {
+            let proxy = Proxy::all(proxy_url)
+                .map_err(|e| Error::Config(format!("Invalid outbound proxy URL: {}", e)))?
+                .no_proxy(NoProxy::from_string(&self.config.no_proxy.join(",")));
+            builder = builder.proxy(proxy);
+        }
+
+        let client = builder
+            .build()
+            .map_err(|e| Error::Config(format!("Failed to build HTTP client: {}", e)))?;
+        Ok(HttpClient { client })
+    }
+
+    fn tls_config(&self) -> Result<ClientConfig> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+        let mut roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        for certificate in &self.root_certificates {
+            roots
+                .add(certificate.clone())
+                .map_err(|e| Error::Config(format!("Invalid root certificate: {}", e)))?;
+        }
+        let verifier =
+            WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+                .build()
+                .map_err(|e| {
+                    Error::Config(format!("Failed to build certificate verifier: {}", e))
+                })?;
+        let verifier = PinningVerifier {
+            inner: verifier,
+            pins: decode_pins(&self.config.spki_pins)?,
+        };
+
+        let mut config = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(|e| Error::Config(format!("Failed to configure TLS: {}", e)))?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(verifier))
+            .with_no_client_auth();
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(config)
+    }
+}
+
+/// Decodes the pins and normalizes the host names to lower case
+fn decode_pins(pins: &HashMap<String, Vec<String>>) -> Result<HashMap<String, Vec<[u8; 32]>>> {
+    pins.iter()
+        .map(|(host, host_pins)| {
+            let decoded = host_pins
+                .iter()
+                .map(|pin| {
+                    BASE64
+                        .decode(pin)
+                        .ok()
+                        .and_then(|hash| <[u8; 32]>::try_from(hash).ok())
+                        .ok_or_else(|| {
+                            Error::Config(format!(
+                                "Pin for {} is not a base64 SHA-256 hash: {}",
+                                host, pin
+                            ))
+                        })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok((host.to_lowercase(), decoded))
+        })
+        .collect()
+}
+
+/// Verifies certificates like the WebPKI verifier and checks the pins of pinned hosts
+#[derive(Debug)]
+struct PinningVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    pins: HashMap<String, Vec<[u8; 32]>>,
+}
+
+impl PinningVerifier {
+    fn pins_of(&self, server_name: &ServerName<'_>) -> Option<(String, &[[u8; 32]])> {
+        let host = match server_name {
+            ServerName::DnsName(name) => name.as_ref().to_lowercase(),
+            ServerName::IpAddress(address) => IpAddr::from(*address).to_string(),
+            _ => return None,
+        };
+        let pins = self.pins.get(&host)?;
+        Some((host, pins))
+    }
+}
+
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+        let Some((host, pins)) = self.pins_of(server_name) else {
+            return Ok(verified);
+        };
+
+        let (_, certificate) = x509_parser::parse_x509_certificate(end_entity.as_ref())
+            .map_err(|_| rustls::Error::InvalidCertificate(CertificateError::BadEncoding))?;
+        let spki_hash: [u8; 32] = Sha256::digest(certificate.public_key().raw).into();
+        if pins.contains(&spki_hash) {
+            return Ok(verified);
+        }
+
+        warn!(
+            host = %host,
+            spki_hash = %BASE64.encode(spki_hash),
+            "Certificate does not match any pinned key"
+        );
+        metrics::counter!(METRIC_OUTBOUND_PIN_FAILURES, "host" => host).increment(1);
+        Err(rustls::Error::InvalidCertificate(
+            CertificateError::ApplicationVerificationFailure,
+        ))
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// HTTP client built by a [`HttpClientFactory`]
+///
+/// Requests are built as with [`reqwest::Client`] and sent with
+/// [`HttpClient::send`], which records their duration per destination host.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: reqwest::Client,
+}
+
+impl HttpClient {
+    pub fn request<U: IntoUrl>(&self, method: Method, url: U) -> RequestBuilder {
+        self.client.request(method, url)
+    }
+
+    pub fn get<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub fn post<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    /// Sends a request built with this client
+    ///
+    /// The duration until the response headers arrive is recorded in
+    /// [`METRIC_OUTBOUND_REQUEST_DURATION`], labelled with the host and the
+    /// status code, or `error` if no response was received.
+    pub async fn send(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        let request = request.build()?;
+        let host = request.url().host_str().unwrap_or("unknown").to_string();
+
+        let start = Instant::now();
+        let result = self.client.execute(request).await;
+        let status = match &result {
+            Ok(response) => response.status().as_u16().to_string(),
+            Err(_) => "error".to_string(),
+        };
+        metrics::histogram!(METRIC_OUTBOUND_REQUEST_DURATION, "host" => host, "status" => status)
+            .record(start.elapsed().as_secs_f64() * 1000.0);
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::ServerConfig;
+    use rustls::pki_types::PrivatePkcs8KeyDer;
+    use std::net::SocketAddr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio_rustls::TlsAcceptor;
+
+    /// Local HTTPS server answering every request with `ok` after `delay`
+    struct TestServer {
+        address: SocketAddr,
+        certificate: CertificateDer<'static>,
+        spki_pin: String,
+    }
+
+    impl TestServer {
+        async fn start(delay: Duration) -> Self {
+            let generated = rcgen::generate_simple_self_signed(vec!["127.0.0.1".to_string()])
+                .expect("Failed to generate certificate");
+            let certificate = generated.cert.der().clone();
+            let spki_pin = BASE64.encode(Sha256::digest(generated.key_pair.public_key_der()));
+            let key = PrivatePkcs8KeyDer::from(generated.key_pair.serialize_der());
+
+            let config = ServerConfig::builder_with_provider(Arc::new(
+                rustls::crypto::ring::default_provider(),
+            ))
+            .with_safe_default_protocol_versions()
+            .expect("valid protocol versions")
+            .with_no_client_auth()
+            .with_single_cert(vec![certificate.clone()], key.into())
+            .expect("valid certificate");
+            let acceptor = TlsAcceptor::from(Arc::new(config));
+
+            let listener = TcpListener::bind("127.0.0.1:0")
+                .await
+                .expect("Failed to bind test server");
+            let address = listener.local_addr().expect("bound address");
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let acceptor = acceptor.clone();
+                    tokio::spawn(async move {
+                        let Ok(mut stream) = acceptor.accept(stream).await else {
+                            return;
+                        };
+                        let mut request = [0u8; 4096];
+                        let _ = stream.read(&mut request).await;
+                        tokio::time::sleep(delay).await;
+                        let _ = stream
+                            .write_all(
+                                b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok",
+                            )
+                            .await;
+                        let _ = stream.shutdown().await;
+                    });
+                }
+            });
+
+            Self {
+                address,
+                certificate,
+                spki_pin,
+            }
+        }
+
+        fn url(&self) -> String {
+            format!("https://{}/", self.address)
+        }
+
+        fn client(&self, config: OutboundHttpConfig) -> HttpClient {
+            HttpClientFactory::new(config)
+                .with_root_certificate(self.certificate.clone())
+                .build()
+                .expect("Failed to build client")
+        }
+    }
+
+    fn pinned(pins: Vec<String>) -> OutboundHttpConfig {
+        OutboundHttpConfig {
+            spki_pins: HashMap::from([("127.0.0.1".to_string(), pins)]),
+            ..OutboundHttpConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_matching_pin_is_accepted() {
+        let server = TestServer::start(Duration::ZERO).await;
+        let client = server.client(pinned(vec![server.spki_pin.clone()]));
+
+        let response = client
+            .send(client.get(server.url()))
+            .await
+            .expect("Pinned key is accepted");
+        assert!(response.status().is_success());
+        assert_eq!(response.text().await.expect("body"), "ok");
+    }
+
+    #[tokio::test]
+    async fn test_pin_mismatch_fails_the_handshake() {
+        let server = TestServer::start(Duration::ZERO).await;
+        let other_key = BASE64.encode(Sha256::digest(b"another key"));
+        let client = server.client(pinned(vec![other_key]));
+
+        let error = client
+            .send(client.get(server.url()))
+            .await
+            .expect_err("Certificate without a pinned key is rejected");
+        assert!(error.is_connect());
+
+        // Unpinned hosts only need a valid certificate
+        let client = server.client(OutboundHttpConfig::default());
+        client
+            .send(client.get(server.url()))
+            .await
+            .expect("Valid certificate is accepted");
+    }
+
+    #[test]
+    fn test_invalid_pin_is_rejected() {
+        let result = HttpClientFactory::new(pinned(vec!["not a hash".to_string()])).build();
+        assert!(matches!(result, Err(Error::Config(_))));
+
+        let truncated = BASE64.encode([0u8; 16]);
+        let result = HttpClientFactory::new(pinned(vec![truncated])).build();
+        assert!(matches!(result, Err(Error::Config(_))));
+    }
+
+    #[tokio::test]
+    async fn test_deadline_aborts_slow_responses() {
+        let server = TestServer::start(Duration::from_secs(5)).await;
+        let client = server.client(OutboundHttpConfig {
+            deadline_ms: 200,
+            ..OutboundHttpConfig::default()
+        });
+
+        let start = Instant::now();
+        let error = client
+            .send(client.get(server.url()))
+            .await
+            .expect_err("Slow response exceeds the deadline");
+        assert!(error.is_timeout());
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_read_timeout_aborts_stalled_responses() {
+        let server = TestServer::start(Duration::from_secs(5)).await;
+        let client = server.client(OutboundHttpConfig {
+            read_timeout_ms: 200,
+            ..OutboundHttpConfig::default()
+        });
+
+        let start = Instant::now();
+        let error = client
+            .send(client.get(server.url()))
+            .await
+            .expect_err("Stalled response exceeds the read timeout");
+        assert!(error.is_timeout());
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_default_timeouts() {
+        let config = OutboundHttpConfig::default();
+        assert_eq!(config.connect_timeout_ms, 5_000);
+        assert_eq!(config.read_timeout_ms, 10_000);
+        assert_eq!(config.deadline_ms, 30_000);
+        assert!(config.proxy_url.is_none());
+        assert!(config.user_agent.starts_with("acci_framework/"));
+    }
+
+    #[test]
+    fn test_invalid_proxy_is_rejected() {
+        let result = HttpClientFactory::new(OutboundHttpConfig {
+            proxy_url: Some("not a url".to_string()),
+            no_proxy: vec!["localhost".to_string()],
+            ..OutboundHttpConfig::default()
+        })
+        .build();
+        assert!(matches!(result, Err(Error::Config(_))));
+    }
+}
@@ -0,0 +1,257 @@
+//! Localized error messages
+//!
+//! Error responses carry a stable, machine-readable `code` (for example
+//! `TENANT_ALREADY_EXISTS`) next to a human-readable `message`. This module
+//! keeps the catalog of translated messages keyed by that code, so the API and
+//! the server-rendered UI localize the same errors the same way. Only the
+//! message is localized; codes never change.
+
+use std::fmt;
+
+/// Languages the message catalog knows about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Locale {
+    /// English, the fallback for every missing translation
+    #[default]
+    En,
+    /// German
+    De,
+}
+
+impl Locale {
+    /// All supported locales, the default first
+    pub const ALL: [Locale; 2] = [Locale::En, Locale::De];
+
+    /// Returns the BCP 47 language tag of the locale
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::De => "de",
+        }
+    }
+
+    /// Parses a language tag such as `de`, `de-AT` or `EN_us`
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag.trim().split(['-', '_']).next()?;
+        Self::ALL
+            .into_iter()
+            .find(|locale| primary.eq_ignore_ascii_case(locale.as_str()))
+    }
+
+    /// Picks the preferred supported locale from an `Accept-Language` header
+    ///
+    /// Entries are ranked by their quality value; unsupported languages,
+    /// wildcards and malformed entries are skipped. Falls back to English.
+    pub fn from_accept_language(header: &str) -> Self {
+        let mut best: Option<(Locale, f32)> = None;
+        for entry in header.split(',') {
+            let mut parts = entry.split(';');
+            let Some(locale) = parts.next().and_then(Self::from_tag) else {
+                continue;
+            };
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            // Entries with the same quality keep the order of the header
+            if quality > 0.0 && best.is_none_or(|(_, current)| quality > current) {
+                best = Some((locale, quality));
+            }
+        }
+        best.map(|(locale, _)| locale).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Message catalog: error code, English message, German message
+///
+/// A `None` translation falls back to the English message.
+const CATALOG: &[(&str, &str, Option<&str>)] = &[
+    (
+        "ACCOUNT_LOCKED",
+        "Account is temporarily locked",
+        Some("Das Konto ist vorübergehend gesperrt"),
+    ),
+    (
+        "ACCOUNT_PENDING_DELETION",
+        "Account deletion is already scheduled",
+        Some("Die Löschung des Kontos ist bereits geplant"),
+    ),
+    (
+        "AUTHENTICATION_REQUIRED",
+        "Authentication required",
+        Some("Anmeldung erforderlich"),
+    ),
+    ("BAD_REQUEST", "Bad request", Some("Ungültige Anfrage")),
+    ("FORBIDDEN", "Permission denied", Some("Zugriff verweigert")),
+    (
+        "IMPERSONATION_NOT_ALLOWED",
+        "This action is not allowed while impersonating a user",
+        Some("Diese Aktion ist während einer Benutzer-Impersonation nicht erlaubt"),
+    ),
+    (
+        "INTERNAL_SERVER_ERROR",
+        "Internal server error",
+        Some("Interner Serverfehler"),
+    ),
+    (
+        "INVALID_CREDENTIALS",
+        "Invalid email or password",
+        Some("Ungültige E-Mail-Adresse oder ungültiges Passwort"),
+    ),
+    (
+        "INVALID_SESSION",
+        "Invalid or expired session",
+        Some("Ungültige oder abgelaufene Sitzung"),
+    ),
+    (
+        "INVALID_TENANT_ID",
+        "Invalid tenant ID",
+        Some("Ungültige Mandanten-ID"),
+    ),
+    (
+        "MFA_REQUIRED",
+        "Multi-factor authentication required",
+        Some("Mehr-Faktor-Authentifizierung erforderlich"),
+    ),
+    (
+        "NO_PENDING_DELETION",
+        "No account deletion is pending",
+        Some("Es ist keine Kontolöschung geplant"),
+    ),
+    (
+        "NOT_FOUND",
+        "Resource not found",
+        Some("Ressource nicht gefunden"),
+    ),
+    (
+        "RATE_LIMIT_EXCEEDED",
+        "Too many requests",
+        Some("Zu viele Anfragen"),
+    ),
+    (
+        "REAUTH_REQUIRED",
+        "Please confirm your identity to continue",
+        Some("Bitte bestätigen Sie Ihre Identität, um fortzufahren"),
+    ),
+    (
+        "SEAT_LIMIT_REACHED",
+        "All seats of the subscription are taken",
+        Some("Alle Plätze des Abonnements sind belegt"),
+    ),
+    (
+        "TENANT_ACCESS_DENIED",
+        "Access to this tenant is denied",
+        Some("Der Zugriff auf diesen Mandanten ist nicht erlaubt"),
+    ),
+    (
+        "TENANT_ALREADY_EXISTS",
+        "Tenant already exists",
+        Some("Der Mandant existiert bereits"),
+    ),
+    (
+        "TENANT_INACTIVE",
+        "Tenant is inactive",
+        Some("Der Mandant ist deaktiviert"),
+    ),
+    ("TENANT_LIMIT_EXCEEDED", "Tenant limit exceeded", None),
+    (
+        "TENANT_NOT_FOUND",
+        "Tenant not found",
+        Some("Mandant nicht gefunden"),
+    ),
+    (
+        "TENANT_SUSPENDED",
+        "Tenant is suspended",
+        Some("Der Mandant ist gesperrt"),
+    ),
+    (
+        "UNAUTHORIZED",
+        "Authentication required",
+        Some("Anmeldung erforderlich"),
+    ),
+    (
+        "USER_ALREADY_EXISTS",
+        "A user with this email already exists",
+        Some("Ein Benutzer mit dieser E-Mail-Adresse existiert bereits"),
+    ),
+    (
+        "USER_NOT_FOUND",
+        "User not found",
+        Some("Benutzer nicht gefunden"),
+    ),
+    (
+        "VALIDATION_ERROR",
+        "Validation error",
+        Some("Validierungsfehler"),
+    ),
+    (
+        "WEAK_PASSWORD",
+        "Password does not meet the requirements",
+        Some("Das Passwort erfüllt die Anforderungen nicht"),
+    ),
+];
+
+/// Returns the translation of an error code, if the catalog has one
+///
+/// Unlike [`error_message`] this does not fall back to English, so callers
+/// can keep a more specific English message of their own.
+pub fn translation(code: &str, locale: Locale) -> Option<&'static str> {
+    let (_, en, de) = CATALOG.iter().find(|(known, ..)| *known == code)?;
+    match locale {
+        Locale::En => Some(en),
+        Locale::De => *de,
+    }
+}
+
+/// Returns the message for an error code in the given locale
+///
+/// Missing translations fall back to English; unknown codes yield `None`.
+pub fn error_message(code: &str, locale: Locale) -> Option<&'static str> {
+    translation(code, locale).or_else(|| translation(code, Locale::En))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale_from_accept_language() {
+        assert_eq!(Locale::from_accept_language("de-DE,de;q=0.9"), Locale::De);
+        assert_eq!(Locale::from_accept_language("fr-FR, de;q=0.5"), Locale::De);
+        assert_eq!(
+            Locale::from_accept_language("de;q=0.4, en;q=0.8"),
+            Locale::En
+        );
+        assert_eq!(Locale::from_accept_language("de;q=0, fr"), Locale::En);
+        assert_eq!(Locale::from_accept_language("*"), Locale::En);
+        assert_eq!(Locale::from_accept_language(""), Locale::En);
+    }
+
+    #[test]
+    fn test_error_message_falls_back_to_english() {
+        assert_eq!(
+            error_message("TENANT_ALREADY_EXISTS", Locale::De),
+            Some("Der Mandant existiert bereits")
+        );
+        assert_eq!(translation("TENANT_LIMIT_EXCEEDED", Locale::De), None);
+        assert_eq!(
+            error_message("TENANT_LIMIT_EXCEEDED", Locale::De),
+            Some("Tenant limit exceeded")
+        );
+        assert_eq!(error_message("NO_SUCH_CODE", Locale::De), None);
+    }
+
+    #[test]
+    fn test_catalog_codes_are_unique() {
+        let mut codes: Vec<_> = CATALOG.iter().map(|(code, ..)| *code).collect();
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), CATALOG.len());
+    }
+}
@@ -1,7 +1,22 @@
 pub mod config;
 pub mod database;
+pub mod datetime;
 pub mod error;
+pub mod http_client;
+pub mod i18n;
+pub mod rollout;
+pub mod secret;
+pub mod secret_store;
+pub mod signing;
 pub mod telemetry;
+pub mod tokens;
 
-pub use database::Database;
-pub use error::Error;
+pub use database::{AdvisoryLock, AdvisoryLockGuard, Database};
+pub use error::{Error, ErrorKind};
+pub use http_client::{HttpClient, HttpClientFactory, OutboundHttpConfig};
+pub use rollout::{
+    PostgresRolloutFlagStore, RolloutFlag, RolloutFlagStore, RolloutFlags, RolloutSubject,
+};
+pub use secret::Secret;
+pub use secret_store::{SecretResolver, SecretStore, SecretStoreConfig};
+pub use tokens::{ActionClaims, ActionToken, ActionTokenError, ActionTokenKeys};
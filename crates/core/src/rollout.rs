@@ -0,0 +1,549 @@
+//! Percentage-based rollouts of risky behavior changes
+//!
+//! A [`RolloutFlag`] enables a new behavior for a percentage of tenants or
+//! users and, regardless of the percentage, for the tenants on its allowlist.
+//! Whether a subject is in the rollout is decided by hashing the flag name
+//! with the subject's tenant id, or its user id if it has no tenant, so the
+//! decision is stable across requests and instances and only grows as the
+//! percentage is raised.
+//!
+//! [`RolloutFlags`] evaluates flags from an in-process cache. The cache is
+//! loaded from a [`RolloutFlagStore`], the `rollout_flags` table by default,
+//! on the first evaluation after its TTL passed, so changes made by another
+//! instance take effect within the TTL. Unknown flags are disabled.
+
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use sqlx::{PgPool, Row, postgres::PgRow};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+
+/// How long evaluated flags are cached before they are loaded again
+pub const DEFAULT_ROLLOUT_CACHE_TTL: Duration = Duration::from_secs(30);
+/// Longest name of a flag the `rollout_flags` table can store
+const MAX_FLAG_NAME_LENGTH: usize = 100;
+
+/// A behavior change rolled out to part of the tenants or users
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RolloutFlag {
+    pub name: String,
+    /// Share of the subjects the flag is enabled for, 0 to 100
+    pub percentage: u8,
+    /// Tenants the flag is enabled for regardless of the percentage
+    pub tenant_allowlist: Vec<Uuid>,
+    pub updated_at: OffsetDateTime,
+}
+
+impl RolloutFlag {
+    pub fn new(name: impl Into<String>, percentage: u8) -> Self {
+        Self {
+            name: name.into(),
+            percentage,
+            tenant_allowlist: Vec::new(),
+            updated_at: OffsetDateTime::now_utc(),
+        }
+    }
+
+    /// Enable the flag for `tenant_id` regardless of the percentage
+    pub fn with_tenant(mut self, tenant_id: Uuid) -> Self {
+        self.tenant_allowlist.push(tenant_id);
+        self
+    }
+
+    /// Whether the flag is enabled for `subject`
+    ///
+    /// Subjects without a tenant or user id are only in a complete rollout.
+    pub fn is_enabled_for(&self, subject: &RolloutSubject) -> bool {
+        if subject
+            .tenant_id
+            .is_some_and(|tenant_id| self.tenant_allowlist.contains(&tenant_id))
+        {
+            return true;
+        }
+        if self.percentage >= 100 {
+            return true;
+        }
+        subject
+            .bucket_key()
+            .is_some_and(|key| rollout_bucket(&self.name, key) < self.percentage)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() || self.name.len() > MAX_FLAG_NAME_LENGTH {
+            return Err(Error::Validation(format!(
+                "Rollout flag name must have 1 to {MAX_FLAG_NAME_LENGTH} characters"
+            )));
+        }
+        if self.percentage > 100 {
+            return Err(Error::Validation(format!(
+                "Rollout percentage of {} must be between 0 and 100",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Tenant and user a rollout flag is evaluated for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RolloutSubject {
+    pub tenant_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+}
+
+impl RolloutSubject {
+    pub fn new(tenant_id: Option<Uuid>, user_id: Option<Uuid>) -> Self {
+        Self { tenant_id, user_id }
+    }
+
+    pub fn tenant(tenant_id: Uuid) -> Self {
+        Self::new(Some(tenant_id), None)
+    }
+
+    pub fn user(user_id: Uuid) -> Self {
+        Self::new(None, Some(user_id))
+    }
+
+    /// Id the subject is bucketed by; all users of a tenant share its decision
+    fn bucket_key(&self) -> Option<Uuid> {
+        self.tenant_id.or(self.user_id)
+    }
+}
+
+/// Bucket from 0 to 99 of `key` for the flag `name`
+///
+/// A flag with percentage `p` is enabled for the keys in the buckets below
+/// `p`. Each flag hashes keys into buckets independently, so the same tenants
+/// are not always the first to get every change.
+pub fn rollout_bucket(name: &str, key: Uuid) -> u8 {
+    let mut hasher = Sha256::new();
+    hasher.update(name.as_bytes());
+    hasher.update([0]);
+    hasher.update(key.as_bytes());
+    let digest = hasher.finalize();
+
+    let mut value = [0u8; 8];
+    value.copy_from_slice(&digest[..8]);
+    (u64::from_be_bytes(value) % 100) as u8
+}
+
+/// Storage of the rollout flags shared by all instances
+#[async_trait]
+pub trait RolloutFlagStore: Send + Sync {
+    /// All stored flags
+    async fn load_flags(&self) -> Result<Vec<RolloutFlag>>;
+
+    /// Create or replace the flag with the name of `flag`
+    async fn save_flag(&self, flag: &RolloutFlag) -> Result<()>;
+}
+
+/// [`RolloutFlagStore`] backed by the `rollout_flags` table
+pub struct PostgresRolloutFlagStore {
+    pool: PgPool,
+}
+
+impl PostgresRolloutFlagStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn map_flag_row(row: PgRow) -> std::result::Result<RolloutFlag, sqlx::Error> {
+    let percentage: i16 = row.try_get("percentage")?;
+    Ok(RolloutFlag {
+        name: row.try_get("name")?,
+        percentage: percentage.clamp(0, 100) as u8,
+        tenant_allowlist: row.try_get("tenant_allowlist")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+#[async_trait]
+impl RolloutFlagStore for PostgresRolloutFlagStore {
+    async fn load_flags(&self) -> Result<Vec<RolloutFlag>> {
+        let rows =
+            sqlx::query("SELECT name, percentage, tenant_allowlist, updated_at FROM rollout_flags")
+                .fetch_all(&self.pool)
+                .await?;
+
+        rows.into_iter()
+            .map(map_flag_row)
+            .collect::<std::result::Result<_, _>>()
+            .map_err(Into::into)
+    }
+
+    async fn save_flag(&self, flag: &RolloutFlag) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO rollout_flags (name, percentage, tenant_allowlist, updated_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (name) DO UPDATE
+            SET percentage = EXCLUDED.percentage,
+                tenant_allowlist = EXCLUDED.tenant_allowlist,
+                updated_at = EXCLUDED.updated_at
+            "#,
+        )
+        .bind(&flag.name)
+        .bind(i16::from(flag.percentage))
+        .bind(&flag.tenant_allowlist)
+        .bind(flag.updated_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct RolloutCache {
+    flags: HashMap<String, RolloutFlag>,
+    /// When the flags were last loaded; `None` until the first load
+    loaded_at: Option<Instant>,
+}
+
+/// Cached rollout flags of this instance
+///
+/// Evaluating a flag only reads the cache, except for the first evaluation
+/// after the TTL passed, which loads all flags from the store. A failed load
+/// keeps the previous flags and is retried after another TTL.
+pub struct RolloutFlags {
+    store: Option<Arc<dyn RolloutFlagStore>>,
+    ttl: Duration,
+    cache: RwLock<RolloutCache>,
+    /// Held while loading, so concurrent evaluations load the flags only once
+    loading: tokio::sync::Mutex<()>,
+}
+
+impl RolloutFlags {
+    /// Flags loaded from `store` and cached for `ttl`
+    pub fn new(store: Arc<dyn RolloutFlagStore>, ttl: Duration) -> Self {
+        Self {
+            store: Some(store),
+            ttl,
+            cache: RwLock::new(RolloutCache::default()),
+            loading: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Fixed flags that are never loaded, e.g. for tests
+    pub fn fixed(flags: impl IntoIterator<Item = RolloutFlag>) -> Self {
+        Self {
+            store: None,
+            ttl: Duration::MAX,
+            cache: RwLock::new(RolloutCache {
+                flags: flags
+                    .into_iter()
+                    .map(|flag| (flag.name.clone(), flag))
+                    .collect(),
+                loaded_at: None,
+            }),
+            loading: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Whether the flag `name` is enabled for `subject`
+    pub async fn is_enabled(&self, name: &str, subject: RolloutSubject) -> bool {
+        self.refresh_if_stale().await;
+        self.cache
+            .read()
+            .expect("rollout flags lock poisoned")
+            .flags
+            .get(name)
+            .is_some_and(|flag| flag.is_enabled_for(&subject))
+    }
+
+    /// All flags, ordered by name
+    pub async fn flags(&self) -> Vec<RolloutFlag> {
+        self.refresh_if_stale().await;
+        let mut flags: Vec<_> = self
+            .cache
+            .read()
+            .expect("rollout flags lock poisoned")
+            .flags
+            .values()
+            .cloned()
+            .collect();
+        flags.sort_by(|a, b| a.name.cmp(&b.name));
+        flags
+    }
+
+    /// Store `flag` and apply it to this instance at once
+    ///
+    /// Other instances apply it once their cache expires.
+    pub async fn set_flag(&self, flag: RolloutFlag) -> Result<RolloutFlag> {
+        flag.validate()?;
+        if let Some(store) = &self.store {
+            store.save_flag(&flag).await?;
+        }
+
+        info!(
+            flag = %flag.name,
+            percentage = flag.percentage,
+            allowlisted_tenants = flag.tenant_allowlist.len(),
+            "Rollout flag updated"
+        );
+        self.cache
+            .write()
+            .expect("rollout flags lock poisoned")
+            .flags
+            .insert(flag.name.clone(), flag.clone());
+        Ok(flag)
+    }
+
+    /// Replace the cached flags with the stored ones
+    pub async fn refresh(&self) -> Result<usize> {
+        let Some(store) = &self.store else {
+            return Ok(self
+                .cache
+                .read()
+                .expect("rollout flags lock poisoned")
+                .flags
+                .len());
+        };
+        let _loading = self.loading.lock().await;
+        self.load(store.as_ref()).await
+    }
+
+    async fn load(&self, store: &dyn RolloutFlagStore) -> Result<usize> {
+        let result = store.load_flags().await;
+        let mut cache = self.cache.write().expect("rollout flags lock poisoned");
+        cache.loaded_at = Some(Instant::now());
+        let flags = result?;
+
+        cache.flags = flags
+            .into_iter()
+            .map(|flag| (flag.name.clone(), flag))
+            .collect();
+        debug!(count = cache.flags.len(), "Loaded rollout flags");
+        Ok(cache.flags.len())
+    }
+
+    fn is_stale(&self) -> bool {
+        self.cache
+            .read()
+            .expect("rollout flags lock poisoned")
+            .loaded_at
+            .is_none_or(|loaded_at| loaded_at.elapsed() >= self.ttl)
+    }
+
+    async fn refresh_if_stale(&self) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        if !self.is_stale() {
+            return;
+        }
+
+        let _loading = self.loading.lock().await;
+        // Loaded by the evaluation that held the lock before
+        if !self.is_stale() {
+            return;
+        }
+        if let Err(err) = self.load(store.as_ref()).await {
+            warn!(error = %err, "Failed to load rollout flags, keeping the cached ones");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct MemoryStore {
+        flags: Mutex<HashMap<String, RolloutFlag>>,
+        loads: AtomicUsize,
+        unavailable: AtomicBool,
+    }
+
+    #[async_trait]
+    impl RolloutFlagStore for MemoryStore {
+        async fn load_flags(&self) -> Result<Vec<RolloutFlag>> {
+            self.loads.fetch_add(1, Ordering::SeqCst);
+            if self.unavailable.load(Ordering::SeqCst) {
+                return Err(Error::Config("store unavailable".to_string()));
+            }
+            Ok(self
+                .flags
+                .lock()
+                .expect("store lock poisoned")
+                .values()
+                .cloned()
+                .collect())
+        }
+
+        async fn save_flag(&self, flag: &RolloutFlag) -> Result<()> {
+            self.flags
+                .lock()
+                .expect("store lock poisoned")
+                .insert(flag.name.clone(), flag.clone());
+            Ok(())
+        }
+    }
+
+    fn keys(count: usize) -> Vec<Uuid> {
+        (0..count as u128).map(Uuid::from_u128).collect()
+    }
+
+    #[test]
+    fn test_bucket_is_deterministic_per_flag() {
+        let key = Uuid::from_u128(42);
+        assert_eq!(
+            rollout_bucket("session_token_hmac", key),
+            rollout_bucket("session_token_hmac", key)
+        );
+        // Flags bucket the same keys differently
+        let differs = keys(100).into_iter().any(|key| {
+            rollout_bucket("session_token_hmac", key)
+                != rollout_bucket("client_binding_enforcement", key)
+        });
+        assert!(differs);
+    }
+
+    #[test]
+    fn test_percentage_boundaries() {
+        for key in keys(200) {
+            let subject = RolloutSubject::user(key);
+            let bucket = rollout_bucket("flag", key);
+
+            assert!(!RolloutFlag::new("flag", 0).is_enabled_for(&subject));
+            assert!(RolloutFlag::new("flag", 100).is_enabled_for(&subject));
+            assert!(!RolloutFlag::new("flag", bucket).is_enabled_for(&subject));
+            assert!(RolloutFlag::new("flag", bucket + 1).is_enabled_for(&subject));
+        }
+    }
+
+    #[test]
+    fn test_percentage_selects_about_that_share() {
+        let flag = RolloutFlag::new("flag", 25);
+        let enabled = keys(10_000)
+            .into_iter()
+            .filter(|key| flag.is_enabled_for(&RolloutSubject::user(*key)))
+            .count();
+        assert!((2_200..=2_800).contains(&enabled), "enabled for {enabled}");
+    }
+
+    #[test]
+    fn test_tenant_decides_for_all_its_users() {
+        let tenant_id = Uuid::from_u128(7);
+        let flag = RolloutFlag::new("flag", rollout_bucket("flag", tenant_id) + 1);
+
+        for user_id in keys(50) {
+            assert!(flag.is_enabled_for(&RolloutSubject::new(Some(tenant_id), Some(user_id))));
+        }
+    }
+
+    #[test]
+    fn test_allowlist_overrides_percentage() {
+        let tenant_id = Uuid::from_u128(7);
+        let flag = RolloutFlag::new("flag", 0).with_tenant(tenant_id);
+
+        assert!(flag.is_enabled_for(&RolloutSubject::tenant(tenant_id)));
+        assert!(flag.is_enabled_for(&RolloutSubject::new(
+            Some(tenant_id),
+            Some(Uuid::from_u128(1))
+        )));
+        assert!(!flag.is_enabled_for(&RolloutSubject::tenant(Uuid::from_u128(8))));
+        assert!(!flag.is_enabled_for(&RolloutSubject::user(tenant_id)));
+    }
+
+    #[test]
+    fn test_subject_without_ids_is_only_in_complete_rollout() {
+        let subject = RolloutSubject::default();
+        assert!(!RolloutFlag::new("flag", 99).is_enabled_for(&subject));
+        assert!(RolloutFlag::new("flag", 100).is_enabled_for(&subject));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_flag_is_disabled() {
+        let flags = RolloutFlags::fixed([RolloutFlag::new("other", 100)]);
+        assert!(
+            !flags
+                .is_enabled("flag", RolloutSubject::user(Uuid::from_u128(1)))
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cache_loads_once_per_ttl() {
+        let store = Arc::new(MemoryStore::default());
+        store
+            .save_flag(&RolloutFlag::new("flag", 100))
+            .await
+            .expect("Flag is saved");
+        let flags = RolloutFlags::new(store.clone(), Duration::from_secs(60));
+        let subject = RolloutSubject::user(Uuid::from_u128(1));
+
+        for _ in 0..10 {
+            assert!(flags.is_enabled("flag", subject).await);
+        }
+        assert_eq!(store.loads.load(Ordering::SeqCst), 1);
+
+        // A change by another instance is not seen before the TTL passed
+        store
+            .save_flag(&RolloutFlag::new("flag", 0))
+            .await
+            .expect("Flag is saved");
+        assert!(flags.is_enabled("flag", subject).await);
+        assert_eq!(flags.refresh().await.expect("Flags are loaded"), 1);
+        assert!(!flags.is_enabled("flag", subject).await);
+    }
+
+    #[tokio::test]
+    async fn test_expired_cache_loads_changes() {
+        let store = Arc::new(MemoryStore::default());
+        let flags = RolloutFlags::new(store.clone(), Duration::ZERO);
+        let subject = RolloutSubject::user(Uuid::from_u128(1));
+        assert!(!flags.is_enabled("flag", subject).await);
+
+        store
+            .save_flag(&RolloutFlag::new("flag", 100))
+            .await
+            .expect("Flag is saved");
+        assert!(flags.is_enabled("flag", subject).await);
+    }
+
+    #[tokio::test]
+    async fn test_failed_load_keeps_cached_flags() {
+        let store = Arc::new(MemoryStore::default());
+        store
+            .save_flag(&RolloutFlag::new("flag", 100))
+            .await
+            .expect("Flag is saved");
+        let flags = RolloutFlags::new(store.clone(), Duration::ZERO);
+        let subject = RolloutSubject::user(Uuid::from_u128(1));
+        assert!(flags.is_enabled("flag", subject).await);
+
+        store.unavailable.store(true, Ordering::SeqCst);
+        assert!(flags.is_enabled("flag", subject).await);
+        assert!(flags.refresh().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_set_flag_applies_locally_and_stores() {
+        let store = Arc::new(MemoryStore::default());
+        let flags = RolloutFlags::new(store.clone(), Duration::from_secs(60));
+        let subject = RolloutSubject::user(Uuid::from_u128(1));
+        assert!(!flags.is_enabled("flag", subject).await);
+
+        flags
+            .set_flag(RolloutFlag::new("flag", 100))
+            .await
+            .expect("Flag is valid");
+        assert!(flags.is_enabled("flag", subject).await);
+        assert_eq!(
+            store.flags.lock().expect("store lock poisoned")["flag"].percentage,
+            100
+        );
+
+        assert!(flags.set_flag(RolloutFlag::new("flag", 101)).await.is_err());
+        assert!(flags.set_flag(RolloutFlag::new(" ", 10)).await.is_err());
+    }
+}
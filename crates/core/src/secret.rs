@@ -0,0 +1,198 @@
+//! Redacted secrets for configuration values
+//!
+//! [`Secret`] wraps values such as JWT secrets, SMTP passwords or API tokens.
+//! Its `Debug` and `Display` output never contains the value, so configs can be
+//! logged safely, and the value is zeroized when the secret is dropped.
+//!
+//! In configuration files a secret is either given inline or as a reference
+//! into a [`SecretStore`](crate::secret_store::SecretStore), written as
+//! `{"secret_ref": "<name>"}`. A reference has no value until it is resolved,
+//! see [`SecretResolver`](crate::secret_store::SecretResolver).
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use zeroize::Zeroize;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// A secret configuration value that is redacted in logs and zeroized on drop
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret<T: Zeroize> {
+    value: T,
+    /// Name of the secret in the secret store, if the value is loaded from there
+    reference: Option<String>,
+    resolved: bool,
+}
+
+impl<T: Zeroize> Secret<T> {
+    /// Wraps a secret value
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            reference: None,
+            resolved: true,
+        }
+    }
+
+    /// Gives access to the secret value
+    ///
+    /// Keep the returned reference out of log statements and error messages.
+    /// A reference that has not been resolved yet exposes the default value.
+    pub fn expose_secret(&self) -> &T {
+        &self.value
+    }
+
+    /// Name of the secret in the secret store, for secrets configured as a reference
+    pub fn secret_ref(&self) -> Option<&str> {
+        self.reference.as_deref()
+    }
+
+    /// Whether the secret has a value, i.e. it was given inline or its reference was resolved
+    pub fn is_resolved(&self) -> bool {
+        self.resolved
+    }
+}
+
+impl<T: Zeroize + Default> Secret<T> {
+    /// A secret whose value is loaded from the secret store under `name`
+    pub fn from_ref(name: impl Into<String>) -> Self {
+        Self {
+            value: T::default(),
+            reference: Some(name.into()),
+            resolved: false,
+        }
+    }
+
+    /// Replaces the value of a referenced secret with the one loaded from the store
+    ///
+    /// The reference is kept, so the secret can be resolved again after the
+    /// store was refreshed.
+    pub fn resolve(&mut self, mut value: Secret<T>) {
+        self.value.zeroize();
+        self.value = std::mem::take(&mut value.value);
+        self.resolved = true;
+    }
+}
+
+impl<T: Zeroize + Default> Default for Secret<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl Secret<String> {
+    /// Returns the secret as bytes, e.g. for use as a signing key
+    pub fn as_bytes(&self) -> &[u8] {
+        self.value.as_bytes()
+    }
+}
+
+impl From<String> for Secret<String> {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<&str> for Secret<String> {
+    fn from(value: &str) -> Self {
+        Self::new(value.to_string())
+    }
+}
+
+impl<T: Zeroize> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+impl<T: Zeroize> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T: Zeroize> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+/// Secrets are written redacted, so serialized configs never leak them
+///
+/// References are written as references, so a serialized config loads again.
+impl<T: Zeroize> Serialize for Secret<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match &self.reference {
+            Some(name) => SecretRef {
+                secret_ref: name.clone(),
+            }
+            .serialize(serializer),
+            None => serializer.serialize_str(REDACTED),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SecretRef {
+    secret_ref: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SecretRepr<T> {
+    Reference(SecretRef),
+    Inline(T),
+}
+
+impl<'de, T: Zeroize + Default + Deserialize<'de>> Deserialize<'de> for Secret<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match SecretRepr::deserialize(deserializer)? {
+            SecretRepr::Reference(SecretRef { secret_ref }) => Self::from_ref(secret_ref),
+            SecretRepr::Inline(value) => Self::new(value),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_is_redacted() {
+        let secret = Secret::from("hunter2");
+        assert_eq!(format!("{secret:?}"), REDACTED);
+        assert_eq!(secret.to_string(), REDACTED);
+        assert_eq!(
+            serde_json::to_string(&secret).expect("Serializes"),
+            format!("\"{REDACTED}\"")
+        );
+        assert_eq!(secret.expose_secret(), "hunter2");
+    }
+
+    #[test]
+    fn test_secret_deserializes_from_plain_value() {
+        let secret: Secret<String> = serde_json::from_str("\"s3cr3t\"").expect("Deserializes");
+        assert_eq!(secret.as_bytes(), b"s3cr3t");
+        assert!(secret.is_resolved());
+        assert_eq!(secret.secret_ref(), None);
+    }
+
+    #[test]
+    fn test_secret_reference_round_trip() {
+        let mut secret: Secret<String> =
+            serde_json::from_str(r#"{"secret_ref": "smtp/password"}"#).expect("Deserializes");
+        assert_eq!(secret.secret_ref(), Some("smtp/password"));
+        assert!(!secret.is_resolved());
+        assert_eq!(secret.expose_secret(), "");
+
+        secret.resolve(Secret::from("hunter2"));
+        assert!(secret.is_resolved());
+        assert_eq!(secret.expose_secret(), "hunter2");
+        assert_eq!(
+            serde_json::to_string(&secret).expect("Serializes"),
+            r#"{"secret_ref":"smtp/password"}"#
+        );
+        assert_eq!(format!("{secret:?}"), REDACTED);
+    }
+}
@@ -0,0 +1,1151 @@
+//! Secret stores for provider credentials
+//!
+//! Secrets configured as `{"secret_ref": "<name>"}` are loaded by name from a
+//! [`SecretStore`]: the environment ([`EnvSecretStore`], the default),
+//! HashiCorp Vault ([`VaultSecretStore`]) or AWS Secrets Manager
+//! ([`AwsSecretsManagerStore`]), chosen by a [`SecretStoreConfig`].
+//! [`CachedSecretStore`] keeps loaded values for a TTL; refreshing it, e.g.
+//! after a rotation, notifies its subscribers so they resolve their secrets
+//! again.
+//!
+//! A name may select one key of a structured secret as `<path>#<key>`. Vault
+//! secrets are read from the key `value` unless another key is given; AWS
+//! secrets without a key are used as stored.
+
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::{StatusCode, Url};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use time::OffsetDateTime;
+use tokio::sync::watch;
+use tracing::{debug, info};
+use zeroize::Zeroize;
+
+use crate::error::{Error, Result};
+use crate::http_client::HttpClient;
+use crate::secret::Secret;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Key read from Vault secrets whose name does not select one
+const DEFAULT_VAULT_KEY: &str = "value";
+
+/// Errors of loading a secret from a [`SecretStore`]
+#[derive(Debug, Error)]
+pub enum SecretStoreError {
+    #[error("Secret {0} not found")]
+    NotFound(String),
+    #[error("Secret {name} has no key {key}")]
+    MissingKey { name: String, key: String },
+    #[error("Secret store rejected the credentials: {0}")]
+    Unauthorized(String),
+    #[error("Secret store request failed: {0}")]
+    Backend(String),
+}
+
+impl From<SecretStoreError> for Error {
+    fn from(err: SecretStoreError) -> Self {
+        Error::Config(err.to_string())
+    }
+}
+
+/// Source of secrets referenced by name from the configuration
+#[async_trait]
+pub trait SecretStore: Send + Sync {
+    /// Load the secret stored under `name`
+    async fn get_secret(&self, name: &str)
+    -> std::result::Result<Secret<String>, SecretStoreError>;
+}
+
+/// Split `name` into the path of the secret and the key selected with `#`
+fn split_key(name: &str) -> (&str, Option<&str>) {
+    match name.split_once('#') {
+        Some((path, key)) => (path, Some(key)),
+        None => (name, None),
+    }
+}
+
+/// The value under `key` of the structured secret `data`
+fn select_key(
+    name: &str,
+    data: &Value,
+    key: &str,
+) -> std::result::Result<Secret<String>, SecretStoreError> {
+    match data.get(key) {
+        Some(Value::String(value)) => Ok(Secret::from(value.as_str())),
+        Some(Value::Null) | None => Err(SecretStoreError::MissingKey {
+            name: name.to_string(),
+            key: key.to_string(),
+        }),
+        Some(value) => Ok(Secret::from(value.to_string())),
+    }
+}
+
+/// Store reading secrets from environment variables
+///
+/// The variable of a secret is its name upper-cased, with every character
+/// other than letters and digits replaced by `_`, after the configured
+/// prefix: with the prefix `ACCI_`, `smtp/password` is read from
+/// `ACCI_SMTP_PASSWORD`.
+#[derive(Debug, Clone, Default)]
+pub struct EnvSecretStore {
+    prefix: String,
+}
+
+impl EnvSecretStore {
+    /// Create a store reading variables without a prefix
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read variables starting with `prefix`
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Environment variable holding the secret `name`
+    pub fn variable_name(&self, name: &str) -> String {
+        let normalized: String = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_uppercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        format!("{}{}", self.prefix, normalized)
+    }
+}
+
+#[async_trait]
+impl SecretStore for EnvSecretStore {
+    async fn get_secret(
+        &self,
+        name: &str,
+    ) -> std::result::Result<Secret<String>, SecretStoreError> {
+        let variable = self.variable_name(name);
+        std::env::var(&variable).map(Secret::from).map_err(|_| {
+            SecretStoreError::NotFound(format!("{name} (environment variable {variable})"))
+        })
+    }
+}
+
+/// Connection to a KV version 2 secrets engine of HashiCorp Vault
+#[derive(Debug, Clone, Deserialize)]
+pub struct VaultConfig {
+    /// Address of the Vault server, e.g. `https://vault.example.com:8200`
+    pub address: String,
+    /// Mount path of the secrets engine
+    #[serde(default = "default_vault_mount")]
+    pub mount: String,
+    /// Vault Enterprise namespace sent with every request
+    #[serde(default)]
+    pub namespace: Option<String>,
+    pub auth: VaultAuth,
+}
+
+fn default_vault_mount() -> String {
+    "secret".to_string()
+}
+
+/// How the store authenticates to Vault
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum VaultAuth {
+    /// A fixed token, e.g. issued by the Vault agent
+    Token { token: Secret<String> },
+    /// AppRole login; the token is requested on first use and again once Vault rejects it
+    AppRole {
+        role_id: String,
+        secret_id: Secret<String>,
+        /// Mount path of the AppRole auth method
+        #[serde(default = "default_approle_mount")]
+        mount: String,
+    },
+}
+
+fn default_approle_mount() -> String {
+    "approle".to_string()
+}
+
+/// Store reading secrets from HashiCorp Vault
+///
+/// The name of a secret is its path within the secrets engine, e.g.
+/// `acci/smtp#password` reads the key `password` of the secret `acci/smtp`.
+pub struct VaultSecretStore {
+    config: VaultConfig,
+    client: HttpClient,
+    /// Token of the last AppRole login
+    login_token: tokio::sync::Mutex<Option<Secret<String>>>,
+}
+
+impl VaultSecretStore {
+    pub fn new(config: VaultConfig, client: HttpClient) -> Self {
+        Self {
+            config,
+            client,
+            login_token: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!(
+            "{}/v1/{}",
+            self.config.address.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        )
+    }
+
+    fn with_namespace(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.config.namespace {
+            Some(namespace) => request.header("X-Vault-Namespace", namespace),
+            None => request,
+        }
+    }
+
+    /// Token for the next request, logging in with AppRole if there is none
+    async fn token(&self) -> std::result::Result<Secret<String>, SecretStoreError> {
+        let (role_id, secret_id, mount) = match &self.config.auth {
+            VaultAuth::Token { token } => return Ok(token.clone()),
+            VaultAuth::AppRole {
+                role_id,
+                secret_id,
+                mount,
+            } => (role_id, secret_id, mount),
+        };
+
+        let mut login_token = self.login_token.lock().await;
+        if let Some(token) = login_token.as_ref() {
+            return Ok(token.clone());
+        }
+
+        #[allow(clippy::disallowed_methods)]
+        let credentials = json!({
+            "role_id": role_id,
+            "secret_id": secret_id.expose_secret(),
+        });
+        let request = self.with_namespace(
+            self.client
+                .post(self.url(&format!("auth/{mount}/login")))
+                .json(&credentials),
+        );
+        let response = self
+            .client
+            .send(request)
+            .await
+            .map_err(|e| SecretStoreError::Backend(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(SecretStoreError::Unauthorized(format!(
+                "AppRole login failed with HTTP {status}"
+            )));
+        }
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| SecretStoreError::Backend(e.to_string()))?;
+        let token = body["auth"]["client_token"]
+            .as_str()
+            .map(Secret::from)
+            .ok_or_else(|| {
+                SecretStoreError::Backend("AppRole login returned no client token".to_string())
+            })?;
+        debug!("Logged in to Vault with AppRole");
+
+        *login_token = Some(token.clone());
+        Ok(token)
+    }
+
+    async fn read(
+        &self,
+        path: &str,
+        token: &Secret<String>,
+    ) -> std::result::Result<reqwest::Response, SecretStoreError> {
+        let request = self.with_namespace(
+            self.client
+                .get(self.url(&format!("{}/data/{}", self.config.mount, path)))
+                .header("X-Vault-Token", token.expose_secret()),
+        );
+        self.client
+            .send(request)
+            .await
+            .map_err(|e| SecretStoreError::Backend(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl SecretStore for VaultSecretStore {
+    async fn get_secret(
+        &self,
+        name: &str,
+    ) -> std::result::Result<Secret<String>, SecretStoreError> {
+        let (path, key) = split_key(name);
+
+        let mut response = self.read(path, &self.token().await?).await?;
+        if response.status() == StatusCode::FORBIDDEN
+            && matches!(self.config.auth, VaultAuth::AppRole { .. })
+        {
+            // The login token expired or was revoked, log in once more
+            self.login_token.lock().await.take();
+            response = self.read(path, &self.token().await?).await?;
+        }
+
+        match response.status() {
+            StatusCode::NOT_FOUND => return Err(SecretStoreError::NotFound(name.to_string())),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                return Err(SecretStoreError::Unauthorized(format!(
+                    "Vault denied reading {path}"
+                )));
+            },
+            status if !status.is_success() => {
+                return Err(SecretStoreError::Backend(format!(
+                    "Vault answered HTTP {status} reading {path}"
+                )));
+            },
+            _ => {},
+        }
+
+        let mut body: Value = response
+            .json()
+            .await
+            .map_err(|e| SecretStoreError::Backend(e.to_string()))?;
+        let secret = select_key(
+            name,
+            &body["data"]["data"],
+            key.unwrap_or(DEFAULT_VAULT_KEY),
+        );
+        body.zeroize_values();
+        secret
+    }
+}
+
+/// Connection to AWS Secrets Manager
+#[derive(Debug, Clone, Deserialize)]
+pub struct AwsSecretsManagerConfig {
+    /// Region of the secrets, e.g. `eu-central-1`
+    pub region: String,
+    /// Endpoint used instead of `https://secretsmanager.<region>.amazonaws.com`,
+    /// e.g. a VPC endpoint
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// Access key ID, read from `AWS_ACCESS_KEY_ID` if unset
+    #[serde(default)]
+    pub access_key_id: Option<String>,
+    /// Secret access key, read from `AWS_SECRET_ACCESS_KEY` if unset
+    #[serde(default)]
+    pub secret_access_key: Option<Secret<String>>,
+    /// Session token of temporary credentials, read from `AWS_SESSION_TOKEN` if unset
+    #[serde(default)]
+    pub session_token: Option<Secret<String>>,
+}
+
+/// Store reading secrets from AWS Secrets Manager
+///
+/// The name of a secret is its name or ARN; `acci/smtp#password` reads the
+/// key `password` of a secret stored as a JSON object. Requests are signed
+/// with Signature Version 4 using the credentials found when the store is
+/// created.
+pub struct AwsSecretsManagerStore {
+    region: String,
+    endpoint: Url,
+    access_key_id: String,
+    secret_access_key: Secret<String>,
+    session_token: Option<Secret<String>>,
+    client: HttpClient,
+}
+
+impl AwsSecretsManagerStore {
+    const SERVICE: &'static str = "secretsmanager";
+    const TARGET: &'static str = "secretsmanager.GetSecretValue";
+    const CONTENT_TYPE: &'static str = "application/x-amz-json-1.1";
+
+    /// Create a store, failing if no credentials are configured or in the environment
+    pub fn new(config: AwsSecretsManagerConfig, client: HttpClient) -> Result<Self> {
+        let endpoint = config.endpoint.clone().unwrap_or_else(|| {
+            format!("https://{}.{}.amazonaws.com", Self::SERVICE, config.region)
+        });
+        let endpoint = Url::parse(&endpoint).map_err(|e| {
+            Error::Config(format!("Invalid Secrets Manager endpoint {endpoint}: {e}"))
+        })?;
+        let access_key_id = config
+            .access_key_id
+            .or_else(|| std::env::var("AWS_ACCESS_KEY_ID").ok())
+            .ok_or_else(|| Error::Config("No AWS access key ID configured".to_string()))?;
+        let secret_access_key = config
+            .secret_access_key
+            .or_else(|| {
+                std::env::var("AWS_SECRET_ACCESS_KEY")
+                    .ok()
+                    .map(Secret::from)
+            })
+            .ok_or_else(|| Error::Config("No AWS secret access key configured".to_string()))?;
+        let session_token = config
+            .session_token
+            .or_else(|| std::env::var("AWS_SESSION_TOKEN").ok().map(Secret::from));
+
+        Ok(Self {
+            region: config.region,
+            endpoint,
+            access_key_id,
+            secret_access_key,
+            session_token,
+            client,
+        })
+    }
+
+    /// `Host` header of the requests, including a non-default port
+    fn host(&self) -> String {
+        let host = self.endpoint.host_str().unwrap_or_default();
+        match self.endpoint.port() {
+            Some(port) => format!("{host}:{port}"),
+            None => host.to_string(),
+        }
+    }
+
+    /// `Authorization` header of a request with `body`, sent at `now`
+    fn authorization(&self, now: OffsetDateTime, body: &[u8]) -> String {
+        let amz_date = amz_date(now);
+        let date = &amz_date[..8];
+
+        let mut headers = vec![
+            ("content-type", Self::CONTENT_TYPE.to_string()),
+            ("host", self.host()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token", token.expose_secret().clone()));
+        }
+        headers.push(("x-amz-target", Self::TARGET.to_string()));
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{name}:{value}\n"))
+            .collect();
+
+        let canonical_request = format!(
+            "POST\n/\n\n{canonical_headers}\n{signed_headers}\n{}",
+            hex::encode(Sha256::digest(body))
+        );
+        let scope = format!("{date}/{}/{}/aws4_request", self.region, Self::SERVICE);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let key = signing_key(
+            self.secret_access_key.expose_secret(),
+            date,
+            &self.region,
+            Self::SERVICE,
+        );
+        let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+            self.access_key_id
+        )
+    }
+}
+
+#[async_trait]
+impl SecretStore for AwsSecretsManagerStore {
+    async fn get_secret(
+        &self,
+        name: &str,
+    ) -> std::result::Result<Secret<String>, SecretStoreError> {
+        let (secret_id, key) = split_key(name);
+        #[allow(clippy::disallowed_methods)]
+        let body = json!({ "SecretId": secret_id }).to_string();
+        let now = OffsetDateTime::now_utc();
+
+        let mut request = self
+            .client
+            .post(self.endpoint.clone())
+            .header("Content-Type", Self::CONTENT_TYPE)
+            .header("X-Amz-Date", amz_date(now))
+            .header("X-Amz-Target", Self::TARGET)
+            .header("Authorization", self.authorization(now, body.as_bytes()));
+        if let Some(token) = &self.session_token {
+            request = request.header("X-Amz-Security-Token", token.expose_secret());
+        }
+        let response = self
+            .client
+            .send(request.body(body))
+            .await
+            .map_err(|e| SecretStoreError::Backend(e.to_string()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body: Value = response.json().await.unwrap_or_default();
+            let error_type = body["__type"].as_str().unwrap_or_default();
+            return Err(if error_type.ends_with("ResourceNotFoundException") {
+                SecretStoreError::NotFound(name.to_string())
+            } else if status == StatusCode::FORBIDDEN
+                || error_type.ends_with("AccessDeniedException")
+                || error_type.ends_with("UnrecognizedClientException")
+            {
+                SecretStoreError::Unauthorized(format!(
+                    "Secrets Manager denied reading {secret_id}"
+                ))
+            } else {
+                SecretStoreError::Backend(format!(
+                    "Secrets Manager answered HTTP {status} ({error_type}) reading {secret_id}"
+                ))
+            });
+        }
+
+        let mut body: Value = response
+            .json()
+            .await
+            .map_err(|e| SecretStoreError::Backend(e.to_string()))?;
+        let Some(secret_string) = body["SecretString"].as_str() else {
+            return Err(SecretStoreError::Backend(format!(
+                "Secret {secret_id} has no string value"
+            )));
+        };
+        let secret = match key {
+            None => Ok(Secret::from(secret_string)),
+            Some(key) => {
+                let mut data: Value = serde_json::from_str(secret_string).map_err(|_| {
+                    SecretStoreError::Backend(format!("Secret {secret_id} is not a JSON object"))
+                })?;
+                let secret = select_key(name, &data, key);
+                data.zeroize_values();
+                secret
+            },
+        };
+        body.zeroize_values();
+        secret
+    }
+}
+
+/// `X-Amz-Date` value of `now`, e.g. `20240131T120000Z`
+fn amz_date(now: OffsetDateTime) -> String {
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        now.year(),
+        u8::from(now.month()),
+        now.day(),
+        now.hour(),
+        now.minute(),
+        now.second()
+    )
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Signature Version 4 key derived from the secret access key for one day, region and service
+fn signing_key(secret_access_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let date_key = hmac_sha256(
+        format!("AWS4{secret_access_key}").as_bytes(),
+        date.as_bytes(),
+    );
+    let region_key = hmac_sha256(&date_key, region.as_bytes());
+    let service_key = hmac_sha256(&region_key, service.as_bytes());
+    hmac_sha256(&service_key, b"aws4_request")
+}
+
+/// Overwrites the strings of JSON responses that carried secrets before they are dropped
+trait ZeroizeValues {
+    fn zeroize_values(&mut self);
+}
+
+impl ZeroizeValues for Value {
+    fn zeroize_values(&mut self) {
+        match self {
+            Value::String(value) => value.zeroize(),
+            Value::Array(values) => values.iter_mut().for_each(ZeroizeValues::zeroize_values),
+            Value::Object(map) => map.values_mut().for_each(ZeroizeValues::zeroize_values),
+            _ => {},
+        }
+    }
+}
+
+/// Store keeping the secrets loaded from another store for a TTL
+///
+/// [`CachedSecretStore::refresh`] drops the cached values and notifies the
+/// receivers of [`CachedSecretStore::subscribe`], which re-resolve their
+/// secrets to pick up rotated values.
+pub struct CachedSecretStore {
+    inner: Arc<dyn SecretStore>,
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (Secret<String>, Instant)>>,
+    generation: watch::Sender<u64>,
+}
+
+impl CachedSecretStore {
+    pub fn new(inner: Arc<dyn SecretStore>, ttl: Duration) -> Self {
+        let (generation, _) = watch::channel(0);
+        Self {
+            inner,
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+            generation,
+        }
+    }
+
+    /// Drop all cached secrets and notify subscribers to resolve theirs again
+    pub fn refresh(&self) {
+        self.entries().clear();
+        self.generation.send_modify(|generation| *generation += 1);
+        info!("Secret store refreshed");
+    }
+
+    /// Receiver that is notified after every [`CachedSecretStore::refresh`]
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.generation.subscribe()
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, HashMap<String, (Secret<String>, Instant)>> {
+        self.entries.lock().expect("secret cache lock poisoned")
+    }
+}
+
+#[async_trait]
+impl SecretStore for CachedSecretStore {
+    async fn get_secret(
+        &self,
+        name: &str,
+    ) -> std::result::Result<Secret<String>, SecretStoreError> {
+        let cached = self
+            .entries()
+            .get(name)
+            .filter(|(_, expires_at)| *expires_at > Instant::now())
+            .map(|(secret, _)| secret.clone());
+        if let Some(secret) = cached {
+            return Ok(secret);
+        }
+
+        let secret = self.inner.get_secret(name).await?;
+        self.entries().insert(
+            name.to_string(),
+            (secret.clone(), Instant::now() + self.ttl),
+        );
+        Ok(secret)
+    }
+}
+
+/// Which secret store the configuration references are resolved with
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SecretStoreConfig {
+    pub backend: SecretBackendConfig,
+    /// How long loaded secrets are kept before the backend is asked again
+    pub cache_ttl_secs: u64,
+}
+
+impl Default for SecretStoreConfig {
+    fn default() -> Self {
+        Self {
+            backend: SecretBackendConfig::default(),
+            cache_ttl_secs: 300,
+        }
+    }
+}
+
+/// Backend of a [`SecretStoreConfig`], selected by its `type`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SecretBackendConfig {
+    Env {
+        #[serde(default)]
+        prefix: String,
+    },
+    Vault(VaultConfig),
+    AwsSecretsManager(AwsSecretsManagerConfig),
+}
+
+impl Default for SecretBackendConfig {
+    fn default() -> Self {
+        Self::Env {
+            prefix: String::new(),
+        }
+    }
+}
+
+impl SecretStoreConfig {
+    /// Build the configured store behind a cache
+    ///
+    /// `client` is used by the backends reached over HTTP.
+    pub fn build(&self, client: &HttpClient) -> Result<CachedSecretStore> {
+        let store: Arc<dyn SecretStore> = match &self.backend {
+            SecretBackendConfig::Env { prefix } => {
+                Arc::new(EnvSecretStore::new().with_prefix(prefix.clone()))
+            },
+            SecretBackendConfig::Vault(config) => {
+                Arc::new(VaultSecretStore::new(config.clone(), client.clone()))
+            },
+            SecretBackendConfig::AwsSecretsManager(config) => {
+                Arc::new(AwsSecretsManagerStore::new(config.clone(), client.clone())?)
+            },
+        };
+        Ok(CachedSecretStore::new(
+            store,
+            Duration::from_secs(self.cache_ttl_secs),
+        ))
+    }
+}
+
+/// Resolves the secret references of a configuration
+///
+/// Every reference is attempted before [`SecretResolver::finish`] reports the
+/// failures, so a deployment learns about all missing secrets at once instead
+/// of one per restart.
+pub struct SecretResolver<'a> {
+    store: &'a dyn SecretStore,
+    failures: Vec<String>,
+}
+
+impl<'a> SecretResolver<'a> {
+    pub fn new(store: &'a dyn SecretStore) -> Self {
+        Self {
+            store,
+            failures: Vec::new(),
+        }
+    }
+
+    /// Load the value of `secret` if it is a reference; `field` names it in errors
+    pub async fn resolve(&mut self, field: &str, secret: &mut Secret<String>) {
+        let Some(name) = secret.secret_ref().map(str::to_string) else {
+            return;
+        };
+        match self.store.get_secret(&name).await {
+            Ok(value) => secret.resolve(value),
+            Err(err) => self.failures.push(format!("{field}: {err}")),
+        }
+    }
+
+    /// Fail with all secrets that could not be resolved
+    pub fn finish(self) -> Result<()> {
+        if self.failures.is_empty() {
+            return Ok(());
+        }
+        Err(Error::Config(format!(
+            "Failed to resolve {} secret(s): {}",
+            self.failures.len(),
+            self.failures.join("; ")
+        )))
+    }
+}
+
+/// Fail if `secret` is a reference that was not resolved yet
+///
+/// Called by whatever builds a provider or signer from a configured secret,
+/// so an unresolved reference is not used as an empty key.
+pub fn ensure_resolved<T: Zeroize>(field: &str, secret: &Secret<T>) -> Result<()> {
+    if secret.is_resolved() {
+        return Ok(());
+    }
+    Err(Error::Config(format!(
+        "Secret {field} references {} in the secret store but was not resolved",
+        secret.secret_ref().unwrap_or_default()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_client::{HttpClientFactory, OutboundHttpConfig};
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Request received by a [`StubServer`]
+    #[derive(Debug, Clone)]
+    struct StubRequest {
+        method: String,
+        path: String,
+        headers: HashMap<String, String>,
+        body: String,
+    }
+
+    /// Local HTTP server answering requests with the responses of a handler
+    struct StubServer {
+        address: SocketAddr,
+        requests: Arc<Mutex<Vec<StubRequest>>>,
+    }
+
+    impl StubServer {
+        async fn start(handler: fn(&StubRequest) -> (u16, Value)) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0")
+                .await
+                .expect("Failed to bind stub server");
+            let address = listener.local_addr().expect("bound address");
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let recorded = Arc::clone(&requests);
+            tokio::spawn(async move {
+                while let Ok((mut stream, _)) = listener.accept().await {
+                    let Some(request) = read_request(&mut stream).await else {
+                        continue;
+                    };
+                    let (status, body) = handler(&request);
+                    recorded
+                        .lock()
+                        .expect("requests lock poisoned")
+                        .push(request);
+                    let body = body.to_string();
+                    let response = format!(
+                        "HTTP/1.1 {status} Stub\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                        body.len()
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                    let _ = stream.shutdown().await;
+                }
+            });
+            Self { address, requests }
+        }
+
+        fn url(&self) -> String {
+            format!("http://{}", self.address)
+        }
+
+        fn requests(&self) -> Vec<StubRequest> {
+            self.requests
+                .lock()
+                .expect("requests lock poisoned")
+                .clone()
+        }
+    }
+
+    async fn read_request(stream: &mut tokio::net::TcpStream) -> Option<StubRequest> {
+        let mut data = Vec::new();
+        let mut buffer = [0u8; 4096];
+        loop {
+            let read = stream.read(&mut buffer).await.ok()?;
+            if read == 0 {
+                return None;
+            }
+            data.extend_from_slice(&buffer[..read]);
+            let text = String::from_utf8_lossy(&data).to_string();
+            let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                continue;
+            };
+            let mut lines = head.lines();
+            let mut request_line = lines.next()?.split_whitespace();
+            let method = request_line.next()?.to_string();
+            let path = request_line.next()?.to_string();
+            let headers: HashMap<String, String> = lines
+                .filter_map(|line| line.split_once(':'))
+                .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+                .collect();
+            let length = headers
+                .get("content-length")
+                .and_then(|length| length.parse::<usize>().ok())
+                .unwrap_or(0);
+            if body.len() >= length {
+                return Some(StubRequest {
+                    method,
+                    path,
+                    headers,
+                    body: body.to_string(),
+                });
+            }
+        }
+    }
+
+    fn client() -> HttpClient {
+        HttpClientFactory::new(OutboundHttpConfig::default())
+            .build()
+            .expect("Failed to build client")
+    }
+
+    fn vault_config(server: &StubServer, auth: VaultAuth) -> VaultConfig {
+        VaultConfig {
+            address: server.url(),
+            mount: "kv".to_string(),
+            namespace: None,
+            auth,
+        }
+    }
+
+    /// Vault serving `kv/acci/smtp` to the token `root-token` and to AppRole logins
+    fn vault(request: &StubRequest) -> (u16, Value) {
+        match (request.method.as_str(), request.path.as_str()) {
+            ("POST", "/v1/auth/approle/login") => {
+                let login: Value = serde_json::from_str(&request.body).expect("JSON login");
+                if login == json!({"role_id": "acci", "secret_id": "approle-secret"}) {
+                    (200, json!({"auth": {"client_token": "root-token"}}))
+                } else {
+                    (400, json!({"errors": ["invalid role or secret ID"]}))
+                }
+            },
+            _ if request.headers.get("x-vault-token").map(String::as_str) != Some("root-token") => {
+                (403, json!({"errors": ["permission denied"]}))
+            },
+            ("GET", "/v1/kv/data/acci/smtp") => (
+                200,
+                json!({"data": {
+                    "data": {"value": "smtp-password", "api_key": "sg-key"},
+                    "metadata": {"version": 3},
+                }}),
+            ),
+            _ => (404, json!({"errors": []})),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_vault_reads_kv_v2_secrets_with_token() {
+        let server = StubServer::start(vault).await;
+        let store = VaultSecretStore::new(
+            vault_config(
+                &server,
+                VaultAuth::Token {
+                    token: Secret::from("root-token"),
+                },
+            ),
+            client(),
+        );
+
+        let secret = store.get_secret("acci/smtp").await.expect("Secret is read");
+        assert_eq!(secret.expose_secret(), "smtp-password");
+        let secret = store
+            .get_secret("acci/smtp#api_key")
+            .await
+            .expect("Key is read");
+        assert_eq!(secret.expose_secret(), "sg-key");
+
+        assert!(matches!(
+            store.get_secret("acci/smtp#missing").await,
+            Err(SecretStoreError::MissingKey { .. })
+        ));
+        assert!(matches!(
+            store.get_secret("acci/unknown").await,
+            Err(SecretStoreError::NotFound(_))
+        ));
+
+        let wrong_token = VaultSecretStore::new(
+            vault_config(
+                &server,
+                VaultAuth::Token {
+                    token: Secret::from("other-token"),
+                },
+            ),
+            client(),
+        );
+        assert!(matches!(
+            wrong_token.get_secret("acci/smtp").await,
+            Err(SecretStoreError::Unauthorized(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_vault_approle_logs_in_once() {
+        let server = StubServer::start(vault).await;
+        let store = VaultSecretStore::new(
+            vault_config(
+                &server,
+                VaultAuth::AppRole {
+                    role_id: "acci".to_string(),
+                    secret_id: Secret::from("approle-secret"),
+                    mount: default_approle_mount(),
+                },
+            ),
+            client(),
+        );
+
+        for _ in 0..2 {
+            let secret = store.get_secret("acci/smtp").await.expect("Secret is read");
+            assert_eq!(secret.expose_secret(), "smtp-password");
+        }
+        let logins = server
+            .requests()
+            .iter()
+            .filter(|request| request.path == "/v1/auth/approle/login")
+            .count();
+        assert_eq!(logins, 1);
+
+        let rejected = VaultSecretStore::new(
+            vault_config(
+                &server,
+                VaultAuth::AppRole {
+                    role_id: "acci".to_string(),
+                    secret_id: Secret::from("wrong-secret"),
+                    mount: default_approle_mount(),
+                },
+            ),
+            client(),
+        );
+        assert!(matches!(
+            rejected.get_secret("acci/smtp").await,
+            Err(SecretStoreError::Unauthorized(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_aws_secrets_manager_signs_requests() {
+        let server = StubServer::start(|request| {
+            let secret_id: Value = serde_json::from_str(&request.body).expect("JSON request");
+            match secret_id["SecretId"].as_str() {
+                Some("acci/sendgrid") => (200, json!({"SecretString": "sg-key"})),
+                Some("acci/twilio") => (
+                    200,
+                    json!({"SecretString": r#"{"sid": "AC123", "token": "twilio-token"}"#}),
+                ),
+                _ => (
+                    400,
+                    json!({"__type": "ResourceNotFoundException", "message": "not found"}),
+                ),
+            }
+        })
+        .await;
+        let store = AwsSecretsManagerStore::new(
+            AwsSecretsManagerConfig {
+                region: "eu-central-1".to_string(),
+                endpoint: Some(server.url()),
+                access_key_id: Some("AKIDEXAMPLE".to_string()),
+                secret_access_key: Some(Secret::from("aws-secret-key")),
+                session_token: None,
+            },
+            client(),
+        )
+        .expect("Credentials are configured");
+
+        let secret = store
+            .get_secret("acci/sendgrid")
+            .await
+            .expect("Secret is read");
+        assert_eq!(secret.expose_secret(), "sg-key");
+        let secret = store
+            .get_secret("acci/twilio#token")
+            .await
+            .expect("Key is read");
+        assert_eq!(secret.expose_secret(), "twilio-token");
+        assert!(matches!(
+            store.get_secret("acci/unknown").await,
+            Err(SecretStoreError::NotFound(_))
+        ));
+
+        let request = &server.requests()[0];
+        assert_eq!(
+            request.headers["x-amz-target"],
+            "secretsmanager.GetSecretValue"
+        );
+        let authorization = &request.headers["authorization"];
+        assert!(
+            authorization.starts_with("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/"),
+            "{authorization}"
+        );
+        assert!(authorization.contains("/eu-central-1/secretsmanager/aws4_request"));
+        assert!(authorization.contains("SignedHeaders=content-type;host;x-amz-date;x-amz-target"));
+    }
+
+    #[test]
+    fn test_signing_key_matches_aws_example() {
+        // Example from the AWS documentation on deriving the signing key
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex::encode(key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_env_store_round_trip() {
+        let store = EnvSecretStore::new().with_prefix("ACCI_SECRET_STORE_TEST_");
+        assert_eq!(
+            store.variable_name("smtp/password"),
+            "ACCI_SECRET_STORE_TEST_SMTP_PASSWORD"
+        );
+
+        // SAFETY: the variable is unique to this test
+        unsafe { std::env::set_var("ACCI_SECRET_STORE_TEST_SMTP_PASSWORD", "hunter2") };
+        let secret = store
+            .get_secret("smtp/password")
+            .await
+            .expect("Variable is set");
+        assert_eq!(secret.expose_secret(), "hunter2");
+        assert!(matches!(
+            store.get_secret("smtp/username").await,
+            Err(SecretStoreError::NotFound(_))
+        ));
+    }
+
+    /// Store counting its reads, returning the number of the read as value
+    struct CountingStore(AtomicUsize);
+
+    #[async_trait]
+    impl SecretStore for CountingStore {
+        async fn get_secret(
+            &self,
+            name: &str,
+        ) -> std::result::Result<Secret<String>, SecretStoreError> {
+            if name == "missing" {
+                return Err(SecretStoreError::NotFound(name.to_string()));
+            }
+            let reads = self.0.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(Secret::from(reads.to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_refresh_notifies_subscribers() {
+        let store = CachedSecretStore::new(
+            Arc::new(CountingStore(AtomicUsize::new(0))),
+            Duration::from_secs(60),
+        );
+        let mut refreshed = store.subscribe();
+
+        let mut secret = Secret::from_ref("jwt");
+        let mut resolver = SecretResolver::new(&store);
+        resolver.resolve("jwt_secret", &mut secret).await;
+        resolver.finish().expect("Secret is resolved");
+        assert_eq!(secret.expose_secret(), "1");
+        let cached = store.get_secret("jwt").await.expect("Secret is cached");
+        assert_eq!(cached.expose_secret(), "1");
+
+        store.refresh();
+        refreshed.changed().await.expect("Store is alive");
+        let mut resolver = SecretResolver::new(&store);
+        resolver.resolve("jwt_secret", &mut secret).await;
+        resolver.finish().expect("Secret is resolved");
+        assert_eq!(secret.expose_secret(), "2");
+        assert_eq!(secret.secret_ref(), Some("jwt"));
+    }
+
+    #[tokio::test]
+    async fn test_resolver_reports_all_failures() {
+        let store = CountingStore(AtomicUsize::new(0));
+        let mut inline = Secret::from("inline");
+        let mut resolved = Secret::from_ref("jwt");
+        let mut first = Secret::from_ref("missing");
+        let mut second = Secret::from_ref("missing");
+
+        let mut resolver = SecretResolver::new(&store);
+        resolver.resolve("inline", &mut inline).await;
+        resolver.resolve("jwt_secret", &mut resolved).await;
+        resolver.resolve("smtp.password", &mut first).await;
+        resolver.resolve("sms.api_key", &mut second).await;
+        let error = resolver.finish().expect_err("Two secrets are missing");
+
+        let message = error.to_string();
+        assert!(message.contains("2 secret(s)"), "{message}");
+        assert!(message.contains("smtp.password"), "{message}");
+        assert!(message.contains("sms.api_key"), "{message}");
+        assert_eq!(inline.expose_secret(), "inline");
+        assert!(resolved.is_resolved());
+        assert!(ensure_resolved("smtp.password", &first).is_err());
+        assert!(ensure_resolved("jwt_secret", &resolved).is_ok());
+    }
+}
@@ -0,0 +1,242 @@
+//! HMAC request signing
+//!
+//! Clients sign a request by sending `X-Timestamp`, `X-Nonce` and
+//! `X-Signature: v1=<hex>`, where the signature is an HMAC-SHA256 over the
+//! timestamp, the nonce, the method, the path including the query and the
+//! SHA-256 of the body. The server recomputes it with the same shared secret,
+//! so neither the body nor the nonce can be swapped without invalidating the
+//! signature. Both sides use the functions of this module to build the
+//! signed string the same way.
+//!
+//! Webhooks are signed over the raw body alone: the sender sends
+//! `X-Webhook-Timestamp` and `X-Webhook-Signature: v1=<hex>`, an HMAC-SHA256
+//! over `<timestamp>.<body>`. During a secret rotation the signature header
+//! may carry several comma-separated signatures, one per secret.
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Version prefix of the signatures produced by [`sign_request`]
+pub const SIGNATURE_VERSION: &str = "v1";
+
+/// Request header carrying the signature
+pub const SIGNATURE_HEADER: &str = "X-Signature";
+
+/// Request header carrying the Unix timestamp the request was signed at
+pub const TIMESTAMP_HEADER: &str = "X-Timestamp";
+
+/// Request header carrying the nonce of the request
+pub const NONCE_HEADER: &str = "X-Nonce";
+
+/// Webhook header carrying the signature
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+/// Webhook header carrying the Unix timestamp the delivery was signed at
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+
+/// The parts of a request covered by its signature
+#[derive(Debug, Clone, Copy)]
+pub struct SignedRequest<'a> {
+    /// Unix timestamp in seconds
+    pub timestamp: i64,
+    /// Single-use value chosen by the client
+    pub nonce: &'a str,
+    /// HTTP method, compared case-insensitively
+    pub method: &'a str,
+    /// Path including the query string, e.g. `/api/transfers?dry_run=true`
+    pub path: &'a str,
+    /// Raw request body
+    pub body: &'a [u8],
+}
+
+impl SignedRequest<'_> {
+    /// The string the HMAC is computed over
+    fn canonical(&self) -> String {
+        format!(
+            "{}\n{}\n{}\n{}\n{}",
+            self.timestamp,
+            self.nonce,
+            self.method.to_ascii_uppercase(),
+            self.path,
+            hex::encode(Sha256::digest(self.body))
+        )
+    }
+
+    fn mac(&self, secret: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any size");
+        mac.update(self.canonical().as_bytes());
+        mac
+    }
+}
+
+/// Sign a request, returning the value of the `X-Signature` header
+pub fn sign_request(secret: &[u8], request: &SignedRequest<'_>) -> String {
+    let signature = request.mac(secret).finalize().into_bytes();
+    format!("{}={}", SIGNATURE_VERSION, hex::encode(signature))
+}
+
+/// Whether `signature` is a valid `X-Signature` header value for the request
+///
+/// The comparison runs in constant time. Unknown versions and malformed
+/// values are rejected.
+pub fn verify_request_signature(
+    secret: &[u8],
+    request: &SignedRequest<'_>,
+    signature: &str,
+) -> bool {
+    let Some(encoded) = signature
+        .trim()
+        .strip_prefix(SIGNATURE_VERSION)
+        .and_then(|rest| rest.strip_prefix('='))
+    else {
+        return false;
+    };
+    let Ok(signature) = hex::decode(encoded) else {
+        return false;
+    };
+
+    request.mac(secret).verify_slice(&signature).is_ok()
+}
+
+fn webhook_mac(secret: &[u8], timestamp: i64, body: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// Sign a webhook delivery, returning the value of the `X-Webhook-Signature` header
+///
+/// `body` must be the exact bytes that are sent.
+pub fn sign_webhook(secret: &[u8], timestamp: i64, body: &[u8]) -> String {
+    let signature = webhook_mac(secret, timestamp, body).finalize().into_bytes();
+    format!("{}={}", SIGNATURE_VERSION, hex::encode(signature))
+}
+
+/// Whether one of the signatures in `header` is valid for the delivery
+///
+/// `body` must be the raw body as received; re-serialized JSON does not
+/// match. The comparison runs in constant time, and signatures of unknown
+/// versions are skipped.
+pub fn verify_webhook_signature(secret: &[u8], timestamp: i64, body: &[u8], header: &str) -> bool {
+    header.split(',').any(|signature| {
+        let Some(encoded) = signature
+            .trim()
+            .strip_prefix(SIGNATURE_VERSION)
+            .and_then(|rest| rest.strip_prefix('='))
+        else {
+            return false;
+        };
+        let Ok(signature) = hex::decode(encoded) else {
+            return false;
+        };
+        webhook_mac(secret, timestamp, body)
+            .verify_slice(&signature)
+            .is_ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"signing-secret";
+
+    fn request(body: &[u8]) -> SignedRequest<'_> {
+        SignedRequest {
+            timestamp: 1_700_000_000,
+            nonce: "3f2a9c",
+            method: "post",
+            path: "/api/transfers",
+            body,
+        }
+    }
+
+    #[test]
+    fn test_signature_roundtrip() {
+        let signature = sign_request(SECRET, &request(b"{\"amount\":10}"));
+        assert!(signature.starts_with("v1="));
+        assert!(verify_request_signature(
+            SECRET,
+            &request(b"{\"amount\":10}"),
+            &signature
+        ));
+        assert!(!verify_request_signature(
+            b"other-secret",
+            &request(b"{\"amount\":10}"),
+            &signature
+        ));
+    }
+
+    #[test]
+    fn test_signature_covers_body_and_nonce() {
+        let signature = sign_request(SECRET, &request(b"{\"amount\":10}"));
+
+        assert!(!verify_request_signature(
+            SECRET,
+            &request(b"{\"amount\":1000}"),
+            &signature
+        ));
+        let other_nonce = SignedRequest {
+            nonce: "3f2a9d",
+            ..request(b"{\"amount\":10}")
+        };
+        assert!(!verify_request_signature(SECRET, &other_nonce, &signature));
+    }
+
+    #[test]
+    fn test_malformed_signature_is_rejected() {
+        let request = request(b"");
+        let signature = sign_request(SECRET, &request);
+
+        assert!(!verify_request_signature(
+            SECRET,
+            &request,
+            signature.trim_start_matches("v1=")
+        ));
+        assert!(!verify_request_signature(
+            SECRET,
+            &request,
+            &signature.replace("v1=", "v2=")
+        ));
+        assert!(!verify_request_signature(SECRET, &request, "v1=not-hex"));
+    }
+
+    #[test]
+    fn test_webhook_signature_with_rotated_secrets() {
+        let body = b"{\"status\":\"paid\"}";
+        let signature = sign_webhook(SECRET, 1_700_000_000, body);
+        assert!(verify_webhook_signature(
+            SECRET,
+            1_700_000_000,
+            body,
+            &signature
+        ));
+        assert!(!verify_webhook_signature(
+            SECRET,
+            1_700_000_001,
+            body,
+            &signature
+        ));
+
+        let header = format!(
+            "{}, {signature}",
+            sign_webhook(b"old-secret", 1_700_000_000, body)
+        );
+        assert!(verify_webhook_signature(
+            SECRET,
+            1_700_000_000,
+            body,
+            &header
+        ));
+        assert!(!verify_webhook_signature(
+            b"unknown-secret",
+            1_700_000_000,
+            body,
+            &header
+        ));
+    }
+}
@@ -1,6 +1,15 @@
+use redis::AsyncCommands;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, warn};
 use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
 
-use crate::error::Result;
+use crate::error::{Error, Result};
+
+/// Redis key prefix of tenant sampling overrides
+const OVERRIDE_KEY_PREFIX: &str = "acci:tracing:override:";
 
 /// Initialize the logging system
 pub fn init_logging(log_level: &str) -> Result<()> {
@@ -25,9 +34,381 @@ pub fn init_metrics() -> Result<()> {
     Ok(())
 }
 
+/// Routes grouped by how much of their traffic is sampled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RouteClass {
+    /// Login, registration and other `/auth` endpoints
+    Auth,
+    /// Administrative endpoints under `/admin`
+    Admin,
+    /// Health checks and other frequently polled routes
+    HighVolume,
+    /// Everything else
+    Default,
+}
+
+impl RouteClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auth => "auth",
+            Self::Admin => "admin",
+            Self::HighVolume => "high_volume",
+            Self::Default => "default",
+        }
+    }
+}
+
+/// Head-based sampling rates per route class
+///
+/// Rates are fractions between 0.0 and 1.0. Server errors and authentication
+/// failures are always sampled when `always_sample_errors` is set.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SamplingConfig {
+    pub default_rate: f64,
+    pub auth_rate: f64,
+    pub admin_rate: f64,
+    pub high_volume_rate: f64,
+    /// Path suffixes of the routes sampled at `high_volume_rate`
+    pub high_volume_routes: Vec<String>,
+    pub always_sample_errors: bool,
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            default_rate: 0.1,
+            auth_rate: 0.25,
+            admin_rate: 1.0,
+            high_volume_rate: 0.01,
+            high_volume_routes: vec!["/health".to_string(), "/auth/validate-token".to_string()],
+            always_sample_errors: true,
+        }
+    }
+}
+
+impl SamplingConfig {
+    /// Class of the route serving `path`
+    pub fn route_class(&self, path: &str) -> RouteClass {
+        let path = path.trim_end_matches('/');
+        if self
+            .high_volume_routes
+            .iter()
+            .any(|route| path.ends_with(route.as_str()))
+        {
+            return RouteClass::HighVolume;
+        }
+
+        let mut segments = path.split('/');
+        if segments.clone().any(|segment| segment == "auth") {
+            RouteClass::Auth
+        } else if segments.any(|segment| segment == "admin") {
+            RouteClass::Admin
+        } else {
+            RouteClass::Default
+        }
+    }
+
+    pub fn rate(&self, route_class: RouteClass) -> f64 {
+        match route_class {
+            RouteClass::Auth => self.auth_rate,
+            RouteClass::Admin => self.admin_rate,
+            RouteClass::HighVolume => self.high_volume_rate,
+            RouteClass::Default => self.default_rate,
+        }
+    }
+}
+
+/// Temporary sampling rate for all requests of a tenant
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplingOverride {
+    pub rate: f64,
+    pub expires_at: SystemTime,
+}
+
+/// Tenant sampling overrides consulted by the [`TraceSampler`]
+///
+/// Expired overrides are ignored. The map is local to the process; use
+/// [`RedisSamplingOverrideStore`] to share overrides between instances.
+#[derive(Debug, Default)]
+pub struct SamplingOverrides {
+    entries: RwLock<HashMap<String, SamplingOverride>>,
+}
+
+impl SamplingOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, tenant_id: &str, rate: f64, expires_at: SystemTime) {
+        self.entries
+            .write()
+            .expect("sampling overrides lock poisoned")
+            .insert(
+                tenant_id.to_string(),
+                SamplingOverride {
+                    rate: rate.clamp(0.0, 1.0),
+                    expires_at,
+                },
+            );
+    }
+
+    /// Rate overriding the route rates for `tenant_id`, unless expired at `now`
+    pub fn rate_for(&self, tenant_id: &str, now: SystemTime) -> Option<f64> {
+        self.entries
+            .read()
+            .expect("sampling overrides lock poisoned")
+            .get(tenant_id)
+            .filter(|entry| entry.expires_at > now)
+            .map(|entry| entry.rate)
+    }
+
+    /// Replace all overrides, e.g. with the ones loaded from Redis
+    pub fn replace(&self, entries: HashMap<String, SamplingOverride>) {
+        *self
+            .entries
+            .write()
+            .expect("sampling overrides lock poisoned") = entries;
+    }
+
+    /// Drop overrides expired at `now`
+    pub fn prune(&self, now: SystemTime) {
+        self.entries
+            .write()
+            .expect("sampling overrides lock poisoned")
+            .retain(|_, entry| entry.expires_at > now);
+    }
+}
+
+/// Outcome of a sampling decision, named after OpenTelemetry's `SamplingDecision`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplingDecision {
+    Drop,
+    RecordAndSample,
+}
+
+impl SamplingDecision {
+    pub fn is_sampled(&self) -> bool {
+        *self == Self::RecordAndSample
+    }
+}
+
+/// Request attributes a sampling decision is based on
+#[derive(Debug, Clone, Copy)]
+pub struct SamplingContext<'a> {
+    pub trace_id: u128,
+    pub route_class: RouteClass,
+    pub tenant_id: Option<&'a str>,
+    /// Response status, known once the request has been handled
+    pub status: Option<u16>,
+}
+
+/// Head-based sampler with per-route-class rates and tenant overrides
+///
+/// Like OpenTelemetry's `TraceIdRatioBased` sampler the decision is derived
+/// from the trace ID, so every component seeing the same trace decides alike.
+/// An active tenant override replaces the route rate when it is higher.
+pub struct TraceSampler {
+    config: SamplingConfig,
+    overrides: Arc<SamplingOverrides>,
+}
+
+impl TraceSampler {
+    pub fn new(config: SamplingConfig, overrides: Arc<SamplingOverrides>) -> Self {
+        Self { config, overrides }
+    }
+
+    pub fn config(&self) -> &SamplingConfig {
+        &self.config
+    }
+
+    pub fn overrides(&self) -> &Arc<SamplingOverrides> {
+        &self.overrides
+    }
+
+    pub fn should_sample(&self, context: &SamplingContext) -> SamplingDecision {
+        self.decide(context, SystemTime::now())
+    }
+
+    fn decide(&self, context: &SamplingContext, now: SystemTime) -> SamplingDecision {
+        let failed = context
+            .status
+            .is_some_and(|status| status >= 500 || status == 401 || status == 403);
+        if failed && self.config.always_sample_errors {
+            return SamplingDecision::RecordAndSample;
+        }
+
+        let route_rate = self.config.rate(context.route_class);
+        let rate = context
+            .tenant_id
+            .and_then(|tenant_id| self.overrides.rate_for(tenant_id, now))
+            .map_or(route_rate, |boost| boost.max(route_rate));
+
+        if trace_id_ratio_sampled(context.trace_id, rate) {
+            SamplingDecision::RecordAndSample
+        } else {
+            SamplingDecision::Drop
+        }
+    }
+}
+
+/// Whether a trace falls into the sampled fraction `rate`
+fn trace_id_ratio_sampled(trace_id: u128, rate: f64) -> bool {
+    if rate >= 1.0 {
+        return true;
+    }
+    if rate <= 0.0 {
+        return false;
+    }
+    let bound = (rate * u64::MAX as f64) as u64;
+    (trace_id as u64) < bound
+}
+
+static SAMPLER: OnceLock<Arc<TraceSampler>> = OnceLock::new();
+
+/// Install the process-wide request sampler
+///
+/// Returns `false` if a sampler was installed before. Without a sampler every
+/// request is sampled.
+pub fn install_sampler(sampler: Arc<TraceSampler>) -> bool {
+    SAMPLER.set(sampler).is_ok()
+}
+
+/// The installed request sampler
+pub fn sampler() -> Option<Arc<TraceSampler>> {
+    SAMPLER.get().cloned()
+}
+
+/// Tenant sampling overrides shared between instances through Redis
+///
+/// Overrides are stored with a Redis TTL; [`RedisSamplingOverrideStore::refresh`]
+/// copies the live ones into the local [`SamplingOverrides`].
+pub struct RedisSamplingOverrideStore {
+    redis_client: Arc<redis::Client>,
+    overrides: Arc<SamplingOverrides>,
+}
+
+impl RedisSamplingOverrideStore {
+    pub fn new(redis_client: Arc<redis::Client>, overrides: Arc<SamplingOverrides>) -> Self {
+        Self {
+            redis_client,
+            overrides,
+        }
+    }
+
+    /// Store an override for `tenant_id` that expires after `ttl`
+    pub async fn set_override(
+        &self,
+        tenant_id: &str,
+        rate: f64,
+        ttl: Duration,
+    ) -> Result<SamplingOverride> {
+        let rate = rate.clamp(0.0, 1.0);
+        let mut conn = self
+            .redis_client
+            .get_async_connection()
+            .await
+            .map_err(redis_error)?;
+        let _: () = conn
+            .set_ex(
+                format!("{}{}", OVERRIDE_KEY_PREFIX, tenant_id),
+                rate.to_string(),
+                ttl.as_secs().max(1),
+            )
+            .await
+            .map_err(redis_error)?;
+
+        let expires_at = SystemTime::now() + ttl;
+        self.overrides.set(tenant_id, rate, expires_at);
+        Ok(SamplingOverride { rate, expires_at })
+    }
+
+    /// Replace the local overrides with the ones stored in Redis
+    pub async fn refresh(&self) -> Result<usize> {
+        let mut conn = self
+            .redis_client
+            .get_async_connection()
+            .await
+            .map_err(redis_error)?;
+
+        let keys: Vec<String> = {
+            let mut iter = conn
+                .scan_match::<_, String>(format!("{}*", OVERRIDE_KEY_PREFIX))
+                .await
+                .map_err(redis_error)?;
+            let mut keys = Vec::new();
+            while let Some(key) = iter.next_item().await {
+                keys.push(key);
+            }
+            keys
+        };
+
+        let now = SystemTime::now();
+        let mut entries = HashMap::new();
+        for key in keys {
+            let rate: Option<String> = conn.get(&key).await.map_err(redis_error)?;
+            let ttl_ms: i64 = conn.pttl(&key).await.map_err(redis_error)?;
+            let Some(rate) = rate.and_then(|rate| rate.parse::<f64>().ok()) else {
+                warn!(key = %key, "Ignoring malformed sampling override");
+                continue;
+            };
+            // Negative TTLs mean the key vanished or never expires
+            if ttl_ms <= 0 {
+                continue;
+            }
+            let tenant_id = key.trim_start_matches(OVERRIDE_KEY_PREFIX).to_string();
+            entries.insert(
+                tenant_id,
+                SamplingOverride {
+                    rate: rate.clamp(0.0, 1.0),
+                    expires_at: now + Duration::from_millis(ttl_ms as u64),
+                },
+            );
+        }
+
+        let count = entries.len();
+        self.overrides.replace(entries);
+        debug!(count, "Refreshed sampling overrides");
+        Ok(count)
+    }
+
+    /// Refresh the local overrides every `interval` in a background task
+    pub fn spawn_refresh(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(err) = self.refresh().await {
+                    warn!(error = %err, "Failed to refresh sampling overrides");
+                }
+            }
+        })
+    }
+}
+
+fn redis_error(err: redis::RedisError) -> Error {
+    Error::Other(err.into())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    fn sampled_fraction(sampler: &TraceSampler, context: SamplingContext, now: SystemTime) -> f64 {
+        let mut rng = StdRng::seed_from_u64(42);
+        let samples = 20_000;
+        let sampled = (0..samples)
+            .filter(|_| {
+                let context = SamplingContext {
+                    trace_id: rng.random(),
+                    ..context
+                };
+                sampler.decide(&context, now).is_sampled()
+            })
+            .count();
+        sampled as f64 / samples as f64
+    }
 
     // We can't directly test initialization since it can only be done once
     // and affects global state. Instead, we'll test the components.
@@ -69,4 +450,140 @@ mod tests {
             assert!(filter_str.ends_with(level));
         }
     }
+
+    #[test]
+    fn test_route_classes() {
+        let config = SamplingConfig::default();
+        assert_eq!(config.route_class("/health"), RouteClass::HighVolume);
+        assert_eq!(
+            config.route_class("/api/v1/auth/validate-token"),
+            RouteClass::HighVolume
+        );
+        assert_eq!(config.route_class("/auth/login"), RouteClass::Auth);
+        assert_eq!(
+            config.route_class("/admin/impersonation"),
+            RouteClass::Admin
+        );
+        assert_eq!(config.route_class("/tenants/abc"), RouteClass::Default);
+        // Only whole path segments count
+        assert_eq!(config.route_class("/authors"), RouteClass::Default);
+    }
+
+    #[test]
+    fn test_sampler_applies_route_rates() {
+        let sampler = TraceSampler::new(SamplingConfig::default(), Arc::default());
+        let now = SystemTime::now();
+        let context = SamplingContext {
+            trace_id: 0,
+            route_class: RouteClass::HighVolume,
+            tenant_id: None,
+            status: Some(200),
+        };
+
+        let fraction = sampled_fraction(&sampler, context, now);
+        assert!((0.005..0.015).contains(&fraction), "sampled {}", fraction);
+
+        let admin = SamplingContext {
+            route_class: RouteClass::Admin,
+            ..context
+        };
+        assert_eq!(sampled_fraction(&sampler, admin, now), 1.0);
+
+        // The decision only depends on the trace ID
+        let trace_id = StdRng::seed_from_u64(7).random();
+        let first = sampler.decide(
+            &SamplingContext {
+                trace_id,
+                ..context
+            },
+            now,
+        );
+        let second = sampler.decide(
+            &SamplingContext {
+                trace_id,
+                ..context
+            },
+            now,
+        );
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_errors_and_auth_failures_are_always_sampled() {
+        let config = SamplingConfig {
+            high_volume_rate: 0.0,
+            auth_rate: 0.0,
+            ..SamplingConfig::default()
+        };
+        let sampler = TraceSampler::new(config, Arc::default());
+        let now = SystemTime::now();
+        let context = SamplingContext {
+            trace_id: 0,
+            route_class: RouteClass::HighVolume,
+            tenant_id: None,
+            status: Some(200),
+        };
+        assert_eq!(sampled_fraction(&sampler, context, now), 0.0);
+
+        for status in [500, 503, 401, 403] {
+            let failed = SamplingContext {
+                route_class: RouteClass::Auth,
+                status: Some(status),
+                ..context
+            };
+            assert_eq!(sampled_fraction(&sampler, failed, now), 1.0);
+        }
+
+        let not_found = SamplingContext {
+            status: Some(404),
+            ..context
+        };
+        assert_eq!(sampled_fraction(&sampler, not_found, now), 0.0);
+    }
+
+    #[test]
+    fn test_tenant_override_boosts_until_expiry() {
+        let overrides = Arc::new(SamplingOverrides::new());
+        let sampler = TraceSampler::new(SamplingConfig::default(), overrides.clone());
+        let now = SystemTime::now();
+        let expires_at = now + Duration::from_secs(30 * 60);
+        overrides.set("tenant-a", 1.0, expires_at);
+
+        let context = SamplingContext {
+            trace_id: 0,
+            route_class: RouteClass::HighVolume,
+            tenant_id: Some("tenant-a"),
+            status: Some(200),
+        };
+        assert_eq!(sampled_fraction(&sampler, context, now), 1.0);
+
+        // Other tenants keep the route rate
+        let other = SamplingContext {
+            tenant_id: Some("tenant-b"),
+            ..context
+        };
+        assert!(sampled_fraction(&sampler, other, now) < 0.015);
+
+        // The override ends with its TTL
+        assert!(sampled_fraction(&sampler, context, expires_at) < 0.015);
+        assert_eq!(overrides.rate_for("tenant-a", expires_at), None);
+        overrides.prune(expires_at);
+        assert_eq!(overrides.rate_for("tenant-a", now), None);
+    }
+
+    #[test]
+    fn test_override_never_lowers_route_rate() {
+        let overrides = Arc::new(SamplingOverrides::new());
+        let sampler = TraceSampler::new(SamplingConfig::default(), overrides.clone());
+        let now = SystemTime::now();
+        overrides.set("tenant-a", 0.0, now + Duration::from_secs(60));
+
+        let context = SamplingContext {
+            trace_id: 0,
+            route_class: RouteClass::Admin,
+            tenant_id: Some("tenant-a"),
+            status: None,
+        };
+        assert_eq!(sampled_fraction(&sampler, context, now), 1.0);
+    }
 }
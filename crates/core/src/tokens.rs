@@ -0,0 +1,443 @@
+//! Signed action tokens for links sent to users
+//!
+//! Password reset links, invitations, email verification links and download
+//! URLs carry an [`ActionToken`]: a compact token naming the action it
+//! authorizes, its subject and tenant, an expiry and a random nonce. Tokens
+//! have the form
+//!
+//! ```text
+//! at1.<key id>.<base64url payload>.<base64url HMAC-SHA256>
+//! ```
+//!
+//! The HMAC covers everything before the last dot, so neither the payload
+//! nor the key id can be swapped. Verification needs no database; the key id
+//! selects the secret from an [`ActionTokenKeys`] ring, so keys can be
+//! rotated while tokens signed with the previous key are still valid.
+//! Single use is enforced with [`ActionToken::consume_once`], which records
+//! the nonce in Redis until the token expires. The same record serves as
+//! deny-list for [`ActionToken::revoke`].
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD as BASE64URL};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Version prefix of the tokens produced by [`ActionToken::issue`]
+pub const ACTION_TOKEN_VERSION: &str = "at1";
+
+/// Random bytes of a token's nonce
+const NONCE_BYTES: usize = 16;
+
+/// Redis key prefix of consumed and revoked nonces
+const CONSUMED_KEY_PREFIX: &str = "action-token:consumed";
+
+/// Errors of issuing, verifying and consuming action tokens
+#[derive(Debug, Error)]
+pub enum ActionTokenError {
+    #[error("Malformed action token")]
+    Malformed,
+
+    #[error("Unsupported action token version")]
+    UnsupportedVersion,
+
+    #[error("Action token signed with an unknown key")]
+    UnknownKey,
+
+    #[error("Invalid action token signature")]
+    InvalidSignature,
+
+    #[error("Action token has expired")]
+    Expired,
+
+    #[error("Action token is not valid for this action")]
+    WrongAction,
+
+    #[error("Action token has already been used")]
+    AlreadyConsumed,
+
+    #[error("Single-use action tokens require Redis")]
+    ConsumptionNotConfigured,
+
+    #[error("Redis error: {0}")]
+    Redis(#[from] redis::RedisError),
+}
+
+/// What a verified action token authorizes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActionClaims {
+    /// Action the token was issued for, e.g. `password-reset`
+    #[serde(rename = "act")]
+    pub action: String,
+    /// Who or what the action applies to, e.g. a user ID
+    #[serde(rename = "sub")]
+    pub subject: String,
+    #[serde(rename = "ten", default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// Unix timestamp in seconds after which the token is rejected
+    #[serde(rename = "exp")]
+    pub expires_at: i64,
+    /// Random value identifying the token for single use and revocation
+    #[serde(rename = "nce")]
+    pub nonce: String,
+}
+
+/// Secret an action token is signed with
+#[derive(Clone)]
+struct SigningKey {
+    id: String,
+    secret: Vec<u8>,
+}
+
+/// Secrets action tokens are signed and verified with
+///
+/// New tokens are signed with the current key. Previous keys only verify, so
+/// after a rotation outstanding tokens stay valid until they expire.
+#[derive(Clone)]
+pub struct ActionTokenKeys {
+    current: SigningKey,
+    previous: Vec<SigningKey>,
+}
+
+impl ActionTokenKeys {
+    /// Key ring signing with `secret`, identified in tokens by `id`
+    ///
+    /// Key ids must not contain dots.
+    pub fn new(id: impl Into<String>, secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            current: SigningKey {
+                id: id.into(),
+                secret: secret.into(),
+            },
+            previous: Vec::new(),
+        }
+    }
+
+    /// Also accepts tokens signed with a previous key
+    pub fn with_previous_key(mut self, id: impl Into<String>, secret: impl Into<Vec<u8>>) -> Self {
+        self.previous.push(SigningKey {
+            id: id.into(),
+            secret: secret.into(),
+        });
+        self
+    }
+
+    fn find(&self, id: &str) -> Option<&SigningKey> {
+        std::iter::once(&self.current)
+            .chain(&self.previous)
+            .find(|key| key.id == id)
+    }
+}
+
+impl std::fmt::Debug for ActionTokenKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let previous: Vec<_> = self.previous.iter().map(|key| &key.id).collect();
+        f.debug_struct("ActionTokenKeys")
+            .field("current", &self.current.id)
+            .field("previous", &previous)
+            .finish()
+    }
+}
+
+/// Issues and verifies signed action tokens
+#[derive(Debug, Clone)]
+pub struct ActionToken {
+    keys: ActionTokenKeys,
+    redis: Option<Arc<redis::Client>>,
+}
+
+impl ActionToken {
+    pub fn new(keys: ActionTokenKeys) -> Self {
+        Self { keys, redis: None }
+    }
+
+    /// Records consumed and revoked tokens in Redis, enabling
+    /// [`consume_once`](Self::consume_once) and [`revoke`](Self::revoke)
+    pub fn with_redis(mut self, client: Arc<redis::Client>) -> Self {
+        self.redis = Some(client);
+        self
+    }
+
+    /// Token authorizing `action` on `subject` for `ttl`
+    pub fn issue(
+        &self,
+        action: &str,
+        subject: &str,
+        tenant: Option<&str>,
+        ttl: Duration,
+    ) -> String {
+        self.issue_at(action, subject, tenant, ttl, unix_now())
+    }
+
+    /// Like [`issue`](Self::issue), as if issued at Unix timestamp `now`
+    pub fn issue_at(
+        &self,
+        action: &str,
+        subject: &str,
+        tenant: Option<&str>,
+        ttl: Duration,
+        now: i64,
+    ) -> String {
+        let mut nonce = [0u8; NONCE_BYTES];
+        rand::rng().fill_bytes(&mut nonce);
+        let claims = ActionClaims {
+            action: action.to_string(),
+            subject: subject.to_string(),
+            tenant: tenant.map(str::to_string),
+            expires_at: now.saturating_add(i64::try_from(ttl.as_secs()).unwrap_or(i64::MAX)),
+            nonce: hex::encode(nonce),
+        };
+        let payload = serde_json::to_vec(&claims).expect("Claims serialize to JSON");
+
+        let key = &self.keys.current;
+        let signed = format!(
+            "{}.{}.{}",
+            ACTION_TOKEN_VERSION,
+            key.id,
+            BASE64URL.encode(payload)
+        );
+        let signature = mac(&key.secret, &signed).finalize().into_bytes();
+        format!("{}.{}", signed, BASE64URL.encode(signature))
+    }
+
+    /// Claims of `token` if it is authentic, unexpired and issued for `expected_action`
+    ///
+    /// Does not check whether the token was consumed or revoked; see
+    /// [`consume_once`](Self::consume_once).
+    pub fn verify(
+        &self,
+        token: &str,
+        expected_action: &str,
+    ) -> Result<ActionClaims, ActionTokenError> {
+        self.verify_at(token, expected_action, unix_now())
+    }
+
+    /// Like [`verify`](Self::verify), as if verified at Unix timestamp `now`
+    pub fn verify_at(
+        &self,
+        token: &str,
+        expected_action: &str,
+        now: i64,
+    ) -> Result<ActionClaims, ActionTokenError> {
+        let (signed, signature) = token.rsplit_once('.').ok_or(ActionTokenError::Malformed)?;
+        let mut parts = signed.splitn(3, '.');
+        let (Some(version), Some(key_id), Some(payload)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(ActionTokenError::Malformed);
+        };
+        if version != ACTION_TOKEN_VERSION {
+            return Err(ActionTokenError::UnsupportedVersion);
+        }
+
+        let key = self.keys.find(key_id).ok_or(ActionTokenError::UnknownKey)?;
+        let signature = BASE64URL
+            .decode(signature)
+            .map_err(|_| ActionTokenError::Malformed)?;
+        mac(&key.secret, signed)
+            .verify_slice(&signature)
+            .map_err(|_| ActionTokenError::InvalidSignature)?;
+
+        // Only authentic payloads are parsed
+        let payload = BASE64URL
+            .decode(payload)
+            .map_err(|_| ActionTokenError::Malformed)?;
+        let claims: ActionClaims =
+            serde_json::from_slice(&payload).map_err(|_| ActionTokenError::Malformed)?;
+
+        if claims.expires_at <= now {
+            return Err(ActionTokenError::Expired);
+        }
+        if claims.action != expected_action {
+            return Err(ActionTokenError::WrongAction);
+        }
+        Ok(claims)
+    }
+
+    /// Verifies `token` and marks it as used, so every later call fails with
+    /// [`ActionTokenError::AlreadyConsumed`]
+    pub async fn consume_once(
+        &self,
+        token: &str,
+        expected_action: &str,
+    ) -> Result<ActionClaims, ActionTokenError> {
+        let claims = self.verify(token, expected_action)?;
+        if !self.mark_consumed(&claims).await? {
+            return Err(ActionTokenError::AlreadyConsumed);
+        }
+        Ok(claims)
+    }
+
+    /// Puts a token on the deny-list, so it can no longer be consumed
+    pub async fn revoke(&self, claims: &ActionClaims) -> Result<(), ActionTokenError> {
+        self.mark_consumed(claims).await.map(|_| ())
+    }
+
+    /// Records the nonce of `claims` until the token expires
+    ///
+    /// Returns whether the nonce was recorded for the first time.
+    async fn mark_consumed(&self, claims: &ActionClaims) -> Result<bool, ActionTokenError> {
+        let redis = self
+            .redis
+            .as_ref()
+            .ok_or(ActionTokenError::ConsumptionNotConfigured)?;
+        let mut conn = redis.get_async_connection().await?;
+
+        // Kept a minute past the expiry, so clock skew cannot reopen the token
+        let ttl = claims.expires_at.saturating_sub(unix_now()).max(0) + 60;
+        let recorded: Option<String> = redis::cmd("SET")
+            .arg(format!("{}:{}", CONSUMED_KEY_PREFIX, claims.nonce))
+            .arg(&claims.action)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl)
+            .query_async(&mut conn)
+            .await?;
+        Ok(recorded.is_some())
+    }
+}
+
+fn mac(secret: &[u8], signed: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any size");
+    mac.update(signed.as_bytes());
+    mac
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| i64::try_from(elapsed.as_secs()).unwrap_or(i64::MAX))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    fn tokens() -> ActionToken {
+        ActionToken::new(ActionTokenKeys::new("k1", b"first-secret".to_vec()))
+    }
+
+    #[test]
+    fn test_token_roundtrip() {
+        let tokens = tokens();
+        let token = tokens.issue_at("password-reset", "user-1", Some("tenant-1"), HOUR, NOW);
+        assert!(token.starts_with("at1.k1."));
+
+        let claims = tokens
+            .verify_at(&token, "password-reset", NOW + 60)
+            .expect("Token is valid");
+        assert_eq!(claims.action, "password-reset");
+        assert_eq!(claims.subject, "user-1");
+        assert_eq!(claims.tenant.as_deref(), Some("tenant-1"));
+        assert_eq!(claims.expires_at, NOW + 3600);
+        assert_eq!(claims.nonce.len(), NONCE_BYTES * 2);
+
+        // Each token gets its own nonce
+        let other = tokens.issue_at("password-reset", "user-1", Some("tenant-1"), HOUR, NOW);
+        assert_ne!(token, other);
+    }
+
+    #[test]
+    fn test_expired_token_is_rejected() {
+        let tokens = tokens();
+        let token = tokens.issue_at("verify-email", "user-1", None, HOUR, NOW);
+
+        assert!(tokens.verify_at(&token, "verify-email", NOW + 3599).is_ok());
+        assert!(matches!(
+            tokens.verify_at(&token, "verify-email", NOW + 3600),
+            Err(ActionTokenError::Expired)
+        ));
+    }
+
+    #[test]
+    fn test_token_for_other_action_is_rejected() {
+        let tokens = tokens();
+        let token = tokens.issue_at("verify-email", "user-1", None, HOUR, NOW);
+
+        assert!(matches!(
+            tokens.verify_at(&token, "password-reset", NOW),
+            Err(ActionTokenError::WrongAction)
+        ));
+    }
+
+    #[test]
+    fn test_tampered_token_is_rejected() {
+        let tokens = tokens();
+        let token = tokens.issue_at("export-download", "export-1", Some("tenant-1"), HOUR, NOW);
+        let parts: Vec<&str> = token.split('.').collect();
+
+        // Payload of another subject with the original signature
+        let forged = ActionClaims {
+            subject: "export-2".to_string(),
+            ..tokens
+                .verify_at(&token, "export-download", NOW)
+                .expect("Token is valid")
+        };
+        let forged_payload =
+            BASE64URL.encode(serde_json::to_vec(&forged).expect("Claims serialize"));
+        let tampered = format!("{}.{}.{}.{}", parts[0], parts[1], forged_payload, parts[3]);
+        assert!(matches!(
+            tokens.verify_at(&tampered, "export-download", NOW),
+            Err(ActionTokenError::InvalidSignature)
+        ));
+
+        // Signed by someone without the key
+        let other = ActionToken::new(ActionTokenKeys::new("k1", b"guessed-secret".to_vec()));
+        let token = other.issue_at("export-download", "export-1", None, HOUR, NOW);
+        assert!(matches!(
+            tokens.verify_at(&token, "export-download", NOW),
+            Err(ActionTokenError::InvalidSignature)
+        ));
+
+        assert!(matches!(
+            tokens.verify_at("not-a-token", "export-download", NOW),
+            Err(ActionTokenError::Malformed)
+        ));
+        assert!(matches!(
+            tokens.verify_at(&token.replacen("at1", "at2", 1), "export-download", NOW),
+            Err(ActionTokenError::UnsupportedVersion)
+        ));
+    }
+
+    #[test]
+    fn test_key_rotation() {
+        let old = tokens();
+        let token = old.issue_at("invitation", "invite-1", Some("tenant-1"), HOUR, NOW);
+
+        let rotated = ActionToken::new(
+            ActionTokenKeys::new("k2", b"second-secret".to_vec())
+                .with_previous_key("k1", b"first-secret".to_vec()),
+        );
+        assert!(rotated.verify_at(&token, "invitation", NOW).is_ok());
+        let new_token = rotated.issue_at("invitation", "invite-2", Some("tenant-1"), HOUR, NOW);
+        assert!(new_token.starts_with("at1.k2."));
+
+        // Once the old key is dropped, its tokens are no longer accepted
+        let retired = ActionToken::new(ActionTokenKeys::new("k2", b"second-secret".to_vec()));
+        assert!(matches!(
+            retired.verify_at(&token, "invitation", NOW),
+            Err(ActionTokenError::UnknownKey)
+        ));
+        assert!(retired.verify_at(&new_token, "invitation", NOW).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_consume_once_requires_redis() {
+        let tokens = tokens();
+        let token = tokens.issue("invitation", "invite-1", None, HOUR);
+
+        assert!(matches!(
+            tokens.consume_once(&token, "invitation").await,
+            Err(ActionTokenError::ConsumptionNotConfigured)
+        ));
+    }
+}
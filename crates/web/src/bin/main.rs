@@ -1,3 +1,4 @@
+use acci_auth::CookieConfig;
 use acci_web::handlers::AppState;
 use acci_web::routes::create_router;
 use acci_web::services::auth::AuthService;
@@ -18,6 +19,7 @@ async fn main() {
     let app_state = AppState {
         auth_service: AuthService::new(),
         leptos_options: LeptosOptions::new(),
+        cookie_config: CookieConfig::default(),
     };
 
     // Create the router with defined routes
@@ -7,6 +7,9 @@ use serde::{Deserialize, Serialize};
 pub struct LoginForm {
     pub email: String,
     pub password: String,
+    /// "Keep me signed in" checkbox, absent from the form data when unchecked
+    #[serde(default)]
+    pub remember_me: bool,
     pub error: Option<String>,
 }
 
@@ -42,6 +45,15 @@ pub fn login_form_ssr(cx: Scope, _action_path: String, _error: Option<String>) -
                     required
                 />
             </div>
+            <div class="form-group form-check">
+                <input
+                    type="checkbox"
+                    id="remember_me"
+                    name="remember_me"
+                    value="true"
+                />
+                <label for="remember_me">Keep me signed in</label>
+            </div>
 
             {
                 match _error {
@@ -91,6 +103,8 @@ mod tests {
         assert!(test_utils::assert_contains_text(&html, "Password"));
         assert!(test_utils::assert_contains_text(&html, "Sign In"));
         assert!(test_utils::assert_contains_text(&html, "/register"));
+        assert!(test_utils::assert_contains_text(&html, "remember_me"));
+        assert!(test_utils::assert_contains_text(&html, "Keep me signed in"));
     }
 
     #[test]
@@ -1,5 +1,6 @@
 use crate::prelude::*;
 use crate::view;
+use acci_core::i18n::{self, Locale};
 
 /// Komponente zur Anzeige von Fehlermeldungen
 ///
@@ -19,6 +20,31 @@ pub fn error_display_ssr(cx: Scope, _message: String, error_type: Option<String>
     }
 }
 
+/// Fehleranzeige für einen stabilen Fehlercode der API
+///
+/// Die Meldung wird über denselben Katalog wie die API-Antworten lokalisiert.
+/// Fehlt eine Übersetzung, wird die englische Meldung angezeigt; ist der Code
+/// gar nicht im Katalog, die übergebene `fallback`-Meldung.
+///
+/// # Parameter
+/// * `cx` - Der Leptos-Scope
+/// * `code` - Der Fehlercode, z. B. `TENANT_ALREADY_EXISTS`
+/// * `fallback` - Die Meldung für unbekannte Fehlercodes
+/// * `locale` - Die Sprache der Anzeige
+/// * `error_type` - Der Typ des Fehlers (optional, Standard ist "error")
+pub fn localized_error_display_ssr(
+    cx: Scope,
+    code: &str,
+    fallback: String,
+    locale: Locale,
+    error_type: Option<String>,
+) -> impl IntoView {
+    let message = i18n::error_message(code, locale)
+        .map(str::to_string)
+        .unwrap_or(fallback);
+    error_display_ssr(cx, message, error_type)
+}
+
 // Legacy-Funktion um Kompatibilität zu wahren
 #[deprecated(note = "Verwende error_display_ssr stattdessen")]
 pub fn error_display_ssr_legacy(
@@ -65,6 +91,39 @@ mod tests {
         assert!(test_utils::assert_contains_text(&html, &message));
     }
 
+    #[test]
+    fn test_localized_error_display_uses_catalog() {
+        // Given a known and an untranslated error code
+        let html = test_utils::render_to_html(|cx| {
+            localized_error_display_ssr(
+                cx,
+                "TENANT_NOT_FOUND",
+                "Unbekannt".to_string(),
+                Locale::De,
+                None,
+            )
+        });
+        let fallback = test_utils::render_to_html(|cx| {
+            localized_error_display_ssr(
+                cx,
+                "TENANT_LIMIT_EXCEEDED",
+                "Unbekannt".to_string(),
+                Locale::De,
+                None,
+            )
+        });
+
+        // Then the German translation or the English message is shown
+        assert!(test_utils::assert_contains_text(
+            &html,
+            "Mandant nicht gefunden"
+        ));
+        assert!(test_utils::assert_contains_text(
+            &fallback,
+            "Tenant limit exceeded"
+        ));
+    }
+
     #[test]
     fn test_error_display_contains_error_icon() {
         // Given an error message
@@ -1,19 +1,23 @@
 use crate::components::auth::{LoginForm, RegistrationForm};
 use crate::pages::login::LoginQuery;
 use crate::pages::login::render_login_page;
-use crate::services::auth::{AuthError, AuthService, CreateUser, LoginCredentials};
+use crate::services::auth::{AuthError, AuthService, CreateUser, LoginCredentials, Session};
 use crate::services::leptos::LeptosOptions;
+use acci_auth::{CookieConfig, CookieConfigError};
 use axum::{
     extract::{Form, Query, State},
     http::{StatusCode, header},
-    response::{IntoResponse, Redirect},
+    response::{IntoResponse, Redirect, Response},
 };
+use tracing::error;
 
 /// AppState Struktur, die die gemeinsam genutzten Anwendungszustände enthält
 #[derive(Clone)]
 pub struct AppState {
     pub auth_service: AuthService,
     pub leptos_options: LeptosOptions,
+    /// Attribute des Session-Cookies
+    pub cookie_config: CookieConfig,
 }
 
 /// Handler für die Anzeige der Login-Seite
@@ -26,6 +30,39 @@ pub async fn login_page_handler(
     (StatusCode::OK, [(header::CONTENT_TYPE, "text/html")], html)
 }
 
+/// Erzeugt das Session-Cookie für eine neue Sitzung
+///
+/// Persistente Sitzungen ("Angemeldet bleiben") erhalten ein Cookie mit `Max-Age`, das bis
+/// zum Ablauf der Sitzung gültig ist. Alle anderen erhalten ein Session-Cookie ohne `Max-Age`,
+/// das der Browser beim Schließen verwirft.
+fn session_cookie(
+    config: &CookieConfig,
+    session: &Session,
+    now: i64,
+) -> Result<String, CookieConfigError> {
+    let max_age = session.persistent.then(|| session.expires_at - now);
+    config.session_cookie(&session.token, max_age)
+}
+
+/// Antwort, die das Cookie setzt und auf `location` weiterleitet
+fn redirect_with_cookie(location: &str, cookie: Result<String, CookieConfigError>) -> Response {
+    let cookie = match cookie {
+        Ok(cookie) => cookie,
+        Err(e) => {
+            error!(error = %e, "Invalid session cookie configuration");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        },
+    };
+
+    let mut response = Redirect::to(location).into_response();
+    response.headers_mut().insert(
+        header::SET_COOKIE,
+        header::HeaderValue::from_str(&cookie)
+            .expect("Failed to create header value from cookie string"),
+    );
+    response
+}
+
 /// Handler für die Verarbeitung des Login-Formulars
 pub async fn handle_login(
     State(state): State<AppState>,
@@ -34,23 +71,18 @@ pub async fn handle_login(
     let credentials = LoginCredentials {
         email: form.email,
         password: form.password,
+        remember_me: form.remember_me,
     };
 
     match state.auth_service.login(&credentials).await {
         Ok(session) => {
             // Erfolgreicher Login, Cookie setzen und zur Startseite weiterleiten
-            let cookie = format!(
-                "auth_token={}; HttpOnly; Path=/; Max-Age=86400",
-                session.token
-            );
-
-            let mut response = Redirect::to("/").into_response();
-            response.headers_mut().insert(
-                header::SET_COOKIE,
-                header::HeaderValue::from_str(&cookie)
-                    .expect("Failed to create header value from cookie string"),
+            let cookie = session_cookie(
+                &state.cookie_config,
+                &session,
+                chrono::Utc::now().timestamp(),
             );
-            response
+            redirect_with_cookie("/", cookie)
         },
         Err(e) => {
             // Fehler bei der Anmeldung, zurück zur Login-Seite mit Fehlermeldung
@@ -101,15 +133,121 @@ pub async fn handle_registration(
 }
 
 /// Handler für die Verarbeitung des Logout
-pub async fn handle_logout() -> impl IntoResponse {
+pub async fn handle_logout(State(state): State<AppState>) -> impl IntoResponse {
     // Lösche das Session-Cookie und leite zur Login-Seite weiter
-    let cookie = "auth_token=; HttpOnly; Path=/; Max-Age=0";
+    redirect_with_cookie("/login", state.cookie_config.removal_cookie())
+}
 
-    let mut response = Redirect::to("/login").into_response();
-    response.headers_mut().insert(
-        header::SET_COOKIE,
-        header::HeaderValue::from_str(cookie)
-            .expect("Failed to create header value from cookie string"),
-    );
-    response
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::FromRequest;
+
+    fn app_state() -> AppState {
+        AppState {
+            auth_service: AuthService::new(),
+            leptos_options: LeptosOptions {
+                site_root: "target/site".to_string(),
+                site_pkg_dir: "pkg".to_string(),
+                site_name: "ACCI Framework".to_string(),
+                ssr_enabled: true,
+            },
+            cookie_config: CookieConfig::default(),
+        }
+    }
+
+    fn login_form(remember_me: bool) -> LoginForm {
+        LoginForm {
+            email: "demo@example.com".to_string(),
+            password: "password".to_string(),
+            remember_me,
+            error: None,
+        }
+    }
+
+    fn set_cookie(response: &axum::response::Response) -> String {
+        response
+            .headers()
+            .get(header::SET_COOKIE)
+            .expect("login should set a cookie")
+            .to_str()
+            .expect("cookie should be valid ASCII")
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_login_without_remember_me_sets_session_cookie() {
+        let response = handle_login(State(app_state()), Form(login_form(false)))
+            .await
+            .into_response();
+
+        let cookie = set_cookie(&response);
+        assert!(cookie.starts_with("auth_token=demo-token-123"));
+        assert!(cookie.contains("HttpOnly"));
+        assert!(!cookie.contains("Max-Age"));
+    }
+
+    #[tokio::test]
+    async fn test_login_with_remember_me_sets_persistent_cookie() {
+        let response = handle_login(State(app_state()), Form(login_form(true)))
+            .await
+            .into_response();
+
+        let cookie = set_cookie(&response);
+        assert!(cookie.starts_with("auth_token=demo-token-123"));
+        assert!(cookie.contains("HttpOnly"));
+        assert!(cookie.contains("Max-Age="));
+    }
+
+    #[test]
+    fn test_session_cookie_max_age_matches_expiry() {
+        let session = Session {
+            token: "token".to_string(),
+            user_id: "user-1".to_string(),
+            expires_at: 1_000 + 30 * 86400,
+            mfa_status: None,
+            persistent: true,
+        };
+
+        let config = CookieConfig::default();
+        assert_eq!(
+            session_cookie(&config, &session, 1_000),
+            Ok(
+                "auth_token=token; Path=/; Max-Age=2592000; Secure; HttpOnly; SameSite=Lax"
+                    .to_string()
+            )
+        );
+
+        let session = Session {
+            persistent: false,
+            ..session
+        };
+        assert_eq!(
+            session_cookie(&config, &session, 1_000),
+            Ok("auth_token=token; Path=/; Secure; HttpOnly; SameSite=Lax".to_string())
+        );
+    }
+
+    async fn parse_form(body: &'static str) -> LoginForm {
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(axum::body::Body::from(body))
+            .expect("request should build");
+
+        let Form(form) = Form::<LoginForm>::from_request(request, &())
+            .await
+            .expect("form should parse");
+        form
+    }
+
+    #[tokio::test]
+    async fn test_remember_me_checkbox_is_optional_in_form_data() {
+        // Browsers omit unchecked checkboxes from the submitted form data
+        let unchecked = parse_form("email=a%40b.c&password=secret").await;
+        assert!(!unchecked.remember_me);
+
+        let checked = parse_form("email=a%40b.c&password=secret&remember_me=true").await;
+        assert!(checked.remember_me);
+    }
 }
@@ -71,3 +71,190 @@ async fn register_page_handler(
         html,
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::AuthService;
+    use crate::services::leptos::LeptosOptions;
+    use acci_auth::CookieConfig;
+    use axum::body::Body;
+    use axum::http::{Method, Request};
+    use tower::ServiceExt;
+
+    // Templates that declare forms, with the number of forms each is expected to contain.
+    // The SSR view macro is still a placeholder that does not emit markup, so the
+    // forms are read from the template sources instead of the rendered pages.
+    const FORM_TEMPLATES: &[(&str, &str, usize)] = &[
+        ("pages/login.rs", include_str!("pages/login.rs"), 1),
+        ("pages/register.rs", include_str!("pages/register.rs"), 1),
+        ("pages/verify.rs", include_str!("pages/verify.rs"), 2),
+        (
+            "components/layout/navigation.rs",
+            include_str!("components/layout/navigation.rs"),
+            1,
+        ),
+    ];
+
+    // Form components receive their action from the page that embeds them
+    const FORM_COMPONENTS: &[(&str, &str)] = &[
+        (
+            "components/auth/login_form.rs",
+            include_str!("components/auth/login_form.rs"),
+        ),
+        (
+            "components/auth/registration_form.rs",
+            include_str!("components/auth/registration_form.rs"),
+        ),
+        (
+            "components/auth/verification_form.rs",
+            include_str!("components/auth/verification_form.rs"),
+        ),
+    ];
+
+    fn app_state() -> AppState {
+        AppState {
+            auth_service: AuthService::new(),
+            leptos_options: LeptosOptions::new(),
+            cookie_config: CookieConfig::default(),
+        }
+    }
+
+    /// Value of a quoted attribute inside a tag
+    fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+        let start = tag.find(&format!(" {}=\"", name))? + name.len() + 3;
+        let len = tag[start..].find('"')?;
+        Some(&tag[start..start + len])
+    }
+
+    /// Opening tags starting with `prefix`, up to the closing `>`
+    fn tags<'a>(source: &'a str, prefix: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        source.match_indices(prefix).filter_map(move |(start, _)| {
+            let len = source[start..].find('>')?;
+            Some(&source[start..start + len])
+        })
+    }
+
+    /// Method and action of every form declared in a template
+    ///
+    /// Covers literal `<form>` elements and form components embedded with a
+    /// literal `action_path`.
+    fn declared_forms(source: &str) -> Vec<(Method, String)> {
+        let literal_forms = tags(source, "<form ").filter_map(|tag| {
+            let action = attribute(tag, "action")?;
+            let method = attribute(tag, "method").unwrap_or("get");
+            Some((method.to_uppercase(), action.to_string()))
+        });
+        let component_forms = tags(source, "FormSSR").filter_map(|tag| {
+            let action = attribute(tag, "action_path")?;
+            Some(("POST".to_string(), action.to_string()))
+        });
+
+        literal_forms
+            .chain(component_forms)
+            .map(|(method, action)| {
+                let method = Method::from_bytes(method.as_bytes()).expect("valid form method");
+                (method, action)
+            })
+            .collect()
+    }
+
+    /// Whether the router serves `method` on `path`
+    ///
+    /// Requests are resolved in-process. Unknown paths yield 404 and known
+    /// paths with a different method yield 405; anything else means a route
+    /// matched. Form handlers reject the empty body before doing any work.
+    async fn serves(router: &Router, method: Method, path: &str) -> bool {
+        let request = Request::builder()
+            .method(method)
+            .uri(path)
+            .body(Body::empty())
+            .expect("valid request");
+        let response = router
+            .clone()
+            .oneshot(request)
+            .await
+            .expect("router is infallible");
+
+        !matches!(
+            response.status(),
+            StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED
+        )
+    }
+
+    #[test]
+    fn test_declared_forms_parsing() {
+        let source = r#"
+            <form method="post" action="/api/auth/login" class="auth-form">
+            <form action="/search">
+            <form method="post" action={_action_path} class="dynamic">
+            <RegistrationFormSSR
+                action_path="/api/auth/register".to_string()
+                error={_error}
+            />
+        "#;
+
+        assert_eq!(
+            declared_forms(source),
+            vec![
+                (Method::POST, "/api/auth/login".to_string()),
+                (Method::GET, "/search".to_string()),
+                (Method::POST, "/api/auth/register".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_form_components_post() {
+        for (file, source) in FORM_COMPONENTS {
+            let forms: Vec<&str> = tags(source, "<form ").collect();
+            assert!(!forms.is_empty(), "{} declares no form", file);
+            for tag in forms {
+                assert_eq!(
+                    attribute(tag, "method"),
+                    Some("post"),
+                    "{} has a form that does not post: {}",
+                    file,
+                    tag
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_forms_submit_to_served_routes() {
+        let router = create_router(app_state());
+
+        for (file, source, expected) in FORM_TEMPLATES {
+            let forms = declared_forms(source);
+            assert_eq!(
+                forms.len(),
+                *expected,
+                "unexpected number of forms in {}: {:?}",
+                file,
+                forms
+            );
+
+            for (method, action) in forms {
+                assert!(
+                    serves(&router, method.clone(), &action).await,
+                    "{} submits {} {} but the router does not serve it",
+                    file,
+                    method,
+                    action
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_serves_detects_unmounted_routes() {
+        let router = create_router(app_state());
+
+        assert!(serves(&router, Method::POST, "/api/auth/login").await);
+        // Page route without a POST handler
+        assert!(!serves(&router, Method::POST, "/login").await);
+        // Not mounted at all
+        assert!(!serves(&router, Method::POST, "/auth/login").await);
+    }
+}
@@ -38,11 +38,19 @@ pub enum AuthError {
     InternalError(Box<dyn StdError + Send + Sync>),
 }
 
+/// Lifetime of a regular session (1 day)
+const SESSION_LIFETIME_SECS: i64 = 86400;
+/// Lifetime of a "remember me" session (30 days)
+const PERSISTENT_SESSION_LIFETIME_SECS: i64 = 30 * 86400;
+
 /// Login credentials
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct LoginCredentials {
     pub email: String,
     pub password: String,
+    /// Keep the user signed in beyond the browser session
+    #[serde(default)]
+    pub remember_me: bool,
 }
 
 /// User registration data
@@ -59,6 +67,9 @@ pub struct Session {
     pub user_id: String,
     pub expires_at: i64,
     pub mfa_status: Option<MfaStatus>,
+    /// Whether this is a persistent ("remember me") session
+    #[serde(default)]
+    pub persistent: bool,
 }
 
 /// Multi-factor authentication status
@@ -215,11 +226,18 @@ impl AuthService {
         // For demonstration purposes, we simulate a successful login
         // when the email is "demo@example.com" and the password is "password"
         if credentials.email == "demo@example.com" && credentials.password == "password" {
+            let lifetime = if credentials.remember_me {
+                PERSISTENT_SESSION_LIFETIME_SECS
+            } else {
+                SESSION_LIFETIME_SECS
+            };
+
             Ok(Session {
                 token: "demo-token-123".to_string(),
                 user_id: "user-1".to_string(),
-                expires_at: chrono::Utc::now().timestamp() + 86400, // 1 day
+                expires_at: chrono::Utc::now().timestamp() + lifetime,
                 mfa_status: Some(MfaStatus::None),
+                persistent: credentials.remember_me,
             })
         } else {
             Err(AuthError::InvalidCredentials)
@@ -255,6 +273,7 @@ impl AuthService {
                 user_id: "user-1".to_string(),
                 expires_at: chrono::Utc::now().timestamp() + 86400, // 1 day
                 mfa_status: Some(MfaStatus::None),
+                persistent: false,
             })
         } else {
             Err(AuthError::InvalidCredentials)
@@ -311,6 +330,7 @@ impl AuthService {
                 user_id: "user-1".to_string(),
                 expires_at: chrono::Utc::now().timestamp() + 86400, // 1 day
                 mfa_status: Some(status),
+                persistent: false,
             })
         } else {
             Err(AuthError::InvalidCredentials)
@@ -0,0 +1,12 @@
+-- Migration: 20250316001_add_session_mfa_pending
+-- Description: Add PENDING status for sessions awaiting mandatory MFA enrollment
+
+-- Up Migration
+ALTER TYPE session_mfa_status ADD VALUE IF NOT EXISTS 'PENDING';
+
+-- Down Migration
+/*
+-- PostgreSQL does not support removing values from an enum type.
+-- Sessions with PENDING status must be migrated before recreating the type.
+UPDATE sessions SET mfa_status = 'REQUIRED' WHERE mfa_status = 'PENDING';
+*/
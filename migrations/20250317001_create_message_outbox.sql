@@ -0,0 +1,36 @@
+-- Migration: 20250317001_create_message_outbox
+-- Description: Durable queue for outgoing email/SMS messages drained by the message dispatcher
+
+-- Up Migration
+CREATE TABLE IF NOT EXISTS message_outbox (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    seq BIGSERIAL NOT NULL,
+    tenant_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    message_type VARCHAR(20) NOT NULL,
+    recipient TEXT NOT NULL,
+    subject TEXT,
+    body TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'Pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    locked_until TIMESTAMPTZ,
+    last_error TEXT,
+    provider_message_id TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    sent_at TIMESTAMPTZ
+);
+
+-- Dispatcher polls due messages per type in enqueue order
+CREATE INDEX IF NOT EXISTS idx_message_outbox_due
+    ON message_outbox(message_type, next_attempt_at, seq)
+    WHERE status = 'Pending';
+CREATE INDEX IF NOT EXISTS idx_message_outbox_failed
+    ON message_outbox(updated_at DESC)
+    WHERE status = 'Failed';
+
+-- Down Migration
+/*
+DROP TABLE IF EXISTS message_outbox;
+*/
@@ -0,0 +1,74 @@
+-- Migration: 20250318002_add_session_mfa_verified
+-- Description: Audit action for completing the second factor of a session
+
+-- Up Migration
+ALTER TABLE session_audit_log DROP CONSTRAINT IF EXISTS valid_session_action;
+ALTER TABLE session_audit_log ADD CONSTRAINT valid_session_action CHECK (action IN (
+    'SESSION_CREATED',
+    'SESSION_RENEWED',
+    'SESSION_RENEW_ATTEMPT',
+    'SESSION_RENEW_FAILED',
+    'SESSION_EXPIRED',
+    'SESSION_INVALIDATED_BY_ADMIN',
+    'SESSION_INVALIDATED_BY_USER',
+    'SESSION_INVALIDATED_DUE_TO_INACTIVITY',
+    'SESSION_INVALIDATED_PASSWORD_CHANGED',
+    'SESSION_INVALIDATED_SECURITY_BREACH',
+    'SESSION_INVALIDATED_SUSPICIOUS_ACTIVITY',
+    'SESSION_INVALIDATED_SUSPICIOUS_LOCATION',
+    'SESSION_INVALIDATED_CONCURRENT_LIMIT',
+    'SESSION_ACTIVITY',
+    'TOKEN_ROTATION_STARTED',
+    'TOKEN_ROTATION_COMPLETED',
+    'TOKEN_ROTATION_FAILED',
+    'DEVICE_CHANGED',
+    'DEVICE_VERIFICATION_STARTED',
+    'DEVICE_VERIFICATION_COMPLETED',
+    'DEVICE_VERIFICATION_FAILED',
+    'LOCATION_TRACKED',
+    'SUSPICIOUS_LOCATION_DETECTED',
+    'FINGERPRINT_UPDATED',
+    'RISK_ASSESSMENT_PERFORMED',
+    'STEP_UP_AUTHENTICATION_REQUIRED',
+    'CONCURRENT_SESSION_DETECTED',
+    'OTHER_SESSIONS_TERMINATED',
+    'TENANT_SWITCHED',
+    'MFA_VERIFIED'
+));
+
+-- Down Migration
+/*
+DELETE FROM session_audit_log WHERE action = 'MFA_VERIFIED';
+ALTER TABLE session_audit_log DROP CONSTRAINT IF EXISTS valid_session_action;
+ALTER TABLE session_audit_log ADD CONSTRAINT valid_session_action CHECK (action IN (
+    'SESSION_CREATED',
+    'SESSION_RENEWED',
+    'SESSION_RENEW_ATTEMPT',
+    'SESSION_RENEW_FAILED',
+    'SESSION_EXPIRED',
+    'SESSION_INVALIDATED_BY_ADMIN',
+    'SESSION_INVALIDATED_BY_USER',
+    'SESSION_INVALIDATED_DUE_TO_INACTIVITY',
+    'SESSION_INVALIDATED_PASSWORD_CHANGED',
+    'SESSION_INVALIDATED_SECURITY_BREACH',
+    'SESSION_INVALIDATED_SUSPICIOUS_ACTIVITY',
+    'SESSION_INVALIDATED_SUSPICIOUS_LOCATION',
+    'SESSION_INVALIDATED_CONCURRENT_LIMIT',
+    'SESSION_ACTIVITY',
+    'TOKEN_ROTATION_STARTED',
+    'TOKEN_ROTATION_COMPLETED',
+    'TOKEN_ROTATION_FAILED',
+    'DEVICE_CHANGED',
+    'DEVICE_VERIFICATION_STARTED',
+    'DEVICE_VERIFICATION_COMPLETED',
+    'DEVICE_VERIFICATION_FAILED',
+    'LOCATION_TRACKED',
+    'SUSPICIOUS_LOCATION_DETECTED',
+    'FINGERPRINT_UPDATED',
+    'RISK_ASSESSMENT_PERFORMED',
+    'STEP_UP_AUTHENTICATION_REQUIRED',
+    'CONCURRENT_SESSION_DETECTED',
+    'OTHER_SESSIONS_TERMINATED',
+    'TENANT_SWITCHED'
+));
+*/
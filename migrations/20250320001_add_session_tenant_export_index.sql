@@ -0,0 +1,14 @@
+-- Migration: 20250320001_add_session_tenant_export_index
+-- Description: Index for keyset-paginated exports of the sessions of a tenant
+
+-- Up Migration
+CREATE INDEX IF NOT EXISTS idx_sessions_tenant_created ON sessions (
+    (COALESCE(metadata->>'active_tenant_id', metadata->>'tenant_id')),
+    created_at,
+    id
+);
+
+-- Down Migration
+/*
+DROP INDEX IF EXISTS idx_sessions_tenant_created;
+*/
@@ -0,0 +1,21 @@
+-- Migration: 20250321001_create_user_external_identities
+-- Description: External identity provider accounts linked to local users
+
+-- Up Migration
+CREATE TABLE IF NOT EXISTS user_external_identities (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider VARCHAR(64) NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    email VARCHAR(255) NOT NULL,
+    linked_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_login_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE(provider, subject)
+);
+
+CREATE INDEX idx_user_external_identities_user_id ON user_external_identities(user_id);
+
+-- Down Migration
+/*
+DROP TABLE IF EXISTS user_external_identities;
+*/
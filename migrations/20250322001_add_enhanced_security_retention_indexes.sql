@@ -0,0 +1,14 @@
+-- Migration: 20250322001_add_enhanced_security_retention_indexes
+-- Description: Indexes for the retention cleanup of enhanced fingerprints and risk assessments
+
+-- Up Migration
+CREATE INDEX IF NOT EXISTS idx_enhanced_fingerprints_created
+    ON enhanced_session_fingerprints (created_at);
+CREATE INDEX IF NOT EXISTS idx_risk_assessments_created
+    ON session_risk_assessments (created_at);
+
+-- Down Migration
+/*
+DROP INDEX IF EXISTS idx_risk_assessments_created;
+DROP INDEX IF EXISTS idx_enhanced_fingerprints_created;
+*/
@@ -0,0 +1,26 @@
+-- Migration: 20250322003_create_subscription_history
+-- Description: History of tenant subscription plan changes for billing integrations
+
+-- Up Migration
+CREATE TABLE IF NOT EXISTS subscription_history (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL REFERENCES tenants(id) ON DELETE CASCADE,
+    subscription_id UUID NOT NULL REFERENCES tenant_subscriptions(id) ON DELETE CASCADE,
+    change_type VARCHAR(20) NOT NULL CHECK (change_type IN ('CREATED', 'UPDATED', 'DEACTIVATED')),
+    old_plan_type tenant_plan_type,
+    new_plan_type tenant_plan_type NOT NULL,
+    old_max_users INTEGER,
+    new_max_users INTEGER,
+    old_expires_at TIMESTAMPTZ,
+    new_expires_at TIMESTAMPTZ,
+    actor_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_subscription_history_tenant_created
+    ON subscription_history(tenant_id, created_at DESC);
+
+-- Down Migration
+/*
+DROP TABLE IF EXISTS subscription_history;
+*/
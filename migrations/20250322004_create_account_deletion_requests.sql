@@ -0,0 +1,20 @@
+-- Migration: 20250322004_create_account_deletion_requests
+-- Description: Self-service account deletion requests with a grace period
+
+-- Up Migration
+CREATE TABLE IF NOT EXISTS account_deletion_requests (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    scheduled_for TIMESTAMPTZ NOT NULL,
+    ownership_notice_sent_at TIMESTAMPTZ,
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_account_deletion_requests_due
+    ON account_deletion_requests(scheduled_for)
+    WHERE completed_at IS NULL;
+
+-- Down Migration
+/*
+DROP TABLE IF EXISTS account_deletion_requests;
+*/
@@ -0,0 +1,11 @@
+-- Migration: 20250323001_add_verification_codes_stats_index
+-- Description: Index for the per-tenant verification statistics
+
+-- Up Migration
+CREATE INDEX IF NOT EXISTS idx_verification_codes_tenant_created
+    ON verification_codes(tenant_id, created_at);
+
+-- Down Migration
+/*
+DROP INDEX IF EXISTS idx_verification_codes_tenant_created;
+*/
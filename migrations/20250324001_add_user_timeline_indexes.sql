@@ -0,0 +1,17 @@
+-- Migration: 20250324001_add_user_timeline_indexes
+-- Description: Indexes for the keyset-paginated activity timeline of a user
+
+-- Up Migration
+CREATE INDEX IF NOT EXISTS idx_user_audit_log_user_created
+    ON user_audit_log(user_id, created_at, id);
+CREATE INDEX IF NOT EXISTS idx_session_audit_log_user_created
+    ON session_audit_log(user_id, created_at, id);
+CREATE INDEX IF NOT EXISTS idx_verification_codes_user_created
+    ON verification_codes(user_id, created_at, id);
+
+-- Down Migration
+/*
+DROP INDEX IF EXISTS idx_verification_codes_user_created;
+DROP INDEX IF EXISTS idx_session_audit_log_user_created;
+DROP INDEX IF EXISTS idx_user_audit_log_user_created;
+*/
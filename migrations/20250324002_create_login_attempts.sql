@@ -0,0 +1,34 @@
+-- Migration: 20250324002_create_login_attempts
+-- Description: Durable record of login attempts for analytics and compliance
+
+-- Up Migration
+CREATE TABLE IF NOT EXISTS login_attempts (
+    id UUID PRIMARY KEY,
+    -- No foreign key: clients may name tenants that do not exist
+    tenant_id UUID,
+    -- Keyed hash of the normalized username, never the username itself
+    username_hash VARCHAR(64) NOT NULL,
+    ip_address VARCHAR(45),
+    user_agent TEXT,
+    fingerprint_hash VARCHAR(64),
+    country_code VARCHAR(2),
+    city TEXT,
+    risk_level VARCHAR(16),
+    outcome VARCHAR(32) NOT NULL,
+    attempted_at TIMESTAMPTZ NOT NULL,
+    CONSTRAINT valid_login_outcome CHECK (outcome IN (
+        'succeeded', 'invalid_credentials', 'rejected', 'failed'
+    ))
+);
+
+CREATE INDEX IF NOT EXISTS idx_login_attempts_tenant_attempted
+    ON login_attempts(tenant_id, attempted_at);
+CREATE INDEX IF NOT EXISTS idx_login_attempts_username_attempted
+    ON login_attempts(username_hash, attempted_at);
+
+-- Down Migration
+/*
+DROP INDEX IF EXISTS idx_login_attempts_username_attempted;
+DROP INDEX IF EXISTS idx_login_attempts_tenant_attempted;
+DROP TABLE IF EXISTS login_attempts;
+*/
@@ -0,0 +1,12 @@
+-- Migration: 20250325001_add_session_client_binding_reason
+-- Description: Add the invalidation reason of sessions used from another client than they are bound to
+
+-- Up Migration
+ALTER TYPE session_invalidation_reason ADD VALUE IF NOT EXISTS 'CLIENT_BINDING_MISMATCH';
+
+-- Down Migration
+/*
+-- PostgreSQL does not support removing values from an enum type.
+UPDATE sessions SET invalidated_reason = 'SUSPICIOUS_ACTIVITY'
+WHERE invalidated_reason = 'CLIENT_BINDING_MISMATCH';
+*/
@@ -0,0 +1,43 @@
+-- Migration: 20250326001_add_session_lookup_indexes
+-- Description: Indexes that keep session listings and the session cleanup fast
+-- when valid sessions are a small fraction of a large sessions table
+--
+-- Partitioning by month was not chosen: session_audit_log, session_locations
+-- and the other session tables reference sessions(id), and a partitioned
+-- table's primary key has to include the partition key.
+--
+-- Existing deployments with large sessions tables should create the indexes
+-- up front without blocking writes, then deploy; the IF NOT EXISTS clauses
+-- skip them here:
+--
+--   CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_sessions_user_created
+--       ON sessions (user_id, created_at DESC);
+--   CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_sessions_user_valid_created
+--       ON sessions (user_id, created_at DESC) WHERE is_valid = true;
+--   CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_sessions_invalid_activity
+--       ON sessions (last_activity_at) WHERE is_valid = false;
+
+-- Up Migration
+
+-- Listing of all or the invalid sessions of a user, newest first
+CREATE INDEX IF NOT EXISTS idx_sessions_user_created ON sessions (user_id, created_at DESC);
+
+-- Listing of the valid sessions of a user, newest first
+CREATE INDEX IF NOT EXISTS idx_sessions_user_valid_created ON sessions (user_id, created_at DESC)
+    WHERE is_valid = true;
+
+-- Deletion of sessions invalid for longer than the retention period; the
+-- invalidation of expired sessions already uses idx_sessions_expiry
+CREATE INDEX IF NOT EXISTS idx_sessions_invalid_activity ON sessions (last_activity_at)
+    WHERE is_valid = false;
+
+-- Superseded by idx_sessions_user_created
+DROP INDEX IF EXISTS idx_sessions_user;
+
+-- Down Migration
+/*
+CREATE INDEX IF NOT EXISTS idx_sessions_user ON sessions(user_id);
+DROP INDEX IF EXISTS idx_sessions_invalid_activity;
+DROP INDEX IF EXISTS idx_sessions_user_valid_created;
+DROP INDEX IF EXISTS idx_sessions_user_created;
+*/
@@ -0,0 +1,12 @@
+-- Migration: 20250327001_add_session_user_deactivated_reason
+-- Description: Add the invalidation reason of sessions whose user was deactivated or deleted
+
+-- Up Migration
+ALTER TYPE session_invalidation_reason ADD VALUE IF NOT EXISTS 'USER_DEACTIVATED';
+
+-- Down Migration
+/*
+-- PostgreSQL does not support removing values from an enum type.
+UPDATE sessions SET invalidated_reason = 'ADMIN_ACTION'
+WHERE invalidated_reason = 'USER_DEACTIVATED';
+*/
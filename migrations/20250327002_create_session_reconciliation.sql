@@ -0,0 +1,25 @@
+-- Migration: 20250327002_create_session_reconciliation
+-- Description: Password change timestamps and the progress of the job that
+-- revalidates long-lived sessions against the state of their users
+
+-- Up Migration
+
+-- Set when a new password is stored; sessions created before are invalidated
+ALTER TABLE users ADD COLUMN IF NOT EXISTS password_changed_at TIMESTAMPTZ;
+
+-- Keyset scan over the valid sessions in ID order
+CREATE INDEX IF NOT EXISTS idx_sessions_valid_id ON sessions (id) WHERE is_valid = true;
+
+-- Last session ID the reconciliation has checked, so a restart resumes the scan
+CREATE TABLE IF NOT EXISTS session_reconciliation_progress (
+    job VARCHAR(64) PRIMARY KEY,
+    last_session_id UUID,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Down Migration
+/*
+DROP TABLE IF EXISTS session_reconciliation_progress;
+DROP INDEX IF EXISTS idx_sessions_valid_id;
+ALTER TABLE users DROP COLUMN IF EXISTS password_changed_at;
+*/
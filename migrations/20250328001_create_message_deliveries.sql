@@ -0,0 +1,32 @@
+-- Migration: 20250328001_create_message_deliveries
+-- Description: Delivery state of sent email/SMS messages, updated from provider callbacks
+
+-- Up Migration
+CREATE TABLE IF NOT EXISTS message_deliveries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    message_type VARCHAR(20) NOT NULL,
+    recipient TEXT NOT NULL,
+    subject TEXT,
+    body TEXT NOT NULL,
+    provider_message_id TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'Queued',
+    attempt INTEGER NOT NULL DEFAULT 1,
+    retry_of UUID REFERENCES message_deliveries(id) ON DELETE SET NULL,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Provider callbacks look deliveries up by the ID returned at send time
+CREATE UNIQUE INDEX IF NOT EXISTS idx_message_deliveries_provider_message_id
+    ON message_deliveries(provider_message_id);
+CREATE INDEX IF NOT EXISTS idx_message_deliveries_failed
+    ON message_deliveries(updated_at DESC)
+    WHERE status = 'Failed';
+
+-- Down Migration
+/*
+DROP TABLE IF EXISTS message_deliveries;
+*/
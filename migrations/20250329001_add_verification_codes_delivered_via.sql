@@ -0,0 +1,10 @@
+-- Migration: 20250329001_add_verification_codes_delivered_via
+-- Description: Channel a verification code was delivered through after channel fallback
+
+-- Up Migration
+ALTER TABLE verification_codes ADD COLUMN IF NOT EXISTS delivered_via VARCHAR(20);
+
+-- Down Migration
+/*
+ALTER TABLE verification_codes DROP COLUMN IF EXISTS delivered_via;
+*/
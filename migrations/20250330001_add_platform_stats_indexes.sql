@@ -0,0 +1,17 @@
+-- Migration: 20250330001_add_platform_stats_indexes
+-- Description: Indexes for the platform-wide counts of the admin overview
+
+-- Up Migration
+
+-- Users created within the last 30 days
+CREATE INDEX IF NOT EXISTS idx_users_created_at ON users(created_at);
+
+-- Login attempts of all tenants since midnight; idx_login_attempts_tenant_attempted
+-- only serves the counts of one tenant
+CREATE INDEX IF NOT EXISTS idx_login_attempts_attempted ON login_attempts(attempted_at);
+
+-- Down Migration
+/*
+DROP INDEX IF EXISTS idx_login_attempts_attempted;
+DROP INDEX IF EXISTS idx_users_created_at;
+*/
@@ -0,0 +1,15 @@
+-- Migration: 20250331001_restore_user_failed_login_columns
+-- Description: Failed login counters reset by a recorded login; the uncommented
+-- down section of 20240224001 drops them again right after adding them
+
+-- Up Migration
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS failed_login_attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS last_failed_login_at TIMESTAMPTZ;
+
+-- Down Migration
+/*
+ALTER TABLE users
+    DROP COLUMN IF EXISTS failed_login_attempts,
+    DROP COLUMN IF EXISTS last_failed_login_at;
+*/
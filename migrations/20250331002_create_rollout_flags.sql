@@ -0,0 +1,17 @@
+-- Migration: 20250331002_create_rollout_flags
+-- Description: Percentage-based rollouts of risky auth behavior changes
+
+-- Up Migration
+CREATE TABLE IF NOT EXISTS rollout_flags (
+    name VARCHAR(100) PRIMARY KEY,
+    percentage SMALLINT NOT NULL DEFAULT 0,
+    -- Tenants the flag is enabled for regardless of the percentage
+    tenant_allowlist UUID[] NOT NULL DEFAULT '{}',
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT valid_rollout_percentage CHECK (percentage BETWEEN 0 AND 100)
+);
+
+-- Down Migration
+/*
+DROP TABLE IF EXISTS rollout_flags;
+*/
@@ -0,0 +1,20 @@
+-- Migration: 20250401001_add_session_user_agent_details
+-- Description: Browser, operating system and device class parsed from the user
+-- agent when a session is created; older sessions are parsed on access
+
+-- Up Migration
+ALTER TABLE sessions
+    ADD COLUMN IF NOT EXISTS browser TEXT,
+    ADD COLUMN IF NOT EXISTS browser_version TEXT,
+    ADD COLUMN IF NOT EXISTS os TEXT,
+    ADD COLUMN IF NOT EXISTS device_type TEXT
+        CHECK (device_type IN ('desktop', 'mobile', 'tablet', 'bot', 'other'));
+
+-- Down Migration
+/*
+ALTER TABLE sessions
+    DROP COLUMN IF EXISTS browser,
+    DROP COLUMN IF EXISTS browser_version,
+    DROP COLUMN IF EXISTS os,
+    DROP COLUMN IF EXISTS device_type;
+*/
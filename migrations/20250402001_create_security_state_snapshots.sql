@@ -0,0 +1,18 @@
+-- Migration: 20250402001_create_security_state_snapshots
+-- Description: Brute force counters kept across restarts
+
+-- Up Migration
+CREATE TABLE IF NOT EXISTS security_state_snapshots (
+    id BIGSERIAL PRIMARY KEY,
+    -- Serialized SecurityStateSnapshot
+    snapshot BYTEA NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_security_state_snapshots_created_at ON security_state_snapshots(created_at);
+
+-- Down Migration
+/*
+DROP INDEX IF EXISTS idx_security_state_snapshots_created_at;
+DROP TABLE IF EXISTS security_state_snapshots;
+*/
@@ -0,0 +1,14 @@
+-- Migration: 20250403001_add_session_region
+-- Description: Region of the instance that created a session, so sessions can
+-- be terminated and counted per region; older sessions have none
+
+-- Up Migration
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS region VARCHAR(64);
+
+CREATE INDEX IF NOT EXISTS idx_sessions_region ON sessions(region) WHERE is_valid = true;
+
+-- Down Migration
+/*
+DROP INDEX IF EXISTS idx_sessions_region;
+ALTER TABLE sessions DROP COLUMN IF EXISTS region;
+*/
@@ -0,0 +1,33 @@
+-- Migration: 20250404001_create_password_history
+-- Description: Password expiry and the history of replaced password hashes
+
+-- Up Migration
+
+-- Hashes of replaced passwords, checked when a user picks a new one
+CREATE TABLE IF NOT EXISTS password_history (
+    id BIGSERIAL PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    password_hash VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_password_history_user_created ON password_history(user_id, created_at DESC);
+
+-- Password expiry counts from password_changed_at, which was only set on
+-- changes so far. Existing users default to their updated_at, capped at their
+-- oldest valid session: session reconciliation invalidates sessions created
+-- before the password change, and the backfill must not log anyone out.
+-- Accounts without a password ('!') have nothing to expire.
+UPDATE users u
+SET password_changed_at = LEAST(
+    u.updated_at,
+    (SELECT MIN(s.created_at) FROM sessions s WHERE s.user_id = u.id AND s.is_valid = true)
+)
+WHERE u.password_changed_at IS NULL
+  AND u.password_hash <> '!';
+
+-- Down Migration
+/*
+DROP INDEX IF EXISTS idx_password_history_user_created;
+DROP TABLE IF EXISTS password_history;
+*/
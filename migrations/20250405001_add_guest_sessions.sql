@@ -0,0 +1,39 @@
+-- Migration: 20250405001_add_guest_sessions
+-- Description: Guest sessions of visitors not logged in yet, bound to no user
+
+-- Up Migration
+
+-- Guest sessions have no user; every other session keeps its user
+ALTER TABLE sessions ALTER COLUMN user_id DROP NOT NULL;
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS kind VARCHAR(16) NOT NULL DEFAULT 'USER';
+ALTER TABLE sessions ADD CONSTRAINT sessions_kind_check CHECK (kind IN ('USER', 'GUEST'));
+ALTER TABLE sessions ADD CONSTRAINT sessions_kind_user_check CHECK ((kind = 'GUEST') = (user_id IS NULL));
+
+-- Guest sessions are purged after a short inactivity, valid or not
+CREATE INDEX IF NOT EXISTS idx_sessions_guest_activity ON sessions (last_activity_at) WHERE kind = 'GUEST';
+
+-- The session audit log belongs to users, guest sessions are not audited
+DROP TRIGGER IF EXISTS session_audit_logger ON sessions;
+CREATE TRIGGER session_audit_logger
+    AFTER INSERT OR UPDATE ON sessions
+    FOR EACH ROW
+    WHEN (NEW.user_id IS NOT NULL)
+    EXECUTE FUNCTION log_session_change();
+
+ALTER TYPE session_invalidation_reason ADD VALUE IF NOT EXISTS 'GUEST_UPGRADED';
+
+-- Down Migration
+/*
+-- PostgreSQL does not support removing values from an enum type.
+DELETE FROM sessions WHERE kind = 'GUEST';
+DROP TRIGGER IF EXISTS session_audit_logger ON sessions;
+CREATE TRIGGER session_audit_logger
+    AFTER INSERT OR UPDATE ON sessions
+    FOR EACH ROW
+    EXECUTE FUNCTION log_session_change();
+DROP INDEX IF EXISTS idx_sessions_guest_activity;
+ALTER TABLE sessions DROP CONSTRAINT IF EXISTS sessions_kind_user_check;
+ALTER TABLE sessions DROP CONSTRAINT IF EXISTS sessions_kind_check;
+ALTER TABLE sessions DROP COLUMN IF EXISTS kind;
+ALTER TABLE sessions ALTER COLUMN user_id SET NOT NULL;
+*/
@@ -0,0 +1,17 @@
+-- Migration: 20250406001_add_access_revocation
+-- Description: Forced password resets and the lookup of the API keys a user issued
+
+-- Up Migration
+
+-- Set when an administrator revokes the access of a user; the next password
+-- login fails until a new password is set, which clears it
+ALTER TABLE users ADD COLUMN IF NOT EXISTS password_reset_required BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Revoking the access of a user revokes the keys they issued in any tenant
+CREATE INDEX IF NOT EXISTS idx_api_keys_created_by ON api_keys(created_by) WHERE revoked_at IS NULL;
+
+-- Down Migration
+/*
+DROP INDEX IF EXISTS idx_api_keys_created_by;
+ALTER TABLE users DROP COLUMN IF EXISTS password_reset_required;
+*/
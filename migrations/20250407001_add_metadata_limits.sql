@@ -0,0 +1,42 @@
+-- Migration: 20250407001_add_metadata_limits
+-- Description: Backstop against oversized tenant and session metadata
+
+-- Up Migration
+
+-- The configured metadata limits are enforced by the services; this ceiling
+-- stays well above any sensible configuration and only stops writes that
+-- bypass them. Existing rows are not rewritten: the check only applies when
+-- the metadata itself changes, and oversized legacy rows are truncated and
+-- logged by the repositories when read, so they can be cleaned up at leisure.
+CREATE OR REPLACE FUNCTION check_metadata_size()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'UPDATE' AND NEW.metadata IS NOT DISTINCT FROM OLD.metadata THEN
+        RETURN NEW;
+    END IF;
+    IF NEW.metadata IS NOT NULL AND octet_length(NEW.metadata::text) > 1048576 THEN
+        RAISE EXCEPTION 'metadata of % % exceeds 1 MiB', TG_TABLE_NAME, NEW.id
+            USING ERRCODE = 'check_violation';
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS tenants_metadata_size ON tenants;
+CREATE TRIGGER tenants_metadata_size
+    BEFORE INSERT OR UPDATE OF metadata ON tenants
+    FOR EACH ROW
+    EXECUTE FUNCTION check_metadata_size();
+
+DROP TRIGGER IF EXISTS sessions_metadata_size ON sessions;
+CREATE TRIGGER sessions_metadata_size
+    BEFORE INSERT OR UPDATE OF metadata ON sessions
+    FOR EACH ROW
+    EXECUTE FUNCTION check_metadata_size();
+
+-- Down Migration
+/*
+DROP TRIGGER IF EXISTS sessions_metadata_size ON sessions;
+DROP TRIGGER IF EXISTS tenants_metadata_size ON tenants;
+DROP FUNCTION IF EXISTS check_metadata_size();
+*/
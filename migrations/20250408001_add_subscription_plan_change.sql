@@ -0,0 +1,26 @@
+-- Migration: 20250408001_add_subscription_plan_change
+-- Description: Record whether a subscription change upgraded or downgraded the plan
+
+-- Up Migration
+ALTER TABLE subscription_history
+    ADD COLUMN IF NOT EXISTS plan_change VARCHAR(20)
+        CHECK (plan_change IN ('UPGRADE', 'DOWNGRADE', 'LATERAL'));
+
+-- Entries recorded before carry the old and new plan already. Custom plans
+-- have no tier, so changes to or from them are lateral.
+UPDATE subscription_history
+SET plan_change = CASE
+    WHEN old_plan_type = 'CUSTOM' OR new_plan_type = 'CUSTOM' THEN 'LATERAL'
+    WHEN array_position(ARRAY['FREE', 'BASIC', 'PROFESSIONAL', 'ENTERPRISE'], new_plan_type::TEXT)
+       > array_position(ARRAY['FREE', 'BASIC', 'PROFESSIONAL', 'ENTERPRISE'], old_plan_type::TEXT)
+        THEN 'UPGRADE'
+    ELSE 'DOWNGRADE'
+END
+WHERE old_plan_type IS NOT NULL
+  AND old_plan_type <> new_plan_type
+  AND plan_change IS NULL;
+
+-- Down Migration
+/*
+ALTER TABLE subscription_history DROP COLUMN IF EXISTS plan_change;
+*/
@@ -0,0 +1,60 @@
+-- Migration: 20250409001_add_tenant_status
+-- Description: Replace the active flag of tenants with a lifecycle status
+
+-- Up Migration
+ALTER TABLE tenants
+    ADD COLUMN IF NOT EXISTS status VARCHAR(20) NOT NULL DEFAULT 'ACTIVE'
+        CHECK (status IN ('ACTIVE', 'SUSPENDED', 'ARCHIVED')),
+    ADD COLUMN IF NOT EXISTS status_reason TEXT,
+    ADD COLUMN IF NOT EXISTS status_changed_at TIMESTAMPTZ,
+    ADD CONSTRAINT tenants_status_changed_at_check
+        CHECK (status = 'ACTIVE' OR status_changed_at IS NOT NULL);
+
+-- Suspensions were kept in the tenant metadata so far
+UPDATE tenants
+SET status = 'SUSPENDED',
+    status_reason = metadata->'suspension'->>'reason',
+    status_changed_at = COALESCE(
+        (metadata->'suspension'->>'suspended_at')::TIMESTAMPTZ,
+        updated_at
+    ),
+    metadata = metadata - 'suspension'
+WHERE is_active AND metadata ? 'suspension';
+
+-- Deactivated tenants were closed to everyone, as archived tenants are now
+UPDATE tenants
+SET status = 'ARCHIVED',
+    status_changed_at = updated_at
+WHERE NOT is_active;
+
+-- The flag stays readable for compatibility; suspended tenants count as
+-- active, as they did before
+ALTER TABLE tenants DROP COLUMN is_active;
+ALTER TABLE tenants
+    ADD COLUMN is_active BOOLEAN GENERATED ALWAYS AS (status <> 'ARCHIVED') STORED;
+CREATE INDEX IF NOT EXISTS idx_tenants_active ON tenants(is_active) WHERE is_active = true;
+
+-- Sessions of archived tenants are terminated with their own reason
+ALTER TYPE session_invalidation_reason ADD VALUE IF NOT EXISTS 'TENANT_ARCHIVED';
+
+-- Down Migration
+/*
+ALTER TABLE tenants DROP COLUMN is_active;
+ALTER TABLE tenants ADD COLUMN is_active BOOLEAN NOT NULL DEFAULT true;
+UPDATE tenants SET is_active = false WHERE status = 'ARCHIVED';
+UPDATE tenants
+SET metadata = COALESCE(metadata, '{}'::JSONB) || jsonb_build_object(
+    'suspension',
+    jsonb_build_object('reason', status_reason, 'suspended_at', status_changed_at)
+)
+WHERE status = 'SUSPENDED';
+CREATE INDEX IF NOT EXISTS idx_tenants_active ON tenants(is_active) WHERE is_active = true;
+ALTER TABLE tenants
+    DROP CONSTRAINT IF EXISTS tenants_status_changed_at_check,
+    DROP COLUMN IF EXISTS status_changed_at,
+    DROP COLUMN IF EXISTS status_reason,
+    DROP COLUMN IF EXISTS status;
+-- PostgreSQL does not support removing values from an enum type.
+UPDATE sessions SET invalidated_reason = 'ADMIN_ACTION'
+WHERE invalidated_reason = 'TENANT_ARCHIVED';
+*/
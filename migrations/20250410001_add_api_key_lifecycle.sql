@@ -0,0 +1,24 @@
+-- Migration: 20250410001_add_api_key_lifecycle
+-- Description: Descriptions, tags and expiry reminders of API keys
+
+-- Up Migration
+ALTER TABLE api_keys
+    ADD COLUMN IF NOT EXISTS description TEXT,
+    ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS reminded_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_api_keys_tags ON api_keys USING GIN (tags);
+
+-- The reminder job looks for valid keys across all tenants
+CREATE INDEX IF NOT EXISTS idx_api_keys_unrevoked_expires_at ON api_keys(expires_at)
+    WHERE revoked_at IS NULL;
+
+-- Down Migration
+/*
+DROP INDEX IF EXISTS idx_api_keys_unrevoked_expires_at;
+DROP INDEX IF EXISTS idx_api_keys_tags;
+ALTER TABLE api_keys
+    DROP COLUMN IF EXISTS reminded_at,
+    DROP COLUMN IF EXISTS tags,
+    DROP COLUMN IF EXISTS description;
+*/
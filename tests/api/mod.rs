@@ -2,9 +2,6 @@
 //!
 //! This module contains tests for the API.
 
-// Mock modules
-mod mocks;
-
 #[cfg(test)]
 mod src {
     pub mod auth_handler_test;
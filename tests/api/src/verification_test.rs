@@ -16,11 +16,12 @@ use acci_api::{
 use acci_auth::{
     models::{TenantId, UserId, VerificationCode, VerificationConfig, VerificationType},
     repository::TenantAwareContext,
-    services::{session::SessionService, verification::VerificationService},
+    services::{mfa::MfaOrchestrator, session::SessionService, verification::VerificationService},
 };
 
-// Import mocks from our mocks module
-use crate::mocks::{MockMessageProvider, MockSessionRepository, MockVerificationCodeRepository};
+use acci_auth::test_support::{
+    MockMessageProvider, MockSessionRepository, MockVerificationCodeRepository,
+};
 
 // Test the verification endpoints
 #[tokio::test]
@@ -32,7 +33,7 @@ async fn test_verification_endpoints() {
     let verification_repo = Arc::new(MockVerificationCodeRepository::new());
 
     // Create a mock email provider
-    let email_provider = Arc::new(MockMessageProvider::new());
+    let email_provider = Arc::new(MockMessageProvider::new(VerificationType::Email));
 
     // Create a mock session repository
     let session_repo = Arc::new(MockSessionRepository::new());
@@ -55,6 +56,10 @@ async fn test_verification_endpoints() {
     let verification_state = VerificationAppState {
         verification_service: verification_service.clone(),
         session_service: session_service.clone(),
+        mfa_orchestrator: Arc::new(MfaOrchestrator::new(
+            verification_service.clone(),
+            session_repo.clone(),
+        )),
         tenant_context: tenant_context.clone(),
     };
 
@@ -75,6 +80,8 @@ async fn test_verification_endpoints() {
         },
         None, // No tenant state
         Some(verification_state.clone()),
+        None, // No message admin state
+        None, // No WebAuthn state
     );
 
     // Test IDs
@@ -113,7 +120,7 @@ async fn test_verification_endpoints() {
     assert_eq!(body["data"]["verification_type"], json!("email"));
 
     // Get the verification code from the mock repository
-    let codes = verification_repo.get_codes();
+    let codes = verification_repo.codes().clone();
     assert_eq!(codes.len(), 1);
 
     let code = &codes[0].code;
@@ -150,12 +157,10 @@ async fn test_verification_endpoints() {
     assert_eq!(body["data"]["verification_type"], json!("email"));
 
     // Check the verification code status in the repository
-    let codes = verification_repo.get_codes();
+    let codes = verification_repo.codes().clone();
     assert_eq!(codes.len(), 1);
     assert_eq!(
         codes[0].status,
         acci_auth::models::VerificationStatus::Verified
     );
 }
-
-// The mock implementations are now in the mocks module
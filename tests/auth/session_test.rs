@@ -7,10 +7,8 @@ use acci_auth::{
     },
 };
 use sqlx::postgres::PgPoolOptions;
-use std::{
-    sync::Arc,
-    time::{Duration, SystemTime},
-};
+use std::{sync::Arc, time::Duration};
+use time::OffsetDateTime;
 use uuid::Uuid;
 
 async fn create_test_db() -> sqlx::PgPool {
@@ -176,7 +174,7 @@ async fn test_postgres_session_repository() {
     // Test session creation
     let user_id = Uuid::new_v4();
     let token_hash = "test_token_hash".to_string();
-    let expires_at = SystemTime::now() + Duration::from_secs(3600);
+    let expires_at = OffsetDateTime::now_utc() + Duration::from_secs(3600);
     let device_id = Some("test_device".to_string());
 
     let session = repo
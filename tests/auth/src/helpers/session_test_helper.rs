@@ -1,7 +1,8 @@
 use sqlx::PgPool;
-use std::time::{Duration, SystemTime};
+use std::time::Duration;
 use testcontainers::clients::Cli;
 use testcontainers::images::postgres::Postgres;
+use time::OffsetDateTime;
 use uuid::Uuid;
 
 pub struct TestSession {
@@ -38,10 +39,10 @@ pub fn generate_test_session(user_id: Uuid) -> TestSession {
     }
 }
 
-pub fn future_timestamp(seconds: u64) -> SystemTime {
-    SystemTime::now() + Duration::from_secs(seconds)
+pub fn future_timestamp(seconds: u64) -> OffsetDateTime {
+    OffsetDateTime::now_utc() + Duration::from_secs(seconds)
 }
 
-pub fn past_timestamp(seconds: u64) -> SystemTime {
-    SystemTime::now() - Duration::from_secs(seconds)
+pub fn past_timestamp(seconds: u64) -> OffsetDateTime {
+    OffsetDateTime::now_utc() - Duration::from_secs(seconds)
 }
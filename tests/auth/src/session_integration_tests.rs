@@ -29,6 +29,8 @@ async fn test_session_lifecycle() {
             Some("127.0.0.1".to_string()),
             Some("Test User Agent".to_string()),
             None,
+            None,
+            None,
         )
         .await
         .expect("Failed to create session");
@@ -114,6 +116,8 @@ async fn test_concurrent_session_management() {
                 None,
                 None,
                 None,
+                None,
+                None,
             )
             .await
             .expect("Failed to create session");
@@ -178,6 +182,8 @@ async fn test_session_cleanup() {
             None,
             None,
             None,
+            None,
+            None,
         )
         .await
         .expect("Failed to create expired session");
@@ -194,6 +200,8 @@ async fn test_session_cleanup() {
             None,
             None,
             None,
+            None,
+            None,
         )
         .await
         .expect("Failed to create valid session");
@@ -1,5 +1,6 @@
 use std::sync::Arc;
-use std::time::{Duration, SystemTime};
+use std::time::Duration;
+use time::OffsetDateTime;
 use uuid::Uuid;
 
 use acci_auth::{
@@ -9,11 +10,14 @@ use acci_auth::{
         session::SessionService,
         user::{UserService, UserServiceError},
     },
-    session::{Session, types::SessionInvalidationReason},
+    session::{
+        Session,
+        types::{MfaStatus, SessionInvalidationReason},
+    },
     utils::jwt::JwtUtils,
 };
 
-use crate::mocks::{MockSessionRepository, MockUserRepository};
+use acci_auth::test_support::{MockSessionRepository, MockUserRepository};
 
 fn create_test_config() -> Arc<AuthConfig> {
     Arc::new(AuthConfig {
@@ -64,7 +68,7 @@ async fn test_user_registration() {
 #[tokio::test]
 async fn test_login_success() {
     let mut mock_user_repo = MockUserRepository::new();
-    let mut mock_session_repo = MockSessionRepository::new();
+    let mock_session_repo = MockSessionRepository::new();
     let config = create_test_config();
 
     let test_user = create_test_user();
@@ -76,29 +80,6 @@ async fn test_login_success() {
         .with(eq(test_email.clone()))
         .returning(move |_| Ok(Some(test_user.clone())));
 
-    mock_session_repo
-        .expect_create_session()
-        .returning(|user_id, _, _, _, _, _, _, _| {
-            Ok(Session {
-                id: Uuid::new_v4(),
-                user_id,
-                token_hash: "test_hash".to_string(),
-                previous_token_hash: None,
-                token_rotation_at: None,
-                expires_at: SystemTime::now() + Duration::from_secs(3600),
-                created_at: SystemTime::now(),
-                last_activity_at: SystemTime::now(),
-                last_activity_update_at: None,
-                ip_address: None,
-                user_agent: None,
-                device_id: None,
-                device_fingerprint: None,
-                is_valid: true,
-                invalidated_reason: None,
-                metadata: None,
-            })
-        });
-
     let session_service = Arc::new(SessionService::new(
         Arc::new(mock_session_repo),
         config.clone(),
@@ -162,35 +143,30 @@ async fn test_login_invalid_credentials() {
 #[tokio::test]
 async fn test_logout() {
     let mock_user_repo = MockUserRepository::new();
-    let mut mock_session_repo = MockSessionRepository::new();
+    let mock_session_repo = MockSessionRepository::new();
     let config = create_test_config();
 
-    mock_session_repo
-        .expect_get_session_by_token()
-        .returning(|_| {
-            Ok(Some(Session {
-                id: Uuid::new_v4(),
-                user_id: Uuid::new_v4(),
-                token_hash: "test_hash".to_string(),
-                previous_token_hash: None,
-                token_rotation_at: None,
-                expires_at: SystemTime::now() + Duration::from_secs(3600),
-                created_at: SystemTime::now(),
-                last_activity_at: SystemTime::now(),
-                last_activity_update_at: None,
-                ip_address: None,
-                user_agent: None,
-                device_id: None,
-                device_fingerprint: None,
-                is_valid: true,
-                invalidated_reason: None,
-                metadata: None,
-            }))
-        });
-
-    mock_session_repo
-        .expect_invalidate_session()
-        .returning(|_, _| Ok(()));
+    mock_session_repo.insert(Session {
+        id: Uuid::new_v4(),
+        user_id: Uuid::new_v4(),
+        token_hash: "test_hash".to_string(),
+        previous_token_hash: None,
+        token_rotation_at: None,
+        expires_at: OffsetDateTime::now_utc() + Duration::from_secs(3600),
+        created_at: OffsetDateTime::now_utc(),
+        last_activity_at: OffsetDateTime::now_utc(),
+        last_activity_update_at: None,
+        ip_address: None,
+        user_agent: None,
+        user_agent_details: None,
+        region: None,
+        device_id: None,
+        device_fingerprint: None,
+        is_valid: true,
+        invalidated_reason: None,
+        metadata: None,
+        mfa_status: MfaStatus::None,
+    });
 
     let session_service = Arc::new(SessionService::new(
         Arc::new(mock_session_repo),
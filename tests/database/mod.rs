@@ -1,7 +1,6 @@
 use crate::helpers::setup_test_db;
 
 mod migration_test;
-mod session_repository_test;
 
 #[tokio::test]
 async fn test_database_connection() {
@@ -14,6 +14,7 @@ pub mod auth_handler_test {
     use acci_auth::{
         models::user::{User, UserError},
         services::user::{LoginResult, UserServiceError},
+        session::types::MfaStatus,
     };
     use axum::{
         Json,
@@ -158,6 +159,7 @@ pub mod auth_handler_test {
             _device_fingerprint: Option<acci_auth::session::types::DeviceFingerprint>,
             _ip_address: Option<String>,
             _user_agent: Option<String>,
+            _persistent: bool,
         ) -> Result<LoginResult, UserServiceError> {
             if email == self.login_email && password == self.login_password {
                 // Create a new response instead of cloning to avoid the clone issue
@@ -168,6 +170,11 @@ pub mod auth_handler_test {
                         Ok(LoginResult {
                             user,
                             session_token: result.session_token.clone(),
+                            mfa_status: result.mfa_status.clone(),
+                            active_tenant_id: result.active_tenant_id,
+                            memberships: Vec::new(),
+                            pending_deletion: result.pending_deletion,
+                            password_expires_in_days: None,
                         })
                     },
                     Err(_) => Err(UserServiceError::InvalidCredentials),
@@ -269,6 +276,7 @@ pub mod auth_handler_test {
                 None, // device_fingerprint
                 None, // ip_address
                 None, // user_agent
+                request.remember_me,
             )
             .await;
 
@@ -280,36 +288,18 @@ pub mod auth_handler_test {
                     user_id: login_result.user.id.to_string(),
                     expires_at: 0,   // Default for tests
                     tenant_id: None, // No tenant for tests
+                    tenant_selection_required: false,
+                    mfa_required: false,
+                    tenants: Vec::new(),
+                    warning: None,
+                    deletion_scheduled_for: None,
+                    password_expires_in_days: login_result.password_expires_in_days,
                 };
 
                 let api_response = ApiResponse::success(response, request_id);
                 (StatusCode::OK, Json(api_response)).into_response()
             },
-            Err(err) => {
-                // Login error
-                let (status, message, code) = match err {
-                    UserServiceError::InvalidCredentials => (
-                        StatusCode::UNAUTHORIZED,
-                        "Invalid email or password",
-                        "INVALID_CREDENTIALS",
-                    ),
-                    UserServiceError::User(UserError::InactiveUser) => {
-                        (StatusCode::FORBIDDEN, "Account is locked", "ACCOUNT_LOCKED")
-                    },
-                    UserServiceError::User(UserError::UnverifiedUser) => (
-                        StatusCode::FORBIDDEN,
-                        "Account is not verified",
-                        "ACCOUNT_UNVERIFIED",
-                    ),
-                    _ => (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        "An error occurred during login",
-                        "LOGIN_ERROR",
-                    ),
-                };
-
-                ApiError::new(status, message, code, request_id).into_response()
-            },
+            Err(err) => ApiError::from_error(err, request_id).into_response(),
         }
     }
 
@@ -355,23 +345,7 @@ pub mod auth_handler_test {
                 let api_response = ApiResponse::success(response, request_id);
                 (StatusCode::CREATED, Json(api_response)).into_response()
             },
-            Err(err) => {
-                // Registration error
-                let (status, message, code) = match err {
-                    UserServiceError::User(UserError::AlreadyExists) => (
-                        StatusCode::CONFLICT,
-                        "User with this email already exists",
-                        "USER_ALREADY_EXISTS",
-                    ),
-                    _ => (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        "An error occurred during registration",
-                        "REGISTRATION_ERROR",
-                    ),
-                };
-
-                ApiError::new(status, message, code, request_id).into_response()
-            },
+            Err(err) => ApiError::from_error(err, request_id).into_response(),
         }
     }
 
@@ -404,6 +378,11 @@ pub mod auth_handler_test {
             let login_result = LoginResult {
                 user: user.clone(),
                 session_token: "test-session-token".to_string(),
+                mfa_status: MfaStatus::None,
+                active_tenant_id: None,
+                memberships: Vec::new(),
+                pending_deletion: None,
+                password_expires_in_days: None,
             };
 
             let test_user_service = TestUserService::new_login_test(
@@ -418,6 +397,9 @@ pub mod auth_handler_test {
                 email: "test@example.com".to_string(),
                 password: "password123".to_string(),
                 tenant_id: None,
+                remember_me: false,
+                fingerprint: None,
+                trusted_device_token: None,
             };
 
             // Act
@@ -452,6 +434,9 @@ pub mod auth_handler_test {
                 email: "test@example.com".to_string(),
                 password: "password123".to_string(),
                 tenant_id: None,
+                remember_me: false,
+                fingerprint: None,
+                trusted_device_token: None,
             };
 
             // Act
@@ -1,5 +1,80 @@
+use crate::fixtures::UserFixture;
+use crate::helpers::setup_test_db_with_url;
+use acci_auth::{PostgresUserRepository, RepositoryConfig, UserRepository};
+
 #[tokio::test]
-async fn test_user_crud_operations() -> Result<(), acci_auth::models::user::UserError> {
-    // TODO: Implement user CRUD test
-    Ok(())
+async fn test_user_crud_operations() {
+    let Ok((_container, pool, database_url)) = setup_test_db_with_url().await else {
+        eprintln!("Skipping test_user_crud_operations: Docker not available");
+        return;
+    };
+
+    let repo = PostgresUserRepository::new(RepositoryConfig {
+        database_url,
+        ..RepositoryConfig::default()
+    })
+    .await
+    .expect("Failed to create user repository");
+
+    // Create through the repository
+    let user = UserFixture::new().build();
+    repo.create(&user).await.expect("Failed to create user");
+
+    let found = repo
+        .find_by_email(&user.email)
+        .await
+        .expect("Failed to find user by email")
+        .expect("User not found by email");
+    assert_eq!(found.id, user.id);
+    assert!(!found.is_verified);
+
+    // Read a user persisted directly by the fixture
+    let persisted = UserFixture::new()
+        .verified()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist fixture user");
+    let found = repo
+        .find_by_id(persisted.id)
+        .await
+        .expect("Failed to find user by id")
+        .expect("User not found by id");
+    assert_eq!(found.email, persisted.email);
+    assert!(found.is_verified);
+
+    // Update
+    let mut updated = user.clone();
+    updated.is_verified = true;
+    repo.update(&updated).await.expect("Failed to update user");
+    let found = repo
+        .find_by_id(user.id)
+        .await
+        .expect("Failed to find user by id")
+        .expect("User not found by id");
+    assert!(found.is_verified);
+
+    // Deactivate and activate
+    repo.deactivate(user.id)
+        .await
+        .expect("Failed to deactivate user");
+    let found = repo
+        .find_by_id(user.id)
+        .await
+        .expect("lookup")
+        .expect("user");
+    assert!(!found.is_active);
+    repo.activate(user.id)
+        .await
+        .expect("Failed to activate user");
+    let found = repo
+        .find_by_id(user.id)
+        .await
+        .expect("lookup")
+        .expect("user");
+    assert!(found.is_active);
+
+    // Delete
+    repo.delete(user.id).await.expect("Failed to delete user");
+    let found = repo.find_by_id(user.id).await.expect("lookup");
+    assert!(found.is_none());
 }
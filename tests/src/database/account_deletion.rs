@@ -0,0 +1,88 @@
+use crate::fixtures::UserFixture;
+use crate::helpers::setup_test_db;
+use acci_auth::{
+    AccountDeletionRepository, AccountDeletionRequest, PostgresAccountDeletionRepository,
+    RepositoryError,
+};
+use time::{Duration, OffsetDateTime};
+
+#[tokio::test]
+async fn test_account_deletion_request_lifecycle() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!("Skipping test_account_deletion_request_lifecycle: Docker not available");
+        return;
+    };
+    let repo = PostgresAccountDeletionRepository::new(pool.clone());
+    let early = UserFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist user");
+    let late = UserFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist user");
+
+    // Timestamps are truncated to what PostgreSQL stores
+    let now = OffsetDateTime::from_unix_timestamp(OffsetDateTime::now_utc().unix_timestamp())
+        .expect("valid timestamp");
+    let early_request = AccountDeletionRequest::new(early.id, now, now + Duration::days(1));
+    let late_request = AccountDeletionRequest::new(late.id, now, now + Duration::days(14));
+    for request in [&late_request, &early_request] {
+        repo.create(request)
+            .await
+            .expect("Failed to create deletion request");
+    }
+    assert!(matches!(
+        repo.create(&early_request).await,
+        Err(RepositoryError::UniqueViolation(_))
+    ));
+    assert_eq!(
+        repo.find_pending(early.id)
+            .await
+            .expect("Failed to find deletion request"),
+        Some(early_request.clone())
+    );
+
+    let due = repo
+        .list_due(now + Duration::days(30), 10)
+        .await
+        .expect("Failed to list due requests");
+    assert_eq!(due, vec![early_request.clone(), late_request.clone()]);
+    assert_eq!(
+        repo.list_due(now + Duration::days(2), 10)
+            .await
+            .expect("Failed to list due requests"),
+        vec![early_request.clone()]
+    );
+
+    repo.record_ownership_notice(early.id, now)
+        .await
+        .expect("Failed to record ownership notice");
+    repo.complete(early.id, now)
+        .await
+        .expect("Failed to complete deletion request");
+    assert!(
+        repo.find_pending(early.id)
+            .await
+            .expect("Failed to find deletion request")
+            .is_none()
+    );
+    assert!(
+        !repo
+            .cancel(early.id)
+            .await
+            .expect("Failed to cancel deletion request")
+    );
+
+    assert!(
+        repo.cancel(late.id)
+            .await
+            .expect("Failed to cancel deletion request")
+    );
+    assert!(
+        repo.list_due(now + Duration::days(30), 10)
+            .await
+            .expect("Failed to list due requests")
+            .is_empty()
+    );
+}
@@ -0,0 +1,77 @@
+use crate::helpers::setup_test_db;
+use acci_core::AdvisoryLock;
+
+#[tokio::test]
+async fn test_advisory_lock_is_exclusive() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!("Skipping test_advisory_lock_is_exclusive: Docker not available");
+        return;
+    };
+    let lock = AdvisoryLock::named(pool.clone(), "acci.test_job");
+    let other_instance = AdvisoryLock::named(pool.clone(), "acci.test_job");
+
+    let guard = lock
+        .try_acquire()
+        .await
+        .expect("Failed to try the lock")
+        .expect("Free lock is acquired");
+    assert!(
+        other_instance
+            .try_acquire()
+            .await
+            .expect("Failed to try the lock")
+            .is_none(),
+        "Held lock cannot be acquired by another session"
+    );
+    let skipped = other_instance
+        .with_lock(|| async { "ran" })
+        .await
+        .expect("Failed to try the lock");
+    assert_eq!(skipped, None);
+
+    // Other keys are independent
+    AdvisoryLock::named(pool.clone(), "acci.other_job")
+        .try_acquire()
+        .await
+        .expect("Failed to try the lock")
+        .expect("Lock of another key is acquired")
+        .release()
+        .await
+        .expect("Failed to release the lock");
+
+    guard.release().await.expect("Failed to release the lock");
+    let ran = other_instance
+        .with_lock(|| async { "ran" })
+        .await
+        .expect("Failed to take the lock");
+    assert_eq!(ran, Some("ran"));
+}
+
+#[tokio::test]
+async fn test_panicking_job_releases_the_lock() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!("Skipping test_panicking_job_releases_the_lock: Docker not available");
+        return;
+    };
+    let lock = AdvisoryLock::named(pool.clone(), "acci.panicking_job");
+
+    let job_lock = lock.clone();
+    let job =
+        tokio::spawn(async move { job_lock.with_lock(|| async { panic!("job failed") }).await });
+    assert!(job.await.is_err(), "Job panicked");
+
+    // The dropped guard closes its connection, which ends the session holding the lock
+    let mut acquired = None;
+    for _ in 0..50 {
+        acquired = lock.try_acquire().await.expect("Failed to try the lock");
+        if acquired.is_some() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    acquired
+        .expect("Lock is released after the panic")
+        .release()
+        .await
+        .expect("Failed to release the lock");
+}
@@ -0,0 +1,120 @@
+use crate::fixtures::TenantFixture;
+use crate::helpers::setup_test_db;
+use acci_auth::{ApiKeyError, ApiKeyQuery, ApiKeyService, IssueApiKey, PostgresApiKeyRepository};
+use std::sync::Arc;
+use time::Duration;
+
+#[tokio::test]
+async fn test_api_key_lifecycle() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!("Skipping test_api_key_lifecycle: Docker not available");
+        return;
+    };
+    let service = ApiKeyService::new(Arc::new(PostgresApiKeyRepository::new(pool.clone())));
+    let tenant = TenantFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist tenant");
+
+    let issued = service
+        .issue(
+            tenant.id,
+            IssueApiKey {
+                name: "Reporting".to_string(),
+                description: Some("Nightly user report".to_string()),
+                tags: vec!["Reporting".to_string(), "nightly".to_string()],
+                scopes: vec!["users:read".to_string(), "sessions:export".to_string()],
+                expires_in: Some(Duration::days(90)),
+                created_by: None,
+            },
+        )
+        .await
+        .expect("Failed to issue API key");
+
+    let stored_hash: String = sqlx::query_scalar("SELECT key_hash FROM api_keys WHERE id = $1")
+        .bind(issued.api_key.id)
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to load key hash");
+    assert_ne!(stored_hash, issued.key);
+
+    let api_key = service
+        .authenticate(&issued.key)
+        .await
+        .expect("Failed to authenticate API key");
+    assert_eq!(api_key.tenant_id, tenant.id);
+    assert_eq!(api_key.scopes, vec!["sessions:export", "users:read"]);
+
+    let keys = service
+        .list(tenant.id, ApiKeyQuery::default())
+        .await
+        .expect("Failed to list keys");
+    assert_eq!(keys.len(), 1);
+    assert!(keys[0].last_used_at.is_some());
+    assert_eq!(keys[0].tags, vec!["nightly", "reporting"]);
+    let tagged = service
+        .list(
+            tenant.id,
+            ApiKeyQuery {
+                tags: vec!["reporting".to_string()],
+                search: Some("NIGHTLY USER".to_string()),
+                ..ApiKeyQuery::default()
+            },
+        )
+        .await
+        .expect("Failed to list keys");
+    assert_eq!(tagged.len(), 1);
+    let untagged = service
+        .list(
+            tenant.id,
+            ApiKeyQuery {
+                tags: vec!["billing".to_string()],
+                ..ApiKeyQuery::default()
+            },
+        )
+        .await
+        .expect("Failed to list keys");
+    assert!(untagged.is_empty());
+
+    // The replaced key stays valid for the rotation overlap
+    let rotated = service
+        .rotate(tenant.id, issued.api_key.id)
+        .await
+        .expect("Failed to rotate API key");
+    assert!(rotated.previous_key_expires_at.is_some());
+    service
+        .authenticate(&issued.key)
+        .await
+        .expect("Failed to authenticate replaced API key");
+    let expiring = service
+        .list(
+            tenant.id,
+            ApiKeyQuery {
+                expiring_within: Some(Duration::days(2)),
+                ..ApiKeyQuery::default()
+            },
+        )
+        .await
+        .expect("Failed to list keys");
+    assert_eq!(expiring.len(), 1);
+    assert_eq!(expiring[0].id, issued.api_key.id);
+
+    service
+        .revoke(tenant.id, rotated.issued.api_key.id)
+        .await
+        .expect("Failed to revoke API key");
+    assert!(matches!(
+        service.authenticate(&rotated.issued.key).await,
+        Err(ApiKeyError::InvalidKey)
+    ));
+    assert_eq!(
+        service
+            .list(tenant.id, ApiKeyQuery::default())
+            .await
+            .expect("Failed to list keys")
+            .iter()
+            .filter(|key| key.is_revoked())
+            .count(),
+        1
+    );
+}
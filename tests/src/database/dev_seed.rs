@@ -0,0 +1,178 @@
+use crate::fixtures::TenantFixture;
+use crate::helpers::setup_test_db_with_url;
+use acci_auth::utils::password::verify_password;
+use acci_auth::{
+    AuthConfig, DevSeedError, DevSeedOptions, DevSeeder, RepositoryConfig, SeededRole,
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+fn small_seed(seed: u64) -> DevSeedOptions {
+    DevSeedOptions {
+        seed,
+        tenants: 2,
+        members_per_tenant: 3,
+        force: false,
+    }
+}
+
+async fn connect(database_url: String) -> DevSeeder {
+    DevSeeder::connect(
+        RepositoryConfig {
+            database_url,
+            ..RepositoryConfig::default()
+        },
+        Arc::new(AuthConfig::default()),
+    )
+    .await
+    .expect("Failed to connect the seeder")
+}
+
+/// Number of rows returned by `query`, which selects the rows violating a reference
+async fn orphans(pool: &PgPool, query: &str) -> i64 {
+    sqlx::query_scalar(query)
+        .fetch_one(pool)
+        .await
+        .unwrap_or_else(|e| panic!("Failed to run {}: {}", query, e))
+}
+
+#[tokio::test]
+async fn test_seeded_data_is_referentially_intact() {
+    let Ok((_container, pool, database_url)) = setup_test_db_with_url().await else {
+        eprintln!("Skipping test_seeded_data_is_referentially_intact: Docker not available");
+        return;
+    };
+    let seeder = connect(database_url).await;
+
+    let summary = seeder.run(&small_seed(7)).await.expect("Seeding failed");
+    assert_eq!(summary.tenants.len(), 2);
+    assert_eq!(summary.users().count(), 8);
+    assert_eq!(summary.counts.active_sessions, 8);
+    assert_eq!(summary.counts.pending_codes, 2);
+    assert_eq!(summary.counts.verified_codes, 2);
+    assert_eq!(summary.counts.expired_codes, 2);
+    assert_eq!(summary.counts.invalidated_codes, 2);
+    assert!(summary.counts.fingerprints >= 2);
+    assert_eq!(summary.counts.risk_assessments, summary.counts.fingerprints);
+
+    for tenant in &summary.tenants {
+        let admins: Vec<Uuid> = sqlx::query_scalar(
+            "SELECT user_id FROM tenant_users WHERE tenant_id = $1 AND tenant_role = 'ADMIN'",
+        )
+        .bind(tenant.id)
+        .fetch_all(&pool)
+        .await
+        .expect("Failed to load admins");
+        assert_eq!(admins, vec![tenant.users[0].id]);
+        assert_eq!(tenant.users[0].role, SeededRole::Admin);
+
+        let subscriptions: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM tenant_subscriptions WHERE tenant_id = $1 AND is_active",
+        )
+        .bind(tenant.id)
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to count subscriptions");
+        assert_eq!(subscriptions, 1);
+    }
+
+    // The printed credentials sign in
+    for user in summary.users() {
+        let hash: String = sqlx::query_scalar("SELECT password_hash FROM users WHERE id = $1")
+            .bind(user.id)
+            .fetch_one(&pool)
+            .await
+            .expect("Seeded user exists");
+        assert!(verify_password(&user.password, &hash).expect("Valid hash"));
+    }
+
+    for query in [
+        "SELECT COUNT(*) FROM tenant_users m \
+         WHERE NOT EXISTS (SELECT 1 FROM tenants t WHERE t.id = m.tenant_id) \
+            OR NOT EXISTS (SELECT 1 FROM users u WHERE u.id = m.user_id)",
+        "SELECT COUNT(*) FROM sessions s \
+         WHERE NOT EXISTS (SELECT 1 FROM users u WHERE u.id = s.user_id)",
+        "SELECT COUNT(*) FROM verification_codes c \
+         WHERE NOT EXISTS (SELECT 1 FROM tenant_users m \
+                           WHERE m.tenant_id = c.tenant_id AND m.user_id = c.user_id)",
+        "SELECT COUNT(*) FROM fingerprints f \
+         WHERE NOT EXISTS (SELECT 1 FROM tenant_users m \
+                           WHERE m.tenant_id = f.tenant_id AND m.user_id = f.user_id) \
+            OR NOT EXISTS (SELECT 1 FROM sessions s \
+                           WHERE s.id = f.session_id AND s.user_id = f.user_id)",
+        "SELECT COUNT(*) FROM session_risk_assessments a \
+         WHERE NOT EXISTS (SELECT 1 FROM sessions s \
+                           WHERE s.id = a.session_id AND s.user_id = a.user_id)",
+    ] {
+        assert_eq!(orphans(&pool, query).await, 0, "{}", query);
+    }
+
+    let (active, expired): (i64, i64) = sqlx::query_as(
+        "SELECT COUNT(*) FILTER (WHERE expires_at > NOW()), \
+                COUNT(*) FILTER (WHERE expires_at <= NOW()) \
+         FROM sessions",
+    )
+    .fetch_one(&pool)
+    .await
+    .expect("Failed to count sessions");
+    assert_eq!(active as usize, summary.counts.active_sessions);
+    assert_eq!(expired as usize, summary.counts.expired_sessions);
+
+    // Seeding again is allowed and repeats the credentials under free subdomains
+    let again = seeder.run(&small_seed(7)).await.expect("Reseeding failed");
+    for (first, second) in summary.tenants.iter().zip(&again.tenants) {
+        assert_eq!(first.name, second.name);
+        assert_ne!(first.subdomain, second.subdomain);
+        let passwords = |tenant: &acci_auth::SeededTenant| {
+            tenant
+                .users
+                .iter()
+                .map(|user| user.password.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(passwords(first), passwords(second));
+    }
+}
+
+#[tokio::test]
+async fn test_seeding_refuses_production_looking_data_unless_forced() {
+    let Ok((_container, pool, database_url)) = setup_test_db_with_url().await else {
+        eprintln!(
+            "Skipping test_seeding_refuses_production_looking_data_unless_forced: Docker not available"
+        );
+        return;
+    };
+    TenantFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist tenant");
+    let seeder = connect(database_url).await;
+
+    let refused = seeder.run(&small_seed(1)).await;
+    assert!(
+        matches!(
+            refused,
+            Err(DevSeedError::ProductionData {
+                tenants: 1,
+                users: 0
+            })
+        ),
+        "Expected the seeder to refuse, got {:?}",
+        refused
+    );
+    let tenants: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tenants")
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to count tenants");
+    assert_eq!(tenants, 1);
+
+    let forced = seeder
+        .run(&DevSeedOptions {
+            force: true,
+            ..small_seed(1)
+        })
+        .await
+        .expect("Forced seeding failed");
+    assert_eq!(forced.tenants.len(), 2);
+}
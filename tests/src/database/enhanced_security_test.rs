@@ -0,0 +1,299 @@
+use crate::fixtures::{SessionFixture, UserFixture};
+use crate::helpers::setup_test_db;
+use acci_auth::{
+    EnhancedFingerprintRepository, EnhancedSessionFingerprint,
+    PostgresEnhancedFingerprintRepository, PostgresRiskAssessmentRepository,
+    PostgresSessionLocationRepository, RiskAssessmentRepository, Session, SessionLocation,
+    SessionLocationRepository, SessionRiskAssessment, session::enhanced_security::RiskLevel,
+};
+use sqlx::PgPool;
+use std::net::IpAddr;
+use std::time::Duration;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+async fn persist_session(pool: &PgPool) -> Session {
+    let user = UserFixture::new()
+        .persist(pool)
+        .await
+        .expect("Failed to persist user");
+    SessionFixture::for_user(user.id)
+        .persist(pool)
+        .await
+        .expect("Failed to persist session")
+}
+
+async fn index_names(pool: &PgPool, table: &str) -> Vec<String> {
+    sqlx::query_scalar(
+        "SELECT c.relname::TEXT FROM pg_catalog.pg_index i \
+         JOIN pg_catalog.pg_class c ON c.oid = i.indexrelid \
+         JOIN pg_catalog.pg_class t ON t.oid = i.indrelid \
+         WHERE t.relname = $1",
+    )
+    .bind(table)
+    .fetch_all(pool)
+    .await
+    .expect("Failed to list indexes")
+}
+
+#[tokio::test]
+async fn test_session_location_lifecycle() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!("Skipping test_session_location_lifecycle: Docker not available");
+        return;
+    };
+    let repo = PostgresSessionLocationRepository::new(pool.clone());
+    let session = persist_session(&pool).await;
+
+    let ipv4: IpAddr = "203.0.113.7".parse().expect("Valid IPv4 address");
+    let ipv6: IpAddr = "2001:db8::42".parse().expect("Valid IPv6 address");
+    let mut first = SessionLocation::new(session.id, session.user_id, ipv4, "DE", 52.52, 13.405);
+    first.recorded_at -= Duration::from_secs(60);
+    first.city = Some("Berlin".to_string());
+    first.region_code = Some("BE".to_string());
+    first.timezone = Some("Europe/Berlin".to_string());
+    let mut second =
+        SessionLocation::new(session.id, session.user_id, ipv6, "US", 40.7128, -74.006);
+    second.is_suspicious = true;
+
+    repo.save_location(&first)
+        .await
+        .expect("Failed to save IPv4 location");
+    repo.save_location(&second)
+        .await
+        .expect("Failed to save IPv6 location");
+
+    let locations = repo
+        .get_locations_by_session_id(session.id)
+        .await
+        .expect("Failed to list session locations");
+    assert_eq!(locations, vec![first.clone(), second.clone()]);
+
+    let recent = repo
+        .get_recent_locations_by_user_id(session.user_id, 1)
+        .await
+        .expect("Failed to list recent locations");
+    assert_eq!(recent, vec![second.clone()]);
+
+    // The insert trigger records every location in the session audit log
+    let audited: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM session_audit_log WHERE session_id = $1 AND action = $2",
+    )
+    .bind(session.id)
+    .bind("LOCATION_TRACKED")
+    .fetch_one(&pool)
+    .await
+    .expect("Failed to count audit entries");
+    assert_eq!(audited, 2);
+
+    let deleted = repo
+        .delete_old_locations(second.recorded_at)
+        .await
+        .expect("Failed to delete old locations");
+    assert_eq!(deleted, 1);
+    let locations = repo
+        .get_locations_by_session_id(session.id)
+        .await
+        .expect("Failed to list session locations");
+    assert_eq!(locations, vec![second]);
+}
+
+#[tokio::test]
+async fn test_enhanced_fingerprint_lifecycle() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!("Skipping test_enhanced_fingerprint_lifecycle: Docker not available");
+        return;
+    };
+    let repo = PostgresEnhancedFingerprintRepository::new(pool.clone());
+    let session = persist_session(&pool).await;
+
+    assert!(
+        repo.get_fingerprint_by_session_id(session.id)
+            .await
+            .expect("Failed to get fingerprint")
+            .is_none()
+    );
+
+    let mut fingerprint = EnhancedSessionFingerprint::new(
+        session.id,
+        session.user_id,
+        "Mozilla/5.0 (X11; Linux x86_64) Gecko/20100101 Firefox/124.0",
+    );
+    fingerprint.browser = Some("Firefox".to_string());
+    fingerprint.browser_version = Some("124.0".to_string());
+    fingerprint.os = Some("Linux".to_string());
+    fingerprint.canvas_fingerprint = Some("canvas-hash".to_string());
+    repo.save_fingerprint(&fingerprint)
+        .await
+        .expect("Failed to save fingerprint");
+
+    let stored = repo
+        .get_fingerprint_by_session_id(session.id)
+        .await
+        .expect("Failed to get fingerprint")
+        .expect("Fingerprint not found");
+    assert_eq!(stored, fingerprint);
+
+    // Saving again updates the stored fingerprint in place
+    fingerprint.device_type = Some("desktop".to_string());
+    fingerprint.webgl_fingerprint = Some("webgl-hash".to_string());
+    fingerprint.updated_at += Duration::from_secs(5);
+    repo.save_fingerprint(&fingerprint)
+        .await
+        .expect("Failed to update fingerprint");
+
+    let fingerprints = repo
+        .get_fingerprints_by_user_id(session.user_id, 10)
+        .await
+        .expect("Failed to list fingerprints");
+    assert_eq!(fingerprints, vec![fingerprint.clone()]);
+
+    let deleted = repo
+        .delete_old_fingerprints(fingerprint.created_at - DAY)
+        .await
+        .expect("Failed to delete old fingerprints");
+    assert_eq!(deleted, 0);
+    let deleted = repo
+        .delete_old_fingerprints(fingerprint.created_at + Duration::from_secs(1))
+        .await
+        .expect("Failed to delete old fingerprints");
+    assert_eq!(deleted, 1);
+    assert!(
+        repo.get_fingerprint_by_session_id(session.id)
+            .await
+            .expect("Failed to get fingerprint")
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn test_risk_assessment_lifecycle() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!("Skipping test_risk_assessment_lifecycle: Docker not available");
+        return;
+    };
+    let repo = PostgresRiskAssessmentRepository::new(pool.clone());
+    let session = persist_session(&pool).await;
+    let other_session = persist_session(&pool).await;
+
+    let mut old = SessionRiskAssessment::new(
+        session.id,
+        session.user_id,
+        0.9,
+        vec!["new_country".to_string()],
+    );
+    old.created_at -= 2 * DAY;
+    // Scores are stored with four decimal places
+    let mut latest = SessionRiskAssessment::new(
+        session.id,
+        session.user_id,
+        0.812_345,
+        vec!["new_country".to_string(), "impossible_travel".to_string()],
+    );
+    latest.is_flagged = true;
+    latest.action_taken = Some("STEP_UP".to_string());
+    let low = SessionRiskAssessment::new(other_session.id, other_session.user_id, 0.1, vec![]);
+    for assessment in [&old, &latest, &low] {
+        repo.save_assessment(assessment)
+            .await
+            .expect("Failed to save assessment");
+    }
+    assert_eq!(latest.risk_score, 0.8123);
+    assert_eq!(latest.risk_level(), RiskLevel::Critical);
+
+    let stored = repo
+        .get_assessment_by_session_id(session.id)
+        .await
+        .expect("Failed to get assessment")
+        .expect("Assessment not found");
+    assert_eq!(stored, latest);
+
+    let since = OffsetDateTime::now_utc() - 3 * DAY;
+    let critical = repo
+        .get_assessments_by_risk_level(RiskLevel::Critical, since, 10)
+        .await
+        .expect("Failed to list critical assessments");
+    assert_eq!(critical, vec![latest.clone(), old.clone()]);
+    let low_assessments = repo
+        .get_assessments_by_risk_level(RiskLevel::Low, since, 10)
+        .await
+        .expect("Failed to list low assessments");
+    assert_eq!(low_assessments, vec![low]);
+    let recent_critical = repo
+        .get_assessments_by_risk_level(RiskLevel::Critical, OffsetDateTime::now_utc() - DAY, 10)
+        .await
+        .expect("Failed to list critical assessments");
+    assert_eq!(recent_critical, vec![latest.clone()]);
+    assert!(
+        repo.get_assessments_by_risk_level(RiskLevel::Medium, since, 10)
+            .await
+            .expect("Failed to list medium assessments")
+            .is_empty()
+    );
+
+    let deleted = repo
+        .delete_old_assessments(OffsetDateTime::now_utc() - DAY)
+        .await
+        .expect("Failed to delete old assessments");
+    assert_eq!(deleted, 1);
+    let critical = repo
+        .get_assessments_by_risk_level(RiskLevel::Critical, since, 10)
+        .await
+        .expect("Failed to list critical assessments");
+    assert_eq!(critical, vec![latest]);
+    assert!(
+        repo.get_assessment_by_session_id(Uuid::new_v4())
+            .await
+            .expect("Failed to get assessment")
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn test_enhanced_security_indexes_exist() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!("Skipping test_enhanced_security_indexes_exist: Docker not available");
+        return;
+    };
+
+    let expected: &[(&str, &[&str])] = &[
+        (
+            "session_locations",
+            &[
+                "idx_session_locations_session",
+                "idx_session_locations_user",
+                "idx_session_locations_recorded",
+            ],
+        ),
+        (
+            "enhanced_session_fingerprints",
+            &[
+                "idx_enhanced_fingerprints_session",
+                "idx_enhanced_fingerprints_user",
+                "idx_enhanced_fingerprints_created",
+            ],
+        ),
+        (
+            "session_risk_assessments",
+            &[
+                "idx_risk_assessments_session",
+                "idx_risk_assessments_score",
+                "idx_risk_assessments_created",
+            ],
+        ),
+    ];
+
+    for (table, indexes) in expected {
+        let existing = index_names(&pool, table).await;
+        for index in *indexes {
+            assert!(
+                existing.iter().any(|name| name == index),
+                "Index {} on {} is missing",
+                index,
+                table
+            );
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use acci_auth::security::{FingerprintRepository, PostgresFingerprintRepository};
+use acci_auth::utils::batch::BatchConfig;
+use chrono::{Duration as ChronoDuration, Utc};
+use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::helpers::setup_test_db;
+
+async fn insert_fingerprints(pool: &PgPool, tenant_id: Uuid, count: i32, age_days: i32) {
+    sqlx::query(
+        "INSERT INTO fingerprints (tenant_id, user_id, fingerprint, first_seen, last_seen, last_ip) \
+         SELECT $1, gen_random_uuid(), '{}'::jsonb, \
+                CURRENT_TIMESTAMP - make_interval(days => $3), \
+                CURRENT_TIMESTAMP - make_interval(days => $3), \
+                '10.0.0.1'::inet \
+         FROM generate_series(1, $2)",
+    )
+    .bind(tenant_id)
+    .bind(count)
+    .bind(age_days)
+    .execute(pool)
+    .await
+    .expect("Failed to insert fingerprints");
+}
+
+async fn count_fingerprints(pool: &PgPool, tenant_id: Uuid) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM fingerprints WHERE tenant_id = $1")
+        .bind(tenant_id)
+        .fetch_one(pool)
+        .await
+        .expect("Failed to count fingerprints")
+}
+
+#[tokio::test]
+async fn test_delete_old_fingerprints_in_batches() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!("Skipping test_delete_old_fingerprints_in_batches: Docker not available");
+        return;
+    };
+    let repo = PostgresFingerprintRepository::new(pool.clone()).with_cleanup_batch(BatchConfig {
+        batch_size: 100,
+        delay: Duration::from_millis(1),
+    });
+
+    let tenant_id = Uuid::new_v4();
+    let other_tenant_id = Uuid::new_v4();
+    insert_fingerprints(&pool, tenant_id, 1000, 60).await;
+    insert_fingerprints(&pool, tenant_id, 5, 1).await;
+    insert_fingerprints(&pool, other_tenant_id, 10, 60).await;
+
+    let deleted = repo
+        .delete_old_fingerprints(tenant_id, Utc::now() - ChronoDuration::days(30))
+        .await
+        .expect("Failed to delete fingerprints");
+
+    assert_eq!(deleted, 1000);
+    // Recent fingerprints and other tenants are untouched
+    assert_eq!(count_fingerprints(&pool, tenant_id).await, 5);
+    assert_eq!(count_fingerprints(&pool, other_tenant_id).await, 10);
+}
@@ -1,3 +1,36 @@
+#[cfg(test)]
+mod account_deletion;
+#[cfg(test)]
+mod advisory_lock;
+#[cfg(test)]
+mod api_key_repository;
+#[cfg(test)]
+mod dev_seed;
+#[cfg(test)]
+mod enhanced_security_test;
+#[cfg(test)]
+mod fingerprint_repository;
+#[cfg(test)]
+mod operation_timeout;
+#[cfg(test)]
+mod password_history;
+#[cfg(test)]
+mod platform_stats;
+#[cfg(test)]
+mod security_snapshot;
+#[cfg(test)]
+mod session_repository;
+#[cfg(test)]
+mod subscription_history;
+#[cfg(test)]
+mod tenant_membership;
+#[cfg(test)]
+mod tenant_update;
+#[cfg(test)]
+mod user_login;
+#[cfg(test)]
+mod verification_repository;
+
 #[tokio::test]
 async fn test_database_connection() {
     // TODO: Implement database connection test
@@ -0,0 +1,88 @@
+use crate::helpers::setup_test_db_with_url;
+use acci_auth::OperationTimeouts;
+use acci_auth::repository::timeout::{TimeoutOrDatabase, execute_within};
+use acci_auth::utils::duration::DurationMs;
+use sqlx::postgres::PgPoolOptions;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_timed_out_query_returns_a_healthy_connection() {
+    let Ok((_container, _pool, database_url)) = setup_test_db_with_url().await else {
+        eprintln!(
+            "Skipping test_timed_out_query_returns_a_healthy_connection: Docker not available"
+        );
+        return;
+    };
+    // A single connection, so the follow-up query has to reuse it
+    let pool = OperationTimeouts::default()
+        .apply_to(PgPoolOptions::new())
+        .max_connections(1)
+        .connect(&database_url)
+        .await
+        .expect("Failed to connect");
+
+    let result = execute_within(
+        "test.pg_sleep",
+        Duration::from_millis(200),
+        &pool,
+        "SELECT pg_sleep(5)",
+    )
+    .await;
+    let Err(TimeoutOrDatabase::Timeout(err)) = result else {
+        panic!("Expected the query to time out, got {:?}", result);
+    };
+    assert_eq!(err.operation, "test.pg_sleep");
+    assert_eq!(err.budget, Duration::from_millis(200));
+
+    let one: i32 = sqlx::query_scalar("SELECT 1")
+        .fetch_one(&pool)
+        .await
+        .expect("Connection is usable after the timeout");
+    assert_eq!(one, 1);
+    let statement_timeout: String = sqlx::query_scalar("SHOW statement_timeout")
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to read statement_timeout");
+    assert_eq!(statement_timeout, "10s");
+}
+
+#[tokio::test]
+async fn test_statement_timeout_cancels_runaway_queries() {
+    let Ok((_container, _pool, database_url)) = setup_test_db_with_url().await else {
+        eprintln!("Skipping test_statement_timeout_cancels_runaway_queries: Docker not available");
+        return;
+    };
+    let timeouts = OperationTimeouts {
+        read: DurationMs::from_millis(200),
+        write: DurationMs::from_millis(200),
+        ..OperationTimeouts::default()
+    };
+    let pool = timeouts
+        .apply_to(PgPoolOptions::new())
+        .max_connections(1)
+        .connect(&database_url)
+        .await
+        .expect("Failed to connect");
+
+    // The client-side budget is larger, so the server cancels the statement
+    let result = execute_within(
+        "test.pg_sleep",
+        timeouts.long_running,
+        &pool,
+        "SELECT pg_sleep(5)",
+    )
+    .await;
+    match result {
+        Err(TimeoutOrDatabase::Database(sqlx::Error::Database(err))) => {
+            // query_canceled
+            assert_eq!(err.code().as_deref(), Some("57014"));
+        },
+        result => panic!("Expected the server to cancel the query, got {:?}", result),
+    }
+
+    let one: i32 = sqlx::query_scalar("SELECT 1")
+        .fetch_one(&pool)
+        .await
+        .expect("Connection is usable after the cancellation");
+    assert_eq!(one, 1);
+}
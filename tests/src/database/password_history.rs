@@ -0,0 +1,90 @@
+use crate::helpers::setup_test_db_with_url;
+use acci_auth::{PostgresUserRepository, RepositoryConfig, User, UserRepository};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn test_password_history_keeps_newest_hashes() {
+    let Ok((_container, _pool, database_url)) = setup_test_db_with_url().await else {
+        eprintln!("Skipping test_password_history_keeps_newest_hashes: Docker not available");
+        return;
+    };
+    let repo = PostgresUserRepository::new(RepositoryConfig {
+        database_url,
+        ..RepositoryConfig::default()
+    })
+    .await
+    .expect("Failed to connect user repository");
+    let user = User::new("history@example.com".to_string(), "hash-0".to_string());
+    repo.create(&user).await.expect("Failed to create user");
+
+    // Registration counts as the first password change
+    let states = repo
+        .session_states(&[user.id])
+        .await
+        .expect("Failed to read session state");
+    assert_eq!(
+        states[&user.id]
+            .password_changed_at
+            .map(|at| at.unix_timestamp()),
+        Some(user.created_at.unix_timestamp())
+    );
+
+    for hash in ["hash-1", "hash-2", "hash-3"] {
+        repo.record_password_history(user.id, hash, 2)
+            .await
+            .expect("Failed to record password");
+    }
+
+    let history = repo
+        .password_history(user.id, 5)
+        .await
+        .expect("Failed to read history");
+    assert_eq!(history, ["hash-3", "hash-2"]);
+    let history = repo
+        .password_history(user.id, 1)
+        .await
+        .expect("Failed to read history");
+    assert_eq!(history, ["hash-3"]);
+}
+
+#[tokio::test]
+async fn test_password_reset_requirement_ends_with_password_change() {
+    let Ok((_container, _pool, database_url)) = setup_test_db_with_url().await else {
+        eprintln!(
+            "Skipping test_password_reset_requirement_ends_with_password_change: Docker not available"
+        );
+        return;
+    };
+    let repo = PostgresUserRepository::new(RepositoryConfig {
+        database_url,
+        ..RepositoryConfig::default()
+    })
+    .await
+    .expect("Failed to connect user repository");
+    let mut user = User::new("reset@example.com".to_string(), "hash-0".to_string());
+    repo.create(&user).await.expect("Failed to create user");
+    let user_id = user.id;
+
+    assert!(!reset_required(&repo, user_id).await);
+    assert!(repo.require_password_reset(user_id).await.unwrap());
+    // Requiring it again changes nothing
+    assert!(!repo.require_password_reset(user_id).await.unwrap());
+    assert!(reset_required(&repo, user_id).await);
+
+    // Other changes keep the requirement, a new password ends it
+    user.is_verified = true;
+    repo.update(&user).await.expect("Failed to update user");
+    assert!(reset_required(&repo, user_id).await);
+    user.password_hash = "hash-1".to_string();
+    repo.update(&user).await.expect("Failed to update user");
+    assert!(!reset_required(&repo, user_id).await);
+
+    assert!(repo.require_password_reset(Uuid::new_v4()).await.is_err());
+}
+
+async fn reset_required(repo: &PostgresUserRepository, user_id: Uuid) -> bool {
+    repo.session_states(&[user_id])
+        .await
+        .expect("Failed to read session state")[&user_id]
+        .password_reset_required
+}
@@ -0,0 +1,178 @@
+use crate::fixtures::{SessionFixture, TenantFixture, UserFixture};
+use crate::helpers::setup_test_db;
+use acci_auth::{
+    LoginAttemptRecord, LoginAttemptRepository, LoginAttemptStats, LoginOutcome,
+    PlatformStatsRepository, PostgresLoginAttemptRepository, PostgresPlatformStatsRepository,
+    SessionInvalidationReason, TenantCounts, UserSignupCounts,
+};
+use serde_json::json;
+use sqlx::PgPool;
+use time::{Duration, OffsetDateTime, Time};
+use uuid::Uuid;
+
+/// Move the creation of `user_id` `days` into the past
+async fn backdate_user(pool: &PgPool, user_id: Uuid, days: i32) {
+    sqlx::query(
+        "UPDATE users SET created_at = created_at - make_interval(days => $2) WHERE id = $1",
+    )
+    .bind(user_id)
+    .bind(days)
+    .execute(pool)
+    .await
+    .expect("Failed to backdate user");
+}
+
+fn login_attempt(
+    tenant_id: Option<Uuid>,
+    outcome: LoginOutcome,
+    attempted_at: OffsetDateTime,
+) -> LoginAttemptRecord {
+    LoginAttemptRecord {
+        id: Uuid::new_v4(),
+        tenant_id,
+        username_hash: "0".repeat(64),
+        ip_address: Some("192.0.2.1".to_string()),
+        user_agent: None,
+        fingerprint_hash: None,
+        country_code: None,
+        city: None,
+        risk_level: None,
+        outcome,
+        attempted_at,
+    }
+}
+
+#[tokio::test]
+async fn test_platform_stats_aggregates() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!("Skipping test_platform_stats_aggregates: Docker not available");
+        return;
+    };
+    let repo = PostgresPlatformStatsRepository::new(pool.clone());
+    let now = OffsetDateTime::now_utc();
+
+    let acme = TenantFixture::new()
+        .with_name("Acme")
+        .persist(&pool)
+        .await
+        .expect("Failed to persist tenant");
+    let globex = TenantFixture::new()
+        .with_name("Globex")
+        .persist(&pool)
+        .await
+        .expect("Failed to persist tenant");
+    let initech = TenantFixture::new()
+        .with_name("Initech")
+        .archived()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist tenant");
+    assert_eq!(
+        repo.tenant_counts().await.expect("Failed to count tenants"),
+        TenantCounts {
+            total: 3,
+            active: 2
+        }
+    );
+
+    let mut users = Vec::new();
+    for days in [0, 10, 40] {
+        let user = UserFixture::new()
+            .persist(&pool)
+            .await
+            .expect("Failed to persist user");
+        backdate_user(&pool, user.id, days).await;
+        users.push(user);
+    }
+    assert_eq!(
+        repo.user_signups(now)
+            .await
+            .expect("Failed to count signups"),
+        UserSignupCounts {
+            last_7_days: 1,
+            last_30_days: 2
+        }
+    );
+
+    let user_id = users[0].id;
+    let sessions = [
+        SessionFixture::for_user(user_id).with_metadata(json!({ "tenant_id": acme.id })),
+        SessionFixture::for_user(user_id).with_metadata(json!({ "tenant_id": acme.id })),
+        // Counted for the tenant the session switched to
+        SessionFixture::for_user(user_id).with_metadata(json!({
+            "tenant_id": initech.id,
+            "active_tenant_id": globex.id,
+        })),
+        // Counted in the total, but for no tenant
+        SessionFixture::for_user(user_id),
+        SessionFixture::expired()
+            .with_user(user_id)
+            .with_metadata(json!({ "tenant_id": globex.id })),
+        SessionFixture::for_user(user_id)
+            .with_metadata(json!({ "tenant_id": globex.id }))
+            .invalidated(SessionInvalidationReason::UserLogout),
+    ];
+    for session in &sessions {
+        session
+            .persist(&pool)
+            .await
+            .expect("Failed to persist session");
+    }
+    assert_eq!(
+        repo.active_sessions(now)
+            .await
+            .expect("Failed to count sessions"),
+        4
+    );
+
+    let top = repo
+        .top_tenants_by_sessions(now, 5)
+        .await
+        .expect("Failed to list top tenants");
+    let top: Vec<_> = top
+        .iter()
+        .map(|tenant| {
+            (
+                tenant.tenant_id,
+                tenant.name.as_str(),
+                tenant.active_sessions,
+            )
+        })
+        .collect();
+    assert_eq!(top, vec![(acme.id, "Acme", 2), (globex.id, "Globex", 1)]);
+    let top = repo
+        .top_tenants_by_sessions(now, 1)
+        .await
+        .expect("Failed to list top tenants");
+    assert_eq!(top.len(), 1);
+    assert_eq!(top[0].tenant_id, acme.id);
+
+    let attempts = PostgresLoginAttemptRepository::new(pool.clone());
+    let midnight = now.replace_time(Time::MIDNIGHT);
+    for attempt in [
+        login_attempt(Some(acme.id), LoginOutcome::Succeeded, now),
+        login_attempt(Some(globex.id), LoginOutcome::Succeeded, now),
+        // Attempts for no known tenant count as well
+        login_attempt(None, LoginOutcome::InvalidCredentials, now),
+        login_attempt(
+            Some(acme.id),
+            LoginOutcome::InvalidCredentials,
+            midnight - Duration::hours(1),
+        ),
+    ] {
+        attempts
+            .record(&attempt)
+            .await
+            .expect("Failed to record attempt");
+    }
+    assert_eq!(
+        repo.login_stats(midnight)
+            .await
+            .expect("Failed to count logins"),
+        LoginAttemptStats {
+            total: 3,
+            succeeded: 2,
+            failed: 1
+        }
+    );
+}
@@ -0,0 +1,71 @@
+use acci_auth::security::{
+    BruteForceConfig, PostgresSnapshotStore, SecurityConfig, SecurityProtection,
+};
+use std::sync::Arc;
+
+use crate::helpers::setup_test_db;
+
+fn memory_protection() -> SecurityProtection {
+    // The memory backend never contacts Redis
+    let redis_client =
+        Arc::new(redis::Client::open("redis://127.0.0.1:1").expect("valid Redis URL"));
+    SecurityProtection::in_memory(
+        redis_client,
+        None,
+        SecurityConfig {
+            brute_force: BruteForceConfig {
+                max_attempts: 3,
+                ..BruteForceConfig::default()
+            },
+            ..SecurityConfig::default()
+        },
+    )
+}
+
+#[tokio::test]
+async fn test_lockout_is_restored_from_postgres() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!("Skipping test_lockout_is_restored_from_postgres: Docker not available");
+        return;
+    };
+    let store = PostgresSnapshotStore::new(pool);
+
+    // Nothing stored yet
+    let restarted = memory_protection();
+    restarted.restore_state(&store).await;
+
+    let protection = memory_protection();
+    for ip in ["192.0.2.1", "192.0.2.2", "192.0.2.3"] {
+        protection
+            .brute_force
+            .record_login_attempt("tenant", "alice", ip, false)
+            .await
+            .expect("Failed to record attempt");
+    }
+    protection.persist_state(&store).await;
+
+    let restarted = memory_protection();
+    restarted.restore_state(&store).await;
+    assert!(
+        restarted
+            .brute_force
+            .is_account_locked("tenant", "alice")
+            .await
+            .expect("locked")
+    );
+
+    // A corrupt snapshot is ignored
+    store
+        .save(b"not a snapshot", acci_auth::DurationSecs::from_secs(3600))
+        .await
+        .expect("Failed to store snapshot");
+    let restarted = memory_protection();
+    restarted.restore_state(&store).await;
+    assert!(
+        !restarted
+            .brute_force
+            .is_account_locked("tenant", "alice")
+            .await
+            .expect("locked")
+    );
+}
@@ -0,0 +1,866 @@
+use crate::fixtures::{SessionFixture, TestTenantWorld, UserFixture, rng};
+use crate::helpers::setup_test_db;
+use acci_auth::{
+    ACTIVE_TENANT_METADATA_KEY, DeviceType, MfaStatus, PlatformStatsRepository,
+    PostgresPlatformStatsRepository, PostgresSessionLocationRepository, RegionSessionCount,
+    SessionExportFilter, SessionFilter, SessionInvalidationReason, SessionKind, SessionLocation,
+    SessionLocationRepository, SessionRepository, UserAgentDetails,
+    session::{
+        DELETE_INVALID_SESSIONS_QUERY, INVALIDATE_EXPIRED_SESSIONS_QUERY,
+        PostgresSessionRepository, SessionRepositoryConfig, USER_SESSIONS_QUERY,
+        VALID_USER_SESSIONS_QUERY,
+    },
+    utils::batch::BatchConfig,
+};
+use acci_core::datetime;
+use futures::TryStreamExt;
+use rand::Rng;
+use serde_json::json;
+use std::collections::HashSet;
+use std::time::Duration;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn test_session_crud_operations() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!("Skipping test_session_crud_operations: Docker not available");
+        return;
+    };
+    let repo = PostgresSessionRepository::new(pool.clone());
+    let user = UserFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist user");
+
+    let template = SessionFixture::for_user(user.id).build();
+    let session = repo
+        .create_session(
+            user.id,
+            template.token_hash.clone(),
+            template.expires_at,
+            template.device_id.clone(),
+            None,
+            template.ip_address.clone(),
+            template.user_agent.clone(),
+            None,
+            None,
+            Some(json!({ "source": "fixture" })),
+        )
+        .await
+        .expect("Failed to create session");
+    assert!(session.is_valid);
+    assert_eq!(session.mfa_status, MfaStatus::None);
+
+    let found = repo
+        .get_session_by_token(&template.token_hash)
+        .await
+        .expect("Failed to find session by token")
+        .expect("Session not found by token");
+    assert_eq!(found.id, session.id);
+    assert_eq!(found.user_id, user.id);
+    assert_eq!(found.ip_address, template.ip_address);
+
+    let sessions = repo
+        .get_user_sessions(user.id, SessionFilter::All)
+        .await
+        .expect("Failed to list sessions");
+    assert_eq!(sessions.len(), 1);
+
+    repo.update_mfa_status(session.id, MfaStatus::Verified)
+        .await
+        .expect("Failed to update MFA status");
+    let found = repo
+        .get_session(session.id)
+        .await
+        .expect("Failed to get session")
+        .expect("Session not found");
+    assert_eq!(found.mfa_status, MfaStatus::Verified);
+
+    repo.invalidate_session(session.id, SessionInvalidationReason::UserLogout)
+        .await
+        .expect("Failed to invalidate session");
+    let found = repo
+        .get_session(session.id)
+        .await
+        .expect("Failed to get session")
+        .expect("Session not found");
+    assert!(!found.is_valid);
+    assert_eq!(
+        found.invalidated_reason,
+        Some(SessionInvalidationReason::UserLogout)
+    );
+}
+
+#[tokio::test]
+async fn test_session_expiration() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!("Skipping test_session_expiration: Docker not available");
+        return;
+    };
+    let repo = PostgresSessionRepository::new(pool.clone());
+    let user = UserFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist user");
+
+    let expired = SessionFixture::expired()
+        .with_user(user.id)
+        .persist(&pool)
+        .await
+        .expect("Failed to persist expired session");
+    let active = SessionFixture::for_user(user.id)
+        .persist(&pool)
+        .await
+        .expect("Failed to persist active session");
+    assert!(expired.expires_at < OffsetDateTime::now_utc());
+
+    let cleaned = repo
+        .cleanup_expired_sessions()
+        .await
+        .expect("Failed to clean up sessions");
+    assert_eq!(cleaned, 1);
+
+    let expired = repo
+        .get_session(expired.id)
+        .await
+        .expect("Failed to get session")
+        .expect("Expired session is kept for the retention period");
+    assert!(!expired.is_valid);
+    assert_eq!(
+        expired.invalidated_reason,
+        Some(SessionInvalidationReason::TokenExpired)
+    );
+
+    let active_sessions = repo
+        .get_user_sessions(user.id, SessionFilter::Active)
+        .await
+        .expect("Failed to list sessions");
+    assert_eq!(active_sessions.len(), 1);
+    assert_eq!(active_sessions[0].id, active.id);
+}
+
+#[tokio::test]
+async fn test_invalidate_all_user_sessions() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!("Skipping test_invalidate_all_user_sessions: Docker not available");
+        return;
+    };
+    let repo = PostgresSessionRepository::new(pool.clone());
+    let world = TestTenantWorld::create(&pool, 2)
+        .await
+        .expect("Failed to create tenant world");
+    let target = &world.users[0];
+
+    // Give the target a second device
+    SessionFixture::for_user(target.id)
+        .expires_in(Duration::from_secs(30 * 60))
+        .persist(&pool)
+        .await
+        .expect("Failed to persist session");
+
+    let invalidated = repo
+        .invalidate_all_user_sessions(target.id, SessionInvalidationReason::PasswordChanged)
+        .await
+        .expect("Failed to invalidate sessions");
+    assert_eq!(invalidated, 2);
+
+    let remaining = repo
+        .get_user_sessions(target.id, SessionFilter::Active)
+        .await
+        .expect("Failed to list sessions");
+    assert!(remaining.is_empty());
+
+    // Other users in the tenant are untouched
+    for user in std::iter::once(&world.admin).chain(&world.users[1..]) {
+        let sessions = repo
+            .get_user_sessions(user.id, SessionFilter::Active)
+            .await
+            .expect("Failed to list sessions");
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id, world.sessions_for(user.id)[0].id);
+    }
+}
+
+#[tokio::test]
+async fn test_cleanup_deletes_invalid_sessions_in_batches() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!(
+            "Skipping test_cleanup_deletes_invalid_sessions_in_batches: Docker not available"
+        );
+        return;
+    };
+    let repo = PostgresSessionRepository::with_config(
+        pool.clone(),
+        SessionRepositoryConfig {
+            invalid_session_retention: Duration::from_secs(60),
+            cleanup_batch: BatchConfig {
+                batch_size: 100,
+                delay: Duration::ZERO,
+            },
+            ..SessionRepositoryConfig::default()
+        },
+    );
+    let user = UserFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist user");
+
+    for _ in 0..250 {
+        SessionFixture::expired()
+            .with_user(user.id)
+            .invalidated(SessionInvalidationReason::TokenExpired)
+            .persist(&pool)
+            .await
+            .expect("Failed to persist session");
+    }
+    let active = SessionFixture::for_user(user.id)
+        .persist(&pool)
+        .await
+        .expect("Failed to persist active session");
+
+    let cleaned = repo
+        .cleanup_expired_sessions()
+        .await
+        .expect("Failed to clean up sessions");
+    assert_eq!(cleaned, 250);
+
+    let remaining = repo
+        .get_user_sessions(user.id, SessionFilter::All)
+        .await
+        .expect("Failed to list sessions");
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].id, active.id);
+}
+
+#[tokio::test]
+async fn test_guest_sessions_are_stored_without_user_and_purged_early() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!(
+            "Skipping test_guest_sessions_are_stored_without_user_and_purged_early: \
+             Docker not available"
+        );
+        return;
+    };
+    let repo = PostgresSessionRepository::with_config(
+        pool.clone(),
+        SessionRepositoryConfig {
+            guest_session_retention: Duration::from_secs(60 * 60),
+            ..SessionRepositoryConfig::default()
+        },
+    );
+    let user = UserFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist user");
+    let user_session = SessionFixture::for_user(user.id)
+        .persist(&pool)
+        .await
+        .expect("Failed to persist session");
+
+    let mut guests = Vec::new();
+    for _ in 0..2 {
+        let template = SessionFixture::new().build();
+        let guest = repo
+            .create_session(
+                Uuid::nil(),
+                template.token_hash.clone(),
+                template.expires_at,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .expect("Failed to create guest session");
+        guests.push((guest.id, template.token_hash));
+    }
+    let (abandoned_id, abandoned_token) = &guests[0];
+    let (active_id, _) = &guests[1];
+
+    let found = repo
+        .get_session_by_token(abandoned_token)
+        .await
+        .expect("Failed to find session by token")
+        .expect("Guest session not found by token");
+    assert_eq!(found.kind, SessionKind::Guest);
+    assert!(found.user_id.is_nil());
+    repo.merge_session_metadata(*abandoned_id, json!({ "cart": ["sku-1"] }))
+        .await
+        .expect("Failed to merge metadata");
+    let found = repo
+        .get_session(*abandoned_id)
+        .await
+        .expect("Failed to load session")
+        .expect("Guest session not found");
+    assert_eq!(found.metadata, Some(json!({ "cart": ["sku-1"] })));
+
+    // Guest sessions are not audited, they have no user
+    let audited: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM session_audit_log WHERE session_id = $1")
+            .bind(abandoned_id)
+            .fetch_one(&pool)
+            .await
+            .expect("Failed to count audit entries");
+    assert_eq!(audited, 0);
+
+    // Idle beyond the guest retention, like the user session
+    sqlx::query(
+        "UPDATE sessions \
+         SET last_activity_at = NOW() - INTERVAL '2 hours', last_activity_update_at = NOW() \
+         WHERE id = ANY($1)",
+    )
+    .bind(vec![*abandoned_id, user_session.id])
+    .execute(&pool)
+    .await
+    .expect("Failed to age sessions");
+
+    let cleaned = repo
+        .cleanup_expired_sessions()
+        .await
+        .expect("Failed to clean up sessions");
+    assert_eq!(cleaned, 1);
+    assert!(
+        repo.get_session(*abandoned_id)
+            .await
+            .expect("Failed to load session")
+            .is_none()
+    );
+    assert!(
+        repo.get_session(*active_id)
+            .await
+            .expect("Failed to load session")
+            .is_some()
+    );
+    assert!(
+        repo.get_session(user_session.id)
+            .await
+            .expect("Failed to load session")
+            .is_some()
+    );
+}
+
+#[tokio::test]
+async fn test_record_mfa_verification_writes_metadata_and_audit_entry() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!(
+            "Skipping test_record_mfa_verification_writes_metadata_and_audit_entry: \
+             Docker not available"
+        );
+        return;
+    };
+    let repo = PostgresSessionRepository::new(pool.clone());
+    let user = UserFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist user");
+    let session = SessionFixture::for_user(user.id)
+        .with_mfa_status(MfaStatus::Required)
+        .persist(&pool)
+        .await
+        .expect("Failed to persist session");
+
+    let verified_at = datetime::from_unix_seconds(1_750_000_000).expect("Valid timestamp");
+    repo.update_mfa_status(session.id, MfaStatus::Verified)
+        .await
+        .expect("Failed to update MFA status");
+    repo.record_mfa_verification(session.id, verified_at)
+        .await
+        .expect("Failed to record MFA verification");
+
+    let found = repo
+        .get_session(session.id)
+        .await
+        .expect("Failed to get session")
+        .expect("Session not found");
+    assert_eq!(found.mfa_status, MfaStatus::Verified);
+    assert_eq!(found.mfa_verified_at(), Some(verified_at));
+    assert_eq!(found.last_auth_at(), verified_at);
+
+    let audit_entries: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM session_audit_log WHERE session_id = $1 AND action = 'MFA_VERIFIED'",
+    )
+    .bind(session.id)
+    .fetch_one(&pool)
+    .await
+    .expect("Failed to count audit entries");
+    assert_eq!(audit_entries, 1);
+}
+
+#[tokio::test]
+async fn test_session_timestamps_round_trip() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!("Skipping test_session_timestamps_round_trip: Docker not available");
+        return;
+    };
+    let repo = PostgresSessionRepository::new(pool.clone());
+    let user = UserFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist user");
+
+    // Random instants between 1900 and 2200, before and after the epoch
+    for _ in 0..50 {
+        let nanos = rng::with_rng(|rng| {
+            rng.random_range(-2_208_988_800_000_000_000i128..7_258_118_400_000_000_000i128)
+        });
+        let expires_at = datetime::truncate_to_micros(
+            OffsetDateTime::from_unix_timestamp_nanos(nanos).expect("Valid timestamp"),
+        );
+        let session = repo
+            .create_session(
+                user.id,
+                rng::hex(32),
+                expires_at,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .expect("Failed to create session");
+        assert_eq!(session.expires_at, expires_at);
+
+        let found = repo
+            .get_session(session.id)
+            .await
+            .expect("Failed to get session")
+            .expect("Session not found");
+        assert_eq!(found.expires_at, expires_at, "seed {}", rng::seed());
+        assert_eq!(found.created_at, session.created_at);
+    }
+
+    let session = SessionFixture::for_user(user.id)
+        .persist(&pool)
+        .await
+        .expect("Failed to persist session");
+    let found = repo
+        .get_session(session.id)
+        .await
+        .expect("Failed to get session")
+        .expect("Session not found");
+    assert_eq!(found.created_at, session.created_at);
+    assert_eq!(found.expires_at, session.expires_at);
+}
+
+#[tokio::test]
+async fn test_stream_sessions_pages_through_tenant_sessions() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!(
+            "Skipping test_stream_sessions_pages_through_tenant_sessions: Docker not available"
+        );
+        return;
+    };
+    let repo = PostgresSessionRepository::new(pool.clone());
+    let user = UserFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist user");
+    let (tenant_id, other_tenant_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+    for (tenant, count) in [(tenant_id, 25), (other_tenant_id, 3)] {
+        let metadata = serde_json::Value::Object(serde_json::Map::from_iter([(
+            ACTIVE_TENANT_METADATA_KEY.to_string(),
+            tenant.to_string().into(),
+        )]));
+        for _ in 0..count {
+            SessionFixture::for_user(user.id)
+                .with_metadata(metadata.clone())
+                .persist(&pool)
+                .await
+                .expect("Failed to persist session");
+        }
+    }
+
+    let filter = SessionExportFilter {
+        page_size: 10,
+        ..SessionExportFilter::new(tenant_id)
+    };
+    let sessions: Vec<_> = repo
+        .stream_sessions(filter.clone())
+        .try_collect()
+        .await
+        .expect("Failed to stream sessions");
+    assert_eq!(sessions.len(), 25);
+    assert!(sessions.iter().all(|s| s.tenant_id() == Some(tenant_id)));
+    assert!(
+        sessions
+            .windows(2)
+            .all(|pair| (pair[0].created_at, pair[0].id) < (pair[1].created_at, pair[1].id))
+    );
+    let unique: HashSet<_> = sessions.iter().map(|s| s.id).collect();
+    assert_eq!(unique.len(), 25);
+
+    let capped: Vec<_> = repo
+        .stream_sessions(SessionExportFilter {
+            limit: 12,
+            ..filter
+        })
+        .try_collect()
+        .await
+        .expect("Failed to stream sessions");
+    assert_eq!(capped.len(), 12);
+    assert_eq!(capped[11].id, sessions[11].id);
+}
+
+/// Plan of `query`, with `user_id` (if any) bound before `binds`
+async fn explain(pool: &sqlx::PgPool, query: &str, binds: &[i64], user_id: Option<Uuid>) -> String {
+    let explain = format!("EXPLAIN {}", query);
+    let mut statement = sqlx::query_scalar::<_, String>(&explain);
+    if let Some(user_id) = user_id {
+        statement = statement.bind(user_id);
+    }
+    for bind in binds {
+        statement = statement.bind(*bind);
+    }
+    statement
+        .fetch_all(pool)
+        .await
+        .expect("Failed to explain query")
+        .join("\n")
+}
+
+#[tokio::test]
+async fn test_session_queries_use_indexes_on_large_table() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!("Skipping test_session_queries_use_indexes_on_large_table: Docker not available");
+        return;
+    };
+    let retention = Duration::from_secs(90 * 24 * 60 * 60);
+    let repo = PostgresSessionRepository::with_config(
+        pool.clone(),
+        SessionRepositoryConfig {
+            invalid_session_retention: retention,
+            cleanup_batch: BatchConfig {
+                batch_size: 1000,
+                delay: Duration::ZERO,
+            },
+            ..SessionRepositoryConfig::default()
+        },
+    );
+    let user = UserFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist user");
+    let other = UserFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist user");
+
+    // Mostly cold sessions: invalid ones still within the retention period,
+    // some past it and a few valid ones
+    sqlx::query(
+        r#"
+        INSERT INTO sessions (user_id, token_hash, expires_at, created_at, last_activity_at, is_valid)
+        SELECT $1, md5(random()::text), now() - interval '1 day', now() - interval '2 days',
+            now() - interval '2 days', false
+        FROM generate_series(1, 50000)
+        "#,
+    )
+    .bind(other.id)
+    .execute(&pool)
+    .await
+    .expect("Failed to seed retained sessions");
+    sqlx::query(
+        r#"
+        INSERT INTO sessions (user_id, token_hash, expires_at, created_at, last_activity_at, is_valid)
+        SELECT $1, md5(random()::text), now() - interval '199 days', now() - interval '200 days',
+            now() - interval '200 days', false
+        FROM generate_series(1, 500)
+        "#,
+    )
+    .bind(other.id)
+    .execute(&pool)
+    .await
+    .expect("Failed to seed old sessions");
+    for _ in 0..20 {
+        SessionFixture::for_user(user.id)
+            .persist(&pool)
+            .await
+            .expect("Failed to persist session");
+    }
+    sqlx::query("ANALYZE sessions")
+        .execute(&pool)
+        .await
+        .expect("Failed to analyze sessions");
+
+    let plan = explain(&pool, USER_SESSIONS_QUERY, &[], Some(user.id)).await;
+    assert!(plan.contains("idx_sessions_user_created"), "{plan}");
+    let plan = explain(&pool, VALID_USER_SESSIONS_QUERY, &[], Some(user.id)).await;
+    assert!(plan.contains("idx_sessions_user_valid_created"), "{plan}");
+    let plan = explain(&pool, INVALIDATE_EXPIRED_SESSIONS_QUERY, &[1000], None).await;
+    assert!(plan.contains("idx_sessions_expiry"), "{plan}");
+    let retention_secs = retention.as_secs() as i64;
+    let plan = explain(
+        &pool,
+        DELETE_INVALID_SESSIONS_QUERY,
+        &[retention_secs, 1000],
+        None,
+    )
+    .await;
+    assert!(plan.contains("idx_sessions_invalid_activity"), "{plan}");
+
+    // The cleanup only touches the old sessions, not the retained backlog
+    let started = std::time::Instant::now();
+    let cleaned = repo
+        .cleanup_expired_sessions()
+        .await
+        .expect("Failed to clean up sessions");
+    assert_eq!(cleaned, 500);
+    assert!(
+        started.elapsed() < Duration::from_secs(10),
+        "Cleanup took {:?}",
+        started.elapsed()
+    );
+
+    let sessions = repo
+        .get_user_sessions(user.id, SessionFilter::Active)
+        .await
+        .expect("Failed to list sessions");
+    assert_eq!(sessions.len(), 20);
+    assert!(
+        sessions
+            .windows(2)
+            .all(|pair| pair[0].created_at >= pair[1].created_at)
+    );
+}
+
+#[tokio::test]
+async fn test_session_user_agent_details_round_trip() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!("Skipping test_session_user_agent_details_round_trip: Docker not available");
+        return;
+    };
+    let repo = PostgresSessionRepository::new(pool.clone());
+    let user = UserFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist user");
+
+    let details = UserAgentDetails {
+        browser: Some("Firefox".to_string()),
+        browser_version: Some("121.0".to_string()),
+        os: Some("Linux".to_string()),
+        device_type: DeviceType::Desktop,
+    };
+    let session = repo
+        .create_session(
+            user.id,
+            rng::hex(32),
+            OffsetDateTime::now_utc() + Duration::from_secs(3600),
+            None,
+            None,
+            None,
+            Some("Mozilla/5.0 (X11; Linux x86_64; rv:121.0) Gecko/20100101 Firefox/121.0".into()),
+            Some(details.clone()),
+            None,
+            None,
+        )
+        .await
+        .expect("Failed to create session");
+    assert_eq!(session.user_agent_details.as_ref(), Some(&details));
+
+    // Sessions created before the details were recorded have none stored
+    let legacy = repo
+        .create_session(
+            user.id,
+            rng::hex(32),
+            OffsetDateTime::now_utc() + Duration::from_secs(3600),
+            None,
+            None,
+            None,
+            Some("Mozilla/5.0 (X11; Linux x86_64; rv:121.0) Gecko/20100101 Firefox/121.0".into()),
+            None,
+            None,
+            None,
+        )
+        .await
+        .expect("Failed to create session");
+
+    let sessions = repo
+        .get_user_sessions(user.id, SessionFilter::All)
+        .await
+        .expect("Failed to list sessions");
+    let find = |id: Uuid| {
+        sessions
+            .iter()
+            .find(|stored| stored.id == id)
+            .expect("Session is listed")
+    };
+    assert_eq!(find(session.id).user_agent_details.as_ref(), Some(&details));
+    assert!(find(legacy.id).user_agent_details.is_none());
+}
+
+#[tokio::test]
+async fn test_invalidate_sessions_by_region() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!("Skipping test_invalidate_sessions_by_region: Docker not available");
+        return;
+    };
+    let repo = PostgresSessionRepository::new(pool.clone());
+    let user = UserFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist user");
+    let tenant_id = Uuid::new_v4();
+
+    // Sessions created before the region was recorded have none
+    for region in [Some("eu-west"), Some("eu-west"), Some("us-east"), None] {
+        repo.create_session(
+            user.id,
+            rng::hex(32),
+            OffsetDateTime::now_utc() + Duration::from_secs(3600),
+            None,
+            None,
+            None,
+            None,
+            None,
+            region.map(str::to_string),
+            Some(json!({ "tenant_id": tenant_id })),
+        )
+        .await
+        .expect("Failed to create session");
+    }
+
+    let regions = PostgresPlatformStatsRepository::new(pool.clone())
+        .active_sessions_by_region(OffsetDateTime::now_utc())
+        .await
+        .expect("Failed to count sessions by region");
+    assert_eq!(
+        regions,
+        [
+            RegionSessionCount {
+                region: Some("eu-west".to_string()),
+                active_sessions: 2,
+            },
+            RegionSessionCount {
+                region: Some("us-east".to_string()),
+                active_sessions: 1,
+            },
+            RegionSessionCount {
+                region: None,
+                active_sessions: 1,
+            },
+        ]
+    );
+
+    // Another tenant's termination leaves the sessions alone
+    let invalidated = repo
+        .invalidate_sessions_by_region(
+            "eu-west",
+            Some(Uuid::new_v4()),
+            true,
+            SessionInvalidationReason::SecurityBreach,
+        )
+        .await
+        .expect("Failed to invalidate sessions");
+    assert_eq!(invalidated, 0);
+
+    let invalidated = repo
+        .invalidate_sessions_by_region(
+            "eu-west",
+            Some(tenant_id),
+            false,
+            SessionInvalidationReason::SecurityBreach,
+        )
+        .await
+        .expect("Failed to invalidate sessions");
+    assert_eq!(invalidated, 2);
+
+    let active = repo
+        .get_user_sessions(user.id, SessionFilter::Active)
+        .await
+        .expect("Failed to list sessions");
+    let mut regions: Vec<_> = active.iter().map(|s| s.region.as_deref()).collect();
+    regions.sort();
+    assert_eq!(regions, [None, Some("us-east")]);
+
+    let invalidated = repo
+        .invalidate_sessions_by_region(
+            "eu-west",
+            None,
+            true,
+            SessionInvalidationReason::SecurityBreach,
+        )
+        .await
+        .expect("Failed to invalidate sessions");
+    assert_eq!(invalidated, 1);
+
+    let active = repo
+        .get_user_sessions(user.id, SessionFilter::Active)
+        .await
+        .expect("Failed to list sessions");
+    assert_eq!(active.len(), 1);
+    assert_eq!(active[0].region.as_deref(), Some("us-east"));
+}
+
+#[tokio::test]
+async fn test_user_sessions_with_location() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!("Skipping test_user_sessions_with_location: Docker not available");
+        return;
+    };
+    let repo = PostgresSessionRepository::new(pool.clone());
+    let locations = PostgresSessionLocationRepository::new(pool.clone());
+    let user = UserFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist user");
+    let located = SessionFixture::for_user(user.id)
+        .persist(&pool)
+        .await
+        .expect("Failed to persist session");
+    let unlocated = SessionFixture::for_user(user.id)
+        .persist(&pool)
+        .await
+        .expect("Failed to persist session");
+
+    let ip = "203.0.113.7".parse().expect("Valid IP address");
+    let mut earlier = SessionLocation::new(located.id, user.id, ip, "FR", 48.8566, 2.3522);
+    earlier.city = Some("Paris".to_string());
+    earlier.recorded_at -= Duration::from_secs(60);
+    let mut latest = SessionLocation::new(located.id, user.id, ip, "DE", 52.52, 13.405);
+    latest.city = Some("Berlin".to_string());
+    latest.region_code = Some("BE".to_string());
+    for location in [&earlier, &latest] {
+        locations
+            .save_location(location)
+            .await
+            .expect("Failed to save location");
+    }
+
+    let sessions = repo
+        .get_user_sessions_with_location(user.id, SessionFilter::Active, Some(unlocated.id))
+        .await
+        .expect("Failed to list sessions");
+    assert_eq!(sessions.len(), 2);
+
+    // One row per session, with the location recorded last
+    let listed = |id: Uuid| {
+        sessions
+            .iter()
+            .find(|listed| listed.session.id == id)
+            .expect("Session is listed")
+    };
+    assert_eq!(listed(located.id).location.as_ref(), Some(&latest));
+    assert!(!listed(located.id).is_current);
+    assert!(listed(unlocated.id).location.is_none());
+    assert!(listed(unlocated.id).is_current);
+
+    repo.invalidate_session(unlocated.id, SessionInvalidationReason::UserLogout)
+        .await
+        .expect("Failed to invalidate session");
+    let sessions = repo
+        .get_user_sessions_with_location(user.id, SessionFilter::Inactive, None)
+        .await
+        .expect("Failed to list sessions");
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].session.id, unlocated.id);
+    assert!(!sessions[0].is_current);
+}
@@ -0,0 +1,116 @@
+use crate::fixtures::TenantFixture;
+use crate::helpers::setup_test_db_with_url;
+use acci_auth::{
+    PlanChange, PostgresTenantRepository, RepositoryConfig, SubscriptionChangeType,
+    SubscriptionHistoryEntry, TenantPlanType, TenantRepository,
+    models::tenant::{CreateSubscriptionDto, UpdateSubscriptionDto},
+};
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn test_subscription_history_roundtrip() {
+    let Ok((_container, pool, database_url)) = setup_test_db_with_url().await else {
+        eprintln!("Skipping test_subscription_history_roundtrip: Docker not available");
+        return;
+    };
+    let repo = PostgresTenantRepository::new(RepositoryConfig {
+        database_url,
+        ..RepositoryConfig::default()
+    })
+    .await
+    .expect("Failed to connect tenant repository");
+    let tenant = TenantFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist tenant");
+
+    let created = repo
+        .create_subscription(
+            tenant.id,
+            CreateSubscriptionDto {
+                plan_type: TenantPlanType::Basic,
+                starts_at: OffsetDateTime::now_utc(),
+                expires_at: None,
+                is_active: Some(true),
+                payment_status: None,
+                max_users: Some(20),
+                features: None,
+            },
+        )
+        .await
+        .expect("Failed to create subscription");
+    let found = repo
+        .find_subscription_by_id(created.id)
+        .await
+        .expect("Failed to find subscription")
+        .expect("Subscription not found");
+    assert_eq!(found.plan_type, TenantPlanType::Basic);
+    assert_eq!(found.max_users, Some(20));
+    assert!(
+        repo.find_subscription_by_id(Uuid::new_v4())
+            .await
+            .expect("Failed to find subscription")
+            .is_none()
+    );
+
+    let updated = repo
+        .update_subscription(
+            created.id,
+            UpdateSubscriptionDto {
+                plan_type: Some(TenantPlanType::Enterprise),
+                expires_at: Some(OffsetDateTime::now_utc() + Duration::days(365)),
+                is_active: None,
+                payment_status: None,
+                max_users: Some(1000),
+                features: None,
+            },
+        )
+        .await
+        .expect("Failed to update subscription");
+
+    let subscriptions = repo
+        .list_subscriptions(tenant.id)
+        .await
+        .expect("Failed to list subscriptions");
+    assert_eq!(subscriptions.len(), 1);
+    assert_eq!(subscriptions[0].id, created.id);
+    assert_eq!(subscriptions[0].plan_type, TenantPlanType::Enterprise);
+
+    let mut creation =
+        SubscriptionHistoryEntry::new(SubscriptionChangeType::Created, None, &created, None);
+    creation.created_at -= Duration::minutes(1);
+    let upgrade = SubscriptionHistoryEntry::new(
+        SubscriptionChangeType::Updated,
+        Some(&created),
+        &updated,
+        None,
+    );
+    for entry in [&creation, &upgrade] {
+        repo.record_subscription_change(entry)
+            .await
+            .expect("Failed to record subscription change");
+    }
+
+    let history = repo
+        .list_subscription_history(tenant.id, 10, 0)
+        .await
+        .expect("Failed to list subscription history");
+    assert_eq!(history, vec![upgrade.clone(), creation.clone()]);
+    assert_eq!(history[0].old_plan_type, Some(TenantPlanType::Basic));
+    assert_eq!(history[0].new_plan_type, TenantPlanType::Enterprise);
+    assert_eq!(history[0].plan_change, Some(PlanChange::Upgrade));
+    assert_eq!(history[1].plan_change, None);
+
+    let second_page = repo
+        .list_subscription_history(tenant.id, 1, 1)
+        .await
+        .expect("Failed to list subscription history");
+    assert_eq!(second_page, vec![creation]);
+    assert!(
+        repo.list_subscription_history(Uuid::new_v4(), 10, 0)
+            .await
+            .expect("Failed to list subscription history")
+            .is_empty()
+    );
+}
@@ -0,0 +1,86 @@
+use crate::fixtures::{TenantFixture, UserFixture};
+use crate::helpers::setup_test_db_with_url;
+use acci_auth::{
+    PostgresTenantRepository, RepositoryConfig, TenantRepository,
+    models::tenant::{CreateTenantUserDto, TenantError},
+};
+use std::sync::Arc;
+
+fn member(user_id: uuid::Uuid, is_active: bool) -> CreateTenantUserDto {
+    CreateTenantUserDto {
+        user_id,
+        tenant_role: "MEMBER".to_string(),
+        is_active: Some(is_active),
+    }
+}
+
+#[tokio::test]
+async fn test_concurrent_adds_respect_tenants_per_user_limit() {
+    let Ok((_container, pool, database_url)) = setup_test_db_with_url().await else {
+        eprintln!(
+            "Skipping test_concurrent_adds_respect_tenants_per_user_limit: Docker not available"
+        );
+        return;
+    };
+    let repo = Arc::new(
+        PostgresTenantRepository::new(RepositoryConfig {
+            database_url,
+            max_connections: 10,
+            ..RepositoryConfig::default()
+        })
+        .await
+        .expect("Failed to connect tenant repository"),
+    );
+    let user = UserFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist user");
+    let mut tenant_ids = Vec::new();
+    for _ in 0..8 {
+        let tenant = TenantFixture::new()
+            .persist(&pool)
+            .await
+            .expect("Failed to persist tenant");
+        tenant_ids.push(tenant.id);
+    }
+
+    // All additions race for the two free slots
+    let handles: Vec<_> = tenant_ids
+        .iter()
+        .map(|tenant_id| {
+            let repo = Arc::clone(&repo);
+            let tenant_id = *tenant_id;
+            tokio::spawn(async move {
+                repo.add_user_to_tenant_within_limit(tenant_id, member(user.id, true), 2)
+                    .await
+            })
+        })
+        .collect();
+    let mut added = 0;
+    for handle in handles {
+        match handle.await.expect("task panicked") {
+            Ok(_) => added += 1,
+            Err(TenantError::TenantsPerUserLimitExceeded { limit, current }) => {
+                assert_eq!((limit, current), (2, 2));
+            },
+            Err(e) => panic!("Unexpected error: {e}"),
+        }
+    }
+    assert_eq!(added, 2);
+    assert_eq!(
+        repo.get_user_tenants(user.id)
+            .await
+            .expect("Failed to list memberships")
+            .len(),
+        2
+    );
+
+    // Inactive associations do not count against the limit
+    let tenant = TenantFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist tenant");
+    repo.add_user_to_tenant_within_limit(tenant.id, member(user.id, false), 2)
+        .await
+        .expect("Inactive association is added");
+}
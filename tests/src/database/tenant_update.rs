@@ -0,0 +1,174 @@
+use crate::fixtures::TenantFixture;
+use crate::helpers::setup_test_db_with_url;
+use acci_auth::{
+    BoundedJson, PostgresTenantRepository, RepositoryConfig, TenantRepository, TenantStatus,
+    models::tenant::UpdateTenantDto,
+};
+use serde_json::json;
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// The `is_active` column generated from the tenant status
+async fn stored_is_active(pool: &PgPool, tenant_id: Uuid) -> bool {
+    sqlx::query_scalar("SELECT is_active FROM tenants WHERE id = $1")
+        .bind(tenant_id)
+        .fetch_one(pool)
+        .await
+        .expect("Failed to read is_active")
+}
+
+#[tokio::test]
+async fn test_update_tenant_merge_patch_metadata() {
+    let Ok((_container, pool, database_url)) = setup_test_db_with_url().await else {
+        eprintln!("Skipping test_update_tenant_merge_patch_metadata: Docker not available");
+        return;
+    };
+    let repo = PostgresTenantRepository::new(RepositoryConfig {
+        database_url,
+        ..RepositoryConfig::default()
+    })
+    .await
+    .expect("Failed to connect tenant repository");
+    let tenant = TenantFixture::new()
+        .with_metadata(json!({ "plan": "basic" }))
+        .persist(&pool)
+        .await
+        .expect("Failed to persist tenant");
+
+    // Every field at once still updates every column
+    let updated = repo
+        .update_tenant(
+            tenant.id,
+            UpdateTenantDto {
+                name: Some("Full Update".to_string()),
+                subdomain: Some(format!("full-{}", tenant.subdomain)),
+                metadata: Some(Some(
+                    BoundedJson::new(json!({ "plan": "pro" }))
+                        .expect("Metadata is within the limits"),
+                )),
+            },
+        )
+        .await
+        .expect("Failed to update tenant");
+    assert_eq!(updated.name, "Full Update");
+    assert_eq!(updated.subdomain, format!("full-{}", tenant.subdomain));
+    assert_eq!(updated.metadata, Some(json!({ "plan": "pro" })));
+
+    // An omitted metadata field is left untouched
+    let patch: UpdateTenantDto =
+        serde_json::from_value(json!({ "name": "Renamed" })).expect("Patch is valid");
+    let updated = repo
+        .update_tenant(tenant.id, patch)
+        .await
+        .expect("Failed to update tenant");
+    assert_eq!(updated.name, "Renamed");
+    assert_eq!(updated.metadata, Some(json!({ "plan": "pro" })));
+
+    // An explicit null clears it
+    let patch: UpdateTenantDto =
+        serde_json::from_value(json!({ "metadata": null })).expect("Patch is valid");
+    let updated = repo
+        .update_tenant(tenant.id, patch)
+        .await
+        .expect("Failed to update tenant");
+    assert_eq!(updated.name, "Renamed");
+    assert_eq!(updated.metadata, None);
+}
+
+#[tokio::test]
+async fn test_oversized_legacy_metadata_is_truncated_on_read() {
+    let Ok((_container, pool, database_url)) = setup_test_db_with_url().await else {
+        eprintln!(
+            "Skipping test_oversized_legacy_metadata_is_truncated_on_read: Docker not available"
+        );
+        return;
+    };
+    let repo = PostgresTenantRepository::new(RepositoryConfig {
+        database_url,
+        ..RepositoryConfig::default()
+    })
+    .await
+    .expect("Failed to connect tenant repository");
+    // Written before the limits, far beyond 16 KiB and 256 keys
+    let mut metadata: serde_json::Map<String, serde_json::Value> = (0..400)
+        .map(|i| (format!("key-{:03}", i), json!("x".repeat(100))))
+        .collect();
+    metadata.insert(
+        "security_policy".to_string(),
+        json!({ "mfa_enforcement": "REQUIRED_FOR_ALL" }),
+    );
+    let tenant = TenantFixture::new()
+        .with_metadata(metadata.into())
+        .persist(&pool)
+        .await
+        .expect("Failed to persist tenant");
+
+    let stored = repo
+        .find_tenant_by_id(tenant.id)
+        .await
+        .expect("Failed to read tenant")
+        .expect("Tenant exists");
+    let metadata = stored.metadata.expect("Metadata is kept");
+    assert!(acci_auth::JsonLimits::default().check(&metadata).is_ok());
+    // Keys managed by the services survive the truncation
+    assert_eq!(
+        metadata["security_policy"],
+        json!({ "mfa_enforcement": "REQUIRED_FOR_ALL" })
+    );
+    assert_eq!(metadata["key-000"], json!("x".repeat(100)));
+}
+
+#[tokio::test]
+async fn test_tenant_status_transitions() {
+    let Ok((_container, pool, database_url)) = setup_test_db_with_url().await else {
+        eprintln!("Skipping test_tenant_status_transitions: Docker not available");
+        return;
+    };
+    let repo = PostgresTenantRepository::new(RepositoryConfig {
+        database_url,
+        ..RepositoryConfig::default()
+    })
+    .await
+    .expect("Failed to connect tenant repository");
+    let tenant = TenantFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist tenant");
+    // Whole seconds survive the microsecond precision of the column
+    let since = OffsetDateTime::now_utc()
+        .replace_nanosecond(0)
+        .expect("Valid nanosecond");
+    let suspended = TenantStatus::Suspended {
+        reason: "Invoice overdue".to_string(),
+        since,
+    };
+    let updated = repo
+        .set_tenant_status(tenant.id, suspended.clone(), None)
+        .await
+        .expect("Failed to suspend tenant");
+    assert_eq!(updated.status, suspended);
+    let stored = repo
+        .find_tenant_by_id(tenant.id)
+        .await
+        .expect("Failed to read tenant")
+        .expect("Tenant exists");
+    assert_eq!(stored.status, suspended);
+    // Suspended tenants stay active for readers of the compatibility column
+    assert!(stored_is_active(&pool, tenant.id).await);
+
+    repo.set_tenant_status(tenant.id, TenantStatus::Archived { since }, None)
+        .await
+        .expect("Failed to archive tenant");
+    assert!(!stored_is_active(&pool, tenant.id).await);
+
+    // The fixture inserts the tenant directly, so only the transitions are audited
+    let actions: Vec<String> = sqlx::query_scalar(
+        "SELECT action FROM tenant_audit_log WHERE tenant_id = $1 ORDER BY created_at",
+    )
+    .bind(tenant.id)
+    .fetch_all(&pool)
+    .await
+    .expect("Failed to read audit log");
+    assert_eq!(actions, vec!["TENANT_SUSPENSION", "TENANT_ARCHIVAL"]);
+}
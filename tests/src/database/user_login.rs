@@ -0,0 +1,101 @@
+use crate::fixtures::UserFixture;
+use crate::helpers::setup_test_db_with_url;
+use acci_auth::{PostgresUserRepository, RepositoryConfig, UserRepository};
+use sqlx::Row;
+use time::OffsetDateTime;
+
+#[tokio::test]
+async fn test_record_login_updates_only_login_columns() {
+    let Ok((_container, pool, database_url)) = setup_test_db_with_url().await else {
+        eprintln!("Skipping test_record_login_updates_only_login_columns: Docker not available");
+        return;
+    };
+    let repo = PostgresUserRepository::new(RepositoryConfig {
+        database_url,
+        ..RepositoryConfig::default()
+    })
+    .await
+    .expect("Failed to connect user repository");
+    let user = UserFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist user");
+    sqlx::query(
+        "UPDATE users SET failed_login_attempts = 3, last_failed_login_at = $1 WHERE id = $2",
+    )
+    .bind(OffsetDateTime::now_utc())
+    .bind(user.id)
+    .execute(&pool)
+    .await
+    .expect("Failed to record failed logins");
+
+    // A profile change lands between the login's read and its write
+    let mut changed = repo
+        .find_by_id(user.id)
+        .await
+        .expect("Failed to find user")
+        .expect("User exists");
+    changed.email = format!("changed.{}", user.email);
+    changed.password_hash = "changed-hash".to_string();
+    changed.is_verified = true;
+    changed.updated_at =
+        OffsetDateTime::from_unix_timestamp(1_735_689_600).expect("valid timestamp");
+    repo.update(&changed).await.expect("Failed to update user");
+
+    let at = OffsetDateTime::from_unix_timestamp(1_735_693_200).expect("valid timestamp");
+    repo.record_login(user.id, at)
+        .await
+        .expect("Failed to record login");
+
+    let stored = repo
+        .find_by_id(user.id)
+        .await
+        .expect("Failed to find user")
+        .expect("User exists");
+    assert_eq!(stored.last_login, Some(at));
+    assert_eq!(stored.email, changed.email);
+    assert_eq!(stored.password_hash, "changed-hash");
+    assert!(stored.is_verified);
+    assert!(stored.is_active);
+    assert_eq!(stored.updated_at, changed.updated_at);
+
+    let row =
+        sqlx::query("SELECT failed_login_attempts, last_failed_login_at FROM users WHERE id = $1")
+            .bind(user.id)
+            .fetch_one(&pool)
+            .await
+            .expect("Failed to read failed logins");
+    assert_eq!(row.get::<i32, _>("failed_login_attempts"), 0);
+    assert_eq!(
+        row.get::<Option<OffsetDateTime>, _>("last_failed_login_at"),
+        None
+    );
+
+    // A later profile update from the stale copy keeps the recorded login
+    repo.update(&changed).await.expect("Failed to update user");
+    let stored = repo
+        .find_by_id(user.id)
+        .await
+        .expect("Failed to find user")
+        .expect("User exists");
+    assert_eq!(stored.last_login, Some(at));
+}
+
+#[tokio::test]
+async fn test_record_login_of_unknown_user() {
+    let Ok((_container, _pool, database_url)) = setup_test_db_with_url().await else {
+        eprintln!("Skipping test_record_login_of_unknown_user: Docker not available");
+        return;
+    };
+    let repo = PostgresUserRepository::new(RepositoryConfig {
+        database_url,
+        ..RepositoryConfig::default()
+    })
+    .await
+    .expect("Failed to connect user repository");
+
+    let result = repo
+        .record_login(uuid::Uuid::new_v4(), OffsetDateTime::now_utc())
+        .await;
+    assert!(matches!(result, Err(acci_auth::UserError::NotFound)));
+}
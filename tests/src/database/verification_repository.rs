@@ -0,0 +1,265 @@
+use crate::fixtures::{TenantFixture, UserFixture};
+use crate::helpers::setup_test_db;
+use acci_auth::repository::{RepositoryError, TenantAwareContext};
+use acci_auth::{
+    ExpiredCodes, PostgresVerificationCodeRepository, VerificationCode, VerificationCodeRepository,
+    VerificationConfig, VerificationStatus, VerificationType,
+};
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+/// The repository scopes its queries by tenant explicitly
+struct ExplicitTenantContext;
+
+impl TenantAwareContext for ExplicitTenantContext {
+    fn set_tenant_context(&self, _tenant_id: &Uuid) -> Result<(), RepositoryError> {
+        Ok(())
+    }
+}
+
+/// Store a code issued at `created_at` that expires at `expires_at` in `status`
+async fn seed_code(
+    repo: &PostgresVerificationCodeRepository,
+    tenant_id: Uuid,
+    user_id: Uuid,
+    created_at: OffsetDateTime,
+    expires_at: OffsetDateTime,
+    status: VerificationStatus,
+) -> VerificationCode {
+    let mut code = VerificationCode::issued_at(
+        tenant_id,
+        user_id,
+        "123456".to_string(),
+        VerificationType::Email,
+        &VerificationConfig::default(),
+        created_at,
+    );
+    code.expires_at = expires_at;
+    code.status = status;
+    repo.save(&code, &ExplicitTenantContext)
+        .await
+        .expect("Failed to save verification code");
+    code
+}
+
+async fn status_of(pool: &sqlx::PgPool, id: Uuid) -> Option<String> {
+    sqlx::query_scalar("SELECT status FROM verification_codes WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .expect("Failed to load verification code")
+}
+
+#[tokio::test]
+async fn test_cleanup_all_expired_sweeps_every_tenant() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!("Skipping test_cleanup_all_expired_sweeps_every_tenant: Docker not available");
+        return;
+    };
+    let repo = PostgresVerificationCodeRepository::new(pool.clone());
+    let first = TenantFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist tenant");
+    let second = TenantFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist tenant");
+    let user = UserFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist user");
+
+    let now = OffsetDateTime::now_utc();
+    let hour_ago = now - Duration::hours(1);
+    let unverified = seed_code(
+        &repo,
+        first.id,
+        user.id,
+        hour_ago - Duration::minutes(10),
+        hour_ago,
+        VerificationStatus::Pending,
+    )
+    .await;
+    let verified = seed_code(
+        &repo,
+        first.id,
+        user.id,
+        hour_ago - Duration::minutes(10),
+        hour_ago,
+        VerificationStatus::Verified,
+    )
+    .await;
+    let live = seed_code(
+        &repo,
+        first.id,
+        user.id,
+        now,
+        now + Duration::minutes(10),
+        VerificationStatus::Pending,
+    )
+    .await;
+    let other_unverified = seed_code(
+        &repo,
+        second.id,
+        user.id,
+        hour_ago - Duration::minutes(10),
+        hour_ago,
+        VerificationStatus::Pending,
+    )
+    .await;
+    let ancient = seed_code(
+        &repo,
+        second.id,
+        user.id,
+        now - Duration::days(10),
+        now - Duration::days(10) + Duration::minutes(10),
+        VerificationStatus::Invalidated,
+    )
+    .await;
+
+    // Only pending codes past their expiry are marked, in every tenant
+    let mut expired = repo
+        .expire_all_pending(now)
+        .await
+        .expect("Failed to expire pending codes");
+    expired.sort_by_key(|tenant| tenant.tenant_id);
+    let mut expected = vec![
+        ExpiredCodes {
+            tenant_id: first.id,
+            count: 1,
+        },
+        ExpiredCodes {
+            tenant_id: second.id,
+            count: 1,
+        },
+    ];
+    expected.sort_by_key(|tenant| tenant.tenant_id);
+    assert_eq!(expired, expected);
+
+    assert_eq!(
+        status_of(&pool, unverified.id).await.as_deref(),
+        Some("Expired")
+    );
+    assert_eq!(
+        status_of(&pool, other_unverified.id).await.as_deref(),
+        Some("Expired")
+    );
+    assert_eq!(
+        status_of(&pool, verified.id).await.as_deref(),
+        Some("Verified")
+    );
+    assert_eq!(status_of(&pool, live.id).await.as_deref(), Some("Pending"));
+
+    // A second run finds nothing left to expire
+    assert!(
+        repo.expire_all_pending(now)
+            .await
+            .expect("Failed to expire pending codes")
+            .is_empty()
+    );
+
+    // Codes past the retention go first, regardless of tenant and status
+    let deleted = repo
+        .cleanup_all_expired(now - Duration::days(7))
+        .await
+        .expect("Failed to clean up expired codes");
+    assert_eq!(deleted, 1);
+    assert_eq!(status_of(&pool, ancient.id).await, None);
+
+    let deleted = repo
+        .cleanup_all_expired(now)
+        .await
+        .expect("Failed to clean up expired codes");
+    assert_eq!(deleted, 3);
+
+    let remaining: Vec<Uuid> = sqlx::query_scalar("SELECT id FROM verification_codes")
+        .fetch_all(&pool)
+        .await
+        .expect("Failed to list verification codes");
+    assert_eq!(remaining, vec![live.id]);
+}
+
+#[tokio::test]
+async fn test_verification_stats_aggregate_outcomes() {
+    let Ok((_container, pool)) = setup_test_db().await else {
+        eprintln!("Skipping test_verification_stats_aggregate_outcomes: Docker not available");
+        return;
+    };
+    let repo = PostgresVerificationCodeRepository::new(pool.clone());
+    let tenant = TenantFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist tenant");
+    let other_tenant = TenantFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist tenant");
+    let user = UserFixture::new()
+        .persist(&pool)
+        .await
+        .expect("Failed to persist user");
+
+    let now = OffsetDateTime::now_utc();
+    let issued = now - Duration::days(1);
+    let lapsed = issued + Duration::minutes(10);
+    for status in [
+        VerificationStatus::Verified,
+        VerificationStatus::Verified,
+        VerificationStatus::Verified,
+        VerificationStatus::Expired,
+        VerificationStatus::Invalidated,
+    ] {
+        seed_code(&repo, tenant.id, user.id, issued, lapsed, status).await;
+    }
+    // Expired, but not yet swept
+    seed_code(
+        &repo,
+        tenant.id,
+        user.id,
+        issued,
+        lapsed,
+        VerificationStatus::Pending,
+    )
+    .await;
+    seed_code(
+        &repo,
+        tenant.id,
+        user.id,
+        now,
+        now + Duration::minutes(10),
+        VerificationStatus::Pending,
+    )
+    .await;
+
+    // Outside the window or of another tenant
+    seed_code(
+        &repo,
+        tenant.id,
+        user.id,
+        now - Duration::days(10),
+        now - Duration::days(10) + Duration::minutes(10),
+        VerificationStatus::Verified,
+    )
+    .await;
+    seed_code(
+        &repo,
+        other_tenant.id,
+        user.id,
+        issued,
+        lapsed,
+        VerificationStatus::Expired,
+    )
+    .await;
+
+    let stats = repo
+        .verification_stats(tenant.id, now - Duration::days(7), &ExplicitTenantContext)
+        .await
+        .expect("Failed to compute verification stats");
+    assert_eq!(stats.issued, 7);
+    assert_eq!(stats.verified, 3);
+    assert_eq!(stats.expired, 2);
+    assert_eq!(stats.invalidated, 1);
+    assert_eq!(stats.pending, 1);
+    assert_eq!(stats.success_rate(), Some(0.5));
+}
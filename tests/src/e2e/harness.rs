@@ -0,0 +1,296 @@
+//! Shared setup for the end-to-end tests
+//!
+//! [`E2eHarness`] wires the real Postgres repositories, services and the API
+//! router together, with the Redis-backed rate limiter in front of it. Outgoing
+//! verification messages are captured instead of sent, so tests can read the
+//! codes a user would have received.
+
+use acci_api::{ApiAppState, ApiConfig, ApiRouter, handlers::verification::VerificationAppState};
+use acci_auth::{
+    AuthConfig, DurationSecs, JwtUtils, Message, MessageProvider, MfaOrchestrator,
+    PostgresUserRepository, PostgresVerificationCodeRepository, RepositoryConfig, RepositoryError,
+    SessionRepository, SessionService, TenantAwareContext, UserService, VerificationConfig,
+    VerificationService, VerificationType,
+    security::{RateLimitLayer, RateStore, config::RateLimitingConfig},
+    session::PostgresSessionRepository,
+};
+use acci_core::error::Result as CoreResult;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use axum::{
+    Router,
+    body::Body,
+    http::{HeaderMap, Method, Request, StatusCode, header},
+};
+use serde_json::Value;
+use sqlx::{PgPool, Row};
+use std::sync::{Arc, Mutex};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use crate::helpers::{RedisGuard, setup_test_db_with_url, setup_test_redis};
+
+/// Message provider that records messages instead of delivering them
+pub struct CapturingMessageProvider {
+    verification_type: VerificationType,
+    sent: Mutex<Vec<Message>>,
+}
+
+impl CapturingMessageProvider {
+    /// Create a provider for the given channel
+    pub fn new(verification_type: VerificationType) -> Self {
+        Self {
+            verification_type,
+            sent: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Messages captured so far, oldest first
+    pub fn sent(&self) -> Vec<Message> {
+        self.sent.lock().expect("message log poisoned").clone()
+    }
+
+    /// Extract the verification code from the latest message to `recipient`
+    pub fn last_code_for(&self, recipient: &str) -> Option<String> {
+        self.sent()
+            .into_iter()
+            .rev()
+            .find(|message| message.recipient == recipient)
+            .and_then(|message| {
+                let (_, rest) = message.body.split_once("code is: ")?;
+                let code: String = rest.chars().take_while(char::is_ascii_digit).collect();
+                (!code.is_empty()).then_some(code)
+            })
+    }
+}
+
+#[async_trait]
+impl MessageProvider for CapturingMessageProvider {
+    fn verification_type(&self) -> VerificationType {
+        self.verification_type
+    }
+
+    async fn send_message(&self, message: Message) -> CoreResult<String> {
+        let id = Uuid::new_v4().to_string();
+        self.sent
+            .lock()
+            .expect("message log poisoned")
+            .push(message);
+        Ok(id)
+    }
+}
+
+/// The Postgres repositories scope their queries by tenant explicitly, so
+/// there is no connection state to set up here
+struct ExplicitTenantContext;
+
+impl TenantAwareContext for ExplicitTenantContext {
+    fn set_tenant_context(&self, _tenant_id: &Uuid) -> Result<(), RepositoryError> {
+        Ok(())
+    }
+}
+
+/// Session columns the end-to-end tests assert on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionRow {
+    pub id: Uuid,
+    pub mfa_status: String,
+    pub is_valid: bool,
+}
+
+/// Verification code columns the end-to-end tests assert on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationCodeRow {
+    pub code: String,
+    pub status: String,
+    pub attempts: i32,
+}
+
+/// Running API backed by Postgres and Redis containers
+pub struct E2eHarness {
+    _postgres: Box<dyn std::any::Any>,
+    _redis: RedisGuard,
+    /// Pool on the test database, for asserting persisted state
+    pub pool: PgPool,
+    /// The API router including the rate limiting layer
+    pub router: Router,
+    /// Captured email verification messages
+    pub email: Arc<CapturingMessageProvider>,
+    /// Tenant the test users authenticate against
+    pub tenant_id: Uuid,
+}
+
+impl E2eHarness {
+    /// Start the containers, run migrations and build the API
+    pub async fn start() -> Result<Self> {
+        let (postgres, pool, database_url) = setup_test_db_with_url()
+            .await
+            .context("failed to start Postgres")?;
+
+        let (redis, redis_client) = setup_test_redis().await?;
+
+        let tenant_id = Uuid::new_v4();
+        sqlx::query("INSERT INTO tenants (id, name, subdomain) VALUES ($1, $2, $3)")
+            .bind(tenant_id)
+            .bind("E2E Tenant")
+            .bind(format!("e2e-{}", tenant_id.simple()))
+            .execute(&pool)
+            .await?;
+
+        let config = Arc::new(AuthConfig::default());
+
+        let user_repository = PostgresUserRepository::new(RepositoryConfig {
+            database_url,
+            ..RepositoryConfig::default()
+        })
+        .await?;
+        let session_repository: Arc<dyn SessionRepository> =
+            Arc::new(PostgresSessionRepository::new(pool.clone()));
+        let session_service = Arc::new(SessionService::new(
+            session_repository.clone(),
+            config.clone(),
+        ));
+
+        let email = Arc::new(CapturingMessageProvider::new(VerificationType::Email));
+        let verification_service = Arc::new(VerificationService::new(
+            Arc::new(PostgresVerificationCodeRepository::new(pool.clone())),
+            VerificationConfig {
+                // Tests send several codes in quick succession
+                throttle_seconds: DurationSecs::from_secs(0),
+                ..VerificationConfig::default()
+            },
+            None,
+            Some(email.clone() as Arc<dyn MessageProvider>),
+        ));
+
+        let user_service = Arc::new(UserService::new(
+            Arc::new(user_repository),
+            Arc::new(JwtUtils::new(config.jwt_secret.as_bytes())),
+            session_service.clone(),
+            Some(verification_service.clone()),
+            config,
+        ));
+
+        let router = ApiRouter::new(ApiConfig::default())
+            .create_router_with_state(
+                ApiAppState {
+                    user_service,
+                    session_service: session_service.clone(),
+                },
+                None,
+                Some(VerificationAppState {
+                    mfa_orchestrator: Arc::new(MfaOrchestrator::new(
+                        verification_service.clone(),
+                        session_repository,
+                    )),
+                    verification_service,
+                    session_service,
+                    tenant_context: Arc::new(ExplicitTenantContext),
+                }),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .layer(RateLimitLayer::new(
+                Arc::new(RateStore::new(redis_client)),
+                RateLimitingConfig::default(),
+            ));
+
+        Ok(Self {
+            _postgres: postgres,
+            _redis: redis,
+            pool,
+            router,
+            email,
+            tenant_id,
+        })
+    }
+
+    /// Send a JSON request through the router
+    ///
+    /// Returns the status, the response headers and the parsed body (or
+    /// `Value::Null` for empty bodies).
+    pub async fn request(
+        &self,
+        method: Method,
+        uri: &str,
+        bearer: Option<&str>,
+        body: Option<Value>,
+    ) -> Result<(StatusCode, HeaderMap, Value)> {
+        let mut builder = Request::builder().method(method).uri(uri);
+        if let Some(token) = bearer {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let request = match body {
+            Some(body) => builder
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec(&body)?))?,
+            None => builder.body(Body::empty())?,
+        };
+
+        let response = self.router.clone().oneshot(request).await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+        let body = if bytes.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice(&bytes)?
+        };
+
+        Ok((status, headers, body))
+    }
+
+    /// All sessions of a user, oldest first
+    pub async fn sessions_for(&self, user_id: Uuid) -> Result<Vec<SessionRow>> {
+        let rows = sqlx::query(
+            "SELECT id, mfa_status::text AS mfa_status, is_valid \
+             FROM sessions WHERE user_id = $1 ORDER BY created_at",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(SessionRow {
+                    id: row.try_get("id")?,
+                    mfa_status: row.try_get("mfa_status")?,
+                    is_valid: row.try_get("is_valid")?,
+                })
+            })
+            .collect()
+    }
+
+    /// All verification codes issued to a user, oldest first
+    pub async fn verification_codes_for(&self, user_id: Uuid) -> Result<Vec<VerificationCodeRow>> {
+        let rows = sqlx::query(
+            "SELECT code, status, attempts FROM verification_codes \
+             WHERE user_id = $1 AND tenant_id = $2 ORDER BY created_at",
+        )
+        .bind(user_id)
+        .bind(self.tenant_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(VerificationCodeRow {
+                    code: row.try_get("code")?,
+                    status: row.try_get("status")?,
+                    attempts: row.try_get("attempts")?,
+                })
+            })
+            .collect()
+    }
+}
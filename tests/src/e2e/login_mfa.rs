@@ -0,0 +1,205 @@
+//! Login followed by an email MFA challenge, asserted against the database
+
+use axum::http::{Method, StatusCode};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use super::E2eHarness;
+
+const EMAIL: &str = "e2e-user@example.com";
+const PASSWORD: &str = "C0rrect-Horse-Battery!";
+
+// A code of the same length that is guaranteed not to match
+fn wrong_code(code: &str) -> String {
+    code.chars()
+        .map(|c| {
+            let digit = c.to_digit(10).expect("codes are numeric");
+            char::from_digit((digit + 1) % 10, 10).expect("digit in range")
+        })
+        .collect()
+}
+
+fn data_field<'a>(body: &'a Value, field: &str) -> &'a str {
+    body["data"][field]
+        .as_str()
+        .unwrap_or_else(|| panic!("response has no data.{}: {}", field, body))
+}
+
+async fn verify(harness: &E2eHarness, user_id: Uuid, token: &str, code: &str) -> StatusCode {
+    let (status, _, _) = harness
+        .request(
+            Method::POST,
+            "/api/v1/auth/verify/code",
+            None,
+            Some(json!({
+                "user_id": user_id.to_string(),
+                "code": code,
+                "verification_type": "email",
+                "tenant_id": harness.tenant_id.to_string(),
+                "session_token": token,
+            })),
+        )
+        .await
+        .expect("verify request failed");
+    status
+}
+
+#[tokio::test]
+async fn test_login_and_email_mfa_flow() {
+    let harness = E2eHarness::start()
+        .await
+        .expect("e2e harness requires Docker");
+
+    // Register and log in
+    let (status, _, body) = harness
+        .request(
+            Method::POST,
+            "/api/v1/auth/register",
+            None,
+            Some(json!({
+                "email": EMAIL,
+                "password": PASSWORD,
+                "password_confirmation": PASSWORD,
+            })),
+        )
+        .await
+        .expect("register request failed");
+    assert_eq!(status, StatusCode::CREATED, "register: {}", body);
+    let user_id = Uuid::parse_str(data_field(&body, "user_id")).expect("user id");
+
+    let (status, headers, body) = harness
+        .request(
+            Method::POST,
+            "/api/v1/auth/login",
+            None,
+            Some(json!({ "email": EMAIL, "password": PASSWORD })),
+        )
+        .await
+        .expect("login request failed");
+    assert_eq!(status, StatusCode::OK, "login: {}", body);
+    assert!(
+        headers.contains_key("X-RateLimit-Limit"),
+        "rate limiter did not consult Redis"
+    );
+    let token = data_field(&body, "token").to_string();
+
+    let sessions = harness.sessions_for(user_id).await.expect("sessions");
+    assert_eq!(sessions.len(), 1);
+    let session_id = sessions[0].id;
+    assert_eq!(sessions[0].mfa_status, "NONE");
+    assert!(sessions[0].is_valid);
+
+    // Request an email code for the session
+    let (status, _, body) = harness
+        .request(
+            Method::POST,
+            "/api/v1/auth/verify/send",
+            None,
+            Some(json!({
+                "user_id": user_id.to_string(),
+                "verification_type": "email",
+                "recipient": EMAIL,
+                "tenant_id": harness.tenant_id.to_string(),
+                "session_token": token,
+            })),
+        )
+        .await
+        .expect("send request failed");
+    assert_eq!(status, StatusCode::OK, "send: {}", body);
+
+    let code = harness
+        .email
+        .last_code_for(EMAIL)
+        .expect("no verification email captured");
+    let codes = harness
+        .verification_codes_for(user_id)
+        .await
+        .expect("codes");
+    assert_eq!(codes.len(), 1);
+    assert_eq!(codes[0].code, code);
+    assert_eq!(codes[0].status, "Pending");
+
+    // A wrong code is rejected and leaves the session unverified
+    let status = verify(&harness, user_id, &token, &wrong_code(&code)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let sessions = harness.sessions_for(user_id).await.expect("sessions");
+    assert_eq!(sessions[0].id, session_id);
+    assert_eq!(sessions[0].mfa_status, "NONE");
+    assert!(sessions[0].is_valid);
+    let codes = harness
+        .verification_codes_for(user_id)
+        .await
+        .expect("codes");
+    assert_eq!(codes[0].status, "Pending");
+
+    // The right code verifies the session
+    let status = verify(&harness, user_id, &token, &code).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let sessions = harness.sessions_for(user_id).await.expect("sessions");
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].id, session_id);
+    assert_eq!(sessions[0].mfa_status, "VERIFIED");
+    assert!(sessions[0].is_valid);
+    let codes = harness
+        .verification_codes_for(user_id)
+        .await
+        .expect("codes");
+    assert_eq!(codes[0].status, "Verified");
+    assert_eq!(codes[0].attempts, 1);
+
+    // The session reports the verified state through the API
+    let (status, _, body) = harness
+        .request(Method::GET, "/api/v1/auth/mfa/status", Some(&token), None)
+        .await
+        .expect("status request failed");
+    assert_eq!(status, StatusCode::OK, "mfa status: {}", body);
+    assert_eq!(data_field(&body, "session_mfa_status"), "VERIFIED");
+
+    // A used code cannot be replayed
+    let status = verify(&harness, user_id, &token, &code).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let codes = harness
+        .verification_codes_for(user_id)
+        .await
+        .expect("codes");
+    assert_eq!(codes[0].status, "Verified");
+}
+
+#[tokio::test]
+async fn test_login_with_wrong_password_creates_no_session() {
+    let harness = E2eHarness::start()
+        .await
+        .expect("e2e harness requires Docker");
+
+    let (status, _, body) = harness
+        .request(
+            Method::POST,
+            "/api/v1/auth/register",
+            None,
+            Some(json!({
+                "email": EMAIL,
+                "password": PASSWORD,
+                "password_confirmation": PASSWORD,
+            })),
+        )
+        .await
+        .expect("register request failed");
+    assert_eq!(status, StatusCode::CREATED, "register: {}", body);
+    let user_id = Uuid::parse_str(data_field(&body, "user_id")).expect("user id");
+
+    let (status, _, _) = harness
+        .request(
+            Method::POST,
+            "/api/v1/auth/login",
+            None,
+            Some(json!({ "email": EMAIL, "password": "not-the-password" })),
+        )
+        .await
+        .expect("login request failed");
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let sessions = harness.sessions_for(user_id).await.expect("sessions");
+    assert!(sessions.is_empty());
+}
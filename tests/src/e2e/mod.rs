@@ -0,0 +1,16 @@
+//! End-to-end tests against real infrastructure
+//!
+//! These tests boot the API router on top of Postgres and Redis containers
+//! and drive complete user flows over HTTP. They need a running Docker daemon
+//! and are only compiled with the `e2e` feature:
+//!
+//! ```bash
+//! cargo test -p acci_tests --features e2e e2e::
+//! ```
+
+pub mod harness;
+
+#[cfg(test)]
+mod login_mfa;
+
+pub use harness::{CapturingMessageProvider, E2eHarness, SessionRow, VerificationCodeRow};
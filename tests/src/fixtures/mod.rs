@@ -1,7 +1,92 @@
 //! Test fixtures for integration tests
 //!
-//! This module contains reusable test data and fixtures.
+//! Builders for users, tenants and sessions with seeded defaults. Each
+//! fixture can `build()` a value for in-memory mocks or `persist(&pool)` it
+//! into the test database:
+//!
+//! ```ignore
+//! let user = UserFixture::new().verified().persist(&pool).await?;
+//! let session = SessionFixture::for_user(user.id).persist(&pool).await?;
+//! let world = TestTenantWorld::create(&pool, 3).await?;
+//! ```
+//!
+//! Defaults are reproducible; see [`rng`] for replaying a failing run.
+
+pub mod rng;
+pub mod sessions;
+pub mod tenants;
+pub mod users;
+pub mod world;
+
+pub use sessions::SessionFixture;
+pub use tenants::TenantFixture;
+pub use users::UserFixture;
+pub use world::TestTenantWorld;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use acci_auth::TenantPlanType;
+
+    // Every test thread starts from the same seed
+    fn on_fresh_thread<T: Send + 'static>(f: fn() -> T) -> T {
+        std::thread::spawn(f)
+            .join()
+            .expect("fixture thread panicked")
+    }
+
+    #[test]
+    fn test_defaults_are_reproducible_per_thread() {
+        let first = on_fresh_thread(|| {
+            let user = UserFixture::new().build();
+            let tenant = TenantFixture::new().build();
+            (user.id, user.email, tenant.id, tenant.subdomain)
+        });
+        let second = on_fresh_thread(|| {
+            let user = UserFixture::new().build();
+            let tenant = TenantFixture::new().build();
+            (user.id, user.email, tenant.id, tenant.subdomain)
+        });
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_consecutive_fixtures_differ() {
+        let a = UserFixture::new();
+        let b = UserFixture::new();
+
+        assert_ne!(a.id(), b.id());
+        assert_ne!(a.email(), b.email());
+    }
+
+    #[test]
+    fn test_builder_overrides() {
+        let user = UserFixture::new()
+            .verified()
+            .with_email("someone@example.com")
+            .build();
+        assert!(user.is_verified);
+        assert!(user.is_active);
+        assert_eq!(user.email, "someone@example.com");
+
+        let tenant = TenantFixture::with_plan(TenantPlanType::Professional);
+        assert_eq!(tenant.plan(), Some(TenantPlanType::Professional));
+
+        let session = SessionFixture::expired().build();
+        assert!(session.is_valid);
+        assert!(session.expires_at < time::OffsetDateTime::now_utc());
+    }
+
+    #[test]
+    fn test_world_links_sessions_to_users() {
+        let world = TestTenantWorld::build(3);
 
-// Example:
-// pub mod users;
-// pub mod organizations;
+        assert_eq!(world.users.len(), 3);
+        assert_eq!(world.sessions.len(), 4);
+        assert_eq!(world.sessions[0].user_id, world.admin.id);
+        for user in &world.users {
+            assert_eq!(world.sessions_for(user.id).len(), 1);
+        }
+    }
+}
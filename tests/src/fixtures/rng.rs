@@ -0,0 +1,58 @@
+//! Seeded randomness for fixture defaults
+//!
+//! All fixture defaults are drawn from a per-thread RNG seeded from
+//! [`SEED_ENV`]. Each test runs on its own thread, so a test sees the same
+//! sequence of values for the same seed no matter which other tests run
+//! alongside it. When the variable is unset a random seed is chosen and
+//! printed once, so a failing run can be replayed with
+//! `ACCI_FIXTURE_SEED=<seed> cargo test ...`.
+
+use once_cell::sync::Lazy;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::cell::RefCell;
+use uuid::{Builder, Uuid};
+
+/// Environment variable holding the fixture seed
+pub const SEED_ENV: &str = "ACCI_FIXTURE_SEED";
+
+static SEED: Lazy<u64> = Lazy::new(|| {
+    let seed = std::env::var(SEED_ENV)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or_else(|| rand::rng().random());
+    eprintln!("fixtures: using {}={}", SEED_ENV, seed);
+    seed
+});
+
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::seed_from_u64(*SEED));
+}
+
+/// The seed fixture defaults are derived from
+pub fn seed() -> u64 {
+    *SEED
+}
+
+/// Run `f` with the current thread's fixture RNG
+pub fn with_rng<R>(f: impl FnOnce(&mut StdRng) -> R) -> R {
+    RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
+
+/// A random (version 4) UUID drawn from the fixture RNG
+pub fn uuid() -> Uuid {
+    Builder::from_random_bytes(with_rng(|rng| rng.random())).into_uuid()
+}
+
+/// A lowercase hex string with `bytes` random bytes
+pub fn hex(bytes: usize) -> String {
+    with_rng(|rng| {
+        (0..bytes)
+            .map(|_| format!("{:02x}", rng.random::<u8>()))
+            .collect()
+    })
+}
+
+/// Pick one of `items`
+pub fn pick<T: Copy>(items: &[T]) -> T {
+    with_rng(|rng| items[rng.random_range(0..items.len())])
+}
@@ -0,0 +1,187 @@
+//! Session fixtures
+
+use acci_auth::{MfaStatus, Session, SessionInvalidationReason, SessionKind};
+use acci_core::datetime;
+use anyhow::Result;
+use serde_json::Value;
+use sqlx::PgPool;
+use std::time::Duration;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use super::rng;
+
+/// Lifetime of fixture sessions unless overridden
+pub const DEFAULT_SESSION_LIFETIME: Duration = Duration::from_secs(60 * 60);
+
+const USER_AGENTS: &[&str] = &[
+    "Mozilla/5.0 (X11; Linux x86_64) Gecko/20100101 Firefox/124.0",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 14_4) AppleWebKit/605.1.15 Safari/605.1.15",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 Chrome/123.0 Safari/537.36",
+];
+
+/// Builder for [`Session`] values
+///
+/// The token hash is an opaque random value; tests that need a hash produced
+/// by the session service should set it with [`SessionFixture::with_token_hash`].
+#[derive(Debug, Clone)]
+pub struct SessionFixture {
+    id: Uuid,
+    user_id: Uuid,
+    token_hash: String,
+    created_at: OffsetDateTime,
+    expires_at: OffsetDateTime,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+    device_id: Option<String>,
+    invalidated_reason: Option<SessionInvalidationReason>,
+    metadata: Option<Value>,
+    mfa_status: MfaStatus,
+}
+
+impl SessionFixture {
+    /// A valid session for a random user that expires in an hour
+    ///
+    /// Timestamps have microsecond precision, so a persisted session equals
+    /// the one loaded back from the database.
+    pub fn new() -> Self {
+        let now = datetime::now_micros();
+        Self {
+            id: rng::uuid(),
+            user_id: rng::uuid(),
+            token_hash: rng::hex(32),
+            created_at: now,
+            expires_at: now + DEFAULT_SESSION_LIFETIME,
+            ip_address: Some(format!(
+                "10.0.{}.{}",
+                rng::pick(&[0, 1, 2]),
+                rng::pick(&[7, 42, 99])
+            )),
+            user_agent: Some(rng::pick(USER_AGENTS).to_string()),
+            device_id: Some(format!("device-{}", rng::hex(4))),
+            invalidated_reason: None,
+            metadata: None,
+            mfa_status: MfaStatus::None,
+        }
+    }
+
+    /// A valid session for `user_id`
+    pub fn for_user(user_id: Uuid) -> Self {
+        Self {
+            user_id,
+            ..Self::new()
+        }
+    }
+
+    /// A session whose expiry lies in the past but that has not been cleaned up yet
+    pub fn expired() -> Self {
+        let now = datetime::now_micros();
+        Self {
+            created_at: now - 2 * DEFAULT_SESSION_LIFETIME,
+            expires_at: now - DEFAULT_SESSION_LIFETIME,
+            ..Self::new()
+        }
+    }
+
+    pub fn with_user(mut self, user_id: Uuid) -> Self {
+        self.user_id = user_id;
+        self
+    }
+
+    pub fn with_token_hash(mut self, token_hash: impl Into<String>) -> Self {
+        self.token_hash = token_hash.into();
+        self
+    }
+
+    pub fn with_ip(mut self, ip_address: impl Into<String>) -> Self {
+        self.ip_address = Some(ip_address.into());
+        self
+    }
+
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: Value) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    pub fn with_mfa_status(mut self, mfa_status: MfaStatus) -> Self {
+        self.mfa_status = mfa_status;
+        self
+    }
+
+    /// Expire the session `after` from now
+    pub fn expires_in(mut self, after: Duration) -> Self {
+        self.expires_at = datetime::now_micros() + after;
+        self
+    }
+
+    /// Mark the session invalid for `reason`
+    pub fn invalidated(mut self, reason: SessionInvalidationReason) -> Self {
+        self.invalidated_reason = Some(reason);
+        self
+    }
+
+    /// Build the session without touching the database
+    pub fn build(&self) -> Session {
+        Session {
+            id: self.id,
+            user_id: self.user_id,
+            kind: SessionKind::User,
+            token_hash: self.token_hash.clone(),
+            previous_token_hash: None,
+            token_rotation_at: None,
+            expires_at: self.expires_at,
+            created_at: self.created_at,
+            last_activity_at: self.created_at,
+            last_activity_update_at: None,
+            ip_address: self.ip_address.clone(),
+            user_agent: self.user_agent.clone(),
+            user_agent_details: None,
+            region: None,
+            device_id: self.device_id.clone(),
+            device_fingerprint: None,
+            is_valid: self.invalidated_reason.is_none(),
+            invalidated_reason: self.invalidated_reason.clone(),
+            metadata: self.metadata.clone(),
+            mfa_status: self.mfa_status.clone(),
+        }
+    }
+
+    /// Insert the session into the test database
+    ///
+    /// The user must already exist.
+    pub async fn persist(&self, pool: &PgPool) -> Result<Session> {
+        let session = self.build();
+        sqlx::query(
+            "INSERT INTO sessions \
+             (id, user_id, token_hash, expires_at, created_at, last_activity_at, ip_address, \
+              user_agent, device_id, is_valid, invalidated_reason, metadata, mfa_status) \
+             VALUES ($1, $2, $3, $4, $5, $5, $6::inet, $7, $8, $9, $10, $11, $12)",
+        )
+        .bind(session.id)
+        .bind(session.user_id)
+        .bind(&session.token_hash)
+        .bind(session.expires_at)
+        .bind(session.created_at)
+        .bind(&session.ip_address)
+        .bind(&session.user_agent)
+        .bind(&session.device_id)
+        .bind(session.is_valid)
+        .bind(&session.invalidated_reason)
+        .bind(&session.metadata)
+        .bind(session.mfa_status.to_string())
+        .execute(pool)
+        .await?;
+        Ok(session)
+    }
+}
+
+impl Default for SessionFixture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
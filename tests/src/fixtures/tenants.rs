@@ -0,0 +1,155 @@
+//! Tenant fixtures
+
+use acci_auth::{Tenant, TenantPlanType, TenantStatus};
+use anyhow::Result;
+use serde_json::Value;
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use super::rng;
+
+const NAMES: &[&str] = &["Acme", "Globex", "Initech", "Umbrella", "Hooli", "Stark"];
+
+/// Builder for [`Tenant`] values and their subscription
+#[derive(Debug, Clone)]
+pub struct TenantFixture {
+    id: Uuid,
+    name: String,
+    subdomain: String,
+    status: TenantStatus,
+    metadata: Option<Value>,
+    plan: Option<TenantPlanType>,
+}
+
+impl TenantFixture {
+    /// An active tenant without a subscription
+    pub fn new() -> Self {
+        let name = rng::pick(NAMES);
+        let suffix = rng::hex(4);
+        Self {
+            id: rng::uuid(),
+            name: format!("{} {}", name, suffix),
+            subdomain: format!("{}-{}", name.to_lowercase(), suffix),
+            status: TenantStatus::Active,
+            metadata: None,
+            plan: None,
+        }
+    }
+
+    /// An active tenant with an active subscription to `plan`
+    pub fn with_plan(plan: TenantPlanType) -> Self {
+        Self {
+            plan: Some(plan),
+            ..Self::new()
+        }
+    }
+
+    pub fn with_id(mut self, id: Uuid) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn with_subdomain(mut self, subdomain: impl Into<String>) -> Self {
+        self.subdomain = subdomain.into();
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: Value) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    pub fn suspended(mut self, reason: impl Into<String>) -> Self {
+        self.status = TenantStatus::Suspended {
+            reason: reason.into(),
+            since: OffsetDateTime::now_utc(),
+        };
+        self
+    }
+
+    pub fn archived(mut self) -> Self {
+        self.status = TenantStatus::Archived {
+            since: OffsetDateTime::now_utc(),
+        };
+        self
+    }
+
+    /// Subscription plan, if any
+    pub fn plan(&self) -> Option<TenantPlanType> {
+        self.plan
+    }
+
+    /// Build the tenant without touching the database
+    pub fn build(&self) -> Tenant {
+        let now = OffsetDateTime::now_utc();
+        Tenant {
+            id: self.id,
+            name: self.name.clone(),
+            subdomain: self.subdomain.clone(),
+            status: self.status.clone(),
+            created_at: now,
+            updated_at: now,
+            metadata: self.metadata.clone(),
+        }
+    }
+
+    /// Insert the tenant, and its subscription if a plan is set
+    pub async fn persist(&self, pool: &PgPool) -> Result<Tenant> {
+        let tenant = self.build();
+        sqlx::query(
+            "INSERT INTO tenants (id, name, subdomain, status, status_reason, status_changed_at, \
+             created_at, updated_at, metadata) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+        )
+        .bind(tenant.id)
+        .bind(&tenant.name)
+        .bind(&tenant.subdomain)
+        .bind(tenant.status.as_str())
+        .bind(tenant.status.reason())
+        .bind(tenant.status.since())
+        .bind(tenant.created_at)
+        .bind(tenant.updated_at)
+        .bind(&tenant.metadata)
+        .execute(pool)
+        .await?;
+
+        if let Some(plan) = self.plan {
+            sqlx::query(
+                "INSERT INTO tenant_subscriptions (tenant_id, plan_type, starts_at, is_active) \
+                 VALUES ($1, $2, $3, true)",
+            )
+            .bind(tenant.id)
+            .bind(plan)
+            .bind(tenant.created_at)
+            .execute(pool)
+            .await?;
+        }
+
+        Ok(tenant)
+    }
+
+    /// Add an existing user to a persisted tenant with the given role
+    pub async fn add_user(pool: &PgPool, tenant_id: Uuid, user_id: Uuid, role: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO tenant_users (tenant_id, user_id, tenant_role) VALUES ($1, $2, $3)",
+        )
+        .bind(tenant_id)
+        .bind(user_id)
+        .bind(role)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+}
+
+impl Default for TenantFixture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
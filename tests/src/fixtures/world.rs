@@ -0,0 +1,93 @@
+//! Composite fixture for a populated tenant
+
+use acci_auth::{Session, Tenant, TenantPlanType, User};
+use anyhow::Result;
+use sqlx::PgPool;
+
+use super::{SessionFixture, TenantFixture, UserFixture};
+
+/// Role given to the tenant administrator
+pub const ADMIN_ROLE: &str = "ADMIN";
+/// Role given to regular tenant members
+pub const MEMBER_ROLE: &str = "USER";
+
+/// A tenant with an administrator, `N` members and one session per user
+#[derive(Debug, Clone)]
+pub struct TestTenantWorld {
+    pub tenant: Tenant,
+    pub admin: User,
+    pub users: Vec<User>,
+    /// Sessions in the order admin, then `users`
+    pub sessions: Vec<Session>,
+}
+
+impl TestTenantWorld {
+    /// Build a world on a Professional plan without touching the database
+    pub fn build(user_count: usize) -> Self {
+        let (tenant, admin, users, sessions) = Self::fixtures(user_count);
+        Self {
+            tenant: tenant.build(),
+            admin: admin.build(),
+            users: users.iter().map(UserFixture::build).collect(),
+            sessions: sessions.iter().map(SessionFixture::build).collect(),
+        }
+    }
+
+    /// Create a world on a Professional plan in the test database
+    pub async fn create(pool: &PgPool, user_count: usize) -> Result<Self> {
+        let (tenant, admin, users, sessions) = Self::fixtures(user_count);
+
+        let tenant = tenant.persist(pool).await?;
+        let admin = admin.persist(pool).await?;
+        TenantFixture::add_user(pool, tenant.id, admin.id, ADMIN_ROLE).await?;
+
+        let mut persisted_users = Vec::with_capacity(users.len());
+        for user in &users {
+            let user = user.persist(pool).await?;
+            TenantFixture::add_user(pool, tenant.id, user.id, MEMBER_ROLE).await?;
+            persisted_users.push(user);
+        }
+
+        let mut persisted_sessions = Vec::with_capacity(sessions.len());
+        for session in &sessions {
+            persisted_sessions.push(session.persist(pool).await?);
+        }
+
+        Ok(Self {
+            tenant,
+            admin,
+            users: persisted_users,
+            sessions: persisted_sessions,
+        })
+    }
+
+    /// Sessions belonging to `user_id`
+    pub fn sessions_for(&self, user_id: uuid::Uuid) -> Vec<&Session> {
+        self.sessions
+            .iter()
+            .filter(|session| session.user_id == user_id)
+            .collect()
+    }
+
+    // All values are drawn up front so `build` and `create` see the same data
+    // for the same seed
+    fn fixtures(
+        user_count: usize,
+    ) -> (
+        TenantFixture,
+        UserFixture,
+        Vec<UserFixture>,
+        Vec<SessionFixture>,
+    ) {
+        let tenant = TenantFixture::with_plan(TenantPlanType::Professional);
+        let admin = UserFixture::new().verified();
+        let users: Vec<UserFixture> = (0..user_count)
+            .map(|_| UserFixture::new().verified())
+            .collect();
+        let sessions = std::iter::once(&admin)
+            .chain(&users)
+            .map(|user| SessionFixture::for_user(user.id()))
+            .collect();
+        (tenant, admin, users, sessions)
+    }
+}
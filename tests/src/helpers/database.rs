@@ -7,6 +7,13 @@ use testcontainers_modules::{
 };
 
 pub async fn setup_test_db() -> Result<(Box<dyn std::any::Any>, PgPool)> {
+    let (container, pool, _connection_string) = setup_test_db_with_url().await?;
+    Ok((container, pool))
+}
+
+/// Like [`setup_test_db`], but also returns the connection string for
+/// components that open their own pool
+pub async fn setup_test_db_with_url() -> Result<(Box<dyn std::any::Any>, PgPool, String)> {
     // Start Postgres container
     let container = postgres::Postgres::default()
         .with_tag("16-alpine")
@@ -33,7 +40,7 @@ pub async fn setup_test_db() -> Result<(Box<dyn std::any::Any>, PgPool)> {
         .run(&pool)
         .await?;
 
-    Ok((Box::new(container), pool))
+    Ok((Box::new(container), pool, connection_string))
 }
 
 #[cfg(test)]
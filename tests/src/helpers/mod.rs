@@ -3,5 +3,9 @@
 //! This module contains shared test utilities and helper functions.
 
 pub mod database;
+pub mod redis_server;
+pub mod security_stack;
 
-pub use database::setup_test_db;
+pub use database::{setup_test_db, setup_test_db_with_url};
+pub use redis_server::{RedisGuard, setup_test_redis};
+pub use security_stack::{SecurityStack, setup_security_stack, setup_security_stack_with_config};
@@ -0,0 +1,75 @@
+//! Redis container for tests of Redis-backed components
+//!
+//! Starting Redis takes a few seconds, so tests running in the same process
+//! share one container for as long as any of them holds its guard. Tests must
+//! therefore keep their keys apart, e.g. by using a fresh tenant ID each.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use std::sync::{Arc, Weak};
+use testcontainers_modules::{
+    redis::{REDIS_PORT, Redis},
+    testcontainers::{ContainerAsync, runners::AsyncRunner},
+};
+use tokio::sync::Mutex;
+
+/// Keeps the shared Redis container alive; the container is removed once the
+/// last guard is dropped
+pub type RedisGuard = Arc<ContainerAsync<Redis>>;
+
+static SHARED_REDIS: Lazy<Mutex<Weak<ContainerAsync<Redis>>>> =
+    Lazy::new(|| Mutex::new(Weak::new()));
+
+/// Start (or join) the shared Redis container and open a client on it
+pub async fn setup_test_redis() -> Result<(RedisGuard, Arc<redis::Client>)> {
+    let container = {
+        let mut shared = SHARED_REDIS.lock().await;
+        match shared.upgrade() {
+            Some(container) => container,
+            None => {
+                let container = Arc::new(
+                    Redis::default()
+                        .start()
+                        .await
+                        .context("failed to start Redis")?,
+                );
+                *shared = Arc::downgrade(&container);
+                container
+            },
+        }
+    };
+
+    let port = container.get_host_port_ipv4(REDIS_PORT).await?;
+    let client = redis::Client::open(format!("redis://127.0.0.1:{}", port))?;
+
+    Ok((container, Arc::new(client)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_redis_setup() {
+        let Ok((first, client)) = setup_test_redis().await else {
+            eprintln!("Skipping test_redis_setup: Docker not available");
+            return;
+        };
+
+        let mut conn = client
+            .get_async_connection()
+            .await
+            .expect("Failed to connect to Redis");
+        let pong: String = redis::cmd("PING")
+            .query_async(&mut conn)
+            .await
+            .expect("Failed to ping Redis");
+        assert_eq!(pong, "PONG");
+
+        // A second setup while the first guard is alive joins the same container
+        let Ok((second, _)) = setup_test_redis().await else {
+            panic!("Joining the running Redis container failed");
+        };
+        assert!(Arc::ptr_eq(&first, &second));
+    }
+}
@@ -0,0 +1,47 @@
+//! [`SecurityProtection`] backed by real Postgres and Redis containers
+
+use acci_auth::security::{SecurityConfig, SecurityProtection, create_security_protection};
+use anyhow::Result;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use super::database::setup_test_db;
+use super::redis_server::{RedisGuard, setup_test_redis};
+
+/// Security services wired to the test containers
+///
+/// The containers stay up as long as the stack is alive.
+pub struct SecurityStack {
+    _postgres: Box<dyn std::any::Any>,
+    _redis: RedisGuard,
+    /// Pool on the migrated test database
+    pub pool: PgPool,
+    /// The security services under test
+    pub protection: Arc<SecurityProtection>,
+}
+
+impl SecurityStack {
+    /// Redis client the services are connected to
+    pub fn redis_client(&self) -> Arc<redis::Client> {
+        self.protection.redis_client.clone()
+    }
+}
+
+/// Start the containers and build [`SecurityProtection`] with the default configuration
+pub async fn setup_security_stack() -> Result<SecurityStack> {
+    setup_security_stack_with_config(SecurityConfig::default()).await
+}
+
+/// Like [`setup_security_stack`], with a custom configuration
+pub async fn setup_security_stack_with_config(config: SecurityConfig) -> Result<SecurityStack> {
+    let (postgres, pool) = setup_test_db().await?;
+    let (redis, redis_client) = setup_test_redis().await?;
+    let protection = create_security_protection(redis_client, pool.clone(), config)?;
+
+    Ok(SecurityStack {
+        _postgres: postgres,
+        _redis: redis,
+        pool,
+        protection,
+    })
+}
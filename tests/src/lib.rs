@@ -5,8 +5,11 @@
 
 #[cfg(test)]
 pub mod api;
+#[cfg(test)]
 pub mod auth;
 pub mod database;
+#[cfg(feature = "e2e")]
+pub mod e2e;
 pub mod fixtures;
 pub mod helpers;
 pub mod mocks;
@@ -17,3 +20,4 @@ pub mod security;
 // - Auth tests: Authentication system component tests
 // - Database tests: Testing database operations and migrations
 // - Security tests: Security audit tests for authentication flow
+// - E2E tests: Full flows against Postgres and Redis (`e2e` feature)
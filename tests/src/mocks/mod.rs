@@ -1,8 +1,6 @@
 //! Mock implementations for integration tests
 //!
-//! This module contains mock implementations of services and dependencies.
+//! The mocks live in `acci_auth` behind its `test-support` feature; they are
+//! re-exported here so integration tests share one set of test doubles.
 
-// Platzhalter für zukünftige Mock-Implementierungen
-// Wir werden diese später implementieren, wenn wir die Tests schreiben
-
-pub struct MockPlaceholder;
+pub use acci_auth::test_support::*;
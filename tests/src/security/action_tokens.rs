@@ -0,0 +1,43 @@
+use acci_core::tokens::{ActionToken, ActionTokenError, ActionTokenKeys};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::helpers::setup_test_redis;
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+#[tokio::test]
+async fn test_action_token_is_consumed_once() {
+    let (_redis, client) = setup_test_redis().await.expect("Failed to start Redis");
+    let tokens =
+        ActionToken::new(ActionTokenKeys::new("k1", b"action-secret".to_vec())).with_redis(client);
+    let user = Uuid::new_v4().to_string();
+
+    let token = tokens.issue("password-reset", &user, None, HOUR);
+    let claims = tokens
+        .consume_once(&token, "password-reset")
+        .await
+        .expect("First use succeeds");
+    assert_eq!(claims.subject, user);
+
+    assert!(matches!(
+        tokens.consume_once(&token, "password-reset").await,
+        Err(ActionTokenError::AlreadyConsumed)
+    ));
+    // Verification alone does not consult the consumed tokens
+    assert!(tokens.verify(&token, "password-reset").is_ok());
+
+    // A revoked token cannot be used at all
+    let revoked = tokens.issue("password-reset", &user, None, HOUR);
+    let claims = tokens
+        .verify(&revoked, "password-reset")
+        .expect("Token is valid");
+    tokens
+        .revoke(&claims)
+        .await
+        .expect("Failed to revoke token");
+    assert!(matches!(
+        tokens.consume_once(&revoked, "password-reset").await,
+        Err(ActionTokenError::AlreadyConsumed)
+    ));
+}
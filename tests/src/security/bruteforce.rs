@@ -0,0 +1,268 @@
+use acci_auth::security::{
+    BruteForceConfig, BruteForceError, BruteForceProtection, Challenge, RiskLevel, SecurityConfig,
+};
+use acci_auth::utils::clock::TestClock;
+use acci_auth::{DurationMs, DurationSecs};
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::helpers::{setup_security_stack_with_config, setup_test_redis};
+
+const KEY: &str = "user@example.com";
+
+fn config() -> BruteForceConfig {
+    BruteForceConfig {
+        max_attempts: 3,
+        window_seconds: DurationSecs::from_secs(60),
+        base_delay_ms: DurationMs::from_millis(1),
+        max_delay_ms: DurationMs::from_millis(4),
+        ..BruteForceConfig::default()
+    }
+}
+
+#[tokio::test]
+async fn test_lockout_after_max_attempts() {
+    let stack = setup_security_stack_with_config(SecurityConfig {
+        brute_force: config(),
+        ..SecurityConfig::default()
+    })
+    .await
+    .expect("Failed to start security stack");
+    let brute_force = &stack.protection.brute_force;
+    let tenant = Uuid::new_v4().to_string();
+
+    for expected_remaining in [2, 1] {
+        brute_force
+            .check_authentication_attempt(&tenant, KEY, false)
+            .await
+            .expect("Attempt below the limit is allowed");
+        assert_eq!(
+            brute_force
+                .remaining_attempts(&tenant, KEY)
+                .await
+                .expect("remaining"),
+            expected_remaining
+        );
+        assert!(
+            !brute_force
+                .is_account_locked(&tenant, KEY)
+                .await
+                .expect("locked")
+        );
+    }
+
+    let result = brute_force
+        .check_authentication_attempt(&tenant, KEY, false)
+        .await;
+    assert!(matches!(result, Err(BruteForceError::AccountLocked)));
+    assert!(
+        brute_force
+            .is_account_locked(&tenant, KEY)
+            .await
+            .expect("locked")
+    );
+    assert_eq!(
+        brute_force
+            .remaining_attempts(&tenant, KEY)
+            .await
+            .expect("remaining"),
+        0
+    );
+
+    // The counter is per tenant and per key
+    let other_tenant = Uuid::new_v4().to_string();
+    assert!(
+        !brute_force
+            .is_account_locked(&other_tenant, KEY)
+            .await
+            .expect("locked")
+    );
+    assert!(
+        !brute_force
+            .is_account_locked(&tenant, "other@example.com")
+            .await
+            .expect("locked")
+    );
+
+    // A successful login clears the counter
+    brute_force
+        .check_authentication_attempt(&tenant, KEY, true)
+        .await
+        .expect("Successful attempt resets the counter");
+    assert!(
+        !brute_force
+            .is_account_locked(&tenant, KEY)
+            .await
+            .expect("locked")
+    );
+    assert_eq!(
+        brute_force
+            .remaining_attempts(&tenant, KEY)
+            .await
+            .expect("remaining"),
+        3
+    );
+}
+
+#[tokio::test]
+async fn test_attempts_outside_window_are_not_counted() {
+    let (_redis, client) = setup_test_redis().await.expect("Failed to start Redis");
+    let clock = TestClock::new(OffsetDateTime::now_utc());
+    let brute_force = BruteForceProtection::with_clock(client, config(), clock.clone());
+    let tenant = Uuid::new_v4().to_string();
+
+    brute_force
+        .record_attempt(&tenant, KEY)
+        .await
+        .expect("record");
+    brute_force
+        .record_attempt(&tenant, KEY)
+        .await
+        .expect("record");
+    assert_eq!(
+        brute_force
+            .remaining_attempts(&tenant, KEY)
+            .await
+            .expect("remaining"),
+        1
+    );
+    assert_eq!(
+        brute_force
+            .calculate_delay(&tenant, KEY)
+            .await
+            .expect("delay"),
+        std::time::Duration::from_millis(2)
+    );
+
+    clock.advance(Duration::seconds(61));
+    brute_force
+        .record_attempt(&tenant, KEY)
+        .await
+        .expect("record");
+
+    assert_eq!(
+        brute_force
+            .remaining_attempts(&tenant, KEY)
+            .await
+            .expect("remaining"),
+        2
+    );
+    assert!(
+        !brute_force
+            .is_account_locked(&tenant, KEY)
+            .await
+            .expect("locked")
+    );
+    assert_eq!(
+        brute_force
+            .calculate_delay(&tenant, KEY)
+            .await
+            .expect("delay"),
+        std::time::Duration::from_millis(1)
+    );
+}
+
+fn dual_config() -> BruteForceConfig {
+    BruteForceConfig {
+        ip_max_attempts: 4,
+        ip_window_seconds: DurationSecs::from_secs(60),
+        ip_success_decay: 2,
+        ..config()
+    }
+}
+
+#[tokio::test]
+async fn test_rotating_ips_are_caught_by_username_counter() {
+    let (_redis, client) = setup_test_redis().await.expect("Failed to start Redis");
+    let brute_force = BruteForceProtection::new(client, dual_config());
+    let tenant = Uuid::new_v4().to_string();
+
+    // Every guess comes from a fresh IP, so no IP counter ever fills up
+    let mut status = None;
+    for i in 0..3 {
+        let ip = format!("203.0.113.{i}");
+        status = Some(
+            brute_force
+                .record_login_attempt(&tenant, KEY, &ip, false)
+                .await
+                .expect("record"),
+        );
+    }
+    let status = status.expect("attempts were recorded");
+
+    assert_eq!(status.username.attempts, 3);
+    assert!(status.username.is_locked());
+    assert_eq!(status.ip.attempts, 1);
+    assert!(!status.ip.is_locked());
+    assert_eq!(status.risk_level, RiskLevel::High);
+    assert!(matches!(status.challenge(), Challenge::MfaRequired));
+    assert!(status.notify_user());
+
+    // The lockout is visible from any IP
+    let status = brute_force
+        .get_protection_status(KEY, "198.51.100.1", &tenant)
+        .await
+        .expect("status");
+    assert!(status.username.is_locked());
+    assert_eq!(status.ip.attempts, 0);
+    assert!(matches!(status.challenge(), Challenge::MfaRequired));
+}
+
+#[tokio::test]
+async fn test_shared_ip_is_throttled_without_locking_users() {
+    let (_redis, client) = setup_test_redis().await.expect("Failed to start Redis");
+    let brute_force = BruteForceProtection::new(client, dual_config());
+    let tenant = Uuid::new_v4().to_string();
+    let office_ip = "192.0.2.10";
+
+    // Several colleagues mistype their password once each
+    for i in 0..5 {
+        brute_force
+            .record_login_attempt(&tenant, &format!("user{i}@example.com"), office_ip, false)
+            .await
+            .expect("record");
+    }
+
+    let status = brute_force
+        .get_protection_status("user0@example.com", office_ip, &tenant)
+        .await
+        .expect("status");
+    assert_eq!(status.ip.attempts, 5);
+    assert!(status.ip.is_locked());
+    assert!(!status.username.is_locked());
+    assert_eq!(status.risk_level, RiskLevel::Medium);
+    // Two attempts past the threshold of four
+    assert_eq!(status.ip_delay_ms, 2);
+    assert!(matches!(status.challenge(), Challenge::Delay(2)));
+    assert!(!status.notify_user());
+}
+
+#[tokio::test]
+async fn test_successful_login_decays_ip_counter() {
+    let (_redis, client) = setup_test_redis().await.expect("Failed to start Redis");
+    let brute_force = BruteForceProtection::new(client, dual_config());
+    let tenant = Uuid::new_v4().to_string();
+    let ip = "198.51.100.20";
+
+    for _ in 0..2 {
+        brute_force
+            .record_login_attempt(&tenant, KEY, ip, false)
+            .await
+            .expect("record");
+    }
+    brute_force
+        .record_login_attempt(&tenant, "typo@example.com", ip, false)
+        .await
+        .expect("record");
+
+    let status = brute_force
+        .record_login_attempt(&tenant, KEY, ip, true)
+        .await
+        .expect("record");
+
+    // The username counter is reset, the IP counter only loses two failures
+    assert_eq!(status.username.attempts, 0);
+    assert_eq!(status.ip.attempts, 1);
+    assert_eq!(status.risk_level, RiskLevel::Low);
+    assert!(matches!(status.challenge(), Challenge::None));
+}
@@ -0,0 +1,293 @@
+//! Scenarios every security backend has to pass
+//!
+//! Each scenario runs against the memory backend and, with the
+//! `security-integration` feature, against Redis, so both backends keep
+//! behaving the same.
+
+use acci_auth::security::config::RateLimit;
+use acci_auth::security::{
+    BruteForceConfig, BruteForceError, SecurityBackend, SecurityConfig, SecurityProtection,
+    create_security_protection,
+};
+use acci_auth::{DurationMs, DurationSecs};
+use std::sync::Arc;
+use uuid::Uuid;
+
+const USERNAME: &str = "user@example.com";
+const IP: &str = "192.0.2.10";
+
+fn config() -> SecurityConfig {
+    SecurityConfig {
+        brute_force: BruteForceConfig {
+            max_attempts: 3,
+            window_seconds: DurationSecs::from_secs(60),
+            base_delay_ms: DurationMs::from_millis(1),
+            max_delay_ms: DurationMs::from_millis(4),
+            ip_max_attempts: 4,
+            ip_window_seconds: DurationSecs::from_secs(60),
+            ip_success_decay: 2,
+            ..BruteForceConfig::default()
+        },
+        ..SecurityConfig::default()
+    }
+}
+
+fn rate_limit() -> RateLimit {
+    RateLimit {
+        window_seconds: DurationSecs::from_secs(60),
+        max_requests: 2,
+        backoff_multiplier: 2.0,
+    }
+}
+
+/// Protection on the memory backend, with clients that are never contacted
+fn memory_protection() -> Arc<SecurityProtection> {
+    let redis_client =
+        Arc::new(redis::Client::open("redis://127.0.0.1:1").expect("valid Redis URL"));
+    let pool =
+        sqlx::PgPool::connect_lazy("postgres://localhost:1/unused").expect("valid database URL");
+    create_security_protection(
+        redis_client,
+        pool,
+        SecurityConfig {
+            backend: SecurityBackend::Memory,
+            ..config()
+        },
+    )
+    .expect("Failed to create memory backend")
+}
+
+async fn rate_limit_backs_off_and_resets(protection: &SecurityProtection) {
+    let store = &protection.rate_store;
+    let tenant = Uuid::new_v4().to_string();
+    let limit = rate_limit();
+    let check = || {
+        let (tenant, limit) = (&tenant, &limit);
+        async move {
+            store
+                .check_rate_limit(tenant, "client", limit)
+                .await
+                .expect("Failed to check rate limit")
+        }
+    };
+
+    let first = check().await;
+    assert!(!first.limit_exceeded);
+    assert_eq!(first.remaining, 1);
+    assert_eq!(check().await.remaining, 0);
+
+    let exceeded = check().await;
+    assert!(exceeded.limit_exceeded);
+    assert_eq!(exceeded.limit, 2);
+    // The backoff halves the limit
+    assert_eq!(check().await.limit, 1);
+
+    // Limits are per tenant
+    let other_tenant = Uuid::new_v4().to_string();
+    assert!(
+        !store
+            .check_rate_limit(&other_tenant, "client", &limit)
+            .await
+            .expect("Failed to check rate limit")
+            .limit_exceeded
+    );
+
+    store
+        .reset_backoff(&tenant, "client", 60)
+        .await
+        .expect("Failed to reset backoff");
+    assert_eq!(check().await.limit, 2);
+}
+
+async fn nonce_is_validated_once(protection: &SecurityProtection) {
+    let nonces = &protection.nonce_store;
+    let tenant = Uuid::new_v4().to_string();
+    let validate = |context: &'static str, nonce: String| {
+        let tenant = tenant.clone();
+        async move {
+            nonces
+                .validate_nonce(&tenant, context, &nonce, None)
+                .await
+                .expect("Failed to validate nonce")
+        }
+    };
+
+    let nonce = nonces
+        .generate_nonce(&tenant, "transfer")
+        .await
+        .expect("Failed to generate nonce");
+    assert!(!validate("login", nonce.clone()).await);
+    assert!(validate("transfer", nonce.clone()).await);
+    assert!(!validate("transfer", nonce).await);
+    assert!(!validate("transfer", "unknown".to_string()).await);
+
+    let token = nonces
+        .generate_csrf_token(&tenant, "profile")
+        .await
+        .expect("Failed to generate CSRF token");
+    assert!(
+        !nonces
+            .validate_csrf_token(&tenant, "password", &token)
+            .await
+            .expect("Failed to validate CSRF token")
+    );
+    assert!(
+        nonces
+            .validate_csrf_token(&tenant, "profile", &token)
+            .await
+            .expect("Failed to validate CSRF token")
+    );
+}
+
+async fn record_once_rejects_replays(protection: &SecurityProtection) {
+    let nonces = &protection.nonce_store;
+    let tenant = Uuid::new_v4().to_string();
+    let other_tenant = Uuid::new_v4().to_string();
+    let record = |tenant: &str| {
+        let tenant = tenant.to_string();
+        async move {
+            nonces
+                .record_once(&tenant, "saml", "assertion-1", 60)
+                .await
+                .expect("Failed to record value")
+        }
+    };
+
+    assert!(record(&tenant).await);
+    assert!(!record(&tenant).await);
+    assert!(record(&other_tenant).await);
+
+    assert!(
+        nonces
+            .consume_signed_nonce(&tenant, "signed-nonce")
+            .await
+            .expect("Failed to consume nonce")
+    );
+    assert!(
+        !nonces
+            .consume_signed_nonce(&tenant, "signed-nonce")
+            .await
+            .expect("Failed to consume nonce")
+    );
+}
+
+async fn brute_force_locks_and_resets_username(protection: &SecurityProtection) {
+    let brute_force = &protection.brute_force;
+    let tenant = Uuid::new_v4().to_string();
+
+    for expected_remaining in [2, 1] {
+        brute_force
+            .check_authentication_attempt(&tenant, USERNAME, false)
+            .await
+            .expect("Attempt below the limit is allowed");
+        assert_eq!(
+            brute_force
+                .remaining_attempts(&tenant, USERNAME)
+                .await
+                .expect("Failed to read remaining attempts"),
+            expected_remaining
+        );
+    }
+    assert!(matches!(
+        brute_force
+            .check_authentication_attempt(&tenant, USERNAME, false)
+            .await,
+        Err(BruteForceError::AccountLocked)
+    ));
+    assert!(
+        brute_force
+            .is_account_locked(&tenant, USERNAME)
+            .await
+            .expect("Failed to read lock")
+    );
+
+    brute_force
+        .reset_attempts(&tenant, USERNAME)
+        .await
+        .expect("Failed to reset attempts");
+    assert!(
+        !brute_force
+            .is_account_locked(&tenant, USERNAME)
+            .await
+            .expect("Failed to read lock")
+    );
+    assert_eq!(
+        brute_force
+            .remaining_attempts(&tenant, USERNAME)
+            .await
+            .expect("Failed to read remaining attempts"),
+        3
+    );
+}
+
+async fn ip_counter_decays_on_success(protection: &SecurityProtection) {
+    let brute_force = &protection.brute_force;
+    let tenant = Uuid::new_v4().to_string();
+
+    let mut status = None;
+    for username in ["a@example.com", "b@example.com", "c@example.com", USERNAME] {
+        status = Some(
+            brute_force
+                .record_login_attempt(&tenant, username, IP, false)
+                .await
+                .expect("Failed to record attempt"),
+        );
+    }
+    let status = status.expect("attempts recorded");
+    assert!(status.ip.is_locked());
+    assert_eq!(status.username.attempts, 1);
+    assert!(status.ip_delay_ms > 0);
+
+    let status = brute_force
+        .record_login_attempt(&tenant, USERNAME, IP, true)
+        .await
+        .expect("Failed to record attempt");
+    assert_eq!(status.username.attempts, 0);
+    assert_eq!(status.ip.attempts, 2);
+    assert!(!status.ip.is_locked());
+
+    assert_eq!(
+        brute_force
+            .get_protection_status(USERNAME, IP, &tenant)
+            .await
+            .expect("Failed to read status"),
+        status
+    );
+}
+
+/// Generates one test per scenario and backend
+macro_rules! conformance {
+    ($($scenario:ident),* $(,)?) => {
+        mod memory {
+            $(
+                #[tokio::test]
+                async fn $scenario() {
+                    super::$scenario(&super::memory_protection()).await;
+                }
+            )*
+        }
+
+        #[cfg(feature = "security-integration")]
+        mod redis {
+            use crate::helpers::setup_security_stack_with_config;
+
+            $(
+                #[tokio::test]
+                async fn $scenario() {
+                    let stack = setup_security_stack_with_config(super::config())
+                        .await
+                        .expect("Failed to start security stack");
+                    super::$scenario(&stack.protection).await;
+                }
+            )*
+        }
+    };
+}
+
+conformance!(
+    rate_limit_backs_off_and_resets,
+    nonce_is_validated_once,
+    record_once_rejects_replays,
+    brute_force_locks_and_resets_username,
+    ip_counter_decays_on_success,
+);
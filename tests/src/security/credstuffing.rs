@@ -0,0 +1,142 @@
+use acci_auth::DurationSecs;
+use acci_auth::security::{LoginAttempt, PatternDetector, create_tenant_redis_key};
+use chrono::Utc;
+use redis::AsyncCommands;
+use uuid::Uuid;
+
+use crate::helpers::{setup_security_stack, setup_test_redis};
+
+const IP: &str = "203.0.113.7";
+
+fn attempt(tenant_id: &str, username: &str, ip_address: &str) -> LoginAttempt {
+    LoginAttempt {
+        tenant_id: tenant_id.to_string(),
+        username: username.to_string(),
+        ip_address: ip_address.to_string(),
+        user_agent: "security-integration".to_string(),
+        timestamp: Utc::now(),
+        fingerprint: None,
+        geolocation: None,
+        successful: false,
+    }
+}
+
+#[tokio::test]
+async fn test_ip_velocity_counts_every_attempt() {
+    let (_redis, client) = setup_test_redis().await.expect("Failed to start Redis");
+    let detector = PatternDetector::new(client);
+    let tenant = Uuid::new_v4().to_string();
+
+    // Recorded well within one second of each other
+    for i in 0..5 {
+        detector
+            .record_login_attempt(&attempt(&tenant, &format!("user{}", i), IP))
+            .await;
+    }
+    detector
+        .record_login_attempt(&attempt(&tenant, "user0", "198.51.100.1"))
+        .await;
+
+    assert_eq!(
+        detector
+            .check_ip_velocity(&tenant, IP, DurationSecs::from_secs(60))
+            .await,
+        5
+    );
+    assert_eq!(
+        detector
+            .check_ip_velocity(&tenant, "198.51.100.1", DurationSecs::from_secs(60))
+            .await,
+        1
+    );
+    assert_eq!(
+        detector
+            .check_ip_velocity(&tenant, "192.0.2.1", DurationSecs::from_secs(60))
+            .await,
+        0
+    );
+
+    let other_tenant = Uuid::new_v4().to_string();
+    assert_eq!(
+        detector
+            .check_ip_velocity(&other_tenant, IP, DurationSecs::from_secs(60))
+            .await,
+        0
+    );
+
+    let recent = detector
+        .get_recent_attempts(&tenant, IP, DurationSecs::from_secs(60))
+        .await;
+    assert_eq!(recent.len(), 5);
+    assert_eq!(recent[0].username, "user4");
+}
+
+#[tokio::test]
+async fn test_ip_velocity_drops_attempts_outside_window() {
+    let (_redis, client) = setup_test_redis().await.expect("Failed to start Redis");
+    let detector = PatternDetector::new(client.clone());
+    let tenant = Uuid::new_v4().to_string();
+
+    detector
+        .record_login_attempt(&attempt(&tenant, "alice", IP))
+        .await;
+    detector
+        .record_login_attempt(&attempt(&tenant, "bob", IP))
+        .await;
+
+    // Attempts recorded ten minutes ago
+    let velocity_key = create_tenant_redis_key(&tenant, "credstuffing:velocity", IP);
+    let stale = Utc::now().timestamp() - 600;
+    let mut conn = client
+        .get_async_connection()
+        .await
+        .expect("Failed to connect to Redis");
+    for i in 0..3 {
+        let _: () = conn
+            .zadd(&velocity_key, format!("{}:stale-{}", stale, i), stale)
+            .await
+            .expect("Failed to seed stale attempt");
+    }
+
+    assert_eq!(
+        detector
+            .check_ip_velocity(&tenant, IP, DurationSecs::from_secs(3600))
+            .await,
+        5
+    );
+    assert_eq!(
+        detector
+            .check_ip_velocity(&tenant, IP, DurationSecs::from_secs(300))
+            .await,
+        2
+    );
+
+    // Counting with the short window pruned the stale entries
+    let remaining: usize = conn.zcard(&velocity_key).await.expect("Failed to count");
+    assert_eq!(remaining, 2);
+    assert_eq!(
+        detector
+            .check_ip_velocity(&tenant, IP, DurationSecs::from_secs(3600))
+            .await,
+        2
+    );
+}
+
+#[tokio::test]
+async fn test_sequential_usernames_are_flagged() {
+    let stack = setup_security_stack()
+        .await
+        .expect("Failed to start security stack");
+    let detector = PatternDetector::new(stack.redis_client());
+    let tenant = Uuid::new_v4().to_string();
+
+    for name in ["alice", "bob", "carol"] {
+        assert!(!detector.check_username_pattern(&tenant, name).await);
+    }
+    for i in 1..=3 {
+        detector
+            .check_username_pattern(&tenant, &format!("user{}", i))
+            .await;
+    }
+    assert!(detector.check_username_pattern(&tenant, "user4").await);
+}
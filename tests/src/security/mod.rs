@@ -1,2 +1,20 @@
-// Security tests module
-// Implementations will come in future tasks
+//! Security component tests against real Redis
+//!
+//! Enabled with the `security-integration` feature. Each test uses its own
+//! tenant ID because the Redis container is shared between tests. The
+//! conformance scenarios also run against the memory backend without it.
+
+#[cfg(all(test, feature = "security-integration"))]
+mod action_tokens;
+#[cfg(all(test, feature = "security-integration"))]
+mod bruteforce;
+#[cfg(test)]
+mod conformance;
+#[cfg(all(test, feature = "security-integration"))]
+mod credstuffing;
+#[cfg(all(test, feature = "security-integration"))]
+mod ratelimit;
+#[cfg(all(test, feature = "security-integration"))]
+mod redis_circuit;
+#[cfg(all(test, feature = "security-integration"))]
+mod replay;
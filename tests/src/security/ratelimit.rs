@@ -0,0 +1,60 @@
+use acci_auth::DurationSecs;
+use acci_auth::security::config::RateLimit;
+use acci_auth::security::{RateLimitAllowList, RateLimitConfig, RateLimitLayer, RateStore};
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::{Router, routing::get};
+use std::sync::Arc;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use crate::helpers::setup_test_redis;
+
+#[tokio::test]
+async fn test_allow_listed_clients_are_never_rate_limited() {
+    let (_redis, client) = setup_test_redis().await.expect("Failed to start Redis");
+    let config = RateLimitConfig {
+        // Every request exceeds a limit of zero
+        default_limits: vec![RateLimit {
+            window_seconds: DurationSecs::from_secs(60),
+            max_requests: 0,
+            backoff_multiplier: 1.0,
+        }],
+        allow_list: RateLimitAllowList {
+            cidrs: vec!["10.0.0.0/8".to_string(), "2001:db8::/32".to_string()],
+            service_accounts: Vec::new(),
+        },
+        ..Default::default()
+    };
+    let router = Router::new()
+        .route("/api/status", get(|| async { "ok" }))
+        .layer(RateLimitLayer::new(
+            Arc::new(RateStore::new(client)),
+            config,
+        ));
+    let tenant = Uuid::new_v4().to_string();
+    let status = |ip: &'static str| {
+        let request = Request::builder()
+            .uri("/api/status")
+            .header("X-Tenant-ID", tenant.as_str())
+            .header("X-Forwarded-For", ip)
+            .body(Body::empty())
+            .expect("valid request");
+        let router = router.clone();
+        async move { router.oneshot(request).await.expect("response").status() }
+    };
+
+    for ip in ["10.1.2.3", "2001:db8::1", "::ffff:10.0.0.5"] {
+        for _ in 0..3 {
+            assert_eq!(status(ip).await, StatusCode::OK, "{ip} is allow-listed");
+        }
+    }
+
+    for ip in ["192.0.2.1", "2001:db9::1"] {
+        assert_eq!(
+            status(ip).await,
+            StatusCode::TOO_MANY_REQUESTS,
+            "{ip} is not allow-listed"
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use acci_auth::DurationSecs;
+use acci_auth::security::config::RateLimit;
+use acci_auth::security::{RateStore, RedisAvailabilityConfig, RedisCircuit};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::helpers::setup_test_redis;
+
+/// ID Redis assigned to the connection the circuit hands out
+async fn client_id(circuit: &RedisCircuit) -> i64 {
+    let mut conn = circuit.connection().await.expect("circuit is closed");
+    redis::cmd("CLIENT")
+        .arg("ID")
+        .query_async(&mut conn)
+        .await
+        .expect("Failed to query the client ID")
+}
+
+#[tokio::test]
+async fn test_repeated_operations_share_one_connection() {
+    let (_redis, client) = setup_test_redis().await.expect("Failed to start Redis");
+    let circuit = Arc::new(RedisCircuit::new(
+        client.clone(),
+        RedisAvailabilityConfig::default(),
+    ));
+
+    // Concurrent first callers wait for the same connection
+    let handles: Vec<_> = (0..16)
+        .map(|_| {
+            let circuit = Arc::clone(&circuit);
+            tokio::spawn(async move { client_id(&circuit).await })
+        })
+        .collect();
+    let mut ids = Vec::new();
+    for handle in handles {
+        ids.push(handle.await.expect("task panicked"));
+    }
+    let first = ids[0];
+    assert!(ids.iter().all(|id| *id == first), "{ids:?}");
+
+    // Operations of a component using the circuit reuse it as well
+    let store = RateStore::new(client).with_redis_circuit(Arc::clone(&circuit));
+    let limit = RateLimit {
+        window_seconds: DurationSecs::from_secs(60),
+        max_requests: 100,
+        backoff_multiplier: 1.0,
+    };
+    let tenant = Uuid::new_v4().to_string();
+    for _ in 0..20 {
+        store
+            .check_rate_limit(&tenant, "203.0.113.7", &limit)
+            .await
+            .expect("Failed to check the rate limit");
+        assert_eq!(client_id(&circuit).await, first);
+    }
+}
+
+#[tokio::test]
+async fn test_reconnect_after_dropped_connection_is_transparent() {
+    let (_redis, client) = setup_test_redis().await.expect("Failed to start Redis");
+    let circuit = RedisCircuit::new(client.clone(), RedisAvailabilityConfig::default());
+    let first = client_id(&circuit).await;
+
+    let mut admin = client
+        .get_async_connection()
+        .await
+        .expect("Failed to connect to Redis");
+    let killed: i64 = redis::cmd("CLIENT")
+        .arg("KILL")
+        .arg("ID")
+        .arg(first)
+        .query_async(&mut admin)
+        .await
+        .expect("Failed to kill the connection");
+    assert_eq!(killed, 1);
+
+    // The next command succeeds on a new connection, which is then reused
+    let second = client_id(&circuit).await;
+    assert_ne!(second, first);
+    assert_eq!(client_id(&circuit).await, second);
+    assert!(!circuit.is_open());
+}
@@ -0,0 +1,220 @@
+use acci_auth::DurationSecs;
+use acci_auth::security::{
+    NonceStore, ReplayProtectionConfig, ReplayProtectionLayer, ReplayProtectionMode,
+    StaticSigningSecrets,
+};
+use acci_core::signing::{SignedRequest, sign_request};
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::{Router, routing::post};
+use chrono::Utc;
+use std::sync::Arc;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use crate::helpers::{setup_security_stack, setup_test_redis};
+
+#[tokio::test]
+async fn test_nonce_is_single_use() {
+    let stack = setup_security_stack()
+        .await
+        .expect("Failed to start security stack");
+    let nonces = &stack.protection.nonce_store;
+    let tenant = Uuid::new_v4().to_string();
+
+    let nonce = nonces
+        .generate_nonce(&tenant, "transfer")
+        .await
+        .expect("Failed to generate nonce");
+    assert_eq!(nonce.len(), 32);
+
+    // Bound to tenant and context
+    let other_tenant = Uuid::new_v4().to_string();
+    assert!(
+        !nonces
+            .validate_nonce(&other_tenant, "transfer", &nonce, None)
+            .await
+            .expect("validate")
+    );
+    assert!(
+        !nonces
+            .validate_nonce(&tenant, "login", &nonce, None)
+            .await
+            .expect("validate")
+    );
+
+    assert!(
+        nonces
+            .validate_nonce(&tenant, "transfer", &nonce, None)
+            .await
+            .expect("validate")
+    );
+    // Replay
+    assert!(
+        !nonces
+            .validate_nonce(&tenant, "transfer", &nonce, None)
+            .await
+            .expect("validate")
+    );
+
+    assert!(
+        !nonces
+            .validate_nonce(&tenant, "transfer", "0123456789abcdef", None)
+            .await
+            .expect("validate")
+    );
+}
+
+#[tokio::test]
+async fn test_csrf_token_is_single_use() {
+    let stack = setup_security_stack()
+        .await
+        .expect("Failed to start security stack");
+    let nonces = &stack.protection.nonce_store;
+    let tenant = Uuid::new_v4().to_string();
+
+    let token = nonces
+        .generate_csrf_token(&tenant, "profile")
+        .await
+        .expect("Failed to generate CSRF token");
+
+    assert!(
+        !nonces
+            .validate_csrf_token(&tenant, "settings", &token)
+            .await
+            .expect("validate")
+    );
+    assert!(
+        nonces
+            .validate_csrf_token(&tenant, "profile", &token)
+            .await
+            .expect("validate")
+    );
+    assert!(
+        !nonces
+            .validate_csrf_token(&tenant, "profile", &token)
+            .await
+            .expect("validate")
+    );
+}
+
+#[tokio::test]
+async fn test_nonce_with_skewed_timestamp_is_rejected_and_consumed() {
+    let (_redis, client) = setup_test_redis().await.expect("Failed to start Redis");
+    let config = ReplayProtectionConfig {
+        max_timestamp_skew_seconds: DurationSecs::from_secs(30),
+        ..ReplayProtectionConfig::default()
+    };
+    let nonces = NonceStore::new(client, config);
+    let tenant = Uuid::new_v4().to_string();
+
+    let nonce = nonces
+        .generate_nonce(&tenant, "api")
+        .await
+        .expect("generate");
+    let skewed = Utc::now().timestamp() - 120;
+    assert!(
+        !nonces
+            .validate_nonce(&tenant, "api", &nonce, Some(skewed))
+            .await
+            .expect("validate")
+    );
+    // A rejected nonce cannot be retried with a corrected timestamp
+    let now = Utc::now().timestamp();
+    assert!(
+        !nonces
+            .validate_nonce(&tenant, "api", &nonce, Some(now))
+            .await
+            .expect("validate")
+    );
+
+    let nonce = nonces
+        .generate_nonce(&tenant, "api")
+        .await
+        .expect("generate");
+    let now = Utc::now().timestamp();
+    assert!(
+        nonces
+            .validate_nonce(&tenant, "api", &nonce, Some(now))
+            .await
+            .expect("validate")
+    );
+}
+
+const SIGNING_SECRET: &[u8] = b"tenant-signing-secret";
+
+/// Signed POST to `/api/transfers`, optionally with a body other than the signed one
+fn signed_transfer(
+    tenant: &str,
+    nonce: &str,
+    timestamp: i64,
+    sent_body: Option<&str>,
+) -> Request<Body> {
+    let body = r#"{"amount":10}"#;
+    let signature = sign_request(
+        SIGNING_SECRET,
+        &SignedRequest {
+            timestamp,
+            nonce,
+            method: "POST",
+            path: "/api/transfers",
+            body: body.as_bytes(),
+        },
+    );
+
+    Request::post("/api/transfers")
+        .header("X-Tenant-ID", tenant)
+        .header("X-Nonce", nonce)
+        .header("X-Timestamp", timestamp.to_string())
+        .header("X-Signature", signature)
+        .body(Body::from(sent_body.unwrap_or(body).to_string()))
+        .expect("request")
+}
+
+#[tokio::test]
+async fn test_signed_requests_are_verified_before_the_nonce_is_consumed() {
+    let (_redis, client) = setup_test_redis().await.expect("Failed to start Redis");
+    let config = ReplayProtectionConfig {
+        default_mode: ReplayProtectionMode::Signed,
+        ..ReplayProtectionConfig::default()
+    };
+    let tenant = Uuid::new_v4().to_string();
+    let secrets = StaticSigningSecrets::new().with_tenant_secret(tenant.clone(), SIGNING_SECRET);
+    let router = Router::new()
+        .route("/api/transfers", post(|| async { "ok" }))
+        .layer(
+            ReplayProtectionLayer::new(Arc::new(NonceStore::new(client, config)))
+                .with_signing_secrets(Arc::new(secrets)),
+        );
+    let send = |request| router.clone().oneshot(request);
+    let now = Utc::now().timestamp();
+
+    // Tampered body
+    let response = send(signed_transfer(
+        &tenant,
+        "nonce-1",
+        now,
+        Some(r#"{"amount":1000}"#),
+    ))
+    .await
+    .expect("response");
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // Stale timestamp
+    let response = send(signed_transfer(&tenant, "nonce-2", now - 3600, None))
+        .await
+        .expect("response");
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // The rejected request did not burn the nonce
+    let response = send(signed_transfer(&tenant, "nonce-1", now, None))
+        .await
+        .expect("response");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Nonce reuse
+    let response = send(signed_transfer(&tenant, "nonce-1", now, None))
+        .await
+        .expect("response");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}